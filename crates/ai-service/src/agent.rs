@@ -0,0 +1,256 @@
+use crate::client::{AIClient, AIError, AIRequest, AIResponse, Usage};
+use crate::messages::ChatMessage;
+use crate::tools::{ToolCall, ToolRegistry, ToolResult};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
+
+/// Model turns an agent may take before it is stopped
+pub const DEFAULT_MAX_STEPS: usize = 10;
+
+/// Generates completions for an agent
+#[async_trait]
+pub trait CompletionModel: Send + Sync {
+    async fn complete(&self, request: AIRequest) -> Result<AIResponse, AIError>;
+}
+
+#[async_trait]
+impl CompletionModel for AIClient {
+    async fn complete(&self, request: AIRequest) -> Result<AIResponse, AIError> {
+        self.generate(request).await
+    }
+}
+
+/// Why an agent run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentStop {
+    /// The model answered without calling a tool
+    Finished,
+    /// The model was still calling tools after the last allowed step
+    MaxSteps,
+}
+
+/// One model turn and the tool calls it made
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentStep {
+    pub content: String,
+    pub tool_calls: Vec<ToolCall>,
+    /// Results of `tool_calls`, in the same order
+    pub tool_results: Vec<ToolResult>,
+    pub usage: Usage,
+    pub finish_reason: String,
+}
+
+/// A finished agent run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentTranscript {
+    /// The whole conversation, the request's messages included
+    pub messages: Vec<ChatMessage>,
+    pub steps: Vec<AgentStep>,
+    /// The content of the last model turn
+    pub output: String,
+    pub stop: AgentStop,
+    /// Usage summed over all steps
+    pub usage: Usage,
+}
+
+/// Runs the tool-calling loop: the model is offered the registered tools,
+/// the tools it calls are run and their results sent back, until it answers
+/// without calling any or runs out of steps
+pub struct AgentExecutor {
+    model: Arc<dyn CompletionModel>,
+    tools: Arc<ToolRegistry>,
+    max_steps: usize,
+}
+
+impl AgentExecutor {
+    pub fn new(model: Arc<dyn CompletionModel>, tools: Arc<ToolRegistry>) -> Self {
+        Self {
+            model,
+            tools,
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+
+    /// Stop after this many model turns (at least 1)
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps.max(1);
+        self
+    }
+
+    /// Run the conversation of `request` to completion. Tools the request
+    /// already lists are offered instead of the registered ones.
+    pub async fn run(&self, mut request: AIRequest) -> Result<AgentTranscript, AIError> {
+        let mut messages = request.conversation();
+        if request.tools.is_none() {
+            let mut tools = self.tools.list_tools();
+            tools.sort_by(|a, b| a.name.cmp(&b.name));
+            request.tools = (!tools.is_empty()).then_some(tools);
+        }
+
+        let mut steps = Vec::new();
+        let mut usage = Usage { prompt_tokens: 0, completion_tokens: 0, total_tokens: 0 };
+        for _ in 0..self.max_steps {
+            let response = self.model.complete(AIRequest {
+                messages: messages.clone(),
+                ..request.clone()
+            }).await?;
+            usage.prompt_tokens += response.usage.prompt_tokens;
+            usage.completion_tokens += response.usage.completion_tokens;
+            usage.total_tokens += response.usage.total_tokens;
+
+            let tool_calls = response.tool_calls.unwrap_or_default();
+            messages.push(ChatMessage::assistant(response.content.clone()).with_tool_calls(tool_calls.clone()));
+            if tool_calls.is_empty() {
+                steps.push(AgentStep {
+                    content: response.content.clone(),
+                    tool_calls,
+                    tool_results: Vec::new(),
+                    usage: response.usage,
+                    finish_reason: response.finish_reason,
+                });
+                return Ok(AgentTranscript {
+                    messages,
+                    steps,
+                    output: response.content,
+                    stop: AgentStop::Finished,
+                    usage,
+                });
+            }
+
+            // Calls of one turn are independent, so they run concurrently
+            let tool_results = futures::future::join_all(tool_calls.iter().map(|call| {
+                let call = ToolCall { arguments: parse_arguments(&call.arguments), ..call.clone() };
+                async move { self.tools.execute(&call).await }
+            })).await;
+            for result in &tool_results {
+                messages.push(ChatMessage::tool(result.tool_call_id.clone(), result_content(result)));
+            }
+            steps.push(AgentStep {
+                content: response.content,
+                tool_calls,
+                tool_results,
+                usage: response.usage,
+                finish_reason: response.finish_reason,
+            });
+        }
+
+        let output = steps.last().map(|step| step.content.clone()).unwrap_or_default();
+        Ok(AgentTranscript {
+            messages,
+            steps,
+            output,
+            stop: AgentStop::MaxSteps,
+            usage,
+        })
+    }
+}
+
+/// OpenAI sends arguments as a JSON string; tools take them as an object
+fn parse_arguments(arguments: &JsonValue) -> JsonValue {
+    match arguments {
+        JsonValue::String(text) => serde_json::from_str(text).unwrap_or_else(|_| arguments.clone()),
+        arguments => arguments.clone(),
+    }
+}
+
+/// The content of the tool message answering a call; failures are reported
+/// to the model so it can recover
+fn result_content(result: &ToolResult) -> String {
+    match (&result.error, &result.result) {
+        (Some(error), _) => serde_json::json!({ "error": error }).to_string(),
+        (None, JsonValue::String(text)) => text.clone(),
+        (None, value) => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ModelType;
+    use crate::tools::CalculatorTool;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// Replies with scripted tool calls, one turn each, then with text
+    struct ScriptedModel {
+        turns: Mutex<VecDeque<Vec<ToolCall>>>,
+        requests: Mutex<Vec<AIRequest>>,
+    }
+
+    impl ScriptedModel {
+        fn new(turns: Vec<Vec<ToolCall>>) -> Self {
+            Self { turns: Mutex::new(turns.into()), requests: Mutex::new(Vec::new()) }
+        }
+    }
+
+    #[async_trait]
+    impl CompletionModel for ScriptedModel {
+        async fn complete(&self, request: AIRequest) -> Result<AIResponse, AIError> {
+            self.requests.lock().unwrap().push(request);
+            let calls = self.turns.lock().unwrap().pop_front();
+            Ok(AIResponse {
+                content: if calls.is_some() { String::new() } else { "The answer is 20.".to_string() },
+                tool_calls: calls,
+                usage: Usage { prompt_tokens: 10, completion_tokens: 2, total_tokens: 12 },
+                model: "gpt-4".to_string(),
+                finish_reason: "stop".to_string(),
+                cached: false,
+                structured: None,
+                fallback_from: None,
+            })
+        }
+    }
+
+    fn call(id: &str, name: &str, arguments: JsonValue) -> ToolCall {
+        ToolCall { id: id.to_string(), name: name.to_string(), arguments }
+    }
+
+    fn registry() -> Arc<ToolRegistry> {
+        let mut registry = ToolRegistry::new();
+        registry.register("calculator".to_string(), Arc::new(CalculatorTool));
+        Arc::new(registry)
+    }
+
+    #[tokio::test]
+    async fn test_agent_runs_tools_until_the_model_answers() {
+        let model = Arc::new(ScriptedModel::new(vec![
+            vec![
+                call("call_1", "calculator", JsonValue::String(r#"{"operation":"multiply","a":4,"b":5}"#.to_string())),
+                call("call_2", "weather", serde_json::json!({})),
+            ],
+        ]));
+        let agent = AgentExecutor::new(model.clone(), registry());
+        let request = AIRequest::new(ModelType::GPT4, "What is 4 times 5?".to_string());
+        let transcript = agent.run(request).await.unwrap();
+
+        assert_eq!(transcript.stop, AgentStop::Finished);
+        assert_eq!(transcript.output, "The answer is 20.");
+        assert_eq!((transcript.steps.len(), transcript.usage.total_tokens), (2, 24));
+        let results = &transcript.steps[0].tool_results;
+        assert_eq!(results[0].result.as_f64(), Some(20.0));
+        assert_eq!(results[1].error.as_deref(), Some("Tool not found: weather"));
+
+        // user, assistant calling tools, two tool results, final answer
+        assert_eq!(transcript.messages.len(), 5);
+        assert_eq!(transcript.messages[2].tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(transcript.messages[2].content, "20.0");
+        let requests = model.requests.lock().unwrap();
+        assert_eq!(requests[0].tools.as_ref().unwrap()[0].name, "calculator");
+        assert_eq!(requests[1].messages.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_agent_stops_after_max_steps() {
+        let looping = (0..5)
+            .map(|i| vec![call(&format!("call_{}", i), "calculator", serde_json::json!({ "operation": "add", "a": 1, "b": 1 }))])
+            .collect();
+        let agent = AgentExecutor::new(Arc::new(ScriptedModel::new(looping)), registry()).with_max_steps(3);
+        let transcript = agent.run(AIRequest::new(ModelType::GPT4, "Count".to_string())).await.unwrap();
+        assert_eq!(transcript.stop, AgentStop::MaxSteps);
+        assert_eq!(transcript.steps.len(), 3);
+        assert!(transcript.steps.iter().all(|step| step.tool_results.len() == 1));
+    }
+}
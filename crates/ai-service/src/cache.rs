@@ -0,0 +1,282 @@
+use crate::client::{AIRequest, AIResponse};
+use async_trait::async_trait;
+use moka::future::Cache;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Which requests are answered from the cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CachePolicy {
+    /// Only requests with a temperature of 0, whose answers are meant to be
+    /// reproducible
+    #[default]
+    Deterministic,
+    /// Every request, sampled answers included
+    Always,
+}
+
+/// Where cached responses are kept
+#[async_trait]
+pub trait CacheStore: Send + Sync {
+    async fn get(&self, key: &str) -> Option<AIResponse>;
+
+    async fn put(&self, key: String, response: &AIResponse, ttl: Duration);
+}
+
+/// Told about cache lookups, e.g. to report hit rates
+#[async_trait]
+pub trait CacheObserver: Send + Sync {
+    /// A request of `model` was answered from the cache, saving `tokens`
+    async fn record_cache_hit(&self, model: &str, tokens: u32);
+
+    async fn record_cache_miss(&self, model: &str);
+}
+
+/// Caches responses in process memory
+pub struct MemoryCacheStore {
+    cache: Cache<String, (AIResponse, Instant)>,
+}
+
+impl MemoryCacheStore {
+    pub fn new(max_entries: u64) -> Self {
+        Self {
+            cache: Cache::builder().max_capacity(max_entries).build(),
+        }
+    }
+}
+
+#[async_trait]
+impl CacheStore for MemoryCacheStore {
+    async fn get(&self, key: &str) -> Option<AIResponse> {
+        let (response, expires_at) = self.cache.get(key).await?;
+        if Instant::now() >= expires_at {
+            self.cache.invalidate(key).await;
+            return None;
+        }
+        Some(response)
+    }
+
+    async fn put(&self, key: String, response: &AIResponse, ttl: Duration) {
+        self.cache.insert(key, (response.clone(), Instant::now() + ttl)).await;
+    }
+}
+
+/// Caches responses in Redis, shared by every instance. Redis failures are
+/// logged and treated as misses: the cache never fails a request.
+pub struct RedisCacheStore {
+    connection: redis::aio::ConnectionManager,
+}
+
+impl RedisCacheStore {
+    pub async fn connect(url: &str) -> Result<Self, redis::RedisError> {
+        let client = redis::Client::open(url)?;
+        Ok(Self {
+            connection: redis::aio::ConnectionManager::new(client).await?,
+        })
+    }
+}
+
+#[async_trait]
+impl CacheStore for RedisCacheStore {
+    async fn get(&self, key: &str) -> Option<AIResponse> {
+        let mut connection = self.connection.clone();
+        let cached: Option<String> = match connection.get(key).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                tracing::warn!("AI cache lookup failed: {}", e);
+                return None;
+            }
+        };
+        serde_json::from_str(&cached?).ok()
+    }
+
+    async fn put(&self, key: String, response: &AIResponse, ttl: Duration) {
+        let Ok(value) = serde_json::to_string(response) else {
+            return;
+        };
+        let mut connection = self.connection.clone();
+        let stored: Result<(), _> = connection.set_ex(key, value, ttl.as_secs().max(1)).await;
+        if let Err(e) = stored {
+            tracing::warn!("AI cache store failed: {}", e);
+        }
+    }
+}
+
+/// Answers repeated AI requests from a cache for a while, so workflows run
+/// again and again do not pay for the same completion each time
+pub struct AICache {
+    store: Arc<dyn CacheStore>,
+    ttl: Duration,
+    policy: CachePolicy,
+    observer: Option<Arc<dyn CacheObserver>>,
+}
+
+impl AICache {
+    pub fn new(store: Arc<dyn CacheStore>, ttl: Duration) -> Self {
+        Self {
+            store,
+            ttl,
+            policy: CachePolicy::default(),
+            observer: None,
+        }
+    }
+
+    pub fn in_memory(max_entries: u64, ttl: Duration) -> Self {
+        Self::new(Arc::new(MemoryCacheStore::new(max_entries)), ttl)
+    }
+
+    pub fn with_policy(mut self, policy: CachePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_observer(mut self, observer: Arc<dyn CacheObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// The cache key of a request, if the policy lets it be cached.
+    ///
+    /// Requests differing only in how they are written share a key: a lone
+    /// prompt equals the same text as the only user message, surrounding
+    /// whitespace of messages is ignored, and so is the order of tools.
+    pub fn key(&self, request: &AIRequest) -> Option<String> {
+        if self.policy == CachePolicy::Deterministic && request.temperature != Some(0.0) {
+            return None;
+        }
+        let messages: Vec<serde_json::Value> = request
+            .conversation()
+            .into_iter()
+            .map(|message| {
+                serde_json::json!({
+                    "role": message.role,
+                    "content": message.content.trim(),
+                    "tool_calls": message.tool_calls,
+                    "tool_call_id": message.tool_call_id,
+                })
+            })
+            .collect();
+        let mut tools = request.tools.clone().unwrap_or_default();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        let normalized = serde_json::json!({
+            "model": request.model.as_str(),
+            "messages": messages,
+            "temperature": request.temperature,
+            "max_tokens": request.max_tokens,
+            "top_p": request.top_p,
+            "tools": tools,
+            "tool_choice": request.tool_choice,
+            "response_format": request.response_format,
+        });
+        let digest = Sha256::digest(normalized.to_string().as_bytes());
+        Some(format!("ai:{:x}", digest))
+    }
+
+    /// The cached response to a request, marked as [`cached`](AIResponse::cached)
+    pub async fn get(&self, request: &AIRequest) -> Option<AIResponse> {
+        let key = self.key(request)?;
+        let model = request.model.as_str();
+        match self.store.get(&key).await {
+            Some(response) => {
+                if let Some(observer) = &self.observer {
+                    observer.record_cache_hit(model, response.usage.total_tokens).await;
+                }
+                Some(AIResponse { cached: true, ..response })
+            }
+            None => {
+                if let Some(observer) = &self.observer {
+                    observer.record_cache_miss(model).await;
+                }
+                None
+            }
+        }
+    }
+
+    pub async fn put(&self, request: &AIRequest, response: &AIResponse) {
+        if let Some(key) = self.key(request) {
+            self.store.put(key, response, self.ttl).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Usage;
+    use crate::messages::ChatMessage;
+    use crate::models::ModelType;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Counts(Mutex<(u32, u32, u32)>);
+
+    #[async_trait]
+    impl CacheObserver for Counts {
+        async fn record_cache_hit(&self, _model: &str, tokens: u32) {
+            let mut counts = self.0.lock().unwrap();
+            counts.0 += 1;
+            counts.2 += tokens;
+        }
+
+        async fn record_cache_miss(&self, _model: &str) {
+            self.0.lock().unwrap().1 += 1;
+        }
+    }
+
+    fn request(prompt: &str, temperature: Option<f32>) -> AIRequest {
+        AIRequest { temperature, ..AIRequest::new(ModelType::GPT4, prompt.to_string()) }
+    }
+
+    fn response() -> AIResponse {
+        AIResponse {
+            content: "Paris".to_string(),
+            tool_calls: None,
+            usage: Usage { prompt_tokens: 12, completion_tokens: 3, total_tokens: 15 },
+            model: "gpt-4".to_string(),
+            finish_reason: "stop".to_string(),
+            cached: false,
+            structured: None,
+            fallback_from: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_hits_normalized_requests() {
+        let counts = Arc::new(Counts::default());
+        let cache = AICache::in_memory(100, Duration::from_secs(60)).with_observer(counts.clone());
+        let question = request("What is the capital of France?", Some(0.0));
+
+        assert!(cache.get(&question).await.is_none());
+        cache.put(&question, &response()).await;
+        let same = AIRequest::with_messages(ModelType::GPT4, vec![ChatMessage::user("  What is the capital of France?\n")]);
+        let hit = cache.get(&AIRequest { temperature: Some(0.0), ..same }).await.unwrap();
+        assert!(hit.cached);
+        assert_eq!(hit.content, "Paris");
+
+        assert!(cache.get(&request("What is the capital of Spain?", Some(0.0))).await.is_none());
+        assert!(cache.get(&AIRequest { max_tokens: Some(5), ..question.clone() }).await.is_none());
+        assert_eq!(*counts.0.lock().unwrap(), (1, 3, 15));
+
+        // Sampled requests are only cached when the policy says so
+        let sampled = request("Write a haiku", Some(0.7));
+        assert!(cache.key(&sampled).is_none());
+        assert!(cache.key(&request("Write a haiku", None)).is_none());
+        let always = AICache::in_memory(100, Duration::from_secs(60)).with_policy(CachePolicy::Always);
+        always.put(&sampled, &response()).await;
+        assert!(always.get(&sampled).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_cached_responses_expire() {
+        let cache = AICache::in_memory(100, Duration::from_millis(50));
+        let question = request("Hi", Some(0.0));
+        cache.put(&question, &response()).await;
+        assert!(cache.get(&question).await.is_some());
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        assert!(cache.get(&question).await.is_none());
+    }
+}
@@ -1,19 +1,35 @@
+use crate::cache::AICache;
+use crate::fallback::{with_fallbacks, HealthTracker, ModelFallbackPolicy};
+use crate::messages::{anthropic_messages, gemini_contents, openai_messages, ChatMessage};
 use crate::models::{ModelConfig, ModelType};
+use crate::resilience::{call_provider, CircuitBreaker, RetryPolicy};
+use crate::structured::{generate_with_repairs, ResponseFormat};
 use crate::tools::{Tool, ToolCall};
+use common::error::{ErrorCode, ErrorInfo, PlatformError};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::Instrument;
 
 /// AI request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AIRequest {
     pub model: ModelType,
     pub prompt: String,
+    /// Conversation sent instead of `prompt` when not empty
+    #[serde(default)]
+    pub messages: Vec<ChatMessage>,
     pub temperature: Option<f32>,
     pub max_tokens: Option<u32>,
     pub top_p: Option<f32>,
     pub tools: Option<Vec<Tool>>,
     pub tool_choice: Option<String>,
+    /// JSON Schema the answer must follow, see [`crate::structured`]
+    #[serde(default)]
+    pub response_format: Option<ResponseFormat>,
 }
 
 impl AIRequest {
@@ -21,11 +37,21 @@ impl AIRequest {
         Self {
             model,
             prompt,
+            messages: Vec::new(),
             temperature: None,
             max_tokens: None,
             top_p: None,
             tools: None,
             tool_choice: None,
+            response_format: None,
+        }
+    }
+
+    /// A request continuing a multi-turn conversation
+    pub fn with_messages(model: ModelType, messages: Vec<ChatMessage>) -> Self {
+        Self {
+            messages,
+            ..Self::new(model, String::new())
         }
     }
 
@@ -33,11 +59,23 @@ impl AIRequest {
         Self {
             model,
             prompt,
+            messages: Vec::new(),
             temperature: Some(config.temperature),
             max_tokens: Some(config.max_tokens),
             top_p: Some(config.top_p),
             tools: None,
             tool_choice: None,
+            response_format: None,
+        }
+    }
+
+    /// The messages sent to the model: `messages`, or `prompt` as the only
+    /// user message
+    pub fn conversation(&self) -> Vec<ChatMessage> {
+        if self.messages.is_empty() {
+            vec![ChatMessage::user(self.prompt.clone())]
+        } else {
+            self.messages.clone()
         }
     }
 }
@@ -50,6 +88,18 @@ pub struct AIResponse {
     pub usage: Usage,
     pub model: String,
     pub finish_reason: String,
+    /// Answered from the [`AICache`] instead of the provider; `usage` is
+    /// that of the original completion
+    #[serde(default)]
+    pub cached: bool,
+    /// The answer parsed and checked against the request's
+    /// [`response_format`](AIRequest::response_format)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured: Option<JsonValue>,
+    /// The model asked for, when it failed and `model` answered instead
+    /// following the client's [`ModelFallbackPolicy`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_from: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,10 +109,28 @@ pub struct Usage {
     pub total_tokens: u32,
 }
 
+/// Azure OpenAI resource serving [`ModelType::AzureOpenAI`] deployments
+#[derive(Debug, Clone)]
+struct AzureResource {
+    /// e.g. `https://my-resource.openai.azure.com`
+    endpoint: String,
+    api_version: String,
+}
+
 /// AI client for making requests to AI providers
 pub struct AIClient {
     client: reqwest::Client,
     api_keys: HashMap<String, String>,
+    azure: Option<AzureResource>,
+    /// Base URL of the OpenAI-compatible server of [`ModelType::Local`] models
+    local: Option<String>,
+    cache: Option<Arc<AICache>>,
+    fallback: ModelFallbackPolicy,
+    health: Option<Arc<dyn HealthTracker>>,
+    /// How long one provider call may take; streams must start within it
+    timeout: Duration,
+    retry: RetryPolicy,
+    breaker: CircuitBreaker,
 }
 
 impl AIClient {
@@ -70,26 +138,167 @@ impl AIClient {
         Self {
             client: reqwest::Client::new(),
             api_keys: HashMap::new(),
+            azure: None,
+            local: None,
+            cache: None,
+            fallback: ModelFallbackPolicy::default(),
+            health: None,
+            timeout: Duration::from_secs(60),
+            retry: RetryPolicy::default(),
+            breaker: CircuitBreaker::default(),
         }
     }
 
+    /// Set the API key of a provider: `openai`, `anthropic`, `gemini`, `azure`
+    /// or `local` (optional)
     pub fn with_api_key(mut self, provider: String, api_key: String) -> Self {
         self.api_keys.insert(provider, api_key);
         self
     }
 
+    /// Send Azure OpenAI requests to the resource at `endpoint` with the
+    /// given `api-version`
+    pub fn with_azure_endpoint(mut self, endpoint: String, api_version: String) -> Self {
+        self.azure = Some(AzureResource {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            api_version,
+        });
+        self
+    }
+
+    /// Send [`ModelType::Local`] requests to the OpenAI-compatible server at
+    /// `endpoint`, e.g. `http://localhost:11434`
+    pub fn with_local_endpoint(mut self, endpoint: String) -> Self {
+        self.local = Some(endpoint.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Retry requests whose model is rate limited or whose provider fails
+    /// with the next model of its chain. Fallback models of providers not
+    /// configured on the client are skipped.
+    pub fn with_fallback_policy(mut self, fallback: ModelFallbackPolicy) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Share provider health with `health`: providers it reports unhealthy
+    /// are tried after the other models of a fallback chain, and the outcome
+    /// of each provider call is recorded with it
+    pub fn with_health_tracker(mut self, health: Arc<dyn HealthTracker>) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Give up on provider calls taking longer than `timeout` (60 seconds by
+    /// default); a streamed completion must start within it
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Retry provider calls failing with a rate limit, a server error, a
+    /// timeout or a connection failure (twice with backoff by default)
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Stop calling a provider for `open_for` after `failure_threshold`
+    /// consecutive failures (5 failures, 30 seconds by default); calls fail
+    /// with [`AIError::CircuitOpen`] meanwhile
+    pub fn with_circuit_breaker(mut self, failure_threshold: u32, open_for: Duration) -> Self {
+        self.breaker = CircuitBreaker::new(failure_threshold, open_for);
+        self
+    }
+
+    /// Answer repeated requests from `cache`; streamed completions bypass it
+    pub fn with_cache(mut self, cache: Arc<AICache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     /// Generate completion
     pub async fn generate(&self, request: AIRequest) -> Result<AIResponse, AIError> {
-        let provider = request.model.provider();
-        let api_key = self
-            .api_keys
+        let Some(cache) = &self.cache else {
+            return self.generate_uncached(request).await;
+        };
+        if let Some(response) = cache.get(&request).await {
+            return Ok(response);
+        }
+        let response = self.generate_uncached(request.clone()).await?;
+        cache.put(&request, &response).await;
+        Ok(response)
+    }
+
+    /// Generate a completion from the provider of the model, or of its
+    /// fallbacks
+    async fn generate_uncached(&self, request: AIRequest) -> Result<AIResponse, AIError> {
+        let requested = request.model.clone();
+        let (mut response, model) = with_fallbacks(self.candidates(&requested), self.health.as_deref(), |model| {
+            self.generate_with_model(AIRequest { model, ..request.clone() })
+        })
+        .await?;
+        if model != requested {
+            response.fallback_from = Some(requested.as_str().to_string());
+        }
+        Ok(response)
+    }
+
+    /// The requested model, then its fallbacks the client can reach
+    fn candidates(&self, model: &ModelType) -> Vec<ModelType> {
+        self.fallback
+            .candidates(model)
+            .into_iter()
+            .enumerate()
+            .filter(|(index, model)| *index == 0 || self.is_configured(model))
+            .map(|(_, model)| model)
+            .collect()
+    }
+
+    fn is_configured(&self, model: &ModelType) -> bool {
+        match model.provider() {
+            "local" => self.local.is_some(),
+            "azure" => self.azure.is_some() && self.api_keys.contains_key("azure"),
+            provider => self.api_keys.contains_key(provider),
+        }
+    }
+
+    fn api_key(&self, provider: &str) -> Result<&str, AIError> {
+        self.api_keys
             .get(provider)
-            .ok_or_else(|| AIError::ApiKeyNotConfigured(provider.to_string()))?;
+            .map(String::as_str)
+            .ok_or_else(|| AIError::ApiKeyNotConfigured(provider.to_string()))
+    }
+
+    /// Generate a completion with the model of the request; answers of
+    /// requests with a response format are checked and, if need be, repaired
+    async fn generate_with_model(&self, request: AIRequest) -> Result<AIResponse, AIError> {
+        let Some(format) = request.response_format.clone() else {
+            return self.send_request(request).await;
+        };
+        let native = request.model.supports_json_schema();
+        generate_with_repairs(request, &format, native, |request| Box::pin(self.send_request(request))).await
+    }
 
-        match provider {
-            "openai" => self.generate_openai(request, api_key).await,
-            "anthropic" => self.generate_anthropic(request, api_key).await,
-            _ => Err(AIError::UnsupportedProvider(provider.to_string())),
+    /// Send a request to the provider of its model, with the client's
+    /// timeout, retries and circuit breaker
+    async fn send_request(&self, request: AIRequest) -> Result<AIResponse, AIError> {
+        let span = tracing::info_span!("ai_request", provider = request.model.provider(), model = request.model.as_str());
+        call_provider(request.model.provider(), self.timeout, &self.retry, &self.breaker, || {
+            self.send_request_once(request.clone())
+        })
+        .instrument(span)
+        .await
+    }
+
+    async fn send_request_once(&self, request: AIRequest) -> Result<AIResponse, AIError> {
+        match request.model.provider() {
+            "openai" => self.generate_openai(request, self.api_key("openai")?).await,
+            "anthropic" => self.generate_anthropic(request, self.api_key("anthropic")?).await,
+            "gemini" => self.generate_gemini(request, self.api_key("gemini")?).await,
+            "azure" => self.generate_azure(request, self.api_key("azure")?).await,
+            "local" => self.generate_local(request).await,
+            provider => Err(AIError::UnsupportedProvider(provider.to_string())),
         }
     }
 
@@ -98,135 +307,90 @@ impl AIClient {
         request: AIRequest,
         api_key: &str,
     ) -> Result<AIResponse, AIError> {
-        let mut body = serde_json::json!({
-            "model": request.model.as_str(),
-            "messages": [
-                {
-                    "role": "user",
-                    "content": request.prompt
-                }
-            ],
-        });
-
-        if let Some(temp) = request.temperature {
-            body["temperature"] = JsonValue::from(temp);
-        }
-        if let Some(max_tokens) = request.max_tokens {
-            body["max_tokens"] = JsonValue::from(max_tokens);
-        }
-        if let Some(top_p) = request.top_p {
-            body["top_p"] = JsonValue::from(top_p);
-        }
-        if let Some(tools) = request.tools {
-            body["tools"] = serde_json::to_value(tools).unwrap();
-        }
+        let response = self.send_openai(openai_body(request), api_key).await?;
 
-        let response = self
-            .client
-            .post("https://api.openai.com/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", api_key))
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
+        let response_json: JsonValue = response
+            .json()
             .await
-            .map_err(|e| AIError::RequestFailed(e.to_string()))?;
+            .map_err(|e| AIError::ParseError(e.to_string()))?;
+        Ok(openai_response(&response_json))
+    }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(AIError::ApiError(error_text));
-        }
+    /// Azure OpenAI serves the OpenAI chat completions API per deployment
+    async fn generate_azure(
+        &self,
+        request: AIRequest,
+        api_key: &str,
+    ) -> Result<AIResponse, AIError> {
+        let deployment = request.model.as_str().to_string();
+        let response = self.send_azure(azure_body(request), api_key, &deployment).await?;
 
         let response_json: JsonValue = response
             .json()
             .await
             .map_err(|e| AIError::ParseError(e.to_string()))?;
+        Ok(openai_response(&response_json))
+    }
 
-        let choice = &response_json["choices"][0];
-        let message = &choice["message"];
-
-        let content = message["content"]
-            .as_str()
-            .unwrap_or("")
-            .to_string();
-
-        let tool_calls = if let Some(calls) = message["tool_calls"].as_array() {
-            Some(
-                calls
-                    .iter()
-                    .map(|call| ToolCall {
-                        id: call["id"].as_str().unwrap_or("").to_string(),
-                        name: call["function"]["name"].as_str().unwrap_or("").to_string(),
-                        arguments: call["function"]["arguments"].clone(),
-                    })
-                    .collect(),
-            )
-        } else {
-            None
-        };
+    /// Local servers speak the OpenAI chat completions API
+    async fn generate_local(&self, request: AIRequest) -> Result<AIResponse, AIError> {
+        let response = self.send_local(openai_body(request)).await?;
 
-        Ok(AIResponse {
-            content,
-            tool_calls,
-            usage: Usage {
-                prompt_tokens: response_json["usage"]["prompt_tokens"].as_u64().unwrap_or(0)
-                    as u32,
-                completion_tokens: response_json["usage"]["completion_tokens"]
-                    .as_u64()
-                    .unwrap_or(0) as u32,
-                total_tokens: response_json["usage"]["total_tokens"].as_u64().unwrap_or(0)
-                    as u32,
-            },
-            model: response_json["model"].as_str().unwrap_or("").to_string(),
-            finish_reason: choice["finish_reason"].as_str().unwrap_or("").to_string(),
-        })
+        let response_json: JsonValue = response
+            .json()
+            .await
+            .map_err(|e| AIError::ParseError(e.to_string()))?;
+        Ok(openai_response(&response_json))
     }
 
-    async fn generate_anthropic(
+    async fn generate_gemini(
         &self,
         request: AIRequest,
         api_key: &str,
     ) -> Result<AIResponse, AIError> {
-        let body = serde_json::json!({
-            "model": request.model.as_str(),
-            "messages": [
-                {
-                    "role": "user",
-                    "content": request.prompt
-                }
-            ],
-            "max_tokens": request.max_tokens.unwrap_or(2000),
-            "temperature": request.temperature.unwrap_or(0.7),
-        });
+        let model = request.model.as_str().to_string();
+        let response = self.send_gemini(gemini_body(request), api_key, &model, false).await?;
 
-        let response = self
-            .client
-            .post("https://api.anthropic.com/v1/messages")
-            .header("x-api-key", api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
+        let response_json: JsonValue = response
+            .json()
             .await
-            .map_err(|e| AIError::RequestFailed(e.to_string()))?;
+            .map_err(|e| AIError::ParseError(e.to_string()))?;
+        let mut streamed = StreamedResponse { model, ..Default::default() };
+        streamed.content = streamed.apply_gemini(&response_json).unwrap_or_default();
+        let tool_calls = gemini_tool_calls(&response_json);
+        Ok(AIResponse { tool_calls, ..streamed.finish() })
+    }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(AIError::ApiError(error_text));
-        }
+    async fn generate_anthropic(
+        &self,
+        request: AIRequest,
+        api_key: &str,
+    ) -> Result<AIResponse, AIError> {
+        let response = self.send_anthropic(anthropic_body(request), api_key).await?;
 
         let response_json: JsonValue = response
             .json()
             .await
             .map_err(|e| AIError::ParseError(e.to_string()))?;
 
-        let content = response_json["content"][0]["text"]
-            .as_str()
-            .unwrap_or("")
-            .to_string();
+        let blocks = response_json["content"].as_array().cloned().unwrap_or_default();
+        let content = blocks.iter()
+            .filter(|block| block["type"] == "text")
+            .filter_map(|block| block["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("");
+        let tool_calls: Vec<ToolCall> = blocks.iter()
+            .filter(|block| block["type"] == "tool_use")
+            .map(|block| ToolCall {
+                id: block["id"].as_str().unwrap_or("").to_string(),
+                name: block["name"].as_str().unwrap_or("").to_string(),
+                arguments: block["input"].clone(),
+            })
+            .collect();
 
         Ok(AIResponse {
             content,
-            tool_calls: None,
+            tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
             usage: Usage {
                 prompt_tokens: response_json["usage"]["input_tokens"].as_u64().unwrap_or(0)
                     as u32,
@@ -241,8 +405,169 @@ impl AIClient {
                 .as_str()
                 .unwrap_or("")
                 .to_string(),
+            cached: false,
+            structured: None,
+            fallback_from: None,
         })
     }
+
+    /// Generate a completion, streaming it from the provider.
+    ///
+    /// The stream yields each piece of text as it arrives, then the whole
+    /// completion with its usage as [`StreamEvent::Done`]. Tool calls are not
+    /// streamed, and the answer is not checked against the request's response
+    /// format. It ends after `Done` or the first error.
+    ///
+    /// Models fall back like in [`generate`](Self::generate) until a
+    /// provider starts streaming.
+    pub async fn generate_stream(&self, request: AIRequest) -> Result<CompletionStream, AIError> {
+        let requested = request.model.clone();
+        let (stream, model) = with_fallbacks(self.candidates(&requested), self.health.as_deref(), |model| {
+            let request = AIRequest { model, ..request.clone() };
+            async move {
+                call_provider(request.model.provider(), self.timeout, &self.retry, &self.breaker, || {
+                    self.open_stream(request.clone())
+                })
+                .await
+            }
+        })
+        .await?;
+        if model == requested {
+            return Ok(stream);
+        }
+        let fallback_from = requested.as_str().to_string();
+        Ok(stream
+            .map(move |event| match event {
+                Ok(StreamEvent::Done(response)) => Ok(StreamEvent::Done(AIResponse {
+                    fallback_from: Some(fallback_from.clone()),
+                    ..response
+                })),
+                other => other,
+            })
+            .boxed())
+    }
+
+    async fn open_stream(&self, request: AIRequest) -> Result<CompletionStream, AIError> {
+        let provider = request.model.provider().to_string();
+        let response = match provider.as_str() {
+            "openai" | "local" => {
+                let mut body = openai_body(request);
+                body["stream"] = JsonValue::Bool(true);
+                body["stream_options"] = serde_json::json!({ "include_usage": true });
+                if provider == "local" {
+                    self.send_local(body).await?
+                } else {
+                    self.send_openai(body, self.api_key("openai")?).await?
+                }
+            }
+            "anthropic" => {
+                let mut body = anthropic_body(request);
+                body["stream"] = JsonValue::Bool(true);
+                self.send_anthropic(body, self.api_key("anthropic")?).await?
+            }
+            "gemini" => {
+                let model = request.model.as_str().to_string();
+                self.send_gemini(gemini_body(request), self.api_key("gemini")?, &model, true).await?
+            }
+            "azure" => {
+                let deployment = request.model.as_str().to_string();
+                let mut body = azure_body(request);
+                body["stream"] = JsonValue::Bool(true);
+                body["stream_options"] = serde_json::json!({ "include_usage": true });
+                self.send_azure(body, self.api_key("azure")?, &deployment).await?
+            }
+            _ => return Err(AIError::UnsupportedProvider(provider)),
+        };
+
+        let chunks = stream::unfold(Some(response), |response| async move {
+            let mut response = response?;
+            match response.chunk().await {
+                Ok(Some(chunk)) => Some((Ok(chunk.to_vec()), Some(response))),
+                Ok(None) => None,
+                Err(e) => Some((Err(AIError::RequestFailed(e.to_string())), None)),
+            }
+        });
+        Ok(decode_stream(provider, chunks.boxed()).boxed())
+    }
+
+    async fn send_openai(&self, body: JsonValue, api_key: &str) -> Result<reqwest::Response, AIError> {
+        let response = self
+            .client
+            .post("https://api.openai.com/v1/chat/completions")
+            .header("Authorization", format!("Bearer {}", api_key))
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| AIError::RequestFailed(e.to_string()))?;
+        check_status(response).await
+    }
+
+    async fn send_anthropic(&self, body: JsonValue, api_key: &str) -> Result<reqwest::Response, AIError> {
+        let response = self
+            .client
+            .post("https://api.anthropic.com/v1/messages")
+            .header("x-api-key", api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| AIError::RequestFailed(e.to_string()))?;
+        check_status(response).await
+    }
+
+    async fn send_azure(&self, body: JsonValue, api_key: &str, deployment: &str) -> Result<reqwest::Response, AIError> {
+        let azure = self.azure.as_ref().ok_or(AIError::AzureEndpointNotConfigured)?;
+        let response = self
+            .client
+            .post(format!("{}/openai/deployments/{}/chat/completions", azure.endpoint, deployment))
+            .query(&[("api-version", azure.api_version.as_str())])
+            .header("api-key", api_key)
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| AIError::RequestFailed(e.to_string()))?;
+        check_status(response).await
+    }
+
+    /// The API key of local servers is optional
+    async fn send_local(&self, body: JsonValue) -> Result<reqwest::Response, AIError> {
+        let endpoint = self.local.as_ref().ok_or(AIError::LocalEndpointNotConfigured)?;
+        let mut request = self
+            .client
+            .post(format!("{}/v1/chat/completions", endpoint))
+            .header("Content-Type", "application/json");
+        if let Some(api_key) = self.api_keys.get("local") {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+        let response = request
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| AIError::RequestFailed(e.to_string()))?;
+        check_status(response).await
+    }
+
+    /// Streaming uses `streamGenerateContent` with server-sent events
+    async fn send_gemini(&self, body: JsonValue, api_key: &str, model: &str, stream: bool) -> Result<reqwest::Response, AIError> {
+        let url = if stream {
+            format!("https://generativelanguage.googleapis.com/v1beta/models/{}:streamGenerateContent?alt=sse", model)
+        } else {
+            format!("https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent", model)
+        };
+        let response = self
+            .client
+            .post(url)
+            .header("x-goog-api-key", api_key)
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| AIError::RequestFailed(e.to_string()))?;
+        check_status(response).await
+    }
 }
 
 impl Default for AIClient {
@@ -251,6 +576,369 @@ impl Default for AIClient {
     }
 }
 
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, AIError> {
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            AIError::RateLimited(error_text)
+        } else if status.is_server_error() {
+            AIError::ProviderUnavailable { status: status.as_u16(), message: error_text }
+        } else {
+            AIError::ApiError(error_text)
+        });
+    }
+    Ok(response)
+}
+
+fn openai_body(request: AIRequest) -> JsonValue {
+    let native_format = request.model.supports_json_schema();
+    let mut body = serde_json::json!({
+        "model": request.model.as_str(),
+        "messages": openai_messages(&request.conversation()),
+    });
+
+    if let Some(temp) = request.temperature {
+        body["temperature"] = JsonValue::from(temp);
+    }
+    if let Some(max_tokens) = request.max_tokens {
+        body["max_tokens"] = JsonValue::from(max_tokens);
+    }
+    if let Some(top_p) = request.top_p {
+        body["top_p"] = JsonValue::from(top_p);
+    }
+    if let Some(tools) = request.tools {
+        let tools: Vec<JsonValue> = tools.iter()
+            .map(|tool| serde_json::json!({ "type": "function", "function": tool }))
+            .collect();
+        body["tools"] = JsonValue::from(tools);
+    }
+    if let Some(format) = request.response_format.filter(|_| native_format) {
+        body["response_format"] = serde_json::json!({
+            "type": "json_schema",
+            "json_schema": { "name": format.name, "schema": format.schema, "strict": true },
+        });
+    }
+    body
+}
+
+/// The OpenAI body without the model, which the Azure deployment determines
+fn azure_body(request: AIRequest) -> JsonValue {
+    let mut body = openai_body(request);
+    if let Some(body) = body.as_object_mut() {
+        body.remove("model");
+    }
+    body
+}
+
+fn gemini_body(request: AIRequest) -> JsonValue {
+    let (system, contents) = gemini_contents(&request.conversation());
+    let mut body = serde_json::json!({ "contents": contents });
+
+    if let Some(system) = system {
+        body["systemInstruction"] = serde_json::json!({ "parts": [{ "text": system }] });
+    }
+    let mut generation = serde_json::Map::new();
+    if let Some(temp) = request.temperature {
+        generation.insert("temperature".to_string(), JsonValue::from(temp));
+    }
+    if let Some(max_tokens) = request.max_tokens {
+        generation.insert("maxOutputTokens".to_string(), JsonValue::from(max_tokens));
+    }
+    if let Some(top_p) = request.top_p {
+        generation.insert("topP".to_string(), JsonValue::from(top_p));
+    }
+    if let Some(format) = request.response_format {
+        generation.insert("responseMimeType".to_string(), JsonValue::from("application/json"));
+        generation.insert("responseSchema".to_string(), format.schema);
+    }
+    if !generation.is_empty() {
+        body["generationConfig"] = JsonValue::Object(generation);
+    }
+    if let Some(tools) = request.tools {
+        body["tools"] = serde_json::json!([{ "functionDeclarations": tools }]);
+    }
+    body
+}
+
+/// A response in the OpenAI chat completions format (also used by Azure)
+fn openai_response(response_json: &JsonValue) -> AIResponse {
+    let choice = &response_json["choices"][0];
+    let message = &choice["message"];
+
+    let content = message["content"]
+        .as_str()
+        .unwrap_or("")
+        .to_string();
+
+    let tool_calls = message["tool_calls"].as_array().map(|calls| {
+        calls
+            .iter()
+            .map(|call| ToolCall {
+                id: call["id"].as_str().unwrap_or("").to_string(),
+                name: call["function"]["name"].as_str().unwrap_or("").to_string(),
+                arguments: call["function"]["arguments"].clone(),
+            })
+            .collect()
+    });
+
+    AIResponse {
+        content,
+        tool_calls,
+        usage: Usage {
+            prompt_tokens: response_json["usage"]["prompt_tokens"].as_u64().unwrap_or(0)
+                as u32,
+            completion_tokens: response_json["usage"]["completion_tokens"]
+                .as_u64()
+                .unwrap_or(0) as u32,
+            total_tokens: response_json["usage"]["total_tokens"].as_u64().unwrap_or(0)
+                as u32,
+        },
+        model: response_json["model"].as_str().unwrap_or("").to_string(),
+        finish_reason: choice["finish_reason"].as_str().unwrap_or("").to_string(),
+        cached: false,
+        structured: None,
+        fallback_from: None,
+    }
+}
+
+/// Function calls of a Gemini response; Gemini has no call IDs, so calls are
+/// identified by function name
+fn gemini_tool_calls(response_json: &JsonValue) -> Option<Vec<ToolCall>> {
+    let calls: Vec<ToolCall> = response_json["candidates"][0]["content"]["parts"]
+        .as_array()?
+        .iter()
+        .filter_map(|part| {
+            let call = part.get("functionCall")?;
+            let name = call["name"].as_str().unwrap_or("").to_string();
+            Some(ToolCall { id: name.clone(), name, arguments: call["args"].clone() })
+        })
+        .collect();
+    (!calls.is_empty()).then_some(calls)
+}
+
+fn anthropic_body(request: AIRequest) -> JsonValue {
+    let (system, messages) = anthropic_messages(&request.conversation());
+    let mut body = serde_json::json!({
+        "model": request.model.as_str(),
+        "messages": messages,
+        "max_tokens": request.max_tokens.unwrap_or(2000),
+        "temperature": request.temperature.unwrap_or(0.7),
+    });
+
+    if let Some(system) = system {
+        body["system"] = JsonValue::from(system);
+    }
+    if let Some(tools) = request.tools {
+        let tools: Vec<JsonValue> = tools.iter()
+            .map(|tool| serde_json::json!({
+                "name": tool.name,
+                "description": tool.description,
+                "input_schema": tool.parameters,
+            }))
+            .collect();
+        body["tools"] = JsonValue::from(tools);
+    }
+    body
+}
+
+/// Streamed completion returned by [`AIClient::generate_stream`]
+pub type CompletionStream = BoxStream<'static, Result<StreamEvent, AIError>>;
+
+/// Item of a streamed completion
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// Text generated since the previous delta
+    Delta(String),
+    /// The whole completion; always the last event
+    Done(AIResponse),
+}
+
+struct DecodeState<S> {
+    chunks: S,
+    provider: String,
+    decoder: SseDecoder,
+    payloads: VecDeque<String>,
+    /// Taken once the stream finished or failed
+    response: Option<StreamedResponse>,
+}
+
+/// Turn the body chunks of a streaming response into completion events
+fn decode_stream<S>(provider: String, chunks: S) -> impl Stream<Item = Result<StreamEvent, AIError>>
+where
+    S: Stream<Item = Result<Vec<u8>, AIError>> + Unpin,
+{
+    let state = DecodeState {
+        chunks,
+        provider,
+        decoder: SseDecoder::default(),
+        payloads: VecDeque::new(),
+        response: Some(StreamedResponse::default()),
+    };
+    stream::unfold(state, |mut state| async move {
+        loop {
+            let response = state.response.as_mut()?;
+            if let Some(data) = state.payloads.pop_front() {
+                match response.apply(&state.provider, &data) {
+                    Ok(Some(delta)) => return Some((Ok(StreamEvent::Delta(delta)), state)),
+                    Ok(None) => continue,
+                    Err(e) => {
+                        state.response = None;
+                        return Some((Err(e), state));
+                    }
+                }
+            }
+            match state.chunks.next().await {
+                Some(Ok(chunk)) => {
+                    let payloads = state.decoder.push(&chunk);
+                    state.payloads.extend(payloads);
+                }
+                Some(Err(e)) => {
+                    state.response = None;
+                    return Some((Err(e), state));
+                }
+                None => {
+                    let done = state.response.take()?.finish();
+                    return Some((Ok(StreamEvent::Done(done)), state));
+                }
+            }
+        }
+    })
+}
+
+/// Splits a server-sent event stream into the payloads of its `data:` lines
+#[derive(Default)]
+struct SseDecoder {
+    buffer: Vec<u8>,
+}
+
+impl SseDecoder {
+    /// Feed received bytes, returning the payloads of the lines completed by them
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut payloads = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(data) = line.trim_end().strip_prefix("data:") {
+                payloads.push(data.trim_start().to_string());
+            }
+        }
+        payloads
+    }
+}
+
+/// A response assembled from stream events
+#[derive(Default)]
+struct StreamedResponse {
+    content: String,
+    usage: Option<Usage>,
+    model: String,
+    finish_reason: String,
+}
+
+impl StreamedResponse {
+    /// Apply one event payload, returning the text it adds
+    fn apply(&mut self, provider: &str, data: &str) -> Result<Option<String>, AIError> {
+        if data == "[DONE]" {
+            return Ok(None);
+        }
+        let event: JsonValue = serde_json::from_str(data).map_err(|e| AIError::ParseError(e.to_string()))?;
+        if let Some(error) = event.get("error") {
+            return Err(AIError::ApiError(error.to_string()));
+        }
+
+        let delta = match provider {
+            "gemini" => self.apply_gemini(&event),
+            "openai" | "azure" | "local" => {
+                if let Some(model) = event["model"].as_str() {
+                    self.model = model.to_string();
+                }
+                let choice = &event["choices"][0];
+                if let Some(reason) = choice["finish_reason"].as_str() {
+                    self.finish_reason = reason.to_string();
+                }
+                if event["usage"].is_object() {
+                    self.usage = Some(Usage {
+                        prompt_tokens: event["usage"]["prompt_tokens"].as_u64().unwrap_or(0) as u32,
+                        completion_tokens: event["usage"]["completion_tokens"].as_u64().unwrap_or(0) as u32,
+                        total_tokens: event["usage"]["total_tokens"].as_u64().unwrap_or(0) as u32,
+                    });
+                }
+                choice["delta"]["content"].as_str().map(str::to_string)
+            }
+            _ => match event["type"].as_str() {
+                Some("message_start") => {
+                    let message = &event["message"];
+                    self.model = message["model"].as_str().unwrap_or("").to_string();
+                    let prompt_tokens = message["usage"]["input_tokens"].as_u64().unwrap_or(0) as u32;
+                    self.usage = Some(Usage { prompt_tokens, completion_tokens: 0, total_tokens: prompt_tokens });
+                    None
+                }
+                Some("message_delta") => {
+                    if let Some(reason) = event["delta"]["stop_reason"].as_str() {
+                        self.finish_reason = reason.to_string();
+                    }
+                    let usage = self.usage.get_or_insert(Usage { prompt_tokens: 0, completion_tokens: 0, total_tokens: 0 });
+                    usage.completion_tokens = event["usage"]["output_tokens"].as_u64().unwrap_or(0) as u32;
+                    usage.total_tokens = usage.prompt_tokens + usage.completion_tokens;
+                    None
+                }
+                Some("content_block_delta") => event["delta"]["text"].as_str().map(str::to_string),
+                _ => None,
+            },
+        };
+
+        let delta = delta.filter(|text| !text.is_empty());
+        if let Some(text) = &delta {
+            self.content.push_str(text);
+        }
+        Ok(delta)
+    }
+
+    /// Apply a Gemini response (a whole one, or a chunk of a streamed one),
+    /// returning its text
+    fn apply_gemini(&mut self, event: &JsonValue) -> Option<String> {
+        if let Some(model) = event["modelVersion"].as_str() {
+            self.model = model.to_string();
+        }
+        let candidate = &event["candidates"][0];
+        // Reasons are upper case, e.g. STOP, MAX_TOKENS or SAFETY
+        if let Some(reason) = candidate["finishReason"].as_str() {
+            self.finish_reason = reason.to_lowercase();
+        }
+        // Every chunk carries the usage so far
+        let usage = &event["usageMetadata"];
+        if usage.is_object() {
+            let prompt_tokens = usage["promptTokenCount"].as_u64().unwrap_or(0) as u32;
+            let completion_tokens = usage["candidatesTokenCount"].as_u64().unwrap_or(0) as u32;
+            self.usage = Some(Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: usage["totalTokenCount"].as_u64().map(|t| t as u32).unwrap_or(prompt_tokens + completion_tokens),
+            });
+        }
+        // Function call parts have no text
+        let text: String = candidate["content"]["parts"].as_array()?.iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect();
+        Some(text)
+    }
+
+    fn finish(self) -> AIResponse {
+        AIResponse {
+            content: self.content,
+            tool_calls: None,
+            usage: self.usage.unwrap_or(Usage { prompt_tokens: 0, completion_tokens: 0, total_tokens: 0 }),
+            model: self.model,
+            finish_reason: self.finish_reason,
+            cached: false,
+            structured: None,
+            fallback_from: None,
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum AIError {
     #[error("API key not configured for provider: {0}")]
@@ -267,6 +955,72 @@ pub enum AIError {
 
     #[error("Parse error: {0}")]
     ParseError(String),
+
+    #[error("Azure OpenAI endpoint not configured")]
+    AzureEndpointNotConfigured,
+
+    #[error("Answer does not match the response schema after {attempts} attempts: {error}")]
+    SchemaViolation { attempts: u32, error: String },
+
+    #[error("Rate limited by provider: {0}")]
+    RateLimited(String),
+
+    #[error("Provider unavailable ({status}): {message}")]
+    ProviderUnavailable { status: u16, message: String },
+
+    #[error("Local model endpoint not configured")]
+    LocalEndpointNotConfigured,
+
+    #[error("Provider did not answer within {0:?}")]
+    Timeout(Duration),
+
+    #[error("Circuit open for provider {provider} after repeated failures; retry in {retry_after:?}")]
+    CircuitOpen { provider: String, retry_after: Duration },
+}
+
+impl AIError {
+    /// Whether another provider may well answer the request: the provider
+    /// rate limited it, failed or could not be reached
+    pub fn should_fall_back(&self) -> bool {
+        self.should_retry() || matches!(self, AIError::CircuitOpen { .. })
+    }
+
+    /// Whether sending the request again may succeed: the provider rate
+    /// limited it, failed, timed out or could not be reached
+    pub fn should_retry(&self) -> bool {
+        matches!(
+            self,
+            AIError::RateLimited(_) | AIError::ProviderUnavailable { .. } | AIError::RequestFailed(_) | AIError::Timeout(_)
+        )
+    }
+}
+
+impl ErrorInfo for AIError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            AIError::ApiKeyNotConfigured(_)
+            | AIError::AzureEndpointNotConfigured
+            | AIError::LocalEndpointNotConfigured => ErrorCode::InvalidInput,
+            AIError::UnsupportedProvider(_) => ErrorCode::InvalidInput,
+            AIError::RequestFailed(_) => ErrorCode::Unavailable,
+            AIError::ApiError(_) | AIError::ParseError(_) => ErrorCode::UpstreamFailed,
+            AIError::SchemaViolation { .. } => ErrorCode::UpstreamFailed,
+            AIError::RateLimited(_) => ErrorCode::RateLimited,
+            AIError::ProviderUnavailable { .. } | AIError::CircuitOpen { .. } => ErrorCode::Unavailable,
+            AIError::Timeout(_) => ErrorCode::Timeout,
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        // Calls to an open circuit are meant to fail fast, not to be retried
+        !matches!(self, AIError::CircuitOpen { .. }) && self.error_code().is_retryable()
+    }
+}
+
+impl From<AIError> for PlatformError {
+    fn from(error: AIError) -> Self {
+        PlatformError::service(error)
+    }
 }
 
 #[cfg(test)]
@@ -280,6 +1034,106 @@ mod tests {
         assert_eq!(request.model, ModelType::GPT4);
     }
 
+    #[test]
+    fn test_gemini_and_azure_bodies() {
+        let mut request = AIRequest::with_messages(ModelType::Gemini15Pro, vec![
+            ChatMessage::system("Answer briefly."),
+            ChatMessage::user("Hi"),
+        ]);
+        request.max_tokens = Some(64);
+        let body = gemini_body(request);
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Answer briefly.");
+        assert_eq!(body["contents"], serde_json::json!([{ "role": "user", "parts": [{ "text": "Hi" }] }]));
+        assert_eq!(body["generationConfig"], serde_json::json!({ "maxOutputTokens": 64 }));
+
+        let azure = ModelType::AzureOpenAI { deployment: "gpt4o-prod".to_string() };
+        let body = azure_body(AIRequest::new(azure, "Hello".to_string()));
+        assert!(body.get("model").is_none());
+        assert_eq!(body["messages"][0]["content"], "Hello");
+
+        let response = serde_json::json!({
+            "candidates": [{
+                "content": { "role": "model", "parts": [{ "functionCall": { "name": "lookup_order", "args": { "order": 7 } } }] },
+                "finishReason": "STOP"
+            }]
+        });
+        let calls = gemini_tool_calls(&response).unwrap();
+        assert_eq!((calls[0].id.as_str(), calls[0].arguments["order"].as_u64()), ("lookup_order", Some(7)));
+    }
+
+    #[test]
+    fn test_response_format_bodies() {
+        let format = ResponseFormat::new(serde_json::json!({ "type": "object" })).with_name("answer");
+        let request = |model| AIRequest { response_format: Some(format.clone()), ..AIRequest::new(model, "Hi".to_string()) };
+
+        let azure = azure_body(request(ModelType::AzureOpenAI { deployment: "gpt4o-prod".to_string() }));
+        assert_eq!(azure["response_format"]["type"], "json_schema");
+        assert_eq!(azure["response_format"]["json_schema"]["name"], "answer");
+        let gemini = gemini_body(request(ModelType::Gemini15Flash));
+        assert_eq!(gemini["generationConfig"]["responseMimeType"], "application/json");
+        assert_eq!(gemini["generationConfig"]["responseSchema"], format.schema);
+
+        // Older OpenAI models are asked for the schema in the prompt instead
+        assert!(openai_body(request(ModelType::GPT4)).get("response_format").is_none());
+        assert!(anthropic_body(request(ModelType::Claude3Opus)).get("response_format").is_none());
+    }
+
+    #[test]
+    fn test_fallback_candidates_need_a_configured_provider() {
+        let local = ModelType::Local { model: "llama3".to_string() };
+        let policy = ModelFallbackPolicy::new().with_chain(vec![ModelType::GPT4o, ModelType::Claude3Sonnet, local.clone()]);
+        let client = AIClient::new()
+            .with_api_key("openai".to_string(), "sk-test".to_string())
+            .with_local_endpoint("http://localhost:11434/".to_string())
+            .with_fallback_policy(policy);
+        assert_eq!(client.candidates(&ModelType::GPT4o), vec![ModelType::GPT4o, local.clone()]);
+        // The requested model is always tried
+        assert_eq!(client.candidates(&ModelType::Claude3Sonnet), vec![ModelType::Claude3Sonnet, local]);
+        assert_eq!(client.local.as_deref(), Some("http://localhost:11434"));
+    }
+
+    #[tokio::test]
+    async fn test_azure_requires_an_endpoint() {
+        let client = AIClient::new().with_api_key("azure".to_string(), "key".to_string());
+        let request = AIRequest::new(ModelType::AzureOpenAI { deployment: "gpt4o-prod".to_string() }, "Hi".to_string());
+        assert!(matches!(client.generate(request).await, Err(AIError::AzureEndpointNotConfigured)));
+    }
+
+    #[test]
+    fn test_request_bodies_carry_the_conversation() {
+        let prompt = openai_body(AIRequest::new(ModelType::GPT4, "Hello".to_string()));
+        assert_eq!(prompt["messages"], serde_json::json!([{ "role": "user", "content": "Hello" }]));
+
+        let request = AIRequest::with_messages(ModelType::Claude3Opus, vec![
+            ChatMessage::system("Answer briefly."),
+            ChatMessage::user("Hi"),
+            ChatMessage::assistant("Hello!"),
+            ChatMessage::user("How are you?"),
+        ]);
+        assert_eq!(openai_body(request.clone())["messages"].as_array().unwrap().len(), 4);
+        let body = anthropic_body(request);
+        assert_eq!(body["system"], "Answer briefly.");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 3);
+        assert_eq!(body["messages"][2]["content"][0]["text"], "How are you?");
+    }
+
+    #[test]
+    fn test_tool_definitions_per_provider() {
+        let mut request = AIRequest::new(ModelType::GPT4, "What is 4 times 5?".to_string());
+        request.tools = Some(vec![Tool {
+            name: "calculator".to_string(),
+            description: "Perform basic arithmetic operations".to_string(),
+            parameters: serde_json::json!({ "type": "object" }),
+        }]);
+        let openai = openai_body(request.clone());
+        assert_eq!(openai["tools"][0]["type"], "function");
+        assert_eq!(openai["tools"][0]["function"]["name"], "calculator");
+        let anthropic = anthropic_body(request.clone());
+        assert_eq!(anthropic["tools"][0]["input_schema"], serde_json::json!({ "type": "object" }));
+        let gemini = gemini_body(request);
+        assert_eq!(gemini["tools"][0]["functionDeclarations"][0]["name"], "calculator");
+    }
+
     #[test]
     fn test_ai_client_creation() {
         let client = AIClient::new()
@@ -287,4 +1141,80 @@ mod tests {
 
         assert!(client.api_keys.contains_key("openai"));
     }
+
+    #[test]
+    fn test_sse_decoder_splits_lines_across_chunks() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b"event: ping\ndata: {\"a\"").is_empty());
+        assert_eq!(decoder.push(b":1}\n\ndata: [DONE]\n"), vec!["{\"a\":1}", "[DONE]"]);
+    }
+
+    #[test]
+    fn test_streamed_responses() {
+        let mut openai = StreamedResponse::default();
+        let events = [
+            r#"{"model":"gpt-4","choices":[{"delta":{"role":"assistant"}}]}"#,
+            r#"{"model":"gpt-4","choices":[{"delta":{"content":"Hel"}}]}"#,
+            r#"{"model":"gpt-4","choices":[{"delta":{"content":"lo"},"finish_reason":"stop"}]}"#,
+            r#"{"model":"gpt-4","choices":[],"usage":{"prompt_tokens":3,"completion_tokens":2,"total_tokens":5}}"#,
+            "[DONE]",
+        ];
+        let deltas: Vec<String> = events.iter().filter_map(|e| openai.apply("openai", e).unwrap()).collect();
+        assert_eq!(deltas, vec!["Hel", "lo"]);
+        let response = openai.finish();
+        assert_eq!(response.content, "Hello");
+        assert_eq!(response.finish_reason, "stop");
+        assert_eq!(response.usage.total_tokens, 5);
+
+        let mut anthropic = StreamedResponse::default();
+        let events = [
+            r#"{"type":"message_start","message":{"model":"claude-3-opus-20240229","usage":{"input_tokens":4}}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":1}}"#,
+        ];
+        for event in events {
+            anthropic.apply("anthropic", event).unwrap();
+        }
+        let response = anthropic.finish();
+        assert_eq!(response.content, "Hi");
+        assert_eq!(response.usage.total_tokens, 5);
+
+        let mut gemini = StreamedResponse::default();
+        let events = [
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"Bon"}]}}],"usageMetadata":{"promptTokenCount":6,"candidatesTokenCount":1,"totalTokenCount":7},"modelVersion":"gemini-1.5-flash-002"}"#,
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"jour"}]},"finishReason":"STOP"}],"usageMetadata":{"promptTokenCount":6,"candidatesTokenCount":2,"totalTokenCount":8},"modelVersion":"gemini-1.5-flash-002"}"#,
+        ];
+        let deltas: Vec<String> = events.iter().filter_map(|e| gemini.apply("gemini", e).unwrap()).collect();
+        assert_eq!(deltas, vec!["Bon", "jour"]);
+        let response = gemini.finish();
+        assert_eq!((response.content.as_str(), response.finish_reason.as_str()), ("Bonjour", "stop"));
+        assert_eq!((response.usage.completion_tokens, response.usage.total_tokens), (2, 8));
+        assert_eq!(response.model, "gemini-1.5-flash-002");
+
+        let error = r#"{"type":"error","error":{"type":"overloaded_error"}}"#;
+        assert!(StreamedResponse::default().apply("anthropic", error).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_decode_stream() {
+        let chunks: Vec<Result<Vec<u8>, AIError>> = vec![
+            Ok(b"data: {\"choices\":[{\"delta\":{\"content\":\"Hel".to_vec()),
+            Ok(b"\"}}]}\n\ndata: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\ndata: [DONE]\n".to_vec()),
+        ];
+        let events: Vec<StreamEvent> = decode_stream("openai".to_string(), stream::iter(chunks))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert!(matches!(&events[..], [StreamEvent::Delta(a), StreamEvent::Delta(b), StreamEvent::Done(done)]
+            if a == "Hel" && b == "lo" && done.content == "Hello"));
+
+        let chunks: Vec<Result<Vec<u8>, AIError>> = vec![
+            Ok(b"data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n".to_vec()),
+            Err(AIError::RequestFailed("connection reset".to_string())),
+            Ok(b"data: {\"choices\":[{\"delta\":{\"content\":\"!\"}}]}\n".to_vec()),
+        ];
+        let events: Vec<_> = decode_stream("openai".to_string(), stream::iter(chunks)).collect().await;
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[1], Err(AIError::RequestFailed(_))));
+    }
 }
@@ -0,0 +1,183 @@
+//! Model fallback: when the model asked for is rate limited or its provider
+//! fails, the request goes to the next model of its fallback chain.
+
+use crate::client::AIError;
+use crate::models::ModelType;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+/// Health of AI providers, shared with whatever else calls them (e.g. the
+/// gateway's failover manager), keyed by provider name such as `openai`
+#[async_trait]
+pub trait HealthTracker: Send + Sync {
+    async fn is_healthy(&self, provider: &str) -> bool;
+
+    async fn record_success(&self, provider: &str);
+
+    async fn record_failure(&self, provider: &str);
+}
+
+/// Chains of models tried in order, e.g. gpt-4o -> claude-3-sonnet -> a
+/// local model. A request for a model of a chain falls back to the models
+/// after it; models outside every chain do not fall back.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelFallbackPolicy {
+    #[serde(default)]
+    pub chains: Vec<Vec<ModelType>>,
+}
+
+impl ModelFallbackPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_chain(mut self, chain: Vec<ModelType>) -> Self {
+        self.chains.push(chain);
+        self
+    }
+
+    /// The models to try for a request of `model`, starting with it; the
+    /// first chain holding the model applies
+    pub fn candidates(&self, model: &ModelType) -> Vec<ModelType> {
+        let fallbacks = self.chains.iter().find_map(|chain| {
+            let position = chain.iter().position(|m| m == model)?;
+            Some(&chain[position + 1..])
+        });
+        let mut candidates = vec![model.clone()];
+        candidates.extend(fallbacks.into_iter().flatten().filter(|m| *m != model).cloned());
+        candidates
+    }
+}
+
+/// Try `attempt` with each candidate model in turn until one answers,
+/// moving on only after errors that another provider may not have (see
+/// [`AIError::should_fall_back`]).
+///
+/// Candidates whose provider the tracker reports unhealthy are tried last.
+/// Outcomes are recorded with the tracker. Returns the result with the model
+/// that produced it.
+pub(crate) async fn with_fallbacks<T, F, Fut>(
+    candidates: Vec<ModelType>,
+    health: Option<&dyn HealthTracker>,
+    attempt: F,
+) -> Result<(T, ModelType), AIError>
+where
+    F: Fn(ModelType) -> Fut,
+    Fut: Future<Output = Result<T, AIError>>,
+{
+    let mut ordered = Vec::with_capacity(candidates.len());
+    let mut unhealthy = Vec::new();
+    for model in candidates {
+        match health {
+            Some(health) if !health.is_healthy(model.provider()).await => unhealthy.push(model),
+            _ => ordered.push(model),
+        }
+    }
+    ordered.extend(unhealthy);
+
+    let last = ordered.len().saturating_sub(1);
+    for (index, model) in ordered.into_iter().enumerate() {
+        let provider = model.provider().to_string();
+        match attempt(model.clone()).await {
+            Ok(value) => {
+                if let Some(health) = health {
+                    health.record_success(&provider).await;
+                }
+                return Ok((value, model));
+            }
+            Err(e) if e.should_fall_back() => {
+                if let Some(health) = health {
+                    health.record_failure(&provider).await;
+                }
+                if index == last {
+                    return Err(e);
+                }
+                tracing::warn!("Model {} failed, falling back: {}", model.as_str(), e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Err(AIError::UnsupportedProvider("no model to try".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Health(Mutex<HashMap<String, i32>>);
+
+    #[async_trait]
+    impl HealthTracker for Health {
+        async fn is_healthy(&self, provider: &str) -> bool {
+            self.0.lock().unwrap().get(provider).copied().unwrap_or(0) > -3
+        }
+
+        async fn record_success(&self, provider: &str) {
+            self.0.lock().unwrap().insert(provider.to_string(), 0);
+        }
+
+        async fn record_failure(&self, provider: &str) {
+            *self.0.lock().unwrap().entry(provider.to_string()).or_default() -= 1;
+        }
+    }
+
+    fn local() -> ModelType {
+        ModelType::Local { model: "llama3".to_string() }
+    }
+
+    fn policy() -> ModelFallbackPolicy {
+        ModelFallbackPolicy::new().with_chain(vec![ModelType::GPT4o, ModelType::Claude3Sonnet, local()])
+    }
+
+    #[test]
+    fn test_candidates() {
+        let policy = policy();
+        assert_eq!(policy.candidates(&ModelType::GPT4o), vec![ModelType::GPT4o, ModelType::Claude3Sonnet, local()]);
+        assert_eq!(policy.candidates(&ModelType::Claude3Sonnet), vec![ModelType::Claude3Sonnet, local()]);
+        assert_eq!(policy.candidates(&ModelType::GPT4), vec![ModelType::GPT4]);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_provider_failures() {
+        let health = Health::default();
+        let attempt = |model: ModelType| async move {
+            match model {
+                ModelType::GPT4o => Err(AIError::RateLimited("slow down".to_string())),
+                ModelType::Claude3Sonnet => Err(AIError::ProviderUnavailable { status: 529, message: "overloaded".to_string() }),
+                _ => Ok("hello"),
+            }
+        };
+
+        let (answer, model) = with_fallbacks(policy().candidates(&ModelType::GPT4o), Some(&health), attempt).await.unwrap();
+        assert_eq!((answer, model), ("hello", local()));
+        assert_eq!(health.0.lock().unwrap()["openai"], -1);
+        assert_eq!(health.0.lock().unwrap()["local"], 0);
+
+        // Requests that are wrong fail without falling back
+        let invalid = |_model: ModelType| async { Err::<(), _>(AIError::ApiError("bad request".to_string())) };
+        let error = with_fallbacks(policy().candidates(&ModelType::GPT4o), Some(&health), invalid).await.unwrap_err();
+        assert!(matches!(error, AIError::ApiError(_)));
+        assert_eq!(health.0.lock().unwrap()["openai"], -1);
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_providers_are_tried_last() {
+        let health = Health::default();
+        for _ in 0..3 {
+            health.record_failure("openai").await;
+        }
+        let tried = Mutex::new(Vec::new());
+        let attempt = |model: ModelType| {
+            tried.lock().unwrap().push(model.clone());
+            async { Err::<(), _>(AIError::RequestFailed("connection refused".to_string())) }
+        };
+
+        let error = with_fallbacks(policy().candidates(&ModelType::GPT4o), Some(&health), attempt).await.unwrap_err();
+        assert!(matches!(error, AIError::RequestFailed(_)));
+        assert_eq!(tried.into_inner().unwrap(), vec![ModelType::Claude3Sonnet, local(), ModelType::GPT4o]);
+    }
+}
@@ -1,11 +1,26 @@
 pub mod models;
 pub mod prompt;
 pub mod injection;
+pub mod messages;
 pub mod tools;
 pub mod client;
+pub mod agent;
+pub mod cache;
+pub mod structured;
+pub mod fallback;
+pub mod resilience;
 
 pub use models::{ModelManager, ModelType, ModelConfig};
-pub use prompt::{PromptTemplate, TemplateEngine};
-pub use injection::InjectionDetector;
+pub use prompt::{CompiledTemplate, MissingVariables, PromptTemplate, TemplateEngine};
+pub use injection::{
+    BlockedPrompt, Finding, HeuristicClassifier, InjectionAuditor, InjectionClassifier, InjectionDetector,
+    LlmClassifier, RegexClassifier, Screening, ScreeningAction, ScreeningPolicy,
+};
+pub use messages::{ChatMessage, MessageRole};
 pub use tools::{ToolRegistry, Tool, ToolCall};
-pub use client::{AIClient, AIRequest, AIResponse};
+pub use client::{AIClient, AIRequest, AIResponse, CompletionStream, StreamEvent};
+pub use agent::{AgentExecutor, AgentStop, AgentTranscript, CompletionModel};
+pub use cache::{AICache, CacheObserver, CachePolicy, CacheStore, MemoryCacheStore, RedisCacheStore};
+pub use structured::ResponseFormat;
+pub use fallback::{HealthTracker, ModelFallbackPolicy};
+pub use resilience::{CircuitBreaker, RetryPolicy};
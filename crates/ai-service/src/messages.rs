@@ -0,0 +1,299 @@
+use crate::tools::ToolCall;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+/// Author of a chat message
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageRole {
+    System,
+    User,
+    Assistant,
+    /// Result of a tool call requested by the assistant
+    Tool,
+}
+
+/// One message of a conversation with a model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: MessageRole,
+    #[serde(default)]
+    pub content: String,
+    /// Tools the assistant called in this message
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// The call a tool message answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    pub fn new(role: MessageRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(MessageRole::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(MessageRole::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(MessageRole::Assistant, content)
+    }
+
+    /// The result of the tool call `tool_call_id`
+    pub fn tool(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Self::new(MessageRole::Tool, content)
+        }
+    }
+
+    pub fn with_tool_calls(mut self, tool_calls: Vec<ToolCall>) -> Self {
+        self.tool_calls = tool_calls;
+        self
+    }
+}
+
+/// Messages in the OpenAI chat completions format
+pub fn openai_messages(messages: &[ChatMessage]) -> Vec<JsonValue> {
+    messages
+        .iter()
+        .map(|message| match message.role {
+            MessageRole::Tool => serde_json::json!({
+                "role": "tool",
+                "tool_call_id": message.tool_call_id.as_deref().unwrap_or(""),
+                "content": message.content,
+            }),
+            MessageRole::Assistant if !message.tool_calls.is_empty() => {
+                let tool_calls: Vec<JsonValue> = message
+                    .tool_calls
+                    .iter()
+                    .map(|call| {
+                        serde_json::json!({
+                            "id": call.id,
+                            "type": "function",
+                            "function": {
+                                "name": call.name,
+                                // OpenAI takes the arguments as a JSON string
+                                "arguments": match &call.arguments {
+                                    JsonValue::String(arguments) => arguments.clone(),
+                                    arguments => arguments.to_string(),
+                                },
+                            },
+                        })
+                    })
+                    .collect();
+                serde_json::json!({
+                    "role": "assistant",
+                    "content": if message.content.is_empty() { JsonValue::Null } else { JsonValue::from(message.content.as_str()) },
+                    "tool_calls": tool_calls,
+                })
+            }
+            role => serde_json::json!({
+                "role": role,
+                "content": message.content,
+            }),
+        })
+        .collect()
+}
+
+/// Messages in the Anthropic messages format, with the system prompt they
+/// carry.
+///
+/// Anthropic takes the system prompt separately, expects tool results in user
+/// messages and requires the roles to alternate, so consecutive messages of
+/// the same role are merged into one.
+pub fn anthropic_messages(messages: &[ChatMessage]) -> (Option<String>, Vec<JsonValue>) {
+    let system: Vec<&str> = messages
+        .iter()
+        .filter(|message| message.role == MessageRole::System)
+        .map(|message| message.content.as_str())
+        .collect();
+
+    let mut turns: Vec<(&str, Vec<JsonValue>)> = Vec::new();
+    for message in messages {
+        let (role, blocks) = match message.role {
+            MessageRole::System => continue,
+            MessageRole::User => ("user", vec![text_block(&message.content)]),
+            MessageRole::Tool => (
+                "user",
+                vec![serde_json::json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.tool_call_id.as_deref().unwrap_or(""),
+                    "content": message.content,
+                })],
+            ),
+            MessageRole::Assistant => {
+                let mut blocks = Vec::new();
+                if !message.content.is_empty() || message.tool_calls.is_empty() {
+                    blocks.push(text_block(&message.content));
+                }
+                for call in &message.tool_calls {
+                    // Anthropic takes the arguments as an object
+                    let input = match &call.arguments {
+                        JsonValue::String(arguments) => {
+                            serde_json::from_str(arguments).unwrap_or_else(|_| serde_json::json!({}))
+                        }
+                        arguments => arguments.clone(),
+                    };
+                    blocks.push(serde_json::json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.name,
+                        "input": input,
+                    }));
+                }
+                ("assistant", blocks)
+            }
+        };
+        match turns.last_mut() {
+            Some((last, content)) if *last == role => content.extend(blocks),
+            _ => turns.push((role, blocks)),
+        }
+    }
+
+    let system = (!system.is_empty()).then(|| system.join("\n\n"));
+    let messages = turns
+        .into_iter()
+        .map(|(role, content)| serde_json::json!({ "role": role, "content": content }))
+        .collect();
+    (system, messages)
+}
+
+/// Contents in the Gemini `generateContent` format, with the system
+/// instruction they carry.
+///
+/// Gemini calls the assistant `model`, takes tool results as
+/// `functionResponse` parts of user turns (named after the call they answer)
+/// and, like Anthropic, expects consecutive turns of one role merged.
+pub fn gemini_contents(messages: &[ChatMessage]) -> (Option<String>, Vec<JsonValue>) {
+    let system: Vec<&str> = messages
+        .iter()
+        .filter(|message| message.role == MessageRole::System)
+        .map(|message| message.content.as_str())
+        .collect();
+    let call_names: HashMap<&str, &str> = messages
+        .iter()
+        .flat_map(|message| &message.tool_calls)
+        .map(|call| (call.id.as_str(), call.name.as_str()))
+        .collect();
+
+    let mut turns: Vec<(&str, Vec<JsonValue>)> = Vec::new();
+    for message in messages {
+        let (role, parts) = match message.role {
+            MessageRole::System => continue,
+            MessageRole::User => ("user", vec![serde_json::json!({ "text": message.content })]),
+            MessageRole::Tool => {
+                let id = message.tool_call_id.as_deref().unwrap_or("");
+                // Gemini takes the result as an object
+                let response = match serde_json::from_str::<JsonValue>(&message.content) {
+                    Ok(JsonValue::Object(object)) => JsonValue::Object(object),
+                    _ => serde_json::json!({ "content": message.content }),
+                };
+                let part = serde_json::json!({
+                    "functionResponse": {
+                        "name": call_names.get(id).copied().unwrap_or(id),
+                        "response": response,
+                    }
+                });
+                ("user", vec![part])
+            }
+            MessageRole::Assistant => {
+                let mut parts = Vec::new();
+                if !message.content.is_empty() || message.tool_calls.is_empty() {
+                    parts.push(serde_json::json!({ "text": message.content }));
+                }
+                for call in &message.tool_calls {
+                    let args = match &call.arguments {
+                        JsonValue::String(arguments) => {
+                            serde_json::from_str(arguments).unwrap_or_else(|_| serde_json::json!({}))
+                        }
+                        arguments => arguments.clone(),
+                    };
+                    parts.push(serde_json::json!({ "functionCall": { "name": call.name, "args": args } }));
+                }
+                ("model", parts)
+            }
+        };
+        match turns.last_mut() {
+            Some((last, content)) if *last == role => content.extend(parts),
+            _ => turns.push((role, parts)),
+        }
+    }
+
+    let system = (!system.is_empty()).then(|| system.join("\n\n"));
+    let contents = turns
+        .into_iter()
+        .map(|(role, parts)| serde_json::json!({ "role": role, "parts": parts }))
+        .collect();
+    (system, contents)
+}
+
+fn text_block(text: &str) -> JsonValue {
+    serde_json::json!({ "type": "text", "text": text })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation() -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::system("You are a support agent."),
+            ChatMessage::user("Where is order 7?"),
+            ChatMessage::assistant("").with_tool_calls(vec![ToolCall {
+                id: "call_1".to_string(),
+                name: "lookup_order".to_string(),
+                arguments: JsonValue::String(r#"{"order":7}"#.to_string()),
+            }]),
+            ChatMessage::tool("call_1", r#"{"status":"shipped"}"#),
+            ChatMessage::user("Thanks, when will it arrive?"),
+        ]
+    }
+
+    #[test]
+    fn test_openai_messages() {
+        let messages = openai_messages(&conversation());
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[0], serde_json::json!({ "role": "system", "content": "You are a support agent." }));
+        assert_eq!(messages[2]["content"], JsonValue::Null);
+        assert_eq!(messages[2]["tool_calls"][0]["function"]["arguments"], r#"{"order":7}"#);
+        assert_eq!(messages[3]["role"], "tool");
+        assert_eq!(messages[3]["tool_call_id"], "call_1");
+    }
+
+    #[test]
+    fn test_anthropic_messages() {
+        let (system, messages) = anthropic_messages(&conversation());
+        assert_eq!(system.as_deref(), Some("You are a support agent."));
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(messages[1]["content"][0]["input"]["order"], 7);
+        // The tool result and the following question form one user turn
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], "call_1");
+        assert_eq!(messages[2]["content"][1]["text"], "Thanks, when will it arrive?");
+    }
+
+    #[test]
+    fn test_gemini_contents() {
+        let (system, contents) = gemini_contents(&conversation());
+        assert_eq!(system.as_deref(), Some("You are a support agent."));
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(contents[1]["parts"][0]["functionCall"]["args"]["order"], 7);
+        assert_eq!(contents[2]["parts"][0]["functionResponse"]["name"], "lookup_order");
+        assert_eq!(contents[2]["parts"][0]["functionResponse"]["response"]["status"], "shipped");
+        assert_eq!(contents[2]["parts"][1]["text"], "Thanks, when will it arrive?");
+    }
+}
@@ -1,3 +1,4 @@
+use common::error::{ErrorCode, ErrorInfo};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -6,6 +7,8 @@ use std::collections::HashMap;
 pub enum ModelType {
     #[serde(rename = "gpt-4")]
     GPT4,
+    #[serde(rename = "gpt-4o")]
+    GPT4o,
     #[serde(rename = "gpt-4-turbo")]
     GPT4Turbo,
     #[serde(rename = "gpt-3.5-turbo")]
@@ -14,23 +17,59 @@ pub enum ModelType {
     Claude3Opus,
     #[serde(rename = "claude-3-sonnet")]
     Claude3Sonnet,
+    #[serde(rename = "gemini-1.5-pro")]
+    Gemini15Pro,
+    #[serde(rename = "gemini-1.5-flash")]
+    Gemini15Flash,
+    /// A model deployed on the Azure OpenAI resource configured on the client,
+    /// addressed by its deployment name
+    #[serde(rename = "azure-openai")]
+    AzureOpenAI { deployment: String },
+    /// A model served by the OpenAI-compatible server configured on the
+    /// client (e.g. Ollama or vLLM), addressed by its name there
+    #[serde(rename = "local")]
+    Local { model: String },
 }
 
 impl ModelType {
     pub fn as_str(&self) -> &str {
         match self {
             ModelType::GPT4 => "gpt-4",
+            ModelType::GPT4o => "gpt-4o",
             ModelType::GPT4Turbo => "gpt-4-turbo",
             ModelType::GPT35Turbo => "gpt-3.5-turbo",
             ModelType::Claude3Opus => "claude-3-opus-20240229",
             ModelType::Claude3Sonnet => "claude-3-sonnet-20240229",
+            ModelType::Gemini15Pro => "gemini-1.5-pro",
+            ModelType::Gemini15Flash => "gemini-1.5-flash",
+            ModelType::AzureOpenAI { deployment } => deployment,
+            ModelType::Local { model } => model,
         }
     }
 
     pub fn provider(&self) -> &str {
         match self {
-            ModelType::GPT4 | ModelType::GPT4Turbo | ModelType::GPT35Turbo => "openai",
+            ModelType::GPT4 | ModelType::GPT4o | ModelType::GPT4Turbo | ModelType::GPT35Turbo => "openai",
             ModelType::Claude3Opus | ModelType::Claude3Sonnet => "anthropic",
+            ModelType::Gemini15Pro | ModelType::Gemini15Flash => "gemini",
+            ModelType::AzureOpenAI { .. } => "azure",
+            ModelType::Local { .. } => "local",
+        }
+    }
+
+    /// Whether the provider holds completions of the model to a JSON Schema
+    /// itself; other models are asked for it in the prompt
+    pub fn supports_json_schema(&self) -> bool {
+        match self {
+            // Structured outputs came after these OpenAI models
+            ModelType::GPT4 | ModelType::GPT4Turbo | ModelType::GPT35Turbo => false,
+            ModelType::GPT4o => true,
+            ModelType::Claude3Opus | ModelType::Claude3Sonnet => false,
+            ModelType::Gemini15Pro | ModelType::Gemini15Flash => true,
+            // Deployments are expected to serve current models
+            ModelType::AzureOpenAI { .. } => true,
+            // Not every server supports it
+            ModelType::Local { .. } => false,
         }
     }
 }
@@ -139,6 +178,15 @@ pub enum ModelError {
     ApiKeyNotConfigured(String),
 }
 
+impl ErrorInfo for ModelError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            ModelError::InvalidParameter(_) | ModelError::ApiKeyNotConfigured(_) => ErrorCode::InvalidInput,
+            ModelError::ModelNotFound(_) => ErrorCode::NotFound,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -147,6 +195,14 @@ mod tests {
     fn test_model_type_as_str() {
         assert_eq!(ModelType::GPT4.as_str(), "gpt-4");
         assert_eq!(ModelType::Claude3Opus.provider(), "anthropic");
+        assert_eq!(ModelType::Gemini15Flash.provider(), "gemini");
+
+        let azure: ModelType = serde_json::from_value(serde_json::json!({ "azure-openai": { "deployment": "gpt4o-prod" } })).unwrap();
+        assert_eq!(azure.as_str(), "gpt4o-prod");
+        assert_eq!(azure.provider(), "azure");
+
+        let local: ModelType = serde_json::from_value(serde_json::json!({ "local": { "model": "llama3" } })).unwrap();
+        assert_eq!((local.as_str(), local.provider()), ("llama3", "local"));
     }
 
     #[test]
@@ -1,9 +1,14 @@
+use crate::messages::{ChatMessage, MessageRole};
+use common::error::{ErrorCode, ErrorInfo};
+use common::types::Port;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use std::collections::HashMap;
-use tera::{Context, Tera};
+use std::collections::{BTreeSet, HashMap};
 
-/// Prompt template using Tera (similar to Jinja2)
+/// Partials may include partials, up to this depth
+pub const MAX_PARTIAL_DEPTH: usize = 16;
+
+/// Prompt template rendered by the [`TemplateEngine`]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PromptTemplate {
     pub template: String,
@@ -30,22 +35,97 @@ impl PromptTemplate {
     }
 
     pub fn render(&self) -> Result<String, TemplateError> {
-        let engine = TemplateEngine::new();
+        self.render_with(&TemplateEngine::new())
+    }
+
+    /// Render with an engine holding partials or a missing-variable mode
+    pub fn render_with(&self, engine: &TemplateEngine) -> Result<String, TemplateError> {
         engine.render(&self.template, &self.variables)
     }
+
+    /// Render into a message from `role`
+    pub fn render_message(&self, role: MessageRole) -> Result<ChatMessage, TemplateError> {
+        Ok(ChatMessage::new(role, self.render()?))
+    }
+
+    /// Render the next turn of a conversation: the `system` template rendered
+    /// with the same variables, the earlier `history`, then this template as
+    /// the user's message
+    pub fn render_messages(
+        &self,
+        system: Option<&str>,
+        history: &[ChatMessage],
+    ) -> Result<Vec<ChatMessage>, TemplateError> {
+        let engine = TemplateEngine::new();
+        let mut messages = Vec::with_capacity(history.len() + 2);
+        if let Some(system) = system {
+            messages.push(ChatMessage::system(engine.render(system, &self.variables)?));
+        }
+        messages.extend(history.iter().cloned());
+        messages.push(self.render_message(MessageRole::User)?);
+        Ok(messages)
+    }
 }
 
-/// Template engine for rendering prompts
+/// What rendering does with a variable that is not defined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingVariables {
+    /// Fail with [`TemplateError::MissingVariable`]
+    #[default]
+    Strict,
+    /// Render nothing, and iterate over nothing
+    Lenient,
+}
+
+/// Template engine for rendering prompts.
+///
+/// The syntax is a subset of Handlebars:
+///
+/// - `{{ name }}`, `{{ customer.address.city }}`, `{{ items.0 }}` output a
+///   variable; strings as they are, other values as JSON, null as nothing
+/// - `{{#if cond}}..{{else}}..{{/if}}` and `{{#unless cond}}..{{/unless}}`;
+///   null, false, 0, "" and [] are false and so are undefined variables,
+///   whatever the mode
+/// - `{{#each items}}..{{else}}..{{/each}}` over arrays and objects. Inside,
+///   names refer to the current item (`{{ this }}` is the item itself),
+///   `{{ ../name }}` to the enclosing scope, and `{{ @index }}`, `{{ @key }}`,
+///   `{{ @first }}`, `{{ @last }}` to the position in the loop
+/// - `{{> name}}` includes a registered partial, rendered in the current scope
+/// - `{{! comment }}` and `{{!-- comment --}}` render nothing
+/// - `~` at either end of a tag (`{{~#each items~}}`) trims the whitespace on
+///   that side
+///
+/// Values are not escaped: prompts are not HTML.
+#[derive(Debug, Default)]
 pub struct TemplateEngine {
-    #[allow(dead_code)]
-    tera: Tera,
+    partials: HashMap<String, Vec<Segment>>,
+    missing: MissingVariables,
 }
 
 impl TemplateEngine {
     pub fn new() -> Self {
-        Self {
-            tera: Tera::default(),
+        Self::default()
+    }
+
+    pub fn with_missing_variables(mut self, missing: MissingVariables) -> Self {
+        self.missing = missing;
+        self
+    }
+
+    /// Register a partial included with `{{> name}}`; replaces one of the same name
+    pub fn register_partial(&mut self, name: impl Into<String>, template: &str) -> Result<(), TemplateError> {
+        let name = name.into();
+        if !is_identifier(&name) {
+            return Err(TemplateError::SyntaxError(format!("invalid partial name {:?}", name)));
         }
+        self.partials.insert(name, parse(template)?);
+        Ok(())
+    }
+
+    /// Parse a template once to render it many times
+    pub fn compile(&self, template: &str) -> Result<CompiledTemplate, TemplateError> {
+        Ok(CompiledTemplate { segments: parse(template)? })
     }
 
     /// Render a template with variables
@@ -54,60 +134,493 @@ impl TemplateEngine {
         template: &str,
         variables: &HashMap<String, JsonValue>,
     ) -> Result<String, TemplateError> {
-        let mut context = Context::new();
-
-        // Convert JsonValue to tera values
-        for (key, value) in variables {
-            match value {
-                JsonValue::String(s) => context.insert(key, s),
-                JsonValue::Number(n) => {
-                    if let Some(i) = n.as_i64() {
-                        context.insert(key, &i);
-                    } else if let Some(f) = n.as_f64() {
-                        context.insert(key, &f);
-                    }
-                }
-                JsonValue::Bool(b) => context.insert(key, b),
-                JsonValue::Array(arr) => context.insert(key, arr),
-                JsonValue::Object(obj) => context.insert(key, obj),
-                JsonValue::Null => context.insert(key, &Option::<String>::None),
-            }
-        }
+        self.render_compiled(&self.compile(template)?, variables)
+    }
 
-        Tera::one_off(template, &context, false)
-            .map_err(|e| TemplateError::RenderError(e.to_string()))
+    pub fn render_compiled(
+        &self,
+        template: &CompiledTemplate,
+        variables: &HashMap<String, JsonValue>,
+    ) -> Result<String, TemplateError> {
+        let root = JsonValue::Object(variables.iter().map(|(k, v)| (k.clone(), v.clone())).collect());
+        let mut scopes = vec![Scope { value: &root, position: None }];
+        let mut out = String::new();
+        self.render_segments(&template.segments, &mut scopes, &mut out, 0)?;
+        Ok(out)
     }
 
     /// Validate template syntax
     pub fn validate(&self, template: &str) -> Result<(), TemplateError> {
-        // Try to parse the template - Tera::render_str doesn't need mut
-        Tera::one_off(template, &Context::new(), false)
-            .map(|_| ())
-            .map_err(|e| TemplateError::SyntaxError(e.to_string()))
+        self.compile(template).map(|_| ())
     }
 
-    /// Extract variable names from template
+    /// Compile a template and check that every variable it reads from the top
+    /// level, partials included, is one of `declared`
+    pub fn check(&self, template: &str, declared: &[&str]) -> Result<CompiledTemplate, TemplateError> {
+        let compiled = self.compile(template)?;
+        let mut undefined = BTreeSet::new();
+        let mut including = Vec::new();
+        self.check_segments(&compiled.segments, 0, declared, &mut undefined, &mut including)?;
+        if !undefined.is_empty() {
+            return Err(TemplateError::UndefinedVariables(undefined.into_iter().collect()));
+        }
+        Ok(compiled)
+    }
+
+    /// [`check`](Self::check) against the input ports a node declares
+    pub fn check_inputs(&self, template: &str, inputs: &[Port]) -> Result<CompiledTemplate, TemplateError> {
+        let declared: Vec<&str> = inputs.iter().map(|port| port.name.as_str()).collect();
+        self.check(template, &declared)
+    }
+
+    /// Extract the top-level variable names a template reads, in order of
+    /// first use; none if the template does not parse
     pub fn extract_variables(&self, template: &str) -> Vec<String> {
-        let mut variables = Vec::new();
-        let re = regex::Regex::new(r"\{\{\s*(\w+)\s*\}\}").unwrap();
+        self.compile(template).map(|compiled| compiled.variables()).unwrap_or_default()
+    }
 
-        for cap in re.captures_iter(template) {
-            if let Some(var) = cap.get(1) {
-                let var_name = var.as_str().to_string();
-                if !variables.contains(&var_name) {
-                    variables.push(var_name);
+    fn render_segments<'v>(
+        &self,
+        segments: &[Segment],
+        scopes: &mut Vec<Scope<'v>>,
+        out: &mut String,
+        depth: usize,
+    ) -> Result<(), TemplateError> {
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Variable(path) => match resolve(path, scopes) {
+                    Some(value) => out.push_str(&to_text(&value)),
+                    None => self.missing(path)?,
+                },
+                Segment::If { negate, condition, then, otherwise } => {
+                    let truthy = resolve(condition, scopes).is_some_and(|value| is_truthy(&value));
+                    let branch = if truthy != *negate { then } else { otherwise };
+                    self.render_segments(branch, scopes, out, depth)?;
+                }
+                Segment::Each { items, body, otherwise } => {
+                    let Some(value) = resolve_value(items, scopes) else {
+                        if resolve(items, scopes).is_some() {
+                            return Err(TemplateError::RenderError(format!("cannot iterate over {}", items)));
+                        }
+                        self.missing(items)?;
+                        self.render_segments(otherwise, scopes, out, depth)?;
+                        continue;
+                    };
+                    let entries: Vec<(Option<&String>, &'v JsonValue)> = match value {
+                        JsonValue::Array(items) => items.iter().map(|item| (None, item)).collect(),
+                        JsonValue::Object(map) => map.iter().map(|(key, item)| (Some(key), item)).collect(),
+                        JsonValue::Null => Vec::new(),
+                        _ => return Err(TemplateError::RenderError(format!("cannot iterate over {}", items))),
+                    };
+                    if entries.is_empty() {
+                        self.render_segments(otherwise, scopes, out, depth)?;
+                        continue;
+                    }
+                    let last = entries.len() - 1;
+                    for (index, (key, item)) in entries.into_iter().enumerate() {
+                        scopes.push(Scope {
+                            value: item,
+                            position: Some(Position { index, key: key.cloned(), last: index == last }),
+                        });
+                        let rendered = self.render_segments(body, scopes, out, depth);
+                        scopes.pop();
+                        rendered?;
+                    }
+                }
+                Segment::Partial(name) => {
+                    let partial = self.partials.get(name).ok_or_else(|| TemplateError::UnknownPartial(name.clone()))?;
+                    if depth >= MAX_PARTIAL_DEPTH {
+                        return Err(TemplateError::RenderError(format!(
+                            "partials nested more than {} deep at {{{{> {}}}}}",
+                            MAX_PARTIAL_DEPTH, name
+                        )));
+                    }
+                    self.render_segments(partial, scopes, out, depth + 1)?;
                 }
             }
         }
+        Ok(())
+    }
+
+    fn missing(&self, path: &Path) -> Result<(), TemplateError> {
+        match self.missing {
+            MissingVariables::Strict => Err(TemplateError::MissingVariable(path.to_string())),
+            MissingVariables::Lenient => Ok(()),
+        }
+    }
 
+    /// `loops` is how many `each` blocks enclose the segments; `including`
+    /// the partials being checked, so recursive partials are checked once
+    fn check_segments<'a>(
+        &'a self,
+        segments: &'a [Segment],
+        loops: usize,
+        declared: &[&str],
+        undefined: &mut BTreeSet<String>,
+        including: &mut Vec<&'a str>,
+    ) -> Result<(), TemplateError> {
+        for segment in segments {
+            match segment {
+                Segment::Text(_) => {}
+                Segment::Variable(path) => check_path(path, loops, declared, undefined),
+                Segment::If { condition, then, otherwise, .. } => {
+                    check_path(condition, loops, declared, undefined);
+                    self.check_segments(then, loops, declared, undefined, including)?;
+                    self.check_segments(otherwise, loops, declared, undefined, including)?;
+                }
+                Segment::Each { items, body, otherwise } => {
+                    check_path(items, loops, declared, undefined);
+                    self.check_segments(body, loops + 1, declared, undefined, including)?;
+                    self.check_segments(otherwise, loops, declared, undefined, including)?;
+                }
+                Segment::Partial(name) => {
+                    let partial = self.partials.get(name).ok_or_else(|| TemplateError::UnknownPartial(name.clone()))?;
+                    if !including.contains(&name.as_str()) {
+                        including.push(name);
+                        self.check_segments(partial, loops, declared, undefined, including)?;
+                        including.pop();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A parsed template
+#[derive(Debug, Clone)]
+pub struct CompiledTemplate {
+    segments: Vec<Segment>,
+}
+
+impl CompiledTemplate {
+    /// Top-level variable names the template reads, in order of first use.
+    /// Names inside `each` blocks refer to the items and are not included;
+    /// neither are those of partials.
+    pub fn variables(&self) -> Vec<String> {
+        let mut variables = Vec::new();
+        collect_variables(&self.segments, 0, &mut variables);
         variables
     }
 }
 
-impl Default for TemplateEngine {
-    fn default() -> Self {
-        Self::new()
+#[derive(Debug, Clone)]
+enum Segment {
+    Text(String),
+    Variable(Path),
+    If { negate: bool, condition: Path, then: Vec<Segment>, otherwise: Vec<Segment> },
+    Each { items: Path, body: Vec<Segment>, otherwise: Vec<Segment> },
+    Partial(String),
+}
+
+/// A variable reference: `up` scopes out, then either a loop variable
+/// (`@index`) or the keys below the scope's value (none for `this`)
+#[derive(Debug, Clone)]
+struct Path {
+    up: usize,
+    loop_variable: Option<String>,
+    keys: Vec<String>,
+    source: String,
+}
+
+impl std::fmt::Display for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+struct Scope<'v> {
+    value: &'v JsonValue,
+    /// Set for the item scopes of `each` blocks
+    position: Option<Position>,
+}
+
+struct Position {
+    index: usize,
+    key: Option<String>,
+    last: bool,
+}
+
+fn scope_of<'s, 'v>(path: &Path, scopes: &'s [Scope<'v>]) -> Option<&'s Scope<'v>> {
+    scopes.len().checked_sub(path.up + 1).map(|index| &scopes[index])
+}
+
+/// The value of a path below a scope, borrowed so it can be iterated
+fn resolve_value<'v>(path: &Path, scopes: &[Scope<'v>]) -> Option<&'v JsonValue> {
+    if path.loop_variable.is_some() {
+        return None;
+    }
+    let mut value = scope_of(path, scopes)?.value;
+    for key in &path.keys {
+        value = match value {
+            JsonValue::Object(map) => map.get(key)?,
+            JsonValue::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+fn resolve(path: &Path, scopes: &[Scope<'_>]) -> Option<JsonValue> {
+    let Some(variable) = &path.loop_variable else {
+        return resolve_value(path, scopes).cloned();
+    };
+    let position = scope_of(path, scopes)?.position.as_ref()?;
+    match variable.as_str() {
+        "index" => Some(JsonValue::from(position.index)),
+        "key" => position.key.clone().map(JsonValue::String),
+        "first" => Some(JsonValue::Bool(position.index == 0)),
+        "last" => Some(JsonValue::Bool(position.last)),
+        _ => None,
+    }
+}
+
+fn check_path(path: &Path, loops: usize, declared: &[&str], undefined: &mut BTreeSet<String>) {
+    let Some(target) = loops.checked_sub(path.up) else {
+        undefined.insert(path.to_string());
+        return;
+    };
+    if target > 0 {
+        // Reads an item, whose shape is not known here
+        return;
+    }
+    match (&path.loop_variable, path.keys.first()) {
+        (Some(_), _) => {
+            undefined.insert(path.to_string());
+        }
+        (None, Some(name)) if !declared.contains(&name.as_str()) => {
+            undefined.insert(name.clone());
+        }
+        _ => {}
+    }
+}
+
+fn collect_variables(segments: &[Segment], loops: usize, variables: &mut Vec<String>) {
+    for segment in segments {
+        match segment {
+            Segment::Text(_) | Segment::Partial(_) => {}
+            Segment::Variable(path) => add_variable(path, loops, variables),
+            Segment::If { condition, then, otherwise, .. } => {
+                add_variable(condition, loops, variables);
+                collect_variables(then, loops, variables);
+                collect_variables(otherwise, loops, variables);
+            }
+            Segment::Each { items, body, otherwise } => {
+                add_variable(items, loops, variables);
+                collect_variables(body, loops + 1, variables);
+                collect_variables(otherwise, loops, variables);
+            }
+        }
+    }
+}
+
+fn add_variable(path: &Path, loops: usize, variables: &mut Vec<String>) {
+    if loops != path.up || path.loop_variable.is_some() {
+        return;
     }
+    if let Some(name) = path.keys.first() {
+        if !variables.contains(name) {
+            variables.push(name.clone());
+        }
+    }
+}
+
+/// Null, false, 0, "" and [] are false
+fn is_truthy(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Null => false,
+        JsonValue::Bool(b) => *b,
+        JsonValue::Number(n) => n.as_f64().is_some_and(|f| f != 0.0),
+        JsonValue::String(s) => !s.is_empty(),
+        JsonValue::Array(items) => !items.is_empty(),
+        JsonValue::Object(_) => true,
+    }
+}
+
+fn to_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => String::new(),
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+enum Tag {
+    Variable(Path),
+    Open { block: String, path: Path },
+    Else,
+    Close(String),
+    Partial(String),
+}
+
+enum Token {
+    Text(String),
+    Tag { tag: Tag, line: usize },
+}
+
+fn syntax_error(line: usize, message: impl std::fmt::Display) -> TemplateError {
+    TemplateError::SyntaxError(format!("line {}: {}", line, message))
+}
+
+fn tokenize(template: &str) -> Result<Vec<Token>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    let mut trim_next = false;
+    let mut line = 1;
+    while let Some(start) = rest.find("{{") {
+        let mut text = &rest[..start];
+        if trim_next {
+            text = text.trim_start();
+        }
+        let after = &rest[start + 2..];
+        let (inner, consumed) = if after.starts_with("!--") || after.starts_with("~!--") {
+            let end = after.find("--}}").ok_or_else(|| syntax_error(line, "unterminated comment"))?;
+            (&after[..end + 2], end + 4)
+        } else {
+            let end = after.find("}}").ok_or_else(|| syntax_error(line, "unterminated '{{'"))?;
+            (&after[..end], end + 2)
+        };
+        line += text.matches('\n').count();
+
+        let trim_before = inner.starts_with('~');
+        trim_next = inner.ends_with('~') && inner.len() > 1;
+        if trim_before {
+            text = text.trim_end();
+        }
+        if !text.is_empty() {
+            tokens.push(Token::Text(text.to_string()));
+        }
+
+        let content = inner.strip_prefix('~').unwrap_or(inner);
+        let content = if trim_next { &content[..content.len() - 1] } else { content };
+        let content = content.trim();
+        if !content.starts_with('!') {
+            tokens.push(Token::Tag { tag: parse_tag(content, line)?, line });
+        }
+        line += inner.matches('\n').count();
+        rest = &after[consumed..];
+    }
+    let text = if trim_next { rest.trim_start() } else { rest };
+    if !text.is_empty() {
+        tokens.push(Token::Text(text.to_string()));
+    }
+    Ok(tokens)
+}
+
+fn parse_tag(content: &str, line: usize) -> Result<Tag, TemplateError> {
+    if let Some(block) = content.strip_prefix('#') {
+        let (block, argument) = block.split_once(char::is_whitespace).unwrap_or((block, ""));
+        if !matches!(block, "if" | "unless" | "each") {
+            return Err(syntax_error(line, format!("unknown block {{{{#{}}}}}", block)));
+        }
+        return Ok(Tag::Open { block: block.to_string(), path: parse_path(argument.trim(), line)? });
+    }
+    if let Some(block) = content.strip_prefix('/') {
+        return Ok(Tag::Close(block.trim().to_string()));
+    }
+    if let Some(name) = content.strip_prefix('>') {
+        let name = name.trim();
+        if !is_identifier(name) {
+            return Err(syntax_error(line, format!("invalid partial name {:?}", name)));
+        }
+        return Ok(Tag::Partial(name.to_string()));
+    }
+    if content == "else" {
+        return Ok(Tag::Else);
+    }
+    Ok(Tag::Variable(parse_path(content, line)?))
+}
+
+fn parse_path(source: &str, line: usize) -> Result<Path, TemplateError> {
+    let invalid = || syntax_error(line, format!("invalid variable {:?}", source));
+    let mut rest = source;
+    let mut up = 0;
+    while let Some(parent) = rest.strip_prefix("../") {
+        up += 1;
+        rest = parent;
+    }
+    let mut path = Path { up, loop_variable: None, keys: Vec::new(), source: source.to_string() };
+    if let Some(variable) = rest.strip_prefix('@') {
+        if !matches!(variable, "index" | "key" | "first" | "last") {
+            return Err(invalid());
+        }
+        path.loop_variable = Some(variable.to_string());
+        return Ok(path);
+    }
+    let rest = match rest {
+        "this" | "." => "",
+        rest => rest.strip_prefix("this.").unwrap_or(rest),
+    };
+    if rest.is_empty() {
+        return if source.is_empty() { Err(invalid()) } else { Ok(path) };
+    }
+    for key in rest.split('.') {
+        if !is_identifier(key) {
+            return Err(invalid());
+        }
+        path.keys.push(key.to_string());
+    }
+    Ok(path)
+}
+
+/// How a sequence of segments ended
+enum End {
+    Eof,
+    Else(usize),
+    Close(String, usize),
+}
+
+fn parse(template: &str) -> Result<Vec<Segment>, TemplateError> {
+    let mut tokens = tokenize(template)?.into_iter();
+    match parse_segments(&mut tokens)? {
+        (segments, End::Eof) => Ok(segments),
+        (_, End::Else(line)) => Err(syntax_error(line, "{{else}} outside of a block")),
+        (_, End::Close(block, line)) => Err(syntax_error(line, format!("{{{{/{}}}}} without {{{{#{}}}}}", block, block))),
+    }
+}
+
+fn parse_segments(tokens: &mut impl Iterator<Item = Token>) -> Result<(Vec<Segment>, End), TemplateError> {
+    let mut segments = Vec::new();
+    while let Some(token) = tokens.next() {
+        let (tag, line) = match token {
+            Token::Text(text) => {
+                segments.push(Segment::Text(text));
+                continue;
+            }
+            Token::Tag { tag, line } => (tag, line),
+        };
+        match tag {
+            Tag::Variable(path) => segments.push(Segment::Variable(path)),
+            Tag::Partial(name) => segments.push(Segment::Partial(name)),
+            Tag::Else => return Ok((segments, End::Else(line))),
+            Tag::Close(block) => return Ok((segments, End::Close(block, line))),
+            Tag::Open { block, path } => {
+                let (then, mut end) = parse_segments(tokens)?;
+                let mut otherwise = Vec::new();
+                if let End::Else(_) = end {
+                    (otherwise, end) = parse_segments(tokens)?;
+                }
+                match end {
+                    End::Close(closed, _) if closed == block => {}
+                    End::Close(closed, line) => {
+                        return Err(syntax_error(line, format!("{{{{/{}}}}} closes {{{{#{}}}}}", closed, block)));
+                    }
+                    End::Else(line) => return Err(syntax_error(line, format!("second {{{{else}}}} in {{{{#{}}}}}", block))),
+                    End::Eof => return Err(syntax_error(line, format!("{{{{#{}}}}} is never closed", block))),
+                }
+                segments.push(match block.as_str() {
+                    "each" => Segment::Each { items: path, body: then, otherwise },
+                    _ => Segment::If { negate: block == "unless", condition: path, then, otherwise },
+                });
+            }
+        }
+    }
+    Ok((segments, End::Eof))
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -120,11 +633,28 @@ pub enum TemplateError {
 
     #[error("Missing variable: {0}")]
     MissingVariable(String),
+
+    #[error("Unknown partial: {0}")]
+    UnknownPartial(String),
+
+    #[error("Undefined variables: {}", .0.join(", "))]
+    UndefinedVariables(Vec<String>),
+}
+
+impl ErrorInfo for TemplateError {
+    fn error_code(&self) -> ErrorCode {
+        ErrorCode::InvalidInput
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::types::DataType;
+
+    fn vars(value: JsonValue) -> HashMap<String, JsonValue> {
+        serde_json::from_value(value).unwrap()
+    }
 
     #[test]
     fn test_template_render() {
@@ -135,6 +665,23 @@ mod tests {
         assert_eq!(result, "Hello World!");
     }
 
+    #[test]
+    fn test_render_messages() {
+        let mut template = PromptTemplate::new("And order {{ order }}?".to_string());
+        template.set_variable("order".to_string(), JsonValue::from(8));
+        template.set_variable("company".to_string(), JsonValue::String("Acme".to_string()));
+
+        let history = vec![ChatMessage::user("Where is order 7?"), ChatMessage::assistant("It shipped.")];
+        let messages = template.render_messages(Some("You support {{ company }} customers."), &history).unwrap();
+        let rendered: Vec<(MessageRole, &str)> = messages.iter().map(|m| (m.role, m.content.as_str())).collect();
+        assert_eq!(rendered, vec![
+            (MessageRole::System, "You support Acme customers."),
+            (MessageRole::User, "Where is order 7?"),
+            (MessageRole::Assistant, "It shipped."),
+            (MessageRole::User, "And order 8?"),
+        ]);
+    }
+
     #[test]
     fn test_extract_variables() {
         let engine = TemplateEngine::new();
@@ -144,12 +691,15 @@ mod tests {
         assert_eq!(vars.len(), 2);
         assert!(vars.contains(&"name".to_string()));
         assert!(vars.contains(&"age".to_string()));
+
+        let template = "{{#each orders}}{{ id }} for {{ ../customer.name }}{{/each}}{{#if vip}}!{{/if}}";
+        assert_eq!(engine.extract_variables(template), vec!["orders", "customer", "vip"]);
     }
 
     #[test]
     fn test_template_with_loop() {
         let engine = TemplateEngine::new();
-        let template = "{% for item in items %}{{ item }}{% endfor %}";
+        let template = "{{#each items}}{{ this }}{{/each}}";
         let mut vars = HashMap::new();
         vars.insert(
             "items".to_string(),
@@ -162,4 +712,87 @@ mod tests {
         let result = engine.render(template, &vars).unwrap();
         assert_eq!(result, "ab");
     }
+
+    #[test]
+    fn test_blocks_and_partials() {
+        let mut engine = TemplateEngine::new();
+        engine.register_partial("line", "{{ @index }}. {{ name }} x{{ qty }}{{#unless @last}}, {{/unless}}").unwrap();
+        let template = "\
+Customer: {{ customer.name }}{{#if customer.vip}} (VIP){{/if}}
+{{~#each items~}}
+  {{> line}}
+{{~else~}}
+  No items.
+{{~/each}}
+{{#each totals}}{{ @key }}={{ this }};{{/each}}{{! not rendered }}";
+        let variables = vars(serde_json::json!({
+            "customer": { "name": "Ada", "vip": true },
+            "items": [{ "name": "Tea", "qty": 2 }, { "name": "Cake", "qty": 1 }],
+            "totals": { "eur": 12.5, "items": 3 },
+        }));
+        assert_eq!(engine.render(template, &variables).unwrap(), "Customer: Ada (VIP)0. Tea x2, 1. Cake x1\neur=12.5;items=3;");
+
+        let variables = vars(serde_json::json!({ "customer": { "name": "Bob" }, "items": [], "totals": {} }));
+        assert_eq!(engine.render(template, &variables).unwrap(), "Customer: BobNo items.\n");
+    }
+
+    #[test]
+    fn test_missing_variable_modes() {
+        let template = "Hi {{ name }}{{#if title}}, {{ title }}{{/if}}{{#each tags}} #{{ this }}{{/each}}";
+        let variables = vars(serde_json::json!({ "tags": ["new"] }));
+
+        let strict = TemplateEngine::new();
+        assert!(matches!(strict.render(template, &variables), Err(TemplateError::MissingVariable(name)) if name == "name"));
+        let lenient = TemplateEngine::new().with_missing_variables(MissingVariables::Lenient);
+        assert_eq!(lenient.render(template, &variables).unwrap(), "Hi  #new");
+        assert_eq!(lenient.render("{{#each nothing}}x{{/each}}", &HashMap::new()).unwrap(), "");
+        assert!(matches!(strict.render("{{> missing}}", &HashMap::new()), Err(TemplateError::UnknownPartial(_))));
+
+        let mut recursive = TemplateEngine::new();
+        recursive.register_partial("loop", "{{> loop}}").unwrap();
+        assert!(matches!(recursive.render("{{> loop}}", &HashMap::new()), Err(TemplateError::RenderError(_))));
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let engine = TemplateEngine::new();
+        for template in [
+            "{{#if a}}open",
+            "{{#each a}}{{/if}}",
+            "{{/each}}",
+            "{{else}}",
+            "{{#with a}}{{/with}}",
+            "{{ a b }}",
+            "{{ @position }}",
+            "{{ unterminated",
+            "{{#if a}}{{else}}{{else}}{{/if}}",
+        ] {
+            assert!(matches!(engine.validate(template), Err(TemplateError::SyntaxError(_))), "{}", template);
+        }
+        let Err(TemplateError::SyntaxError(message)) = engine.validate("Hi\n\n{{#if a}}") else { panic!() };
+        assert!(message.starts_with("line 3:"), "{}", message);
+    }
+
+    #[test]
+    fn test_check_against_declared_inputs() {
+        let port = |name: &str| Port { id: name.to_string(), name: name.to_string(), data_type: DataType::Any };
+        let mut engine = TemplateEngine::new();
+        engine.register_partial("signature", "{{ agent }} at {{ company }}").unwrap();
+        let template = "{{#each tickets}}{{ subject }} ({{ ../customer }}, {{ @index }}){{/each}}{{> signature}}";
+
+        let inputs = [port("tickets"), port("customer"), port("agent"), port("company")];
+        assert!(engine.check_inputs(template, &inputs).is_ok());
+        match engine.check_inputs(template, &inputs[..2]) {
+            Err(TemplateError::UndefinedVariables(names)) => assert_eq!(names, vec!["agent", "company"]),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+        match engine.check("{{ @index }}{{ ../up }}{{> missing}}", &[]) {
+            Err(TemplateError::UnknownPartial(name)) => assert_eq!(name, "missing"),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+        match engine.check("{{ @index }}{{ ../up }}", &[]) {
+            Err(TemplateError::UndefinedVariables(names)) => assert_eq!(names, vec!["../up", "@index"]),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+    }
 }
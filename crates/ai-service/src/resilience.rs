@@ -0,0 +1,236 @@
+//! Timeouts, retries and circuit breaking for provider calls.
+
+use crate::client::AIError;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Retry policy for provider calls failing with a retryable error (rate
+/// limits, 5xx statuses, timeouts and connection failures)
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub backoff_multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            backoff_multiplier: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self { max_retries: 0, ..Self::default() }
+    }
+
+    /// Delay before the given retry, starting at 1
+    pub fn calculate_delay(&self, retry: u32) -> Duration {
+        if retry == 0 {
+            return Duration::ZERO;
+        }
+        let delay_ms = self.initial_delay.as_millis() as f64 * self.backoff_multiplier.powi((retry - 1) as i32);
+        Duration::from_millis(delay_ms as u64).min(self.max_delay)
+    }
+}
+
+/// Failures of one provider since its last success
+#[derive(Debug, Default)]
+struct Circuit {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// Stops calling a provider after consecutive failures, so callers fail fast
+/// with [`AIError::CircuitOpen`] instead of waiting on a provider that is
+/// down. Once `open_for` has passed, calls go through again; the next
+/// failure opens the circuit anew, a success closes it.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_for: Duration,
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_for: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            open_for,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Fail with [`AIError::CircuitOpen`] while the provider's circuit is open
+    pub fn check(&self, provider: &str) -> Result<(), AIError> {
+        let circuits = self.circuits.lock().unwrap_or_else(|e| e.into_inner());
+        let opened_at = circuits.get(provider).and_then(|circuit| circuit.opened_at);
+        match opened_at {
+            Some(opened_at) if opened_at.elapsed() < self.open_for => Err(AIError::CircuitOpen {
+                provider: provider.to_string(),
+                retry_after: self.open_for - opened_at.elapsed(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn record_success(&self, provider: &str) {
+        self.circuits.lock().unwrap_or_else(|e| e.into_inner()).remove(provider);
+    }
+
+    pub fn record_failure(&self, provider: &str) {
+        let mut circuits = self.circuits.lock().unwrap_or_else(|e| e.into_inner());
+        let circuit = circuits.entry(provider.to_string()).or_default();
+        circuit.consecutive_failures += 1;
+        if circuit.consecutive_failures >= self.failure_threshold {
+            if circuit.opened_at.is_none_or(|opened_at| opened_at.elapsed() >= self.open_for) {
+                tracing::warn!("Opening circuit of AI provider {} after {} failures", provider, circuit.consecutive_failures);
+            }
+            circuit.opened_at = Some(Instant::now());
+        }
+    }
+
+    pub fn is_open(&self, provider: &str) -> bool {
+        self.check(provider).is_err()
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(30))
+    }
+}
+
+/// Call a provider through the circuit breaker, giving each attempt at most
+/// `timeout` and retrying retryable failures with backoff
+pub(crate) async fn call_provider<T, F, Fut>(
+    provider: &str,
+    timeout: Duration,
+    retry: &RetryPolicy,
+    breaker: &CircuitBreaker,
+    call: F,
+) -> Result<T, AIError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, AIError>>,
+{
+    let mut retries = 0;
+    loop {
+        breaker.check(provider)?;
+        let result = tokio::time::timeout(timeout, call())
+            .await
+            .unwrap_or(Err(AIError::Timeout(timeout)));
+        match result {
+            Ok(value) => {
+                breaker.record_success(provider);
+                return Ok(value);
+            }
+            Err(e) if e.should_retry() => {
+                breaker.record_failure(provider);
+                retries += 1;
+                if retries > retry.max_retries {
+                    return Err(e);
+                }
+                let delay = retry.calculate_delay(retries);
+                tracing::warn!("AI provider {} failed: {}. Retrying in {:?}", provider, e, delay);
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn quick_retries(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            backoff_multiplier: 2.0,
+        }
+    }
+
+    #[test]
+    fn test_backoff_delays() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.calculate_delay(1), Duration::from_millis(500));
+        assert_eq!(policy.calculate_delay(3), Duration::from_secs(2));
+        assert_eq!(policy.calculate_delay(10), Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_retries_retryable_failures() {
+        let breaker = CircuitBreaker::default();
+        let calls = AtomicU32::new(0);
+        let flaky = || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(AIError::ProviderUnavailable { status: 503, message: "busy".to_string() }),
+                1 => Err(AIError::RateLimited("slow down".to_string())),
+                _ => Ok("hello"),
+            }
+        };
+        let answer = call_provider("openai", Duration::from_secs(1), &quick_retries(2), &breaker, flaky).await;
+        assert_eq!(answer.unwrap(), "hello");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // Errors of the request itself are not retried
+        calls.store(0, Ordering::SeqCst);
+        let invalid = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(AIError::ApiError("bad request".to_string()))
+        };
+        assert!(call_provider("openai", Duration::from_secs(1), &quick_retries(2), &breaker, invalid).await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_times_out_slow_calls() {
+        let breaker = CircuitBreaker::default();
+        let slow = || async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        };
+        let error = call_provider("gemini", Duration::from_millis(20), &RetryPolicy::none(), &breaker, slow).await.unwrap_err();
+        assert!(matches!(error, AIError::Timeout(_)));
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_millis(100));
+        let calls = AtomicU32::new(0);
+        let down = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(AIError::RequestFailed("connection refused".to_string()))
+        };
+
+        let error = call_provider("anthropic", Duration::from_secs(1), &quick_retries(5), &breaker, down).await.unwrap_err();
+        assert!(matches!(error, AIError::CircuitOpen { .. }));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(breaker.is_open("anthropic"));
+        assert!(!breaker.is_open("openai"));
+
+        // Callers fail fast while the circuit is open
+        let error = call_provider("anthropic", Duration::from_secs(1), &RetryPolicy::none(), &breaker, down).await.unwrap_err();
+        assert!(matches!(error, AIError::CircuitOpen { ref provider, .. } if provider == "anthropic"));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // After a while one call goes through again and closes it on success
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        let up = || async { Ok("back") };
+        assert_eq!(call_provider("anthropic", Duration::from_secs(1), &RetryPolicy::none(), &breaker, up).await.unwrap(), "back");
+        assert!(!breaker.is_open("anthropic"));
+    }
+}
@@ -0,0 +1,225 @@
+//! Structured output: completions held to a JSON Schema.
+//!
+//! Models whose provider supports it natively (see
+//! [`ModelType::supports_json_schema`](crate::models::ModelType::supports_json_schema))
+//! are sent the schema with the request; for the others the schema goes into
+//! the system prompt. Either way the answer is parsed and checked against the schema,
+//! and answers that do not match are sent back to the model to be repaired.
+
+use crate::client::{AIError, AIRequest, AIResponse, Usage};
+use crate::messages::{ChatMessage, MessageRole};
+use common::data_type::DataType;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// The JSON Schema a completion must follow
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseFormat {
+    /// Name of the schema, sent to providers that ask for one
+    #[serde(default = "default_name")]
+    pub name: String,
+    pub schema: JsonValue,
+    /// How many times an answer not matching the schema is sent back to be
+    /// repaired before the request fails
+    #[serde(default = "default_max_repairs")]
+    pub max_repairs: u32,
+}
+
+fn default_name() -> String {
+    "response".to_string()
+}
+
+fn default_max_repairs() -> u32 {
+    2
+}
+
+impl ResponseFormat {
+    pub fn new(schema: JsonValue) -> Self {
+        Self {
+            name: default_name(),
+            schema,
+            max_repairs: default_max_repairs(),
+        }
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    pub fn with_max_repairs(mut self, max_repairs: u32) -> Self {
+        self.max_repairs = max_repairs;
+        self
+    }
+
+    /// The value of an answer, converted to the types of the schema where
+    /// the coercion rules allow (e.g. `"3"` for an integer property).
+    ///
+    /// The JSON may be wrapped in a Markdown code block or surrounded by text.
+    pub fn parse(&self, content: &str) -> Result<JsonValue, String> {
+        let value: JsonValue = serde_json::from_str(extract_json(content))
+            .map_err(|e| format!("the answer is not valid JSON: {}", e))?;
+        DataType::ObjectSchema(self.schema.clone()).coerce(&value)
+    }
+
+    /// System prompt asking for answers following the schema, for providers
+    /// without native structured output
+    pub fn instructions(&self) -> String {
+        format!(
+            "Answer only with a JSON value matching this JSON Schema, without any other text:\n{}",
+            self.schema
+        )
+    }
+
+    fn repair_prompt(&self, error: &str) -> String {
+        format!(
+            "Your answer does not match the JSON Schema: {}. Answer again with only the corrected JSON.",
+            error
+        )
+    }
+}
+
+/// The outermost JSON object or array in a text
+fn extract_json(content: &str) -> &str {
+    let start = content.find(['{', '[']);
+    let end = content.rfind(['}', ']']);
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => &content[start..=end],
+        _ => content.trim(),
+    }
+}
+
+/// Generate a completion following `format`, sending answers that do not
+/// match the schema back to the model with the reason.
+///
+/// `native` tells whether the provider enforces the schema itself; otherwise
+/// the schema is added to the system prompt. Responses with tool calls are
+/// returned as they are. The usage of the response covers every attempt.
+pub(crate) async fn generate_with_repairs<'a, F>(
+    mut request: AIRequest,
+    format: &ResponseFormat,
+    native: bool,
+    send: F,
+) -> Result<AIResponse, AIError>
+where
+    F: Fn(AIRequest) -> BoxFuture<'a, Result<AIResponse, AIError>>,
+{
+    let mut conversation = request.conversation();
+    if !native {
+        match conversation.first_mut() {
+            Some(system) if system.role == MessageRole::System => {
+                system.content = format!("{}\n\n{}", system.content, format.instructions());
+            }
+            _ => conversation.insert(0, ChatMessage::system(format.instructions())),
+        }
+    }
+
+    let mut attempts = 0;
+    let mut usage = Usage { prompt_tokens: 0, completion_tokens: 0, total_tokens: 0 };
+    loop {
+        request.messages = conversation.clone();
+        let mut response = send(request.clone()).await?;
+        attempts += 1;
+        usage.prompt_tokens += response.usage.prompt_tokens;
+        usage.completion_tokens += response.usage.completion_tokens;
+        usage.total_tokens += response.usage.total_tokens;
+        response.usage = usage.clone();
+
+        if response.tool_calls.as_ref().is_some_and(|calls| !calls.is_empty()) {
+            return Ok(response);
+        }
+        match format.parse(&response.content) {
+            Ok(value) => {
+                return Ok(AIResponse { structured: Some(value), ..response });
+            }
+            Err(error) if attempts <= format.max_repairs => {
+                tracing::debug!("Repairing answer not matching schema {}: {}", format.name, error);
+                conversation.push(ChatMessage::assistant(response.content));
+                conversation.push(ChatMessage::user(format.repair_prompt(&error)));
+            }
+            Err(error) => return Err(AIError::SchemaViolation { attempts, error }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ModelType;
+    use std::sync::Mutex;
+
+    fn person() -> ResponseFormat {
+        ResponseFormat::new(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "integer" }
+            },
+            "required": ["name", "age"]
+        }))
+    }
+
+    fn response(content: &str) -> AIResponse {
+        AIResponse {
+            content: content.to_string(),
+            tool_calls: None,
+            usage: Usage { prompt_tokens: 10, completion_tokens: 5, total_tokens: 15 },
+            model: "claude-3-opus".to_string(),
+            finish_reason: "stop".to_string(),
+            cached: false,
+            structured: None,
+            fallback_from: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_answers() {
+        let format = person();
+        let parsed = format.parse("Here you go:\n```json\n{\"name\": \"Ada\", \"age\": \"36\"}\n```").unwrap();
+        assert_eq!(parsed, serde_json::json!({ "name": "Ada", "age": 36 }));
+
+        assert!(format.parse("{\"name\": \"Ada\"}").unwrap_err().contains("missing required property 'age'"));
+        assert!(format.parse("{\"name\": \"Ada\", \"age\": 36.5}").unwrap_err().contains("not an integer"));
+        assert!(format.parse("I don't know").unwrap_err().contains("not valid JSON"));
+    }
+
+    #[tokio::test]
+    async fn test_repairs_answers_until_they_match() {
+        let sent = Mutex::new(Vec::new());
+        let answers = Mutex::new(vec!["{\"name\": \"Ada\", \"age\": 36}", "{\"name\": \"Ada\"}"]);
+        let send = |request: AIRequest| -> BoxFuture<'_, Result<AIResponse, AIError>> {
+            sent.lock().unwrap().push(request.messages);
+            let answer = answers.lock().unwrap().pop().unwrap();
+            Box::pin(async move { Ok(response(answer)) })
+        };
+
+        let request = AIRequest::new(ModelType::Claude3Opus, "Who wrote the first program?".to_string());
+        let response = generate_with_repairs(request, &person(), false, send).await.unwrap();
+        assert_eq!(response.structured, Some(serde_json::json!({ "name": "Ada", "age": 36 })));
+        assert_eq!(response.usage.total_tokens, 30);
+
+        let sent = sent.into_inner().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0][0].role, MessageRole::System);
+        assert!(sent[0][0].content.contains("\"required\":[\"name\",\"age\"]"));
+        let repair = &sent[1];
+        assert_eq!(repair.len(), 4);
+        assert_eq!(repair[2].content, "{\"name\": \"Ada\"}");
+        assert!(repair[3].content.contains("missing required property 'age'"));
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_repairs() {
+        let calls = Mutex::new(0);
+        let send = |_request: AIRequest| -> BoxFuture<'_, Result<AIResponse, AIError>> {
+            *calls.lock().unwrap() += 1;
+            Box::pin(async { Ok(response("not json")) })
+        };
+
+        let request = AIRequest::new(ModelType::GPT4, "Who?".to_string());
+        let error = generate_with_repairs(request, &person().with_max_repairs(1), true, send).await.unwrap_err();
+        assert!(matches!(error, AIError::SchemaViolation { attempts: 2, .. }));
+        assert_eq!(*calls.lock().unwrap(), 2);
+    }
+}
@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use common::error::{ErrorCode, ErrorInfo};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
@@ -116,6 +117,16 @@ pub enum ToolError {
     NotFound(String),
 }
 
+impl ErrorInfo for ToolError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            ToolError::ExecutionFailed(_) => ErrorCode::UpstreamFailed,
+            ToolError::InvalidArguments(_) => ErrorCode::InvalidInput,
+            ToolError::NotFound(_) => ErrorCode::NotFound,
+        }
+    }
+}
+
 // Example tool: Calculator
 pub struct CalculatorTool;
 
@@ -0,0 +1,442 @@
+use audit_service::AuditLogger;
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use common::types::{AuditAction, AuditLog, AuditResult, ExecutionState, ResourceType};
+use rbac_service::jwt::JwtClaims;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::revisions::WorkflowRevision;
+use workflow_engine::{RevisionStore, WorkflowScheduler};
+
+use crate::execution_service::ExecutionStore;
+use crate::request_limiter::client_ip;
+use crate::user_service::{verify_password, User, UserStore};
+
+/// Self-service account data export and deletion
+#[derive(Clone)]
+pub struct AccountServiceState {
+    users: Arc<dyn UserStore>,
+    revisions: Arc<RevisionStore>,
+    executions: ExecutionStore,
+    scheduler: Option<Arc<WorkflowScheduler>>,
+    audit: Option<Arc<AuditLogger>>,
+    trust_forwarded_for: bool,
+}
+
+impl AccountServiceState {
+    pub fn new(users: Arc<dyn UserStore>, revisions: Arc<RevisionStore>, executions: ExecutionStore) -> Self {
+        Self {
+            users,
+            revisions,
+            executions,
+            scheduler: None,
+            audit: None,
+            trust_forwarded_for: false,
+        }
+    }
+
+    /// Remove the schedules of workflows deleted with an account
+    pub fn with_scheduler(mut self, scheduler: Arc<WorkflowScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// Export and anonymize audit entries, and log account deletions
+    pub fn with_audit_logger(mut self, audit: Arc<AuditLogger>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Log the client IP from `X-Forwarded-For` as the request limiter
+    /// does; only enable behind a proxy that appends it
+    pub fn with_trusted_forwarded_for(mut self, trust_forwarded_for: bool) -> Self {
+        self.trust_forwarded_for = trust_forwarded_for;
+        self
+    }
+}
+
+/// Everything stored about a user, as downloaded by them
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountArchive {
+    pub exported_at: DateTime<Utc>,
+    pub profile: AccountProfile,
+    /// Every revision of the workflows the user owns
+    pub workflows: Vec<OwnedWorkflow>,
+    /// Executions of the owned workflows, without their payloads
+    pub executions: Vec<ExecutionSummary>,
+    /// Audit entries written by the user or about them and their workflows;
+    /// empty when no audit log is configured
+    pub audit_entries: Vec<AuditLog>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountProfile {
+    pub id: Uuid,
+    pub email: String,
+    pub name: String,
+    pub role: String,
+    pub avatar: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OwnedWorkflow {
+    pub workflow_id: Uuid,
+    pub revisions: Vec<WorkflowRevision>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExecutionSummary {
+    pub execution_id: Uuid,
+    pub workflow_id: Uuid,
+    pub organization_id: Option<Uuid>,
+    pub state: ExecutionState,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+/// Account deletion request
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountRequest {
+    /// Current password, confirming the deletion
+    pub password: String,
+    /// User who takes over the owned workflows; without one they are deleted
+    #[serde(default)]
+    pub transfer_to: Option<Uuid>,
+}
+
+/// 导出当前用户的全部个人数据：个人资料、拥有的工作流及其修订、执行元数据（不含载荷）以及相关审计日志
+pub async fn export_account_data(
+    State(state): State<AccountServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+) -> Response {
+    let user = match current_user(&state, claims.sub).await {
+        Ok(user) => user,
+        Err(response) => return response.into_response(),
+    };
+
+    let mut owned = state.revisions.owned_by(user.id).await;
+    owned.sort();
+
+    let audit_entries = match &state.audit {
+        Some(audit) => match audit.storage().query().get_logs_referencing(user.id, &owned).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                return error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("读取审计日志失败: {}", e))
+                    .into_response();
+            }
+        },
+        None => Vec::new(),
+    };
+
+    let mut workflows = Vec::with_capacity(owned.len());
+    for workflow_id in &owned {
+        workflows.push(OwnedWorkflow {
+            workflow_id: *workflow_id,
+            revisions: state.revisions.revisions(*workflow_id).await,
+        });
+    }
+
+    let history = state.executions.history().list_for_workflows(&owned, usize::MAX).await;
+    let executions = owned.iter()
+        .flat_map(|workflow_id| history.get(workflow_id).into_iter().flatten())
+        .map(|record| ExecutionSummary {
+            execution_id: record.result.execution_id,
+            workflow_id: record.workflow_id,
+            organization_id: record.organization_id,
+            state: record.result.state.clone(),
+            started_at: record.started_at,
+            completed_at: record.result.completed_at,
+            error: record.result.error.clone(),
+        })
+        .collect();
+
+    let archive = AccountArchive {
+        exported_at: Utc::now(),
+        profile: AccountProfile {
+            id: user.id,
+            email: user.email,
+            name: user.name,
+            role: user.role,
+            avatar: user.avatar,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            last_login_at: user.last_login_at,
+        },
+        workflows,
+        executions,
+        audit_entries,
+    };
+
+    let disposition = format!("attachment; filename=\"flowvex-account-{}.json\"", user.id);
+    ([(header::CONTENT_DISPOSITION, disposition)], Json(archive)).into_response()
+}
+
+/// 删除当前账户：需确认密码。拥有的工作流转交给指定用户，未指定时连同执行记录和调度一并删除；
+/// 审计日志保留但去除用户标识、IP 和 User-Agent，删除操作本身写入审计日志
+pub async fn delete_account(
+    State(state): State<AccountServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(request): Json<DeleteAccountRequest>,
+) -> impl IntoResponse {
+    let user = match current_user(&state, claims.sub).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    if !verify_password(&request.password, &user.password_hash) {
+        return error_response(StatusCode::FORBIDDEN, "密码错误".to_string());
+    }
+    if let Some(heir) = request.transfer_to {
+        let heir = match state.users.get_user_by_id(heir).await {
+            Ok(heir) => heir,
+            Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("读取用户失败: {}", e)),
+        };
+        let valid = heir.is_some_and(|heir| heir.id != user.id && heir.is_active);
+        if !valid {
+            return error_response(StatusCode::BAD_REQUEST, "接收工作流的用户不存在或不可用".to_string());
+        }
+    }
+
+    // Anonymize first: if the audit log is unavailable nothing has been deleted yet
+    let anonymized = match &state.audit {
+        Some(audit) => match audit.storage().anonymize_user(user.id).await {
+            Ok(count) => count,
+            Err(e) => {
+                return error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("审计日志匿名化失败，账户未删除: {}", e));
+            }
+        },
+        None => 0,
+    };
+
+    let owned = state.revisions.owned_by(user.id).await;
+    let (mut transferred, mut deleted) = (Vec::new(), Vec::new());
+    for workflow_id in owned {
+        match request.transfer_to {
+            Some(heir) => {
+                state.revisions.transfer_ownership(workflow_id, heir).await;
+                transferred.push(workflow_id);
+            }
+            None => {
+                if let Some(scheduler) = &state.scheduler {
+                    if let Err(e) = scheduler.remove_schedule(workflow_id).await {
+                        tracing::error!("Failed to remove schedule of deleted workflow {}: {}", workflow_id, e);
+                    }
+                }
+                state.executions.remove_workflow(workflow_id).await;
+                state.revisions.delete_workflow(workflow_id).await;
+                deleted.push(workflow_id);
+            }
+        }
+    }
+    state.revisions.forget_user(user.id).await;
+    if let Err(e) = state.users.delete_user(user.id).await {
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("账户删除失败，可重试: {}", e));
+    }
+
+    if let Some(audit) = &state.audit {
+        let ip_address = client_ip(&headers, peer.map(|ConnectInfo(addr)| addr.ip()), state.trust_forwarded_for)
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let user_agent = headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let mut log = AuditLog::new(
+            user.id,
+            AuditAction::Delete,
+            ResourceType::User,
+            user.id,
+            ip_address,
+            user_agent,
+            AuditResult::Success,
+        );
+        log.details = serde_json::json!({
+            "transferred_to": request.transfer_to,
+            "transferred_workflows": transferred,
+            "deleted_workflows": deleted,
+            "anonymized_audit_entries": anonymized,
+        });
+        log.is_security_sensitive = true;
+        if let Err(e) = audit.log_immediate(log).await {
+            tracing::error!("Failed to record deletion of account {}: {}", user.id, e);
+        }
+    }
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "transferred_workflows": transferred,
+            "deleted_workflows": deleted,
+            "anonymized_audit_entries": anonymized,
+            "message": "账户已删除"
+        })),
+    )
+}
+
+/// The caller's account, or the response explaining why it is unavailable
+async fn current_user(state: &AccountServiceState, id: Uuid) -> Result<User, (StatusCode, Json<serde_json::Value>)> {
+    match state.users.get_user_by_id(id).await {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => Err(error_response(StatusCode::NOT_FOUND, "用户不存在".to_string())),
+        Err(e) => Err(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("读取用户失败: {}", e))),
+    }
+}
+
+fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, claims, request, send};
+    use crate::user_service::InMemoryUserStore;
+    use argon2::password_hash::{rand_core::OsRng, PasswordHasher, SaltString};
+    use argon2::Argon2;
+    use axum::routing::{delete, get};
+    use axum::Router;
+    use common::types::{ExecutionResult, Role, Workflow, WORKFLOW_SCHEMA_VERSION};
+    use std::collections::HashMap;
+    use workflow_engine::history::ExecutionRecord;
+
+    fn app(state: AccountServiceState, user: Uuid) -> Router {
+        Router::new()
+            .route("/account/export", get(export_account_data))
+            .route("/account", delete(delete_account))
+            .layer(Extension(claims(user, Role::User)))
+            .with_state(state)
+    }
+
+    async fn create_user(users: &dyn UserStore, email: &str) -> Uuid {
+        let hash = Argon2::default()
+            .hash_password(b"secret-password", &SaltString::generate(&mut OsRng))
+            .unwrap()
+            .to_string();
+        users.create_user(email.to_string(), hash, "Test".to_string()).await.unwrap().id
+    }
+
+    fn workflow(name: &str) -> Workflow {
+        Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: None,
+            nodes: vec![],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        }
+    }
+
+    async fn record_execution(executions: &ExecutionStore, workflow_id: Uuid) {
+        executions.history().record(ExecutionRecord {
+            workflow_id,
+            organization_id: None,
+            started_at: Utc::now(),
+            result: ExecutionResult {
+                execution_id: Uuid::new_v4(),
+                state: ExecutionState::Completed,
+                completed_at: Some(Utc::now()),
+                error: None,
+                output: Some(serde_json::json!({ "token": "payload" })),
+                usage: None,
+            },
+            nodes: vec![],
+        }).await;
+    }
+
+    #[tokio::test]
+    async fn test_export_account_data() {
+        let users: Arc<dyn UserStore> = Arc::new(InMemoryUserStore::new());
+        let revisions = Arc::new(RevisionStore::new());
+        let executions = ExecutionStore::new();
+        let (user, other) = (create_user(users.as_ref(), "me@example.com").await, create_user(users.as_ref(), "other@example.com").await);
+
+        let mine = workflow("Mine");
+        revisions.submit(mine.clone(), user).await;
+        revisions.submit(workflow("Theirs"), other).await;
+        record_execution(&executions, mine.id).await;
+
+        let state = AccountServiceState::new(users, revisions, executions);
+        let (status, headers, body) = send(app(state, user), request("GET", "/account/export", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(headers[header::CONTENT_DISPOSITION].to_str().unwrap().starts_with("attachment"));
+
+        let archive: AccountArchive = serde_json::from_value(body.clone()).unwrap();
+        assert_eq!(archive.profile.email, "me@example.com");
+        assert_eq!(archive.workflows.len(), 1);
+        assert_eq!(archive.workflows[0].revisions[0].workflow.name, "Mine");
+        assert_eq!(archive.executions.len(), 1);
+        assert_eq!(archive.executions[0].workflow_id, mine.id);
+        // Metadata only: execution payloads and the password hash stay out
+        assert!(!body.to_string().contains("payload"));
+        assert!(!body.to_string().contains("argon2"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_account() {
+        let users: Arc<dyn UserStore> = Arc::new(InMemoryUserStore::new());
+        let revisions = Arc::new(RevisionStore::new());
+        let executions = ExecutionStore::new();
+        let (user, heir) = (create_user(users.as_ref(), "me@example.com").await, create_user(users.as_ref(), "heir@example.com").await);
+        let kept = workflow("Kept");
+        revisions.submit(kept.clone(), user).await;
+        record_execution(&executions, kept.id).await;
+        let state = AccountServiceState::new(users.clone(), revisions.clone(), executions.clone());
+
+        let (status, _) = call(app(state.clone(), user), "DELETE", "/account", Some(serde_json::json!({ "password": "wrong" }))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = call(
+            app(state.clone(), user),
+            "DELETE",
+            "/account",
+            Some(serde_json::json!({ "password": "secret-password", "transfer_to": Uuid::new_v4() })),
+        ).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(users.get_user_by_id(user).await.unwrap().is_some());
+
+        let (status, body) = call(
+            app(state.clone(), user),
+            "DELETE",
+            "/account",
+            Some(serde_json::json!({ "password": "secret-password", "transfer_to": heir })),
+        ).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["transferred_workflows"][0], kept.id.to_string());
+        assert!(users.get_user_by_id(user).await.unwrap().is_none());
+        assert!(users.get_user_by_email("me@example.com").await.unwrap().is_none());
+        assert_eq!(revisions.owner(kept.id).await, Some(heir));
+
+        // Without an heir the owned workflows go with the account
+        let deleted = workflow("Deleted");
+        revisions.submit(deleted.clone(), heir).await;
+        record_execution(&executions, deleted.id).await;
+        let (status, body) = call(app(state, heir), "DELETE", "/account", Some(serde_json::json!({ "password": "secret-password" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["deleted_workflows"].as_array().unwrap().len(), 2);
+        assert!(revisions.revisions(deleted.id).await.is_empty());
+        assert!(executions.history().list_for_workflow(kept.id, 10).await.is_empty());
+    }
+}
@@ -0,0 +1,411 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use async_trait::async_trait;
+use audit_service::AuditLogger;
+use axum::{
+    extract::{ConnectInfo, FromRef, FromRequestParts, Path, Query, State},
+    http::{request::Parts, StatusCode},
+    Json,
+};
+use common::types::{ActionType2, AuditAction, AuditLog, AuditResult, Permission, ResourceType, Scope};
+use rbac_service::jwt::JwtClaims;
+use rbac_service::{AuthUser, JwtManager, PermissionChecker, RoleManager};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::errors::ApiError;
+use crate::request_limiter::client_ip;
+use crate::user_service::{hash_password, User, UserQuery, UserResponse, UserStore};
+use crate::validation::{FieldErrors, Validate, ValidJson};
+
+/// Roles an admin may give to users
+const ASSIGNABLE_ROLES: [&str; 5] = ["admin", "manager", "user", "viewer", "inspector"];
+
+/// Characters of temporary passwords, without look-alikes
+const TEMPORARY_PASSWORD_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnpqrstuvwxyz23456789";
+const TEMPORARY_PASSWORD_LEN: usize = 16;
+
+/// Admin actions kept for [`AdminServiceState::actions`]
+const RECENT_ACTIONS: usize = 100;
+
+type ApiResult = Result<(StatusCode, Json<JsonValue>), ApiError>;
+
+/// Admin user management service state
+#[derive(Clone)]
+pub struct AdminServiceState {
+    users: Arc<dyn UserStore>,
+    jwt_manager: Arc<JwtManager>,
+    /// Checks the role of the caller's token
+    permissions: Arc<PermissionChecker>,
+    audit: Option<Arc<AuditLogger>>,
+    /// Most recent admin actions of this process (for development, the audit logger persists them all)
+    actions: Arc<RwLock<VecDeque<AuditLog>>>,
+    trust_forwarded_for: bool,
+}
+
+impl AdminServiceState {
+    pub fn new(users: Arc<dyn UserStore>, jwt_manager: Arc<JwtManager>) -> Self {
+        Self {
+            users,
+            jwt_manager,
+            permissions: Arc::new(PermissionChecker::new(Arc::new(RoleManager::new()))),
+            audit: None,
+            actions: Arc::new(RwLock::new(VecDeque::new())),
+            trust_forwarded_for: false,
+        }
+    }
+
+    /// Forward every admin action to the audit log
+    pub fn with_audit_logger(mut self, audit: Arc<AuditLogger>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Record the client IP from `X-Forwarded-For` as the request limiter
+    /// does; only enable behind a proxy that appends it
+    pub fn with_trusted_forwarded_for(mut self, trust_forwarded_for: bool) -> Self {
+        self.trust_forwarded_for = trust_forwarded_for;
+        self
+    }
+
+    /// The most recent admin actions, oldest first
+    pub async fn actions(&self) -> Vec<AuditLog> {
+        self.actions.read().await.iter().cloned().collect()
+    }
+
+    /// Whether the caller may perform `action` on every user; denials are audited
+    async fn authorize(
+        &self,
+        claims: &JwtClaims,
+        origin: &RequestOrigin,
+        action: ActionType2,
+        audit_action: AuditAction,
+        user_id: Uuid,
+    ) -> Result<(), ApiError> {
+        let permission = Permission {
+            resource: ResourceType::User,
+            action,
+            scope: Scope::All,
+        };
+        // No owner: only a permission over all users grants access
+        if self.permissions.check_role_permission(claims.sub, &claims.role, &permission, None, None, None).await {
+            return Ok(());
+        }
+        self.record(claims, origin, audit_action, user_id, AuditResult::Denied, serde_json::json!({})).await;
+        Err(ApiError::forbidden("只有管理员可以管理用户"))
+    }
+
+    async fn record(
+        &self,
+        claims: &JwtClaims,
+        origin: &RequestOrigin,
+        action: AuditAction,
+        user_id: Uuid,
+        result: AuditResult,
+        details: JsonValue,
+    ) {
+        let mut log = AuditLog::new(
+            claims.sub,
+            action,
+            ResourceType::User,
+            user_id,
+            origin.ip_address.clone(),
+            origin.user_agent.clone(),
+            result,
+        );
+        log.details = details;
+        log.is_security_sensitive = true;
+
+        if let Some(audit) = &self.audit {
+            if let Err(e) = audit.log(log.clone()) {
+                tracing::error!("Failed to record admin action on user {}: {}", user_id, e);
+            }
+        }
+        let mut actions = self.actions.write().await;
+        actions.push_back(log);
+        if actions.len() > RECENT_ACTIONS {
+            actions.pop_front();
+        }
+    }
+
+    /// The user an admin acts on; admins may not act on their own account
+    async fn target(&self, claims: &JwtClaims, user_id: Uuid) -> Result<User, ApiError> {
+        if user_id == claims.sub {
+            return Err(ApiError::conflict("不能修改自己的账户状态或角色"));
+        }
+        self.users
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|e| ApiError::from_error("读取用户失败", &e))?
+            .ok_or_else(|| ApiError::not_found("用户不存在"))
+    }
+
+    async fn save(&self, user: &User) -> Result<User, ApiError> {
+        self.users
+            .update_user(user)
+            .await
+            .map_err(|e| ApiError::from_error("保存用户失败", &e))?
+            .ok_or_else(|| ApiError::not_found("用户不存在"))
+    }
+
+    /// Revoke every session of the user, returning how many there were
+    async fn revoke_sessions(&self, user_id: Uuid) -> Result<usize, ApiError> {
+        self.jwt_manager
+            .sessions()
+            .revoke_all(user_id)
+            .await
+            .map_err(|e| ApiError::from_error("注销用户会话失败", &e))
+    }
+}
+
+/// Where an admin request comes from, recorded with the action: the client
+/// IP the request limiter counts it under and the user agent
+pub struct RequestOrigin {
+    ip_address: String,
+    user_agent: String,
+}
+
+#[async_trait]
+impl FromRequestParts<AdminServiceState> for RequestOrigin {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AdminServiceState) -> Result<Self, Infallible> {
+        let peer = parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip());
+        let ip_address = client_ip(&parts.headers, peer, state.trust_forwarded_for)
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let user_agent = parts
+            .headers
+            .get("user-agent")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        Ok(Self { ip_address, user_agent })
+    }
+}
+
+/// Lets [`AuthUser`] validate bearer tokens on the admin routes
+impl FromRef<AdminServiceState> for Arc<JwtManager> {
+    fn from_ref(state: &AdminServiceState) -> Self {
+        state.jwt_manager.clone()
+    }
+}
+
+/// Role change request
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub role: String,
+}
+
+impl Validate for UpdateRoleRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.check(
+            ASSIGNABLE_ROLES.contains(&self.role.as_str()),
+            "role",
+            format!("角色必须是 {} 之一", ASSIGNABLE_ROLES.join(", ")),
+        );
+    }
+}
+
+/// A random password handed to the user by an admin
+fn temporary_password() -> String {
+    (0..TEMPORARY_PASSWORD_LEN)
+        .map(|_| {
+            let index = OsRng.next_u32() as usize % TEMPORARY_PASSWORD_CHARS.len();
+            TEMPORARY_PASSWORD_CHARS[index] as char
+        })
+        .collect()
+}
+
+/// 列出用户（仅管理员）：可按角色、启用状态和邮箱/用户名关键字过滤，按注册时间倒序分页
+pub async fn list_users(
+    caller: AuthUser,
+    origin: RequestOrigin,
+    State(state): State<AdminServiceState>,
+    Query(query): Query<UserQuery>,
+) -> ApiResult {
+    state.authorize(&caller, &origin, ActionType2::Read, AuditAction::Read, Uuid::nil()).await?;
+    let page = state.users.list_users(&query).await.map_err(|e| ApiError::from_error("读取用户失败", &e))?;
+
+    let details = serde_json::json!({
+        "role": query.role,
+        "is_active": query.is_active,
+        "search": query.search,
+        "offset": page.offset,
+        "limit": page.limit,
+        "total": page.total,
+    });
+    state.record(&caller, &origin, AuditAction::Read, Uuid::nil(), AuditResult::Success, details).await;
+
+    Ok((StatusCode::OK, Json(serde_json::json!({ "success": true, "data": page }))))
+}
+
+/// 修改用户角色（仅管理员），不能修改自己的角色；用户的所有会话随即注销，需重新登录
+pub async fn update_user_role(
+    caller: AuthUser,
+    origin: RequestOrigin,
+    State(state): State<AdminServiceState>,
+    Path(user_id): Path<Uuid>,
+    ValidJson(request): ValidJson<UpdateRoleRequest>,
+) -> ApiResult {
+    state.authorize(&caller, &origin, ActionType2::Update, AuditAction::PermissionChange, user_id).await?;
+    let mut user = state.target(&caller, user_id).await?;
+
+    let previous = std::mem::replace(&mut user.role, request.role);
+    let user = state.save(&user).await?;
+    // Tokens carry the role, so the user signs in again to pick up the new one
+    let revoked = state.revoke_sessions(user_id).await?;
+    let details = serde_json::json!({ "from": previous, "to": user.role, "revoked_sessions": revoked });
+    state.record(&caller, &origin, AuditAction::PermissionChange, user_id, AuditResult::Success, details).await;
+
+    Ok((StatusCode::OK, Json(serde_json::json!({ "success": true, "user": UserResponse::from(&user) }))))
+}
+
+/// 停用用户（仅管理员）：停用后无法登录且所有会话随即注销，不能停用自己
+pub async fn deactivate_user(
+    caller: AuthUser,
+    origin: RequestOrigin,
+    State(state): State<AdminServiceState>,
+    Path(user_id): Path<Uuid>,
+) -> ApiResult {
+    set_active(caller, origin, state, user_id, false).await
+}
+
+/// 重新启用被停用的用户（仅管理员）
+pub async fn reactivate_user(
+    caller: AuthUser,
+    origin: RequestOrigin,
+    State(state): State<AdminServiceState>,
+    Path(user_id): Path<Uuid>,
+) -> ApiResult {
+    set_active(caller, origin, state, user_id, true).await
+}
+
+async fn set_active(
+    caller: AuthUser,
+    origin: RequestOrigin,
+    state: AdminServiceState,
+    user_id: Uuid,
+    active: bool,
+) -> ApiResult {
+    state.authorize(&caller, &origin, ActionType2::Update, AuditAction::Update, user_id).await?;
+    let mut user = state.target(&caller, user_id).await?;
+
+    user.is_active = active;
+    let user = state.save(&user).await?;
+    let revoked = if active { 0 } else { state.revoke_sessions(user_id).await? };
+    let details = serde_json::json!({ "is_active": active, "revoked_sessions": revoked });
+    state.record(&caller, &origin, AuditAction::Update, user_id, AuditResult::Success, details).await;
+
+    Ok((StatusCode::OK, Json(serde_json::json!({ "success": true, "user": UserResponse::from(&user) }))))
+}
+
+/// 强制重置用户密码（仅管理员）：生成一次性临时密码并返回给管理员，注销用户的所有会话，用户登录后须修改密码
+pub async fn reset_user_password(
+    caller: AuthUser,
+    origin: RequestOrigin,
+    State(state): State<AdminServiceState>,
+    Path(user_id): Path<Uuid>,
+) -> ApiResult {
+    state.authorize(&caller, &origin, ActionType2::Update, AuditAction::Update, user_id).await?;
+    let mut user = state.target(&caller, user_id).await?;
+
+    let password = temporary_password();
+    user.password_hash = hash_password(&password)?;
+    user.must_change_password = true;
+    let user = state.save(&user).await?;
+    let revoked = state.revoke_sessions(user_id).await?;
+    let details = serde_json::json!({ "password_reset": true, "revoked_sessions": revoked });
+    state.record(&caller, &origin, AuditAction::Update, user_id, AuditResult::Success, details).await;
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "user": UserResponse::from(&user),
+            "temporary_password": password
+        })),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::call_with_token;
+    use crate::user_service::{verify_password, InMemoryUserStore};
+    use axum::routing::{get, post, put};
+    use axum::Router;
+    use common::types::Role;
+    use rbac_service::SessionInfo;
+
+    fn app(state: AdminServiceState) -> Router {
+        Router::new()
+            .route("/admin/users", get(list_users))
+            .route("/admin/users/:user_id/role", put(update_user_role))
+            .route("/admin/users/:user_id/deactivate", post(deactivate_user))
+            .route("/admin/users/:user_id/reactivate", post(reactivate_user))
+            .route("/admin/users/:user_id/password-reset", post(reset_user_password))
+            .with_state(state)
+    }
+
+    async fn call(state: &AdminServiceState, token: &str, method: &str, uri: &str, body: JsonValue) -> (StatusCode, JsonValue) {
+        call_with_token(app(state.clone()), token, method, uri, Some(body)).await
+    }
+
+    #[tokio::test]
+    async fn test_admin_user_management() {
+        let users: Arc<dyn UserStore> = Arc::new(InMemoryUserStore::new());
+        let jwt_manager = Arc::new(JwtManager::new("secret", 1));
+        let state = AdminServiceState::new(users.clone(), jwt_manager.clone());
+        let create = |email: &str| users.create_user(email.to_string(), "hash".to_string(), "Test".to_string());
+        let (admin, member) = (create("root@example.com").await.unwrap(), create("dev@example.com").await.unwrap());
+        let admin_token = jwt_manager.generate_token(admin.id, Role::Admin, vec![]).unwrap();
+        let member_token = jwt_manager.generate_token(member.id, Role::Manager, vec![]).unwrap();
+        let (session_token, _) = jwt_manager.start_session(member.id, Role::Manager, vec![], SessionInfo::default()).await.unwrap();
+
+        let (status, _) = call(&state, &member_token, "GET", "/admin/users", JsonValue::Null).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = call(&state, &admin_token, "GET", "/admin/users?search=dev&limit=10", JsonValue::Null).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["total"], 1);
+        assert_eq!(body["data"]["users"][0]["email"], "dev@example.com");
+
+        let uri = format!("/admin/users/{}/role", member.id);
+        let (status, body) = call(&state, &admin_token, "PUT", &uri, serde_json::json!({ "role": "owner" })).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["field_errors"][0]["field"], "role");
+        let (status, body) = call(&state, &admin_token, "PUT", &uri, serde_json::json!({ "role": "viewer" })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["user"]["role"], "viewer");
+        assert!(jwt_manager.validate_token(&session_token).await.is_err());
+        let self_uri = format!("/admin/users/{}/role", admin.id);
+        let (status, _) = call(&state, &admin_token, "PUT", &self_uri, serde_json::json!({ "role": "user" })).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, body) = call(&state, &admin_token, "POST", &format!("/admin/users/{}/deactivate", member.id), JsonValue::Null).await;
+        assert_eq!((status, body["user"]["is_active"].clone()), (StatusCode::OK, JsonValue::Bool(false)));
+        let (status, _) = call(&state, &admin_token, "POST", &format!("/admin/users/{}/reactivate", member.id), JsonValue::Null).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(users.get_user_by_id(member.id).await.unwrap().unwrap().is_active);
+
+        let (status, body) = call(&state, &admin_token, "POST", &format!("/admin/users/{}/password-reset", member.id), JsonValue::Null).await;
+        assert_eq!(status, StatusCode::OK);
+        let reset = users.get_user_by_id(member.id).await.unwrap().unwrap();
+        assert!(reset.must_change_password);
+        assert!(verify_password(body["temporary_password"].as_str().unwrap(), &reset.password_hash));
+
+        let actions = state.actions().await;
+        let recorded: Vec<_> = actions.iter().map(|log| (log.action.clone(), log.result.clone())).collect();
+        assert_eq!(recorded.len(), 6);
+        assert!(matches!(recorded[0], (AuditAction::Read, AuditResult::Denied)));
+        assert!(matches!(recorded[2], (AuditAction::PermissionChange, AuditResult::Success)));
+        assert_eq!(actions[2].details["to"], "viewer");
+        assert_eq!(actions[2].details["revoked_sessions"], 1);
+        assert!(actions.iter().all(|log| log.is_security_sensitive));
+    }
+}
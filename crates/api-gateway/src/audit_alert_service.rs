@@ -0,0 +1,108 @@
+use audit_service::{AlertEngine, AlertRuleInput};
+use axum::{
+    extract::{FromRef, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use rbac_service::middleware::AdminOnly;
+use rbac_service::{AuthUser, JwtManager};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::errors::ApiError;
+
+/// Audit alerting service state
+#[derive(Clone)]
+pub struct AuditAlertServiceState {
+    pub alerts: Arc<AlertEngine>,
+    pub jwt_manager: Arc<JwtManager>,
+}
+
+impl AuditAlertServiceState {
+    pub fn new(alerts: Arc<AlertEngine>, jwt_manager: Arc<JwtManager>) -> Self {
+        Self { alerts, jwt_manager }
+    }
+}
+
+impl FromRef<AuditAlertServiceState> for Arc<JwtManager> {
+    fn from_ref(state: &AuditAlertServiceState) -> Self {
+        state.jwt_manager.clone()
+    }
+}
+
+/// 列出审计告警规则（仅管理员）
+pub async fn list_alert_rules(
+    _admin: AuthUser<AdminOnly>,
+    State(state): State<AuditAlertServiceState>,
+) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "success": true,
+        "rules": state.alerts.list_rules()
+    }))
+}
+
+/// 创建审计告警规则（仅管理员）：连续登录失败、非工作时间的权限变更或批量删除触发时，通过 Slack 或邮件通知
+pub async fn create_alert_rule(
+    _admin: AuthUser<AdminOnly>,
+    State(state): State<AuditAlertServiceState>,
+    Json(input): Json<AlertRuleInput>,
+) -> Result<impl IntoResponse, ApiError> {
+    let rule = state
+        .alerts
+        .create_rule(input)
+        .map_err(|e| ApiError::from_error("创建告警规则失败", &e))?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "success": true,
+            "rule": rule
+        })),
+    ))
+}
+
+/// 更新审计告警规则（仅管理员），条件变化时重新计数
+pub async fn update_alert_rule(
+    _admin: AuthUser<AdminOnly>,
+    State(state): State<AuditAlertServiceState>,
+    Path(rule_id): Path<Uuid>,
+    Json(input): Json<AlertRuleInput>,
+) -> Result<impl IntoResponse, ApiError> {
+    let rule = state
+        .alerts
+        .update_rule(rule_id, input)
+        .map_err(|e| ApiError::from_error("更新告警规则失败", &e))?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "rule": rule
+    })))
+}
+
+/// 删除审计告警规则（仅管理员）
+pub async fn delete_alert_rule(
+    _admin: AuthUser<AdminOnly>,
+    State(state): State<AuditAlertServiceState>,
+    Path(rule_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .alerts
+        .delete_rule(rule_id)
+        .map_err(|e| ApiError::from_error("删除告警规则失败", &e))?;
+
+    Ok(Json(serde_json::json!({
+        "success": true
+    })))
+}
+
+/// 列出最近触发的审计告警（仅管理员），最新的在前
+pub async fn list_recent_alerts(
+    _admin: AuthUser<AdminOnly>,
+    State(state): State<AuditAlertServiceState>,
+) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "success": true,
+        "alerts": state.alerts.recent_alerts()
+    }))
+}
@@ -0,0 +1,156 @@
+use audit_service::{AuditExportJobs, AuditLogger, ExportJobStatus};
+use axum::{
+    extract::{ConnectInfo, FromRef, Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use common::types::{AuditAction, AuditFilter, AuditLog, AuditResult, ExportFormat, ResourceType};
+use rbac_service::middleware::AdminOnly;
+use rbac_service::{AuthUser, JwtManager};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::errors::ApiError;
+use crate::file_service::file_download;
+use crate::request_limiter::client_ip;
+use crate::validation::{FieldErrors, Validate, ValidJson};
+
+/// Audit log export service state
+#[derive(Clone)]
+pub struct AuditExportServiceState {
+    pub jobs: Arc<AuditExportJobs>,
+    pub jwt_manager: Arc<JwtManager>,
+    audit: Option<Arc<AuditLogger>>,
+    trust_forwarded_for: bool,
+}
+
+impl AuditExportServiceState {
+    pub fn new(jobs: Arc<AuditExportJobs>, jwt_manager: Arc<JwtManager>) -> Self {
+        Self { jobs, jwt_manager, audit: None, trust_forwarded_for: false }
+    }
+
+    /// Record every export in the audit log
+    pub fn with_audit_logger(mut self, audit: Arc<AuditLogger>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Record the client IP from `X-Forwarded-For` as the request limiter
+    /// does; only enable behind a proxy that appends it
+    pub fn with_trusted_forwarded_for(mut self, trust_forwarded_for: bool) -> Self {
+        self.trust_forwarded_for = trust_forwarded_for;
+        self
+    }
+}
+
+impl FromRef<AuditExportServiceState> for Arc<JwtManager> {
+    fn from_ref(state: &AuditExportServiceState) -> Self {
+        state.jwt_manager.clone()
+    }
+}
+
+/// Export job request
+#[derive(Debug, Deserialize)]
+pub struct CreateExportRequest {
+    /// Logs to export; the limit and offset are not used
+    #[serde(default)]
+    pub filter: AuditFilter,
+    pub format: ExportFormat,
+}
+
+impl Validate for CreateExportRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        if let (Some(start), Some(end)) = (self.filter.start_time, self.filter.end_time) {
+            errors.check(start <= end, "filter.end_time", "结束时间不能早于开始时间");
+        }
+    }
+}
+
+/// 创建审计日志导出任务（仅管理员）：在后台分批读取日志并写入 JSON、CSV、NDJSON 或 Parquet 文件，返回任务供轮询
+pub async fn create_audit_export(
+    admin: AuthUser<AdminOnly>,
+    State(state): State<AuditExportServiceState>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    ValidJson(request): ValidJson<CreateExportRequest>,
+) -> impl IntoResponse {
+    let job = state.jobs.create(admin.claims.sub, request.filter, request.format);
+
+    if let Some(audit) = &state.audit {
+        let ip_address = client_ip(&headers, peer.map(|ConnectInfo(addr)| addr.ip()), state.trust_forwarded_for)
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let user_agent = headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let mut log = AuditLog::new(
+            admin.claims.sub,
+            AuditAction::Read,
+            ResourceType::AuditLog,
+            job.id,
+            ip_address,
+            user_agent,
+            AuditResult::Success,
+        );
+        log.details = serde_json::json!({ "export": { "format": job.format, "filter": job.filter } });
+        log.is_security_sensitive = true;
+        if let Err(e) = audit.log(log) {
+            tracing::error!("Failed to record audit export {}: {}", job.id, e);
+        }
+    }
+
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "success": true,
+            "job": job
+        })),
+    )
+}
+
+/// 列出审计日志导出任务（仅管理员），最新的在前
+pub async fn list_audit_exports(
+    _admin: AuthUser<AdminOnly>,
+    State(state): State<AuditExportServiceState>,
+) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "success": true,
+        "jobs": state.jobs.list()
+    }))
+}
+
+/// 查询审计日志导出任务的状态（仅管理员）
+pub async fn get_audit_export(
+    _admin: AuthUser<AdminOnly>,
+    State(state): State<AuditExportServiceState>,
+    Path(job_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let job = state.jobs.get(job_id).ok_or_else(|| ApiError::not_found("导出任务不存在"))?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "job": job
+    })))
+}
+
+/// 下载已完成的审计日志导出文件（仅管理员）
+pub async fn download_audit_export(
+    _admin: AuthUser<AdminOnly>,
+    State(state): State<AuditExportServiceState>,
+    Path(job_id): Path<Uuid>,
+) -> Result<Response, ApiError> {
+    let job = state.jobs.get(job_id).ok_or_else(|| ApiError::not_found("导出任务不存在"))?;
+    if job.status != ExportJobStatus::Completed {
+        return Err(ApiError::conflict("导出任务尚未完成"));
+    }
+    let (Some(path), Some(file_name)) = (state.jobs.file_path(&job), job.file_name.as_deref()) else {
+        return Err(ApiError::not_found("导出文件不存在"));
+    };
+
+    file_download(&path, file_name, job.format.content_type()).await
+}
@@ -0,0 +1,47 @@
+use audit_service::AuditRetention;
+use axum::{
+    extract::{FromRef, State},
+    response::IntoResponse,
+    Json,
+};
+use rbac_service::middleware::AdminOnly;
+use rbac_service::{AuthUser, JwtManager};
+use std::sync::Arc;
+
+use crate::errors::ApiError;
+
+/// Audit log retention service state
+#[derive(Clone)]
+pub struct AuditRetentionServiceState {
+    pub retention: Arc<AuditRetention>,
+    pub jwt_manager: Arc<JwtManager>,
+}
+
+impl AuditRetentionServiceState {
+    pub fn new(retention: Arc<AuditRetention>, jwt_manager: Arc<JwtManager>) -> Self {
+        Self { retention, jwt_manager }
+    }
+}
+
+impl FromRef<AuditRetentionServiceState> for Arc<JwtManager> {
+    fn from_ref(state: &AuditRetentionServiceState) -> Self {
+        state.jwt_manager.clone()
+    }
+}
+
+/// 查询审计日志保留策略（仅管理员）：各类日志的保留天数、下次清理时间、上次清理结果，以及日志表与归档文件占用的存储空间
+pub async fn get_audit_retention(
+    _admin: AuthUser<AdminOnly>,
+    State(state): State<AuditRetentionServiceState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let status = state
+        .retention
+        .status()
+        .await
+        .map_err(|e| ApiError::from_error("读取审计日志存储用量失败", &e))?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "retention": status
+    })))
+}
@@ -0,0 +1,275 @@
+//! Audit trail of mutating requests.
+//!
+//! Every `POST`, `PUT`, `PATCH` and `DELETE` request is recorded in the
+//! audit log once its handler has answered: the user of its bearer token,
+//! an action inferred from the method and route, the resource type and id
+//! taken from the path, and a result taken from the response status. The
+//! client IP is the connection's peer, or the entry a trusted proxy appended
+//! to `X-Forwarded-For` (see [`client_ip`]). Routes
+//! whose handlers record richer audit logs themselves, and routes that only
+//! read despite their method, are skipped.
+
+use audit_service::AuditLogger;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use common::types::{AuditAction, AuditLog, AuditResult, ResourceType};
+use rbac_service::JwtManager;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::request_limiter::client_ip;
+
+/// Routes not recorded by default, by path prefix
+pub const DEFAULT_SKIPPED_ROUTES: &[&str] = &[
+    // Audited by their handlers
+    "/api/v1/auth/login",
+    "/api/v1/account",
+    "/api/v1/admin/users",
+    "/api/v1/admin/audit/exports",
+    // Read-only or machine-driven
+    "/api/v1/graphql",
+    "/api/v1/quotas/preview",
+    "/api/v1/signatures/verify",
+    "/api/v1/webhooks",
+];
+
+/// Last path segments of routes that run something
+const EXECUTE_SEGMENTS: &[&str] = &["execute", "run", "cancel", "pause", "resume", "requeue", "instantiate"];
+
+/// Last path segments of routes that change who may do what
+const PERMISSION_SEGMENTS: &[&str] = &["role", "ownership", "deactivate", "reactivate", "password", "password-reset"];
+
+/// Path segments of routes that change configuration
+const CONFIG_SEGMENTS: &[&str] = &[
+    "settings",
+    "quotas",
+    "encryption",
+    "sampling",
+    "review-policy",
+    "on-call",
+    "alert-rules",
+];
+
+/// Records mutating requests in the audit log
+pub struct AuditTrail {
+    audit: Arc<AuditLogger>,
+    jwt_manager: Arc<JwtManager>,
+    skipped: Vec<String>,
+    trust_forwarded_for: bool,
+}
+
+impl AuditTrail {
+    pub fn new(audit: Arc<AuditLogger>, jwt_manager: Arc<JwtManager>) -> Self {
+        Self {
+            audit,
+            jwt_manager,
+            skipped: DEFAULT_SKIPPED_ROUTES.iter().map(|route| route.to_string()).collect(),
+            trust_forwarded_for: false,
+        }
+    }
+
+    /// Record the client IP from `X-Forwarded-For` as the request limiter
+    /// does; only enable behind a proxy that appends it
+    pub fn with_trusted_forwarded_for(mut self, trust_forwarded_for: bool) -> Self {
+        self.trust_forwarded_for = trust_forwarded_for;
+        self
+    }
+
+    /// Also skip the routes under `prefix`, matched on whole segments
+    pub fn with_skipped_route(mut self, prefix: &str) -> Self {
+        self.skipped.push(prefix.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Whether a request is recorded
+    pub fn records(&self, method: &Method, path: &str) -> bool {
+        let mutating = matches!(*method, Method::POST | Method::PUT | Method::PATCH | Method::DELETE);
+        mutating
+            && !self.skipped.iter().any(|prefix| {
+                path.strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+    }
+
+    /// The audit log of a request answered with `status`
+    pub async fn audit_log(&self, method: &Method, path: &str, headers: &HeaderMap, peer: Option<SocketAddr>, status: StatusCode) -> AuditLog {
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let user_id = match token {
+            Some(token) => self.jwt_manager.validate_token(token).await.ok().map(|claims| claims.sub),
+            None => None,
+        };
+        let segments: Vec<&str> = path
+            .trim_start_matches("/api/v1")
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let resource_type = resource_type(&segments);
+        let resource_id = segments
+            .iter()
+            .find_map(|segment| segment.parse::<Uuid>().ok())
+            .or(match resource_type {
+                ResourceType::User => user_id,
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        let ip_address = client_ip(headers, peer.map(|addr| addr.ip()), self.trust_forwarded_for)
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let user_agent = headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("unknown")
+            .to_string();
+        let result = match status.as_u16() {
+            401 | 403 => AuditResult::Denied,
+            code if code < 400 => AuditResult::Success,
+            code => AuditResult::Failure(format!("HTTP {}", code)),
+        };
+
+        let mut log = AuditLog::new(
+            user_id.unwrap_or_default(),
+            action(method, &segments),
+            resource_type,
+            resource_id,
+            ip_address,
+            user_agent,
+            result,
+        );
+        log.details = serde_json::json!({
+            "method": method.as_str(),
+            "path": path,
+            "status": status.as_u16(),
+        });
+        log
+    }
+
+    /// Middleware recording the request once its handler has answered
+    pub async fn middleware(State(trail): State<Arc<Self>>, req: Request, next: Next) -> Response {
+        let method = req.method().clone();
+        let path = req.uri().path().to_string();
+        if !trail.records(&method, &path) {
+            return next.run(req).await;
+        }
+        let headers = req.headers().clone();
+        let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| *addr);
+        let request_id = req.extensions().get::<Uuid>().copied();
+
+        let response = next.run(req).await;
+        let mut log = trail.audit_log(&method, &path, &headers, peer, response.status()).await;
+        if let Some(request_id) = request_id {
+            log.details["request_id"] = serde_json::json!(request_id);
+        }
+        if let Err(e) = trail.audit.log(log) {
+            tracing::error!("Failed to record {} {} in the audit log: {}", method, path, e);
+        }
+        response
+    }
+}
+
+/// Resource type of the route's first segment; routes of no resource type
+/// are recorded as settings
+fn resource_type(segments: &[&str]) -> ResourceType {
+    let segments = match segments {
+        ["admin", rest @ ..] => rest,
+        segments => segments,
+    };
+    match segments.first().copied().unwrap_or_default() {
+        "workflows" | "webhooks" | "revisions" | "schema-drift" | "digests" => ResourceType::Workflow,
+        "templates" => ResourceType::Template,
+        "executions" | "dead-letters" => ResourceType::Execution,
+        "integrations" | "credentials" | "oauth" | "files" => ResourceType::Integration,
+        "auth" | "account" | "users" => ResourceType::User,
+        "audit" => ResourceType::AuditLog,
+        _ => ResourceType::Settings,
+    }
+}
+
+fn action(method: &Method, segments: &[&str]) -> AuditAction {
+    let last = segments.iter().rev().find(|segment| segment.parse::<Uuid>().is_err()).copied().unwrap_or_default();
+    if last == "logout" {
+        AuditAction::Logout
+    } else if PERMISSION_SEGMENTS.contains(&last) {
+        AuditAction::PermissionChange
+    } else if segments.iter().any(|segment| CONFIG_SEGMENTS.contains(segment)) {
+        AuditAction::ConfigChange
+    } else if *method == Method::DELETE {
+        AuditAction::Delete
+    } else if EXECUTE_SEGMENTS.contains(&last) {
+        AuditAction::Execute
+    } else if *method == Method::POST {
+        AuditAction::Create
+    } else {
+        AuditAction::Update
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trail() -> AuditTrail {
+        let pool = sqlx::PgPool::connect_lazy("postgresql://localhost/test").unwrap();
+        let audit = AuditLogger::new(Arc::new(audit_service::AuditStorage::new(pool)));
+        AuditTrail::new(Arc::new(audit), Arc::new(JwtManager::new("secret", 1)))
+    }
+
+    #[tokio::test]
+    async fn test_only_mutating_routes_are_recorded() {
+        let trail = trail().with_skipped_route("/api/v1/files/");
+        assert!(trail.records(&Method::POST, "/api/v1/workflows"));
+        assert!(trail.records(&Method::DELETE, "/api/v1/accounts"));
+        assert!(!trail.records(&Method::GET, "/api/v1/workflows"));
+        assert!(!trail.records(&Method::POST, "/api/v1/auth/login"));
+        assert!(!trail.records(&Method::DELETE, "/api/v1/account"));
+        assert!(!trail.records(&Method::POST, "/api/v1/files/write"));
+    }
+
+    #[tokio::test]
+    async fn test_action_resource_and_result_are_inferred() {
+        let trail = trail();
+        let workflow_id = Uuid::new_v4();
+        let user = Uuid::new_v4();
+        let token = trail.jwt_manager.generate_token(user, common::types::Role::User, vec![]).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        headers.insert("x-forwarded-for", "203.0.113.7, 10.0.0.2".parse().unwrap());
+
+        let path = format!("/api/v1/workflows/{}/execute", workflow_id);
+        let peer: SocketAddr = "10.0.0.1:443".parse().unwrap();
+        let log = trail.audit_log(&Method::POST, &path, &headers, Some(peer), StatusCode::OK).await;
+        assert_eq!((log.user_id, log.resource_id), (user, workflow_id));
+        assert!(matches!(log.action, AuditAction::Execute));
+        assert!(matches!(log.resource_type, ResourceType::Workflow));
+        assert!(matches!(log.result, AuditResult::Success));
+        // Forwarded addresses are only believed behind a trusted proxy, and
+        // only the entry it appended
+        assert_eq!(log.ip_address, "10.0.0.1");
+        let proxied = self::trail().with_trusted_forwarded_for(true);
+        let log = proxied.audit_log(&Method::POST, &path, &headers, Some(peer), StatusCode::OK).await;
+        assert_eq!(log.ip_address, "10.0.0.2");
+
+        let path = format!("/api/v1/workflows/{}/ownership", workflow_id);
+        let log = trail.audit_log(&Method::PUT, &path, &headers, None, StatusCode::FORBIDDEN).await;
+        assert!(matches!(log.action, AuditAction::PermissionChange));
+        assert!(matches!(log.result, AuditResult::Denied));
+        assert!(log.is_security_sensitive);
+
+        let log = trail.audit_log(&Method::PUT, "/api/v1/auth/profile", &headers, None, StatusCode::BAD_REQUEST).await;
+        assert!(matches!(log.action, AuditAction::Update));
+        assert!(matches!(log.resource_type, ResourceType::User));
+        assert_eq!(log.resource_id, user);
+        assert!(matches!(log.result, AuditResult::Failure(ref reason) if reason == "HTTP 400"));
+
+        let log = trail.audit_log(&Method::PUT, "/api/v1/admin/audit/alert-rules/x", &HeaderMap::new(), None, StatusCode::OK).await;
+        assert!(matches!(log.action, AuditAction::ConfigChange));
+        assert!(matches!(log.resource_type, ResourceType::AuditLog));
+        assert_eq!(log.user_id, Uuid::nil());
+    }
+}
@@ -1,25 +1,60 @@
-use common::types::{ApiResponse, CachedResponse};
+use common::types::{ApiResponse, CachedResponse, ProviderConfig};
 use chrono::Utc;
 use moka::future::Cache;
+use moka::policy::EvictionPolicy;
+use std::collections::HashMap;
 use std::time::Duration;
+use tokio::sync::RwLock;
 
-/// Response cache using moka for in-memory caching
+/// Response cache using moka for in-memory caching.
+///
+/// Once full, the least recently used entries are evicted. Responses of a
+/// provider are cached for the `cache_ttl` of its [`ProviderConfig`], and
+/// not at all for providers without one.
 pub struct ResponseCache {
     cache: Cache<String, CachedResponse>,
+    /// Cache TTL of each configured provider
+    ttls: RwLock<HashMap<String, Duration>>,
 }
 
 impl ResponseCache {
-    /// Create a new response cache with specified capacity
+    /// Create a new response cache with specified capacity; no entry
+    /// outlives `default_ttl`
     pub fn new(max_capacity: u64, default_ttl: Duration) -> Self {
         let cache = Cache::builder()
             .max_capacity(max_capacity)
             .time_to_live(default_ttl)
+            .eviction_policy(EvictionPolicy::lru())
+            .support_invalidation_closures()
             .build();
 
-        Self { cache }
+        Self {
+            cache,
+            ttls: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Cache responses of a provider for its `cache_ttl`, or stop caching
+    /// them when it has none
+    pub async fn configure_provider(&self, config: &ProviderConfig) {
+        let mut ttls = self.ttls.write().await;
+        match config.cache_ttl {
+            Some(ttl) if !ttl.is_zero() => {
+                ttls.insert(config.name.clone(), ttl);
+            }
+            _ => {
+                ttls.remove(&config.name);
+            }
+        }
+    }
+
+    /// How long responses of a provider are cached; `None` when they are not
+    pub async fn ttl(&self, provider: &str) -> Option<Duration> {
+        self.ttls.read().await.get(provider).copied()
     }
 
-    /// Generate cache key from provider, endpoint, and request parameters
+    /// Generate cache key from provider, endpoint, and request parameters.
+    /// Keys start with the provider so its entries can be invalidated together.
     pub fn generate_key(provider: &str, endpoint: &str, method: &str, body: &str) -> String {
         use sha2::{Digest, Sha256};
         let mut hasher = Sha256::new();
@@ -27,7 +62,7 @@ impl ResponseCache {
         hasher.update(endpoint.as_bytes());
         hasher.update(method.as_bytes());
         hasher.update(body.as_bytes());
-        format!("{:x}", hasher.finalize())
+        format!("{}:{:x}", provider, hasher.finalize())
     }
 
     /// Get cached response if available and not expired
@@ -59,10 +94,12 @@ impl ResponseCache {
     }
 
     /// Invalidate all cache entries for a provider
-    pub async fn invalidate_provider(&self, _provider: &str) {
-        // Note: moka doesn't support prefix-based invalidation
-        // In production, consider using Redis with pattern matching
-        self.cache.invalidate_all();
+    pub async fn invalidate_provider(&self, provider: &str) {
+        let prefix = format!("{}:", provider);
+        if let Err(e) = self.cache.invalidate_entries_if(move |key, _| key.starts_with(&prefix)) {
+            tracing::warn!("Failed to invalidate cached responses of {}: {}", provider, e);
+            self.cache.invalidate_all();
+        }
     }
 
     /// Get cache statistics
@@ -141,6 +178,38 @@ mod tests {
         assert!(cache.get(&key).await.is_none());
     }
 
+    #[tokio::test]
+    async fn test_provider_ttl_and_invalidation() {
+        let cache = ResponseCache::new(100, Duration::from_secs(60));
+        let config = |name: &str, cache_ttl| ProviderConfig {
+            name: name.to_string(),
+            api_keys: vec![],
+            rate_limit: Default::default(),
+            cache_ttl,
+            failover_providers: vec![],
+        };
+        cache.configure_provider(&config("openai", Some(Duration::from_secs(30)))).await;
+        cache.configure_provider(&config("anthropic", None)).await;
+        assert_eq!(cache.ttl("openai").await, Some(Duration::from_secs(30)));
+        assert_eq!(cache.ttl("anthropic").await, None);
+
+        let response = ApiResponse {
+            request_id: Uuid::new_v4(),
+            status_code: 200,
+            headers: HashMap::new(),
+            body: None,
+            latency_ms: 100,
+        };
+        let openai = ResponseCache::generate_key("openai", "/v1/chat", "POST", "{}");
+        let other = ResponseCache::generate_key("openai-eu", "/v1/chat", "POST", "{}");
+        cache.set(openai.clone(), response.clone(), Duration::from_secs(30)).await;
+        cache.set(other.clone(), response, Duration::from_secs(30)).await;
+
+        cache.invalidate_provider("openai").await;
+        assert!(cache.get(&openai).await.is_none());
+        assert!(cache.get(&other).await.is_some());
+    }
+
     #[tokio::test]
     async fn test_cache_invalidate() {
         let cache = ResponseCache::new(100, Duration::from_secs(60));
@@ -0,0 +1,101 @@
+use async_trait::async_trait;
+use integration_service::catalog::{CatalogEntry, CatalogError, CatalogStore, IntegrationSetting};
+use sqlx::types::Json;
+use sqlx::{PgPool, Row};
+
+/// Postgres-backed integration catalog (see
+/// `migrations/011_integration_catalog.sql`)
+pub struct PgCatalogStore {
+    pool: PgPool,
+}
+
+impl PgCatalogStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl CatalogStore for PgCatalogStore {
+    async fn save_entry(&self, entry: &CatalogEntry) -> Result<(), CatalogError> {
+        sqlx::query(
+            r#"
+            INSERT INTO integration_catalog (name, entry, updated_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (name) DO UPDATE SET entry = $2, updated_at = $3
+            "#,
+        )
+        .bind(&entry.info.name)
+        .bind(Json(entry))
+        .bind(entry.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn load_entries(&self) -> Result<Vec<CatalogEntry>, CatalogError> {
+        let rows = sqlx::query("SELECT entry FROM integration_catalog ORDER BY name")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        rows.iter()
+            .map(|row| {
+                let Json(entry) = row.try_get::<Json<CatalogEntry>, _>("entry").map_err(storage_error)?;
+                Ok(entry)
+            })
+            .collect()
+    }
+
+    async fn save_setting(&self, setting: &IntegrationSetting) -> Result<(), CatalogError> {
+        let disabled_actions: Vec<String> = setting.disabled_actions.iter().cloned().collect();
+        sqlx::query(
+            r#"
+            INSERT INTO organization_integrations (
+                organization_id, integration, enabled, disabled_actions, updated_by, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (organization_id, integration) DO UPDATE
+            SET enabled = $3, disabled_actions = $4, updated_by = $5, updated_at = $6
+            "#,
+        )
+        .bind(setting.organization_id)
+        .bind(&setting.integration)
+        .bind(setting.enabled)
+        .bind(disabled_actions)
+        .bind(setting.updated_by)
+        .bind(setting.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn load_settings(&self) -> Result<Vec<IntegrationSetting>, CatalogError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT organization_id, integration, enabled, disabled_actions, updated_by, updated_at
+            FROM organization_integrations
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        rows.iter().map(decode).collect()
+    }
+}
+
+fn decode(row: &sqlx::postgres::PgRow) -> Result<IntegrationSetting, CatalogError> {
+    Ok(IntegrationSetting {
+        organization_id: row.try_get("organization_id").map_err(storage_error)?,
+        integration: row.try_get("integration").map_err(storage_error)?,
+        enabled: row.try_get("enabled").map_err(storage_error)?,
+        disabled_actions: row.try_get::<Vec<String>, _>("disabled_actions").map_err(storage_error)?.into_iter().collect(),
+        updated_by: row.try_get("updated_by").map_err(storage_error)?,
+        updated_at: row.try_get("updated_at").map_err(storage_error)?,
+    })
+}
+
+fn storage_error(e: sqlx::Error) -> CatalogError {
+    CatalogError::Storage(e.to_string())
+}
@@ -0,0 +1,498 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use common::types::{ActionType2, JsonValue, Permission, ResourceType, Scope};
+use integration_service::credentials::CredentialStatus;
+use integration_service::{CredentialManager, CredentialStore, CredentialUse, IntegrationRegistry, StoredCredential};
+use rbac_service::jwt::JwtClaims;
+use rbac_service::{OrgService, PermissionChecker, RoleManager};
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::errors::{ApiError, FieldError};
+use crate::validation::{FieldErrors, Validate, ValidJson};
+
+type ApiResult = Result<(StatusCode, Json<JsonValue>), ApiError>;
+
+/// Shown in place of the encrypted credential fields
+const MASKED_DATA: &str = "********";
+
+/// Credential service state
+#[derive(Clone)]
+pub struct CredentialServiceState {
+    pub credentials: Arc<CredentialStore>,
+    /// Encrypts saved credential fields; tests decrypt them through the store
+    pub manager: Arc<CredentialManager>,
+    /// Integrations credentials are bound to and tested against
+    pub integrations: Arc<IntegrationRegistry>,
+    /// Permissions of each role; checks use the role in the caller's token
+    pub roles: Arc<RoleManager>,
+    pub permissions: Arc<PermissionChecker>,
+    /// Memberships resolving Team- and Organization-scoped permissions
+    pub organizations: Option<Arc<OrgService>>,
+}
+
+impl CredentialServiceState {
+    pub fn new(
+        credentials: Arc<CredentialStore>,
+        manager: Arc<CredentialManager>,
+        integrations: Arc<IntegrationRegistry>,
+    ) -> Self {
+        let roles = Arc::new(RoleManager::new());
+        Self {
+            credentials,
+            manager,
+            integrations,
+            permissions: Arc::new(PermissionChecker::new(roles.clone())),
+            roles,
+            organizations: None,
+        }
+    }
+
+    pub fn with_org_service(mut self, organizations: Arc<OrgService>) -> Self {
+        self.permissions = Arc::new(PermissionChecker::new(self.roles.clone()).with_org_service(organizations.clone()));
+        self.organizations = Some(organizations);
+        self
+    }
+
+    /// Whether the caller may perform `action` on a credential of `owner_id`;
+    /// `None` asks whether they may do so on anyone's credentials
+    pub(crate) async fn can(&self, claims: &JwtClaims, action: ActionType2, owner_id: Option<Uuid>) -> bool {
+        let permission = Permission {
+            resource: ResourceType::Integration,
+            action,
+            scope: Scope::Own,
+        };
+        self.permissions.check_role_permission(claims.sub, &claims.role, &permission, owner_id, None, None).await
+    }
+
+    /// The stored credential, if the caller may perform `action` on it
+    pub(crate) async fn authorized(
+        &self,
+        claims: &JwtClaims,
+        action: ActionType2,
+        credential_id: Uuid,
+    ) -> Result<StoredCredential, ApiError> {
+        let stored = self.credentials
+            .get(credential_id)
+            .await
+            .ok_or_else(|| ApiError::not_found("凭证不存在"))?;
+        if !self.can(claims, action, Some(stored.owner)).await {
+            // Credentials the caller may not even read are not revealed
+            let denied = "没有操作该凭证的权限";
+            return Err(if self.can(claims, ActionType2::Read, Some(stored.owner)).await {
+                ApiError::forbidden(denied)
+            } else {
+                ApiError::not_found(denied)
+            });
+        }
+        Ok(stored)
+    }
+}
+
+/// Credential list filter
+#[derive(Debug, Default, Deserialize)]
+pub struct CredentialQuery {
+    /// Only credentials of this integration
+    pub integration: Option<String>,
+}
+
+/// New credential
+#[derive(Debug, Deserialize)]
+pub struct CreateCredentialRequest {
+    pub name: String,
+    /// Integration the credential belongs to, e.g. "github"
+    pub integration: String,
+    /// Credential fields as the integration expects them: a JSON object, or
+    /// a string such as an API token
+    pub data: JsonValue,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Validate for CreateCredentialRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.check(!self.name.trim().is_empty(), "name", "凭证名称不能为空");
+        errors.check(!self.integration.trim().is_empty(), "integration", "集成不能为空");
+        validate_data(&self.data, errors);
+    }
+}
+
+/// Credential changes; fields left out are kept
+#[derive(Debug, Deserialize)]
+pub struct UpdateCredentialRequest {
+    pub name: Option<String>,
+    /// New credential fields, replacing the secret
+    pub data: Option<JsonValue>,
+    /// Expiry of the new secret; only applies together with `data`
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Validate for UpdateCredentialRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        if let Some(name) = &self.name {
+            errors.check(!name.trim().is_empty(), "name", "凭证名称不能为空");
+        }
+        if let Some(data) = &self.data {
+            validate_data(data, errors);
+        }
+        errors.check(self.expires_at.is_none() || self.data.is_some(), "expires_at", "过期时间只能随凭证内容一起修改");
+    }
+}
+
+fn validate_data(data: &JsonValue, errors: &mut FieldErrors) {
+    let valid = match data {
+        JsonValue::String(secret) => !secret.is_empty(),
+        JsonValue::Object(fields) => !fields.is_empty(),
+        _ => false,
+    };
+    errors.check(valid, "data", "凭证内容必须是非空字符串或对象");
+}
+
+/// The plaintext integrations receive: strings as they are, objects as JSON
+fn plaintext(data: &JsonValue) -> String {
+    match data {
+        JsonValue::String(secret) => secret.clone(),
+        data => data.to_string(),
+    }
+}
+
+/// The credential without its encrypted fields
+pub(crate) fn masked(mut credential: StoredCredential) -> StoredCredential {
+    credential.data = MASKED_DATA.to_string();
+    credential
+}
+
+/// 列出调用者可读的凭证（不含凭证内容）：有全局读取权限时列出全部；属于组织的用户列出其有权读取的
+/// 团队和组织成员的凭证；否则只列出自己的。可按集成筛选
+pub async fn list_credentials(
+    State(state): State<CredentialServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Query(query): Query<CredentialQuery>,
+) -> ApiResult {
+    let all = state.can(&claims, ActionType2::Read, None).await;
+    let in_organization = match &state.organizations {
+        Some(organizations) => !organizations.organizations_of(claims.sub).await.is_empty(),
+        None => false,
+    };
+    let mut credentials = if all || in_organization {
+        state.credentials.list().await
+    } else {
+        state.credentials.list_for_owner(claims.sub).await
+    };
+    if let Some(integration) = &query.integration {
+        credentials.retain(|c| &c.integration == integration);
+    }
+    if !all && in_organization {
+        let mut readable = Vec::with_capacity(credentials.len());
+        for credential in credentials {
+            if state.can(&claims, ActionType2::Read, Some(credential.owner)).await {
+                readable.push(credential);
+            }
+        }
+        credentials = readable;
+    }
+    credentials.sort_by_key(|c| c.created_at);
+    let credentials: Vec<StoredCredential> = credentials.into_iter().map(masked).collect();
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "total": credentials.len(),
+            "credentials": credentials
+        })),
+    ))
+}
+
+/// 创建凭证：凭证内容加密保存，归调用者所有；集成必须已注册，认证方式取自集成
+pub async fn create_credential(
+    State(state): State<CredentialServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    ValidJson(request): ValidJson<CreateCredentialRequest>,
+) -> ApiResult {
+    if !state.can(&claims, ActionType2::Create, Some(claims.sub)).await {
+        return Err(ApiError::forbidden("没有创建凭证的权限"));
+    }
+    let integration = state.integrations
+        .get(&request.integration)
+        .await
+        .ok_or_else(|| field_error("integration", "集成不存在"))?;
+
+    let data = state.manager
+        .encrypt(&plaintext(&request.data))
+        .map_err(|e| ApiError::from_error("加密凭证失败", &e))?;
+    let now = Utc::now();
+    let credential = StoredCredential {
+        id: Uuid::new_v4(),
+        owner: claims.sub,
+        name: request.name.trim().to_string(),
+        integration: request.integration,
+        auth_type: integration.info().auth_type,
+        schema_version: 1,
+        data,
+        status: CredentialStatus::Active,
+        created_at: now,
+        updated_at: now,
+        rotated_at: None,
+        expires_at: request.expires_at,
+        token_expires_at: None,
+    };
+    state.credentials
+        .save(credential.clone())
+        .await
+        .map_err(|e| ApiError::from_error("保存凭证失败", &e))?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "success": true,
+            "credential": masked(credential)
+        })),
+    ))
+}
+
+/// 查询凭证详情（不含凭证内容）
+pub async fn get_credential(
+    State(state): State<CredentialServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(credential_id): Path<Uuid>,
+) -> ApiResult {
+    let stored = state.authorized(&claims, ActionType2::Read, credential_id).await?;
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "credential": masked(stored)
+        })),
+    ))
+}
+
+/// 修改凭证：可重命名；给出新的凭证内容时替换密钥并重新激活凭证
+pub async fn update_credential(
+    State(state): State<CredentialServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(credential_id): Path<Uuid>,
+    ValidJson(request): ValidJson<UpdateCredentialRequest>,
+) -> ApiResult {
+    let mut stored = state.authorized(&claims, ActionType2::Update, credential_id).await?;
+    if let Some(data) = &request.data {
+        stored = state.credentials
+            .rotate(credential_id, &plaintext(data), request.expires_at, &state.manager)
+            .await
+            .map_err(|e| ApiError::from_error("更新凭证失败", &e))?;
+    }
+    if let Some(name) = &request.name {
+        stored.name = name.trim().to_string();
+        stored.updated_at = Utc::now();
+        state.credentials
+            .save(stored.clone())
+            .await
+            .map_err(|e| ApiError::from_error("更新凭证失败", &e))?;
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "credential": masked(stored)
+        })),
+    ))
+}
+
+/// 删除凭证
+pub async fn delete_credential(
+    State(state): State<CredentialServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(credential_id): Path<Uuid>,
+) -> ApiResult {
+    state.authorized(&claims, ActionType2::Delete, credential_id).await?;
+    state.credentials
+        .delete(credential_id)
+        .await
+        .map_err(|e| ApiError::from_error("删除凭证失败", &e))?;
+    Ok((StatusCode::OK, Json(serde_json::json!({ "success": true }))))
+}
+
+/// 测试凭证连接：解密凭证（记入审计日志）并交由集成校验，返回凭证是否有效
+pub async fn test_credential(
+    State(state): State<CredentialServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(credential_id): Path<Uuid>,
+) -> ApiResult {
+    let stored = state.authorized(&claims, ActionType2::Execute, credential_id).await?;
+    let integration = state.integrations
+        .get(&stored.integration)
+        .await
+        .ok_or_else(|| ApiError::not_found("凭证所属的集成不存在"))?;
+    let used_by = CredentialUse::ConnectionTest { user_id: claims.sub };
+    let plaintext = state.credentials
+        .decrypt(credential_id, used_by, &state.manager)
+        .await
+        .map_err(|e| ApiError::from_error("解密凭证失败", &e))?;
+    let valid = integration
+        .validate_credentials(&plaintext)
+        .await
+        .map_err(|e| ApiError::from_error("测试凭证连接失败", &e))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "valid": valid
+        })),
+    ))
+}
+
+pub(crate) fn field_error(field: &str, message: impl Into<String>) -> ApiError {
+    ApiError::validation(vec![FieldError { field: field.to_string(), message: message.into() }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, claims};
+    use async_trait::async_trait;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use common::types::Role;
+    use integration_service::integrations::{
+        ActionDefinition, AuthType, Integration, IntegrationCategory, IntegrationError, IntegrationInfo,
+    };
+
+    /// Accepts the token "valid"
+    #[derive(Clone)]
+    struct TokenIntegration;
+
+    #[async_trait]
+    impl Integration for TokenIntegration {
+        fn info(&self) -> IntegrationInfo {
+            IntegrationInfo {
+                name: "token".to_string(),
+                display_name: "Token".to_string(),
+                description: "Token check".to_string(),
+                category: IntegrationCategory::Other,
+                auth_type: AuthType::ApiKey,
+                icon_url: None,
+                tags: vec![],
+            }
+        }
+
+        async fn execute(&self, action: &str, _params: JsonValue, _credentials: &str) -> Result<JsonValue, IntegrationError> {
+            Err(IntegrationError::ActionNotFound(action.to_string()))
+        }
+
+        async fn validate_credentials(&self, credentials: &str) -> Result<bool, IntegrationError> {
+            Ok(credentials == "valid")
+        }
+
+        fn actions(&self) -> Vec<ActionDefinition> {
+            vec![]
+        }
+
+        fn clone_box(&self) -> Box<dyn Integration> {
+            Box::new(self.clone())
+        }
+    }
+
+    async fn state() -> CredentialServiceState {
+        let integrations = IntegrationRegistry::new();
+        integrations.register("token".to_string(), Box::new(TokenIntegration)).await;
+        CredentialServiceState::new(
+            Arc::new(CredentialStore::new()),
+            Arc::new(CredentialManager::new(&[7u8; 32])),
+            Arc::new(integrations),
+        )
+    }
+
+    fn app(state: CredentialServiceState, user: Uuid, role: Role) -> Router {
+        Router::new()
+            .route("/credentials", get(list_credentials).post(create_credential))
+            .route("/credentials/:id", get(get_credential).put(update_credential).delete(delete_credential))
+            .route("/credentials/:id/test", post(test_credential))
+            .layer(Extension(claims(user, role)))
+            .with_state(state)
+    }
+
+    #[tokio::test]
+    async fn test_credential_crud_and_connection_test() {
+        let state = state().await;
+        let alice = Uuid::new_v4();
+
+        let create = serde_json::json!({ "name": "Deploy token", "integration": "token", "data": "invalid" });
+        let (status, body) = call(app(state.clone(), alice, Role::User), "POST", "/credentials", Some(create)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["credential"]["data"], MASKED_DATA);
+        assert_eq!(body["credential"]["owner"], alice.to_string());
+        let id = body["credential"]["id"].as_str().unwrap().to_string();
+        let uri = format!("/credentials/{}", id);
+
+        let stored = state.credentials.get(id.parse().unwrap()).await.unwrap();
+        assert_eq!(state.manager.decrypt(&stored.data).unwrap(), "invalid");
+
+        let (status, body) = call(app(state.clone(), alice, Role::User), "POST", &format!("{}/test", uri), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["valid"], false);
+
+        let update = serde_json::json!({ "name": "CI token", "data": "valid" });
+        let (status, body) = call(app(state.clone(), alice, Role::User), "PUT", &uri, Some(update)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["credential"]["name"], "CI token");
+        assert!(body["credential"]["rotated_at"].is_string());
+
+        let (_, body) = call(app(state.clone(), alice, Role::User), "POST", &format!("{}/test", uri), None).await;
+        assert_eq!(body["valid"], true);
+
+        let (_, body) = call(app(state.clone(), alice, Role::User), "GET", "/credentials?integration=token", None).await;
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["credentials"][0]["data"], MASKED_DATA);
+
+        let (status, _) = call(app(state.clone(), alice, Role::User), "DELETE", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(app(state.clone(), alice, Role::User), "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_credentials_are_scoped_by_owner() {
+        let state = state().await;
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let create = serde_json::json!({ "name": "Token", "integration": "token", "data": { "api_key": "k" } });
+        let (_, body) = call(app(state.clone(), alice, Role::User), "POST", "/credentials", Some(create)).await;
+        let uri = format!("/credentials/{}", body["credential"]["id"].as_str().unwrap());
+
+        // Others neither see nor use the credential; admins do
+        let (_, body) = call(app(state.clone(), bob, Role::User), "GET", "/credentials", None).await;
+        assert_eq!(body["total"], 0);
+        for (method, uri) in [("GET", uri.clone()), ("DELETE", uri.clone()), ("POST", format!("{}/test", uri))] {
+            let (status, _) = call(app(state.clone(), bob, Role::User), method, &uri, None).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        }
+        let (_, body) = call(app(state.clone(), bob, Role::Admin), "GET", "/credentials", None).await;
+        assert_eq!(body["total"], 1);
+
+        // Viewers may not create credentials
+        let create = serde_json::json!({ "name": "Token", "integration": "token", "data": "k" });
+        let (status, _) = call(app(state.clone(), bob, Role::Viewer), "POST", "/credentials", Some(create)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_create_credential_validation() {
+        let state = state().await;
+        let alice = Uuid::new_v4();
+
+        let unknown = serde_json::json!({ "name": "Token", "integration": "missing", "data": "k" });
+        let (status, body) = call(app(state.clone(), alice, Role::User), "POST", "/credentials", Some(unknown)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["field_errors"][0]["field"], "integration");
+
+        let empty = serde_json::json!({ "name": " ", "integration": "token", "data": {} });
+        let (status, _) = call(app(state.clone(), alice, Role::User), "POST", "/credentials", Some(empty)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(state.credentials.list().await.is_empty());
+    }
+}
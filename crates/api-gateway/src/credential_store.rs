@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use integration_service::credentials::{CredentialError, CredentialStatus, CredentialStorage, StoredCredential};
+use integration_service::integrations::AuthType;
+use serde_json::Value as JsonValue;
+use sqlx::types::Json;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+/// Postgres-backed credential storage (see `migrations/013_credentials.sql`);
+/// credentials are written as the `CredentialStore` holds them, encrypted
+pub struct PgCredentialStorage {
+    pool: PgPool,
+}
+
+impl PgCredentialStorage {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl CredentialStorage for PgCredentialStorage {
+    async fn save(&self, credential: &StoredCredential) -> Result<(), CredentialError> {
+        let auth_type = serde_json::to_value(&credential.auth_type).map_err(|e| CredentialError::Storage(e.to_string()))?;
+        sqlx::query(
+            r#"
+            INSERT INTO credentials (
+                id, owner, name, integration, auth_type, schema_version, data, status,
+                created_at, updated_at, rotated_at, expires_at, token_expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            ON CONFLICT (id) DO UPDATE
+            SET name = $3, auth_type = $5, schema_version = $6, data = $7, status = $8,
+                updated_at = $10, rotated_at = $11, expires_at = $12, token_expires_at = $13
+            "#,
+        )
+        .bind(credential.id)
+        .bind(credential.owner)
+        .bind(&credential.name)
+        .bind(&credential.integration)
+        .bind(auth_type.as_str())
+        .bind(credential.schema_version as i32)
+        .bind(&credential.data)
+        .bind(Json(&credential.status))
+        .bind(credential.created_at)
+        .bind(credential.updated_at)
+        .bind(credential.rotated_at)
+        .bind(credential.expires_at)
+        .bind(credential.token_expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), CredentialError> {
+        sqlx::query("DELETE FROM credentials WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn load(&self) -> Result<Vec<StoredCredential>, CredentialError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, owner, name, integration, auth_type, schema_version, data, status,
+                   created_at, updated_at, rotated_at, expires_at, token_expires_at
+            FROM credentials
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        rows.iter().map(decode).collect()
+    }
+}
+
+fn decode(row: &sqlx::postgres::PgRow) -> Result<StoredCredential, CredentialError> {
+    let auth_type: String = row.try_get("auth_type").map_err(storage_error)?;
+    let auth_type: AuthType = serde_json::from_value(JsonValue::String(auth_type))
+        .map_err(|e| CredentialError::Storage(e.to_string()))?;
+    let schema_version: i32 = row.try_get("schema_version").map_err(storage_error)?;
+    let Json(status) = row.try_get::<Json<CredentialStatus>, _>("status").map_err(storage_error)?;
+    Ok(StoredCredential {
+        id: row.try_get("id").map_err(storage_error)?,
+        owner: row.try_get("owner").map_err(storage_error)?,
+        name: row.try_get("name").map_err(storage_error)?,
+        integration: row.try_get("integration").map_err(storage_error)?,
+        auth_type,
+        schema_version: schema_version as u32,
+        data: row.try_get("data").map_err(storage_error)?,
+        status,
+        created_at: row.try_get("created_at").map_err(storage_error)?,
+        updated_at: row.try_get("updated_at").map_err(storage_error)?,
+        rotated_at: row.try_get("rotated_at").map_err(storage_error)?,
+        expires_at: row.try_get("expires_at").map_err(storage_error)?,
+        token_expires_at: row.try_get("token_expires_at").map_err(storage_error)?,
+    })
+}
+
+fn storage_error(e: sqlx::Error) -> CredentialError {
+    CredentialError::Storage(e.to_string())
+}
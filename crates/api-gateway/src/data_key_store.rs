@@ -0,0 +1,84 @@
+use async_trait::async_trait;
+use common::error::WorkflowError;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+use workflow_engine::encryption::{DataKeyStore, WrappedDataKey};
+
+/// Postgres-backed store of wrapped organization data keys (see
+/// `migrations/006_data_keys.sql`)
+pub struct PgDataKeyStore {
+    pool: PgPool,
+}
+
+impl PgDataKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl DataKeyStore for PgDataKeyStore {
+    async fn get(&self, organization_id: Uuid) -> Result<Option<WrappedDataKey>, WorkflowError> {
+        let row = sqlx::query(
+            r#"
+            SELECT organization_id, master_key_id, wrapped_key, created_at, rotated_at
+            FROM organization_data_keys
+            WHERE organization_id = $1
+            "#,
+        )
+        .bind(organization_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        row.map(|row| decode(&row)).transpose()
+    }
+
+    async fn save(&self, key: &WrappedDataKey) -> Result<(), WorkflowError> {
+        sqlx::query(
+            r#"
+            INSERT INTO organization_data_keys (
+                organization_id, master_key_id, wrapped_key, created_at, rotated_at
+            )
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (organization_id) DO UPDATE
+            SET master_key_id = $2, wrapped_key = $3, rotated_at = $5
+            "#,
+        )
+        .bind(key.organization_id)
+        .bind(key.master_key_id as i32)
+        .bind(&key.wrapped)
+        .bind(key.created_at)
+        .bind(key.rotated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<WrappedDataKey>, WorkflowError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT organization_id, master_key_id, wrapped_key, created_at, rotated_at
+            FROM organization_data_keys
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        rows.iter().map(decode).collect()
+    }
+}
+
+fn decode(row: &sqlx::postgres::PgRow) -> Result<WrappedDataKey, WorkflowError> {
+    Ok(WrappedDataKey {
+        organization_id: row.try_get("organization_id").map_err(storage_error)?,
+        master_key_id: row.try_get::<i32, _>("master_key_id").map_err(storage_error)? as u32,
+        wrapped: row.try_get("wrapped_key").map_err(storage_error)?,
+        created_at: row.try_get("created_at").map_err(storage_error)?,
+        rotated_at: row.try_get("rotated_at").map_err(storage_error)?,
+    })
+}
+
+fn storage_error(e: sqlx::Error) -> WorkflowError {
+    WorkflowError::Storage(e.to_string())
+}
@@ -0,0 +1,271 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use common::types::ActionType2;
+use rbac_service::jwt::JwtClaims;
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::dead_letter::{DeadLetter, DeadLetterQueue, DeadLetterStatus};
+
+use crate::errors::error_envelope;
+use crate::workflow_access::WorkflowAccess;
+
+/// Dead letter service state
+#[derive(Clone)]
+pub struct DeadLetterServiceState {
+    pub dead_letters: Arc<DeadLetterQueue>,
+    /// Authorizes callers on the workflows of the dead letters
+    access: WorkflowAccess,
+}
+
+impl DeadLetterServiceState {
+    pub fn new(dead_letters: Arc<DeadLetterQueue>) -> Self {
+        Self { dead_letters, access: WorkflowAccess::default() }
+    }
+
+    /// Authorize callers against the saved workflows and organizations of `access`
+    pub fn with_access(mut self, access: WorkflowAccess) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Whether the caller may perform `action` on a dead letter: on its
+    /// workflow, and for letters of an organization as one of its members,
+    /// unless they may do so on everyone's workflows
+    async fn can(&self, claims: &JwtClaims, action: ActionType2, letter: &DeadLetter) -> bool {
+        let owner_id = self.access.owner(letter.workflow_id).await;
+        if !self.access.can(claims, action.clone(), owner_id).await {
+            return false;
+        }
+        letter.organization_id.is_none()
+            || self.access.can(claims, action, None).await
+            || self.access.is_member(claims, letter.organization_id, owner_id).await
+    }
+
+    /// A dead letter the caller may perform `action` on; letters they may
+    /// not even read are not revealed
+    async fn authorized(
+        &self,
+        claims: &JwtClaims,
+        action: ActionType2,
+        id: Uuid,
+        denied: &str,
+    ) -> Result<DeadLetter, (StatusCode, Json<serde_json::Value>)> {
+        let letter = match self.dead_letters.get(id).await {
+            Ok(Some(letter)) if self.can(claims, ActionType2::Read, &letter).await => letter,
+            Ok(_) => return Err(error_response(StatusCode::NOT_FOUND, "死信不存在".to_string())),
+            Err(e) => return Err(error_envelope("读取死信失败", &e)),
+        };
+        if !self.can(claims, action, &letter).await {
+            return Err(error_response(StatusCode::FORBIDDEN, denied.to_string()));
+        }
+        Ok(letter)
+    }
+}
+
+/// Dead letter list filter
+#[derive(Debug, Default, Deserialize)]
+pub struct DeadLetterQuery {
+    pub workflow_id: Option<Uuid>,
+    pub status: Option<DeadLetterStatus>,
+}
+
+/// Requeue request
+#[derive(Debug, Default, Deserialize)]
+pub struct RequeueRequest {
+    /// Continue from the failed node instead of rerunning the whole workflow
+    #[serde(default)]
+    pub resume_from_failure: bool,
+}
+
+/// 列出调用者可查看的失败执行（死信），可按工作流和状态过滤
+pub async fn list_dead_letters(
+    State(state): State<DeadLetterServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Query(query): Query<DeadLetterQuery>,
+) -> impl IntoResponse {
+    let letters = match state.dead_letters.list(query.workflow_id, query.status).await {
+        Ok(letters) => letters,
+        Err(e) => return error_envelope("读取死信失败", &e),
+    };
+    let mut visible = Vec::with_capacity(letters.len());
+    for letter in letters {
+        if state.can(&claims, ActionType2::Read, &letter).await {
+            visible.push(letter);
+        }
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "total": visible.len(),
+            "dead_letters": visible
+        })),
+    )
+}
+
+/// 查询死信详情：失败节点、错误、失败时的变量和历次重跑
+pub async fn get_dead_letter(
+    State(state): State<DeadLetterServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state.authorized(&claims, ActionType2::Read, id, "没有查看死信的权限").await {
+        Ok(letter) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "dead_letter": letter
+            })),
+        ),
+        Err(response) => response,
+    }
+}
+
+/// 重新执行死信：从头重跑或从失败节点继续，返回重跑的执行 ID
+pub async fn requeue_dead_letter(
+    State(state): State<DeadLetterServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(id): Path<Uuid>,
+    request: Option<Json<RequeueRequest>>,
+) -> impl IntoResponse {
+    if let Err(response) = state.authorized(&claims, ActionType2::Execute, id, "没有重新执行工作流的权限").await {
+        return response;
+    }
+    let Json(request) = request.unwrap_or_default();
+
+    match state.dead_letters.requeue(id, request.resume_from_failure).await {
+        Ok(Some(execution_id)) => (
+            StatusCode::ACCEPTED,
+            Json(serde_json::json!({
+                "success": true,
+                "execution_id": execution_id
+            })),
+        ),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "死信不存在".to_string()),
+        Err(e) => error_envelope("重新执行死信失败", &e),
+    }
+}
+
+/// 丢弃死信，不再重新执行
+pub async fn discard_dead_letter(
+    State(state): State<DeadLetterServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    if let Err(response) = state.authorized(&claims, ActionType2::Delete, id, "没有丢弃死信的权限").await {
+        return response;
+    }
+
+    match state.dead_letters.discard(id).await {
+        Ok(true) => (StatusCode::OK, Json(serde_json::json!({ "success": true }))),
+        Ok(false) => error_response(StatusCode::NOT_FOUND, "死信不存在".to_string()),
+        Err(e) => error_envelope("丢弃死信失败", &e),
+    }
+}
+
+fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, claims};
+    use axum::routing::{get, post};
+    use axum::Router;
+    use common::types::{Role, Workflow, WORKFLOW_SCHEMA_VERSION};
+    use rbac_service::org::MemberRole;
+    use rbac_service::OrgService;
+    use std::collections::HashMap;
+    use workflow_engine::dead_letter::{record_failure, DeadLetterStore, FailedExecution, InMemoryDeadLetterStore};
+    use workflow_engine::workflows::{InMemoryWorkflowStore, StoredWorkflow, WorkflowStore};
+    use workflow_engine::WorkflowExecutor;
+
+    fn app(state: DeadLetterServiceState, user: Uuid, role: Role) -> Router {
+        Router::new()
+            .route("/dead-letters", get(list_dead_letters))
+            .route("/dead-letters/:id", get(get_dead_letter).delete(discard_dead_letter))
+            .route("/dead-letters/:id/requeue", post(requeue_dead_letter))
+            .layer(Extension(claims(user, role)))
+            .with_state(state)
+    }
+
+    #[tokio::test]
+    async fn test_list_requeue_and_discard() {
+        let organizations = Arc::new(OrgService::new());
+        let (owner, viewer, stranger) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let acme = organizations.create_organization("Acme".to_string(), owner).await;
+        let team = organizations.create_team(acme.id, "Ops".to_string(), owner).await.unwrap();
+        organizations.add_member(acme.id, viewer, MemberRole::Member).await.unwrap();
+        organizations.add_team_member(team.id, owner).await.unwrap();
+        organizations.add_team_member(team.id, viewer).await.unwrap();
+
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Empty".to_string(),
+            description: None,
+            nodes: vec![],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+        let workflows = Arc::new(InMemoryWorkflowStore::new());
+        workflows.save(&StoredWorkflow { workflow: workflow.clone(), owner_id: owner, active: true }).await.unwrap();
+        let store: Arc<dyn DeadLetterStore> = Arc::new(InMemoryDeadLetterStore::new());
+        let executor = Arc::new(WorkflowExecutor::new().with_dead_letters(store.clone()));
+        let state = DeadLetterServiceState::new(Arc::new(DeadLetterQueue::new(store.clone(), executor)))
+            .with_access(WorkflowAccess::new(workflows).with_org_service(organizations));
+        let letter = record_failure(store.as_ref(), FailedExecution {
+            execution_id: Uuid::new_v4(),
+            workflow: workflow.clone(),
+            organization_id: Some(acme.id),
+            failed_node: None,
+            error: "Upstream timed out".to_string(),
+            input: HashMap::new(),
+            variables: HashMap::new(),
+        }).await.unwrap();
+
+        let list_uri = format!("/dead-letters?workflow_id={}&status=pending", workflow.id);
+        let (status, body) = call(app(state.clone(), viewer, Role::Viewer), "GET", &list_uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["dead_letters"][0]["error"], "Upstream timed out");
+
+        // Dead letters of other users' workflows are not revealed
+        let uri = format!("/dead-letters/{}", letter.id);
+        let (status, body) = call(app(state.clone(), stranger, Role::User), "GET", "/dead-letters", None).await;
+        assert_eq!((status, body["total"].clone()), (StatusCode::OK, serde_json::json!(0)));
+        let (status, _) = call(app(state.clone(), stranger, Role::User), "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = call(app(state.clone(), stranger, Role::User), "DELETE", &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let requeue_uri = format!("/dead-letters/{}/requeue", letter.id);
+        let (status, _) = call(app(state.clone(), viewer, Role::Viewer), "POST", &requeue_uri, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = call(app(state.clone(), owner, Role::User), "POST", &requeue_uri, None).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert!(body["execution_id"].is_string());
+        let (status, body) = call(app(state.clone(), owner, Role::User), "POST", &requeue_uri, None).await;
+        assert_eq!((status, body["code"].clone()), (StatusCode::CONFLICT, serde_json::json!("CONFLICT")));
+
+        let (status, _) = call(app(state.clone(), owner, Role::User), "DELETE", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(app(state, owner, Role::User), "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use common::error::WorkflowError;
+use sqlx::types::Json;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+use workflow_engine::dead_letter::{DeadLetter, DeadLetterStatus, DeadLetterStore};
+
+/// Postgres-backed dead letter store (see `migrations/005_dead_letters.sql`)
+pub struct PgDeadLetterStore {
+    pool: PgPool,
+}
+
+impl PgDeadLetterStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl DeadLetterStore for PgDeadLetterStore {
+    async fn save(&self, letter: &DeadLetter) -> Result<(), WorkflowError> {
+        sqlx::query(
+            r#"
+            INSERT INTO dead_letters (id, workflow_id, status, letter, last_failed_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (id) DO UPDATE SET status = $3, letter = $4, last_failed_at = $5
+            "#,
+        )
+        .bind(letter.id)
+        .bind(letter.workflow_id)
+        .bind(status_name(letter.status))
+        .bind(Json(letter))
+        .bind(letter.last_failed_at)
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Option<DeadLetter>, WorkflowError> {
+        let row = sqlx::query("SELECT letter FROM dead_letters WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        row.map(|row| decode(&row)).transpose()
+    }
+
+    async fn list(
+        &self,
+        workflow_id: Option<Uuid>,
+        status: Option<DeadLetterStatus>,
+    ) -> Result<Vec<DeadLetter>, WorkflowError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT letter
+            FROM dead_letters
+            WHERE ($1::uuid IS NULL OR workflow_id = $1)
+              AND ($2::text IS NULL OR status = $2)
+            ORDER BY last_failed_at DESC
+            "#,
+        )
+        .bind(workflow_id)
+        .bind(status.map(status_name))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        rows.iter().map(decode).collect()
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, WorkflowError> {
+        let deleted = sqlx::query("DELETE FROM dead_letters WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(deleted.rows_affected() > 0)
+    }
+}
+
+fn status_name(status: DeadLetterStatus) -> &'static str {
+    match status {
+        DeadLetterStatus::Pending => "pending",
+        DeadLetterStatus::Requeued => "requeued",
+        DeadLetterStatus::Resolved => "resolved",
+    }
+}
+
+fn decode(row: &sqlx::postgres::PgRow) -> Result<DeadLetter, WorkflowError> {
+    let Json(letter) = row.try_get::<Json<DeadLetter>, _>("letter").map_err(storage_error)?;
+    Ok(letter)
+}
+
+fn storage_error(e: sqlx::Error) -> WorkflowError {
+    WorkflowError::Storage(e.to_string())
+}
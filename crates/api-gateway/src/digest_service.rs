@@ -0,0 +1,260 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::Utc;
+use common::types::Role;
+use rbac_service::jwt::JwtClaims;
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::digest::{DigestError, DigestScope, DigestService, DigestSettings, DigestSubscription};
+
+use crate::errors::error_envelope;
+
+/// Digest subscription service state
+#[derive(Clone)]
+pub struct DigestServiceState {
+    pub digests: Arc<DigestService>,
+}
+
+impl DigestServiceState {
+    pub fn new(digests: Arc<DigestService>) -> Self {
+        Self { digests }
+    }
+}
+
+/// 列出当前用户的摘要订阅
+pub async fn list_digests(
+    State(state): State<DigestServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+) -> impl IntoResponse {
+    let subscriptions = state.digests.list_for_user(claims.sub).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "total": subscriptions.len(),
+            "subscriptions": subscriptions
+        })),
+    )
+}
+
+/// 订阅工作流健康摘要：按计划通过邮件或 Slack 发送运行次数、失败、资源消耗、
+/// 常见错误和即将过期的凭证；组织范围的摘要仅限管理员和经理
+pub async fn create_digest(
+    State(state): State<DigestServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Json(settings): Json<DigestSettings>,
+) -> impl IntoResponse {
+    if let Err(response) = check_scope(&claims, &settings) {
+        return response;
+    }
+
+    match state.digests.subscribe(claims.sub, settings).await {
+        Ok(subscription) => (
+            StatusCode::CREATED,
+            Json(serde_json::json!({
+                "success": true,
+                "subscription": subscription
+            })),
+        ),
+        Err(e) => digest_error(e),
+    }
+}
+
+/// 修改摘要订阅（仅订阅者本人）
+pub async fn update_digest(
+    State(state): State<DigestServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(digest_id): Path<Uuid>,
+    Json(settings): Json<DigestSettings>,
+) -> impl IntoResponse {
+    if let Err(response) = owned_subscription(&state, &claims, digest_id).await {
+        return response;
+    }
+    if let Err(response) = check_scope(&claims, &settings) {
+        return response;
+    }
+
+    match state.digests.update(digest_id, settings).await {
+        Ok(subscription) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "subscription": subscription
+            })),
+        ),
+        Err(e) => digest_error(e),
+    }
+}
+
+/// 取消摘要订阅（仅订阅者本人）
+pub async fn delete_digest(
+    State(state): State<DigestServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(digest_id): Path<Uuid>,
+) -> impl IntoResponse {
+    if let Err(response) = owned_subscription(&state, &claims, digest_id).await {
+        return response;
+    }
+
+    state.digests.unsubscribe(digest_id).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "message": "摘要订阅已取消"
+        })),
+    )
+}
+
+/// 预览下一期摘要的内容（不发送）
+pub async fn preview_digest(
+    State(state): State<DigestServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(digest_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let subscription = match owned_subscription(&state, &claims, digest_id).await {
+        Ok(subscription) => subscription,
+        Err(response) => return response,
+    };
+
+    let digest = state.digests.build(&subscription, Utc::now()).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "next_due": subscription.next_due(),
+            "subject": digest.subject(),
+            "text": digest.to_text(),
+            "digest": digest
+        })),
+    )
+}
+
+fn check_scope(claims: &JwtClaims, settings: &DigestSettings) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    if matches!(settings.scope, DigestScope::Organization { .. }) && !matches!(claims.role, Role::Admin | Role::Manager) {
+        return Err(error_response(StatusCode::FORBIDDEN, "没有订阅组织摘要的权限".to_string()));
+    }
+    Ok(())
+}
+
+async fn owned_subscription(
+    state: &DigestServiceState,
+    claims: &JwtClaims,
+    digest_id: Uuid,
+) -> Result<DigestSubscription, (StatusCode, Json<serde_json::Value>)> {
+    match state.digests.get(digest_id).await {
+        Some(subscription) if subscription.user_id == claims.sub => Ok(subscription),
+        _ => Err(error_response(StatusCode::NOT_FOUND, "摘要订阅不存在".to_string())),
+    }
+}
+
+fn digest_error(e: DigestError) -> (StatusCode, Json<serde_json::Value>) {
+    let context = match e {
+        DigestError::InvalidSchedule(_) => "发送计划无效",
+        DigestError::InvalidChannel(_) => "发送渠道无效",
+        DigestError::NotFound(_) => "摘要订阅不存在",
+        DigestError::Delivery(_) => "摘要发送失败",
+    };
+    error_envelope(context, &e)
+}
+
+fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, claims};
+    use async_trait::async_trait;
+    use axum::routing::get;
+    use axum::Router;
+    use integration_service::CredentialStore;
+    use workflow_engine::digest::{Digest, DigestChannel, DigestSender};
+    use workflow_engine::{ExecutionHistory, RevisionStore};
+
+    struct NoopSender;
+
+    #[async_trait]
+    impl DigestSender for NoopSender {
+        async fn send(&self, _channel: &DigestChannel, _digest: &Digest) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    fn app(state: DigestServiceState, user: Uuid, role: Role) -> Router {
+        Router::new()
+            .route("/digests", get(list_digests).post(create_digest))
+            .route("/digests/:digest_id", axum::routing::put(update_digest).delete(delete_digest))
+            .route("/digests/:digest_id/preview", get(preview_digest))
+            .layer(Extension(claims(user, role)))
+            .with_state(state)
+    }
+
+    #[tokio::test]
+    async fn test_digest_subscriptions() {
+        let state = DigestServiceState::new(Arc::new(DigestService::new(
+            Arc::new(ExecutionHistory::default()),
+            Arc::new(RevisionStore::new()),
+            Arc::new(CredentialStore::new()),
+            Arc::new(NoopSender),
+        )));
+        let (user, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let settings = |scope: serde_json::Value| serde_json::json!({
+            "scope": scope,
+            "schedule": "0 9 * * 1-5",
+            "timezone": "Asia/Shanghai",
+            "channels": [{ "type": "email", "to": "ops@example.com" }]
+        });
+
+        let organization = serde_json::json!({ "type": "organization", "organization_id": Uuid::new_v4() });
+        let (status, _) = call(app(state.clone(), user, Role::User), "POST", "/digests", Some(settings(organization.clone()))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = call(app(state.clone(), user, Role::Manager), "POST", "/digests", Some(settings(organization))).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let mut invalid = settings(serde_json::json!({ "type": "user" }));
+        invalid["channels"] = serde_json::json!([{ "type": "slack", "webhook_url": "http://hooks.example.com" }]);
+        let (status, _) = call(app(state.clone(), user, Role::User), "POST", "/digests", Some(invalid)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = call(app(state.clone(), user, Role::User), "POST", "/digests", Some(settings(serde_json::json!({ "type": "user" })))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["subscription"]["timezone"], "Asia/Shanghai");
+        let uri = format!("/digests/{}", body["subscription"]["id"].as_str().unwrap());
+
+        let (status, body) = call(app(state.clone(), user, Role::User), "GET", "/digests", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], 2);
+
+        let (status, _) = call(app(state.clone(), other, Role::Admin), "GET", &format!("{}/preview", uri), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, body) = call(app(state.clone(), user, Role::User), "GET", &format!("{}/preview", uri), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["digest"]["runs"], 0);
+        assert_eq!(body["subject"], "Workflow digest: 0 runs, no failures");
+
+        let mut disabled = settings(serde_json::json!({ "type": "user" }));
+        disabled["enabled"] = serde_json::json!(false);
+        let (status, body) = call(app(state.clone(), user, Role::User), "PUT", &uri, Some(disabled)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["subscription"]["enabled"], false);
+
+        let (status, _) = call(app(state.clone(), other, Role::User), "DELETE", &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = call(app(state.clone(), user, Role::User), "DELETE", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = call(app(state, user, Role::User), "GET", "/digests", None).await;
+        assert_eq!(body["total"], 1);
+    }
+}
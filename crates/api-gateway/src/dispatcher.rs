@@ -0,0 +1,467 @@
+use async_trait::async_trait;
+use common::error::{GatewayError, PlatformError};
+use common::trace::{TraceContext, TRACEPARENT};
+use common::types::{ApiRequest, ApiResponse, HttpMethod};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, Mutex};
+use uuid::Uuid;
+use workflow_engine::HttpDispatcher;
+
+use crate::cache::ResponseCache;
+use crate::failover::FailoverManager;
+use crate::load_balancer::LoadBalancer;
+use crate::logger::ApiLogger;
+use crate::metrics::MetricsCollector;
+use crate::pool::RequestPool;
+use crate::proxy::ApiProxy;
+use crate::rate_limiter::RateLimiter;
+
+type Waiters = HashMap<Uuid, oneshot::Sender<Result<ApiResponse, GatewayError>>>;
+
+/// Dispatches workflow HTTP actions through the gateway.
+///
+/// Requests are queued in the [`RequestPool`] so that higher-priority
+/// requests go first and concurrency stays bounded, wait for their
+/// provider's [`RateLimiter`] capacity, which organizations share fairly,
+/// and are retried according to their
+/// `retry_config` on transport errors, 429 and 5xx responses.
+///
+/// Optionally, requests of providers with API keys are sent with a key
+/// picked by the [`LoadBalancer`], providers failing a request are failed
+/// over through the [`FailoverManager`], every provider call is counted in
+/// the [`MetricsCollector`] and logged by the [`ApiLogger`], and successful
+/// GET responses of providers with a cache TTL are answered from the
+/// [`ResponseCache`] before going upstream.
+#[derive(Clone)]
+pub struct GatewayDispatcher {
+    pool: Arc<RequestPool>,
+    rate_limiter: Arc<RateLimiter>,
+    proxy: Arc<ApiProxy>,
+    waiters: Arc<Mutex<Waiters>>,
+    load_balancer: Option<Arc<LoadBalancer>>,
+    failover: Option<Arc<FailoverManager>>,
+    metrics: Option<Arc<MetricsCollector>>,
+    logger: Option<Arc<ApiLogger>>,
+    cache: Option<Arc<ResponseCache>>,
+}
+
+impl GatewayDispatcher {
+    pub fn new(pool: Arc<RequestPool>, rate_limiter: Arc<RateLimiter>) -> Self {
+        Self {
+            pool,
+            rate_limiter,
+            proxy: Arc::new(ApiProxy::new()),
+            waiters: Arc::new(Mutex::new(HashMap::new())),
+            load_balancer: None,
+            failover: None,
+            metrics: None,
+            logger: None,
+            cache: None,
+        }
+    }
+
+    /// Send requests of providers with configured keys with one of them
+    pub fn with_load_balancer(mut self, load_balancer: Arc<LoadBalancer>) -> Self {
+        self.load_balancer = Some(load_balancer);
+        self
+    }
+
+    /// Skip unhealthy providers and retry failed requests on the failover
+    /// providers of registered ones
+    pub fn with_failover(mut self, failover: Arc<FailoverManager>) -> Self {
+        self.failover = Some(failover);
+        self
+    }
+
+    /// Count every provider call, successful or failed
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Log every provider call and cache hit
+    pub fn with_logger(mut self, logger: Arc<ApiLogger>) -> Self {
+        self.logger = Some(logger);
+        self
+    }
+
+    /// Answer repeated GET requests from `cache` after a successful
+    /// response, for the cache TTL of their provider
+    pub fn with_cache(mut self, cache: Arc<ResponseCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn pool(&self) -> &Arc<RequestPool> {
+        &self.pool
+    }
+
+    pub fn rate_limiter(&self) -> &Arc<RateLimiter> {
+        &self.rate_limiter
+    }
+
+    /// Take a pool permit, then send the highest-priority queued request
+    /// and hand the result to whoever is waiting for it
+    async fn process_next(&self) {
+        let _permit = self.pool.acquire_permit().await;
+        let Some(request) = self.pool.dequeue().await else {
+            return;
+        };
+
+        let request_id = request.id;
+        let result = self.send_with_failover(&request).await;
+        if succeeded(&result) {
+            self.pool.mark_completed(request_id).await;
+        } else {
+            self.pool.mark_failed(request_id).await;
+        }
+
+        if let Some(waiter) = self.waiters.lock().await.remove(&request_id) {
+            let _ = waiter.send(result);
+        }
+    }
+
+    /// Providers to try a request on, in order: the request's provider if
+    /// healthy, then its healthy failover providers. Providers the failover
+    /// manager does not know are used as-is.
+    async fn providers(&self, provider: &str) -> Vec<String> {
+        let Some(failover) = &self.failover else {
+            return vec![provider.to_string()];
+        };
+        if !failover.is_registered(provider).await {
+            return vec![provider.to_string()];
+        }
+
+        let mut providers = Vec::new();
+        if failover.is_healthy(provider).await {
+            providers.push(provider.to_string());
+        }
+        providers.extend(failover.get_failover_providers(provider).await);
+        providers
+    }
+
+    async fn send_with_failover(&self, request: &ApiRequest) -> Result<ApiResponse, GatewayError> {
+        let mut result = Err(GatewayError::FailoverFailed);
+        for provider in self.providers(&request.provider).await {
+            let mut attempt = request.clone();
+            attempt.provider = provider;
+
+            let start = Instant::now();
+            result = self.send_with_retry(&attempt).await;
+            self.record(&attempt, &result, start.elapsed()).await;
+
+            let success = succeeded(&result);
+            if let Some(failover) = &self.failover {
+                if success {
+                    failover.record_success(&attempt.provider).await;
+                } else {
+                    failover.record_failure(&attempt.provider).await;
+                }
+            }
+            if success {
+                break;
+            }
+            tracing::warn!("Request {} failed on provider {}", request.id, attempt.provider);
+        }
+        result
+    }
+
+    async fn send_with_retry(&self, request: &ApiRequest) -> Result<ApiResponse, GatewayError> {
+        let max_retries = request.retry_config.max_retries;
+        let mut retry = 0;
+        loop {
+            self.rate_limiter.acquire(&request.provider, request.organization_id).await?;
+
+            let result = match self.select_key(&request.provider).await? {
+                Some(key) => self.proxy.send(request.clone(), &key).await,
+                None => self.proxy.forward(request).await,
+            };
+            let result = result.map_err(|e| match e {
+                PlatformError::ApiGateway(e) => e,
+                other => GatewayError::ProviderUnavailable(other.to_string()),
+            });
+            let retryable = match &result {
+                Ok(response) => response.status_code == 429 || response.status_code >= 500,
+                Err(_) => true,
+            };
+            if !retryable {
+                return result;
+            }
+            if retry >= max_retries {
+                return match result {
+                    Err(e) if max_retries > 0 => Err(GatewayError::RetriesExhausted(format!("{}: {}", request.id, e))),
+                    other => other,
+                };
+            }
+
+            retry += 1;
+            tracing::warn!("Retrying request {} to {} (retry {})", request.id, request.provider, retry);
+            tokio::time::sleep(request.retry_config.delay(retry)).await;
+        }
+    }
+
+    /// Key to send a request of `provider` with; `None` when the provider
+    /// has no keys and requests carry their own credentials
+    async fn select_key(&self, provider: &str) -> Result<Option<String>, GatewayError> {
+        let Some(load_balancer) = &self.load_balancer else {
+            return Ok(None);
+        };
+        if load_balancer.get_provider_keys(provider).await.is_none() {
+            return Ok(None);
+        }
+        let key = load_balancer
+            .select_key(provider)
+            .await
+            .ok_or_else(|| GatewayError::InvalidApiKey(provider.to_string()))?;
+        load_balancer.increment_usage(provider, &key.key).await;
+        Ok(Some(key.key))
+    }
+
+    /// Count and log a call to the request's provider
+    async fn record(&self, request: &ApiRequest, result: &Result<ApiResponse, GatewayError>, elapsed: Duration) {
+        let latency_ms = elapsed.as_millis() as u64;
+        if let Some(metrics) = &self.metrics {
+            if succeeded(result) {
+                metrics.record_success(&request.provider, latency_ms, 0.0).await;
+            } else {
+                metrics.record_failure(&request.provider, latency_ms).await;
+            }
+        }
+        if let Some(logger) = &self.logger {
+            let logged = match result {
+                Ok(response) => logger.log_success(request, response, false).await,
+                Err(e) => logger.log_failure(request, &e.to_string(), latency_ms).await,
+            };
+            if let Err(e) = logged {
+                tracing::warn!("Failed to log request {}: {}", request.id, e);
+            }
+        }
+    }
+
+    /// Cache and key of a cacheable request. Responses depend on the
+    /// caller's credentials, so the key covers the organization and the headers.
+    async fn cache_key(&self, request: &ApiRequest) -> Option<(&ResponseCache, String, Duration)> {
+        let cache = self.cache.as_ref()?;
+        if !matches!(request.method, HttpMethod::GET) {
+            return None;
+        }
+        let ttl = cache.ttl(&request.provider).await?;
+        let mut headers: Vec<_> = request.headers.iter().collect();
+        headers.sort();
+        let scope = serde_json::json!({ "organization_id": request.organization_id, "headers": headers });
+        let key = ResponseCache::generate_key(&request.provider, &request.endpoint, "GET", &scope.to_string());
+        Some((cache, key, ttl))
+    }
+}
+
+/// Whether a provider handled a request, including client errors
+fn succeeded(result: &Result<ApiResponse, GatewayError>) -> bool {
+    matches!(result, Ok(response) if response.status_code < 500)
+}
+
+#[async_trait]
+impl HttpDispatcher for GatewayDispatcher {
+    async fn dispatch(&self, request: ApiRequest) -> Result<ApiResponse, GatewayError> {
+        let cache_key = self.cache_key(&request).await;
+        if let Some((cache, key, _)) = &cache_key {
+            let cached = cache.get(key).await;
+            if let Some(metrics) = &self.metrics {
+                metrics.record_response_cache(&request.provider, cached.is_some()).await;
+            }
+            if let Some(cached) = cached {
+                let response = ApiResponse { request_id: request.id, latency_ms: 0, ..cached };
+                if let Some(logger) = &self.logger {
+                    if let Err(e) = logger.log_success(&request, &response, true).await {
+                        tracing::warn!("Failed to log request {}: {}", request.id, e);
+                    }
+                }
+                return Ok(response);
+            }
+        }
+
+        // Outbound requests continue the trace of the execution sending them
+        let mut request = request;
+        if let Some(trace) = TraceContext::current() {
+            if !request.headers.keys().any(|name| name.eq_ignore_ascii_case(TRACEPARENT)) {
+                request.headers.insert(TRACEPARENT.to_string(), trace.child().traceparent());
+            }
+        }
+
+        let (tx, rx) = oneshot::channel();
+        self.waiters.lock().await.insert(request.id, tx);
+        self.pool.enqueue(request).await;
+
+        // Processing runs detached so a cancelled caller cannot strand
+        // the request it happened to dequeue
+        let this = self.clone();
+        tokio::spawn(async move { this.process_next().await });
+
+        let response = rx.await
+            .map_err(|_| GatewayError::ProviderUnavailable("request dropped by the gateway".to_string()))??;
+
+        if let Some((cache, key, ttl)) = cache_key {
+            if (200..300).contains(&response.status_code) {
+                cache.set(key, response.clone(), ttl).await;
+            }
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use axum::http::HeaderMap;
+    use axum::routing::get;
+    use common::types::{ApiKeyConfig, LoadBalanceStrategy, Priority, ProviderConfig, RateLimitConfig, RetryConfig};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Local upstream that fails the first `failures` calls with 503
+    async fn upstream(failures: usize) -> (String, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let app = Router::new().route(
+            "/echo",
+            post(move |Json(body): Json<serde_json::Value>| {
+                let call = counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if call < failures {
+                        (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({ "error": "busy" })))
+                    } else {
+                        (StatusCode::OK, Json(serde_json::json!({ "echo": body })))
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/echo", addr), calls)
+    }
+
+    fn request(url: &str, max_retries: u32) -> ApiRequest {
+        ApiRequest {
+            id: Uuid::new_v4(),
+            provider: "local".to_string(),
+            endpoint: url.to_string(),
+            method: HttpMethod::POST,
+            headers: HashMap::new(),
+            body: Some(serde_json::json!({ "n": 1 })),
+            priority: Priority::Normal,
+            workflow_id: Uuid::new_v4(),
+            node_id: Uuid::new_v4(),
+            organization_id: None,
+            timeout: std::time::Duration::from_secs(5),
+            retry_config: RetryConfig { max_retries, initial_delay_ms: 1, ..Default::default() },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_retries_through_pool() {
+        let (url, calls) = upstream(2).await;
+        let dispatcher = GatewayDispatcher::new(Arc::new(RequestPool::new(4)), Arc::new(RateLimiter::new()));
+
+        let response = dispatcher.dispatch(request(&url, 3)).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body.unwrap()["echo"]["n"], 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // Without retries the error status is returned as-is
+        let (url, _) = upstream(1).await;
+        let response = dispatcher.dispatch(request(&url, 0)).await.unwrap();
+        assert_eq!(response.status_code, 503);
+
+        let metrics = dispatcher.pool().get_metrics().await;
+        assert_eq!((metrics.completed, metrics.failed, metrics.queued), (1, 1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_carries_the_trace() {
+        let app = Router::new().route(
+            "/trace",
+            post(|headers: HeaderMap| async move {
+                let traceparent = headers.get("traceparent").and_then(|v| v.to_str().ok()).map(str::to_string);
+                Json(serde_json::json!({ "traceparent": traceparent }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/trace", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let dispatcher = GatewayDispatcher::new(Arc::new(RequestPool::new(4)), Arc::new(RateLimiter::new()));
+
+        let response = dispatcher.dispatch(request(&url, 0)).await.unwrap();
+        assert_eq!(response.body.unwrap()["traceparent"], serde_json::Value::Null);
+
+        let trace = TraceContext::new_root();
+        let response = trace.clone().scope(dispatcher.dispatch(request(&url, 0))).await.unwrap();
+        let sent = TraceContext::parse(response.body.unwrap()["traceparent"].as_str().unwrap()).unwrap();
+        assert_eq!(sent.trace_id, trace.trace_id);
+        assert_ne!(sent.span_id, trace.span_id);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_fails_over_and_caches() {
+        // Upstream accepting only the backup provider's key
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let app = Router::new().route(
+            "/keyed",
+            get(move |headers: HeaderMap| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    match headers.get("authorization").and_then(|v| v.to_str().ok()) {
+                        Some("Bearer good") => (StatusCode::OK, Json(serde_json::json!({ "ok": true }))),
+                        _ => (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({ "error": "busy" }))),
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/keyed", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let load_balancer = Arc::new(LoadBalancer::new(LoadBalanceStrategy::RoundRobin));
+        let failover = Arc::new(FailoverManager::new(Duration::from_secs(10), 1, Duration::from_secs(30)));
+        let cache = Arc::new(ResponseCache::new(100, Duration::from_secs(60)));
+        for (provider, key, failovers) in [("primary", "bad", vec!["backup".to_string()]), ("backup", "good", vec![])] {
+            let keys = vec![ApiKeyConfig { key: key.to_string(), ..Default::default() }];
+            load_balancer.configure_provider(provider.to_string(), keys.clone()).await;
+            let config = ProviderConfig {
+                name: provider.to_string(),
+                api_keys: keys,
+                rate_limit: RateLimitConfig::default(),
+                cache_ttl: Some(Duration::from_secs(60)),
+                failover_providers: failovers,
+            };
+            cache.configure_provider(&config).await;
+            failover.register_provider(config).await;
+        }
+        let metrics = Arc::new(MetricsCollector::new());
+        let dispatcher = GatewayDispatcher::new(Arc::new(RequestPool::new(4)), Arc::new(RateLimiter::new()))
+            .with_load_balancer(load_balancer)
+            .with_failover(failover.clone())
+            .with_metrics(metrics.clone())
+            .with_cache(cache);
+
+        let get_request = || ApiRequest { provider: "primary".to_string(), method: HttpMethod::GET, body: None, ..request(&url, 0) };
+        let response = dispatcher.dispatch(get_request()).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert!(!failover.is_healthy("primary").await);
+        assert_eq!(metrics.get_metrics("primary").await.unwrap().failed_requests, 1);
+        assert_eq!(metrics.get_metrics("backup").await.unwrap().successful_requests, 1);
+
+        // The repeated request is answered from the cache
+        let request = get_request();
+        let response = dispatcher.dispatch(request.clone()).await.unwrap();
+        assert_eq!((response.status_code, response.request_id), (200, request.id));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        let cached = metrics.get_response_cache_metrics().await;
+        assert_eq!((cached[0].hits, cached[0].misses), (1, 1));
+    }
+
+}
@@ -0,0 +1,128 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use common::error::WorkflowError;
+use common::types::Role;
+use rbac_service::jwt::JwtClaims;
+use serde::Deserialize;
+use std::sync::Arc;
+use workflow_engine::encryption::{decode_master_key, PayloadEncryption};
+
+/// Execution data encryption service state
+#[derive(Clone)]
+pub struct EncryptionServiceState {
+    pub encryption: Arc<PayloadEncryption>,
+}
+
+impl EncryptionServiceState {
+    pub fn new(encryption: Arc<PayloadEncryption>) -> Self {
+        Self { encryption }
+    }
+}
+
+/// Master key rotation request
+#[derive(Debug, Deserialize)]
+pub struct RotateKeyRequest {
+    /// ID of the new master key, not used before
+    pub master_key_id: u32,
+    /// Base64-encoded 256-bit key
+    pub master_key: String,
+}
+
+/// 查询当前用于包装数据密钥的主密钥 ID
+pub async fn get_encryption_status(State(state): State<EncryptionServiceState>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "success": true,
+        "master_key_id": state.encryption.master_key_id().await
+    }))
+}
+
+/// 轮换主密钥（仅管理员）：用新主密钥重新包装所有组织的数据密钥，已加密的执行数据无需重新加密
+pub async fn rotate_master_key(
+    State(state): State<EncryptionServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Json(request): Json<RotateKeyRequest>,
+) -> impl IntoResponse {
+    if claims.role != Role::Admin {
+        return error_response(StatusCode::FORBIDDEN, "只有管理员可以轮换主密钥".to_string());
+    }
+    let Ok(master_key) = decode_master_key(&request.master_key) else {
+        return error_response(StatusCode::BAD_REQUEST, "主密钥必须是 Base64 编码的 32 字节".to_string());
+    };
+
+    match state.encryption.rotate_master_key(request.master_key_id, &master_key).await {
+        Ok(rewrapped) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "master_key_id": request.master_key_id,
+                "rewrapped": rewrapped
+            })),
+        ),
+        Err(WorkflowError::ValidationFailed(message)) => error_response(StatusCode::CONFLICT, message),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("主密钥轮换未完成，可用同一密钥重试: {}", e)),
+    }
+}
+
+fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, claims};
+    use axum::routing::{get, post};
+    use axum::Router;
+    use uuid::Uuid;
+    use workflow_engine::encryption::{DataKeyStore, InMemoryDataKeyStore};
+    use workflow_engine::OrgSettingsStore;
+
+    fn app(state: EncryptionServiceState, role: Role) -> Router {
+        Router::new()
+            .route("/encryption", get(get_encryption_status))
+            .route("/encryption/rotate", post(rotate_master_key))
+            .layer(Extension(claims(Uuid::new_v4(), role)))
+            .with_state(state)
+    }
+
+    #[tokio::test]
+    async fn test_rotate_master_key() {
+        let keys = Arc::new(InMemoryDataKeyStore::new());
+        let encryption = Arc::new(PayloadEncryption::new(1, &[1u8; 32], keys.clone(), Arc::new(OrgSettingsStore::new())));
+        let organization_id = Uuid::new_v4();
+        encryption.encrypt_value(organization_id, &serde_json::json!("secret")).await.unwrap();
+        let state = EncryptionServiceState::new(encryption);
+
+        // base64 of 32 0x02 bytes
+        let new_key = "AgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgI=";
+        let rotate = serde_json::json!({ "master_key_id": 2, "master_key": new_key });
+        let (status, _) = call(app(state.clone(), Role::User), "POST", "/encryption/rotate", Some(rotate.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = call(
+            app(state.clone(), Role::Admin),
+            "POST",
+            "/encryption/rotate",
+            Some(serde_json::json!({ "master_key_id": 2, "master_key": "c2hvcnQ=" })),
+        ).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = call(app(state.clone(), Role::Admin), "POST", "/encryption/rotate", Some(rotate)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["rewrapped"], 1);
+        assert_eq!(keys.get(organization_id).await.unwrap().unwrap().master_key_id, 2);
+
+        let (status, body) = call(app(state, Role::Viewer), "GET", "/encryption", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["master_key_id"], 2);
+    }
+}
@@ -0,0 +1,157 @@
+//! The error envelope of the gateway's JSON responses.
+//!
+//! Every error is answered with the status of its [`ErrorCode`] and a body
+//! like `{"success": false, "code": "NOT_FOUND", "message": "...", "retryable": false, "field_errors": []}`.
+//! The message only carries what users may see; internal details are logged.
+//! `field_errors` lists the request fields that failed validation.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use common::error::{ErrorCode, ErrorInfo};
+use serde::Serialize;
+
+/// A request field that failed validation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// An error answered with the gateway's error envelope
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: ErrorCode,
+    pub message: String,
+    pub retryable: bool,
+    pub field_errors: Vec<FieldError>,
+}
+
+impl ApiError {
+    /// An error answered with the status of `code`
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::from_u16(code.http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            retryable: code.is_retryable(),
+            code,
+            message: message.into(),
+            field_errors: Vec::new(),
+        }
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn unauthenticated(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Unauthenticated, message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::PermissionDenied, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Conflict, message)
+    }
+
+    /// An internal failure: `detail` is logged, only `message` is shown
+    pub fn internal(message: impl Into<String>, detail: impl std::fmt::Display) -> Self {
+        let message = message.into();
+        tracing::error!("{}: {}", message, detail);
+        Self::new(ErrorCode::Internal, message)
+    }
+
+    /// A request whose fields failed validation, answered with 422
+    pub fn validation(field_errors: Vec<FieldError>) -> Self {
+        Self {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            field_errors,
+            ..Self::new(ErrorCode::InvalidInput, "请求参数校验未通过")
+        }
+    }
+
+    /// The error for `error`, its message prefixed with what was being done
+    pub fn from_error(context: &str, error: &dyn ErrorInfo) -> Self {
+        let report = error.report();
+        if report.code == ErrorCode::Internal {
+            tracing::error!(code = %report.code, chain = ?report.chain, "{}: {}", context, report.detail);
+        }
+        Self {
+            retryable: report.retryable,
+            ..Self::new(report.code, format!("{}: {}", context, report.message))
+        }
+    }
+
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    fn body(&self) -> serde_json::Value {
+        serde_json::json!({
+            "success": false,
+            "code": self.code,
+            "message": self.message,
+            "retryable": self.retryable,
+            "field_errors": self.field_errors,
+        })
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.body())).into_response()
+    }
+}
+
+/// The response for `error`, its message prefixed with what was being done
+pub fn error_envelope(context: &str, error: &dyn ErrorInfo) -> (StatusCode, Json<serde_json::Value>) {
+    let error = ApiError::from_error(context, error);
+    (error.status, Json(error.body()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::error::{PlatformError, WorkflowError};
+
+    #[test]
+    fn test_envelope_hides_internal_details() {
+        let (status, Json(body)) = error_envelope("触发工作流失败", &WorkflowError::QueueFull("wf".to_string()));
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["code"], "UNAVAILABLE");
+        assert_eq!(body["retryable"], true);
+        assert_eq!(body["message"], "触发工作流失败: Run queue full: wf");
+
+        let error = PlatformError::from(WorkflowError::Storage("connection reset by 10.0.0.3".to_string()))
+            .context("loading execution");
+        let (status, Json(body)) = error_envelope("解密执行数据失败", &error);
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["code"], "INTERNAL");
+        assert_eq!(body["retryable"], false);
+        assert_eq!(body["message"], "解密执行数据失败: Internal error");
+        assert_eq!(body["field_errors"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_validation_errors_list_fields() {
+        let error = ApiError::validation(vec![FieldError {
+            field: "email".to_string(),
+            message: "无效的邮箱地址".to_string(),
+        }]);
+        let body = error.body();
+        assert_eq!(error.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "INVALID_INPUT");
+        assert_eq!(body["field_errors"][0]["field"], "email");
+
+        let error = ApiError::conflict("邮箱已被注册");
+        assert_eq!(error.status, StatusCode::CONFLICT);
+        assert!(!error.retryable);
+    }
+}
@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use common::error::WorkflowError;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+use workflow_engine::events::{ExecutionEvent, ExecutionEventStore, StoredEvent};
+
+/// Postgres-backed execution event store (table `execution_events`, see
+/// `migrations/003_execution_events.sql`)
+pub struct PgEventStore {
+    pool: PgPool,
+}
+
+impl PgEventStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ExecutionEventStore for PgEventStore {
+    async fn append(&self, events: Vec<StoredEvent>) -> Result<(), WorkflowError> {
+        let mut tx = self.pool.begin().await.map_err(storage_error)?;
+        for stored in events {
+            let payload = serde_json::to_value(&stored.event).map_err(|e| WorkflowError::Storage(e.to_string()))?;
+            let event_type = payload["event"].as_str().unwrap_or_default().to_string();
+            sqlx::query(
+                r#"
+                INSERT INTO execution_events (
+                    execution_id, sequence, workflow_id, event_type, payload, created_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+            )
+            .bind(stored.event.execution_id)
+            .bind(stored.sequence as i64)
+            .bind(stored.event.workflow_id)
+            .bind(event_type)
+            .bind(sqlx::types::Json(payload))
+            .bind(stored.event.timestamp)
+            .execute(&mut *tx)
+            .await
+            .map_err(storage_error)?;
+        }
+        tx.commit().await.map_err(storage_error)
+    }
+
+    async fn list(&self, execution_id: Uuid, after: u64) -> Result<Vec<StoredEvent>, WorkflowError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT sequence, payload
+            FROM execution_events
+            WHERE execution_id = $1 AND sequence > $2
+            ORDER BY sequence
+            "#,
+        )
+        .bind(execution_id)
+        .bind(after as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(storage_error)?;
+
+        rows.into_iter()
+            .map(|row| {
+                let sequence: i64 = row.get("sequence");
+                let sqlx::types::Json(payload): sqlx::types::Json<serde_json::Value> = row.get("payload");
+                let event: ExecutionEvent = serde_json::from_value(payload)
+                    .map_err(|e| WorkflowError::Storage(format!("event {} of {}: {}", sequence, execution_id, e)))?;
+                Ok(StoredEvent { sequence: sequence as u64, event })
+            })
+            .collect()
+    }
+}
+
+fn storage_error(e: sqlx::Error) -> WorkflowError {
+    WorkflowError::Storage(e.to_string())
+}
@@ -0,0 +1,961 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use common::error::WorkflowError;
+use common::types::{ActionType2, ExecutionResult, ExecutionState, ExecutionUsage, NodeExecutionState, NodeUsage, Workflow};
+use rbac_service::jwt::JwtClaims;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use workflow_engine::events::{ExecutionEventStore, InMemoryEventStore};
+use workflow_engine::history::{ExecutionHistory, ExecutionRecord};
+use workflow_engine::ownership::OwnershipStore;
+use workflow_engine::sampling::SamplingPolicy;
+
+use crate::errors::error_envelope;
+use crate::workflow_access::WorkflowAccess;
+
+/// Number of slowest nodes highlighted in the usage report
+const SLOWEST_NODES: usize = 5;
+
+/// Executions listed per workflow when no limit is given
+const DEFAULT_HISTORY_LIMIT: usize = 20;
+
+/// In-memory execution record store (for development, replace with database in production)
+#[derive(Clone)]
+pub struct ExecutionStore {
+    executions: Arc<RwLock<HashMap<Uuid, ExecutionResult>>>,
+    traces: Arc<RwLock<HashMap<Uuid, ExecutionTrace>>>,
+    /// Execution history written by the engine, with per-node runs
+    history: Arc<ExecutionHistory>,
+    /// Persisted executor events, for timelines
+    events: Arc<dyn ExecutionEventStore>,
+    /// Owners of failing nodes, reported with failed executions
+    ownership: Option<Arc<OwnershipStore>>,
+    /// Who may read the executions of which workflows
+    access: WorkflowAccess,
+}
+
+impl Default for ExecutionStore {
+    fn default() -> Self {
+        Self {
+            executions: Arc::default(),
+            traces: Arc::default(),
+            history: Arc::default(),
+            events: Arc::new(InMemoryEventStore::new()),
+            ownership: None,
+            access: WorkflowAccess::default(),
+        }
+    }
+}
+
+/// Execution internals kept for inspection: the workflow graph and per-node states
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionTrace {
+    pub organization_id: Option<Uuid>,
+    pub workflow: Workflow,
+    pub nodes: Vec<NodeExecutionState>,
+}
+
+impl ExecutionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve execution history from the history the executor records into
+    pub fn with_history(mut self, history: Arc<ExecutionHistory>) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn history(&self) -> &Arc<ExecutionHistory> {
+        &self.history
+    }
+
+    /// Serve event timelines from the store a `PersistentEventBus` writes to
+    pub fn with_events(mut self, events: Arc<dyn ExecutionEventStore>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Report the owner of the failing node with failed executions, for
+    /// escalation tooling
+    pub fn with_ownership(mut self, ownership: Arc<OwnershipStore>) -> Self {
+        self.ownership = Some(ownership);
+        self
+    }
+
+    /// Authorize reads of executions on the workflows of `access`; without
+    /// it only callers who may read every workflow see executions
+    pub fn with_access(mut self, access: WorkflowAccess) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Save or replace an execution record
+    pub async fn record(&self, result: ExecutionResult) {
+        let mut executions = self.executions.write().await;
+        executions.insert(result.execution_id, result);
+    }
+
+    pub async fn get(&self, execution_id: Uuid) -> Option<ExecutionResult> {
+        {
+            let executions = self.executions.read().await;
+            if let Some(result) = executions.get(&execution_id) {
+                return Some(result.clone());
+            }
+        }
+        self.history.get(execution_id).await.map(|record| record.result)
+    }
+
+    /// Drop everything recorded about the executions of a deleted workflow;
+    /// returns how many history records were dropped
+    pub async fn remove_workflow(&self, workflow_id: Uuid) -> usize {
+        {
+            let mut traces = self.traces.write().await;
+            let mut executions = self.executions.write().await;
+            traces.retain(|execution_id, trace| {
+                let keep = trace.workflow.id != workflow_id;
+                if !keep {
+                    executions.remove(execution_id);
+                }
+                keep
+            });
+        }
+        if let Some(ownership) = &self.ownership {
+            ownership.remove_workflow(workflow_id).await;
+        }
+        self.history.remove_workflow(workflow_id).await
+    }
+
+    /// Save or replace the trace of an execution
+    pub async fn record_trace(&self, execution_id: Uuid, trace: ExecutionTrace) {
+        let mut traces = self.traces.write().await;
+        traces.insert(execution_id, trace);
+    }
+
+    pub async fn get_trace(&self, execution_id: Uuid) -> Option<ExecutionTrace> {
+        let traces = self.traces.read().await;
+        traces.get(&execution_id).cloned()
+    }
+
+    /// Workflow an execution ran, from its history record, trace or events
+    async fn workflow_of(&self, execution_id: Uuid) -> Option<Uuid> {
+        if let Some(record) = self.history.get(execution_id).await {
+            return Some(record.workflow_id);
+        }
+        if let Some(trace) = self.get_trace(execution_id).await {
+            return Some(trace.workflow.id);
+        }
+        match self.events.list(execution_id, 0).await {
+            Ok(events) => events.first().map(|stored| stored.event.workflow_id),
+            Err(e) => {
+                tracing::warn!("Failed to read the events of execution {}: {}", execution_id, e);
+                None
+            }
+        }
+    }
+
+    /// Whether the caller may read the executions of a workflow; those of
+    /// unknown workflows need read access to every workflow
+    async fn can_read(&self, claims: &JwtClaims, workflow_id: Option<Uuid>) -> bool {
+        match workflow_id {
+            Some(workflow_id) => self.access.can_on(claims, ActionType2::Read, workflow_id).await,
+            None => self.access.can(claims, ActionType2::Read, None).await,
+        }
+    }
+
+    /// Check the caller may perform `action` on a workflow's executions;
+    /// workflows the caller may not even read are not revealed
+    async fn authorized(
+        &self,
+        claims: &JwtClaims,
+        action: ActionType2,
+        workflow_id: Uuid,
+        denied: &str,
+    ) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+        let owner_id = self.access.owner(workflow_id).await;
+        if self.access.can(claims, action, owner_id).await {
+            return Ok(());
+        }
+        if !self.access.can(claims, ActionType2::Read, owner_id).await {
+            return Err(workflow_not_found(workflow_id));
+        }
+        Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "success": false,
+                "message": denied
+            })),
+        ))
+    }
+}
+
+/// Usage report returned by the execution API
+#[derive(Debug, Serialize)]
+pub struct ExecutionUsageResponse {
+    pub execution_id: Uuid,
+    pub usage: ExecutionUsage,
+    pub slowest_nodes: Vec<NodeUsage>,
+}
+
+/// Query parameters of the workflow execution list
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventsQuery {
+    /// Only events with a higher sequence number, for resuming a timeline
+    #[serde(default)]
+    pub after: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HeatmapQuery {
+    pub workflow_id: Option<Uuid>,
+    /// IANA time zone name, UTC when absent
+    pub tz: Option<String>,
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CountersQuery {
+    /// Start of the counted period, the last 24 hours when absent
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Whether the caller reads the encrypted payloads of an execution decrypted:
+/// they may read the workflow and the execution is of their organization, or
+/// of their own workflow. Inspectors and admins looking into other
+/// organizations' executions see them as stored.
+async fn can_decrypt(store: &ExecutionStore, claims: &JwtClaims, record: &ExecutionRecord) -> bool {
+    let owner_id = store.access.owner(record.workflow_id).await;
+    store.access.can(claims, ActionType2::Read, owner_id).await
+        && store.access.is_member(claims, record.organization_id, owner_id).await
+}
+
+/// Decrypt the payloads of a history record for callers allowed to read them
+async fn readable(store: &ExecutionStore, claims: &JwtClaims, record: &mut ExecutionRecord) -> Result<(), WorkflowError> {
+    match store.history.encryption() {
+        Some(encryption) if can_decrypt(store, claims, record).await => encryption.decrypt_record(record).await,
+        _ => Ok(()),
+    }
+}
+
+/// 获取执行记录，包括按执行顺序排列的节点输入、输出、耗时和错误；
+/// 失败的执行附带失败节点及其负责人（节点、工作流标注或创建者）；
+/// 已加密的执行数据对有权限的用户透明解密
+pub async fn get_execution(
+    State(store): State<ExecutionStore>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(execution_id): Path<Uuid>,
+) -> impl IntoResponse {
+    if let Some(mut record) = store.history.get(execution_id).await {
+        // Executions the caller may not read are not revealed
+        if !store.can_read(&claims, Some(record.workflow_id)).await {
+            return not_found(execution_id);
+        }
+        if let Err(e) = readable(&store, &claims, &mut record).await {
+            return decryption_failed(e);
+        }
+        let mut body = serde_json::json!({
+            "success": true,
+            "execution": record.result,
+            "workflow_id": record.workflow_id,
+            "started_at": record.started_at,
+            "nodes": record.nodes
+        });
+        if record.result.state == ExecutionState::Failed {
+            let failed_node = record.nodes.iter().rev()
+                .find(|n| n.state.state == ExecutionState::Failed)
+                .map(|n| n.state.node_id);
+            let owner = match &store.ownership {
+                Some(ownership) => ownership.resolve(record.workflow_id, failed_node).await,
+                None => None,
+            };
+            body["failure"] = serde_json::json!({
+                "node_id": failed_node,
+                "owner": owner
+            });
+        }
+        return (StatusCode::OK, Json(body));
+    }
+    let workflow_id = store.get_trace(execution_id).await.map(|trace| trace.workflow.id);
+    if !store.can_read(&claims, workflow_id).await {
+        return not_found(execution_id);
+    }
+    match store.get(execution_id).await {
+        Some(result) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "execution": result,
+                "nodes": []
+            })),
+        ),
+        None => not_found(execution_id),
+    }
+}
+
+/// 列出工作流最近的执行（从新到旧），每条包含节点执行明细
+pub async fn list_workflow_executions(
+    State(store): State<ExecutionStore>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+    Query(query): Query<HistoryQuery>,
+) -> impl IntoResponse {
+    if !store.can_read(&claims, Some(workflow_id)).await {
+        return workflow_not_found(workflow_id);
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+    let mut executions = store.history.list_for_workflow(workflow_id, limit).await;
+    for record in &mut executions {
+        if let Err(e) = readable(&store, &claims, record).await {
+            return decryption_failed(e);
+        }
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "workflow_id": workflow_id,
+            "executions": executions
+        })),
+    )
+}
+
+/// 获取执行的事件时间线（按序号排列），可用 after 参数增量拉取
+pub async fn list_execution_events(
+    State(store): State<ExecutionStore>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(execution_id): Path<Uuid>,
+    Query(query): Query<EventsQuery>,
+) -> impl IntoResponse {
+    if !store.can_read(&claims, store.workflow_of(execution_id).await).await {
+        return not_found(execution_id);
+    }
+    match store.events.list(execution_id, query.after).await {
+        Ok(events) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "execution_id": execution_id,
+                "events": events
+            })),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "success": false,
+                "message": format!("读取执行事件失败: {}", e)
+            })),
+        ),
+    }
+}
+
+/// 失败热力图：按星期几和小时统计各工作流及各外部服务的运行与失败次数，
+/// 用于发现维护窗口等周期性故障
+pub async fn failure_heatmap(
+    State(store): State<ExecutionStore>,
+    Extension(claims): Extension<JwtClaims>,
+    Query(query): Query<HeatmapQuery>,
+) -> impl IntoResponse {
+    // Without a workflow the heatmap covers everyone's executions
+    if !store.can_read(&claims, query.workflow_id).await {
+        return match query.workflow_id {
+            Some(workflow_id) => workflow_not_found(workflow_id),
+            None => (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({
+                    "success": false,
+                    "message": "没有查看所有工作流执行的权限，请指定 workflow_id"
+                })),
+            ),
+        };
+    }
+    let tz_name = query.tz.as_deref().unwrap_or("UTC");
+    let Ok(timezone) = tz_name.parse::<chrono_tz::Tz>() else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "message": format!("无效的时区: {}", tz_name)
+            })),
+        );
+    };
+
+    let heatmap = store.history.failure_heatmap(query.workflow_id, query.since, timezone).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "heatmap": heatmap
+        })),
+    )
+}
+
+/// 获取执行的资源消耗（节点耗时、AI tokens、外部调用、抓取页数、存储字节）
+pub async fn get_execution_usage(
+    State(store): State<ExecutionStore>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(execution_id): Path<Uuid>,
+) -> impl IntoResponse {
+    if !store.can_read(&claims, store.workflow_of(execution_id).await).await {
+        return not_found(execution_id);
+    }
+    let Some(result) = store.get(execution_id).await else {
+        return not_found(execution_id);
+    };
+
+    let usage = result.usage.unwrap_or_default();
+    let slowest_nodes = usage.slowest_nodes(SLOWEST_NODES).into_iter().cloned().collect();
+    let report = ExecutionUsageResponse {
+        execution_id,
+        usage,
+        slowest_nodes,
+    };
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "report": report
+        })),
+    )
+}
+
+/// 查询工作流的执行采样策略，以及期间内（按小时）的执行计数；
+/// 计数包含被采样丢弃的执行，统计不受采样影响
+pub async fn get_sampling(
+    State(store): State<ExecutionStore>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+    Query(query): Query<CountersQuery>,
+) -> impl IntoResponse {
+    if !store.can_read(&claims, Some(workflow_id)).await {
+        return workflow_not_found(workflow_id);
+    }
+    let until = chrono::Utc::now();
+    let since = query.since.unwrap_or(until - chrono::Duration::hours(24));
+    let counters = store.history.counters_between(since, until).await
+        .remove(&workflow_id)
+        .map(|c| c.counters)
+        .unwrap_or_default();
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "workflow_id": workflow_id,
+            "policy": store.history.sampling(workflow_id).await,
+            "since": since,
+            "counters": counters
+        })),
+    )
+}
+
+/// 设置高频工作流的执行采样策略：保留所有失败、按比例保留成功、
+/// 保留每小时的第一条和最后一条执行
+pub async fn update_sampling(
+    State(store): State<ExecutionStore>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+    Json(policy): Json<SamplingPolicy>,
+) -> impl IntoResponse {
+    if let Err(denied) = store.authorized(&claims, ActionType2::Update, workflow_id, "没有修改采样策略的权限").await {
+        return denied;
+    }
+    if let Err(e) = store.history.set_sampling(workflow_id, policy.clone()).await {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "message": format!("采样策略无效: {}", e)
+            })),
+        );
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "workflow_id": workflow_id,
+            "policy": policy
+        })),
+    )
+}
+
+/// 取消采样，重新保留工作流的所有执行
+pub async fn delete_sampling(
+    State(store): State<ExecutionStore>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+) -> impl IntoResponse {
+    if let Err(denied) = store.authorized(&claims, ActionType2::Update, workflow_id, "没有修改采样策略的权限").await {
+        return denied;
+    }
+    store.history.clear_sampling(workflow_id).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "message": "已取消执行采样"
+        })),
+    )
+}
+
+fn decryption_failed(e: WorkflowError) -> (StatusCode, Json<serde_json::Value>) {
+    error_envelope("解密执行数据失败", &e)
+}
+
+fn not_found(execution_id: Uuid) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({
+            "success": false,
+            "message": format!("执行记录不存在: {}", execution_id)
+        })),
+    )
+}
+
+fn workflow_not_found(workflow_id: Uuid) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({
+            "success": false,
+            "message": format!("工作流不存在: {}", workflow_id)
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, claims};
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::get;
+    use axum::Router;
+    use common::types::{ResourceUsage, Role};
+    use rbac_service::OrgService;
+    use tower::ServiceExt;
+    use workflow_engine::history::ExecutionRecord;
+    use workflow_engine::workflows::{InMemoryWorkflowStore, StoredWorkflow, WorkflowStore};
+
+    /// Routes reading execution history, called as `user` with `role`
+    fn app(store: &ExecutionStore, user: Uuid, role: Role) -> Router {
+        Router::new()
+            .route("/executions/:execution_id", get(get_execution))
+            .route("/executions/:execution_id/events", get(list_execution_events))
+            .route("/workflows/:workflow_id/executions", get(list_workflow_executions))
+            .route("/analytics/failure-heatmap", get(failure_heatmap))
+            .layer(Extension(claims(user, role)))
+            .with_state(store.clone())
+    }
+
+    /// Access to a store holding one workflow of `owner`, and its id
+    async fn access_to(owner: Uuid) -> (WorkflowAccess, Uuid) {
+        let workflows = Arc::new(InMemoryWorkflowStore::new());
+        let now = chrono::Utc::now();
+        let workflow = Workflow {
+            version: 1,
+            id: Uuid::new_v4(),
+            name: "Sync".to_string(),
+            description: None,
+            nodes: vec![],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: now,
+            updated_at: now,
+            trash: Default::default(),
+        };
+        let workflow_id = workflow.id;
+        workflows.save(&StoredWorkflow { workflow, owner_id: owner, active: true }).await.unwrap();
+        (WorkflowAccess::new(workflows), workflow_id)
+    }
+
+    fn record(workflow_id: Uuid, organization_id: Option<Uuid>, execution_id: Uuid) -> ExecutionRecord {
+        ExecutionRecord {
+            workflow_id,
+            organization_id,
+            started_at: chrono::Utc::now(),
+            result: ExecutionResult {
+                execution_id,
+                state: ExecutionState::Completed,
+                completed_at: None,
+                error: None,
+                output: None,
+                usage: None,
+            },
+            nodes: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_usage_report() {
+        let store = ExecutionStore::new();
+        let execution_id = Uuid::new_v4();
+        let mut usage = ExecutionUsage::default();
+        usage.record_node(Uuid::new_v4(), 40, ResourceUsage::default());
+        usage.record_node(Uuid::new_v4(), 900, ResourceUsage {
+            prompt_tokens: 500,
+            completion_tokens: 200,
+            provider_calls: 1,
+            ..Default::default()
+        });
+        store.record(ExecutionResult {
+            execution_id,
+            state: ExecutionState::Completed,
+            completed_at: None,
+            error: None,
+            output: None,
+            usage: Some(usage),
+        }).await;
+
+        let usage_app = |role: Role| {
+            Router::new()
+                .route("/executions/:execution_id/usage", get(get_execution_usage))
+                .layer(Extension(claims(Uuid::new_v4(), role)))
+                .with_state(store.clone())
+        };
+        let app = usage_app(Role::Admin);
+
+        let response = app.clone()
+            .oneshot(Request::builder().uri(format!("/executions/{}/usage", execution_id)).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["report"]["usage"]["totals"]["prompt_tokens"], 500);
+        assert_eq!(json["report"]["slowest_nodes"][0]["wall_time_ms"], 900);
+
+        let response = app
+            .oneshot(Request::builder().uri(format!("/executions/{}/usage", Uuid::new_v4())).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // Usage of executions of unknown workflows is for callers who may read every workflow
+        let (status, _) = call(usage_app(Role::User), "GET", &format!("/executions/{}/usage", execution_id), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_execution_history_routes() {
+        use workflow_engine::history::NodeRecord;
+        use workflow_engine::ownership::{Owner, WorkflowOwnership};
+
+        let owner = Uuid::new_v4();
+        let (access, workflow_id) = access_to(owner).await;
+        let history = Arc::new(ExecutionHistory::default());
+        let store = ExecutionStore::new().with_history(history.clone()).with_access(access);
+        let (execution_id, node_id) = (Uuid::new_v4(), Uuid::new_v4());
+        history.record(ExecutionRecord {
+            workflow_id,
+            organization_id: None,
+            started_at: chrono::Utc::now(),
+            result: ExecutionResult {
+                execution_id,
+                state: ExecutionState::Failed,
+                completed_at: None,
+                error: Some("boom".to_string()),
+                output: None,
+                usage: None,
+            },
+            nodes: vec![NodeRecord {
+                state: NodeExecutionState {
+                    node_id,
+                    state: ExecutionState::Failed,
+                    started_at: None,
+                    completed_at: None,
+                    input: Some(serde_json::json!({ "x": 1 })),
+                    output: None,
+                    error: Some("boom".to_string()),
+                },
+                duration_ms: 12,
+                provider: None,
+            }],
+        }).await;
+
+        let json = fetch_history(&store, owner, Role::User, format!("/executions/{}", execution_id)).await;
+        assert_eq!(json["execution"]["error"], "boom");
+        assert_eq!(json["failure"]["node_id"], node_id.to_string());
+        assert!(json["failure"]["owner"].is_null());
+        assert_eq!(json["nodes"][0]["node_id"], node_id.to_string());
+        assert_eq!(json["nodes"][0]["input"]["x"], 1);
+        assert_eq!(json["nodes"][0]["duration_ms"], 12);
+
+        let json = fetch_history(&store, owner, Role::User, format!("/workflows/{}/executions?limit=5", workflow_id)).await;
+        assert_eq!(json["executions"][0]["result"]["execution_id"], execution_id.to_string());
+
+        let ownership = Arc::new(OwnershipStore::new());
+        ownership.set(workflow_id, WorkflowOwnership {
+            workflow: None,
+            nodes: HashMap::from([(node_id, Owner::Team { team: "payments".to_string() })]),
+        }).await;
+        let store = store.with_ownership(ownership);
+        let json = fetch_history(&store, owner, Role::User, format!("/executions/{}", execution_id)).await;
+        assert_eq!(json["failure"]["owner"]["owner"], serde_json::json!({ "type": "team", "team": "payments" }));
+        assert_eq!(json["failure"]["owner"]["source"], "node");
+    }
+
+    async fn fetch_history(store: &ExecutionStore, user: Uuid, role: Role, uri: String) -> serde_json::Value {
+        call(app(store, user, role), "GET", &uri, None).await.1
+    }
+
+    #[tokio::test]
+    async fn test_executions_of_other_organizations_are_hidden() {
+        let organizations = Arc::new(OrgService::new());
+        let (owner, lead, outsider) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let acme = organizations.create_organization("Acme".to_string(), owner).await;
+        let team = organizations.create_team(acme.id, "Ops".to_string(), owner).await.unwrap();
+        organizations.add_member(acme.id, lead, rbac_service::org::MemberRole::Member).await.unwrap();
+        organizations.add_team_member(team.id, owner).await.unwrap();
+        organizations.add_team_member(team.id, lead).await.unwrap();
+        let globex = organizations.create_organization("Globex".to_string(), outsider).await;
+        let rivals = organizations.create_team(globex.id, "Ops".to_string(), outsider).await.unwrap();
+        organizations.add_team_member(rivals.id, outsider).await.unwrap();
+
+        let (access, workflow_id) = access_to(owner).await;
+        let history = Arc::new(ExecutionHistory::default());
+        let store = ExecutionStore::new()
+            .with_history(history.clone())
+            .with_access(access.with_org_service(organizations));
+        let execution_id = Uuid::new_v4();
+        history.record(record(workflow_id, Some(acme.id), execution_id)).await;
+
+        let uris = [
+            format!("/executions/{}", execution_id),
+            format!("/executions/{}/events", execution_id),
+            format!("/workflows/{}/executions", workflow_id),
+            format!("/analytics/failure-heatmap?workflow_id={}", workflow_id),
+        ];
+        for uri in &uris {
+            let (status, _) = call(app(&store, lead, Role::Manager), "GET", uri, None).await;
+            assert_eq!(status, StatusCode::OK, "{}", uri);
+            let (status, _) = call(app(&store, outsider, Role::Manager), "GET", uri, None).await;
+            assert_eq!(status, StatusCode::NOT_FOUND, "{}", uri);
+        }
+
+        // Heatmaps over every workflow are for callers who may read them all
+        let (status, _) = call(app(&store, lead, Role::Manager), "GET", "/analytics/failure-heatmap", None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = call(app(&store, outsider, Role::Admin), "GET", "/analytics/failure-heatmap", None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_history_is_decrypted_on_read() {
+        use workflow_engine::encryption::{is_encrypted, InMemoryDataKeyStore, PayloadEncryption};
+        use workflow_engine::settings::{OrgSettings, OrgSettingsStore};
+
+        let organizations = Arc::new(OrgService::new());
+        let owner = Uuid::new_v4();
+        let organization_id = organizations.create_organization("Acme".to_string(), owner).await.id;
+        let settings = Arc::new(OrgSettingsStore::new());
+        settings.set(organization_id, OrgSettings {
+            encrypt_execution_data: true,
+            ..Default::default()
+        }).await;
+        let encryption = Arc::new(PayloadEncryption::new(1, &[5u8; 32], Arc::new(InMemoryDataKeyStore::new()), settings));
+        let history = Arc::new(ExecutionHistory::default().with_encryption(encryption));
+        let (access, workflow_id) = access_to(owner).await;
+        let store = ExecutionStore::new()
+            .with_history(history.clone())
+            .with_access(access.with_org_service(organizations));
+        let execution_id = Uuid::new_v4();
+        history.record(ExecutionRecord {
+            workflow_id,
+            organization_id: Some(organization_id),
+            started_at: chrono::Utc::now(),
+            result: ExecutionResult {
+                execution_id,
+                state: ExecutionState::Completed,
+                completed_at: None,
+                error: None,
+                output: Some(serde_json::json!({ "token": "s3cret" })),
+                usage: None,
+            },
+            nodes: vec![],
+        }).await;
+        assert!(is_encrypted(history.get(execution_id).await.unwrap().result.output.as_ref().unwrap()));
+
+        let json = fetch_history(&store, owner, Role::User, format!("/executions/{}", execution_id)).await;
+        assert_eq!(json["execution"]["output"]["token"], "s3cret");
+        let json = fetch_history(&store, owner, Role::User, format!("/workflows/{}/executions", workflow_id)).await;
+        assert_eq!(json["executions"][0]["result"]["output"]["token"], "s3cret");
+
+        // Callers outside the organization read the payloads as stored
+        for role in [Role::Inspector, Role::Admin] {
+            let json = fetch_history(&store, Uuid::new_v4(), role, format!("/executions/{}", execution_id)).await;
+            assert!(json["execution"]["output"]["$encrypted"].is_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failure_heatmap_route() {
+        use chrono::{TimeZone, Utc};
+        use workflow_engine::history::NodeRecord;
+
+        let history = Arc::new(ExecutionHistory::default());
+        let store = ExecutionStore::new().with_history(history.clone());
+        let workflow_id = Uuid::new_v4();
+        // Tuesday 01:30 UTC, Tuesday 03:30 in Berlin
+        let started_at = Utc.with_ymd_and_hms(2024, 5, 7, 1, 30, 0).unwrap();
+        history.record(ExecutionRecord {
+            workflow_id,
+            organization_id: None,
+            started_at,
+            result: ExecutionResult {
+                execution_id: Uuid::new_v4(),
+                state: ExecutionState::Failed,
+                completed_at: None,
+                error: Some("maintenance".to_string()),
+                output: None,
+                usage: None,
+            },
+            nodes: vec![NodeRecord {
+                state: NodeExecutionState {
+                    node_id: Uuid::new_v4(),
+                    state: ExecutionState::Failed,
+                    started_at: Some(started_at),
+                    completed_at: None,
+                    input: None,
+                    output: None,
+                    error: Some("maintenance".to_string()),
+                },
+                duration_ms: 30,
+                provider: Some("slack".to_string()),
+            }],
+        }).await;
+
+        let app = app(&store, Uuid::new_v4(), Role::Admin);
+
+        let response = app.clone()
+            .oneshot(Request::builder().uri("/analytics/failure-heatmap?tz=Europe/Berlin").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["heatmap"]["providers"]["slack"]["failures"][1][3], 1);
+        assert_eq!(json["heatmap"]["workflows"][workflow_id.to_string()]["runs"][1][3], 1);
+
+        let response = app
+            .oneshot(Request::builder().uri("/analytics/failure-heatmap?tz=Mars/Olympus").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_execution_event_timeline() {
+        use workflow_engine::events::{ExecutionEvent, ExecutionEventKind, StoredEvent};
+
+        let owner = Uuid::new_v4();
+        let (access, workflow_id) = access_to(owner).await;
+        let events = Arc::new(InMemoryEventStore::new());
+        let store = ExecutionStore::new().with_events(events.clone()).with_access(access);
+        let (execution_id, node_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let kinds = [
+            ExecutionEventKind::NodeStarted { node_id },
+            ExecutionEventKind::NodeFailed { node_id, error: "timeout".to_string() },
+            ExecutionEventKind::ExecutionFinished { state: ExecutionState::Failed, error: Some("timeout".to_string()) },
+        ];
+        events.append(kinds.into_iter().enumerate().map(|(i, kind)| StoredEvent {
+            sequence: i as u64 + 1,
+            event: ExecutionEvent {
+                execution_id,
+                workflow_id,
+                kind,
+                progress: 0.0,
+                timestamp: chrono::Utc::now(),
+            },
+        }).collect()).await.unwrap();
+
+        let response = app(&store, owner, Role::User)
+            .oneshot(Request::builder().uri(format!("/executions/{}/events?after=1", execution_id)).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["events"].as_array().unwrap().len(), 2);
+        assert_eq!(json["events"][0]["sequence"], 2);
+        assert_eq!(json["events"][0]["event"], "node_failed");
+    }
+
+    #[tokio::test]
+    async fn test_sampling_routes() {
+        let organizations = Arc::new(OrgService::new());
+        let (owner, viewer, outsider) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let acme = organizations.create_organization("Acme".to_string(), owner).await;
+        let team = organizations.create_team(acme.id, "Ops".to_string(), owner).await.unwrap();
+        organizations.add_member(acme.id, viewer, rbac_service::org::MemberRole::Member).await.unwrap();
+        organizations.add_team_member(team.id, owner).await.unwrap();
+        organizations.add_team_member(team.id, viewer).await.unwrap();
+        let (access, workflow_id) = access_to(owner).await;
+        let history = Arc::new(ExecutionHistory::default());
+        let store = ExecutionStore::new()
+            .with_history(history.clone())
+            .with_access(access.with_org_service(organizations));
+        let sampling = |user: Uuid, role: Role, method: &'static str, body: Option<serde_json::Value>| {
+            let app = Router::new()
+                .route("/workflows/:workflow_id/sampling", get(get_sampling).put(update_sampling).delete(delete_sampling))
+                .layer(Extension(claims(user, role)))
+                .with_state(store.clone());
+            let uri = format!("/workflows/{}/sampling", workflow_id);
+            async move { call(app, method, &uri, body).await }
+        };
+
+        let policy = serde_json::json!({ "success_percent": 0 });
+        let (status, _) = sampling(viewer, Role::Viewer, "PUT", Some(policy.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        // Other users' workflows are not revealed
+        for role in [Role::User, Role::Manager] {
+            let (status, _) = sampling(outsider, role, "PUT", Some(policy.clone())).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        }
+        let (status, _) = sampling(outsider, Role::User, "GET", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = sampling(owner, Role::User, "PUT", Some(serde_json::json!({ "success_percent": 120 }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = sampling(owner, Role::User, "PUT", Some(policy)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["policy"]["keep_failures"], true);
+
+        let started_at = chrono::Utc::now();
+        for _ in 0..3 {
+            history.record(ExecutionRecord {
+                workflow_id,
+                organization_id: None,
+                started_at,
+                result: ExecutionResult {
+                    execution_id: Uuid::new_v4(),
+                    state: ExecutionState::Completed,
+                    completed_at: None,
+                    error: None,
+                    output: None,
+                    usage: None,
+                },
+                nodes: vec![],
+            }).await;
+        }
+        // The first and the latest run of the hour are kept, the counters see all three
+        assert_eq!(history.list_for_workflow(workflow_id, 10).await.len(), 2);
+        let (_, body) = sampling(viewer, Role::Viewer, "GET", None).await;
+        assert_eq!(body["counters"]["runs"], 3);
+        assert_eq!(body["counters"]["sampled_out"], 1);
+
+        let (status, _) = sampling(owner, Role::User, "DELETE", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = sampling(viewer, Role::Viewer, "GET", None).await;
+        assert!(body["policy"].is_null());
+    }
+}
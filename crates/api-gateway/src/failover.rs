@@ -1,3 +1,5 @@
+use ai_service::HealthTracker;
+use async_trait::async_trait;
 use common::types::ProviderConfig;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -73,6 +75,11 @@ impl FailoverManager {
         }
     }
 
+    /// Whether a provider was registered
+    pub async fn is_registered(&self, provider: &str) -> bool {
+        self.providers.read().await.contains_key(provider)
+    }
+
     /// Check if a provider is healthy
     pub async fn is_healthy(&self, provider: &str) -> bool {
         let providers = self.providers.read().await;
@@ -178,6 +185,24 @@ impl FailoverManager {
     }
 }
 
+/// AI clients share the health of providers with the gateway. Providers
+/// the gateway does not route are assumed healthy.
+#[async_trait]
+impl HealthTracker for FailoverManager {
+    async fn is_healthy(&self, provider: &str) -> bool {
+        let providers = self.providers.read().await;
+        providers.get(provider).map(|h| h.is_healthy).unwrap_or(true)
+    }
+
+    async fn record_success(&self, provider: &str) {
+        FailoverManager::record_success(self, provider).await
+    }
+
+    async fn record_failure(&self, provider: &str) {
+        FailoverManager::record_failure(self, provider).await
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ProviderHealthStatus {
     pub is_healthy: bool,
@@ -257,4 +282,20 @@ mod tests {
             Some("backup1".to_string())
         );
     }
+
+    #[tokio::test]
+    async fn test_health_shared_with_ai_clients() {
+        let manager = FailoverManager::new(Duration::from_secs(10), 2, Duration::from_secs(30));
+        manager.register_provider(create_test_config("openai", vec![])).await;
+        let tracker: &dyn HealthTracker = &manager;
+
+        assert!(tracker.is_healthy("openai").await);
+        assert!(tracker.is_healthy("local").await);
+        tracker.record_failure("openai").await;
+        tracker.record_failure("openai").await;
+        assert!(!tracker.is_healthy("openai").await);
+        assert!(!FailoverManager::is_healthy(&manager, "openai").await);
+        tracker.record_success("openai").await;
+        assert!(tracker.is_healthy("openai").await);
+    }
 }
@@ -1,14 +1,19 @@
 use axum::{
+    body::Body,
     extract::{Multipart, Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use tokio::fs;
+use tokio_util::io::ReaderStream;
 use uuid::Uuid;
 
+use crate::errors::ApiError;
+use crate::validation::{FieldErrors, Validate, ValidJson};
+
 /// 文件服务配置
 #[derive(Clone)]
 pub struct FileServiceConfig {
@@ -58,8 +63,7 @@ pub struct FileListResponse {
 #[derive(Debug, Serialize)]
 pub struct FileUploadResponse {
     pub success: bool,
-    pub file: Option<FileInfo>,
-    pub error: Option<String>,
+    pub file: FileInfo,
 }
 
 /// 文件读取请求
@@ -72,9 +76,8 @@ pub struct ReadFileRequest {
 #[derive(Debug, Serialize)]
 pub struct ReadFileResponse {
     pub success: bool,
-    pub content: Option<String>,
-    pub file: Option<FileInfo>,
-    pub error: Option<String>,
+    pub content: String,
+    pub file: FileInfo,
 }
 
 /// 文件写入请求
@@ -84,19 +87,23 @@ pub struct WriteFileRequest {
     pub content: String,
 }
 
+impl Validate for WriteFileRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.check(!safe_file_name(&self.path).is_empty(), "path", "文件名不能为空");
+    }
+}
+
 /// 文件写入响应
 #[derive(Debug, Serialize)]
 pub struct WriteFileResponse {
     pub success: bool,
-    pub file: Option<FileInfo>,
-    pub error: Option<String>,
+    pub file: FileInfo,
 }
 
 /// 文件删除响应
 #[derive(Debug, Serialize)]
 pub struct DeleteFileResponse {
     pub success: bool,
-    pub error: Option<String>,
 }
 
 /// 初始化文件服务（创建上传目录）
@@ -156,250 +163,210 @@ pub async fn list_files(
 pub async fn upload_file(
     State(config): State<FileServiceConfig>,
     mut multipart: Multipart,
-) -> impl IntoResponse {
-    while let Ok(Some(field)) = multipart.next_field().await {
-        let _name = field.name().unwrap_or("file").to_string();
-        let file_name = field.file_name().unwrap_or("unknown").to_string();
-        
-        // 检查文件扩展名
-        let extension = std::path::Path::new(&file_name)
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-        
-        if !config.allowed_extensions.contains(&extension) {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(FileUploadResponse {
-                    success: false,
-                    file: None,
-                    error: Some(format!("不支持的文件类型: {}", extension)),
-                }),
-            );
-        }
-        
-        // 读取文件内容
-        let data = match field.bytes().await {
-            Ok(bytes) => bytes,
-            Err(e) => {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(FileUploadResponse {
-                        success: false,
-                        file: None,
-                        error: Some(format!("读取文件失败: {}", e)),
-                    }),
-                );
-            }
-        };
-        
-        // 检查文件大小
-        if data.len() > config.max_file_size {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(FileUploadResponse {
-                    success: false,
-                    file: None,
-                    error: Some(format!(
-                        "文件太大，最大允许 {} MB",
-                        config.max_file_size / 1024 / 1024
-                    )),
-                }),
-            );
-        }
-        
-        // 生成唯一文件名
-        let unique_name = format!("{}_{}", Uuid::new_v4(), file_name);
-        let file_path = config.upload_dir.join(&unique_name);
-        
-        // 写入文件
-        match fs::write(&file_path, &data).await {
-            Ok(_) => {
-                let file_info = FileInfo {
-                    id: Uuid::new_v4().to_string(),
-                    name: file_name,
-                    path: format!("/api/v1/files/{}", unique_name),
-                    size: data.len() as u64,
-                    mime_type: mime_guess::from_path(&file_path)
-                        .first_or_octet_stream()
-                        .to_string(),
-                    created_at: chrono::Utc::now().to_rfc3339(),
-                };
-                
-                return (
-                    StatusCode::OK,
-                    Json(FileUploadResponse {
-                        success: true,
-                        file: Some(file_info),
-                        error: None,
-                    }),
-                );
-            }
-            Err(e) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(FileUploadResponse {
-                        success: false,
-                        file: None,
-                        error: Some(format!("保存文件失败: {}", e)),
-                    }),
-                );
-            }
-        }
-    }
-    
-    (
-        StatusCode::BAD_REQUEST,
+) -> Result<impl IntoResponse, ApiError> {
+    let field = match multipart.next_field().await {
+        Ok(Some(field)) => field,
+        _ => return Err(ApiError::invalid_input("没有找到上传的文件")),
+    };
+    let file_name = field.file_name().unwrap_or("unknown").to_string();
+
+    // 检查文件扩展名
+    let extension = std::path::Path::new(&file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let mut errors = FieldErrors::default();
+    errors.check(
+        config.allowed_extensions.contains(&extension),
+        "file",
+        format!("不支持的文件类型: {}", extension),
+    );
+    errors.into_result()?;
+
+    // 读取文件内容
+    let data = field
+        .bytes()
+        .await
+        .map_err(|e| ApiError::invalid_input(format!("读取文件失败: {}", e)))?;
+
+    // 检查文件大小
+    let mut errors = FieldErrors::default();
+    check_size(&config, data.len(), "file", &mut errors);
+    errors.into_result()?;
+
+    // 生成唯一文件名
+    let unique_name = format!("{}_{}", Uuid::new_v4(), file_name);
+    let file_path = config.upload_dir.join(&unique_name);
+
+    // 写入文件
+    fs::write(&file_path, &data)
+        .await
+        .map_err(|e| ApiError::internal("保存文件失败", e))?;
+
+    let file_info = FileInfo {
+        id: Uuid::new_v4().to_string(),
+        name: file_name,
+        path: format!("/api/v1/files/{}", unique_name),
+        size: data.len() as u64,
+        mime_type: mime_guess::from_path(&file_path)
+            .first_or_octet_stream()
+            .to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    Ok((
+        StatusCode::OK,
         Json(FileUploadResponse {
-            success: false,
-            file: None,
-            error: Some("没有找到上传的文件".to_string()),
+            success: true,
+            file: file_info,
         }),
-    )
+    ))
 }
 
 /// 读取文件内容
 pub async fn read_file(
     State(config): State<FileServiceConfig>,
     Path(filename): Path<String>,
-) -> impl IntoResponse {
-    let file_path = config.upload_dir.join(&filename);
-    
-    // 安全检查：确保路径在上传目录内
-    if !file_path.starts_with(&config.upload_dir) {
-        return (
-            StatusCode::FORBIDDEN,
-            Json(ReadFileResponse {
-                success: false,
-                content: None,
-                file: None,
-                error: Some("访问被拒绝".to_string()),
-            }),
-        );
-    }
-    
-    match fs::read_to_string(&file_path).await {
-        Ok(content) => {
-            let metadata = fs::metadata(&file_path).await.ok();
-            
-            let file_info = FileInfo {
-                id: Uuid::new_v4().to_string(),
-                name: filename.clone(),
-                path: format!("/api/v1/files/{}", filename),
-                size: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
-                mime_type: mime_guess::from_path(&file_path)
-                    .first_or_octet_stream()
-                    .to_string(),
-                created_at: metadata
-                    .and_then(|m| m.created().ok())
-                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                    .map(|d| chrono::DateTime::from_timestamp(d.as_secs() as i64, 0)
-                        .map(|dt| dt.to_rfc3339())
-                        .unwrap_or_default())
-                    .unwrap_or_default(),
-            };
-            
-            (
-                StatusCode::OK,
-                Json(ReadFileResponse {
-                    success: true,
-                    content: Some(content),
-                    file: Some(file_info),
-                    error: None,
-                }),
-            )
-        }
-        Err(e) => (
-            StatusCode::NOT_FOUND,
-            Json(ReadFileResponse {
-                success: false,
-                content: None,
-                file: None,
-                error: Some(format!("读取文件失败: {}", e)),
-            }),
-        ),
-    }
+) -> Result<impl IntoResponse, ApiError> {
+    let file_path = contained_path(&config, &filename)?;
+
+    let content = fs::read_to_string(&file_path)
+        .await
+        .map_err(|e| ApiError::not_found(format!("读取文件失败: {}", e)))?;
+    let metadata = fs::metadata(&file_path).await.ok();
+
+    let file_info = FileInfo {
+        id: Uuid::new_v4().to_string(),
+        name: filename.clone(),
+        path: format!("/api/v1/files/{}", filename),
+        size: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+        mime_type: mime_guess::from_path(&file_path)
+            .first_or_octet_stream()
+            .to_string(),
+        created_at: metadata
+            .and_then(|m| m.created().ok())
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| chrono::DateTime::from_timestamp(d.as_secs() as i64, 0)
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_default())
+            .unwrap_or_default(),
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(ReadFileResponse {
+            success: true,
+            content,
+            file: file_info,
+        }),
+    ))
+}
+
+/// 下载文件：按原始字节返回，适用于二进制文件
+pub async fn download_file(
+    State(config): State<FileServiceConfig>,
+    Path(filename): Path<String>,
+) -> Result<Response, ApiError> {
+    let file_path = contained_path(&config, &filename)?;
+    let content_type = mime_guess::from_path(&file_path).first_or_octet_stream();
+    file_download(&file_path, &filename, content_type.as_ref()).await
+}
+
+/// 以附件形式返回文件，边读边发送，不把整个文件读入内存
+pub async fn file_download(
+    path: &std::path::Path,
+    download_name: &str,
+    content_type: &str,
+) -> Result<Response, ApiError> {
+    let file = fs::File::open(path)
+        .await
+        .map_err(|e| ApiError::not_found(format!("读取文件失败: {}", e)))?;
+    let size = file
+        .metadata()
+        .await
+        .map_err(|e| ApiError::internal("读取文件失败", e))?
+        .len();
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, size)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", safe_file_name(download_name).replace('"', "")),
+        )
+        .body(Body::from_stream(ReaderStream::new(file)))
+        .map_err(|e| ApiError::internal("读取文件失败", e))
 }
 
 /// 写入文件
 pub async fn write_file(
     State(config): State<FileServiceConfig>,
-    Json(req): Json<WriteFileRequest>,
-) -> impl IntoResponse {
-    // 清理文件名
-    let safe_name = req.path
-        .replace("..", "")
-        .replace("/", "_")
-        .replace("\\", "_");
-    
+    ValidJson(req): ValidJson<WriteFileRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut errors = FieldErrors::default();
+    check_size(&config, req.content.len(), "content", &mut errors);
+    errors.into_result()?;
+
+    let safe_name = safe_file_name(&req.path);
     let file_path = config.upload_dir.join(&safe_name);
-    
-    match fs::write(&file_path, &req.content).await {
-        Ok(_) => {
-            let file_info = FileInfo {
-                id: Uuid::new_v4().to_string(),
-                name: safe_name.clone(),
-                path: format!("/api/v1/files/{}", safe_name),
-                size: req.content.len() as u64,
-                mime_type: mime_guess::from_path(&file_path)
-                    .first_or_octet_stream()
-                    .to_string(),
-                created_at: chrono::Utc::now().to_rfc3339(),
-            };
-            
-            (
-                StatusCode::OK,
-                Json(WriteFileResponse {
-                    success: true,
-                    file: Some(file_info),
-                    error: None,
-                }),
-            )
-        }
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(WriteFileResponse {
-                success: false,
-                file: None,
-                error: Some(format!("写入文件失败: {}", e)),
-            }),
-        ),
-    }
+
+    fs::write(&file_path, &req.content)
+        .await
+        .map_err(|e| ApiError::internal("写入文件失败", e))?;
+
+    let file_info = FileInfo {
+        id: Uuid::new_v4().to_string(),
+        name: safe_name.clone(),
+        path: format!("/api/v1/files/{}", safe_name),
+        size: req.content.len() as u64,
+        mime_type: mime_guess::from_path(&file_path)
+            .first_or_octet_stream()
+            .to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(WriteFileResponse {
+            success: true,
+            file: file_info,
+        }),
+    ))
 }
 
 /// 删除文件
 pub async fn delete_file(
     State(config): State<FileServiceConfig>,
     Path(filename): Path<String>,
-) -> impl IntoResponse {
-    let file_path = config.upload_dir.join(&filename);
-    
-    // 安全检查
+) -> Result<impl IntoResponse, ApiError> {
+    let file_path = contained_path(&config, &filename)?;
+
+    fs::remove_file(&file_path)
+        .await
+        .map_err(|e| ApiError::not_found(format!("删除文件失败: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(DeleteFileResponse { success: true })))
+}
+
+/// 上传目录中的文件路径；目录之外的路径被拒绝
+fn contained_path(config: &FileServiceConfig, filename: &str) -> Result<PathBuf, ApiError> {
+    let file_path = config.upload_dir.join(filename);
     if !file_path.starts_with(&config.upload_dir) {
-        return (
-            StatusCode::FORBIDDEN,
-            Json(DeleteFileResponse {
-                success: false,
-                error: Some("访问被拒绝".to_string()),
-            }),
-        );
-    }
-    
-    match fs::remove_file(&file_path).await {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(DeleteFileResponse {
-                success: true,
-                error: None,
-            }),
-        ),
-        Err(e) => (
-            StatusCode::NOT_FOUND,
-            Json(DeleteFileResponse {
-                success: false,
-                error: Some(format!("删除文件失败: {}", e)),
-            }),
-        ),
+        return Err(ApiError::forbidden("访问被拒绝"));
     }
+    Ok(file_path)
+}
+
+/// 清理文件名：去掉上级目录并把路径分隔符换成下划线
+fn safe_file_name(path: &str) -> String {
+    path.replace("..", "").replace(['/', '\\'], "_")
+}
+
+fn check_size(config: &FileServiceConfig, size: usize, field: &str, errors: &mut FieldErrors) {
+    errors.check(
+        size <= config.max_file_size,
+        field,
+        format!("文件太大，最大允许 {} MB", config.max_file_size / 1024 / 1024),
+    );
 }
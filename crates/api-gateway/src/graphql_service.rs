@@ -0,0 +1,353 @@
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Enum, Object, Result, Schema, SimpleObject,
+};
+use axum::{extract::State, Json};
+use chrono::{DateTime, Utc};
+use common::types::Workflow;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::history::{ExecutionHistory, ExecutionRecord, NodeRecord};
+use workflow_engine::RevisionStore;
+
+use crate::execution_service::ExecutionStore;
+
+/// Executions listed per workflow when no limit is given
+const DEFAULT_EXECUTION_LIMIT: usize = 20;
+
+pub type FlowvexSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// GraphQL service state
+#[derive(Clone)]
+pub struct GraphqlServiceState {
+    pub schema: FlowvexSchema,
+    revisions: Arc<RevisionStore>,
+    history: Arc<ExecutionHistory>,
+}
+
+impl GraphqlServiceState {
+    pub fn new(revisions: Arc<RevisionStore>, executions: &ExecutionStore) -> Self {
+        Self {
+            schema: Schema::build(QueryRoot, EmptyMutation, EmptySubscription).finish(),
+            revisions,
+            history: executions.history().clone(),
+        }
+    }
+}
+
+/// 执行 GraphQL 查询（工作流、执行记录、节点运行和指标的只读模型）
+pub async fn graphql_handler(
+    State(state): State<GraphqlServiceState>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    // Loaders are per request, so batches never mix data across callers
+    let request = request
+        .data(state.history.clone())
+        .data(DataLoader::new(WorkflowLoader(state.revisions.clone()), tokio::spawn))
+        .data(DataLoader::new(ExecutionsLoader(state.history.clone()), tokio::spawn));
+    Json(state.schema.execute(request).await)
+}
+
+/// Batches workflow lookups into one revision store read
+pub struct WorkflowLoader(Arc<RevisionStore>);
+
+impl Loader<Uuid> for WorkflowLoader {
+    type Value = Workflow;
+    type Error = Infallible;
+
+    async fn load(&self, keys: &[Uuid]) -> Result<HashMap<Uuid, Workflow>, Infallible> {
+        Ok(self.0.latest_many(keys).await)
+    }
+}
+
+/// Batches the retained executions of several workflows into one history read
+pub struct ExecutionsLoader(Arc<ExecutionHistory>);
+
+impl Loader<Uuid> for ExecutionsLoader {
+    type Value = Vec<ExecutionRecord>;
+    type Error = Infallible;
+
+    async fn load(&self, keys: &[Uuid]) -> Result<HashMap<Uuid, Vec<ExecutionRecord>>, Infallible> {
+        Ok(self.0.list_for_workflows(keys, usize::MAX).await)
+    }
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+#[graphql(name = "ExecutionState", remote = "common::types::ExecutionState")]
+pub enum GqlExecutionState {
+    Pending,
+    Running,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Newest definition of a workflow, pending revision included
+    async fn workflow(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<GqlWorkflow>> {
+        let loader = ctx.data_unchecked::<DataLoader<WorkflowLoader>>();
+        Ok(loader.load_one(id).await?.map(GqlWorkflow))
+    }
+
+    /// Workflows in the order asked for; unknown IDs are left out
+    async fn workflows(&self, ctx: &Context<'_>, ids: Vec<Uuid>) -> Result<Vec<GqlWorkflow>> {
+        let loader = ctx.data_unchecked::<DataLoader<WorkflowLoader>>();
+        let mut found = loader.load_many(ids.iter().copied()).await?;
+        Ok(ids.iter().filter_map(|id| found.remove(id)).map(GqlWorkflow).collect())
+    }
+
+    async fn execution(&self, ctx: &Context<'_>, id: Uuid) -> Option<GqlExecution> {
+        let history = ctx.data_unchecked::<Arc<ExecutionHistory>>();
+        history.get(id).await.map(GqlExecution)
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct WorkflowMetrics {
+    /// Retained executions
+    pub executions: usize,
+    pub failed: usize,
+    /// Share of retained executions that completed, 0 without executions
+    pub success_rate: f64,
+    /// Mean wall time of finished executions
+    pub avg_duration_ms: Option<f64>,
+}
+
+impl WorkflowMetrics {
+    fn from_records(records: &[ExecutionRecord]) -> Self {
+        use common::types::ExecutionState;
+
+        let count = |state: ExecutionState| records.iter().filter(|r| r.result.state == state).count();
+        let durations: Vec<i64> = records.iter()
+            .filter_map(|r| Some((r.result.completed_at? - r.started_at).num_milliseconds()))
+            .collect();
+        Self {
+            executions: records.len(),
+            failed: count(ExecutionState::Failed),
+            success_rate: if records.is_empty() {
+                0.0
+            } else {
+                count(ExecutionState::Completed) as f64 / records.len() as f64
+            },
+            avg_duration_ms: (!durations.is_empty())
+                .then(|| durations.iter().sum::<i64>() as f64 / durations.len() as f64),
+        }
+    }
+}
+
+pub struct GqlWorkflow(Workflow);
+
+#[Object(name = "Workflow")]
+impl GqlWorkflow {
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.0.description.as_deref()
+    }
+
+    async fn node_count(&self) -> usize {
+        self.0.nodes.len()
+    }
+
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.0.updated_at
+    }
+
+    /// Recent executions, newest first
+    async fn executions(&self, ctx: &Context<'_>, limit: Option<usize>) -> Result<Vec<GqlExecution>> {
+        let loader = ctx.data_unchecked::<DataLoader<ExecutionsLoader>>();
+        let records = loader.load_one(self.0.id).await?.unwrap_or_default();
+        let limit = limit.unwrap_or(DEFAULT_EXECUTION_LIMIT);
+        Ok(records.into_iter().take(limit).map(GqlExecution).collect())
+    }
+
+    /// Metrics over the retained executions
+    async fn metrics(&self, ctx: &Context<'_>) -> Result<WorkflowMetrics> {
+        let loader = ctx.data_unchecked::<DataLoader<ExecutionsLoader>>();
+        let records = loader.load_one(self.0.id).await?.unwrap_or_default();
+        Ok(WorkflowMetrics::from_records(&records))
+    }
+}
+
+pub struct GqlExecution(ExecutionRecord);
+
+#[Object(name = "Execution")]
+impl GqlExecution {
+    async fn id(&self) -> Uuid {
+        self.0.result.execution_id
+    }
+
+    async fn workflow_id(&self) -> Uuid {
+        self.0.workflow_id
+    }
+
+    async fn state(&self) -> GqlExecutionState {
+        self.0.result.state.clone().into()
+    }
+
+    async fn started_at(&self) -> DateTime<Utc> {
+        self.0.started_at
+    }
+
+    async fn completed_at(&self) -> Option<DateTime<Utc>> {
+        self.0.result.completed_at
+    }
+
+    async fn error(&self) -> Option<&str> {
+        self.0.result.error.as_deref()
+    }
+
+    async fn workflow(&self, ctx: &Context<'_>) -> Result<Option<GqlWorkflow>> {
+        let loader = ctx.data_unchecked::<DataLoader<WorkflowLoader>>();
+        Ok(loader.load_one(self.0.workflow_id).await?.map(GqlWorkflow))
+    }
+
+    /// Top-level node runs in execution order
+    async fn nodes(&self) -> Vec<GqlNodeRun> {
+        self.0.nodes.iter().cloned().map(GqlNodeRun).collect()
+    }
+}
+
+pub struct GqlNodeRun(NodeRecord);
+
+#[Object(name = "NodeRun")]
+impl GqlNodeRun {
+    async fn node_id(&self) -> Uuid {
+        self.0.state.node_id
+    }
+
+    async fn state(&self) -> GqlExecutionState {
+        self.0.state.state.clone().into()
+    }
+
+    async fn started_at(&self) -> Option<DateTime<Utc>> {
+        self.0.state.started_at
+    }
+
+    async fn completed_at(&self) -> Option<DateTime<Utc>> {
+        self.0.state.completed_at
+    }
+
+    async fn duration_ms(&self) -> u64 {
+        self.0.duration_ms
+    }
+
+    async fn provider(&self) -> Option<&str> {
+        self.0.provider.as_deref()
+    }
+
+    async fn input(&self) -> Option<async_graphql::Json<serde_json::Value>> {
+        self.0.state.input.clone().map(async_graphql::Json)
+    }
+
+    async fn output(&self) -> Option<async_graphql::Json<serde_json::Value>> {
+        self.0.state.output.clone().map(async_graphql::Json)
+    }
+
+    async fn error(&self) -> Option<&str> {
+        self.0.state.error.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::post;
+    use axum::Router;
+    use common::types::{ExecutionResult, ExecutionState, NodeExecutionState, WORKFLOW_SCHEMA_VERSION};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_nested_workflow_query() {
+        let revisions = Arc::new(RevisionStore::new());
+        let history = Arc::new(ExecutionHistory::default());
+        let executions = ExecutionStore::new().with_history(history.clone());
+        let workflow_id = Uuid::new_v4();
+        revisions.submit(Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: workflow_id,
+            name: "Nightly export".to_string(),
+            description: None,
+            nodes: vec![],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        }, Uuid::new_v4()).await;
+
+        for state in [ExecutionState::Completed, ExecutionState::Failed] {
+            history.record(ExecutionRecord {
+                workflow_id,
+                organization_id: None,
+                started_at: Utc::now(),
+                result: ExecutionResult {
+                    execution_id: Uuid::new_v4(),
+                    state: state.clone(),
+                    completed_at: Some(Utc::now()),
+                    error: None,
+                    output: None,
+                    usage: None,
+                },
+                nodes: vec![NodeRecord {
+                    state: NodeExecutionState {
+                        node_id: Uuid::new_v4(),
+                        state,
+                        started_at: None,
+                        completed_at: None,
+                        input: None,
+                        output: Some(serde_json::json!({ "rows": 3 })),
+                        error: None,
+                    },
+                    duration_ms: 7,
+                    provider: Some("s3".to_string()),
+                }],
+            }).await;
+        }
+
+        let app = Router::new()
+            .route("/graphql", post(graphql_handler))
+            .with_state(GraphqlServiceState::new(revisions, &executions));
+        let query = format!(
+            r#"{{ workflows(ids: ["{id}", "{unknown}"]) {{ name metrics {{ executions failed successRate }}
+                executions(limit: 1) {{ state workflow {{ name }} nodes {{ durationMs provider output }} }} }} }}"#,
+            id = workflow_id,
+            unknown = Uuid::new_v4(),
+        );
+        let request = Request::builder()
+            .method("POST")
+            .uri("/graphql")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::json!({ "query": query }).to_string()))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert!(json["errors"].is_null(), "{}", json);
+        let workflows = json["data"]["workflows"].as_array().unwrap();
+        assert_eq!(workflows.len(), 1);
+        assert_eq!(workflows[0]["metrics"]["executions"], 2);
+        assert_eq!(workflows[0]["metrics"]["failed"], 1);
+        assert_eq!(workflows[0]["metrics"]["successRate"], 0.5);
+        let execution = &workflows[0]["executions"][0];
+        assert_eq!(execution["state"], "FAILED");
+        assert_eq!(execution["workflow"]["name"], "Nightly export");
+        assert_eq!(execution["nodes"][0]["output"]["rows"], 3);
+        assert_eq!(execution["nodes"][0]["provider"], "s3");
+    }
+}
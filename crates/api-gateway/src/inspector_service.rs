@@ -0,0 +1,345 @@
+use audit_service::AuditLogger;
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use common::types::{
+    ActionType2, AuditAction, AuditLog, AuditResult, NodeUsage, Permission, ResourceType, Role, Scope,
+};
+use integration_service::sharing::redact_credentials;
+use rbac_service::jwt::JwtClaims;
+use rbac_service::permissions::permission_string;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::execution_service::{ExecutionStore, ExecutionTrace};
+use crate::request_limiter::client_ip;
+
+/// Inspector service state
+#[derive(Clone)]
+pub struct InspectorState {
+    executions: ExecutionStore,
+    audit: Option<Arc<AuditLogger>>,
+    /// Inspections recorded in this process (for development, the audit logger persists them)
+    inspections: Arc<RwLock<Vec<AuditLog>>>,
+    trust_forwarded_for: bool,
+}
+
+impl InspectorState {
+    pub fn new(executions: ExecutionStore) -> Self {
+        Self {
+            executions,
+            audit: None,
+            inspections: Arc::new(RwLock::new(Vec::new())),
+            trust_forwarded_for: false,
+        }
+    }
+
+    /// Forward every inspection to the audit log
+    pub fn with_audit_logger(mut self, audit: Arc<AuditLogger>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Record the client IP from `X-Forwarded-For` as the request limiter
+    /// does; only enable behind a proxy that appends it
+    pub fn with_trusted_forwarded_for(mut self, trust_forwarded_for: bool) -> Self {
+        self.trust_forwarded_for = trust_forwarded_for;
+        self
+    }
+
+    /// Inspections recorded so far, oldest first
+    pub async fn inspections(&self) -> Vec<AuditLog> {
+        self.inspections.read().await.clone()
+    }
+
+    async fn record(
+        &self,
+        claims: &JwtClaims,
+        headers: &HeaderMap,
+        peer: Option<ConnectInfo<SocketAddr>>,
+        execution_id: Uuid,
+        result: AuditResult,
+        details: serde_json::Value,
+    ) {
+        let ip_address = client_ip(headers, peer.map(|ConnectInfo(addr)| addr.ip()), self.trust_forwarded_for)
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let user_agent = headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let mut log = AuditLog::new(
+            claims.sub,
+            AuditAction::Inspect,
+            ResourceType::Execution,
+            execution_id,
+            ip_address,
+            user_agent,
+            result,
+        );
+        log.details = details;
+
+        if let Some(audit) = &self.audit {
+            if let Err(e) = audit.log(log.clone()) {
+                tracing::error!("Failed to record inspection of {}: {}", execution_id, e);
+            }
+        }
+        self.inspections.write().await.push(log);
+    }
+}
+
+/// Whether the caller may inspect executions of any organization
+fn can_inspect(claims: &JwtClaims) -> bool {
+    let required = permission_string(&Permission {
+        resource: ResourceType::Execution,
+        action: ActionType2::Inspect,
+        scope: Scope::All,
+    });
+    matches!(claims.role, Role::Admin | Role::Inspector) || claims.permissions.contains(&required)
+}
+
+/// 只读检查任意组织的执行：执行图、节点耗时以及脱敏后的输入输出，每次检查都会写入审计日志
+pub async fn inspect_execution(
+    State(state): State<InspectorState>,
+    Extension(claims): Extension<JwtClaims>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Path(execution_id): Path<Uuid>,
+) -> impl IntoResponse {
+    if !can_inspect(&claims) {
+        state
+            .record(&claims, &headers, peer, execution_id, AuditResult::Denied, serde_json::json!({}))
+            .await;
+        return error_response(StatusCode::FORBIDDEN, "没有检查执行的权限".to_string());
+    }
+
+    let Some(result) = state.executions.get(execution_id).await else {
+        state
+            .record(
+                &claims,
+                &headers,
+                peer,
+                execution_id,
+                AuditResult::Failure("execution not found".to_string()),
+                serde_json::json!({}),
+            )
+            .await;
+        return error_response(StatusCode::NOT_FOUND, format!("执行记录不存在: {}", execution_id));
+    };
+    let trace = state.executions.get_trace(execution_id).await;
+    let usage = result.usage.clone().unwrap_or_default();
+
+    let inspection = serde_json::json!({
+        "execution_id": execution_id,
+        "state": result.state,
+        "completed_at": result.completed_at,
+        "error": result.error,
+        "output": result.output.as_ref().map(redact_credentials),
+        "organization_id": trace.as_ref().and_then(|t| t.organization_id),
+        "graph": trace.as_ref().map(graph),
+        "nodes": node_views(trace.as_ref(), &usage.nodes),
+        "wall_time_ms": usage.wall_time_ms,
+        "totals": usage.totals,
+    });
+
+    state
+        .record(
+            &claims,
+            &headers,
+            peer,
+            execution_id,
+            AuditResult::Success,
+            serde_json::json!({
+                "organization_id": inspection["organization_id"],
+                "workflow_id": trace.as_ref().map(|t| t.workflow.id),
+            }),
+        )
+        .await;
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "inspection": inspection
+        })),
+    )
+}
+
+/// Workflow structure without node parameters (which may hold credentials)
+fn graph(trace: &ExecutionTrace) -> serde_json::Value {
+    let nodes: Vec<_> = trace.workflow.nodes.iter()
+        .map(|node| serde_json::json!({
+            "id": node.id,
+            "node_type": node.node_type,
+            "position": node.position,
+        }))
+        .collect();
+
+    serde_json::json!({
+        "workflow_id": trace.workflow.id,
+        "name": trace.workflow.name,
+        "nodes": nodes,
+        "edges": trace.workflow.edges,
+    })
+}
+
+/// Per-node timings merged with the traced node states, inputs and outputs redacted
+fn node_views(trace: Option<&ExecutionTrace>, usage: &[NodeUsage]) -> Vec<serde_json::Value> {
+    let timing = |node_id: Uuid| usage.iter().find(|n| n.node_id == node_id);
+
+    let mut views: Vec<_> = trace.map(|t| t.nodes.as_slice()).unwrap_or_default().iter()
+        .map(|node| {
+            let node_usage = timing(node.node_id);
+            serde_json::json!({
+                "node_id": node.node_id,
+                "state": node.state,
+                "started_at": node.started_at,
+                "completed_at": node.completed_at,
+                "wall_time_ms": node_usage.map(|n| n.wall_time_ms),
+                "usage": node_usage.map(|n| &n.usage),
+                "input": node.input.as_ref().map(redact_credentials),
+                "output": node.output.as_ref().map(redact_credentials),
+                "error": node.error,
+            })
+        })
+        .collect();
+
+    // Nodes that reported usage but were not traced still show their timings
+    for node in usage {
+        if !trace.is_some_and(|t| t.nodes.iter().any(|n| n.node_id == node.node_id)) {
+            views.push(serde_json::json!({
+                "node_id": node.node_id,
+                "wall_time_ms": node.wall_time_ms,
+                "usage": node.usage,
+            }));
+        }
+    }
+    views
+}
+
+fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::claims;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::get;
+    use axum::Router;
+    use common::types::{
+        ExecutionResult, ExecutionState, ExecutionUsage, Node, NodeConfig, NodeExecutionState, NodeType,
+        Position, ResourceUsage, TriggerType, Workflow, WORKFLOW_SCHEMA_VERSION,
+    };
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    fn app(state: InspectorState, role: Role) -> Router {
+        Router::new()
+            .route("/inspector/executions/:execution_id", get(inspect_execution))
+            .layer(Extension(claims(Uuid::new_v4(), role)))
+            .with_state(state)
+    }
+
+    async fn inspect(app: Router, execution_id: Uuid) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/inspector/executions/{}", execution_id))
+                    .header("user-agent", "support-console")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_inspection_is_redacted_and_audited() {
+        let store = ExecutionStore::new();
+        let execution_id = Uuid::new_v4();
+        let node_id = Uuid::new_v4();
+        let organization_id = Uuid::new_v4();
+
+        let mut usage = ExecutionUsage::default();
+        usage.record_node(node_id, 120, ResourceUsage::default());
+        store.record(ExecutionResult {
+            execution_id,
+            state: ExecutionState::Completed,
+            completed_at: None,
+            error: None,
+            output: None,
+            usage: Some(usage),
+        }).await;
+        store.record_trace(execution_id, ExecutionTrace {
+            organization_id: Some(organization_id),
+            workflow: Workflow {
+                version: WORKFLOW_SCHEMA_VERSION,
+                id: Uuid::new_v4(),
+                name: "Webhook".to_string(),
+                description: None,
+                nodes: vec![Node {
+                    id: node_id,
+                    node_type: NodeType::Trigger { trigger_type: TriggerType::Webhook },
+                    config: NodeConfig {
+                        parameters: HashMap::from([("secret".to_string(), serde_json::json!("hook-secret"))]),
+                        ..Default::default()
+                    },
+                    position: Position { x: 0.0, y: 0.0 },
+                    inputs: vec![],
+                    outputs: vec![],
+                }],
+                edges: vec![],
+                variables: HashMap::new(),
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+                trash: Default::default(),
+            },
+            nodes: vec![NodeExecutionState {
+                node_id,
+                state: ExecutionState::Completed,
+                started_at: None,
+                completed_at: None,
+                input: Some(serde_json::json!({ "headers": { "Authorization": "Bearer abc" } })),
+                output: Some(serde_json::json!({ "status": 200 })),
+                error: None,
+            }],
+        }).await;
+
+        let state = InspectorState::new(store);
+        let (status, body) = inspect(app(state.clone(), Role::Inspector), execution_id).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!body.to_string().contains("hook-secret") && !body.to_string().contains("Bearer abc"));
+        let node = &body["inspection"]["nodes"][0];
+        assert_eq!(node["wall_time_ms"], 120);
+        assert_eq!(node["output"]["status"], 200);
+        assert_eq!(body["inspection"]["organization_id"], organization_id.to_string());
+
+        let (status, _) = inspect(app(state.clone(), Role::Viewer), execution_id).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let inspections = state.inspections().await;
+        assert_eq!(inspections.len(), 2);
+        assert!(matches!(inspections[0].result, AuditResult::Success));
+        assert_eq!(inspections[0].user_agent, "support-console");
+        assert!(matches!(inspections[1].result, AuditResult::Denied));
+    }
+}
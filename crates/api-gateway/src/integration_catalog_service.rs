@@ -0,0 +1,192 @@
+use axum::{
+    extract::{FromRef, Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use integration_service::{IntegrationFilter, IntegrationRegistry};
+use rbac_service::middleware::AdminOnly;
+use rbac_service::{AuthUser, JwtManager};
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::errors::ApiError;
+
+/// Integration catalog service state
+#[derive(Clone)]
+pub struct IntegrationCatalogServiceState {
+    pub integrations: Arc<IntegrationRegistry>,
+    pub jwt_manager: Arc<JwtManager>,
+}
+
+impl IntegrationCatalogServiceState {
+    pub fn new(integrations: Arc<IntegrationRegistry>, jwt_manager: Arc<JwtManager>) -> Self {
+        Self { integrations, jwt_manager }
+    }
+}
+
+impl FromRef<IntegrationCatalogServiceState> for Arc<JwtManager> {
+    fn from_ref(state: &IntegrationCatalogServiceState) -> Self {
+        state.jwt_manager.clone()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnablementRequest {
+    pub enabled: bool,
+}
+
+/// 列出可用的集成：可按分类、标签筛选；给出组织时只列出该组织已启用的集成
+pub async fn list_integrations(
+    _user: AuthUser,
+    State(state): State<IntegrationCatalogServiceState>,
+    Query(filter): Query<IntegrationFilter>,
+) -> impl IntoResponse {
+    let integrations = state.integrations.list(&filter).await;
+    Json(serde_json::json!({
+        "success": true,
+        "total": integrations.len(),
+        "integrations": integrations
+    }))
+}
+
+/// 列出集成目录（仅管理员），包括其他实例注册的集成及其操作
+pub async fn list_integration_catalog(
+    _admin: AuthUser<AdminOnly>,
+    State(state): State<IntegrationCatalogServiceState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let entries = state
+        .integrations
+        .catalog()
+        .await
+        .map_err(|e| ApiError::from_error("读取集成目录失败", &e))?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "total": entries.len(),
+        "integrations": entries
+    })))
+}
+
+/// 查询组织的集成启用设置（仅管理员）；没有设置的集成及操作均为启用
+pub async fn list_organization_integrations(
+    _admin: AuthUser<AdminOnly>,
+    State(state): State<IntegrationCatalogServiceState>,
+    Path(organization_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let settings = state.integrations.settings_for(organization_id).await;
+    Json(serde_json::json!({
+        "success": true,
+        "settings": settings
+    }))
+}
+
+/// 为组织启用或停用集成（仅管理员）；已停用的操作在重新启用集成后仍保持停用
+pub async fn set_integration_enablement(
+    admin: AuthUser<AdminOnly>,
+    State(state): State<IntegrationCatalogServiceState>,
+    Path((organization_id, integration)): Path<(Uuid, String)>,
+    Json(request): Json<EnablementRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let setting = state
+        .integrations
+        .set_integration_enabled(organization_id, &integration, request.enabled, admin.claims.sub)
+        .await
+        .map_err(|e| ApiError::from_error("更新集成启用设置失败", &e))?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "setting": setting
+    })))
+}
+
+/// 为组织启用或停用集成的单个操作（仅管理员）
+pub async fn set_action_enablement(
+    admin: AuthUser<AdminOnly>,
+    State(state): State<IntegrationCatalogServiceState>,
+    Path((organization_id, integration, action)): Path<(Uuid, String, String)>,
+    Json(request): Json<EnablementRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let setting = state
+        .integrations
+        .set_action_enabled(organization_id, &integration, &action, request.enabled, admin.claims.sub)
+        .await
+        .map_err(|e| ApiError::from_error("更新操作启用设置失败", &e))?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "setting": setting
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::call_with_token;
+    use axum::http::StatusCode;
+    use axum::routing::{get, put};
+    use axum::Router;
+    use common::types::{JsonValue, Role};
+
+    fn app(state: IntegrationCatalogServiceState) -> Router {
+        Router::new()
+            .route("/integrations", get(list_integrations))
+            .route("/admin/integrations", get(list_integration_catalog))
+            .route("/admin/organizations/:organization_id/integrations", get(list_organization_integrations))
+            .route("/admin/organizations/:organization_id/integrations/:integration", put(set_integration_enablement))
+            .route(
+                "/admin/organizations/:organization_id/integrations/:integration/actions/:action",
+                put(set_action_enablement),
+            )
+            .with_state(state)
+    }
+
+    async fn call(state: &IntegrationCatalogServiceState, token: &str, method: &str, uri: &str, body: JsonValue) -> (StatusCode, JsonValue) {
+        call_with_token(app(state.clone()), token, method, uri, Some(body)).await
+    }
+
+    #[tokio::test]
+    async fn test_integration_enablement() {
+        let jwt_manager = Arc::new(JwtManager::new("secret", 1));
+        let integrations = Arc::new(IntegrationRegistry::new());
+        integrations.load_catalog().await.unwrap();
+        let state = IntegrationCatalogServiceState::new(integrations.clone(), jwt_manager.clone());
+        let admin_token = jwt_manager.generate_token(Uuid::new_v4(), Role::Admin, vec![]).unwrap();
+        let user_token = jwt_manager.generate_token(Uuid::new_v4(), Role::User, vec![]).unwrap();
+        let organization_id = Uuid::new_v4();
+
+        let (status, body) = call(&state, &user_token, "GET", "/integrations?category=Storage", JsonValue::Null).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["integrations"][0]["name"], "s3");
+        let (_, body) = call(&state, &user_token, "GET", "/integrations?tag=sql", JsonValue::Null).await;
+        assert_eq!(body["integrations"][0]["name"], "database");
+
+        let uri = format!("/admin/organizations/{}/integrations/slack", organization_id);
+        let disable = serde_json::json!({ "enabled": false });
+        let (status, _) = call(&state, &user_token, "PUT", &uri, disable.clone()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = call(&state, &admin_token, "PUT", &uri, disable.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["setting"]["enabled"], false);
+
+        let uri = format!("/admin/organizations/{}/integrations/github/actions/create_issue", organization_id);
+        let (status, body) = call(&state, &admin_token, "PUT", &uri, disable.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["setting"]["disabled_actions"], serde_json::json!(["create_issue"]));
+        let uri = format!("/admin/organizations/{}/integrations/github/actions/delete_repo", organization_id);
+        let (status, _) = call(&state, &admin_token, "PUT", &uri, disable).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let uri = format!("/integrations?organization_id={}", organization_id);
+        let (_, body) = call(&state, &user_token, "GET", &uri, JsonValue::Null).await;
+        let names: Vec<&str> = body["integrations"].as_array().unwrap().iter().map(|i| i["name"].as_str().unwrap()).collect();
+        assert!(names.contains(&"github") && !names.contains(&"slack"));
+
+        let uri = format!("/admin/organizations/{}/integrations", organization_id);
+        let (_, body) = call(&state, &admin_token, "GET", &uri, JsonValue::Null).await;
+        assert_eq!(body["settings"].as_array().unwrap().len(), 2);
+        let (_, body) = call(&state, &admin_token, "GET", "/admin/integrations", JsonValue::Null).await;
+        assert_eq!(body["total"], 7);
+    }
+}
@@ -0,0 +1,207 @@
+use common::types::LatencyPercentiles;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Relative width of the sketch buckets: quantiles are within 5% of the
+/// recorded latencies
+const GAMMA: f64 = 1.1;
+
+/// Buckets of a sketch; latencies past the last one (about two days) are
+/// counted in it
+const BUCKETS: usize = 200;
+
+/// Minutes of per-minute sketches kept for the windowed views
+const WINDOW_MINUTES: u64 = 60;
+
+/// Fixed-size sketch of latencies in logarithmic buckets, answering
+/// quantiles with bounded relative error
+#[derive(Debug, Clone)]
+pub struct LatencySketch {
+    counts: [u32; BUCKETS],
+    count: u64,
+    sum_ms: u64,
+}
+
+impl Default for LatencySketch {
+    fn default() -> Self {
+        Self {
+            counts: [0; BUCKETS],
+            count: 0,
+            sum_ms: 0,
+        }
+    }
+}
+
+impl LatencySketch {
+    pub fn record(&mut self, latency_ms: u64) {
+        let bucket = if latency_ms <= 1 {
+            0
+        } else {
+            ((latency_ms as f64).ln() / GAMMA.ln()).ceil() as usize
+        };
+        let bucket = bucket.min(BUCKETS - 1);
+        self.counts[bucket] = self.counts[bucket].saturating_add(1);
+        self.count += 1;
+        self.sum_ms += latency_ms;
+    }
+
+    pub fn merge(&mut self, other: &LatencySketch) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count = count.saturating_add(*other);
+        }
+        self.count += other.count;
+        self.sum_ms += other.sum_ms;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum_ms as f64 / self.count as f64
+        }
+    }
+
+    /// Latency at quantile `q` (0 to 1); 0 when nothing was recorded
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let rank = (q.clamp(0.0, 1.0) * (self.count - 1) as f64).floor() as u64;
+        let mut seen = 0;
+        for (bucket, &count) in self.counts.iter().enumerate() {
+            seen += count as u64;
+            if seen > rank {
+                return Self::bucket_value(bucket);
+            }
+        }
+        Self::bucket_value(BUCKETS - 1)
+    }
+
+    /// Value reported for a bucket: the middle of its bounds
+    fn bucket_value(bucket: usize) -> f64 {
+        if bucket == 0 {
+            return 1.0;
+        }
+        2.0 * GAMMA.powi(bucket as i32) / (GAMMA + 1.0)
+    }
+
+    pub fn percentiles(&self) -> LatencyPercentiles {
+        LatencyPercentiles {
+            requests: self.count,
+            p50_ms: self.quantile(0.50),
+            p95_ms: self.quantile(0.95),
+            p99_ms: self.quantile(0.99),
+        }
+    }
+}
+
+/// Latencies of a provider since startup and per minute over the last hour
+#[derive(Debug, Clone)]
+pub struct LatencyTracker {
+    started: Instant,
+    total: LatencySketch,
+    /// Sketches of recent minutes, oldest first, by minute since `started`
+    minutes: VecDeque<(u64, LatencySketch)>,
+}
+
+impl Default for LatencyTracker {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            total: LatencySketch::default(),
+            minutes: VecDeque::new(),
+        }
+    }
+}
+
+impl LatencyTracker {
+    pub fn record(&mut self, latency_ms: u64) {
+        let minute = self.minute();
+        self.record_at(minute, latency_ms);
+    }
+
+    fn record_at(&mut self, minute: u64, latency_ms: u64) {
+        self.total.record(latency_ms);
+        if self.minutes.back().map(|(last, _)| *last) != Some(minute) {
+            self.minutes.push_back((minute, LatencySketch::default()));
+        }
+        if let Some((_, sketch)) = self.minutes.back_mut() {
+            sketch.record(latency_ms);
+        }
+        self.prune_at(minute);
+    }
+
+    /// Drop the sketches of minutes past the last hour
+    pub fn prune(&mut self) {
+        let minute = self.minute();
+        self.prune_at(minute);
+    }
+
+    fn prune_at(&mut self, minute: u64) {
+        while self.minutes.front().is_some_and(|(first, _)| first + WINDOW_MINUTES <= minute) {
+            self.minutes.pop_front();
+        }
+    }
+
+    fn minute(&self) -> u64 {
+        self.started.elapsed().as_secs() / 60
+    }
+
+    /// Latencies since startup
+    pub fn total(&self) -> &LatencySketch {
+        &self.total
+    }
+
+    /// Latencies of the last `minutes` minutes, the current one included
+    pub fn window(&self, minutes: u64) -> LatencySketch {
+        self.window_at(self.minute(), minutes)
+    }
+
+    fn window_at(&self, now: u64, minutes: u64) -> LatencySketch {
+        let mut window = LatencySketch::default();
+        for (minute, sketch) in &self.minutes {
+            if minute + minutes > now {
+                window.merge(sketch);
+            }
+        }
+        window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantiles_within_error() {
+        let mut sketch = LatencySketch::default();
+        for latency in 1..=1000 {
+            sketch.record(latency);
+        }
+        for (q, expected) in [(0.5, 500.0), (0.95, 950.0), (0.99, 990.0)] {
+            let estimate = sketch.quantile(q);
+            assert!((estimate - expected).abs() / expected <= 0.05, "p{} = {}", q * 100.0, estimate);
+        }
+        assert_eq!(sketch.mean(), 500.5);
+    }
+
+    #[test]
+    fn test_windows_forget_old_minutes() {
+        let mut tracker = LatencyTracker::default();
+        tracker.record_at(0, 2_000);
+        tracker.record_at(50, 100);
+        tracker.record_at(58, 100);
+
+        assert_eq!(tracker.window_at(58, 5).count(), 1);
+        assert_eq!(tracker.window_at(58, 60).count(), 3);
+        tracker.record_at(61, 100);
+        assert_eq!(tracker.window_at(61, 60).count(), 3);
+        assert!(tracker.window_at(61, 60).quantile(0.99) < 200.0);
+        assert_eq!(tracker.total().count(), 4);
+        assert_eq!(tracker.minutes.len(), 3);
+    }
+}
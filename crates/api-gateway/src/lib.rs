@@ -1,25 +1,113 @@
+pub mod account_service;
+pub mod admin_service;
+pub mod audit_alert_service;
+pub mod audit_export_service;
+pub mod audit_retention_service;
+pub mod audit_trail;
 pub mod cache;
+pub mod catalog_store;
+pub mod credential_service;
+pub mod credential_store;
+pub mod dead_letter_service;
+pub mod dead_letter_store;
+pub mod digest_service;
+pub mod data_key_store;
+pub mod dispatcher;
+pub mod encryption_service;
+pub mod errors;
+pub mod event_store;
+pub mod execution_service;
 pub mod failover;
 pub mod file_service;
+pub mod graphql_service;
+pub mod integration_catalog_service;
+pub mod inspector_service;
+pub mod latency;
 pub mod load_balancer;
 pub mod logger;
 pub mod metrics;
+pub mod metrics_service;
+pub mod oauth_service;
+pub mod oauth_state_store;
+pub mod ownership_service;
 pub mod pool;
+pub mod prometheus;
 pub mod proxy;
+pub mod quota_service;
 pub mod rate_limiter;
+pub mod request_limiter;
+pub mod review_service;
+pub mod schedule_store;
+pub mod schema_drift_service;
 pub mod server;
+pub mod session_store;
+pub mod settings_service;
+pub mod signatures;
+pub mod sharing_service;
+pub mod template_service;
+pub mod test_suite_service;
 pub mod user_service;
+pub mod user_store;
+pub mod validation;
+pub mod webhook_service;
 pub mod websocket;
+pub mod workflow_access;
+pub mod workflow_service;
+pub mod workflow_store;
 
+#[cfg(test)]
+mod test_support;
+
+pub use account_service::AccountServiceState;
+pub use admin_service::AdminServiceState;
+pub use audit_alert_service::AuditAlertServiceState;
+pub use audit_export_service::AuditExportServiceState;
+pub use audit_retention_service::AuditRetentionServiceState;
+pub use audit_trail::AuditTrail;
 pub use cache::ResponseCache;
+pub use catalog_store::PgCatalogStore;
+pub use credential_service::CredentialServiceState;
+pub use credential_store::PgCredentialStorage;
+pub use dead_letter_service::DeadLetterServiceState;
+pub use dead_letter_store::PgDeadLetterStore;
+pub use data_key_store::PgDataKeyStore;
+pub use digest_service::DigestServiceState;
+pub use dispatcher::GatewayDispatcher;
+pub use encryption_service::EncryptionServiceState;
+pub use errors::{ApiError, FieldError};
+pub use event_store::PgEventStore;
+pub use execution_service::ExecutionStore;
 pub use failover::FailoverManager;
 pub use file_service::{FileServiceConfig, FileInfo, init_file_service};
+pub use graphql_service::GraphqlServiceState;
+pub use integration_catalog_service::IntegrationCatalogServiceState;
+pub use inspector_service::InspectorState;
+pub use latency::{LatencySketch, LatencyTracker};
 pub use load_balancer::LoadBalancer;
 pub use logger::{ApiLogger, ApiRequestLog, LogFilter, ProviderStats};
-pub use metrics::{MetricsCollector, MetricsSummary};
+pub use metrics::{CacheMetrics, LatencyHistogram, MetricsCollector, MetricsSummary, RateLimitMetrics, ResponseCacheMetrics};
+pub use metrics_service::MetricsServiceState;
+pub use oauth_service::OAuthServiceState;
+pub use oauth_state_store::PgOAuth2StateStore;
+pub use ownership_service::OwnershipServiceState;
 pub use pool::RequestPool;
+pub use prometheus::PrometheusState;
 pub use proxy::ApiProxy;
 pub use rate_limiter::RateLimiter;
-pub use server::{create_server, ServerConfig, AppState};
-pub use user_service::{UserServiceState, UserResponse};
+pub use request_limiter::{RequestLimitConfig, RequestLimiter, RouteGroup, RouteLimit};
+pub use review_service::ReviewServiceState;
+pub use schedule_store::{PgLeaderLock, PgScheduleStore};
+pub use schema_drift_service::SchemaDriftServiceState;
+pub use server::{create_server, create_server_with_services, ServerConfig, AppState, SharedServices};
+pub use session_store::PgSessionStore;
+pub use settings_service::SettingsServiceState;
+pub use template_service::TemplateServiceState;
+pub use test_suite_service::TestSuiteServiceState;
+pub use user_service::{InMemoryUserStore, UserQuery, UserServiceState, UserResponse, UserStore, UserStoreError};
+pub use user_store::PgUserStore;
+pub use validation::{FieldErrors, Validate, ValidJson};
+pub use webhook_service::WebhookServiceState;
 pub use websocket::{WebSocketManager, WorkflowUpdate, WorkflowStatus};
+pub use workflow_access::WorkflowAccess;
+pub use workflow_service::WorkflowServiceState;
+pub use workflow_store::PgWorkflowStore;
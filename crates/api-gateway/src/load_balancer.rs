@@ -118,7 +118,7 @@ impl LoadBalancer {
         enabled_keys
             .into_iter()
             .min_by_key(|k| k.usage_count)
-            .map(|k| k.clone())
+            .cloned()
     }
 
     /// Increment usage count for a key
@@ -1,7 +1,7 @@
 use common::types::{ApiRequest, ApiResponse};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{PgPool, FromRow, Row};
+use sqlx::{PgPool, FromRow, Postgres, QueryBuilder, Row};
 use uuid::Uuid;
 
 /// API request logger for persisting request/response data
@@ -118,40 +118,13 @@ impl ApiLogger {
         Ok(())
     }
 
-    /// Query logs with filters
+    /// Query logs with filters, newest first
     pub async fn query_logs(
         &self,
         filter: LogFilter,
     ) -> Result<Vec<ApiRequestLog>, sqlx::Error> {
-        let mut query = String::from(
-            "SELECT id, provider, endpoint, method, status_code, latency_ms, 
-             request_size, response_size, workflow_id, node_id, cached, 
-             error_message, created_at FROM api_request_logs WHERE 1=1"
-        );
-
-        if let Some(provider) = &filter.provider {
-            query.push_str(&format!(" AND provider = '{}'", provider));
-        }
-
-        if let Some(workflow_id) = filter.workflow_id {
-            query.push_str(&format!(" AND workflow_id = '{}'", workflow_id));
-        }
-
-        if let Some(start_time) = filter.start_time {
-            query.push_str(&format!(" AND created_at >= '{}'", start_time));
-        }
-
-        if let Some(end_time) = filter.end_time {
-            query.push_str(&format!(" AND created_at <= '{}'", end_time));
-        }
-
-        if filter.errors_only {
-            query.push_str(" AND error_message IS NOT NULL");
-        }
-
-        query.push_str(&format!(" ORDER BY created_at DESC LIMIT {}", filter.limit));
-
-        let logs = sqlx::query_as::<_, ApiRequestLog>(&query)
+        let logs = build_log_query(&filter)
+            .build_query_as::<ApiRequestLog>()
             .fetch_all(&self.pool)
             .await?;
 
@@ -203,6 +176,9 @@ impl ApiLogger {
     }
 }
 
+/// Most logs one query returns
+pub const MAX_LOG_LIMIT: i64 = 1000;
+
 #[derive(Debug, Clone)]
 pub struct LogFilter {
     pub provider: Option<String>,
@@ -210,7 +186,16 @@ pub struct LogFilter {
     pub start_time: Option<DateTime<Utc>>,
     pub end_time: Option<DateTime<Utc>>,
     pub errors_only: bool,
+    /// Status code range, inclusive; requests that got no response have none
+    pub min_status_code: Option<u16>,
+    pub max_status_code: Option<u16>,
+    /// Latency range in milliseconds, inclusive
+    pub min_latency_ms: Option<i64>,
+    pub max_latency_ms: Option<i64>,
+    /// Page size, at most [`MAX_LOG_LIMIT`]
     pub limit: i64,
+    /// Logs to skip, for the following pages
+    pub offset: i64,
 }
 
 impl Default for LogFilter {
@@ -221,11 +206,60 @@ impl Default for LogFilter {
             start_time: None,
             end_time: None,
             errors_only: false,
+            min_status_code: None,
+            max_status_code: None,
+            min_latency_ms: None,
+            max_latency_ms: None,
             limit: 100,
+            offset: 0,
         }
     }
 }
 
+/// Query of the logs matching a filter; every filter value is a bound parameter
+fn build_log_query(filter: &LogFilter) -> QueryBuilder<'_, Postgres> {
+    let mut query = QueryBuilder::new(
+        "SELECT id, provider, endpoint, method, status_code, latency_ms, \
+         request_size, response_size, workflow_id, node_id, cached, \
+         error_message, created_at FROM api_request_logs WHERE TRUE",
+    );
+
+    if let Some(provider) = &filter.provider {
+        query.push(" AND provider = ").push_bind(provider);
+    }
+    if let Some(workflow_id) = filter.workflow_id {
+        query.push(" AND workflow_id = ").push_bind(workflow_id);
+    }
+    if let Some(start_time) = filter.start_time {
+        query.push(" AND created_at >= ").push_bind(start_time);
+    }
+    if let Some(end_time) = filter.end_time {
+        query.push(" AND created_at <= ").push_bind(end_time);
+    }
+    if filter.errors_only {
+        query.push(" AND error_message IS NOT NULL");
+    }
+    if let Some(min) = filter.min_status_code {
+        query.push(" AND status_code >= ").push_bind(min as i32);
+    }
+    if let Some(max) = filter.max_status_code {
+        query.push(" AND status_code <= ").push_bind(max as i32);
+    }
+    if let Some(min) = filter.min_latency_ms {
+        query.push(" AND latency_ms >= ").push_bind(min);
+    }
+    if let Some(max) = filter.max_latency_ms {
+        query.push(" AND latency_ms <= ").push_bind(max);
+    }
+
+    // The ID breaks ties so pages do not overlap
+    query.push(" ORDER BY created_at DESC, id DESC LIMIT ")
+        .push_bind(filter.limit.clamp(1, MAX_LOG_LIMIT))
+        .push(" OFFSET ")
+        .push_bind(filter.offset.max(0));
+    query
+}
+
 #[derive(Debug, Clone)]
 pub struct ProviderStats {
     pub total_requests: i64,
@@ -249,4 +283,26 @@ mod tests {
         assert_eq!(filter.limit, 100);
         assert!(!filter.errors_only);
     }
+
+    #[test]
+    fn test_log_query_binds_filter_values() {
+        let filter = LogFilter {
+            provider: Some("openai' OR '1'='1".to_string()),
+            errors_only: true,
+            min_status_code: Some(500),
+            max_status_code: Some(599),
+            min_latency_ms: Some(1_000),
+            limit: 50_000,
+            offset: 200,
+            ..Default::default()
+        };
+        let query = build_log_query(&filter);
+        let sql = query.sql();
+        assert!(!sql.contains("openai"));
+        assert!(sql.ends_with(
+            "WHERE TRUE AND provider = $1 AND error_message IS NOT NULL AND status_code >= $2 \
+             AND status_code <= $3 AND latency_ms >= $4 ORDER BY created_at DESC, id DESC LIMIT $5 OFFSET $6"
+        ));
+        assert!(build_log_query(&LogFilter::default()).sql().ends_with("WHERE TRUE ORDER BY created_at DESC, id DESC LIMIT $1 OFFSET $2"));
+    }
 }
@@ -1,37 +1,384 @@
-use api_gateway::{create_server, ServerConfig};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use ai_service::{AIClient, InjectionDetector, LlmClassifier, ModelType};
+use api_gateway::{
+    create_server_with_services, ApiLogger, ExecutionStore, FailoverManager, GatewayDispatcher, LoadBalancer, PgCatalogStore,
+    PgCredentialStorage, PgDataKeyStore, PgDeadLetterStore, PgEventStore, PgLeaderLock, PgOAuth2StateStore, PgScheduleStore,
+    PgSessionStore, PgUserStore, PgWorkflowStore, RateLimiter, RequestLimitConfig, RequestPool, ResponseCache, ServerConfig, SharedServices,
+};
+use audit_service::{
+    AlertEngine, AuditExportJobs, AuditExporter, AuditLogger, AuditQuery, AuditRetention, AuditStorage, RetentionPolicy,
+};
+use common::config::{self, AppConfig};
+use common::database::{Database, DatabaseError};
+use common::trace::OtlpExporter;
+use common::types::{ApiKeyConfig, ProviderConfig, RateLimitConfig};
+use integration_service::{
+    ChannelNotifier, CredentialManager, CredentialStore, GitHubIntegration, IntegrationRegistry, LocalMasterKey,
+    OAuth2Handler, OAuth2TokenRefresher, SlackIntegration,
+};
+use scraper_service::{BrowserPool, ScraperExecutor};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing_subscriber::{layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter};
+use workflow_engine::encryption::{decode_master_key, DataKeyStore, InMemoryDataKeyStore, PayloadEncryption};
+use workflow_engine::{
+    BroadcastEventBus, ChannelSender, DeadLetterQueue, DigestService, ExecutionHistory, OwnershipStore, PersistentEventBus,
+    WorkflowExecutor, WorkflowRunQueue, WorkflowScheduler,
+};
+
+/// How long shutdown waits for running executions
+const SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+
+/// Concurrent digest webhook calls
+const DIGEST_MAX_CONCURRENT_REQUESTS: usize = 10;
+
+/// Concurrent requests of workflow HTTP action nodes
+const WORKFLOW_MAX_CONCURRENT_REQUESTS: usize = 50;
+
+/// Seconds between checks whether failed providers have recovered
+const FAILOVER_HEALTH_CHECK_SECS: u64 = 10;
 
 #[tokio::main]
 async fn main() {
-    // Initialize tracing
+    // Load configuration: CONFIG_FILE (TOML or YAML), then environment overrides
+    let config_path = std::env::var_os("CONFIG_FILE").map(PathBuf::from);
+    let app_config = AppConfig::load(config_path.as_deref()).unwrap_or_else(|e| startup_error(e));
+
+    // Initialize tracing; the filter follows configuration reloads and spans
+    // go to the OTLP collector when one is configured
+    let otlp = OtlpExporter::from_config(&app_config.tracing).unwrap_or_else(|e| startup_error(e));
+    let (filter, filter_handle) = reload::Layer::new(EnvFilter::new(&app_config.log.filter));
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "api_gateway=debug,tower_http=debug".into()),
-        )
+        .with(filter)
         .with(tracing_subscriber::fmt::layer())
+        .with(otlp.as_ref().map(OtlpExporter::layer))
         .init();
 
-    // Load configuration
+    let mut reloads = config::reload_on_hangup(config_path, app_config.clone());
+    tokio::spawn(async move {
+        let mut current = reloads.borrow_and_update().clone();
+        while reloads.changed().await.is_ok() {
+            let reloaded = reloads.borrow_and_update().clone();
+            if let Err(e) = filter_handle.reload(EnvFilter::new(&reloaded.log.filter)) {
+                tracing::warn!("Failed to apply the reloaded log filter: {}", e);
+            }
+            let restart = reloaded.restart_required(&current);
+            if !restart.is_empty() {
+                tracing::warn!("Changes to {} take effect on restart", restart.join(", "));
+            }
+            current = reloaded;
+        }
+    });
+
+    let server = &app_config.server;
     let config = ServerConfig {
-        host: std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
-        port: std::env::var("PORT")
-            .ok()
-            .and_then(|p| p.parse().ok())
-            .unwrap_or(8080),
-        jwt_secret: std::env::var("JWT_SECRET")
-            .unwrap_or_else(|_| "your-secret-key-change-in-production".to_string()),
-        jwt_expiration_hours: std::env::var("JWT_EXPIRATION_HOURS")
-            .ok()
-            .and_then(|h| h.parse().ok())
-            .unwrap_or(24),
+        host: server.host.clone(),
+        port: server.port,
+        jwt_secret: server.jwt_secret.clone(),
+        jwt_expiration_hours: server.jwt_expiration_hours,
+        request_limits: RequestLimitConfig {
+            // Behind a load balancer the peer address is the balancer's
+            trust_forwarded_for: server.trust_forwarded_for,
+            ..Default::default()
+        },
+        metrics_token: server.metrics_token.clone(),
+        upload_dir: server.upload_dir.clone(),
     };
 
     let addr = format!("{}:{}", config.host, config.port);
     tracing::info!("Starting API Gateway server on {}", addr);
 
-    // Create server
-    let app = create_server(config.clone());
+    let mut services = SharedServices::default();
+
+    // Keep workflows and user accounts in Postgres when a database is configured
+    let database = match Database::connect(&app_config.database).await {
+        Ok(database) => {
+            if app_config.database.migrate {
+                database.migrate().await.unwrap_or_else(|e| startup_error(format!("Failed to migrate the database: {}", e)));
+            }
+            Some(database.pool().clone())
+        }
+        Err(DatabaseError::NotConfigured) => {
+            tracing::warn!("No database is configured, workflows, user accounts and sessions are kept in memory");
+            None
+        }
+        Err(e) => startup_error(e),
+    };
+    if let Some(pool) = &database {
+        services.workflows = Arc::new(PgWorkflowStore::new(pool.clone()));
+        services.users = Arc::new(PgUserStore::new(pool.clone()));
+        services.sessions = Arc::new(PgSessionStore::new(pool.clone()));
+
+        // Record audit logs, alerting on the configured security rules
+        let storage = Arc::new(AuditStorage::new(pool.clone()));
+        let alerts = Arc::new(AlertEngine::new(Arc::new(ChannelNotifier::new())));
+        let audit = Arc::new(AuditLogger::new(storage.clone()).with_alert_engine(alerts.clone()));
+        services.audit = Some(audit.clone());
+        services.audit_alerts = Some(alerts);
+
+        // Keep saved credentials in Postgres, recording every decryption
+        let credentials = CredentialStore::new()
+            .with_storage(Arc::new(PgCredentialStorage::new(pool.clone())))
+            .with_auditor(audit);
+        match credentials.load().await {
+            Ok(loaded) => tracing::info!("Loaded {} saved credentials", loaded),
+            Err(e) => tracing::error!("Failed to load the saved credentials: {}", e),
+        }
+        services.credentials = Arc::new(credentials);
+
+        // Archive and delete audit logs past their retention window
+        let audit = &app_config.audit;
+        let retention = AuditRetention::new(
+            storage,
+            RetentionPolicy::from_config(audit),
+            audit.archive_dir.clone(),
+        )
+        .with_interval(Duration::from_secs(audit.purge_interval_hours as u64 * 60 * 60));
+        let retention = Arc::new(retention);
+        retention.clone().start();
+        services.audit_retention = Some(retention);
+
+        // Export large log ranges in the background
+        let exporter = AuditExporter::new(AuditQuery::new(pool.clone()));
+        services.audit_exports = Some(Arc::new(AuditExportJobs::new(exporter, audit.export_dir.clone())));
+    }
+
+    // Encrypt execution payloads of organizations that opt in
+    let keys = &app_config.encryption;
+    if let Some(master_key) = &keys.execution_data_master_key {
+        let master_key = decode_master_key(master_key).unwrap_or_else(|_| {
+            startup_error("encryption.execution_data_master_key must be 32 base64-encoded bytes")
+        });
+        let master_key_id = keys.execution_data_master_key_id;
+        // Wrapped data keys must outlive restarts or the payloads they
+        // encrypted become unreadable
+        let data_keys: Arc<dyn DataKeyStore> = match &database {
+            Some(pool) => Arc::new(PgDataKeyStore::new(pool.clone())),
+            None => Arc::new(InMemoryDataKeyStore::new()),
+        };
+        let encryption = Arc::new(PayloadEncryption::new(
+            master_key_id,
+            &master_key,
+            data_keys,
+            services.settings.clone(),
+        ));
+        let history = ExecutionHistory::default().with_encryption(encryption.clone());
+        services.executions = ExecutionStore::new().with_history(Arc::new(history));
+        services.encryption = Some(encryption);
+    }
+
+    // Publish the integrations to the catalog and load the organizations'
+    // enablement of them; email attachments and S3 uploads are read from the
+    // file service's uploads
+    let mut integrations = IntegrationRegistry::new().with_file_root(&app_config.server.upload_dir);
+    if let Some(pool) = &database {
+        integrations = integrations.with_catalog_store(Arc::new(PgCatalogStore::new(pool.clone())));
+    }
+    match integrations.load_catalog().await {
+        Ok(settings) => tracing::info!("Loaded {} organization integration settings", settings),
+        Err(e) => tracing::error!("Failed to load the integration catalog: {}", e),
+    }
+    services.integrations = Arc::new(integrations);
+
+    // Save users' credentials, enabling the credential routes
+    if let Some(master_key) = &keys.credentials_master_key {
+        let master_key = LocalMasterKey::from_base64(keys.credentials_master_key_id, master_key).unwrap_or_else(|_| {
+            startup_error("encryption.credentials_master_key must be 32 base64-encoded bytes")
+        });
+        services.credential_manager = Some(Arc::new(CredentialManager::with_master_key(Arc::new(master_key))));
+    }
+
+    // Connect integrations through their OAuth2 apps, refreshing the saved
+    // tokens before they expire; the provider redirects to the public URL
+    if let (Some(manager), Some(public_url)) = (&services.credential_manager, &app_config.server.public_url) {
+        let redirect_uri = format!("{}/api/v1/oauth/callback", public_url.trim_end_matches('/'));
+        let mut handler = OAuth2Handler::new();
+        if let Some(pool) = &database {
+            handler = handler.with_state_store(Arc::new(PgOAuth2StateStore::new(pool.clone())));
+        }
+        let apps = [
+            ("slack", "SLACK_OAUTH", SlackIntegration::oauth_config as fn(&str, &str, &str) -> _),
+            ("github", "GITHUB_OAUTH", GitHubIntegration::oauth_config),
+        ];
+        for (integration, prefix, oauth_config) in apps {
+            let client_id = std::env::var(format!("{}_CLIENT_ID", prefix));
+            let client_secret = std::env::var(format!("{}_CLIENT_SECRET", prefix));
+            if let (Ok(client_id), Ok(client_secret)) = (client_id, client_secret) {
+                handler.register_config(integration, oauth_config(&client_id, &client_secret, &redirect_uri)).await;
+                tracing::info!("Registered the {} OAuth2 app", integration);
+            }
+        }
+        let handler = Arc::new(handler);
+        let refresher = OAuth2TokenRefresher::new(handler.clone(), services.credentials.clone(), manager.clone());
+        Arc::new(refresher).start();
+        services.oauth = Some(handler);
+    }
+
+    // Alert the creators of workflows without annotated owners
+    services.ownership = Arc::new(OwnershipStore::new().with_revisions(services.revisions.clone()));
+
+    // Run workflows with the configured AI providers, browser pool and
+    // integrations
+    let scraper = &app_config.scraper;
+    let browsers = BrowserPool::new(scraper.max_contexts, scraper.idle_timeout_secs)
+        .with_queue_timeout(scraper.queue_timeout_ms)
+        .with_max_restarts(scraper.max_restarts);
+    let mut executor = WorkflowExecutor::new()
+        // Record finished executions in the history the execution routes serve
+        .with_history(services.executions.history().clone())
+        .with_scraper(Arc::new(ScraperExecutor::new(Arc::new(browsers))))
+        .with_integrations(services.integrations.clone())
+        // Admit organizations' executions under the quotas the quota routes manage
+        .with_quotas(services.quotas.clone())
+        // Apply the organizations' settings (header/env injection, prompt
+        // screening) the settings routes manage
+        .with_settings(services.settings.clone())
+        // Warn workflows of drifting provider responses on the schema drift routes
+        .with_schema_drift(services.schema_drift.clone())
+        // Alert the owners of nodes that keep failing
+        .with_ownership(services.ownership.clone());
+    // Integration actions authenticate with the users' saved credentials
+    if let Some(manager) = &services.credential_manager {
+        executor = executor.with_credentials(services.credentials.clone(), manager.clone());
+    }
+    if !app_config.ai.api_keys.is_empty() {
+        let client = app_config.ai.api_keys.iter().fold(AIClient::new(), |client, (provider, key)| {
+            client.with_api_key(provider.clone(), key.clone())
+        });
+        let client = Arc::new(client);
+        // Screen AI node prompts with the configured model as well
+        if let Some(model) = &app_config.ai.injection_classifier_model {
+            let model: ModelType = serde_json::from_value(serde_json::Value::String(model.clone()))
+                .unwrap_or_else(|_| startup_error(format!("ai.injection_classifier_model: unknown model {:?}", model)));
+            let detector = InjectionDetector::new().with_classifier(Arc::new(LlmClassifier::new(client.clone(), model)));
+            executor = executor.with_injection_detector(Arc::new(detector));
+        }
+        executor = executor.with_ai(client);
+    }
+    // Record blocked AI node prompts in the audit log
+    if let Some(audit) = &services.audit {
+        executor = executor.with_injection_auditor(audit.clone());
+    }
+
+    // Send the requests of HTTP action nodes through the gateway's pool,
+    // rate limits and retries, with the configured providers' API keys,
+    // failover providers and response caching
+    let gateway = &app_config.gateway;
+    let load_balancer = Arc::new(LoadBalancer::new(gateway.load_balance_strategy));
+    let failover = Arc::new(FailoverManager::new(
+        Duration::from_secs(FAILOVER_HEALTH_CHECK_SECS),
+        gateway.failure_threshold,
+        Duration::from_secs(gateway.recovery_timeout_secs),
+    ));
+    let cache = (gateway.cache_capacity > 0).then(|| {
+        let longest_ttl = gateway.providers.values().filter_map(|provider| provider.cache_ttl_secs).max();
+        Arc::new(ResponseCache::new(gateway.cache_capacity, Duration::from_secs(longest_ttl.unwrap_or(0))))
+    });
+    for (name, provider) in &gateway.providers {
+        let api_keys: Vec<_> = provider.api_keys.iter()
+            .map(|key| ApiKeyConfig { key: key.clone(), ..Default::default() })
+            .collect();
+        if !api_keys.is_empty() {
+            load_balancer.configure_provider(name.clone(), api_keys.clone()).await;
+        }
+        let config = ProviderConfig {
+            name: name.clone(),
+            api_keys,
+            rate_limit: RateLimitConfig::default(),
+            cache_ttl: provider.cache_ttl_secs.map(Duration::from_secs),
+            failover_providers: provider.failover_providers.clone(),
+        };
+        if let Some(cache) = &cache {
+            cache.configure_provider(&config).await;
+        }
+        failover.register_provider(config).await;
+    }
+    failover.clone().start_health_check_task();
+    let mut http = GatewayDispatcher::new(
+        Arc::new(RequestPool::new(WORKFLOW_MAX_CONCURRENT_REQUESTS)),
+        Arc::new(RateLimiter::new()),
+    )
+    .with_load_balancer(load_balancer)
+    .with_failover(failover)
+    .with_metrics(services.metrics.clone());
+    if let Some(cache) = cache {
+        http = http.with_cache(cache);
+    }
+    if let Some(pool) = &database {
+        http = http.with_logger(Arc::new(ApiLogger::new(pool.clone())));
+    }
+    services.dispatchers.push(("workflows".to_string(), http.clone()));
+    executor = executor.with_http_dispatcher(Arc::new(http));
+
+    // Persist executor events for execution timelines, forwarding them to
+    // WebSocket clients
+    if let Some(pool) = &database {
+        let store = Arc::new(PgEventStore::new(pool.clone()));
+        let live = Arc::new(BroadcastEventBus::default());
+        executor = executor.with_event_bus(Arc::new(PersistentEventBus::new(store.clone()).with_forward(live.clone())));
+        services.executions = services.executions.clone().with_events(store);
+        services.events = Some(live);
+    }
+
+    // Record failed executions as dead letters, to be inspected and requeued
+    let dead_letters = database.as_ref().map(|pool| Arc::new(PgDeadLetterStore::new(pool.clone())));
+    if let Some(store) = &dead_letters {
+        executor = executor.with_dead_letters(store.clone());
+    }
+    let executor = Arc::new(executor);
+    if let Some(store) = dead_letters {
+        services.dead_letters = Arc::new(DeadLetterQueue::new(store, executor.clone()));
+    }
+    // Triggers start executions through the run queue, bounding how many run at once
+    let run_queue = WorkflowRunQueue::new(executor.clone(), app_config.scheduler.max_concurrent_runs)
+        .with_max_queued(app_config.scheduler.max_queued_runs);
+    let mut scheduler = WorkflowScheduler::new(executor).with_run_queue(run_queue);
+    if let Some(pool) = database.as_ref().filter(|_| app_config.scheduler.persist) {
+        let mut lock = PgLeaderLock::new(pool.clone());
+        if let Some(key) = app_config.scheduler.leader_lock_key {
+            lock = lock.with_key(key);
+        }
+        scheduler = scheduler
+            .with_store(Arc::new(PgScheduleStore::new(pool.clone())))
+            .with_leader_lock(Arc::new(lock));
+    }
+    services.scheduler = Arc::new(scheduler);
+
+    // Deliver workflow health digests; email needs a mailer, Slack goes
+    // through the gateway's dispatcher
+    let mut dispatcher = GatewayDispatcher::new(
+        Arc::new(RequestPool::new(DIGEST_MAX_CONCURRENT_REQUESTS)),
+        Arc::new(RateLimiter::new()),
+    )
+    .with_metrics(services.metrics.clone());
+    if let Some(pool) = &database {
+        dispatcher = dispatcher.with_logger(Arc::new(ApiLogger::new(pool.clone())));
+    }
+    services.dispatchers.push(("digests".to_string(), dispatcher.clone()));
+    let mut digests = DigestService::new(
+        services.executions.history().clone(),
+        services.revisions.clone(),
+        services.credentials.clone(),
+        Arc::new(ChannelSender::new().with_http(Arc::new(dispatcher))),
+    );
+    if let Some(public_url) = &app_config.server.public_url {
+        digests = digests.with_base_url(public_url.clone());
+    }
+    digests.start();
+    services.digests = Some(Arc::new(digests));
+    let app = create_server_with_services(config.clone(), services.clone());
+
+    // Reload persisted schedules, then fire the time-based ones
+    match services.scheduler.restore().await {
+        Ok(restored) => tracing::info!("Restored {} schedules", restored),
+        Err(e) => tracing::error!("Failed to restore schedules: {}", e),
+    }
+    if app_config.scheduler.enabled {
+        if let Err(e) = services.scheduler.start().await {
+            tracing::error!("Failed to start scheduler: {}", e);
+        }
+    }
 
     // Start server
     let listener = tokio::net::TcpListener::bind(&addr)
@@ -40,7 +387,45 @@ async fn main() {
 
     tracing::info!("Server listening on {}", addr);
 
-    axum::serve(listener, app)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
         .await
         .expect("Server error");
+
+    // Let running executions finish before exiting
+    tracing::info!("Waiting for background executions to finish");
+    if !services.shutdown(Duration::from_secs(SHUTDOWN_TIMEOUT_SECS)).await {
+        tracing::warn!("Shutting down with executions still running");
+    }
+    if let Some(otlp) = &otlp {
+        otlp.shutdown();
+    }
+}
+
+/// Report a configuration the gateway cannot start with and exit
+fn startup_error(message: impl std::fmt::Display) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+/// Completes on Ctrl+C or, on Unix, SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("Failed to listen for Ctrl+C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    tracing::info!("Shutdown signal received, draining connections");
 }
@@ -1,20 +1,45 @@
+use ai_service::CacheObserver;
+use async_trait::async_trait;
 use common::types::ProviderMetrics;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
+use crate::latency::{LatencySketch, LatencyTracker};
+
 /// Metrics collector for API Gateway
 pub struct MetricsCollector {
     providers: Arc<RwLock<HashMap<String, ProviderMetricsData>>>,
+    /// AI response cache lookups per model
+    cache: Arc<RwLock<HashMap<String, CacheMetricsData>>>,
+    /// Inbound requests per rate-limited route group: (allowed, limited)
+    rate_limits: Arc<RwLock<HashMap<String, (u64, u64)>>>,
+    /// Gateway response cache lookups per provider: (hits, misses)
+    responses: Arc<RwLock<HashMap<String, (u64, u64)>>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct CacheMetricsData {
+    hits: u64,
+    misses: u64,
+    saved_tokens: u64,
 }
 
+/// Upper bounds of the request latency histogram buckets, in milliseconds
+pub const LATENCY_BUCKETS_MS: [u64; 12] = [5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000];
+
 #[derive(Debug, Clone)]
 struct ProviderMetricsData {
     total_requests: u64,
     successful_requests: u64,
     failed_requests: u64,
-    latencies: Vec<u64>,
+    /// Latency percentiles since startup and over recent windows
+    latency: LatencyTracker,
+    /// Requests per latency bucket, the last one past the largest bound
+    latency_buckets: [u64; LATENCY_BUCKETS_MS.len() + 1],
+    latency_sum_ms: u64,
     total_cost: f64,
     #[allow(dead_code)]
     last_reset: Instant,
@@ -26,17 +51,52 @@ impl Default for ProviderMetricsData {
             total_requests: 0,
             successful_requests: 0,
             failed_requests: 0,
-            latencies: Vec::new(),
+            latency: LatencyTracker::default(),
+            latency_buckets: [0; LATENCY_BUCKETS_MS.len() + 1],
+            latency_sum_ms: 0,
             total_cost: 0.0,
             last_reset: Instant::now(),
         }
     }
 }
 
+impl ProviderMetricsData {
+    fn record_latency(&mut self, latency_ms: u64) {
+        self.latency.record(latency_ms);
+        let bucket = LATENCY_BUCKETS_MS.partition_point(|&bound| bound < latency_ms);
+        self.latency_buckets[bucket] += 1;
+        self.latency_sum_ms += latency_ms;
+    }
+
+    fn metrics(&self, provider: &str) -> ProviderMetrics {
+        let error_rate = if self.total_requests > 0 {
+            self.failed_requests as f64 / self.total_requests as f64
+        } else {
+            0.0
+        };
+
+        ProviderMetrics {
+            provider: provider.to_string(),
+            total_requests: self.total_requests,
+            successful_requests: self.successful_requests,
+            failed_requests: self.failed_requests,
+            average_latency_ms: self.latency.total().mean(),
+            error_rate,
+            total_cost: self.total_cost,
+            latency: self.latency.total().percentiles(),
+            latency_5m: self.latency.window(5).percentiles(),
+            latency_1h: self.latency.window(60).percentiles(),
+        }
+    }
+}
+
 impl MetricsCollector {
     pub fn new() -> Self {
         Self {
             providers: Arc::new(RwLock::new(HashMap::new())),
+            cache: Arc::new(RwLock::new(HashMap::new())),
+            rate_limits: Arc::new(RwLock::new(HashMap::new())),
+            responses: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -47,13 +107,8 @@ impl MetricsCollector {
 
         metrics.total_requests += 1;
         metrics.successful_requests += 1;
-        metrics.latencies.push(latency_ms);
+        metrics.record_latency(latency_ms);
         metrics.total_cost += cost;
-
-        // Keep only last 1000 latencies to avoid memory growth
-        if metrics.latencies.len() > 1000 {
-            metrics.latencies.drain(0..500);
-        }
     }
 
     /// Record a failed request
@@ -63,71 +118,131 @@ impl MetricsCollector {
 
         metrics.total_requests += 1;
         metrics.failed_requests += 1;
-        metrics.latencies.push(latency_ms);
+        metrics.record_latency(latency_ms);
+    }
 
-        if metrics.latencies.len() > 1000 {
-            metrics.latencies.drain(0..500);
-        }
+    /// Record an AI request of `model` answered from the cache
+    pub async fn record_cache_hit(&self, model: &str, saved_tokens: u32) {
+        let mut cache = self.cache.write().await;
+        let metrics = cache.entry(model.to_string()).or_default();
+        metrics.hits += 1;
+        metrics.saved_tokens += saved_tokens as u64;
     }
 
-    /// Get metrics for a specific provider
-    pub async fn get_metrics(&self, provider: &str) -> Option<ProviderMetrics> {
-        let providers = self.providers.read().await;
-        let data = providers.get(provider)?;
+    /// Record an AI request of `model` the cache could not answer
+    pub async fn record_cache_miss(&self, model: &str) {
+        let mut cache = self.cache.write().await;
+        cache.entry(model.to_string()).or_default().misses += 1;
+    }
 
-        let average_latency_ms = if data.latencies.is_empty() {
-            0.0
+    /// AI response cache metrics of every model, by model name
+    pub async fn get_cache_metrics(&self) -> Vec<CacheMetrics> {
+        let cache = self.cache.read().await;
+        let mut metrics: Vec<CacheMetrics> = cache
+            .iter()
+            .map(|(model, data)| {
+                let lookups = data.hits + data.misses;
+                CacheMetrics {
+                    model: model.clone(),
+                    hits: data.hits,
+                    misses: data.misses,
+                    hit_rate: if lookups > 0 { data.hits as f64 / lookups as f64 } else { 0.0 },
+                    saved_tokens: data.saved_tokens,
+                }
+            })
+            .collect();
+        metrics.sort_by(|a, b| a.model.cmp(&b.model));
+        metrics
+    }
+
+    /// Record an inbound request of a route group, let through or rejected
+    /// by the request rate limits
+    pub async fn record_rate_limit(&self, group: &str, allowed: bool) {
+        let mut rate_limits = self.rate_limits.write().await;
+        let (passed, limited) = rate_limits.entry(group.to_string()).or_default();
+        if allowed {
+            *passed += 1;
         } else {
-            data.latencies.iter().sum::<u64>() as f64 / data.latencies.len() as f64
-        };
+            *limited += 1;
+        }
+    }
 
-        let error_rate = if data.total_requests > 0 {
-            data.failed_requests as f64 / data.total_requests as f64
+    /// Request rate limit metrics of every route group, by group name
+    pub async fn get_rate_limit_metrics(&self) -> Vec<RateLimitMetrics> {
+        let rate_limits = self.rate_limits.read().await;
+        let mut metrics: Vec<RateLimitMetrics> = rate_limits
+            .iter()
+            .map(|(group, &(allowed, limited))| RateLimitMetrics { group: group.clone(), allowed, limited })
+            .collect();
+        metrics.sort_by(|a, b| a.group.cmp(&b.group));
+        metrics
+    }
+
+    /// Record a request to `provider` answered from the response cache, or
+    /// one the cache could not answer
+    pub async fn record_response_cache(&self, provider: &str, hit: bool) {
+        let mut responses = self.responses.write().await;
+        let (hits, misses) = responses.entry(provider.to_string()).or_default();
+        if hit {
+            *hits += 1;
         } else {
-            0.0
-        };
+            *misses += 1;
+        }
+    }
 
-        Some(ProviderMetrics {
-            provider: provider.to_string(),
-            total_requests: data.total_requests,
-            successful_requests: data.successful_requests,
-            failed_requests: data.failed_requests,
-            average_latency_ms,
-            error_rate,
-            total_cost: data.total_cost,
-        })
+    /// Response cache metrics of every provider, by provider name
+    pub async fn get_response_cache_metrics(&self) -> Vec<ResponseCacheMetrics> {
+        let responses = self.responses.read().await;
+        let mut metrics: Vec<ResponseCacheMetrics> = responses
+            .iter()
+            .map(|(provider, &(hits, misses))| ResponseCacheMetrics {
+                provider: provider.clone(),
+                hits,
+                misses,
+                hit_rate: if hits + misses > 0 { hits as f64 / (hits + misses) as f64 } else { 0.0 },
+            })
+            .collect();
+        metrics.sort_by(|a, b| a.provider.cmp(&b.provider));
+        metrics
+    }
+
+    /// Get metrics for a specific provider
+    pub async fn get_metrics(&self, provider: &str) -> Option<ProviderMetrics> {
+        let providers = self.providers.read().await;
+        providers.get(provider).map(|data| data.metrics(provider))
     }
 
     /// Get metrics for all providers
     pub async fn get_all_metrics(&self) -> Vec<ProviderMetrics> {
         let providers = self.providers.read().await;
-        let mut metrics = Vec::new();
-
-        for (provider, data) in providers.iter() {
-            let average_latency_ms = if data.latencies.is_empty() {
-                0.0
-            } else {
-                data.latencies.iter().sum::<u64>() as f64 / data.latencies.len() as f64
-            };
-
-            let error_rate = if data.total_requests > 0 {
-                data.failed_requests as f64 / data.total_requests as f64
-            } else {
-                0.0
-            };
-
-            metrics.push(ProviderMetrics {
-                provider: provider.clone(),
-                total_requests: data.total_requests,
-                successful_requests: data.successful_requests,
-                failed_requests: data.failed_requests,
-                average_latency_ms,
-                error_rate,
-                total_cost: data.total_cost,
-            });
-        }
+        providers.iter().map(|(provider, data)| data.metrics(provider)).collect()
+    }
 
-        metrics
+    /// Latency histograms of every provider, by provider name
+    pub async fn get_latency_histograms(&self) -> Vec<LatencyHistogram> {
+        let providers = self.providers.read().await;
+        let mut histograms: Vec<LatencyHistogram> = providers
+            .iter()
+            .map(|(provider, data)| {
+                let mut cumulative = 0;
+                let buckets = LATENCY_BUCKETS_MS
+                    .iter()
+                    .zip(&data.latency_buckets)
+                    .map(|(&bound, &count)| {
+                        cumulative += count;
+                        (bound, cumulative)
+                    })
+                    .collect();
+                LatencyHistogram {
+                    provider: provider.clone(),
+                    buckets,
+                    count: data.latency_buckets.iter().sum(),
+                    sum_ms: data.latency_sum_ms,
+                }
+            })
+            .collect();
+        histograms.sort_by(|a, b| a.provider.cmp(&b.provider));
+        histograms
     }
 
     /// Reset metrics for a provider
@@ -140,6 +255,9 @@ impl MetricsCollector {
     pub async fn reset_all(&self) {
         let mut providers = self.providers.write().await;
         providers.clear();
+        self.cache.write().await.clear();
+        self.rate_limits.write().await.clear();
+        self.responses.write().await.clear();
     }
 
     /// Get metrics summary
@@ -150,36 +268,33 @@ impl MetricsCollector {
         let mut total_successful = 0;
         let mut total_failed = 0;
         let mut total_cost = 0.0;
-        let mut all_latencies = Vec::new();
+        let mut latencies = LatencySketch::default();
 
         for data in providers.values() {
             total_requests += data.total_requests;
             total_successful += data.successful_requests;
             total_failed += data.failed_requests;
             total_cost += data.total_cost;
-            all_latencies.extend(&data.latencies);
+            latencies.merge(data.latency.total());
         }
 
-        let average_latency_ms = if all_latencies.is_empty() {
-            0.0
-        } else {
-            all_latencies.iter().sum::<u64>() as f64 / all_latencies.len() as f64
-        };
-
         let error_rate = if total_requests > 0 {
             total_failed as f64 / total_requests as f64
         } else {
             0.0
         };
 
+        let cache = self.cache.read().await;
         MetricsSummary {
             total_requests,
             successful_requests: total_successful,
             failed_requests: total_failed,
-            average_latency_ms,
+            average_latency_ms: latencies.mean(),
             error_rate,
             total_cost,
             provider_count: providers.len(),
+            cache_hits: cache.values().map(|data| data.hits).sum(),
+            cache_misses: cache.values().map(|data| data.misses).sum(),
         }
     }
 
@@ -198,10 +313,8 @@ impl MetricsCollector {
     async fn cleanup_old_metrics(&self) {
         let mut providers = self.providers.write().await;
         for data in providers.values_mut() {
-            // Keep only recent latencies
-            if data.latencies.len() > 1000 {
-                data.latencies.drain(0..data.latencies.len() - 1000);
-            }
+            // Forget the latency windows of idle providers
+            data.latency.prune();
         }
     }
 }
@@ -212,7 +325,18 @@ impl Default for MetricsCollector {
     }
 }
 
-#[derive(Debug, Clone)]
+#[async_trait]
+impl CacheObserver for MetricsCollector {
+    async fn record_cache_hit(&self, model: &str, tokens: u32) {
+        MetricsCollector::record_cache_hit(self, model, tokens).await;
+    }
+
+    async fn record_cache_miss(&self, model: &str) {
+        MetricsCollector::record_cache_miss(self, model).await;
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct MetricsSummary {
     pub total_requests: u64,
     pub successful_requests: u64,
@@ -221,6 +345,46 @@ pub struct MetricsSummary {
     pub error_rate: f64,
     pub total_cost: f64,
     pub provider_count: usize,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheMetrics {
+    pub model: String,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+    /// Tokens of the completions answered from the cache
+    pub saved_tokens: u64,
+}
+
+/// Latencies of a provider's requests in cumulative buckets
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyHistogram {
+    pub provider: String,
+    /// Upper bound in milliseconds and requests at most that slow, for
+    /// each bound of [`LATENCY_BUCKETS_MS`]
+    pub buckets: Vec<(u64, u64)>,
+    pub count: u64,
+    pub sum_ms: u64,
+}
+
+/// Gateway requests to a provider answered from the response cache
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseCacheMetrics {
+    pub provider: String,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+}
+
+/// Inbound requests of a route group and how many were rate limited
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitMetrics {
+    pub group: String,
+    pub allowed: u64,
+    pub limited: u64,
 }
 
 #[cfg(test)]
@@ -262,6 +426,9 @@ mod tests {
 
         let metrics = collector.get_metrics("openai").await.unwrap();
         assert_eq!(metrics.average_latency_ms, 200.0);
+        assert_eq!(metrics.latency_5m.requests, 3);
+        let p50 = metrics.latency_1h.p50_ms;
+        assert!((p50 - 200.0).abs() <= 10.0, "p50 = {}", p50);
     }
 
     #[tokio::test]
@@ -277,4 +444,35 @@ mod tests {
         assert_eq!(summary.failed_requests, 1);
         assert_eq!(summary.provider_count, 2);
     }
+
+    #[tokio::test]
+    async fn test_ai_cache_metrics() {
+        use ai_service::{AICache, AIRequest, AIResponse, ModelType};
+        use ai_service::client::Usage;
+
+        let collector = Arc::new(MetricsCollector::new());
+        let cache = AICache::in_memory(10, Duration::from_secs(60)).with_observer(collector.clone());
+        let request = AIRequest { temperature: Some(0.0), ..AIRequest::new(ModelType::GPT4, "Hi".to_string()) };
+        let response = AIResponse {
+            content: "Hello".to_string(),
+            tool_calls: None,
+            usage: Usage { prompt_tokens: 5, completion_tokens: 2, total_tokens: 7 },
+            model: "gpt-4".to_string(),
+            finish_reason: "stop".to_string(),
+            cached: false,
+            structured: None,
+            fallback_from: None,
+        };
+
+        assert!(cache.get(&request).await.is_none());
+        cache.put(&request, &response).await;
+        assert!(cache.get(&request).await.unwrap().cached);
+        assert!(cache.get(&request).await.is_some());
+
+        let metrics = collector.get_cache_metrics().await;
+        assert_eq!(metrics.len(), 1);
+        assert_eq!((metrics[0].hits, metrics[0].misses, metrics[0].saved_tokens), (2, 1, 14));
+        let summary = collector.get_summary().await;
+        assert_eq!((summary.cache_hits, summary.cache_misses), (2, 1));
+    }
 }
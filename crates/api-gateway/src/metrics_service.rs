@@ -0,0 +1,41 @@
+use axum::{
+    extract::{FromRef, State},
+    response::IntoResponse,
+    Json,
+};
+use rbac_service::middleware::AdminOnly;
+use rbac_service::{AuthUser, JwtManager};
+use std::sync::Arc;
+
+use crate::metrics::MetricsCollector;
+
+/// Gateway metrics service state
+#[derive(Clone)]
+pub struct MetricsServiceState {
+    pub metrics: Arc<MetricsCollector>,
+    pub jwt_manager: Arc<JwtManager>,
+}
+
+impl MetricsServiceState {
+    pub fn new(metrics: Arc<MetricsCollector>, jwt_manager: Arc<JwtManager>) -> Self {
+        Self { metrics, jwt_manager }
+    }
+}
+
+impl FromRef<MetricsServiceState> for Arc<JwtManager> {
+    fn from_ref(state: &MetricsServiceState) -> Self {
+        state.jwt_manager.clone()
+    }
+}
+
+/// 查询网关指标（仅管理员）：上游服务商调用、AI 响应缓存、网关响应缓存与各路由组的限流计数
+pub async fn get_metrics(_admin: AuthUser<AdminOnly>, State(state): State<MetricsServiceState>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "success": true,
+        "summary": state.metrics.get_summary().await,
+        "providers": state.metrics.get_all_metrics().await,
+        "cache": state.metrics.get_cache_metrics().await,
+        "response_cache": state.metrics.get_response_cache_metrics().await,
+        "rate_limits": state.metrics.get_rate_limit_metrics().await,
+    }))
+}
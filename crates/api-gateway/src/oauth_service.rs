@@ -0,0 +1,296 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use common::types::{ActionType2, JsonValue};
+use integration_service::credentials::CredentialStatus;
+use integration_service::integrations::AuthType;
+use integration_service::{OAuth2Handler, StoredCredential};
+use rbac_service::jwt::JwtClaims;
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::credential_service::{field_error, masked, CredentialServiceState};
+use crate::errors::ApiError;
+use crate::validation::{FieldErrors, Validate, ValidJson};
+
+type ApiResult = Result<(StatusCode, Json<JsonValue>), ApiError>;
+
+/// OAuth service state
+#[derive(Clone)]
+pub struct OAuthServiceState {
+    /// Registered OAuth2 apps and the pending authorizations
+    pub handler: Arc<OAuth2Handler>,
+    /// Credentials the authorized tokens are saved as, with their
+    /// permissions
+    pub credentials: CredentialServiceState,
+}
+
+impl OAuthServiceState {
+    pub fn new(handler: Arc<OAuth2Handler>, credentials: CredentialServiceState) -> Self {
+        Self { handler, credentials }
+    }
+}
+
+/// Authorization to start
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeRequest {
+    /// Integration to connect, e.g. "slack"
+    pub integration: String,
+    /// Name of the credential to create
+    pub name: Option<String>,
+    /// Credential to replace the token of, when reconnecting
+    pub credential_id: Option<Uuid>,
+}
+
+impl Validate for AuthorizeRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.check(!self.integration.trim().is_empty(), "integration", "集成不能为空");
+        let named = self.name.as_ref().is_some_and(|name| !name.trim().is_empty());
+        errors.check(named || self.credential_id.is_some(), "name", "凭证名称不能为空");
+    }
+}
+
+/// Redirect of the OAuth2 provider
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    pub state: String,
+    pub code: Option<String>,
+    /// Set instead of `code` when the user or provider refused
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// 发起 OAuth2 授权：返回跳转到服务商的授权地址（含 PKCE 挑战）。给出凭证 ID 时为重新连接，授权完成后替换该凭证的令牌
+pub async fn authorize_oauth(
+    State(state): State<OAuthServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    ValidJson(request): ValidJson<AuthorizeRequest>,
+) -> ApiResult {
+    let name = match request.credential_id {
+        Some(credential_id) => {
+            let stored = state.credentials.authorized(&claims, ActionType2::Update, credential_id).await?;
+            if stored.integration != request.integration {
+                return Err(field_error("credential_id", "凭证不属于该集成"));
+            }
+            stored.name
+        }
+        None => {
+            if !state.credentials.can(&claims, ActionType2::Create, Some(claims.sub)).await {
+                return Err(ApiError::forbidden("没有创建凭证的权限"));
+            }
+            if state.credentials.integrations.get(&request.integration).await.is_none() {
+                return Err(field_error("integration", "集成不存在"));
+            }
+            request.name.unwrap_or_default().trim().to_string()
+        }
+    };
+
+    let (authorization_url, started) = state
+        .handler
+        .start_authorization(&request.integration, claims.sub, &name, request.credential_id)
+        .await
+        .map_err(|e| ApiError::from_error("发起授权失败", &e))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "authorization_url": authorization_url,
+            "expires_at": started.expires_at
+        })),
+    ))
+}
+
+/// OAuth2 回调（公开）：校验 state，用授权码和 PKCE 校验码换取令牌，并加密保存为凭证（重新连接时替换原凭证的令牌）
+pub async fn oauth_callback(
+    State(state): State<OAuthServiceState>,
+    Query(query): Query<CallbackQuery>,
+) -> ApiResult {
+    if let Some(error) = &query.error {
+        let reason = query.error_description.as_deref().unwrap_or(error);
+        return Err(ApiError::invalid_input(format!("授权被拒绝: {}", reason)));
+    }
+    let code = query.code.as_deref().ok_or_else(|| ApiError::invalid_input("缺少授权码"))?;
+    let (started, token) = state
+        .handler
+        .complete_authorization(&query.state, code)
+        .await
+        .map_err(|e| ApiError::from_error("完成授权失败", &e))?;
+
+    let manager = &state.credentials.manager;
+    let (status, credential) = match started.credential_id {
+        Some(credential_id) => {
+            let credential = state
+                .credentials
+                .credentials
+                .update_token(credential_id, &token, manager)
+                .await
+                .map_err(|e| ApiError::from_error("保存令牌失败", &e))?;
+            (StatusCode::OK, credential)
+        }
+        None => {
+            let now = Utc::now();
+            let mut credential = StoredCredential {
+                id: Uuid::new_v4(),
+                owner: started.owner,
+                name: started.credential_name,
+                integration: started.integration,
+                auth_type: AuthType::OAuth2,
+                schema_version: 1,
+                data: String::new(),
+                status: CredentialStatus::Active,
+                created_at: now,
+                updated_at: now,
+                rotated_at: None,
+                expires_at: None,
+                token_expires_at: None,
+            };
+            credential
+                .set_token(&token, manager)
+                .map_err(|e| ApiError::from_error("保存令牌失败", &e))?;
+            state
+                .credentials
+                .credentials
+                .save(credential.clone())
+                .await
+                .map_err(|e| ApiError::from_error("保存令牌失败", &e))?;
+            (StatusCode::CREATED, credential)
+        }
+    };
+
+    Ok((
+        status,
+        Json(serde_json::json!({
+            "success": true,
+            "credential": masked(credential)
+        })),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, claims};
+    use axum::response::IntoResponse;
+    use axum::routing::{get, post};
+    use axum::{Form, Router};
+    use common::types::Role;
+    use integration_service::{
+        CredentialManager, CredentialStore, CredentialUse, InMemoryCredentialStorage, IntegrationRegistry, OAuth2Token,
+    };
+    use std::collections::HashMap;
+
+    /// A token endpoint exchanging the code "c-1" sent with a PKCE verifier
+    async fn fake_provider() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        let app = Router::new().route(
+            "/token",
+            post(|Form(params): Form<HashMap<String, String>>| async move {
+                if params.get("code").map(String::as_str) != Some("c-1") || !params.contains_key("code_verifier") {
+                    return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": "invalid_grant" }))).into_response();
+                }
+                Json(serde_json::json!({
+                    "access_token": "xoxb-1",
+                    "refresh_token": "r-1",
+                    "expires_in": 3600,
+                    "token_type": "bearer"
+                }))
+                .into_response()
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    async fn state(storage: Arc<InMemoryCredentialStorage>) -> OAuthServiceState {
+        let integrations = Arc::new(IntegrationRegistry::new());
+        integrations.load_catalog().await.unwrap();
+        let handler = Arc::new(OAuth2Handler::new());
+        let mut config = integration_service::SlackIntegration::oauth_config("id", "secret", "https://flowvex.example.com/cb");
+        config.token_url = fake_provider().await;
+        handler.register_config("slack", config).await;
+        let credentials = CredentialServiceState::new(
+            Arc::new(CredentialStore::new().with_storage(storage)),
+            Arc::new(CredentialManager::new(&[7u8; 32])),
+            integrations,
+        );
+        OAuthServiceState::new(handler, credentials)
+    }
+
+    fn app(state: OAuthServiceState, user: Uuid) -> Router {
+        Router::new()
+            .route("/oauth/authorize", post(authorize_oauth))
+            .layer(Extension(claims(user, Role::User)))
+            .route("/oauth/callback", get(oauth_callback))
+            .with_state(state)
+    }
+
+    /// The `state` parameter of an authorization URL
+    fn state_of(url: &str) -> String {
+        url.split(['?', '&']).find_map(|param| param.strip_prefix("state=")).unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_authorization_callback_saves_and_replaces_tokens() {
+        let storage = Arc::new(InMemoryCredentialStorage::new());
+        let state = state(storage.clone()).await;
+        let (user, other) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let request = serde_json::json!({ "integration": "slack", "name": "Team Slack" });
+        let (status, body) = call(app(state.clone(), user), "POST", "/oauth/authorize", Some(request)).await;
+        assert_eq!(status, StatusCode::OK);
+        let url = body["authorization_url"].as_str().unwrap();
+        assert!(url.contains("code_challenge_method=S256"));
+        let (status, _) = call(app(state.clone(), user), "POST", "/oauth/authorize", Some(serde_json::json!({ "integration": "github", "name": "GitHub" }))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let callback = format!("/oauth/callback?state={}&code=c-1", state_of(url));
+        let (status, body) = call(app(state.clone(), user), "GET", &callback, None).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["credential"]["name"], "Team Slack");
+        assert_eq!(body["credential"]["auth_type"], "OAuth2");
+        assert_eq!(body["credential"]["data"], "********");
+        let credential_id: Uuid = body["credential"]["id"].as_str().unwrap().parse().unwrap();
+        let stored = state.credentials.credentials.get(credential_id).await.unwrap();
+        assert_eq!(stored.owner, user);
+        assert!(stored.token_expires_at.is_some());
+        let plaintext = state
+            .credentials
+            .credentials
+            .decrypt(credential_id, CredentialUse::ConnectionTest { user_id: user }, &state.credentials.manager)
+            .await
+            .unwrap();
+        let token: OAuth2Token = serde_json::from_str(&plaintext).unwrap();
+        assert_eq!(token.access_token, "xoxb-1");
+        let restarted = CredentialStore::new().with_storage(storage.clone());
+        assert_eq!(restarted.load().await.unwrap(), 1);
+        assert_eq!(restarted.get(credential_id).await.unwrap().token_expires_at, stored.token_expires_at);
+
+        // States are used once
+        let (status, _) = call(app(state.clone(), user), "GET", &callback, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Only the owner reconnects a credential
+        let reconnect = serde_json::json!({ "integration": "slack", "credential_id": credential_id });
+        let (status, _) = call(app(state.clone(), other), "POST", "/oauth/authorize", Some(reconnect.clone())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (_, body) = call(app(state.clone(), user), "POST", "/oauth/authorize", Some(reconnect)).await;
+        let callback = format!("/oauth/callback?state={}&code=c-1", state_of(body["authorization_url"].as_str().unwrap()));
+        let (status, body) = call(app(state.clone(), user), "GET", &callback, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["credential"]["id"], credential_id.to_string());
+        assert!(state.credentials.credentials.get(credential_id).await.unwrap().rotated_at.is_some());
+        assert_eq!(state.credentials.credentials.list().await.len(), 1);
+        let restarted = CredentialStore::new().with_storage(storage);
+        restarted.load().await.unwrap();
+        assert!(restarted.get(credential_id).await.unwrap().rotated_at.is_some());
+
+        let (status, _) = call(app(state, user), "GET", "/oauth/callback?state=s&error=access_denied", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
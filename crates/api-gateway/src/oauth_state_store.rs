@@ -0,0 +1,82 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use integration_service::oauth::{OAuth2Error, OAuth2State, OAuth2StateStore};
+use sqlx::{PgPool, Row};
+
+/// Postgres-backed OAuth2 state store (see `migrations/012_oauth_states.sql`),
+/// so any gateway replica can complete an authorization
+pub struct PgOAuth2StateStore {
+    pool: PgPool,
+}
+
+impl PgOAuth2StateStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OAuth2StateStore for PgOAuth2StateStore {
+    async fn save(&self, state: &OAuth2State) -> Result<(), OAuth2Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO oauth_states (
+                state, integration, owner, credential_name, credential_id, code_verifier, created_at, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(&state.state)
+        .bind(&state.integration)
+        .bind(state.owner)
+        .bind(&state.credential_name)
+        .bind(state.credential_id)
+        .bind(&state.code_verifier)
+        .bind(state.created_at)
+        .bind(state.expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn take(&self, state: &str) -> Result<Option<OAuth2State>, OAuth2Error> {
+        let row = sqlx::query(
+            r#"
+            DELETE FROM oauth_states WHERE state = $1
+            RETURNING state, integration, owner, credential_name, credential_id, code_verifier, created_at, expires_at
+            "#,
+        )
+        .bind(state)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        row.as_ref().map(decode).transpose()
+    }
+
+    async fn purge_expired(&self, now: DateTime<Utc>) -> Result<usize, OAuth2Error> {
+        let result = sqlx::query("DELETE FROM oauth_states WHERE expires_at <= $1")
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(result.rows_affected() as usize)
+    }
+}
+
+fn decode(row: &sqlx::postgres::PgRow) -> Result<OAuth2State, OAuth2Error> {
+    Ok(OAuth2State {
+        state: row.try_get("state").map_err(storage_error)?,
+        integration: row.try_get("integration").map_err(storage_error)?,
+        owner: row.try_get("owner").map_err(storage_error)?,
+        credential_name: row.try_get("credential_name").map_err(storage_error)?,
+        credential_id: row.try_get("credential_id").map_err(storage_error)?,
+        code_verifier: row.try_get("code_verifier").map_err(storage_error)?,
+        created_at: row.try_get("created_at").map_err(storage_error)?,
+        expires_at: row.try_get("expires_at").map_err(storage_error)?,
+    })
+}
+
+fn storage_error(e: sqlx::Error) -> OAuth2Error {
+    OAuth2Error::Storage(e.to_string())
+}
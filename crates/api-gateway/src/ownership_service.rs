@@ -0,0 +1,264 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use common::types::{ActionType2, Role};
+use rbac_service::jwt::JwtClaims;
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::ownership::{OwnershipStore, WorkflowOwnership};
+use workflow_engine::revisions::RevisionStore;
+
+use crate::workflow_access::WorkflowAccess;
+
+/// Ownership service state
+#[derive(Clone)]
+pub struct OwnershipServiceState {
+    pub ownership: Arc<OwnershipStore>,
+    /// Annotated nodes are checked against the latest revision
+    pub revisions: Arc<RevisionStore>,
+    /// Authorizes ownership annotations on the workflows
+    access: WorkflowAccess,
+}
+
+impl OwnershipServiceState {
+    pub fn new(ownership: Arc<OwnershipStore>, revisions: Arc<RevisionStore>) -> Self {
+        Self { ownership, revisions, access: WorkflowAccess::default() }
+    }
+
+    /// Authorize callers against the saved workflows of `access`
+    pub fn with_access(mut self, access: WorkflowAccess) -> Self {
+        self.access = access;
+        self
+    }
+}
+
+/// On-call update request
+#[derive(Debug, Deserialize)]
+pub struct OnCallRequest {
+    pub members: Vec<Uuid>,
+}
+
+/// 查询工作流及其节点的负责人标注，以及工作流的实际负责人（未标注时为创建者）
+pub async fn get_ownership(
+    State(state): State<OwnershipServiceState>,
+    Path(workflow_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let ownership = state.ownership.get(workflow_id).await;
+    let owner = state.ownership.resolve(workflow_id, None).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "ownership": ownership,
+            "owner": owner
+        })),
+    )
+}
+
+/// 设置工作流和节点的负责人（用户或团队）；节点连续失败时通知负责人而不是创建者
+pub async fn update_ownership(
+    State(state): State<OwnershipServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+    Json(ownership): Json<WorkflowOwnership>,
+) -> impl IntoResponse {
+    if !state.access.can_on(&claims, ActionType2::Update, workflow_id).await {
+        return error_response(StatusCode::FORBIDDEN, "没有修改负责人的权限".to_string());
+    }
+    let Some(workflow) = state.revisions.latest(workflow_id).await else {
+        return error_response(StatusCode::NOT_FOUND, "工作流不存在".to_string());
+    };
+    if let Some(node_id) = ownership.nodes.keys().find(|id| !workflow.nodes.iter().any(|n| n.id == **id)) {
+        return error_response(StatusCode::BAD_REQUEST, format!("节点 {} 不存在", node_id));
+    }
+
+    state.ownership.set(workflow_id, ownership.clone()).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "ownership": ownership
+        })),
+    )
+}
+
+/// 查询团队的值班成员
+pub async fn get_on_call(
+    State(state): State<OwnershipServiceState>,
+    Path(team): Path<String>,
+) -> impl IntoResponse {
+    let members = state.ownership.on_call(&team).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "team": team,
+            "members": members
+        })),
+    )
+}
+
+/// 设置团队的值班成员（仅管理员和经理），团队负责的节点失败时通知他们
+pub async fn update_on_call(
+    State(state): State<OwnershipServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(team): Path<String>,
+    Json(request): Json<OnCallRequest>,
+) -> impl IntoResponse {
+    if !matches!(claims.role, Role::Admin | Role::Manager) {
+        return error_response(StatusCode::FORBIDDEN, "没有修改值班安排的权限".to_string());
+    }
+
+    state.ownership.set_on_call(&team, request.members.clone()).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "team": team,
+            "members": request.members
+        })),
+    )
+}
+
+/// 当前用户收到的失败告警（作为负责人或团队值班成员），从新到旧
+pub async fn failure_alerts(
+    State(state): State<OwnershipServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+) -> impl IntoResponse {
+    let alerts = state.ownership.alerts_for(claims.sub).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "total": alerts.len(),
+            "alerts": alerts
+        })),
+    )
+}
+
+fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, claims};
+    use axum::routing::get;
+    use axum::Router;
+    use common::types::{
+        ActionType, ExecutionResult, ExecutionState, Node, NodeConfig, NodeExecutionState, NodeType, Position, Workflow,
+        WORKFLOW_SCHEMA_VERSION,
+    };
+    use std::collections::HashMap;
+    use workflow_engine::history::{ExecutionRecord, NodeRecord};
+    use workflow_engine::workflows::{InMemoryWorkflowStore, StoredWorkflow, WorkflowStore};
+
+    fn app(state: OwnershipServiceState, user: Uuid, role: Role) -> Router {
+        Router::new()
+            .route("/workflows/:workflow_id/ownership", get(get_ownership).put(update_ownership))
+            .route("/teams/:team/on-call", get(get_on_call).put(update_on_call))
+            .route("/failure-alerts", get(failure_alerts))
+            .layer(Extension(claims(user, role)))
+            .with_state(state)
+    }
+
+    #[tokio::test]
+    async fn test_ownership_and_alerts() {
+        let node = Node {
+            id: Uuid::new_v4(),
+            node_type: NodeType::Action { action_type: ActionType::Http },
+            config: NodeConfig::default(),
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![],
+        };
+        let node_id = node.id;
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Payments".to_string(),
+            description: None,
+            nodes: vec![node],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+        let creator = Uuid::new_v4();
+        let revisions = Arc::new(RevisionStore::new());
+        revisions.submit(workflow.clone(), creator).await;
+        let ownership = Arc::new(OwnershipStore::new().with_revisions(revisions.clone()).with_alert_threshold(1));
+        let workflows = Arc::new(InMemoryWorkflowStore::new());
+        workflows.save(&StoredWorkflow { workflow: workflow.clone(), owner_id: creator, active: true }).await.unwrap();
+        let state = OwnershipServiceState::new(ownership.clone(), revisions).with_access(WorkflowAccess::new(workflows));
+
+        let uri = format!("/workflows/{}/ownership", workflow.id);
+        let (status, body) = call(app(state.clone(), creator, Role::Viewer), "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["owner"]["source"], "creator");
+
+        let annotation = serde_json::json!({ "nodes": { node_id.to_string(): { "type": "team", "team": "payments" } } });
+        let (status, _) = call(app(state.clone(), creator, Role::Viewer), "PUT", &uri, Some(annotation.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = call(app(state.clone(), Uuid::new_v4(), Role::User), "PUT", &uri, Some(annotation.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let unknown = serde_json::json!({ "nodes": { Uuid::new_v4().to_string(): { "type": "team", "team": "payments" } } });
+        let (status, _) = call(app(state.clone(), creator, Role::User), "PUT", &uri, Some(unknown)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = call(app(state.clone(), creator, Role::User), "PUT", &uri, Some(annotation)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let on_call = Uuid::new_v4();
+        let members = serde_json::json!({ "members": [on_call] });
+        let (status, _) = call(app(state.clone(), creator, Role::User), "PUT", "/teams/payments/on-call", Some(members.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = call(app(state.clone(), creator, Role::Manager), "PUT", "/teams/payments/on-call", Some(members)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        ownership.observe(&ExecutionRecord {
+            workflow_id: workflow.id,
+            organization_id: None,
+            started_at: chrono::Utc::now(),
+            result: ExecutionResult {
+                execution_id: Uuid::new_v4(),
+                state: ExecutionState::Failed,
+                completed_at: None,
+                error: Some("card processor unavailable".to_string()),
+                output: None,
+                usage: None,
+            },
+            nodes: vec![NodeRecord {
+                state: NodeExecutionState {
+                    node_id,
+                    state: ExecutionState::Failed,
+                    started_at: None,
+                    completed_at: None,
+                    input: None,
+                    output: None,
+                    error: None,
+                },
+                duration_ms: 0,
+                provider: None,
+            }],
+        }).await;
+
+        // The team's on-call member is alerted, not the creator
+        let (_, body) = call(app(state.clone(), on_call, Role::User), "GET", "/failure-alerts", None).await;
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["alerts"][0]["node_id"], node_id.to_string());
+        let (_, body) = call(app(state, creator, Role::User), "GET", "/failure-alerts", None).await;
+        assert_eq!(body["total"], 0);
+    }
+}
@@ -166,6 +166,7 @@ mod tests {
             priority,
             workflow_id: Uuid::new_v4(),
             node_id: Uuid::new_v4(),
+            organization_id: None,
             timeout: std::time::Duration::from_secs(30),
             retry_config: RetryConfig::default(),
         }
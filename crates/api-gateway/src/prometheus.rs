@@ -0,0 +1,188 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::fmt::{Display, Write};
+use std::sync::Arc;
+
+use crate::dispatcher::GatewayDispatcher;
+use crate::errors::ApiError;
+use crate::metrics::MetricsCollector;
+use crate::websocket::WebSocketManager;
+
+/// Content type of the Prometheus text exposition format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// State of the Prometheus scrape route
+#[derive(Clone)]
+pub struct PrometheusState {
+    pub metrics: Arc<MetricsCollector>,
+    pub ws_manager: WebSocketManager,
+    /// Dispatchers whose request pool and rate limiter are exported, by name
+    pub dispatchers: Vec<(String, GatewayDispatcher)>,
+    /// Bearer token scrapers must send; the route is open when unset
+    pub token: Option<String>,
+}
+
+/// Writes metric families in the Prometheus text exposition format
+#[derive(Default)]
+struct PrometheusWriter {
+    out: String,
+}
+
+impl PrometheusWriter {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(label, value)| format!("{}=\"{}\"", label, escape_label(value)))
+                .collect();
+            let _ = write!(self.out, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.out, " {}", value);
+    }
+}
+
+/// Escape a label value: backslashes, quotes and line feeds
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Render the gateway metrics, request pools, rate limiter tokens and
+/// WebSocket connections for a Prometheus scrape
+pub async fn render(state: &PrometheusState) -> String {
+    let mut w = PrometheusWriter::default();
+    let metrics = &state.metrics;
+
+    let providers = metrics.get_all_metrics().await;
+    w.family("flowvex_provider_requests_total", "counter", "Upstream provider requests by outcome.");
+    for provider in &providers {
+        w.sample("flowvex_provider_requests_total", &[("provider", &provider.provider), ("outcome", "success")], provider.successful_requests);
+        w.sample("flowvex_provider_requests_total", &[("provider", &provider.provider), ("outcome", "failure")], provider.failed_requests);
+    }
+    w.family("flowvex_provider_cost_total", "counter", "Cost of upstream provider requests.");
+    for provider in &providers {
+        w.sample("flowvex_provider_cost_total", &[("provider", &provider.provider)], provider.total_cost);
+    }
+
+    w.family("flowvex_provider_request_duration_seconds", "histogram", "Latency of upstream provider requests.");
+    for histogram in metrics.get_latency_histograms().await {
+        let provider = histogram.provider.as_str();
+        for (bound_ms, count) in &histogram.buckets {
+            let le = (*bound_ms as f64 / 1000.0).to_string();
+            w.sample("flowvex_provider_request_duration_seconds_bucket", &[("provider", provider), ("le", &le)], count);
+        }
+        w.sample("flowvex_provider_request_duration_seconds_bucket", &[("provider", provider), ("le", "+Inf")], histogram.count);
+        w.sample("flowvex_provider_request_duration_seconds_sum", &[("provider", provider)], histogram.sum_ms as f64 / 1000.0);
+        w.sample("flowvex_provider_request_duration_seconds_count", &[("provider", provider)], histogram.count);
+    }
+
+    w.family("flowvex_ai_cache_lookups_total", "counter", "AI response cache lookups by model and result.");
+    for cache in metrics.get_cache_metrics().await {
+        w.sample("flowvex_ai_cache_lookups_total", &[("model", &cache.model), ("result", "hit")], cache.hits);
+        w.sample("flowvex_ai_cache_lookups_total", &[("model", &cache.model), ("result", "miss")], cache.misses);
+    }
+    w.family("flowvex_response_cache_lookups_total", "counter", "Gateway response cache lookups by provider and result.");
+    for cache in metrics.get_response_cache_metrics().await {
+        w.sample("flowvex_response_cache_lookups_total", &[("provider", &cache.provider), ("result", "hit")], cache.hits);
+        w.sample("flowvex_response_cache_lookups_total", &[("provider", &cache.provider), ("result", "miss")], cache.misses);
+    }
+
+    w.family("flowvex_inbound_requests_total", "counter", "Inbound requests by route group, allowed or rate limited.");
+    for group in metrics.get_rate_limit_metrics().await {
+        w.sample("flowvex_inbound_requests_total", &[("group", &group.group), ("outcome", "allowed")], group.allowed);
+        w.sample("flowvex_inbound_requests_total", &[("group", &group.group), ("outcome", "limited")], group.limited);
+    }
+
+    w.family("flowvex_request_pool_requests", "gauge", "Requests queued or processing in a request pool.");
+    let mut pools = Vec::new();
+    for (name, dispatcher) in &state.dispatchers {
+        let pool = dispatcher.pool().get_metrics().await;
+        w.sample("flowvex_request_pool_requests", &[("pool", name), ("state", "queued")], pool.queued);
+        w.sample("flowvex_request_pool_requests", &[("pool", name), ("state", "processing")], pool.processing);
+        pools.push((name, pool));
+    }
+    w.family("flowvex_request_pool_finished_total", "counter", "Requests a request pool finished, by outcome.");
+    for (name, pool) in &pools {
+        w.sample("flowvex_request_pool_finished_total", &[("pool", name), ("outcome", "completed")], pool.completed);
+        w.sample("flowvex_request_pool_finished_total", &[("pool", name), ("outcome", "failed")], pool.failed);
+    }
+
+    w.family("flowvex_rate_limiter_tokens", "gauge", "Tokens left in provider rate limit buckets.");
+    for (name, dispatcher) in &state.dispatchers {
+        for (provider, (second, minute, hour)) in dispatcher.rate_limiter().get_all_available_tokens().await {
+            for (window, tokens) in [("second", second), ("minute", minute), ("hour", hour)] {
+                w.sample("flowvex_rate_limiter_tokens", &[("pool", name), ("provider", &provider), ("window", window)], tokens);
+            }
+        }
+    }
+
+    w.family("flowvex_websocket_connections", "gauge", "Connected WebSocket clients.");
+    w.sample("flowvex_websocket_connections", &[], state.ws_manager.connection_count().await);
+
+    w.out
+}
+
+/// Whether the request carries the scrape token, compared in constant time
+fn authorized(headers: &HeaderMap, token: &str) -> bool {
+    let Some(given) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Prometheus scrape endpoint
+pub async fn get_prometheus_metrics(State(state): State<PrometheusState>, headers: HeaderMap) -> Response {
+    if let Some(token) = &state.token {
+        if !authorized(&headers, token) {
+            return ApiError::unauthenticated("缺少或无效的指标访问令牌").into_response();
+        }
+    }
+    (StatusCode::OK, [(header::CONTENT_TYPE, CONTENT_TYPE)], render(&state).await).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool::RequestPool;
+    use crate::rate_limiter::RateLimiter;
+    use common::types::RateLimitConfig;
+
+    #[tokio::test]
+    async fn test_render_prometheus_text() {
+        let metrics = Arc::new(MetricsCollector::new());
+        metrics.record_success("open\"ai", 40, 0.5).await;
+        metrics.record_failure("open\"ai", 2_000).await;
+        metrics.record_rate_limit("auth", false).await;
+        let rate_limiter = Arc::new(RateLimiter::new());
+        rate_limiter.configure("openai".to_string(), RateLimitConfig::default()).await;
+        let dispatcher = GatewayDispatcher::new(Arc::new(RequestPool::new(2)), rate_limiter);
+        let state = PrometheusState {
+            metrics,
+            ws_manager: WebSocketManager::new(),
+            dispatchers: vec![("digests".to_string(), dispatcher)],
+            token: None,
+        };
+
+        let text = render(&state).await;
+        assert!(text.contains("# TYPE flowvex_provider_request_duration_seconds histogram\n"));
+        assert!(text.contains("flowvex_provider_requests_total{provider=\"open\\\"ai\",outcome=\"failure\"} 1\n"));
+        assert!(text.contains("flowvex_provider_request_duration_seconds_bucket{provider=\"open\\\"ai\",le=\"0.05\"} 1\n"));
+        assert!(text.contains("flowvex_provider_request_duration_seconds_bucket{provider=\"open\\\"ai\",le=\"+Inf\"} 2\n"));
+        assert!(text.contains("flowvex_provider_request_duration_seconds_sum{provider=\"open\\\"ai\"} 2.04\n"));
+        assert!(text.contains("flowvex_inbound_requests_total{group=\"auth\",outcome=\"limited\"} 1\n"));
+        assert!(text.contains("flowvex_rate_limiter_tokens{pool=\"digests\",provider=\"openai\",window=\"second\"}"));
+        assert!(text.contains("flowvex_websocket_connections 0\n"));
+    }
+}
@@ -0,0 +1,185 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use common::types::{Role, Workflow};
+use rbac_service::jwt::JwtClaims;
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::quota::{AdmissionDecision, OrgQuota, QuotaManager};
+
+/// Quota service state
+#[derive(Clone)]
+pub struct QuotaServiceState {
+    pub quotas: Arc<QuotaManager>,
+}
+
+impl QuotaServiceState {
+    pub fn new(quotas: Arc<QuotaManager>) -> Self {
+        Self { quotas }
+    }
+}
+
+/// Admission preview request
+#[derive(Debug, Deserialize)]
+pub struct PreviewRequest {
+    pub organization_id: Uuid,
+    pub workflow: Workflow,
+    /// Ask for an admin override; ignored for non-admin users
+    #[serde(default)]
+    pub override_quota: bool,
+}
+
+/// 预览执行是否会超出组织配额
+pub async fn preview_admission(
+    State(state): State<QuotaServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Json(request): Json<PreviewRequest>,
+) -> impl IntoResponse {
+    let admin_override = request.override_quota && claims.role == Role::Admin;
+    let preview = state.quotas
+        .preview(request.organization_id, &request.workflow, admin_override)
+        .await;
+
+    let status = match preview.decision {
+        AdmissionDecision::Rejected => StatusCode::TOO_MANY_REQUESTS,
+        _ => StatusCode::OK,
+    };
+
+    (
+        status,
+        Json(serde_json::json!({
+            "success": status == StatusCode::OK,
+            "preview": preview
+        })),
+    )
+}
+
+/// 查询组织配额与当月用量
+pub async fn get_quota(
+    State(state): State<QuotaServiceState>,
+    Path(organization_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let usage = state.quotas.get_usage(organization_id).await;
+    let quota = state.quotas.get_quota(organization_id).await;
+    let queued = state.quotas.queued(organization_id).await;
+
+    Json(serde_json::json!({
+        "success": true,
+        "quota": quota,
+        "usage": usage,
+        "queued": queued
+    }))
+}
+
+/// 设置组织配额（仅管理员），并释放配额内的排队执行
+pub async fn update_quota(
+    State(state): State<QuotaServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(organization_id): Path<Uuid>,
+    Json(quota): Json<OrgQuota>,
+) -> impl IntoResponse {
+    if claims.role != Role::Admin {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "只有管理员可以修改配额"
+            })),
+        );
+    }
+
+    state.quotas.set_quota(organization_id, quota).await;
+    let released = state.quotas.release_queued(organization_id).await;
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "released": released
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::claims;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::{post, put};
+    use axum::Router;
+    use common::types::{AINodeType, Node, NodeConfig, NodeType, Position, WORKFLOW_SCHEMA_VERSION};
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    fn app(state: QuotaServiceState, role: Role) -> Router {
+        Router::new()
+            .route("/quotas/preview", post(preview_admission))
+            .route("/quotas/:organization_id", put(update_quota))
+            .layer(Extension(claims(Uuid::new_v4(), role)))
+            .with_state(state)
+    }
+
+    fn json_request(method: &str, uri: &str, body: serde_json::Value) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_preview_and_admin_only_update() {
+        let state = QuotaServiceState::new(Arc::new(QuotaManager::new()));
+        let org = Uuid::new_v4();
+        let quota = serde_json::json!({ "monthly_ai_tokens": 100 });
+
+        let response = app(state.clone(), Role::User)
+            .oneshot(json_request("PUT", &format!("/quotas/{}", org), quota.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app(state.clone(), Role::Admin)
+            .oneshot(json_request("PUT", &format!("/quotas/{}", org), quota))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "AI".to_string(),
+            description: None,
+            nodes: vec![Node {
+                id: Uuid::new_v4(),
+                node_type: NodeType::AI { ai_type: AINodeType::TextGeneration },
+                config: NodeConfig {
+                    parameters: HashMap::from([("max_tokens".to_string(), serde_json::json!(500))]),
+                    ..Default::default()
+                },
+                position: Position { x: 0.0, y: 0.0 },
+                inputs: vec![],
+                outputs: vec![],
+            }],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+        let response = app(state, Role::User)
+            .oneshot(json_request("POST", "/quotas/preview", serde_json::json!({
+                "organization_id": org,
+                "workflow": workflow
+            })))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
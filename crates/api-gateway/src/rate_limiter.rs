@@ -1,13 +1,20 @@
 use common::types::RateLimitConfig;
 use common::error::GatewayError;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock};
+use uuid::Uuid;
+
+/// How long `acquire` waits for capacity before giving up
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// How often the request at the head of a provider's queue checks for a token
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
 
 /// Token bucket for rate limiting
 #[derive(Debug, Clone)]
-struct TokenBucket {
+pub(crate) struct TokenBucket {
     tokens: f64,
     capacity: f64,
     refill_rate: f64, // tokens per second
@@ -15,11 +22,11 @@ struct TokenBucket {
 }
 
 impl TokenBucket {
-    fn new(capacity: u32, refill_rate: u32) -> Self {
+    pub(crate) fn new(capacity: u32, refill_rate: f64) -> Self {
         Self {
             tokens: capacity as f64,
             capacity: capacity as f64,
-            refill_rate: refill_rate as f64,
+            refill_rate,
             last_refill: Instant::now(),
         }
     }
@@ -33,7 +40,7 @@ impl TokenBucket {
         self.last_refill = now;
     }
 
-    fn try_consume(&mut self, tokens: f64) -> bool {
+    pub(crate) fn try_consume(&mut self, tokens: f64) -> bool {
         self.refill();
         
         if self.tokens >= tokens {
@@ -44,10 +51,101 @@ impl TokenBucket {
         }
     }
 
-    fn available_tokens(&mut self) -> f64 {
+    pub(crate) fn available_tokens(&mut self) -> f64 {
         self.refill();
         self.tokens
     }
+
+    /// How long until `tokens` can be consumed
+    pub(crate) fn time_until(&mut self, tokens: f64) -> Duration {
+        self.refill();
+        let missing = tokens - self.tokens;
+        if missing <= 0.0 || self.refill_rate <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(missing / self.refill_rate)
+        }
+    }
+
+    /// Whether the bucket refilled to capacity
+    pub(crate) fn is_full(&mut self) -> bool {
+        self.refill();
+        self.tokens >= self.capacity
+    }
+}
+
+/// Per-second, per-minute and per-hour buckets of a provider
+type ProviderBuckets = (TokenBucket, TokenBucket, TokenBucket);
+
+/// Buckets of a configuration; only the per-second bucket holds a burst
+fn provider_buckets(config: &RateLimitConfig) -> ProviderBuckets {
+    let burst = config.burst.unwrap_or(config.requests_per_second).max(1);
+    (
+        TokenBucket::new(burst, config.requests_per_second as f64),
+        TokenBucket::new(config.requests_per_minute, config.requests_per_minute as f64 / 60.0),
+        TokenBucket::new(config.requests_per_hour, config.requests_per_hour as f64 / 3600.0),
+    )
+}
+
+/// Requests waiting for a provider's capacity, served in weighted fair order.
+///
+/// Each request gets a virtual finish time: the later of the queue's
+/// virtual time and its tenant's previous finish time, plus the inverse of
+/// the tenant's weight. The smallest finish time goes first, so a tenant
+/// with many waiting requests cannot hold back the others.
+#[derive(Debug, Default)]
+struct FairQueue {
+    /// Finish time of the request served last
+    virtual_time: f64,
+    /// Finish time of each tenant's latest request
+    last_finish: HashMap<Option<Uuid>, f64>,
+    /// Waiting requests by finish time and arrival
+    waiting: Vec<(f64, u64)>,
+    arrivals: u64,
+}
+
+impl FairQueue {
+    fn enqueue(&mut self, tenant: Option<Uuid>, weight: f64) -> (f64, u64) {
+        let start = self.last_finish.get(&tenant).copied().unwrap_or(0.0).max(self.virtual_time);
+        let ticket = (start + 1.0 / weight, self.arrivals);
+        self.arrivals += 1;
+        self.last_finish.insert(tenant, ticket.0);
+        self.waiting.push(ticket);
+        ticket
+    }
+
+    fn head(&self) -> Option<(f64, u64)> {
+        self.waiting.iter().copied().min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    fn serve(&mut self, ticket: (f64, u64)) {
+        self.virtual_time = self.virtual_time.max(ticket.0);
+        // Tenants that are caught up start from the virtual time again
+        let virtual_time = self.virtual_time;
+        self.last_finish.retain(|_, finish| *finish > virtual_time);
+    }
+}
+
+/// A request's place in a provider's fair queue, given up when dropped
+struct Ticket<'a> {
+    queues: &'a Mutex<HashMap<String, FairQueue>>,
+    served: &'a Notify,
+    provider: &'a str,
+    ticket: (f64, u64),
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let mut queues = self.queues.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(queue) = queues.get_mut(self.provider) {
+            queue.waiting.retain(|ticket| *ticket != self.ticket);
+            if queue.waiting.is_empty() && queue.last_finish.is_empty() {
+                queues.remove(self.provider);
+            }
+        }
+        // Served or given up, the next request may be at the head now
+        self.served.notify_waiters();
+    }
 }
 
 /// Rate limiter implementation
@@ -56,7 +154,13 @@ pub struct RateLimiter {
     /// Rate limit configurations per provider
     configs: Arc<RwLock<HashMap<String, RateLimitConfig>>>,
     /// Token buckets per provider (second, minute, hour)
-    buckets: Arc<RwLock<HashMap<String, (TokenBucket, TokenBucket, TokenBucket)>>>,
+    buckets: Arc<RwLock<HashMap<String, ProviderBuckets>>>,
+    /// Share of each organization in providers' capacity; 1 when unset
+    weights: Arc<RwLock<HashMap<Uuid, u32>>>,
+    /// Requests waiting for capacity per provider
+    queues: Arc<Mutex<HashMap<String, FairQueue>>>,
+    /// Woken whenever a waiting request was served or gave up
+    served: Arc<Notify>,
 }
 
 impl RateLimiter {
@@ -64,9 +168,19 @@ impl RateLimiter {
         Self {
             configs: Arc::new(RwLock::new(HashMap::new())),
             buckets: Arc::new(RwLock::new(HashMap::new())),
+            weights: Arc::new(RwLock::new(HashMap::new())),
+            queues: Arc::new(Mutex::new(HashMap::new())),
+            served: Arc::new(Notify::new()),
         }
     }
 
+    /// Set an organization's share of the capacity of providers it shares
+    /// with others: waiting requests of an organization with weight 2 are
+    /// served twice as often as those of one with weight 1
+    pub async fn set_tenant_weight(&self, organization_id: Uuid, weight: u32) {
+        self.weights.write().await.insert(organization_id, weight.max(1));
+    }
+
     /// Configure rate limits for a provider
     pub async fn configure(&self, provider: String, config: RateLimitConfig) {
         let mut configs = self.configs.write().await;
@@ -74,14 +188,7 @@ impl RateLimiter {
 
         // Initialize token buckets
         let mut buckets = self.buckets.write().await;
-        buckets.insert(
-            provider,
-            (
-                TokenBucket::new(config.requests_per_second, config.requests_per_second),
-                TokenBucket::new(config.requests_per_minute, config.requests_per_minute / 60),
-                TokenBucket::new(config.requests_per_hour, config.requests_per_hour / 3600),
-            ),
-        );
+        buckets.insert(provider, provider_buckets(&config));
     }
 
     /// Check if a request can proceed
@@ -136,22 +243,74 @@ impl RateLimiter {
         })
     }
 
+    /// Available tokens of every provider with rate limits, by provider name
+    pub async fn get_all_available_tokens(&self) -> Vec<(String, (f64, f64, f64))> {
+        let mut buckets = self.buckets.write().await;
+        let mut tokens: Vec<_> = buckets
+            .iter_mut()
+            .map(|(provider, (second, minute, hour))| {
+                (
+                    provider.clone(),
+                    (second.available_tokens(), minute.available_tokens(), hour.available_tokens()),
+                )
+            })
+            .collect();
+        tokens.sort_by(|a, b| a.0.cmp(&b.0));
+        tokens
+    }
+
     /// Wait until rate limit allows request
     pub async fn wait_for_capacity(&self, provider: &str) -> Result<(), GatewayError> {
-        let max_wait = Duration::from_secs(60);
+        self.acquire(provider, None).await
+    }
+
+    /// Wait until rate limit allows a request of an organization.
+    ///
+    /// Waiting requests take the provider's tokens in weighted fair order
+    /// across organizations (see [`RateLimiter::set_tenant_weight`]);
+    /// requests without an organization count as one more tenant.
+    pub async fn acquire(&self, provider: &str, organization_id: Option<Uuid>) -> Result<(), GatewayError> {
+        if !self.buckets.read().await.contains_key(provider) {
+            return Ok(());
+        }
+        let weight = match organization_id {
+            Some(id) => self.weights.read().await.get(&id).copied().unwrap_or(1),
+            None => 1,
+        };
+        let ticket = {
+            let mut queues = self.queues.lock().unwrap_or_else(|e| e.into_inner());
+            let queue = queues.entry(provider.to_string()).or_default();
+            Ticket {
+                queues: &self.queues,
+                served: &self.served,
+                provider,
+                ticket: queue.enqueue(organization_id, weight as f64),
+            }
+        };
+
         let start = Instant::now();
-        
         loop {
-            if self.check_limit(provider).await.is_ok() {
+            let served = self.served.notified();
+            let is_head = {
+                let queues = self.queues.lock().unwrap_or_else(|e| e.into_inner());
+                queues.get(provider).and_then(FairQueue::head) == Some(ticket.ticket)
+            };
+            if is_head && self.check_limit(provider).await.is_ok() {
+                if let Some(queue) = self.queues.lock().unwrap_or_else(|e| e.into_inner()).get_mut(provider) {
+                    queue.serve(ticket.ticket);
+                }
                 return Ok(());
             }
-            
-            if start.elapsed() > max_wait {
-                return Err(GatewayError::Timeout(max_wait.as_millis() as u64));
+
+            if start.elapsed() > MAX_WAIT {
+                return Err(GatewayError::Timeout(MAX_WAIT.as_millis() as u64));
+            }
+            // The head polls for tokens; the rest wait for their turn
+            if is_head {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            } else {
+                let _ = tokio::time::timeout(MAX_WAIT.saturating_sub(start.elapsed()), served).await;
             }
-            
-            // Wait a bit before retrying
-            tokio::time::sleep(Duration::from_millis(100)).await;
         }
     }
 
@@ -160,14 +319,7 @@ impl RateLimiter {
         let configs = self.configs.read().await;
         if let Some(config) = configs.get(provider) {
             let mut buckets = self.buckets.write().await;
-            buckets.insert(
-                provider.to_string(),
-                (
-                    TokenBucket::new(config.requests_per_second, config.requests_per_second),
-                    TokenBucket::new(config.requests_per_minute, config.requests_per_minute / 60),
-                    TokenBucket::new(config.requests_per_hour, config.requests_per_hour / 3600),
-                ),
-            );
+            buckets.insert(provider.to_string(), provider_buckets(config));
         }
     }
 
@@ -206,6 +358,7 @@ mod tests {
             requests_per_minute: 10,
             requests_per_hour: 100,
             concurrent_limit: 5,
+            burst: None,
         };
         
         limiter.configure("test_provider".to_string(), config).await;
@@ -227,6 +380,7 @@ mod tests {
             requests_per_minute: 60,
             requests_per_hour: 3600,
             concurrent_limit: 5,
+            burst: None,
         };
         
         limiter.configure("test_provider".to_string(), config).await;
@@ -253,6 +407,7 @@ mod tests {
             requests_per_minute: 100,
             requests_per_hour: 1000,
             concurrent_limit: 5,
+            burst: None,
         };
         
         limiter.configure("test_provider".to_string(), config).await;
@@ -275,6 +430,7 @@ mod tests {
             requests_per_minute: 10,
             requests_per_hour: 100,
             concurrent_limit: 5,
+            burst: None,
         };
         
         limiter.configure("test_provider".to_string(), config).await;
@@ -289,4 +445,62 @@ mod tests {
         // Should succeed after reset
         assert!(limiter.check_limit("test_provider").await.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_burst_separate_from_refill_rate() {
+        let limiter = RateLimiter::new();
+        limiter.configure("test_provider".to_string(), RateLimitConfig {
+            requests_per_second: 1,
+            requests_per_minute: 60,
+            requests_per_hour: 3600,
+            concurrent_limit: 5,
+            burst: Some(3),
+        }).await;
+
+        for _ in 0..3 {
+            assert!(limiter.check_limit("test_provider").await.is_ok());
+        }
+        assert!(limiter.check_limit("test_provider").await.is_err());
+        let (second, minute, _) = limiter.get_available_tokens("test_provider").await.unwrap();
+        assert!(second < 1.0);
+        assert!(minute > 56.0);
+    }
+
+    #[tokio::test]
+    async fn test_tenants_share_capacity_fairly() {
+        let limiter = Arc::new(RateLimiter::new());
+        limiter.configure("shared".to_string(), RateLimitConfig {
+            requests_per_second: 50,
+            requests_per_minute: 3000,
+            requests_per_hour: 100_000,
+            concurrent_limit: 5,
+            burst: Some(1),
+        }).await;
+        let (heavy, light) = (Uuid::new_v4(), Uuid::new_v4());
+        let served = Arc::new(Mutex::new(Vec::new()));
+
+        let mut tasks = Vec::new();
+        for tenant in std::iter::repeat_n(heavy, 6).chain(std::iter::repeat_n(light, 2)) {
+            let (limiter, served) = (limiter.clone(), served.clone());
+            tasks.push(tokio::spawn(async move {
+                limiter.acquire("shared", Some(tenant)).await.unwrap();
+                served.lock().unwrap().push(tenant);
+            }));
+            // Queue the heavy tenant's backlog first
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let served = served.lock().unwrap();
+        assert_eq!(served.len(), 8);
+        // Served first-come, the light tenant would come last
+        let light_positions: Vec<_> = served.iter().enumerate()
+            .filter(|(_, tenant)| **tenant == light)
+            .map(|(position, _)| position)
+            .collect();
+        assert!(light_positions.iter().all(|position| *position < 6), "{:?}", light_positions);
+        assert!(limiter.queues.lock().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,369 @@
+//! Rate limiting of inbound requests.
+//!
+//! Each request is counted against a token bucket of its route group and
+//! client: the authenticated user, or the client IP when the request carries
+//! no valid token. A client out of tokens is answered with 429 and a
+//! `Retry-After` header; allowed and limited requests of every group are
+//! counted in the [`MetricsCollector`].
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use common::error::ErrorCode;
+use rbac_service::JwtManager;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::errors::ApiError;
+use crate::metrics::MetricsCollector;
+use crate::rate_limiter::TokenBucket;
+
+/// Group of the requests matching no configured route group
+pub const DEFAULT_GROUP: &str = "default";
+
+/// Most clients tracked at once. When the table is full, the buckets of idle
+/// clients are dropped; if none is idle, new clients share the bucket of
+/// [`ClientKey::Unknown`] until some are.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Least time between two sweeps for idle clients of a full table
+const PRUNE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sustained rate and burst allowed to a client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteLimit {
+    pub requests_per_minute: u32,
+    /// Requests a client may send at once after being idle
+    pub burst: u32,
+}
+
+impl RouteLimit {
+    pub fn new(requests_per_minute: u32, burst: u32) -> Self {
+        Self { requests_per_minute, burst }
+    }
+}
+
+/// Routes sharing a limit, by path prefix
+#[derive(Debug, Clone)]
+pub struct RouteGroup {
+    /// Name the group's requests are counted under
+    pub name: String,
+    /// Path prefix of the group's routes, matched on whole segments
+    pub prefix: String,
+    /// Limit of each client, `None` for unlimited routes
+    pub limit: Option<RouteLimit>,
+}
+
+impl RouteGroup {
+    pub fn new(name: &str, prefix: &str, limit: Option<RouteLimit>) -> Self {
+        Self { name: name.to_string(), prefix: prefix.to_string(), limit }
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let prefix = self.prefix.trim_end_matches('/');
+        path.strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+/// Inbound request limits
+#[derive(Debug, Clone)]
+pub struct RequestLimitConfig {
+    /// Limit of the routes in no group
+    pub default: RouteLimit,
+    /// Groups with their own limits; the longest matching prefix wins
+    pub groups: Vec<RouteGroup>,
+    /// Take the client IP from the last `X-Forwarded-For` entry, the one
+    /// the proxy appended; only enable behind a proxy that sets it
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for RequestLimitConfig {
+    fn default() -> Self {
+        Self {
+            default: RouteLimit::new(600, 100),
+            groups: vec![
+                RouteGroup::new("health", "/health", None),
+                // Login and registration are guessable, keep them slow
+                RouteGroup::new("auth", "/api/v1/auth", Some(RouteLimit::new(30, 10))),
+                RouteGroup::new("webhooks", "/api/v1/webhooks", Some(RouteLimit::new(1200, 200))),
+            ],
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+impl RequestLimitConfig {
+    /// Name and limit of the group of `path`
+    fn group_of(&self, path: &str) -> (&str, Option<RouteLimit>) {
+        self.groups
+            .iter()
+            .filter(|group| group.matches(path))
+            .max_by_key(|group| group.prefix.trim_end_matches('/').len())
+            .map(|group| (group.name.as_str(), group.limit))
+            .unwrap_or((DEFAULT_GROUP, Some(self.default)))
+    }
+}
+
+/// Who a request is counted against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClientKey {
+    User(Uuid),
+    Ip(IpAddr),
+    /// Neither authenticated nor with a known address
+    Unknown,
+}
+
+/// Token buckets of the tracked clients, per route group
+#[derive(Default)]
+struct Buckets {
+    clients: HashMap<(String, ClientKey), TokenBucket>,
+    pruned_at: Option<Instant>,
+}
+
+impl Buckets {
+    /// Key of the bucket `client` is counted in, keeping the table at most
+    /// `MAX_TRACKED_CLIENTS` clients (plus one overflow bucket per group)
+    fn key_for(&mut self, group: &str, client: ClientKey) -> (String, ClientKey) {
+        let key = (group.to_string(), client);
+        if self.clients.len() < MAX_TRACKED_CLIENTS || self.clients.contains_key(&key) {
+            return key;
+        }
+
+        // Sweep at most once per interval, a flood of new clients would
+        // otherwise scan the whole table on every request
+        if self.pruned_at.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL) {
+            self.pruned_at = Some(Instant::now());
+            self.clients.retain(|_, bucket| !bucket.is_full());
+            if self.clients.len() < MAX_TRACKED_CLIENTS {
+                return key;
+            }
+        }
+        (group.to_string(), ClientKey::Unknown)
+    }
+}
+
+/// Per-client token buckets of the route groups
+pub struct RequestLimiter {
+    config: RequestLimitConfig,
+    jwt_manager: Arc<JwtManager>,
+    buckets: Mutex<Buckets>,
+    metrics: Option<Arc<MetricsCollector>>,
+}
+
+impl RequestLimiter {
+    pub fn new(config: RequestLimitConfig, jwt_manager: Arc<JwtManager>) -> Self {
+        Self {
+            config,
+            jwt_manager,
+            buckets: Mutex::new(Buckets::default()),
+            metrics: None,
+        }
+    }
+
+    /// Count allowed and limited requests per group in `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// The authenticated user of a request, else its client IP
+    pub async fn client_key(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> ClientKey {
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if let Some(token) = token {
+            if let Ok(claims) = self.jwt_manager.validate_token(token).await {
+                return ClientKey::User(claims.sub);
+            }
+        }
+
+        client_ip(headers, peer, self.config.trust_forwarded_for)
+            .map(ClientKey::Ip)
+            .unwrap_or(ClientKey::Unknown)
+    }
+
+    /// Take a token of `client` for a request to `path`. Returns the group
+    /// the request counts under and, if it is limited, how long until the
+    /// client may retry.
+    pub fn check(&self, path: &str, client: ClientKey) -> (String, Result<(), Duration>) {
+        let (group, limit) = self.config.group_of(path);
+        let Some(limit) = limit else {
+            return (group.to_string(), Ok(()));
+        };
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let key = buckets.key_for(group, client);
+        let bucket = buckets.clients.entry(key).or_insert_with(|| {
+            TokenBucket::new(limit.burst.max(1), limit.requests_per_minute.max(1) as f64 / 60.0)
+        });
+        let result = if bucket.try_consume(1.0) {
+            Ok(())
+        } else {
+            Err(bucket.time_until(1.0))
+        };
+        (group.to_string(), result)
+    }
+
+    /// Middleware answering clients over their group's limit with 429
+    pub async fn middleware(State(limiter): State<Arc<Self>>, req: Request, next: Next) -> Response {
+        let peer = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let client = limiter.client_key(req.headers(), peer).await;
+        let (group, result) = limiter.check(req.uri().path(), client);
+        if let Some(metrics) = &limiter.metrics {
+            metrics.record_rate_limit(&group, result.is_ok()).await;
+        }
+
+        match result {
+            Ok(()) => next.run(req).await,
+            Err(wait) => {
+                let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+                tracing::debug!(group = %group, client = ?client, retry_after, "Request rate limited");
+                let mut response = ApiError::new(
+                    ErrorCode::RateLimited,
+                    format!("请求过于频繁，请在 {} 秒后重试", retry_after),
+                )
+                .into_response();
+                response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+                response
+            }
+        }
+    }
+}
+
+/// IP address of a request's client: the entry the trusted proxy appended
+/// to `X-Forwarded-For` when `trust_forwarded_for` is set, else the peer.
+///
+/// Entries left of the last one come from the client and can be forged, so
+/// only the last one is read.
+pub fn client_ip(headers: &HeaderMap, peer: Option<IpAddr>, trust_forwarded_for: bool) -> Option<IpAddr> {
+    let forwarded = trust_forwarded_for
+        .then(|| headers.get_all("x-forwarded-for").iter().next_back())
+        .flatten()
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit(',').next())
+        .and_then(|ip| ip.trim().parse().ok());
+    forwarded.or(peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::Router;
+    use common::types::Role;
+    use tower::ServiceExt;
+
+    fn limiter(config: RequestLimitConfig) -> RequestLimiter {
+        RequestLimiter::new(config, Arc::new(JwtManager::new("secret", 1)))
+    }
+
+    #[test]
+    fn test_route_groups_and_clients() {
+        let config = RequestLimitConfig {
+            default: RouteLimit::new(60, 2),
+            groups: vec![
+                RouteGroup::new("health", "/health", None),
+                RouteGroup::new("auth", "/api/v1/auth", Some(RouteLimit::new(60, 1))),
+            ],
+            trust_forwarded_for: false,
+        };
+        assert_eq!(config.group_of("/api/v1/auth/login").0, "auth");
+        assert_eq!(config.group_of("/api/v1/authors").0, DEFAULT_GROUP);
+        assert_eq!(config.group_of("/health").1, None);
+
+        let limiter = limiter(config);
+        let (alice, bob) = (ClientKey::User(Uuid::new_v4()), ClientKey::User(Uuid::new_v4()));
+        assert!(limiter.check("/api/v1/auth/login", alice).1.is_ok());
+        let wait = limiter.check("/api/v1/auth/login", alice).1.unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_secs(1));
+        // Other clients and other groups have their own buckets
+        assert!(limiter.check("/api/v1/auth/login", bob).1.is_ok());
+        assert!(limiter.check("/api/v1/workflows", alice).1.is_ok());
+        for _ in 0..10 {
+            assert!(limiter.check("/health", alice).1.is_ok());
+        }
+    }
+
+    #[test]
+    fn test_tracked_clients_are_capped() {
+        let limiter = limiter(RequestLimitConfig { default: RouteLimit::new(1, 1), ..Default::default() });
+        let ip = |n: usize| ClientKey::Ip(IpAddr::from([10, (n >> 16) as u8, (n >> 8) as u8, n as u8]));
+        for n in 0..MAX_TRACKED_CLIENTS {
+            assert!(limiter.check("/api/v1/workflows", ip(n)).1.is_ok());
+        }
+
+        // No client is idle: new ones share a single bucket, tracked ones keep theirs
+        assert!(limiter.check("/api/v1/workflows", ip(MAX_TRACKED_CLIENTS)).1.is_ok());
+        assert!(limiter.check("/api/v1/workflows", ip(MAX_TRACKED_CLIENTS + 1)).1.is_err());
+        assert!(limiter.check("/api/v1/workflows", ip(0)).1.is_err());
+        assert_eq!(limiter.buckets.lock().unwrap().clients.len(), MAX_TRACKED_CLIENTS + 1);
+    }
+
+    #[tokio::test]
+    async fn test_client_key() {
+        let jwt_manager = Arc::new(JwtManager::new("secret", 1));
+        let config = RequestLimitConfig { trust_forwarded_for: true, ..Default::default() };
+        let limiter = RequestLimiter::new(config, jwt_manager.clone());
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+
+        let user = Uuid::new_v4();
+        let token = jwt_manager.generate_token(user, Role::User, vec![]).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        assert_eq!(limiter.client_key(&headers, Some(peer)).await, ClientKey::User(user));
+
+        // Invalid tokens count against the address
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer forged".parse().unwrap());
+        assert_eq!(limiter.client_key(&headers, Some(peer)).await, ClientKey::Ip(peer));
+        // Only the entry the proxy appended counts, the client forges the others
+        headers.insert("x-forwarded-for", "198.51.100.1, 203.0.113.7".parse().unwrap());
+        assert_eq!(limiter.client_key(&headers, Some(peer)).await, ClientKey::Ip("203.0.113.7".parse().unwrap()));
+        headers.append("x-forwarded-for", "192.0.2.9".parse().unwrap());
+        assert_eq!(limiter.client_key(&headers, Some(peer)).await, ClientKey::Ip("192.0.2.9".parse().unwrap()));
+        headers.insert("x-forwarded-for", "not-an-ip".parse().unwrap());
+        assert_eq!(limiter.client_key(&headers, Some(peer)).await, ClientKey::Ip(peer));
+        let untrusted = RequestLimiter::new(RequestLimitConfig::default(), jwt_manager);
+        headers.insert("x-forwarded-for", "203.0.113.7".parse().unwrap());
+        assert_eq!(untrusted.client_key(&headers, Some(peer)).await, ClientKey::Ip(peer));
+        assert_eq!(limiter.client_key(&HeaderMap::new(), None).await, ClientKey::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_limited_requests_get_retry_after() {
+        let metrics = Arc::new(MetricsCollector::new());
+        let config = RequestLimitConfig {
+            default: RouteLimit::new(1, 1),
+            groups: vec![],
+            trust_forwarded_for: false,
+        };
+        let limiter = Arc::new(limiter(config).with_metrics(metrics.clone()));
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(limiter, RequestLimiter::middleware));
+        let request = || Request::builder().uri("/").body(Body::empty()).unwrap();
+
+        assert_eq!(app.clone().oneshot(request()).await.unwrap().status(), StatusCode::OK);
+        let response = app.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()[header::RETRY_AFTER].to_str().unwrap().parse().unwrap();
+        assert!((1..=60).contains(&retry_after));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "RATE_LIMITED");
+
+        let counted = metrics.get_rate_limit_metrics().await;
+        assert_eq!((counted[0].group.as_str(), counted[0].allowed, counted[0].limited), (DEFAULT_GROUP, 1, 1));
+    }
+}
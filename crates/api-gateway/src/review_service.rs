@@ -0,0 +1,517 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use common::types::{ActionType2, Permission, ResourceType, Role, Scope, Workflow};
+use rbac_service::jwt::JwtClaims;
+use rbac_service::permissions::permission_string;
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::diff::diff_workflows;
+use workflow_engine::graph;
+use workflow_engine::revisions::{ReviewError, ReviewPolicy, RevisionStore, SubmitOutcome};
+use workflow_engine::WorkflowScheduler;
+
+use crate::workflow_access::WorkflowAccess;
+
+/// Review service state
+#[derive(Clone)]
+pub struct ReviewServiceState {
+    pub revisions: Arc<RevisionStore>,
+    /// Schedules of the workflows, to hold back edits that would break scheduled runs
+    pub scheduler: Option<Arc<WorkflowScheduler>>,
+    /// Authorizes graph cleanups and trash restores on the workflows
+    access: WorkflowAccess,
+}
+
+impl ReviewServiceState {
+    pub fn new(revisions: Arc<RevisionStore>) -> Self {
+        Self { revisions, scheduler: None, access: WorkflowAccess::default() }
+    }
+
+    pub fn with_scheduler(mut self, scheduler: Arc<WorkflowScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// Authorize callers against the saved workflows of `access`
+    pub fn with_access(mut self, access: WorkflowAccess) -> Self {
+        self.access = access;
+        self
+    }
+}
+
+/// Revision submit options
+#[derive(Debug, Default, Deserialize)]
+pub struct SubmitQuery {
+    /// Save even when the edit breaks scheduled runs
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Approve/reject request
+#[derive(Debug, Default, Deserialize)]
+pub struct ReviewRequest {
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// Trash restore request
+#[derive(Debug, Deserialize)]
+pub struct RestoreRequest {
+    pub node_ids: Vec<Uuid>,
+}
+
+/// Whether the caller holds the Approve permission at any scope
+fn can_approve(claims: &JwtClaims) -> bool {
+    let granted = [Scope::All, Scope::Team].into_iter().any(|scope| {
+        claims.permissions.contains(&permission_string(&Permission {
+            resource: ResourceType::Workflow,
+            action: ActionType2::Approve,
+            scope,
+        }))
+    });
+    matches!(claims.role, Role::Admin | Role::Manager) || granted
+}
+
+/// 提交工作流修订：需要审核的生产工作流生成待审核修订（返回差异），否则立即生效。
+/// 修改会破坏已调度运行（删除或改变仍被下游引用的输出）时返回 409 及破坏性变更，
+/// 确认后可用 force=true 强制保存
+pub async fn submit_revision(
+    State(state): State<ReviewServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+    Query(query): Query<SubmitQuery>,
+    Json(workflow): Json<Workflow>,
+) -> impl IntoResponse {
+    if workflow.id != workflow_id {
+        return error_response(StatusCode::BAD_REQUEST, "工作流 ID 与路径不一致".to_string());
+    }
+
+    // Scheduled runs use the active revision
+    let breaking_changes = match state.revisions.active(workflow_id).await {
+        Some(active) => diff_workflows(&active, &workflow).breaking_changes,
+        None => Vec::new(),
+    };
+    if !breaking_changes.is_empty() && !query.force {
+        if let Some(scheduler) = &state.scheduler {
+            if scheduler.get_schedule(workflow_id).await.is_some_and(|s| s.enabled) {
+                return (
+                    StatusCode::CONFLICT,
+                    Json(serde_json::json!({
+                        "success": false,
+                        "message": "修改会破坏已调度的运行：被删除或改变类型的输出仍被下游节点引用",
+                        "breaking_changes": breaking_changes
+                    })),
+                );
+            }
+        }
+    }
+
+    let outcome = state.revisions.submit(workflow, claims.sub).await;
+    let status = match outcome {
+        SubmitOutcome::Activated { .. } => StatusCode::OK,
+        SubmitOutcome::PendingReview { .. } => StatusCode::ACCEPTED,
+    };
+
+    (
+        status,
+        Json(serde_json::json!({
+            "success": true,
+            "result": outcome,
+            "breaking_changes": breaking_changes
+        })),
+    )
+}
+
+/// 列出工作流的全部修订
+pub async fn list_revisions(
+    State(state): State<ReviewServiceState>,
+    Path(workflow_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let revisions = state.revisions.revisions(workflow_id).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "revisions": revisions
+        })),
+    )
+}
+
+/// 批准待审核修订，调度器和 Webhook 随即切换到该修订
+pub async fn approve_revision(
+    State(state): State<ReviewServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(revision_id): Path<Uuid>,
+    request: Option<Json<ReviewRequest>>,
+) -> impl IntoResponse {
+    if !can_approve(&claims) {
+        return error_response(StatusCode::FORBIDDEN, "没有审批修订的权限".to_string());
+    }
+    let comment = request.and_then(|Json(r)| r.comment);
+    match state.revisions.approve(revision_id, claims.sub, comment).await {
+        Ok(revision) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "revision": revision
+            })),
+        ),
+        Err(e) => review_error(e),
+    }
+}
+
+/// 驳回待审核修订，当前生效的修订保持不变
+pub async fn reject_revision(
+    State(state): State<ReviewServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(revision_id): Path<Uuid>,
+    request: Option<Json<ReviewRequest>>,
+) -> impl IntoResponse {
+    if !can_approve(&claims) {
+        return error_response(StatusCode::FORBIDDEN, "没有审批修订的权限".to_string());
+    }
+    let comment = request.and_then(|Json(r)| r.comment);
+    match state.revisions.reject(revision_id, claims.sub, comment).await {
+        Ok(revision) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "revision": revision
+            })),
+        ),
+        Err(e) => review_error(e),
+    }
+}
+
+/// 获取工作流的审核策略
+pub async fn get_review_policy(
+    State(state): State<ReviewServiceState>,
+    Path(workflow_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let policy = state.revisions.get_policy(workflow_id).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "policy": policy
+        })),
+    )
+}
+
+/// 设置工作流的审核策略（需要审批权限）
+pub async fn update_review_policy(
+    State(state): State<ReviewServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+    Json(policy): Json<ReviewPolicy>,
+) -> impl IntoResponse {
+    if !can_approve(&claims) {
+        return error_response(StatusCode::FORBIDDEN, "没有修改审核策略的权限".to_string());
+    }
+    state.revisions.set_policy(workflow_id, policy.clone()).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "policy": policy
+        })),
+    )
+}
+
+/// 当前用户的审核通知：待审核请求（审批人）以及自己修订的审批结果
+pub async fn review_inbox(
+    State(state): State<ReviewServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+) -> impl IntoResponse {
+    let notifications = state.revisions.notifications_for(claims.sub, can_approve(&claims)).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "notifications": notifications
+        })),
+    )
+}
+
+/// 分析工作流最新修订中不可达和无效分支（结果未被任何动作使用）的节点
+pub async fn analyze_graph(
+    State(state): State<ReviewServiceState>,
+    Path(workflow_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let Some(workflow) = state.revisions.latest(workflow_id).await else {
+        return error_response(StatusCode::NOT_FOUND, "工作流没有任何修订".to_string());
+    };
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "analysis": graph::analyze(&workflow),
+            "trash": workflow.trash
+        })),
+    )
+}
+
+/// 清理不可达和无效分支节点：节点移入工作流的回收区，并作为新修订提交
+pub async fn cleanup_graph(
+    State(state): State<ReviewServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+) -> impl IntoResponse {
+    if !state.access.can_on(&claims, ActionType2::Update, workflow_id).await {
+        return error_response(StatusCode::FORBIDDEN, "没有修改工作流的权限".to_string());
+    }
+    let Some(mut workflow) = state.revisions.latest(workflow_id).await else {
+        return error_response(StatusCode::NOT_FOUND, "工作流没有任何修订".to_string());
+    };
+
+    let moved = graph::prune(&mut workflow);
+    if moved.nodes.is_empty() {
+        return (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "moved": moved,
+                "result": null
+            })),
+        );
+    }
+    workflow.updated_at = chrono::Utc::now();
+    submitted(state.revisions.submit(workflow, claims.sub).await, moved)
+}
+
+/// 从回收区恢复节点（两端都已恢复的连线随之恢复），并作为新修订提交
+pub async fn restore_trash(
+    State(state): State<ReviewServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+    Json(request): Json<RestoreRequest>,
+) -> impl IntoResponse {
+    if !state.access.can_on(&claims, ActionType2::Update, workflow_id).await {
+        return error_response(StatusCode::FORBIDDEN, "没有修改工作流的权限".to_string());
+    }
+    let Some(mut workflow) = state.revisions.latest(workflow_id).await else {
+        return error_response(StatusCode::NOT_FOUND, "工作流没有任何修订".to_string());
+    };
+
+    let moved = graph::restore_from_trash(&mut workflow, &request.node_ids);
+    if moved.nodes.len() != request.node_ids.len() {
+        return error_response(StatusCode::BAD_REQUEST, "部分节点不在回收区中".to_string());
+    }
+    workflow.updated_at = chrono::Utc::now();
+    submitted(state.revisions.submit(workflow, claims.sub).await, moved)
+}
+
+fn submitted(outcome: SubmitOutcome, moved: graph::TrashMove) -> (StatusCode, Json<serde_json::Value>) {
+    let status = match outcome {
+        SubmitOutcome::Activated { .. } => StatusCode::OK,
+        SubmitOutcome::PendingReview { .. } => StatusCode::ACCEPTED,
+    };
+    (
+        status,
+        Json(serde_json::json!({
+            "success": true,
+            "moved": moved,
+            "result": outcome
+        })),
+    )
+}
+
+fn review_error(error: ReviewError) -> (StatusCode, Json<serde_json::Value>) {
+    let status = match error {
+        ReviewError::RevisionNotFound(_) => StatusCode::NOT_FOUND,
+        ReviewError::NotPending(_) => StatusCode::CONFLICT,
+        ReviewError::SelfReview => StatusCode::FORBIDDEN,
+    };
+    error_response(status, error.to_string())
+}
+
+fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, claims};
+    use axum::routing::{get, post, put};
+    use axum::Router;
+    use common::types::WORKFLOW_SCHEMA_VERSION;
+    use std::collections::HashMap;
+    use workflow_engine::workflows::{InMemoryWorkflowStore, StoredWorkflow, WorkflowStore};
+
+    fn app(state: ReviewServiceState, user_id: Uuid, role: Role) -> Router {
+        Router::new()
+            .route("/workflows/:workflow_id/revisions", put(submit_revision))
+            .route("/revisions/:revision_id/approve", post(approve_revision))
+            .route("/reviews/inbox", get(review_inbox))
+            .route("/workflows/:workflow_id/graph/cleanup", post(cleanup_graph))
+            .route("/workflows/:workflow_id/trash/restore", post(restore_trash))
+            .layer(Extension(claims(user_id, role)))
+            .with_state(state)
+    }
+
+    #[tokio::test]
+    async fn test_pending_revision_requires_approver() {
+        let state = ReviewServiceState::new(Arc::new(RevisionStore::new()));
+        let (author, reviewer) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Billing".to_string(),
+            description: None,
+            nodes: vec![],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+        state.revisions.set_policy(workflow.id, ReviewPolicy { require_review: true, reviewers: vec![] }).await;
+        let uri = format!("/workflows/{}/revisions", workflow.id);
+
+        let (status, _) = call(app(state.clone(), author, Role::User), "PUT", &uri, Some(serde_json::json!(workflow))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        workflow.name = "Billing v2".to_string();
+        let (status, body) = call(app(state.clone(), author, Role::User), "PUT", &uri, Some(serde_json::json!(workflow))).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(body["result"]["request"]["diff"]["fields"][0]["after"], "Billing v2");
+        let approve_uri = format!("/revisions/{}/approve", body["result"]["request"]["revision_id"].as_str().unwrap());
+
+        let (status, _) = call(app(state.clone(), author, Role::User), "POST", &approve_uri, Some(serde_json::json!({}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, inbox) = call(app(state.clone(), reviewer, Role::Manager), "GET", "/reviews/inbox", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(inbox["notifications"][0]["event"], "requested");
+
+        let (status, _) = call(app(state.clone(), reviewer, Role::Manager), "POST", &approve_uri, Some(serde_json::json!({ "comment": "ok" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(state.revisions.active(workflow.id).await.unwrap().name, "Billing v2");
+    }
+
+    #[tokio::test]
+    async fn test_breaking_edit_of_scheduled_workflow_needs_force() {
+        use common::types::{ActionType, DataType, Edge, Node, NodeConfig, NodeType, Port, Position, TriggerType};
+        use workflow_engine::scheduler::{ScheduleConfig, ScheduleType};
+        use workflow_engine::WorkflowExecutor;
+
+        let node = |node_type, outputs| Node {
+            id: Uuid::new_v4(),
+            node_type,
+            config: NodeConfig::default(),
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs,
+        };
+        let trigger = node(NodeType::Trigger { trigger_type: TriggerType::Schedule }, vec![Port {
+            id: "output".to_string(),
+            name: "output".to_string(),
+            data_type: DataType::Object,
+        }]);
+        let action = node(NodeType::Action { action_type: ActionType::Http }, vec![]);
+        let mut workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Nightly sync".to_string(),
+            description: None,
+            edges: vec![Edge {
+                id: Uuid::new_v4(),
+                source: trigger.id,
+                source_handle: "output".to_string(),
+                target: action.id,
+                target_handle: "input".to_string(),
+            }],
+            nodes: vec![trigger, action],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+        let scheduler = Arc::new(WorkflowScheduler::new(Arc::new(WorkflowExecutor::new())));
+        scheduler.add_schedule(ScheduleConfig {
+            workflow_id: workflow.id,
+            schedule_type: ScheduleType::Interval(std::time::Duration::from_secs(3600)),
+            enabled: true,
+        }).await.unwrap();
+        let state = ReviewServiceState::new(Arc::new(RevisionStore::new())).with_scheduler(scheduler);
+        let user = Uuid::new_v4();
+        state.revisions.submit(workflow.clone(), user).await;
+
+        // The trigger loses the output its edge still reads from
+        workflow.nodes[0].outputs.clear();
+        let uri = format!("/workflows/{}/revisions", workflow.id);
+        let (status, body) = call(app(state.clone(), user, Role::User), "PUT", &uri, Some(serde_json::json!(workflow))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["breaking_changes"][0]["kind"], "removed_output");
+        assert_eq!(state.revisions.revisions(workflow.id).await.len(), 1);
+
+        let (status, body) = call(app(state.clone(), user, Role::User), "PUT", &format!("{}?force=true", uri), Some(serde_json::json!(workflow))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["breaking_changes"][0]["port"], "output");
+        assert!(state.revisions.active(workflow.id).await.unwrap().nodes[0].outputs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_moves_orphans_to_trash_as_new_revision() {
+        use common::types::{ActionType, Node, NodeConfig, NodeType, Position, TriggerType};
+
+        let node = |node_type| Node {
+            id: Uuid::new_v4(),
+            node_type,
+            config: NodeConfig::default(),
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![],
+        };
+        let trigger = node(NodeType::Trigger { trigger_type: TriggerType::Manual });
+        let orphan = node(NodeType::Action { action_type: ActionType::Http });
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Cleanup".to_string(),
+            description: None,
+            nodes: vec![trigger, orphan.clone()],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+        let user = Uuid::new_v4();
+        let workflows = Arc::new(InMemoryWorkflowStore::new());
+        workflows.save(&StoredWorkflow { workflow: workflow.clone(), owner_id: user, active: true }).await.unwrap();
+        let state = ReviewServiceState::new(Arc::new(RevisionStore::new())).with_access(WorkflowAccess::new(workflows));
+        state.revisions.submit(workflow.clone(), user).await;
+
+        // Viewers, and users editing someone else's workflow, may not clean up
+        let cleanup_uri = format!("/workflows/{}/graph/cleanup", workflow.id);
+        let (status, _) = call(app(state.clone(), user, Role::Viewer), "POST", &cleanup_uri, Some(serde_json::json!({}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = call(app(state.clone(), Uuid::new_v4(), Role::User), "POST", &cleanup_uri, Some(serde_json::json!({}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = call(app(state.clone(), user, Role::User), "POST", &cleanup_uri, Some(serde_json::json!({}))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["moved"]["nodes"][0], orphan.id.to_string());
+        let active = state.revisions.active(workflow.id).await.unwrap();
+        assert_eq!((active.nodes.len(), active.trash.nodes.len()), (1, 1));
+        assert_eq!(state.revisions.revisions(workflow.id).await.len(), 2);
+
+        let restore_uri = format!("/workflows/{}/trash/restore", workflow.id);
+        let (status, _) = call(app(state.clone(), user, Role::User), "POST", &restore_uri, Some(serde_json::json!({ "node_ids": [orphan.id] }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(state.revisions.active(workflow.id).await.unwrap().nodes.len(), 2);
+    }
+}
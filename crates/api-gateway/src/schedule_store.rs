@@ -0,0 +1,219 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common::error::WorkflowError;
+use serde::de::DeserializeOwned;
+use sqlx::pool::PoolConnection;
+use sqlx::types::Json;
+use sqlx::{PgPool, Postgres, Row};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use workflow_engine::scheduler::{
+    LeaderLock, OneOffSchedule, ScheduleCheckpoint, ScheduleConfig, SchedulePersistence,
+};
+
+/// Advisory lock key scheduler replicas compete for unless configured otherwise
+/// ("flowvex" in ASCII)
+pub const DEFAULT_SCHEDULER_LOCK_KEY: i64 = 0x0066_6c6f_7776_6578;
+
+/// Postgres-backed schedule store shared by scheduler replicas (see
+/// `migrations/004_schedules.sql`)
+pub struct PgScheduleStore {
+    pool: PgPool,
+}
+
+impl PgScheduleStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SchedulePersistence for PgScheduleStore {
+    async fn save_schedule(&self, config: &ScheduleConfig) -> Result<(), WorkflowError> {
+        sqlx::query(
+            r#"
+            INSERT INTO workflow_schedules (workflow_id, config, updated_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (workflow_id) DO UPDATE SET config = $2, updated_at = $3
+            "#,
+        )
+        .bind(config.workflow_id)
+        .bind(Json(config))
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn delete_schedule(&self, workflow_id: Uuid) -> Result<(), WorkflowError> {
+        sqlx::query("DELETE FROM workflow_schedules WHERE workflow_id = $1")
+            .bind(workflow_id)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn load_schedules(&self) -> Result<Vec<ScheduleConfig>, WorkflowError> {
+        let rows = sqlx::query("SELECT config FROM workflow_schedules")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(rows.iter().filter_map(|row| decode(row, "config")).collect())
+    }
+
+    async fn save_one_off(&self, schedule: &OneOffSchedule) -> Result<(), WorkflowError> {
+        sqlx::query(
+            r#"
+            INSERT INTO one_off_schedules (id, workflow_id, run_at, schedule)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (id) DO UPDATE SET run_at = $3, schedule = $4
+            "#,
+        )
+        .bind(schedule.id)
+        .bind(schedule.workflow.id)
+        .bind(schedule.run_at)
+        .bind(Json(schedule))
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn delete_one_off(&self, id: Uuid) -> Result<(), WorkflowError> {
+        sqlx::query("DELETE FROM one_off_schedules WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn load_one_offs(&self) -> Result<Vec<OneOffSchedule>, WorkflowError> {
+        let rows = sqlx::query("SELECT schedule FROM one_off_schedules ORDER BY run_at")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(rows.iter().filter_map(|row| decode(row, "schedule")).collect())
+    }
+
+    async fn save_checkpoint(&self, checkpoint: &ScheduleCheckpoint) -> Result<(), WorkflowError> {
+        sqlx::query(
+            r#"
+            INSERT INTO schedule_checkpoints (workflow_id, checked_until)
+            VALUES ($1, $2)
+            ON CONFLICT (workflow_id) DO UPDATE SET checked_until = $2
+            "#,
+        )
+        .bind(checkpoint.workflow_id)
+        .bind(checkpoint.checked_until)
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn load_checkpoint(&self, workflow_id: Uuid) -> Result<Option<ScheduleCheckpoint>, WorkflowError> {
+        let row = sqlx::query("SELECT checked_until FROM schedule_checkpoints WHERE workflow_id = $1")
+            .bind(workflow_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(row.map(|row| ScheduleCheckpoint {
+            workflow_id,
+            checked_until: row.get::<DateTime<Utc>, _>("checked_until"),
+        }))
+    }
+
+    async fn delete_checkpoint(&self, workflow_id: Uuid) -> Result<(), WorkflowError> {
+        sqlx::query("DELETE FROM schedule_checkpoints WHERE workflow_id = $1")
+            .bind(workflow_id)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+}
+
+/// Decode a JSONB column, skipping rows written by an incompatible version
+fn decode<T: DeserializeOwned>(row: &sqlx::postgres::PgRow, column: &str) -> Option<T> {
+    match row.try_get::<Json<T>, _>(column) {
+        Ok(Json(value)) => Some(value),
+        Err(e) => {
+            tracing::warn!("Skipping unreadable {} row: {}", column, e);
+            None
+        }
+    }
+}
+
+/// Scheduler leadership through a Postgres session advisory lock.
+///
+/// The lock lives as long as the connection holding it, so a crashed leader
+/// loses it when its connection drops and another replica takes over.
+pub struct PgLeaderLock {
+    pool: PgPool,
+    key: i64,
+    held: Mutex<Option<PoolConnection<Postgres>>>,
+}
+
+impl PgLeaderLock {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            key: DEFAULT_SCHEDULER_LOCK_KEY,
+            held: Mutex::new(None),
+        }
+    }
+
+    /// Separate lock key, e.g. for scheduler clusters sharing one database
+    pub fn with_key(mut self, key: i64) -> Self {
+        self.key = key;
+        self
+    }
+}
+
+#[async_trait]
+impl LeaderLock for PgLeaderLock {
+    async fn try_acquire(&self) -> Result<bool, WorkflowError> {
+        let mut held = self.held.lock().await;
+        if let Some(conn) = held.as_mut() {
+            if sqlx::query("SELECT 1").execute(&mut **conn).await.is_ok() {
+                return Ok(true);
+            }
+            // The session and with it the lock are gone; close the connection
+            // rather than returning it to the pool
+            if let Some(conn) = held.take() {
+                drop(conn.detach());
+            }
+            tracing::warn!("Lost scheduler leader lock connection");
+        }
+
+        let mut conn = self.pool.acquire().await.map_err(storage_error)?;
+        let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(self.key)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(storage_error)?;
+        if acquired {
+            *held = Some(conn);
+        }
+        Ok(acquired)
+    }
+
+    async fn release(&self) -> Result<(), WorkflowError> {
+        let Some(mut conn) = self.held.lock().await.take() else {
+            return Ok(());
+        };
+        sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(self.key)
+            .execute(&mut *conn)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+}
+
+fn storage_error(e: sqlx::Error) -> WorkflowError {
+    WorkflowError::Storage(e.to_string())
+}
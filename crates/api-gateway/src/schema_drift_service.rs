@@ -0,0 +1,183 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use common::types::ActionType2;
+use rbac_service::jwt::JwtClaims;
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::schema_drift::SchemaDriftDetector;
+
+use crate::workflow_access::WorkflowAccess;
+
+/// Schema drift service state
+#[derive(Clone)]
+pub struct SchemaDriftServiceState {
+    pub detector: Arc<SchemaDriftDetector>,
+    /// Authorizes callers on the affected workflows
+    access: WorkflowAccess,
+}
+
+impl SchemaDriftServiceState {
+    pub fn new(detector: Arc<SchemaDriftDetector>) -> Self {
+        Self { detector, access: WorkflowAccess::default() }
+    }
+
+    /// Authorize callers against the saved workflows of `access`
+    pub fn with_access(mut self, access: WorkflowAccess) -> Self {
+        self.access = access;
+        self
+    }
+}
+
+/// Drift warning list filter
+#[derive(Debug, Default, Deserialize)]
+pub struct DriftQuery {
+    /// Include warnings already acknowledged
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// 列出影响工作流的接口响应结构漂移警告（新增/删除字段、类型变化）
+pub async fn list_workflow_drift(
+    State(state): State<SchemaDriftServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+    Query(query): Query<DriftQuery>,
+) -> impl IntoResponse {
+    if !state.access.can_on(&claims, ActionType2::Read, workflow_id).await {
+        return error_response(StatusCode::NOT_FOUND, "工作流不存在".to_string());
+    }
+    let warnings = state.detector.warnings_for_workflow(workflow_id, query.all).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "total": warnings.len(),
+            "warnings": warnings
+        })),
+    )
+}
+
+/// 确认漂移警告：已处理，不再显示（需要修改任一受影响工作流的权限）
+pub async fn acknowledge_drift(
+    State(state): State<SchemaDriftServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(warning_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let Some(warning) = state.detector.get_warning(warning_id).await else {
+        return error_response(StatusCode::NOT_FOUND, "漂移警告不存在".to_string());
+    };
+    let mut workflows: Vec<Uuid> = warning.affected.iter().map(|node| node.workflow_id).collect();
+    workflows.sort();
+    workflows.dedup();
+    let mut allowed = false;
+    for workflow_id in workflows {
+        if state.access.can_on(&claims, ActionType2::Update, workflow_id).await {
+            allowed = true;
+            break;
+        }
+    }
+    if !allowed {
+        return error_response(StatusCode::FORBIDDEN, "没有确认漂移警告的权限".to_string());
+    }
+
+    match state.detector.acknowledge(warning_id).await {
+        Some(warning) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "warning": warning
+            })),
+        ),
+        None => error_response(StatusCode::NOT_FOUND, "漂移警告不存在".to_string()),
+    }
+}
+
+fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, claims};
+    use axum::routing::{get, post};
+    use axum::Router;
+    use common::types::{Role, Workflow, WORKFLOW_SCHEMA_VERSION};
+    use std::collections::HashMap;
+    use workflow_engine::schema_drift::{ActionKey, AffectedNode};
+    use workflow_engine::workflows::{InMemoryWorkflowStore, StoredWorkflow, WorkflowStore};
+
+    fn app(state: SchemaDriftServiceState, user: Uuid, role: Role) -> Router {
+        Router::new()
+            .route("/workflows/:workflow_id/schema-drift", get(list_workflow_drift))
+            .route("/schema-drift/:warning_id/acknowledge", post(acknowledge_drift))
+            .layer(Extension(claims(user, role)))
+            .with_state(state)
+    }
+
+    #[tokio::test]
+    async fn test_list_and_acknowledge_drift() {
+        let (owner, stranger, inspector) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "CRM sync".to_string(),
+            description: None,
+            nodes: vec![],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+        let workflows = Arc::new(InMemoryWorkflowStore::new());
+        workflows.save(&StoredWorkflow { workflow: workflow.clone(), owner_id: owner, active: true }).await.unwrap();
+        let state = SchemaDriftServiceState::new(Arc::new(SchemaDriftDetector::default()))
+            .with_access(WorkflowAccess::new(workflows));
+        let key = ActionKey { provider: "crm".to_string(), action: "GET /contacts".to_string() };
+        let caller = AffectedNode { workflow_id: workflow.id, node_id: Uuid::new_v4() };
+        for _ in 0..5 {
+            state.detector.observe(key.clone(), caller, &serde_json::json!({ "email": "a@example.com" })).await;
+        }
+        let warning = state.detector
+            .observe(key, caller, &serde_json::json!({ "emails": ["a@example.com"] }))
+            .await
+            .unwrap();
+
+        let list_uri = format!("/workflows/{}/schema-drift", caller.workflow_id);
+        let (status, body) = call(app(state.clone(), inspector, Role::Inspector), "GET", &list_uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["warnings"][0]["severity"], "breaking");
+        assert_eq!(body["warnings"][0]["changes"][0]["kind"], "removed");
+        let (status, _) = call(app(state.clone(), stranger, Role::User), "GET", &list_uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Acknowledging takes editing rights on an affected workflow
+        let ack_uri = format!("/schema-drift/{}/acknowledge", warning.id);
+        let (status, _) = call(app(state.clone(), inspector, Role::Inspector), "POST", &ack_uri, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = call(app(state.clone(), stranger, Role::User), "POST", &ack_uri, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = call(app(state.clone(), owner, Role::User), "POST", &ack_uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(app(state.clone(), owner, Role::User), "POST", &format!("/schema-drift/{}/acknowledge", Uuid::new_v4()), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (_, body) = call(app(state.clone(), owner, Role::User), "GET", &list_uri, None).await;
+        assert_eq!(body["total"], 0);
+        let (_, body) = call(app(state, owner, Role::User), "GET", &format!("{}?all=true", list_uri), None).await;
+        assert_eq!(body["total"], 1);
+    }
+}
@@ -1,32 +1,117 @@
 use axum::{
     extract::Request,
-    http::StatusCode,
+    http::HeaderValue,
     middleware::{self, Next},
     response::{IntoResponse, Response},
     routing::{get, post, put, delete},
     Json, Router,
 };
+use common::config::PLACEHOLDER_JWT_SECRET;
+use common::trace::{TraceContext, TRACEPARENT};
 use serde_json::json;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tower_http::{
     compression::CompressionLayer,
     cors::CorsLayer,
     trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer},
 };
-use tracing::{info, Level};
+use tracing::{info, Instrument, Level};
 use uuid::Uuid;
+use workflow_engine::{
+    BroadcastEventBus, DeadLetterQueue, DigestService, InMemoryWorkflowStore, OrgSettingsStore, OwnershipStore, PayloadEncryption, QuotaManager,
+    RevisionStore, SchemaDriftDetector, TemplateStore, TestSuiteStore, WorkflowScheduler, WorkflowStore,
+};
+use audit_service::{AlertEngine, AuditExportJobs, AuditLogger, AuditRetention};
+use integration_service::{CredentialManager, CredentialStore, IntegrationRegistry, OAuth2Handler};
 
-use rbac_service::{JwtManager, AuthMiddleware};
+use rbac_service::{AuthMiddleware, InMemorySessionStore, JwtManager, OrgService, SessionStore};
 use crate::websocket::{websocket_handler, WebSocketManager};
+use crate::account_service::{AccountServiceState, export_account_data, delete_account};
+use crate::admin_service::{
+    AdminServiceState,
+    list_users, update_user_role, deactivate_user, reactivate_user, reset_user_password,
+};
 use crate::file_service::{
     FileServiceConfig,
-    list_files, upload_file, read_file, write_file, delete_file,
+    list_files, upload_file, read_file, download_file, write_file, delete_file,
+};
+use crate::credential_service::{
+    CredentialServiceState,
+    list_credentials, create_credential, get_credential, update_credential, delete_credential, test_credential,
+};
+use crate::oauth_service::{OAuthServiceState, authorize_oauth, oauth_callback};
+use crate::integration_catalog_service::{
+    IntegrationCatalogServiceState,
+    list_integrations, list_integration_catalog, list_organization_integrations, set_integration_enablement,
+    set_action_enablement,
+};
+use crate::dead_letter_service::{
+    DeadLetterServiceState,
+    list_dead_letters, get_dead_letter, requeue_dead_letter, discard_dead_letter,
+};
+use crate::digest_service::{
+    DigestServiceState,
+    list_digests, create_digest, update_digest, delete_digest, preview_digest,
+};
+use crate::audit_alert_service::{
+    AuditAlertServiceState,
+    list_alert_rules, create_alert_rule, update_alert_rule, delete_alert_rule, list_recent_alerts,
 };
+use crate::audit_export_service::{
+    AuditExportServiceState, create_audit_export, list_audit_exports, get_audit_export, download_audit_export,
+};
+use crate::audit_retention_service::{AuditRetentionServiceState, get_audit_retention};
+use crate::audit_trail::AuditTrail;
+use crate::encryption_service::{EncryptionServiceState, get_encryption_status, rotate_master_key};
+use crate::execution_service::{
+    ExecutionStore,
+    delete_sampling, failure_heatmap, get_execution, get_execution_usage, get_sampling, list_execution_events,
+    list_workflow_executions, update_sampling,
+};
+use crate::graphql_service::{GraphqlServiceState, graphql_handler};
+use crate::inspector_service::{InspectorState, inspect_execution};
+use crate::metrics::MetricsCollector;
+use crate::metrics_service::{MetricsServiceState, get_metrics};
+use crate::prometheus::{get_prometheus_metrics, PrometheusState};
+use crate::dispatcher::GatewayDispatcher;
+use crate::ownership_service::{
+    OwnershipServiceState,
+    get_ownership, update_ownership, get_on_call, update_on_call, failure_alerts,
+};
+use crate::quota_service::{QuotaServiceState, preview_admission, get_quota, update_quota};
+use crate::request_limiter::{RequestLimitConfig, RequestLimiter};
+use crate::review_service::{
+    ReviewServiceState,
+    submit_revision, list_revisions, approve_revision, reject_revision,
+    get_review_policy, update_review_policy, review_inbox,
+    analyze_graph, cleanup_graph, restore_trash,
+};
+use crate::schema_drift_service::{SchemaDriftServiceState, list_workflow_drift, acknowledge_drift};
+use crate::settings_service::{SettingsServiceState, get_settings, update_settings, set_injection_opt_out};
+use crate::sharing_service::{
+    SharingServiceState,
+    export_workflow, import_workflow, bind_import, export_portable, import_portable,
+};
+use crate::template_service::{
+    TemplateServiceState,
+    list_templates, list_template_categories, get_template, create_template, update_template, delete_template,
+    instantiate_template,
+};
+use crate::test_suite_service::{TestSuiteServiceState, get_test_suite, update_test_suite, run_test_suite};
 use crate::user_service::{
-    UserServiceState,
+    InMemoryUserStore, UserServiceState, UserStore,
     register_handler, login_handler, get_me_handler,
     update_profile_handler, change_password_handler,
+    list_sessions_handler, revoke_session_handler,
+};
+use crate::webhook_service::{WebhookServiceState, receive_webhook, verify_signature_sample};
+use crate::workflow_access::WorkflowAccess;
+use crate::workflow_service::{
+    WorkflowServiceState,
+    list_workflows, create_workflow, get_workflow, update_workflow, delete_workflow, duplicate_workflow,
+    activate_workflow, deactivate_workflow, execute_workflow, cancel_execution, pause_execution, resume_execution,
 };
 
 /// Server configuration
@@ -36,6 +121,12 @@ pub struct ServerConfig {
     pub port: u16,
     pub jwt_secret: String,
     pub jwt_expiration_hours: i64,
+    /// Per-user and per-IP limits of inbound requests
+    pub request_limits: RequestLimitConfig,
+    /// Bearer token Prometheus must send to scrape `/metrics`; open when unset
+    pub metrics_token: Option<String>,
+    /// Directory the file routes store uploads in
+    pub upload_dir: PathBuf,
 }
 
 impl Default for ServerConfig {
@@ -43,8 +134,11 @@ impl Default for ServerConfig {
         Self {
             host: "0.0.0.0".to_string(),
             port: 8080,
-            jwt_secret: "your-secret-key-change-in-production".to_string(),
+            jwt_secret: PLACEHOLDER_JWT_SECRET.to_string(),
             jwt_expiration_hours: 24,
+            request_limits: RequestLimitConfig::default(),
+            metrics_token: None,
+            upload_dir: FileServiceConfig::default().upload_dir,
         }
     }
 }
@@ -56,22 +150,157 @@ pub struct AppState {
     pub ws_manager: WebSocketManager,
 }
 
+/// Services shared between the HTTP server and the workflow engine
+#[derive(Clone)]
+pub struct SharedServices {
+    /// Saved workflow definitions
+    pub workflows: Arc<dyn WorkflowStore>,
+    /// Execution records published by the engine
+    pub executions: ExecutionStore,
+    /// Organization quotas used for execution admission control
+    pub quotas: Arc<QuotaManager>,
+    /// Audit log receiving execution inspections
+    pub audit: Option<Arc<AuditLogger>>,
+    /// Workflow revisions and review gates; the scheduler runs the active revisions
+    pub revisions: Arc<RevisionStore>,
+    /// Organization settings read by the executor (header/env injection)
+    pub settings: Arc<OrgSettingsStore>,
+    /// Executor progress events, forwarded to WebSocket clients
+    pub events: Option<Arc<BroadcastEventBus>>,
+    /// Scheduler holding webhook schedules; incoming webhooks trigger its workflows
+    pub scheduler: Arc<WorkflowScheduler>,
+    /// Failed executions awaiting requeue
+    pub dead_letters: Arc<DeadLetterQueue>,
+    /// Execution payload encryption, also given to the execution history and
+    /// dead letter store; enables the master key routes
+    pub encryption: Option<Arc<PayloadEncryption>>,
+    /// Response schema fingerprints of integration actions, given to the
+    /// executor; drift warnings are listed per workflow
+    pub schema_drift: Arc<SchemaDriftDetector>,
+    /// Node and workflow owners; failed executions report them and repeated
+    /// failures alert them
+    pub ownership: Arc<OwnershipStore>,
+    /// Users' saved credentials; digests list the ones about to expire
+    pub credentials: Arc<CredentialStore>,
+    /// Encryption of saved credentials; enables the credential routes
+    pub credential_manager: Option<Arc<CredentialManager>>,
+    /// Available integrations and organizations' enablement of them;
+    /// credentials are bound to and tested against them
+    pub integrations: Arc<IntegrationRegistry>,
+    /// Registered OAuth2 apps; together with the credential manager enables
+    /// the OAuth routes
+    pub oauth: Option<Arc<OAuth2Handler>>,
+    /// Scheduled workflow health digests; enables the digest routes
+    pub digests: Option<Arc<DigestService>>,
+    /// Scheduled purge of expired audit logs; enables the retention route
+    pub audit_retention: Option<Arc<AuditRetention>>,
+    /// Background audit log exports; enables the export job routes
+    pub audit_exports: Option<Arc<AuditExportJobs>>,
+    /// Security alert rules evaluated on every audit log; enables the alert
+    /// rule routes
+    pub audit_alerts: Option<Arc<AlertEngine>>,
+    /// Organizations and teams; Team- and Organization-scoped permissions
+    /// on workflows resolve against their memberships
+    pub organizations: Arc<OrgService>,
+    /// Gateway metrics, including the inbound rate limit counters; served
+    /// to admins by the metrics route
+    pub metrics: Arc<MetricsCollector>,
+    /// User accounts of the auth, account and admin routes
+    pub users: Arc<dyn UserStore>,
+    /// Signed-in devices of the users; tokens of revoked sessions are rejected
+    pub sessions: Arc<dyn SessionStore>,
+    /// Outbound request dispatchers, by name; their request pools and rate
+    /// limiters are exported to Prometheus
+    pub dispatchers: Vec<(String, GatewayDispatcher)>,
+}
+
+impl Default for SharedServices {
+    fn default() -> Self {
+        Self {
+            workflows: Arc::new(InMemoryWorkflowStore::new()),
+            executions: Default::default(),
+            quotas: Default::default(),
+            audit: None,
+            revisions: Default::default(),
+            settings: Default::default(),
+            events: None,
+            scheduler: Default::default(),
+            dead_letters: Default::default(),
+            encryption: None,
+            schema_drift: Default::default(),
+            ownership: Default::default(),
+            credentials: Default::default(),
+            credential_manager: None,
+            integrations: Default::default(),
+            oauth: None,
+            digests: None,
+            audit_retention: None,
+            audit_exports: None,
+            audit_alerts: None,
+            organizations: Default::default(),
+            metrics: Default::default(),
+            users: Arc::new(InMemoryUserStore::new()),
+            sessions: Arc::new(InMemorySessionStore::new()),
+            dispatchers: Vec::new(),
+        }
+    }
+}
+
+impl SharedServices {
+    /// Stop the scheduler, requeue loop and digest delivery, then wait up to
+    /// `timeout` for the work they started. Returns whether all finished in time.
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        let digests = async {
+            match &self.digests {
+                Some(digests) => digests.shutdown(timeout).await,
+                None => true,
+            }
+        };
+        let (scheduled, requeued, digested) = tokio::join!(
+            self.scheduler.shutdown(timeout),
+            self.dead_letters.shutdown(timeout),
+            digests,
+        );
+        let scheduled = scheduled.unwrap_or_else(|e| {
+            tracing::error!("Failed to stop scheduler: {}", e);
+            false
+        });
+        scheduled && requeued && digested
+    }
+}
+
 /// Create and configure the HTTP server
 pub fn create_server(config: ServerConfig) -> Router {
+    create_server_with_services(config, SharedServices::default())
+}
+
+/// Create the HTTP server backed by services shared with the workflow engine
+pub fn create_server_with_services(config: ServerConfig, services: SharedServices) -> Router {
     // Initialize JWT manager
-    let jwt_manager = Arc::new(JwtManager::new(
-        &config.jwt_secret,
-        config.jwt_expiration_hours,
-    ));
+    let jwt_manager = Arc::new(
+        JwtManager::new(&config.jwt_secret, config.jwt_expiration_hours).with_sessions(services.sessions.clone()),
+    );
 
     // Initialize WebSocket manager
     let ws_manager = WebSocketManager::new();
+    if let Some(events) = &services.events {
+        ws_manager.forward_events(events.subscribe());
+    }
 
     // Initialize file service config
-    let file_config = FileServiceConfig::default();
+    let file_config = FileServiceConfig {
+        upload_dir: config.upload_dir.clone(),
+        ..Default::default()
+    };
 
     // Initialize user service state
-    let user_state = UserServiceState::new(jwt_manager.clone());
+    let trust_forwarded_for = config.request_limits.trust_forwarded_for;
+    let mut user_state = UserServiceState::new(jwt_manager.clone())
+        .with_store(services.users.clone())
+        .with_trusted_forwarded_for(trust_forwarded_for);
+    if let Some(audit) = services.audit.clone() {
+        user_state = user_state.with_audit_logger(audit);
+    }
 
     // Create application state
     let app_state = AppState {
@@ -79,8 +308,27 @@ pub fn create_server(config: ServerConfig) -> Router {
         ws_manager: ws_manager.clone(),
     };
 
+    // Limit inbound requests per user, or per IP for anonymous clients
+    let request_limiter = Arc::new(
+        RequestLimiter::new(config.request_limits, jwt_manager.clone()).with_metrics(services.metrics.clone()),
+    );
+
+    // Gateway metrics routes (admin only), and the Prometheus scrape route
+    let prometheus_routes = Router::new()
+        .route("/metrics", get(get_prometheus_metrics))
+        .with_state(PrometheusState {
+            metrics: services.metrics.clone(),
+            ws_manager: ws_manager.clone(),
+            dispatchers: services.dispatchers.clone(),
+            token: config.metrics_token.clone(),
+        });
+    let metrics_routes = Router::new()
+        .route("/api/v1/metrics", get(get_metrics))
+        .with_state(MetricsServiceState::new(services.metrics, jwt_manager.clone()))
+        .merge(prometheus_routes);
+
     // Create auth middleware
-    let auth_middleware = AuthMiddleware::new(jwt_manager);
+    let auth_middleware = AuthMiddleware::new(jwt_manager.clone());
 
     // Build router with public routes
     let public_routes = Router::new()
@@ -94,7 +342,22 @@ pub fn create_server(config: ServerConfig) -> Router {
         .route("/api/v1/auth/me", get(get_me_handler))
         .route("/api/v1/auth/profile", put(update_profile_handler))
         .route("/api/v1/auth/password", put(change_password_handler))
-        .with_state(user_state);
+        .route("/api/v1/auth/sessions", get(list_sessions_handler))
+        .route("/api/v1/auth/sessions/:session_id", delete(revoke_session_handler))
+        .with_state(user_state.clone());
+
+    // Webhook routes (public, authenticated by the HMAC signature)
+    let webhook_routes = Router::new()
+        .route("/api/v1/webhooks/:workflow_id", post(receive_webhook))
+        .with_state(WebhookServiceState::new(services.scheduler.clone()));
+
+    // Signature debugging for webhook senders and receivers (protected)
+    let signature_routes = Router::new()
+        .route("/api/v1/signatures/verify", post(verify_signature_sample))
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ));
 
     // File service routes (public for now, can add auth later)
     let file_routes = Router::new()
@@ -103,24 +366,408 @@ pub fn create_server(config: ServerConfig) -> Router {
         .route("/api/v1/files/write", post(write_file))
         .route("/api/v1/files/:filename", get(read_file))
         .route("/api/v1/files/:filename", delete(delete_file))
+        .route("/api/v1/files/:filename/download", get(download_file))
         .with_state(file_config);
 
-    // Build router with protected routes
-    let protected_routes = Router::new()
+    // Reads of workflow data are authorized on the workflows' owners
+    let access = WorkflowAccess::new(services.workflows.clone()).with_org_service(services.organizations.clone());
+
+    // Failed executions report the owners of their failing nodes
+    let executions = services.executions
+        .with_ownership(services.ownership.clone())
+        .with_access(access.clone());
+
+    // Account data export and deletion routes (protected)
+    let mut account_state = AccountServiceState::new(
+        user_state.store.clone(),
+        services.revisions.clone(),
+        executions.clone(),
+    )
+    .with_scheduler(services.scheduler.clone())
+    .with_trusted_forwarded_for(trust_forwarded_for);
+    if let Some(audit) = services.audit.clone() {
+        account_state = account_state.with_audit_logger(audit);
+    }
+    let account_routes = Router::new()
+        .route("/api/v1/account/export", get(export_account_data))
+        .route("/api/v1/account", delete(delete_account))
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ))
+        .with_state(account_state);
+
+    // Admin user management routes (admin only, every action audited)
+    let mut admin_state = AdminServiceState::new(services.users.clone(), jwt_manager.clone())
+        .with_trusted_forwarded_for(trust_forwarded_for);
+    if let Some(audit) = services.audit.clone() {
+        admin_state = admin_state.with_audit_logger(audit);
+    }
+    let admin_user_routes = Router::new()
+        .route("/api/v1/admin/users", get(list_users))
+        .route("/api/v1/admin/users/:user_id/role", put(update_user_role))
+        .route("/api/v1/admin/users/:user_id/deactivate", post(deactivate_user))
+        .route("/api/v1/admin/users/:user_id/reactivate", post(reactivate_user))
+        .route("/api/v1/admin/users/:user_id/password-reset", post(reset_user_password))
+        .with_state(admin_state);
+
+    // Inspector routes (protected, read-only)
+    let mut inspector_state = InspectorState::new(executions.clone()).with_trusted_forwarded_for(trust_forwarded_for);
+    if let Some(audit) = services.audit.clone() {
+        inspector_state = inspector_state.with_audit_logger(audit);
+    }
+    let inspector_routes = Router::new()
+        .route("/api/v1/inspector/executions/:execution_id", get(inspect_execution))
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ))
+        .with_state(inspector_state);
+
+    // GraphQL read models over workflows and executions (protected)
+    let graphql_routes = Router::new()
+        .route("/api/v1/graphql", post(graphql_handler))
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ))
+        .with_state(GraphqlServiceState::new(services.revisions.clone(), &executions));
+
+    // Execution routes (protected)
+    let execution_routes = Router::new()
+        .route("/api/v1/executions/:execution_id", get(get_execution))
+        .route("/api/v1/executions/:execution_id/usage", get(get_execution_usage))
+        .route("/api/v1/executions/:execution_id/events", get(list_execution_events))
+        .route("/api/v1/workflows/:workflow_id/executions", get(list_workflow_executions))
+        .route("/api/v1/workflows/:workflow_id/sampling", get(get_sampling))
+        .route("/api/v1/workflows/:workflow_id/sampling", put(update_sampling))
+        .route("/api/v1/workflows/:workflow_id/sampling", delete(delete_sampling))
+        .route("/api/v1/analytics/failure-heatmap", get(failure_heatmap))
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ))
+        .with_state(executions);
+
+    // Ownership and on-call routes (protected)
+    let ownership_routes = Router::new()
+        .route("/api/v1/workflows/:workflow_id/ownership", get(get_ownership))
+        .route("/api/v1/workflows/:workflow_id/ownership", put(update_ownership))
+        .route("/api/v1/teams/:team/on-call", get(get_on_call))
+        .route("/api/v1/teams/:team/on-call", put(update_on_call))
+        .route("/api/v1/failure-alerts", get(failure_alerts))
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ))
+        .with_state(
+            OwnershipServiceState::new(services.ownership, services.revisions.clone())
+                .with_access(access.clone()),
+        );
+
+    // Dead letter routes (protected)
+    let dead_letter_routes = Router::new()
+        .route("/api/v1/dead-letters", get(list_dead_letters))
+        .route("/api/v1/dead-letters/:id", get(get_dead_letter))
+        .route("/api/v1/dead-letters/:id", delete(discard_dead_letter))
+        .route("/api/v1/dead-letters/:id/requeue", post(requeue_dead_letter))
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ))
+        .with_state(DeadLetterServiceState::new(services.dead_letters).with_access(access.clone()));
+
+    // Schema drift routes (protected)
+    let schema_drift_routes = Router::new()
+        .route("/api/v1/workflows/:workflow_id/schema-drift", get(list_workflow_drift))
+        .route("/api/v1/schema-drift/:warning_id/acknowledge", post(acknowledge_drift))
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ))
+        .with_state(SchemaDriftServiceState::new(services.schema_drift).with_access(access.clone()));
+
+    // Quota routes (protected)
+    let quota_routes = Router::new()
+        .route("/api/v1/quotas/preview", post(preview_admission))
+        .route("/api/v1/quotas/:organization_id", get(get_quota))
+        .route("/api/v1/quotas/:organization_id", put(update_quota))
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ))
+        .with_state(QuotaServiceState::new(services.quotas));
+
+    // Workflow sharing routes (protected)
+    let sharing_routes = Router::new()
+        .route("/api/v1/workflows/export", post(export_workflow))
+        .route("/api/v1/workflows/import", post(import_workflow))
+        .route("/api/v1/workflows/import/:import_id/bindings", post(bind_import))
+        .route("/api/v1/workflows/export/portable", post(export_portable))
+        .route("/api/v1/workflows/import/portable", post(import_portable))
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ))
+        .with_state(SharingServiceState::new());
+
+    // Workflow test suite routes (protected)
+    let test_suite_routes = Router::new()
+        .route("/api/v1/workflows/:workflow_id/tests", get(get_test_suite))
+        .route("/api/v1/workflows/:workflow_id/tests", put(update_test_suite))
+        .route("/api/v1/workflows/:workflow_id/tests/run", post(run_test_suite))
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ))
+        .with_state(
+            TestSuiteServiceState::new(Arc::new(TestSuiteStore::new()), services.revisions.clone())
+                .with_access(access.clone()),
+        );
+
+    // Template library routes (protected)
+    let template_routes = Router::new()
+        .route("/api/v1/templates", get(list_templates))
+        .route("/api/v1/templates", post(create_template))
+        .route("/api/v1/templates/categories", get(list_template_categories))
+        .route("/api/v1/templates/:template_id", get(get_template))
+        .route("/api/v1/templates/:template_id", put(update_template))
+        .route("/api/v1/templates/:template_id", delete(delete_template))
+        .route("/api/v1/templates/:template_id/instantiate", post(instantiate_template))
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ))
+        .with_state(
+            TemplateServiceState::new(Arc::new(TemplateStore::new()), services.revisions.clone())
+                .with_access(access.clone()),
+        );
+
+    // Organization settings routes (protected)
+    let settings_routes = Router::new()
+        .route("/api/v1/organizations/:organization_id/settings", get(get_settings))
+        .route("/api/v1/organizations/:organization_id/settings", put(update_settings))
+        .route(
+            "/api/v1/organizations/:organization_id/settings/injection/opt-out/:workflow_id",
+            put(set_injection_opt_out),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ))
+        .with_state(SettingsServiceState::new(services.settings).with_access(access.clone()));
+
+    // Execution data encryption routes (protected)
+    let encryption_routes = match services.encryption {
+        Some(encryption) => Router::new()
+            .route("/api/v1/admin/encryption", get(get_encryption_status))
+            .route("/api/v1/admin/encryption/rotate", post(rotate_master_key))
+            .route_layer(middleware::from_fn_with_state(
+                auth_middleware.clone(),
+                AuthMiddleware::auth_middleware,
+            ))
+            .with_state(EncryptionServiceState::new(encryption)),
+        None => Router::new(),
+    };
+
+    // Audit log retention routes (admin only)
+    let audit_retention_routes = match services.audit_retention {
+        Some(retention) => Router::new()
+            .route("/api/v1/admin/audit/retention", get(get_audit_retention))
+            .with_state(AuditRetentionServiceState::new(retention, jwt_manager.clone())),
+        None => Router::new(),
+    };
+
+    // Audit log export job routes (admin only)
+    let audit_export_routes = match services.audit_exports {
+        Some(jobs) => {
+            let mut state =
+                AuditExportServiceState::new(jobs, jwt_manager.clone()).with_trusted_forwarded_for(trust_forwarded_for);
+            if let Some(audit) = services.audit.clone() {
+                state = state.with_audit_logger(audit);
+            }
+            Router::new()
+                .route("/api/v1/admin/audit/exports", get(list_audit_exports))
+                .route("/api/v1/admin/audit/exports", post(create_audit_export))
+                .route("/api/v1/admin/audit/exports/:job_id", get(get_audit_export))
+                .route("/api/v1/admin/audit/exports/:job_id/download", get(download_audit_export))
+                .with_state(state)
+        }
+        None => Router::new(),
+    };
+
+    // Audit alert rule routes (admin only)
+    let audit_alert_routes = match services.audit_alerts {
+        Some(alerts) => Router::new()
+            .route("/api/v1/admin/audit/alert-rules", get(list_alert_rules))
+            .route("/api/v1/admin/audit/alert-rules", post(create_alert_rule))
+            .route("/api/v1/admin/audit/alert-rules/:rule_id", put(update_alert_rule))
+            .route("/api/v1/admin/audit/alert-rules/:rule_id", delete(delete_alert_rule))
+            .route("/api/v1/admin/audit/alerts", get(list_recent_alerts))
+            .with_state(AuditAlertServiceState::new(alerts, jwt_manager.clone())),
+        None => Router::new(),
+    };
+
+    // Workflow health digest routes (protected)
+    let digest_routes = match services.digests {
+        Some(digests) => Router::new()
+            .route("/api/v1/digests", get(list_digests))
+            .route("/api/v1/digests", post(create_digest))
+            .route("/api/v1/digests/:digest_id", put(update_digest))
+            .route("/api/v1/digests/:digest_id", delete(delete_digest))
+            .route("/api/v1/digests/:digest_id/preview", get(preview_digest))
+            .route_layer(middleware::from_fn_with_state(
+                auth_middleware.clone(),
+                AuthMiddleware::auth_middleware,
+            ))
+            .with_state(DigestServiceState::new(digests)),
+        None => Router::new(),
+    };
+
+    // Integration catalog routes (enablement is admin only)
+    let integration_routes = Router::new()
+        .route("/api/v1/integrations", get(list_integrations))
+        .route("/api/v1/admin/integrations", get(list_integration_catalog))
+        .route("/api/v1/admin/organizations/:organization_id/integrations", get(list_organization_integrations))
+        .route(
+            "/api/v1/admin/organizations/:organization_id/integrations/:integration",
+            put(set_integration_enablement),
+        )
+        .route(
+            "/api/v1/admin/organizations/:organization_id/integrations/:integration/actions/:action",
+            put(set_action_enablement),
+        )
+        .with_state(IntegrationCatalogServiceState::new(services.integrations.clone(), jwt_manager.clone()));
+
+    // OAuth routes (the provider's callback is public)
+    let oauth_routes = match (services.oauth, services.credential_manager.clone()) {
+        (Some(handler), Some(manager)) => {
+            let credentials = CredentialServiceState::new(services.credentials.clone(), manager, services.integrations.clone())
+                .with_org_service(services.organizations.clone());
+            Router::new()
+                .route("/api/v1/oauth/authorize", post(authorize_oauth))
+                .route_layer(middleware::from_fn_with_state(
+                    auth_middleware.clone(),
+                    AuthMiddleware::auth_middleware,
+                ))
+                .route("/api/v1/oauth/callback", get(oauth_callback))
+                .with_state(OAuthServiceState::new(handler, credentials))
+        }
+        _ => Router::new(),
+    };
+
+    // Saved credential routes (protected)
+    let credential_routes = match services.credential_manager {
+        Some(manager) => Router::new()
+            .route("/api/v1/credentials", get(list_credentials))
+            .route("/api/v1/credentials", post(create_credential))
+            .route("/api/v1/credentials/:credential_id", get(get_credential))
+            .route("/api/v1/credentials/:credential_id", put(update_credential))
+            .route("/api/v1/credentials/:credential_id", delete(delete_credential))
+            .route("/api/v1/credentials/:credential_id/test", post(test_credential))
+            .route_layer(middleware::from_fn_with_state(
+                auth_middleware.clone(),
+                AuthMiddleware::auth_middleware,
+            ))
+            .with_state(
+                CredentialServiceState::new(services.credentials.clone(), manager, services.integrations)
+                    .with_org_service(services.organizations.clone()),
+            ),
+        None => Router::new(),
+    };
+
+    // Workflow revision review routes (protected)
+    let review_routes = Router::new()
+        .route("/api/v1/workflows/:workflow_id/revisions", get(list_revisions))
+        .route("/api/v1/workflows/:workflow_id/revisions", put(submit_revision))
+        .route("/api/v1/workflows/:workflow_id/review-policy", get(get_review_policy))
+        .route("/api/v1/workflows/:workflow_id/review-policy", put(update_review_policy))
+        .route("/api/v1/revisions/:revision_id/approve", post(approve_revision))
+        .route("/api/v1/revisions/:revision_id/reject", post(reject_revision))
+        .route("/api/v1/reviews/inbox", get(review_inbox))
+        .route("/api/v1/workflows/:workflow_id/graph/analysis", get(analyze_graph))
+        .route("/api/v1/workflows/:workflow_id/graph/cleanup", post(cleanup_graph))
+        .route("/api/v1/workflows/:workflow_id/trash/restore", post(restore_trash))
+        .route_layer(middleware::from_fn_with_state(
+            auth_middleware.clone(),
+            AuthMiddleware::auth_middleware,
+        ))
+        .with_state(
+            ReviewServiceState::new(services.revisions)
+                .with_scheduler(services.scheduler.clone())
+                .with_access(access.clone()),
+        );
+
+    // Workflow definition routes (protected)
+    let workflow_routes = Router::new()
         .route("/api/v1/workflows", get(list_workflows))
         .route("/api/v1/workflows", post(create_workflow))
+        .route("/api/v1/workflows/:workflow_id", get(get_workflow))
+        .route("/api/v1/workflows/:workflow_id", put(update_workflow))
+        .route("/api/v1/workflows/:workflow_id", delete(delete_workflow))
+        .route("/api/v1/workflows/:workflow_id/duplicate", post(duplicate_workflow))
+        .route("/api/v1/workflows/:workflow_id/activate", post(activate_workflow))
+        .route("/api/v1/workflows/:workflow_id/deactivate", post(deactivate_workflow))
+        .route("/api/v1/workflows/:workflow_id/execute", post(execute_workflow))
+        .route("/api/v1/executions/:execution_id/cancel", post(cancel_execution))
+        .route("/api/v1/executions/:execution_id/pause", post(pause_execution))
+        .route("/api/v1/executions/:execution_id/resume", post(resume_execution))
         .route_layer(middleware::from_fn_with_state(
             auth_middleware.clone(),
             AuthMiddleware::auth_middleware,
-        ));
+        ))
+        .with_state(
+            WorkflowServiceState::new(services.workflows)
+                .with_org_service(services.organizations)
+                .with_scheduler(services.scheduler),
+        );
 
     // Combine routes
-    let app = Router::new()
+    let routes = Router::new()
         .merge(public_routes)
         .merge(auth_routes)
+        .merge(account_routes)
+        .merge(webhook_routes)
+        .merge(signature_routes)
         .merge(file_routes)
-        .merge(protected_routes)
+        .merge(execution_routes)
+        .merge(ownership_routes)
+        .merge(dead_letter_routes)
+        .merge(schema_drift_routes)
+        .merge(graphql_routes)
+        .merge(inspector_routes)
+        .merge(quota_routes)
+        .merge(sharing_routes)
+        .merge(template_routes)
+        .merge(test_suite_routes)
+        .merge(review_routes)
+        .merge(settings_routes)
+        .merge(encryption_routes)
+        .merge(admin_user_routes)
+        .merge(audit_retention_routes)
+        .merge(audit_export_routes)
+        .merge(audit_alert_routes)
+        .merge(digest_routes)
+        .merge(integration_routes)
+        .merge(oauth_routes)
+        .merge(credential_routes)
+        .merge(workflow_routes)
+        .merge(metrics_routes);
+
+    // Record every mutating request in the audit log
+    let routes = match services.audit {
+        Some(audit) => {
+            let trail = AuditTrail::new(audit, jwt_manager.clone()).with_trusted_forwarded_for(trust_forwarded_for);
+            let trail = Arc::new(trail);
+            routes.layer(middleware::from_fn_with_state(trail, AuditTrail::middleware))
+        }
+        None => routes,
+    };
+
+    routes
+        .layer(middleware::from_fn_with_state(request_limiter, RequestLimiter::middleware))
         .layer(middleware::from_fn(request_logging_middleware))
+        .layer(middleware::from_fn(trace_context_middleware))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
@@ -128,9 +775,7 @@ pub fn create_server(config: ServerConfig) -> Router {
         )
         .layer(CompressionLayer::new())
         .layer(CorsLayer::permissive())
-        .with_state(app_state);
-
-    app
+        .with_state(app_state)
 }
 
 /// Request logging middleware
@@ -169,6 +814,34 @@ async fn request_logging_middleware(req: Request, next: Next) -> Response {
     response
 }
 
+/// Trace context middleware: continues the caller's trace from its
+/// `traceparent` header, or starts one, and runs the request in a span of it.
+/// Executions started by the request run in child spans of the same trace.
+async fn trace_context_middleware(mut req: Request, next: Next) -> Response {
+    let parent = req
+        .headers()
+        .get(TRACEPARENT)
+        .and_then(|value| value.to_str().ok())
+        .and_then(TraceContext::parse);
+    let trace = parent.as_ref().map(TraceContext::child).unwrap_or_else(TraceContext::new_root);
+    req.extensions_mut().insert(trace.clone());
+
+    let span = tracing::info_span!(
+        "gateway_request",
+        trace_id = %trace.trace_id,
+        span_id = %trace.span_id,
+        method = %req.method(),
+        uri = %req.uri(),
+    );
+    parent.as_ref().unwrap_or(&trace).adopt(&span);
+    let traceparent = HeaderValue::from_str(&trace.traceparent());
+    let mut response = trace.scope(next.run(req)).instrument(span).await;
+    if let Ok(traceparent) = traceparent {
+        response.headers_mut().insert(TRACEPARENT, traceparent);
+    }
+    response
+}
+
 /// Health check endpoint
 async fn health_check() -> impl IntoResponse {
     Json(json!({
@@ -177,27 +850,6 @@ async fn health_check() -> impl IntoResponse {
     }))
 }
 
-/// List workflows handler (placeholder)
-async fn list_workflows() -> impl IntoResponse {
-    Json(json!({
-        "workflows": [],
-        "total": 0,
-    }))
-}
-
-/// Create workflow handler (placeholder)
-async fn create_workflow() -> impl IntoResponse {
-    (
-        StatusCode::NOT_IMPLEMENTED,
-        Json(json!({
-            "error": {
-                "code": "NOT_IMPLEMENTED",
-                "message": "Create workflow endpoint not yet implemented",
-            }
-        })),
-    )
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -240,4 +892,291 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     }
+
+    #[tokio::test]
+    async fn test_inspector_token_is_read_only() {
+        let config = ServerConfig::default();
+        let token = JwtManager::new(&config.jwt_secret, config.jwt_expiration_hours)
+            .generate_token(Uuid::new_v4(), common::types::Role::Inspector, vec![])
+            .unwrap();
+        let app = create_server(config);
+
+        let request = |method: &str, uri: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("GET", "/api/v1/workflows")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.clone().oneshot(request("POST", "/api/v1/workflows")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let uri = format!("/api/v1/inspector/executions/{}", Uuid::new_v4());
+        let response = app.oneshot(request("GET", &uri)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_route_counts_rate_limits() {
+        let config = ServerConfig::default();
+        let jwt_manager = JwtManager::new(&config.jwt_secret, config.jwt_expiration_hours);
+        let admin = jwt_manager.generate_token(Uuid::new_v4(), common::types::Role::Admin, vec![]).unwrap();
+        let user = jwt_manager.generate_token(Uuid::new_v4(), common::types::Role::User, vec![]).unwrap();
+        let app = create_server(config);
+
+        let request = |token: &str| {
+            Request::builder()
+                .uri("/api/v1/metrics")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request(&user)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app.oneshot(request(&admin)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["rate_limits"][0]["group"], "default");
+        assert_eq!(body["rate_limits"][0]["allowed"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_audit_retention_route_is_admin_only() {
+        let config = ServerConfig::default();
+        let jwt_manager = JwtManager::new(&config.jwt_secret, config.jwt_expiration_hours);
+        let user = jwt_manager.generate_token(Uuid::new_v4(), common::types::Role::User, vec![]).unwrap();
+        let request = || {
+            Request::builder()
+                .uri("/api/v1/admin/audit/retention")
+                .header("authorization", format!("Bearer {}", user))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = create_server(config.clone()).oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let pool = sqlx::PgPool::connect_lazy("postgresql://localhost/test").unwrap();
+        let retention = AuditRetention::new(
+            Arc::new(audit_service::AuditStorage::new(pool)),
+            Default::default(),
+            "audit-archive",
+        );
+        let services = SharedServices { audit_retention: Some(Arc::new(retention)), ..Default::default() };
+        let response = create_server_with_services(config, services).oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_audit_export_jobs_are_admin_only() {
+        let config = ServerConfig::default();
+        let jwt_manager = JwtManager::new(&config.jwt_secret, config.jwt_expiration_hours);
+        let admin = jwt_manager.generate_token(Uuid::new_v4(), common::types::Role::Admin, vec![]).unwrap();
+        let user = jwt_manager.generate_token(Uuid::new_v4(), common::types::Role::User, vec![]).unwrap();
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .acquire_timeout(std::time::Duration::from_millis(200))
+            .connect_lazy("postgresql://localhost:1/test")
+            .unwrap();
+        let exporter = audit_service::AuditExporter::new(audit_service::AuditQuery::new(pool));
+        let dir = std::env::temp_dir().join(format!("audit-exports-{}", Uuid::new_v4()));
+        let services = SharedServices {
+            audit_exports: Some(Arc::new(AuditExportJobs::new(exporter, &dir))),
+            ..Default::default()
+        };
+        let app = create_server_with_services(config, services);
+        let request = |method: &str, uri: &str, token: &str, body: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {}", token))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let create = r#"{"format": "Parquet", "filter": {"security_only": true}}"#;
+        let response = app.clone().oneshot(request("POST", "/api/v1/admin/audit/exports", &user, create)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app.clone().oneshot(request("POST", "/api/v1/admin/audit/exports", &admin, create)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let job_uri = format!("/api/v1/admin/audit/exports/{}", body["job"]["id"].as_str().unwrap());
+
+        // The export fails without a database, leaving nothing to download
+        let job = loop {
+            let response = app.clone().oneshot(request("GET", &job_uri, &admin, "")).await.unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            if !body["job"]["finished_at"].is_null() {
+                break body["job"].clone();
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        };
+        assert_eq!(job["status"], "failed");
+        let download = format!("{}/download", job_uri);
+        let response = app.oneshot(request("GET", &download, &admin, "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_route_requires_token() {
+        let config = ServerConfig { metrics_token: Some("scrape".to_string()), ..Default::default() };
+        let app = create_server(config);
+        let request = |token: &str| {
+            Request::builder()
+                .uri("/metrics")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("guess")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.oneshot(request("scrape")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain; version=0.0.4"));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("flowvex_websocket_connections 0"));
+    }
+
+    #[tokio::test]
+    async fn test_requests_continue_the_callers_trace() {
+        let app = create_server(ServerConfig::default());
+        let parent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let request = Request::builder().uri("/health").header("traceparent", parent).body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let trace = TraceContext::parse(response.headers()["traceparent"].to_str().unwrap()).unwrap();
+        assert_eq!(trace.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_ne!(trace.span_id, "00f067aa0ba902b7");
+
+        let request = Request::builder().uri("/health").header("traceparent", "garbage").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let trace = TraceContext::parse(response.headers()["traceparent"].to_str().unwrap()).unwrap();
+        assert_ne!(trace.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+    }
+
+    #[tokio::test]
+    async fn test_sessions_can_be_listed_and_revoked() {
+        let mut config = ServerConfig::default();
+        config.request_limits.trust_forwarded_for = true;
+        let app = create_server(config);
+        let send = |method: &str, uri: &str, token: Option<&str>, body: serde_json::Value| {
+            let mut request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .header("user-agent", "session-test")
+                .header("x-forwarded-for", "198.51.100.1, 203.0.113.7");
+            if let Some(token) = token {
+                request = request.header("authorization", format!("Bearer {}", token));
+            }
+            let request = request.body(Body::from(body.to_string())).unwrap();
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default())
+            }
+        };
+
+        let credentials = json!({ "email": "sessions@example.com", "password": "password123", "name": "Sessions" });
+        let (status, body) = send("POST", "/api/v1/auth/register", None, credentials).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let laptop = body["token"].as_str().unwrap().to_string();
+        let login = json!({ "email": "sessions@example.com", "password": "password123", "device_name": "phone" });
+        let (status, body) = send("POST", "/api/v1/auth/login", None, login).await;
+        assert_eq!(status, StatusCode::OK);
+        let phone = body["token"].as_str().unwrap().to_string();
+
+        let (status, body) = send("GET", "/api/v1/auth/sessions", Some(&phone), json!(null)).await;
+        assert_eq!(status, StatusCode::OK);
+        let sessions = body["sessions"].as_array().unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!((sessions[0]["device_name"].clone(), sessions[0]["current"].clone()), (json!("phone"), json!(true)));
+        assert_eq!(sessions[1]["user_agent"], "session-test");
+        // The entry the proxy appended, not the one the client sent
+        assert_eq!(sessions[1]["ip_address"], "203.0.113.7");
+        let laptop_session = sessions[1]["id"].as_str().unwrap().to_string();
+
+        let uri = format!("/api/v1/auth/sessions/{}", laptop_session);
+        let (status, _) = send("DELETE", &uri, Some(&phone), json!(null)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send("GET", "/api/v1/auth/me", Some(&laptop), json!(null)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send("DELETE", &uri, Some(&phone), json!(null)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_failed_logins_trigger_alert_rules() {
+        let config = ServerConfig::default();
+        let jwt_manager = JwtManager::new(&config.jwt_secret, config.jwt_expiration_hours);
+        let admin = jwt_manager.generate_token(Uuid::new_v4(), common::types::Role::Admin, vec![]).unwrap();
+        let user = jwt_manager.generate_token(Uuid::new_v4(), common::types::Role::User, vec![]).unwrap();
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .acquire_timeout(std::time::Duration::from_millis(200))
+            .connect_lazy("postgresql://localhost:1/test")
+            .unwrap();
+        let alerts = Arc::new(AlertEngine::new(Arc::new(integration_service::ChannelNotifier::new())));
+        let audit = AuditLogger::new(Arc::new(audit_service::AuditStorage::new(pool))).with_alert_engine(alerts.clone());
+        let services = SharedServices {
+            audit: Some(Arc::new(audit)),
+            audit_alerts: Some(alerts),
+            ..Default::default()
+        };
+        let app = create_server_with_services(config, services);
+        let send = |method: &str, uri: &str, token: Option<&str>, body: serde_json::Value| {
+            let mut request = Request::builder().method(method).uri(uri).header("content-type", "application/json");
+            if let Some(token) = token {
+                request = request.header("authorization", format!("Bearer {}", token));
+            }
+            let request = request.body(Body::from(body.to_string())).unwrap();
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default())
+            }
+        };
+
+        let rule = json!({
+            "name": "Brute force",
+            "condition": { "type": "failed_logins", "threshold": 2, "window_minutes": 5 },
+            "channels": [{ "type": "slack", "webhook_url": "https://hooks.example.com/services/x" }]
+        });
+        let (status, _) = send("POST", "/api/v1/admin/audit/alert-rules", Some(&user), rule.clone()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = send("POST", "/api/v1/admin/audit/alert-rules", Some(&admin), rule).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let rule_uri = format!("/api/v1/admin/audit/alert-rules/{}", body["rule"]["id"].as_str().unwrap());
+
+        let login = json!({ "email": "nobody@example.com", "password": "wrong-password" });
+        for _ in 0..2 {
+            let (status, _) = send("POST", "/api/v1/auth/login", None, login.clone()).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+        let (status, body) = send("GET", "/api/v1/admin/audit/alerts", Some(&admin), json!(null)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["alerts"].as_array().unwrap().len(), 1);
+        assert_eq!(body["alerts"][0]["rule_name"], "Brute force");
+
+        let (status, _) = send("DELETE", &rule_uri, Some(&admin), json!(null)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send("DELETE", &rule_uri, Some(&admin), json!(null)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
 }
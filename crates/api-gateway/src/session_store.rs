@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common::error::{PlatformError, Result};
+use rbac_service::{Session, SessionStore};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+/// Postgres-backed session store (see `migrations/014_sessions.sql`), so
+/// sessions survive restarts and every gateway replica accepts their tokens
+pub struct PgSessionStore {
+    pool: PgPool,
+}
+
+impl PgSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SessionStore for PgSessionStore {
+    async fn start(&self, session: &Session) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO sessions (id, user_id, device_name, ip_address, user_agent, issued_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(session.id)
+        .bind(session.user_id)
+        .bind(&session.device_name)
+        .bind(&session.ip_address)
+        .bind(&session.user_agent)
+        .bind(session.issued_at)
+        .bind(session.expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+
+        // Drop the user's expired sessions, they can no longer be used
+        sqlx::query("DELETE FROM sessions WHERE user_id = $1 AND expires_at <= now()")
+            .bind(session.user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn is_active(&self, id: Uuid) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM sessions WHERE id = $1 AND expires_at > now()")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(row.is_some())
+    }
+
+    async fn extend(&self, id: Uuid, expires_at: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query("UPDATE sessions SET expires_at = $2 WHERE id = $1 AND expires_at > now()")
+            .bind(id)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Session>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user_id, device_name, ip_address, user_agent, issued_at, expires_at
+            FROM sessions
+            WHERE user_id = $1 AND expires_at > now()
+            ORDER BY issued_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        rows.iter().map(decode).collect()
+    }
+
+    async fn revoke(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM sessions WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn revoke_all(&self, user_id: Uuid) -> Result<usize> {
+        let result = sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(result.rows_affected() as usize)
+    }
+}
+
+fn decode(row: &sqlx::postgres::PgRow) -> Result<Session> {
+    Ok(Session {
+        id: row.try_get("id").map_err(storage_error)?,
+        user_id: row.try_get("user_id").map_err(storage_error)?,
+        device_name: row.try_get("device_name").map_err(storage_error)?,
+        ip_address: row.try_get("ip_address").map_err(storage_error)?,
+        user_agent: row.try_get("user_agent").map_err(storage_error)?,
+        issued_at: row.try_get("issued_at").map_err(storage_error)?,
+        expires_at: row.try_get("expires_at").map_err(storage_error)?,
+    })
+}
+
+fn storage_error(e: sqlx::Error) -> PlatformError {
+    PlatformError::Database(e.to_string())
+}
@@ -0,0 +1,181 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use common::types::{ActionType2, Role};
+use rbac_service::jwt::JwtClaims;
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::settings::{OrgSettings, OrgSettingsStore};
+
+use crate::workflow_access::WorkflowAccess;
+
+/// Organization settings service state
+#[derive(Clone)]
+pub struct SettingsServiceState {
+    pub settings: Arc<OrgSettingsStore>,
+    /// Authorizes workflow opt-outs on the workflows
+    access: WorkflowAccess,
+}
+
+impl SettingsServiceState {
+    pub fn new(settings: Arc<OrgSettingsStore>) -> Self {
+        Self { settings, access: WorkflowAccess::default() }
+    }
+
+    /// Authorize callers against the saved workflows of `access`
+    pub fn with_access(mut self, access: WorkflowAccess) -> Self {
+        self.access = access;
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OptOutRequest {
+    pub opt_out: bool,
+}
+
+/// 查询组织设置（包括请求头/环境变量注入策略）
+pub async fn get_settings(
+    State(state): State<SettingsServiceState>,
+    Path(organization_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let settings = state.settings.get(organization_id).await;
+    Json(serde_json::json!({
+        "success": true,
+        "settings": settings
+    }))
+}
+
+/// 更新组织设置（仅管理员），执行器在节点执行时读取最新设置
+pub async fn update_settings(
+    State(state): State<SettingsServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(organization_id): Path<Uuid>,
+    Json(settings): Json<OrgSettings>,
+) -> impl IntoResponse {
+    if claims.role != Role::Admin {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "只有管理员可以修改组织设置"
+            })),
+        );
+    }
+
+    state.settings.set(organization_id, settings.clone()).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "settings": settings
+        })),
+    )
+}
+
+/// 设置单个工作流是否退出组织的注入策略
+pub async fn set_injection_opt_out(
+    State(state): State<SettingsServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path((organization_id, workflow_id)): Path<(Uuid, Uuid)>,
+    Json(request): Json<OptOutRequest>,
+) -> impl IntoResponse {
+    if !state.access.can_on(&claims, ActionType2::Update, workflow_id).await {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "success": false,
+                "message": "没有修改工作流的权限"
+            })),
+        );
+    }
+
+    state.settings.set_opt_out(organization_id, workflow_id, request.opt_out).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "workflow_id": workflow_id,
+            "opt_out": request.opt_out
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::claims;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::put;
+    use axum::Router;
+    use common::types::{Workflow, WORKFLOW_SCHEMA_VERSION};
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+    use workflow_engine::workflows::{InMemoryWorkflowStore, StoredWorkflow, WorkflowStore};
+
+    fn app(state: SettingsServiceState, user: Uuid, role: Role) -> Router {
+        Router::new()
+            .route("/organizations/:organization_id/settings", put(update_settings))
+            .route(
+                "/organizations/:organization_id/settings/injection/opt-out/:workflow_id",
+                put(set_injection_opt_out),
+            )
+            .layer(Extension(claims(user, role)))
+            .with_state(state)
+    }
+
+    async fn put_json(app: Router, uri: &str, body: serde_json::Value) -> StatusCode {
+        app.oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+    }
+
+    #[tokio::test]
+    async fn test_admin_policy_and_workflow_opt_out() {
+        let (owner, stranger) = (Uuid::new_v4(), Uuid::new_v4());
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Tracked".to_string(),
+            description: None,
+            nodes: vec![],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+        let workflows = Arc::new(InMemoryWorkflowStore::new());
+        workflows.save(&StoredWorkflow { workflow: workflow.clone(), owner_id: owner, active: true }).await.unwrap();
+        let state = SettingsServiceState::new(Arc::new(OrgSettingsStore::new()))
+            .with_access(WorkflowAccess::new(workflows));
+        let (org, workflow_id) = (Uuid::new_v4(), workflow.id);
+        let uri = format!("/organizations/{}/settings", org);
+        let policy = serde_json::json!({ "injection": { "headers": { "X-Tracking": "abc" } } });
+
+        assert_eq!(put_json(app(state.clone(), owner, Role::User), &uri, policy.clone()).await, StatusCode::FORBIDDEN);
+        assert_eq!(put_json(app(state.clone(), owner, Role::Admin), &uri, policy).await, StatusCode::OK);
+        assert_eq!(state.settings.get(org).await.injection.headers["X-Tracking"], "abc");
+
+        // Only those who may edit the workflow opt it out
+        let opt_out_uri = format!("{}/injection/opt-out/{}", uri, workflow_id);
+        let opt_out = serde_json::json!({ "opt_out": true });
+        assert_eq!(put_json(app(state.clone(), owner, Role::Viewer), &opt_out_uri, opt_out.clone()).await, StatusCode::FORBIDDEN);
+        assert_eq!(put_json(app(state.clone(), stranger, Role::User), &opt_out_uri, opt_out.clone()).await, StatusCode::FORBIDDEN);
+        assert_eq!(put_json(app(state.clone(), owner, Role::User), &opt_out_uri, opt_out).await, StatusCode::OK);
+        assert!(!state.settings.get(org).await.injection.applies_to(workflow_id));
+    }
+}
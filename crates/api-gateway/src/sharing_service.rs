@@ -0,0 +1,353 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use common::types::Workflow;
+use integration_service::sharing::{
+    bind_credentials, decrypt_bundle, export_encrypted, export_public, PublicBundle, SharingError,
+    WorkflowBundle,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use workflow_engine::{PortableFormat, WorkflowParser};
+
+/// Pending public-bundle imports waiting for credential bindings
+#[derive(Clone, Default)]
+pub struct SharingServiceState {
+    imports: Arc<RwLock<HashMap<Uuid, PublicBundle>>>,
+}
+
+impl SharingServiceState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Export request; a passphrase produces an encrypted bundle, otherwise a public one
+#[derive(Debug, Deserialize)]
+pub struct ExportRequest {
+    pub workflow: Workflow,
+    #[serde(default)]
+    pub passphrase: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportRequest {
+    pub bundle: WorkflowBundle,
+    #[serde(default)]
+    pub passphrase: Option<String>,
+}
+
+/// Portable export request
+#[derive(Debug, Deserialize)]
+pub struct PortableExportRequest {
+    pub workflow: Workflow,
+    #[serde(default)]
+    pub format: PortableFormat,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BindRequest {
+    /// Placeholder ID -> credential ID
+    pub bindings: HashMap<String, String>,
+}
+
+/// 导出工作流：提供口令时生成加密包（内部迁移），否则生成去除凭证的公开包
+pub async fn export_workflow(Json(request): Json<ExportRequest>) -> impl IntoResponse {
+    let bundle = match request.passphrase.as_deref() {
+        Some("") => {
+            return error_response(StatusCode::BAD_REQUEST, "口令不能为空".to_string());
+        }
+        Some(passphrase) => match export_encrypted(&request.workflow, passphrase) {
+            Ok(bundle) => WorkflowBundle::Encrypted(bundle),
+            Err(e) => return sharing_error(e),
+        },
+        None => WorkflowBundle::Public(export_public(&request.workflow)),
+    };
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "bundle": bundle
+        })),
+    )
+}
+
+/// 导入工作流：加密包直接还原；公开包返回需要绑定的凭证占位符
+pub async fn import_workflow(
+    State(state): State<SharingServiceState>,
+    Json(request): Json<ImportRequest>,
+) -> impl IntoResponse {
+    match request.bundle {
+        WorkflowBundle::Encrypted(bundle) => {
+            let Some(passphrase) = request.passphrase else {
+                return error_response(StatusCode::BAD_REQUEST, "加密包需要口令".to_string());
+            };
+            match decrypt_bundle(&bundle, &passphrase) {
+                Ok(workflow) => (
+                    StatusCode::OK,
+                    Json(serde_json::json!({
+                        "success": true,
+                        "workflow": workflow
+                    })),
+                ),
+                Err(e) => sharing_error(e),
+            }
+        }
+        WorkflowBundle::Public(bundle) => import_public(&state, bundle).await,
+    }
+}
+
+/// 导出为可移植格式（JSON 或 YAML）：去除实例相关的 ID，凭证替换为命名占位符，可导入其他 flowvex 实例
+pub async fn export_portable(Json(request): Json<PortableExportRequest>) -> Response {
+    let (content_type, extension) = match request.format {
+        PortableFormat::Json => ("application/json", "json"),
+        PortableFormat::Yaml => ("application/yaml", "yaml"),
+    };
+    match WorkflowParser::new().serialize_portable(&request.workflow, request.format) {
+        Ok(bundle) => (
+            [
+                (header::CONTENT_TYPE, content_type.to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"workflow.flowvex.{}\"", extension)),
+            ],
+            bundle,
+        ).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("导出失败: {}", e)).into_response(),
+    }
+}
+
+/// 导入可移植格式的工作流（JSON 或 YAML 原文）：重新生成节点 ID，需要绑定的凭证与公开包相同
+pub async fn import_portable(State(state): State<SharingServiceState>, body: String) -> impl IntoResponse {
+    match WorkflowParser::new().import_portable(&body) {
+        Ok(bundle) => import_public(&state, bundle).await,
+        Err(e) => error_response(StatusCode::BAD_REQUEST, format!("无法导入工作流: {}", e)),
+    }
+}
+
+/// 没有凭证时直接完成导入，否则保存待绑定的导入任务并返回占位符
+async fn import_public(state: &SharingServiceState, bundle: PublicBundle) -> (StatusCode, Json<serde_json::Value>) {
+    if bundle.placeholders.is_empty() {
+        return match bind_credentials(&bundle, &HashMap::new()) {
+            Ok(workflow) => (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "success": true,
+                    "workflow": workflow
+                })),
+            ),
+            Err(e) => sharing_error(e),
+        };
+    }
+
+    let import_id = Uuid::new_v4();
+    let placeholders = bundle.placeholders.clone();
+    state.imports.write().await.insert(import_id, bundle);
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "success": true,
+            "import_id": import_id,
+            "placeholders": placeholders
+        })),
+    )
+}
+
+/// 为待导入的工作流绑定凭证，全部绑定后完成导入
+pub async fn bind_import(
+    State(state): State<SharingServiceState>,
+    Path(import_id): Path<Uuid>,
+    Json(request): Json<BindRequest>,
+) -> impl IntoResponse {
+    let mut imports = state.imports.write().await;
+    let Some(bundle) = imports.get(&import_id) else {
+        return error_response(StatusCode::NOT_FOUND, format!("导入任务不存在: {}", import_id));
+    };
+
+    match bind_credentials(bundle, &request.bindings) {
+        Ok(workflow) => {
+            imports.remove(&import_id);
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "success": true,
+                    "workflow": workflow
+                })),
+            )
+        }
+        Err(e) => sharing_error(e),
+    }
+}
+
+fn sharing_error(error: SharingError) -> (StatusCode, Json<serde_json::Value>) {
+    let status = match error {
+        SharingError::EncryptionFailed => StatusCode::INTERNAL_SERVER_ERROR,
+        SharingError::DecryptionFailed => StatusCode::FORBIDDEN,
+        SharingError::UnboundPlaceholders(ref missing) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(serde_json::json!({
+                    "success": false,
+                    "message": error.to_string(),
+                    "missing": missing
+                })),
+            );
+        }
+        _ => StatusCode::BAD_REQUEST,
+    };
+    error_response(status, error.to_string())
+}
+
+fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::call;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::post;
+    use axum::Router;
+    use common::types::{ActionType, Node, NodeConfig, NodeType, Position, WORKFLOW_SCHEMA_VERSION};
+    use tower::ServiceExt;
+
+    fn app(state: SharingServiceState) -> Router {
+        Router::new()
+            .route("/workflows/export", post(export_workflow))
+            .route("/workflows/import", post(import_workflow))
+            .route("/workflows/import/:import_id/bindings", post(bind_import))
+            .route("/workflows/export/portable", post(export_portable))
+            .route("/workflows/import/portable", post(import_portable))
+            .with_state(state)
+    }
+
+    #[tokio::test]
+    async fn test_public_export_and_import_wizard() {
+        let app = app(SharingServiceState::new());
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Notify".to_string(),
+            description: None,
+            nodes: vec![Node {
+                id: Uuid::new_v4(),
+                node_type: NodeType::Action { action_type: ActionType::Integration },
+                config: NodeConfig {
+                    parameters: HashMap::from([
+                        ("integration".to_string(), serde_json::json!("slack")),
+                        ("token".to_string(), serde_json::json!("xoxb-secret")),
+                    ]),
+                    ..Default::default()
+                },
+                position: Position { x: 0.0, y: 0.0 },
+                inputs: vec![],
+                outputs: vec![],
+            }],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+
+        let (status, exported) = call(app.clone(), "POST", "/workflows/export", Some(serde_json::json!({ "workflow": workflow }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!exported.to_string().contains("xoxb-secret"));
+
+        let (status, plan) = call(app.clone(), "POST", "/workflows/import", Some(serde_json::json!({ "bundle": exported["bundle"] }))).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(plan["placeholders"][0]["integration"], "slack");
+        let import_uri = format!("/workflows/import/{}/bindings", plan["import_id"].as_str().unwrap());
+
+        let (status, _) = call(app.clone(), "POST", &import_uri, Some(serde_json::json!({ "bindings": {} }))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let (status, imported) = call(app.clone(), "POST", &import_uri, Some(serde_json::json!({ "bindings": { "cred_1": "my-slack" } }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(imported["workflow"]["nodes"][0]["config"]["parameters"]["token"]["$credential"], "my-slack");
+
+        let (status, _) = call(app.clone(), "POST", "/workflows/export", Some(serde_json::json!({ "workflow": workflow, "passphrase": "pw" }))).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_portable_yaml_export_and_import() {
+        let app = app(SharingServiceState::new());
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Notify".to_string(),
+            description: Some("Posts to Slack".to_string()),
+            nodes: vec![Node {
+                id: Uuid::new_v4(),
+                node_type: NodeType::Action { action_type: ActionType::Integration },
+                config: NodeConfig {
+                    parameters: HashMap::from([
+                        ("integration".to_string(), serde_json::json!("slack")),
+                        ("token".to_string(), serde_json::json!("xoxb-secret")),
+                    ]),
+                    ..Default::default()
+                },
+                position: Position { x: 0.0, y: 0.0 },
+                inputs: vec![],
+                outputs: vec![],
+            }],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+
+        let response = app.clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/workflows/export/portable")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::json!({ "workflow": workflow, "format": "yaml" }).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/yaml");
+        let yaml = String::from_utf8(axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap();
+        assert!(yaml.contains("name: Notify") && !yaml.contains("xoxb-secret"));
+
+        let response = app.clone()
+            .oneshot(Request::builder().method("POST").uri("/workflows/import/portable").body(Body::from(yaml)).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let plan: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(plan["placeholders"][0]["integration"], "slack");
+
+        let import_uri = format!("/workflows/import/{}/bindings", plan["import_id"].as_str().unwrap());
+        let (status, imported) = call(app.clone(), "POST", &import_uri, Some(serde_json::json!({ "bindings": { "cred_1": "my-slack" } }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_ne!(imported["workflow"]["id"], workflow.id.to_string());
+        assert_ne!(imported["workflow"]["nodes"][0]["id"], workflow.nodes[0].id.to_string());
+        assert_eq!(imported["workflow"]["description"], "Posts to Slack");
+
+        let response = app
+            .oneshot(Request::builder().method("POST").uri("/workflows/import/portable").body(Body::from("name: x")).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,193 @@
+//! Webhook signatures, shared by incoming webhook triggers and outgoing
+//! webhook deliveries.
+//!
+//! A signature header looks like `t=1700000000,v1=<hex>`: `t` is the Unix
+//! time of signing and each `v1` is the hex HMAC-SHA256 of the canonical
+//! payload `"{t}.{body}"` under a secret. Several `v1` entries may be sent
+//! while a secret is rotated; one matching is enough. Signing the timestamp
+//! lets receivers reject replayed requests outside a tolerance window.
+//!
+//! The legacy form `sha256=<hex>` (or bare hex) signs the body alone. It has
+//! no timestamp, so a captured request can be replayed forever; it is only
+//! accepted when the receiver opts in.
+
+use common::error::{ErrorCode, ErrorInfo};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+
+/// Header carrying the signature
+pub const SIGNATURE_HEADER: &str = "x-flowvex-signature";
+
+/// Scheme of the current signatures
+pub const SIGNATURE_SCHEME: &str = "v1";
+
+/// Largest accepted difference between the signing time and now, in seconds
+pub const DEFAULT_TOLERANCE_SECS: i64 = 300;
+
+/// A parsed signature header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParsedSignature {
+    /// `None` for legacy body-only signatures
+    pub timestamp: Option<i64>,
+    /// Hex signatures to try
+    pub signatures: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SignatureError {
+    #[error("Malformed signature header: {0}")]
+    Malformed(String),
+
+    #[error("Signature timestamp is {skew_secs}s away from now, more than the {tolerance_secs}s tolerated")]
+    OutsideTolerance { skew_secs: i64, tolerance_secs: i64 },
+
+    #[error("Signature does not match the payload")]
+    Mismatch,
+
+    #[error("Legacy body-only signatures are not accepted, sign with t=<timestamp>,v1=<signature>")]
+    LegacyRejected,
+}
+
+impl ErrorInfo for SignatureError {
+    fn error_code(&self) -> ErrorCode {
+        ErrorCode::Unauthenticated
+    }
+}
+
+/// The bytes signed for a request: `"{timestamp}.{body}"`
+pub fn canonical_payload(timestamp: i64, body: &[u8]) -> Vec<u8> {
+    let mut payload = format!("{}.", timestamp).into_bytes();
+    payload.extend_from_slice(body);
+    payload
+}
+
+/// Hex HMAC-SHA256 of the canonical payload
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    hex::encode(mac(secret, &canonical_payload(timestamp, body)).finalize().into_bytes())
+}
+
+/// The signature header value of a delivery, e.g. for outgoing webhooks
+pub fn signature_header(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    format!("t={},{}={}", timestamp, SIGNATURE_SCHEME, sign(secret, timestamp, body))
+}
+
+/// Parse a signature header in either form; entries of unknown schemes are
+/// skipped so new schemes can be sent alongside `v1`
+pub fn parse_header(header: &str) -> Result<ParsedSignature, SignatureError> {
+    let header = header.trim();
+    if !header.contains('=') || header.starts_with("sha256=") {
+        let signature = header.strip_prefix("sha256=").unwrap_or(header);
+        return Ok(ParsedSignature { timestamp: None, signatures: vec![signature.to_string()] });
+    }
+
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for entry in header.split(',') {
+        let (key, value) = entry.trim().split_once('=')
+            .ok_or_else(|| SignatureError::Malformed(format!("expected key=value, got {:?}", entry)))?;
+        match key {
+            "t" => {
+                let parsed = value.parse()
+                    .map_err(|_| SignatureError::Malformed(format!("invalid timestamp {:?}", value)))?;
+                timestamp = Some(parsed);
+            }
+            SIGNATURE_SCHEME => signatures.push(value.to_string()),
+            _ => {}
+        }
+    }
+    if timestamp.is_none() {
+        return Err(SignatureError::Malformed("missing t=<timestamp>".to_string()));
+    }
+    if signatures.is_empty() {
+        return Err(SignatureError::Malformed(format!("missing {}=<signature>", SIGNATURE_SCHEME)));
+    }
+    Ok(ParsedSignature { timestamp, signatures })
+}
+
+/// Check that the signing time is within `tolerance_secs` of `now` (Unix seconds)
+pub fn check_timestamp(timestamp: i64, now: i64, tolerance_secs: i64) -> Result<(), SignatureError> {
+    let skew_secs = (now - timestamp).abs();
+    if skew_secs > tolerance_secs {
+        return Err(SignatureError::OutsideTolerance { skew_secs, tolerance_secs });
+    }
+    Ok(())
+}
+
+/// Verify a signature header against the request body in constant time;
+/// legacy body-only signatures only pass with `accept_legacy`
+pub fn verify(
+    secret: &str,
+    header: &str,
+    body: &[u8],
+    now: i64,
+    tolerance_secs: i64,
+    accept_legacy: bool,
+) -> Result<ParsedSignature, SignatureError> {
+    let parsed = parse_header(header)?;
+    let signed = match parsed.timestamp {
+        Some(timestamp) => {
+            check_timestamp(timestamp, now, tolerance_secs)?;
+            canonical_payload(timestamp, body)
+        }
+        None if accept_legacy => body.to_vec(),
+        None => return Err(SignatureError::LegacyRejected),
+    };
+    let matches = parsed.signatures.iter().any(|signature| {
+        let Ok(signature) = hex::decode(signature.trim()) else {
+            return false;
+        };
+        mac(secret, &signed).verify_slice(&signature).is_ok()
+    });
+    if !matches {
+        return Err(SignatureError::Mismatch);
+    }
+    Ok(parsed)
+}
+
+fn mac(secret: &str, payload: &[u8]) -> Hmac<Sha256> {
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC key of any length");
+    mac.update(payload);
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let body = br#"{"ref":"main"}"#;
+        let header = signature_header("s3cret", 1_700_000_000, body);
+        assert_eq!(canonical_payload(1_700_000_000, body), br#"1700000000.{"ref":"main"}"#.to_vec());
+        assert!(header.starts_with("t=1700000000,v1="));
+
+        let parsed = verify("s3cret", &header, body, 1_700_000_100, DEFAULT_TOLERANCE_SECS, false).unwrap();
+        assert_eq!(parsed.timestamp, Some(1_700_000_000));
+        assert_eq!(verify("wrong", &header, body, 1_700_000_100, DEFAULT_TOLERANCE_SECS, false), Err(SignatureError::Mismatch));
+        assert_eq!(verify("s3cret", &header, b"{}", 1_700_000_100, DEFAULT_TOLERANCE_SECS, false), Err(SignatureError::Mismatch));
+        assert_eq!(
+            verify("s3cret", &header, body, 1_700_001_000, DEFAULT_TOLERANCE_SECS, false),
+            Err(SignatureError::OutsideTolerance { skew_secs: 1000, tolerance_secs: DEFAULT_TOLERANCE_SECS }),
+        );
+
+        // During a secret rotation either signature is accepted
+        let rotated = format!("{},v1={},v0=ignored", header, sign("n3w", 1_700_000_000, body));
+        assert!(verify("n3w", &rotated, body, 1_700_000_000, DEFAULT_TOLERANCE_SECS, false).is_ok());
+    }
+
+    #[test]
+    fn test_legacy_body_signatures() {
+        let body = b"payload";
+        let legacy = hex::encode(mac("s3cret", body).finalize().into_bytes());
+        for header in [format!("sha256={}", legacy), legacy.clone()] {
+            assert_eq!(verify("s3cret", &header, body, 0, DEFAULT_TOLERANCE_SECS, false), Err(SignatureError::LegacyRejected));
+            let parsed = verify("s3cret", &header, body, 0, DEFAULT_TOLERANCE_SECS, true).unwrap();
+            assert_eq!(parsed.timestamp, None);
+        }
+        assert!(matches!(parse_header("v1=abc"), Err(SignatureError::Malformed(_))));
+        assert!(matches!(parse_header("t=soon,v1=abc"), Err(SignatureError::Malformed(_))));
+        assert!(matches!(parse_header("t=1"), Err(SignatureError::Malformed(_))));
+    }
+}
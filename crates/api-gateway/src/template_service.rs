@@ -0,0 +1,350 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use common::types::{ActionType2, JsonValue, Permission, ResourceType, Role, Scope};
+use integration_service::sharing::{bind_credentials, SharingError};
+use rbac_service::jwt::JwtClaims;
+use rbac_service::permissions::permission_string;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::revisions::RevisionStore;
+use workflow_engine::templates::{TemplateDraft, TemplateError, TemplateStore};
+
+use crate::workflow_access::WorkflowAccess;
+
+/// Template service state
+#[derive(Clone)]
+pub struct TemplateServiceState {
+    pub templates: Arc<TemplateStore>,
+    /// Instantiated workflows are saved as their first revision
+    pub revisions: Arc<RevisionStore>,
+    /// Authorizes creating the instantiated workflows
+    access: WorkflowAccess,
+}
+
+impl TemplateServiceState {
+    pub fn new(templates: Arc<TemplateStore>, revisions: Arc<RevisionStore>) -> Self {
+        Self { templates, revisions, access: WorkflowAccess::default() }
+    }
+
+    /// Authorize callers with the permissions and organizations of `access`
+    pub fn with_access(mut self, access: WorkflowAccess) -> Self {
+        self.access = access;
+        self
+    }
+}
+
+/// Template list filter
+#[derive(Debug, Default, Deserialize)]
+pub struct TemplateQuery {
+    pub category: Option<String>,
+    /// Matched against names, descriptions and tags
+    pub search: Option<String>,
+}
+
+/// Instantiation request
+#[derive(Debug, Default, Deserialize)]
+pub struct InstantiateRequest {
+    /// Parameter name -> value; parameters with defaults may be left out
+    #[serde(default)]
+    pub parameters: HashMap<String, JsonValue>,
+    /// Name of the new workflow; the template's workflow name by default
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Credential placeholder ID -> credential ID
+    #[serde(default)]
+    pub bindings: HashMap<String, String>,
+}
+
+/// Whether the caller may create templates, i.e. holds the template Create
+/// permission at any scope
+fn can_manage_templates(claims: &JwtClaims) -> bool {
+    let granted = [Scope::All, Scope::Team, Scope::Own].into_iter().any(|scope| {
+        claims.permissions.contains(&permission_string(&Permission {
+            resource: ResourceType::Template,
+            action: ActionType2::Create,
+            scope,
+        }))
+    });
+    matches!(claims.role, Role::Admin | Role::Manager) || granted
+}
+
+/// 列出模板，可按分类和关键字（名称、描述、标签）过滤
+pub async fn list_templates(
+    State(state): State<TemplateServiceState>,
+    Query(query): Query<TemplateQuery>,
+) -> impl IntoResponse {
+    let templates = state.templates.list(query.category.as_deref(), query.search.as_deref()).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "total": templates.len(),
+            "templates": templates
+        })),
+    )
+}
+
+/// 列出模板分类及各分类的模板数量
+pub async fn list_template_categories(State(state): State<TemplateServiceState>) -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "categories": state.templates.categories().await
+        })),
+    )
+}
+
+/// 查询模板详情：参数定义和工作流
+pub async fn get_template(
+    State(state): State<TemplateServiceState>,
+    Path(template_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state.templates.get(template_id).await {
+        Some(template) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "template": template
+            })),
+        ),
+        None => error_response(StatusCode::NOT_FOUND, "模板不存在".to_string()),
+    }
+}
+
+/// 创建模板：工作流中的 [[ 参数名 ]] 占位符必须都有参数定义，凭证会被替换为占位符
+pub async fn create_template(
+    State(state): State<TemplateServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Json(draft): Json<TemplateDraft>,
+) -> impl IntoResponse {
+    if !can_manage_templates(&claims) {
+        return error_response(StatusCode::FORBIDDEN, "没有创建模板的权限".to_string());
+    }
+
+    match state.templates.create(draft, claims.sub).await {
+        Ok(template) => (
+            StatusCode::CREATED,
+            Json(serde_json::json!({
+                "success": true,
+                "template": template
+            })),
+        ),
+        Err(e) => template_error(e),
+    }
+}
+
+/// 修改模板，仅限作者和管理员
+pub async fn update_template(
+    State(state): State<TemplateServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(template_id): Path<Uuid>,
+    Json(draft): Json<TemplateDraft>,
+) -> impl IntoResponse {
+    if let Err(denied) = check_author(&state, &claims, template_id).await {
+        return denied;
+    }
+
+    match state.templates.update(template_id, draft).await {
+        Ok(template) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "template": template
+            })),
+        ),
+        Err(e) => template_error(e),
+    }
+}
+
+/// 删除模板，仅限作者和管理员；已创建的工作流不受影响
+pub async fn delete_template(
+    State(state): State<TemplateServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(template_id): Path<Uuid>,
+) -> impl IntoResponse {
+    if let Err(denied) = check_author(&state, &claims, template_id).await {
+        return denied;
+    }
+
+    state.templates.delete(template_id).await;
+    (StatusCode::OK, Json(serde_json::json!({ "success": true })))
+}
+
+/// 用模板创建工作流：填入参数值并绑定凭证，新工作流作为首个修订保存，归调用者所有
+pub async fn instantiate_template(
+    State(state): State<TemplateServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(template_id): Path<Uuid>,
+    Json(request): Json<InstantiateRequest>,
+) -> impl IntoResponse {
+    if !state.access.can(&claims, ActionType2::Create, Some(claims.sub)).await {
+        return error_response(StatusCode::FORBIDDEN, "没有创建工作流的权限".to_string());
+    }
+
+    let bundle = match state.templates.instantiate(template_id, &request.parameters, request.name).await {
+        Ok(bundle) => bundle,
+        Err(e) => return template_error(e),
+    };
+    let workflow = match bind_credentials(&bundle, &request.bindings) {
+        Ok(workflow) => workflow,
+        Err(SharingError::UnboundPlaceholders(missing)) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(serde_json::json!({
+                    "success": false,
+                    "message": "以下凭证占位符尚未绑定",
+                    "missing": missing,
+                    "placeholders": bundle.placeholders
+                })),
+            );
+        }
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+
+    let outcome = state.revisions.submit(workflow.clone(), claims.sub).await;
+    (
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "success": true,
+            "workflow": workflow,
+            "result": outcome
+        })),
+    )
+}
+
+/// Only the template's author and admins may change it
+async fn check_author(
+    state: &TemplateServiceState,
+    claims: &JwtClaims,
+    template_id: Uuid,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let Some(template) = state.templates.get(template_id).await else {
+        return Err(error_response(StatusCode::NOT_FOUND, "模板不存在".to_string()));
+    };
+    if template.author != claims.sub && claims.role != Role::Admin {
+        return Err(error_response(StatusCode::FORBIDDEN, "只有模板作者和管理员可以修改模板".to_string()));
+    }
+    Ok(())
+}
+
+fn template_error(error: TemplateError) -> (StatusCode, Json<serde_json::Value>) {
+    let status = match error {
+        TemplateError::NotFound(_) => StatusCode::NOT_FOUND,
+        TemplateError::InvalidWorkflow(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    error_response(status, error.to_string())
+}
+
+fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, claims};
+    use axum::routing::{get, post};
+    use axum::Router;
+    use common::types::{Node, NodeConfig, NodeType, Position, TriggerType, Workflow, WORKFLOW_SCHEMA_VERSION};
+
+    fn app(state: TemplateServiceState, user: Uuid, role: Role) -> Router {
+        Router::new()
+            .route("/templates", get(list_templates).post(create_template))
+            .route("/templates/categories", get(list_template_categories))
+            .route("/templates/:id", get(get_template).put(update_template).delete(delete_template))
+            .route("/templates/:id/instantiate", post(instantiate_template))
+            .layer(Extension(claims(user, role)))
+            .with_state(state)
+    }
+
+    fn draft() -> serde_json::Value {
+        let trigger = Node {
+            id: Uuid::new_v4(),
+            node_type: NodeType::Trigger { trigger_type: TriggerType::Manual },
+            config: NodeConfig {
+                parameters: HashMap::from([("label".to_string(), serde_json::json!("Daily report for [[ team ]]"))]),
+                ..Default::default()
+            },
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![],
+        };
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Daily report".to_string(),
+            description: None,
+            nodes: vec![trigger],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+        serde_json::json!({
+            "name": "Daily report",
+            "category": "reporting",
+            "parameters": [{ "name": "team" }],
+            "workflow": workflow
+        })
+    }
+
+    #[tokio::test]
+    async fn test_template_lifecycle() {
+        let revisions = Arc::new(RevisionStore::new());
+        let state = TemplateServiceState::new(Arc::new(TemplateStore::new()), revisions.clone());
+        let (manager, user) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let (status, _) = call(app(state.clone(), user, Role::User), "POST", "/templates", Some(draft())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = call(app(state.clone(), manager, Role::Manager), "POST", "/templates", Some(draft())).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let template_id = body["template"]["id"].as_str().unwrap().to_string();
+
+        let (_, body) = call(app(state.clone(), user, Role::Viewer), "GET", "/templates?category=reporting", None).await;
+        assert_eq!(body["total"], 1);
+        let (_, body) = call(app(state.clone(), user, Role::Viewer), "GET", "/templates/categories", None).await;
+        assert_eq!(body["categories"][0]["name"], "reporting");
+
+        let uri = format!("/templates/{}", template_id);
+        let (status, _) = call(app(state.clone(), user, Role::Manager), "PUT", &uri, Some(draft())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let mut invalid = draft();
+        invalid["parameters"] = serde_json::json!([]);
+        let (status, body) = call(app(state.clone(), manager, Role::Manager), "PUT", &uri, Some(invalid)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["message"].as_str().unwrap().contains("team"));
+
+        let instantiate_uri = format!("/templates/{}/instantiate", template_id);
+        let (status, _) = call(app(state.clone(), user, Role::Viewer), "POST", &instantiate_uri, Some(serde_json::json!({}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = call(app(state.clone(), user, Role::User), "POST", &instantiate_uri, Some(serde_json::json!({}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let request = serde_json::json!({ "parameters": { "team": "Sales" }, "name": "Sales report" });
+        let (status, body) = call(app(state.clone(), user, Role::User), "POST", &instantiate_uri, Some(request)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["workflow"]["nodes"][0]["config"]["parameters"]["label"], "Daily report for Sales");
+        let workflow_id = Uuid::parse_str(body["workflow"]["id"].as_str().unwrap()).unwrap();
+        assert_eq!(revisions.owner(workflow_id).await, Some(user));
+        assert_eq!(revisions.active(workflow_id).await.unwrap().name, "Sales report");
+
+        let (status, _) = call(app(state.clone(), manager, Role::Manager), "DELETE", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(app(state, user, Role::Viewer), "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,251 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use common::types::{ActionType2, Workflow};
+use rbac_service::jwt::JwtClaims;
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::revisions::RevisionStore;
+use workflow_engine::test_suite::{run_suite, TestSuiteStore, WorkflowTestCase};
+
+use crate::workflow_access::WorkflowAccess;
+
+/// Test suite service state
+#[derive(Clone)]
+pub struct TestSuiteServiceState {
+    pub suites: Arc<TestSuiteStore>,
+    /// Suites run against the latest revision by default
+    pub revisions: Arc<RevisionStore>,
+    /// Authorizes callers on the suites' workflows
+    access: WorkflowAccess,
+}
+
+impl TestSuiteServiceState {
+    pub fn new(suites: Arc<TestSuiteStore>, revisions: Arc<RevisionStore>) -> Self {
+        Self { suites, revisions, access: WorkflowAccess::default() }
+    }
+
+    /// Authorize callers against the saved workflows of `access`
+    pub fn with_access(mut self, access: WorkflowAccess) -> Self {
+        self.access = access;
+        self
+    }
+}
+
+/// Suite run request
+#[derive(Debug, Default, Deserialize)]
+pub struct RunSuiteRequest {
+    /// Unsaved edit to test instead of the latest revision
+    #[serde(default)]
+    pub workflow: Option<Workflow>,
+}
+
+/// 查询工作流的测试用例
+pub async fn get_test_suite(
+    State(state): State<TestSuiteServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+) -> impl IntoResponse {
+    if !state.access.can_on(&claims, ActionType2::Read, workflow_id).await {
+        return error_response(StatusCode::NOT_FOUND, "工作流不存在".to_string());
+    }
+    let cases = state.suites.cases(workflow_id).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "total": cases.len(),
+            "cases": cases
+        })),
+    )
+}
+
+/// 保存工作流的测试用例（整体替换）：触发输入、模拟的接口响应和对节点输出的断言
+pub async fn update_test_suite(
+    State(state): State<TestSuiteServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+    Json(cases): Json<Vec<WorkflowTestCase>>,
+) -> impl IntoResponse {
+    if !state.access.can_on(&claims, ActionType2::Update, workflow_id).await {
+        return error_response(StatusCode::FORBIDDEN, "没有修改测试用例的权限".to_string());
+    }
+    if let Some(case) = cases.iter().find(|case| case.assertions.is_empty()) {
+        return error_response(StatusCode::BAD_REQUEST, format!("测试用例 {} 没有断言", case.name));
+    }
+
+    state.suites.set_cases(workflow_id, cases.clone()).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "total": cases.len(),
+            "cases": cases
+        })),
+    )
+}
+
+/// 运行测试用例：默认针对最新修订（含待审核修订），也可提交未保存的修改；
+/// 外部接口调用全部使用模拟响应，返回每个断言的通过情况
+pub async fn run_test_suite(
+    State(state): State<TestSuiteServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+    request: Option<Json<RunSuiteRequest>>,
+) -> impl IntoResponse {
+    // Suites run on mocks only, so reading the workflow is enough
+    if !state.access.can_on(&claims, ActionType2::Read, workflow_id).await {
+        return error_response(StatusCode::FORBIDDEN, "没有运行测试的权限".to_string());
+    }
+    let Json(request) = request.unwrap_or_default();
+
+    let workflow = match request.workflow {
+        Some(workflow) if workflow.id != workflow_id => {
+            return error_response(StatusCode::BAD_REQUEST, "工作流 ID 与路径不一致".to_string());
+        }
+        Some(workflow) => workflow,
+        None => match state.revisions.latest(workflow_id).await {
+            Some(workflow) => workflow,
+            None => return error_response(StatusCode::NOT_FOUND, "工作流不存在".to_string()),
+        },
+    };
+    let cases = state.suites.cases(workflow_id).await;
+    if cases.is_empty() {
+        return error_response(StatusCode::NOT_FOUND, "工作流没有测试用例".to_string());
+    }
+
+    let report = run_suite(&workflow, &cases).await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "passed": report.failed == 0,
+            "report": report
+        })),
+    )
+}
+
+fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, claims};
+    use axum::routing::{get, post};
+    use axum::Router;
+    use common::types::{ActionType, Edge, Node, NodeConfig, NodeType, Position, Role, TriggerType, WORKFLOW_SCHEMA_VERSION};
+    use rbac_service::org::MemberRole;
+    use rbac_service::OrgService;
+    use std::collections::HashMap;
+    use workflow_engine::workflows::{InMemoryWorkflowStore, StoredWorkflow, WorkflowStore};
+
+    fn app(state: TestSuiteServiceState, user: Uuid, role: Role) -> Router {
+        Router::new()
+            .route("/workflows/:workflow_id/tests", get(get_test_suite).put(update_test_suite))
+            .route("/workflows/:workflow_id/tests/run", post(run_test_suite))
+            .layer(Extension(claims(user, role)))
+            .with_state(state)
+    }
+
+    #[tokio::test]
+    async fn test_save_and_run_suite() {
+        let node = |node_type, parameters| Node {
+            id: Uuid::new_v4(),
+            node_type,
+            config: NodeConfig { parameters, ..Default::default() },
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![],
+        };
+        let trigger = node(NodeType::Trigger { trigger_type: TriggerType::Manual }, HashMap::new());
+        let fetch = node(
+            NodeType::Action { action_type: ActionType::Http },
+            HashMap::from([("url".to_string(), serde_json::json!("https://api.example.com/orders/{{ order }}"))]),
+        );
+        let fetch_id = fetch.id;
+        let mut workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Orders".to_string(),
+            description: None,
+            edges: vec![Edge {
+                id: Uuid::new_v4(),
+                source: trigger.id,
+                source_handle: "output".to_string(),
+                target: fetch.id,
+                target_handle: "input".to_string(),
+            }],
+            nodes: vec![trigger, fetch],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+        // The viewer reads the owner's workflows through their team
+        let organizations = Arc::new(OrgService::new());
+        let (owner, viewer, stranger) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let acme = organizations.create_organization("Acme".to_string(), owner).await;
+        let team = organizations.create_team(acme.id, "Ops".to_string(), owner).await.unwrap();
+        organizations.add_member(acme.id, viewer, MemberRole::Member).await.unwrap();
+        organizations.add_team_member(team.id, owner).await.unwrap();
+        organizations.add_team_member(team.id, viewer).await.unwrap();
+        let workflows = Arc::new(InMemoryWorkflowStore::new());
+        workflows.save(&StoredWorkflow { workflow: workflow.clone(), owner_id: owner, active: true }).await.unwrap();
+
+        let revisions = Arc::new(RevisionStore::new());
+        revisions.submit(workflow.clone(), owner).await;
+        let state = TestSuiteServiceState::new(Arc::new(TestSuiteStore::new()), revisions)
+            .with_access(WorkflowAccess::new(workflows).with_org_service(organizations));
+
+        let uri = format!("/workflows/{}/tests", workflow.id);
+        let cases = serde_json::json!([{
+            "name": "shipped order",
+            "trigger": { "order": 7 },
+            "mocks": [{ "node_id": fetch_id, "body": { "status": "shipped", "items": [{ "sku": "A1" }] } }],
+            "assertions": [
+                { "node_id": fetch_id, "path": "$.body.status", "op": "equals", "value": "shipped" },
+                { "node_id": fetch_id, "path": "$.body.items[*].sku", "op": "contains", "value": "A1" },
+                { "path": "$.state", "op": "equals", "value": "Completed" }
+            ]
+        }]);
+        let (status, _) = call(app(state.clone(), viewer, Role::Viewer), "PUT", &uri, Some(cases.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = call(app(state.clone(), stranger, Role::User), "PUT", &uri, Some(cases.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = call(app(state.clone(), owner, Role::User), "PUT", &uri, Some(cases)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["cases"][0]["assertions"][1]["op"], "contains");
+        let (status, body) = call(app(state.clone(), viewer, Role::Viewer), "GET", &uri, None).await;
+        assert_eq!((status, body["total"].clone()), (StatusCode::OK, serde_json::json!(1)));
+        let (status, _) = call(app(state.clone(), stranger, Role::User), "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let run_uri = format!("{}/run", uri);
+        let (status, _) = call(app(state.clone(), stranger, Role::User), "POST", &run_uri, None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = call(app(state.clone(), viewer, Role::Viewer), "POST", &run_uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["passed"], true, "{}", body);
+        assert_eq!(body["report"]["cases"][0]["assertions"][0]["actual"], "shipped");
+
+        // An unsaved edit that drops the node fails the suite
+        workflow.nodes.truncate(1);
+        workflow.edges.clear();
+        let (status, body) = call(app(state, owner, Role::User), "POST", &run_uri, Some(serde_json::json!({ "workflow": workflow }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["passed"], false);
+        assert_eq!(body["report"]["failed"], 1);
+    }
+}
@@ -0,0 +1,63 @@
+//! Helpers of the route tests
+
+use axum::body::Body;
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, Request, StatusCode};
+use axum::Router;
+use common::types::Role;
+use rbac_service::jwt::JwtClaims;
+use serde_json::Value as JsonValue;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+/// Claims of a token of `user` with `role` that never expires, for an
+/// `Extension` layer standing in for the auth middleware
+pub(crate) fn claims(user: Uuid, role: Role) -> JwtClaims {
+    JwtClaims {
+        sub: user,
+        role,
+        permissions: vec![],
+        exp: i64::MAX,
+        iat: 0,
+        sid: None,
+    }
+}
+
+/// A request to `uri` with `body` as JSON, if any
+pub(crate) fn request(method: &str, uri: &str, body: Option<JsonValue>) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(body.map(|body| Body::from(body.to_string())).unwrap_or_else(Body::empty))
+        .unwrap()
+}
+
+/// Send `request` and read the JSON response; `null` when it has no body
+pub(crate) async fn send(app: Router, request: Request<Body>) -> (StatusCode, HeaderMap, JsonValue) {
+    let response = app.oneshot(request).await.unwrap();
+    let (status, headers) = (response.status(), response.headers().clone());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = if body.is_empty() { JsonValue::Null } else { serde_json::from_slice(&body).unwrap() };
+    (status, headers, body)
+}
+
+/// Send a request to `uri` with `body` as JSON, if any, and read the JSON response
+pub(crate) async fn call(app: Router, method: &str, uri: &str, body: Option<JsonValue>) -> (StatusCode, JsonValue) {
+    let (status, _, body) = send(app, request(method, uri, body)).await;
+    (status, body)
+}
+
+/// [`call`] with the bearer `token` the auth middleware checks
+pub(crate) async fn call_with_token(
+    app: Router,
+    token: &str,
+    method: &str,
+    uri: &str,
+    body: Option<JsonValue>,
+) -> (StatusCode, JsonValue) {
+    let mut request = request(method, uri, body);
+    request.headers_mut().insert(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+    let (status, _, body) = send(app, request).await;
+    (status, body)
+}
@@ -1,6 +1,7 @@
+use async_trait::async_trait;
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{ConnectInfo, FromRef, Path, State},
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
@@ -14,8 +15,25 @@ use chrono::{DateTime, Utc};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use std::collections::HashMap;
-
-use rbac_service::JwtManager;
+use std::net::SocketAddr;
+
+use audit_service::AuditLogger;
+use common::error::{ErrorCode, ErrorInfo};
+use common::types::{AuditAction, AuditLog, AuditResult, ResourceType};
+use rbac_service::{AuthUser, JwtManager, Session, SessionInfo};
+
+use crate::errors::ApiError;
+use crate::request_limiter::client_ip;
+use crate::validation::{is_email, FieldErrors, Validate, ValidJson};
+
+/// Least length of a password
+const MIN_PASSWORD_LEN: usize = 6;
+/// Most characters of a user name
+const MAX_NAME_LEN: usize = 64;
+/// Users listed per page unless asked otherwise
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+/// Most users listed per page
+pub const MAX_PAGE_SIZE: u32 = 200;
 
 /// User model
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +49,12 @@ pub struct User {
     pub updated_at: DateTime<Utc>,
     pub last_login_at: Option<DateTime<Utc>>,
     pub is_active: bool,
+    /// Set when an admin reset the password; cleared by changing it
+    #[serde(default)]
+    pub must_change_password: bool,
+    /// When the account was deleted; deleted accounts are kept but not found
+    #[serde(default, skip_serializing)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 /// User response (without sensitive data)
@@ -42,6 +66,10 @@ pub struct UserResponse {
     pub role: String,
     pub avatar: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub is_active: bool,
+    /// The password was reset by an admin and has to be changed
+    pub must_change_password: bool,
 }
 
 impl From<&User> for UserResponse {
@@ -53,6 +81,9 @@ impl From<&User> for UserResponse {
             role: user.role.clone(),
             avatar: user.avatar.clone(),
             created_at: user.created_at,
+            last_login_at: user.last_login_at,
+            is_active: user.is_active,
+            must_change_password: user.must_change_password,
         }
     }
 }
@@ -62,6 +93,9 @@ impl From<&User> for UserResponse {
 pub struct LoginRequest {
     pub email: String,
     pub password: String,
+    /// Name of the signing-in device, listed with the user's sessions
+    #[serde(default)]
+    pub device_name: Option<String>,
 }
 
 /// Register request
@@ -70,6 +104,9 @@ pub struct RegisterRequest {
     pub email: String,
     pub password: String,
     pub name: String,
+    /// Name of the signing-in device, listed with the user's sessions
+    #[serde(default)]
+    pub device_name: Option<String>,
 }
 
 /// Auth response
@@ -95,210 +132,423 @@ pub struct ChangePasswordRequest {
     pub new_password: String,
 }
 
+impl Validate for LoginRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.check(!self.email.is_empty(), "email", "邮箱不能为空");
+        errors.check(!self.password.is_empty(), "password", "密码不能为空");
+        validate_device_name(self.device_name.as_deref(), errors);
+    }
+}
+
+impl Validate for RegisterRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.check(is_email(&self.email), "email", "无效的邮箱地址");
+        errors.check(self.password.chars().count() >= MIN_PASSWORD_LEN, "password", format!("密码长度至少{}位", MIN_PASSWORD_LEN));
+        validate_name(&self.name, errors);
+        validate_device_name(self.device_name.as_deref(), errors);
+    }
+}
+
+impl Validate for UpdateProfileRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        if let Some(name) = &self.name {
+            validate_name(name, errors);
+        }
+        if let Some(avatar) = &self.avatar {
+            errors.check(avatar.starts_with("https://") || avatar.starts_with("http://"), "avatar", "头像必须是 http(s) 地址");
+        }
+    }
+}
+
+impl Validate for ChangePasswordRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.check(!self.current_password.is_empty(), "current_password", "当前密码不能为空");
+        errors.check(
+            self.new_password.chars().count() >= MIN_PASSWORD_LEN,
+            "new_password",
+            format!("新密码长度至少{}位", MIN_PASSWORD_LEN),
+        );
+    }
+}
+
+fn validate_device_name(device_name: Option<&str>, errors: &mut FieldErrors) {
+    if let Some(device_name) = device_name {
+        errors.check(
+            device_name.chars().count() <= MAX_NAME_LEN,
+            "device_name",
+            format!("设备名称最多{}个字符", MAX_NAME_LEN),
+        );
+    }
+}
+
+fn validate_name(name: &str, errors: &mut FieldErrors) {
+    if name.trim().is_empty() {
+        errors.add("name", "用户名不能为空");
+    } else {
+        errors.check(name.chars().count() <= MAX_NAME_LEN, "name", format!("用户名最多{}个字符", MAX_NAME_LEN));
+    }
+}
+
+/// Failures of a [`UserStore`]
+#[derive(Debug, thiserror::Error)]
+pub enum UserStoreError {
+    #[error("Email address already registered")]
+    EmailTaken,
+
+    #[error("User storage failed: {0}")]
+    Storage(String),
+}
+
+impl ErrorInfo for UserStoreError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            UserStoreError::EmailTaken => ErrorCode::Conflict,
+            UserStoreError::Storage(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+/// Which users to list and which page of them
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UserQuery {
+    pub role: Option<String>,
+    pub is_active: Option<bool>,
+    /// Part of the email address or name, case-insensitive
+    pub search: Option<String>,
+    #[serde(default)]
+    pub offset: u32,
+    /// Users per page, [`DEFAULT_PAGE_SIZE`] when unset
+    pub limit: Option<u32>,
+}
+
+impl UserQuery {
+    /// The page size asked for, within [1, MAX_PAGE_SIZE]
+    pub fn page_size(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+
+    fn matches(&self, user: &User) -> bool {
+        let search = self.search.as_deref().map(str::to_lowercase);
+        self.role.as_ref().is_none_or(|role| &user.role == role)
+            && self.is_active.is_none_or(|active| user.is_active == active)
+            && search.is_none_or(|search| {
+                user.email.to_lowercase().contains(&search) || user.name.to_lowercase().contains(&search)
+            })
+    }
+}
+
+/// A page of users and how many match in total
+#[derive(Debug, Clone, Serialize)]
+pub struct UserPage {
+    pub users: Vec<UserResponse>,
+    pub total: u64,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+/// Durable storage of user accounts. Email addresses are unique among the
+/// users not deleted, ignoring case; deleted users are kept but no longer found.
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    async fn create_user(&self, email: String, password_hash: String, name: String) -> Result<User, UserStoreError>;
+    async fn get_user_by_email(&self, email: &str) -> Result<Option<User>, UserStoreError>;
+    async fn get_user_by_id(&self, id: Uuid) -> Result<Option<User>, UserStoreError>;
+    /// Save the profile, role, password and flags of `user`; `None`
+    /// when there is no such user
+    async fn update_user(&self, user: &User) -> Result<Option<User>, UserStoreError>;
+    async fn update_last_login(&self, id: Uuid) -> Result<(), UserStoreError>;
+    /// Soft-delete a user, freeing their email address
+    async fn delete_user(&self, id: Uuid) -> Result<bool, UserStoreError>;
+    /// Users matching `query`, newest first
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError>;
+}
+
 /// In-memory user store (for development, replace with database in production)
-#[derive(Clone)]
-pub struct UserStore {
-    users: Arc<RwLock<HashMap<Uuid, User>>>,
-    email_index: Arc<RwLock<HashMap<String, Uuid>>>,
+#[derive(Default)]
+pub struct InMemoryUserStore {
+    users: RwLock<HashMap<Uuid, User>>,
 }
 
-impl UserStore {
+impl InMemoryUserStore {
     pub fn new() -> Self {
-        Self {
-            users: Arc::new(RwLock::new(HashMap::new())),
-            email_index: Arc::new(RwLock::new(HashMap::new())),
-        }
+        Self::default()
     }
+}
 
-    pub async fn create_user(&self, email: String, password_hash: String, name: String) -> Result<User, String> {
-        let mut email_index = self.email_index.write().await;
-        
-        if email_index.contains_key(&email) {
-            return Err("邮箱已被注册".to_string());
+#[async_trait]
+impl UserStore for InMemoryUserStore {
+    async fn create_user(&self, email: String, password_hash: String, name: String) -> Result<User, UserStoreError> {
+        let mut users = self.users.write().await;
+        let email = email.trim().to_lowercase();
+        if users.values().any(|user| user.deleted_at.is_none() && user.email == email) {
+            return Err(UserStoreError::EmailTaken);
         }
 
+        let now = Utc::now();
         let user = User {
             id: Uuid::new_v4(),
-            email: email.clone(),
+            email,
             password_hash,
             name,
             role: "user".to_string(),
             avatar: None,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
+            created_at: now,
+            updated_at: now,
             last_login_at: None,
             is_active: true,
+            must_change_password: false,
+            deleted_at: None,
         };
-
-        email_index.insert(email, user.id);
-        self.users.write().await.insert(user.id, user.clone());
-
+        users.insert(user.id, user.clone());
         Ok(user)
     }
 
-    pub async fn get_user_by_email(&self, email: &str) -> Option<User> {
-        let email_index = self.email_index.read().await;
-        if let Some(user_id) = email_index.get(email) {
-            let users = self.users.read().await;
-            return users.get(user_id).cloned();
-        }
-        None
+    async fn get_user_by_email(&self, email: &str) -> Result<Option<User>, UserStoreError> {
+        let email = email.trim().to_lowercase();
+        let users = self.users.read().await;
+        Ok(users.values().find(|user| user.deleted_at.is_none() && user.email == email).cloned())
     }
 
-    pub async fn get_user_by_id(&self, id: Uuid) -> Option<User> {
+    async fn get_user_by_id(&self, id: Uuid) -> Result<Option<User>, UserStoreError> {
         let users = self.users.read().await;
-        users.get(&id).cloned()
+        Ok(users.get(&id).filter(|user| user.deleted_at.is_none()).cloned())
     }
 
-    pub async fn update_user(&self, id: Uuid, updates: impl FnOnce(&mut User)) -> Option<User> {
+    async fn update_user(&self, user: &User) -> Result<Option<User>, UserStoreError> {
         let mut users = self.users.write().await;
-        if let Some(user) = users.get_mut(&id) {
-            updates(user);
-            user.updated_at = Utc::now();
-            return Some(user.clone());
+        let Some(stored) = users.get_mut(&user.id).filter(|stored| stored.deleted_at.is_none()) else {
+            return Ok(None);
+        };
+        stored.name = user.name.clone();
+        stored.avatar = user.avatar.clone();
+        stored.role = user.role.clone();
+        stored.password_hash = user.password_hash.clone();
+        stored.is_active = user.is_active;
+        stored.must_change_password = user.must_change_password;
+        stored.updated_at = Utc::now();
+        Ok(Some(stored.clone()))
+    }
+
+    async fn update_last_login(&self, id: Uuid) -> Result<(), UserStoreError> {
+        if let Some(user) = self.users.write().await.get_mut(&id) {
+            user.last_login_at = Some(Utc::now());
         }
-        None
+        Ok(())
     }
 
-    pub async fn update_last_login(&self, id: Uuid) {
+    async fn delete_user(&self, id: Uuid) -> Result<bool, UserStoreError> {
         let mut users = self.users.write().await;
-        if let Some(user) = users.get_mut(&id) {
-            user.last_login_at = Some(Utc::now());
+        match users.get_mut(&id).filter(|user| user.deleted_at.is_none()) {
+            Some(user) => {
+                user.deleted_at = Some(Utc::now());
+                user.is_active = false;
+                Ok(true)
+            }
+            None => Ok(false),
         }
     }
+
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let users = self.users.read().await;
+        let mut matching: Vec<&User> = users
+            .values()
+            .filter(|user| user.deleted_at.is_none() && query.matches(user))
+            .collect();
+        matching.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(a.id.cmp(&b.id)));
+
+        let limit = query.page_size();
+        Ok(UserPage {
+            total: matching.len() as u64,
+            users: matching
+                .into_iter()
+                .skip(query.offset as usize)
+                .take(limit as usize)
+                .map(UserResponse::from)
+                .collect(),
+            offset: query.offset,
+            limit,
+        })
+    }
 }
 
 /// User service state
 #[derive(Clone)]
 pub struct UserServiceState {
-    pub store: UserStore,
+    pub store: Arc<dyn UserStore>,
     pub jwt_manager: Arc<JwtManager>,
+    audit: Option<Arc<AuditLogger>>,
+    trust_forwarded_for: bool,
 }
 
 impl UserServiceState {
     pub fn new(jwt_manager: Arc<JwtManager>) -> Self {
         Self {
-            store: UserStore::new(),
+            store: Arc::new(InMemoryUserStore::new()),
             jwt_manager,
+            audit: None,
+            trust_forwarded_for: false,
         }
     }
 
-    fn hash_password(&self, password: &str) -> Result<String, String> {
-        let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-        
-        argon2
-            .hash_password(password.as_bytes(), &salt)
-            .map(|h| h.to_string())
-            .map_err(|_| "密码加密失败".to_string())
+    /// Keep accounts in `store` instead of in memory
+    pub fn with_store(mut self, store: Arc<dyn UserStore>) -> Self {
+        self.store = store;
+        self
     }
 
-    fn verify_password(&self, password: &str, hash: &str) -> bool {
-        if let Ok(parsed_hash) = PasswordHash::new(hash) {
-            Argon2::default()
-                .verify_password(password.as_bytes(), &parsed_hash)
-                .is_ok()
-        } else {
-            false
-        }
+    /// Record every login attempt in the audit log
+    pub fn with_audit_logger(mut self, audit: Arc<AuditLogger>) -> Self {
+        self.audit = Some(audit);
+        self
     }
-}
 
-/// Register handler
-pub async fn register_handler(
-    State(state): State<UserServiceState>,
-    Json(req): Json<RegisterRequest>,
-) -> impl IntoResponse {
-    // Validate input
-    if req.email.is_empty() || !req.email.contains('@') {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(AuthResponse {
-                success: false,
-                token: None,
-                user: None,
-                message: Some("无效的邮箱地址".to_string()),
-            }),
-        );
+    /// Read the client IP of sessions from `X-Forwarded-For` as the request
+    /// limiter does; only enable behind a proxy that appends it
+    pub fn with_trusted_forwarded_for(mut self, trust_forwarded_for: bool) -> Self {
+        self.trust_forwarded_for = trust_forwarded_for;
+        self
     }
 
-    if req.password.len() < 6 {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(AuthResponse {
-                success: false,
-                token: None,
-                user: None,
-                message: Some("密码长度至少6位".to_string()),
-            }),
+    /// Audit a login attempt; `user_id` is `None` when no account has the email
+    fn record_login(&self, user_id: Option<Uuid>, email: &str, info: &SessionInfo, result: AuditResult) {
+        let Some(audit) = &self.audit else {
+            return;
+        };
+        let user_id = user_id.unwrap_or_default();
+        let mut log = AuditLog::new(
+            user_id,
+            AuditAction::Login,
+            ResourceType::User,
+            user_id,
+            info.ip_address.clone(),
+            info.user_agent.clone(),
+            result,
         );
+        log.details = serde_json::json!({ "email": email });
+        if let Err(e) = audit.log(log) {
+            tracing::error!("Failed to record login attempt: {}", e);
+        }
     }
 
-    if req.name.is_empty() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(AuthResponse {
-                success: false,
-                token: None,
-                user: None,
-                message: Some("用户名不能为空".to_string()),
-            }),
-        );
+    fn hash_password(&self, password: &str) -> Result<String, ApiError> {
+        hash_password(password)
     }
 
-    // Hash password
-    let password_hash = match state.hash_password(&req.password) {
-        Ok(hash) => hash,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AuthResponse {
-                    success: false,
-                    token: None,
-                    user: None,
-                    message: Some(e),
-                }),
-            );
-        }
-    };
+    fn verify_password(&self, password: &str, hash: &str) -> bool {
+        verify_password(password, hash)
+    }
 
-    // Create user
-    let user = match state.store.create_user(req.email, password_hash, req.name).await {
-        Ok(user) => user,
-        Err(e) => {
-            return (
-                StatusCode::CONFLICT,
-                Json(AuthResponse {
-                    success: false,
-                    token: None,
-                    user: None,
-                    message: Some(e),
-                }),
-            );
-        }
-    };
+    /// Sign `user` in: start a session of the device and generate its token
+    async fn start_session(&self, user: &User, info: SessionInfo) -> Result<String, ApiError> {
+        let role = match user.role.as_str() {
+            "admin" => common::types::Role::Admin,
+            "manager" => common::types::Role::Manager,
+            "viewer" => common::types::Role::Viewer,
+            "inspector" => common::types::Role::Inspector,
+            _ => common::types::Role::User,
+        };
+        self.jwt_manager
+            .start_session(user.id, role, vec![], info)
+            .await
+            .map(|(token, _)| token)
+            .map_err(|e| ApiError::internal("生成令牌失败", e))
+    }
 
-    // Generate token
-    let token = match state.jwt_manager.generate_token(
-        user.id,
-        common::types::Role::User,
-        vec![],
-    ) {
-        Ok(token) => token,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AuthResponse {
-                    success: false,
-                    token: None,
-                    user: None,
-                    message: Some("生成令牌失败".to_string()),
-                }),
-            );
-        }
-    };
+    /// The caller's account
+    async fn current_user(&self, id: Uuid) -> Result<User, ApiError> {
+        self.store
+            .get_user_by_id(id)
+            .await
+            .map_err(|e| ApiError::internal("读取用户失败", e))?
+            .ok_or_else(|| ApiError::not_found("用户不存在"))
+    }
+
+    async fn save_user(&self, user: &User) -> Result<User, ApiError> {
+        self.store
+            .update_user(user)
+            .await
+            .map_err(|e| ApiError::internal("保存用户失败", e))?
+            .ok_or_else(|| ApiError::not_found("用户不存在"))
+    }
+
+}
 
-    (
+/// Lets [`AuthUser`] validate bearer tokens on the user routes
+impl FromRef<UserServiceState> for Arc<JwtManager> {
+    fn from_ref(state: &UserServiceState) -> Self {
+        state.jwt_manager.clone()
+    }
+}
+
+/// Hash a password with Argon2 and a random salt
+pub(crate) fn hash_password(password: &str) -> Result<String, ApiError> {
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::default();
+
+    argon2
+        .hash_password(password.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| ApiError::internal("密码加密失败", e))
+}
+
+/// Check a password against a stored Argon2 hash
+pub(crate) fn verify_password(password: &str, hash: &str) -> bool {
+    if let Ok(parsed_hash) = PasswordHash::new(hash) {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_ok()
+    } else {
+        false
+    }
+}
+
+/// Where a request signs in from: the client IP the request limiter counts it under
+fn session_info(
+    state: &UserServiceState,
+    headers: &HeaderMap,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    device_name: Option<String>,
+) -> SessionInfo {
+    let ip_address = client_ip(headers, peer.map(|ConnectInfo(addr)| addr.ip()), state.trust_forwarded_for)
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    SessionInfo {
+        device_name: device_name.map(|name| name.trim().to_string()).filter(|name| !name.is_empty()),
+        ip_address,
+        user_agent: headers
+            .get("user-agent")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+            .unwrap_or_else(|| "unknown".to_string()),
+    }
+}
+
+/// A session of the caller, marking the one of the calling token
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    #[serde(flatten)]
+    pub session: Session,
+    pub current: bool,
+}
+
+/// Register handler
+pub async fn register_handler(
+    State(state): State<UserServiceState>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    ValidJson(req): ValidJson<RegisterRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let password_hash = state.hash_password(&req.password)?;
+    let user = state.store
+        .create_user(req.email, password_hash, req.name.trim().to_string())
+        .await
+        .map_err(|e| match e {
+            UserStoreError::EmailTaken => ApiError::conflict("邮箱已被注册"),
+            e => ApiError::internal("注册失败", e),
+        })?;
+    let token = state.start_session(&user, session_info(&state, &headers, peer, req.device_name)).await?;
+
+    Ok((
         StatusCode::CREATED,
         Json(AuthResponse {
             success: true,
@@ -306,83 +556,41 @@ pub async fn register_handler(
             user: Some(UserResponse::from(&user)),
             message: Some("注册成功".to_string()),
         }),
-    )
+    ))
 }
 
 /// Login handler
 pub async fn login_handler(
     State(state): State<UserServiceState>,
-    Json(req): Json<LoginRequest>,
-) -> impl IntoResponse {
-    // Find user by email
-    let user = match state.store.get_user_by_email(&req.email).await {
-        Some(user) => user,
-        None => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(AuthResponse {
-                    success: false,
-                    token: None,
-                    user: None,
-                    message: Some("邮箱或密码错误".to_string()),
-                }),
-            );
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    ValidJson(req): ValidJson<LoginRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let info = session_info(&state, &headers, peer, req.device_name);
+    let account = state.store
+        .get_user_by_email(&req.email)
+        .await
+        .map_err(|e| ApiError::internal("登录失败", e))?;
+    let user = match account {
+        Some(user) if state.verify_password(&req.password, &user.password_hash) => user,
+        account => {
+            let failure = AuditResult::Failure("invalid credentials".to_string());
+            state.record_login(account.map(|user| user.id), &req.email, &info, failure);
+            return Err(ApiError::unauthenticated("邮箱或密码错误"));
         }
     };
-
-    // Verify password
-    if !state.verify_password(&req.password, &user.password_hash) {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(AuthResponse {
-                success: false,
-                token: None,
-                user: None,
-                message: Some("邮箱或密码错误".to_string()),
-            }),
-        );
-    }
-
-    // Check if user is active
     if !user.is_active {
-        return (
-            StatusCode::FORBIDDEN,
-            Json(AuthResponse {
-                success: false,
-                token: None,
-                user: None,
-                message: Some("账户已被禁用".to_string()),
-            }),
-        );
+        state.record_login(Some(user.id), &req.email, &info, AuditResult::Denied);
+        return Err(ApiError::forbidden("账户已被禁用"));
     }
 
-    // Update last login
-    state.store.update_last_login(user.id).await;
-
-    // Generate token
-    let role = match user.role.as_str() {
-        "admin" => common::types::Role::Admin,
-        "manager" => common::types::Role::Manager,
-        "viewer" => common::types::Role::Viewer,
-        _ => common::types::Role::User,
-    };
-
-    let token = match state.jwt_manager.generate_token(user.id, role, vec![]) {
-        Ok(token) => token,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AuthResponse {
-                    success: false,
-                    token: None,
-                    user: None,
-                    message: Some("生成令牌失败".to_string()),
-                }),
-            );
-        }
-    };
+    if let Err(e) = state.store.update_last_login(user.id).await {
+        tracing::warn!("Failed to record login of user {}: {}", user.id, e);
+    }
+    state.record_login(Some(user.id), &req.email, &info, AuditResult::Success);
+    let token = state.start_session(&user, info).await?;
 
-    (
+    Ok((
         StatusCode::OK,
         Json(AuthResponse {
             success: true,
@@ -390,261 +598,160 @@ pub async fn login_handler(
             user: Some(UserResponse::from(&user)),
             message: Some("登录成功".to_string()),
         }),
-    )
+    ))
 }
 
 /// Get current user handler
 pub async fn get_me_handler(
     State(state): State<UserServiceState>,
-    headers: axum::http::HeaderMap,
-) -> impl IntoResponse {
-    // Extract token from Authorization header
-    let token = match headers.get("Authorization") {
-        Some(value) => {
-            let value = value.to_str().unwrap_or("");
-            if value.starts_with("Bearer ") {
-                &value[7..]
-            } else {
-                return (
-                    StatusCode::UNAUTHORIZED,
-                    Json(serde_json::json!({
-                        "success": false,
-                        "message": "无效的认证头"
-                    })),
-                );
-            }
-        }
-        None => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "success": false,
-                    "message": "未提供认证令牌"
-                })),
-            );
-        }
-    };
+    caller: AuthUser,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = state.current_user(caller.sub).await?;
 
-    // Validate token
-    let claims = match state.jwt_manager.validate_token(token) {
-        Ok(claims) => claims,
-        Err(_) => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "success": false,
-                    "message": "令牌无效或已过期"
-                })),
-            );
-        }
-    };
-
-    // Get user
-    let user = match state.store.get_user_by_id(claims.sub).await {
-        Some(user) => user,
-        None => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({
-                    "success": false,
-                    "message": "用户不存在"
-                })),
-            );
-        }
-    };
-
-    (
+    Ok((
         StatusCode::OK,
         Json(serde_json::json!({
             "success": true,
             "user": UserResponse::from(&user)
         })),
-    )
+    ))
 }
 
 /// Update profile handler
 pub async fn update_profile_handler(
     State(state): State<UserServiceState>,
-    headers: axum::http::HeaderMap,
-    Json(req): Json<UpdateProfileRequest>,
-) -> impl IntoResponse {
-    // Extract and validate token
-    let token = match headers.get("Authorization") {
-        Some(value) => {
-            let value = value.to_str().unwrap_or("");
-            if value.starts_with("Bearer ") {
-                &value[7..]
-            } else {
-                return (
-                    StatusCode::UNAUTHORIZED,
-                    Json(serde_json::json!({
-                        "success": false,
-                        "message": "无效的认证头"
-                    })),
-                );
-            }
-        }
-        None => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "success": false,
-                    "message": "未提供认证令牌"
-                })),
-            );
-        }
-    };
-
-    let claims = match state.jwt_manager.validate_token(token) {
-        Ok(claims) => claims,
-        Err(_) => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "success": false,
-                    "message": "令牌无效或已过期"
-                })),
-            );
-        }
-    };
-
-    // Update user
-    let user = match state.store.update_user(claims.sub, |user| {
-        if let Some(name) = &req.name {
-            user.name = name.clone();
-        }
-        if let Some(avatar) = &req.avatar {
-            user.avatar = Some(avatar.clone());
-        }
-    }).await {
-        Some(user) => user,
-        None => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({
-                    "success": false,
-                    "message": "用户不存在"
-                })),
-            );
-        }
-    };
+    caller: AuthUser,
+    ValidJson(req): ValidJson<UpdateProfileRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut user = state.current_user(caller.sub).await?;
+    if let Some(name) = &req.name {
+        user.name = name.trim().to_string();
+    }
+    if let Some(avatar) = req.avatar {
+        user.avatar = Some(avatar);
+    }
+    let user = state.save_user(&user).await?;
 
-    (
+    Ok((
         StatusCode::OK,
         Json(serde_json::json!({
             "success": true,
             "user": UserResponse::from(&user),
             "message": "更新成功"
         })),
-    )
+    ))
 }
 
 /// Change password handler
 pub async fn change_password_handler(
     State(state): State<UserServiceState>,
-    headers: axum::http::HeaderMap,
-    Json(req): Json<ChangePasswordRequest>,
-) -> impl IntoResponse {
-    // Extract and validate token
-    let token = match headers.get("Authorization") {
-        Some(value) => {
-            let value = value.to_str().unwrap_or("");
-            if value.starts_with("Bearer ") {
-                &value[7..]
-            } else {
-                return (
-                    StatusCode::UNAUTHORIZED,
-                    Json(serde_json::json!({
-                        "success": false,
-                        "message": "无效的认证头"
-                    })),
-                );
-            }
-        }
-        None => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "success": false,
-                    "message": "未提供认证令牌"
-                })),
-            );
-        }
-    };
-
-    let claims = match state.jwt_manager.validate_token(token) {
-        Ok(claims) => claims,
-        Err(_) => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "success": false,
-                    "message": "令牌无效或已过期"
-                })),
-            );
-        }
-    };
+    caller: AuthUser,
+    ValidJson(req): ValidJson<ChangePasswordRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut user = state.current_user(caller.sub).await?;
 
-    // Get current user
-    let user = match state.store.get_user_by_id(claims.sub).await {
-        Some(user) => user,
-        None => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({
-                    "success": false,
-                    "message": "用户不存在"
-                })),
-            );
-        }
-    };
-
-    // Verify current password
     if !state.verify_password(&req.current_password, &user.password_hash) {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "success": false,
-                "message": "当前密码错误"
-            })),
-        );
+        let mut errors = FieldErrors::default();
+        errors.add("current_password", "当前密码错误");
+        errors.into_result()?;
     }
 
-    // Validate new password
-    if req.new_password.len() < 6 {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "success": false,
-                "message": "新密码长度至少6位"
-            })),
-        );
-    }
+    user.password_hash = state.hash_password(&req.new_password)?;
+    user.must_change_password = false;
+    state.save_user(&user).await?;
 
-    // Hash new password
-    let new_hash = match state.hash_password(&req.new_password) {
-        Ok(hash) => hash,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "success": false,
-                    "message": "密码加密失败"
-                })),
-            );
-        }
-    };
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "message": "密码修改成功"
+        })),
+    ))
+}
 
-    // Update password
-    state.store.update_user(claims.sub, |user| {
-        user.password_hash = new_hash;
-    }).await;
+/// List sessions handler: the caller's signed-in devices, marking the one of the calling token
+pub async fn list_sessions_handler(
+    State(state): State<UserServiceState>,
+    caller: AuthUser,
+) -> Result<impl IntoResponse, ApiError> {
+    let sessions: Vec<SessionResponse> = state.jwt_manager
+        .sessions()
+        .list(caller.sub)
+        .await
+        .map_err(|e| ApiError::from_error("读取会话失败", &e))?
+        .into_iter()
+        .map(|session| SessionResponse { current: caller.sid == Some(session.id), session })
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "sessions": sessions
+        })),
+    ))
+}
+
+/// Revoke session handler: tokens of the session stop working at once, including the calling one
+pub async fn revoke_session_handler(
+    State(state): State<UserServiceState>,
+    caller: AuthUser,
+    Path(session_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let revoked = state.jwt_manager
+        .sessions()
+        .revoke(caller.sub, session_id)
+        .await
+        .map_err(|e| ApiError::from_error("注销会话失败", &e))?;
+    if !revoked {
+        return Err(ApiError::not_found("会话不存在"));
+    }
 
-    (
+    Ok((
         StatusCode::OK,
         Json(serde_json::json!({
             "success": true,
-            "message": "密码修改成功"
+            "message": "会话已注销"
         })),
-    )
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_user_store() {
+        let store = InMemoryUserStore::new();
+        let alice = store.create_user("Alice@Example.com".to_string(), "hash".to_string(), "Alice".to_string()).await.unwrap();
+        assert_eq!(alice.email, "alice@example.com");
+        assert!(matches!(
+            store.create_user("alice@example.COM".to_string(), "hash".to_string(), "Again".to_string()).await,
+            Err(UserStoreError::EmailTaken)
+        ));
+        let bob = store.create_user("bob@example.com".to_string(), "hash".to_string(), "Bob".to_string()).await.unwrap();
+        let mut admin = store.create_user("root@example.com".to_string(), "hash".to_string(), "Root".to_string()).await.unwrap();
+        admin.role = "admin".to_string();
+        store.update_user(&admin).await.unwrap().unwrap();
+
+        let page = store.list_users(&UserQuery { limit: Some(2), ..Default::default() }).await.unwrap();
+        assert_eq!((page.total, page.users.len()), (3, 2));
+        let page = store.list_users(&UserQuery { offset: 2, limit: Some(2), ..Default::default() }).await.unwrap();
+        assert_eq!(page.users.len(), 1);
+        let query = UserQuery { role: Some("user".to_string()), search: Some("ALI".to_string()), ..Default::default() };
+        let page = store.list_users(&query).await.unwrap();
+        assert_eq!(page.users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![alice.id]);
+
+        // Deleted users are kept but not found, and free their address
+        assert!(store.delete_user(alice.id).await.unwrap());
+        assert!(!store.delete_user(alice.id).await.unwrap());
+        assert!(store.get_user_by_id(alice.id).await.unwrap().is_none());
+        assert!(store.get_user_by_email("alice@example.com").await.unwrap().is_none());
+        assert!(store.update_user(&alice).await.unwrap().is_none());
+        assert_eq!(store.list_users(&UserQuery::default()).await.unwrap().total, 2);
+        let again = store.create_user("alice@example.com".to_string(), "hash".to_string(), "Alice".to_string()).await.unwrap();
+        assert_ne!(again.id, alice.id);
+        assert_eq!(store.get_user_by_email("ALICE@example.com").await.unwrap().unwrap().id, again.id);
+        assert!(store.get_user_by_id(bob.id).await.unwrap().is_some());
+    }
 }
@@ -0,0 +1,202 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::user_service::{User, UserPage, UserQuery, UserResponse, UserStore, UserStoreError};
+
+/// Columns of a [`User`], in the order [`decode`] reads them
+const USER_COLUMNS: &str =
+    "id, email, password_hash, full_name, role, avatar, created_at, updated_at, last_login_at, is_active, must_change_password, deleted_at";
+
+/// Users not deleted matching role `$1`, active flag `$2` and LIKE pattern `$3`
+const USER_FILTER: &str = r#"
+    deleted_at IS NULL
+    AND ($1::text IS NULL OR role = $1)
+    AND ($2::boolean IS NULL OR COALESCE(is_active, true) = $2)
+    AND ($3::text IS NULL OR lower(email) LIKE $3 OR lower(full_name) LIKE $3)
+"#;
+
+/// Postgres-backed user store (see `migrations/001_initial_schema.sql`,
+/// `migrations/008_user_store.sql` and `migrations/009_admin_users.sql`)
+pub struct PgUserStore {
+    pool: PgPool,
+}
+
+impl PgUserStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UserStore for PgUserStore {
+    async fn create_user(&self, email: String, password_hash: String, name: String) -> Result<User, UserStoreError> {
+        let now = Utc::now();
+        let user = User {
+            id: Uuid::new_v4(),
+            email: email.trim().to_lowercase(),
+            password_hash,
+            name,
+            role: "user".to_string(),
+            avatar: None,
+            created_at: now,
+            updated_at: now,
+            last_login_at: None,
+            is_active: true,
+            must_change_password: false,
+            deleted_at: None,
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO users (id, email, password_hash, full_name, role, created_at, updated_at, is_active)
+            VALUES ($1, $2, $3, $4, $5, $6, $6, true)
+            "#,
+        )
+        .bind(user.id)
+        .bind(&user.email)
+        .bind(&user.password_hash)
+        .bind(&user.name)
+        .bind(&user.role)
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => UserStoreError::EmailTaken,
+            _ => storage_error(e),
+        })?;
+        Ok(user)
+    }
+
+    async fn get_user_by_email(&self, email: &str) -> Result<Option<User>, UserStoreError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM users WHERE lower(email) = $1 AND deleted_at IS NULL",
+            USER_COLUMNS
+        ))
+        .bind(email.trim().to_lowercase())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        row.map(|row| decode(&row)).transpose()
+    }
+
+    async fn get_user_by_id(&self, id: Uuid) -> Result<Option<User>, UserStoreError> {
+        let row = sqlx::query(&format!("SELECT {} FROM users WHERE id = $1 AND deleted_at IS NULL", USER_COLUMNS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        row.map(|row| decode(&row)).transpose()
+    }
+
+    async fn update_user(&self, user: &User) -> Result<Option<User>, UserStoreError> {
+        let row = sqlx::query(&format!(
+            r#"
+            UPDATE users
+            SET full_name = $2, avatar = $3, role = $4, password_hash = $5, is_active = $6,
+                must_change_password = $7, updated_at = $8
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING {}
+            "#,
+            USER_COLUMNS
+        ))
+        .bind(user.id)
+        .bind(&user.name)
+        .bind(&user.avatar)
+        .bind(&user.role)
+        .bind(&user.password_hash)
+        .bind(user.is_active)
+        .bind(user.must_change_password)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        row.map(|row| decode(&row)).transpose()
+    }
+
+    async fn update_last_login(&self, id: Uuid) -> Result<(), UserStoreError> {
+        sqlx::query("UPDATE users SET last_login_at = $2 WHERE id = $1")
+            .bind(id)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn delete_user(&self, id: Uuid) -> Result<bool, UserStoreError> {
+        let deleted = sqlx::query(
+            "UPDATE users SET deleted_at = $2, is_active = false WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(deleted.rows_affected() > 0)
+    }
+
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let limit = query.page_size();
+        let search = query.search.as_ref().map(|search| format!("%{}%", escape_like(&search.to_lowercase())));
+        let rows = sqlx::query(&format!(
+            "SELECT {}, COUNT(*) OVER () AS total FROM users WHERE {} ORDER BY created_at DESC, id OFFSET $4 LIMIT $5",
+            USER_COLUMNS, USER_FILTER
+        ))
+        .bind(&query.role)
+        .bind(query.is_active)
+        .bind(&search)
+        .bind(query.offset as i64)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(storage_error)?;
+
+        let total = match rows.first() {
+            Some(row) => row.try_get::<i64, _>("total").map_err(storage_error)? as u64,
+            // Past the last page the window has no rows to count
+            None if query.offset > 0 => {
+                let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM users WHERE {}", USER_FILTER))
+                    .bind(&query.role)
+                    .bind(query.is_active)
+                    .bind(&search)
+                    .fetch_one(&self.pool)
+                    .await
+                    .map_err(storage_error)?;
+                total as u64
+            }
+            None => 0,
+        };
+        let users = rows
+            .iter()
+            .map(|row| decode(row).map(|user| UserResponse::from(&user)))
+            .collect::<Result<_, _>>()?;
+        Ok(UserPage { users, total, offset: query.offset, limit })
+    }
+}
+
+/// Escape the wildcards of a LIKE pattern
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+fn decode(row: &sqlx::postgres::PgRow) -> Result<User, UserStoreError> {
+    Ok(User {
+        id: row.try_get("id").map_err(storage_error)?,
+        email: row.try_get("email").map_err(storage_error)?,
+        password_hash: row.try_get("password_hash").map_err(storage_error)?,
+        name: row.try_get::<Option<String>, _>("full_name").map_err(storage_error)?.unwrap_or_default(),
+        role: row.try_get("role").map_err(storage_error)?,
+        avatar: row.try_get("avatar").map_err(storage_error)?,
+        created_at: row.try_get::<Option<_>, _>("created_at").map_err(storage_error)?.unwrap_or_else(Utc::now),
+        updated_at: row.try_get::<Option<_>, _>("updated_at").map_err(storage_error)?.unwrap_or_else(Utc::now),
+        last_login_at: row.try_get("last_login_at").map_err(storage_error)?,
+        is_active: row.try_get::<Option<bool>, _>("is_active").map_err(storage_error)?.unwrap_or(true),
+        must_change_password: row.try_get("must_change_password").map_err(storage_error)?,
+        deleted_at: row.try_get("deleted_at").map_err(storage_error)?,
+    })
+}
+
+fn storage_error(e: sqlx::Error) -> UserStoreError {
+    UserStoreError::Storage(e.to_string())
+}
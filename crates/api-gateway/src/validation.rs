@@ -0,0 +1,167 @@
+//! Validation of JSON request bodies.
+//!
+//! Handlers take [`ValidJson<T>`] instead of `Json<T>`: a body that is not
+//! valid JSON for `T` is answered with 400 (422 for a missing or mistyped
+//! field), one failing `T`'s [`Validate`] rules with 422 and every failed
+//! field in `field_errors`.
+
+use axum::async_trait;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{FromRequest, Request};
+use axum::Json;
+use serde::de::DeserializeOwned;
+
+use crate::errors::{ApiError, FieldError};
+
+/// Field rules of a request body
+pub trait Validate {
+    /// Record every field breaking a rule
+    fn validate(&self, errors: &mut FieldErrors);
+}
+
+/// Free-form bodies are checked by their handlers
+impl Validate for serde_json::Value {
+    fn validate(&self, _errors: &mut FieldErrors) {}
+}
+
+/// The fields of a request that failed validation
+#[derive(Debug, Default)]
+pub struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Record `message` for `field` unless `valid`
+    pub fn check(&mut self, valid: bool, field: &str, message: impl Into<String>) {
+        if !valid {
+            self.add(field, message);
+        }
+    }
+
+    pub fn into_result(self) -> Result<(), ApiError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::validation(self.0))
+        }
+    }
+}
+
+/// Validate `value`, failing with all of its invalid fields
+pub fn validate<T: Validate>(value: &T) -> Result<(), ApiError> {
+    let mut errors = FieldErrors::default();
+    value.validate(&mut errors);
+    errors.into_result()
+}
+
+/// Whether `email` looks like an address: something, `@`, a dotted domain
+pub fn is_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.split('.').count() >= 2
+                && domain.split('.').all(|label| !label.is_empty())
+                && !email.chars().any(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+/// A JSON request body that passed its [`Validate`] rules
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        validate(&value)?;
+        Ok(Self(value))
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError::invalid_input(format!("请求体无效: {}", rejection.body_text())).with_status(rejection.status())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use axum::routing::post;
+    use axum::Router;
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    #[derive(Deserialize)]
+    struct Signup {
+        email: String,
+        age: u32,
+    }
+
+    impl Validate for Signup {
+        fn validate(&self, errors: &mut FieldErrors) {
+            errors.check(is_email(&self.email), "email", "无效的邮箱地址");
+            errors.check(self.age >= 18, "age", "年龄至少18岁");
+        }
+    }
+
+    async fn signup(ValidJson(signup): ValidJson<Signup>) -> impl IntoResponse {
+        signup.email
+    }
+
+    async fn post_json(body: &str) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = Router::new().route("/", post(signup)).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_valid_json_rejections() {
+        let (status, _) = post_json(r#"{"email": "a@example.com", "age": 30}"#).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = post_json(r#"{"email": "nobody", "age": 12}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "INVALID_INPUT");
+        let fields: Vec<_> = body["field_errors"].as_array().unwrap().iter().map(|e| e["field"].clone()).collect();
+        assert_eq!(fields, vec!["email", "age"]);
+
+        let (status, body) = post_json(r#"{"email": "#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "INVALID_INPUT");
+        let (status, _) = post_json(r#"{"email": "a@example.com"}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn test_is_email() {
+        assert!(is_email("ops@flowvex.io"));
+        for invalid in ["", "ops", "@flowvex.io", "ops@flowvex", "ops@@flowvex.io", "ops@.io", "o ps@flowvex.io"] {
+            assert!(!is_email(invalid), "{}", invalid);
+        }
+    }
+}
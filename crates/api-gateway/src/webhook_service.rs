@@ -0,0 +1,330 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use common::error::WorkflowError;
+use common::types::RateLimitConfig;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::scheduler::ScheduleType;
+use workflow_engine::WorkflowScheduler;
+
+use crate::errors::error_envelope;
+use crate::rate_limiter::RateLimiter;
+use crate::signatures::{self, SignatureError, DEFAULT_TOLERANCE_SECS, SIGNATURE_HEADER};
+
+/// Headers not passed on to the workflow
+const HIDDEN_HEADERS: &[&str] = &["authorization", "cookie", SIGNATURE_HEADER];
+
+/// Webhook service state
+#[derive(Clone)]
+pub struct WebhookServiceState {
+    pub scheduler: Arc<WorkflowScheduler>,
+    rate_limiter: Arc<RateLimiter>,
+    /// Limit applied to workflows without their own
+    default_limit: RateLimitConfig,
+}
+
+impl WebhookServiceState {
+    pub fn new(scheduler: Arc<WorkflowScheduler>) -> Self {
+        Self {
+            scheduler,
+            rate_limiter: Arc::new(RateLimiter::new()),
+            default_limit: RateLimitConfig::default(),
+        }
+    }
+
+    pub fn with_default_limit(mut self, limit: RateLimitConfig) -> Self {
+        self.default_limit = limit;
+        self
+    }
+
+    /// Set the webhook rate limit of one workflow
+    pub async fn configure_limit(&self, workflow_id: Uuid, limit: RateLimitConfig) {
+        self.rate_limiter.configure(limit_key(workflow_id), limit).await;
+    }
+
+    async fn check_limit(&self, workflow_id: Uuid) -> bool {
+        let key = limit_key(workflow_id);
+        if self.rate_limiter.get_config(&key).await.is_none() {
+            self.rate_limiter.configure(key.clone(), self.default_limit.clone()).await;
+        }
+        self.rate_limiter.check_limit(&key).await.is_ok()
+    }
+}
+
+fn limit_key(workflow_id: Uuid) -> String {
+    format!("webhook:{}", workflow_id)
+}
+
+/// Sample signature checked by the debug endpoint
+#[derive(Debug, Deserialize)]
+pub struct VerifySignatureRequest {
+    pub secret: String,
+    /// The raw request body that was signed
+    pub payload: String,
+    /// Value of the signature header; omitted to only compute the expected one
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// Unix time to check the signature against, defaults to now
+    #[serde(default)]
+    pub now: Option<i64>,
+    #[serde(default)]
+    pub tolerance_secs: Option<i64>,
+    /// Check legacy body-only signatures as a webhook accepting them would
+    #[serde(default)]
+    pub accept_legacy: bool,
+}
+
+/// 接收 Webhook 并触发工作流。配置了密钥时校验签名（`t=时间戳,v1=签名`，
+/// 超出容忍时间的请求视为重放；旧的 `sha256=` 请求体签名仅在 Webhook 开启兼容时接受），并按工作流限流
+pub async fn receive_webhook(
+    State(state): State<WebhookServiceState>,
+    Path(workflow_id): Path<Uuid>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let (secret, accept_legacy) = match state.scheduler.get_schedule(workflow_id).await {
+        Some(config) if config.enabled => match config.schedule_type {
+            ScheduleType::Webhook { secret, accept_legacy_signatures, .. } => (secret, accept_legacy_signatures),
+            _ => return error(StatusCode::NOT_FOUND, format!("工作流未配置 Webhook: {}", workflow_id)),
+        },
+        Some(_) => return error(StatusCode::NOT_FOUND, format!("Webhook 已停用: {}", workflow_id)),
+        None => return error(StatusCode::NOT_FOUND, format!("工作流未配置 Webhook: {}", workflow_id)),
+    };
+
+    if let Some(secret) = secret {
+        let Some(signature) = headers.get(SIGNATURE_HEADER).and_then(|v| v.to_str().ok()) else {
+            return error(StatusCode::UNAUTHORIZED, "缺少 Webhook 签名".to_string());
+        };
+        let now = chrono::Utc::now().timestamp();
+        if let Err(e) = signatures::verify(&secret, signature, &body, now, DEFAULT_TOLERANCE_SECS, accept_legacy) {
+            return error(StatusCode::UNAUTHORIZED, format!("Webhook 签名无效: {}", signature_reason(&e)));
+        }
+    }
+
+    if !state.check_limit(workflow_id).await {
+        return error(StatusCode::TOO_MANY_REQUESTS, "Webhook 请求过于频繁".to_string());
+    }
+
+    let payload = if body.is_empty() {
+        serde_json::Value::Null
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(payload) => payload,
+            Err(e) => return error(StatusCode::BAD_REQUEST, format!("请求体不是有效的 JSON: {}", e)),
+        }
+    };
+
+    let Some(workflow) = state.scheduler.scheduled_workflow(workflow_id).await else {
+        return error(StatusCode::NOT_FOUND, format!("工作流不存在: {}", workflow_id));
+    };
+
+    match state.scheduler.trigger_webhook(&workflow, payload, visible_headers(&headers)).await {
+        Ok(execution_id) => (
+            StatusCode::ACCEPTED,
+            Json(serde_json::json!({
+                "success": true,
+                "execution_id": execution_id
+            })),
+        ),
+        Err(e @ WorkflowError::QueueFull(_)) => error_envelope("执行队列已满，请稍后重试", &e),
+        Err(e) => error_envelope("触发工作流失败", &e),
+    }
+}
+
+/// 签名调试：按给定密钥和请求体计算规范签名串与期望的签名头，并校验示例签名，
+/// 供各语言 SDK 实现 Webhook 签名（收发双方使用相同算法）时对照
+pub async fn verify_signature_sample(Json(request): Json<VerifySignatureRequest>) -> impl IntoResponse {
+    let now = request.now.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let tolerance_secs = request.tolerance_secs.unwrap_or(DEFAULT_TOLERANCE_SECS);
+    if tolerance_secs < 0 {
+        return error(StatusCode::BAD_REQUEST, "容忍时间不能为负数".to_string());
+    }
+    let body = request.payload.as_bytes();
+
+    // 按示例签名的时间戳计算期望值，便于逐项比对；没有时使用当前时间
+    let parsed = request.signature.as_deref().map(signatures::parse_header);
+    let timestamp = match &parsed {
+        Some(Ok(parsed)) => parsed.timestamp.unwrap_or(now),
+        _ => now,
+    };
+    let result = request.signature.as_deref()
+        .map(|signature| signatures::verify(&request.secret, signature, body, now, tolerance_secs, request.accept_legacy));
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "header": SIGNATURE_HEADER,
+            "timestamp": timestamp,
+            "canonical_payload": String::from_utf8_lossy(&signatures::canonical_payload(timestamp, body)),
+            "expected_signature": signatures::signature_header(&request.secret, timestamp, body),
+            "parsed": parsed.and_then(Result::ok),
+            "valid": result.as_ref().map(Result::is_ok),
+            "reason": result.and_then(Result::err).map(|e| signature_reason(&e)),
+            "tolerance_secs": tolerance_secs
+        })),
+    )
+}
+
+fn signature_reason(e: &SignatureError) -> String {
+    match e {
+        SignatureError::Malformed(reason) => format!("签名头格式错误 ({})", reason),
+        SignatureError::OutsideTolerance { skew_secs, tolerance_secs } => {
+            format!("时间戳相差 {} 秒，超出容忍的 {} 秒", skew_secs, tolerance_secs)
+        }
+        SignatureError::Mismatch => "签名与请求体不匹配".to_string(),
+        SignatureError::LegacyRejected => "不接受旧的 sha256= 请求体签名，请使用 t=时间戳,v1=签名".to_string(),
+    }
+}
+
+fn visible_headers(headers: &HeaderMap) -> HashMap<String, String> {
+    headers.iter()
+        .filter(|(name, _)| !HIDDEN_HEADERS.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+fn error(status: StatusCode, message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "message": message
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::post;
+    use axum::Router;
+    use common::types::{Workflow, WORKFLOW_SCHEMA_VERSION};
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use tower::ServiceExt;
+    use workflow_engine::scheduler::ScheduleConfig;
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        signatures::signature_header(secret, chrono::Utc::now().timestamp(), body)
+    }
+
+    fn sign_legacy(secret: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[tokio::test]
+    async fn test_webhook_signature_and_rate_limit() {
+        let scheduler = Arc::new(WorkflowScheduler::default());
+        let workflow_id = Uuid::new_v4();
+        scheduler.register_workflow(Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: workflow_id,
+            name: "Deploy hook".to_string(),
+            description: None,
+            nodes: vec![],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        }).await;
+        let webhook = |accept_legacy_signatures: bool| ScheduleConfig {
+            workflow_id,
+            schedule_type: ScheduleType::Webhook {
+                url: format!("/api/v1/webhooks/{}", workflow_id),
+                secret: Some("s3cret".to_string()),
+                accept_legacy_signatures,
+            },
+            enabled: true,
+        };
+        scheduler.add_schedule(webhook(false)).await.unwrap();
+
+        let state = WebhookServiceState::new(scheduler.clone()).with_default_limit(RateLimitConfig {
+            requests_per_second: 2,
+            requests_per_minute: 60,
+            requests_per_hour: 1000,
+            concurrent_limit: 2,
+            burst: None,
+        });
+        let app = Router::new()
+            .route("/webhooks/:workflow_id", post(receive_webhook))
+            .with_state(state);
+        let send = |workflow_id: Uuid, signature: Option<String>| {
+            let app = app.clone();
+            async move {
+                let mut request = Request::builder().method("POST").uri(format!("/webhooks/{}", workflow_id));
+                if let Some(signature) = signature {
+                    request = request.header(SIGNATURE_HEADER, signature);
+                }
+                let response = app.oneshot(request.body(Body::from(r#"{"ref":"main"}"#)).unwrap()).await.unwrap();
+                response.status()
+            }
+        };
+        let body = br#"{"ref":"main"}"#;
+
+        assert_eq!(send(workflow_id, None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(send(workflow_id, Some(sign("wrong", body))).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(send(Uuid::new_v4(), Some(sign("s3cret", body))).await, StatusCode::NOT_FOUND);
+        let stale = signatures::signature_header("s3cret", chrono::Utc::now().timestamp() - 3600, body);
+        assert_eq!(send(workflow_id, Some(stale)).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(send(workflow_id, Some(sign("s3cret", body))).await, StatusCode::ACCEPTED);
+        // Legacy body-only signatures can be replayed, so only webhooks opting in accept them
+        assert_eq!(send(workflow_id, Some(sign_legacy("s3cret", body))).await, StatusCode::UNAUTHORIZED);
+        scheduler.add_schedule(webhook(true)).await.unwrap();
+        assert_eq!(send(workflow_id, Some(sign_legacy("s3cret", body))).await, StatusCode::ACCEPTED);
+        assert_eq!(send(workflow_id, Some(sign("s3cret", body))).await, StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_verify_signature_sample() {
+        let app = Router::new().route("/signatures/verify", post(verify_signature_sample));
+        let call = |body: serde_json::Value| {
+            let app = app.clone();
+            async move {
+                let request = Request::builder()
+                    .method("POST")
+                    .uri("/signatures/verify")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap();
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+            }
+        };
+        let payload = r#"{"ref":"main"}"#;
+        let signature = signatures::signature_header("s3cret", 1_700_000_000, payload.as_bytes());
+
+        let (status, body) = call(serde_json::json!({
+            "secret": "s3cret", "payload": payload, "signature": signature, "now": 1_700_000_060
+        })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["valid"], true);
+        assert_eq!(body["canonical_payload"], format!("1700000000.{}", payload));
+        assert_eq!(body["expected_signature"], signature);
+
+        let (_, body) = call(serde_json::json!({
+            "secret": "wrong", "payload": payload, "signature": signature, "now": 1_700_000_060
+        })).await;
+        assert_eq!(body["valid"], false);
+        assert_eq!(body["reason"], "签名与请求体不匹配");
+
+        // Without a sample, only the expected signature is computed
+        let (_, body) = call(serde_json::json!({ "secret": "s3cret", "payload": payload, "now": 1_700_000_000 })).await;
+        assert_eq!(body["valid"], serde_json::Value::Null);
+        assert_eq!(body["expected_signature"], signature);
+    }
+}
@@ -7,10 +7,13 @@ use axum::{
 };
 use futures::{sink::SinkExt, stream::StreamExt};
 use serde::{Deserialize, Serialize};
+use common::types::ExecutionState;
 use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
+use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
 use uuid::Uuid;
+use workflow_engine::events::{ExecutionEvent, ExecutionEventKind};
 
 use crate::server::AppState;
 
@@ -45,6 +48,25 @@ impl WebSocketManager {
         }
     }
 
+    /// Forward executor progress events to connected clients until the bus closes
+    pub fn forward_events(&self, mut events: broadcast::Receiver<ExecutionEvent>) -> JoinHandle<()> {
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    // No connected clients is not an error for progress updates
+                    Ok(event) => {
+                        let _ = tx.send(WorkflowUpdate::from(&event));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Progress forwarding lagged, skipped {} events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
     /// Register a new connection
     async fn register_connection(&self, connection_id: Uuid) {
         self.connections.write().await.push(connection_id);
@@ -83,9 +105,21 @@ pub struct WorkflowUpdate {
     pub current_node: Option<Uuid>,
     pub progress: f32,
     pub message: Option<String>,
+    /// Live output of a streaming AI node
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation: Option<GenerationProgress>,
     pub timestamp: i64,
 }
 
+/// Tokens an AI node generated so far, the text added since the previous
+/// update and the end of its text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationProgress {
+    pub tokens: u64,
+    pub delta: String,
+    pub preview: String,
+}
+
 /// Workflow execution status
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -98,6 +132,61 @@ pub enum WorkflowStatus {
     Cancelled,
 }
 
+impl From<&ExecutionState> for WorkflowStatus {
+    fn from(state: &ExecutionState) -> Self {
+        match state {
+            ExecutionState::Pending => WorkflowStatus::Queued,
+            ExecutionState::Running => WorkflowStatus::Running,
+            ExecutionState::Paused => WorkflowStatus::Paused,
+            ExecutionState::Completed => WorkflowStatus::Completed,
+            ExecutionState::Failed => WorkflowStatus::Failed,
+            ExecutionState::Cancelled => WorkflowStatus::Cancelled,
+        }
+    }
+}
+
+impl From<&ExecutionEvent> for WorkflowUpdate {
+    fn from(event: &ExecutionEvent) -> Self {
+        let mut generation = None;
+        let (status, current_node, message) = match &event.kind {
+            ExecutionEventKind::NodeStarted { node_id } => {
+                (WorkflowStatus::Running, Some(*node_id), None)
+            }
+            ExecutionEventKind::NodeCompleted { node_id, .. } => {
+                (WorkflowStatus::Running, Some(*node_id), None)
+            }
+            ExecutionEventKind::NodeFailed { node_id, error } => {
+                (WorkflowStatus::Running, Some(*node_id), Some(error.clone()))
+            }
+            ExecutionEventKind::NodeProgress { node_id, tokens, delta, preview } => {
+                generation = Some(GenerationProgress {
+                    tokens: *tokens,
+                    delta: delta.clone(),
+                    preview: preview.clone(),
+                });
+                (WorkflowStatus::Running, Some(*node_id), None)
+            }
+            ExecutionEventKind::StateChanged { state } => {
+                (WorkflowStatus::from(state), None, None)
+            }
+            ExecutionEventKind::ExecutionFinished { state, error } => {
+                (WorkflowStatus::from(state), None, error.clone())
+            }
+        };
+
+        WorkflowUpdate {
+            workflow_id: event.workflow_id,
+            execution_id: event.execution_id,
+            status,
+            current_node,
+            progress: event.progress,
+            message,
+            generation,
+            timestamp: event.timestamp.timestamp(),
+        }
+    }
+}
+
 /// WebSocket handler
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
@@ -228,6 +317,7 @@ mod tests {
             current_node: Some(Uuid::new_v4()),
             progress: 0.5,
             message: Some("Processing node".to_string()),
+            generation: None,
             timestamp: chrono::Utc::now().timestamp(),
         };
 
@@ -237,4 +327,43 @@ mod tests {
         assert_eq!(received.workflow_id, update.workflow_id);
         assert_eq!(received.execution_id, update.execution_id);
     }
+
+    #[tokio::test]
+    async fn test_forward_execution_events() {
+        use workflow_engine::events::{BroadcastEventBus, ExecutionEventBus};
+
+        let manager = WebSocketManager::new();
+        let mut rx = manager.tx.subscribe();
+        let bus = BroadcastEventBus::default();
+        manager.forward_events(bus.subscribe());
+
+        let node_id = Uuid::new_v4();
+        let event = |kind, progress| ExecutionEvent {
+            execution_id: Uuid::new_v4(),
+            workflow_id: Uuid::new_v4(),
+            kind,
+            progress,
+            timestamp: chrono::Utc::now(),
+        };
+        bus.publish(event(ExecutionEventKind::NodeFailed { node_id, error: "boom".to_string() }, 0.5));
+        bus.publish(event(ExecutionEventKind::NodeProgress {
+            node_id,
+            tokens: 12,
+            delta: " upon".to_string(),
+            preview: "Once upon".to_string(),
+        }, 0.5));
+        bus.publish(event(ExecutionEventKind::ExecutionFinished { state: ExecutionState::Failed, error: None }, 1.0));
+
+        let update = rx.recv().await.unwrap();
+        assert!(matches!(update.status, WorkflowStatus::Running));
+        assert_eq!(update.current_node, Some(node_id));
+        assert_eq!(update.message.as_deref(), Some("boom"));
+        assert!(update.generation.is_none());
+        let update = rx.recv().await.unwrap();
+        let generation = update.generation.unwrap();
+        assert_eq!((generation.tokens, generation.delta.as_str(), generation.preview.as_str()), (12, " upon", "Once upon"));
+        let update = rx.recv().await.unwrap();
+        assert!(matches!(update.status, WorkflowStatus::Failed));
+        assert_eq!(update.progress, 1.0);
+    }
 }
@@ -0,0 +1,86 @@
+use common::types::{ActionType2, Permission, ResourceType, Scope};
+use rbac_service::jwt::JwtClaims;
+use rbac_service::{OrgService, PermissionChecker, RoleManager};
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::workflows::{InMemoryWorkflowStore, WorkflowStore};
+
+/// Authorizes callers on the data of workflows (executions, dead letters,
+/// tests, reviews, ...) the way the workflow routes authorize on workflows:
+/// the role in the caller's token must grant the action over the workflow's
+/// owner.
+#[derive(Clone)]
+pub struct WorkflowAccess {
+    workflows: Arc<dyn WorkflowStore>,
+    permissions: Arc<PermissionChecker>,
+    /// Memberships resolving Team- and Organization-scoped permissions
+    organizations: Option<Arc<OrgService>>,
+}
+
+impl Default for WorkflowAccess {
+    fn default() -> Self {
+        Self::new(Arc::new(InMemoryWorkflowStore::new()))
+    }
+}
+
+impl WorkflowAccess {
+    pub fn new(workflows: Arc<dyn WorkflowStore>) -> Self {
+        Self {
+            workflows,
+            permissions: Arc::new(PermissionChecker::new(Arc::new(RoleManager::new()))),
+            organizations: None,
+        }
+    }
+
+    pub fn with_org_service(mut self, organizations: Arc<OrgService>) -> Self {
+        self.permissions = Arc::new(
+            PermissionChecker::new(Arc::new(RoleManager::new())).with_org_service(organizations.clone()),
+        );
+        self.organizations = Some(organizations);
+        self
+    }
+
+    /// Owner of a saved workflow; `None` for unknown workflows, or when
+    /// the store cannot be read
+    pub async fn owner(&self, workflow_id: Uuid) -> Option<Uuid> {
+        match self.workflows.get(workflow_id).await {
+            Ok(stored) => stored.map(|stored| stored.owner_id),
+            Err(e) => {
+                tracing::warn!("Failed to read the owner of workflow {}: {}", workflow_id, e);
+                None
+            }
+        }
+    }
+
+    /// Whether the caller may perform `action` on a workflow of `owner_id`;
+    /// `None` asks whether they may do so on anyone's workflows
+    pub async fn can(&self, claims: &JwtClaims, action: ActionType2, owner_id: Option<Uuid>) -> bool {
+        let permission = Permission {
+            resource: ResourceType::Workflow,
+            action,
+            scope: Scope::Own,
+        };
+        self.permissions
+            .check_role_permission(claims.sub, &claims.role, &permission, owner_id, None, None)
+            .await
+    }
+
+    /// Whether the caller may perform `action` on the workflow; unknown
+    /// workflows need the permission over everyone's
+    pub async fn can_on(&self, claims: &JwtClaims, action: ActionType2, workflow_id: Uuid) -> bool {
+        let owner_id = self.owner(workflow_id).await;
+        self.can(claims, action, owner_id).await
+    }
+
+    /// Whether data of `organization_id`, or without one of `owner_id`, is
+    /// the caller's own: they own it or are a member of its organization
+    pub async fn is_member(&self, claims: &JwtClaims, organization_id: Option<Uuid>, owner_id: Option<Uuid>) -> bool {
+        match (organization_id, &self.organizations) {
+            (Some(organization_id), Some(organizations)) => {
+                organizations.get_membership(organization_id, claims.sub).await.is_some()
+            }
+            (Some(_), None) => false,
+            (None, _) => owner_id == Some(claims.sub),
+        }
+    }
+}
@@ -0,0 +1,634 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use common::types::{ActionType2, ExecutionState, JsonValue, Permission, ResourceType, Scope, Workflow};
+use rbac_service::jwt::JwtClaims;
+use rbac_service::{OrgService, PermissionChecker, RoleManager};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use workflow_engine::workflows::{StoredWorkflow, WorkflowStore};
+use workflow_engine::{WorkflowParser, WorkflowScheduler, WorkflowValidator};
+
+use crate::errors::{ApiError, FieldError};
+use crate::validation::ValidJson;
+
+type ApiResult = Result<(StatusCode, Json<JsonValue>), ApiError>;
+
+/// Workflow service state
+#[derive(Clone)]
+pub struct WorkflowServiceState {
+    pub workflows: Arc<dyn WorkflowStore>,
+    /// Permissions of each role; checks use the role in the caller's token
+    pub roles: Arc<RoleManager>,
+    pub permissions: Arc<PermissionChecker>,
+    /// Memberships resolving Team- and Organization-scoped permissions
+    pub organizations: Option<Arc<OrgService>>,
+    /// Schedules follow their workflow's activation and deletion; manual
+    /// executions are started and controlled through it
+    pub scheduler: Option<Arc<WorkflowScheduler>>,
+}
+
+impl WorkflowServiceState {
+    pub fn new(workflows: Arc<dyn WorkflowStore>) -> Self {
+        let roles = Arc::new(RoleManager::new());
+        Self {
+            workflows,
+            permissions: Arc::new(PermissionChecker::new(roles.clone())),
+            roles,
+            organizations: None,
+            scheduler: None,
+        }
+    }
+
+    pub fn with_org_service(mut self, organizations: Arc<OrgService>) -> Self {
+        self.permissions = Arc::new(PermissionChecker::new(self.roles.clone()).with_org_service(organizations.clone()));
+        self.organizations = Some(organizations);
+        self
+    }
+
+    pub fn with_scheduler(mut self, scheduler: Arc<WorkflowScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// Whether the caller may perform `action` on a workflow of `owner_id`;
+    /// `None` asks whether they may do so on anyone's workflows
+    async fn can(&self, claims: &JwtClaims, action: ActionType2, owner_id: Option<Uuid>) -> bool {
+        let permission = Permission {
+            resource: ResourceType::Workflow,
+            action,
+            scope: Scope::Own,
+        };
+        self.permissions.check_role_permission(claims.sub, &claims.role, &permission, owner_id, None, None).await
+    }
+
+    /// The stored workflow, if the caller may perform `action` on it
+    async fn authorized(
+        &self,
+        claims: &JwtClaims,
+        action: ActionType2,
+        workflow_id: Uuid,
+    ) -> Result<StoredWorkflow, ApiError> {
+        let stored = self.workflows
+            .get(workflow_id)
+            .await
+            .map_err(|e| ApiError::from_error("读取工作流失败", &e))?
+            .ok_or_else(|| ApiError::not_found("工作流不存在"))?;
+        if !self.can(claims, action, Some(stored.owner_id)).await {
+            // Workflows the caller may not even read are not revealed
+            let denied = "没有操作该工作流的权限";
+            return Err(if self.can(claims, ActionType2::Read, Some(stored.owner_id)).await {
+                ApiError::forbidden(denied)
+            } else {
+                ApiError::not_found(denied)
+            });
+        }
+        Ok(stored)
+    }
+
+    fn scheduler(&self) -> Result<&Arc<WorkflowScheduler>, ApiError> {
+        self.scheduler
+            .as_ref()
+            .ok_or_else(|| ApiError::new(common::error::ErrorCode::Unavailable, "执行服务不可用"))
+    }
+
+    async fn save(&self, stored: &StoredWorkflow) -> Result<(), ApiError> {
+        self.workflows
+            .save(stored)
+            .await
+            .map_err(|e| ApiError::from_error("保存工作流失败", &e))
+    }
+}
+
+/// 列出调用者可读的工作流：有全局读取权限时列出全部；属于组织的用户列出其有权读取的
+/// 团队和组织成员的工作流；否则只列出自己的
+pub async fn list_workflows(
+    State(state): State<WorkflowServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+) -> ApiResult {
+    let all = state.can(&claims, ActionType2::Read, None).await;
+    let in_organization = match &state.organizations {
+        Some(organizations) => !organizations.organizations_of(claims.sub).await.is_empty(),
+        None => false,
+    };
+    let owner_id = if all || in_organization { None } else { Some(claims.sub) };
+    let mut workflows = state.workflows
+        .list(owner_id)
+        .await
+        .map_err(|e| ApiError::from_error("列出工作流失败", &e))?;
+    if !all && in_organization {
+        let mut readable = Vec::with_capacity(workflows.len());
+        for stored in workflows {
+            if state.can(&claims, ActionType2::Read, Some(stored.owner_id)).await {
+                readable.push(stored);
+            }
+        }
+        workflows = readable;
+    }
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "total": workflows.len(),
+            "workflows": workflows
+        })),
+    ))
+}
+
+/// 创建工作流：定义经解析和校验后保存，归调用者所有；未给出 ID 时自动生成
+pub async fn create_workflow(
+    State(state): State<WorkflowServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    ValidJson(definition): ValidJson<JsonValue>,
+) -> ApiResult {
+    if !state.can(&claims, ActionType2::Create, Some(claims.sub)).await {
+        return Err(ApiError::forbidden("没有创建工作流的权限"));
+    }
+
+    let id = match definition.get("id").filter(|id| !id.is_null()) {
+        Some(id) => serde_json::from_value::<Uuid>(id.clone())
+            .map_err(|_| field_error("id", "工作流 ID 无效"))?,
+        None => Uuid::new_v4(),
+    };
+    let existing = state.workflows
+        .get(id)
+        .await
+        .map_err(|e| ApiError::from_error("读取工作流失败", &e))?;
+    if existing.is_some() {
+        return Err(ApiError::conflict("工作流已存在"));
+    }
+
+    let (workflow, warnings) = checked_workflow(definition, id, Utc::now())?;
+    let stored = StoredWorkflow {
+        workflow,
+        owner_id: claims.sub,
+        active: true,
+    };
+    state.save(&stored).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "success": true,
+            "workflow": stored,
+            "warnings": warnings
+        })),
+    ))
+}
+
+/// 查询工作流详情
+pub async fn get_workflow(
+    State(state): State<WorkflowServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+) -> ApiResult {
+    let stored = state.authorized(&claims, ActionType2::Read, workflow_id).await?;
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "workflow": stored
+        })),
+    ))
+}
+
+/// 修改工作流：新定义经解析和校验后替换原定义，创建时间和所有者不变
+pub async fn update_workflow(
+    State(state): State<WorkflowServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+    ValidJson(definition): ValidJson<JsonValue>,
+) -> ApiResult {
+    let mut stored = state.authorized(&claims, ActionType2::Update, workflow_id).await?;
+    if definition.get("id").is_some_and(|id| !id.is_null() && *id != serde_json::json!(workflow_id)) {
+        return Err(field_error("id", "工作流 ID 与路径不一致"));
+    }
+
+    let (workflow, warnings) = checked_workflow(definition, workflow_id, stored.workflow.created_at)?;
+    stored.workflow = workflow;
+    state.save(&stored).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "workflow": stored,
+            "warnings": warnings
+        })),
+    ))
+}
+
+/// 删除工作流及其调度
+pub async fn delete_workflow(
+    State(state): State<WorkflowServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+) -> ApiResult {
+    state.authorized(&claims, ActionType2::Delete, workflow_id).await?;
+    state.workflows
+        .delete(workflow_id)
+        .await
+        .map_err(|e| ApiError::from_error("删除工作流失败", &e))?;
+    if let Some(scheduler) = &state.scheduler {
+        if scheduler.get_schedule(workflow_id).await.is_some() {
+            if let Err(e) = scheduler.remove_schedule(workflow_id).await {
+                tracing::warn!("Failed to remove schedule of deleted workflow {}: {}", workflow_id, e);
+            }
+        }
+    }
+    Ok((StatusCode::OK, Json(serde_json::json!({ "success": true }))))
+}
+
+/// 复制工作流：副本使用新 ID，名称加上“(副本)”，归调用者所有且处于停用状态
+pub async fn duplicate_workflow(
+    State(state): State<WorkflowServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+) -> ApiResult {
+    let original = state.authorized(&claims, ActionType2::Read, workflow_id).await?;
+    if !state.can(&claims, ActionType2::Create, Some(claims.sub)).await {
+        return Err(ApiError::forbidden("没有创建工作流的权限"));
+    }
+
+    let now = Utc::now();
+    let copy = StoredWorkflow {
+        workflow: Workflow {
+            id: Uuid::new_v4(),
+            name: format!("{} (副本)", original.workflow.name),
+            created_at: now,
+            updated_at: now,
+            ..original.workflow
+        },
+        owner_id: claims.sub,
+        active: false,
+    };
+    state.save(&copy).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "success": true,
+            "workflow": copy
+        })),
+    ))
+}
+
+/// 启用工作流，同时恢复其调度
+pub async fn activate_workflow(
+    State(state): State<WorkflowServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+) -> ApiResult {
+    set_active(state, claims, workflow_id, true).await
+}
+
+/// 停用工作流：定义保留，但调度暂停、不再触发
+pub async fn deactivate_workflow(
+    State(state): State<WorkflowServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+) -> ApiResult {
+    set_active(state, claims, workflow_id, false).await
+}
+
+async fn set_active(state: WorkflowServiceState, claims: JwtClaims, workflow_id: Uuid, active: bool) -> ApiResult {
+    let mut stored = state.authorized(&claims, ActionType2::Update, workflow_id).await?;
+    stored.active = active;
+    stored.workflow.updated_at = Utc::now();
+    state.save(&stored).await?;
+
+    if let Some(scheduler) = &state.scheduler {
+        if scheduler.get_schedule(workflow_id).await.is_some() {
+            let toggled = if active {
+                scheduler.enable_schedule(workflow_id).await
+            } else {
+                scheduler.disable_schedule(workflow_id).await
+            };
+            toggled.map_err(|e| ApiError::from_error("更新工作流调度失败", &e))?;
+        }
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "workflow": stored
+        })),
+    ))
+}
+
+/// 手动执行请求：输入作为执行变量传入工作流
+#[derive(Debug, Default, Deserialize)]
+pub struct ExecuteRequest {
+    #[serde(default)]
+    pub input: HashMap<String, JsonValue>,
+}
+
+/// 手动执行工作流：立即返回执行 ID，执行状态通过 WebSocket 推送
+pub async fn execute_workflow(
+    State(state): State<WorkflowServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(workflow_id): Path<Uuid>,
+    body: Option<Json<ExecuteRequest>>,
+) -> ApiResult {
+    let stored = state.authorized(&claims, ActionType2::Execute, workflow_id).await?;
+    if !stored.active {
+        return Err(ApiError::conflict("工作流已停用"));
+    }
+
+    let Json(request) = body.unwrap_or_default();
+    let execution_id = state.scheduler()?
+        .trigger_manual(&stored.workflow, request.input)
+        .await
+        .map_err(|e| ApiError::from_error("触发工作流失败", &e))?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "success": true,
+            "execution_id": execution_id
+        })),
+    ))
+}
+
+/// 取消执行：当前节点执行完后不再执行后续节点
+pub async fn cancel_execution(
+    State(state): State<WorkflowServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(execution_id): Path<Uuid>,
+) -> ApiResult {
+    control_execution(state, claims, execution_id, ExecutionState::Cancelled).await
+}
+
+/// 暂停执行：在下一个节点开始前暂停，直到恢复或取消
+pub async fn pause_execution(
+    State(state): State<WorkflowServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(execution_id): Path<Uuid>,
+) -> ApiResult {
+    control_execution(state, claims, execution_id, ExecutionState::Paused).await
+}
+
+/// 恢复已暂停的执行
+pub async fn resume_execution(
+    State(state): State<WorkflowServiceState>,
+    Extension(claims): Extension<JwtClaims>,
+    Path(execution_id): Path<Uuid>,
+) -> ApiResult {
+    control_execution(state, claims, execution_id, ExecutionState::Running).await
+}
+
+/// Move a running execution to `target`, if the caller may execute its workflow
+async fn control_execution(
+    state: WorkflowServiceState,
+    claims: JwtClaims,
+    execution_id: Uuid,
+    target: ExecutionState,
+) -> ApiResult {
+    let executor = state.scheduler()?.executor().clone();
+    let ctx = executor
+        .get_context(execution_id)
+        .await
+        .ok_or_else(|| ApiError::not_found("执行不存在"))?;
+    state.authorized(&claims, ActionType2::Execute, ctx.workflow_id).await?;
+
+    let changed = match target {
+        ExecutionState::Cancelled => executor.cancel(execution_id).await,
+        ExecutionState::Paused => executor.pause(execution_id).await,
+        _ => executor.resume(execution_id).await,
+    };
+    changed.map_err(|e| ApiError::from_error("更新执行状态失败", &e))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "success": true,
+            "execution_id": execution_id,
+            "state": target
+        })),
+    ))
+}
+
+/// Parse and validate a definition saved as workflow `id`, returning the
+/// workflow and the validator's warnings
+fn checked_workflow(
+    mut definition: JsonValue,
+    id: Uuid,
+    created_at: chrono::DateTime<Utc>,
+) -> Result<(Workflow, Vec<String>), ApiError> {
+    let Some(fields) = definition.as_object_mut() else {
+        return Err(ApiError::invalid_input("工作流定义必须是 JSON 对象"));
+    };
+    fields.insert("id".to_string(), serde_json::json!(id));
+    fields.insert("created_at".to_string(), serde_json::json!(created_at));
+    fields.insert("updated_at".to_string(), serde_json::json!(Utc::now()));
+    for (field, empty) in [("nodes", serde_json::json!([])), ("edges", serde_json::json!([])), ("variables", serde_json::json!({}))] {
+        fields.entry(field).or_insert(empty);
+    }
+
+    let workflow = WorkflowParser::new()
+        .parse(&definition.to_string())
+        .map_err(|e| field_error("definition", e.to_string()))?;
+    let result = WorkflowValidator::new()
+        .validate(&workflow)
+        .map_err(|e| field_error("definition", e.to_string()))?;
+    if !result.valid {
+        let field_errors = result.errors
+            .into_iter()
+            .map(|message| FieldError { field: "definition".to_string(), message })
+            .collect();
+        return Err(ApiError::validation(field_errors));
+    }
+    Ok((workflow, result.warnings))
+}
+
+fn field_error(field: &str, message: impl Into<String>) -> ApiError {
+    ApiError::validation(vec![FieldError { field: field.to_string(), message: message.into() }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, claims};
+    use axum::routing::{get, post};
+    use axum::Router;
+    use common::types::{NodeConfig, NodeType, Port, Position, Role, TriggerType, DataType};
+    use workflow_engine::workflows::InMemoryWorkflowStore;
+
+    fn app(state: WorkflowServiceState, user: Uuid, role: Role) -> Router {
+        Router::new()
+            .route("/workflows", get(list_workflows).post(create_workflow))
+            .route("/workflows/:id", get(get_workflow).put(update_workflow).delete(delete_workflow))
+            .route("/workflows/:id/duplicate", post(duplicate_workflow))
+            .route("/workflows/:id/activate", post(activate_workflow))
+            .route("/workflows/:id/deactivate", post(deactivate_workflow))
+            .route("/workflows/:id/execute", post(execute_workflow))
+            .route("/executions/:id/cancel", post(cancel_execution))
+            .route("/executions/:id/pause", post(pause_execution))
+            .layer(Extension(claims(user, role)))
+            .with_state(state)
+    }
+
+    fn definition(name: &str) -> JsonValue {
+        let trigger = common::types::Node {
+            id: Uuid::new_v4(),
+            node_type: NodeType::Trigger { trigger_type: TriggerType::Manual },
+            config: NodeConfig {
+                parameters: Default::default(),
+                timeout_ms: None,
+                retry: None,
+                on_error: Default::default(),
+            },
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![Port { id: "out".to_string(), name: "Output".to_string(), data_type: DataType::Any }],
+        };
+        serde_json::json!({ "name": name, "description": null, "nodes": [trigger] })
+    }
+
+    #[tokio::test]
+    async fn test_workflow_crud() {
+        let state = WorkflowServiceState::new(Arc::new(InMemoryWorkflowStore::new()));
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let (status, body) = call(app(state.clone(), alice, Role::User), "POST", "/workflows", Some(definition("Sync"))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["workflow"]["owner_id"], alice.to_string());
+        assert_eq!(body["workflow"]["active"], true);
+        let id = body["workflow"]["id"].as_str().unwrap().to_string();
+        let uri = format!("/workflows/{}", id);
+
+        let (status, body) = call(app(state.clone(), alice, Role::User), "PUT", &uri, Some(definition("Nightly sync"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["workflow"]["name"], "Nightly sync");
+
+        let (status, body) = call(app(state.clone(), alice, Role::User), "POST", &format!("{}/deactivate", uri), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["workflow"]["active"], false);
+
+        let (status, body) = call(app(state.clone(), alice, Role::User), "POST", &format!("{}/duplicate", uri), None).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["workflow"]["name"], "Nightly sync (副本)");
+        assert_ne!(body["workflow"]["id"], id.as_str());
+
+        let (_, body) = call(app(state.clone(), alice, Role::User), "GET", "/workflows", None).await;
+        assert_eq!(body["total"], 2);
+
+        let (status, _) = call(app(state.clone(), alice, Role::User), "DELETE", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(app(state.clone(), alice, Role::User), "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Others only see their own workflows; admins see everyone's
+        let (_, body) = call(app(state.clone(), bob, Role::User), "GET", "/workflows", None).await;
+        assert_eq!(body["total"], 0);
+        let (_, body) = call(app(state, bob, Role::Admin), "GET", "/workflows", None).await;
+        assert_eq!(body["total"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_owner_scoping_and_validation() {
+        let state = WorkflowServiceState::new(Arc::new(InMemoryWorkflowStore::new()));
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let (_, body) = call(app(state.clone(), alice, Role::User), "POST", "/workflows", Some(definition("Sync"))).await;
+        let uri = format!("/workflows/{}", body["workflow"]["id"].as_str().unwrap());
+
+        let (status, _) = call(app(state.clone(), bob, Role::User), "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = call(app(state.clone(), bob, Role::User), "PUT", &uri, Some(definition("Mine"))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = call(app(state.clone(), bob, Role::Viewer), "POST", "/workflows", Some(definition("Mine"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = call(app(state.clone(), bob, Role::Admin), "DELETE", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // A trigger without outputs fails validation and is not saved
+        let mut invalid = definition("Broken");
+        invalid["nodes"][0]["outputs"] = serde_json::json!([]);
+        let (status, body) = call(app(state.clone(), alice, Role::User), "POST", "/workflows", Some(invalid)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["field_errors"][0]["field"], "definition");
+        assert!(body["field_errors"][0]["message"].as_str().unwrap().contains("must have at least one output"));
+        let (status, _) = call(app(state.clone(), alice, Role::User), "POST", "/workflows", Some(serde_json::json!(["not", "a", "workflow"]))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, body) = call(app(state, alice, Role::User), "GET", "/workflows", None).await;
+        assert_eq!(body["total"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_team_members_share_workflows() {
+        let organizations = Arc::new(OrgService::new());
+        let state = WorkflowServiceState::new(Arc::new(InMemoryWorkflowStore::new())).with_org_service(organizations.clone());
+        let (lead, teammate, outsider) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let org = organizations.create_organization("Acme".to_string(), lead).await;
+        let team = organizations.create_team(org.id, "Ops".to_string(), lead).await.unwrap();
+        organizations.add_member(org.id, teammate, rbac_service::org::MemberRole::Member).await.unwrap();
+        organizations.add_team_member(team.id, lead).await.unwrap();
+        organizations.add_team_member(team.id, teammate).await.unwrap();
+
+        for (owner, name) in [(teammate, "Shared"), (outsider, "Private")] {
+            call(app(state.clone(), owner, Role::User), "POST", "/workflows", Some(definition(name))).await;
+        }
+
+        // Managers' Team scope covers their teammates' workflows only
+        let (_, body) = call(app(state.clone(), lead, Role::Manager), "GET", "/workflows", None).await;
+        assert_eq!(body["total"], 1);
+        assert_eq!(body["workflows"][0]["name"], "Shared");
+        let uri = format!("/workflows/{}", body["workflows"][0]["id"].as_str().unwrap());
+        let (status, _) = call(app(state.clone(), lead, Role::Manager), "PUT", &uri, Some(definition("Shared v2"))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Plain users keep to their own
+        let (_, body) = call(app(state, lead, Role::User), "GET", "/workflows", None).await;
+        assert_eq!(body["total"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_manual_execution() {
+        let executor = Arc::new(workflow_engine::WorkflowExecutor::new());
+        let state = WorkflowServiceState::new(Arc::new(InMemoryWorkflowStore::new()))
+            .with_scheduler(Arc::new(WorkflowScheduler::new(executor.clone())));
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let (_, body) = call(app(state.clone(), alice, Role::User), "POST", "/workflows", Some(definition("Sync"))).await;
+        let uri = format!("/workflows/{}", body["workflow"]["id"].as_str().unwrap());
+
+        let input = serde_json::json!({ "input": { "customer": "acme" } });
+        let (status, body) = call(app(state.clone(), alice, Role::User), "POST", &format!("{}/execute", uri), Some(input)).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let execution_id: Uuid = serde_json::from_value(body["execution_id"].clone()).unwrap();
+        let cancel = format!("/executions/{}/cancel", execution_id);
+
+        let mut finished = false;
+        for _ in 0..50 {
+            if let Some(ctx) = executor.get_context(execution_id).await {
+                if ctx.state == ExecutionState::Completed {
+                    assert_eq!(ctx.variables.read().await["customer"], "acme");
+                    finished = true;
+                    break;
+                }
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert!(finished);
+
+        // Finished executions can no longer be cancelled, others' not at all
+        let (status, _) = call(app(state.clone(), alice, Role::User), "POST", &cancel, None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = call(app(state.clone(), bob, Role::User), "POST", &cancel, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = call(app(state.clone(), alice, Role::User), "POST", &format!("/executions/{}/pause", Uuid::new_v4()), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        call(app(state.clone(), alice, Role::User), "POST", &format!("{}/deactivate", uri), None).await;
+        let (status, _) = call(app(state, alice, Role::User), "POST", &format!("{}/execute", uri), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,93 @@
+use async_trait::async_trait;
+use common::error::WorkflowError;
+use common::types::Workflow;
+use sqlx::types::Json;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+use workflow_engine::workflows::{StoredWorkflow, WorkflowStore};
+
+/// Postgres-backed workflow store (see `migrations/001_initial_schema.sql`
+/// and `migrations/007_workflow_store.sql`)
+pub struct PgWorkflowStore {
+    pool: PgPool,
+}
+
+impl PgWorkflowStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl WorkflowStore for PgWorkflowStore {
+    async fn save(&self, stored: &StoredWorkflow) -> Result<(), WorkflowError> {
+        let workflow = &stored.workflow;
+        sqlx::query(
+            r#"
+            INSERT INTO workflows (id, user_id, name, description, definition, is_active, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (id) DO UPDATE
+            SET user_id = $2, name = $3, description = $4, definition = $5, is_active = $6, updated_at = $8
+            "#,
+        )
+        .bind(workflow.id)
+        .bind(stored.owner_id)
+        .bind(&workflow.name)
+        .bind(&workflow.description)
+        .bind(Json(workflow))
+        .bind(stored.active)
+        .bind(workflow.created_at)
+        .bind(workflow.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Option<StoredWorkflow>, WorkflowError> {
+        let row = sqlx::query("SELECT user_id, definition, is_active FROM workflows WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        row.map(|row| decode(&row)).transpose()
+    }
+
+    async fn list(&self, owner_id: Option<Uuid>) -> Result<Vec<StoredWorkflow>, WorkflowError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT user_id, definition, is_active
+            FROM workflows
+            WHERE ($1::uuid IS NULL OR user_id = $1)
+            ORDER BY updated_at DESC
+            "#,
+        )
+        .bind(owner_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(storage_error)?;
+        rows.iter().map(decode).collect()
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, WorkflowError> {
+        let deleted = sqlx::query("DELETE FROM workflows WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(deleted.rows_affected() > 0)
+    }
+}
+
+fn decode(row: &sqlx::postgres::PgRow) -> Result<StoredWorkflow, WorkflowError> {
+    let Json(workflow) = row.try_get::<Json<Workflow>, _>("definition").map_err(storage_error)?;
+    Ok(StoredWorkflow {
+        workflow,
+        owner_id: row.try_get("user_id").map_err(storage_error)?,
+        active: row.try_get::<Option<bool>, _>("is_active").map_err(storage_error)?.unwrap_or(true),
+    })
+}
+
+fn storage_error(e: sqlx::Error) -> WorkflowError {
+    WorkflowError::Storage(e.to_string())
+}
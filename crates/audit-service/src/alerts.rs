@@ -0,0 +1,520 @@
+use chrono::{DateTime, Datelike, Duration, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use common::error::{ErrorCode, ErrorInfo};
+use common::types::{AuditAction, AuditLog, AuditResult};
+use integration_service::{Notification, NotificationChannel, Notifier};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use uuid::Uuid;
+
+/// Alerts kept for the recent alerts list
+const RECENT_ALERTS: usize = 100;
+
+/// Subjects with events in a window before idle ones are dropped
+const MAX_TRACKED_SUBJECTS: usize = 10_000;
+
+/// What makes a rule fire
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// `threshold` failed or denied logins of one account, or from one
+    /// address when the account is unknown, within `window_minutes`
+    FailedLogins { threshold: u32, window_minutes: u32 },
+    /// A permission change outside `start_hour`..`end_hour` in `timezone`,
+    /// or on a weekend unless `weekends` are business days
+    OffHoursPermissionChange {
+        #[serde(default = "default_start_hour")]
+        start_hour: u32,
+        #[serde(default = "default_end_hour")]
+        end_hour: u32,
+        #[serde(default = "default_timezone")]
+        timezone: String,
+        #[serde(default)]
+        weekends: bool,
+    },
+    /// `threshold` deletions by one user within `window_minutes`
+    MassDeletion { threshold: u32, window_minutes: u32 },
+}
+
+fn default_start_hour() -> u32 {
+    9
+}
+
+fn default_end_hour() -> u32 {
+    18
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn default_cooldown_minutes() -> u32 {
+    60
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Settings of an alert rule, as created or updated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRuleInput {
+    pub name: String,
+    pub condition: AlertCondition,
+    pub channels: Vec<NotificationChannel>,
+    /// Minutes a rule stays quiet for a subject after alerting about it
+    #[serde(default = "default_cooldown_minutes")]
+    pub cooldown_minutes: u32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+impl AlertRuleInput {
+    fn validate(&self) -> Result<(), AlertError> {
+        let invalid = |message: String| Err(AlertError::InvalidRule(message));
+        if self.name.trim().is_empty() {
+            return invalid("name must not be empty".to_string());
+        }
+        if self.channels.is_empty() {
+            return invalid("at least one channel is required".to_string());
+        }
+        for channel in &self.channels {
+            channel.validate().map_err(|e| AlertError::InvalidRule(e.to_string()))?;
+        }
+        match &self.condition {
+            AlertCondition::FailedLogins { threshold, window_minutes }
+            | AlertCondition::MassDeletion { threshold, window_minutes } => {
+                if *threshold == 0 || *window_minutes == 0 {
+                    return invalid("threshold and window_minutes must be positive".to_string());
+                }
+            }
+            AlertCondition::OffHoursPermissionChange { start_hour, end_hour, timezone, .. } => {
+                if start_hour >= end_hour || *end_hour > 24 {
+                    return invalid("business hours must satisfy start_hour < end_hour <= 24".to_string());
+                }
+                if timezone.parse::<Tz>().is_err() {
+                    return invalid(format!("unknown timezone {:?}", timezone));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertRule {
+    pub id: Uuid,
+    pub name: String,
+    pub condition: AlertCondition,
+    pub channels: Vec<NotificationChannel>,
+    pub cooldown_minutes: u32,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub last_triggered_at: Option<DateTime<Utc>>,
+}
+
+/// A rule firing on the audit events of one subject
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub rule_id: Uuid,
+    pub rule_name: String,
+    /// User ID, or address for logins of unknown accounts
+    pub subject: String,
+    pub message: String,
+    /// Audit logs that triggered the alert
+    pub log_ids: Vec<Uuid>,
+    pub triggered_at: DateTime<Utc>,
+}
+
+impl Alert {
+    fn notification(&self) -> Notification {
+        Notification {
+            subject: format!("Security alert: {}", self.rule_name),
+            body: format!(
+                "{}\nSubject: {}\nTriggered at: {}\nAudit logs: {}",
+                self.message,
+                self.subject,
+                self.triggered_at.to_rfc3339(),
+                self.log_ids.iter().map(Uuid::to_string).collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AlertError {
+    #[error("Invalid alert rule: {0}")]
+    InvalidRule(String),
+
+    #[error("Alert rule not found: {0}")]
+    NotFound(Uuid),
+}
+
+impl ErrorInfo for AlertError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            AlertError::InvalidRule(_) => ErrorCode::InvalidInput,
+            AlertError::NotFound(_) => ErrorCode::NotFound,
+        }
+    }
+}
+
+/// A rule and the subject it counts events of
+type SubjectKey = (Uuid, String);
+
+#[derive(Default)]
+struct Windows {
+    /// Times and IDs of recent matching events
+    events: HashMap<SubjectKey, VecDeque<(DateTime<Utc>, Uuid)>>,
+    /// When each rule last fired for each subject
+    fired: HashMap<SubjectKey, DateTime<Utc>>,
+}
+
+/// Evaluates audit events against alert rules as they are logged.
+///
+/// Rules count events per subject over sliding windows, using the events'
+/// own timestamps; once a rule fires for a subject it stays quiet for that
+/// subject for its cooldown. Alerts go to the rule's channels through the
+/// integration service's [`Notifier`].
+pub struct AlertEngine {
+    rules: RwLock<HashMap<Uuid, AlertRule>>,
+    windows: Mutex<Windows>,
+    recent: Mutex<VecDeque<Alert>>,
+    notifier: Arc<dyn Notifier>,
+}
+
+impl AlertEngine {
+    pub fn new(notifier: Arc<dyn Notifier>) -> Self {
+        Self {
+            rules: RwLock::new(HashMap::new()),
+            windows: Mutex::new(Windows::default()),
+            recent: Mutex::new(VecDeque::new()),
+            notifier,
+        }
+    }
+
+    pub fn create_rule(&self, input: AlertRuleInput) -> Result<AlertRule, AlertError> {
+        input.validate()?;
+        let now = Utc::now();
+        let rule = AlertRule {
+            id: Uuid::new_v4(),
+            name: input.name,
+            condition: input.condition,
+            channels: input.channels,
+            cooldown_minutes: input.cooldown_minutes,
+            enabled: input.enabled,
+            created_at: now,
+            updated_at: now,
+            last_triggered_at: None,
+        };
+        self.rules.write().unwrap().insert(rule.id, rule.clone());
+        Ok(rule)
+    }
+
+    /// Replace a rule's settings; a changed condition starts counting afresh
+    pub fn update_rule(&self, rule_id: Uuid, input: AlertRuleInput) -> Result<AlertRule, AlertError> {
+        input.validate()?;
+        let mut rules = self.rules.write().unwrap();
+        let rule = rules.get_mut(&rule_id).ok_or(AlertError::NotFound(rule_id))?;
+        if rule.condition != input.condition {
+            self.forget(rule_id);
+        }
+        rule.name = input.name;
+        rule.condition = input.condition;
+        rule.channels = input.channels;
+        rule.cooldown_minutes = input.cooldown_minutes;
+        rule.enabled = input.enabled;
+        rule.updated_at = Utc::now();
+        Ok(rule.clone())
+    }
+
+    pub fn delete_rule(&self, rule_id: Uuid) -> Result<(), AlertError> {
+        self.rules.write().unwrap().remove(&rule_id).ok_or(AlertError::NotFound(rule_id))?;
+        self.forget(rule_id);
+        Ok(())
+    }
+
+    pub fn get_rule(&self, rule_id: Uuid) -> Option<AlertRule> {
+        self.rules.read().unwrap().get(&rule_id).cloned()
+    }
+
+    /// Rules, oldest first
+    pub fn list_rules(&self) -> Vec<AlertRule> {
+        let mut rules: Vec<AlertRule> = self.rules.read().unwrap().values().cloned().collect();
+        rules.sort_by_key(|rule| rule.created_at);
+        rules
+    }
+
+    /// Latest alerts, newest first
+    pub fn recent_alerts(&self) -> Vec<Alert> {
+        self.recent.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Evaluate a logged event and send the alerts it triggers
+    pub fn observe(&self, log: &AuditLog) {
+        for alert in self.evaluate(log) {
+            let channels = self.get_rule(alert.rule_id).map(|rule| rule.channels).unwrap_or_default();
+            let notifier = self.notifier.clone();
+            tokio::spawn(async move {
+                let notification = alert.notification();
+                for channel in &channels {
+                    if let Err(e) = notifier.notify(channel, &notification).await {
+                        tracing::error!("Failed to send alert of rule {}: {}", alert.rule_id, e);
+                    }
+                }
+            });
+        }
+    }
+
+    /// The alerts an event triggers
+    pub fn evaluate(&self, log: &AuditLog) -> Vec<Alert> {
+        let now = log.timestamp;
+        let mut rules = self.rules.write().unwrap();
+        let mut windows = self.windows.lock().unwrap();
+        let mut alerts = Vec::new();
+
+        for rule in rules.values_mut().filter(|rule| rule.enabled) {
+            let Some((subject, log_ids, message)) = Self::matches(rule, log, &mut windows) else {
+                continue;
+            };
+            let key = (rule.id, subject.clone());
+            let cooldown = Duration::minutes(rule.cooldown_minutes as i64);
+            if windows.fired.get(&key).is_some_and(|fired| now < *fired + cooldown) {
+                continue;
+            }
+            windows.fired.insert(key, now);
+            rule.last_triggered_at = Some(now);
+            alerts.push(Alert {
+                rule_id: rule.id,
+                rule_name: rule.name.clone(),
+                subject,
+                message,
+                log_ids,
+                triggered_at: now,
+            });
+        }
+
+        if windows.events.len() > MAX_TRACKED_SUBJECTS {
+            let idle = now - Duration::days(1);
+            windows.events.retain(|_, events| events.back().is_some_and(|(at, _)| *at > idle));
+            windows.fired.retain(|_, fired| *fired > idle);
+        }
+        drop(windows);
+        drop(rules);
+
+        let mut recent = self.recent.lock().unwrap();
+        for alert in &alerts {
+            recent.push_front(alert.clone());
+        }
+        recent.truncate(RECENT_ALERTS);
+        alerts
+    }
+
+    /// Whether `log` makes `rule` fire: the subject, the logs behind it and
+    /// a description
+    fn matches(rule: &AlertRule, log: &AuditLog, windows: &mut Windows) -> Option<(String, Vec<Uuid>, String)> {
+        match &rule.condition {
+            AlertCondition::FailedLogins { threshold, window_minutes } => {
+                if !matches!(log.action, AuditAction::Login) || matches!(log.result, AuditResult::Success) {
+                    return None;
+                }
+                let subject = if log.user_id.is_nil() { log.ip_address.clone() } else { log.user_id.to_string() };
+                let log_ids = Self::count(rule.id, &subject, log, *threshold, *window_minutes, windows)?;
+                let message = format!("{} failed logins within {} minutes", log_ids.len(), window_minutes);
+                Some((subject, log_ids, message))
+            }
+            AlertCondition::OffHoursPermissionChange { start_hour, end_hour, timezone, weekends } => {
+                if !matches!(log.action, AuditAction::PermissionChange) {
+                    return None;
+                }
+                let tz: Tz = timezone.parse().ok()?;
+                let local = log.timestamp.with_timezone(&tz);
+                let weekend = matches!(local.weekday(), Weekday::Sat | Weekday::Sun);
+                let business_hours = (*start_hour..*end_hour).contains(&local.hour());
+                if business_hours && (*weekends || !weekend) {
+                    return None;
+                }
+                let message = format!("Permission change at {} ({})", local.format("%a %H:%M"), timezone);
+                Some((log.user_id.to_string(), vec![log.id], message))
+            }
+            AlertCondition::MassDeletion { threshold, window_minutes } => {
+                if !matches!(log.action, AuditAction::Delete) || !matches!(log.result, AuditResult::Success) {
+                    return None;
+                }
+                let subject = log.user_id.to_string();
+                let log_ids = Self::count(rule.id, &subject, log, *threshold, *window_minutes, windows)?;
+                let message = format!("{} deletions within {} minutes", log_ids.len(), window_minutes);
+                Some((subject, log_ids, message))
+            }
+        }
+    }
+
+    /// Add `log` to the subject's window; once the window holds `threshold`
+    /// events they are taken out and returned
+    fn count(
+        rule_id: Uuid,
+        subject: &str,
+        log: &AuditLog,
+        threshold: u32,
+        window_minutes: u32,
+        windows: &mut Windows,
+    ) -> Option<Vec<Uuid>> {
+        let events = windows.events.entry((rule_id, subject.to_string())).or_default();
+        let start = log.timestamp - Duration::minutes(window_minutes as i64);
+        events.push_back((log.timestamp, log.id));
+        while events.front().is_some_and(|(at, _)| *at <= start) {
+            events.pop_front();
+        }
+        if events.len() < threshold as usize {
+            return None;
+        }
+        Some(events.drain(..).map(|(_, id)| id).collect())
+    }
+
+    /// Drop the windows and cooldowns of a rule
+    fn forget(&self, rule_id: Uuid) {
+        let mut windows = self.windows.lock().unwrap();
+        windows.events.retain(|(id, _), _| *id != rule_id);
+        windows.fired.retain(|(id, _), _| *id != rule_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use chrono::TimeZone;
+    use common::types::ResourceType;
+    use integration_service::integrations::IntegrationError;
+
+    struct NoNotifier;
+
+    #[async_trait]
+    impl Notifier for NoNotifier {
+        async fn notify(&self, _channel: &NotificationChannel, _notification: &Notification) -> Result<(), IntegrationError> {
+            Ok(())
+        }
+    }
+
+    fn rule(condition: AlertCondition) -> AlertRuleInput {
+        AlertRuleInput {
+            name: "test".to_string(),
+            condition,
+            channels: vec![NotificationChannel::Email { to: "sec@example.com".to_string() }],
+            cooldown_minutes: 30,
+            enabled: true,
+        }
+    }
+
+    fn event(user_id: Uuid, action: AuditAction, result: AuditResult, at: DateTime<Utc>) -> AuditLog {
+        let mut log = AuditLog::new(
+            user_id,
+            action,
+            ResourceType::User,
+            user_id,
+            "10.0.0.1".to_string(),
+            "test-agent".to_string(),
+            result,
+        );
+        log.timestamp = at;
+        log
+    }
+
+    #[test]
+    fn test_failed_logins_fire_once_per_cooldown() {
+        let engine = AlertEngine::new(Arc::new(NoNotifier));
+        engine
+            .create_rule(rule(AlertCondition::FailedLogins { threshold: 3, window_minutes: 5 }))
+            .unwrap();
+        let user = Uuid::new_v4();
+        let start = Utc::now();
+        let failure = |minutes: i64| {
+            event(user, AuditAction::Login, AuditResult::Failure("bad password".to_string()), start + Duration::minutes(minutes))
+        };
+
+        // Spread over more than the window: no alert
+        assert!(engine.evaluate(&failure(0)).is_empty());
+        assert!(engine.evaluate(&failure(4)).is_empty());
+        assert!(engine.evaluate(&failure(6)).is_empty());
+        let alerts = engine.evaluate(&failure(7));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].subject, user.to_string());
+        assert_eq!(alerts[0].log_ids.len(), 3);
+
+        // Quiet during the cooldown, even past the threshold again
+        for minute in 8..11 {
+            assert!(engine.evaluate(&failure(minute)).is_empty());
+        }
+        assert!(engine.evaluate(&event(user, AuditAction::Login, AuditResult::Success, start + Duration::minutes(11))).is_empty());
+        for minute in 38..41 {
+            let alerts = engine.evaluate(&failure(minute));
+            assert_eq!(alerts.len(), usize::from(minute == 40));
+        }
+        assert_eq!(engine.recent_alerts().len(), 2);
+        assert!(engine.list_rules()[0].last_triggered_at.is_some());
+    }
+
+    #[test]
+    fn test_permission_changes_outside_business_hours() {
+        let engine = AlertEngine::new(Arc::new(NoNotifier));
+        engine
+            .create_rule(rule(AlertCondition::OffHoursPermissionChange {
+                start_hour: 9,
+                end_hour: 18,
+                timezone: "Asia/Shanghai".to_string(),
+                weekends: false,
+            }))
+            .unwrap();
+        let change = |at: DateTime<Utc>| event(Uuid::new_v4(), AuditAction::PermissionChange, AuditResult::Success, at);
+
+        // Wednesday 10:00 and 20:00 in Shanghai, then Saturday 10:00
+        assert!(engine.evaluate(&change(Utc.with_ymd_and_hms(2026, 3, 4, 2, 0, 0).unwrap())).is_empty());
+        assert_eq!(engine.evaluate(&change(Utc.with_ymd_and_hms(2026, 3, 4, 12, 0, 0).unwrap())).len(), 1);
+        assert_eq!(engine.evaluate(&change(Utc.with_ymd_and_hms(2026, 3, 7, 2, 0, 0).unwrap())).len(), 1);
+    }
+
+    #[test]
+    fn test_mass_deletions_are_counted_per_user() {
+        let engine = AlertEngine::new(Arc::new(NoNotifier));
+        let created = engine
+            .create_rule(rule(AlertCondition::MassDeletion { threshold: 2, window_minutes: 10 }))
+            .unwrap();
+        let now = Utc::now();
+        let delete = |user: Uuid| event(user, AuditAction::Delete, AuditResult::Success, now);
+
+        assert!(engine.evaluate(&delete(Uuid::new_v4())).is_empty());
+        assert!(engine.evaluate(&delete(Uuid::new_v4())).is_empty());
+        let user = Uuid::new_v4();
+        assert!(engine.evaluate(&delete(user)).is_empty());
+        assert_eq!(engine.evaluate(&delete(user)).len(), 1);
+
+        let mut disabled = rule(AlertCondition::MassDeletion { threshold: 2, window_minutes: 10 });
+        disabled.enabled = false;
+        engine.update_rule(created.id, disabled).unwrap();
+        let user = Uuid::new_v4();
+        assert!(engine.evaluate(&delete(user)).is_empty());
+        assert!(engine.evaluate(&delete(user)).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        let engine = AlertEngine::new(Arc::new(NoNotifier));
+        let mut input = rule(AlertCondition::FailedLogins { threshold: 0, window_minutes: 5 });
+        assert!(matches!(engine.create_rule(input.clone()), Err(AlertError::InvalidRule(_))));
+
+        input.condition = AlertCondition::OffHoursPermissionChange {
+            start_hour: 9,
+            end_hour: 18,
+            timezone: "Mars/Olympus".to_string(),
+            weekends: false,
+        };
+        assert!(matches!(engine.create_rule(input.clone()), Err(AlertError::InvalidRule(_))));
+
+        input.condition = AlertCondition::FailedLogins { threshold: 5, window_minutes: 5 };
+        input.channels.clear();
+        assert!(matches!(engine.create_rule(input), Err(AlertError::InvalidRule(_))));
+        assert!(matches!(engine.delete_rule(Uuid::new_v4()), Err(AlertError::NotFound(_))));
+    }
+}
@@ -1,7 +1,23 @@
-use common::types::{AuditLog, AuditFilter, ExportFormat};
+use arrow_array::{ArrayRef, BooleanArray, RecordBatch, StringArray, TimestampMicrosecondArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use common::types::{AuditLog, AuditFilter, AuditResult, ExportFormat};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression as ParquetCompression, GzipLevel};
+use parquet::file::properties::WriterProperties;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
 
 use crate::{query::AuditQuery, storage::AuditError};
 
+/// Logs read from the database at a time by streaming exports
+pub const EXPORT_PAGE_SIZE: u32 = 1000;
+
+/// Rows of a Parquet row group; the writer buffers one group in memory
+const PARQUET_ROW_GROUP_SIZE: usize = 64 * 1024;
+
 /// Audit exporter for exporting logs in various formats
 pub struct AuditExporter {
     query: AuditQuery,
@@ -19,77 +35,226 @@ impl AuditExporter {
         format: ExportFormat,
     ) -> Result<Vec<u8>, AuditError> {
         let logs = self.query.query(filter).await?;
+        self.export_logs(&logs, format)
+    }
 
-        match format {
-            ExportFormat::Json => self.export_json(&logs),
-            ExportFormat::Csv => self.export_csv(&logs),
-        }
+    /// Export the given logs in the specified format
+    pub fn export_logs(&self, logs: &[AuditLog], format: ExportFormat) -> Result<Vec<u8>, AuditError> {
+        let mut writer = LogWriter::new(format, Vec::new())?;
+        writer.write(logs)?;
+        writer.finish()
     }
 
-    /// Export logs as JSON
-    fn export_json(&self, logs: &[AuditLog]) -> Result<Vec<u8>, AuditError> {
-        serde_json::to_vec_pretty(logs)
-            .map_err(|e| AuditError::ExportError(e.to_string()))
-    }
-
-    /// Export logs as CSV
-    fn export_csv(&self, logs: &[AuditLog]) -> Result<Vec<u8>, AuditError> {
-        let mut wtr = csv::Writer::from_writer(vec![]);
-
-        // Write header
-        wtr.write_record(&[
-            "id",
-            "user_id",
-            "action",
-            "resource_type",
-            "resource_id",
-            "ip_address",
-            "user_agent",
-            "timestamp",
-            "result",
-            "is_security_sensitive",
-        ])
-        .map_err(|e| AuditError::ExportError(e.to_string()))?;
-
-        // Write data
-        for log in logs {
-            wtr.write_record(&[
-                log.id.to_string(),
-                log.user_id.to_string(),
-                format!("{:?}", log.action),
-                format!("{:?}", log.resource_type),
-                log.resource_id.to_string(),
-                log.ip_address.clone(),
-                log.user_agent.clone(),
-                log.timestamp.to_rfc3339(),
-                match &log.result {
-                    common::types::AuditResult::Success => "Success".to_string(),
-                    common::types::AuditResult::Failure(e) => format!("Failure: {}", e),
-                    common::types::AuditResult::Denied => "Denied".to_string(),
-                },
-                log.is_security_sensitive.to_string(),
-            ])
-            .map_err(|e| AuditError::ExportError(e.to_string()))?;
+    /// Export every log matching `filter` to `out`, newest first, reading
+    /// them a page at a time so large ranges are never held in memory. The
+    /// filter's limit and offset are not used. Returns the number of logs
+    /// written.
+    pub async fn export_to<W: Write + Send>(
+        &self,
+        filter: &AuditFilter,
+        format: ExportFormat,
+        out: W,
+    ) -> Result<u64, AuditError> {
+        let mut writer = LogWriter::new(format, out)?;
+        let mut written = 0;
+        let mut last: Option<AuditLog> = None;
+        loop {
+            let page = self.query.query_page(filter, last.as_ref(), EXPORT_PAGE_SIZE).await?;
+            writer.write(&page)?;
+            written += page.len() as u64;
+            if page.len() < EXPORT_PAGE_SIZE as usize {
+                break;
+            }
+            last = page.into_iter().last();
         }
-
-        wtr.into_inner()
-            .map_err(|e| AuditError::ExportError(e.to_string()))
+        writer.finish()?.flush().map_err(export_error)?;
+        Ok(written)
     }
 
-    /// Export to file
+    /// Export every log matching `filter` to a file
     pub async fn export_to_file(
         &self,
         filter: AuditFilter,
         format: ExportFormat,
         path: &str,
-    ) -> Result<(), AuditError> {
-        let data = self.export(filter, format).await?;
-        
-        std::fs::write(path, data)
-            .map_err(|e| AuditError::ExportError(e.to_string()))?;
+    ) -> Result<u64, AuditError> {
+        let file = std::fs::File::create(path).map_err(export_error)?;
+        self.export_to(&filter, format, std::io::BufWriter::new(file)).await
+    }
+
+    /// Write logs to a gzip-compressed NDJSON archive at `path`. The file is
+    /// complete once this returns; a failed write leaves no file behind.
+    pub fn archive(&self, logs: &[AuditLog], path: &Path) -> Result<(), AuditError> {
+        let ndjson = self.export_logs(logs, ExportFormat::Ndjson)?;
+        let partial = path.with_extension("partial");
+        let write = || -> std::io::Result<()> {
+            let mut encoder = GzEncoder::new(std::fs::File::create(&partial)?, Compression::default());
+            encoder.write_all(&ndjson)?;
+            encoder.finish()?.sync_all()?;
+            std::fs::rename(&partial, path)
+        };
+        write().map_err(|e| {
+            let _ = std::fs::remove_file(&partial);
+            AuditError::ExportError(format!("Failed to write archive {}: {}", path.display(), e))
+        })
+    }
+}
 
+/// Writes logs in an export format, a page at a time
+enum LogWriter<W: Write + Send> {
+    /// A JSON array, one log per line
+    Json { out: W, empty: bool },
+    Csv(Box<csv::Writer<W>>),
+    Ndjson(W),
+    Parquet(Box<ArrowWriter<W>>),
+}
+
+impl<W: Write + Send> LogWriter<W> {
+    fn new(format: ExportFormat, mut out: W) -> Result<Self, AuditError> {
+        Ok(match format {
+            ExportFormat::Json => {
+                out.write_all(b"[").map_err(export_error)?;
+                LogWriter::Json { out, empty: true }
+            }
+            ExportFormat::Csv => {
+                let mut wtr = csv::Writer::from_writer(out);
+                wtr.write_record([
+                    "id",
+                    "user_id",
+                    "action",
+                    "resource_type",
+                    "resource_id",
+                    "ip_address",
+                    "user_agent",
+                    "timestamp",
+                    "result",
+                    "is_security_sensitive",
+                ])
+                .map_err(export_error)?;
+                LogWriter::Csv(Box::new(wtr))
+            }
+            ExportFormat::Ndjson => LogWriter::Ndjson(out),
+            ExportFormat::Parquet => {
+                let properties = WriterProperties::builder()
+                    .set_compression(ParquetCompression::GZIP(GzipLevel::default()))
+                    .set_max_row_group_size(PARQUET_ROW_GROUP_SIZE)
+                    .build();
+                let writer = ArrowWriter::try_new(out, parquet_schema(), Some(properties)).map_err(export_error)?;
+                LogWriter::Parquet(Box::new(writer))
+            }
+        })
+    }
+
+    fn write(&mut self, logs: &[AuditLog]) -> Result<(), AuditError> {
+        match self {
+            LogWriter::Json { out, empty } => {
+                for log in logs {
+                    out.write_all(if *empty { b"\n" } else { b",\n" }).map_err(export_error)?;
+                    serde_json::to_writer(&mut *out, log).map_err(export_error)?;
+                    *empty = false;
+                }
+            }
+            LogWriter::Csv(wtr) => {
+                for log in logs {
+                    wtr.write_record(&[
+                        log.id.to_string(),
+                        log.user_id.to_string(),
+                        format!("{:?}", log.action),
+                        format!("{:?}", log.resource_type),
+                        log.resource_id.to_string(),
+                        log.ip_address.clone(),
+                        log.user_agent.clone(),
+                        log.timestamp.to_rfc3339(),
+                        result_text(&log.result),
+                        log.is_security_sensitive.to_string(),
+                    ])
+                    .map_err(export_error)?;
+                }
+            }
+            LogWriter::Ndjson(out) => {
+                for log in logs {
+                    serde_json::to_writer(&mut *out, log).map_err(export_error)?;
+                    out.write_all(b"\n").map_err(export_error)?;
+                }
+            }
+            LogWriter::Parquet(writer) => {
+                if !logs.is_empty() {
+                    writer.write(&record_batch(logs)?).map_err(export_error)?;
+                }
+            }
+        }
         Ok(())
     }
+
+    /// Complete the export and return the output
+    fn finish(self) -> Result<W, AuditError> {
+        match self {
+            LogWriter::Json { mut out, .. } => {
+                out.write_all(b"\n]\n").map_err(export_error)?;
+                Ok(out)
+            }
+            LogWriter::Csv(wtr) => wtr.into_inner().map_err(export_error),
+            LogWriter::Ndjson(out) => Ok(out),
+            LogWriter::Parquet(writer) => writer.into_inner().map_err(export_error),
+        }
+    }
+}
+
+fn result_text(result: &AuditResult) -> String {
+    match result {
+        AuditResult::Success => "Success".to_string(),
+        AuditResult::Failure(e) => format!("Failure: {}", e),
+        AuditResult::Denied => "Denied".to_string(),
+    }
+}
+
+/// Columns of Parquet exports: the CSV columns plus the JSON details, with
+/// a UTC timestamp column
+fn parquet_schema() -> SchemaRef {
+    let text = |name: &str| Field::new(name, DataType::Utf8, false);
+    Arc::new(Schema::new(vec![
+        text("id"),
+        text("user_id"),
+        text("action"),
+        text("resource_type"),
+        text("resource_id"),
+        text("ip_address"),
+        text("user_agent"),
+        Field::new("timestamp", DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())), false),
+        text("result"),
+        text("details"),
+        Field::new("is_security_sensitive", DataType::Boolean, false),
+    ]))
+}
+
+fn record_batch(logs: &[AuditLog]) -> Result<RecordBatch, AuditError> {
+    let text = |value: fn(&AuditLog) -> String| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(logs.iter().map(value)))
+    };
+    let timestamps = TimestampMicrosecondArray::from_iter_values(logs.iter().map(|log| log.timestamp.timestamp_micros()))
+        .with_timezone("UTC");
+
+    RecordBatch::try_new(
+        parquet_schema(),
+        vec![
+            text(|log| log.id.to_string()),
+            text(|log| log.user_id.to_string()),
+            text(|log| format!("{:?}", log.action)),
+            text(|log| format!("{:?}", log.resource_type)),
+            text(|log| log.resource_id.to_string()),
+            text(|log| log.ip_address.clone()),
+            text(|log| log.user_agent.clone()),
+            Arc::new(timestamps),
+            text(|log| result_text(&log.result)),
+            text(|log| log.details.to_string()),
+            Arc::new(BooleanArray::from_iter(logs.iter().map(|log| Some(log.is_security_sensitive)))),
+        ],
+    )
+    .map_err(export_error)
+}
+
+fn export_error(e: impl std::fmt::Display) -> AuditError {
+    AuditError::ExportError(e.to_string())
 }
 
 #[cfg(test)]
@@ -97,8 +262,8 @@ mod tests {
     use super::*;
     use uuid::Uuid;
 
-    #[test]
-    fn test_export_json() {
+    #[tokio::test]
+    async fn test_export_json() {
         let logs = vec![AuditLog::new(
             Uuid::new_v4(),
             common::types::AuditAction::Create,
@@ -111,13 +276,108 @@ mod tests {
 
         let query = AuditQuery::new(sqlx::PgPool::connect_lazy("postgresql://localhost/test").unwrap());
         let exporter = AuditExporter::new(query);
-        let result = exporter.export_json(&logs);
+        let result = exporter.export_logs(&logs, ExportFormat::Json);
 
         assert!(result.is_ok());
     }
 
-    #[test]
-    fn test_export_csv() {
+    fn sample_logs(count: usize) -> Vec<AuditLog> {
+        (0..count)
+            .map(|_| {
+                AuditLog::new(
+                    Uuid::new_v4(),
+                    common::types::AuditAction::Update,
+                    common::types::ResourceType::Settings,
+                    Uuid::new_v4(),
+                    "127.0.0.1".to_string(),
+                    "test-agent".to_string(),
+                    common::types::AuditResult::Denied,
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_json_export_written_in_pages_is_one_array() {
+        let logs = sample_logs(3);
+        let mut writer = LogWriter::new(ExportFormat::Json, Vec::new()).unwrap();
+        writer.write(&logs[..2]).unwrap();
+        writer.write(&logs[2..]).unwrap();
+        let exported: Vec<AuditLog> = serde_json::from_slice(&writer.finish().unwrap()).unwrap();
+        assert_eq!(exported.len(), 3);
+
+        let empty = LogWriter::new(ExportFormat::Json, Vec::new()).unwrap().finish().unwrap();
+        assert_eq!(serde_json::from_slice::<Vec<AuditLog>>(&empty).unwrap().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_parquet_export_reads_back() {
+        use arrow_array::Array;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let logs = sample_logs(5);
+        let query = AuditQuery::new(sqlx::PgPool::connect_lazy("postgresql://localhost/test").unwrap());
+        let exporter = AuditExporter::new(query);
+        let path = std::env::temp_dir().join(format!("audit-export-{}.parquet", Uuid::new_v4()));
+        std::fs::write(&path, exporter.export_logs(&logs, ExportFormat::Parquet).unwrap()).unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+        assert_eq!(batches.iter().map(|batch| batch.num_rows()).sum::<usize>(), 5);
+        let ids = batches[0]
+            .column_by_name("id")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(ids.value(0), logs[0].id.to_string());
+        let results = batches[0].column_by_name("result").unwrap();
+        assert_eq!(results.len(), 5);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_archive_is_gzipped_ndjson() {
+        use std::io::Read;
+
+        let logs: Vec<AuditLog> = (0..3)
+            .map(|_| {
+                AuditLog::new(
+                    Uuid::new_v4(),
+                    common::types::AuditAction::Login,
+                    common::types::ResourceType::User,
+                    Uuid::new_v4(),
+                    "127.0.0.1".to_string(),
+                    "test-agent".to_string(),
+                    common::types::AuditResult::Success,
+                )
+            })
+            .collect();
+        let query = AuditQuery::new(sqlx::PgPool::connect_lazy("postgresql://localhost/test").unwrap());
+        let exporter = AuditExporter::new(query);
+        let dir = std::env::temp_dir().join(format!("audit-archive-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("logs.ndjson.gz");
+
+        exporter.archive(&logs, &path).unwrap();
+        let mut ndjson = String::new();
+        flate2::read::GzDecoder::new(std::fs::File::open(&path).unwrap())
+            .read_to_string(&mut ndjson)
+            .unwrap();
+        let ids: Vec<Uuid> = ndjson
+            .lines()
+            .map(|line| serde_json::from_str::<AuditLog>(line).unwrap().id)
+            .collect();
+        assert_eq!(ids, logs.iter().map(|log| log.id).collect::<Vec<_>>());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_csv() {
         let logs = vec![AuditLog::new(
             Uuid::new_v4(),
             common::types::AuditAction::Create,
@@ -130,7 +390,7 @@ mod tests {
 
         let query = AuditQuery::new(sqlx::PgPool::connect_lazy("postgresql://localhost/test").unwrap());
         let exporter = AuditExporter::new(query);
-        let result = exporter.export_csv(&logs);
+        let result = exporter.export_logs(&logs, ExportFormat::Csv);
 
         assert!(result.is_ok());
     }
@@ -0,0 +1,199 @@
+use chrono::{DateTime, Utc};
+use common::types::{AuditFilter, ExportFormat};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+use crate::export::AuditExporter;
+use crate::storage::AuditError;
+
+/// Exports run at once; later jobs wait
+const MAX_RUNNING_EXPORTS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportJobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// An export of audit logs to a file, run in the background
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportJob {
+    pub id: Uuid,
+    pub requested_by: Uuid,
+    pub filter: AuditFilter,
+    pub format: ExportFormat,
+    pub status: ExportJobStatus,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub exported_logs: u64,
+    /// Name of the exported file, once completed
+    pub file_name: Option<String>,
+    pub file_size: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Background exports of large log ranges.
+///
+/// Each job streams its logs to a file in the export directory, named after
+/// the job; the file is only there once the job completes. Jobs are kept in
+/// memory, so their status is lost on restart while their files remain.
+pub struct AuditExportJobs {
+    exporter: AuditExporter,
+    dir: PathBuf,
+    jobs: RwLock<HashMap<Uuid, ExportJob>>,
+    running: Semaphore,
+}
+
+impl AuditExportJobs {
+    pub fn new(exporter: AuditExporter, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            exporter,
+            dir: dir.into(),
+            jobs: RwLock::new(HashMap::new()),
+            running: Semaphore::new(MAX_RUNNING_EXPORTS),
+        }
+    }
+
+    /// Queue an export and return its job
+    pub fn create(self: &Arc<Self>, requested_by: Uuid, filter: AuditFilter, format: ExportFormat) -> ExportJob {
+        let job = ExportJob {
+            id: Uuid::new_v4(),
+            requested_by,
+            filter,
+            format,
+            status: ExportJobStatus::Pending,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            exported_logs: 0,
+            file_name: None,
+            file_size: None,
+            error: None,
+        };
+        self.jobs.write().unwrap().insert(job.id, job.clone());
+
+        let jobs = self.clone();
+        let queued = job.clone();
+        tokio::spawn(async move { jobs.run(queued).await });
+        job
+    }
+
+    pub fn get(&self, job_id: Uuid) -> Option<ExportJob> {
+        self.jobs.read().unwrap().get(&job_id).cloned()
+    }
+
+    /// Jobs, newest first
+    pub fn list(&self) -> Vec<ExportJob> {
+        let mut jobs: Vec<ExportJob> = self.jobs.read().unwrap().values().cloned().collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+        jobs
+    }
+
+    /// Path of a completed job's file
+    pub fn file_path(&self, job: &ExportJob) -> Option<PathBuf> {
+        job.file_name.as_ref().map(|name| self.dir.join(name))
+    }
+
+    async fn run(&self, job: ExportJob) {
+        let Ok(_permit) = self.running.acquire().await else {
+            return;
+        };
+        self.update(job.id, |job| {
+            job.status = ExportJobStatus::Running;
+            job.started_at = Some(Utc::now());
+        });
+
+        let file_name = format!("audit-export-{}.{}", job.id, job.format.extension());
+        let result = self.export(&job, &file_name).await;
+        if let Err(e) = &result {
+            tracing::error!("Audit export {} failed: {}", job.id, e);
+        }
+        self.update(job.id, |job| {
+            job.finished_at = Some(Utc::now());
+            match result {
+                Ok((exported_logs, file_size)) => {
+                    job.status = ExportJobStatus::Completed;
+                    job.exported_logs = exported_logs;
+                    job.file_name = Some(file_name);
+                    job.file_size = Some(file_size);
+                }
+                Err(e) => {
+                    job.status = ExportJobStatus::Failed;
+                    job.error = Some(e.to_string());
+                }
+            }
+        });
+    }
+
+    /// Write the job's logs to `file_name`; returns the logs written and
+    /// the file size
+    async fn export(&self, job: &ExportJob, file_name: &str) -> Result<(u64, u64), AuditError> {
+        let io_error = |e: std::io::Error| AuditError::ExportError(e.to_string());
+        std::fs::create_dir_all(&self.dir).map_err(io_error)?;
+        let path = self.dir.join(file_name);
+        let partial = path.with_extension("partial");
+
+        let file = std::fs::File::create(&partial).map_err(io_error)?;
+        let written = self
+            .exporter
+            .export_to(&job.filter, job.format, std::io::BufWriter::new(file))
+            .await
+            .and_then(|written| {
+                std::fs::rename(&partial, &path).map_err(io_error)?;
+                Ok(written)
+            });
+        if written.is_err() {
+            let _ = std::fs::remove_file(&partial);
+        }
+        let written = written?;
+        let size = std::fs::metadata(&path).map_err(io_error)?.len();
+        Ok((written, size))
+    }
+
+    fn update(&self, job_id: Uuid, f: impl FnOnce(&mut ExportJob)) {
+        if let Some(job) = self.jobs.write().unwrap().get_mut(&job_id) {
+            f(job);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::AuditQuery;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_failed_export_leaves_no_file() {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgresql://localhost:1/test")
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("audit-exports-{}", Uuid::new_v4()));
+        let jobs = Arc::new(AuditExportJobs::new(AuditExporter::new(AuditQuery::new(pool)), &dir));
+
+        let job = jobs.create(Uuid::new_v4(), AuditFilter::default(), ExportFormat::Csv);
+        assert_eq!(job.status, ExportJobStatus::Pending);
+        let job = loop {
+            let job = jobs.get(job.id).unwrap();
+            if job.finished_at.is_some() {
+                break job;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        };
+
+        assert_eq!(job.status, ExportJobStatus::Failed);
+        assert!(job.error.is_some());
+        assert_eq!(jobs.file_path(&job), None);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,9 +1,15 @@
+pub mod alerts;
 pub mod export;
+pub mod jobs;
 pub mod logger;
 pub mod query;
+pub mod retention;
 pub mod storage;
 
+pub use alerts::{AlertEngine, AlertRule, AlertRuleInput};
 pub use export::AuditExporter;
+pub use jobs::{AuditExportJobs, ExportJob, ExportJobStatus};
 pub use logger::AuditLogger;
 pub use query::AuditQuery;
+pub use retention::{AuditRetention, RetentionPolicy};
 pub use storage::AuditStorage;
@@ -1,14 +1,19 @@
+use ai_service::injection::{BlockedPrompt, InjectionAuditor};
+use async_trait::async_trait;
 use common::types::{AuditLog, AuditAction, AuditResult, ResourceType};
+use integration_service::{CredentialAccess, CredentialAuditor};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+use crate::alerts::AlertEngine;
 use crate::storage::{AuditStorage, AuditError};
 
 /// Audit logger for creating and logging audit entries
 pub struct AuditLogger {
     storage: Arc<AuditStorage>,
     batch_sender: mpsc::UnboundedSender<AuditLog>,
+    alerts: Option<Arc<AlertEngine>>,
 }
 
 impl AuditLogger {
@@ -50,11 +55,26 @@ impl AuditLogger {
         Self {
             storage,
             batch_sender: tx,
+            alerts: None,
         }
     }
 
+    /// Evaluate every logged entry against the engine's alert rules
+    pub fn with_alert_engine(mut self, alerts: Arc<AlertEngine>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    /// Storage the logs are written to
+    pub fn storage(&self) -> &Arc<AuditStorage> {
+        &self.storage
+    }
+
     /// Log an audit entry
     pub fn log(&self, log: AuditLog) -> Result<(), AuditError> {
+        if let Some(alerts) = &self.alerts {
+            alerts.observe(&log);
+        }
         self.batch_sender
             .send(log)
             .map_err(|e| AuditError::StorageError(e.to_string()))
@@ -84,6 +104,7 @@ impl AuditLogger {
     }
 
     /// Log a failed action
+    #[allow(clippy::too_many_arguments)]
     pub fn log_failure(
         &self,
         user_id: Uuid,
@@ -132,10 +153,73 @@ impl AuditLogger {
 
     /// Log immediately without batching (for critical events)
     pub async fn log_immediate(&self, log: AuditLog) -> Result<(), AuditError> {
+        if let Some(alerts) = &self.alerts {
+            alerts.observe(&log);
+        }
         self.storage.store(&log).await
     }
 }
 
+/// Blocked prompt injection attempts are logged as denied executions of the
+/// workflow, by no user
+#[async_trait]
+impl InjectionAuditor for AuditLogger {
+    async fn record_blocked(&self, attempt: &BlockedPrompt) {
+        let mut log = AuditLog::new(
+            Uuid::nil(),
+            AuditAction::Execute,
+            ResourceType::Workflow,
+            attempt.workflow_id,
+            "unknown".to_string(),
+            "unknown".to_string(),
+            AuditResult::Denied,
+        );
+        log.details = serde_json::json!({
+            "event": "prompt_injection_blocked",
+            "organization_id": attempt.organization_id,
+            "execution_id": attempt.execution_id,
+            "node_id": attempt.node_id,
+            "score": attempt.score,
+            "rules": attempt.rules,
+        });
+        log.is_security_sensitive = true;
+        if let Err(e) = self.log(log) {
+            tracing::error!("Failed to record blocked prompt of execution {}: {}", attempt.execution_id, e);
+        }
+    }
+}
+
+/// Credential decryptions are logged as reads of the credential by the
+/// user it is decrypted for
+#[async_trait]
+impl CredentialAuditor for AuditLogger {
+    async fn record_access(&self, access: &CredentialAccess) {
+        let result = match &access.error {
+            Some(error) => AuditResult::Failure(error.clone()),
+            None => AuditResult::Success,
+        };
+        let mut log = AuditLog::new(
+            access.used_by.user_id(),
+            AuditAction::Read,
+            ResourceType::Integration,
+            access.credential_id,
+            "unknown".to_string(),
+            "unknown".to_string(),
+            result,
+        );
+        log.details = serde_json::json!({
+            "event": "credential_decrypted",
+            "integration": access.integration,
+            "owner": access.owner,
+            "used_by": access.used_by,
+        });
+        log.is_security_sensitive = true;
+        if let Err(e) = self.log(log) {
+            tracing::error!("Failed to record access to credential {}: {}", access.credential_id, e);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,9 +1,15 @@
+use chrono::{DateTime, Utc};
 use common::types::{AuditLog, AuditFilter};
-use sqlx::{PgPool, Row};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
 use uuid::Uuid;
 
+use crate::retention::RetentionCategory;
 use crate::storage::AuditError;
 
+/// Most logs one query returns
+pub const MAX_QUERY_LIMIT: u32 = 1000;
+
 /// Audit query for searching and filtering audit logs
 pub struct AuditQuery {
     pool: PgPool,
@@ -14,83 +20,42 @@ impl AuditQuery {
         Self { pool }
     }
 
-    /// Query audit logs with filters
+    /// Query audit logs with filters, newest first
     pub async fn query(&self, filter: AuditFilter) -> Result<Vec<AuditLog>, AuditError> {
-        let mut query = String::from(
-            "SELECT id, user_id, action, resource_type, resource_id, 
-             ip_address, user_agent, timestamp, result, details, 
-             is_security_sensitive FROM audit_logs WHERE 1=1"
-        );
-
-        if let Some(user_id) = filter.user_id {
-            query.push_str(&format!(" AND user_id = '{}'", user_id));
-        }
-
-        if let Some(action) = filter.action {
-            query.push_str(&format!(" AND action = '{:?}'", action));
-        }
-
-        if let Some(resource_type) = filter.resource_type {
-            query.push_str(&format!(" AND resource_type = '{:?}'", resource_type));
-        }
-
-        if let Some(start_time) = filter.start_time {
-            query.push_str(&format!(" AND timestamp >= '{}'", start_time));
-        }
+        let rows = build_query(&filter)
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AuditError::QueryError(e.to_string()))?;
 
-        if let Some(end_time) = filter.end_time {
-            query.push_str(&format!(" AND timestamp <= '{}'", end_time));
-        }
+        let logs = rows.into_iter().map(row_to_log).collect();
 
-        if filter.security_only {
-            query.push_str(" AND is_security_sensitive = true");
-        }
-
-        query.push_str(" ORDER BY timestamp DESC LIMIT 1000");
+        Ok(logs)
+    }
 
-        let rows = sqlx::query(&query)
+    /// The page of logs matching `filter` that follows `after`, the last
+    /// log of the previous page, newest first. The filter's limit and
+    /// offset are not used.
+    pub async fn query_page(
+        &self,
+        filter: &AuditFilter,
+        after: Option<&AuditLog>,
+        limit: u32,
+    ) -> Result<Vec<AuditLog>, AuditError> {
+        let rows = build_page_query(filter, after, limit)
+            .build()
             .fetch_all(&self.pool)
             .await
             .map_err(|e| AuditError::QueryError(e.to_string()))?;
 
-        let logs = rows
-            .into_iter()
-            .map(|row| {
-                let result_str: String = row.get("result");
-                let result = match result_str.as_str() {
-                    "Success" => common::types::AuditResult::Success,
-                    "Denied" => common::types::AuditResult::Denied,
-                    _ => common::types::AuditResult::Failure("Unknown".to_string()),
-                };
-
-                AuditLog {
-                    id: row.get("id"),
-                    user_id: row.get("user_id"),
-                    action: parse_audit_action(row.get("action")),
-                    resource_type: parse_resource_type(row.get("resource_type")),
-                    resource_id: row.get("resource_id"),
-                    ip_address: row.get("ip_address"),
-                    user_agent: row.get("user_agent"),
-                    timestamp: row.get("timestamp"),
-                    result,
-                    details: row.get("details"),
-                    is_security_sensitive: row.get("is_security_sensitive"),
-                }
-            })
-            .collect();
-
-        Ok(logs)
+        Ok(rows.into_iter().map(row_to_log).collect())
     }
 
     /// Get security-sensitive logs
     pub async fn get_security_alerts(&self) -> Result<Vec<AuditLog>, AuditError> {
         let filter = AuditFilter {
-            user_id: None,
-            action: None,
-            resource_type: None,
-            start_time: None,
-            end_time: None,
             security_only: true,
+            ..Default::default()
         };
 
         self.query(filter).await
@@ -100,61 +65,177 @@ impl AuditQuery {
     pub async fn get_user_logs(&self, user_id: Uuid) -> Result<Vec<AuditLog>, AuditError> {
         let filter = AuditFilter {
             user_id: Some(user_id),
-            action: None,
-            resource_type: None,
-            start_time: None,
-            end_time: None,
-            security_only: false,
+            ..Default::default()
         };
 
         self.query(filter).await
     }
 
-    /// Get recent logs
-    pub async fn get_recent_logs(&self, limit: i32) -> Result<Vec<AuditLog>, AuditError> {
-        let query = format!(
-            "SELECT id, user_id, action, resource_type, resource_id, 
-             ip_address, user_agent, timestamp, result, details, 
-             is_security_sensitive FROM audit_logs 
-             ORDER BY timestamp DESC LIMIT {}",
-            limit
-        );
+    /// Logs written by a user or about the user and the given resources
+    /// (e.g. the workflows they own), newest first
+    pub async fn get_logs_referencing(&self, user_id: Uuid, resource_ids: &[Uuid]) -> Result<Vec<AuditLog>, AuditError> {
+        let mut resource_ids = resource_ids.to_vec();
+        resource_ids.push(user_id);
+        let rows = sqlx::query(
+            "SELECT id, user_id, action, resource_type, resource_id,
+             ip_address, user_agent, created_at AS timestamp, result, details,
+             is_security_sensitive FROM audit_logs
+             WHERE user_id = $1 OR resource_id = ANY($2)
+             ORDER BY created_at DESC",
+        )
+        .bind(user_id)
+        .bind(&resource_ids)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditError::QueryError(e.to_string()))?;
 
-        let rows = sqlx::query(&query)
+        Ok(rows.into_iter().map(row_to_log).collect())
+    }
+
+    /// Logs of a retention category created before `before`, oldest first
+    pub async fn expired(
+        &self,
+        category: &RetentionCategory,
+        before: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<AuditLog>, AuditError> {
+        let rows = build_expired_query(category, before, limit)
+            .build()
             .fetch_all(&self.pool)
             .await
             .map_err(|e| AuditError::QueryError(e.to_string()))?;
 
-        let logs = rows
-            .into_iter()
-            .map(|row| {
-                let result_str: String = row.get("result");
-                let result = match result_str.as_str() {
-                    "Success" => common::types::AuditResult::Success,
-                    "Denied" => common::types::AuditResult::Denied,
-                    _ => common::types::AuditResult::Failure("Unknown".to_string()),
-                };
-
-                AuditLog {
-                    id: row.get("id"),
-                    user_id: row.get("user_id"),
-                    action: parse_audit_action(row.get("action")),
-                    resource_type: parse_resource_type(row.get("resource_type")),
-                    resource_id: row.get("resource_id"),
-                    ip_address: row.get("ip_address"),
-                    user_agent: row.get("user_agent"),
-                    timestamp: row.get("timestamp"),
-                    result,
-                    details: row.get("details"),
-                    is_security_sensitive: row.get("is_security_sensitive"),
-                }
-            })
-            .collect();
+        Ok(rows.into_iter().map(row_to_log).collect())
+    }
+
+    /// Get recent logs
+    pub async fn get_recent_logs(&self, limit: i32) -> Result<Vec<AuditLog>, AuditError> {
+        let rows = sqlx::query(
+            "SELECT id, user_id, action, resource_type, resource_id,
+             ip_address, user_agent, created_at AS timestamp, result, details,
+             is_security_sensitive FROM audit_logs
+             ORDER BY created_at DESC LIMIT $1",
+        )
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditError::QueryError(e.to_string()))?;
+
+        let logs = rows.into_iter().map(row_to_log).collect();
 
         Ok(logs)
     }
 }
 
+/// Query of the logs matching a filter; every filter value is a bound parameter
+fn build_query(filter: &AuditFilter) -> QueryBuilder<'static, Postgres> {
+    let mut query = filtered_query(filter);
+    let limit = filter.limit.unwrap_or(MAX_QUERY_LIMIT).clamp(1, MAX_QUERY_LIMIT);
+    query.push(" ORDER BY created_at DESC, id DESC LIMIT ")
+        .push_bind(limit as i64)
+        .push(" OFFSET ")
+        .push_bind(filter.offset.unwrap_or(0) as i64);
+    query
+}
+
+/// Query of the page of logs following `after`. Pages are keyed on the
+/// last log's time and ID, so deep pages cost no more than the first.
+fn build_page_query(filter: &AuditFilter, after: Option<&AuditLog>, limit: u32) -> QueryBuilder<'static, Postgres> {
+    let mut query = filtered_query(filter);
+    if let Some(after) = after {
+        query.push(" AND (created_at, id) < (")
+            .push_bind(after.timestamp)
+            .push(", ")
+            .push_bind(after.id)
+            .push(")");
+    }
+    query.push(" ORDER BY created_at DESC, id DESC LIMIT ")
+        .push_bind(limit as i64);
+    query
+}
+
+fn filtered_query(filter: &AuditFilter) -> QueryBuilder<'static, Postgres> {
+    let mut query = QueryBuilder::new(
+        "SELECT id, user_id, action, resource_type, resource_id, \
+         ip_address, user_agent, created_at AS timestamp, result, details, \
+         is_security_sensitive FROM audit_logs WHERE TRUE",
+    );
+
+    if let Some(user_id) = filter.user_id {
+        query.push(" AND user_id = ").push_bind(user_id);
+    }
+    if let Some(action) = &filter.action {
+        query.push(" AND action = ").push_bind(format!("{:?}", action));
+    }
+    if let Some(resource_type) = &filter.resource_type {
+        query.push(" AND resource_type = ").push_bind(format!("{:?}", resource_type));
+    }
+    if let Some(start_time) = filter.start_time {
+        query.push(" AND created_at >= ").push_bind(start_time);
+    }
+    if let Some(end_time) = filter.end_time {
+        query.push(" AND created_at <= ").push_bind(end_time);
+    }
+    if filter.security_only {
+        query.push(" AND is_security_sensitive = true");
+    }
+    query
+}
+
+/// Query of the expired logs of a retention category
+fn build_expired_query(category: &RetentionCategory, before: DateTime<Utc>, limit: u32) -> QueryBuilder<'static, Postgres> {
+    let mut query = QueryBuilder::new(
+        "SELECT id, user_id, action, resource_type, resource_id, \
+         ip_address, user_agent, created_at AS timestamp, result, details, \
+         is_security_sensitive FROM audit_logs WHERE ",
+    );
+
+    match category {
+        RetentionCategory::Security => {
+            query.push("is_security_sensitive = true");
+        }
+        RetentionCategory::Resource(resource_type) => {
+            query.push("is_security_sensitive = false AND resource_type = ")
+                .push_bind(resource_type.clone());
+        }
+        RetentionCategory::Default { except } => {
+            query.push("is_security_sensitive = false AND resource_type <> ALL(")
+                .push_bind(except.clone())
+                .push(")");
+        }
+    }
+    query.push(" AND created_at < ")
+        .push_bind(before)
+        .push(" ORDER BY created_at ASC, id ASC LIMIT ")
+        .push_bind(limit as i64);
+    query
+}
+
+/// Anonymized entries have no user, address or user agent; they read back
+/// with the nil user ID and empty strings
+fn row_to_log(row: PgRow) -> AuditLog {
+    let result_str: String = row.get("result");
+    let result = match result_str.as_str() {
+        "Success" => common::types::AuditResult::Success,
+        "Denied" => common::types::AuditResult::Denied,
+        _ => common::types::AuditResult::Failure("Unknown".to_string()),
+    };
+
+    AuditLog {
+        id: row.get("id"),
+        user_id: row.get::<Option<Uuid>, _>("user_id").unwrap_or_default(),
+        action: parse_audit_action(row.get("action")),
+        resource_type: parse_resource_type(row.get("resource_type")),
+        resource_id: row.get("resource_id"),
+        ip_address: row.get::<Option<String>, _>("ip_address").unwrap_or_default(),
+        user_agent: row.get::<Option<String>, _>("user_agent").unwrap_or_default(),
+        timestamp: row.get("timestamp"),
+        result,
+        details: row.get("details"),
+        is_security_sensitive: row.get("is_security_sensitive"),
+    }
+}
+
 fn parse_audit_action(s: String) -> common::types::AuditAction {
     match s.as_str() {
         "Create" => common::types::AuditAction::Create,
@@ -166,6 +247,7 @@ fn parse_audit_action(s: String) -> common::types::AuditAction {
         "Logout" => common::types::AuditAction::Logout,
         "PermissionChange" => common::types::AuditAction::PermissionChange,
         "ConfigChange" => common::types::AuditAction::ConfigChange,
+        "Inspect" => common::types::AuditAction::Inspect,
         _ => common::types::AuditAction::Read,
     }
 }
@@ -178,6 +260,7 @@ fn parse_resource_type(s: String) -> common::types::ResourceType {
         "User" => common::types::ResourceType::User,
         "AuditLog" => common::types::ResourceType::AuditLog,
         "Settings" => common::types::ResourceType::Settings,
+        "Execution" => common::types::ResourceType::Execution,
         _ => common::types::ResourceType::Workflow,
     }
 }
@@ -186,6 +269,60 @@ fn parse_resource_type(s: String) -> common::types::ResourceType {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_query_binds_filter_values() {
+        let filter = AuditFilter {
+            user_id: Some(Uuid::new_v4()),
+            action: Some(common::types::AuditAction::Login),
+            security_only: true,
+            limit: Some(50),
+            offset: Some(100),
+            ..Default::default()
+        };
+        let query = build_query(&filter);
+        assert!(query.sql().ends_with(
+            "WHERE TRUE AND user_id = $1 AND action = $2 AND is_security_sensitive = true \
+             ORDER BY created_at DESC, id DESC LIMIT $3 OFFSET $4"
+        ));
+    }
+
+    #[test]
+    fn test_pages_follow_the_previous_log() {
+        let filter = AuditFilter { security_only: true, ..Default::default() };
+        let first = build_page_query(&filter, None, 500);
+        assert!(first.sql().ends_with(
+            "WHERE TRUE AND is_security_sensitive = true ORDER BY created_at DESC, id DESC LIMIT $1"
+        ));
+
+        let last = AuditLog::new(
+            Uuid::new_v4(),
+            common::types::AuditAction::Login,
+            common::types::ResourceType::User,
+            Uuid::new_v4(),
+            "127.0.0.1".to_string(),
+            "test-agent".to_string(),
+            common::types::AuditResult::Success,
+        );
+        let next = build_page_query(&filter, Some(&last), 500);
+        assert!(next.sql().ends_with(
+            "AND is_security_sensitive = true AND (created_at, id) < ($1, $2) \
+             ORDER BY created_at DESC, id DESC LIMIT $3"
+        ));
+    }
+
+    #[test]
+    fn test_expired_query_excludes_other_categories() {
+        let query = build_expired_query(
+            &RetentionCategory::Default { except: vec!["Execution".to_string()] },
+            chrono::Utc::now(),
+            100,
+        );
+        assert!(query.sql().ends_with(
+            "WHERE is_security_sensitive = false AND resource_type <> ALL($1) \
+             AND created_at < $2 ORDER BY created_at ASC, id ASC LIMIT $3"
+        ));
+    }
+
     #[test]
     fn test_parse_audit_action() {
         assert!(matches!(
@@ -0,0 +1,292 @@
+use chrono::{DateTime, Utc};
+use common::config::AuditConfig;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::export::AuditExporter;
+use crate::storage::{AuditError, AuditStorage, TableUsage};
+
+/// Logs archived into one file
+const ARCHIVE_BATCH: u32 = 1000;
+
+/// How long audit logs are kept, by category.
+///
+/// Security-sensitive logs use `security_days` whatever their resource;
+/// other logs use the window of their resource type, or `default_days`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RetentionPolicy {
+    pub default_days: u32,
+    pub security_days: u32,
+    /// Windows by resource type name (`Workflow`, `Execution`, ...)
+    pub resource_days: BTreeMap<String, u32>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            default_days: 90,
+            security_days: 365,
+            resource_days: BTreeMap::new(),
+        }
+    }
+}
+
+impl RetentionPolicy {
+    pub fn from_config(config: &AuditConfig) -> Self {
+        Self {
+            default_days: config.retention_days,
+            security_days: config.security_retention_days,
+            resource_days: config.resource_retention_days.clone(),
+        }
+    }
+
+    /// Every category with its window in days; each log is in exactly one
+    pub fn categories(&self) -> Vec<(RetentionCategory, u32)> {
+        let mut categories = vec![(RetentionCategory::Security, self.security_days)];
+        categories.extend(
+            self.resource_days
+                .iter()
+                .map(|(resource_type, days)| (RetentionCategory::Resource(resource_type.clone()), *days)),
+        );
+        categories.push((
+            RetentionCategory::Default {
+                except: self.resource_days.keys().cloned().collect(),
+            },
+            self.default_days,
+        ));
+        categories
+    }
+}
+
+/// Logs sharing a retention window
+#[derive(Debug, Clone, PartialEq)]
+pub enum RetentionCategory {
+    /// Security-sensitive logs
+    Security,
+    /// Other logs of a resource type with its own window
+    Resource(String),
+    /// Other logs of the remaining resource types
+    Default { except: Vec<String> },
+}
+
+impl RetentionCategory {
+    /// Name used in archive file names
+    pub fn name(&self) -> String {
+        match self {
+            RetentionCategory::Security => "security".to_string(),
+            RetentionCategory::Resource(resource_type) => resource_type.to_lowercase(),
+            RetentionCategory::Default { .. } => "default".to_string(),
+        }
+    }
+}
+
+/// Outcome of a purge
+#[derive(Debug, Clone, Serialize)]
+pub struct PurgeReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub archived_logs: u64,
+    pub deleted_logs: u64,
+    pub archive_files: Vec<PathBuf>,
+    /// Set when the purge stopped early; logs archived before it are deleted
+    pub error: Option<String>,
+}
+
+/// Disk space taken by audit logs, stored and archived
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageUsage {
+    #[serde(flatten)]
+    pub table: TableUsage,
+    pub archive_files: u64,
+    pub archive_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionStatus {
+    pub policy: RetentionPolicy,
+    pub next_purge_at: Option<DateTime<Utc>>,
+    pub last_purge: Option<PurgeReport>,
+    pub usage: StorageUsage,
+}
+
+#[derive(Default)]
+struct PurgeState {
+    next_purge_at: Option<DateTime<Utc>>,
+    last_purge: Option<PurgeReport>,
+}
+
+/// Scheduled purge of expired audit logs.
+///
+/// Each purge archives the expired logs of every category to gzip-compressed
+/// NDJSON files, in batches of 1000 oldest first, and deletes a batch only
+/// once its file is written.
+pub struct AuditRetention {
+    storage: Arc<AuditStorage>,
+    exporter: AuditExporter,
+    policy: RetentionPolicy,
+    archive_dir: PathBuf,
+    interval: Duration,
+    state: Mutex<PurgeState>,
+}
+
+impl AuditRetention {
+    pub fn new(storage: Arc<AuditStorage>, policy: RetentionPolicy, archive_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            exporter: AuditExporter::new(storage.query()),
+            storage,
+            policy,
+            archive_dir: archive_dir.into(),
+            interval: Duration::from_secs(24 * 60 * 60),
+            state: Mutex::new(PurgeState::default()),
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn policy(&self) -> &RetentionPolicy {
+        &self.policy
+    }
+
+    /// Purge now, then every interval
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        self.state.lock().unwrap().next_purge_at = Some(Utc::now());
+        tokio::spawn(async move {
+            loop {
+                self.run_once(Utc::now()).await;
+                self.state.lock().unwrap().next_purge_at = Some(Utc::now() + self.interval);
+                tokio::time::sleep(self.interval).await;
+            }
+        })
+    }
+
+    /// Archive and delete the logs expired at `now`
+    pub async fn run_once(&self, now: DateTime<Utc>) -> PurgeReport {
+        let mut report = PurgeReport {
+            started_at: Utc::now(),
+            finished_at: Utc::now(),
+            archived_logs: 0,
+            deleted_logs: 0,
+            archive_files: Vec::new(),
+            error: None,
+        };
+        if let Err(e) = self.purge(now, &mut report).await {
+            tracing::error!("Audit log purge stopped: {}", e);
+            report.error = Some(e.to_string());
+        }
+        report.finished_at = Utc::now();
+        if report.deleted_logs > 0 {
+            tracing::info!(
+                "Archived {} expired audit logs to {} files, deleted {}",
+                report.archived_logs,
+                report.archive_files.len(),
+                report.deleted_logs
+            );
+        }
+
+        self.state.lock().unwrap().last_purge = Some(report.clone());
+        report
+    }
+
+    async fn purge(&self, now: DateTime<Utc>, report: &mut PurgeReport) -> Result<(), AuditError> {
+        std::fs::create_dir_all(&self.archive_dir).map_err(|e| {
+            AuditError::ExportError(format!("Failed to create {}: {}", self.archive_dir.display(), e))
+        })?;
+        let query = self.storage.query();
+
+        for (category, days) in self.policy.categories() {
+            let before = now - chrono::Duration::days(days as i64);
+            for batch in 0.. {
+                let logs = query.expired(&category, before, ARCHIVE_BATCH).await?;
+                if logs.is_empty() {
+                    break;
+                }
+                let path = self.archive_dir.join(archive_file_name(&category, now, batch));
+                self.exporter.archive(&logs, &path)?;
+                report.archived_logs += logs.len() as u64;
+                report.archive_files.push(path);
+
+                let ids: Vec<Uuid> = logs.iter().map(|log| log.id).collect();
+                report.deleted_logs += self.storage.delete_logs(&ids).await?;
+                if logs.len() < ARCHIVE_BATCH as usize {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Policy, schedule and disk usage
+    pub async fn status(&self) -> Result<RetentionStatus, AuditError> {
+        let table = self.storage.usage().await?;
+        let (archive_files, archive_bytes) = archive_usage(&self.archive_dir);
+        let state = self.state.lock().unwrap();
+        Ok(RetentionStatus {
+            policy: self.policy.clone(),
+            next_purge_at: state.next_purge_at,
+            last_purge: state.last_purge.clone(),
+            usage: StorageUsage {
+                table,
+                archive_files,
+                archive_bytes,
+            },
+        })
+    }
+}
+
+fn archive_file_name(category: &RetentionCategory, now: DateTime<Utc>, batch: u32) -> String {
+    format!("audit-{}-{}-{}.ndjson.gz", category.name(), now.format("%Y%m%dT%H%M%SZ"), batch)
+}
+
+/// Number and total size of the archives in `dir`
+fn archive_usage(dir: &Path) -> (u64, u64) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (0, 0);
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".ndjson.gz"))
+        .filter_map(|entry| entry.metadata().ok())
+        .fold((0, 0), |(files, bytes), metadata| (files + 1, bytes + metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_log_has_one_category() {
+        let policy = RetentionPolicy {
+            resource_days: BTreeMap::from([("Execution".to_string(), 30)]),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.categories(),
+            vec![
+                (RetentionCategory::Security, 365),
+                (RetentionCategory::Resource("Execution".to_string()), 30),
+                (RetentionCategory::Default { except: vec!["Execution".to_string()] }, 90),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_archive_usage_counts_archives() {
+        let dir = std::env::temp_dir().join(format!("audit-usage-{}", Uuid::new_v4()));
+        assert_eq!(archive_usage(&dir), (0, 0));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        let name = archive_file_name(&RetentionCategory::Security, Utc::now(), 0);
+        std::fs::write(dir.join(&name), [0u8; 10]).unwrap();
+        std::fs::write(dir.join("notes.txt"), [0u8; 5]).unwrap();
+        assert_eq!(archive_usage(&dir), (1, 10));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
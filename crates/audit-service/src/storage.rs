@@ -1,7 +1,11 @@
+use common::error::{ErrorCode, ErrorInfo};
 use common::types::{AuditLog, AuditResult};
-use sqlx::PgPool;
+use serde::Serialize;
+use sqlx::{PgPool, Row};
 use uuid::Uuid;
 
+use crate::query::AuditQuery;
+
 /// Audit storage for persisting audit logs
 pub struct AuditStorage {
     pool: PgPool,
@@ -92,7 +96,59 @@ impl AuditStorage {
         Ok(())
     }
 
-    /// Check if audit logs are immutable (no updates/deletes allowed)
+    /// Remove a deleted user from their audit entries: the entries stay, but
+    /// without the user ID, IP address and user agent. This is the only
+    /// update made to stored logs. Returns the number of entries changed.
+    pub async fn anonymize_user(&self, user_id: Uuid) -> Result<u64, AuditError> {
+        let result = sqlx::query(
+            "UPDATE audit_logs SET user_id = NULL, ip_address = NULL, user_agent = NULL WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditError::StorageError(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Delete archived logs past their retention. Returns the number of
+    /// entries deleted.
+    pub async fn delete_logs(&self, ids: &[Uuid]) -> Result<u64, AuditError> {
+        let result = sqlx::query("DELETE FROM audit_logs WHERE id = ANY($1)")
+            .bind(ids)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AuditError::StorageError(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Entries stored and the disk space of the table with its indexes
+    pub async fn usage(&self) -> Result<TableUsage, AuditError> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) AS log_count,
+             COUNT(*) FILTER (WHERE is_security_sensitive) AS security_log_count,
+             pg_total_relation_size('audit_logs') AS table_bytes
+             FROM audit_logs",
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AuditError::StorageError(e.to_string()))?;
+
+        Ok(TableUsage {
+            log_count: row.get("log_count"),
+            security_log_count: row.get("security_log_count"),
+            table_bytes: row.get("table_bytes"),
+        })
+    }
+
+    /// Query the stored logs
+    pub fn query(&self) -> AuditQuery {
+        AuditQuery::new(self.pool.clone())
+    }
+
+    /// Check if audit logs are immutable (no updates/deletes besides
+    /// anonymization and retention purges)
     pub async fn verify_immutability(&self, log_id: Uuid) -> Result<bool, AuditError> {
         // In a real implementation, this would check database constraints
         // For now, we just verify the log exists
@@ -108,6 +164,14 @@ impl AuditStorage {
     }
 }
 
+/// Size of the audit log table
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TableUsage {
+    pub log_count: i64,
+    pub security_log_count: i64,
+    pub table_bytes: i64,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum AuditError {
     #[error("Storage error: {0}")]
@@ -120,15 +184,23 @@ pub enum AuditError {
     ExportError(String),
 }
 
+impl ErrorInfo for AuditError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            AuditError::StorageError(_) => ErrorCode::Unavailable,
+            AuditError::QueryError(_) | AuditError::ExportError(_) => ErrorCode::Internal,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_audit_storage_creation() {
+    #[tokio::test]
+    async fn test_audit_storage_creation() {
         let pool = PgPool::connect_lazy("postgresql://localhost/test").unwrap();
         let _storage = AuditStorage::new(pool);
-        assert!(true); // Just test creation
     }
 }
 
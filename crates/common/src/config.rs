@@ -1,52 +1,704 @@
+use crate::types::{LoadBalanceStrategy, ResourceType};
+use serde::de::IntoDeserializer;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::watch;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Shortest JWT secret accepted; HS256 keys shorter than its output are weak
+pub const MIN_JWT_SECRET_LEN: usize = 32;
+
+/// JWT secret of the default configuration, which does not validate
+pub const PLACEHOLDER_JWT_SECRET: &str = "your-secret-key-change-in-production";
+
+/// JWT secrets of the default configuration and the example files; anyone
+/// can read them, so a deployment must not run with one
+const PLACEHOLDER_JWT_SECRETS: [&str; 3] = [
+    PLACEHOLDER_JWT_SECRET,
+    "your-secret-key-change-this-in-production",
+    "replace-with-at-least-32-random-characters",
+];
+
+/// AI providers whose key can be set with `<PROVIDER>_API_KEY`
+const AI_PROVIDERS: [&str; 4] = ["openai", "anthropic", "gemini", "azure"];
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file {path}: {source}")]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("Failed to parse config file {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+    #[error("Invalid value {value:?} of environment variable {name}")]
+    Env { name: String, value: String },
+    #[error("Invalid configuration: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct DatabaseConfig {
-    pub url: String,
+    /// Postgres URL; data is kept in memory when unset
+    pub url: Option<String>,
     pub max_connections: u32,
     pub min_connections: u32,
+    /// How long a query waits for a free connection
+    pub acquire_timeout_secs: u64,
+    /// Close connections idle for this long, down to `min_connections`
+    pub idle_timeout_secs: u64,
+    /// Replace connections after this long
+    pub max_lifetime_secs: u64,
+    /// Apply pending migrations at startup
+    pub migrate: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            max_connections: 10,
+            min_connections: 2,
+            acquire_timeout_secs: 30,
+            idle_timeout_secs: 600,
+            max_lifetime_secs: 1800,
+            migrate: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct RedisConfig {
     pub url: String,
     pub pool_size: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Default for RedisConfig {
+    fn default() -> Self {
+        Self {
+            url: "redis://localhost:6379".to_string(),
+            pool_size: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    pub jwt_secret: String,
+    pub jwt_expiration_hours: i64,
+    /// Take the client IP from the last `X-Forwarded-For` entry; only enable
+    /// behind a proxy that appends it
+    pub trust_forwarded_for: bool,
+    /// Bearer token Prometheus must send to scrape `/metrics`
+    pub metrics_token: Option<String>,
+    /// URL the platform is reached at, used in links sent out (digests)
+    pub public_url: Option<String>,
+    /// Directory of the file service's uploads, which the email and S3
+    /// integrations read files from
+    pub upload_dir: PathBuf,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 8080,
+            jwt_secret: PLACEHOLDER_JWT_SECRET.to_string(),
+            jwt_expiration_hours: 24,
+            trust_forwarded_for: false,
+            metrics_token: None,
+            public_url: None,
+            upload_dir: PathBuf::from("./uploads"),
+        }
+    }
+}
+
+/// Browser pool of the scraper
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScraperConfig {
+    pub max_contexts: usize,
+    pub idle_timeout_secs: u64,
+    /// How long a request waits for a free context when the pool is full
+    pub queue_timeout_ms: u64,
+    /// Restarts of a crashed context before it is given up
+    pub max_restarts: u32,
+}
+
+impl Default for ScraperConfig {
+    fn default() -> Self {
+        Self {
+            max_contexts: 10,
+            idle_timeout_secs: 300,
+            queue_timeout_ms: 30_000,
+            max_restarts: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AiConfig {
+    /// API keys by provider (`openai`, `anthropic`, `gemini`, `azure`)
+    pub api_keys: BTreeMap<String, String>,
+    /// Model (e.g. `gpt-4o`) also screening AI node prompts for injection
+    /// attempts, after the built-in classifiers; needs its provider's key
+    pub injection_classifier_model: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulerConfig {
+    /// Fire cron, interval and one-off schedules from this process
+    pub enabled: bool,
+    /// Keep schedules in the database, when one is configured, so they
+    /// survive restarts and replicas share them
+    pub persist: bool,
+    /// Advisory lock key replicas compete for; replicas sharing a database
+    /// but not their schedules need different keys
+    pub leader_lock_key: Option<i64>,
+    /// Executions triggers run at once; further ones wait in the run queue
+    pub max_concurrent_runs: usize,
+    /// Executions waiting in the run queue before triggers are rejected
+    pub max_queued_runs: usize,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            persist: true,
+            leader_lock_key: None,
+            max_concurrent_runs: 50,
+            max_queued_runs: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// Days audit logs are kept before they are archived and deleted
+    pub retention_days: u32,
+    /// Days security-sensitive logs (logins, permission changes, ...) are kept
+    pub security_retention_days: u32,
+    /// Days logs of a resource type (`Workflow`, `Execution`, ...) are kept,
+    /// overriding `retention_days`
+    pub resource_retention_days: BTreeMap<String, u32>,
+    /// Directory of the compressed NDJSON archives of purged logs
+    pub archive_dir: PathBuf,
+    /// Hours between purges
+    pub purge_interval_hours: u32,
+    /// Directory of the files written by export jobs
+    pub export_dir: PathBuf,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: 90,
+            security_retention_days: 365,
+            resource_retention_days: BTreeMap::new(),
+            archive_dir: PathBuf::from("audit-archive"),
+            purge_interval_hours: 24,
+            export_dir: PathBuf::from("audit-exports"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EncryptionConfig {
+    /// Master key of saved credentials, 32 base64-encoded bytes; enables
+    /// the credential routes
+    pub credentials_master_key: Option<String>,
+    /// ID recorded with the data keys the credentials master key wraps;
+    /// change it whenever the key changes
+    pub credentials_master_key_id: u32,
+    /// Master key of the execution payloads of organizations that opt in,
+    /// 32 base64-encoded bytes
+    pub execution_data_master_key: Option<String>,
+    /// ID recorded with the data keys the execution data master key wraps
+    pub execution_data_master_key_id: u32,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            credentials_master_key: None,
+            credentials_master_key_id: 1,
+            execution_data_master_key: None,
+            execution_data_master_key_id: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GatewayConfig {
+    /// How requests pick one of a provider's API keys: `round_robin`,
+    /// `weighted` or `least_connections`
+    pub load_balance_strategy: LoadBalanceStrategy,
+    /// Consecutive failures after which requests to a provider go to its
+    /// failover providers
+    pub failure_threshold: u32,
+    /// Seconds after its last failure before a failed provider is tried again
+    pub recovery_timeout_secs: u64,
+    /// Responses kept by the response cache; 0 disables it
+    pub cache_capacity: u64,
+    /// Providers of workflow HTTP action requests, by name
+    pub providers: BTreeMap<String, GatewayProviderConfig>,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            load_balance_strategy: LoadBalanceStrategy::RoundRobin,
+            failure_threshold: 3,
+            recovery_timeout_secs: 60,
+            cache_capacity: 0,
+            providers: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GatewayProviderConfig {
+    /// API keys requests are sent with; without any, requests carry their
+    /// own credentials
+    pub api_keys: Vec<String>,
+    /// Providers a failing request is retried on, in order
+    pub failover_providers: Vec<String>,
+    /// Seconds successful GET responses are answered from the response
+    /// cache; not cached without one
+    pub cache_ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TracingConfig {
+    /// OTLP/gRPC collector spans are exported to, e.g.
+    /// `http://localhost:4317`; spans are not exported without one
+    pub otlp_endpoint: Option<String>,
+    /// `service.name` of the exported spans
+    pub service_name: String,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: "api-gateway".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// `tracing` filter directives; applied again on reload
+    pub filter: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            filter: "api_gateway=debug,tower_http=debug".to_string(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Configuration of the platform, read from a TOML or YAML file with
+/// environment variables taking precedence.
+///
+/// Every section and field is optional in the file. The environment
+/// variables are the ones the services read before config files existed
+/// (`HOST`, `PORT`, `JWT_SECRET`, `DATABASE_URL`, ...), plus
+/// `<PROVIDER>_API_KEY` for AI providers and the `*_MASTER_KEY` and
+/// `*_MASTER_KEY_ID` variables of the encryption keys and the standard
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` and `OTEL_SERVICE_NAME`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct AppConfig {
+    pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub redis: RedisConfig,
-    pub server: ServerConfig,
+    pub scraper: ScraperConfig,
+    pub ai: AiConfig,
+    pub scheduler: SchedulerConfig,
+    pub audit: AuditConfig,
+    pub encryption: EncryptionConfig,
+    pub gateway: GatewayConfig,
+    pub tracing: TracingConfig,
+    pub log: LogConfig,
+}
+
+impl AppConfig {
+    /// Load the file at `path`, if any, apply the environment and validate
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.apply_env(|name| std::env::var(name).ok())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parse a config file: YAML for `.yaml`/`.yml`, TOML otherwise
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let yaml = matches!(path.extension().and_then(|e| e.to_str()), Some("yaml" | "yml"));
+        let parsed = if yaml {
+            serde_yaml::from_str(&text).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(&text).map_err(|e| e.to_string())
+        };
+        parsed.map_err(|message| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message,
+        })
+    }
+
+    /// Override settings with the variables `env` returns
+    pub fn apply_env(&mut self, env: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        let server = &mut self.server;
+        set(&env, "HOST", &mut server.host)?;
+        set(&env, "PORT", &mut server.port)?;
+        set(&env, "JWT_SECRET", &mut server.jwt_secret)?;
+        set(&env, "JWT_EXPIRATION_HOURS", &mut server.jwt_expiration_hours)?;
+        if let Some(value) = env("TRUST_FORWARDED_FOR") {
+            server.trust_forwarded_for = value == "true" || value == "1";
+        }
+        set_optional(&env, "METRICS_TOKEN", &mut server.metrics_token);
+        set_optional(&env, "PUBLIC_URL", &mut server.public_url);
+        set(&env, "UPLOAD_DIR", &mut server.upload_dir)?;
+
+        set_optional(&env, "DATABASE_URL", &mut self.database.url);
+        set(&env, "DATABASE_MAX_CONNECTIONS", &mut self.database.max_connections)?;
+        set(&env, "DATABASE_MIN_CONNECTIONS", &mut self.database.min_connections)?;
+        set_flag(&env, "DATABASE_MIGRATE", &mut self.database.migrate)?;
+        set(&env, "REDIS_URL", &mut self.redis.url)?;
+        set(&env, "REDIS_POOL_SIZE", &mut self.redis.pool_size)?;
+
+        set(&env, "SCRAPER_MAX_CONTEXTS", &mut self.scraper.max_contexts)?;
+        set(&env, "SCRAPER_IDLE_TIMEOUT_SECS", &mut self.scraper.idle_timeout_secs)?;
+
+        for provider in AI_PROVIDERS {
+            if let Some(key) = env(&format!("{}_API_KEY", provider.to_uppercase())).filter(|k| !k.is_empty()) {
+                self.ai.api_keys.insert(provider.to_string(), key);
+            }
+        }
+
+        if let Some(value) = env("SCHEDULER_ENABLED") {
+            self.scheduler.enabled = value == "true" || value == "1";
+        }
+        let encryption = &mut self.encryption;
+        set_optional(&env, "CREDENTIALS_MASTER_KEY", &mut encryption.credentials_master_key);
+        set(&env, "CREDENTIALS_MASTER_KEY_ID", &mut encryption.credentials_master_key_id)?;
+        set_optional(&env, "EXECUTION_DATA_MASTER_KEY", &mut encryption.execution_data_master_key);
+        set(&env, "EXECUTION_DATA_MASTER_KEY_ID", &mut encryption.execution_data_master_key_id)?;
+
+        set_optional(&env, "OTEL_EXPORTER_OTLP_ENDPOINT", &mut self.tracing.otlp_endpoint);
+        set(&env, "OTEL_SERVICE_NAME", &mut self.tracing.service_name)?;
+        set(&env, "RUST_LOG", &mut self.log.filter)?;
+        Ok(())
+    }
+
+    /// Check the settings, reporting every problem at once
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+        if self.server.jwt_secret.len() < MIN_JWT_SECRET_LEN {
+            problems.push(format!("server.jwt_secret must be at least {} characters", MIN_JWT_SECRET_LEN));
+        } else if PLACEHOLDER_JWT_SECRETS.contains(&self.server.jwt_secret.as_str()) {
+            problems.push("server.jwt_secret must be changed from the default, set JWT_SECRET".to_string());
+        }
+        if self.server.port == 0 {
+            problems.push("server.port must be between 1 and 65535".to_string());
+        }
+        if self.server.jwt_expiration_hours <= 0 {
+            problems.push("server.jwt_expiration_hours must be positive".to_string());
+        }
+        if self.database.max_connections == 0 || self.database.min_connections > self.database.max_connections {
+            problems.push("database.max_connections must be positive and at least database.min_connections".to_string());
+        }
+        if self.database.acquire_timeout_secs == 0 {
+            problems.push("database.acquire_timeout_secs must be positive".to_string());
+        }
+        if self.database.url.as_ref().is_some_and(|url| !url.starts_with("postgres://") && !url.starts_with("postgresql://")) {
+            problems.push("database.url must be a postgres:// URL".to_string());
+        }
+        if self.tracing.otlp_endpoint.as_ref().is_some_and(|url| !url.starts_with("http://") && !url.starts_with("https://")) {
+            problems.push("tracing.otlp_endpoint must be an http:// or https:// URL".to_string());
+        }
+        if self.scheduler.max_concurrent_runs == 0 {
+            problems.push("scheduler.max_concurrent_runs must be at least 1".to_string());
+        }
+        if self.scraper.max_contexts == 0 {
+            problems.push("scraper.max_contexts must be at least 1".to_string());
+        }
+        for (provider, key) in &self.ai.api_keys {
+            if !AI_PROVIDERS.contains(&provider.as_str()) {
+                problems.push(format!("ai.api_keys has unknown provider {:?}", provider));
+            } else if key.trim().is_empty() {
+                problems.push(format!("ai.api_keys.{} is empty", provider));
+            }
+        }
+        if self.ai.injection_classifier_model.is_some() && self.ai.api_keys.is_empty() {
+            problems.push("ai.injection_classifier_model needs an ai.api_keys entry".to_string());
+        }
+        if self.audit.retention_days == 0 || self.audit.security_retention_days == 0 {
+            problems.push("audit.retention_days and audit.security_retention_days must be positive".to_string());
+        }
+        for (resource_type, days) in &self.audit.resource_retention_days {
+            let known: Result<ResourceType, serde::de::value::Error> =
+                ResourceType::deserialize(resource_type.as_str().into_deserializer());
+            if known.is_err() {
+                problems.push(format!("audit.resource_retention_days has unknown resource type {:?}", resource_type));
+            } else if *days == 0 {
+                problems.push(format!("audit.resource_retention_days.{} must be positive", resource_type));
+            }
+        }
+        if self.audit.purge_interval_hours == 0 {
+            problems.push("audit.purge_interval_hours must be positive".to_string());
+        }
+        if self.gateway.failure_threshold == 0 || self.gateway.recovery_timeout_secs == 0 {
+            problems.push("gateway.failure_threshold and gateway.recovery_timeout_secs must be positive".to_string());
+        }
+        for (name, provider) in &self.gateway.providers {
+            if provider.cache_ttl_secs == Some(0) {
+                problems.push(format!("gateway.providers.{}.cache_ttl_secs must be positive", name));
+            }
+            if provider.api_keys.iter().any(|key| key.trim().is_empty()) {
+                problems.push(format!("gateway.providers.{}.api_keys has an empty key", name));
+            }
+            for failover in &provider.failover_providers {
+                if failover == name || !self.gateway.providers.contains_key(failover) {
+                    problems.push(format!(
+                        "gateway.providers.{}.failover_providers has {:?}, which is not another configured provider",
+                        name, failover
+                    ));
+                }
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    /// Sections that differ from `other` and only take effect on restart;
+    /// the log filter is applied on reload
+    pub fn restart_required(&self, other: &AppConfig) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.server != other.server {
+            changed.push("server");
+        }
+        if self.database != other.database {
+            changed.push("database");
+        }
+        if self.redis != other.redis {
+            changed.push("redis");
+        }
+        if self.scraper != other.scraper {
+            changed.push("scraper");
+        }
+        if self.ai != other.ai {
+            changed.push("ai");
+        }
+        if self.scheduler != other.scheduler {
+            changed.push("scheduler");
+        }
+        if self.audit != other.audit {
+            changed.push("audit");
+        }
+        if self.encryption != other.encryption {
+            changed.push("encryption");
+        }
+        if self.gateway != other.gateway {
+            changed.push("gateway");
+        }
+        if self.tracing != other.tracing {
+            changed.push("tracing");
+        }
+        changed
+    }
 }
 
-impl Default for AppConfig {
-    fn default() -> Self {
-        Self {
-            database: DatabaseConfig {
-                url: std::env::var("DATABASE_URL")
-                    .unwrap_or_else(|_| "postgresql://localhost/ai_workflow".to_string()),
-                max_connections: 10,
-                min_connections: 2,
-            },
-            redis: RedisConfig {
-                url: std::env::var("REDIS_URL")
-                    .unwrap_or_else(|_| "redis://localhost:6379".to_string()),
-                pool_size: 10,
-            },
-            server: ServerConfig {
-                host: std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
-                port: std::env::var("PORT")
-                    .ok()
-                    .and_then(|p| p.parse().ok())
-                    .unwrap_or(8080),
-            },
+fn set<T: FromStr>(env: &impl Fn(&str) -> Option<String>, name: &str, field: &mut T) -> Result<(), ConfigError> {
+    if let Some(value) = env(name) {
+        *field = value.parse().map_err(|_| ConfigError::Env {
+            name: name.to_string(),
+            value,
+        })?;
+    }
+    Ok(())
+}
+
+/// Set a flag from `true`/`1` or `false`/`0`
+fn set_flag(env: &impl Fn(&str) -> Option<String>, name: &str, field: &mut bool) -> Result<(), ConfigError> {
+    if let Some(value) = env(name) {
+        *field = match value.as_str() {
+            "true" | "1" => true,
+            "false" | "0" => false,
+            _ => {
+                return Err(ConfigError::Env {
+                    name: name.to_string(),
+                    value,
+                })
+            }
+        };
+    }
+    Ok(())
+}
+
+/// Set an optional field; an empty variable leaves it unset
+fn set_optional(env: &impl Fn(&str) -> Option<String>, name: &str, field: &mut Option<String>) {
+    if let Some(value) = env(name) {
+        *field = Some(value).filter(|value| !value.is_empty());
+    }
+}
+
+/// Reload the configuration on SIGHUP and publish it to the returned
+/// receiver. A configuration that fails to load or validate is logged and
+/// the previous one kept.
+pub fn reload_on_hangup(path: Option<PathBuf>, config: AppConfig) -> watch::Receiver<Arc<AppConfig>> {
+    let (tx, rx) = watch::channel(Arc::new(config));
+    #[cfg(unix)]
+    tokio::spawn(async move {
+        let mut hangups = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(e) => {
+                tracing::warn!("Failed to listen for SIGHUP, configuration reload is disabled: {}", e);
+                return;
+            }
+        };
+        while hangups.recv().await.is_some() {
+            match AppConfig::load(path.as_deref()) {
+                Ok(config) => {
+                    tracing::info!("Configuration reloaded");
+                    if tx.send(Arc::new(config)).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => tracing::error!("Keeping the current configuration: {}", e),
+            }
         }
+    });
+    #[cfg(not(unix))]
+    drop((tx, path));
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: BTreeMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_database_migrate_override() {
+        for (value, migrate) in [("true", true), ("1", true), ("false", false), ("0", false)] {
+            let mut config = AppConfig::default();
+            config.apply_env(env(&[("DATABASE_MIGRATE", value)])).unwrap();
+            assert_eq!(config.database.migrate, migrate, "DATABASE_MIGRATE={}", value);
+        }
+
+        // Anything else is an error rather than silently false
+        for value in ["yes", "TRUE ", "ture", ""] {
+            let mut config = AppConfig::default();
+            let err = config.apply_env(env(&[("DATABASE_MIGRATE", value)])).unwrap_err();
+            assert!(matches!(err, ConfigError::Env { ref name, .. } if name == "DATABASE_MIGRATE"), "{:?}", value);
+            assert!(config.database.migrate);
+        }
+    }
+
+    #[test]
+    fn test_master_keys_override() {
+        let mut config = AppConfig::default();
+        config.apply_env(env(&[
+            ("CREDENTIALS_MASTER_KEY", "Y3JlZGVudGlhbHM="),
+            ("CREDENTIALS_MASTER_KEY_ID", "2"),
+            ("EXECUTION_DATA_MASTER_KEY", ""),
+        ])).unwrap();
+        assert_eq!(config.encryption.credentials_master_key.as_deref(), Some("Y3JlZGVudGlhbHM="));
+        assert_eq!(config.encryption.credentials_master_key_id, 2);
+        assert_eq!(config.encryption.execution_data_master_key, None);
+        assert_eq!(config.encryption.execution_data_master_key_id, 1);
+        assert_eq!(config.restart_required(&AppConfig::default()), vec!["encryption"]);
+
+        // A key ID that is not a number is an error rather than silently 1
+        let err = AppConfig::default().apply_env(env(&[("EXECUTION_DATA_MASTER_KEY_ID", "two")])).unwrap_err();
+        assert!(matches!(err, ConfigError::Env { ref name, .. } if name == "EXECUTION_DATA_MASTER_KEY_ID"));
+    }
+
+    #[test]
+    fn test_gateway_providers() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            [server]
+            jwt_secret = "0123456789abcdef0123456789abcdef"
+
+            [gateway]
+            load_balance_strategy = "weighted"
+            cache_capacity = 1000
+
+            [gateway.providers.openai]
+            api_keys = ["sk-1", "sk-2"]
+            failover_providers = ["anthropic"]
+            cache_ttl_secs = 300
+
+            [gateway.providers.anthropic]
+            api_keys = ["sk-ant"]
+            "#,
+        ).unwrap();
+        assert_eq!(config.gateway.load_balance_strategy, LoadBalanceStrategy::Weighted);
+        assert_eq!(config.gateway.providers["openai"].api_keys.len(), 2);
+        assert_eq!(config.gateway.providers["anthropic"].cache_ttl_secs, None);
+        config.validate().unwrap();
+
+        // Failover providers must be configured themselves
+        let mut config = config;
+        config.gateway.providers.remove("anthropic");
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("gateway.providers.openai.failover_providers"), "{}", err);
+    }
+
+    #[test]
+    fn test_placeholder_jwt_secret_is_rejected() {
+        let err = AppConfig::default().validate().unwrap_err();
+        assert!(err.to_string().contains("server.jwt_secret must be changed"), "{}", err);
+
+        let mut config = AppConfig::default();
+        config.apply_env(env(&[("JWT_SECRET", "your-secret-key-change-this-in-production")])).unwrap();
+        assert!(config.validate().is_err());
+        config.apply_env(env(&[("JWT_SECRET", "0123456789abcdef0123456789abcdef")])).unwrap();
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_otlp_override() {
+        let mut config = AppConfig::default();
+        assert_eq!(config.tracing.otlp_endpoint, None);
+        config.apply_env(env(&[
+            ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4317"),
+            ("OTEL_SERVICE_NAME", "gateway-eu"),
+        ])).unwrap();
+        assert_eq!(config.tracing.otlp_endpoint.as_deref(), Some("http://collector:4317"));
+        assert_eq!(config.tracing.service_name, "gateway-eu");
+        assert_eq!(config.restart_required(&AppConfig::default()), vec!["tracing"]);
     }
 }
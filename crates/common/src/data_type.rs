@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::JsonValue;
+
+/// Type of the values passing through a port.
+///
+/// Plain types serialize as their name (`"String"`); parameterized ones as
+/// an object tagged with `type`, the way the editor stores them:
+/// `{"type": "Array", "itemType": "Number"}`,
+/// `{"type": "Object", "schema": {...}}` and
+/// `{"type": "Nullable", "innerType": "String"}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "DataTypeRepr", into = "DataTypeRepr")]
+pub enum DataType {
+    String,
+    Number,
+    Boolean,
+    Object,
+    Array,
+    Any,
+    /// Array whose items all have the given type
+    ArrayOf(Box<DataType>),
+    /// Object described by a JSON Schema (`type`, `properties`, `required`
+    /// and `items` are checked)
+    ObjectSchema(JsonValue),
+    /// The given type or null; other types do not accept null
+    Nullable(Box<DataType>),
+}
+
+impl DataType {
+    /// Whether the type has parameters, which values are held to at run
+    /// time; plain types only convert what the coercion rules allow
+    pub fn is_parameterized(&self) -> bool {
+        matches!(self, DataType::ArrayOf(_) | DataType::ObjectSchema(_) | DataType::Nullable(_))
+    }
+
+    /// Whether values of this type may flow into a port of type `target`.
+    ///
+    /// Besides identical types and `Any`, the coercion rules allow
+    /// Number, Boolean -> String, String, Boolean -> Number and
+    /// String, Number -> Boolean (conversions from strings can still fail at
+    /// run time), plain and parameterized arrays and objects in either
+    /// direction, and a nullable type only into a nullable one.
+    pub fn can_coerce_to(&self, target: &DataType) -> bool {
+        use DataType::*;
+        match (self, target) {
+            (a, b) if a == b => true,
+            (Any, _) | (_, Any) => true,
+            (Nullable(source), Nullable(target)) => source.can_coerce_to(target),
+            (Nullable(_), _) => false,
+            (source, Nullable(target)) => source.can_coerce_to(target),
+            (Number | Boolean, String) | (String | Boolean, Number) | (String | Number, Boolean) => true,
+            (ArrayOf(source), ArrayOf(target)) => source.can_coerce_to(target),
+            (ArrayOf(_), Array) | (Array, ArrayOf(_)) => true,
+            (ObjectSchema(_), Object | ObjectSchema(_)) | (Object, ObjectSchema(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Convert a value to this type following the coercion rules, e.g. the
+    /// number `42` to the string `"42"` or `"true"` to `true`. Array items
+    /// and schema properties are converted too.
+    pub fn coerce(&self, value: &JsonValue) -> Result<JsonValue, String> {
+        match (self, value) {
+            (DataType::Any, _) => Ok(value.clone()),
+            (DataType::Nullable(_), JsonValue::Null) => Ok(JsonValue::Null),
+            (DataType::Nullable(inner), _) => inner.coerce(value),
+            // Schemas without a type still describe an object
+            (DataType::ObjectSchema(schema), _) if schema.get("type").is_none() && !value.is_object() => {
+                Err(format!("cannot convert {} to Object", kind(value)))
+            }
+            // Otherwise the schema says whether null is allowed
+            (DataType::ObjectSchema(schema), _) => coerce_to_schema(value, schema, "value"),
+            (_, JsonValue::Null) => Err(format!("null where {} is expected", self.name())),
+            (DataType::ArrayOf(item_type), JsonValue::Array(items)) => items.iter()
+                .enumerate()
+                .map(|(index, item)| item_type.coerce(item).map_err(|e| format!("item {}: {}", index, e)))
+                .collect::<Result<_, _>>()
+                .map(JsonValue::Array),
+            (DataType::ArrayOf(_), _) => coerce_plain(&DataType::Array, value),
+            _ => coerce_plain(self, value),
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            DataType::String => "String".to_string(),
+            DataType::Number => "Number".to_string(),
+            DataType::Boolean => "Boolean".to_string(),
+            DataType::Object | DataType::ObjectSchema(_) => "Object".to_string(),
+            DataType::Array => "Array".to_string(),
+            DataType::Any => "Any".to_string(),
+            DataType::ArrayOf(item_type) => format!("Array<{}>", item_type.name()),
+            DataType::Nullable(inner) => format!("{}?", inner.name()),
+        }
+    }
+}
+
+/// Convert a value to a plain type
+fn coerce_plain(data_type: &DataType, value: &JsonValue) -> Result<JsonValue, String> {
+    let converted = match (data_type, value) {
+        (DataType::String, JsonValue::String(_))
+        | (DataType::Number, JsonValue::Number(_))
+        | (DataType::Boolean, JsonValue::Bool(_))
+        | (DataType::Object, JsonValue::Object(_))
+        | (DataType::Array, JsonValue::Array(_)) => Some(value.clone()),
+        (DataType::String, JsonValue::Number(n)) => Some(JsonValue::String(n.to_string())),
+        (DataType::String, JsonValue::Bool(b)) => Some(JsonValue::String(b.to_string())),
+        (DataType::Number, JsonValue::String(s)) => {
+            let s = s.trim();
+            s.parse::<i64>().map(JsonValue::from).ok()
+                .or_else(|| s.parse::<f64>().ok().and_then(|f| serde_json::Number::from_f64(f).map(JsonValue::Number)))
+        }
+        (DataType::Number, JsonValue::Bool(b)) => Some(JsonValue::from(*b as i64)),
+        (DataType::Boolean, JsonValue::String(s)) => match s.trim().to_ascii_lowercase().as_str() {
+            "true" => Some(JsonValue::Bool(true)),
+            "false" => Some(JsonValue::Bool(false)),
+            _ => None,
+        },
+        (DataType::Boolean, JsonValue::Number(n)) => match n.as_i64() {
+            Some(0) => Some(JsonValue::Bool(false)),
+            Some(1) => Some(JsonValue::Bool(true)),
+            _ => None,
+        },
+        (DataType::Any, _) => Some(value.clone()),
+        _ => None,
+    };
+    converted.ok_or_else(|| format!("cannot convert {} to {}", kind(value), data_type.name()))
+}
+
+/// Convert a value to match a JSON Schema, checking `type` (a name or a
+/// list of names), `required`, `properties` and `items`
+fn coerce_to_schema(value: &JsonValue, schema: &JsonValue, path: &str) -> Result<JsonValue, String> {
+    let types: Vec<&str> = match schema.get("type") {
+        Some(JsonValue::String(name)) => vec![name.as_str()],
+        Some(JsonValue::Array(names)) => names.iter().filter_map(JsonValue::as_str).collect(),
+        _ => Vec::new(),
+    };
+
+    let mut value = value.clone();
+    if !types.is_empty() {
+        // Prefer a type the value already has over converting it
+        let matching = types.iter().find(|name| schema_type_matches(name, &value));
+        value = match matching {
+            Some(_) => value,
+            None => types.iter()
+                .filter_map(|name| schema_data_type(name))
+                .find_map(|data_type| coerce_plain(&data_type, &value).ok())
+                .ok_or_else(|| format!("{}: {} does not match schema type {}", path, kind(&value), types.join(" or ")))?,
+        };
+        if types.contains(&"integer") && value.as_f64().is_some_and(|f| f.fract() != 0.0) {
+            return Err(format!("{}: {} is not an integer", path, value));
+        }
+    }
+
+    match &mut value {
+        JsonValue::Object(map) => {
+            let required = schema.get("required").and_then(JsonValue::as_array).into_iter().flatten();
+            for key in required.filter_map(JsonValue::as_str) {
+                if !map.contains_key(key) {
+                    return Err(format!("{}: missing required property '{}'", path, key));
+                }
+            }
+            if let Some(properties) = schema.get("properties").and_then(JsonValue::as_object) {
+                for (key, property_schema) in properties {
+                    if let Some(property) = map.get(key) {
+                        let coerced = coerce_to_schema(property, property_schema, &format!("{}.{}", path, key))?;
+                        map.insert(key.clone(), coerced);
+                    }
+                }
+            }
+        }
+        JsonValue::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter_mut().enumerate() {
+                    *item = coerce_to_schema(item, item_schema, &format!("{}[{}]", path, index))?;
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(value)
+}
+
+fn schema_type_matches(name: &str, value: &JsonValue) -> bool {
+    match name {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+fn schema_data_type(name: &str) -> Option<DataType> {
+    match name {
+        "string" => Some(DataType::String),
+        "number" | "integer" => Some(DataType::Number),
+        "boolean" => Some(DataType::Boolean),
+        _ => None,
+    }
+}
+
+fn kind(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "Boolean",
+        JsonValue::Number(_) => "Number",
+        JsonValue::String(_) => "String",
+        JsonValue::Array(_) => "Array",
+        JsonValue::Object(_) => "Object",
+    }
+}
+
+/// Serialized form of [`DataType`]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum DataTypeRepr {
+    Name(String),
+    Parameterized(ParameterizedType),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum ParameterizedType {
+    Array {
+        #[serde(rename = "itemType")]
+        item_type: Box<DataType>,
+    },
+    Object {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        schema: Option<JsonValue>,
+    },
+    #[serde(alias = "Optional")]
+    Nullable {
+        #[serde(rename = "innerType")]
+        inner_type: Box<DataType>,
+    },
+}
+
+impl TryFrom<DataTypeRepr> for DataType {
+    type Error = String;
+
+    fn try_from(repr: DataTypeRepr) -> Result<Self, Self::Error> {
+        Ok(match repr {
+            DataTypeRepr::Name(name) => match name.as_str() {
+                "String" => DataType::String,
+                "Number" => DataType::Number,
+                "Boolean" => DataType::Boolean,
+                "Object" => DataType::Object,
+                "Array" => DataType::Array,
+                "Any" => DataType::Any,
+                other => return Err(format!("unknown data type '{}'", other)),
+            },
+            DataTypeRepr::Parameterized(ParameterizedType::Array { item_type }) => DataType::ArrayOf(item_type),
+            DataTypeRepr::Parameterized(ParameterizedType::Object { schema: Some(schema) }) => DataType::ObjectSchema(schema),
+            DataTypeRepr::Parameterized(ParameterizedType::Object { schema: None }) => DataType::Object,
+            DataTypeRepr::Parameterized(ParameterizedType::Nullable { inner_type }) => DataType::Nullable(inner_type),
+        })
+    }
+}
+
+impl From<DataType> for DataTypeRepr {
+    fn from(data_type: DataType) -> Self {
+        match data_type {
+            DataType::ArrayOf(item_type) => DataTypeRepr::Parameterized(ParameterizedType::Array { item_type }),
+            DataType::ObjectSchema(schema) => DataTypeRepr::Parameterized(ParameterizedType::Object { schema: Some(schema) }),
+            DataType::Nullable(inner_type) => DataTypeRepr::Parameterized(ParameterizedType::Nullable { inner_type }),
+            plain => DataTypeRepr::Name(plain.name()),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::config::DatabaseConfig;
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Schema migrations in `migrations/` (users, workflows, executions, audit
+/// logs, API request logs, ...), embedded at build time
+pub static MIGRATOR: Migrator = sqlx::migrate!("../../migrations");
+
+#[derive(Debug, Error)]
+pub enum DatabaseError {
+    #[error("No database URL is configured")]
+    NotConfigured,
+    #[error("Failed to connect to the database: {0}")]
+    Connect(#[source] sqlx::Error),
+    #[error("Failed to migrate the database: {0}")]
+    Migrate(#[from] MigrateError),
+}
+
+/// Connection pool of the platform database.
+///
+/// Services take the [`PgPool`] from [`Database::pool`]; building it here
+/// keeps sizing and timeouts in one place, set by [`DatabaseConfig`].
+#[derive(Debug, Clone)]
+pub struct Database {
+    pool: PgPool,
+}
+
+impl Database {
+    /// Connect to the configured database
+    pub async fn connect(config: &DatabaseConfig) -> Result<Self, DatabaseError> {
+        let url = config.url.as_deref().ok_or(DatabaseError::NotConfigured)?;
+        let pool = Self::pool_options(config)
+            .connect(url)
+            .await
+            .map_err(DatabaseError::Connect)?;
+        Ok(Self { pool })
+    }
+
+    /// Pool options of a configuration
+    pub fn pool_options(config: &DatabaseConfig) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .max_connections(config.max_connections)
+            .min_connections(config.min_connections)
+            .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
+            .idle_timeout(Duration::from_secs(config.idle_timeout_secs))
+            .max_lifetime(Duration::from_secs(config.max_lifetime_secs))
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Apply the migrations the database has not run yet. Safe to run on
+    /// every start and from several replicas at once: sqlx takes an
+    /// advisory lock while migrating.
+    pub async fn migrate(&self) -> Result<(), DatabaseError> {
+        MIGRATOR.run(&self.pool).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_options_follow_config() {
+        let config = DatabaseConfig {
+            max_connections: 25,
+            min_connections: 5,
+            acquire_timeout_secs: 7,
+            idle_timeout_secs: 120,
+            max_lifetime_secs: 900,
+            ..Default::default()
+        };
+        let options = Database::pool_options(&config);
+        assert_eq!(options.get_max_connections(), 25);
+        assert_eq!(options.get_min_connections(), 5);
+        assert_eq!(options.get_acquire_timeout(), Duration::from_secs(7));
+        assert_eq!(options.get_idle_timeout(), Some(Duration::from_secs(120)));
+        assert_eq!(options.get_max_lifetime(), Some(Duration::from_secs(900)));
+    }
+
+    #[tokio::test]
+    async fn test_connect_without_url() {
+        let config = DatabaseConfig { url: None, ..Default::default() };
+        assert!(matches!(Database::connect(&config).await, Err(DatabaseError::NotConfigured)));
+    }
+}
@@ -1,8 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error as StdError;
 use thiserror::Error;
 use uuid::Uuid;
 
 pub type Result<T> = std::result::Result<T, PlatformError>;
 
+/// Error codes shared by every service. Each error type maps its variants to
+/// one of them, so callers decide how to respond (HTTP status, retry, alert)
+/// without knowing where the error came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The request or definition is wrong; fix it before trying again
+    InvalidInput,
+    NotFound,
+    /// Conflicts with the current state, e.g. a duplicate or stale update
+    Conflict,
+    Unauthenticated,
+    PermissionDenied,
+    QuotaExceeded,
+    RateLimited,
+    Timeout,
+    /// A dependency is temporarily unavailable
+    Unavailable,
+    /// A dependency (provider, integration, browser) failed the request
+    UpstreamFailed,
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidInput => "INVALID_INPUT",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::Conflict => "CONFLICT",
+            ErrorCode::Unauthenticated => "UNAUTHENTICATED",
+            ErrorCode::PermissionDenied => "PERMISSION_DENIED",
+            ErrorCode::QuotaExceeded => "QUOTA_EXCEEDED",
+            ErrorCode::RateLimited => "RATE_LIMITED",
+            ErrorCode::Timeout => "TIMEOUT",
+            ErrorCode::Unavailable => "UNAVAILABLE",
+            ErrorCode::UpstreamFailed => "UPSTREAM_FAILED",
+            ErrorCode::Internal => "INTERNAL",
+        }
+    }
+
+    /// The HTTP status an API answers errors of this code with
+    pub fn http_status(&self) -> u16 {
+        match self {
+            ErrorCode::InvalidInput => 400,
+            ErrorCode::Unauthenticated => 401,
+            ErrorCode::PermissionDenied => 403,
+            ErrorCode::NotFound => 404,
+            ErrorCode::Conflict => 409,
+            ErrorCode::QuotaExceeded | ErrorCode::RateLimited => 429,
+            ErrorCode::UpstreamFailed => 502,
+            ErrorCode::Unavailable => 503,
+            ErrorCode::Timeout => 504,
+            ErrorCode::Internal => 500,
+        }
+    }
+
+    /// Whether trying again later may succeed
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorCode::RateLimited | ErrorCode::Timeout | ErrorCode::Unavailable | ErrorCode::UpstreamFailed
+        )
+    }
+
+    /// Whether the error's own message may be shown to users; internal
+    /// failures are described generically and only logged in full
+    pub fn is_user_facing(&self) -> bool {
+        *self != ErrorCode::Internal
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Classification implemented by the error types of every crate
+pub trait ErrorInfo: StdError {
+    fn error_code(&self) -> ErrorCode;
+
+    fn is_retryable(&self) -> bool {
+        self.error_code().is_retryable()
+    }
+
+    /// The message shown to users; the `Display` message is the internal one
+    fn user_message(&self) -> String {
+        if self.error_code().is_user_facing() {
+            self.to_string()
+        } else {
+            "Internal error".to_string()
+        }
+    }
+
+    /// A serializable summary of the error and its sources
+    fn report(&self) -> ErrorReport {
+        let mut chain = Vec::new();
+        let mut source = self.source();
+        while let Some(error) = source {
+            chain.push(error.to_string());
+            source = error.source();
+        }
+        ErrorReport {
+            code: self.error_code(),
+            message: self.user_message(),
+            retryable: self.is_retryable(),
+            detail: self.to_string(),
+            chain,
+        }
+    }
+}
+
+/// An error as reported across service boundaries
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorReport {
+    pub code: ErrorCode,
+    /// Safe to show to users
+    pub message: String,
+    pub retryable: bool,
+    /// The internal message, for logs
+    #[serde(skip_serializing, default)]
+    pub detail: String,
+    /// Messages of the underlying errors, outermost first, for logs
+    #[serde(skip_serializing, default)]
+    pub chain: Vec<String>,
+}
+
 #[derive(Debug, Error)]
 pub enum PlatformError {
     #[error("Workflow error: {0}")]
@@ -31,6 +160,108 @@ pub enum PlatformError {
     
     #[error("Internal error: {0}")]
     Internal(String),
+
+    /// An error of another crate, keeping its classification
+    #[error("{message}")]
+    Service {
+        code: ErrorCode,
+        retryable: bool,
+        message: String,
+        user_message: String,
+        #[source]
+        source: Option<Box<dyn StdError + Send + Sync>>,
+    },
+
+    /// An error with what was being done when it happened
+    #[error("{context}: {source}")]
+    Context {
+        context: String,
+        #[source]
+        source: Box<PlatformError>,
+    },
+}
+
+impl PlatformError {
+    /// Wrap an error of another crate, keeping its code and retryability
+    pub fn service<E: ErrorInfo + Send + Sync + 'static>(error: E) -> Self {
+        PlatformError::Service {
+            code: error.error_code(),
+            retryable: error.is_retryable(),
+            message: error.to_string(),
+            user_message: error.user_message(),
+            source: Some(Box::new(error)),
+        }
+    }
+
+    /// Add what was being done when the error happened
+    pub fn context(self, context: impl Into<String>) -> Self {
+        PlatformError::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The innermost platform error, past any added context
+    pub fn root(&self) -> &PlatformError {
+        match self {
+            PlatformError::Context { source, .. } => source.root(),
+            other => other,
+        }
+    }
+}
+
+impl ErrorInfo for PlatformError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            PlatformError::Workflow(e) => e.error_code(),
+            PlatformError::Parse(e) => e.error_code(),
+            PlatformError::ApiGateway(e) => e.error_code(),
+            PlatformError::Integration(e) => e.error_code(),
+            PlatformError::Auth(e) => e.error_code(),
+            PlatformError::Permission(_) => ErrorCode::PermissionDenied,
+            PlatformError::Validation(_) => ErrorCode::InvalidInput,
+            PlatformError::Database(_) | PlatformError::Internal(_) => ErrorCode::Internal,
+            PlatformError::Service { code, .. } => *code,
+            PlatformError::Context { source, .. } => source.error_code(),
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            PlatformError::Workflow(e) => e.is_retryable(),
+            PlatformError::ApiGateway(e) => e.is_retryable(),
+            PlatformError::Service { retryable, .. } => *retryable,
+            PlatformError::Context { source, .. } => source.is_retryable(),
+            other => other.error_code().is_retryable(),
+        }
+    }
+
+    fn user_message(&self) -> String {
+        match self {
+            PlatformError::Service { user_message, .. } => user_message.clone(),
+            // The context describes internals; users see the cause
+            PlatformError::Context { source, .. } => source.user_message(),
+            other if other.error_code().is_user_facing() => other.to_string(),
+            _ => "Internal error".to_string(),
+        }
+    }
+}
+
+/// Adds context to the error of a result, converting it to a [`PlatformError`]
+pub trait ResultExt<T> {
+    fn context(self, context: impl Into<String>) -> Result<T>;
+
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T>;
+}
+
+impl<T, E: Into<PlatformError>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T> {
+        self.map_err(|e| e.into().context(context()))
+    }
 }
 
 #[derive(Debug, Error)]
@@ -55,6 +286,21 @@ pub enum ParseError {
     
     #[error("Cycle detected at node: {0}")]
     CycleDetected(Uuid),
+
+    #[error("Invalid schema version: {0}")]
+    InvalidVersion(String),
+
+    #[error("Unsupported workflow schema version {found} (supported: 1-{supported})")]
+    UnsupportedVersion { found: u32, supported: u32 },
+
+    #[error("Migration from schema version {from} failed: {reason}")]
+    MigrationFailed { from: u32, reason: String },
+
+    #[error("Invalid YAML: {0}")]
+    InvalidYaml(String),
+
+    #[error("Invalid portable bundle: {0}")]
+    InvalidBundle(String),
 }
 
 #[derive(Debug, Error)]
@@ -73,6 +319,29 @@ pub enum WorkflowError {
     
     #[error("Workflow validation failed: {0}")]
     ValidationFailed(String),
+    
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    #[error("Run queue full: {0}")]
+    QueueFull(String),
+
+    /// The execution is not in a state allowing the operation, e.g.
+    /// resuming an execution that is not paused
+    #[error("Invalid execution state: {0}")]
+    InvalidState(String),
+
+    /// A node failed with an error of another crate, kept as the source so
+    /// its code and retryability decide how the failure is handled
+    #[error("Node execution failed: {node_id}, reason: {source}")]
+    NodeFailed {
+        node_id: String,
+        #[source]
+        source: Box<PlatformError>,
+    },
 }
 
 #[derive(Debug, Error)]
@@ -125,3 +394,65 @@ pub enum AuthError {
     #[error("User not found")]
     UserNotFound,
 }
+
+impl ErrorInfo for ParseError {
+    fn error_code(&self) -> ErrorCode {
+        ErrorCode::InvalidInput
+    }
+}
+
+impl ErrorInfo for WorkflowError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            WorkflowError::NodeNotFound(_) => ErrorCode::NotFound,
+            WorkflowError::InvalidConnection(_, _) | WorkflowError::ValidationFailed(_) => ErrorCode::InvalidInput,
+            WorkflowError::Timeout(_) => ErrorCode::Timeout,
+            WorkflowError::NodeExecutionFailed(_, _) => ErrorCode::UpstreamFailed,
+            WorkflowError::QuotaExceeded(_) => ErrorCode::QuotaExceeded,
+            WorkflowError::Storage(_) => ErrorCode::Internal,
+            WorkflowError::QueueFull(_) => ErrorCode::Unavailable,
+            WorkflowError::InvalidState(_) => ErrorCode::Conflict,
+            WorkflowError::NodeFailed { source, .. } => source.error_code(),
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            WorkflowError::NodeFailed { source, .. } => source.is_retryable(),
+            other => other.error_code().is_retryable(),
+        }
+    }
+}
+
+impl ErrorInfo for GatewayError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            GatewayError::RateLimitExceeded(_) => ErrorCode::RateLimited,
+            GatewayError::ProviderUnavailable(_) | GatewayError::FailoverFailed => ErrorCode::Unavailable,
+            GatewayError::Timeout(_) => ErrorCode::Timeout,
+            GatewayError::RetriesExhausted(_) => ErrorCode::UpstreamFailed,
+            GatewayError::InvalidApiKey(_) => ErrorCode::Unauthenticated,
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        // Retrying already happened, or cannot fix the key
+        !matches!(self, GatewayError::RetriesExhausted(_) | GatewayError::InvalidApiKey(_))
+    }
+}
+
+impl ErrorInfo for IntegrationError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            IntegrationError::NotFound(_) => ErrorCode::NotFound,
+            IntegrationError::InvalidCredentials => ErrorCode::Unauthenticated,
+            IntegrationError::OAuth2(_) | IntegrationError::ApiCallFailed(_) => ErrorCode::UpstreamFailed,
+        }
+    }
+}
+
+impl ErrorInfo for AuthError {
+    fn error_code(&self) -> ErrorCode {
+        ErrorCode::Unauthenticated
+    }
+}
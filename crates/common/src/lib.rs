@@ -1,5 +1,8 @@
+pub mod data_type;
 pub mod error;
 pub mod types;
 pub mod config;
+pub mod database;
+pub mod trace;
 
-pub use error::{PlatformError, ParseError, Result};
+pub use error::{ErrorCode, ErrorInfo, ErrorReport, PlatformError, ParseError, Result, ResultExt};
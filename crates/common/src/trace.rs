@@ -0,0 +1,160 @@
+use crate::config::TracingConfig;
+use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState, TracerProvider as _};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use std::fmt;
+use std::future::Future;
+use tracing::Subscriber;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+use uuid::Uuid;
+
+/// Header carrying the trace context between services (W3C Trace Context)
+pub const TRACEPARENT: &str = "traceparent";
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+/// Position of the current work in a distributed trace: the trace it
+/// belongs to and the span doing it.
+///
+/// The gateway starts or continues a trace per request, executions and their
+/// nodes run in child spans, and outbound HTTP actions carry the context in
+/// the `traceparent` header, so one workflow run is a single trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    /// 32 lowercase hex digits
+    pub trace_id: String,
+    /// 16 lowercase hex digits
+    pub span_id: String,
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// Start a new trace
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: Uuid::new_v4().simple().to_string(),
+            span_id: new_span_id(),
+            sampled: true,
+        }
+    }
+
+    /// A new span of the same trace
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id.clone(),
+            span_id: new_span_id(),
+            sampled: self.sampled,
+        }
+    }
+
+    /// Parse a `traceparent` header; `None` when malformed or all-zero
+    pub fn parse(traceparent: &str) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let (version, trace_id, span_id, flags) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        let hex = |value: &str, len: usize| {
+            value.len() == len
+                && value.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+                && value.bytes().any(|b| b != b'0')
+        };
+        // Version ff is invalid; later versions may append fields
+        if version.len() != 2 || version == "ff" || (version == "00" && parts.next().is_some()) {
+            return None;
+        }
+        if !hex(trace_id, 32) || !hex(span_id, 16) || flags.len() != 2 {
+            return None;
+        }
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            span_id: span_id.to_string(),
+            sampled: flags & 1 == 1,
+        })
+    }
+
+    /// The `traceparent` header of this context
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-{:02x}", self.trace_id, self.span_id, self.sampled as u8)
+    }
+
+    /// Trace context of the running task, if it runs in one
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+
+    /// A child span of the running task's context, or a new trace
+    pub fn current_child() -> Self {
+        Self::current().map(|ctx| ctx.child()).unwrap_or_else(Self::new_root)
+    }
+
+    /// Run `f` in this context
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        CURRENT.scope(self, f).await
+    }
+
+    /// Export `span` as a child of this context, so it lands in the same
+    /// trace as the `traceparent` headers carry; no-op without an exporter
+    pub fn adopt(&self, span: &tracing::Span) {
+        let (Ok(trace_id), Ok(span_id)) = (TraceId::from_hex(&self.trace_id), SpanId::from_hex(&self.span_id)) else {
+            return;
+        };
+        let flags = if self.sampled { TraceFlags::SAMPLED } else { TraceFlags::default() };
+        let parent = SpanContext::new(trace_id, span_id, flags, true, TraceState::default());
+        span.set_parent(opentelemetry::Context::new().with_remote_span_context(parent));
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.traceparent())
+    }
+}
+
+fn new_span_id() -> String {
+    Uuid::new_v4().simple().to_string()[..16].to_string()
+}
+
+/// Exports `tracing` spans to an OTLP collector
+pub struct OtlpExporter {
+    provider: TracerProvider,
+    tracer: Tracer,
+}
+
+impl OtlpExporter {
+    /// The exporter `config` enables, if it names an endpoint; spans are
+    /// sent in batches from the Tokio runtime
+    pub fn from_config(config: &TracingConfig) -> Result<Option<Self>, opentelemetry::trace::TraceError> {
+        let Some(endpoint) = &config.otlp_endpoint else {
+            return Ok(None);
+        };
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .build()?;
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(Resource::new([KeyValue::new("service.name", config.service_name.clone())]))
+            .build();
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        Ok(Some(Self { provider, tracer }))
+    }
+
+    /// Layer sending the spans of a subscriber to the collector
+    pub fn layer<S>(&self) -> OpenTelemetryLayer<S, Tracer>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.tracer.clone())
+    }
+
+    /// Send the spans not yet exported and stop
+    pub fn shutdown(&self) {
+        if let Err(e) = self.provider.shutdown() {
+            tracing::warn!("Failed to flush exported spans: {}", e);
+        }
+    }
+}
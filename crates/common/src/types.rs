@@ -9,8 +9,15 @@ use tokio::sync::RwLock;
 pub type JsonValue = serde_json::Value;
 
 // Workflow types
+
+/// Schema version of workflow definitions produced by this release
+pub const WORKFLOW_SCHEMA_VERSION: u32 = 2;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Workflow {
+    /// Definition schema version; older definitions are migrated by the parser
+    #[serde(default = "current_schema_version")]
+    pub version: u32,
     pub id: Uuid,
     pub name: String,
     pub description: Option<String>,
@@ -19,6 +26,26 @@ pub struct Workflow {
     pub variables: HashMap<String, JsonValue>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Nodes removed by graph cleanup, kept so they can be restored
+    #[serde(default, skip_serializing_if = "WorkflowTrash::is_empty")]
+    pub trash: WorkflowTrash,
+}
+
+/// Removed nodes and the edges that connected them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkflowTrash {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl WorkflowTrash {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.edges.is_empty()
+    }
+}
+
+fn current_schema_version() -> u32 {
+    WORKFLOW_SCHEMA_VERSION
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +74,8 @@ pub enum TriggerType {
     Webhook,
     Schedule,
     Manual,
+    /// Polls an RSS/Atom/JSON feed and fires once per new entry
+    Rss,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +84,8 @@ pub enum ActionType {
     Email,
     Database,
     Integration,
+    /// Browser or HTTP scraping through the scraper service
+    Scraper,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -83,19 +114,66 @@ pub struct CustomNodeConfig {
     pub dependencies: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct NodeConfig {
     pub parameters: HashMap<String, JsonValue>,
+    /// Fail an attempt that runs longer than this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    /// Retry failed attempts before applying `on_error`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<NodeRetryPolicy>,
+    #[serde(default)]
+    pub on_error: OnError,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NodeRetryPolicy {
+    /// Retries after the first attempt
+    pub max: u32,
+    #[serde(default)]
+    pub backoff: Backoff,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Backoff {
+    Fixed { delay_ms: u64 },
+    Exponential { initial_ms: u64, max_ms: u64 },
 }
 
-impl Default for NodeConfig {
+impl Default for Backoff {
     fn default() -> Self {
-        Self {
-            parameters: HashMap::new(),
-        }
+        Backoff::Exponential { initial_ms: 100, max_ms: 30000 }
     }
 }
 
+impl Backoff {
+    /// Delay before the given retry (1-based)
+    pub fn delay(&self, retry: u32) -> std::time::Duration {
+        let ms = match self {
+            Backoff::Fixed { delay_ms } => *delay_ms,
+            Backoff::Exponential { initial_ms, max_ms } => initial_ms
+                .saturating_mul(2_u64.saturating_pow(retry.saturating_sub(1)))
+                .min(*max_ms),
+        };
+        std::time::Duration::from_millis(ms)
+    }
+}
+
+/// What to do when a node fails after all retries
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnError {
+    /// Fail the whole execution
+    #[default]
+    Fail,
+    /// Record the error as the node output and keep going
+    Continue,
+    /// Route only through the node's "error" output handle
+    FallbackBranch,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Position {
     pub x: f64,
@@ -109,15 +187,7 @@ pub struct Port {
     pub data_type: DataType,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum DataType {
-    String,
-    Number,
-    Boolean,
-    Object,
-    Array,
-    Any,
-}
+pub use crate::data_type::DataType;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Edge {
@@ -148,6 +218,21 @@ pub struct ConcurrentExecutionContext {
     pub state: ExecutionState,
     pub started_at: DateTime<Utc>,
     pub current_node: Option<Uuid>,
+    /// Organization the execution runs for, when known
+    pub organization_id: Option<Uuid>,
+    /// Routes taken by the nodes that finished, keyed by node id
+    pub routes: Arc<RwLock<HashMap<Uuid, NodeRoute>>>,
+}
+
+/// How a finished node routes to its successors. Kept apart from the node's
+/// output so that output content cannot select a branch.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeRoute {
+    /// The node failed under a `continue` or `fallback_branch` policy
+    pub failed: bool,
+    /// Source handle selected by a condition node, or "error" for a
+    /// `fallback_branch` failure
+    pub branch: Option<String>,
 }
 
 impl ConcurrentExecutionContext {
@@ -159,6 +244,8 @@ impl ConcurrentExecutionContext {
             state: ExecutionState::Pending,
             started_at: Utc::now(),
             current_node: None,
+            organization_id: None,
+            routes: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -170,6 +257,8 @@ impl ConcurrentExecutionContext {
             state: ctx.state,
             started_at: ctx.started_at,
             current_node: ctx.current_node,
+            organization_id: None,
+            routes: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 }
@@ -191,6 +280,75 @@ pub struct ExecutionResult {
     pub completed_at: Option<DateTime<Utc>>,
     pub error: Option<String>,
     pub output: Option<JsonValue>,
+    #[serde(default)]
+    pub usage: Option<ExecutionUsage>,
+}
+
+/// Resource consumption reported by a node or aggregated over an execution
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ResourceUsage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub provider_calls: u64,
+    #[serde(default)]
+    pub scraper_pages: u64,
+    #[serde(default)]
+    pub bytes_stored: u64,
+}
+
+impl ResourceUsage {
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    pub fn add(&mut self, other: &ResourceUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.provider_calls += other.provider_calls;
+        self.scraper_pages += other.scraper_pages;
+        self.bytes_stored += other.bytes_stored;
+    }
+}
+
+/// Per-node wall time and resource usage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeUsage {
+    pub node_id: Uuid,
+    pub wall_time_ms: u64,
+    pub usage: ResourceUsage,
+}
+
+/// Resource usage of a whole execution
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionUsage {
+    pub wall_time_ms: u64,
+    pub nodes: Vec<NodeUsage>,
+    pub totals: ResourceUsage,
+}
+
+impl ExecutionUsage {
+    /// Record a node run, accumulating into the totals
+    pub fn record_node(&mut self, node_id: Uuid, wall_time_ms: u64, usage: ResourceUsage) {
+        self.totals.add(&usage);
+        match self.nodes.iter_mut().find(|n| n.node_id == node_id) {
+            Some(node) => {
+                node.wall_time_ms += wall_time_ms;
+                node.usage.add(&usage);
+            }
+            None => self.nodes.push(NodeUsage { node_id, wall_time_ms, usage }),
+        }
+    }
+
+    /// Nodes ordered by wall time, slowest first
+    pub fn slowest_nodes(&self, limit: usize) -> Vec<&NodeUsage> {
+        let mut nodes: Vec<_> = self.nodes.iter().collect();
+        nodes.sort_by_key(|n| std::cmp::Reverse(n.wall_time_ms));
+        nodes.truncate(limit);
+        nodes
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -238,6 +396,10 @@ pub struct ApiRequest {
     pub priority: Priority,
     pub workflow_id: Uuid,
     pub node_id: Uuid,
+    /// Organization the request is sent for; providers shared by several
+    /// organizations divide their capacity fairly between them
+    #[serde(default)]
+    pub organization_id: Option<Uuid>,
     #[serde(with = "duration_serde")]
     pub timeout: std::time::Duration,
     pub retry_config: RetryConfig,
@@ -264,7 +426,7 @@ mod duration_serde {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum HttpMethod {
     GET,
     POST,
@@ -300,6 +462,15 @@ impl Default for RetryConfig {
     }
 }
 
+impl RetryConfig {
+    /// Delay before the given retry (1-based)
+    pub fn delay(&self, retry: u32) -> std::time::Duration {
+        let factor = self.backoff_multiplier.max(1.0).powi(retry.saturating_sub(1) as i32);
+        let ms = (self.initial_delay_ms as f64 * factor).min(self.max_delay_ms as f64);
+        std::time::Duration::from_millis(ms as u64)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiResponse {
     pub request_id: Uuid,
@@ -324,6 +495,10 @@ pub struct RateLimitConfig {
     pub requests_per_minute: u32,
     pub requests_per_hour: u32,
     pub concurrent_limit: u32,
+    /// Requests allowed at once after a quiet period; defaults to
+    /// `requests_per_second`. Refill still follows the per-second rate.
+    #[serde(default)]
+    pub burst: Option<u32>,
 }
 
 impl Default for RateLimitConfig {
@@ -333,6 +508,7 @@ impl Default for RateLimitConfig {
             requests_per_minute: 100,
             requests_per_hour: 1000,
             concurrent_limit: 10,
+            burst: None,
         }
     }
 }
@@ -365,6 +541,24 @@ pub struct ProviderMetrics {
     pub average_latency_ms: f64,
     pub error_rate: f64,
     pub total_cost: f64,
+    /// Latency percentiles since startup
+    #[serde(default)]
+    pub latency: LatencyPercentiles,
+    /// Latency percentiles of the last 5 minutes
+    #[serde(default)]
+    pub latency_5m: LatencyPercentiles,
+    /// Latency percentiles of the last hour
+    #[serde(default)]
+    pub latency_1h: LatencyPercentiles,
+}
+
+/// Request latency percentiles over a period
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencyPercentiles {
+    pub requests: u64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -392,7 +586,8 @@ impl CachedResponse {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum LoadBalanceStrategy {
     RoundRobin,
     Weighted,
@@ -406,6 +601,8 @@ pub enum Role {
     Manager,
     User,
     Viewer,
+    /// Support staff: read-only inspection of any organization's executions
+    Inspector,
     Custom(String),
 }
 
@@ -416,6 +613,7 @@ impl Role {
             Role::Manager => "manager",
             Role::User => "user",
             Role::Viewer => "viewer",
+            Role::Inspector => "inspector",
             Role::Custom(name) => name.as_str(),
         }
     }
@@ -436,6 +634,7 @@ pub enum ResourceType {
     User,
     AuditLog,
     Settings,
+    Execution,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -446,6 +645,10 @@ pub enum ActionType2 {
     Delete,
     Execute,
     Share,
+    /// View execution internals (graph, node timings, redacted data)
+    Inspect,
+    /// Approve pending workflow revisions before they go live
+    Approve,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -516,6 +719,7 @@ impl AuditLog {
                 | AuditAction::Logout
                 | AuditAction::PermissionChange
                 | AuditAction::ConfigChange
+                | AuditAction::Inspect
         );
 
         Self {
@@ -545,6 +749,7 @@ pub enum AuditAction {
     Logout,
     PermissionChange,
     ConfigChange,
+    Inspect,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -554,7 +759,7 @@ pub enum AuditResult {
     Denied,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AuditFilter {
     pub user_id: Option<Uuid>,
     pub action: Option<AuditAction>,
@@ -562,23 +767,40 @@ pub struct AuditFilter {
     pub start_time: Option<DateTime<Utc>>,
     pub end_time: Option<DateTime<Utc>>,
     pub security_only: bool,
+    /// Page size; 1000 when unset, which is also the most allowed
+    #[serde(default)]
+    pub limit: Option<u32>,
+    /// Logs to skip, for the following pages
+    #[serde(default)]
+    pub offset: Option<u32>,
 }
 
-impl Default for AuditFilter {
-    fn default() -> Self {
-        Self {
-            user_id: None,
-            action: None,
-            resource_type: None,
-            start_time: None,
-            end_time: None,
-            security_only: false,
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ExportFormat {
     Json,
     Csv,
+    /// One JSON object per line
+    Ndjson,
+    /// Columnar, for loading into analytics tools
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
 }
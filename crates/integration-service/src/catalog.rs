@@ -0,0 +1,148 @@
+//! Persisted catalog of the registered integrations and of each
+//! organization's enablement of them
+//!
+//! Organizations may use every integration and action until an admin
+//! disables it for them. The registry publishes its integrations to the
+//! catalog and reads the organizations' settings from it, so settings
+//! survive restarts and are shared by gateway replicas.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common::error::{ErrorCode, ErrorInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::integrations::{IntegrationCategory, IntegrationInfo};
+
+/// A registered integration as recorded in the catalog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    #[serde(flatten)]
+    pub info: IntegrationInfo,
+    /// Names of the integration's actions
+    pub actions: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An organization's enablement of one integration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IntegrationSetting {
+    pub organization_id: Uuid,
+    pub integration: String,
+    pub enabled: bool,
+    /// Actions disabled while the integration itself stays enabled
+    #[serde(default)]
+    pub disabled_actions: BTreeSet<String>,
+    pub updated_by: Uuid,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl IntegrationSetting {
+    /// The setting of an integration nothing has been disabled of
+    pub fn enabled(organization_id: Uuid, integration: &str, updated_by: Uuid) -> Self {
+        Self {
+            organization_id,
+            integration: integration.to_string(),
+            enabled: true,
+            disabled_actions: BTreeSet::new(),
+            updated_by,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Whether the integration, and `action` of it when given, may be used
+    pub fn allows(&self, action: Option<&str>) -> bool {
+        self.enabled && action.is_none_or(|action| !self.disabled_actions.contains(action))
+    }
+}
+
+/// Which integrations a listing includes
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct IntegrationFilter {
+    pub category: Option<IntegrationCategory>,
+    /// Matched case-insensitively
+    pub tag: Option<String>,
+    /// Only integrations enabled for this organization
+    pub organization_id: Option<Uuid>,
+}
+
+impl IntegrationFilter {
+    /// Whether the integration has the category and tag asked for; the
+    /// organization is checked by the registry
+    pub fn matches(&self, info: &IntegrationInfo) -> bool {
+        self.category.as_ref().is_none_or(|category| *category == info.category)
+            && self.tag.as_ref().is_none_or(|tag| info.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+    }
+}
+
+/// Durable storage of the catalog
+#[async_trait]
+pub trait CatalogStore: Send + Sync {
+    /// Insert or replace the entry of an integration
+    async fn save_entry(&self, entry: &CatalogEntry) -> Result<(), CatalogError>;
+    /// Entries of all integrations ever published, by name
+    async fn load_entries(&self) -> Result<Vec<CatalogEntry>, CatalogError>;
+    /// Insert or replace an organization's setting of an integration
+    async fn save_setting(&self, setting: &IntegrationSetting) -> Result<(), CatalogError>;
+    async fn load_settings(&self) -> Result<Vec<IntegrationSetting>, CatalogError>;
+}
+
+/// In-memory catalog store (for development, replace with database in production)
+#[derive(Default)]
+pub struct InMemoryCatalogStore {
+    entries: RwLock<HashMap<String, CatalogEntry>>,
+    settings: RwLock<HashMap<(Uuid, String), IntegrationSetting>>,
+}
+
+impl InMemoryCatalogStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl CatalogStore for InMemoryCatalogStore {
+    async fn save_entry(&self, entry: &CatalogEntry) -> Result<(), CatalogError> {
+        self.entries.write().await.insert(entry.info.name.clone(), entry.clone());
+        Ok(())
+    }
+
+    async fn load_entries(&self) -> Result<Vec<CatalogEntry>, CatalogError> {
+        let mut entries: Vec<CatalogEntry> = self.entries.read().await.values().cloned().collect();
+        entries.sort_by(|a, b| a.info.name.cmp(&b.info.name));
+        Ok(entries)
+    }
+
+    async fn save_setting(&self, setting: &IntegrationSetting) -> Result<(), CatalogError> {
+        let key = (setting.organization_id, setting.integration.clone());
+        self.settings.write().await.insert(key, setting.clone());
+        Ok(())
+    }
+
+    async fn load_settings(&self) -> Result<Vec<IntegrationSetting>, CatalogError> {
+        Ok(self.settings.read().await.values().cloned().collect())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CatalogError {
+    #[error("Integration not found: {0}")]
+    UnknownIntegration(String),
+
+    #[error("Action {action} not found in integration {integration}")]
+    UnknownAction { integration: String, action: String },
+
+    #[error("Catalog storage error: {0}")]
+    Storage(String),
+}
+
+impl ErrorInfo for CatalogError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            CatalogError::UnknownIntegration(_) | CatalogError::UnknownAction { .. } => ErrorCode::NotFound,
+            CatalogError::Storage(_) => ErrorCode::Unavailable,
+        }
+    }
+}
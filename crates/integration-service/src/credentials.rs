@@ -2,71 +2,604 @@ use aes_gcm::{
     aead::{Aead, KeyInit},
     Aes256Gcm, Nonce,
 };
+use async_trait::async_trait;
 use base64::{engine::general_purpose, Engine};
-use rand::Rng;
-use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use common::error::{ErrorCode, ErrorInfo};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock as SyncRwLock};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::integrations::AuthType;
+use crate::oauth::OAuth2Token;
+
+/// Prefix of envelope-encrypted credentials:
+/// `env1:<master key ID>:<wrapped data key>:<ciphertext>`, both base64
+const ENVELOPE_PREFIX: &str = "env1";
+
+/// ID of the master key given to [`CredentialManager::new`]
+pub const DEFAULT_MASTER_KEY_ID: u32 = 1;
+
+const NONCE_LEN: usize = 12;
+
+/// A master key data keys are wrapped with, e.g. one from configuration or
+/// held by a KMS
+pub trait MasterKey: Send + Sync {
+    fn id(&self) -> u32;
+    fn wrap(&self, data_key: &[u8]) -> Result<Vec<u8>, CredentialError>;
+    fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, CredentialError>;
+}
+
+/// A master key held in configuration
+pub struct LocalMasterKey {
+    id: u32,
+    cipher: Aes256Gcm,
+}
+
+impl LocalMasterKey {
+    pub fn new(id: u32, key: &[u8; 32]) -> Self {
+        Self {
+            id,
+            cipher: Aes256Gcm::new(key.into()),
+        }
+    }
+
+    /// A key given as 32 base64-encoded bytes, e.g. in an environment variable
+    pub fn from_base64(id: u32, encoded: &str) -> Result<Self, CredentialError> {
+        let key: [u8; 32] = general_purpose::STANDARD
+            .decode(encoded.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(CredentialError::InvalidFormat)?;
+        Ok(Self::new(id, &key))
+    }
+}
+
+impl MasterKey for LocalMasterKey {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn wrap(&self, data_key: &[u8]) -> Result<Vec<u8>, CredentialError> {
+        seal(&self.cipher, data_key)
+    }
+
+    fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, CredentialError> {
+        open(&self.cipher, wrapped)
+    }
+}
+
+/// Master keys by ID; the current one wraps new and re-wrapped data keys
+struct MasterKeys {
+    current: u32,
+    keys: HashMap<u32, Arc<dyn MasterKey>>,
+}
 
 /// Credential manager for encrypting and decrypting sensitive data
+///
+/// Credentials are envelope-encrypted: each gets a random data key that
+/// encrypts it with AES-256-GCM, stored alongside it wrapped with the master
+/// key. Rotating the master key only re-wraps data keys, see
+/// [`rewrap`](Self::rewrap). Credentials encrypted directly with the key of
+/// [`new`](Self::new), as before envelopes, still decrypt.
 pub struct CredentialManager {
-    cipher: Arc<Aes256Gcm>,
+    masters: SyncRwLock<MasterKeys>,
+    /// Key credentials were encrypted with before envelopes
+    legacy: Option<Arc<Aes256Gcm>>,
 }
 
 impl CredentialManager {
-    /// Create a new credential manager with a 256-bit key
+    /// Create a new credential manager with a 256-bit key, used as master
+    /// key [`DEFAULT_MASTER_KEY_ID`]
     pub fn new(key: &[u8; 32]) -> Self {
-        let cipher = Aes256Gcm::new(key.into());
+        let mut manager = Self::with_master_key(Arc::new(LocalMasterKey::new(DEFAULT_MASTER_KEY_ID, key)));
+        manager.legacy = Some(Arc::new(Aes256Gcm::new(key.into())));
+        manager
+    }
+
+    /// Create a credential manager wrapping data keys with `master`
+    pub fn with_master_key(master: Arc<dyn MasterKey>) -> Self {
         Self {
-            cipher: Arc::new(cipher),
+            masters: SyncRwLock::new(MasterKeys {
+                current: master.id(),
+                keys: HashMap::from([(master.id(), master)]),
+            }),
+            legacy: None,
         }
     }
 
-    /// Encrypt credentials using AES-256-GCM
-    pub fn encrypt(&self, plaintext: &str) -> Result<String, CredentialError> {
-        // Generate random nonce
-        let mut rng = rand::thread_rng();
-        let nonce_bytes: [u8; 12] = rng.gen();
-        let nonce = Nonce::from_slice(&nonce_bytes);
+    /// Also unwrap data keys still wrapped with an earlier master key, e.g.
+    /// after a rotation that did not finish
+    pub fn with_previous_master_key(mut self, master: Arc<dyn MasterKey>) -> Self {
+        self.masters.get_mut().unwrap().keys.entry(master.id()).or_insert(master);
+        self
+    }
+
+    /// ID of the master key new data keys are wrapped with
+    pub fn master_key_id(&self) -> u32 {
+        self.masters.read().unwrap().current
+    }
+
+    /// Make `master` the current master key; the previous one is kept to
+    /// unwrap data keys until they are re-wrapped with
+    /// [`CredentialStore::rewrap_all`]
+    pub fn rotate_master_key(&self, master: Arc<dyn MasterKey>) -> Result<(), CredentialError> {
+        let mut masters = self.masters.write().unwrap();
+        if masters.keys.contains_key(&master.id()) && masters.current != master.id() {
+            return Err(CredentialError::MasterKeyReused(master.id()));
+        }
+        masters.current = master.id();
+        masters.keys.insert(master.id(), master);
+        Ok(())
+    }
 
-        // Encrypt
-        let ciphertext = self
-            .cipher
-            .encrypt(nonce, plaintext.as_bytes())
-            .map_err(|_| CredentialError::EncryptionFailed)?;
+    /// Encrypt credentials with a new data key
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, CredentialError> {
+        let mut data_key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut data_key);
+        let ciphertext = seal(&Aes256Gcm::new(&data_key.into()), plaintext.as_bytes())?;
 
-        // Combine nonce + ciphertext and encode as base64
-        let mut result = nonce_bytes.to_vec();
-        result.extend_from_slice(&ciphertext);
-        Ok(general_purpose::STANDARD.encode(result))
+        let masters = self.masters.read().unwrap();
+        let wrapped = masters.keys[&masters.current].wrap(&data_key)?;
+        Ok(format!(
+            "{}:{}:{}:{}",
+            ENVELOPE_PREFIX,
+            masters.current,
+            general_purpose::STANDARD.encode(wrapped),
+            general_purpose::STANDARD.encode(ciphertext)
+        ))
     }
 
     /// Decrypt credentials
     pub fn decrypt(&self, encrypted: &str) -> Result<String, CredentialError> {
-        // Decode from base64
-        let data = general_purpose::STANDARD
-            .decode(encrypted)
-            .map_err(|_| CredentialError::InvalidFormat)?;
+        let plaintext = match Envelope::parse(encrypted)? {
+            Some(envelope) => {
+                let data_key = self.unwrap_data_key(&envelope)?;
+                let cipher = Aes256Gcm::new_from_slice(&data_key).map_err(|_| CredentialError::DecryptionFailed)?;
+                open(&cipher, &envelope.ciphertext)?
+            }
+            None => {
+                let legacy = self.legacy.as_ref().ok_or(CredentialError::InvalidFormat)?;
+                let data = general_purpose::STANDARD
+                    .decode(encrypted)
+                    .map_err(|_| CredentialError::InvalidFormat)?;
+                open(legacy, &data)?
+            }
+        };
+        String::from_utf8(plaintext).map_err(|_| CredentialError::InvalidFormat)
+    }
 
-        if data.len() < 12 {
-            return Err(CredentialError::InvalidFormat);
+    /// Re-wrap the data key of encrypted credentials with the current
+    /// master key, leaving the ciphertext as it is; credentials from before
+    /// envelopes are encrypted again
+    pub fn rewrap(&self, encrypted: &str) -> Result<String, CredentialError> {
+        let Some(envelope) = Envelope::parse(encrypted)? else {
+            return self.encrypt(&self.decrypt(encrypted)?);
+        };
+        let current = self.master_key_id();
+        if envelope.master_key_id == current {
+            return Ok(encrypted.to_string());
         }
+        let data_key = self.unwrap_data_key(&envelope)?;
+        let wrapped = self.masters.read().unwrap().keys[&current].wrap(&data_key)?;
+        Ok(format!(
+            "{}:{}:{}:{}",
+            ENVELOPE_PREFIX,
+            current,
+            general_purpose::STANDARD.encode(wrapped),
+            general_purpose::STANDARD.encode(&envelope.ciphertext)
+        ))
+    }
 
-        // Split nonce and ciphertext
-        let (nonce_bytes, ciphertext) = data.split_at(12);
-        let nonce = Nonce::from_slice(nonce_bytes);
-
-        // Decrypt
-        let plaintext = self
-            .cipher
-            .decrypt(nonce, ciphertext)
-            .map_err(|_| CredentialError::DecryptionFailed)?;
-
-        String::from_utf8(plaintext).map_err(|_| CredentialError::InvalidFormat)
+    /// Whether encrypted credentials still need [`rewrap`](Self::rewrap)
+    pub fn needs_rewrap(&self, encrypted: &str) -> bool {
+        !matches!(Envelope::parse(encrypted), Ok(Some(envelope)) if envelope.master_key_id == self.master_key_id())
     }
 
     /// Validate credentials by attempting decryption
     pub fn validate(&self, encrypted: &str) -> bool {
         self.decrypt(encrypted).is_ok()
     }
+
+    fn unwrap_data_key(&self, envelope: &Envelope) -> Result<Vec<u8>, CredentialError> {
+        let masters = self.masters.read().unwrap();
+        let master = masters
+            .keys
+            .get(&envelope.master_key_id)
+            .ok_or(CredentialError::UnknownMasterKey(envelope.master_key_id))?;
+        master.unwrap(&envelope.wrapped_key)
+    }
+}
+
+/// Parts of envelope-encrypted credentials
+struct Envelope {
+    master_key_id: u32,
+    wrapped_key: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+impl Envelope {
+    /// The envelope of encrypted credentials; `None` for credentials from
+    /// before envelopes
+    fn parse(encrypted: &str) -> Result<Option<Self>, CredentialError> {
+        let Some(rest) = encrypted.strip_prefix(ENVELOPE_PREFIX).and_then(|rest| rest.strip_prefix(':')) else {
+            return Ok(None);
+        };
+        let mut parts = rest.split(':');
+        let (Some(id), Some(wrapped_key), Some(ciphertext), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(CredentialError::InvalidFormat);
+        };
+        let decode = |part: &str| general_purpose::STANDARD.decode(part).map_err(|_| CredentialError::InvalidFormat);
+        Ok(Some(Self {
+            master_key_id: id.parse().map_err(|_| CredentialError::InvalidFormat)?,
+            wrapped_key: decode(wrapped_key)?,
+            ciphertext: decode(ciphertext)?,
+        }))
+    }
+}
+
+/// Nonce followed by the AES-256-GCM ciphertext
+fn seal(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<Vec<u8>, CredentialError> {
+    let nonce_bytes: [u8; NONCE_LEN] = rand::thread_rng().gen();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), plaintext)
+        .map_err(|_| CredentialError::EncryptionFailed)?;
+    let mut sealed = nonce_bytes.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn open(cipher: &Aes256Gcm, sealed: &[u8]) -> Result<Vec<u8>, CredentialError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(CredentialError::InvalidFormat);
+    }
+    let (nonce_bytes, ciphertext) = sealed.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
+        .map_err(|_| CredentialError::DecryptionFailed)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CredentialStatus {
+    Active,
+    /// The credential could not be upgraded to the integration's current
+    /// schema and must be reconnected by its owner
+    NeedsAttention { reason: String },
+}
+
+/// A connection credential saved by a user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredCredential {
+    pub id: Uuid,
+    pub owner: Uuid,
+    pub name: String,
+    /// Integration the credential belongs to, e.g. "slack"
+    pub integration: String,
+    pub auth_type: AuthType,
+    /// Version of the integration's credential schema `data` follows
+    pub schema_version: u32,
+    /// JSON credential fields, encrypted with the `CredentialManager`
+    pub data: String,
+    pub status: CredentialStatus,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Last time the secret itself was replaced
+    #[serde(default)]
+    pub rotated_at: Option<DateTime<Utc>>,
+    /// When the credential stops working, e.g. an API key's or a refresh
+    /// token's expiry; unknown for most credentials
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// When the OAuth2 access token in `data` expires, for tokens that are
+    /// refreshed before then
+    #[serde(default)]
+    pub token_expires_at: Option<DateTime<Utc>>,
+}
+
+impl StoredCredential {
+    /// Replace the credential's data with an OAuth2 token; the credential
+    /// is active again afterwards
+    pub fn set_token(&mut self, token: &OAuth2Token, manager: &CredentialManager) -> Result<(), CredentialError> {
+        let json = serde_json::to_string(token).map_err(|_| CredentialError::InvalidFormat)?;
+        self.data = manager.encrypt(&json)?;
+        self.status = CredentialStatus::Active;
+        // A token without a refresh token stops working when it expires
+        if token.refresh_token.is_some() {
+            self.token_expires_at = token.expiry();
+            self.expires_at = None;
+        } else {
+            self.token_expires_at = None;
+            self.expires_at = token.expiry();
+        }
+        self.updated_at = Utc::now();
+        Ok(())
+    }
+}
+
+/// What a credential is decrypted for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "purpose", rename_all = "snake_case")]
+pub enum CredentialUse {
+    /// A workflow execution, run as `user_id`
+    Execution {
+        user_id: Uuid,
+        workflow_id: Uuid,
+        execution_id: Uuid,
+    },
+    /// A user testing the connection
+    ConnectionTest { user_id: Uuid },
+    /// Refreshing the OAuth2 token of `user_id`'s credential
+    TokenRefresh { user_id: Uuid },
+}
+
+impl CredentialUse {
+    /// User the credential is decrypted for
+    pub fn user_id(&self) -> Uuid {
+        match self {
+            CredentialUse::Execution { user_id, .. }
+            | CredentialUse::ConnectionTest { user_id }
+            | CredentialUse::TokenRefresh { user_id } => *user_id,
+        }
+    }
+}
+
+/// A decryption of a credential, as recorded in the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialAccess {
+    pub credential_id: Uuid,
+    pub owner: Uuid,
+    pub integration: String,
+    pub used_by: CredentialUse,
+    /// Why the credential could not be decrypted, when it could not
+    pub error: Option<String>,
+}
+
+/// Records decryptions of credentials
+#[async_trait]
+pub trait CredentialAuditor: Send + Sync {
+    async fn record_access(&self, access: &CredentialAccess);
+}
+
+/// Persistence of saved credentials, still encrypted
+#[async_trait]
+pub trait CredentialStorage: Send + Sync {
+    /// Insert or replace a credential
+    async fn save(&self, credential: &StoredCredential) -> Result<(), CredentialError>;
+    async fn delete(&self, id: Uuid) -> Result<(), CredentialError>;
+    async fn load(&self) -> Result<Vec<StoredCredential>, CredentialError>;
+}
+
+/// In-memory credential storage (for development, replace with database in production)
+#[derive(Default)]
+pub struct InMemoryCredentialStorage {
+    credentials: RwLock<HashMap<Uuid, StoredCredential>>,
+}
+
+impl InMemoryCredentialStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl CredentialStorage for InMemoryCredentialStorage {
+    async fn save(&self, credential: &StoredCredential) -> Result<(), CredentialError> {
+        self.credentials.write().await.insert(credential.id, credential.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), CredentialError> {
+        self.credentials.write().await.remove(&id);
+        Ok(())
+    }
+
+    async fn load(&self) -> Result<Vec<StoredCredential>, CredentialError> {
+        Ok(self.credentials.read().await.values().cloned().collect())
+    }
+}
+
+/// Saved credentials of all users
+///
+/// Credentials stay encrypted in the store and everything reading it;
+/// [`decrypt`](Self::decrypt) is the way to their plaintext, and records
+/// every use with the auditor. Every change is written to the storage
+/// before it is applied; [`load`](Self::load) reads the saved credentials.
+pub struct CredentialStore {
+    credentials: Arc<RwLock<HashMap<Uuid, StoredCredential>>>,
+    storage: Arc<dyn CredentialStorage>,
+    auditor: Option<Arc<dyn CredentialAuditor>>,
+}
+
+impl CredentialStore {
+    pub fn new() -> Self {
+        Self {
+            credentials: Arc::new(RwLock::new(HashMap::new())),
+            storage: Arc::new(InMemoryCredentialStorage::new()),
+            auditor: None,
+        }
+    }
+
+    /// Keep the credentials in `storage`; [`load`](Self::load) reads the
+    /// ones saved in it
+    pub fn with_storage(mut self, storage: Arc<dyn CredentialStorage>) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Record decryptions of credentials with `auditor`
+    pub fn with_auditor(mut self, auditor: Arc<dyn CredentialAuditor>) -> Self {
+        self.auditor = Some(auditor);
+        self
+    }
+
+    /// Read the credentials saved in the storage; returns their number
+    pub async fn load(&self) -> Result<usize, CredentialError> {
+        let loaded = self.storage.load().await?;
+        let mut credentials = self.credentials.write().await;
+        credentials.clear();
+        for credential in loaded {
+            credentials.insert(credential.id, credential);
+        }
+        Ok(credentials.len())
+    }
+
+    pub async fn save(&self, credential: StoredCredential) -> Result<(), CredentialError> {
+        let mut credentials = self.credentials.write().await;
+        self.storage.save(&credential).await?;
+        credentials.insert(credential.id, credential);
+        Ok(())
+    }
+
+    pub async fn get(&self, id: Uuid) -> Option<StoredCredential> {
+        self.credentials.read().await.get(&id).cloned()
+    }
+
+    /// Credentials of an integration, oldest update first
+    pub async fn list_for_integration(&self, integration: &str) -> Vec<StoredCredential> {
+        let credentials = self.credentials.read().await;
+        let mut found: Vec<StoredCredential> = credentials.values()
+            .filter(|c| c.integration == integration)
+            .cloned()
+            .collect();
+        found.sort_by_key(|c| c.updated_at);
+        found
+    }
+
+    pub async fn list(&self) -> Vec<StoredCredential> {
+        self.credentials.read().await.values().cloned().collect()
+    }
+
+    pub async fn list_for_owner(&self, owner: Uuid) -> Vec<StoredCredential> {
+        let credentials = self.credentials.read().await;
+        credentials.values().filter(|c| c.owner == owner).cloned().collect()
+    }
+
+    /// The owner's credentials expiring before `before` (including those
+    /// already expired), soonest first
+    pub async fn expiring_for_owner(&self, owner: Uuid, before: DateTime<Utc>) -> Vec<StoredCredential> {
+        let credentials = self.credentials.read().await;
+        let mut expiring: Vec<StoredCredential> = credentials.values()
+            .filter(|c| c.owner == owner && c.expires_at.is_some_and(|at| at < before))
+            .cloned()
+            .collect();
+        expiring.sort_by_key(|c| c.expires_at);
+        expiring
+    }
+
+    /// Active credentials whose OAuth2 token expires before `before`,
+    /// soonest first
+    pub async fn tokens_expiring(&self, before: DateTime<Utc>) -> Vec<StoredCredential> {
+        let credentials = self.credentials.read().await;
+        let mut expiring: Vec<StoredCredential> = credentials.values()
+            .filter(|c| c.status == CredentialStatus::Active && c.token_expires_at.is_some_and(|at| at < before))
+            .cloned()
+            .collect();
+        expiring.sort_by_key(|c| c.token_expires_at);
+        expiring
+    }
+
+    pub async fn delete(&self, id: Uuid) -> Result<Option<StoredCredential>, CredentialError> {
+        let mut credentials = self.credentials.write().await;
+        if !credentials.contains_key(&id) {
+            return Ok(None);
+        }
+        self.storage.delete(id).await?;
+        Ok(credentials.remove(&id))
+    }
+
+    /// Replace the secret of a credential, e.g. with a regenerated API key;
+    /// the credential is active again afterwards
+    pub async fn rotate(
+        &self,
+        id: Uuid,
+        plaintext: &str,
+        expires_at: Option<DateTime<Utc>>,
+        manager: &CredentialManager,
+    ) -> Result<StoredCredential, CredentialError> {
+        let data = manager.encrypt(plaintext)?;
+        let mut credentials = self.credentials.write().await;
+        let mut credential = credentials.get(&id).cloned().ok_or(CredentialError::NotFound(id))?;
+        let now = Utc::now();
+        credential.data = data;
+        credential.status = CredentialStatus::Active;
+        credential.expires_at = expires_at;
+        credential.token_expires_at = None;
+        credential.rotated_at = Some(now);
+        credential.updated_at = now;
+        self.storage.save(&credential).await?;
+        credentials.insert(id, credential.clone());
+        tracing::info!("Rotated credential {} of {}", id, credential.integration);
+        Ok(credential)
+    }
+
+    /// Replace the OAuth2 token of a credential, e.g. with a refreshed or
+    /// reauthorized one
+    pub async fn update_token(
+        &self,
+        id: Uuid,
+        token: &OAuth2Token,
+        manager: &CredentialManager,
+    ) -> Result<StoredCredential, CredentialError> {
+        let mut credentials = self.credentials.write().await;
+        let mut credential = credentials.get(&id).cloned().ok_or(CredentialError::NotFound(id))?;
+        credential.set_token(token, manager)?;
+        credential.rotated_at = Some(credential.updated_at);
+        self.storage.save(&credential).await?;
+        credentials.insert(id, credential.clone());
+        Ok(credential)
+    }
+
+    /// Re-wrap the data keys of every credential with the manager's current
+    /// master key; returns the number of credentials re-wrapped
+    pub async fn rewrap_all(&self, manager: &CredentialManager) -> Result<usize, CredentialError> {
+        let mut credentials = self.credentials.write().await;
+        let mut rewrapped = 0;
+        for credential in credentials.values_mut() {
+            if manager.needs_rewrap(&credential.data) {
+                let mut updated = credential.clone();
+                updated.data = manager.rewrap(&credential.data)?;
+                self.storage.save(&updated).await?;
+                *credential = updated;
+                rewrapped += 1;
+            }
+        }
+        tracing::info!("Re-wrapped {} credentials with master key {}", rewrapped, manager.master_key_id());
+        Ok(rewrapped)
+    }
+
+    /// Decrypt a credential for an execution or connection test, recording
+    /// the access
+    pub async fn decrypt(
+        &self,
+        id: Uuid,
+        used_by: CredentialUse,
+        manager: &CredentialManager,
+    ) -> Result<String, CredentialError> {
+        let credential = self.get(id).await.ok_or(CredentialError::NotFound(id))?;
+        let plaintext = manager.decrypt(&credential.data);
+        if let Some(auditor) = &self.auditor {
+            let access = CredentialAccess {
+                credential_id: id,
+                owner: credential.owner,
+                integration: credential.integration.clone(),
+                used_by,
+                error: plaintext.as_ref().err().map(|e| e.to_string()),
+            };
+            auditor.record_access(&access).await;
+        }
+        plaintext
+    }
+}
+
+impl Default for CredentialStore {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -79,11 +612,37 @@ pub enum CredentialError {
 
     #[error("Invalid credential format")]
     InvalidFormat,
+
+    #[error("Credential not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Data key is wrapped with unknown master key {0}")]
+    UnknownMasterKey(u32),
+
+    #[error("Master key {0} was used before, rotate to a new key ID")]
+    MasterKeyReused(u32),
+
+    #[error("Credential storage error: {0}")]
+    Storage(String),
+}
+
+impl ErrorInfo for CredentialError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            CredentialError::InvalidFormat | CredentialError::MasterKeyReused(_) => ErrorCode::InvalidInput,
+            CredentialError::NotFound(_) => ErrorCode::NotFound,
+            CredentialError::EncryptionFailed
+            | CredentialError::DecryptionFailed
+            | CredentialError::UnknownMasterKey(_)
+            | CredentialError::Storage(_) => ErrorCode::Internal,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
 
     #[test]
     fn test_encrypt_decrypt() {
@@ -106,5 +665,121 @@ mod tests {
         assert!(manager.validate(&encrypted));
         assert!(!manager.validate("invalid"));
     }
-}
 
+    #[test]
+    fn test_credentials_from_before_envelopes_still_decrypt() {
+        let key = [3u8; 32];
+        let legacy = general_purpose::STANDARD.encode(seal(&Aes256Gcm::new(&key.into()), b"old-key").unwrap());
+        let manager = CredentialManager::new(&key);
+        assert_eq!(manager.decrypt(&legacy).unwrap(), "old-key");
+
+        assert!(manager.needs_rewrap(&legacy));
+        let rewrapped = manager.rewrap(&legacy).unwrap();
+        assert!(rewrapped.starts_with("env1:1:"));
+        assert_eq!(manager.decrypt(&rewrapped).unwrap(), "old-key");
+    }
+
+    fn credential(manager: &CredentialManager, secret: &str) -> StoredCredential {
+        StoredCredential {
+            id: Uuid::new_v4(),
+            owner: Uuid::new_v4(),
+            name: "CRM".to_string(),
+            integration: "crm".to_string(),
+            auth_type: AuthType::ApiKey,
+            schema_version: 1,
+            data: manager.encrypt(secret).unwrap(),
+            status: CredentialStatus::Active,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            rotated_at: None,
+            expires_at: None,
+            token_expires_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_master_key_rotation_rewraps_data_keys() {
+        let manager = CredentialManager::with_master_key(Arc::new(LocalMasterKey::new(1, &[1u8; 32])));
+        let store = CredentialStore::new();
+        let stored = credential(&manager, "k-1");
+        store.save(stored.clone()).await.unwrap();
+
+        manager.rotate_master_key(Arc::new(LocalMasterKey::new(2, &[2u8; 32]))).unwrap();
+        assert_eq!(store.rewrap_all(&manager).await.unwrap(), 1);
+        let rewrapped = store.get(stored.id).await.unwrap().data;
+        // The ciphertext stays, only the data key is wrapped anew
+        assert_eq!(rewrapped.rsplit(':').next(), stored.data.rsplit(':').next());
+        assert_eq!(store.rewrap_all(&manager).await.unwrap(), 0);
+
+        // The new master key alone decrypts re-wrapped credentials
+        let restarted = CredentialManager::with_master_key(Arc::new(LocalMasterKey::new(2, &[2u8; 32])));
+        assert_eq!(restarted.decrypt(&rewrapped).unwrap(), "k-1");
+        assert!(matches!(restarted.decrypt(&stored.data), Err(CredentialError::UnknownMasterKey(1))));
+        assert!(matches!(
+            manager.rotate_master_key(Arc::new(LocalMasterKey::new(1, &[1u8; 32]))),
+            Err(CredentialError::MasterKeyReused(1))
+        ));
+    }
+
+    #[derive(Default)]
+    struct Accesses(Mutex<Vec<CredentialAccess>>);
+
+    #[async_trait]
+    impl CredentialAuditor for Accesses {
+        async fn record_access(&self, access: &CredentialAccess) {
+            self.0.lock().unwrap().push(access.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rotation_and_audited_decryption() {
+        let manager = CredentialManager::new(&[5u8; 32]);
+        let accesses = Arc::new(Accesses::default());
+        let store = CredentialStore::new().with_auditor(accesses.clone());
+        let stored = credential(&manager, "k-1");
+        store.save(stored.clone()).await.unwrap();
+
+        let rotated = store.rotate(stored.id, "k-2", None, &manager).await.unwrap();
+        assert!(rotated.rotated_at.is_some());
+        assert_eq!(rotated.created_at, stored.created_at);
+
+        let used_by = CredentialUse::Execution {
+            user_id: stored.owner,
+            workflow_id: Uuid::new_v4(),
+            execution_id: Uuid::new_v4(),
+        };
+        assert_eq!(store.decrypt(stored.id, used_by, &manager).await.unwrap(), "k-2");
+        let other_key = CredentialManager::new(&[6u8; 32]);
+        assert!(store.decrypt(stored.id, used_by, &other_key).await.is_err());
+        assert!(matches!(
+            store.decrypt(Uuid::new_v4(), used_by, &manager).await,
+            Err(CredentialError::NotFound(_))
+        ));
+
+        let accesses = accesses.0.lock().unwrap();
+        assert_eq!(accesses.len(), 2);
+        assert_eq!((accesses[0].credential_id, accesses[0].used_by), (stored.id, used_by));
+        assert!(accesses[0].error.is_none());
+        assert_eq!(accesses[1].error.as_deref(), Some("Decryption failed"));
+    }
+
+    #[tokio::test]
+    async fn test_credentials_are_kept_in_storage() {
+        let manager = CredentialManager::new(&[7u8; 32]);
+        let storage = Arc::new(InMemoryCredentialStorage::new());
+        let store = CredentialStore::new().with_storage(storage.clone());
+        let (kept, deleted) = (credential(&manager, "k-1"), credential(&manager, "k-2"));
+        store.save(kept.clone()).await.unwrap();
+        store.save(deleted.clone()).await.unwrap();
+        store.rotate(kept.id, "k-3", None, &manager).await.unwrap();
+        assert_eq!(store.delete(deleted.id).await.unwrap().map(|c| c.id), Some(deleted.id));
+        assert!(store.delete(deleted.id).await.unwrap().is_none());
+
+        // Another instance on the same storage reads the changes
+        let restarted = CredentialStore::new().with_storage(storage);
+        assert_eq!(restarted.load().await.unwrap(), 1);
+        let used_by = CredentialUse::ConnectionTest { user_id: kept.owner };
+        assert_eq!(restarted.decrypt(kept.id, used_by, &manager).await.unwrap(), "k-3");
+        assert!(restarted.get(deleted.id).await.is_none());
+    }
+}
@@ -0,0 +1,719 @@
+//! Email integration.
+//!
+//! Sends mail over SMTP through lettre, with STARTTLS, implicit TLS or in
+//! the clear, and `AUTH PLAIN` when the credentials carry a username.
+//! Subjects and bodies are templates rendered with the action's `variables` through the
+//! [`TemplateEngine`]; values are HTML-escaped in the HTML body.
+//! Attachments are read from the file service's upload directory or passed
+//! inline as base64.
+
+use ai_service::{MissingVariables, TemplateEngine};
+use async_trait::async_trait;
+use base64::Engine;
+use lettre::message::header::{ContentTransferEncoding, ContentType};
+use lettre::message::{Attachment as AttachmentPart, Body, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::transport::smtp::extension::ClientId;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::integrations::{
+    ActionDefinition, AuthType, Integration, IntegrationCategory, IntegrationError, IntegrationInfo,
+    ParameterDefinition, ParameterType,
+};
+
+/// Timeout of each SMTP command
+const SMTP_TIMEOUT_SECS: u64 = 30;
+
+/// Largest total size of a message's attachments
+pub const MAX_ATTACHMENT_BYTES: usize = 25 * 1024 * 1024;
+
+/// Name the client greets the server with
+const HELLO_NAME: &str = "flowvex";
+
+/// Reply of a server refusing the credentials
+const AUTH_FAILED: u16 = 535;
+
+/// How the connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection with `STARTTLS`
+    #[default]
+    StartTls,
+    /// TLS from the first byte (SMTPS)
+    Tls,
+    /// No TLS; only for local relays
+    None,
+}
+
+impl SmtpSecurity {
+    fn default_port(self) -> u16 {
+        match self {
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::None => 25,
+        }
+    }
+}
+
+/// Credentials of the email integration, stored as JSON
+#[derive(Debug, Clone, Deserialize)]
+pub struct SmtpCredentials {
+    pub host: String,
+    /// Defaults to the port of the security mode
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    /// Sign in with `AUTH PLAIN` when set
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Sender address
+    pub from: String,
+}
+
+impl SmtpCredentials {
+    pub fn parse(credentials: &str) -> Result<Self, IntegrationError> {
+        let credentials: Self = serde_json::from_str(credentials).map_err(|_| IntegrationError::InvalidCredentials)?;
+        if credentials.host.trim().is_empty() || !is_address(&credentials.from) {
+            return Err(IntegrationError::InvalidCredentials);
+        }
+        Ok(credentials)
+    }
+
+    fn port(&self) -> u16 {
+        self.port.unwrap_or_else(|| self.security.default_port())
+    }
+
+    /// Transport to the server, signing in with `AUTH PLAIN` when a
+    /// username is set
+    fn transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>, IntegrationError> {
+        let host = self.host.trim();
+        let builder = match self.security {
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host).map_err(smtp_error)?,
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host).map_err(smtp_error)?,
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        };
+        let mut builder = builder
+            .port(self.port())
+            .hello_name(ClientId::Domain(HELLO_NAME.to_string()))
+            .timeout(Some(Duration::from_secs(SMTP_TIMEOUT_SECS)));
+        if let Some(username) = &self.username {
+            let password = self.password.clone().unwrap_or_default();
+            builder = builder
+                .credentials(Credentials::new(username.clone(), password))
+                .authentication(vec![Mechanism::Plain]);
+        }
+        Ok(builder.build())
+    }
+}
+
+/// A file attached to a message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+/// A message ready to send
+#[derive(Debug, Clone, Default)]
+pub struct Email {
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    /// Receive the message without appearing in its headers
+    pub bcc: Vec<String>,
+    pub reply_to: Option<String>,
+    pub subject: String,
+    pub text: Option<String>,
+    pub html: Option<String>,
+    pub attachments: Vec<Attachment>,
+}
+
+impl Email {
+    /// Every address the message is delivered to
+    pub fn recipients(&self) -> impl Iterator<Item = &String> {
+        self.to.iter().chain(&self.cc).chain(&self.bcc)
+    }
+
+    /// The message as sent from `from`
+    pub fn message(&self, from: &str) -> Result<Message, IntegrationError> {
+        let domain = from.rsplit('@').next().unwrap_or("localhost");
+        let mut builder = Message::builder()
+            .from(mailbox(from)?)
+            .subject(self.subject.replace(['\r', '\n'], " "))
+            .message_id(Some(format!("<{}@{}>", Uuid::new_v4(), domain)));
+        for to in &self.to {
+            builder = builder.to(mailbox(to)?);
+        }
+        for cc in &self.cc {
+            builder = builder.cc(mailbox(cc)?);
+        }
+        for bcc in &self.bcc {
+            builder = builder.bcc(mailbox(bcc)?);
+        }
+        if let Some(reply_to) = &self.reply_to {
+            builder = builder.reply_to(mailbox(reply_to)?);
+        }
+
+        let text = self.text.as_deref().map(|text| text_part(ContentType::TEXT_PLAIN, text));
+        let html = self.html.as_deref().map(|html| text_part(ContentType::TEXT_HTML, html));
+        let mut mixed = match (text, html) {
+            (Some(text), Some(html)) => {
+                let alternative = MultiPart::alternative().singlepart(text).singlepart(html);
+                if self.attachments.is_empty() {
+                    return builder.multipart(alternative).map_err(message_error);
+                }
+                MultiPart::mixed().multipart(alternative)
+            }
+            (text, html) => {
+                let part = text.or(html).unwrap_or_else(|| text_part(ContentType::TEXT_PLAIN, ""));
+                if self.attachments.is_empty() {
+                    return builder.singlepart(part).map_err(message_error);
+                }
+                MultiPart::mixed().singlepart(part)
+            }
+        };
+
+        for attachment in &self.attachments {
+            let content_type = media_type(&attachment.content_type)
+                .and_then(|content_type| ContentType::parse(&content_type).ok())
+                .ok_or_else(|| {
+                    IntegrationError::InvalidParameters(format!("invalid content type {:?}", attachment.content_type))
+                })?;
+            let content = Body::new_with_encoding(attachment.content.clone(), ContentTransferEncoding::Base64)
+                .map_err(|_| IntegrationError::InvalidParameters("cannot encode attachment".to_string()))?;
+            let filename = attachment.filename.replace(['"', '\r', '\n'], "");
+            mixed = mixed.singlepart(AttachmentPart::new(filename).body(content, content_type));
+        }
+        builder.multipart(mixed).map_err(message_error)
+    }
+}
+
+/// A base64-encoded text part
+fn text_part(content_type: ContentType, text: &str) -> SinglePart {
+    SinglePart::builder()
+        .header(content_type)
+        .header(ContentTransferEncoding::Base64)
+        .body(text.to_string())
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, IntegrationError> {
+    address
+        .parse()
+        .map_err(|_| IntegrationError::InvalidParameters(format!("invalid address {:?}", address)))
+}
+
+fn message_error(e: lettre::error::Error) -> IntegrationError {
+    IntegrationError::InvalidParameters(format!("cannot build the message: {}", e))
+}
+
+/// Failures of the server, reported with its reply. 4xx replies and
+/// connection problems are transient.
+fn smtp_error(e: lettre::transport::smtp::Error) -> IntegrationError {
+    match e.status().map(u16::from) {
+        Some(AUTH_FAILED) => IntegrationError::InvalidCredentials,
+        Some(_) if e.is_permanent() => IntegrationError::ExecutionFailed(format!("SMTP server replied {}", e)),
+        Some(_) => IntegrationError::NetworkError(format!("SMTP server replied {}", e)),
+        None if e.is_client() || e.is_response() => IntegrationError::ExecutionFailed(format!("SMTP failed: {}", e)),
+        None => IntegrationError::NetworkError(format!("SMTP failed: {}", e)),
+    }
+}
+
+/// A bare media type, as `text/csv`; parameters and anything that is not
+/// a `type/subtype` token are refused, since the value goes into a header
+fn media_type(content_type: &str) -> Option<String> {
+    let parsed: mime_guess::mime::Mime = content_type.parse().ok()?;
+    (parsed.params().next().is_none() && parsed.essence_str() == content_type.to_ascii_lowercase())
+        .then(|| parsed.essence_str().to_string())
+}
+
+/// A bare address, as `user@example.com`
+fn is_address(address: &str) -> bool {
+    let mut parts = address.splitn(2, '@');
+    let (Some(user), Some(domain)) = (parts.next(), parts.next()) else {
+        return false;
+    };
+    !user.is_empty()
+        && domain.contains('.')
+        && !address.chars().any(|c| c.is_whitespace() || c.is_control() || "<>,;\"".contains(c))
+}
+
+/// Addresses of a parameter given as one string or an array of strings
+fn addresses(params: &JsonValue, name: &str) -> Result<Vec<String>, IntegrationError> {
+    let addresses: Vec<String> = match &params[name] {
+        JsonValue::Null => Vec::new(),
+        JsonValue::String(address) => vec![address.clone()],
+        JsonValue::Array(addresses) => addresses
+            .iter()
+            .map(|address| address.as_str().map(str::to_string))
+            .collect::<Option<_>>()
+            .ok_or_else(|| IntegrationError::InvalidParameters(format!("{} must hold strings", name)))?,
+        _ => return Err(IntegrationError::InvalidParameters(format!("{} must be a string or an array", name))),
+    };
+    match addresses.iter().find(|address| !is_address(address)) {
+        Some(invalid) => Err(IntegrationError::InvalidParameters(format!("invalid address {:?} in {}", invalid, name))),
+        None => Ok(addresses),
+    }
+}
+
+/// Escape every string in `value` for HTML
+fn escape_html(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::String(s) => JsonValue::String(
+            s.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+                .replace('\'', "&#39;"),
+        ),
+        JsonValue::Array(items) => JsonValue::Array(items.iter().map(escape_html).collect()),
+        JsonValue::Object(fields) => {
+            JsonValue::Object(fields.iter().map(|(name, value)| (name.clone(), escape_html(value))).collect())
+        }
+        value => value.clone(),
+    }
+}
+
+/// Email integration over SMTP
+#[derive(Clone, Default)]
+pub struct EmailIntegration {
+    /// Upload directory of the file service, where attachments are read
+    file_root: Option<PathBuf>,
+}
+
+impl EmailIntegration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach files of the file service stored under `root`
+    pub fn with_file_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.file_root = Some(root.into());
+        self
+    }
+
+    /// Send a message
+    pub async fn send(&self, credentials: &SmtpCredentials, email: &Email) -> Result<(), IntegrationError> {
+        if email.to.is_empty() {
+            return Err(IntegrationError::InvalidParameters("to required".to_string()));
+        }
+        let message = email.message(&credentials.from)?;
+        credentials.transport()?.send(message).await.map_err(smtp_error)?;
+        Ok(())
+    }
+
+    /// The message described by an action's parameters, without attachments
+    fn email(params: &JsonValue) -> Result<Email, IntegrationError> {
+        let variables: HashMap<String, JsonValue> = match &params["variables"] {
+            JsonValue::Null => HashMap::new(),
+            JsonValue::Object(variables) => variables.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            _ => return Err(IntegrationError::InvalidParameters("variables must be an object".to_string())),
+        };
+        let engine = TemplateEngine::new().with_missing_variables(MissingVariables::Strict);
+        let render = |name: &str, variables: &HashMap<String, JsonValue>| -> Result<Option<String>, IntegrationError> {
+            params[name]
+                .as_str()
+                .map(|template| engine.render(template, variables))
+                .transpose()
+                .map_err(|e| IntegrationError::InvalidParameters(format!("{}: {}", name, e)))
+        };
+
+        let subject = render("subject", &variables)?
+            .ok_or_else(|| IntegrationError::InvalidParameters("subject required".to_string()))?;
+        let text = render("text", &variables)?;
+        let escaped = variables.iter().map(|(name, value)| (name.clone(), escape_html(value))).collect();
+        let html = render("html", &escaped)?;
+        if text.is_none() && html.is_none() {
+            return Err(IntegrationError::InvalidParameters("text or html required".to_string()));
+        }
+
+        let reply_to = addresses(params, "reply_to")?.into_iter().next();
+        Ok(Email {
+            to: addresses(params, "to")?,
+            cc: addresses(params, "cc")?,
+            bcc: addresses(params, "bcc")?,
+            reply_to,
+            subject,
+            text,
+            html,
+            attachments: Vec::new(),
+        })
+    }
+
+    /// Attachments given as `{"file": "<name>"}` from the file service or
+    /// `{"filename": "..", "content_base64": ".."}`
+    async fn attachments(&self, params: &JsonValue) -> Result<Vec<Attachment>, IntegrationError> {
+        let invalid = |message: String| IntegrationError::InvalidParameters(message);
+        let Some(specs) = params["attachments"].as_array().filter(|specs| !specs.is_empty()) else {
+            return Err(invalid("attachments must be a non-empty array".to_string()));
+        };
+
+        let mut attachments = Vec::with_capacity(specs.len());
+        let mut total = 0;
+        for spec in specs {
+            let (filename, content) = match (spec["file"].as_str(), spec["content_base64"].as_str()) {
+                (Some(file), _) => {
+                    let path = self.file_path(file)?;
+                    let content = tokio::fs::read(&path)
+                        .await
+                        .map_err(|e| invalid(format!("cannot read attachment {}: {}", file, e)))?;
+                    let name = Path::new(file).file_name().map(|name| name.to_string_lossy().into_owned());
+                    (spec["filename"].as_str().map(str::to_string).or(name).unwrap_or_default(), content)
+                }
+                (None, Some(encoded)) => {
+                    let content = base64::engine::general_purpose::STANDARD
+                        .decode(encoded)
+                        .map_err(|_| invalid("content_base64 is not valid base64".to_string()))?;
+                    let filename = spec["filename"]
+                        .as_str()
+                        .ok_or_else(|| invalid("inline attachments need a filename".to_string()))?;
+                    (filename.to_string(), content)
+                }
+                (None, None) => return Err(invalid("attachments need a file or content_base64".to_string())),
+            };
+
+            total += content.len();
+            if total > MAX_ATTACHMENT_BYTES {
+                return Err(invalid(format!("attachments are larger than {} bytes", MAX_ATTACHMENT_BYTES)));
+            }
+            let content_type = match spec["content_type"].as_str() {
+                Some(content_type) => media_type(content_type)
+                    .ok_or_else(|| invalid(format!("invalid attachment content_type {:?}", content_type)))?,
+                None => mime_guess::from_path(&filename).first_or_octet_stream().essence_str().to_string(),
+            };
+            attachments.push(Attachment { filename, content_type, content });
+        }
+        Ok(attachments)
+    }
+
+    /// Path of a file service file, which must stay inside its directory
+    fn file_path(&self, file: &str) -> Result<PathBuf, IntegrationError> {
+        let root = self
+            .file_root
+            .as_ref()
+            .ok_or_else(|| IntegrationError::InvalidParameters("file attachments are not configured".to_string()))?;
+        let relative = Path::new(file);
+        if !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(IntegrationError::InvalidParameters(format!("invalid attachment path {}", file)));
+        }
+        Ok(root.join(relative))
+    }
+}
+
+fn parameter(name: &str, display_name: &str, description: &str, param_type: ParameterType, required: bool) -> ParameterDefinition {
+    ParameterDefinition {
+        name: name.to_string(),
+        display_name: display_name.to_string(),
+        description: description.to_string(),
+        param_type,
+        required,
+        default_value: None,
+    }
+}
+
+#[async_trait]
+impl Integration for EmailIntegration {
+    fn info(&self) -> IntegrationInfo {
+        IntegrationInfo {
+            name: "email".to_string(),
+            display_name: "Email (SMTP)".to_string(),
+            description: "Send templated email with attachments through an SMTP server".to_string(),
+            category: IntegrationCategory::Email,
+            auth_type: AuthType::Basic,
+            icon_url: None,
+            tags: ["email", "smtp", "notifications"].map(String::from).to_vec(),
+        }
+    }
+
+    async fn execute(
+        &self,
+        action: &str,
+        params: JsonValue,
+        credentials: &str,
+    ) -> Result<JsonValue, IntegrationError> {
+        let mut email = match action {
+            "send_email" | "send_with_attachment" => Self::email(&params)?,
+            _ => return Err(IntegrationError::ActionNotFound(action.to_string())),
+        };
+        if action == "send_with_attachment" {
+            email.attachments = self.attachments(&params).await?;
+        }
+        let credentials = SmtpCredentials::parse(credentials)?;
+        self.send(&credentials, &email).await?;
+
+        Ok(serde_json::json!({
+            "sent": true,
+            "recipients": email.recipients().count(),
+            "attachments": email.attachments.len(),
+        }))
+    }
+
+    /// Sign in to the SMTP server and leave
+    async fn validate_credentials(&self, credentials: &str) -> Result<bool, IntegrationError> {
+        let Ok(credentials) = SmtpCredentials::parse(credentials) else {
+            return Ok(false);
+        };
+        match credentials.transport()?.test_connection().await.map_err(smtp_error) {
+            Ok(connected) => Ok(connected),
+            Err(IntegrationError::InvalidCredentials) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn actions(&self) -> Vec<ActionDefinition> {
+        let message = || {
+            vec![
+                parameter("to", "To", "Recipient address or addresses", ParameterType::Array, true),
+                parameter("cc", "Cc", "Copied addresses", ParameterType::Array, false),
+                parameter("bcc", "Bcc", "Blind-copied addresses", ParameterType::Array, false),
+                parameter("reply_to", "Reply To", "Address replies go to", ParameterType::String, false),
+                parameter("subject", "Subject", "Subject template", ParameterType::String, true),
+                parameter("text", "Text Body", "Plain text body template", ParameterType::String, false),
+                parameter("html", "HTML Body", "HTML body template", ParameterType::String, false),
+                parameter("variables", "Variables", "Values the templates are rendered with", ParameterType::Object, false),
+            ]
+        };
+        let mut with_attachments = message();
+        with_attachments.push(parameter(
+            "attachments",
+            "Attachments",
+            "Files as {\"file\": name} from the file service or {\"filename\", \"content_base64\"}",
+            ParameterType::Array,
+            true,
+        ));
+        vec![
+            ActionDefinition {
+                name: "send_email".to_string(),
+                display_name: "Send Email".to_string(),
+                description: "Send an email with text and HTML bodies".to_string(),
+                parameters: message(),
+                returns: Some("Number of recipients".to_string()),
+            },
+            ActionDefinition {
+                name: "send_with_attachment".to_string(),
+                display_name: "Send Email With Attachments".to_string(),
+                description: "Send an email with files attached".to_string(),
+                parameters: with_attachments,
+                returns: Some("Number of recipients and attachments".to_string()),
+            },
+        ]
+    }
+
+    fn clone_box(&self) -> Box<dyn Integration> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    /// Plain SMTP server accepting `user`/`pass`, recording each session
+    async fn smtp_server() -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let sessions = Arc::new(Mutex::new(Vec::new()));
+        let recorded = sessions.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let sessions = recorded.clone();
+                tokio::spawn(async move {
+                    let (reader, mut writer) = socket.into_split();
+                    let mut reader = BufReader::new(reader);
+                    let mut transcript = String::new();
+                    writer.write_all(b"220 test ESMTP\r\n").await.unwrap();
+                    let mut in_data = false;
+                    loop {
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+                            break;
+                        }
+                        transcript.push_str(&line);
+                        if in_data {
+                            if line == ".\r\n" {
+                                in_data = false;
+                                writer.write_all(b"250 queued\r\n").await.unwrap();
+                            }
+                            continue;
+                        }
+                        let reply: &[u8] = match line.trim_end() {
+                            l if l.starts_with("EHLO") => b"250-test\r\n250-AUTH PLAIN LOGIN\r\n250 8BITMIME\r\n",
+                            l if l.starts_with("AUTH PLAIN") => {
+                                let token = base64::engine::general_purpose::STANDARD.encode("\0user\0pass");
+                                match l.ends_with(&token) {
+                                    true => b"235 ok\r\n",
+                                    false => b"535 bad credentials\r\n",
+                                }
+                            }
+                            l if l.starts_with("RCPT TO:<nobody@") => b"550 no such user\r\n",
+                            "DATA" => {
+                                in_data = true;
+                                b"354 go ahead\r\n"
+                            }
+                            "QUIT" => {
+                                writer.write_all(b"221 bye\r\n").await.unwrap();
+                                break;
+                            }
+                            _ => b"250 ok\r\n",
+                        };
+                        writer.write_all(reply).await.unwrap();
+                    }
+                    let mut rest = String::new();
+                    let _ = reader.read_to_string(&mut rest).await;
+                    sessions.lock().unwrap().push(transcript);
+                });
+            }
+        });
+        let credentials = serde_json::json!({
+            "host": "127.0.0.1",
+            "port": port,
+            "security": "none",
+            "username": "user",
+            "password": "pass",
+            "from": "flows@example.com",
+        });
+        (credentials.to_string(), sessions)
+    }
+
+    /// Decoded base64 parts of a recorded message
+    fn decoded_parts(transcript: &str) -> Vec<String> {
+        transcript
+            .split("\r\n\r\n")
+            .filter_map(|block| {
+                let encoded: String = block.lines().take_while(|line| !line.starts_with("--")).collect();
+                base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok()
+            })
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .collect()
+    }
+
+    async fn wait_for_sessions(sessions: &Arc<Mutex<Vec<String>>>, count: usize) -> Vec<String> {
+        for _ in 0..100 {
+            if sessions.lock().unwrap().len() >= count {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        sessions.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn test_send_email_renders_templates() {
+        let (credentials, sessions) = smtp_server().await;
+        let params = serde_json::json!({
+            "to": "ops@example.com",
+            "cc": ["lead@example.com"],
+            "bcc": ["audit@example.com"],
+            "subject": "Run {{ workflow }} failed",
+            "text": "{{ workflow }} failed at {{ node }}",
+            "html": "<p>{{ workflow }} failed at <b>{{ node }}</b></p>",
+            "variables": { "workflow": "Nightly <sync>", "node": "fetch" },
+        });
+        let result = EmailIntegration::new().execute("send_email", params, &credentials).await.unwrap();
+        assert_eq!(result["recipients"], 3);
+
+        let transcript = wait_for_sessions(&sessions, 1).await.remove(0);
+        assert!(transcript.contains("RCPT TO:<audit@example.com>"));
+        assert!(!transcript.contains("Bcc:"));
+        assert!(transcript.contains("Cc: lead@example.com\r\n"));
+        assert!(transcript.contains("Subject: Run Nightly <sync> failed\r\n"));
+        assert!(transcript.contains("multipart/alternative"));
+        let parts = decoded_parts(&transcript);
+        assert!(parts.contains(&"Nightly <sync> failed at fetch".to_string()));
+        assert!(parts.contains(&"<p>Nightly &lt;sync&gt; failed at <b>fetch</b></p>".to_string()));
+
+        let params = serde_json::json!({ "to": "ops@example.com", "subject": "{{ missing }}", "text": "x" });
+        assert!(matches!(
+            EmailIntegration::new().execute("send_email", params, &credentials).await,
+            Err(IntegrationError::InvalidParameters(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_attachments_come_from_the_file_service() {
+        let (credentials, sessions) = smtp_server().await;
+        let root = std::env::temp_dir().join(format!("email-files-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("report.csv"), "a,b\n1,2\n").unwrap();
+        let integration = EmailIntegration::new().with_file_root(&root);
+
+        let params = serde_json::json!({
+            "to": "ops@example.com",
+            "subject": "Report",
+            "text": "Attached",
+            "attachments": [{ "file": "report.csv" }, { "filename": "note.txt", "content_base64": "aGk=" }],
+        });
+        let result = integration.execute("send_with_attachment", params.clone(), &credentials).await.unwrap();
+        assert_eq!(result["attachments"], 2);
+        let transcript = wait_for_sessions(&sessions, 1).await.remove(0);
+        assert!(transcript.contains("Content-Type: text/csv\r\n") && transcript.contains("filename=\"report.csv\""));
+        assert!(transcript.contains("filename=\"note.txt\""));
+        assert!(decoded_parts(&transcript).contains(&"a,b\n1,2\n".to_string()));
+
+        let mut escape = params.clone();
+        escape["attachments"] = serde_json::json!([{ "file": "../secrets.txt" }]);
+        assert!(matches!(
+            integration.execute("send_with_attachment", escape, &credentials).await,
+            Err(IntegrationError::InvalidParameters(_))
+        ));
+
+        // Content types go into a header and must not inject others
+        for content_type in ["text/csv\r\nBcc: spy@example.com", "text/csv; name=\"x\"", "csv"] {
+            let mut injected = params.clone();
+            injected["attachments"] = serde_json::json!([{ "filename": "a.csv", "content_base64": "aGk=", "content_type": content_type }]);
+            assert!(matches!(
+                integration.execute("send_with_attachment", injected, &credentials).await,
+                Err(IntegrationError::InvalidParameters(_))
+            ));
+        }
+        assert_eq!(media_type("Text/CSV").as_deref(), Some("text/csv"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_credentials_are_checked_with_a_handshake() {
+        let (credentials, _) = smtp_server().await;
+        let integration = EmailIntegration::new();
+        assert!(integration.validate_credentials(&credentials).await.unwrap());
+
+        let mut wrong: JsonValue = serde_json::from_str(&credentials).unwrap();
+        wrong["password"] = "wrong".into();
+        assert!(!integration.validate_credentials(&wrong.to_string()).await.unwrap());
+        assert!(!integration.validate_credentials("{}").await.unwrap());
+
+        let params = serde_json::json!({ "to": "nobody@example.com", "subject": "x", "text": "x" });
+        assert!(matches!(
+            integration.execute("send_email", params, &credentials).await,
+            Err(IntegrationError::ExecutionFailed(m)) if m.contains("550")
+        ));
+    }
+
+    #[test]
+    fn test_mime_headers() {
+        let email = Email {
+            to: vec!["a@example.com".to_string()],
+            bcc: vec!["b@example.com".to_string()],
+            subject: "Résumé".to_string(),
+            text: Some(".hidden".to_string()),
+            ..Default::default()
+        };
+        let mime = String::from_utf8(email.message("flows@example.com").unwrap().formatted()).unwrap();
+        assert!(mime.contains("Subject: =?utf-8?b?UsOpc3Vtw6k=?=\r\n"));
+        assert!(mime.contains("Message-ID: <"));
+        assert!(mime.contains("@example.com>\r\n"));
+        assert!(!mime.contains("b@example.com"));
+        assert!(!is_address("Name <a@example.com>"));
+        assert!(!is_address("a@localhost"));
+    }
+}
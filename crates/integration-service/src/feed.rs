@@ -0,0 +1,537 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::integrations::{
+    ActionDefinition, AuthType, Integration, IntegrationCategory, IntegrationError,
+    IntegrationInfo, ParameterDefinition, ParameterType,
+};
+
+/// Source format of a parsed feed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+    Json,
+}
+
+/// Feed normalized from RSS 2.0, Atom 1.0 or JSON Feed 1.x
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Feed {
+    pub format: FeedFormat,
+    pub title: String,
+    pub link: Option<String>,
+    pub description: Option<String>,
+    pub entries: Vec<FeedEntry>,
+}
+
+/// A single normalized feed entry
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FeedEntry {
+    /// GUID (RSS), id (Atom / JSON Feed), falling back to the link
+    pub id: String,
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub summary: Option<String>,
+    pub content: Option<String>,
+    pub author: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+    pub updated: Option<DateTime<Utc>>,
+    pub categories: Vec<String>,
+}
+
+/// Parser that detects the feed format and normalizes it
+pub struct FeedParser;
+
+impl FeedParser {
+    /// Parse feed content in any supported format
+    pub fn parse(content: &str) -> Result<Feed, IntegrationError> {
+        let trimmed = content.trim_start_matches('\u{feff}').trim_start();
+        if trimmed.starts_with('{') {
+            return Self::parse_json_feed(trimmed);
+        }
+
+        let doc = roxmltree::Document::parse(trimmed)
+            .map_err(|e| IntegrationError::InvalidParameters(format!("Invalid feed XML: {}", e)))?;
+        let root = doc.root_element();
+
+        match root.tag_name().name() {
+            "rss" | "RDF" => Self::parse_rss(root),
+            "feed" => Self::parse_atom(root),
+            other => Err(IntegrationError::InvalidParameters(format!(
+                "Unsupported feed root element: {}",
+                other
+            ))),
+        }
+    }
+
+    fn parse_rss(root: roxmltree::Node) -> Result<Feed, IntegrationError> {
+        // RSS 2.0 nests items in <channel>, RSS 1.0 (RDF) puts them next to it
+        let channel = child(root, "channel")
+            .ok_or_else(|| IntegrationError::InvalidParameters("RSS feed has no channel".to_string()))?;
+
+        let entries = root
+            .descendants()
+            .filter(|n| n.is_element() && n.tag_name().name() == "item")
+            .map(|item| {
+                let link = child_text(item, "link");
+                let guid = child_text(item, "guid");
+                FeedEntry {
+                    id: guid
+                        .or_else(|| link.clone())
+                        .or_else(|| child_text(item, "title"))
+                        .unwrap_or_default(),
+                    title: child_text(item, "title"),
+                    link,
+                    summary: child_text(item, "description"),
+                    content: child_text(item, "encoded"),
+                    author: child_text(item, "author").or_else(|| child_text(item, "creator")),
+                    published: child_text(item, "pubDate")
+                        .or_else(|| child_text(item, "date"))
+                        .and_then(|d| parse_date(&d)),
+                    updated: None,
+                    categories: children(item, "category")
+                        .filter_map(|c| c.text().map(|t| t.trim().to_string()))
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Ok(Feed {
+            format: FeedFormat::Rss,
+            title: child_text(channel, "title").unwrap_or_default(),
+            link: child_text(channel, "link"),
+            description: child_text(channel, "description"),
+            entries,
+        })
+    }
+
+    fn parse_atom(root: roxmltree::Node) -> Result<Feed, IntegrationError> {
+        let entries = children(root, "entry")
+            .map(|entry| {
+                let link = atom_link(entry);
+                FeedEntry {
+                    id: child_text(entry, "id")
+                        .or_else(|| link.clone())
+                        .unwrap_or_default(),
+                    title: child_text(entry, "title"),
+                    link,
+                    summary: child_text(entry, "summary"),
+                    content: child_text(entry, "content"),
+                    author: child(entry, "author").and_then(|a| child_text(a, "name")),
+                    published: child_text(entry, "published").and_then(|d| parse_date(&d)),
+                    updated: child_text(entry, "updated").and_then(|d| parse_date(&d)),
+                    categories: children(entry, "category")
+                        .filter_map(|c| c.attribute("term").map(String::from))
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Ok(Feed {
+            format: FeedFormat::Atom,
+            title: child_text(root, "title").unwrap_or_default(),
+            link: atom_link(root),
+            description: child_text(root, "subtitle"),
+            entries,
+        })
+    }
+
+    fn parse_json_feed(content: &str) -> Result<Feed, IntegrationError> {
+        let value: JsonValue = serde_json::from_str(content)
+            .map_err(|e| IntegrationError::InvalidParameters(format!("Invalid JSON feed: {}", e)))?;
+
+        let str_field = |v: &JsonValue, key: &str| v.get(key).and_then(|f| f.as_str()).map(String::from);
+
+        let entries = value["items"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .map(|item| {
+                        let link = str_field(item, "url");
+                        FeedEntry {
+                            id: str_field(item, "id")
+                                .or_else(|| item.get("id").filter(|v| v.is_number()).map(|v| v.to_string()))
+                                .or_else(|| link.clone())
+                                .unwrap_or_default(),
+                            title: str_field(item, "title"),
+                            link,
+                            summary: str_field(item, "summary"),
+                            content: str_field(item, "content_html").or_else(|| str_field(item, "content_text")),
+                            author: item
+                                .get("authors")
+                                .and_then(|a| a.get(0))
+                                .or_else(|| item.get("author"))
+                                .and_then(|a| str_field(a, "name")),
+                            published: str_field(item, "date_published").and_then(|d| parse_date(&d)),
+                            updated: str_field(item, "date_modified").and_then(|d| parse_date(&d)),
+                            categories: item["tags"]
+                                .as_array()
+                                .map(|tags| tags.iter().filter_map(|t| t.as_str().map(String::from)).collect())
+                                .unwrap_or_default(),
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Feed {
+            format: FeedFormat::Json,
+            title: str_field(&value, "title").unwrap_or_default(),
+            link: str_field(&value, "home_page_url"),
+            description: str_field(&value, "description"),
+            entries,
+        })
+    }
+
+    /// Fetch and parse a feed over HTTP
+    pub async fn fetch(client: &reqwest::Client, url: &str) -> Result<Feed, IntegrationError> {
+        let response = client
+            .get(url)
+            .header(
+                "Accept",
+                "application/rss+xml, application/atom+xml, application/feed+json, application/xml;q=0.9, */*;q=0.8",
+            )
+            .send()
+            .await
+            .map_err(|e| IntegrationError::NetworkError(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(IntegrationError::ExecutionFailed(format!(
+                "Feed request returned {}",
+                response.status()
+            )));
+        }
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| IntegrationError::NetworkError(e.to_string()))?;
+        Self::parse(&body)
+    }
+}
+
+fn child<'a, 'i>(node: roxmltree::Node<'a, 'i>, name: &str) -> Option<roxmltree::Node<'a, 'i>> {
+    node.children().find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn children<'a, 'i: 'a>(
+    node: roxmltree::Node<'a, 'i>,
+    name: &'a str,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'i>> + 'a {
+    node.children().filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+/// Text content of a child element, including CDATA sections
+fn child_text(node: roxmltree::Node, name: &str) -> Option<String> {
+    let text: String = child(node, name)?
+        .descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Prefer rel="alternate" (or no rel) links in Atom documents
+fn atom_link(node: roxmltree::Node) -> Option<String> {
+    children(node, "link")
+        .find(|l| matches!(l.attribute("rel"), None | Some("alternate")))
+        .or_else(|| child(node, "link"))
+        .and_then(|l| l.attribute("href").map(String::from))
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_rfc2822(value))
+        .map(|d| d.with_timezone(&Utc))
+        .ok()
+}
+
+/// Polls feeds and emits only entries that have not been seen before
+pub struct FeedTrigger {
+    client: reqwest::Client,
+    seen: Arc<RwLock<HashMap<String, SeenEntries>>>,
+    max_remembered: usize,
+}
+
+/// Bounded set of entry IDs already emitted for a feed
+#[derive(Debug, Default)]
+struct SeenEntries {
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl FeedTrigger {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            seen: Arc::new(RwLock::new(HashMap::new())),
+            max_remembered: 1000,
+        }
+    }
+
+    /// Poll a feed URL and return trigger payloads for new entries
+    pub async fn poll(&self, url: &str) -> Result<Vec<JsonValue>, IntegrationError> {
+        let feed = FeedParser::fetch(&self.client, url).await?;
+        Ok(self.new_entries(url, &feed).await)
+    }
+
+    /// Dedupe entries against previously emitted GUIDs
+    ///
+    /// The first poll of a feed only records the current entries so that
+    /// enabling a trigger does not replay the whole backlog.
+    pub async fn new_entries(&self, url: &str, feed: &Feed) -> Vec<JsonValue> {
+        let mut seen = self.seen.write().await;
+        let first_poll = !seen.contains_key(url);
+        let state = seen.entry(url.to_string()).or_default();
+
+        let mut payloads = Vec::new();
+        // Feeds list newest first; emit oldest first
+        for entry in feed.entries.iter().rev() {
+            if entry.id.is_empty() || !state.ids.insert(entry.id.clone()) {
+                continue;
+            }
+            state.order.push_back(entry.id.clone());
+            if state.order.len() > self.max_remembered {
+                if let Some(old) = state.order.pop_front() {
+                    state.ids.remove(&old);
+                }
+            }
+
+            if !first_poll {
+                payloads.push(serde_json::json!({
+                    "feed_url": url,
+                    "feed_title": feed.title,
+                    "entry": entry,
+                }));
+            }
+        }
+
+        payloads
+    }
+
+    /// Forget dedupe state for a feed
+    pub async fn reset(&self, url: &str) {
+        self.seen.write().await.remove(url);
+    }
+}
+
+impl Default for FeedTrigger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Feed integration exposing the `parse` action
+#[derive(Clone)]
+pub struct FeedIntegration;
+
+#[async_trait]
+impl Integration for FeedIntegration {
+    fn info(&self) -> IntegrationInfo {
+        IntegrationInfo {
+            name: "feed".to_string(),
+            display_name: "RSS / Atom Feed".to_string(),
+            description: "Parse RSS, Atom and JSON feeds into a common structure".to_string(),
+            category: IntegrationCategory::Document,
+            auth_type: AuthType::None,
+            icon_url: None,
+            tags: ["rss", "atom", "news"].map(String::from).to_vec(),
+        }
+    }
+
+    async fn execute(
+        &self,
+        action: &str,
+        params: JsonValue,
+        _credentials: &str,
+    ) -> Result<JsonValue, IntegrationError> {
+        match action {
+            "parse" => {
+                let feed = match (params["content"].as_str(), params["url"].as_str()) {
+                    (Some(content), _) => FeedParser::parse(content)?,
+                    (None, Some(url)) => FeedParser::fetch(&reqwest::Client::new(), url).await?,
+                    (None, None) => {
+                        return Err(IntegrationError::InvalidParameters(
+                            "url or content required".to_string(),
+                        ))
+                    }
+                };
+
+                let mut feed = feed;
+                if let Some(limit) = params["limit"].as_u64() {
+                    feed.entries.truncate(limit as usize);
+                }
+
+                serde_json::to_value(feed).map_err(|e| IntegrationError::ExecutionFailed(e.to_string()))
+            }
+            _ => Err(IntegrationError::ActionNotFound(action.to_string())),
+        }
+    }
+
+    async fn validate_credentials(&self, _credentials: &str) -> Result<bool, IntegrationError> {
+        Ok(true)
+    }
+
+    fn actions(&self) -> Vec<ActionDefinition> {
+        vec![ActionDefinition {
+            name: "parse".to_string(),
+            display_name: "Parse Feed".to_string(),
+            description: "Fetch or parse an RSS/Atom/JSON feed".to_string(),
+            parameters: vec![
+                ParameterDefinition {
+                    name: "url".to_string(),
+                    display_name: "Feed URL".to_string(),
+                    description: "URL of the feed to fetch".to_string(),
+                    param_type: ParameterType::String,
+                    required: false,
+                    default_value: None,
+                },
+                ParameterDefinition {
+                    name: "content".to_string(),
+                    display_name: "Content".to_string(),
+                    description: "Raw feed content, used instead of url when set".to_string(),
+                    param_type: ParameterType::String,
+                    required: false,
+                    default_value: None,
+                },
+                ParameterDefinition {
+                    name: "limit".to_string(),
+                    display_name: "Limit".to_string(),
+                    description: "Maximum number of entries to return".to_string(),
+                    param_type: ParameterType::Number,
+                    required: false,
+                    default_value: None,
+                },
+            ],
+            returns: Some("Normalized feed with entries".to_string()),
+        }]
+    }
+
+    fn clone_box(&self) -> Box<dyn Integration> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/">
+  <channel>
+    <title>Example News</title>
+    <link>https://example.com</link>
+    <description>Latest news</description>
+    <item>
+      <title>Second</title>
+      <link>https://example.com/2</link>
+      <guid>post-2</guid>
+      <pubDate>Tue, 10 Jun 2025 04:00:00 GMT</pubDate>
+      <content:encoded><![CDATA[<p>Body</p>]]></content:encoded>
+      <category>tech</category>
+    </item>
+    <item>
+      <title>First</title>
+      <link>https://example.com/1</link>
+    </item>
+  </channel>
+</rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Example Blog</title>
+  <link rel="self" href="https://example.org/feed.xml"/>
+  <link href="https://example.org/"/>
+  <entry>
+    <id>urn:uuid:1225c695</id>
+    <title>Atom entry</title>
+    <link href="https://example.org/entry"/>
+    <updated>2025-06-10T18:30:02Z</updated>
+    <author><name>Jane</name></author>
+    <category term="rust"/>
+  </entry>
+</feed>"#;
+
+    #[test]
+    fn test_parse_rss() {
+        let feed = FeedParser::parse(RSS).unwrap();
+        assert_eq!(feed.format, FeedFormat::Rss);
+        assert_eq!(feed.title, "Example News");
+        assert_eq!(feed.entries.len(), 2);
+        assert_eq!(feed.entries[0].id, "post-2");
+        assert_eq!(feed.entries[0].content.as_deref(), Some("<p>Body</p>"));
+        assert_eq!(feed.entries[0].categories, vec!["tech".to_string()]);
+        assert!(feed.entries[0].published.is_some());
+        // Falls back to link when there is no guid
+        assert_eq!(feed.entries[1].id, "https://example.com/1");
+    }
+
+    #[test]
+    fn test_parse_atom() {
+        let feed = FeedParser::parse(ATOM).unwrap();
+        assert_eq!(feed.format, FeedFormat::Atom);
+        assert_eq!(feed.link.as_deref(), Some("https://example.org/"));
+        assert_eq!(feed.entries[0].id, "urn:uuid:1225c695");
+        assert_eq!(feed.entries[0].author.as_deref(), Some("Jane"));
+        assert_eq!(feed.entries[0].categories, vec!["rust".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_json_feed() {
+        let feed = FeedParser::parse(
+            r#"{"version":"https://jsonfeed.org/version/1.1","title":"JSON","items":[
+                {"id":"1","url":"https://example.net/1","content_text":"Hi","date_published":"2025-06-10T10:00:00Z","tags":["a"]}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(feed.format, FeedFormat::Json);
+        assert_eq!(feed.entries[0].content.as_deref(), Some("Hi"));
+        assert_eq!(feed.entries[0].categories, vec!["a".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_trigger_dedupes_by_guid() {
+        let trigger = FeedTrigger::new();
+        let url = "https://example.com/rss";
+        let mut feed = FeedParser::parse(RSS).unwrap();
+
+        // First poll only primes the dedupe state
+        assert!(trigger.new_entries(url, &feed).await.is_empty());
+        assert!(trigger.new_entries(url, &feed).await.is_empty());
+
+        feed.entries.insert(0, FeedEntry {
+            id: "post-3".to_string(),
+            title: Some("Third".to_string()),
+            link: None,
+            summary: None,
+            content: None,
+            author: None,
+            published: None,
+            updated: None,
+            categories: vec![],
+        });
+        let payloads = trigger.new_entries(url, &feed).await;
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0]["entry"]["id"], "post-3");
+        assert_eq!(payloads[0]["feed_title"], "Example News");
+    }
+
+    #[tokio::test]
+    async fn test_feed_integration_parse_action() {
+        let integration = FeedIntegration;
+        let result = integration
+            .execute("parse", serde_json::json!({ "content": RSS, "limit": 1 }), "")
+            .await
+            .unwrap();
+        assert_eq!(result["entries"].as_array().unwrap().len(), 1);
+        assert!(integration.execute("parse", serde_json::json!({}), "").await.is_err());
+    }
+}
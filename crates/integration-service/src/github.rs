@@ -0,0 +1,515 @@
+//! GitHub integration.
+//!
+//! Opens issues, comments on issues and pull requests, fires repository
+//! dispatch events and reads files through the GitHub REST API, with a
+//! personal access token or an OAuth2 token of a GitHub app.
+
+use async_trait::async_trait;
+use base64::Engine;
+use reqwest::{Method, StatusCode};
+use serde_json::Value as JsonValue;
+
+use crate::integrations::{
+    ActionDefinition, AuthType, Integration, IntegrationCategory, IntegrationError, IntegrationInfo,
+    ParameterDefinition, ParameterType,
+};
+use crate::oauth::OAuth2Config;
+
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+const GITHUB_AUTH_URL: &str = "https://github.com/login/oauth/authorize";
+const GITHUB_TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+const GITHUB_API_VERSION: &str = "2022-11-28";
+
+/// OAuth2 scopes the actions need
+pub const GITHUB_SCOPES: &[&str] = &["repo"];
+
+/// GitHub integration
+#[derive(Clone)]
+pub struct GitHubIntegration {
+    client: reqwest::Client,
+    api_url: String,
+}
+
+impl GitHubIntegration {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_url: GITHUB_API_URL.to_string(),
+        }
+    }
+
+    /// Call the REST API at `api_url`, e.g. of GitHub Enterprise Server
+    pub fn with_api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// OAuth2 settings of a GitHub OAuth app, to register with the
+    /// [`OAuth2Handler`](crate::OAuth2Handler)
+    pub fn oauth_config(client_id: &str, client_secret: &str, redirect_uri: &str) -> OAuth2Config {
+        OAuth2Config {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            auth_url: GITHUB_AUTH_URL.to_string(),
+            token_url: GITHUB_TOKEN_URL.to_string(),
+            scopes: GITHUB_SCOPES.iter().map(|scope| scope.to_string()).collect(),
+            redirect_uri: redirect_uri.to_string(),
+        }
+    }
+
+    /// The token of stored credentials: an OAuth2 token or a bare personal
+    /// access token
+    fn token(credentials: &str) -> Result<String, IntegrationError> {
+        let credentials = credentials.trim();
+        let token = if credentials.starts_with('{') {
+            serde_json::from_str::<JsonValue>(credentials)
+                .ok()
+                .and_then(|token| token["access_token"].as_str().map(str::to_string))
+        } else {
+            Some(credentials.to_string())
+        };
+        token.filter(|token| !token.is_empty()).ok_or(IntegrationError::InvalidCredentials)
+    }
+
+    /// Call the REST API; answers without content are `null`
+    async fn call(
+        &self,
+        token: &str,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<JsonValue>,
+    ) -> Result<JsonValue, IntegrationError> {
+        let mut request = self
+            .client
+            .request(method, format!("{}{}", self.api_url, path))
+            .bearer_auth(token)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .header(reqwest::header::USER_AGENT, "flowvex")
+            .header("x-github-api-version", GITHUB_API_VERSION)
+            .query(query);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request.send().await.map_err(|e| IntegrationError::NetworkError(e.to_string()))?;
+
+        let status = response.status();
+        let rate_limited = status == StatusCode::TOO_MANY_REQUESTS
+            || (status == StatusCode::FORBIDDEN
+                && response
+                    .headers()
+                    .get("x-ratelimit-remaining")
+                    .is_some_and(|remaining| remaining.as_bytes() == b"0"));
+        let text = response.text().await.map_err(|e| IntegrationError::NetworkError(e.to_string()))?;
+        if status.is_success() {
+            if text.trim().is_empty() {
+                return Ok(JsonValue::Null);
+            }
+            return serde_json::from_str(&text)
+                .map_err(|e| IntegrationError::ExecutionFailed(format!("Invalid GitHub response: {}", e)));
+        }
+
+        let message = serde_json::from_str::<JsonValue>(&text)
+            .ok()
+            .and_then(|body| body["message"].as_str().map(str::to_string))
+            .unwrap_or_else(|| status.as_u16().to_string());
+        let message = format!("GitHub {} returned {}: {}", path, status.as_u16(), message);
+        Err(match status {
+            StatusCode::UNAUTHORIZED => IntegrationError::InvalidCredentials,
+            _ if rate_limited || status.is_server_error() => IntegrationError::NetworkError(message),
+            StatusCode::UNPROCESSABLE_ENTITY => IntegrationError::InvalidParameters(message),
+            _ => IntegrationError::ExecutionFailed(message),
+        })
+    }
+
+    async fn create_issue(&self, token: &str, params: &JsonValue) -> Result<JsonValue, IntegrationError> {
+        let mut issue = serde_json::json!({ "title": required_str(params, "title")? });
+        for field in ["body", "labels", "assignees", "milestone"] {
+            if !params[field].is_null() {
+                issue[field] = params[field].clone();
+            }
+        }
+        let path = format!("{}/issues", repo_path(params)?);
+        let created = self.call(token, Method::POST, &path, &[], Some(issue)).await?;
+        Ok(serde_json::json!({
+            "id": created["id"],
+            "number": created["number"],
+            "url": created["html_url"],
+        }))
+    }
+
+    /// Comment on an issue or pull request, which share their numbers
+    async fn comment_on_issue(&self, token: &str, params: &JsonValue) -> Result<JsonValue, IntegrationError> {
+        let number = params["issue_number"]
+            .as_u64()
+            .ok_or_else(|| IntegrationError::InvalidParameters("issue_number required".to_string()))?;
+        let comment = serde_json::json!({ "body": required_str(params, "body")? });
+        let path = format!("{}/issues/{}/comments", repo_path(params)?, number);
+        let created = self.call(token, Method::POST, &path, &[], Some(comment)).await?;
+        Ok(serde_json::json!({
+            "id": created["id"],
+            "url": created["html_url"],
+        }))
+    }
+
+    /// Fire a `repository_dispatch` event, e.g. to start a GitHub Actions
+    /// workflow
+    async fn create_repository_dispatch(&self, token: &str, params: &JsonValue) -> Result<JsonValue, IntegrationError> {
+        let event_type = required_str(params, "event_type")?;
+        let mut event = serde_json::json!({ "event_type": event_type });
+        if !params["client_payload"].is_null() {
+            if !params["client_payload"].is_object() {
+                return Err(IntegrationError::InvalidParameters("client_payload must be an object".to_string()));
+            }
+            event["client_payload"] = params["client_payload"].clone();
+        }
+        let path = format!("{}/dispatches", repo_path(params)?);
+        self.call(token, Method::POST, &path, &[], Some(event)).await?;
+        Ok(serde_json::json!({ "dispatched": true, "event_type": event_type }))
+    }
+
+    /// A file's content, as text when it is UTF-8, or a directory's entries
+    async fn get_file_contents(&self, token: &str, params: &JsonValue) -> Result<JsonValue, IntegrationError> {
+        let file = required_str(params, "path")?;
+        let encoded: Vec<String> = file
+            .trim_matches('/')
+            .split('/')
+            .map(|segment| urlencoding::encode(segment).into_owned())
+            .collect();
+        let path = format!("{}/contents/{}", repo_path(params)?, encoded.join("/"));
+        let query: Vec<(&str, &str)> = params["ref"].as_str().map(|git_ref| ("ref", git_ref)).into_iter().collect();
+        let contents = self.call(token, Method::GET, &path, &query, None).await?;
+
+        if let Some(entries) = contents.as_array() {
+            let entries: Vec<JsonValue> = entries
+                .iter()
+                .map(|entry| {
+                    serde_json::json!({
+                        "name": entry["name"],
+                        "path": entry["path"],
+                        "type": entry["type"],
+                        "size": entry["size"],
+                    })
+                })
+                .collect();
+            return Ok(serde_json::json!({ "type": "dir", "path": file, "entries": entries }));
+        }
+        if contents["encoding"] != "base64" {
+            return Err(IntegrationError::ExecutionFailed(format!(
+                "{} is not a file, or too large for the contents API",
+                file
+            )));
+        }
+
+        // GitHub wraps the base64 at 60 columns
+        let encoded: String = contents["content"].as_str().unwrap_or_default().split_whitespace().collect();
+        let content = base64::engine::general_purpose::STANDARD
+            .decode(&encoded)
+            .map_err(|_| IntegrationError::ExecutionFailed("Invalid file content from GitHub".to_string()))?;
+        let mut file = serde_json::json!({
+            "type": "file",
+            "path": contents["path"],
+            "sha": contents["sha"],
+            "size": contents["size"],
+        });
+        match String::from_utf8(content) {
+            Ok(text) => file["content"] = JsonValue::String(text),
+            Err(_) => file["content_base64"] = JsonValue::String(encoded),
+        }
+        Ok(file)
+    }
+}
+
+impl Default for GitHubIntegration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn required_str<'a>(params: &'a JsonValue, name: &str) -> Result<&'a str, IntegrationError> {
+    params[name]
+        .as_str()
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| IntegrationError::InvalidParameters(format!("{} required", name)))
+}
+
+/// `/repos/{owner}/{repo}` of an action's parameters
+fn repo_path(params: &JsonValue) -> Result<String, IntegrationError> {
+    Ok(format!(
+        "/repos/{}/{}",
+        urlencoding::encode(required_str(params, "owner")?),
+        urlencoding::encode(required_str(params, "repo")?)
+    ))
+}
+
+fn parameter(name: &str, display_name: &str, description: &str, param_type: ParameterType, required: bool) -> ParameterDefinition {
+    ParameterDefinition {
+        name: name.to_string(),
+        display_name: display_name.to_string(),
+        description: description.to_string(),
+        param_type,
+        required,
+        default_value: None,
+    }
+}
+
+#[async_trait]
+impl Integration for GitHubIntegration {
+    fn info(&self) -> IntegrationInfo {
+        IntegrationInfo {
+            name: "github".to_string(),
+            display_name: "GitHub".to_string(),
+            description: "Work with GitHub issues, pull requests and repositories".to_string(),
+            category: IntegrationCategory::Other,
+            auth_type: AuthType::OAuth2,
+            icon_url: None,
+            tags: ["git", "developer", "issues"].map(String::from).to_vec(),
+        }
+    }
+
+    async fn execute(
+        &self,
+        action: &str,
+        params: JsonValue,
+        credentials: &str,
+    ) -> Result<JsonValue, IntegrationError> {
+        let token = || Self::token(credentials);
+        match action {
+            "create_issue" => self.create_issue(&token()?, &params).await,
+            "comment_on_issue" => self.comment_on_issue(&token()?, &params).await,
+            "create_repository_dispatch" => self.create_repository_dispatch(&token()?, &params).await,
+            "get_file_contents" => self.get_file_contents(&token()?, &params).await,
+            _ => Err(IntegrationError::ActionNotFound(action.to_string())),
+        }
+    }
+
+    async fn validate_credentials(&self, credentials: &str) -> Result<bool, IntegrationError> {
+        let Ok(token) = Self::token(credentials) else {
+            return Ok(false);
+        };
+        match self.call(&token, Method::GET, "/user", &[], None).await {
+            Ok(_) => Ok(true),
+            Err(IntegrationError::InvalidCredentials) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn actions(&self) -> Vec<ActionDefinition> {
+        let owner = || parameter("owner", "Owner", "User or organization owning the repository", ParameterType::String, true);
+        let repo = || parameter("repo", "Repository", "Name of the repository", ParameterType::String, true);
+        vec![
+            ActionDefinition {
+                name: "create_issue".to_string(),
+                display_name: "Create Issue".to_string(),
+                description: "Open an issue".to_string(),
+                parameters: vec![
+                    owner(),
+                    repo(),
+                    parameter("title", "Title", "Title of the issue", ParameterType::String, true),
+                    parameter("body", "Body", "Description, in GitHub markdown", ParameterType::String, false),
+                    parameter("labels", "Labels", "Names of labels to add", ParameterType::Array, false),
+                    parameter("assignees", "Assignees", "Logins of users to assign", ParameterType::Array, false),
+                ],
+                returns: Some("Number and URL of the issue".to_string()),
+            },
+            ActionDefinition {
+                name: "comment_on_issue".to_string(),
+                display_name: "Comment on Issue or Pull Request".to_string(),
+                description: "Add a comment to an issue or pull request".to_string(),
+                parameters: vec![
+                    owner(),
+                    repo(),
+                    parameter("issue_number", "Number", "Number of the issue or pull request", ParameterType::Number, true),
+                    parameter("body", "Comment", "Comment, in GitHub markdown", ParameterType::String, true),
+                ],
+                returns: Some("ID and URL of the comment".to_string()),
+            },
+            ActionDefinition {
+                name: "create_repository_dispatch".to_string(),
+                display_name: "Trigger Repository Dispatch".to_string(),
+                description: "Fire a repository_dispatch event to start GitHub Actions workflows".to_string(),
+                parameters: vec![
+                    owner(),
+                    repo(),
+                    parameter("event_type", "Event Type", "Type the workflows listen for", ParameterType::String, true),
+                    parameter("client_payload", "Payload", "Data passed to the workflows", ParameterType::Object, false),
+                ],
+                returns: None,
+            },
+            ActionDefinition {
+                name: "get_file_contents".to_string(),
+                display_name: "Get File Contents".to_string(),
+                description: "Read a file or list a directory".to_string(),
+                parameters: vec![
+                    owner(),
+                    repo(),
+                    parameter("path", "Path", "Path of the file in the repository", ParameterType::String, true),
+                    parameter("ref", "Ref", "Branch, tag or commit; the default branch otherwise", ParameterType::String, false),
+                ],
+                returns: Some("The file's content, as text when it is UTF-8, or the directory's entries".to_string()),
+            },
+        ]
+    }
+
+    fn clone_box(&self) -> Box<dyn Integration> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{Path, Query, State};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::response::IntoResponse;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    const TOKEN: &str = "ghp_test";
+
+    type Requests = Arc<Mutex<Vec<(String, JsonValue)>>>;
+
+    fn authorized(headers: &HeaderMap) -> bool {
+        headers.get("authorization").is_some_and(|value| value == "Bearer ghp_test") && headers.contains_key("user-agent")
+    }
+
+    async fn fake_github(requests: Requests) -> GitHubIntegration {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new()
+            .route(
+                "/user",
+                get(|headers: HeaderMap| async move {
+                    match authorized(&headers) {
+                        true => Json(serde_json::json!({ "login": "octocat" })).into_response(),
+                        false => (StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "message": "Bad credentials" })))
+                            .into_response(),
+                    }
+                }),
+            )
+            .route(
+                "/repos/:owner/:repo/issues",
+                post(|State(requests): State<Requests>, Path((owner, repo)): Path<(String, String)>, Json(issue): Json<JsonValue>| async move {
+                    if issue["labels"].as_array().is_some_and(|labels| labels.iter().any(|label| label == "")) {
+                        let error = serde_json::json!({ "message": "Validation Failed" });
+                        return (StatusCode::UNPROCESSABLE_ENTITY, Json(error)).into_response();
+                    }
+                    requests.lock().unwrap().push((format!("{}/{}", owner, repo), issue));
+                    let url = format!("https://github.com/{}/{}/issues/7", owner, repo);
+                    Json(serde_json::json!({ "id": 1007, "number": 7, "html_url": url })).into_response()
+                }),
+            )
+            .route(
+                "/repos/:owner/:repo/issues/:number/comments",
+                post(|State(requests): State<Requests>, Path((_, _, number)): Path<(String, String, u64)>, Json(comment): Json<JsonValue>| async move {
+                    requests.lock().unwrap().push((format!("comment {}", number), comment));
+                    Json(serde_json::json!({ "id": 55, "html_url": "https://github.com/o/r/pull/3#issuecomment-55" }))
+                }),
+            )
+            .route(
+                "/repos/:owner/:repo/dispatches",
+                post(|State(requests): State<Requests>, Json(event): Json<JsonValue>| async move {
+                    requests.lock().unwrap().push(("dispatch".to_string(), event));
+                    StatusCode::NO_CONTENT
+                }),
+            )
+            .route(
+                "/repos/:owner/:repo/contents/*path",
+                get(|Path((_, _, path)): Path<(String, String, String)>, Query(query): Query<HashMap<String, String>>| async move {
+                    match path.as_str() {
+                        "docs" => Json(serde_json::json!([
+                            { "name": "guide.md", "path": "docs/guide.md", "type": "file", "size": 12 },
+                        ]))
+                        .into_response(),
+                        "docs/guide.md" => Json(serde_json::json!({
+                            "type": "file",
+                            "encoding": "base64",
+                            "path": path,
+                            "sha": query.get("ref").cloned().unwrap_or_default(),
+                            "size": 12,
+                            "content": "IyBHdWlkZQpI\naSB0aGVyZQo=\n",
+                        }))
+                        .into_response(),
+                        _ => (StatusCode::NOT_FOUND, Json(serde_json::json!({ "message": "Not Found" }))).into_response(),
+                    }
+                }),
+            )
+            .with_state(requests);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        GitHubIntegration::new().with_api_url(&url)
+    }
+
+    #[tokio::test]
+    async fn test_issues_comments_and_dispatches() {
+        let requests = Requests::default();
+        let integration = fake_github(requests.clone()).await;
+
+        let issue = serde_json::json!({
+            "owner": "acme",
+            "repo": "site",
+            "title": "Nightly scrape failed",
+            "labels": ["flowvex"],
+        });
+        let created = integration.execute("create_issue", issue, TOKEN).await.unwrap();
+        assert_eq!(created["number"], 7);
+        assert_eq!(created["url"], "https://github.com/acme/site/issues/7");
+
+        let comment = serde_json::json!({ "owner": "acme", "repo": "site", "issue_number": 3, "body": "Deployed" });
+        let comment = integration.execute("comment_on_issue", comment, r#"{"access_token": "ghp_test"}"#).await.unwrap();
+        assert_eq!(comment["id"], 55);
+
+        let dispatch = serde_json::json!({
+            "owner": "acme",
+            "repo": "site",
+            "event_type": "deploy",
+            "client_payload": { "env": "prod" },
+        });
+        let result = integration.execute("create_repository_dispatch", dispatch, TOKEN).await.unwrap();
+        assert_eq!(result["dispatched"], true);
+
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests[0].0, "acme/site");
+        assert_eq!(requests[0].1["labels"][0], "flowvex");
+        assert_eq!(requests[1], ("comment 3".to_string(), serde_json::json!({ "body": "Deployed" })));
+        assert_eq!(requests[2].1["client_payload"]["env"], "prod");
+
+        let invalid = serde_json::json!({ "owner": "acme", "repo": "site", "title": "x", "labels": [""] });
+        assert!(matches!(
+            integration.execute("create_issue", invalid, TOKEN).await,
+            Err(IntegrationError::InvalidParameters(m)) if m.contains("Validation Failed")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_file_contents_and_credentials() {
+        let integration = fake_github(Requests::default()).await;
+
+        let params = serde_json::json!({ "owner": "acme", "repo": "site", "path": "docs/guide.md", "ref": "main" });
+        let file = integration.execute("get_file_contents", params, TOKEN).await.unwrap();
+        assert_eq!(file["content"], "# Guide\nHi there\n");
+        assert_eq!(file["sha"], "main");
+
+        let params = serde_json::json!({ "owner": "acme", "repo": "site", "path": "docs" });
+        let dir = integration.execute("get_file_contents", params, TOKEN).await.unwrap();
+        assert_eq!(dir["entries"][0]["path"], "docs/guide.md");
+
+        let params = serde_json::json!({ "owner": "acme", "repo": "site", "path": "missing" });
+        assert!(matches!(
+            integration.execute("get_file_contents", params, TOKEN).await,
+            Err(IntegrationError::ExecutionFailed(m)) if m.contains("Not Found")
+        ));
+
+        assert!(integration.validate_credentials(TOKEN).await.unwrap());
+        assert!(!integration.validate_credentials("ghp_wrong").await.unwrap());
+        assert!(!integration.validate_credentials("").await.unwrap());
+    }
+
+    #[test]
+    fn test_oauth_config() {
+        let config = GitHubIntegration::oauth_config("id", "secret", "https://flowvex.dev/oauth/callback");
+        assert_eq!(config.auth_url, GITHUB_AUTH_URL);
+        assert_eq!(config.scopes, vec!["repo".to_string()]);
+    }
+}
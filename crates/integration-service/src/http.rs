@@ -0,0 +1,625 @@
+//! HTTP request integration.
+//!
+//! The `request` action sends any method with custom headers, query
+//! parameters and a JSON, form, multipart or text body, authenticated with
+//! basic or bearer credentials. Responses are returned as JSON, text or
+//! base64 bytes up to a size cap; error statuses fail the action unless
+//! `fail_on_status` is turned off.
+
+use async_trait::async_trait;
+use base64::Engine;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{redirect, Method, StatusCode};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::integrations::{
+    ActionDefinition, AuthType, Integration, IntegrationCategory, IntegrationError, IntegrationInfo,
+    ParameterDefinition, ParameterType,
+};
+
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Characters of an error response kept in the error message
+const ERROR_BODY_CHARS: usize = 200;
+
+/// Credentials of the HTTP integration, stored as JSON
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HttpCredentials {
+    Basic {
+        username: String,
+        #[serde(default)]
+        password: Option<String>,
+    },
+    Bearer { token: String },
+}
+
+impl HttpCredentials {
+    /// Parse stored credentials; empty credentials mean none
+    pub fn parse(credentials: &str) -> Result<Option<Self>, IntegrationError> {
+        if credentials.trim().is_empty() {
+            return Ok(None);
+        }
+        serde_json::from_str(credentials).map(Some).map_err(|_| IntegrationError::InvalidCredentials)
+    }
+}
+
+/// How the `body` parameter is sent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyType {
+    #[default]
+    Json,
+    /// `application/x-www-form-urlencoded` from an object of fields
+    Form,
+    /// `multipart/form-data` from an object of text fields and files
+    Multipart,
+    /// A string sent as is
+    Text,
+}
+
+/// How the response body is read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseType {
+    /// JSON when the content type says so, else text, else bytes
+    #[default]
+    Auto,
+    Json,
+    Text,
+    /// Base64-encoded
+    Bytes,
+}
+
+/// Parameters of the `request` action
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpRequestParams {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Query parameters added to the URL; scalar values are stringified
+    #[serde(default)]
+    pub query: BTreeMap<String, JsonValue>,
+    #[serde(default)]
+    pub body: Option<JsonValue>,
+    #[serde(default)]
+    pub body_type: BodyType,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_follow_redirects")]
+    pub follow_redirects: bool,
+    #[serde(default = "default_max_redirects")]
+    pub max_redirects: usize,
+    #[serde(default)]
+    pub response_type: ResponseType,
+    #[serde(default = "default_max_response_bytes")]
+    pub max_response_bytes: usize,
+    /// Fail on 4xx and 5xx responses instead of returning them
+    #[serde(default = "default_fail_on_status")]
+    pub fail_on_status: bool,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+fn default_follow_redirects() -> bool {
+    true
+}
+
+fn default_max_redirects() -> usize {
+    DEFAULT_MAX_REDIRECTS
+}
+
+fn default_max_response_bytes() -> usize {
+    DEFAULT_MAX_RESPONSE_BYTES
+}
+
+fn default_fail_on_status() -> bool {
+    true
+}
+
+impl HttpRequestParams {
+    fn method(&self) -> Result<Method, IntegrationError> {
+        match self.method.to_ascii_uppercase().as_str() {
+            "GET" => Ok(Method::GET),
+            "POST" => Ok(Method::POST),
+            "PUT" => Ok(Method::PUT),
+            "PATCH" => Ok(Method::PATCH),
+            "DELETE" => Ok(Method::DELETE),
+            "HEAD" => Ok(Method::HEAD),
+            other => Err(IntegrationError::InvalidParameters(format!("Invalid method {}", other))),
+        }
+    }
+
+    fn headers(&self) -> Result<HeaderMap, IntegrationError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let invalid = || IntegrationError::InvalidParameters(format!("Invalid header {}", name));
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?;
+            let value = HeaderValue::from_str(value).map_err(|_| invalid())?;
+            headers.insert(name, value);
+        }
+        Ok(headers)
+    }
+
+    fn query(&self) -> Result<Vec<(&str, String)>, IntegrationError> {
+        self.query
+            .iter()
+            .map(|(name, value)| Ok((name.as_str(), scalar(name, value)?)))
+            .collect()
+    }
+}
+
+/// A form or query value as text
+fn scalar(name: &str, value: &JsonValue) -> Result<String, IntegrationError> {
+    match value {
+        JsonValue::String(s) => Ok(s.clone()),
+        JsonValue::Number(n) => Ok(n.to_string()),
+        JsonValue::Bool(b) => Ok(b.to_string()),
+        _ => Err(IntegrationError::InvalidParameters(format!("{} must be a string, number or boolean", name))),
+    }
+}
+
+fn body_fields(body: &JsonValue) -> Result<&serde_json::Map<String, JsonValue>, IntegrationError> {
+    body.as_object()
+        .ok_or_else(|| IntegrationError::InvalidParameters("form and multipart bodies must be objects".to_string()))
+}
+
+/// A multipart body: scalar fields are text parts, objects with a
+/// `filename` and `content` (or base64 `content_base64`) are files
+fn multipart_form(body: &JsonValue) -> Result<reqwest::multipart::Form, IntegrationError> {
+    let mut form = reqwest::multipart::Form::new();
+    for (name, value) in body_fields(body)? {
+        let JsonValue::Object(file) = value else {
+            form = form.text(name.clone(), scalar(name, value)?);
+            continue;
+        };
+        let invalid = |message: &str| IntegrationError::InvalidParameters(format!("{}: {}", name, message));
+        let content = match (&file.get("content"), &file.get("content_base64")) {
+            (Some(JsonValue::String(text)), _) => text.clone().into_bytes(),
+            (_, Some(JsonValue::String(encoded))) => base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|_| invalid("content_base64 is not valid base64"))?,
+            _ => return Err(invalid("file parts need content or content_base64")),
+        };
+        let mut part = reqwest::multipart::Part::bytes(content);
+        if let Some(filename) = file.get("filename").and_then(JsonValue::as_str) {
+            part = part.file_name(filename.to_string());
+        }
+        if let Some(content_type) = file.get("content_type").and_then(JsonValue::as_str) {
+            part = part.mime_str(content_type).map_err(|_| invalid("invalid content_type"))?;
+        }
+        form = form.part(name.clone(), part);
+    }
+    Ok(form)
+}
+
+/// The error of a response with an error status
+fn status_error(status: StatusCode, body: &[u8]) -> IntegrationError {
+    let snippet: String = String::from_utf8_lossy(body).chars().take(ERROR_BODY_CHARS).collect();
+    let message = format!("HTTP {}: {}", status.as_u16(), snippet.trim());
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => IntegrationError::InvalidCredentials,
+        StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS => IntegrationError::NetworkError(message),
+        status if status.is_server_error() => IntegrationError::NetworkError(message),
+        _ => IntegrationError::ExecutionFailed(message),
+    }
+}
+
+fn network_error(e: reqwest::Error) -> IntegrationError {
+    if e.is_builder() {
+        IntegrationError::InvalidParameters(e.to_string())
+    } else {
+        IntegrationError::NetworkError(e.to_string())
+    }
+}
+
+/// HTTP request integration
+#[derive(Clone)]
+pub struct HttpIntegration;
+
+impl HttpIntegration {
+    /// Send a request and read its response
+    pub async fn request(&self, params: HttpRequestParams, credentials: &str) -> Result<JsonValue, IntegrationError> {
+        let method = params.method()?;
+        let redirects = if params.follow_redirects {
+            redirect::Policy::limited(params.max_redirects)
+        } else {
+            redirect::Policy::none()
+        };
+        let client = reqwest::Client::builder()
+            .redirect(redirects)
+            .timeout(Duration::from_secs(params.timeout_secs))
+            .build()
+            .map_err(|e| IntegrationError::ExecutionFailed(e.to_string()))?;
+
+        let mut request = client
+            .request(method.clone(), &params.url)
+            .headers(params.headers()?)
+            .query(&params.query()?);
+        request = match HttpCredentials::parse(credentials)? {
+            Some(HttpCredentials::Basic { username, password }) => request.basic_auth(username, password),
+            Some(HttpCredentials::Bearer { token }) => request.bearer_auth(token),
+            None => request,
+        };
+        if let Some(body) = params.body.as_ref().filter(|body| !body.is_null()) {
+            request = match params.body_type {
+                BodyType::Json => request.json(body),
+                BodyType::Form => {
+                    let fields = body_fields(body)?
+                        .iter()
+                        .map(|(name, value)| Ok((name.clone(), scalar(name, value)?)))
+                        .collect::<Result<Vec<_>, IntegrationError>>()?;
+                    request.form(&fields)
+                }
+                BodyType::Multipart => request.multipart(multipart_form(body)?),
+                BodyType::Text => match body {
+                    JsonValue::String(text) => request.body(text.clone()),
+                    body => request.body(body.to_string()),
+                },
+            };
+        }
+
+        let mut response = request.send().await.map_err(network_error)?;
+        let status = response.status();
+        let url = response.url().to_string();
+        let headers: BTreeMap<String, String> = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let content_type = headers.get(CONTENT_TYPE.as_str()).cloned().unwrap_or_default();
+
+        let too_large = || {
+            IntegrationError::ExecutionFailed(format!(
+                "Response is larger than {} bytes",
+                params.max_response_bytes
+            ))
+        };
+        if response.content_length().is_some_and(|length| length > params.max_response_bytes as u64) {
+            return Err(too_large());
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(network_error)? {
+            if bytes.len() + chunk.len() > params.max_response_bytes {
+                return Err(too_large());
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        if params.fail_on_status && (status.is_client_error() || status.is_server_error()) {
+            return Err(status_error(status, &bytes));
+        }
+
+        let (body_type, body) = if method == Method::HEAD || bytes.is_empty() {
+            ("empty", JsonValue::Null)
+        } else {
+            read_body(&bytes, &content_type, params.response_type)?
+        };
+        Ok(serde_json::json!({
+            "status": status.as_u16(),
+            "url": url,
+            "headers": headers,
+            "body_type": body_type,
+            "body": body,
+        }))
+    }
+}
+
+/// The body as JSON, text or base64 bytes, with the name of its form
+fn read_body(bytes: &[u8], content_type: &str, response_type: ResponseType) -> Result<(&'static str, JsonValue), IntegrationError> {
+    let bytes_body = || ("bytes", JsonValue::String(base64::engine::general_purpose::STANDARD.encode(bytes)));
+    match response_type {
+        ResponseType::Json => serde_json::from_slice(bytes)
+            .map(|json| ("json", json))
+            .map_err(|e| IntegrationError::ExecutionFailed(format!("Response is not JSON: {}", e))),
+        ResponseType::Text => Ok(("text", JsonValue::String(String::from_utf8_lossy(bytes).into_owned()))),
+        ResponseType::Bytes => Ok(bytes_body()),
+        ResponseType::Auto => {
+            let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+            if mime == "application/json" || mime.ends_with("+json") {
+                if let Ok(json) = serde_json::from_slice(bytes) {
+                    return Ok(("json", json));
+                }
+            }
+            Ok(match std::str::from_utf8(bytes) {
+                Ok(text) => ("text", JsonValue::String(text.to_string())),
+                Err(_) => bytes_body(),
+            })
+        }
+    }
+}
+
+#[async_trait]
+impl Integration for HttpIntegration {
+    fn info(&self) -> IntegrationInfo {
+        IntegrationInfo {
+            name: "http".to_string(),
+            display_name: "HTTP Request".to_string(),
+            description: "Make HTTP requests to any endpoint".to_string(),
+            category: IntegrationCategory::Http,
+            auth_type: AuthType::None,
+            icon_url: None,
+            tags: ["http", "api", "webhook"].map(String::from).to_vec(),
+        }
+    }
+
+    async fn execute(
+        &self,
+        action: &str,
+        params: JsonValue,
+        credentials: &str,
+    ) -> Result<JsonValue, IntegrationError> {
+        match action {
+            "request" => {
+                let params: HttpRequestParams = serde_json::from_value(params)
+                    .map_err(|e| IntegrationError::InvalidParameters(e.to_string()))?;
+                self.request(params, credentials).await
+            }
+            _ => Err(IntegrationError::ActionNotFound(action.to_string())),
+        }
+    }
+
+    async fn validate_credentials(&self, credentials: &str) -> Result<bool, IntegrationError> {
+        Ok(HttpCredentials::parse(credentials).is_ok())
+    }
+
+    fn actions(&self) -> Vec<ActionDefinition> {
+        let parameter = |name: &str, display_name: &str, description: &str, param_type, required, default_value| {
+            ParameterDefinition {
+                name: name.to_string(),
+                display_name: display_name.to_string(),
+                description: description.to_string(),
+                param_type,
+                required,
+                default_value,
+            }
+        };
+        vec![ActionDefinition {
+            name: "request".to_string(),
+            display_name: "HTTP Request".to_string(),
+            description: "Make an HTTP request".to_string(),
+            parameters: vec![
+                parameter("url", "URL", "The URL to request", ParameterType::String, true, None),
+                parameter(
+                    "method",
+                    "Method",
+                    "GET, POST, PUT, PATCH, DELETE or HEAD",
+                    ParameterType::String,
+                    false,
+                    Some(serde_json::json!("GET")),
+                ),
+                parameter("headers", "Headers", "Request headers by name", ParameterType::Object, false, None),
+                parameter("query", "Query", "Query parameters by name", ParameterType::Object, false, None),
+                parameter("body", "Body", "Request body", ParameterType::Object, false, None),
+                parameter(
+                    "body_type",
+                    "Body Type",
+                    "json, form, multipart or text",
+                    ParameterType::String,
+                    false,
+                    Some(serde_json::json!("json")),
+                ),
+                parameter(
+                    "timeout_secs",
+                    "Timeout",
+                    "Seconds before the request is abandoned",
+                    ParameterType::Number,
+                    false,
+                    Some(serde_json::json!(DEFAULT_TIMEOUT_SECS)),
+                ),
+                parameter(
+                    "follow_redirects",
+                    "Follow Redirects",
+                    "Follow redirects, up to max_redirects",
+                    ParameterType::Boolean,
+                    false,
+                    Some(serde_json::json!(true)),
+                ),
+                parameter(
+                    "max_redirects",
+                    "Max Redirects",
+                    "Redirects followed before failing",
+                    ParameterType::Number,
+                    false,
+                    Some(serde_json::json!(DEFAULT_MAX_REDIRECTS)),
+                ),
+                parameter(
+                    "response_type",
+                    "Response Type",
+                    "auto, json, text or bytes (base64)",
+                    ParameterType::String,
+                    false,
+                    Some(serde_json::json!("auto")),
+                ),
+                parameter(
+                    "max_response_bytes",
+                    "Max Response Size",
+                    "Largest response body read, in bytes",
+                    ParameterType::Number,
+                    false,
+                    Some(serde_json::json!(DEFAULT_MAX_RESPONSE_BYTES)),
+                ),
+                parameter(
+                    "fail_on_status",
+                    "Fail On Error Status",
+                    "Fail on 4xx and 5xx responses instead of returning them",
+                    ParameterType::Boolean,
+                    false,
+                    Some(serde_json::json!(true)),
+                ),
+            ],
+            returns: Some("Response object with status, url, headers and body".to_string()),
+        }]
+    }
+
+    fn clone_box(&self) -> Box<dyn Integration> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve one request per connection: `/redirect` redirects to `/`,
+    /// `/status/<code>` answers with that status, `/bytes` with binary and
+    /// anything else echoes the raw request as text
+    async fn upstream() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 4096];
+                    loop {
+                        let n = socket.read(&mut buf).await.unwrap();
+                        request.extend_from_slice(&buf[..n]);
+                        let text = String::from_utf8_lossy(&request).to_string();
+                        if let Some(end) = text.find("\r\n\r\n") {
+                            let length = text
+                                .lines()
+                                .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length: ").map(str::to_string))
+                                .and_then(|length| length.trim().parse::<usize>().ok())
+                                .unwrap_or(0);
+                            if request.len() >= end + 4 + length || n == 0 {
+                                break;
+                            }
+                        }
+                    }
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    let path = text.split(' ').nth(1).unwrap_or("/").to_string();
+                    let (status, content_type, body): (&str, &str, Vec<u8>) = if path == "/redirect" {
+                        ("302 Found\r\nLocation: /", "text/plain", Vec::new())
+                    } else if let Some(code) = path.strip_prefix("/status/") {
+                        (if code == "401" { "401 Unauthorized" } else { "500 Internal Server Error" }, "text/plain", b"nope".to_vec())
+                    } else if path == "/bytes" {
+                        ("200 OK", "application/octet-stream", vec![0xff, 0x00, 0xfe])
+                    } else if path == "/json" {
+                        ("200 OK", "application/json", br#"{"ok":true}"#.to_vec())
+                    } else {
+                        ("200 OK", "text/plain", request.clone())
+                    };
+                    let head = format!(
+                        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        status,
+                        content_type,
+                        body.len()
+                    );
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    let _ = socket.write_all(&body).await;
+                });
+            }
+        });
+        url
+    }
+
+    async fn request(params: JsonValue, credentials: &str) -> Result<JsonValue, IntegrationError> {
+        HttpIntegration.execute("request", params, credentials).await
+    }
+
+    #[tokio::test]
+    async fn test_http_integration() {
+        let integration = HttpIntegration;
+        let info = integration.info();
+        assert_eq!(info.name, "http");
+
+        let actions = integration.actions();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].name, "request");
+        assert!(integration.validate_credentials(r#"{"type": "bearer", "token": "t"}"#).await.unwrap());
+        assert!(!integration.validate_credentials("not json").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_methods_headers_query_and_auth() {
+        let url = upstream().await;
+        let params = serde_json::json!({
+            "url": format!("{}/echo", url),
+            "method": "patch",
+            "headers": { "X-Trace": "abc" },
+            "query": { "page": 2, "q": "a b" },
+            "body": { "name": "flow" },
+        });
+        let response = request(params, r#"{"type": "bearer", "token": "secret"}"#).await.unwrap();
+        assert_eq!(response["status"], 200);
+        assert_eq!(response["body_type"], "text");
+        let echo = response["body"].as_str().unwrap().to_ascii_lowercase();
+        assert!(echo.starts_with("patch /echo?page=2&q=a+b http/1.1"));
+        assert!(echo.contains("x-trace: abc"));
+        assert!(echo.contains("authorization: bearer secret"));
+        assert!(echo.ends_with(r#"{"name":"flow"}"#));
+
+        let params = serde_json::json!({ "url": url, "method": "HEAD" });
+        let response = request(params, r#"{"type": "basic", "username": "u"}"#).await.unwrap();
+        assert_eq!(response["body"], JsonValue::Null);
+        let params = serde_json::json!({ "url": url, "method": "TRACE" });
+        assert!(matches!(request(params, "").await, Err(IntegrationError::InvalidParameters(_))));
+    }
+
+    #[tokio::test]
+    async fn test_form_and_multipart_bodies() {
+        let url = upstream().await;
+        let params = serde_json::json!({ "url": url, "method": "POST", "body_type": "form", "body": { "a": 1, "b": "x y" } });
+        let echo = request(params, "").await.unwrap()["body"].as_str().unwrap().to_string();
+        assert!(echo.contains("application/x-www-form-urlencoded"));
+        assert!(echo.ends_with("a=1&b=x+y"));
+
+        let params = serde_json::json!({
+            "url": url,
+            "method": "PUT",
+            "body_type": "multipart",
+            "body": {
+                "title": "report",
+                "file": { "filename": "r.txt", "content": "hello", "content_type": "text/plain" },
+            },
+        });
+        let echo = request(params, "").await.unwrap()["body"].as_str().unwrap().to_string();
+        assert!(echo.contains("multipart/form-data; boundary="));
+        assert!(echo.contains("name=\"file\"; filename=\"r.txt\""));
+        assert!(echo.contains("hello"));
+    }
+
+    #[tokio::test]
+    async fn test_response_controls() {
+        let url = upstream().await;
+        let response = request(serde_json::json!({ "url": format!("{}/json", url) }), "").await.unwrap();
+        assert_eq!((response["body_type"].as_str(), &response["body"]["ok"]), (Some("json"), &JsonValue::Bool(true)));
+        let response = request(serde_json::json!({ "url": format!("{}/bytes", url) }), "").await.unwrap();
+        assert_eq!((response["body_type"].as_str(), response["body"].as_str()), (Some("bytes"), Some("/wD+")));
+
+        let params = serde_json::json!({ "url": format!("{}/json", url), "max_response_bytes": 4 });
+        assert!(matches!(request(params, "").await, Err(IntegrationError::ExecutionFailed(_))));
+
+        let response = request(serde_json::json!({ "url": format!("{}/redirect", url) }), "").await.unwrap();
+        assert_eq!(response["url"], format!("{}/", url));
+        let params = serde_json::json!({ "url": format!("{}/redirect", url), "follow_redirects": false });
+        assert_eq!(request(params, "").await.unwrap()["status"], 302);
+    }
+
+    #[tokio::test]
+    async fn test_error_statuses_are_mapped() {
+        let url = upstream().await;
+        let status = |code: u16| serde_json::json!({ "url": format!("{}/status/{}", url, code) });
+        assert!(matches!(request(status(401), "").await, Err(IntegrationError::InvalidCredentials)));
+        assert!(matches!(request(status(500), "").await, Err(IntegrationError::NetworkError(m)) if m == "HTTP 500: nope"));
+
+        let mut params = status(500);
+        params["fail_on_status"] = JsonValue::Bool(false);
+        assert_eq!(request(params, "").await.unwrap()["status"], 500);
+    }
+}
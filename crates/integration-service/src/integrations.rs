@@ -1,19 +1,51 @@
 use async_trait::async_trait;
+use chrono::Utc;
+use common::error::{ErrorCode, ErrorInfo, PlatformError};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tracing::Instrument;
+
+use uuid::Uuid;
+
+use crate::catalog::{CatalogEntry, CatalogError, CatalogStore, InMemoryCatalogStore, IntegrationFilter, IntegrationSetting};
+use crate::retry::RetryPolicy;
+use crate::{DatabaseIntegration, EmailIntegration, FeedIntegration, GitHubIntegration, S3Integration, SlackIntegration};
+pub use crate::http::HttpIntegration;
 
 /// Integration registry for managing available integrations
+///
+/// The registered integrations are published to the catalog, and the
+/// organizations' enablement of them is read from it; `execute_for` refuses
+/// what an organization has disabled. Actions failing with a retryable
+/// error are tried again under the integration's retry policy, and their
+/// results report the attempts made under [`METADATA_KEY`].
 pub struct IntegrationRegistry {
     integrations: Arc<RwLock<HashMap<String, Box<dyn Integration>>>>,
+    catalog: Arc<dyn CatalogStore>,
+    /// Organization settings by organization and integration, as saved in
+    /// the catalog
+    settings: Arc<RwLock<HashMap<(Uuid, String), IntegrationSetting>>>,
+    /// Retry policy of integrations without their own
+    retry: RetryPolicy,
+    /// Retry policies by integration
+    retry_policies: HashMap<String, RetryPolicy>,
 }
 
+/// Key of the execution metadata added to action results
+pub const METADATA_KEY: &str = "_metadata";
+
 impl IntegrationRegistry {
     pub fn new() -> Self {
         let mut registry = Self {
             integrations: Arc::new(RwLock::new(HashMap::new())),
+            catalog: Arc::new(InMemoryCatalogStore::new()),
+            settings: Arc::new(RwLock::new(HashMap::new())),
+            retry: RetryPolicy::default(),
+            retry_policies: HashMap::new(),
         };
 
         // Register built-in integrations
@@ -22,14 +54,91 @@ impl IntegrationRegistry {
     }
 
     fn register_builtin_integrations(&mut self) {
-        // This would register all 50+ integrations
-        // For now, we'll register a few examples
+        let builtins: Vec<Box<dyn Integration>> = vec![
+            Box::new(HttpIntegration),
+            Box::new(SlackIntegration::new()),
+            Box::new(EmailIntegration::new()),
+            Box::new(DatabaseIntegration::new()),
+            Box::new(S3Integration::new()),
+            Box::new(GitHubIntegration::new()),
+            Box::new(FeedIntegration),
+        ];
+        let integrations = Arc::get_mut(&mut self.integrations)
+            .expect("registry is not shared while it is created")
+            .get_mut();
+        for integration in builtins {
+            integrations.insert(integration.info().name, integration);
+        }
+    }
+
+    /// Read the files of the file service, i.e. email attachments and S3
+    /// uploads, from its upload directory `root`
+    pub fn with_file_root(mut self, root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let with_files: Vec<Box<dyn Integration>> = vec![
+            Box::new(EmailIntegration::new().with_file_root(&root)),
+            Box::new(S3Integration::new().with_file_root(&root)),
+        ];
+        let integrations = Arc::get_mut(&mut self.integrations)
+            .expect("registry is not shared while it is created")
+            .get_mut();
+        for integration in with_files {
+            integrations.insert(integration.info().name, integration);
+        }
+        self
+    }
+
+    /// Keep the catalog in `store`; [`load_catalog`](Self::load_catalog)
+    /// reads the settings saved in it
+    pub fn with_catalog_store(mut self, store: Arc<dyn CatalogStore>) -> Self {
+        self.catalog = store;
+        self
+    }
+
+    /// Retry actions of integrations without their own policy with `retry`
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Retry the actions of `integration` with `retry`
+    pub fn with_integration_retry_policy(mut self, integration: &str, retry: RetryPolicy) -> Self {
+        self.retry_policies.insert(integration.to_string(), retry);
+        self
+    }
+
+    /// Retry policy of an integration's actions
+    pub fn retry_policy(&self, integration: &str) -> &RetryPolicy {
+        self.retry_policies.get(integration).unwrap_or(&self.retry)
     }
 
-    /// Register a new integration
+    /// Publish the registered integrations to the catalog and load the
+    /// organizations' settings from it; returns the number of settings
+    pub async fn load_catalog(&self) -> Result<usize, CatalogError> {
+        let entries: Vec<CatalogEntry> = {
+            let integrations = self.integrations.read().await;
+            integrations.iter().map(|(name, integration)| catalog_entry(name, integration.as_ref())).collect()
+        };
+        for entry in &entries {
+            self.catalog.save_entry(entry).await?;
+        }
+
+        let loaded = self.catalog.load_settings().await?;
+        let mut settings = self.settings.write().await;
+        settings.clear();
+        for setting in loaded {
+            settings.insert((setting.organization_id, setting.integration.clone()), setting);
+        }
+        Ok(settings.len())
+    }
+
+    /// Register a new integration and publish it to the catalog
     pub async fn register(&self, name: String, integration: Box<dyn Integration>) {
-        let mut integrations = self.integrations.write().await;
-        integrations.insert(name, integration);
+        let entry = catalog_entry(&name, integration.as_ref());
+        self.integrations.write().await.insert(name, integration);
+        if let Err(e) = self.catalog.save_entry(&entry).await {
+            tracing::warn!("Failed to publish integration {} to the catalog: {}", entry.info.name, e);
+        }
     }
 
     /// Get an integration by name
@@ -38,29 +147,203 @@ impl IntegrationRegistry {
         integrations.get(name).map(|i| i.clone_box())
     }
 
-    /// List all available integrations
-    pub async fn list(&self) -> Vec<IntegrationInfo> {
+    /// List the integrations matching `filter`, by name
+    pub async fn list(&self, filter: &IntegrationFilter) -> Vec<IntegrationInfo> {
         let integrations = self.integrations.read().await;
-        integrations
+        let settings = self.settings.read().await;
+        let mut found: Vec<IntegrationInfo> = integrations
+            .iter()
+            .filter(|(name, _)| {
+                filter.organization_id.is_none_or(|organization_id| allows(&settings, organization_id, name, None))
+            })
+            .map(|(_, i)| i.info())
+            .filter(|info| filter.matches(info))
+            .collect();
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        found
+    }
+
+    /// Every integration published to the catalog, including ones other
+    /// instances registered
+    pub async fn catalog(&self) -> Result<Vec<CatalogEntry>, CatalogError> {
+        self.catalog.load_entries().await
+    }
+
+    /// Settings of the integrations an organization changed, by integration
+    pub async fn settings_for(&self, organization_id: Uuid) -> Vec<IntegrationSetting> {
+        let settings = self.settings.read().await;
+        let mut found: Vec<IntegrationSetting> = settings
             .values()
-            .map(|i| i.info())
-            .collect()
+            .filter(|setting| setting.organization_id == organization_id)
+            .cloned()
+            .collect();
+        found.sort_by(|a, b| a.integration.cmp(&b.integration));
+        found
     }
 
-    /// Execute an integration action
+    /// Whether an organization may use the integration, and `action` of it
+    /// when given
+    pub async fn is_enabled(&self, organization_id: Uuid, integration: &str, action: Option<&str>) -> bool {
+        allows(&*self.settings.read().await, organization_id, integration, action)
+    }
+
+    /// Enable or disable an integration for an organization; its disabled
+    /// actions stay disabled
+    pub async fn set_integration_enabled(
+        &self,
+        organization_id: Uuid,
+        integration: &str,
+        enabled: bool,
+        updated_by: Uuid,
+    ) -> Result<IntegrationSetting, CatalogError> {
+        self.get(integration)
+            .await
+            .ok_or_else(|| CatalogError::UnknownIntegration(integration.to_string()))?;
+        self.update_setting(organization_id, integration, updated_by, |setting| setting.enabled = enabled)
+            .await
+    }
+
+    /// Enable or disable one action of an integration for an organization
+    pub async fn set_action_enabled(
+        &self,
+        organization_id: Uuid,
+        integration: &str,
+        action: &str,
+        enabled: bool,
+        updated_by: Uuid,
+    ) -> Result<IntegrationSetting, CatalogError> {
+        let found = self.get(integration)
+            .await
+            .ok_or_else(|| CatalogError::UnknownIntegration(integration.to_string()))?;
+        if !found.actions().iter().any(|a| a.name == action) {
+            return Err(CatalogError::UnknownAction {
+                integration: integration.to_string(),
+                action: action.to_string(),
+            });
+        }
+        self.update_setting(organization_id, integration, updated_by, |setting| {
+            if enabled {
+                setting.disabled_actions.remove(action);
+            } else {
+                setting.disabled_actions.insert(action.to_string());
+            }
+        })
+        .await
+    }
+
+    /// Change and save an organization's setting of an integration
+    async fn update_setting(
+        &self,
+        organization_id: Uuid,
+        integration: &str,
+        updated_by: Uuid,
+        change: impl FnOnce(&mut IntegrationSetting),
+    ) -> Result<IntegrationSetting, CatalogError> {
+        let mut settings = self.settings.write().await;
+        let key = (organization_id, integration.to_string());
+        let mut setting = settings
+            .get(&key)
+            .cloned()
+            .unwrap_or_else(|| IntegrationSetting::enabled(organization_id, integration, updated_by));
+        change(&mut setting);
+        setting.updated_by = updated_by;
+        setting.updated_at = Utc::now();
+        self.catalog.save_setting(&setting).await?;
+        settings.insert(key, setting.clone());
+        Ok(setting)
+    }
+
+    /// Execute an integration action, retrying under the integration's
+    /// retry policy
     pub async fn execute(
         &self,
         name: &str,
         action: &str,
         params: JsonValue,
         credentials: &str,
+    ) -> Result<JsonValue, IntegrationError> {
+        self.execute_with_policy(name, action, params, credentials, self.retry_policy(name)).await
+    }
+
+    /// Execute an integration action, retrying under `retry`
+    pub async fn execute_with_policy(
+        &self,
+        name: &str,
+        action: &str,
+        params: JsonValue,
+        credentials: &str,
+        retry: &RetryPolicy,
     ) -> Result<JsonValue, IntegrationError> {
         let integration = self
             .get(name)
             .await
             .ok_or_else(|| IntegrationError::NotFound(name.to_string()))?;
 
-        integration.execute(action, params, credentials).await
+        let span = tracing::info_span!("integration_action", integration = name, action, attempts = tracing::field::Empty);
+        let (result, attempts) = retry
+            .execute_if(IntegrationError::is_retryable, || integration.execute(action, params.clone(), credentials))
+            .instrument(span.clone())
+            .await;
+        span.record("attempts", attempts);
+        match result {
+            Ok(result) => Ok(with_metadata(result, attempts)),
+            Err(e) => {
+                if attempts > 1 {
+                    tracing::warn!("{}.{} failed after {} attempts: {}", name, action, attempts, e);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Execute an integration action for an organization, unless the
+    /// organization disabled the integration or the action
+    pub async fn execute_for(
+        &self,
+        organization_id: Uuid,
+        name: &str,
+        action: &str,
+        params: JsonValue,
+        credentials: &str,
+    ) -> Result<JsonValue, IntegrationError> {
+        if !self.is_enabled(organization_id, name, Some(action)).await {
+            return Err(IntegrationError::Disabled(format!("{}.{}", name, action)));
+        }
+        self.execute(name, action, params, credentials).await
+    }
+}
+
+/// Integrations and actions without a setting are enabled
+fn allows(
+    settings: &HashMap<(Uuid, String), IntegrationSetting>,
+    organization_id: Uuid,
+    integration: &str,
+    action: Option<&str>,
+) -> bool {
+    settings
+        .get(&(organization_id, integration.to_string()))
+        .is_none_or(|setting| setting.allows(action))
+}
+
+/// The result with its execution metadata; results that are not objects
+/// are moved under "result"
+fn with_metadata(result: JsonValue, attempts: u32) -> JsonValue {
+    let metadata = serde_json::json!({ "attempts": attempts });
+    let mut fields = match result {
+        JsonValue::Object(fields) => fields,
+        result => serde_json::Map::from_iter([("result".to_string(), result)]),
+    };
+    fields.insert(METADATA_KEY.to_string(), metadata);
+    JsonValue::Object(fields)
+}
+
+fn catalog_entry(name: &str, integration: &dyn Integration) -> CatalogEntry {
+    let mut info = integration.info();
+    info.name = name.to_string();
+    CatalogEntry {
+        info,
+        actions: integration.actions().into_iter().map(|action| action.name).collect(),
+        updated_at: Utc::now(),
     }
 }
 
@@ -102,9 +385,12 @@ pub struct IntegrationInfo {
     pub category: IntegrationCategory,
     pub auth_type: AuthType,
     pub icon_url: Option<String>,
+    /// Keywords the catalog can be searched by
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum IntegrationCategory {
     SocialMedia,
     Email,
@@ -118,7 +404,7 @@ pub enum IntegrationCategory {
     Other,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum AuthType {
     ApiKey,
     OAuth2,
@@ -174,129 +460,185 @@ pub enum IntegrationError {
 
     #[error("Network error: {0}")]
     NetworkError(String),
-}
 
-// Example integration: HTTP Request
-#[derive(Clone)]
-pub struct HttpIntegration;
+    #[error("Disabled for the organization: {0}")]
+    Disabled(String),
+}
 
-#[async_trait]
-impl Integration for HttpIntegration {
-    fn info(&self) -> IntegrationInfo {
-        IntegrationInfo {
-            name: "http".to_string(),
-            display_name: "HTTP Request".to_string(),
-            description: "Make HTTP requests to any endpoint".to_string(),
-            category: IntegrationCategory::Http,
-            auth_type: AuthType::None,
-            icon_url: None,
-        }
+impl IntegrationError {
+    /// Whether trying the action again may succeed; integrations report
+    /// network failures, timeouts, rate limiting and server errors as
+    /// `NetworkError`, and everything retrying cannot fix as other variants
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, IntegrationError::NetworkError(_))
     }
+}
 
-    async fn execute(
-        &self,
-        action: &str,
-        params: JsonValue,
-        _credentials: &str,
-    ) -> Result<JsonValue, IntegrationError> {
-        match action {
-            "request" => {
-                let url = params["url"]
-                    .as_str()
-                    .ok_or_else(|| IntegrationError::InvalidParameters("url required".to_string()))?;
-
-                let method = params["method"].as_str().unwrap_or("GET");
-
-                let client = reqwest::Client::new();
-                let response = match method {
-                    "GET" => client.get(url).send().await,
-                    "POST" => client.post(url).json(&params["body"]).send().await,
-                    _ => {
-                        return Err(IntegrationError::InvalidParameters(
-                            "Invalid method".to_string(),
-                        ))
-                    }
-                }
-                .map_err(|e| IntegrationError::NetworkError(e.to_string()))?;
-
-                let status = response.status().as_u16();
-                let body = response
-                    .json::<JsonValue>()
-                    .await
-                    .unwrap_or(serde_json::json!({}));
-
-                Ok(serde_json::json!({
-                    "status": status,
-                    "body": body
-                }))
-            }
-            _ => Err(IntegrationError::ActionNotFound(action.to_string())),
+impl ErrorInfo for IntegrationError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            IntegrationError::NotFound(_) | IntegrationError::ActionNotFound(_) => ErrorCode::NotFound,
+            IntegrationError::InvalidCredentials => ErrorCode::Unauthenticated,
+            IntegrationError::InvalidParameters(_) => ErrorCode::InvalidInput,
+            IntegrationError::ExecutionFailed(_) => ErrorCode::UpstreamFailed,
+            IntegrationError::NetworkError(_) => ErrorCode::Unavailable,
+            IntegrationError::Disabled(_) => ErrorCode::PermissionDenied,
         }
     }
+}
 
-    async fn validate_credentials(&self, _credentials: &str) -> Result<bool, IntegrationError> {
-        Ok(true) // HTTP doesn't require credentials
-    }
-
-    fn actions(&self) -> Vec<ActionDefinition> {
-        vec![ActionDefinition {
-            name: "request".to_string(),
-            display_name: "HTTP Request".to_string(),
-            description: "Make an HTTP request".to_string(),
-            parameters: vec![
-                ParameterDefinition {
-                    name: "url".to_string(),
-                    display_name: "URL".to_string(),
-                    description: "The URL to request".to_string(),
-                    param_type: ParameterType::String,
-                    required: true,
-                    default_value: None,
-                },
-                ParameterDefinition {
-                    name: "method".to_string(),
-                    display_name: "Method".to_string(),
-                    description: "HTTP method (GET, POST, etc.)".to_string(),
-                    param_type: ParameterType::String,
-                    required: false,
-                    default_value: Some(serde_json::json!("GET")),
-                },
-            ],
-            returns: Some("Response object with status and body".to_string()),
-        }]
-    }
-
-    fn clone_box(&self) -> Box<dyn Integration> {
-        Box::new(self.clone())
+impl From<IntegrationError> for PlatformError {
+    fn from(error: IntegrationError) -> Self {
+        PlatformError::service(error)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::catalog::InMemoryCatalogStore;
 
     #[tokio::test]
     async fn test_registry() {
         let registry = IntegrationRegistry::new();
+        assert!(registry.get("http").await.is_some());
+        assert!(registry.get("github").await.is_some());
+
         registry
-            .register("http".to_string(), Box::new(HttpIntegration))
+            .register("webhook".to_string(), Box::new(HttpIntegration))
             .await;
+        let list = registry.list(&IntegrationFilter::default()).await;
+        assert_eq!(list.len(), 8);
+        assert!(list.windows(2).all(|pair| pair[0].name <= pair[1].name));
+        assert!(registry.catalog().await.unwrap().iter().any(|entry| entry.info.name == "webhook"));
+    }
 
-        let integration = registry.get("http").await;
-        assert!(integration.is_some());
+    #[tokio::test]
+    async fn test_list_by_category_and_tag() {
+        let registry = IntegrationRegistry::new();
+        let by_category = IntegrationFilter { category: Some(IntegrationCategory::Storage), ..Default::default() };
+        let names: Vec<String> = registry.list(&by_category).await.into_iter().map(|i| i.name).collect();
+        assert_eq!(names, ["s3"]);
 
-        let list = registry.list().await;
-        assert_eq!(list.len(), 1);
+        let by_tag = IntegrationFilter { tag: Some("Notifications".to_string()), ..Default::default() };
+        let names: Vec<String> = registry.list(&by_tag).await.into_iter().map(|i| i.name).collect();
+        assert_eq!(names, ["email", "slack"]);
     }
 
     #[tokio::test]
-    async fn test_http_integration() {
-        let integration = HttpIntegration;
-        let info = integration.info();
-        assert_eq!(info.name, "http");
-
-        let actions = integration.actions();
-        assert_eq!(actions.len(), 1);
-        assert_eq!(actions[0].name, "request");
+    async fn test_organization_enablement_is_persisted() {
+        let store = Arc::new(InMemoryCatalogStore::new());
+        let registry = IntegrationRegistry::new().with_catalog_store(store.clone());
+        registry.load_catalog().await.unwrap();
+        let (org, other_org, admin) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        registry.set_integration_enabled(org, "slack", false, admin).await.unwrap();
+        registry.set_action_enabled(org, "github", "create_issue", false, admin).await.unwrap();
+        assert!(matches!(
+            registry.set_action_enabled(org, "github", "delete_repo", false, admin).await,
+            Err(CatalogError::UnknownAction { .. })
+        ));
+        assert!(matches!(
+            registry.set_integration_enabled(org, "fax", false, admin).await,
+            Err(CatalogError::UnknownIntegration(_))
+        ));
+
+        let filter = IntegrationFilter { organization_id: Some(org), ..Default::default() };
+        assert!(registry.list(&filter).await.iter().all(|i| i.name != "slack"));
+        assert!(registry.is_enabled(org, "github", Some("comment_on_issue")).await);
+        assert!(!registry.is_enabled(org, "github", Some("create_issue")).await);
+        assert!(registry.is_enabled(other_org, "slack", None).await);
+        let refused = registry.execute_for(org, "github", "create_issue", serde_json::json!({}), "token").await;
+        assert!(matches!(refused, Err(IntegrationError::Disabled(_))));
+
+        // Another instance on the same store reads the settings
+        let restarted = IntegrationRegistry::new().with_catalog_store(store);
+        assert_eq!(restarted.load_catalog().await.unwrap(), 2);
+        assert!(!restarted.is_enabled(org, "slack", None).await);
+        assert_eq!(restarted.settings_for(org).await.len(), 2);
+
+        registry.set_integration_enabled(org, "slack", true, admin).await.unwrap();
+        assert!(registry.is_enabled(org, "slack", Some("send_message")).await);
     }
-}
 
+    /// Fails its first `failures` calls with the given error
+    #[derive(Clone)]
+    struct Flaky {
+        calls: Arc<std::sync::atomic::AtomicU32>,
+        failures: u32,
+        error: fn() -> IntegrationError,
+    }
+
+    #[async_trait]
+    impl Integration for Flaky {
+        fn info(&self) -> IntegrationInfo {
+            IntegrationInfo {
+                name: "flaky".to_string(),
+                display_name: "Flaky".to_string(),
+                description: "Fails at first".to_string(),
+                category: IntegrationCategory::Other,
+                auth_type: AuthType::None,
+                icon_url: None,
+                tags: vec![],
+            }
+        }
+
+        async fn execute(&self, _action: &str, _params: JsonValue, _credentials: &str) -> Result<JsonValue, IntegrationError> {
+            let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            match call < self.failures {
+                true => Err((self.error)()),
+                false => Ok(serde_json::json!(["done"])),
+            }
+        }
+
+        async fn validate_credentials(&self, _credentials: &str) -> Result<bool, IntegrationError> {
+            Ok(true)
+        }
+
+        fn actions(&self) -> Vec<ActionDefinition> {
+            vec![]
+        }
+
+        fn clone_box(&self) -> Box<dyn Integration> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retryable_errors_are_retried() {
+        let quick = RetryPolicy::new(3, std::time::Duration::from_millis(1), std::time::Duration::from_millis(5), 2.0);
+        let registry = IntegrationRegistry::new()
+            .with_retry_policy(quick.clone())
+            .with_integration_retry_policy("once", RetryPolicy { max_retries: 0, ..quick.clone() });
+        let flaky = |failures, error: fn() -> IntegrationError| {
+            let calls = Arc::new(std::sync::atomic::AtomicU32::new(0));
+            (Box::new(Flaky { calls: calls.clone(), failures, error }), calls)
+        };
+        let calls = |calls: &Arc<std::sync::atomic::AtomicU32>| calls.load(std::sync::atomic::Ordering::SeqCst);
+
+        let (integration, network) = flaky(2, || IntegrationError::NetworkError("HTTP 503".to_string()));
+        registry.register("network".to_string(), integration).await;
+        let result = registry.execute("network", "run", JsonValue::Null, "").await.unwrap();
+        assert_eq!(result, serde_json::json!({ "result": ["done"], "_metadata": { "attempts": 3 } }));
+        assert_eq!(calls(&network), 3);
+
+        let (integration, terminal) = flaky(1, || IntegrationError::InvalidParameters("missing url".to_string()));
+        registry.register("terminal".to_string(), integration).await;
+        let error = registry.execute("terminal", "run", JsonValue::Null, "").await.unwrap_err();
+        assert!(!error.is_retryable());
+        assert_eq!(calls(&terminal), 1);
+
+        // Per integration and per call policies
+        let (integration, once) = flaky(1, || IntegrationError::NetworkError("reset".to_string()));
+        registry.register("once".to_string(), integration).await;
+        assert!(registry.execute("once", "run", JsonValue::Null, "").await.is_err());
+        let result = registry.execute_with_policy("once", "run", JsonValue::Null, "", &quick).await.unwrap();
+        assert_eq!(result[METADATA_KEY]["attempts"], 1);
+        assert_eq!(calls(&once), 2);
+
+        let (integration, exhausted) = flaky(10, || IntegrationError::NetworkError("timeout".to_string()));
+        registry.register("exhausted".to_string(), integration).await;
+        assert!(registry.execute("exhausted", "run", JsonValue::Null, "").await.is_err());
+        assert_eq!(calls(&exhausted), 4);
+    }
+}
@@ -1,9 +1,36 @@
+pub mod catalog;
 pub mod credentials;
+pub mod database;
+pub mod email;
+pub mod feed;
+pub mod github;
+pub mod http;
 pub mod integrations;
+pub mod migration;
+pub mod notify;
 pub mod oauth;
 pub mod retry;
+pub mod s3;
+pub mod sharing;
+pub mod slack;
 
-pub use credentials::CredentialManager;
+pub use catalog::{CatalogEntry, CatalogStore, InMemoryCatalogStore, IntegrationFilter, IntegrationSetting};
+pub use credentials::{
+    CredentialAccess, CredentialAuditor, CredentialManager, CredentialStorage, CredentialStore, CredentialUse,
+    InMemoryCredentialStorage, LocalMasterKey, MasterKey, StoredCredential,
+};
+pub use database::{DatabaseIntegration, DatabaseQueryParams};
+pub use email::{EmailIntegration, SmtpCredentials};
+pub use feed::{FeedIntegration, FeedParser, FeedTrigger};
+pub use github::GitHubIntegration;
+pub use http::{HttpCredentials, HttpIntegration, HttpRequestParams};
 pub use integrations::IntegrationRegistry;
-pub use oauth::OAuth2Handler;
+pub use migration::{CredentialMigration, CredentialMigrator, MigrationReport};
+pub use notify::{ChannelNotifier, Mailer, Notification, NotificationChannel, Notifier};
+pub use oauth::{
+    InMemoryOAuth2StateStore, OAuth2Handler, OAuth2State, OAuth2StateStore, OAuth2Token, OAuth2TokenRefresher, PkceChallenge,
+};
 pub use retry::RetryPolicy;
+pub use s3::{S3Credentials, S3Integration};
+pub use slack::SlackIntegration;
+pub use sharing::{WorkflowBundle, PublicBundle, EncryptedBundle, CredentialPlaceholder};
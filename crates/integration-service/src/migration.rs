@@ -0,0 +1,379 @@
+use chrono::{DateTime, Utc};
+use common::types::Workflow;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::credentials::{CredentialManager, CredentialStatus, CredentialStore, StoredCredential};
+use crate::integrations::AuthType;
+use crate::sharing::CREDENTIAL_REF_KEY;
+
+/// Result of upgrading credential data by one schema version
+#[derive(Debug, Clone)]
+pub enum MigrationStep {
+    Upgraded(JsonValue),
+    /// The data cannot be converted, e.g. an API key cannot become an OAuth
+    /// token; the owner has to reconnect
+    NeedsAction(String),
+}
+
+/// Upgrades the credentials of one integration from one schema version to the next
+pub trait CredentialMigration: Send + Sync {
+    fn integration(&self) -> &str;
+
+    /// Schema version this migration reads; it produces `source_version() + 1`
+    fn source_version(&self) -> u32;
+
+    /// Auth type of credentials after the migration
+    fn auth_type(&self) -> AuthType;
+
+    fn migrate(&self, data: JsonValue) -> MigrationStep;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum MigrationOutcome {
+    Upgraded { from_version: u32, to_version: u32 },
+    Flagged { version: u32, reason: String },
+}
+
+/// A workflow referencing a migrated credential
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AffectedWorkflow {
+    pub workflow_id: Uuid,
+    pub name: String,
+    pub node_ids: Vec<Uuid>,
+}
+
+/// Message to a credential owner about a migration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialNotice {
+    pub id: Uuid,
+    pub owner: Uuid,
+    pub credential_id: Uuid,
+    pub credential_name: String,
+    pub integration: String,
+    #[serde(flatten)]
+    pub outcome: MigrationOutcome,
+    pub workflows: Vec<AffectedWorkflow>,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MigrationReport {
+    /// Credentials already at their integration's current version
+    pub up_to_date: usize,
+    pub upgraded: Vec<Uuid>,
+    pub flagged: Vec<Uuid>,
+    pub notices: Vec<CredentialNotice>,
+}
+
+/// Registered credential migrations, applied in version order to stored credentials
+pub struct CredentialMigrator {
+    migrations: HashMap<(String, u32), Arc<dyn CredentialMigration>>,
+    notices: Arc<RwLock<Vec<CredentialNotice>>>,
+}
+
+impl CredentialMigrator {
+    pub fn new() -> Self {
+        Self {
+            migrations: HashMap::new(),
+            notices: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    pub fn with_migration(mut self, migration: Arc<dyn CredentialMigration>) -> Self {
+        let key = (migration.integration().to_string(), migration.source_version());
+        self.migrations.insert(key, migration);
+        self
+    }
+
+    /// Latest credential schema version of an integration; 1 when it has no migrations
+    pub fn current_version(&self, integration: &str) -> u32 {
+        self.migrations.keys()
+            .filter(|(name, _)| name == integration)
+            .map(|(_, from)| from + 1)
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Upgrade every stored credential of `integration` to its current schema.
+    ///
+    /// Credentials that cannot be upgraded keep the last version they reached
+    /// and are flagged. Owners of upgraded and flagged credentials are notified
+    /// with the workflows referencing the credential.
+    pub async fn migrate_integration(
+        &self,
+        integration: &str,
+        store: &CredentialStore,
+        manager: &CredentialManager,
+        workflows: &[Workflow],
+    ) -> MigrationReport {
+        let current = self.current_version(integration);
+        let mut report = MigrationReport::default();
+
+        for mut credential in store.list_for_integration(integration).await {
+            if credential.schema_version >= current {
+                report.up_to_date += 1;
+                continue;
+            }
+            let from_version = credential.schema_version;
+            let outcome = self.upgrade(&mut credential, current, manager);
+            if let MigrationOutcome::Flagged { reason, .. } = &outcome {
+                credential.status = CredentialStatus::NeedsAttention { reason: reason.clone() };
+            }
+            credential.updated_at = Utc::now();
+            // A credential that could not be saved is migrated on the next run
+            if let Err(e) = store.save(credential.clone()).await {
+                tracing::error!("Failed to save migrated credential {}: {}", credential.id, e);
+                continue;
+            }
+            match &outcome {
+                MigrationOutcome::Upgraded { .. } => report.upgraded.push(credential.id),
+                MigrationOutcome::Flagged { .. } => report.flagged.push(credential.id),
+            }
+
+            tracing::info!(
+                "Credential {} of {} migrated from schema version {}: {:?}",
+                credential.id, integration, from_version, outcome
+            );
+            report.notices.push(notice(&credential, outcome, workflows));
+        }
+
+        self.notices.write().await.extend(report.notices.iter().cloned());
+        report
+    }
+
+    /// Apply migrations one version at a time, writing the reached version back
+    fn upgrade(&self, credential: &mut StoredCredential, target: u32, manager: &CredentialManager) -> MigrationOutcome {
+        let from_version = credential.schema_version;
+        let flagged = |version: u32, reason: String| MigrationOutcome::Flagged { version, reason };
+
+        let mut data: JsonValue = match manager.decrypt(&credential.data) {
+            Ok(plaintext) => match serde_json::from_str(&plaintext) {
+                Ok(data) => data,
+                Err(e) => return flagged(from_version, format!("credential data is not JSON: {}", e)),
+            },
+            Err(e) => return flagged(from_version, e.to_string()),
+        };
+
+        let mut outcome = None;
+        while credential.schema_version < target {
+            let version = credential.schema_version;
+            let Some(migration) = self.migrations.get(&(credential.integration.clone(), version)) else {
+                outcome = Some(flagged(version, format!("no migration from schema version {}", version)));
+                break;
+            };
+            match migration.migrate(data.clone()) {
+                MigrationStep::Upgraded(upgraded) => {
+                    data = upgraded;
+                    credential.schema_version = version + 1;
+                    credential.auth_type = migration.auth_type();
+                }
+                MigrationStep::NeedsAction(reason) => {
+                    outcome = Some(flagged(version, reason));
+                    break;
+                }
+            }
+        }
+
+        if credential.schema_version != from_version {
+            match manager.encrypt(&data.to_string()) {
+                Ok(encrypted) => credential.data = encrypted,
+                Err(e) => {
+                    credential.schema_version = from_version;
+                    return flagged(from_version, e.to_string());
+                }
+            }
+        }
+        outcome.unwrap_or(MigrationOutcome::Upgraded { from_version, to_version: credential.schema_version })
+    }
+
+    /// Migration notices sent to a user, oldest first
+    pub async fn notices_for(&self, owner: Uuid) -> Vec<CredentialNotice> {
+        let notices = self.notices.read().await;
+        notices.iter().filter(|n| n.owner == owner).cloned().collect()
+    }
+}
+
+impl Default for CredentialMigrator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn notice(credential: &StoredCredential, outcome: MigrationOutcome, workflows: &[Workflow]) -> CredentialNotice {
+    let affected = affected_workflows(credential.id, workflows);
+    let message = match &outcome {
+        MigrationOutcome::Upgraded { to_version, .. } => format!(
+            "Credential \"{}\" was upgraded to the {} schema version {}",
+            credential.name, credential.integration, to_version
+        ),
+        MigrationOutcome::Flagged { reason, .. } => format!(
+            "Credential \"{}\" must be reconnected ({}); {} workflow(s) will fail until then",
+            credential.name, reason, affected.len()
+        ),
+    };
+    CredentialNotice {
+        id: Uuid::new_v4(),
+        owner: credential.owner,
+        credential_id: credential.id,
+        credential_name: credential.name.clone(),
+        integration: credential.integration.clone(),
+        outcome,
+        workflows: affected,
+        message,
+        created_at: Utc::now(),
+    }
+}
+
+/// Workflows whose node parameters hold a `$credential` reference to the credential
+pub fn affected_workflows(credential_id: Uuid, workflows: &[Workflow]) -> Vec<AffectedWorkflow> {
+    let id = credential_id.to_string();
+    workflows.iter()
+        .filter_map(|workflow| {
+            let node_ids: Vec<Uuid> = workflow.nodes.iter()
+                .filter(|node| node.config.parameters.values().any(|value| references(value, &id)))
+                .map(|node| node.id)
+                .collect();
+            (!node_ids.is_empty()).then(|| AffectedWorkflow {
+                workflow_id: workflow.id,
+                name: workflow.name.clone(),
+                node_ids,
+            })
+        })
+        .collect()
+}
+
+fn references(value: &JsonValue, credential_id: &str) -> bool {
+    match value {
+        JsonValue::Object(map) => {
+            map.get(CREDENTIAL_REF_KEY).and_then(|v| v.as_str()) == Some(credential_id)
+                || map.values().any(|v| references(v, credential_id))
+        }
+        JsonValue::Array(items) => items.iter().any(|v| references(v, credential_id)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::types::{ActionType, Node, NodeConfig, NodeType, Position, WORKFLOW_SCHEMA_VERSION};
+
+    /// v1 stored `{"key": ..}`, v2 renamed it to `api_key`
+    struct RenameKey;
+
+    impl CredentialMigration for RenameKey {
+        fn integration(&self) -> &str { "crm" }
+        fn source_version(&self) -> u32 { 1 }
+        fn auth_type(&self) -> AuthType { AuthType::ApiKey }
+        fn migrate(&self, data: JsonValue) -> MigrationStep {
+            MigrationStep::Upgraded(serde_json::json!({ "api_key": data["key"] }))
+        }
+    }
+
+    /// v3 switched to OAuth; API keys cannot be converted
+    struct ToOAuth;
+
+    impl CredentialMigration for ToOAuth {
+        fn integration(&self) -> &str { "crm" }
+        fn source_version(&self) -> u32 { 2 }
+        fn auth_type(&self) -> AuthType { AuthType::OAuth2 }
+        fn migrate(&self, data: JsonValue) -> MigrationStep {
+            match data.get("refresh_token") {
+                Some(_) => MigrationStep::Upgraded(data),
+                None => MigrationStep::NeedsAction("the integration now requires OAuth".to_string()),
+            }
+        }
+    }
+
+    fn credential(manager: &CredentialManager, version: u32, data: JsonValue) -> StoredCredential {
+        StoredCredential {
+            id: Uuid::new_v4(),
+            owner: Uuid::new_v4(),
+            name: "CRM".to_string(),
+            integration: "crm".to_string(),
+            auth_type: AuthType::ApiKey,
+            schema_version: version,
+            data: manager.encrypt(&data.to_string()).unwrap(),
+            status: CredentialStatus::Active,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            rotated_at: None,
+            expires_at: None,
+            token_expires_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_migrate_upgrades_and_flags_with_affected_workflows() {
+        let manager = CredentialManager::new(&[7u8; 32]);
+        let store = CredentialStore::new();
+        let migrator = CredentialMigrator::new()
+            .with_migration(Arc::new(RenameKey))
+            .with_migration(Arc::new(ToOAuth));
+        assert_eq!(migrator.current_version("crm"), 3);
+
+        let api_key = credential(&manager, 1, serde_json::json!({ "key": "k-1" }));
+        let oauth = credential(&manager, 2, serde_json::json!({ "refresh_token": "r-1" }));
+        let current = credential(&manager, 3, serde_json::json!({ "refresh_token": "r-2" }));
+        for c in [&api_key, &oauth, &current] {
+            store.save(c.clone()).await.unwrap();
+        }
+
+        let node = Node {
+            id: Uuid::new_v4(),
+            node_type: NodeType::Action { action_type: ActionType::Integration },
+            config: NodeConfig {
+                parameters: HashMap::from([(
+                    "auth".to_string(),
+                    serde_json::json!({ "token": { CREDENTIAL_REF_KEY: api_key.id.to_string() } }),
+                )]),
+                ..Default::default()
+            },
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![],
+        };
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Sync leads".to_string(),
+            description: None,
+            nodes: vec![node.clone()],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        };
+
+        let report = migrator.migrate_integration("crm", &store, &manager, std::slice::from_ref(&workflow)).await;
+        assert_eq!(report.up_to_date, 1);
+        assert_eq!(report.upgraded, vec![oauth.id]);
+        assert_eq!(report.flagged, vec![api_key.id]);
+
+        // The rename still applied before the OAuth step flagged the credential
+        let flagged = store.get(api_key.id).await.unwrap();
+        assert_eq!(flagged.schema_version, 2);
+        assert!(matches!(flagged.status, CredentialStatus::NeedsAttention { .. }));
+        let data: JsonValue = serde_json::from_str(&manager.decrypt(&flagged.data).unwrap()).unwrap();
+        assert_eq!(data["api_key"], "k-1");
+        assert_eq!(store.get(oauth.id).await.unwrap().auth_type, AuthType::OAuth2);
+
+        let notices = migrator.notices_for(api_key.owner).await;
+        assert_eq!(notices.len(), 1);
+        assert_eq!(notices[0].outcome, MigrationOutcome::Flagged { version: 2, reason: "the integration now requires OAuth".to_string() });
+        assert_eq!(notices[0].workflows, vec![AffectedWorkflow {
+            workflow_id: workflow.id,
+            name: "Sync leads".to_string(),
+            node_ids: vec![node.id],
+        }]);
+        assert!(migrator.notices_for(oauth.owner).await[0].workflows.is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::integrations::IntegrationError;
+
+/// Timeout of a Slack webhook call
+const SLACK_TIMEOUT_SECS: u64 = 10;
+
+/// Where a notification is delivered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotificationChannel {
+    Email { to: String },
+    /// A Slack incoming webhook
+    Slack { webhook_url: String },
+}
+
+impl NotificationChannel {
+    /// Check the address or webhook URL
+    pub fn validate(&self) -> Result<(), IntegrationError> {
+        match self {
+            NotificationChannel::Email { to } if !to.contains('@') || to.trim() != to => {
+                Err(IntegrationError::InvalidParameters(format!("invalid email address {:?}", to)))
+            }
+            NotificationChannel::Slack { webhook_url } if !webhook_url.starts_with("https://") => {
+                Err(IntegrationError::InvalidParameters("Slack webhook URL must use https".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A message for people, sent as an email or a Slack message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub subject: String,
+    pub body: String,
+}
+
+/// Sends email, implemented by the deployment's mail provider
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), String>;
+}
+
+/// Delivers notifications to their channels
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, channel: &NotificationChannel, notification: &Notification) -> Result<(), IntegrationError>;
+}
+
+/// Posts Slack notifications to their webhooks and sends email through a
+/// [`Mailer`]; email fails without one
+#[derive(Default)]
+pub struct ChannelNotifier {
+    client: reqwest::Client,
+    mailer: Option<Arc<dyn Mailer>>,
+}
+
+impl ChannelNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_mailer(mut self, mailer: Arc<dyn Mailer>) -> Self {
+        self.mailer = Some(mailer);
+        self
+    }
+}
+
+#[async_trait]
+impl Notifier for ChannelNotifier {
+    async fn notify(&self, channel: &NotificationChannel, notification: &Notification) -> Result<(), IntegrationError> {
+        match channel {
+            NotificationChannel::Email { to } => {
+                let mailer = self
+                    .mailer
+                    .as_ref()
+                    .ok_or_else(|| IntegrationError::ExecutionFailed("email delivery is not configured".to_string()))?;
+                mailer
+                    .send(to, &notification.subject, &notification.body)
+                    .await
+                    .map_err(IntegrationError::ExecutionFailed)
+            }
+            NotificationChannel::Slack { webhook_url } => {
+                let response = self
+                    .client
+                    .post(webhook_url)
+                    .timeout(Duration::from_secs(SLACK_TIMEOUT_SECS))
+                    .json(&serde_json::json!({
+                        "text": format!("*{}*\n{}", notification.subject, notification.body),
+                    }))
+                    .send()
+                    .await
+                    .map_err(|e| IntegrationError::NetworkError(e.to_string()))?;
+                if !response.status().is_success() {
+                    return Err(IntegrationError::ExecutionFailed(format!(
+                        "Slack webhook returned {}",
+                        response.status().as_u16()
+                    )));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingMailer(Mutex<Vec<(String, String)>>);
+
+    #[async_trait]
+    impl Mailer for RecordingMailer {
+        async fn send(&self, to: &str, subject: &str, _body: &str) -> Result<(), String> {
+            self.0.lock().unwrap().push((to.to_string(), subject.to_string()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_channel_validation() {
+        assert!(NotificationChannel::Email { to: "sec@example.com".to_string() }.validate().is_ok());
+        assert!(NotificationChannel::Email { to: "nobody".to_string() }.validate().is_err());
+        assert!(NotificationChannel::Slack { webhook_url: "http://hooks.example.com/x".to_string() }
+            .validate()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_email_goes_through_the_mailer() {
+        let notification = Notification { subject: "Alert".to_string(), body: "Details".to_string() };
+        let channel = NotificationChannel::Email { to: "sec@example.com".to_string() };
+        assert!(ChannelNotifier::new().notify(&channel, &notification).await.is_err());
+
+        let mailer = Arc::new(RecordingMailer::default());
+        let notifier = ChannelNotifier::new().with_mailer(mailer.clone());
+        notifier.notify(&channel, &notification).await.unwrap();
+        assert_eq!(*mailer.0.lock().unwrap(), vec![("sec@example.com".to_string(), "Alert".to_string())]);
+    }
+}
@@ -1,14 +1,38 @@
+//! OAuth2 authorization code flow with PKCE, and refresh of saved tokens
+//!
+//! [`OAuth2Handler::start_authorization`] saves a single-use state holding
+//! the PKCE code verifier and returns the provider's authorization URL; the
+//! provider's redirect is completed with
+//! [`OAuth2Handler::complete_authorization`]. Tokens saved as credentials
+//! are refreshed ahead of their expiry by [`OAuth2TokenRefresher`].
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use chrono::{DateTime, Duration, Utc};
+use common::error::{ErrorCode, ErrorInfo};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
+use crate::credentials::{
+    CredentialError, CredentialManager, CredentialStatus, CredentialStore, CredentialUse, StoredCredential,
+};
+
+/// How long a started authorization waits for the provider's redirect
+const DEFAULT_STATE_TTL_MINUTES: i64 = 10;
+
 /// OAuth2 handler for managing OAuth2 flows
 pub struct OAuth2Handler {
-    configs: Arc<RwLock<HashMap<Uuid, OAuth2Config>>>,
-    tokens: Arc<RwLock<HashMap<Uuid, OAuth2Token>>>,
+    /// Configurations by integration name
+    configs: Arc<RwLock<HashMap<String, OAuth2Config>>>,
+    tokens: Arc<RwLock<HashMap<String, OAuth2Token>>>,
+    states: Arc<dyn OAuth2StateStore>,
+    state_ttl: Duration,
     client: reqwest::Client,
 }
 
@@ -26,53 +50,201 @@ pub struct OAuth2Config {
 pub struct OAuth2Token {
     pub access_token: String,
     pub refresh_token: Option<String>,
+    /// `DateTime::<Utc>::MAX_UTC` for tokens that do not expire
     pub expires_at: DateTime<Utc>,
     pub token_type: String,
 }
 
+impl OAuth2Token {
+    /// When the access token stops working; `None` for tokens that do not
+    /// expire
+    pub fn expiry(&self) -> Option<DateTime<Utc>> {
+        (self.expires_at != DateTime::<Utc>::MAX_UTC).then_some(self.expires_at)
+    }
+}
+
+/// PKCE code verifier and its S256 code challenge (RFC 7636)
+#[derive(Debug, Clone)]
+pub struct PkceChallenge {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl PkceChallenge {
+    /// A random verifier of 43 URL-safe characters
+    pub fn new() -> Self {
+        Self::from_verifier(random_token())
+    }
+
+    pub fn from_verifier(verifier: String) -> Self {
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        Self { verifier, challenge }
+    }
+}
+
+impl Default for PkceChallenge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A started authorization, waiting for the provider's redirect
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuth2State {
+    /// Sent as the `state` parameter and returned by the provider
+    pub state: String,
+    pub integration: String,
+    /// User the token is saved for
+    pub owner: Uuid,
+    /// Name of the credential to create
+    pub credential_name: String,
+    /// Credential to replace the token of, when reconnecting
+    pub credential_id: Option<Uuid>,
+    pub code_verifier: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Durable storage of started authorizations
+#[async_trait]
+pub trait OAuth2StateStore: Send + Sync {
+    async fn save(&self, state: &OAuth2State) -> Result<(), OAuth2Error>;
+    /// Remove and return a state; states are used once
+    async fn take(&self, state: &str) -> Result<Option<OAuth2State>, OAuth2Error>;
+    /// Delete the states expired at `now`; returns how many
+    async fn purge_expired(&self, now: DateTime<Utc>) -> Result<usize, OAuth2Error>;
+}
+
+/// In-memory state store (for development, replace with database in production)
+#[derive(Default)]
+pub struct InMemoryOAuth2StateStore {
+    states: RwLock<HashMap<String, OAuth2State>>,
+}
+
+impl InMemoryOAuth2StateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl OAuth2StateStore for InMemoryOAuth2StateStore {
+    async fn save(&self, state: &OAuth2State) -> Result<(), OAuth2Error> {
+        self.states.write().await.insert(state.state.clone(), state.clone());
+        Ok(())
+    }
+
+    async fn take(&self, state: &str) -> Result<Option<OAuth2State>, OAuth2Error> {
+        Ok(self.states.write().await.remove(state))
+    }
+
+    async fn purge_expired(&self, now: DateTime<Utc>) -> Result<usize, OAuth2Error> {
+        let mut states = self.states.write().await;
+        let before = states.len();
+        states.retain(|_, state| state.expires_at > now);
+        Ok(before - states.len())
+    }
+}
+
 impl OAuth2Handler {
     pub fn new() -> Self {
         Self {
             configs: Arc::new(RwLock::new(HashMap::new())),
             tokens: Arc::new(RwLock::new(HashMap::new())),
+            states: Arc::new(InMemoryOAuth2StateStore::new()),
+            state_ttl: Duration::minutes(DEFAULT_STATE_TTL_MINUTES),
             client: reqwest::Client::new(),
         }
     }
 
-    /// Register an OAuth2 configuration
-    pub async fn register_config(&self, integration_id: Uuid, config: OAuth2Config) {
+    /// Keep started authorizations in `states`, e.g. to complete them on
+    /// another gateway replica
+    pub fn with_state_store(mut self, states: Arc<dyn OAuth2StateStore>) -> Self {
+        self.states = states;
+        self
+    }
+
+    /// How long started authorizations may be completed
+    pub fn with_state_ttl(mut self, ttl: Duration) -> Self {
+        self.state_ttl = ttl;
+        self
+    }
+
+    /// Register the OAuth2 configuration of an integration
+    pub async fn register_config(&self, integration: &str, config: OAuth2Config) {
         let mut configs = self.configs.write().await;
-        configs.insert(integration_id, config);
+        configs.insert(integration.to_string(), config);
     }
 
     /// Generate authorization URL
-    pub async fn get_auth_url(&self, integration_id: Uuid, state: &str) -> Option<String> {
+    pub async fn get_auth_url(&self, integration: &str, state: &str) -> Option<String> {
         let configs = self.configs.read().await;
-        let config = configs.get(&integration_id)?;
+        let config = configs.get(integration)?;
+        Some(authorization_url(config, state, None))
+    }
+
+    /// Start an authorization of `integration` for `owner`: save a state with
+    /// a PKCE verifier and return the URL to send the user to
+    pub async fn start_authorization(
+        &self,
+        integration: &str,
+        owner: Uuid,
+        credential_name: &str,
+        credential_id: Option<Uuid>,
+    ) -> Result<(String, OAuth2State), OAuth2Error> {
+        let config = self.config(integration).await?;
+        let now = Utc::now();
+        if let Err(e) = self.states.purge_expired(now).await {
+            tracing::warn!("Failed to purge expired OAuth2 states: {}", e);
+        }
+
+        let pkce = PkceChallenge::new();
+        let state = OAuth2State {
+            state: random_token(),
+            integration: integration.to_string(),
+            owner,
+            credential_name: credential_name.to_string(),
+            credential_id,
+            code_verifier: pkce.verifier.clone(),
+            created_at: now,
+            expires_at: now + self.state_ttl,
+        };
+        self.states.save(&state).await?;
+        Ok((authorization_url(&config, &state.state, Some(&pkce)), state))
+    }
+
+    /// Complete an authorization the provider redirected back with: check
+    /// the state, then exchange the code with the state's PKCE verifier
+    pub async fn complete_authorization(
+        &self,
+        state: &str,
+        code: &str,
+    ) -> Result<(OAuth2State, OAuth2Token), OAuth2Error> {
+        let started = self.states.take(state).await?.ok_or(OAuth2Error::InvalidState)?;
+        if started.expires_at <= Utc::now() {
+            return Err(OAuth2Error::StateExpired);
+        }
+        let config = self.config(&started.integration).await?;
 
-        let scopes = config.scopes.join(" ");
-        Some(format!(
-            "{}?client_id={}&redirect_uri={}&scope={}&state={}&response_type=code",
-            config.auth_url,
-            urlencoding::encode(&config.client_id),
-            urlencoding::encode(&config.redirect_uri),
-            urlencoding::encode(&scopes),
-            state
-        ))
+        let params = [
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &config.redirect_uri),
+            ("client_id", &config.client_id),
+            ("client_secret", &config.client_secret),
+            ("code_verifier", &started.code_verifier),
+        ];
+        let response = self.request_token(&config, &params, OAuth2Error::TokenExchangeFailed).await?;
+        Ok((started, response.into_token(None)))
     }
 
     /// Exchange authorization code for access token
     pub async fn exchange_code(
         &self,
-        integration_id: Uuid,
+        integration: &str,
         code: &str,
     ) -> Result<OAuth2Token, OAuth2Error> {
-        let configs = self.configs.read().await;
-        let config = configs
-            .get(&integration_id)
-            .ok_or(OAuth2Error::ConfigNotFound)?
-            .clone();
-        drop(configs);
+        let config = self.config(integration).await?;
 
         let params = [
             ("grant_type", "authorization_code"),
@@ -81,45 +253,23 @@ impl OAuth2Handler {
             ("client_id", &config.client_id),
             ("client_secret", &config.client_secret),
         ];
-
-        let response = self
-            .client
-            .post(&config.token_url)
-            .form(&params)
-            .send()
-            .await
-            .map_err(|e| OAuth2Error::RequestFailed(e.to_string()))?;
-
-        if !response.status().is_success() {
-            return Err(OAuth2Error::TokenExchangeFailed(
-                response.status().to_string(),
-            ));
-        }
-
-        let token_response: TokenResponse = response
-            .json()
-            .await
-            .map_err(|e| OAuth2Error::InvalidResponse(e.to_string()))?;
-
-        let token = OAuth2Token {
-            access_token: token_response.access_token,
-            refresh_token: token_response.refresh_token,
-            expires_at: Utc::now() + Duration::seconds(token_response.expires_in as i64),
-            token_type: token_response.token_type,
-        };
+        let token = self
+            .request_token(&config, &params, OAuth2Error::TokenExchangeFailed)
+            .await?
+            .into_token(None);
 
         // Store token
         let mut tokens = self.tokens.write().await;
-        tokens.insert(integration_id, token.clone());
+        tokens.insert(integration.to_string(), token.clone());
 
         Ok(token)
     }
 
     /// Refresh an access token
-    pub async fn refresh_token(&self, integration_id: Uuid) -> Result<OAuth2Token, OAuth2Error> {
+    pub async fn refresh_token(&self, integration: &str) -> Result<OAuth2Token, OAuth2Error> {
         let tokens = self.tokens.read().await;
         let old_token = tokens
-            .get(&integration_id)
+            .get(integration)
             .ok_or(OAuth2Error::TokenNotFound)?;
 
         let refresh_token = old_token
@@ -129,64 +279,75 @@ impl OAuth2Handler {
             .clone();
         drop(tokens);
 
-        let configs = self.configs.read().await;
-        let config = configs
-            .get(&integration_id)
-            .ok_or(OAuth2Error::ConfigNotFound)?
-            .clone();
-        drop(configs);
+        let token = self.refresh_with(integration, &refresh_token).await?;
+
+        // Update token
+        let mut tokens = self.tokens.write().await;
+        tokens.insert(integration.to_string(), token.clone());
+
+        Ok(token)
+    }
+
+    /// Get a new access token with `refresh_token`; the refresh token is
+    /// kept unless the provider rotates it
+    pub async fn refresh_with(&self, integration: &str, refresh_token: &str) -> Result<OAuth2Token, OAuth2Error> {
+        let config = self.config(integration).await?;
 
         let params = [
             ("grant_type", "refresh_token"),
-            ("refresh_token", &refresh_token),
+            ("refresh_token", refresh_token),
             ("client_id", &config.client_id),
             ("client_secret", &config.client_secret),
         ];
+        let response = self.request_token(&config, &params, OAuth2Error::RefreshFailed).await?;
+        Ok(response.into_token(Some(refresh_token)))
+    }
+
+    /// Get valid access token (refresh if expired)
+    pub async fn get_valid_token(&self, integration: &str) -> Result<String, OAuth2Error> {
+        let tokens = self.tokens.read().await;
+        if let Some(token) = tokens.get(integration) {
+            if token.expires_at > Utc::now() {
+                return Ok(token.access_token.clone());
+            }
+        }
+        drop(tokens);
 
+        // Token expired or not found, refresh it
+        let token = self.refresh_token(integration).await?;
+        Ok(token.access_token)
+    }
+
+    async fn config(&self, integration: &str) -> Result<OAuth2Config, OAuth2Error> {
+        let configs = self.configs.read().await;
+        configs.get(integration).cloned().ok_or(OAuth2Error::ConfigNotFound)
+    }
+
+    /// POST `params` to the token endpoint; a rejection by the provider is
+    /// reported with `rejected`
+    async fn request_token(
+        &self,
+        config: &OAuth2Config,
+        params: &[(&str, &str)],
+        rejected: fn(String) -> OAuth2Error,
+    ) -> Result<TokenResponse, OAuth2Error> {
         let response = self
             .client
             .post(&config.token_url)
-            .form(&params)
+            .header("Accept", "application/json")
+            .form(params)
             .send()
             .await
             .map_err(|e| OAuth2Error::RequestFailed(e.to_string()))?;
 
         if !response.status().is_success() {
-            return Err(OAuth2Error::RefreshFailed(response.status().to_string()));
+            return Err(rejected(response.status().to_string()));
         }
 
-        let token_response: TokenResponse = response
+        response
             .json()
             .await
-            .map_err(|e| OAuth2Error::InvalidResponse(e.to_string()))?;
-
-        let token = OAuth2Token {
-            access_token: token_response.access_token,
-            refresh_token: token_response.refresh_token.or(Some(refresh_token)),
-            expires_at: Utc::now() + Duration::seconds(token_response.expires_in as i64),
-            token_type: token_response.token_type,
-        };
-
-        // Update token
-        let mut tokens = self.tokens.write().await;
-        tokens.insert(integration_id, token.clone());
-
-        Ok(token)
-    }
-
-    /// Get valid access token (refresh if expired)
-    pub async fn get_valid_token(&self, integration_id: Uuid) -> Result<String, OAuth2Error> {
-        let tokens = self.tokens.read().await;
-        if let Some(token) = tokens.get(&integration_id) {
-            if token.expires_at > Utc::now() {
-                return Ok(token.access_token.clone());
-            }
-        }
-        drop(tokens);
-
-        // Token expired or not found, refresh it
-        let token = self.refresh_token(integration_id).await?;
-        Ok(token.access_token)
+            .map_err(|e| OAuth2Error::InvalidResponse(e.to_string()))
     }
 }
 
@@ -196,14 +357,159 @@ impl Default for OAuth2Handler {
     }
 }
 
+fn authorization_url(config: &OAuth2Config, state: &str, pkce: Option<&PkceChallenge>) -> String {
+    let scopes = config.scopes.join(" ");
+    let mut url = format!(
+        "{}?client_id={}&redirect_uri={}&scope={}&state={}&response_type=code",
+        config.auth_url,
+        urlencoding::encode(&config.client_id),
+        urlencoding::encode(&config.redirect_uri),
+        urlencoding::encode(&scopes),
+        state
+    );
+    if let Some(pkce) = pkce {
+        url.push_str(&format!("&code_challenge={}&code_challenge_method=S256", pkce.challenge));
+    }
+    url
+}
+
+/// 32 random bytes, base64url-encoded without padding
+fn random_token() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
 #[derive(Debug, Deserialize)]
 struct TokenResponse {
     access_token: String,
     refresh_token: Option<String>,
-    expires_in: u64,
+    /// Absent for tokens that do not expire, such as Slack bot tokens
+    #[serde(default)]
+    expires_in: Option<u64>,
     token_type: String,
 }
 
+impl TokenResponse {
+    fn expires_at(&self) -> DateTime<Utc> {
+        match self.expires_in {
+            Some(seconds) => Utc::now() + Duration::seconds(seconds as i64),
+            None => DateTime::<Utc>::MAX_UTC,
+        }
+    }
+
+    /// The token, keeping `refresh_token` when the response has none
+    fn into_token(self, refresh_token: Option<&str>) -> OAuth2Token {
+        OAuth2Token {
+            expires_at: self.expires_at(),
+            access_token: self.access_token,
+            refresh_token: self.refresh_token.or(refresh_token.map(str::to_string)),
+            token_type: self.token_type,
+        }
+    }
+}
+
+/// Outcome of a refresh pass
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RefreshReport {
+    pub refreshed: usize,
+    /// Credentials flagged for reconnection because the provider refused
+    /// the refresh
+    pub flagged: usize,
+    /// Refreshes to retry on the next pass
+    pub failed: usize,
+}
+
+/// Refreshes the OAuth2 tokens of saved credentials before they expire
+pub struct OAuth2TokenRefresher {
+    handler: Arc<OAuth2Handler>,
+    credentials: Arc<CredentialStore>,
+    manager: Arc<CredentialManager>,
+    /// Tokens expiring within this window are refreshed
+    refresh_before: Duration,
+    interval: std::time::Duration,
+}
+
+impl OAuth2TokenRefresher {
+    pub fn new(handler: Arc<OAuth2Handler>, credentials: Arc<CredentialStore>, manager: Arc<CredentialManager>) -> Self {
+        Self {
+            handler,
+            credentials,
+            manager,
+            refresh_before: Duration::minutes(5),
+            interval: std::time::Duration::from_secs(60),
+        }
+    }
+
+    pub fn with_refresh_before(mut self, refresh_before: Duration) -> Self {
+        self.refresh_before = refresh_before;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: std::time::Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Refresh the tokens due now, then every interval
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                self.run_once(Utc::now()).await;
+                tokio::time::sleep(self.interval).await;
+            }
+        })
+    }
+
+    /// Refresh the tokens expiring within the window after `now`
+    pub async fn run_once(&self, now: DateTime<Utc>) -> RefreshReport {
+        let mut report = RefreshReport::default();
+        for credential in self.credentials.tokens_expiring(now + self.refresh_before).await {
+            match self.refresh(&credential).await {
+                Ok(()) => report.refreshed += 1,
+                Err(e @ (OAuth2Error::NoRefreshToken | OAuth2Error::RefreshFailed(_))) => {
+                    tracing::warn!("Refreshing the token of credential {} was refused: {}", credential.id, e);
+                    let reason = format!("The OAuth2 token could not be refreshed ({}), reconnect the integration", e);
+                    let id = credential.id;
+                    let mut flagged = credential;
+                    flagged.status = CredentialStatus::NeedsAttention { reason };
+                    flagged.updated_at = Utc::now();
+                    match self.credentials.save(flagged).await {
+                        Ok(()) => report.flagged += 1,
+                        Err(e) => {
+                            tracing::warn!("Failed to flag credential {}: {}", id, e);
+                            report.failed += 1;
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to refresh the token of credential {}: {}", credential.id, e);
+                    report.failed += 1;
+                }
+            }
+        }
+        if report.refreshed + report.flagged + report.failed > 0 {
+            tracing::info!(
+                "Refreshed {} OAuth2 tokens, flagged {}, {} to retry",
+                report.refreshed,
+                report.flagged,
+                report.failed
+            );
+        }
+        report
+    }
+
+    async fn refresh(&self, credential: &StoredCredential) -> Result<(), OAuth2Error> {
+        let used_by = CredentialUse::TokenRefresh { user_id: credential.owner };
+        let plaintext = self.credentials.decrypt(credential.id, used_by, &self.manager).await?;
+        let token: OAuth2Token =
+            serde_json::from_str(&plaintext).map_err(|e| OAuth2Error::InvalidResponse(e.to_string()))?;
+        let refresh_token = token.refresh_token.ok_or(OAuth2Error::NoRefreshToken)?;
+        let refreshed = self.handler.refresh_with(&credential.integration, &refresh_token).await?;
+        self.credentials.update_token(credential.id, &refreshed, &self.manager).await?;
+        Ok(())
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum OAuth2Error {
     #[error("OAuth2 config not found")]
@@ -215,6 +521,12 @@ pub enum OAuth2Error {
     #[error("No refresh token available")]
     NoRefreshToken,
 
+    #[error("Unknown or already used OAuth2 state")]
+    InvalidState,
+
+    #[error("OAuth2 authorization expired")]
+    StateExpired,
+
     #[error("Request failed: {0}")]
     RequestFailed(String),
 
@@ -226,28 +538,193 @@ pub enum OAuth2Error {
 
     #[error("Invalid response: {0}")]
     InvalidResponse(String),
+
+    #[error("OAuth2 state storage error: {0}")]
+    Storage(String),
+
+    #[error(transparent)]
+    Credential(#[from] CredentialError),
+}
+
+impl ErrorInfo for OAuth2Error {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            OAuth2Error::ConfigNotFound | OAuth2Error::TokenNotFound => ErrorCode::NotFound,
+            // The connection has to be authorized again
+            OAuth2Error::NoRefreshToken => ErrorCode::Unauthenticated,
+            OAuth2Error::InvalidState | OAuth2Error::StateExpired => ErrorCode::InvalidInput,
+            OAuth2Error::RequestFailed(_) | OAuth2Error::Storage(_) => ErrorCode::Unavailable,
+            OAuth2Error::TokenExchangeFailed(_) | OAuth2Error::RefreshFailed(_) | OAuth2Error::InvalidResponse(_) => {
+                ErrorCode::UpstreamFailed
+            }
+            OAuth2Error::Credential(e) => e.error_code(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::credentials::{InMemoryCredentialStorage, StoredCredential};
+    use crate::integrations::AuthType;
+    use axum::{http::StatusCode, response::IntoResponse, routing::post, Form, Json, Router};
 
-    #[tokio::test]
-    async fn test_register_config() {
-        let handler = OAuth2Handler::new();
-        let id = Uuid::new_v4();
-        let config = OAuth2Config {
+    fn config(token_url: &str) -> OAuth2Config {
+        OAuth2Config {
             client_id: "test".to_string(),
             client_secret: "secret".to_string(),
             auth_url: "https://auth.example.com".to_string(),
-            token_url: "https://token.example.com".to_string(),
+            token_url: token_url.to_string(),
             scopes: vec!["read".to_string()],
             redirect_uri: "https://callback.example.com".to_string(),
-        };
+        }
+    }
 
-        handler.register_config(id, config).await;
-        let auth_url = handler.get_auth_url(id, "state123").await;
+    /// A token endpoint accepting the code "c-1" with the verifier
+    /// "verifier" and the refresh token "r-1"
+    async fn fake_provider() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        let app = Router::new().route(
+            "/token",
+            post(|Form(params): Form<HashMap<String, String>>| async move {
+                let param = |name: &str| params.get(name).map(String::as_str);
+                let accepted = match param("grant_type") {
+                    Some("authorization_code") => param("code") == Some("c-1") && param("code_verifier") == Some("verifier"),
+                    Some("refresh_token") => param("refresh_token") == Some("r-1"),
+                    _ => false,
+                };
+                if !accepted {
+                    return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": "invalid_grant" }))).into_response();
+                }
+                Json(serde_json::json!({
+                    "access_token": format!("a-{}", param("grant_type").unwrap()),
+                    "refresh_token": (param("grant_type") == Some("authorization_code")).then_some("r-1"),
+                    "expires_in": 3600,
+                    "token_type": "bearer"
+                }))
+                .into_response()
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_register_config() {
+        let handler = OAuth2Handler::new();
+        handler.register_config("crm", config("https://token.example.com")).await;
+        let auth_url = handler.get_auth_url("crm", "state123").await;
         assert!(auth_url.is_some());
+        assert!(handler.get_auth_url("other", "state123").await.is_none());
     }
-}
 
+    #[test]
+    fn test_pkce_challenge() {
+        let pkce = PkceChallenge::from_verifier("dBjftJeZ4CVP-mJ92K27uhbUJU1p1r_wW1gFWFOEjXk".to_string());
+        assert_eq!(pkce.challenge, "ngF5GsXcbwljx6u133FFr3Xht9xooA_DuaX_3QwODtc");
+        let generated = PkceChallenge::new();
+        assert_eq!(generated.verifier.len(), 43);
+        assert_ne!(generated.verifier, PkceChallenge::new().verifier);
+    }
+
+    #[tokio::test]
+    async fn test_authorization_states_are_single_use_and_expire() {
+        let states = Arc::new(InMemoryOAuth2StateStore::new());
+        let handler = OAuth2Handler::new().with_state_store(states.clone());
+        handler.register_config("crm", config(&fake_provider().await)).await;
+        let owner = Uuid::new_v4();
+
+        let (url, state) = handler.start_authorization("crm", owner, "CRM", None).await.unwrap();
+        assert!(url.contains(&format!("state={}", state.state)));
+        assert!(url.contains(&format!("code_challenge={}&code_challenge_method=S256", PkceChallenge::from_verifier(state.code_verifier.clone()).challenge)));
+        assert!(matches!(
+            handler.start_authorization("other", owner, "CRM", None).await,
+            Err(OAuth2Error::ConfigNotFound)
+        ));
+
+        // The provider checks the verifier saved with the state
+        let mut started = states.take(&state.state).await.unwrap().unwrap();
+        started.code_verifier = "verifier".to_string();
+        states.save(&started).await.unwrap();
+        let (completed, token) = handler.complete_authorization(&state.state, "c-1").await.unwrap();
+        assert_eq!((completed.owner, completed.credential_name.as_str()), (owner, "CRM"));
+        assert_eq!(token.access_token, "a-authorization_code");
+        assert_eq!(token.refresh_token.as_deref(), Some("r-1"));
+        assert!(matches!(
+            handler.complete_authorization(&state.state, "c-1").await,
+            Err(OAuth2Error::InvalidState)
+        ));
+
+        let expired = OAuth2Handler::new().with_state_store(states.clone()).with_state_ttl(Duration::zero());
+        expired.register_config("crm", config("https://token.example.com")).await;
+        let (_, state) = expired.start_authorization("crm", owner, "CRM", None).await.unwrap();
+        assert!(matches!(
+            expired.complete_authorization(&state.state, "c-1").await,
+            Err(OAuth2Error::StateExpired)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_refresher_refreshes_tokens_before_expiry() {
+        let handler = Arc::new(OAuth2Handler::new());
+        handler.register_config("crm", config(&fake_provider().await)).await;
+        let manager = Arc::new(CredentialManager::new(&[7u8; 32]));
+        let storage = Arc::new(InMemoryCredentialStorage::new());
+        let credentials = Arc::new(CredentialStore::new().with_storage(storage.clone()));
+        let now = Utc::now();
+
+        let token = |access: &str, refresh: &str, expires_at| OAuth2Token {
+            access_token: access.to_string(),
+            refresh_token: Some(refresh.to_string()),
+            expires_at,
+            token_type: "bearer".to_string(),
+        };
+        let mut saved = Vec::new();
+        for (refresh, expires_at) in [("r-1", now + Duration::minutes(2)), ("revoked", now), ("r-1", now + Duration::hours(1))] {
+            let mut credential = StoredCredential {
+                id: Uuid::new_v4(),
+                owner: Uuid::new_v4(),
+                name: "CRM".to_string(),
+                integration: "crm".to_string(),
+                auth_type: AuthType::OAuth2,
+                schema_version: 1,
+                data: String::new(),
+                status: CredentialStatus::Active,
+                created_at: now,
+                updated_at: now,
+                rotated_at: None,
+                expires_at: None,
+                token_expires_at: None,
+            };
+            credential.set_token(&token("a-0", refresh, expires_at), &manager).unwrap();
+            assert_eq!(credential.token_expires_at, Some(expires_at));
+            credentials.save(credential.clone()).await.unwrap();
+            saved.push(credential);
+        }
+
+        let refresher = OAuth2TokenRefresher::new(handler, credentials.clone(), manager.clone());
+        let report = refresher.run_once(now).await;
+        assert_eq!((report.refreshed, report.flagged, report.failed), (1, 1, 0));
+
+        let refreshed = credentials.get(saved[0].id).await.unwrap();
+        assert!(refreshed.token_expires_at.unwrap() > now + Duration::minutes(30));
+        let used_by = CredentialUse::TokenRefresh { user_id: refreshed.owner };
+        let stored: OAuth2Token = serde_json::from_str(&credentials.decrypt(refreshed.id, used_by, &manager).await.unwrap()).unwrap();
+        assert_eq!((stored.access_token.as_str(), stored.refresh_token.as_deref()), ("a-refresh_token", Some("r-1")));
+
+        let flagged = credentials.get(saved[1].id).await.unwrap();
+        assert!(matches!(flagged.status, CredentialStatus::NeedsAttention { .. }));
+        assert_eq!(credentials.get(saved[2].id).await.unwrap().rotated_at, None);
+
+        // The refreshed token and the flag outlive a restart
+        let restarted = CredentialStore::new().with_storage(storage);
+        restarted.load().await.unwrap();
+        assert_eq!(restarted.get(saved[0].id).await.unwrap().token_expires_at, refreshed.token_expires_at);
+        assert!(matches!(restarted.get(saved[1].id).await.unwrap().status, CredentialStatus::NeedsAttention { .. }));
+
+        // Flagged credentials are left for their owner to reconnect
+        let report = refresher.run_once(now).await;
+        assert_eq!((report.refreshed, report.flagged, report.failed), (0, 0, 0));
+    }
+}
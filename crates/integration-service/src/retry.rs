@@ -1,3 +1,4 @@
+use rand::Rng;
 use std::time::Duration;
 use tokio::time::sleep;
 
@@ -8,6 +9,9 @@ pub struct RetryPolicy {
     pub initial_delay: Duration,
     pub max_delay: Duration,
     pub backoff_multiplier: f64,
+    /// Fraction of each delay that is randomized, so that callers failing
+    /// together do not retry together; 0 waits the exact backoff
+    pub jitter: f64,
 }
 
 impl Default for RetryPolicy {
@@ -17,6 +21,7 @@ impl Default for RetryPolicy {
             initial_delay: Duration::from_millis(100),
             max_delay: Duration::from_secs(30),
             backoff_multiplier: 2.0,
+            jitter: 0.5,
         }
     }
 }
@@ -33,9 +38,16 @@ impl RetryPolicy {
             initial_delay,
             max_delay,
             backoff_multiplier,
+            jitter: 0.5,
         }
     }
 
+    /// Randomize this fraction of each delay
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
     /// Calculate delay for a given retry attempt
     pub fn calculate_delay(&self, attempt: u32) -> Duration {
         if attempt == 0 {
@@ -49,9 +61,32 @@ impl RetryPolicy {
         delay.min(self.max_delay)
     }
 
+    /// The delay for a given retry attempt, shortened by a random part of
+    /// up to `jitter` of it
+    pub fn jittered_delay(&self, attempt: u32) -> Duration {
+        let delay = self.calculate_delay(attempt);
+        if self.jitter <= 0.0 || delay.is_zero() {
+            return delay;
+        }
+        let cut = rand::thread_rng().gen_range(0.0..self.jitter.min(1.0));
+        delay.mul_f64(1.0 - cut)
+    }
+
     /// Execute a function with retry logic
-    pub async fn execute<F, Fut, T, E>(&self, mut f: F) -> Result<T, E>
+    pub async fn execute<F, Fut, T, E>(&self, f: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, E>>,
+        E: std::fmt::Display,
+    {
+        self.execute_if(|_| true, f).await.0
+    }
+
+    /// Execute a function, retrying only the errors `retryable` accepts;
+    /// returns the outcome and the number of attempts made
+    pub async fn execute_if<R, F, Fut, T, E>(&self, retryable: R, mut f: F) -> (Result<T, E>, u32)
     where
+        R: Fn(&E) -> bool,
         F: FnMut() -> Fut,
         Fut: std::future::Future<Output = Result<T, E>>,
         E: std::fmt::Display,
@@ -60,14 +95,14 @@ impl RetryPolicy {
 
         loop {
             match f().await {
-                Ok(result) => return Ok(result),
+                Ok(result) => return (Ok(result), attempt + 1),
                 Err(e) => {
                     attempt += 1;
-                    if attempt > self.max_retries {
-                        return Err(e);
+                    if attempt > self.max_retries || !retryable(&e) {
+                        return (Err(e), attempt);
                     }
 
-                    let delay = self.calculate_delay(attempt);
+                    let delay = self.jittered_delay(attempt);
                     tracing::warn!(
                         "Attempt {} failed: {}. Retrying in {:?}...",
                         attempt,
@@ -92,6 +127,7 @@ impl RetryPolicy {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
 
     #[test]
     fn test_calculate_delay() {
@@ -110,6 +146,7 @@ mod tests {
             initial_delay: Duration::from_secs(1),
             max_delay: Duration::from_secs(5),
             backoff_multiplier: 2.0,
+            jitter: 0.0,
         };
 
         // Should cap at max_delay
@@ -119,12 +156,12 @@ mod tests {
     #[tokio::test]
     async fn test_execute_success() {
         let policy = RetryPolicy::default();
-        let mut attempts = 0;
+        let attempts = AtomicU32::new(0);
 
         let result = policy
             .execute(|| async {
-                attempts += 1;
-                if attempts < 2 {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                if attempt < 2 {
                     Err("temporary error")
                 } else {
                     Ok(42)
@@ -133,7 +170,7 @@ mod tests {
             .await;
 
         assert_eq!(result, Ok(42));
-        assert_eq!(attempts, 2);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
     }
 
     #[tokio::test]
@@ -143,18 +180,49 @@ mod tests {
             initial_delay: Duration::from_millis(1),
             max_delay: Duration::from_secs(1),
             backoff_multiplier: 2.0,
+            jitter: 0.0,
         };
 
-        let mut attempts = 0;
+        let attempts = AtomicU32::new(0);
         let result = policy
             .execute(|| async {
-                attempts += 1;
+                attempts.fetch_add(1, Ordering::SeqCst);
                 Err::<i32, _>("persistent error")
             })
             .await;
 
         assert!(result.is_err());
-        assert_eq!(attempts, 3); // Initial + 2 retries
+        assert_eq!(attempts.load(Ordering::SeqCst), 3); // Initial + 2 retries
+    }
+
+    #[test]
+    fn test_jittered_delay() {
+        let policy = RetryPolicy::default().with_jitter(0.5);
+        for _ in 0..100 {
+            let delay = policy.jittered_delay(3);
+            assert!(delay > Duration::from_millis(200) && delay <= Duration::from_millis(400));
+        }
+        assert_eq!(policy.with_jitter(0.0).jittered_delay(3), Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_execute_if_stops_at_terminal_errors() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(1), Duration::from_millis(10), 2.0);
+        let attempts = AtomicU32::new(0);
+        let (result, made) = policy
+            .execute_if(
+                |e: &&str| *e == "timeout",
+                || async {
+                    match attempts.fetch_add(1, Ordering::SeqCst) {
+                        0 | 1 => Err("timeout"),
+                        _ => Err("bad request"),
+                    }
+                },
+            )
+            .await;
+
+        assert_eq!(result, Err::<(), _>("bad request"));
+        assert_eq!(made, 3);
     }
 
     #[test]
@@ -0,0 +1,434 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use argon2::Argon2;
+use base64::{engine::general_purpose, Engine};
+use chrono::{DateTime, Utc};
+use common::error::{ErrorCode, ErrorInfo};
+use common::types::{ActionType, NodeType, TriggerType, Workflow};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::integrations::AuthType;
+
+/// Current bundle format version
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Key marking a credential placeholder object inside node parameters
+pub const PLACEHOLDER_KEY: &str = "$placeholder";
+
+/// Key marking a bound credential reference inside node parameters
+pub const CREDENTIAL_REF_KEY: &str = "$credential";
+
+/// Replacement for credential values in redacted output
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Parameter names (case-insensitive) whose values are treated as credentials
+const CREDENTIAL_KEYS: &[&str] = &[
+    "credentials",
+    "credential_id",
+    "api_key",
+    "apikey",
+    "password",
+    "secret",
+    "token",
+    "access_token",
+    "refresh_token",
+    "client_secret",
+    "authorization",
+];
+
+/// A credential removed from a public bundle, describing what must be bound on import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialPlaceholder {
+    pub id: String,
+    pub node_id: Uuid,
+    /// Dotted path of the parameter inside the node configuration
+    pub parameter: String,
+    /// Integration the credential belongs to, e.g. "http" or "slack"
+    pub integration: String,
+    pub auth_type: AuthType,
+}
+
+/// Sanitized bundle safe to share publicly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicBundle {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub workflow: Workflow,
+    pub placeholders: Vec<CredentialPlaceholder>,
+}
+
+/// Passphrase-encrypted bundle carrying the full workflow, credentials included
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedBundle {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub kdf: String,
+    /// Base64-encoded KDF salt
+    pub salt: String,
+    /// Base64-encoded nonce + AES-256-GCM ciphertext
+    pub ciphertext: String,
+}
+
+/// Exported workflow bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum WorkflowBundle {
+    Public(PublicBundle),
+    Encrypted(EncryptedBundle),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SharingError {
+    #[error("Encryption failed")]
+    EncryptionFailed,
+
+    #[error("Wrong passphrase or corrupted bundle")]
+    DecryptionFailed,
+
+    #[error("Invalid bundle: {0}")]
+    InvalidBundle(String),
+
+    #[error("Unsupported bundle version: {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("Unbound credential placeholders: {0:?}")]
+    UnboundPlaceholders(Vec<String>),
+
+    #[error("Unknown credential placeholder: {0}")]
+    UnknownPlaceholder(String),
+}
+
+impl ErrorInfo for SharingError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            SharingError::EncryptionFailed => ErrorCode::Internal,
+            SharingError::DecryptionFailed
+            | SharingError::InvalidBundle(_)
+            | SharingError::UnsupportedVersion(_)
+            | SharingError::UnboundPlaceholders(_)
+            | SharingError::UnknownPlaceholder(_) => ErrorCode::InvalidInput,
+        }
+    }
+}
+
+/// Export a workflow with every credential replaced by a typed placeholder
+pub fn export_public(workflow: &Workflow) -> PublicBundle {
+    let mut workflow = workflow.clone();
+    let mut placeholders = Vec::new();
+
+    for node in &mut workflow.nodes {
+        let integration = integration_name(&node.node_type, &node.config.parameters);
+        let declared_auth = node.config.parameters.get("auth_type")
+            .and_then(|v| serde_json::from_value::<AuthType>(v.clone()).ok());
+
+        let mut keys: Vec<String> = node.config.parameters.keys().cloned().collect();
+        keys.sort();
+        for key in keys {
+            if let Some(value) = node.config.parameters.get_mut(&key) {
+                let mut found = Vec::new();
+                find_credentials(value, &key, &mut found);
+                for (path, key_name) in found {
+                    let id = format!("cred_{}", placeholders.len() + 1);
+                    let auth_type = declared_auth.clone().unwrap_or_else(|| auth_type_for(&key_name));
+                    set_at_path(value, &path, serde_json::json!({
+                        PLACEHOLDER_KEY: id,
+                        "integration": integration,
+                        "auth_type": auth_type,
+                    }));
+                    placeholders.push(CredentialPlaceholder {
+                        id,
+                        node_id: node.id,
+                        parameter: std::iter::once(key.as_str())
+                            .chain(path.iter().map(String::as_str))
+                            .collect::<Vec<_>>()
+                            .join("."),
+                        integration: integration.clone(),
+                        auth_type,
+                    });
+                }
+            }
+        }
+    }
+
+    PublicBundle {
+        version: BUNDLE_VERSION,
+        exported_at: Utc::now(),
+        workflow,
+        placeholders,
+    }
+}
+
+/// Copy a JSON value with every credential-like field replaced by [`REDACTED`]
+pub fn redact_credentials(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Object(map) => JsonValue::Object(
+            map.iter()
+                .map(|(key, child)| {
+                    let child = if is_credential_key(key) && !child.is_null() {
+                        JsonValue::String(REDACTED.to_string())
+                    } else {
+                        redact_credentials(child)
+                    };
+                    (key.clone(), child)
+                })
+                .collect(),
+        ),
+        JsonValue::Array(items) => JsonValue::Array(items.iter().map(redact_credentials).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Export the full workflow encrypted with a key derived from the passphrase (Argon2id)
+pub fn export_encrypted(workflow: &Workflow, passphrase: &str) -> Result<EncryptedBundle, SharingError> {
+    let plaintext = serde_json::to_vec(workflow).map_err(|_| SharingError::EncryptionFailed)?;
+
+    let mut rng = rand::thread_rng();
+    let salt: [u8; 16] = rng.gen();
+    let nonce_bytes: [u8; 12] = rng.gen();
+    let cipher = cipher_for(passphrase, &salt)?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_slice())
+        .map_err(|_| SharingError::EncryptionFailed)?;
+
+    let mut payload = nonce_bytes.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok(EncryptedBundle {
+        version: BUNDLE_VERSION,
+        exported_at: Utc::now(),
+        kdf: "argon2id".to_string(),
+        salt: general_purpose::STANDARD.encode(salt),
+        ciphertext: general_purpose::STANDARD.encode(payload),
+    })
+}
+
+/// Decrypt an encrypted bundle back into the original workflow
+pub fn decrypt_bundle(bundle: &EncryptedBundle, passphrase: &str) -> Result<Workflow, SharingError> {
+    if bundle.version > BUNDLE_VERSION {
+        return Err(SharingError::UnsupportedVersion(bundle.version));
+    }
+    if bundle.kdf != "argon2id" {
+        return Err(SharingError::InvalidBundle(format!("unsupported kdf '{}'", bundle.kdf)));
+    }
+
+    let salt = general_purpose::STANDARD
+        .decode(&bundle.salt)
+        .map_err(|_| SharingError::InvalidBundle("salt is not base64".to_string()))?;
+    let payload = general_purpose::STANDARD
+        .decode(&bundle.ciphertext)
+        .map_err(|_| SharingError::InvalidBundle("ciphertext is not base64".to_string()))?;
+    if payload.len() < 12 {
+        return Err(SharingError::InvalidBundle("ciphertext too short".to_string()));
+    }
+
+    let (nonce_bytes, ciphertext) = payload.split_at(12);
+    let plaintext = cipher_for(passphrase, &salt)?
+        .decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
+        .map_err(|_| SharingError::DecryptionFailed)?;
+    serde_json::from_slice(&plaintext).map_err(|e| SharingError::InvalidBundle(e.to_string()))
+}
+
+/// Replace every placeholder with a reference to one of the importer's credentials
+///
+/// `bindings` maps placeholder IDs to credential IDs; all placeholders must be bound.
+pub fn bind_credentials(
+    bundle: &PublicBundle,
+    bindings: &HashMap<String, String>,
+) -> Result<Workflow, SharingError> {
+    if bundle.version > BUNDLE_VERSION {
+        return Err(SharingError::UnsupportedVersion(bundle.version));
+    }
+    if let Some(unknown) = bindings.keys().find(|id| !bundle.placeholders.iter().any(|p| &p.id == *id)) {
+        return Err(SharingError::UnknownPlaceholder(unknown.clone()));
+    }
+    let missing: Vec<String> = bundle.placeholders.iter()
+        .filter(|p| !bindings.contains_key(&p.id))
+        .map(|p| p.id.clone())
+        .collect();
+    if !missing.is_empty() {
+        return Err(SharingError::UnboundPlaceholders(missing));
+    }
+
+    let mut workflow = bundle.workflow.clone();
+    for node in &mut workflow.nodes {
+        for value in node.config.parameters.values_mut() {
+            bind(value, bindings);
+        }
+    }
+    Ok(workflow)
+}
+
+fn cipher_for(passphrase: &str, salt: &[u8]) -> Result<Aes256Gcm, SharingError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|_| SharingError::EncryptionFailed)?;
+    Ok(Aes256Gcm::new(&key.into()))
+}
+
+fn is_credential_key(key: &str) -> bool {
+    CREDENTIAL_KEYS.contains(&key.to_ascii_lowercase().as_str())
+}
+
+/// Collect paths (relative to `value`) of credential values, paired with their key
+fn find_credentials(value: &JsonValue, key: &str, found: &mut Vec<(Vec<String>, String)>) {
+    fn walk(value: &JsonValue, key: &str, path: &mut Vec<String>, found: &mut Vec<(Vec<String>, String)>) {
+        if is_credential_key(key) && !value.is_null() {
+            found.push((path.clone(), key.to_string()));
+            return;
+        }
+        if let JsonValue::Object(map) = value {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            for child in keys {
+                path.push(child.clone());
+                walk(&map[child], child, path, found);
+                path.pop();
+            }
+        }
+    }
+    walk(value, key, &mut Vec::new(), found);
+}
+
+fn set_at_path(value: &mut JsonValue, path: &[String], replacement: JsonValue) {
+    let mut target = value;
+    for segment in path {
+        match target.get_mut(segment) {
+            Some(next) => target = next,
+            None => return,
+        }
+    }
+    *target = replacement;
+}
+
+fn bind(value: &mut JsonValue, bindings: &HashMap<String, String>) {
+    let placeholder = value.get(PLACEHOLDER_KEY).and_then(|v| v.as_str()).map(String::from);
+    if let Some(credential) = placeholder.and_then(|id| bindings.get(&id)) {
+        *value = serde_json::json!({ CREDENTIAL_REF_KEY: credential });
+        return;
+    }
+    match value {
+        JsonValue::Object(map) => map.values_mut().for_each(|v| bind(v, bindings)),
+        JsonValue::Array(items) => items.iter_mut().for_each(|v| bind(v, bindings)),
+        _ => {}
+    }
+}
+
+fn integration_name(node_type: &NodeType, parameters: &HashMap<String, JsonValue>) -> String {
+    if let Some(name) = parameters.get("integration").and_then(|v| v.as_str()) {
+        return name.to_string();
+    }
+    match node_type {
+        NodeType::Action { action_type } => match action_type {
+            ActionType::Http => "http",
+            ActionType::Email => "email",
+            ActionType::Database => "database",
+            ActionType::Integration => "integration",
+            ActionType::Scraper => "scraper",
+        },
+        NodeType::AI { .. } => parameters.get("provider").and_then(|v| v.as_str()).unwrap_or("ai"),
+        NodeType::Trigger { trigger_type: TriggerType::Webhook } => "webhook",
+        _ => "custom",
+    }
+    .to_string()
+}
+
+fn auth_type_for(key: &str) -> AuthType {
+    match key.to_ascii_lowercase().as_str() {
+        "password" => AuthType::Basic,
+        "token" | "access_token" | "authorization" => AuthType::Bearer,
+        "refresh_token" | "client_secret" => AuthType::OAuth2,
+        _ => AuthType::ApiKey,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::types::{Node, NodeConfig, Position, WORKFLOW_SCHEMA_VERSION};
+
+    fn workflow() -> Workflow {
+        let node = Node {
+            id: Uuid::new_v4(),
+            node_type: NodeType::Action { action_type: ActionType::Http },
+            config: NodeConfig {
+                parameters: HashMap::from([
+                    ("url".to_string(), serde_json::json!("https://api.example.com")),
+                    ("headers".to_string(), serde_json::json!({ "Authorization": "Bearer s3cret", "Accept": "json" })),
+                    ("api_key".to_string(), serde_json::json!("sk-live-123")),
+                ]),
+                ..Default::default()
+            },
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![],
+        };
+        Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Shared".to_string(),
+            description: None,
+            nodes: vec![node],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_public_bundle_hides_credentials() {
+        let bundle = export_public(&workflow());
+        let exported = serde_json::to_string(&bundle).unwrap();
+        assert!(!exported.contains("s3cret") && !exported.contains("sk-live-123"));
+        assert!(exported.contains("https://api.example.com"));
+
+        let params: Vec<&str> = bundle.placeholders.iter().map(|p| p.parameter.as_str()).collect();
+        assert_eq!(params, ["api_key", "headers.Authorization"]);
+        assert!(matches!(bundle.placeholders[1].auth_type, AuthType::Bearer));
+        assert_eq!(bundle.placeholders[0].integration, "http");
+
+        let partial = HashMap::from([("cred_1".to_string(), "vault-1".to_string())]);
+        assert!(matches!(bind_credentials(&bundle, &partial), Err(SharingError::UnboundPlaceholders(ref m)) if m == &["cred_2"]));
+
+        let bindings = HashMap::from([
+            ("cred_1".to_string(), "vault-1".to_string()),
+            ("cred_2".to_string(), "vault-2".to_string()),
+        ]);
+        let imported = bind_credentials(&bundle, &bindings).unwrap();
+        let params = &imported.nodes[0].config.parameters;
+        assert_eq!(params["api_key"], serde_json::json!({ CREDENTIAL_REF_KEY: "vault-1" }));
+        assert_eq!(params["headers"]["Authorization"][CREDENTIAL_REF_KEY], "vault-2");
+    }
+
+    #[test]
+    fn test_encrypted_bundle_round_trip() {
+        let original = workflow();
+        let bundle = export_encrypted(&original, "correct horse").unwrap();
+        assert!(!bundle.ciphertext.contains("sk-live"));
+
+        let restored = decrypt_bundle(&bundle, "correct horse").unwrap();
+        assert_eq!(restored.nodes[0].config.parameters["api_key"], "sk-live-123");
+        assert!(matches!(decrypt_bundle(&bundle, "wrong"), Err(SharingError::DecryptionFailed)));
+    }
+
+    #[test]
+    fn test_redact_credentials() {
+        let value = serde_json::json!({
+            "url": "https://api.example.com",
+            "requests": [{ "headers": { "Authorization": "Bearer abc" }, "password": null }],
+            "Token": "t0k"
+        });
+        let redacted = redact_credentials(&value);
+        assert_eq!(redacted["url"], "https://api.example.com");
+        assert_eq!(redacted["requests"][0]["headers"]["Authorization"], REDACTED);
+        assert!(redacted["requests"][0]["password"].is_null());
+        assert_eq!(redacted["Token"], REDACTED);
+    }
+}
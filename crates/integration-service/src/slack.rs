@@ -0,0 +1,563 @@
+//! Slack integration.
+//!
+//! Posts messages and Block Kit layouts, uploads files and lists channels
+//! through the Slack Web API with a bot token. Requests Slack rate limits
+//! are retried after the `Retry-After` it sends, up to the retry policy's
+//! attempts.
+
+use async_trait::async_trait;
+use base64::Engine;
+use reqwest::StatusCode;
+use serde_json::Value as JsonValue;
+use std::time::Duration;
+
+use crate::integrations::{
+    ActionDefinition, AuthType, Integration, IntegrationCategory, IntegrationError, IntegrationInfo,
+    ParameterDefinition, ParameterType,
+};
+use crate::oauth::OAuth2Config;
+use crate::retry::RetryPolicy;
+
+pub const SLACK_API_URL: &str = "https://slack.com/api";
+const SLACK_AUTH_URL: &str = "https://slack.com/oauth/v2/authorize";
+const SLACK_TOKEN_URL: &str = "https://slack.com/api/oauth.v2.access";
+
+/// Bot scopes the actions need
+pub const SLACK_SCOPES: &[&str] = &["chat:write", "files:write", "channels:read", "groups:read"];
+
+/// Slack errors meaning the token has to be authorized again
+const AUTH_ERRORS: &[&str] = &["invalid_auth", "not_authed", "account_inactive", "token_revoked", "token_expired"];
+
+/// Channels listed per page unless the caller asks otherwise
+const DEFAULT_CHANNEL_PAGE: u64 = 200;
+
+/// How a Web API method is called
+enum SlackRequest {
+    Json(JsonValue),
+    Form(Vec<(&'static str, String)>),
+}
+
+/// Slack integration
+#[derive(Clone)]
+pub struct SlackIntegration {
+    client: reqwest::Client,
+    api_url: String,
+    retry: RetryPolicy,
+}
+
+impl SlackIntegration {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_url: SLACK_API_URL.to_string(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Call the Web API at `api_url` instead of Slack's
+    pub fn with_api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Attempts and backoff of rate-limited calls; `Retry-After` wins when
+    /// Slack sends it
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// OAuth2 settings of a Slack app, to register with the
+    /// [`OAuth2Handler`](crate::OAuth2Handler)
+    pub fn oauth_config(client_id: &str, client_secret: &str, redirect_uri: &str) -> OAuth2Config {
+        OAuth2Config {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            auth_url: SLACK_AUTH_URL.to_string(),
+            token_url: SLACK_TOKEN_URL.to_string(),
+            scopes: SLACK_SCOPES.iter().map(|scope| scope.to_string()).collect(),
+            redirect_uri: redirect_uri.to_string(),
+        }
+    }
+
+    /// The bot token of stored credentials: an OAuth2 token or a bare token
+    fn token(credentials: &str) -> Result<String, IntegrationError> {
+        let credentials = credentials.trim();
+        let token = if credentials.starts_with('{') {
+            serde_json::from_str::<JsonValue>(credentials)
+                .ok()
+                .and_then(|token| token["access_token"].as_str().map(str::to_string))
+        } else {
+            Some(credentials.to_string())
+        };
+        token.filter(|token| !token.is_empty()).ok_or(IntegrationError::InvalidCredentials)
+    }
+
+    /// Call a Web API method, retrying while rate limited
+    async fn call(&self, token: &str, method: &str, request: SlackRequest) -> Result<JsonValue, IntegrationError> {
+        let url = format!("{}/{}", self.api_url, method);
+        let mut attempt = 0;
+        loop {
+            let builder = self.client.post(&url).bearer_auth(token);
+            let builder = match &request {
+                SlackRequest::Json(body) => builder.json(body),
+                SlackRequest::Form(fields) => builder.form(fields),
+            };
+            let response = builder.send().await.map_err(|e| IntegrationError::NetworkError(e.to_string()))?;
+
+            let status = response.status();
+            if status == StatusCode::TOO_MANY_REQUESTS {
+                attempt += 1;
+                if attempt > self.retry.max_retries {
+                    return Err(IntegrationError::NetworkError(format!("Slack rate limited {}", method)));
+                }
+                let wait = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.trim().parse().ok())
+                    .map(Duration::from_secs)
+                    .unwrap_or_else(|| self.retry.calculate_delay(attempt));
+                tracing::warn!("Slack rate limited {}, retrying in {:?}", method, wait);
+                tokio::time::sleep(wait.min(self.retry.max_delay)).await;
+                continue;
+            }
+            if !status.is_success() {
+                let message = format!("Slack {} returned {}", method, status.as_u16());
+                return Err(if status.is_server_error() {
+                    IntegrationError::NetworkError(message)
+                } else {
+                    IntegrationError::ExecutionFailed(message)
+                });
+            }
+
+            let body: JsonValue = response
+                .json()
+                .await
+                .map_err(|e| IntegrationError::ExecutionFailed(format!("Invalid Slack response: {}", e)))?;
+            if body["ok"].as_bool() == Some(true) {
+                return Ok(body);
+            }
+            let error = body["error"].as_str().unwrap_or("unknown_error");
+            return Err(if AUTH_ERRORS.contains(&error) {
+                IntegrationError::InvalidCredentials
+            } else {
+                IntegrationError::ExecutionFailed(format!("Slack {} failed: {}", method, error))
+            });
+        }
+    }
+
+    async fn send_message(&self, token: &str, params: &JsonValue, blocks: bool) -> Result<JsonValue, IntegrationError> {
+        let channel = required_str(params, "channel")?;
+        let mut message = serde_json::json!({ "channel": channel });
+        if blocks {
+            let Some(layout) = params["blocks"].as_array().filter(|layout| !layout.is_empty()) else {
+                return Err(IntegrationError::InvalidParameters("blocks must be a non-empty array".to_string()));
+            };
+            message["blocks"] = JsonValue::Array(layout.clone());
+            // Shown in notifications, where blocks are not rendered
+            if let Some(text) = params["text"].as_str() {
+                message["text"] = text.into();
+            }
+        } else {
+            message["text"] = required_str(params, "text")?.into();
+        }
+        if let Some(thread_ts) = params["thread_ts"].as_str() {
+            message["thread_ts"] = thread_ts.into();
+        }
+
+        let response = self.call(token, "chat.postMessage", SlackRequest::Json(message)).await?;
+        Ok(serde_json::json!({
+            "channel": response["channel"],
+            "ts": response["ts"],
+        }))
+    }
+
+    /// Upload through an upload URL, then share the file in the channel
+    async fn upload_file(&self, token: &str, params: &JsonValue) -> Result<JsonValue, IntegrationError> {
+        let channel = required_str(params, "channel")?;
+        let filename = required_str(params, "filename")?;
+        let content = match (params["content"].as_str(), params["content_base64"].as_str()) {
+            (Some(text), _) => text.as_bytes().to_vec(),
+            (None, Some(encoded)) => base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|_| IntegrationError::InvalidParameters("content_base64 is not valid base64".to_string()))?,
+            (None, None) => {
+                return Err(IntegrationError::InvalidParameters("content or content_base64 required".to_string()))
+            }
+        };
+
+        let fields = vec![("filename", filename.to_string()), ("length", content.len().to_string())];
+        let upload = self.call(token, "files.getUploadURLExternal", SlackRequest::Form(fields)).await?;
+        let (Some(upload_url), Some(file_id)) = (upload["upload_url"].as_str(), upload["file_id"].as_str()) else {
+            return Err(IntegrationError::ExecutionFailed("Slack returned no upload URL".to_string()));
+        };
+        let response = self
+            .client
+            .post(upload_url)
+            .body(content)
+            .send()
+            .await
+            .map_err(|e| IntegrationError::NetworkError(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(IntegrationError::ExecutionFailed(format!(
+                "Slack file upload returned {}",
+                response.status().as_u16()
+            )));
+        }
+
+        let title = params["title"].as_str().unwrap_or(filename);
+        let mut complete = serde_json::json!({
+            "files": [{ "id": file_id, "title": title }],
+            "channel_id": channel,
+        });
+        for field in ["initial_comment", "thread_ts"] {
+            if let Some(value) = params[field].as_str() {
+                complete[field] = value.into();
+            }
+        }
+        self.call(token, "files.completeUploadExternal", SlackRequest::Json(complete)).await?;
+        Ok(serde_json::json!({ "file_id": file_id, "channel": channel }))
+    }
+
+    async fn list_channels(&self, token: &str, params: &JsonValue) -> Result<JsonValue, IntegrationError> {
+        let mut fields = vec![
+            ("types", params["types"].as_str().unwrap_or("public_channel,private_channel").to_string()),
+            ("limit", params["limit"].as_u64().unwrap_or(DEFAULT_CHANNEL_PAGE).to_string()),
+            ("exclude_archived", "true".to_string()),
+        ];
+        if let Some(cursor) = params["cursor"].as_str().filter(|cursor| !cursor.is_empty()) {
+            fields.push(("cursor", cursor.to_string()));
+        }
+
+        let response = self.call(token, "conversations.list", SlackRequest::Form(fields)).await?;
+        let channels: Vec<JsonValue> = response["channels"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|channel| {
+                serde_json::json!({
+                    "id": channel["id"],
+                    "name": channel["name"],
+                    "is_private": channel["is_private"],
+                    "is_member": channel["is_member"],
+                })
+            })
+            .collect();
+        let next_cursor = response["response_metadata"]["next_cursor"].as_str().filter(|cursor| !cursor.is_empty());
+        Ok(serde_json::json!({
+            "channels": channels,
+            "next_cursor": next_cursor,
+        }))
+    }
+}
+
+impl Default for SlackIntegration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn required_str<'a>(params: &'a JsonValue, name: &str) -> Result<&'a str, IntegrationError> {
+    params[name]
+        .as_str()
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| IntegrationError::InvalidParameters(format!("{} required", name)))
+}
+
+fn parameter(name: &str, display_name: &str, description: &str, param_type: ParameterType, required: bool) -> ParameterDefinition {
+    ParameterDefinition {
+        name: name.to_string(),
+        display_name: display_name.to_string(),
+        description: description.to_string(),
+        param_type,
+        required,
+        default_value: None,
+    }
+}
+
+#[async_trait]
+impl Integration for SlackIntegration {
+    fn info(&self) -> IntegrationInfo {
+        IntegrationInfo {
+            name: "slack".to_string(),
+            display_name: "Slack".to_string(),
+            description: "Send messages and files to Slack channels".to_string(),
+            category: IntegrationCategory::Notification,
+            auth_type: AuthType::OAuth2,
+            icon_url: None,
+            tags: ["chat", "messaging", "notifications"].map(String::from).to_vec(),
+        }
+    }
+
+    async fn execute(
+        &self,
+        action: &str,
+        params: JsonValue,
+        credentials: &str,
+    ) -> Result<JsonValue, IntegrationError> {
+        let token = || Self::token(credentials);
+        match action {
+            "send_message" => self.send_message(&token()?, &params, false).await,
+            "send_blocks" => self.send_message(&token()?, &params, true).await,
+            "upload_file" => self.upload_file(&token()?, &params).await,
+            "list_channels" => self.list_channels(&token()?, &params).await,
+            _ => Err(IntegrationError::ActionNotFound(action.to_string())),
+        }
+    }
+
+    async fn validate_credentials(&self, credentials: &str) -> Result<bool, IntegrationError> {
+        let Ok(token) = Self::token(credentials) else {
+            return Ok(false);
+        };
+        match self.call(&token, "auth.test", SlackRequest::Form(Vec::new())).await {
+            Ok(_) => Ok(true),
+            Err(IntegrationError::InvalidCredentials) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn actions(&self) -> Vec<ActionDefinition> {
+        let channel = || parameter("channel", "Channel", "Channel ID to post to", ParameterType::String, true);
+        let thread = || parameter("thread_ts", "Thread", "Timestamp of the message to reply to", ParameterType::String, false);
+        vec![
+            ActionDefinition {
+                name: "send_message".to_string(),
+                display_name: "Send Message".to_string(),
+                description: "Post a text message to a channel".to_string(),
+                parameters: vec![
+                    channel(),
+                    parameter("text", "Text", "Message text, in Slack markdown", ParameterType::String, true),
+                    thread(),
+                ],
+                returns: Some("Channel and timestamp of the message".to_string()),
+            },
+            ActionDefinition {
+                name: "send_blocks".to_string(),
+                display_name: "Send Blocks".to_string(),
+                description: "Post a Block Kit message to a channel".to_string(),
+                parameters: vec![
+                    channel(),
+                    parameter("blocks", "Blocks", "Block Kit blocks", ParameterType::Array, true),
+                    parameter("text", "Fallback Text", "Text shown in notifications", ParameterType::String, false),
+                    thread(),
+                ],
+                returns: Some("Channel and timestamp of the message".to_string()),
+            },
+            ActionDefinition {
+                name: "upload_file".to_string(),
+                display_name: "Upload File".to_string(),
+                description: "Upload a file and share it in a channel".to_string(),
+                parameters: vec![
+                    channel(),
+                    parameter("filename", "File Name", "Name of the uploaded file", ParameterType::String, true),
+                    parameter("content", "Content", "Text content of the file", ParameterType::String, false),
+                    parameter(
+                        "content_base64",
+                        "Binary Content",
+                        "Base64 content, used when content is not set",
+                        ParameterType::String,
+                        false,
+                    ),
+                    parameter("title", "Title", "Title shown in Slack", ParameterType::String, false),
+                    parameter("initial_comment", "Comment", "Message posted with the file", ParameterType::String, false),
+                    thread(),
+                ],
+                returns: Some("ID of the uploaded file".to_string()),
+            },
+            ActionDefinition {
+                name: "list_channels".to_string(),
+                display_name: "List Channels".to_string(),
+                description: "List the workspace's channels, a page at a time".to_string(),
+                parameters: vec![
+                    parameter("types", "Types", "Comma-separated conversation types", ParameterType::String, false),
+                    parameter("limit", "Limit", "Channels per page", ParameterType::Number, false),
+                    parameter("cursor", "Cursor", "next_cursor of the previous page", ParameterType::String, false),
+                ],
+                returns: Some("Channels and the cursor of the next page".to_string()),
+            },
+        ]
+    }
+
+    fn clone_box(&self) -> Box<dyn Integration> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::response::IntoResponse;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    const TOKEN: &str = "xoxb-test";
+
+    #[derive(Clone, Default)]
+    struct FakeSlack {
+        /// Calls of chat.postMessage answered with 429 before succeeding
+        rate_limited: Arc<AtomicU32>,
+        messages: Arc<Mutex<Vec<JsonValue>>>,
+        uploads: Arc<Mutex<Vec<Vec<u8>>>>,
+        shared: Arc<Mutex<Vec<JsonValue>>>,
+    }
+
+    fn authorized(headers: &HeaderMap) -> bool {
+        headers.get("authorization").and_then(|v| v.to_str().ok()) == Some(&format!("Bearer {}", TOKEN))
+    }
+
+    async fn fake_slack(slack: FakeSlack) -> SlackIntegration {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let upload_url = format!("{}/upload", url);
+        let app = Router::new()
+            .route(
+                "/auth.test",
+                post(|headers: HeaderMap| async move {
+                    Json(match authorized(&headers) {
+                        true => serde_json::json!({ "ok": true }),
+                        false => serde_json::json!({ "ok": false, "error": "invalid_auth" }),
+                    })
+                }),
+            )
+            .route(
+                "/chat.postMessage",
+                post(|State(slack): State<FakeSlack>, Json(message): Json<JsonValue>| async move {
+                    if slack.rate_limited.load(Ordering::SeqCst) > 0 {
+                        slack.rate_limited.fetch_sub(1, Ordering::SeqCst);
+                        return (StatusCode::TOO_MANY_REQUESTS, [("retry-after", "0")], "").into_response();
+                    }
+                    if message["channel"] == "C-missing" {
+                        return Json(serde_json::json!({ "ok": false, "error": "channel_not_found" })).into_response();
+                    }
+                    slack.messages.lock().unwrap().push(message.clone());
+                    Json(serde_json::json!({ "ok": true, "channel": message["channel"], "ts": "1700000000.000100" }))
+                        .into_response()
+                }),
+            )
+            .route(
+                "/files.getUploadURLExternal",
+                post(move |body: String| async move {
+                    assert!(body.contains("length=4"));
+                    Json(serde_json::json!({ "ok": true, "upload_url": upload_url, "file_id": "F1" }))
+                }),
+            )
+            .route(
+                "/upload",
+                post(|State(slack): State<FakeSlack>, body: axum::body::Bytes| async move {
+                    slack.uploads.lock().unwrap().push(body.to_vec());
+                    "OK"
+                }),
+            )
+            .route(
+                "/files.completeUploadExternal",
+                post(|State(slack): State<FakeSlack>, Json(body): Json<JsonValue>| async move {
+                    slack.shared.lock().unwrap().push(body);
+                    Json(serde_json::json!({ "ok": true, "files": [{ "id": "F1" }] }))
+                }),
+            )
+            .route(
+                "/conversations.list",
+                post(|body: String| async move {
+                    let page = match body.contains("cursor=page2") {
+                        false => serde_json::json!({
+                            "ok": true,
+                            "channels": [{ "id": "C1", "name": "general", "is_private": false, "is_member": true }],
+                            "response_metadata": { "next_cursor": "page2" },
+                        }),
+                        true => serde_json::json!({
+                            "ok": true,
+                            "channels": [{ "id": "C2", "name": "ops", "is_private": true, "is_member": false }],
+                            "response_metadata": { "next_cursor": "" },
+                        }),
+                    };
+                    Json(page)
+                }),
+            )
+            .with_state(slack);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        SlackIntegration::new().with_api_url(&url)
+    }
+
+    #[tokio::test]
+    async fn test_messages_retry_when_rate_limited() {
+        let slack = FakeSlack::default();
+        slack.rate_limited.store(2, Ordering::SeqCst);
+        let integration = fake_slack(slack.clone()).await;
+
+        let params = serde_json::json!({ "channel": "C1", "text": "Run finished" });
+        let result = integration.execute("send_message", params, TOKEN).await.unwrap();
+        assert_eq!(result["ts"], "1700000000.000100");
+        assert_eq!(slack.messages.lock().unwrap()[0]["text"], "Run finished");
+
+        let blocks = serde_json::json!({
+            "channel": "C1",
+            "blocks": [{ "type": "section", "text": { "type": "mrkdwn", "text": "*Failed*" } }],
+            "text": "Failed",
+        });
+        integration.execute("send_blocks", blocks, r#"{"access_token": "xoxb-test"}"#).await.unwrap();
+        assert_eq!(slack.messages.lock().unwrap()[1]["blocks"][0]["type"], "section");
+
+        let missing = serde_json::json!({ "channel": "C-missing", "text": "x" });
+        let error = integration.execute("send_message", missing, TOKEN).await.unwrap_err();
+        assert!(matches!(error, IntegrationError::ExecutionFailed(m) if m.ends_with("channel_not_found")));
+
+        slack.rate_limited.store(10, Ordering::SeqCst);
+        let params = serde_json::json!({ "channel": "C1", "text": "again" });
+        assert!(matches!(
+            integration.execute("send_message", params, TOKEN).await,
+            Err(IntegrationError::NetworkError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_shares_in_the_channel() {
+        let slack = FakeSlack::default();
+        let integration = fake_slack(slack.clone()).await;
+
+        let params = serde_json::json!({
+            "channel": "C1",
+            "filename": "report.csv",
+            "content_base64": "YSxiCg==",
+            "initial_comment": "Nightly report",
+        });
+        let result = integration.execute("upload_file", params, TOKEN).await.unwrap();
+        assert_eq!(result["file_id"], "F1");
+        assert_eq!(*slack.uploads.lock().unwrap(), vec![b"a,b\n".to_vec()]);
+        let shared = slack.shared.lock().unwrap()[0].clone();
+        assert_eq!(shared["channel_id"], "C1");
+        assert_eq!(shared["files"][0]["title"], "report.csv");
+        assert_eq!(shared["initial_comment"], "Nightly report");
+    }
+
+    #[tokio::test]
+    async fn test_list_channels_pages_and_credentials() {
+        let integration = fake_slack(FakeSlack::default()).await;
+
+        let first = integration.execute("list_channels", serde_json::json!({}), TOKEN).await.unwrap();
+        assert_eq!(first["channels"][0]["name"], "general");
+        assert_eq!(first["next_cursor"], "page2");
+        let second = integration
+            .execute("list_channels", serde_json::json!({ "cursor": "page2" }), TOKEN)
+            .await
+            .unwrap();
+        assert_eq!(second["channels"][0]["is_private"], true);
+        assert_eq!(second["next_cursor"], JsonValue::Null);
+
+        assert!(integration.validate_credentials(TOKEN).await.unwrap());
+        assert!(!integration.validate_credentials("xoxb-revoked").await.unwrap());
+        assert!(!integration.validate_credentials("").await.unwrap());
+    }
+
+    #[test]
+    fn test_oauth_config() {
+        let config = SlackIntegration::oauth_config("id", "secret", "https://flowvex.example.com/callback");
+        assert_eq!(config.token_url, SLACK_TOKEN_URL);
+        assert!(config.scopes.contains(&"chat:write".to_string()));
+    }
+}
@@ -7,7 +7,7 @@ use sqlx::{PgPool, Row};
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::{jwt::JwtManager, roles::{Role, RoleManager}};
+use crate::{jwt::JwtManager, permissions::permission_string, roles::{Role, RoleManager}};
 
 /// Authentication service for user login and registration
 pub struct AuthService {
@@ -83,25 +83,7 @@ impl AuthService {
         let permissions = self.role_manager.get_role_permissions(&role).await;
         let permission_strings: Vec<String> = permissions
             .iter()
-            .map(|p| format!("{}:{}:{:?}", 
-                match p.resource {
-                    common::types::ResourceType::Workflow => "workflow",
-                    common::types::ResourceType::Template => "template",
-                    common::types::ResourceType::Integration => "integration",
-                    common::types::ResourceType::User => "user",
-                    common::types::ResourceType::AuditLog => "audit_log",
-                    common::types::ResourceType::Settings => "settings",
-                },
-                match p.action {
-                    common::types::ActionType2::Create => "create",
-                    common::types::ActionType2::Read => "read",
-                    common::types::ActionType2::Update => "update",
-                    common::types::ActionType2::Delete => "delete",
-                    common::types::ActionType2::Execute => "execute",
-                    common::types::ActionType2::Share => "share",
-                },
-                p.scope
-            ))
+            .map(permission_string)
             .collect();
 
         // Generate JWT token
@@ -141,6 +123,7 @@ impl AuthService {
             "manager" => Role::Manager,
             "user" => Role::User,
             "viewer" => Role::Viewer,
+            "inspector" => Role::Inspector,
             custom => Role::Custom(custom.to_string()),
         };
 
@@ -148,25 +131,7 @@ impl AuthService {
         let permissions = self.role_manager.get_role_permissions(&role).await;
         let permission_strings: Vec<String> = permissions
             .iter()
-            .map(|p| format!("{}:{}:{:?}", 
-                match p.resource {
-                    common::types::ResourceType::Workflow => "workflow",
-                    common::types::ResourceType::Template => "template",
-                    common::types::ResourceType::Integration => "integration",
-                    common::types::ResourceType::User => "user",
-                    common::types::ResourceType::AuditLog => "audit_log",
-                    common::types::ResourceType::Settings => "settings",
-                },
-                match p.action {
-                    common::types::ActionType2::Create => "create",
-                    common::types::ActionType2::Read => "read",
-                    common::types::ActionType2::Update => "update",
-                    common::types::ActionType2::Delete => "delete",
-                    common::types::ActionType2::Execute => "execute",
-                    common::types::ActionType2::Share => "share",
-                },
-                p.scope
-            ))
+            .map(permission_string)
             .collect();
 
         // Generate JWT token
@@ -203,8 +168,8 @@ impl AuthService {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_hash_and_verify_password() {
+    #[tokio::test]
+    async fn test_hash_and_verify_password() {
         let pool = PgPool::connect_lazy("postgresql://localhost/test").unwrap();
         let jwt_manager = Arc::new(JwtManager::new("secret", 24));
         let role_manager = Arc::new(RoleManager::new());
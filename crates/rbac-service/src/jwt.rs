@@ -1,11 +1,14 @@
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use uuid::Uuid;
 
 use common::types::Role;
 use common::error::{AuthError, PlatformError, Result};
 
+use crate::sessions::{InMemorySessionStore, Session, SessionInfo, SessionStore};
+
 /// JWT Claims structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JwtClaims {
@@ -14,6 +17,10 @@ pub struct JwtClaims {
     pub permissions: Vec<String>,
     pub exp: i64,            // expiration timestamp
     pub iat: i64,            // issued at timestamp
+    /// Session of the token; tokens without one are not tied to a device
+    /// and cannot be revoked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>,
 }
 
 /// JWT Manager for token generation and validation
@@ -21,6 +28,8 @@ pub struct JwtManager {
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
     token_expiration: Duration,
+    /// Sessions of the tokens issued with one; revoked sessions fail validation
+    sessions: Arc<dyn SessionStore>,
 }
 
 impl JwtManager {
@@ -30,9 +39,21 @@ impl JwtManager {
             encoding_key: EncodingKey::from_secret(secret.as_bytes()),
             decoding_key: DecodingKey::from_secret(secret.as_bytes()),
             token_expiration: Duration::hours(token_expiration_hours),
+            sessions: Arc::new(InMemorySessionStore::new()),
         }
     }
 
+    /// Keep sessions in `sessions`, shared with other managers of the same secret
+    pub fn with_sessions(mut self, sessions: Arc<dyn SessionStore>) -> Self {
+        self.sessions = sessions;
+        self
+    }
+
+    /// Sessions of the tokens issued by [`JwtManager::start_session`]
+    pub fn sessions(&self) -> &Arc<dyn SessionStore> {
+        &self.sessions
+    }
+
     /// Generate a JWT token for a user
     pub fn generate_token(
         &self,
@@ -40,15 +61,41 @@ impl JwtManager {
         role: Role,
         permissions: Vec<String>,
     ) -> Result<String> {
-        let now = Utc::now();
-        let exp = now + self.token_expiration;
+        let exp = Utc::now() + self.token_expiration;
+        self.encode(user_id, role, permissions, exp, None)
+    }
+
+    /// Sign a user in from a device: start a session and generate a token
+    /// tied to it
+    pub async fn start_session(
+        &self,
+        user_id: Uuid,
+        role: Role,
+        permissions: Vec<String>,
+        info: SessionInfo,
+    ) -> Result<(String, Session)> {
+        let exp = Utc::now() + self.token_expiration;
+        let session = Session::new(user_id, info, exp);
+        self.sessions.start(&session).await?;
+        let token = self.encode(user_id, role, permissions, exp, Some(session.id))?;
+        Ok((token, session))
+    }
 
+    fn encode(
+        &self,
+        user_id: Uuid,
+        role: Role,
+        permissions: Vec<String>,
+        exp: DateTime<Utc>,
+        sid: Option<Uuid>,
+    ) -> Result<String> {
         let claims = JwtClaims {
             sub: user_id,
             role,
             permissions,
             exp: exp.timestamp(),
-            iat: now.timestamp(),
+            iat: Utc::now().timestamp(),
+            sid,
         };
 
         encode(&Header::default(), &claims, &self.encoding_key)
@@ -56,7 +103,7 @@ impl JwtManager {
     }
 
     /// Validate and decode a JWT token
-    pub fn validate_token(&self, token: &str) -> Result<JwtClaims> {
+    pub async fn validate_token(&self, token: &str) -> Result<JwtClaims> {
         let token_data = decode::<JwtClaims>(
             token,
             &self.decoding_key,
@@ -64,11 +111,66 @@ impl JwtManager {
         )
         .map_err(|_| PlatformError::Auth(AuthError::InvalidToken))?;
 
+        // Tokens of revoked or expired sessions are no longer valid; when
+        // the sessions cannot be read, neither is the token
+        if let Some(sid) = token_data.claims.sid {
+            let active = self.sessions.is_active(sid).await.unwrap_or_else(|e| {
+                tracing::warn!("Failed to read session {}: {}", sid, e);
+                false
+            });
+            if !active {
+                return Err(PlatformError::Auth(AuthError::InvalidToken));
+            }
+        }
+
         Ok(token_data.claims)
     }
 
-    /// Refresh a token (generate a new one with updated expiration)
-    pub fn refresh_token(&self, claims: &JwtClaims) -> Result<String> {
-        self.generate_token(claims.sub, claims.role.clone(), claims.permissions.clone())
+    /// Refresh a token (generate a new one with updated expiration); the
+    /// token's session, if any, is extended with it
+    pub async fn refresh_token(&self, claims: &JwtClaims) -> Result<String> {
+        let exp = Utc::now() + self.token_expiration;
+        if let Some(sid) = claims.sid {
+            if !self.sessions.extend(sid, exp).await? {
+                return Err(PlatformError::Auth(AuthError::InvalidToken));
+            }
+        }
+        self.encode(claims.sub, claims.role.clone(), claims.permissions.clone(), exp, claims.sid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_revoked_sessions_invalidate_tokens() {
+        let jwt = JwtManager::new("secret", 1);
+        let user = Uuid::new_v4();
+        let info = |device: &str| SessionInfo {
+            device_name: Some(device.to_string()),
+            ip_address: "10.0.0.1".to_string(),
+            user_agent: "test".to_string(),
+        };
+        let (laptop_token, laptop) = jwt.start_session(user, Role::User, vec![], info("laptop")).await.unwrap();
+        let (phone_token, _) = jwt.start_session(user, Role::User, vec![], info("phone")).await.unwrap();
+        let plain_token = jwt.generate_token(user, Role::User, vec![]).unwrap();
+
+        let claims = jwt.validate_token(&laptop_token).await.unwrap();
+        assert_eq!(claims.sid, Some(laptop.id));
+        assert!(jwt.refresh_token(&claims).await.is_ok());
+        assert_eq!(jwt.sessions().list(user).await.unwrap().len(), 2);
+
+        // Other users cannot revoke the session
+        assert!(!jwt.sessions().revoke(Uuid::new_v4(), laptop.id).await.unwrap());
+        assert!(jwt.sessions().revoke(user, laptop.id).await.unwrap());
+        assert!(jwt.validate_token(&laptop_token).await.is_err());
+        assert!(jwt.refresh_token(&claims).await.is_err());
+        assert!(jwt.validate_token(&phone_token).await.is_ok());
+        assert_eq!(jwt.validate_token(&plain_token).await.unwrap().sid, None);
+
+        assert_eq!(jwt.sessions().revoke_all(user).await.unwrap(), 1);
+        assert!(jwt.validate_token(&phone_token).await.is_err());
+        assert!(jwt.sessions().list(user).await.unwrap().is_empty());
     }
 }
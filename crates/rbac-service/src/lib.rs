@@ -1,14 +1,18 @@
 pub mod auth;
 pub mod jwt;
 pub mod middleware;
+pub mod org;
 pub mod permissions;
 pub mod roles;
+pub mod sessions;
 
 pub use auth::AuthService;
 pub use jwt::JwtManager;
-pub use middleware::AuthMiddleware;
+pub use middleware::{AuthMiddleware, AuthUser};
+pub use org::{OrgError, OrgService};
 pub use permissions::PermissionChecker;
 pub use roles::RoleManager;
+pub use sessions::{InMemorySessionStore, Session, SessionInfo, SessionStore};
 
 // Re-export Role from common
 pub use common::types::Role;
@@ -1,14 +1,20 @@
 use axum::{
-    extract::{Request, State},
-    http::{header, StatusCode},
+    async_trait,
+    extract::{FromRef, FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap, Method, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
+use std::marker::PhantomData;
+use std::ops::Deref;
 use std::sync::Arc;
 
 use crate::jwt::{JwtClaims, JwtManager};
+use crate::permissions::permission_string;
+use common::error::{ErrorCode, ErrorInfo};
+use common::types::{Permission, Role};
 
 /// Auth middleware state
 #[derive(Clone)]
@@ -27,22 +33,14 @@ impl AuthMiddleware {
         mut req: Request,
         next: Next,
     ) -> Result<Response, AuthError> {
-        // Extract token from Authorization header
-        let auth_header = req
-            .headers()
-            .get(header::AUTHORIZATION)
-            .and_then(|h| h.to_str().ok())
-            .ok_or(AuthError::MissingToken)?;
-
-        // Check for Bearer token
-        let token = auth_header
-            .strip_prefix("Bearer ")
-            .ok_or(AuthError::InvalidTokenFormat)?;
-
-        // Validate token
-        let claims = auth.jwt_manager
-            .validate_token(token)
-            .map_err(|_| AuthError::InvalidToken)?;
+        let claims = bearer_claims(req.headers(), &auth.jwt_manager).await?;
+
+        // Inspector tokens are read-only: they may never execute or modify anything
+        if claims.role == Role::Inspector
+            && !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+        {
+            return Err(AuthError::ReadOnlyRole);
+        }
 
         // Insert claims into request extensions for downstream handlers
         req.extensions_mut().insert(claims);
@@ -51,27 +49,149 @@ impl AuthMiddleware {
     }
 }
 
+/// Validate the request's `Authorization: Bearer` token
+async fn bearer_claims(headers: &HeaderMap, jwt_manager: &JwtManager) -> Result<JwtClaims, AuthError> {
+    // Extract token from Authorization header
+    let auth_header = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .ok_or(AuthError::MissingToken)?;
+
+    // Check for Bearer token
+    let token = auth_header
+        .strip_prefix("Bearer ")
+        .ok_or(AuthError::InvalidTokenFormat)?;
+
+    // Validate token
+    jwt_manager
+        .validate_token(token)
+        .await
+        .map_err(|_| AuthError::InvalidToken)
+}
+
+/// A requirement on the caller, checked when extracting [`AuthUser`]
+pub trait AccessRule {
+    fn allows(claims: &JwtClaims) -> bool;
+}
+
+/// Any authenticated user
+pub struct AnyUser;
+
+impl AccessRule for AnyUser {
+    fn allows(_claims: &JwtClaims) -> bool {
+        true
+    }
+}
+
+/// Admins only
+pub struct AdminOnly;
+
+impl AccessRule for AdminOnly {
+    fn allows(claims: &JwtClaims) -> bool {
+        claims.role == Role::Admin
+    }
+}
+
+/// The authenticated caller of a handler.
+///
+/// Uses the claims [`AuthMiddleware`] stored on the request, or validates the
+/// bearer token with the state's [`JwtManager`] on routes without the
+/// middleware. `AuthUser<AdminOnly>` and other [`AccessRule`]s reject callers
+/// not meeting them with 403.
+pub struct AuthUser<R: AccessRule = AnyUser> {
+    pub claims: JwtClaims,
+    rule: PhantomData<R>,
+}
+
+impl<R: AccessRule> AuthUser<R> {
+    /// Fail with 403 unless the caller has one of `roles`
+    pub fn require_role(&self, roles: &[Role]) -> Result<(), AuthError> {
+        if roles.contains(&self.claims.role) {
+            Ok(())
+        } else {
+            Err(AuthError::Forbidden)
+        }
+    }
+
+    /// Fail with 403 unless the caller is an admin or their token carries `permission`
+    pub fn require_permission(&self, permission: &Permission) -> Result<(), AuthError> {
+        let required = permission_string(permission);
+        if self.claims.role == Role::Admin || self.claims.permissions.contains(&required) {
+            Ok(())
+        } else {
+            Err(AuthError::Forbidden)
+        }
+    }
+}
+
+impl<R: AccessRule> Deref for AuthUser<R> {
+    type Target = JwtClaims;
+
+    fn deref(&self) -> &JwtClaims {
+        &self.claims
+    }
+}
+
+#[async_trait]
+impl<S, R> FromRequestParts<S> for AuthUser<R>
+where
+    Arc<JwtManager>: FromRef<S>,
+    S: Send + Sync,
+    R: AccessRule,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let claims = match parts.extensions.get::<JwtClaims>() {
+            Some(claims) => claims.clone(),
+            None => {
+                let claims = bearer_claims(&parts.headers, &Arc::<JwtManager>::from_ref(state)).await?;
+                parts.extensions.insert(claims.clone());
+                claims
+            }
+        };
+        if !R::allows(&claims) {
+            return Err(AuthError::Forbidden);
+        }
+        Ok(Self { claims, rule: PhantomData })
+    }
+}
+
 /// Authentication errors
-#[derive(Debug)]
+#[derive(Debug, thiserror::Error)]
 pub enum AuthError {
+    #[error("Missing authorization token")]
     MissingToken,
+    #[error("Invalid token format. Expected 'Bearer <token>'")]
     InvalidTokenFormat,
+    #[error("Invalid or expired token")]
     InvalidToken,
+    #[error("Inspector access is read-only")]
+    ReadOnlyRole,
+    #[error("Insufficient permissions")]
+    Forbidden,
+}
+
+impl ErrorInfo for AuthError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            AuthError::MissingToken | AuthError::InvalidTokenFormat | AuthError::InvalidToken => ErrorCode::Unauthenticated,
+            AuthError::ReadOnlyRole | AuthError::Forbidden => ErrorCode::PermissionDenied,
+        }
+    }
 }
 
+/// Answered with the gateway's error envelope
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            AuthError::MissingToken => (StatusCode::UNAUTHORIZED, "Missing authorization token"),
-            AuthError::InvalidTokenFormat => (StatusCode::UNAUTHORIZED, "Invalid token format. Expected 'Bearer <token>'"),
-            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid or expired token"),
-        };
-
+        let code = self.error_code();
+        let status = StatusCode::from_u16(code.http_status()).unwrap_or(StatusCode::UNAUTHORIZED);
         let body = Json(json!({
-            "error": {
-                "code": "AUTH_ERROR",
-                "message": message,
-            }
+            "success": false,
+            "code": code,
+            "message": self.to_string(),
+            "retryable": false,
+            "field_errors": [],
         }));
 
         (status, body).into_response()
@@ -88,3 +208,69 @@ impl ClaimsExt for Request {
         self.extensions().get::<JwtClaims>()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use common::types::{ActionType2, ResourceType, Scope};
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    async fn whoami(user: AuthUser) -> String {
+        user.sub.to_string()
+    }
+
+    async fn admin_area(_admin: AuthUser<AdminOnly>) -> &'static str {
+        "ok"
+    }
+
+    async fn fetch(jwt: Arc<JwtManager>, uri: &str, token: Option<String>) -> (StatusCode, String) {
+        let app = Router::new()
+            .route("/me", get(whoami))
+            .route("/admin", get(admin_area))
+            .with_state(jwt);
+        let mut request = Request::builder().uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let response = app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_auth_user_extractor() {
+        let jwt = Arc::new(JwtManager::new("secret", 1));
+        let user_id = Uuid::new_v4();
+        let user_token = jwt.generate_token(user_id, Role::User, vec![]).unwrap();
+        let admin_token = jwt.generate_token(Uuid::new_v4(), Role::Admin, vec![]).unwrap();
+
+        assert_eq!(fetch(jwt.clone(), "/me", Some(user_token.clone())).await, (StatusCode::OK, user_id.to_string()));
+        let (status, body) = fetch(jwt.clone(), "/me", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert!(body.contains("UNAUTHENTICATED"));
+        assert_eq!(fetch(jwt.clone(), "/me", Some("garbage".to_string())).await.0, StatusCode::UNAUTHORIZED);
+
+        assert_eq!(fetch(jwt.clone(), "/admin", Some(user_token)).await.0, StatusCode::FORBIDDEN);
+        assert_eq!(fetch(jwt, "/admin", Some(admin_token)).await.0, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_require_permission() {
+        let claims = |role, permissions| AuthUser::<AnyUser> {
+            claims: JwtClaims { sub: Uuid::new_v4(), role, permissions, exp: i64::MAX, iat: 0, sid: None },
+            rule: PhantomData,
+        };
+        let read_all = Permission { resource: ResourceType::Workflow, action: ActionType2::Read, scope: Scope::All };
+
+        assert!(claims(Role::User, vec!["workflow:read:All".to_string()]).require_permission(&read_all).is_ok());
+        assert!(claims(Role::User, vec![]).require_permission(&read_all).is_err());
+        assert!(claims(Role::Admin, vec![]).require_permission(&read_all).is_ok());
+        assert!(claims(Role::Manager, vec![]).require_role(&[Role::Admin, Role::Manager]).is_ok());
+        assert!(claims(Role::Viewer, vec![]).require_role(&[Role::Admin]).is_err());
+    }
+}
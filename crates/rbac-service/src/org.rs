@@ -0,0 +1,395 @@
+use chrono::{DateTime, Duration, Utc};
+use common::error::{ErrorCode, ErrorInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// How long an invitation can be accepted
+const INVITATION_TTL_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Organization {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A group of organization members sharing Team-scoped resources
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Team {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What a member may do in their organization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MemberRole {
+    Owner,
+    /// Manages teams, members and invitations
+    Admin,
+    Member,
+}
+
+impl MemberRole {
+    pub fn can_manage(&self) -> bool {
+        matches!(self, MemberRole::Owner | MemberRole::Admin)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Membership {
+    pub organization_id: Uuid,
+    pub user_id: Uuid,
+    pub role: MemberRole,
+    /// Teams of the organization the member belongs to
+    pub teams: HashSet<Uuid>,
+    pub joined_at: DateTime<Utc>,
+}
+
+/// An invitation to join an organization, and optionally one of its teams
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invitation {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub team_id: Option<Uuid>,
+    pub email: String,
+    pub role: MemberRole,
+    pub invited_by: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+}
+
+/// Organizations, their teams and memberships
+pub struct OrgService {
+    organizations: Arc<RwLock<HashMap<Uuid, Organization>>>,
+    teams: Arc<RwLock<HashMap<Uuid, Team>>>,
+    /// Keyed by (organization, user)
+    memberships: Arc<RwLock<HashMap<(Uuid, Uuid), Membership>>>,
+    invitations: Arc<RwLock<HashMap<Uuid, Invitation>>>,
+}
+
+impl OrgService {
+    pub fn new() -> Self {
+        Self {
+            organizations: Arc::new(RwLock::new(HashMap::new())),
+            teams: Arc::new(RwLock::new(HashMap::new())),
+            memberships: Arc::new(RwLock::new(HashMap::new())),
+            invitations: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Create an organization owned by `owner_id`
+    pub async fn create_organization(&self, name: String, owner_id: Uuid) -> Organization {
+        let organization = Organization {
+            id: Uuid::new_v4(),
+            name,
+            created_at: Utc::now(),
+        };
+        self.organizations.write().await.insert(organization.id, organization.clone());
+        self.memberships.write().await.insert(
+            (organization.id, owner_id),
+            Membership {
+                organization_id: organization.id,
+                user_id: owner_id,
+                role: MemberRole::Owner,
+                teams: HashSet::new(),
+                joined_at: Utc::now(),
+            },
+        );
+        organization
+    }
+
+    pub async fn get_organization(&self, organization_id: Uuid) -> Option<Organization> {
+        self.organizations.read().await.get(&organization_id).cloned()
+    }
+
+    pub async fn create_team(&self, organization_id: Uuid, name: String, created_by: Uuid) -> Result<Team, OrgError> {
+        self.require_manager(organization_id, created_by).await?;
+        let team = Team {
+            id: Uuid::new_v4(),
+            organization_id,
+            name,
+            created_at: Utc::now(),
+        };
+        self.teams.write().await.insert(team.id, team.clone());
+        Ok(team)
+    }
+
+    pub async fn get_team(&self, team_id: Uuid) -> Option<Team> {
+        self.teams.read().await.get(&team_id).cloned()
+    }
+
+    /// Teams of an organization
+    pub async fn list_teams(&self, organization_id: Uuid) -> Vec<Team> {
+        self.teams.read().await.values()
+            .filter(|team| team.organization_id == organization_id)
+            .cloned()
+            .collect()
+    }
+
+    pub async fn add_member(&self, organization_id: Uuid, user_id: Uuid, role: MemberRole) -> Result<Membership, OrgError> {
+        if self.get_organization(organization_id).await.is_none() {
+            return Err(OrgError::OrganizationNotFound(organization_id));
+        }
+        let mut memberships = self.memberships.write().await;
+        if memberships.contains_key(&(organization_id, user_id)) {
+            return Err(OrgError::AlreadyMember(user_id));
+        }
+        let membership = Membership {
+            organization_id,
+            user_id,
+            role,
+            teams: HashSet::new(),
+            joined_at: Utc::now(),
+        };
+        memberships.insert((organization_id, user_id), membership.clone());
+        Ok(membership)
+    }
+
+    /// Remove a member from the organization and all of its teams
+    pub async fn remove_member(&self, organization_id: Uuid, user_id: Uuid) -> Result<(), OrgError> {
+        let mut memberships = self.memberships.write().await;
+        let membership = memberships.get(&(organization_id, user_id)).ok_or(OrgError::NotAMember(user_id))?;
+        if membership.role == MemberRole::Owner {
+            let owners = memberships.values()
+                .filter(|m| m.organization_id == organization_id && m.role == MemberRole::Owner)
+                .count();
+            if owners == 1 {
+                return Err(OrgError::LastOwner);
+            }
+        }
+        memberships.remove(&(organization_id, user_id));
+        Ok(())
+    }
+
+    pub async fn get_membership(&self, organization_id: Uuid, user_id: Uuid) -> Option<Membership> {
+        self.memberships.read().await.get(&(organization_id, user_id)).cloned()
+    }
+
+    /// Add a member of the team's organization to the team
+    pub async fn add_team_member(&self, team_id: Uuid, user_id: Uuid) -> Result<(), OrgError> {
+        let team = self.get_team(team_id).await.ok_or(OrgError::TeamNotFound(team_id))?;
+        let mut memberships = self.memberships.write().await;
+        let membership = memberships
+            .get_mut(&(team.organization_id, user_id))
+            .ok_or(OrgError::NotAMember(user_id))?;
+        membership.teams.insert(team_id);
+        Ok(())
+    }
+
+    pub async fn remove_team_member(&self, team_id: Uuid, user_id: Uuid) -> Result<(), OrgError> {
+        let team = self.get_team(team_id).await.ok_or(OrgError::TeamNotFound(team_id))?;
+        let mut memberships = self.memberships.write().await;
+        let removed = memberships
+            .get_mut(&(team.organization_id, user_id))
+            .is_some_and(|membership| membership.teams.remove(&team_id));
+        if removed {
+            Ok(())
+        } else {
+            Err(OrgError::NotAMember(user_id))
+        }
+    }
+
+    /// Invite `email` to the organization, and to `team_id` if given
+    pub async fn invite(
+        &self,
+        organization_id: Uuid,
+        team_id: Option<Uuid>,
+        email: String,
+        role: MemberRole,
+        invited_by: Uuid,
+    ) -> Result<Invitation, OrgError> {
+        self.require_manager(organization_id, invited_by).await?;
+        if let Some(team_id) = team_id {
+            let team = self.get_team(team_id).await.ok_or(OrgError::TeamNotFound(team_id))?;
+            if team.organization_id != organization_id {
+                return Err(OrgError::TeamNotFound(team_id));
+            }
+        }
+        let invitation = Invitation {
+            id: Uuid::new_v4(),
+            organization_id,
+            team_id,
+            email: email.trim().to_lowercase(),
+            role,
+            invited_by,
+            expires_at: Utc::now() + Duration::days(INVITATION_TTL_DAYS),
+            accepted_at: None,
+        };
+        self.invitations.write().await.insert(invitation.id, invitation.clone());
+        Ok(invitation)
+    }
+
+    /// Accept an invitation sent to `email`, joining its organization and team
+    pub async fn accept_invitation(&self, invitation_id: Uuid, user_id: Uuid, email: &str) -> Result<Membership, OrgError> {
+        let invitation = {
+            let mut invitations = self.invitations.write().await;
+            let invitation = invitations
+                .get_mut(&invitation_id)
+                .filter(|invitation| invitation.email == email.trim().to_lowercase())
+                .ok_or(OrgError::InvitationNotFound(invitation_id))?;
+            if invitation.accepted_at.is_some() || invitation.expires_at <= Utc::now() {
+                return Err(OrgError::InvitationExpired(invitation_id));
+            }
+            invitation.accepted_at = Some(Utc::now());
+            invitation.clone()
+        };
+
+        if self.get_membership(invitation.organization_id, user_id).await.is_none() {
+            self.add_member(invitation.organization_id, user_id, invitation.role).await?;
+        }
+        if let Some(team_id) = invitation.team_id {
+            self.add_team_member(team_id, user_id).await?;
+        }
+        self.get_membership(invitation.organization_id, user_id)
+            .await
+            .ok_or(OrgError::NotAMember(user_id))
+    }
+
+    /// Organizations the user belongs to
+    pub async fn organizations_of(&self, user_id: Uuid) -> HashSet<Uuid> {
+        self.memberships.read().await.values()
+            .filter(|m| m.user_id == user_id)
+            .map(|m| m.organization_id)
+            .collect()
+    }
+
+    /// Teams the user belongs to, across organizations
+    pub async fn teams_of(&self, user_id: Uuid) -> HashSet<Uuid> {
+        self.memberships.read().await.values()
+            .filter(|m| m.user_id == user_id)
+            .flat_map(|m| m.teams.iter().copied())
+            .collect()
+    }
+
+    pub async fn is_team_member(&self, team_id: Uuid, user_id: Uuid) -> bool {
+        self.teams_of(user_id).await.contains(&team_id)
+    }
+
+    /// Whether both users are on a common team
+    pub async fn share_team(&self, user_id: Uuid, other_id: Uuid) -> bool {
+        let teams = self.teams_of(user_id).await;
+        !teams.is_disjoint(&self.teams_of(other_id).await)
+    }
+
+    /// Whether both users belong to a common organization
+    pub async fn share_organization(&self, user_id: Uuid, other_id: Uuid) -> bool {
+        let organizations = self.organizations_of(user_id).await;
+        !organizations.is_disjoint(&self.organizations_of(other_id).await)
+    }
+
+    async fn require_manager(&self, organization_id: Uuid, user_id: Uuid) -> Result<(), OrgError> {
+        if self.get_organization(organization_id).await.is_none() {
+            return Err(OrgError::OrganizationNotFound(organization_id));
+        }
+        match self.get_membership(organization_id, user_id).await {
+            Some(membership) if membership.role.can_manage() => Ok(()),
+            Some(_) => Err(OrgError::PermissionDenied),
+            None => Err(OrgError::NotAMember(user_id)),
+        }
+    }
+}
+
+impl Default for OrgService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OrgError {
+    #[error("Organization not found: {0}")]
+    OrganizationNotFound(Uuid),
+
+    #[error("Team not found: {0}")]
+    TeamNotFound(Uuid),
+
+    #[error("User {0} is not a member")]
+    NotAMember(Uuid),
+
+    #[error("User {0} is already a member")]
+    AlreadyMember(Uuid),
+
+    #[error("An organization keeps at least one owner")]
+    LastOwner,
+
+    #[error("Invitation not found: {0}")]
+    InvitationNotFound(Uuid),
+
+    #[error("Invitation expired or already accepted: {0}")]
+    InvitationExpired(Uuid),
+
+    #[error("Permission denied")]
+    PermissionDenied,
+}
+
+impl ErrorInfo for OrgError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            OrgError::OrganizationNotFound(_)
+            | OrgError::TeamNotFound(_)
+            | OrgError::InvitationNotFound(_) => ErrorCode::NotFound,
+            OrgError::AlreadyMember(_) | OrgError::LastOwner | OrgError::InvitationExpired(_) => ErrorCode::Conflict,
+            OrgError::NotAMember(_) | OrgError::PermissionDenied => ErrorCode::PermissionDenied,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_teams_and_memberships() {
+        let orgs = OrgService::new();
+        let (owner, member, outsider) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let org = orgs.create_organization("Acme".to_string(), owner).await;
+
+        let team = orgs.create_team(org.id, "Ops".to_string(), owner).await.unwrap();
+        orgs.add_member(org.id, member, MemberRole::Member).await.unwrap();
+        assert!(matches!(
+            orgs.create_team(org.id, "Mine".to_string(), member).await,
+            Err(OrgError::PermissionDenied)
+        ));
+        assert!(matches!(orgs.add_team_member(team.id, outsider).await, Err(OrgError::NotAMember(_))));
+
+        orgs.add_team_member(team.id, owner).await.unwrap();
+        orgs.add_team_member(team.id, member).await.unwrap();
+        assert!(orgs.is_team_member(team.id, member).await);
+        assert!(orgs.share_team(owner, member).await);
+        assert!(!orgs.share_organization(owner, outsider).await);
+
+        orgs.remove_member(org.id, member).await.unwrap();
+        assert!(!orgs.is_team_member(team.id, member).await);
+        assert!(matches!(orgs.remove_member(org.id, owner).await, Err(OrgError::LastOwner)));
+    }
+
+    #[tokio::test]
+    async fn test_invitations() {
+        let orgs = OrgService::new();
+        let (owner, invitee) = (Uuid::new_v4(), Uuid::new_v4());
+        let org = orgs.create_organization("Acme".to_string(), owner).await;
+        let team = orgs.create_team(org.id, "Ops".to_string(), owner).await.unwrap();
+
+        let invitation = orgs
+            .invite(org.id, Some(team.id), "Dev@Acme.io".to_string(), MemberRole::Member, owner)
+            .await
+            .unwrap();
+        assert!(matches!(
+            orgs.accept_invitation(invitation.id, invitee, "other@acme.io").await,
+            Err(OrgError::InvitationNotFound(_))
+        ));
+
+        let membership = orgs.accept_invitation(invitation.id, invitee, "dev@acme.io").await.unwrap();
+        assert_eq!(membership.role, MemberRole::Member);
+        assert!(membership.teams.contains(&team.id));
+        assert!(matches!(
+            orgs.accept_invitation(invitation.id, invitee, "dev@acme.io").await,
+            Err(OrgError::InvitationExpired(_))
+        ));
+    }
+}
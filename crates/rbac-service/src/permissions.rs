@@ -1,17 +1,27 @@
-use common::types::{Permission, ResourceType, ActionType2, Scope};
+use common::error::{ErrorCode, ErrorInfo};
+use common::types::{Permission, ResourceType, ActionType2, Role, Scope};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::org::OrgService;
 use crate::roles::RoleManager;
 
 /// Permission checker for validating user permissions
 pub struct PermissionChecker {
     role_manager: Arc<RoleManager>,
+    /// Resolves Team and Organization scopes against memberships; without
+    /// it only explicitly given team IDs are compared
+    org_service: Option<Arc<OrgService>>,
 }
 
 impl PermissionChecker {
     pub fn new(role_manager: Arc<RoleManager>) -> Self {
-        Self { role_manager }
+        Self { role_manager, org_service: None }
+    }
+
+    pub fn with_org_service(mut self, org_service: Arc<OrgService>) -> Self {
+        self.org_service = Some(org_service);
+        self
     }
 
     /// Check if a user has a specific permission
@@ -24,18 +34,43 @@ impl PermissionChecker {
         user_team_id: Option<Uuid>,
     ) -> bool {
         let user_permissions = self.role_manager.get_user_permissions(user_id).await;
+        self.matches_any(&user_permissions, required_permission, user_id, resource_owner_id, resource_team_id, user_team_id).await
+    }
+
+    /// Check a permission against the permissions of `role`, such as the
+    /// role in the user's token, rather than the role recorded for the user
+    pub async fn check_role_permission(
+        &self,
+        user_id: Uuid,
+        role: &Role,
+        required_permission: &Permission,
+        resource_owner_id: Option<Uuid>,
+        resource_team_id: Option<Uuid>,
+        user_team_id: Option<Uuid>,
+    ) -> bool {
+        let role_permissions = self.role_manager.get_role_permissions(role).await;
+        self.matches_any(&role_permissions, required_permission, user_id, resource_owner_id, resource_team_id, user_team_id).await
+    }
 
-        for permission in user_permissions {
-            if self.matches_permission(&permission, required_permission, user_id, resource_owner_id, resource_team_id, user_team_id) {
+    async fn matches_any(
+        &self,
+        permissions: &[Permission],
+        required: &Permission,
+        user_id: Uuid,
+        resource_owner_id: Option<Uuid>,
+        resource_team_id: Option<Uuid>,
+        user_team_id: Option<Uuid>,
+    ) -> bool {
+        for permission in permissions {
+            if self.matches_permission(permission, required, user_id, resource_owner_id, resource_team_id, user_team_id).await {
                 return true;
             }
         }
-
         false
     }
 
     /// Check if a permission matches the required permission
-    fn matches_permission(
+    async fn matches_permission(
         &self,
         permission: &Permission,
         required: &Permission,
@@ -54,14 +89,19 @@ impl PermissionChecker {
             return false;
         }
 
-        // Check scope
+        // Check scope; wider scopes include the user's own resources and
+        // an organization includes its teams
         match permission.scope {
             Scope::All => true,
             Scope::Organization => {
-                // For now, treat organization same as team
-                self.check_team_scope(resource_team_id, user_team_id)
+                self.check_own_scope(user_id, resource_owner_id)
+                    || self.check_team_scope(user_id, resource_owner_id, resource_team_id, user_team_id).await
+                    || self.check_organization_scope(user_id, resource_owner_id, resource_team_id).await
+            }
+            Scope::Team => {
+                self.check_own_scope(user_id, resource_owner_id)
+                    || self.check_team_scope(user_id, resource_owner_id, resource_team_id, user_team_id).await
             }
-            Scope::Team => self.check_team_scope(resource_team_id, user_team_id),
             Scope::Own => self.check_own_scope(user_id, resource_owner_id),
         }
     }
@@ -70,14 +110,48 @@ impl PermissionChecker {
         resource_owner_id.map(|owner| owner == user_id).unwrap_or(false)
     }
 
-    fn check_team_scope(
+    /// Whether the resource belongs to a team of the user: its team is the
+    /// user's, or its owner is on a team with the user
+    async fn check_team_scope(
         &self,
+        user_id: Uuid,
+        resource_owner_id: Option<Uuid>,
         resource_team_id: Option<Uuid>,
         user_team_id: Option<Uuid>,
     ) -> bool {
-        match (resource_team_id, user_team_id) {
-            (Some(resource_team), Some(user_team)) => resource_team == user_team,
-            _ => false,
+        if let (Some(resource_team), Some(user_team)) = (resource_team_id, user_team_id) {
+            if resource_team == user_team {
+                return true;
+            }
+        }
+        let Some(orgs) = &self.org_service else {
+            return false;
+        };
+        match (resource_team_id, resource_owner_id) {
+            (Some(resource_team), _) => orgs.is_team_member(resource_team, user_id).await,
+            (None, Some(owner)) => orgs.share_team(user_id, owner).await,
+            (None, None) => false,
+        }
+    }
+
+    /// Whether the resource belongs to an organization of the user: through
+    /// its team, or its owner being a member
+    async fn check_organization_scope(
+        &self,
+        user_id: Uuid,
+        resource_owner_id: Option<Uuid>,
+        resource_team_id: Option<Uuid>,
+    ) -> bool {
+        let Some(orgs) = &self.org_service else {
+            return false;
+        };
+        match (resource_team_id, resource_owner_id) {
+            (Some(resource_team), _) => match orgs.get_team(resource_team).await {
+                Some(team) => orgs.get_membership(team.organization_id, user_id).await.is_some(),
+                None => false,
+            },
+            (None, Some(owner)) => orgs.share_organization(user_id, owner).await,
+            (None, None) => false,
         }
     }
 
@@ -133,16 +207,45 @@ impl PermissionChecker {
     }
 }
 
+/// Encode a permission the way it is carried in JWT claims, e.g. `workflow:read:Own`
+pub fn permission_string(permission: &Permission) -> String {
+    let resource = match permission.resource {
+        ResourceType::Workflow => "workflow",
+        ResourceType::Template => "template",
+        ResourceType::Integration => "integration",
+        ResourceType::User => "user",
+        ResourceType::AuditLog => "audit_log",
+        ResourceType::Settings => "settings",
+        ResourceType::Execution => "execution",
+    };
+    let action = match permission.action {
+        ActionType2::Create => "create",
+        ActionType2::Read => "read",
+        ActionType2::Update => "update",
+        ActionType2::Delete => "delete",
+        ActionType2::Execute => "execute",
+        ActionType2::Share => "share",
+        ActionType2::Inspect => "inspect",
+        ActionType2::Approve => "approve",
+    };
+    format!("{}:{}:{:?}", resource, action, permission.scope)
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum PermissionError {
     #[error("Permission denied")]
     PermissionDenied,
 }
 
+impl ErrorInfo for PermissionError {
+    fn error_code(&self) -> ErrorCode {
+        ErrorCode::PermissionDenied
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use common::types::Role;
 
     #[tokio::test]
     async fn test_admin_has_all_permissions() {
@@ -211,6 +314,66 @@ mod tests {
         assert!(!can_read);
     }
 
+    #[tokio::test]
+    async fn test_team_scope_covers_own_resources() {
+        let role_manager = Arc::new(RoleManager::new());
+        let checker = PermissionChecker::new(role_manager.clone());
+        let user_id = Uuid::new_v4();
+        let team_id = Uuid::new_v4();
+
+        role_manager.assign_role(user_id, Role::Manager).await.unwrap();
+
+        let can_update = |owner, resource_team, user_team| {
+            checker.can_perform_action(user_id, ResourceType::Workflow, ActionType2::Update, owner, resource_team, user_team)
+        };
+        assert!(can_update(Some(user_id), None, None).await);
+        assert!(can_update(Some(Uuid::new_v4()), Some(team_id), Some(team_id)).await);
+        assert!(!can_update(Some(Uuid::new_v4()), Some(team_id), Some(Uuid::new_v4())).await);
+        assert!(!can_update(Some(Uuid::new_v4()), None, None).await);
+    }
+
+    #[tokio::test]
+    async fn test_scopes_resolve_against_memberships() {
+        use crate::org::MemberRole;
+
+        let role_manager = Arc::new(RoleManager::new());
+        let orgs = Arc::new(OrgService::new());
+        let checker = PermissionChecker::new(role_manager.clone()).with_org_service(orgs.clone());
+        let (manager, teammate, colleague, outsider) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        role_manager.assign_role(manager, Role::Manager).await.unwrap();
+
+        let org = orgs.create_organization("Acme".to_string(), manager).await;
+        let team = orgs.create_team(org.id, "Ops".to_string(), manager).await.unwrap();
+        for user in [teammate, colleague] {
+            orgs.add_member(org.id, user, MemberRole::Member).await.unwrap();
+        }
+        orgs.add_team_member(team.id, manager).await.unwrap();
+        orgs.add_team_member(team.id, teammate).await.unwrap();
+
+        let can_update = |owner, resource_team| {
+            checker.can_perform_action(manager, ResourceType::Workflow, ActionType2::Update, owner, resource_team, None)
+        };
+        assert!(can_update(Some(teammate), None).await);
+        assert!(can_update(Some(outsider), Some(team.id)).await);
+        assert!(!can_update(Some(colleague), None).await);
+        assert!(!can_update(Some(outsider), None).await);
+
+        // Organization scope reaches members outside the user's teams
+        role_manager
+            .create_custom_role(
+                "org_editor".to_string(),
+                vec![Permission { resource: ResourceType::Workflow, action: ActionType2::Update, scope: Scope::Organization }],
+            )
+            .await
+            .unwrap();
+        role_manager.assign_role(teammate, Role::Custom("org_editor".to_string())).await.unwrap();
+        let can_update = |owner| {
+            checker.can_perform_action(teammate, ResourceType::Workflow, ActionType2::Update, owner, None, None)
+        };
+        assert!(can_update(Some(colleague)).await);
+        assert!(!can_update(Some(outsider)).await);
+    }
+
     #[tokio::test]
     async fn test_viewer_cannot_create() {
         let role_manager = Arc::new(RoleManager::new());
@@ -232,5 +395,49 @@ mod tests {
 
         assert!(!can_create);
     }
-}
 
+    #[tokio::test]
+    async fn test_inspector_is_read_only() {
+        let role_manager = Arc::new(RoleManager::new());
+        let checker = PermissionChecker::new(role_manager.clone());
+        let user_id = Uuid::new_v4();
+        let other_user_id = Uuid::new_v4();
+
+        role_manager.assign_role(user_id, Role::Inspector).await.unwrap();
+
+        assert!(checker
+            .can_perform_action(user_id, ResourceType::Execution, ActionType2::Inspect, Some(other_user_id), None, None)
+            .await);
+        for action in [ActionType2::Execute, ActionType2::Update, ActionType2::Delete] {
+            assert!(!checker
+                .can_perform_action(user_id, ResourceType::Workflow, action, Some(other_user_id), None, None)
+                .await);
+        }
+
+        let permissions = role_manager.get_role_permissions(&Role::Inspector).await;
+        assert!(permissions.iter().map(permission_string).any(|p| p == "execution:inspect:All"));
+    }
+
+    #[tokio::test]
+    async fn test_role_permission_ignores_recorded_role() {
+        let role_manager = Arc::new(RoleManager::new());
+        let checker = PermissionChecker::new(role_manager.clone());
+        let user_id = Uuid::new_v4();
+        let other_user_id = Uuid::new_v4();
+        let delete = Permission {
+            resource: ResourceType::Workflow,
+            action: ActionType2::Delete,
+            scope: Scope::Own,
+        };
+
+        // A user recorded as admin whose token now says user
+        role_manager.assign_role(user_id, Role::Admin).await.unwrap();
+        assert!(!checker.check_role_permission(user_id, &Role::User, &delete, Some(other_user_id), None, None).await);
+        assert!(checker.check_role_permission(user_id, &Role::User, &delete, Some(user_id), None, None).await);
+
+        // Checking does not record a role
+        let unknown_user_id = Uuid::new_v4();
+        assert!(checker.check_role_permission(unknown_user_id, &Role::Admin, &delete, Some(other_user_id), None, None).await);
+        assert_eq!(role_manager.get_user_role(unknown_user_id).await, None);
+    }
+}
@@ -1,3 +1,4 @@
+use common::error::{ErrorCode, ErrorInfo};
 use common::types::{Permission, ResourceType, ActionType2, Scope};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -15,21 +16,10 @@ pub struct RoleManager {
 
 impl RoleManager {
     pub fn new() -> Self {
-        let mut manager = Self {
-            role_permissions: Arc::new(RwLock::new(HashMap::new())),
+        // Start with the default role permissions
+        Self {
+            role_permissions: Arc::new(RwLock::new(Self::get_default_role_permissions())),
             user_roles: Arc::new(RwLock::new(HashMap::new())),
-        };
-        
-        // Initialize default role permissions
-        manager.initialize_default_permissions();
-        manager
-    }
-
-    fn initialize_default_permissions(&mut self) {
-        let default_permissions = Self::get_default_role_permissions();
-        let mut perms = self.role_permissions.blocking_write();
-        for (role, permissions) in default_permissions {
-            perms.insert(role, permissions);
         }
     }
 
@@ -66,11 +56,21 @@ impl RoleManager {
                     action: ActionType2::Execute,
                     scope: Scope::All,
                 },
+                Permission {
+                    resource: ResourceType::Workflow,
+                    action: ActionType2::Approve,
+                    scope: Scope::All,
+                },
                 Permission {
                     resource: ResourceType::User,
                     action: ActionType2::Create,
                     scope: Scope::All,
                 },
+                Permission {
+                    resource: ResourceType::User,
+                    action: ActionType2::Read,
+                    scope: Scope::All,
+                },
                 Permission {
                     resource: ResourceType::User,
                     action: ActionType2::Update,
@@ -81,6 +81,36 @@ impl RoleManager {
                     action: ActionType2::Update,
                     scope: Scope::All,
                 },
+                Permission {
+                    resource: ResourceType::Execution,
+                    action: ActionType2::Inspect,
+                    scope: Scope::All,
+                },
+                Permission {
+                    resource: ResourceType::Integration,
+                    action: ActionType2::Create,
+                    scope: Scope::All,
+                },
+                Permission {
+                    resource: ResourceType::Integration,
+                    action: ActionType2::Read,
+                    scope: Scope::All,
+                },
+                Permission {
+                    resource: ResourceType::Integration,
+                    action: ActionType2::Update,
+                    scope: Scope::All,
+                },
+                Permission {
+                    resource: ResourceType::Integration,
+                    action: ActionType2::Delete,
+                    scope: Scope::All,
+                },
+                Permission {
+                    resource: ResourceType::Integration,
+                    action: ActionType2::Execute,
+                    scope: Scope::All,
+                },
             ],
         );
 
@@ -113,6 +143,11 @@ impl RoleManager {
                     action: ActionType2::Execute,
                     scope: Scope::Team,
                 },
+                Permission {
+                    resource: ResourceType::Workflow,
+                    action: ActionType2::Approve,
+                    scope: Scope::Team,
+                },
                 Permission {
                     resource: ResourceType::Template,
                     action: ActionType2::Create,
@@ -155,6 +190,26 @@ impl RoleManager {
                     action: ActionType2::Create,
                     scope: Scope::Own,
                 },
+                Permission {
+                    resource: ResourceType::Integration,
+                    action: ActionType2::Read,
+                    scope: Scope::Own,
+                },
+                Permission {
+                    resource: ResourceType::Integration,
+                    action: ActionType2::Update,
+                    scope: Scope::Own,
+                },
+                Permission {
+                    resource: ResourceType::Integration,
+                    action: ActionType2::Delete,
+                    scope: Scope::Own,
+                },
+                Permission {
+                    resource: ResourceType::Integration,
+                    action: ActionType2::Execute,
+                    scope: Scope::Own,
+                },
             ],
         );
 
@@ -175,6 +230,28 @@ impl RoleManager {
             ],
         );
 
+        // Inspector - support staff, read-only view of any organization's executions
+        permissions.insert(
+            "inspector".to_string(),
+            vec![
+                Permission {
+                    resource: ResourceType::Workflow,
+                    action: ActionType2::Read,
+                    scope: Scope::All,
+                },
+                Permission {
+                    resource: ResourceType::Execution,
+                    action: ActionType2::Read,
+                    scope: Scope::All,
+                },
+                Permission {
+                    resource: ResourceType::Execution,
+                    action: ActionType2::Inspect,
+                    scope: Scope::All,
+                },
+            ],
+        );
+
         permissions
     }
 
@@ -260,6 +337,16 @@ pub enum RbacError {
     PermissionDenied,
 }
 
+impl ErrorInfo for RbacError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            RbacError::RoleAlreadyExists(_) => ErrorCode::Conflict,
+            RbacError::RoleNotFound(_) => ErrorCode::NotFound,
+            RbacError::PermissionDenied => ErrorCode::PermissionDenied,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,136 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// A signed-in device of a user: the tokens carrying its ID are valid until
+/// it expires or is revoked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub device_name: Option<String>,
+    pub ip_address: String,
+    pub user_agent: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Session {
+    /// A new session of `user_id` signing in from `info`, lasting until `expires_at`
+    pub fn new(user_id: Uuid, info: SessionInfo, expires_at: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            user_id,
+            device_name: info.device_name,
+            ip_address: info.ip_address,
+            user_agent: info.user_agent,
+            issued_at: Utc::now(),
+            expires_at,
+        }
+    }
+}
+
+/// Where a session signs in from
+#[derive(Debug, Clone, Default)]
+pub struct SessionInfo {
+    /// Name the user gave the device, if any
+    pub device_name: Option<String>,
+    pub ip_address: String,
+    pub user_agent: String,
+}
+
+/// Sessions of the issued tokens. Every replica validating tokens must
+/// see the same sessions, or tokens of sessions started elsewhere fail.
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// Save a new session
+    async fn start(&self, session: &Session) -> Result<()>;
+
+    /// Whether a session exists, was not revoked and has not expired
+    async fn is_active(&self, id: Uuid) -> Result<bool>;
+
+    /// Move the expiry of an active session, as when its token is refreshed
+    async fn extend(&self, id: Uuid, expires_at: DateTime<Utc>) -> Result<bool>;
+
+    /// Active sessions of a user, most recently started first
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Session>>;
+
+    /// Revoke a session of `user_id`; sessions of other users are left alone
+    async fn revoke(&self, user_id: Uuid, id: Uuid) -> Result<bool>;
+
+    /// Revoke every session of a user, returning how many there were
+    async fn revoke_all(&self, user_id: Uuid) -> Result<usize>;
+}
+
+/// In-memory session store (for development and tests; sessions are lost
+/// on restart and not shared between replicas)
+#[derive(Debug, Default)]
+pub struct InMemorySessionStore {
+    sessions: RwLock<HashMap<Uuid, Session>>,
+}
+
+impl InMemorySessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SessionStore for InMemorySessionStore {
+    async fn start(&self, session: &Session) -> Result<()> {
+        let mut sessions = self.sessions.write().await;
+        let now = Utc::now();
+        sessions.retain(|_, session| session.expires_at > now);
+        sessions.insert(session.id, session.clone());
+        Ok(())
+    }
+
+    async fn is_active(&self, id: Uuid) -> Result<bool> {
+        let sessions = self.sessions.read().await;
+        Ok(sessions.get(&id).is_some_and(|session| session.expires_at > Utc::now()))
+    }
+
+    async fn extend(&self, id: Uuid, expires_at: DateTime<Utc>) -> Result<bool> {
+        let mut sessions = self.sessions.write().await;
+        match sessions.get_mut(&id).filter(|session| session.expires_at > Utc::now()) {
+            Some(session) => {
+                session.expires_at = expires_at;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn list(&self, user_id: Uuid) -> Result<Vec<Session>> {
+        let sessions = self.sessions.read().await;
+        let now = Utc::now();
+        let mut active: Vec<Session> = sessions
+            .values()
+            .filter(|session| session.user_id == user_id && session.expires_at > now)
+            .cloned()
+            .collect();
+        active.sort_by_key(|session| std::cmp::Reverse(session.issued_at));
+        Ok(active)
+    }
+
+    async fn revoke(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+        let mut sessions = self.sessions.write().await;
+        if sessions.get(&id).is_some_and(|session| session.user_id == user_id) {
+            sessions.remove(&id);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    async fn revoke_all(&self, user_id: Uuid) -> Result<usize> {
+        let mut sessions = self.sessions.write().await;
+        let before = sessions.len();
+        sessions.retain(|_, session| session.user_id != user_id);
+        Ok(before - sessions.len())
+    }
+}
@@ -2,12 +2,15 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
 use crate::error::ScraperError;
-use crate::types::Viewport;
+use crate::har::{Har, HarEntry, HarRecorder};
+use crate::types::{DeviceProfile, Viewport};
+use crate::wait::AutoWait;
 
 /// 浏览器上下文 ID
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
@@ -44,6 +47,33 @@ pub struct BrowserContextConfig {
     pub user_agent: Option<String>,
     pub viewport: Option<Viewport>,
     pub timeout: u64,
+    /// 设备模拟配置，显式设置的 user_agent / viewport 优先
+    pub device: Option<DeviceProfile>,
+    /// 是否记录上下文生命周期内的 HAR
+    pub record_har: bool,
+    /// 交互动作前自动等待目标元素，None 时不等待
+    pub auto_wait: Option<AutoWait>,
+}
+
+impl BrowserContextConfig {
+    /// 实际生效的视口
+    pub fn effective_viewport(&self) -> Option<Viewport> {
+        self.viewport
+            .clone()
+            .or_else(|| self.device.as_ref().map(|d| d.viewport.clone()))
+    }
+
+    /// 实际生效的 User-Agent
+    pub fn effective_user_agent(&self) -> Option<String> {
+        self.user_agent
+            .clone()
+            .or_else(|| self.device.as_ref().map(|d| d.user_agent.clone()))
+    }
+
+    /// 是否支持触摸手势
+    pub fn has_touch(&self) -> bool {
+        self.device.as_ref().map(|d| d.has_touch).unwrap_or(false)
+    }
 }
 
 impl Default for BrowserContextConfig {
@@ -53,6 +83,9 @@ impl Default for BrowserContextConfig {
             user_agent: None,
             viewport: Some(Viewport::default()),
             timeout: 30000,
+            device: None,
+            record_har: false,
+            auto_wait: None,
         }
     }
 }
@@ -62,6 +95,8 @@ impl Default for BrowserContextConfig {
 pub enum ContextStatus {
     Active,
     Idle,
+    /// 浏览器进程崩溃或失去响应
+    Crashed,
     Closed,
 }
 
@@ -75,6 +110,16 @@ pub struct BrowserContext {
     pub status: ContextStatus,
     pub created_at: DateTime<Utc>,
     pub last_used_at: DateTime<Utc>,
+    pub har: Option<HarRecorder>,
+    /// 关联的浏览器进程 ID，由驱动在启动后登记
+    pub process_id: Option<u32>,
+    pub last_heartbeat: DateTime<Utc>,
+    /// 崩溃后被重建的次数
+    pub restart_count: u32,
+    /// 创建该上下文的工作流执行 ID，执行结束时统一关闭
+    pub owner: Option<Uuid>,
+    /// 占用的浏览器进程名额，上下文移除时自动归还
+    slot: Option<OwnedSemaphorePermit>,
     // 在实际实现中，这里会有 Playwright 页面句柄
     // page_handle: Option<PlaywrightPage>,
 }
@@ -82,6 +127,7 @@ pub struct BrowserContext {
 impl BrowserContext {
     pub fn new(id: BrowserContextId, config: BrowserContextConfig) -> Self {
         let now = Utc::now();
+        let har = config.record_har.then(HarRecorder::default);
         BrowserContext {
             id,
             config,
@@ -90,6 +136,12 @@ impl BrowserContext {
             status: ContextStatus::Active,
             created_at: now,
             last_used_at: now,
+            har,
+            process_id: None,
+            last_heartbeat: now,
+            restart_count: 0,
+            owner: None,
+            slot: None,
         }
     }
     
@@ -101,7 +153,7 @@ impl BrowserContext {
     }
     
     pub fn is_valid(&self) -> bool {
-        self.status != ContextStatus::Closed
+        self.status != ContextStatus::Closed && self.status != ContextStatus::Crashed
     }
     
     pub fn close(&mut self) {
@@ -112,16 +164,73 @@ impl BrowserContext {
 /// 浏览器池管理器
 pub struct BrowserPool {
     contexts: Arc<RwLock<HashMap<BrowserContextId, BrowserContext>>>,
+    /// 浏览器进程名额，超出时 create_context 排队等待
+    slots: Arc<Semaphore>,
     max_contexts: usize,
     idle_timeout_secs: u64,
+    queue_timeout_ms: u64,
+    heartbeat_timeout_secs: u64,
+    max_restarts: u32,
 }
 
 impl BrowserPool {
     pub fn new(max_contexts: usize, idle_timeout_secs: u64) -> Self {
         BrowserPool {
             contexts: Arc::new(RwLock::new(HashMap::new())),
+            slots: Arc::new(Semaphore::new(max_contexts)),
             max_contexts,
             idle_timeout_secs,
+            queue_timeout_ms: 30000,
+            heartbeat_timeout_secs: 60,
+            max_restarts: 3,
+        }
+    }
+    
+    /// 设置排队等待浏览器名额的超时时间，0 表示不排队
+    pub fn with_queue_timeout(mut self, queue_timeout_ms: u64) -> Self {
+        self.queue_timeout_ms = queue_timeout_ms;
+        self
+    }
+    
+    /// 设置心跳超时，超过该时间未收到心跳的进程视为僵死
+    pub fn with_heartbeat_timeout(mut self, heartbeat_timeout_secs: u64) -> Self {
+        self.heartbeat_timeout_secs = heartbeat_timeout_secs;
+        self
+    }
+    
+    /// 设置单个上下文崩溃后最多重建的次数
+    pub fn with_max_restarts(mut self, max_restarts: u32) -> Self {
+        self.max_restarts = max_restarts;
+        self
+    }
+    
+    /// 最大并发浏览器数
+    pub fn max_contexts(&self) -> usize {
+        self.max_contexts
+    }
+    
+    /// 当前可用的浏览器名额
+    pub fn available_slots(&self) -> usize {
+        self.slots.available_permits()
+    }
+    
+    /// 申请浏览器进程名额，名额不足时排队直到超时
+    async fn acquire_slot(&self) -> Result<OwnedSemaphorePermit, ScraperError> {
+        if let Ok(permit) = self.slots.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+        if self.queue_timeout_ms == 0 {
+            return Err(ScraperError::PoolExhausted);
+        }
+        
+        tracing::debug!("Browser pool full, queueing create_context request");
+        match tokio::time::timeout(
+            Duration::from_millis(self.queue_timeout_ms),
+            self.slots.clone().acquire_owned(),
+        ).await {
+            Ok(Ok(permit)) => Ok(permit),
+            Ok(Err(e)) => Err(ScraperError::Internal(e.to_string())),
+            Err(_) => Err(ScraperError::PoolExhausted),
         }
     }
     
@@ -130,14 +239,21 @@ impl BrowserPool {
         &self,
         config: BrowserContextConfig,
     ) -> Result<BrowserContextId, ScraperError> {
-        let contexts = self.contexts.read().await;
-        if contexts.len() >= self.max_contexts {
-            return Err(ScraperError::PoolExhausted);
-        }
-        drop(contexts);
+        self.create_owned_context(config, None).await
+    }
+    
+    /// 创建归属于某个工作流执行的浏览器上下文，执行结束时由 close_owned_by 关闭
+    pub async fn create_owned_context(
+        &self,
+        config: BrowserContextConfig,
+        owner: Option<Uuid>,
+    ) -> Result<BrowserContextId, ScraperError> {
+        let slot = self.acquire_slot().await?;
         
         let id = BrowserContextId::new();
-        let context = BrowserContext::new(id.clone(), config);
+        let mut context = BrowserContext::new(id.clone(), config);
+        context.slot = Some(slot);
+        context.owner = owner;
         
         let mut contexts = self.contexts.write().await;
         contexts.insert(id.clone(), context);
@@ -146,6 +262,96 @@ impl BrowserPool {
         Ok(id)
     }
     
+    /// 登记上下文对应的浏览器进程，开始心跳监测
+    pub async fn attach_process(&self, id: &BrowserContextId, pid: u32) -> Result<(), ScraperError> {
+        let mut contexts = self.contexts.write().await;
+        let context = contexts.get_mut(id)
+            .ok_or_else(|| ScraperError::ContextNotFound(id.to_string()))?;
+        context.process_id = Some(pid);
+        context.last_heartbeat = Utc::now();
+        Ok(())
+    }
+    
+    /// 记录浏览器进程心跳
+    pub async fn record_heartbeat(&self, id: &BrowserContextId) -> Result<(), ScraperError> {
+        let mut contexts = self.contexts.write().await;
+        let context = contexts.get_mut(id)
+            .ok_or_else(|| ScraperError::ContextNotFound(id.to_string()))?;
+        context.last_heartbeat = Utc::now();
+        Ok(())
+    }
+    
+    /// 标记上下文崩溃（驱动收到 disconnected / crash 事件时调用）
+    pub async fn mark_crashed(&self, id: &BrowserContextId) -> Result<(), ScraperError> {
+        let mut contexts = self.contexts.write().await;
+        let context = contexts.get_mut(id)
+            .ok_or_else(|| ScraperError::ContextNotFound(id.to_string()))?;
+        if context.status != ContextStatus::Closed {
+            context.status = ContextStatus::Crashed;
+            tracing::warn!("Browser context crashed: {} (pid {:?})", id, context.process_id);
+        }
+        Ok(())
+    }
+    
+    /// 检测僵死进程：已登记进程但心跳超时的上下文标记为崩溃，返回检测到的数量
+    pub async fn detect_zombies(&self) -> usize {
+        let now = Utc::now();
+        let mut contexts = self.contexts.write().await;
+        let mut count = 0;
+        
+        for (id, context) in contexts.iter_mut() {
+            if context.process_id.is_none() || !context.is_valid() {
+                continue;
+            }
+            let silent_secs = (now - context.last_heartbeat).num_seconds().max(0) as u64;
+            if silent_secs > self.heartbeat_timeout_secs {
+                context.status = ContextStatus::Crashed;
+                tracing::warn!("Detected zombie browser process for context {}: no heartbeat for {}s", id, silent_secs);
+                count += 1;
+            }
+        }
+        
+        count
+    }
+    
+    /// 确保上下文可用
+    ///
+    /// 上下文崩溃时会在原 ID 下用相同配置重建并恢复到崩溃前的页面，
+    /// 但仍返回 `BrowserCrashed`，由调用方（工作流引擎）决定是否重试当前动作
+    pub async fn ensure_alive(&self, id: &BrowserContextId) -> Result<(), ScraperError> {
+        let mut contexts = self.contexts.write().await;
+        let context = contexts.get_mut(id)
+            .ok_or_else(|| ScraperError::ContextInvalid(id.to_string()))?;
+        
+        match context.status {
+            ContextStatus::Closed => Err(ScraperError::ContextInvalid(id.to_string())),
+            ContextStatus::Crashed => {
+                if context.restart_count >= self.max_restarts {
+                    contexts.remove(id);
+                    tracing::error!("Browser context {} exceeded max restarts, giving up", id);
+                    return Err(ScraperError::ContextInvalid(id.to_string()));
+                }
+                
+                // 重建上下文，沿用原有的进程名额
+                let mut recreated = BrowserContext::new(id.clone(), context.config.clone());
+                recreated.current_url = context.current_url.clone();
+                recreated.page_title = context.page_title.clone();
+                recreated.har = context.har.take();
+                recreated.restart_count = context.restart_count + 1;
+                recreated.owner = context.owner;
+                recreated.slot = context.slot.take();
+                *context = recreated;
+                
+                tracing::info!(
+                    "Recreated crashed browser context {} (restart {}), restored url {}",
+                    id, context.restart_count, context.current_url
+                );
+                Err(ScraperError::BrowserCrashed(id.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+    
     /// 获取浏览器上下文（可变引用）
     pub async fn get_context_mut(
         &self,
@@ -169,6 +375,22 @@ impl BrowserPool {
         contexts.get(id).map(|c| c.is_valid()).unwrap_or(false)
     }
     
+    /// 获取上下文配置
+    pub async fn get_config(&self, id: &BrowserContextId) -> Result<BrowserContextConfig, ScraperError> {
+        let contexts = self.contexts.read().await;
+        contexts.get(id)
+            .map(|c| c.config.clone())
+            .ok_or_else(|| ScraperError::ContextNotFound(id.to_string()))
+    }
+    
+    /// 获取当前 URL 及最近一次页面活动时间
+    pub async fn page_state(&self, id: &BrowserContextId) -> Result<(String, DateTime<Utc>), ScraperError> {
+        let contexts = self.contexts.read().await;
+        contexts.get(id)
+            .map(|c| (c.current_url.clone(), c.last_used_at))
+            .ok_or_else(|| ScraperError::ContextNotFound(id.to_string()))
+    }
+    
     /// 更新上下文的 URL 和标题
     pub async fn update_context_page(
         &self,
@@ -186,16 +408,76 @@ impl BrowserPool {
         Ok(())
     }
     
-    /// 关闭浏览器上下文
-    pub async fn close_context(&self, id: &BrowserContextId) -> Result<(), ScraperError> {
+    /// 开始记录新页面的 HAR（未开启记录时忽略）
+    pub async fn start_har_page(&self, id: &BrowserContextId, title: &str) -> Result<(), ScraperError> {
+        let mut contexts = self.contexts.write().await;
+        let context = contexts.get_mut(id)
+            .ok_or_else(|| ScraperError::ContextNotFound(id.to_string()))?;
+        if let Some(har) = context.har.as_mut() {
+            har.start_page(title);
+        }
+        Ok(())
+    }
+    
+    /// 记录一次网络请求（未开启记录时忽略）
+    pub async fn record_network_entry(
+        &self,
+        id: &BrowserContextId,
+        entry: HarEntry,
+    ) -> Result<(), ScraperError> {
+        let mut contexts = self.contexts.write().await;
+        let context = contexts.get_mut(id)
+            .ok_or_else(|| ScraperError::ContextNotFound(id.to_string()))?;
+        if let Some(har) = context.har.as_mut() {
+            har.record(entry);
+        }
+        Ok(())
+    }
+    
+    /// 导出当前已记录的 HAR
+    pub async fn export_har(&self, id: &BrowserContextId) -> Result<Option<Har>, ScraperError> {
+        let contexts = self.contexts.read().await;
+        let context = contexts.get(id)
+            .ok_or_else(|| ScraperError::ContextNotFound(id.to_string()))?;
+        Ok(context.har.as_ref().map(|h| h.to_har()))
+    }
+    
+    /// 关闭浏览器上下文，开启记录时返回完整的 HAR
+    pub async fn close_context(&self, id: &BrowserContextId) -> Result<Option<Har>, ScraperError> {
         let mut contexts = self.contexts.write().await;
         if let Some(context) = contexts.get_mut(id) {
             context.close();
             tracing::info!("Closed browser context: {}", id);
         }
         // 从池中移除
-        contexts.remove(id);
-        Ok(())
+        let har = contexts.remove(id).and_then(|c| c.har.map(|h| h.to_har()));
+        Ok(har)
+    }
+    
+    /// 某个工作流执行仍持有的上下文
+    pub async fn contexts_owned_by(&self, owner: Uuid) -> Vec<BrowserContextId> {
+        self.contexts.read().await
+            .values()
+            .filter(|c| c.owner == Some(owner))
+            .map(|c| c.id.clone())
+            .collect()
+    }
+    
+    /// 关闭某个工作流执行持有的全部上下文并归还名额，返回关闭的数量
+    pub async fn close_owned_by(&self, owner: Uuid) -> usize {
+        let mut contexts = self.contexts.write().await;
+        let owned: Vec<BrowserContextId> = contexts.values()
+            .filter(|c| c.owner == Some(owner))
+            .map(|c| c.id.clone())
+            .collect();
+        
+        for id in &owned {
+            if let Some(mut context) = contexts.remove(id) {
+                context.close();
+                tracing::info!("Closed browser context {} left open by execution {}", id, owner);
+            }
+        }
+        owned.len()
     }
     
     /// 清理空闲上下文
@@ -239,3 +521,77 @@ impl Default for BrowserPool {
         BrowserPool::new(10, 300) // 默认最多10个上下文，5分钟空闲超时
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_create_context_queues_when_full() {
+        let pool = Arc::new(BrowserPool::new(1, 300).with_queue_timeout(1000));
+        let first = pool.create_context(BrowserContextConfig::default()).await.unwrap();
+        
+        let waiter = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.create_context(BrowserContextConfig::default()).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+        
+        pool.close_context(&first).await.unwrap();
+        assert!(waiter.await.unwrap().is_ok());
+        assert_eq!(pool.available_slots(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_create_context_times_out() {
+        let pool = BrowserPool::new(1, 300).with_queue_timeout(10);
+        pool.create_context(BrowserContextConfig::default()).await.unwrap();
+        assert!(matches!(
+            pool.create_context(BrowserContextConfig::default()).await,
+            Err(ScraperError::PoolExhausted)
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_zombie_detection_and_restart_limit() {
+        let pool = BrowserPool::new(2, 300)
+            .with_heartbeat_timeout(0)
+            .with_max_restarts(1);
+        let id = pool.create_context(BrowserContextConfig::default()).await.unwrap();
+        pool.attach_process(&id, 4242).await.unwrap();
+        
+        {
+            let mut contexts = pool.contexts.write().await;
+            contexts.get_mut(&id).unwrap().last_heartbeat = Utc::now() - chrono::Duration::seconds(5);
+        }
+        assert_eq!(pool.detect_zombies().await, 1);
+        assert!(matches!(pool.ensure_alive(&id).await, Err(ScraperError::BrowserCrashed(_))));
+        assert!(pool.ensure_alive(&id).await.is_ok());
+        
+        pool.mark_crashed(&id).await.unwrap();
+        assert!(matches!(pool.ensure_alive(&id).await, Err(ScraperError::ContextInvalid(_))));
+        assert_eq!(pool.context_count().await, 0);
+        assert_eq!(pool.available_slots(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_close_contexts_owned_by_execution() {
+        let pool = BrowserPool::new(3, 300);
+        let (execution, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let first = pool.create_owned_context(BrowserContextConfig::default(), Some(execution)).await.unwrap();
+        let second = pool.create_owned_context(BrowserContextConfig::default(), Some(execution)).await.unwrap();
+        let kept = pool.create_owned_context(BrowserContextConfig::default(), Some(other)).await.unwrap();
+        
+        let mut owned = pool.contexts_owned_by(execution).await;
+        owned.sort_by_key(|id| id.to_string());
+        let mut expected = vec![first, second];
+        expected.sort_by_key(|id| id.to_string());
+        assert_eq!(owned, expected);
+        
+        assert_eq!(pool.close_owned_by(execution).await, 2);
+        assert_eq!(pool.list_contexts().await, vec![kept]);
+        assert_eq!(pool.available_slots(), 2);
+        assert_eq!(pool.close_owned_by(execution).await, 0);
+    }
+}
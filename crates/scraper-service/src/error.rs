@@ -1,5 +1,6 @@
 //! 爬虫服务错误类型
 
+use common::error::{ErrorCode, ErrorInfo};
 use thiserror::Error;
 
 /// 爬虫服务错误
@@ -44,6 +45,33 @@ pub enum ScraperError {
     #[error("操作超时: {0}")]
     Timeout(String),
     
+    #[error("未知的设备配置: {0}")]
+    UnknownDevice(String),
+    
+    #[error("当前上下文不支持触摸操作: {0}")]
+    TouchNotSupported(String),
+    
+    #[error("robots.txt 不允许抓取: {0}")]
+    RobotsDisallowed(String),
+    
+    #[error("浏览器进程崩溃，上下文已重建: {0}")]
+    BrowserCrashed(String),
+    
+    #[error("该操作需要浏览器模式: {0}")]
+    BrowserRequired(String),
+    
+    #[error("抓取任务不存在: {0}")]
+    JobNotFound(String),
+    
+    #[error("表单字段无效: {0}")]
+    FormFieldInvalid(String),
+    
+    #[error("制品不存在: {0}")]
+    ArtifactNotFound(String),
+    
+    #[error("选择器修复失败: {0}")]
+    SelectorRepairFailed(String),
+    
     #[error("内部错误: {0}")]
     Internal(String),
 }
@@ -65,18 +93,74 @@ impl ScraperError {
             ScraperError::InvalidUrl(_) => "SCRAPER_010",
             ScraperError::InvalidSelector(_) => "SCRAPER_011",
             ScraperError::Timeout(_) => "SCRAPER_012",
+            ScraperError::UnknownDevice(_) => "SCRAPER_013",
+            ScraperError::TouchNotSupported(_) => "SCRAPER_014",
+            ScraperError::RobotsDisallowed(_) => "SCRAPER_015",
+            ScraperError::BrowserCrashed(_) => "SCRAPER_016",
+            ScraperError::BrowserRequired(_) => "SCRAPER_017",
+            ScraperError::JobNotFound(_) => "SCRAPER_018",
+            ScraperError::FormFieldInvalid(_) => "SCRAPER_019",
+            ScraperError::ArtifactNotFound(_) => "SCRAPER_020",
+            ScraperError::SelectorRepairFailed(_) => "SCRAPER_021",
             ScraperError::Internal(_) => "SCRAPER_999",
         }
     }
 }
 
+/// 统一错误模型中的分类：错误码类别、是否可重试
+impl ErrorInfo for ScraperError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            ScraperError::PoolExhausted | ScraperError::BrowserCrashed(_) => ErrorCode::Unavailable,
+            ScraperError::ContextNotFound(_)
+            | ScraperError::JobNotFound(_)
+            | ScraperError::ArtifactNotFound(_)
+            | ScraperError::ElementNotFound(_) => ErrorCode::NotFound,
+            ScraperError::ContextInvalid(_)
+            | ScraperError::InvalidUrl(_)
+            | ScraperError::InvalidSelector(_)
+            | ScraperError::InvalidInputElement(_)
+            | ScraperError::UnknownDevice(_)
+            | ScraperError::TouchNotSupported(_)
+            | ScraperError::BrowserRequired(_)
+            | ScraperError::FormFieldInvalid(_) => ErrorCode::InvalidInput,
+            ScraperError::RobotsDisallowed(_) => ErrorCode::PermissionDenied,
+            ScraperError::SelectorTimeout(_) | ScraperError::Timeout(_) => ErrorCode::Timeout,
+            ScraperError::NavigationFailed(_)
+            | ScraperError::ElementNotClickable(_)
+            | ScraperError::ScriptError(_)
+            | ScraperError::ScreenshotFailed(_)
+            | ScraperError::SelectorRepairFailed(_) => ErrorCode::UpstreamFailed,
+            ScraperError::Internal(_) => ErrorCode::Internal,
+        }
+    }
+
+    /// 是否为可重试的错误：浏览器池满、浏览器崩溃和操作超时
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ScraperError::PoolExhausted
+                | ScraperError::BrowserCrashed(_)
+                | ScraperError::Timeout(_)
+        )
+    }
+}
+
+impl From<ScraperError> for common::error::PlatformError {
+    fn from(err: ScraperError) -> Self {
+        common::error::PlatformError::service(err)
+    }
+}
+
 /// 将 ScraperError 转换为 JSON 响应
 impl From<ScraperError> for serde_json::Value {
     fn from(err: ScraperError) -> Self {
         serde_json::json!({
             "error": true,
             "code": err.code(),
+            "category": err.error_code(),
             "message": err.to_string(),
+            "retryable": err.is_retryable(),
         })
     }
 }
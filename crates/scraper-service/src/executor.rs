@@ -1,12 +1,27 @@
 //! 爬虫节点执行器
 
+use std::collections::HashMap;
 use std::sync::Arc;
 use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
 use serde_json::Value;
+use tracing::Instrument;
+use uuid::Uuid;
+use common::error::ErrorInfo;
 
 use crate::browser::{BrowserPool, BrowserContextId, BrowserContextConfig};
 use crate::types::*;
 use crate::error::ScraperError;
+use crate::fetch::{ExtractField, HttpFetcher, StaticPage};
+use crate::form::{ArtifactStore, FormField, FormSubmit};
+use crate::har::{HarArtifact, HarEntry};
+use crate::policy::{PolicyEnforcer, ScraperPolicy};
+use crate::repair::{
+    fallback_selectors, page_outline, RepairRequest, RepairStatus, RepairStore, SelectorRepair, SelectorRepairer,
+    SelectorResolution, SelectorSource,
+};
+use crate::screenshot::{process_screenshot, ScreenshotOptions};
+use crate::wait::{wait_for, AutoWait, ElementState, PageProbe, WaitMode, WaitPlan, WaitStrategy};
 
 /// 爬虫节点执行请求
 #[derive(Debug, Deserialize)]
@@ -48,18 +63,46 @@ pub enum ScraperAction {
         #[serde(default)]
         mode: ScrollMode,
     },
+    /// 等待条件；selector 非空时作为第一个策略，与 strategies 按 mode 组合
     Wait { 
+        #[serde(default)]
         selector: String,
         #[serde(default)]
         condition: WaitCondition,
         #[serde(default)]
         find_by: SelectorType,
+        #[serde(default)]
+        strategies: Vec<WaitStrategy>,
+        #[serde(default)]
+        mode: WaitMode,
     },
     LoopElements { 
         selector: String,
         #[serde(default)]
         find_by: SelectorType,
     },
+    Extract {
+        fields: Vec<ExtractField>,
+    },
+    FillForm {
+        fields: Vec<FormField>,
+        #[serde(default)]
+        submit: Option<FormSubmit>,
+    },
+    Tap {
+        selector: String,
+        #[serde(default)]
+        find_by: SelectorType,
+    },
+    Swipe {
+        #[serde(default)]
+        selector: Option<String>,
+        #[serde(default)]
+        direction: SwipeDirection,
+        #[serde(default = "default_swipe_distance")]
+        distance: u32,
+    },
+    ExportHar,
     ExecuteScript { code: String },
     Screenshot {
         #[serde(default)]
@@ -67,6 +110,25 @@ pub enum ScraperAction {
     },
 }
 
+impl ScraperAction {
+    /// 按单个选择器定位元素的动作，可使用备用选择器和选择器修复
+    fn selector_mut(&mut self) -> Option<(&mut String, &SelectorType)> {
+        match self {
+            ScraperAction::GetText { selector, find_by }
+            | ScraperAction::GetAttribute { selector, find_by, .. }
+            | ScraperAction::Click { selector, find_by }
+            | ScraperAction::Input { selector, find_by, .. }
+            | ScraperAction::LoopElements { selector, find_by }
+            | ScraperAction::Tap { selector, find_by } => Some((selector, &*find_by)),
+            _ => None,
+        }
+    }
+}
+
+fn default_swipe_distance() -> u32 {
+    300
+}
+
 impl Default for ScrollMode {
     fn default() -> Self {
         ScrollMode::Pixels { x: 0, y: 500 }
@@ -78,18 +140,29 @@ impl Default for ScrollMode {
 pub struct ScraperResponse {
     pub success: bool,
     pub context_id: Option<String>,
-    pub data: Value,
+    /// 失败时为 null
+    pub data: Option<ScraperData>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    /// 失败是否可重试（如浏览器崩溃后上下文已重建）
+    pub retryable: bool,
+    /// 主选择器未匹配时的替代选择结果
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<SelectorResolution>,
 }
 
 impl ScraperResponse {
-    pub fn success(context_id: Option<String>, data: Value) -> Self {
+    pub fn success(context_id: Option<String>, data: ScraperData) -> Self {
         ScraperResponse {
             success: true,
             context_id,
-            data,
+            data: Some(data),
             error: None,
+            error_code: None,
+            retryable: false,
+            selector: None,
         }
     }
     
@@ -97,27 +170,302 @@ impl ScraperResponse {
         ScraperResponse {
             success: false,
             context_id,
-            data: Value::Null,
+            data: None,
+            error: Some(error.to_string()),
+            error_code: None,
+            retryable: false,
+            selector: None,
+        }
+    }
+    
+    /// 由 ScraperError 构造失败响应，携带错误码和可重试标记
+    pub fn failed(context_id: Option<String>, error: ScraperError) -> Self {
+        ScraperResponse {
+            success: false,
+            context_id,
+            data: None,
+            error_code: Some(error.code().to_string()),
+            retryable: error.is_retryable(),
             error: Some(error.to_string()),
+            selector: None,
         }
     }
 }
 
+/// 基于浏览器上下文状态的页面探针
+struct ContextProbe<'a> {
+    pool: &'a BrowserPool,
+    id: &'a BrowserContextId,
+}
+
+impl PageProbe for ContextProbe<'_> {
+    async fn current_url(&self) -> Result<String, ScraperError> {
+        Ok(self.pool.page_state(self.id).await?.0)
+    }
+
+    async fn element(&self, _selector: &str, _find_by: &SelectorType) -> Result<Option<ElementState>, ScraperError> {
+        // 在实际实现中，这里会查询 locator 的挂载状态、可见性与 innerText
+        Ok(Some(ElementState { visible: true, text: String::new() }))
+    }
+
+    async fn network_idle_for(&self) -> Result<std::time::Duration, ScraperError> {
+        let (_, last_activity) = self.pool.page_state(self.id).await?;
+        Ok((chrono::Utc::now() - last_activity).to_std().unwrap_or_default())
+    }
+
+    async fn evaluate_predicate(&self, _script: &str) -> Result<bool, ScraperError> {
+        // 在实际实现中，这里会调用 page.evaluate 并取返回值的真值
+        Ok(true)
+    }
+}
+
+/// 选择器匹配检查的目标页面
+enum TargetPage {
+    Static(StaticPage),
+    Browser(BrowserContextId),
+}
+
 /// 爬虫执行器
 pub struct ScraperExecutor {
     browser_pool: Arc<BrowserPool>,
+    policy: Option<Arc<PolicyEnforcer>>,
+    /// HTTP 模式打开的静态页面，按上下文 ID 索引，不占用浏览器池
+    static_pages: RwLock<HashMap<String, StaticPage>>,
+    /// 静态页面所属的工作流执行
+    static_owners: RwLock<HashMap<String, Uuid>>,
+    /// 表单文件上传引用的制品
+    artifacts: RwLock<ArtifactStore>,
+    /// 根据页面结构提出替代选择器
+    repairer: Option<Arc<dyn SelectorRepairer>>,
+    /// 选择器修复建议，等待审核
+    repairs: Arc<RepairStore>,
 }
 
 impl ScraperExecutor {
     pub fn new(browser_pool: Arc<BrowserPool>) -> Self {
-        ScraperExecutor { browser_pool }
+        ScraperExecutor {
+            browser_pool,
+            policy: None,
+            static_pages: RwLock::new(HashMap::new()),
+            static_owners: RwLock::new(HashMap::new()),
+            artifacts: RwLock::new(ArtifactStore::new()),
+            repairer: None,
+            repairs: Arc::new(RepairStore::new()),
+        }
+    }
+    
+    /// 保存制品（如待上传的文件），返回可在 FillForm 中引用的 ID
+    pub async fn store_artifact(&self, name: &str, mime_type: &str, data: Vec<u8>) -> String {
+        self.artifacts.write().await.put(name, mime_type, data)
+    }
+    
+    /// 启用 robots.txt 与域名限速策略
+    pub fn with_policy(mut self, policy: ScraperPolicy) -> Self {
+        self.policy = Some(Arc::new(PolicyEnforcer::new(policy)));
+        self
+    }
+    
+    /// 启用选择器修复：动作配置 selectorRepair 为 true 且备用选择器均未匹配时请求修复建议
+    pub fn with_selector_repair(mut self, repairer: Arc<dyn SelectorRepairer>) -> Self {
+        self.repairer = Some(repairer);
+        self
+    }
+    
+    /// 选择器修复记录，供审核
+    pub fn repairs(&self) -> Arc<RepairStore> {
+        self.repairs.clone()
+    }
+    
+    /// 当前生效的合规策略
+    pub fn policy(&self) -> Option<&ScraperPolicy> {
+        self.policy.as_ref().map(|p| p.policy())
     }
     
     /// 执行爬虫请求
     pub async fn execute(&self, request: ScraperRequest) -> ScraperResponse {
+        self.run(request, None).await
+    }
+    
+    /// 代表某个工作流执行发起请求：打开的页面归该执行所有，由 close_execution 统一关闭
+    pub async fn execute_for(&self, execution_id: Uuid, request: ScraperRequest) -> ScraperResponse {
+        self.run(request, Some(execution_id)).await
+    }
+    
+    /// 关闭工作流执行打开且尚未关闭的全部页面（浏览器上下文和静态页面），返回关闭的数量
+    pub async fn close_execution(&self, execution_id: Uuid) -> usize {
+        let static_ids: Vec<String> = {
+            let mut owners = self.static_owners.write().await;
+            let ids = owners.iter()
+                .filter(|(_, owner)| **owner == execution_id)
+                .map(|(id, _)| id.clone())
+                .collect::<Vec<_>>();
+            for id in &ids {
+                owners.remove(id);
+            }
+            ids
+        };
+        {
+            let mut pages = self.static_pages.write().await;
+            for id in &static_ids {
+                pages.remove(id);
+            }
+        }
+        static_ids.len() + self.browser_pool.close_owned_by(execution_id).await
+    }
+    
+    async fn run(&self, request: ScraperRequest, owner: Option<Uuid>) -> ScraperResponse {
+        // 追踪跨度挂在所属工作流节点的跨度之下
+        let span = tracing::info_span!(
+            "scraper_action",
+            context_id = request.context_id.as_deref().unwrap_or(""),
+            execution_id = %owner.map(|id| id.to_string()).unwrap_or_default(),
+        );
+        self.run_action(request, owner).instrument(span).await
+    }
+
+    async fn run_action(&self, mut request: ScraperRequest, owner: Option<Uuid>) -> ScraperResponse {
+        let resolution = match request.action.selector_mut() {
+            Some((selector, find_by)) => {
+                let resolution = self.resolve_selector(
+                    request.context_id.as_deref(),
+                    selector,
+                    find_by,
+                    &request.config,
+                ).await;
+                if let Some(used) = resolution.as_ref().and_then(|r| r.used.clone()) {
+                    *selector = used;
+                }
+                resolution
+            }
+            None => None,
+        };
+        
+        let mut response = self.dispatch(request, owner).await;
+        response.selector = resolution;
+        response
+    }
+    
+    /// 主选择器未匹配时依次尝试已批准的修复和备用选择器，仍未匹配且启用 selectorRepair 时请求修复建议；
+    /// 未配置任何替代方式或主选择器匹配时返回 None
+    async fn resolve_selector(
+        &self,
+        context_id: Option<&str>,
+        selector: &str,
+        find_by: &SelectorType,
+        config: &Value,
+    ) -> Option<SelectorResolution> {
+        let fallbacks = fallback_selectors(config);
+        let repair = self.repairer.is_some()
+            && config.get("selectorRepair").and_then(|v| v.as_bool()).unwrap_or(false);
+        
+        // 上下文无效时不做处理，由动作本身报告错误
+        let id = context_id?;
+        let static_page = self.static_pages.read().await.get(id).cloned();
+        let (target, url) = match static_page {
+            Some(page) => {
+                let url = page.url.clone();
+                (TargetPage::Static(page), url)
+            }
+            None => {
+                let ctx_id = BrowserContextId::from_string(id).ok()?;
+                let url = self.browser_pool.page_state(&ctx_id).await.ok()?.0;
+                (TargetPage::Browser(ctx_id), url)
+            }
+        };
+        let known = self.repairs.for_selector(&url, selector).await;
+        if fallbacks.is_empty() && !repair && known.is_empty() {
+            return None;
+        }
+        if self.selector_matches(&target, selector, find_by).await {
+            return None;
+        }
+        
+        let mut resolution = SelectorResolution::new(selector);
+        let approved = known.iter()
+            .filter(|r| r.status == RepairStatus::Approved)
+            .map(|r| (r.proposed.clone(), SelectorSource::ApprovedRepair, Some(r.id)));
+        let fallbacks = fallbacks.into_iter().map(|s| (s, SelectorSource::Fallback, None));
+        for (candidate, source, repair_id) in approved.chain(fallbacks) {
+            if candidate == selector || resolution.tried.contains(&candidate) {
+                continue;
+            }
+            if self.selector_matches(&target, &candidate, find_by).await {
+                resolution.resolve(candidate, source, repair_id);
+                return Some(resolution);
+            }
+            resolution.tried.push(candidate);
+        }
+        if !repair {
+            return Some(resolution);
+        }
+        
+        // 待审核的建议仍然匹配时直接使用，不重复请求
+        for pending in known.iter().filter(|r| r.status == RepairStatus::Pending) {
+            if self.selector_matches(&target, &pending.proposed, find_by).await {
+                resolution.resolve(pending.proposed.clone(), SelectorSource::Repair, Some(pending.id));
+                return Some(resolution);
+            }
+        }
+        
+        let html = match &target {
+            TargetPage::Static(page) => page.html.clone(),
+            // 在实际实现中，这里会调用 page.content() 获取当前 DOM
+            TargetPage::Browser(_) => return Some(resolution),
+        };
+        let request = RepairRequest {
+            url,
+            selector: selector.to_string(),
+            find_by: find_by.clone(),
+            tried: resolution.tried.clone(),
+            outline: page_outline(&html),
+        };
+        let repairer = self.repairer.as_ref()?;
+        match repairer.propose(&request).await {
+            Ok(proposal) => {
+                let rejected = known.iter()
+                    .any(|r| r.status == RepairStatus::Rejected && r.proposed == proposal.selector);
+                if rejected {
+                    tracing::debug!("Discarding rejected selector repair {} for {}", proposal.selector, selector);
+                    return Some(resolution);
+                }
+                let verified = self.selector_matches(&target, &proposal.selector, find_by).await;
+                let recorded = self.repairs.record(SelectorRepair::new(&request, proposal, verified)).await;
+                if verified {
+                    resolution.resolve(recorded.proposed, SelectorSource::Repair, Some(recorded.id));
+                } else {
+                    resolution.tried.push(recorded.proposed);
+                }
+            }
+            Err(e) => tracing::warn!("Selector repair for {} on {} failed: {}", selector, request.url, e),
+        }
+        Some(resolution)
+    }
+    
+    /// 选择器能否在页面上匹配到元素；无效的选择器视为不匹配
+    async fn selector_matches(&self, target: &TargetPage, selector: &str, find_by: &SelectorType) -> bool {
+        match target {
+            TargetPage::Static(page) => page.outer_html(selector, find_by)
+                .map(|matched| !matched.is_empty())
+                .unwrap_or(false),
+            TargetPage::Browser(id) => {
+                let probe = ContextProbe { pool: &self.browser_pool, id };
+                matches!(probe.element(selector, find_by).await, Ok(Some(_)))
+            }
+        }
+    }
+    
+    async fn dispatch(&self, request: ScraperRequest, owner: Option<Uuid>) -> ScraperResponse {
+        // HTTP 模式打开的页面直接解析静态 HTML
+        if let Some(id) = request.context_id.as_deref() {
+            let page = self.static_pages.read().await.get(id).cloned();
+            if let Some(page) = page {
+                return self.execute_static(id, page, request.action, &request.config).await;
+            }
+        }
+        
         match request.action {
             ScraperAction::OpenPage { url } => {
-                self.execute_open_page(&url, &request.config).await
+                self.execute_open_page(&url, &request.config, owner).await
             }
             ScraperAction::ClosePage => {
                 self.execute_close_page(request.context_id.as_deref()).await
@@ -163,12 +511,14 @@ impl ScraperExecutor {
                     &request.config,
                 ).await
             }
-            ScraperAction::Wait { selector, condition, find_by } => {
+            ScraperAction::Wait { selector, condition, find_by, strategies, mode } => {
                 self.execute_wait(
                     request.context_id.as_deref(),
                     &selector,
                     condition,
                     find_by,
+                    strategies,
+                    mode,
                     &request.config,
                 ).await
             }
@@ -180,6 +530,36 @@ impl ScraperExecutor {
                     &request.config,
                 ).await
             }
+            ScraperAction::Tap { selector, find_by } => {
+                self.execute_tap(
+                    request.context_id.as_deref(),
+                    &selector,
+                    find_by,
+                    &request.config,
+                ).await
+            }
+            ScraperAction::Swipe { selector, direction, distance } => {
+                self.execute_swipe(
+                    request.context_id.as_deref(),
+                    selector.as_deref(),
+                    direction,
+                    distance,
+                ).await
+            }
+            ScraperAction::Extract { fields } => {
+                self.execute_extract(request.context_id.as_deref(), &fields).await
+            }
+            ScraperAction::FillForm { fields, submit } => {
+                self.execute_fill_form(
+                    request.context_id.as_deref(),
+                    &fields,
+                    submit.as_ref(),
+                    &request.config,
+                ).await
+            }
+            ScraperAction::ExportHar => {
+                self.execute_export_har(request.context_id.as_deref()).await
+            }
             ScraperAction::ExecuteScript { code } => {
                 self.execute_script(
                     request.context_id.as_deref(),
@@ -206,12 +586,34 @@ impl ScraperExecutor {
     }
 
     /// 执行打开网页
-    async fn execute_open_page(&self, url: &str, config: &Value) -> ScraperResponse {
+    async fn execute_open_page(&self, url: &str, config: &Value, owner: Option<Uuid>) -> ScraperResponse {
         // 验证 URL
         if url.is_empty() {
             return ScraperResponse::error(None, ScraperError::InvalidUrl("URL 不能为空".to_string()));
         }
         
+        // 合规检查：robots.txt 与域名限速，许可在导航完成前一直持有
+        let _permit = match &self.policy {
+            Some(policy) => match policy.acquire(url).await {
+                Ok(permit) => Some(permit),
+                Err(e) => return ScraperResponse::error(None, e),
+            },
+            None => None,
+        };
+        
+        // 解析设备配置：字符串为内置设备名，对象为自定义设备
+        let device = match config.get("device") {
+            None | Some(Value::Null) => None,
+            Some(Value::String(name)) => match DeviceProfile::by_name(name) {
+                Some(profile) => Some(profile),
+                None => return ScraperResponse::error(None, ScraperError::UnknownDevice(name.clone())),
+            },
+            Some(custom) => match serde_json::from_value::<DeviceProfile>(custom.clone()) {
+                Ok(profile) => Some(profile),
+                Err(e) => return ScraperResponse::error(None, ScraperError::UnknownDevice(e.to_string())),
+            },
+        };
+        
         // 解析配置
         let browser_config = BrowserContextConfig {
             headless: config.get("headless").and_then(|v| v.as_bool()).unwrap_or(true),
@@ -223,10 +625,33 @@ impl ScraperExecutor {
                 })
             }),
             timeout: config.get("timeout").and_then(|v| v.as_u64()).unwrap_or(30000),
+            device,
+            record_har: config.get("recordHar").and_then(|v| v.as_bool()).unwrap_or(false),
+            auto_wait: AutoWait::from_config(config.get("autoWait")),
         };
+        let device_name = browser_config.device.as_ref().map(|d| d.name.clone());
+        
+        // 选择获取模式：设备模拟和 HAR 记录依赖浏览器
+        let fetch_mode = config.get("fetchMode")
+            .and_then(|v| serde_json::from_value::<FetchMode>(v.clone()).ok())
+            .unwrap_or_default();
+        let browser_only = browser_config.device.is_some() || browser_config.record_har;
+        match fetch_mode {
+            FetchMode::Http => {
+                return self.open_static_page(url, &browser_config, owner).await
+                    .unwrap_or_else(|e| ScraperResponse::failed(None, e));
+            }
+            FetchMode::Auto if !browser_only => {
+                match self.open_static_page(url, &browser_config, owner).await {
+                    Ok(response) => return response,
+                    Err(e) => tracing::debug!("HTTP mode not suitable for {}, falling back to browser: {}", url, e),
+                }
+            }
+            _ => {}
+        }
         
         // 创建浏览器上下文
-        match self.browser_pool.create_context(browser_config).await {
+        match self.browser_pool.create_owned_context(browser_config, owner).await {
             Ok(context_id) => {
                 // 在实际实现中，这里会导航到 URL
                 // 模拟导航成功
@@ -236,15 +661,119 @@ impl ScraperExecutor {
                     "Page Title".to_string(), // 实际实现会获取真实标题
                 ).await;
                 
+                // 实际实现中由 page.on('requestfinished') 回调记录每个请求
+                let _ = self.browser_pool.start_har_page(&context_id, "Page Title").await;
+                let mut document = HarEntry::get(url, 200, "text/html", 0.0);
+                document.resource_type = Some("document".to_string());
+                let _ = self.browser_pool.record_network_entry(&context_id, document).await;
+                
                 ScraperResponse::success(
                     Some(context_id.to_string()),
-                    serde_json::json!({
-                        "title": "Page Title",
-                        "url": url,
-                    }),
+                    ScraperData::Navigation {
+                        title: "Page Title".to_string(),
+                        url: url.to_string(),
+                        mode: FetchMode::Browser,
+                        device: device_name,
+                        status: None,
+                    },
                 )
             }
-            Err(e) => ScraperResponse::error(None, e),
+            Err(e) => ScraperResponse::failed(None, e),
+        }
+    }
+    
+    /// 以 HTTP 模式打开页面；页面需要浏览器渲染时返回 BrowserRequired
+    async fn open_static_page(
+        &self,
+        url: &str,
+        config: &BrowserContextConfig,
+        owner: Option<Uuid>,
+    ) -> Result<ScraperResponse, ScraperError> {
+        let fetcher = HttpFetcher::new(config.timeout, config.user_agent.as_deref());
+        let page = fetcher.fetch(url).await?;
+        if page.needs_browser() {
+            return Err(ScraperError::BrowserRequired(format!("{} 需要 JavaScript 渲染", url)));
+        }
+        Ok(self.register_static_page(page, owner).await)
+    }
+    
+    /// 登记静态页面并返回打开结果
+    async fn register_static_page(&self, page: StaticPage, owner: Option<Uuid>) -> ScraperResponse {
+        let context_id = BrowserContextId::new().to_string();
+        let data = ScraperData::Navigation {
+            title: page.title(),
+            url: page.url.clone(),
+            mode: FetchMode::Http,
+            device: None,
+            status: Some(page.status),
+        };
+        self.static_pages.write().await.insert(context_id.clone(), page);
+        if let Some(owner) = owner {
+            self.static_owners.write().await.insert(context_id.clone(), owner);
+        }
+        ScraperResponse::success(Some(context_id), data)
+    }
+    
+    /// 在静态页面上执行动作
+    async fn execute_static(
+        &self,
+        context_id: &str,
+        page: StaticPage,
+        action: ScraperAction,
+        config: &Value,
+    ) -> ScraperResponse {
+        let ctx = Some(context_id.to_string());
+        let result = match action {
+            ScraperAction::ClosePage => {
+                self.static_pages.write().await.remove(context_id);
+                self.static_owners.write().await.remove(context_id);
+                return ScraperResponse::success(None, ScraperData::Closed { closed: true, har: None });
+            }
+            ScraperAction::GetText { selector, find_by } => {
+                let multiple = config.get("multiple").and_then(|v| v.as_bool()).unwrap_or(false);
+                let include_html = config.get("includeHtml").and_then(|v| v.as_bool()).unwrap_or(false);
+                page.get_text(&selector, &find_by, include_html).map(|results| {
+                    if multiple {
+                        let texts: Vec<String> = results.into_iter().map(|r| r.text).collect();
+                        ScraperData::Texts { count: texts.len(), texts }
+                    } else {
+                        match results.into_iter().next() {
+                            Some(r) => ScraperData::Text { text: r.text, html: r.html },
+                            None => ScraperData::Text { text: String::new(), html: None },
+                        }
+                    }
+                })
+            }
+            ScraperAction::GetAttribute { selector, attribute, find_by } => {
+                let multiple = config.get("multiple").and_then(|v| v.as_bool()).unwrap_or(false);
+                page.get_attribute(&selector, &attribute, &find_by).map(|values| {
+                    if multiple {
+                        ScraperData::Attributes { count: values.len(), values }
+                    } else {
+                        ScraperData::Attribute { value: values.into_iter().next().flatten() }
+                    }
+                })
+            }
+            ScraperAction::LoopElements { selector, find_by } => {
+                let max_iterations = config.get("maxIterations")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(100) as usize;
+                page.outer_html(&selector, &find_by).map(|items| {
+                    let elements: Vec<ElementHtml> = items.into_iter()
+                        .take(max_iterations)
+                        .enumerate()
+                        .map(|(index, html)| ElementHtml { index, html })
+                        .collect();
+                    ScraperData::Elements { total: elements.len(), elements }
+                })
+            }
+            ScraperAction::Extract { fields } => page.extract(&fields).map(ScraperData::Fields),
+            other => Err(ScraperError::BrowserRequired(format!("{:?}", other))),
+        };
+        
+        match result {
+            Ok(data) => ScraperResponse::success(ctx, data),
+            Err(e) => ScraperResponse::failed(ctx, e),
         }
     }
     
@@ -253,7 +782,10 @@ impl ScraperExecutor {
         match self.validate_context_id(context_id) {
             Ok(ctx_id) => {
                 match self.browser_pool.close_context(&ctx_id).await {
-                    Ok(_) => ScraperResponse::success(None, serde_json::json!({ "closed": true })),
+                    Ok(har) => ScraperResponse::success(
+                        None,
+                        ScraperData::Closed { closed: true, har: har.as_ref().map(HarArtifact::new) },
+                    ),
                     Err(e) => ScraperResponse::error(context_id.map(String::from), e),
                 }
             }
@@ -261,11 +793,63 @@ impl ScraperExecutor {
         }
     }
     
+    /// 执行字段批量提取
+    async fn execute_extract(
+        &self,
+        context_id: Option<&str>,
+        fields: &[ExtractField],
+    ) -> ScraperResponse {
+        let ctx_id = match self.validate_context_id(context_id) {
+            Ok(id) => id,
+            Err(e) => return ScraperResponse::error(context_id.map(String::from), e),
+        };
+        
+        if let Err(e) = self.browser_pool.ensure_alive(&ctx_id).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
+        }
+        
+        // 在实际实现中，这里会在页面中逐个字段执行选择器查询
+        // 模拟返回结果
+        let data = fields.iter()
+            .map(|f| {
+                let value = match (&f.attribute, f.multiple) {
+                    (None, true) => serde_json::json!(["Sample text 1", "Sample text 2"]),
+                    (None, false) => serde_json::json!("Sample text 1"),
+                    (Some(_), true) => serde_json::json!(["https://example.com"]),
+                    (Some(_), false) => serde_json::json!("https://example.com"),
+                };
+                (f.name.clone(), value)
+            })
+            .collect();
+        
+        ScraperResponse::success(context_id.map(String::from), ScraperData::Fields(data))
+    }
+    
+    /// 导出当前上下文的 HAR，不关闭页面
+    async fn execute_export_har(&self, context_id: Option<&str>) -> ScraperResponse {
+        let ctx_id = match self.validate_context_id(context_id) {
+            Ok(id) => id,
+            Err(e) => return ScraperResponse::error(context_id.map(String::from), e),
+        };
+        
+        match self.browser_pool.export_har(&ctx_id).await {
+            Ok(Some(har)) => ScraperResponse::success(
+                context_id.map(String::from),
+                ScraperData::Har(HarArtifact::new(&har)),
+            ),
+            Ok(None) => ScraperResponse::error(
+                context_id.map(String::from),
+                ScraperError::Internal("该上下文未开启 HAR 记录 (recordHar)".to_string()),
+            ),
+            Err(e) => ScraperResponse::error(context_id.map(String::from), e),
+        }
+    }
+    
     /// 执行获取文本
     async fn execute_get_text(
         &self,
         context_id: Option<&str>,
-        selector: &str,
+        _selector: &str,
         _find_by: SelectorType,
         config: &Value,
     ) -> ScraperResponse {
@@ -275,11 +859,8 @@ impl ScraperExecutor {
         };
         
         // 验证上下文有效性
-        if !self.browser_pool.is_context_valid(&ctx_id).await {
-            return ScraperResponse::error(
-                context_id.map(String::from),
-                ScraperError::ContextInvalid(ctx_id.to_string()),
-            );
+        if let Err(e) = self.browser_pool.ensure_alive(&ctx_id).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
         }
         
         let multiple = config.get("multiple").and_then(|v| v.as_bool()).unwrap_or(false);
@@ -290,14 +871,9 @@ impl ScraperExecutor {
         let texts = vec!["Sample text 1".to_string(), "Sample text 2".to_string()];
         
         let data = if multiple {
-            serde_json::json!({
-                "texts": texts,
-                "count": texts.len(),
-            })
+            ScraperData::Texts { count: texts.len(), texts }
         } else {
-            serde_json::json!({
-                "text": texts.first().unwrap_or(&String::new()),
-            })
+            ScraperData::Text { text: texts.into_iter().next().unwrap_or_default(), html: None }
         };
         
         ScraperResponse::success(context_id.map(String::from), data)
@@ -307,8 +883,8 @@ impl ScraperExecutor {
     async fn execute_get_attribute(
         &self,
         context_id: Option<&str>,
-        selector: &str,
-        attribute: &str,
+        _selector: &str,
+        _attribute: &str,
         _find_by: SelectorType,
         config: &Value,
     ) -> ScraperResponse {
@@ -317,27 +893,19 @@ impl ScraperExecutor {
             Err(e) => return ScraperResponse::error(context_id.map(String::from), e),
         };
         
-        if !self.browser_pool.is_context_valid(&ctx_id).await {
-            return ScraperResponse::error(
-                context_id.map(String::from),
-                ScraperError::ContextInvalid(ctx_id.to_string()),
-            );
+        if let Err(e) = self.browser_pool.ensure_alive(&ctx_id).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
         }
         
         let multiple = config.get("multiple").and_then(|v| v.as_bool()).unwrap_or(false);
         
         // 模拟返回结果
-        let values = vec!["https://example.com".to_string()];
+        let values = vec![Some("https://example.com".to_string())];
         
         let data = if multiple {
-            serde_json::json!({
-                "values": values,
-                "count": values.len(),
-            })
+            ScraperData::Attributes { count: values.len(), values }
         } else {
-            serde_json::json!({
-                "value": values.first(),
-            })
+            ScraperData::Attribute { value: values.into_iter().next().flatten() }
         };
         
         ScraperResponse::success(context_id.map(String::from), data)
@@ -348,7 +916,7 @@ impl ScraperExecutor {
         &self,
         context_id: Option<&str>,
         selector: &str,
-        _find_by: SelectorType,
+        find_by: SelectorType,
         config: &Value,
     ) -> ScraperResponse {
         let ctx_id = match self.validate_context_id(context_id) {
@@ -356,11 +924,12 @@ impl ScraperExecutor {
             Err(e) => return ScraperResponse::error(context_id.map(String::from), e),
         };
         
-        if !self.browser_pool.is_context_valid(&ctx_id).await {
-            return ScraperResponse::error(
-                context_id.map(String::from),
-                ScraperError::ContextInvalid(ctx_id.to_string()),
-            );
+        if let Err(e) = self.browser_pool.ensure_alive(&ctx_id).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
+        }
+        
+        if let Err(e) = self.auto_wait(&ctx_id, selector, &find_by, config).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
         }
         
         let _wait_for_navigation = config.get("waitForNavigation")
@@ -371,7 +940,7 @@ impl ScraperExecutor {
         // 模拟成功
         ScraperResponse::success(
             context_id.map(String::from),
-            serde_json::json!({ "clicked": true }),
+            ScraperData::action(serde_json::json!({ "clicked": true })),
         )
     }
     
@@ -381,7 +950,7 @@ impl ScraperExecutor {
         context_id: Option<&str>,
         selector: &str,
         value: &str,
-        _find_by: SelectorType,
+        find_by: SelectorType,
         config: &Value,
     ) -> ScraperResponse {
         let ctx_id = match self.validate_context_id(context_id) {
@@ -389,11 +958,12 @@ impl ScraperExecutor {
             Err(e) => return ScraperResponse::error(context_id.map(String::from), e),
         };
         
-        if !self.browser_pool.is_context_valid(&ctx_id).await {
-            return ScraperResponse::error(
-                context_id.map(String::from),
-                ScraperError::ContextInvalid(ctx_id.to_string()),
-            );
+        if let Err(e) = self.browser_pool.ensure_alive(&ctx_id).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
+        }
+        
+        if let Err(e) = self.auto_wait(&ctx_id, selector, &find_by, config).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
         }
         
         let _clear_before = config.get("clearBefore")
@@ -406,15 +976,16 @@ impl ScraperExecutor {
         // 模拟成功
         ScraperResponse::success(
             context_id.map(String::from),
-            serde_json::json!({ "typed": true, "value": value }),
+            ScraperData::action(serde_json::json!({ "typed": true, "value": value })),
         )
     }
     
-    /// 执行滚动
-    async fn execute_scroll(
+    /// 按顺序填写表单字段，可选提交并等待导航
+    async fn execute_fill_form(
         &self,
         context_id: Option<&str>,
-        mode: ScrollMode,
+        fields: &[FormField],
+        submit: Option<&FormSubmit>,
         config: &Value,
     ) -> ScraperResponse {
         let ctx_id = match self.validate_context_id(context_id) {
@@ -422,13 +993,65 @@ impl ScraperExecutor {
             Err(e) => return ScraperResponse::error(context_id.map(String::from), e),
         };
         
-        if !self.browser_pool.is_context_valid(&ctx_id).await {
-            return ScraperResponse::error(
+        if let Err(e) = self.browser_pool.ensure_alive(&ctx_id).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
+        }
+        
+        if fields.is_empty() {
+            return ScraperResponse::failed(
                 context_id.map(String::from),
-                ScraperError::ContextInvalid(ctx_id.to_string()),
+                ScraperError::FormFieldInvalid("表单字段列表为空".to_string()),
             );
         }
         
+        let artifacts = self.artifacts.read().await;
+        // 先校验全部字段，避免填写到一半才失败
+        for (index, field) in fields.iter().enumerate() {
+            if let Err(e) = field.validate(index, &artifacts) {
+                return ScraperResponse::failed(context_id.map(String::from), e);
+            }
+        }
+        for field in fields {
+            if let Err(e) = self.auto_wait(&ctx_id, &field.selector, &field.find_by, config).await {
+                return ScraperResponse::failed(context_id.map(String::from), e);
+            }
+        }
+        
+        // 在实际实现中，这里会按字段类型调用 fill / selectOption / check / setInputFiles
+        let filled: Vec<Value> = fields.iter().map(|f| f.summary(&artifacts)).collect();
+        
+        let submitted = submit.map(|s| serde_json::json!({
+            "submitted": true,
+            "via": s.selector.as_deref().unwrap_or("enter"),
+            "waitedForNavigation": s.wait_for_navigation,
+        }));
+        
+        ScraperResponse::success(
+            context_id.map(String::from),
+            ScraperData::action(serde_json::json!({
+                "filled": filled,
+                "count": filled.len(),
+                "submit": submitted,
+            })),
+        )
+    }
+    
+    /// 执行滚动
+    async fn execute_scroll(
+        &self,
+        context_id: Option<&str>,
+        mode: ScrollMode,
+        _config: &Value,
+    ) -> ScraperResponse {
+        let ctx_id = match self.validate_context_id(context_id) {
+            Ok(id) => id,
+            Err(e) => return ScraperResponse::error(context_id.map(String::from), e),
+        };
+        
+        if let Err(e) = self.browser_pool.ensure_alive(&ctx_id).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
+        }
+        
         // 模拟成功
         let scroll_info = match mode {
             ScrollMode::Pixels { x, y } => serde_json::json!({ "scrolledX": x, "scrolledY": y }),
@@ -437,16 +1060,19 @@ impl ScraperExecutor {
             ScrollMode::Top => serde_json::json!({ "scrolledTo": "top" }),
         };
         
-        ScraperResponse::success(context_id.map(String::from), scroll_info)
+        ScraperResponse::success(context_id.map(String::from), ScraperData::action(scroll_info))
     }
     
     /// 执行等待
+    #[allow(clippy::too_many_arguments)]
     async fn execute_wait(
         &self,
         context_id: Option<&str>,
         selector: &str,
         condition: WaitCondition,
-        _find_by: SelectorType,
+        find_by: SelectorType,
+        strategies: Vec<WaitStrategy>,
+        mode: WaitMode,
         config: &Value,
     ) -> ScraperResponse {
         let ctx_id = match self.validate_context_id(context_id) {
@@ -454,32 +1080,74 @@ impl ScraperExecutor {
             Err(e) => return ScraperResponse::error(context_id.map(String::from), e),
         };
         
-        if !self.browser_pool.is_context_valid(&ctx_id).await {
-            return ScraperResponse::error(
-                context_id.map(String::from),
-                ScraperError::ContextInvalid(ctx_id.to_string()),
-            );
+        if let Err(e) = self.browser_pool.ensure_alive(&ctx_id).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
         }
         
-        let _timeout = config.get("timeout")
+        let timeout = config.get("timeout")
             .and_then(|v| v.as_u64())
             .unwrap_or(30000);
         
-        // 模拟成功
-        ScraperResponse::success(
-            context_id.map(String::from),
-            serde_json::json!({
-                "found": true,
-                "condition": format!("{:?}", condition),
-            }),
-        )
+        let mut all = Vec::with_capacity(strategies.len() + 1);
+        if !selector.is_empty() {
+            all.push(WaitStrategy::Selector {
+                selector: selector.to_string(),
+                condition: condition.clone(),
+                find_by,
+                timeout_ms: None,
+            });
+        }
+        all.extend(strategies);
+        if all.is_empty() {
+            return ScraperResponse::error(
+                context_id.map(String::from),
+                ScraperError::InvalidSelector("等待条件为空".to_string()),
+            );
+        }
+        
+        let mut plan = WaitPlan::new(all, mode, timeout);
+        if let Some(interval) = config.get("pollInterval").and_then(|v| v.as_u64()) {
+            plan.poll_interval_ms = interval.max(10);
+        }
+        
+        let probe = ContextProbe { pool: &self.browser_pool, id: &ctx_id };
+        match wait_for(&probe, &plan).await {
+            Ok(outcomes) => ScraperResponse::success(
+                context_id.map(String::from),
+                ScraperData::action(serde_json::json!({
+                    "found": true,
+                    "condition": format!("{:?}", condition),
+                    "mode": mode,
+                    "strategies": outcomes,
+                })),
+            ),
+            Err(e) => ScraperResponse::failed(context_id.map(String::from), e),
+        }
+    }
+    
+    /// 交互动作前的自动等待；上下文未启用或动作配置 autoWait 为 false 时跳过
+    async fn auto_wait(
+        &self,
+        ctx_id: &BrowserContextId,
+        selector: &str,
+        find_by: &SelectorType,
+        config: &Value,
+    ) -> Result<(), ScraperError> {
+        if config.get("autoWait").and_then(|v| v.as_bool()) == Some(false) {
+            return Ok(());
+        }
+        let Some(auto_wait) = self.browser_pool.get_config(ctx_id).await?.auto_wait else {
+            return Ok(());
+        };
+        let probe = ContextProbe { pool: &self.browser_pool, id: ctx_id };
+        wait_for(&probe, &auto_wait.plan(selector, find_by)).await.map(|_| ())
     }
 
     /// 执行循环元素
     async fn execute_loop_elements(
         &self,
         context_id: Option<&str>,
-        selector: &str,
+        _selector: &str,
         _find_by: SelectorType,
         config: &Value,
     ) -> ScraperResponse {
@@ -488,11 +1156,8 @@ impl ScraperExecutor {
             Err(e) => return ScraperResponse::error(context_id.map(String::from), e),
         };
         
-        if !self.browser_pool.is_context_valid(&ctx_id).await {
-            return ScraperResponse::error(
-                context_id.map(String::from),
-                ScraperError::ContextInvalid(ctx_id.to_string()),
-            );
+        if let Err(e) = self.browser_pool.ensure_alive(&ctx_id).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
         }
         
         let max_iterations = config.get("maxIterations")
@@ -500,27 +1165,95 @@ impl ScraperExecutor {
             .unwrap_or(100) as usize;
         
         // 模拟返回元素列表
-        let elements: Vec<serde_json::Value> = (0..3.min(max_iterations))
-            .map(|i| serde_json::json!({
-                "index": i,
-                "html": format!("<div>Element {}</div>", i),
-            }))
+        let elements: Vec<ElementHtml> = (0..3.min(max_iterations))
+            .map(|index| ElementHtml {
+                index,
+                html: format!("<div>Element {}</div>", index),
+            })
             .collect();
         
         ScraperResponse::success(
             context_id.map(String::from),
-            serde_json::json!({
-                "elements": elements,
-                "total": elements.len(),
-            }),
+            ScraperData::Elements { total: elements.len(), elements },
+        )
+    }
+    
+    /// 执行轻触
+    async fn execute_tap(
+        &self,
+        context_id: Option<&str>,
+        selector: &str,
+        find_by: SelectorType,
+        config: &Value,
+    ) -> ScraperResponse {
+        let ctx_id = match self.validate_context_id(context_id) {
+            Ok(id) => id,
+            Err(e) => return ScraperResponse::error(context_id.map(String::from), e),
+        };
+        
+        if let Err(e) = self.ensure_touch(&ctx_id).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
+        }
+        
+        if let Err(e) = self.auto_wait(&ctx_id, selector, &find_by, config).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
+        }
+        
+        let _wait_for_navigation = config.get("waitForNavigation")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        
+        // 在实际实现中，这里会调用 locator.tap()
+        ScraperResponse::success(
+            context_id.map(String::from),
+            ScraperData::action(serde_json::json!({ "tapped": true, "selector": selector })),
         )
     }
     
+    /// 执行滑动
+    async fn execute_swipe(
+        &self,
+        context_id: Option<&str>,
+        selector: Option<&str>,
+        direction: SwipeDirection,
+        distance: u32,
+    ) -> ScraperResponse {
+        let ctx_id = match self.validate_context_id(context_id) {
+            Ok(id) => id,
+            Err(e) => return ScraperResponse::error(context_id.map(String::from), e),
+        };
+        
+        if let Err(e) = self.ensure_touch(&ctx_id).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
+        }
+        
+        // 在实际实现中，这里会通过 touchscreen 派发 touchstart/touchmove/touchend
+        ScraperResponse::success(
+            context_id.map(String::from),
+            ScraperData::action(serde_json::json!({
+                "swiped": true,
+                "selector": selector,
+                "direction": direction,
+                "distance": distance,
+            })),
+        )
+    }
+    
+    /// 校验上下文有效且启用了触摸
+    async fn ensure_touch(&self, ctx_id: &BrowserContextId) -> Result<(), ScraperError> {
+        self.browser_pool.ensure_alive(ctx_id).await?;
+        let config = self.browser_pool.get_config(ctx_id).await?;
+        if !config.has_touch() {
+            return Err(ScraperError::TouchNotSupported(ctx_id.to_string()));
+        }
+        Ok(())
+    }
+    
     /// 执行脚本
     async fn execute_script(
         &self,
         context_id: Option<&str>,
-        code: &str,
+        _code: &str,
         config: &Value,
     ) -> ScraperResponse {
         let ctx_id = match self.validate_context_id(context_id) {
@@ -528,11 +1261,8 @@ impl ScraperExecutor {
             Err(e) => return ScraperResponse::error(context_id.map(String::from), e),
         };
         
-        if !self.browser_pool.is_context_valid(&ctx_id).await {
-            return ScraperResponse::error(
-                context_id.map(String::from),
-                ScraperError::ContextInvalid(ctx_id.to_string()),
-            );
+        if let Err(e) = self.browser_pool.ensure_alive(&ctx_id).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
         }
         
         let _timeout = config.get("timeout")
@@ -543,10 +1273,10 @@ impl ScraperExecutor {
         // 模拟返回结果
         ScraperResponse::success(
             context_id.map(String::from),
-            serde_json::json!({
-                "result": "Script executed successfully",
-                "logs": [],
-            }),
+            ScraperData::Script {
+                result: serde_json::json!("Script executed successfully"),
+                logs: Vec::new(),
+            },
         )
     }
     
@@ -562,33 +1292,69 @@ impl ScraperExecutor {
             Err(e) => return ScraperResponse::error(context_id.map(String::from), e),
         };
         
-        if !self.browser_pool.is_context_valid(&ctx_id).await {
-            return ScraperResponse::error(
-                context_id.map(String::from),
-                ScraperError::ContextInvalid(ctx_id.to_string()),
-            );
+        if let Err(e) = self.browser_pool.ensure_alive(&ctx_id).await {
+            return ScraperResponse::failed(context_id.map(String::from), e);
         }
         
-        let format = config.get("format")
-            .and_then(|v| v.as_str())
-            .unwrap_or("png");
-        let _quality = config.get("quality")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(100) as u8;
+        let options = match ScreenshotOptions::from_config(config) {
+            Ok(options) => options,
+            Err(e) => return ScraperResponse::failed(context_id.map(String::from), e),
+        };
         
-        // 模拟返回截图数据
-        ScraperResponse::success(
-            context_id.map(String::from),
-            serde_json::json!({
-                "data": "base64_encoded_image_data_placeholder",
-                "width": 1280,
-                "height": 720,
-                "format": format,
-            }),
-        )
+        let viewport = match self.browser_pool.get_config(&ctx_id).await {
+            Ok(c) => c.effective_viewport().unwrap_or_default(),
+            Err(e) => return ScraperResponse::failed(context_id.map(String::from), e),
+        };
+        
+        // 在实际实现中：FullPage/Viewport 使用 page.screenshot，Clip 传入 clip 参数，
+        // Element 使用 locator.screenshot，均返回原始 PNG
+        let (width, height) = match &mode {
+            ScreenshotMode::FullPage => (viewport.width, viewport.height * 3),
+            ScreenshotMode::Viewport => (viewport.width, viewport.height),
+            ScreenshotMode::Element { .. } => (viewport.width.min(400), viewport.height.min(300)),
+            ScreenshotMode::Clip { width, height, .. } => {
+                if *width == 0 || *height == 0 {
+                    return ScraperResponse::failed(
+                        context_id.map(String::from),
+                        ScraperError::ScreenshotFailed("裁剪区域的宽高必须大于 0".to_string()),
+                    );
+                }
+                (*width, *height)
+            }
+        };
+        
+        // 模拟原始截图数据
+        let raw = match blank_png(width, height) {
+            Ok(raw) => raw,
+            Err(e) => return ScraperResponse::failed(context_id.map(String::from), e),
+        };
+        
+        match process_screenshot(&raw, &options) {
+            Ok(result) => ScraperResponse::success(
+                context_id.map(String::from),
+                ScraperData::Screenshot {
+                    data: result.data,
+                    width: result.width,
+                    height: result.height,
+                    format: result.format,
+                    mode,
+                },
+            ),
+            Err(e) => ScraperResponse::failed(context_id.map(String::from), e),
+        }
     }
 }
 
+/// 生成指定尺寸的空白 PNG，用于模拟浏览器截图
+fn blank_png(width: u32, height: u32) -> Result<Vec<u8>, ScraperError> {
+    let image = image::RgbaImage::from_pixel(width, height, image::Rgba([255, 255, 255, 255]));
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgba8(image)
+        .write_to(&mut buffer, image::ImageFormat::Png)
+        .map_err(|e| ScraperError::ScreenshotFailed(e.to_string()))?;
+    Ok(buffer.into_inner())
+}
+
 impl Default for ScraperExecutor {
     fn default() -> Self {
         ScraperExecutor::new(Arc::new(BrowserPool::default()))
@@ -598,6 +1364,40 @@ impl Default for ScraperExecutor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::repair::ProposedSelector;
+    
+    /// 响应数据的 JSON 形式，即工作流节点看到的输出
+    fn data(response: &ScraperResponse) -> Value {
+        serde_json::to_value(&response.data).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_typed_data_keeps_json_fields() {
+        let executor = ScraperExecutor::default();
+        let opened = executor.execute(ScraperRequest {
+            action: ScraperAction::OpenPage { url: "https://example.com".to_string() },
+            context_id: None,
+            config: serde_json::json!({}),
+        }).await;
+        assert!(matches!(opened.data, Some(ScraperData::Navigation { mode: FetchMode::Browser, .. })));
+        
+        let get_texts = |context_id: Option<String>| ScraperRequest {
+            action: ScraperAction::GetText { selector: "li".to_string(), find_by: SelectorType::CssSelector },
+            context_id,
+            config: serde_json::json!({ "multiple": true }),
+        };
+        let response = executor.execute(get_texts(opened.context_id.clone())).await;
+        let json = data(&response);
+        assert_eq!(json["dataType"], "texts");
+        assert_eq!(json["count"], 2);
+        assert_eq!(json["texts"][0], "Sample text 1");
+        let parsed: ScraperData = serde_json::from_value(json).unwrap();
+        assert!(matches!(parsed, ScraperData::Texts { count: 2, .. }));
+        
+        let failed = executor.execute(get_texts(None)).await;
+        assert!(!failed.success);
+        assert!(data(&failed).is_null());
+    }
     
     #[tokio::test]
     async fn test_open_page() {
@@ -639,4 +1439,425 @@ mod tests {
         let close_response = executor.execute(close_request).await;
         assert!(close_response.success);
     }
+    
+    #[tokio::test]
+    async fn test_close_execution_pages() {
+        let executor = ScraperExecutor::default();
+        let (execution_id, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let open = || ScraperRequest {
+            action: ScraperAction::OpenPage { url: "https://example.com".to_string() },
+            context_id: None,
+            config: serde_json::json!({ "fetchMode": "browser" }),
+        };
+        assert!(executor.execute_for(execution_id, open()).await.success);
+        assert!(executor.execute_for(other, open()).await.success);
+        assert!(executor.execute(open()).await.success);
+        executor.register_static_page(StaticPage::new("https://example.com/", 200, "<html></html>"), Some(execution_id)).await;
+        
+        assert_eq!(executor.close_execution(execution_id).await, 2);
+        assert!(executor.static_pages.read().await.is_empty());
+        assert_eq!(executor.browser_pool.context_count().await, 2);
+        assert_eq!(executor.close_execution(execution_id).await, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_open_page_with_device() {
+        let executor = ScraperExecutor::default();
+        let request = ScraperRequest {
+            action: ScraperAction::OpenPage { 
+                url: "https://example.com".to_string() 
+            },
+            context_id: None,
+            config: serde_json::json!({ "device": "iphone 14" }),
+        };
+        
+        let response = executor.execute(request).await;
+        assert!(response.success);
+        assert_eq!(data(&response)["device"], "iPhone 14");
+        
+        let request = ScraperRequest {
+            action: ScraperAction::OpenPage { 
+                url: "https://example.com".to_string() 
+            },
+            context_id: None,
+            config: serde_json::json!({ "device": "Nokia 3310" }),
+        };
+        let response = executor.execute(request).await;
+        assert!(!response.success);
+    }
+    
+    #[tokio::test]
+    async fn test_tap_requires_touch() {
+        let executor = ScraperExecutor::default();
+        
+        let open_request = ScraperRequest {
+            action: ScraperAction::OpenPage { 
+                url: "https://example.com".to_string() 
+            },
+            context_id: None,
+            config: serde_json::json!({}),
+        };
+        let desktop_id = executor.execute(open_request).await.context_id;
+        
+        let tap_request = ScraperRequest {
+            action: ScraperAction::Tap {
+                selector: "#menu".to_string(),
+                find_by: SelectorType::CssSelector,
+            },
+            context_id: desktop_id,
+            config: serde_json::json!({}),
+        };
+        assert!(!executor.execute(tap_request).await.success);
+        
+        let open_request = ScraperRequest {
+            action: ScraperAction::OpenPage { 
+                url: "https://example.com".to_string() 
+            },
+            context_id: None,
+            config: serde_json::json!({ "device": "Pixel 7" }),
+        };
+        let mobile_id = executor.execute(open_request).await.context_id;
+        
+        let swipe_request = ScraperRequest {
+            action: ScraperAction::Swipe {
+                selector: None,
+                direction: SwipeDirection::Left,
+                distance: 200,
+            },
+            context_id: mobile_id,
+            config: serde_json::json!({}),
+        };
+        assert!(executor.execute(swipe_request).await.success);
+    }
+    
+    #[tokio::test]
+    async fn test_har_capture() {
+        let executor = ScraperExecutor::default();
+        let open_request = ScraperRequest {
+            action: ScraperAction::OpenPage { 
+                url: "https://example.com/list?page=1".to_string() 
+            },
+            context_id: None,
+            config: serde_json::json!({ "recordHar": true }),
+        };
+        let context_id = executor.execute(open_request).await.context_id;
+        
+        let export_request = ScraperRequest {
+            action: ScraperAction::ExportHar,
+            context_id: context_id.clone(),
+            config: serde_json::json!({}),
+        };
+        let exported = executor.execute(export_request).await;
+        assert!(exported.success);
+        assert_eq!(data(&exported)["entryCount"], 1);
+        assert_eq!(data(&exported)["content"]["log"]["version"], "1.2");
+        
+        let close_request = ScraperRequest {
+            action: ScraperAction::ClosePage,
+            context_id,
+            config: serde_json::json!({}),
+        };
+        let closed = executor.execute(close_request).await;
+        assert!(closed.success);
+        assert_eq!(
+            data(&closed)["har"]["content"]["log"]["entries"][0]["request"]["queryString"][0]["name"],
+            "page",
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_crashed_context_is_recreated() {
+        let pool = Arc::new(BrowserPool::default());
+        let executor = ScraperExecutor::new(pool.clone());
+        let open_request = ScraperRequest {
+            action: ScraperAction::OpenPage { 
+                url: "https://example.com/feed".to_string() 
+            },
+            context_id: None,
+            config: serde_json::json!({}),
+        };
+        let context_id = executor.execute(open_request).await.context_id;
+        let ctx_id = BrowserContextId::from_string(context_id.as_deref().unwrap()).unwrap();
+        pool.mark_crashed(&ctx_id).await.unwrap();
+        
+        let click = || ScraperRequest {
+            action: ScraperAction::Click {
+                selector: "#more".to_string(),
+                find_by: SelectorType::CssSelector,
+            },
+            context_id: context_id.clone(),
+            config: serde_json::json!({}),
+        };
+        let crashed = executor.execute(click()).await;
+        assert!(!crashed.success);
+        assert!(crashed.retryable);
+        assert_eq!(crashed.error_code.as_deref(), Some("SCRAPER_016"));
+        
+        // 重试时上下文已被透明重建
+        assert!(executor.execute(click()).await.success);
+    }
+    
+    #[tokio::test]
+    async fn test_fill_form() {
+        let pool = Arc::new(BrowserPool::default());
+        let executor = ScraperExecutor::new(pool);
+        let context_id = executor.execute(ScraperRequest {
+            action: ScraperAction::OpenPage { 
+                url: "https://example.com/apply".to_string() 
+            },
+            context_id: None,
+            config: serde_json::json!({}),
+        }).await.context_id;
+        let resume = executor.store_artifact("resume.pdf", "application/pdf", vec![0x25, 0x50]).await;
+        
+        let fill = |fields: Value| ScraperRequest {
+            action: serde_json::from_value(serde_json::json!({
+                "type": "fillForm",
+                "fields": fields,
+                "submit": { "selector": "button[type=submit]" },
+            })).unwrap(),
+            context_id: context_id.clone(),
+            config: serde_json::json!({}),
+        };
+        
+        let response = executor.execute(fill(serde_json::json!([
+            { "selector": "#name", "type": "text", "value": "Alice" },
+            { "selector": "#country", "type": "select", "value": "de" },
+            { "selector": "#terms", "type": "checkbox", "checked": true },
+            { "selector": "input[name=plan]", "type": "radio", "value": "pro" },
+            { "selector": "#cv", "type": "file", "artifactId": resume },
+        ]))).await;
+        assert!(response.success);
+        assert_eq!(data(&response)["count"], 5);
+        assert_eq!(data(&response)["filled"][4]["value"], "resume.pdf");
+        assert_eq!(data(&response)["submit"]["waitedForNavigation"], true);
+        
+        let response = executor.execute(fill(serde_json::json!([
+            { "selector": "#name", "type": "text", "value": "Alice" },
+            { "selector": "#email", "type": "text", "value": " " },
+        ]))).await;
+        assert!(!response.success);
+        assert_eq!(response.error_code.as_deref(), Some("SCRAPER_019"));
+    }
+    
+    #[tokio::test]
+    async fn test_composite_wait() {
+        let pool = Arc::new(BrowserPool::default());
+        let executor = ScraperExecutor::new(pool.clone());
+        let context_id = executor.execute(ScraperRequest {
+            action: ScraperAction::OpenPage {
+                url: "https://example.com/orders".to_string()
+            },
+            context_id: None,
+            config: serde_json::json!({ "autoWait": { "timeoutMs": 200 } }),
+        }).await.context_id;
+        let ctx_id = BrowserContextId::from_string(context_id.as_deref().unwrap()).unwrap();
+        assert_eq!(pool.get_config(&ctx_id).await.unwrap().auto_wait.unwrap().timeout_ms, 200);
+
+        let wait = |action: Value| ScraperRequest {
+            action: serde_json::from_value(action).unwrap(),
+            context_id: context_id.clone(),
+            config: serde_json::json!({ "timeout": 300, "pollInterval": 10 }),
+        };
+
+        let response = executor.execute(wait(serde_json::json!({
+            "type": "wait",
+            "selector": "#orders",
+            "strategies": [
+                { "type": "urlMatches", "pattern": "https://example.com/*" },
+                { "type": "jsPredicate", "script": "window.ready === true" },
+            ],
+        }))).await;
+        assert!(response.success);
+        assert_eq!(data(&response)["strategies"].as_array().unwrap().len(), 3);
+
+        let response = executor.execute(wait(serde_json::json!({
+            "type": "wait",
+            "mode": "any",
+            "strategies": [
+                { "type": "urlMatches", "pattern": "/checkout", "timeoutMs": 50 },
+                { "type": "networkIdle", "idleMs": 20 },
+            ],
+        }))).await;
+        assert!(response.success);
+
+        let response = executor.execute(wait(serde_json::json!({
+            "type": "wait",
+            "strategies": [{ "type": "urlMatches", "pattern": "/checkout" }],
+        }))).await;
+        assert!(!response.success);
+        assert_eq!(response.error_code.as_deref(), Some("SCRAPER_002"));
+
+        let response = executor.execute(ScraperRequest {
+            action: ScraperAction::Click { selector: "#submit".to_string(), find_by: SelectorType::CssSelector },
+            context_id: context_id.clone(),
+            config: serde_json::json!({}),
+        }).await;
+        assert!(response.success);
+    }
+
+    #[tokio::test]
+    async fn test_static_page_actions() {
+        let executor = ScraperExecutor::default();
+        let body = "Static content that does not need a browser. ".repeat(10);
+        let page = StaticPage::new(
+            "https://example.com/",
+            200,
+            format!("<html><head><title>Home</title></head><body><h1>Hello</h1><a href=\"/next\">Next</a><p>{}</p></body></html>", body),
+        );
+        let opened = executor.register_static_page(page, None).await;
+        assert_eq!(data(&opened)["mode"], "http");
+        assert_eq!(executor.browser_pool.context_count().await, 0);
+        
+        let get_text = ScraperRequest {
+            action: ScraperAction::GetText {
+                selector: "h1".to_string(),
+                find_by: SelectorType::CssSelector,
+            },
+            context_id: opened.context_id.clone(),
+            config: serde_json::json!({}),
+        };
+        assert_eq!(data(&executor.execute(get_text).await)["text"], "Hello");
+        
+        let get_attr = ScraperRequest {
+            action: ScraperAction::GetAttribute {
+                selector: "a".to_string(),
+                attribute: "href".to_string(),
+                find_by: SelectorType::CssSelector,
+            },
+            context_id: opened.context_id.clone(),
+            config: serde_json::json!({}),
+        };
+        assert_eq!(data(&executor.execute(get_attr).await)["value"], "https://example.com/next");
+        
+        let click = ScraperRequest {
+            action: ScraperAction::Click {
+                selector: "a".to_string(),
+                find_by: SelectorType::CssSelector,
+            },
+            context_id: opened.context_id.clone(),
+            config: serde_json::json!({}),
+        };
+        let clicked = executor.execute(click).await;
+        assert!(!clicked.success);
+        assert_eq!(clicked.error_code.as_deref(), Some("SCRAPER_017"));
+        
+        let close = ScraperRequest {
+            action: ScraperAction::ClosePage,
+            context_id: opened.context_id,
+            config: serde_json::json!({}),
+        };
+        assert!(executor.execute(close).await.success);
+        assert!(executor.static_pages.read().await.is_empty());
+    }
+    
+    /// 固定返回同一建议的修复器
+    struct FixedRepairer(&'static str);
+    
+    #[async_trait::async_trait]
+    impl SelectorRepairer for FixedRepairer {
+        async fn propose(&self, request: &RepairRequest) -> Result<ProposedSelector, ScraperError> {
+            assert!(request.outline.contains("span.amount"));
+            Ok(ProposedSelector { selector: self.0.to_string(), reason: Some("price element".to_string()) })
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_fallback_selectors_and_repair() {
+        let executor = ScraperExecutor::default().with_selector_repair(Arc::new(FixedRepairer("span.amount")));
+        let page = StaticPage::new(
+            "https://shop.example.com/item/1",
+            200,
+            r#"<html><body><h1 class="title">Lamp</h1><div class="cost"><span class="amount">$20</span></div></body></html>"#,
+        );
+        let context_id = executor.register_static_page(page, None).await.context_id;
+        let get_text = |selector: &str, config: Value| ScraperRequest {
+            action: ScraperAction::GetText { selector: selector.to_string(), find_by: SelectorType::CssSelector },
+            context_id: context_id.clone(),
+            config,
+        };
+        
+        // 主选择器匹配时不附带选择结果
+        let response = executor.execute(get_text("h1", serde_json::json!({ "fallbackSelectors": [".name"] }))).await;
+        assert_eq!(data(&response)["text"], "Lamp");
+        assert!(response.selector.is_none());
+        
+        let response = executor.execute(get_text("h1.name", serde_json::json!({
+            "fallbackSelectors": [".product-name", "h1.title"],
+        }))).await;
+        assert_eq!(data(&response)["text"], "Lamp");
+        let resolution = response.selector.unwrap();
+        assert_eq!(resolution.used.as_deref(), Some("h1.title"));
+        assert_eq!(resolution.source, Some(SelectorSource::Fallback));
+        assert_eq!(resolution.tried, vec![".product-name"]);
+        
+        // 未启用 selectorRepair 时不请求修复
+        let response = executor.execute(get_text(".price", serde_json::json!({ "fallbackSelectors": [".cost-value"] }))).await;
+        assert_eq!(data(&response)["text"], "");
+        assert!(response.selector.unwrap().used.is_none());
+        assert!(executor.repairs().list(None).await.is_empty());
+        
+        let repair_config = serde_json::json!({ "fallbackSelectors": [".cost-value"], "selectorRepair": true });
+        let response = executor.execute(get_text(".price", repair_config.clone())).await;
+        assert_eq!(data(&response)["text"], "$20");
+        let resolution = response.selector.unwrap();
+        assert_eq!(resolution.source, Some(SelectorSource::Repair));
+        let repair = executor.repairs().get(resolution.repair_id.unwrap()).await.unwrap();
+        assert_eq!((repair.status, repair.verified, repair.host.as_str()), (RepairStatus::Pending, true, "shop.example.com"));
+        
+        // 批准后无需启用修复也会优先使用
+        executor.repairs().review(repair.id, true, Uuid::new_v4()).await.unwrap();
+        let response = executor.execute(get_text(".price", serde_json::json!({}))).await;
+        assert_eq!(data(&response)["text"], "$20");
+        assert_eq!(response.selector.unwrap().source, Some(SelectorSource::ApprovedRepair));
+        
+        // 无法匹配的建议只记录，不使用
+        let executor = ScraperExecutor::default().with_selector_repair(Arc::new(FixedRepairer("#missing")));
+        let page = StaticPage::new("https://shop.example.com/", 200, r#"<html><body><span class="amount">$5</span></body></html>"#);
+        let context_id = executor.register_static_page(page, None).await.context_id;
+        let response = executor.execute(ScraperRequest {
+            action: ScraperAction::GetText { selector: ".price".to_string(), find_by: SelectorType::CssSelector },
+            context_id,
+            config: repair_config,
+        }).await;
+        let resolution = response.selector.unwrap();
+        assert!(resolution.used.is_none());
+        assert_eq!(resolution.tried, vec![".cost-value", "#missing"]);
+        assert!(!executor.repairs().list(Some(RepairStatus::Pending)).await[0].verified);
+    }
+    
+    #[tokio::test]
+    async fn test_screenshot_clip_and_resize() {
+        let executor = ScraperExecutor::default();
+        let open_request = ScraperRequest {
+            action: ScraperAction::OpenPage { 
+                url: "https://example.com".to_string() 
+            },
+            context_id: None,
+            config: serde_json::json!({}),
+        };
+        let context_id = executor.execute(open_request).await.context_id;
+        
+        let screenshot = ScraperRequest {
+            action: ScraperAction::Screenshot {
+                mode: ScreenshotMode::Clip { x: 10, y: 20, width: 600, height: 400 },
+            },
+            context_id: context_id.clone(),
+            config: serde_json::json!({ "format": "jpeg", "quality": 70, "resize": { "height": 200 } }),
+        };
+        let response = executor.execute(screenshot).await;
+        assert!(response.success);
+        assert_eq!(data(&response)["width"], 300);
+        assert_eq!(data(&response)["height"], 200);
+        assert_eq!(data(&response)["format"], "jpeg");
+        
+        let invalid = ScraperRequest {
+            action: ScraperAction::Screenshot {
+                mode: ScreenshotMode::Clip { x: 0, y: 0, width: 0, height: 100 },
+            },
+            context_id,
+            config: serde_json::json!({}),
+        };
+        assert!(!executor.execute(invalid).await.success);
+    }
 }
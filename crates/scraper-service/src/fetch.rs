@@ -0,0 +1,316 @@
+//! 直接 HTTP 抓取模式
+//!
+//! 不启动浏览器，使用 reqwest 获取页面并解析静态 HTML，
+//! 适用于无需执行 JavaScript 的页面，节省浏览器池名额
+
+use std::time::Duration;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::ScraperError;
+use crate::types::{SelectorType, TextResult};
+
+/// 页面正文少于该字符数时视为需要 JavaScript 渲染
+const MIN_STATIC_TEXT_LEN: usize = 200;
+
+/// 提取字段配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractField {
+    pub name: String,
+    pub selector: String,
+    /// 为空时提取文本，否则提取该属性
+    #[serde(default)]
+    pub attribute: Option<String>,
+    #[serde(default)]
+    pub multiple: bool,
+}
+
+/// 通过 HTTP 获取的静态页面
+#[derive(Debug, Clone)]
+pub struct StaticPage {
+    pub url: String,
+    pub status: u16,
+    pub html: String,
+}
+
+impl StaticPage {
+    pub fn new(url: impl Into<String>, status: u16, html: impl Into<String>) -> Self {
+        StaticPage {
+            url: url.into(),
+            status,
+            html: html.into(),
+        }
+    }
+
+    /// 页面标题
+    pub fn title(&self) -> String {
+        let document = Html::parse_document(&self.html);
+        let selector = Selector::parse("title").expect("valid selector");
+        document
+            .select(&selector)
+            .next()
+            .map(|e| collect_text(&e))
+            .unwrap_or_default()
+    }
+
+    /// 判断页面是否需要浏览器渲染（正文过少或提示启用 JavaScript）
+    pub fn needs_browser(&self) -> bool {
+        let document = Html::parse_document(&self.html);
+        let body_selector = Selector::parse("body").expect("valid selector");
+        let body_text: String = document
+            .select(&body_selector)
+            .next()
+            .map(|b| {
+                b.descendants()
+                    .filter_map(|n| {
+                        let parent = n.parent().and_then(|p| p.value().as_element().map(|e| e.name()));
+                        match parent {
+                            Some("script") | Some("style") | Some("noscript") => None,
+                            _ => n.value().as_text().map(|t| t.to_string()),
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let lower = self.html.to_lowercase();
+        body_text.split_whitespace().collect::<String>().chars().count() < MIN_STATIC_TEXT_LEN
+            || (lower.contains("<noscript") && lower.contains("enable javascript"))
+    }
+
+    /// 查询元素文本
+    pub fn get_text(
+        &self,
+        selector: &str,
+        find_by: &SelectorType,
+        include_html: bool,
+    ) -> Result<Vec<TextResult>, ScraperError> {
+        let selector = parse_selector(selector, find_by)?;
+        let document = Html::parse_document(&self.html);
+        Ok(document
+            .select(&selector)
+            .map(|e| TextResult {
+                text: collect_text(&e),
+                html: include_html.then(|| e.inner_html()),
+            })
+            .collect())
+    }
+
+    /// 查询元素属性
+    pub fn get_attribute(
+        &self,
+        selector: &str,
+        attribute: &str,
+        find_by: &SelectorType,
+    ) -> Result<Vec<Option<String>>, ScraperError> {
+        let selector = parse_selector(selector, find_by)?;
+        let document = Html::parse_document(&self.html);
+        Ok(document
+            .select(&selector)
+            .map(|e| e.value().attr(attribute).map(|v| self.resolve_url(attribute, v)))
+            .collect())
+    }
+
+    /// 按字段配置批量提取
+    pub fn extract(&self, fields: &[ExtractField]) -> Result<serde_json::Map<String, Value>, ScraperError> {
+        let document = Html::parse_document(&self.html);
+        let mut result = serde_json::Map::new();
+
+        for field in fields {
+            let selector = parse_selector(&field.selector, &SelectorType::CssSelector)?;
+            let values: Vec<Value> = document
+                .select(&selector)
+                .map(|e| match &field.attribute {
+                    Some(attr) => e
+                        .value()
+                        .attr(attr)
+                        .map(|v| Value::String(self.resolve_url(attr, v)))
+                        .unwrap_or(Value::Null),
+                    None => Value::String(collect_text(&e)),
+                })
+                .collect();
+
+            let value = if field.multiple {
+                Value::Array(values)
+            } else {
+                values.into_iter().next().unwrap_or(Value::Null)
+            };
+            result.insert(field.name.clone(), value);
+        }
+
+        Ok(result)
+    }
+
+    /// 查询匹配元素的外层 HTML
+    pub fn outer_html(&self, selector: &str, find_by: &SelectorType) -> Result<Vec<String>, ScraperError> {
+        let selector = parse_selector(selector, find_by)?;
+        let document = Html::parse_document(&self.html);
+        Ok(document.select(&selector).map(|e| e.html()).collect())
+    }
+
+    /// href/src 属性转换为绝对地址
+    fn resolve_url(&self, attribute: &str, value: &str) -> String {
+        if attribute != "href" && attribute != "src" {
+            return value.to_string();
+        }
+        reqwest::Url::parse(&self.url)
+            .and_then(|base| base.join(value))
+            .map(|u| u.to_string())
+            .unwrap_or_else(|_| value.to_string())
+    }
+}
+
+fn parse_selector(selector: &str, find_by: &SelectorType) -> Result<Selector, ScraperError> {
+    if *find_by == SelectorType::Xpath {
+        return Err(ScraperError::InvalidSelector(format!(
+            "HTTP 模式不支持 XPath: {}",
+            selector
+        )));
+    }
+    Selector::parse(selector).map_err(|e| ScraperError::InvalidSelector(format!("{}: {}", selector, e)))
+}
+
+fn collect_text(element: &ElementRef) -> String {
+    element
+        .text()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// HTTP 页面获取器
+pub struct HttpFetcher {
+    client: reqwest::Client,
+}
+
+impl HttpFetcher {
+    pub fn new(timeout_ms: u64, user_agent: Option<&str>) -> Self {
+        let mut builder = reqwest::Client::builder().timeout(Duration::from_millis(timeout_ms));
+        if let Some(ua) = user_agent {
+            builder = builder.user_agent(ua.to_string());
+        }
+        HttpFetcher {
+            client: builder.build().unwrap_or_default(),
+        }
+    }
+
+    /// GET 页面并返回静态页面
+    pub async fn fetch(&self, url: &str) -> Result<StaticPage, ScraperError> {
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    ScraperError::Timeout(url.to_string())
+                } else {
+                    ScraperError::NavigationFailed(e.to_string())
+                }
+            })?;
+
+        let status = response.status().as_u16();
+        let final_url = response.url().to_string();
+        if !response.status().is_success() {
+            return Err(ScraperError::NavigationFailed(format!("{} 返回 {}", url, status)));
+        }
+
+        let is_html = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|ct| ct.contains("html") || ct.contains("xml"))
+            .unwrap_or(true);
+        if !is_html {
+            return Err(ScraperError::NavigationFailed(format!("{} 不是 HTML 页面", url)));
+        }
+
+        let html = response
+            .text()
+            .await
+            .map_err(|e| ScraperError::NavigationFailed(e.to_string()))?;
+
+        Ok(StaticPage::new(final_url, status, html))
+    }
+}
+
+impl Default for HttpFetcher {
+    fn default() -> Self {
+        HttpFetcher::new(30000, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page() -> StaticPage {
+        let filler = "Lorem ipsum dolor sit amet. ".repeat(10);
+        StaticPage::new(
+            "https://example.com/news/",
+            200,
+            format!(
+                r#"<html><head><title> Daily  News </title></head><body>
+                <ul>
+                  <li class="item"><a href="/a/1">First  story</a></li>
+                  <li class="item"><a href="https://other.com/2">Second story</a></li>
+                </ul>
+                <p>{}</p>
+                </body></html>"#,
+                filler
+            ),
+        )
+    }
+
+    #[test]
+    fn test_static_get_text_and_attribute() {
+        let page = page();
+        assert_eq!(page.title(), "Daily News");
+
+        let texts = page.get_text("li.item a", &SelectorType::CssSelector, false).unwrap();
+        assert_eq!(texts.len(), 2);
+        assert_eq!(texts[0].text, "First story");
+
+        let hrefs = page.get_attribute("li.item a", "href", &SelectorType::CssSelector).unwrap();
+        assert_eq!(hrefs[0].as_deref(), Some("https://example.com/a/1"));
+        assert_eq!(hrefs[1].as_deref(), Some("https://other.com/2"));
+
+        assert!(page.get_text("//li", &SelectorType::Xpath, false).is_err());
+    }
+
+    #[test]
+    fn test_static_extract() {
+        let fields = vec![
+            ExtractField {
+                name: "titles".to_string(),
+                selector: "li.item a".to_string(),
+                attribute: None,
+                multiple: true,
+            },
+            ExtractField {
+                name: "firstLink".to_string(),
+                selector: "li.item a".to_string(),
+                attribute: Some("href".to_string()),
+                multiple: false,
+            },
+        ];
+        let data = page().extract(&fields).unwrap();
+        assert_eq!(data["titles"][1], "Second story");
+        assert_eq!(data["firstLink"], "https://example.com/a/1");
+    }
+
+    #[test]
+    fn test_needs_browser() {
+        assert!(!page().needs_browser());
+        let shell = StaticPage::new(
+            "https://app.example.com",
+            200,
+            r#"<html><body><noscript>Please enable JavaScript</noscript><div id="root"></div><script>boot()</script></body></html>"#,
+        );
+        assert!(shell.needs_browser());
+    }
+}
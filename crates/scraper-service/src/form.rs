@@ -0,0 +1,223 @@
+//! 表单批量填写
+//!
+//! 一个 FillForm 动作按顺序填写多个字段并可选提交，替代冗长的 Input/Click 节点链
+
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::ScraperError;
+use crate::types::SelectorType;
+
+/// 表单字段的值及填写方式
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FieldValue {
+    /// 文本输入框 / 文本域
+    Text { value: String },
+    /// 下拉框，按 value 或可见文本选择
+    #[serde(rename_all = "camelCase")]
+    Select {
+        #[serde(default)]
+        value: Option<String>,
+        #[serde(default)]
+        label: Option<String>,
+    },
+    Checkbox { checked: bool },
+    /// 单选框，选中 value 匹配的选项
+    Radio { value: String },
+    /// 文件上传，文件来自制品存储
+    #[serde(rename_all = "camelCase")]
+    File { artifact_id: String },
+}
+
+/// 字段校验规则
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldValidation {
+    #[serde(default)]
+    pub max_length: Option<usize>,
+    /// 允许的取值（文本、下拉、单选）
+    #[serde(default)]
+    pub one_of: Option<Vec<String>>,
+}
+
+/// 表单字段
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormField {
+    pub selector: String,
+    #[serde(default)]
+    pub find_by: SelectorType,
+    #[serde(flatten)]
+    pub value: FieldValue,
+    /// 必填字段不允许为空值
+    #[serde(default = "default_required")]
+    pub required: bool,
+    #[serde(default)]
+    pub validation: Option<FieldValidation>,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+/// 提交配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormSubmit {
+    /// 提交按钮；为空时在最后一个字段上按回车
+    #[serde(default)]
+    pub selector: Option<String>,
+    #[serde(default)]
+    pub find_by: SelectorType,
+    #[serde(default = "default_wait_for_navigation")]
+    pub wait_for_navigation: bool,
+}
+
+fn default_wait_for_navigation() -> bool {
+    true
+}
+
+impl FormField {
+    /// 填写前校验字段值
+    pub fn validate(&self, index: usize, artifacts: &ArtifactStore) -> Result<(), ScraperError> {
+        let invalid = |reason: &str| {
+            ScraperError::FormFieldInvalid(format!("第 {} 个字段 {}: {}", index + 1, self.selector, reason))
+        };
+
+        if self.selector.trim().is_empty() {
+            return Err(invalid("选择器为空"));
+        }
+
+        let text = match &self.value {
+            FieldValue::Text { value } | FieldValue::Radio { value } => Some(value.as_str()),
+            FieldValue::Select { value, label } => {
+                if value.is_none() && label.is_none() {
+                    return Err(invalid("下拉框需要 value 或 label"));
+                }
+                value.as_deref().or(label.as_deref())
+            }
+            FieldValue::Checkbox { .. } => None,
+            FieldValue::File { artifact_id } => {
+                if !artifacts.contains(artifact_id) {
+                    return Err(ScraperError::ArtifactNotFound(artifact_id.clone()));
+                }
+                None
+            }
+        };
+
+        if let Some(text) = text {
+            if self.required && text.trim().is_empty() {
+                return Err(invalid("必填字段为空"));
+            }
+            if let Some(validation) = &self.validation {
+                if validation.max_length.is_some_and(|max| text.chars().count() > max) {
+                    return Err(invalid("超出最大长度"));
+                }
+                if validation.one_of.as_ref().is_some_and(|allowed| !allowed.iter().any(|a| a == text)) {
+                    return Err(invalid("取值不在允许范围内"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 字段填写结果摘要
+    pub fn summary(&self, artifacts: &ArtifactStore) -> serde_json::Value {
+        let (kind, value) = match &self.value {
+            FieldValue::Text { value } => ("text", serde_json::json!(value)),
+            FieldValue::Select { value, label } => ("select", serde_json::json!(value.as_ref().or(label.as_ref()))),
+            FieldValue::Checkbox { checked } => ("checkbox", serde_json::json!(checked)),
+            FieldValue::Radio { value } => ("radio", serde_json::json!(value)),
+            FieldValue::File { artifact_id } => (
+                "file",
+                serde_json::json!(artifacts.get(artifact_id).map(|a| a.name.clone())),
+            ),
+        };
+        serde_json::json!({ "selector": self.selector, "type": kind, "value": value, "filled": true })
+    }
+}
+
+/// 制品（上传文件、截图等）
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub id: String,
+    pub name: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 内存制品存储，供文件上传字段引用
+#[derive(Debug, Default)]
+pub struct ArtifactStore {
+    artifacts: HashMap<String, Artifact>,
+}
+
+impl ArtifactStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 保存制品并返回 ID
+    pub fn put(&mut self, name: &str, mime_type: &str, data: Vec<u8>) -> String {
+        let id = Uuid::new_v4().to_string();
+        self.artifacts.insert(id.clone(), Artifact {
+            id: id.clone(),
+            name: name.to_string(),
+            mime_type: mime_type.to_string(),
+            data,
+            created_at: Utc::now(),
+        });
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Artifact> {
+        self.artifacts.get(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.artifacts.contains_key(id)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<Artifact> {
+        self.artifacts.remove(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(value: serde_json::Value) -> FormField {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_field_validation() {
+        let mut artifacts = ArtifactStore::new();
+        let resume = artifacts.put("resume.pdf", "application/pdf", vec![1, 2, 3]);
+
+        let name = field(serde_json::json!({
+            "selector": "#name", "type": "text", "value": "Alice",
+            "validation": { "maxLength": 3 }
+        }));
+        assert!(matches!(name.validate(0, &artifacts), Err(ScraperError::FormFieldInvalid(_))));
+
+        let country = field(serde_json::json!({ "selector": "#country", "type": "select", "label": "Germany" }));
+        assert!(country.validate(1, &artifacts).is_ok());
+
+        let optional = field(serde_json::json!({ "selector": "#note", "type": "text", "value": "", "required": false }));
+        assert!(optional.validate(2, &artifacts).is_ok());
+
+        let upload = field(serde_json::json!({ "selector": "#cv", "type": "file", "artifactId": resume }));
+        assert!(upload.validate(3, &artifacts).is_ok());
+        assert_eq!(upload.summary(&artifacts)["value"], "resume.pdf");
+
+        let missing = field(serde_json::json!({ "selector": "#cv", "type": "file", "artifactId": "nope" }));
+        assert!(matches!(missing.validate(4, &artifacts), Err(ScraperError::ArtifactNotFound(_))));
+    }
+}
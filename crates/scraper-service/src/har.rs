@@ -0,0 +1,299 @@
+//! HAR (HTTP Archive 1.2) 网络记录
+//!
+//! 记录浏览器上下文生命周期内的全部网络请求，便于排查页面数据未加载的原因，
+//! 以及回放抓取过程中发现的 API 接口
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// HAR 根对象
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Har {
+    pub log: HarLog,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HarLog {
+    pub version: String,
+    pub creator: HarCreator,
+    pub pages: Vec<HarPage>,
+    pub entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HarCreator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPage {
+    pub started_date_time: DateTime<Utc>,
+    pub id: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pageref: Option<String>,
+    pub started_date_time: DateTime<Utc>,
+    /// 总耗时（毫秒）
+    pub time: f64,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    pub timings: HarTimings,
+    /// 资源类型（document / xhr / fetch / script ...），对应 Playwright 的 resourceType
+    #[serde(rename = "_resourceType", skip_serializing_if = "Option::is_none")]
+    pub resource_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    pub headers: Vec<HarHeader>,
+    pub query_string: Vec<HarHeader>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HarPostData>,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPostData {
+    pub mime_type: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub http_version: String,
+    pub headers: Vec<HarHeader>,
+    pub content: HarContent,
+    #[serde(rename = "redirectURL")]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    pub size: i64,
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// 名称/值对，用于 headers 和 queryString
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HarHeader {
+    pub name: String,
+    pub value: String,
+}
+
+/// 各阶段耗时（毫秒），-1 表示不适用
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HarTimings {
+    pub blocked: f64,
+    pub dns: f64,
+    pub connect: f64,
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+}
+
+impl Default for HarTimings {
+    fn default() -> Self {
+        HarTimings {
+            blocked: -1.0,
+            dns: -1.0,
+            connect: -1.0,
+            send: 0.0,
+            wait: 0.0,
+            receive: 0.0,
+        }
+    }
+}
+
+impl HarEntry {
+    /// 构造一个简单的 GET 请求记录
+    pub fn get(url: &str, status: u16, mime_type: &str, time_ms: f64) -> Self {
+        let query_string = reqwest::Url::parse(url)
+            .map(|u| {
+                u.query_pairs()
+                    .map(|(k, v)| HarHeader { name: k.into_owned(), value: v.into_owned() })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        HarEntry {
+            pageref: None,
+            started_date_time: Utc::now(),
+            time: time_ms,
+            request: HarRequest {
+                method: "GET".to_string(),
+                url: url.to_string(),
+                http_version: "HTTP/1.1".to_string(),
+                headers: Vec::new(),
+                query_string,
+                post_data: None,
+                headers_size: -1,
+                body_size: 0,
+            },
+            response: HarResponse {
+                status,
+                status_text: String::new(),
+                http_version: "HTTP/1.1".to_string(),
+                headers: Vec::new(),
+                content: HarContent {
+                    size: -1,
+                    mime_type: mime_type.to_string(),
+                    text: None,
+                },
+                redirect_url: String::new(),
+                headers_size: -1,
+                body_size: -1,
+            },
+            timings: HarTimings {
+                wait: time_ms,
+                ..Default::default()
+            },
+            resource_type: None,
+        }
+    }
+
+    /// 是否为可回放的 API 请求（XHR/fetch 或 JSON 响应）
+    pub fn is_api_call(&self) -> bool {
+        matches!(self.resource_type.as_deref(), Some("xhr") | Some("fetch"))
+            || self.response.content.mime_type.contains("json")
+    }
+}
+
+/// HAR 记录器，随浏览器上下文存在
+#[derive(Debug, Clone)]
+pub struct HarRecorder {
+    pages: Vec<HarPage>,
+    entries: Vec<HarEntry>,
+    max_entries: usize,
+    dropped: usize,
+}
+
+impl HarRecorder {
+    pub fn new(max_entries: usize) -> Self {
+        HarRecorder {
+            pages: Vec::new(),
+            entries: Vec::new(),
+            max_entries,
+            dropped: 0,
+        }
+    }
+
+    /// 开始记录新页面，返回页面 ID
+    pub fn start_page(&mut self, title: &str) -> String {
+        let id = format!("page_{}", self.pages.len() + 1);
+        self.pages.push(HarPage {
+            started_date_time: Utc::now(),
+            id: id.clone(),
+            title: title.to_string(),
+        });
+        id
+    }
+
+    /// 记录一次网络请求，超过上限时丢弃并计数
+    pub fn record(&mut self, mut entry: HarEntry) {
+        if self.entries.len() >= self.max_entries {
+            self.dropped += 1;
+            return;
+        }
+        if entry.pageref.is_none() {
+            entry.pageref = self.pages.last().map(|p| p.id.clone());
+        }
+        self.entries.push(entry);
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn dropped_count(&self) -> usize {
+        self.dropped
+    }
+
+    /// 导出为 HAR 文档
+    pub fn to_har(&self) -> Har {
+        Har {
+            log: HarLog {
+                version: "1.2".to_string(),
+                creator: HarCreator {
+                    name: "flowvex-scraper".to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                },
+                pages: self.pages.clone(),
+                entries: self.entries.clone(),
+            },
+        }
+    }
+}
+
+impl Default for HarRecorder {
+    fn default() -> Self {
+        HarRecorder::new(5000)
+    }
+}
+
+impl Har {
+    /// 抓取过程中发现的 API 接口（去重后的 method + url）
+    pub fn api_endpoints(&self) -> Vec<(String, String)> {
+        let mut seen = std::collections::HashSet::new();
+        self.log
+            .entries
+            .iter()
+            .filter(|e| e.is_api_call())
+            .map(|e| (e.request.method.clone(), e.request.url.clone()))
+            .filter(|key| seen.insert(key.clone()))
+            .collect()
+    }
+}
+
+/// 发现的 API 接口
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiEndpoint {
+    pub method: String,
+    pub url: String,
+}
+
+/// HAR 产物：完整 HAR 文档及发现的 API 接口
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarArtifact {
+    pub file_name: String,
+    pub mime_type: String,
+    pub entry_count: usize,
+    pub api_endpoints: Vec<ApiEndpoint>,
+    pub content: Har,
+}
+
+impl HarArtifact {
+    pub fn new(har: &Har) -> Self {
+        HarArtifact {
+            file_name: "session.har".to_string(),
+            mime_type: "application/json".to_string(),
+            entry_count: har.log.entries.len(),
+            api_endpoints: har.api_endpoints()
+                .into_iter()
+                .map(|(method, url)| ApiEndpoint { method, url })
+                .collect(),
+            content: har.clone(),
+        }
+    }
+}
@@ -6,7 +6,23 @@ pub mod browser;
 pub mod executor;
 pub mod types;
 pub mod error;
+pub mod policy;
+pub mod har;
+pub mod fetch;
+pub mod screenshot;
+pub mod planner;
+pub mod form;
+pub mod wait;
+pub mod repair;
 
 pub use browser::{BrowserPool, BrowserContext, BrowserContextId, BrowserContextConfig};
 pub use executor::{ScraperExecutor, ScraperRequest, ScraperResponse, ScraperAction};
 pub use error::ScraperError;
+pub use types::{DeviceProfile, ScraperData};
+pub use policy::{ScraperPolicy, PolicyEnforcer};
+pub use har::{Har, HarArtifact, HarEntry, HarRecorder};
+pub use fetch::{HttpFetcher, StaticPage, ExtractField};
+pub use planner::{JobPlanner, JobStore, ScrapeJob, JobShard, Sitemap};
+pub use form::{ArtifactStore, FormField, FieldValue, FormSubmit};
+pub use wait::{AutoWait, PageProbe, WaitMode, WaitPlan, WaitStrategy};
+pub use repair::{ProposedSelector, RepairRequest, RepairStatus, RepairStore, SelectorRepair, SelectorRepairer, SelectorResolution, SelectorSource};
@@ -0,0 +1,453 @@
+//! 大规模抓取任务规划
+//!
+//! 将目标 URL 列表（sitemap 或上传的 CSV）按域名切分为分片，
+//! 依据每个域名的礼貌策略错开调度时间，分片进度持久化到磁盘，服务重启后可继续未完成的分片
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::ScraperError;
+use crate::policy::{domain_key, ScraperPolicy};
+
+/// sitemap 索引的最大展开深度
+const MAX_SITEMAP_DEPTH: usize = 3;
+
+/// 解析后的 sitemap 内容
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sitemap {
+    /// `<urlset>` 中的页面地址
+    pub urls: Vec<String>,
+    /// `<sitemapindex>` 中的子 sitemap 地址
+    pub sitemaps: Vec<String>,
+}
+
+impl Sitemap {
+    /// 解析 sitemap XML，只提取 `<loc>` 内容
+    pub fn parse(xml: &str) -> Self {
+        let is_index = xml.contains("<sitemapindex");
+        let mut locs = Vec::new();
+        let mut rest = xml;
+        while let Some(start) = rest.find("<loc>") {
+            rest = &rest[start + 5..];
+            let Some(end) = rest.find("</loc>") else {
+                break;
+            };
+            let loc = rest[..end]
+                .trim()
+                .trim_start_matches("<![CDATA[")
+                .trim_end_matches("]]>")
+                .replace("&amp;", "&");
+            if !loc.is_empty() {
+                locs.push(loc);
+            }
+            rest = &rest[end + 6..];
+        }
+
+        if is_index {
+            Sitemap { urls: Vec::new(), sitemaps: locs }
+        } else {
+            Sitemap { urls: locs, sitemaps: Vec::new() }
+        }
+    }
+
+    /// 获取 sitemap 并递归展开索引，返回全部页面地址
+    pub async fn fetch_all(client: &reqwest::Client, url: &str) -> Result<Vec<String>, ScraperError> {
+        let mut urls = Vec::new();
+        let mut pending = vec![(url.to_string(), 0usize)];
+        let mut visited = HashSet::new();
+
+        while let Some((sitemap_url, depth)) = pending.pop() {
+            if depth > MAX_SITEMAP_DEPTH || !visited.insert(sitemap_url.clone()) {
+                continue;
+            }
+            let body = client
+                .get(&sitemap_url)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| ScraperError::NavigationFailed(format!("{}: {}", sitemap_url, e)))?
+                .text()
+                .await
+                .map_err(|e| ScraperError::NavigationFailed(e.to_string()))?;
+
+            let sitemap = Sitemap::parse(&body);
+            urls.extend(sitemap.urls);
+            pending.extend(sitemap.sitemaps.into_iter().map(|s| (s, depth + 1)));
+        }
+
+        Ok(urls)
+    }
+}
+
+/// 从 CSV 中读取 URL 列：表头含 `url` 列时使用该列，否则使用第一列
+pub fn parse_url_csv(content: &str) -> Vec<String> {
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty()).peekable();
+    let split = |line: &str| -> Vec<String> {
+        line.split(',')
+            .map(|c| c.trim().trim_matches('"').to_string())
+            .collect()
+    };
+
+    let mut column = 0;
+    if let Some(header) = lines.peek() {
+        let cells = split(header);
+        if let Some(idx) = cells.iter().position(|c| c.eq_ignore_ascii_case("url")) {
+            column = idx;
+            lines.next();
+        } else if !cells.first().is_some_and(|c| c.contains("://")) {
+            // 第一行既不是 URL 也没有 url 列，视为表头跳过
+            lines.next();
+        }
+    }
+
+    lines
+        .filter_map(|line| split(line).into_iter().nth(column))
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+/// 分片状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShardStatus {
+    Pending,
+    Running,
+    Completed,
+}
+
+/// 抓取任务分片，同一分片内的 URL 属于同一域名
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobShard {
+    pub index: usize,
+    pub domain: String,
+    pub urls: Vec<String>,
+    /// 计划开始时间
+    pub scheduled_at: DateTime<Utc>,
+    pub status: ShardStatus,
+    /// 已处理的 URL 数量（恢复时从此处继续）
+    pub cursor: usize,
+    pub failed_urls: Vec<String>,
+}
+
+impl JobShard {
+    /// 下一个待处理的 URL
+    pub fn next_url(&self) -> Option<&str> {
+        self.urls.get(self.cursor).map(|u| u.as_str())
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.urls.len().saturating_sub(self.cursor)
+    }
+}
+
+/// 分片规划后的抓取任务
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrapeJob {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub shards: Vec<JobShard>,
+    /// 规划时丢弃的无效或重复 URL 数量
+    pub rejected: usize,
+}
+
+impl ScrapeJob {
+    pub fn total_urls(&self) -> usize {
+        self.shards.iter().map(|s| s.urls.len()).sum()
+    }
+
+    pub fn processed_urls(&self) -> usize {
+        self.shards.iter().map(|s| s.cursor).sum()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.shards.iter().all(|s| s.status == ShardStatus::Completed)
+    }
+
+    /// 已到计划时间且未完成的分片（不含正在运行的）
+    pub fn due_shards(&self, now: DateTime<Utc>) -> Vec<usize> {
+        self.shards
+            .iter()
+            .filter(|s| s.status == ShardStatus::Pending && s.scheduled_at <= now)
+            .map(|s| s.index)
+            .collect()
+    }
+
+    /// 标记分片开始执行
+    pub fn start_shard(&mut self, index: usize) -> Result<&JobShard, ScraperError> {
+        let shard = self.shard_mut(index)?;
+        if shard.status == ShardStatus::Pending {
+            shard.status = ShardStatus::Running;
+        }
+        Ok(shard)
+    }
+
+    /// 记录分片中下一个 URL 的处理结果
+    pub fn record_progress(&mut self, index: usize, success: bool) -> Result<(), ScraperError> {
+        let shard = self.shard_mut(index)?;
+        if let Some(url) = shard.urls.get(shard.cursor).cloned() {
+            if !success {
+                shard.failed_urls.push(url);
+            }
+            shard.cursor += 1;
+        }
+        if shard.remaining() == 0 {
+            shard.status = ShardStatus::Completed;
+        }
+        Ok(())
+    }
+
+    /// 重启后恢复：运行中的分片回到待执行状态，保留游标，过期的计划时间顺延到 now
+    pub fn prepare_resume(&mut self, now: DateTime<Utc>) {
+        for shard in &mut self.shards {
+            if shard.status == ShardStatus::Running {
+                shard.status = ShardStatus::Pending;
+            }
+            if shard.status == ShardStatus::Pending && shard.scheduled_at < now {
+                shard.scheduled_at = now;
+            }
+        }
+    }
+
+    fn shard_mut(&mut self, index: usize) -> Result<&mut JobShard, ScraperError> {
+        self.shards
+            .get_mut(index)
+            .ok_or_else(|| ScraperError::JobNotFound(format!("{}#{}", self.id, index)))
+    }
+}
+
+/// 任务规划器
+pub struct JobPlanner {
+    policy: ScraperPolicy,
+    shard_size: usize,
+}
+
+impl JobPlanner {
+    pub fn new(policy: ScraperPolicy) -> Self {
+        JobPlanner { policy, shard_size: 100 }
+    }
+
+    /// 设置每个分片的最大 URL 数
+    pub fn with_shard_size(mut self, shard_size: usize) -> Self {
+        self.shard_size = shard_size.max(1);
+        self
+    }
+
+    /// 规划任务：去重、按域名分组切片，同域名的分片按礼貌间隔依次排开，不同域名并行
+    pub fn plan(&self, name: &str, urls: Vec<String>, start_at: DateTime<Utc>) -> ScrapeJob {
+        let mut seen = HashSet::new();
+        let mut by_domain: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut rejected = 0;
+
+        for url in urls {
+            let domain = Url::parse(url.trim())
+                .ok()
+                .filter(|u| matches!(u.scheme(), "http" | "https"))
+                .and_then(|u| domain_key(&u).ok());
+            match domain {
+                Some(domain) if seen.insert(url.trim().to_string()) => {
+                    by_domain.entry(domain).or_default().push(url.trim().to_string());
+                }
+                _ => rejected += 1,
+            }
+        }
+
+        let mut shards = Vec::new();
+        for (domain, urls) in by_domain {
+            let mut scheduled_at = start_at;
+            for chunk in urls.chunks(self.shard_size) {
+                shards.push(JobShard {
+                    index: shards.len(),
+                    domain: domain.clone(),
+                    urls: chunk.to_vec(),
+                    scheduled_at,
+                    status: ShardStatus::Pending,
+                    cursor: 0,
+                    failed_urls: Vec::new(),
+                });
+                scheduled_at += self.shard_duration(chunk.len());
+            }
+        }
+
+        ScrapeJob {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            created_at: Utc::now(),
+            shards,
+            rejected,
+        }
+    }
+
+    /// 按域名最小间隔和并发数估算一个分片的耗时
+    fn shard_duration(&self, url_count: usize) -> chrono::Duration {
+        let concurrency = self.policy.max_concurrent_per_domain.max(1) as u64;
+        let millis = url_count as u64 * self.policy.min_delay_ms / concurrency;
+        chrono::Duration::from_std(Duration::from_millis(millis)).unwrap_or_else(|_| chrono::Duration::zero())
+    }
+}
+
+/// 基于文件的任务进度存储，每个任务一个 JSON 文件
+pub struct JobStore {
+    dir: PathBuf,
+}
+
+impl JobStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        JobStore { dir: dir.into() }
+    }
+
+    fn path_for(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    /// 保存任务，先写临时文件再重命名，避免中途崩溃留下损坏的文件
+    pub async fn save(&self, job: &ScrapeJob) -> Result<(), ScraperError> {
+        tokio::fs::create_dir_all(&self.dir).await.map_err(io_error)?;
+        let data = serde_json::to_vec_pretty(job).map_err(|e| ScraperError::Internal(e.to_string()))?;
+        let path = self.path_for(job.id);
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, data).await.map_err(io_error)?;
+        tokio::fs::rename(&tmp, &path).await.map_err(io_error)
+    }
+
+    pub async fn load(&self, id: Uuid) -> Result<ScrapeJob, ScraperError> {
+        let path = self.path_for(id);
+        match tokio::fs::read(&path).await {
+            Ok(data) => read_job(&path, &data),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(ScraperError::JobNotFound(id.to_string())),
+            Err(e) => Err(io_error(e)),
+        }
+    }
+
+    pub async fn delete(&self, id: Uuid) -> Result<(), ScraperError> {
+        match tokio::fs::remove_file(self.path_for(id)).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(ScraperError::JobNotFound(id.to_string())),
+            Err(e) => Err(io_error(e)),
+        }
+    }
+
+    /// 加载全部未完成的任务并准备恢复执行
+    pub async fn resume_incomplete(&self) -> Result<Vec<ScrapeJob>, ScraperError> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(io_error(e)),
+        };
+
+        let now = Utc::now();
+        let mut jobs = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let data = tokio::fs::read(&path).await.map_err(io_error)?;
+            match read_job(&path, &data) {
+                Ok(mut job) if !job.is_complete() => {
+                    job.prepare_resume(now);
+                    jobs.push(job);
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Skipping unreadable job file: {}", e),
+            }
+        }
+        jobs.sort_by_key(|j| j.created_at);
+        Ok(jobs)
+    }
+}
+
+fn read_job(path: &Path, data: &[u8]) -> Result<ScrapeJob, ScraperError> {
+    serde_json::from_slice(data)
+        .map_err(|e| ScraperError::Internal(format!("{}: {}", path.display(), e)))
+}
+
+fn io_error(e: std::io::Error) -> ScraperError {
+    ScraperError::Internal(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ScraperPolicy {
+        ScraperPolicy {
+            min_delay_ms: 1000,
+            max_concurrent_per_domain: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_sitemap_and_csv() {
+        let index = Sitemap::parse(
+            r#"<sitemapindex><sitemap><loc>https://a.com/s1.xml</loc></sitemap></sitemapindex>"#,
+        );
+        assert_eq!(index.sitemaps, vec!["https://a.com/s1.xml"]);
+
+        let set = Sitemap::parse(
+            "<urlset><url><loc> https://a.com/p?x=1&amp;y=2 </loc></url><url><loc>https://a.com/q</loc></url></urlset>",
+        );
+        assert_eq!(set.urls, vec!["https://a.com/p?x=1&y=2", "https://a.com/q"]);
+
+        let csv = "id,URL,title\n1,https://a.com/1,One\n2,\"https://b.com/2\",Two\n\n";
+        assert_eq!(parse_url_csv(csv), vec!["https://a.com/1", "https://b.com/2"]);
+        assert_eq!(parse_url_csv("https://a.com/x\nhttps://a.com/y"), vec!["https://a.com/x", "https://a.com/y"]);
+    }
+
+    #[test]
+    fn test_plan_shards_by_domain() {
+        let start = Utc::now();
+        let mut urls: Vec<String> = (0..5).map(|i| format!("https://a.com/{}", i)).collect();
+        urls.push("https://b.com/1".to_string());
+        urls.push("https://a.com/0".to_string());
+        urls.push("ftp://a.com/file".to_string());
+
+        let job = JobPlanner::new(policy()).with_shard_size(2).plan("crawl", urls, start);
+        assert_eq!(job.total_urls(), 6);
+        assert_eq!(job.rejected, 2);
+
+        let a: Vec<_> = job.shards.iter().filter(|s| s.domain == "a.com").collect();
+        assert_eq!(a.len(), 3);
+        // 每个分片 2 个 URL、间隔 1 秒、并发 2：同域名分片相隔 1 秒
+        assert_eq!(a[1].scheduled_at - a[0].scheduled_at, chrono::Duration::seconds(1));
+        let b = job.shards.iter().find(|s| s.domain == "b.com").unwrap();
+        assert_eq!(b.scheduled_at, start);
+        assert_eq!(job.due_shards(start).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_progress_persists_and_resumes() {
+        let dir = std::env::temp_dir().join(format!("flowvex-jobs-{}", Uuid::new_v4()));
+        let store = JobStore::new(&dir);
+        let urls = vec!["https://a.com/1".to_string(), "https://a.com/2".to_string()];
+        let mut job = JobPlanner::new(policy()).plan("crawl", urls, Utc::now());
+
+        job.start_shard(0).unwrap();
+        job.record_progress(0, false).unwrap();
+        store.save(&job).await.unwrap();
+
+        let resumed = store.resume_incomplete().await.unwrap();
+        assert_eq!(resumed.len(), 1);
+        let mut resumed = resumed.into_iter().next().unwrap();
+        assert_eq!(resumed.shards[0].status, ShardStatus::Pending);
+        assert_eq!(resumed.shards[0].next_url(), Some("https://a.com/2"));
+        assert_eq!(resumed.shards[0].failed_urls, vec!["https://a.com/1"]);
+
+        resumed.record_progress(0, true).unwrap();
+        assert!(resumed.is_complete());
+        store.save(&resumed).await.unwrap();
+        assert!(store.resume_incomplete().await.unwrap().is_empty());
+
+        store.delete(job.id).await.unwrap();
+        assert!(matches!(store.load(job.id).await, Err(ScraperError::JobNotFound(_))));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
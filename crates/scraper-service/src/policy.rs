@@ -0,0 +1,385 @@
+//! 爬虫合规策略
+//!
+//! 按域名缓存 robots.txt，拒绝不允许抓取的路径，并限制每个域名的请求间隔与并发数
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, OwnedSemaphorePermit, RwLock, Semaphore};
+
+use crate::error::ScraperError;
+
+/// 爬虫合规策略配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScraperPolicy {
+    /// 是否遵守 robots.txt
+    pub respect_robots_txt: bool,
+    /// 匹配 robots.txt 分组时使用的 User-Agent
+    pub user_agent: String,
+    /// 同一域名两次请求之间的最小间隔（毫秒）
+    pub min_delay_ms: u64,
+    /// 同一域名的最大并发请求数
+    pub max_concurrent_per_domain: usize,
+    /// robots.txt 缓存时间（秒）
+    pub robots_cache_ttl_secs: u64,
+}
+
+impl Default for ScraperPolicy {
+    fn default() -> Self {
+        ScraperPolicy {
+            respect_robots_txt: true,
+            user_agent: "FlowvexBot".to_string(),
+            min_delay_ms: 1000,
+            max_concurrent_per_domain: 2,
+            robots_cache_ttl_secs: 3600,
+        }
+    }
+}
+
+/// 解析后的 robots.txt 规则
+#[derive(Debug, Clone, Default)]
+pub struct RobotsRules {
+    allow: Vec<String>,
+    disallow: Vec<String>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    /// 解析 robots.txt，只保留与 user_agent 匹配的分组（无精确匹配时使用 `*` 分组）
+    pub fn parse(content: &str, user_agent: &str) -> Self {
+        let agent = user_agent.to_lowercase();
+        let mut specific: Option<RobotsRules> = None;
+        let mut wildcard: Option<RobotsRules> = None;
+
+        let mut group_agents: Vec<String> = Vec::new();
+        let mut group = RobotsRules::default();
+        let mut in_rules = false;
+
+        let mut flush = |agents: &[String], rules: &RobotsRules| {
+            for a in agents {
+                if a == "*" {
+                    wildcard.get_or_insert_with(RobotsRules::default).merge(rules);
+                } else if agent.contains(a.as_str()) {
+                    specific.get_or_insert_with(RobotsRules::default).merge(rules);
+                }
+            }
+        };
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        flush(&group_agents, &group);
+                        group_agents.clear();
+                        group = RobotsRules::default();
+                        in_rules = false;
+                    }
+                    group_agents.push(value.to_lowercase());
+                }
+                "allow" => {
+                    in_rules = true;
+                    if !value.is_empty() {
+                        group.allow.push(value.to_string());
+                    }
+                }
+                "disallow" => {
+                    in_rules = true;
+                    if !value.is_empty() {
+                        group.disallow.push(value.to_string());
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    group.crawl_delay = value.parse::<f64>().ok().map(Duration::from_secs_f64);
+                }
+                _ => {}
+            }
+        }
+        flush(&group_agents, &group);
+
+        specific.or(wildcard).unwrap_or_default()
+    }
+
+    fn merge(&mut self, other: &RobotsRules) {
+        self.allow.extend(other.allow.iter().cloned());
+        self.disallow.extend(other.disallow.iter().cloned());
+        if other.crawl_delay.is_some() {
+            self.crawl_delay = other.crawl_delay;
+        }
+    }
+
+    /// 判断路径是否允许抓取（最长匹配优先，长度相同时 Allow 优先）
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let longest = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter(|p| pattern_matches(p, path))
+                .map(|p| p.len())
+                .max()
+        };
+
+        match (longest(&self.allow), longest(&self.disallow)) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(a), Some(d)) => a >= d,
+        }
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// 支持 `*` 通配和 `$` 结尾锚定的路径匹配
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    if !path.starts_with(first) {
+        return false;
+    }
+    let mut pos = first.len();
+    let rest: Vec<&str> = parts.collect();
+
+    for (i, part) in rest.iter().enumerate() {
+        if anchored && i == rest.len() - 1 {
+            return path.len() >= pos + part.len() && path.ends_with(part);
+        }
+        match path[pos..].find(part) {
+            Some(idx) => pos += idx + part.len(),
+            None => return false,
+        }
+    }
+
+    !anchored || pos == path.len()
+}
+
+/// 域名访问许可，释放时归还并发名额
+#[derive(Debug)]
+pub struct DomainPermit {
+    pub domain: String,
+    _permit: OwnedSemaphorePermit,
+}
+
+/// 爬虫合规策略执行器
+pub struct PolicyEnforcer {
+    policy: ScraperPolicy,
+    client: reqwest::Client,
+    robots_cache: RwLock<HashMap<String, (Arc<RobotsRules>, Instant)>>,
+    semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+    next_slot: Mutex<HashMap<String, Instant>>,
+}
+
+impl PolicyEnforcer {
+    pub fn new(policy: ScraperPolicy) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(policy.user_agent.clone())
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+
+        PolicyEnforcer {
+            policy,
+            client,
+            robots_cache: RwLock::new(HashMap::new()),
+            semaphores: Mutex::new(HashMap::new()),
+            next_slot: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn policy(&self) -> &ScraperPolicy {
+        &self.policy
+    }
+
+    /// 申请访问 URL：校验 robots.txt，等待域名间隔并占用并发名额
+    pub async fn acquire(&self, url: &str) -> Result<DomainPermit, ScraperError> {
+        let parsed = Url::parse(url).map_err(|e| ScraperError::InvalidUrl(e.to_string()))?;
+        let domain = domain_key(&parsed)?;
+
+        let rules = if self.policy.respect_robots_txt {
+            let rules = self.robots_for(&parsed, &domain).await;
+            let path = match parsed.query() {
+                Some(q) => format!("{}?{}", parsed.path(), q),
+                None => parsed.path().to_string(),
+            };
+            if !rules.is_allowed(&path) {
+                return Err(ScraperError::RobotsDisallowed(url.to_string()));
+            }
+            Some(rules)
+        } else {
+            None
+        };
+
+        let semaphore = {
+            let mut semaphores = self.semaphores.lock().await;
+            semaphores
+                .entry(domain.clone())
+                .or_insert_with(|| Arc::new(Semaphore::new(self.policy.max_concurrent_per_domain.max(1))))
+                .clone()
+        };
+        let permit = semaphore
+            .acquire_owned()
+            .await
+            .map_err(|e| ScraperError::Internal(e.to_string()))?;
+
+        let min_delay = Duration::from_millis(self.policy.min_delay_ms);
+        let delay = rules
+            .and_then(|r| r.crawl_delay())
+            .map(|d| d.max(min_delay))
+            .unwrap_or(min_delay);
+
+        let wait = {
+            let mut next_slot = self.next_slot.lock().await;
+            let now = Instant::now();
+            let slot = next_slot.get(&domain).copied().filter(|s| *s > now).unwrap_or(now);
+            next_slot.insert(domain.clone(), slot + delay);
+            slot - now
+        };
+        if !wait.is_zero() {
+            tracing::debug!("Politeness delay for {}: {:?}", domain, wait);
+            tokio::time::sleep(wait).await;
+        }
+
+        Ok(DomainPermit { domain, _permit: permit })
+    }
+
+    /// 获取域名的 robots.txt 规则（带缓存），获取失败视为全部允许
+    async fn robots_for(&self, url: &Url, domain: &str) -> Arc<RobotsRules> {
+        let ttl = Duration::from_secs(self.policy.robots_cache_ttl_secs);
+        if let Some((rules, fetched_at)) = self.robots_cache.read().await.get(domain) {
+            if fetched_at.elapsed() < ttl {
+                return rules.clone();
+            }
+        }
+
+        let robots_url = format!("{}://{}/robots.txt", url.scheme(), domain);
+        let rules = match self.client.get(&robots_url).send().await {
+            Ok(resp) if resp.status().is_success() => match resp.text().await {
+                Ok(body) => RobotsRules::parse(&body, &self.policy.user_agent),
+                Err(_) => RobotsRules::default(),
+            },
+            Ok(resp) => {
+                tracing::debug!("robots.txt for {} returned {}", domain, resp.status());
+                RobotsRules::default()
+            }
+            Err(e) => {
+                tracing::warn!("Failed to fetch robots.txt for {}: {}", domain, e);
+                RobotsRules::default()
+            }
+        };
+
+        let rules = Arc::new(rules);
+        self.robots_cache
+            .write()
+            .await
+            .insert(domain.to_string(), (rules.clone(), Instant::now()));
+        rules
+    }
+
+    /// 直接写入域名的 robots.txt 内容（预加载或测试）
+    pub async fn set_robots(&self, domain: &str, content: &str) {
+        let rules = RobotsRules::parse(content, &self.policy.user_agent);
+        self.robots_cache
+            .write()
+            .await
+            .insert(domain.to_string(), (Arc::new(rules), Instant::now()));
+    }
+}
+
+pub(crate) fn domain_key(url: &Url) -> Result<String, ScraperError> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| ScraperError::InvalidUrl(url.to_string()))?;
+    Ok(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "
+User-agent: *
+Disallow: /private/
+Allow: /private/public-page
+Disallow: /*.pdf$
+
+User-agent: FlowvexBot
+Disallow: /no-bots/
+Crawl-delay: 2
+";
+
+    #[test]
+    fn test_robots_wildcard_group() {
+        let rules = RobotsRules::parse(ROBOTS, "OtherBot");
+        assert!(rules.is_allowed("/"));
+        assert!(!rules.is_allowed("/private/secret"));
+        assert!(rules.is_allowed("/private/public-page"));
+        assert!(!rules.is_allowed("/files/report.pdf"));
+        assert!(rules.is_allowed("/files/report.pdf?x=1"));
+        assert!(rules.crawl_delay().is_none());
+    }
+
+    #[test]
+    fn test_robots_specific_group() {
+        let rules = RobotsRules::parse(ROBOTS, "FlowvexBot/1.0");
+        assert!(rules.is_allowed("/private/secret"));
+        assert!(!rules.is_allowed("/no-bots/page"));
+        assert_eq!(rules.crawl_delay(), Some(Duration::from_secs(2)));
+    }
+
+    #[tokio::test]
+    async fn test_enforcer_refuses_disallowed() {
+        let enforcer = PolicyEnforcer::new(ScraperPolicy {
+            min_delay_ms: 0,
+            ..Default::default()
+        });
+        enforcer.set_robots("example.com", "User-agent: *\nDisallow: /admin").await;
+
+        assert!(enforcer.acquire("https://example.com/index.html").await.is_ok());
+        assert!(matches!(
+            enforcer.acquire("https://example.com/admin/users").await,
+            Err(ScraperError::RobotsDisallowed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_enforcer_domain_concurrency() {
+        let enforcer = PolicyEnforcer::new(ScraperPolicy {
+            respect_robots_txt: false,
+            min_delay_ms: 0,
+            max_concurrent_per_domain: 1,
+            ..Default::default()
+        });
+
+        let permit = enforcer.acquire("https://example.com/a").await.unwrap();
+        let blocked = tokio::time::timeout(
+            Duration::from_millis(50),
+            enforcer.acquire("https://example.com/b"),
+        )
+        .await;
+        assert!(blocked.is_err());
+
+        // 其他域名不受影响
+        assert!(enforcer.acquire("https://example.org/a").await.is_ok());
+
+        drop(permit);
+        assert!(enforcer.acquire("https://example.com/b").await.is_ok());
+    }
+}
@@ -0,0 +1,356 @@
+//! 选择器自适应重试与修复
+//!
+//! 主选择器未匹配时依次尝试已批准的修复和动作配置的备用选择器（`fallbackSelectors`）；
+//! 仍未匹配且启用 `selectorRepair` 时，根据页面结构请求修复建议，
+//! 建议记录为待审核，审核通过后在同一站点的后续执行中优先使用
+
+use std::collections::HashMap;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::error::ScraperError;
+use crate::types::SelectorType;
+
+/// 页面结构摘要的最大行数
+const OUTLINE_MAX_LINES: usize = 300;
+
+/// 页面结构摘要中每个元素保留的文本长度（字符）
+const OUTLINE_TEXT_CHARS: usize = 40;
+
+/// 摘要中保留的元素属性，常用于编写稳定的选择器
+const OUTLINE_ATTRIBUTES: [&str; 6] = ["name", "type", "role", "aria-label", "data-testid", "href"];
+
+/// 动作配置的备用选择器，按顺序尝试
+pub fn fallback_selectors(config: &Value) -> Vec<String> {
+    config.get("fallbackSelectors")
+        .and_then(|v| v.as_array())
+        .map(|selectors| {
+            selectors.iter()
+                .filter_map(|s| s.as_str())
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 页面结构摘要：每行一个元素，缩进表示层级，附带 id、class、常用属性和自身文本
+pub fn page_outline(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let body = Selector::parse("body").expect("valid selector");
+    let mut lines = Vec::new();
+    if let Some(body) = document.select(&body).next() {
+        outline(body, 0, &mut lines);
+    }
+    lines.join("\n")
+}
+
+fn outline(element: ElementRef, depth: usize, lines: &mut Vec<String>) {
+    let value = element.value();
+    if lines.len() >= OUTLINE_MAX_LINES || matches!(value.name(), "script" | "style" | "noscript" | "svg") {
+        return;
+    }
+
+    let mut line = format!("{}{}", "  ".repeat(depth), value.name());
+    if let Some(id) = value.id() {
+        line.push('#');
+        line.push_str(id);
+    }
+    for class in value.classes() {
+        line.push('.');
+        line.push_str(class);
+    }
+    for attribute in OUTLINE_ATTRIBUTES {
+        if let Some(v) = value.attr(attribute) {
+            line.push_str(&format!("[{}=\"{}\"]", attribute, v));
+        }
+    }
+    let text = element.children()
+        .filter_map(|n| n.value().as_text())
+        .flat_map(|t| t.split_whitespace())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !text.is_empty() {
+        let text: String = text.chars().take(OUTLINE_TEXT_CHARS).collect();
+        line.push_str(&format!(" \"{}\"", text));
+    }
+    lines.push(line);
+
+    for child in element.children().filter_map(ElementRef::wrap) {
+        outline(child, depth + 1, lines);
+    }
+}
+
+/// 页面 URL 的主机名，修复记录按站点区分
+fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(String::from))
+        .unwrap_or_default()
+}
+
+/// 请求修复建议时提供的上下文
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairRequest {
+    pub url: String,
+    /// 失效的选择器
+    pub selector: String,
+    pub find_by: SelectorType,
+    /// 已尝试且未匹配的备用选择器
+    pub tried: Vec<String>,
+    /// 页面结构摘要，见 [`page_outline`]
+    pub outline: String,
+}
+
+/// 修复建议
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProposedSelector {
+    pub selector: String,
+    /// 选择该元素的理由
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// 根据页面结构提出替代选择器，如由 AI 模型实现
+#[async_trait]
+pub trait SelectorRepairer: Send + Sync {
+    async fn propose(&self, request: &RepairRequest) -> Result<ProposedSelector, ScraperError>;
+}
+
+/// 修复建议的审核状态
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RepairStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// 一条选择器修复记录
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectorRepair {
+    pub id: Uuid,
+    pub host: String,
+    /// 提出建议时的页面
+    pub url: String,
+    /// 失效的选择器
+    pub selector: String,
+    pub find_by: SelectorType,
+    pub proposed: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// 提出时建议的选择器能否匹配页面元素
+    pub verified: bool,
+    pub status: RepairStatus,
+    /// 同一建议被提出的次数
+    pub occurrences: u32,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reviewed_by: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+impl SelectorRepair {
+    pub fn new(request: &RepairRequest, proposal: ProposedSelector, verified: bool) -> Self {
+        SelectorRepair {
+            id: Uuid::new_v4(),
+            host: host_of(&request.url),
+            url: request.url.clone(),
+            selector: request.selector.clone(),
+            find_by: request.find_by.clone(),
+            proposed: proposal.selector,
+            reason: proposal.reason,
+            verified,
+            status: RepairStatus::Pending,
+            occurrences: 1,
+            created_at: Utc::now(),
+            reviewed_by: None,
+            reviewed_at: None,
+        }
+    }
+}
+
+/// 选择器修复记录存储
+#[derive(Default)]
+pub struct RepairStore {
+    repairs: RwLock<HashMap<Uuid, SelectorRepair>>,
+}
+
+impl RepairStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 保存修复建议；同一站点、同一选择器已有相同的待审核建议时只累加次数
+    pub async fn record(&self, repair: SelectorRepair) -> SelectorRepair {
+        let mut repairs = self.repairs.write().await;
+        let existing = repairs.values_mut().find(|r| {
+            r.status == RepairStatus::Pending
+                && r.host == repair.host
+                && r.selector == repair.selector
+                && r.proposed == repair.proposed
+        });
+        if let Some(existing) = existing {
+            existing.occurrences += 1;
+            existing.verified |= repair.verified;
+            return existing.clone();
+        }
+        repairs.insert(repair.id, repair.clone());
+        repair
+    }
+
+    /// 按状态筛选修复记录，最新的在前
+    pub async fn list(&self, status: Option<RepairStatus>) -> Vec<SelectorRepair> {
+        let mut repairs: Vec<SelectorRepair> = self.repairs.read().await
+            .values()
+            .filter(|r| status.is_none_or(|s| r.status == s))
+            .cloned()
+            .collect();
+        repairs.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+        repairs
+    }
+
+    pub async fn get(&self, id: Uuid) -> Option<SelectorRepair> {
+        self.repairs.read().await.get(&id).cloned()
+    }
+
+    /// 批准或拒绝修复建议，可修改之前的审核结果
+    pub async fn review(&self, id: Uuid, approve: bool, reviewer: Uuid) -> Option<SelectorRepair> {
+        let mut repairs = self.repairs.write().await;
+        let repair = repairs.get_mut(&id)?;
+        repair.status = if approve { RepairStatus::Approved } else { RepairStatus::Rejected };
+        repair.reviewed_by = Some(reviewer);
+        repair.reviewed_at = Some(Utc::now());
+        Some(repair.clone())
+    }
+
+    /// 页面所在站点上针对该选择器的修复记录，最新的在前
+    pub async fn for_selector(&self, url: &str, selector: &str) -> Vec<SelectorRepair> {
+        let host = host_of(url);
+        let mut repairs: Vec<SelectorRepair> = self.repairs.read().await
+            .values()
+            .filter(|r| r.host == host && r.selector == selector)
+            .cloned()
+            .collect();
+        repairs.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+        repairs
+    }
+}
+
+/// 实际使用的选择器来源
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SelectorSource {
+    /// 已批准的修复
+    ApprovedRepair,
+    /// 动作配置的备用选择器
+    Fallback,
+    /// 待审核的修复建议
+    Repair,
+}
+
+/// 主选择器未匹配时的选择结果，附在动作响应中
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectorResolution {
+    pub requested: String,
+    /// 实际使用的选择器；均未匹配时为 null，动作仍使用主选择器
+    pub used: Option<String>,
+    pub source: Option<SelectorSource>,
+    /// 已尝试且未匹配的替代选择器
+    pub tried: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repair_id: Option<Uuid>,
+}
+
+impl SelectorResolution {
+    pub fn new(requested: &str) -> Self {
+        SelectorResolution {
+            requested: requested.to_string(),
+            used: None,
+            source: None,
+            tried: Vec::new(),
+            repair_id: None,
+        }
+    }
+
+    /// 记录匹配的替代选择器
+    pub fn resolve(&mut self, selector: String, source: SelectorSource, repair_id: Option<Uuid>) {
+        self.used = Some(selector);
+        self.source = Some(source);
+        self.repair_id = repair_id;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_outline() {
+        let html = r#"<html><head><title>Shop</title></head><body>
+            <div id="main" class="page wide">
+              <h2 class="price-tag" data-testid="price">  $19.99  </h2>
+              <script>track()</script>
+              <a href="/cart">Cart</a>
+            </div>
+        </body></html>"#;
+        let outline = page_outline(html);
+        assert_eq!(
+            outline,
+            [
+                "body",
+                "  div#main.page.wide",
+                "    h2.price-tag[data-testid=\"price\"] \"$19.99\"",
+                "    a[href=\"/cart\"] \"Cart\"",
+            ].join("\n"),
+        );
+
+        let config = serde_json::json!({ "fallbackSelectors": [".price", " ", "[data-testid=price]"] });
+        assert_eq!(fallback_selectors(&config), vec![".price", "[data-testid=price]"]);
+        assert!(fallback_selectors(&serde_json::json!({})).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_repair_store_review() {
+        let store = RepairStore::new();
+        let request = RepairRequest {
+            url: "https://shop.example.com/item/1".to_string(),
+            selector: ".price".to_string(),
+            find_by: SelectorType::CssSelector,
+            tried: vec![],
+            outline: String::new(),
+        };
+        let proposal = || ProposedSelector { selector: "h2.price-tag".to_string(), reason: None };
+        let first = store.record(SelectorRepair::new(&request, proposal(), true)).await;
+        let again = store.record(SelectorRepair::new(&request, proposal(), false)).await;
+        assert_eq!(again.id, first.id);
+        assert_eq!(again.occurrences, 2);
+        assert!(again.verified);
+        assert_eq!(store.list(Some(RepairStatus::Pending)).await.len(), 1);
+
+        let reviewer = Uuid::new_v4();
+        let approved = store.review(first.id, true, reviewer).await.unwrap();
+        assert_eq!(approved.status, RepairStatus::Approved);
+        assert_eq!(approved.reviewed_by, Some(reviewer));
+        assert!(store.review(Uuid::new_v4(), true, reviewer).await.is_none());
+
+        // 同一站点的其他页面共享修复记录
+        assert_eq!(store.for_selector("https://shop.example.com/item/2", ".price").await.len(), 1);
+        assert!(store.for_selector("https://other.example.com/", ".price").await.is_empty());
+
+        // 已审核的建议再次提出时新建记录
+        let next = store.record(SelectorRepair::new(&request, proposal(), true)).await;
+        assert_ne!(next.id, first.id);
+        assert_eq!(store.list(None).await.len(), 2);
+    }
+}
@@ -0,0 +1,163 @@
+//! 截图后处理
+//!
+//! 在返回 base64 数据之前完成缩放和格式转换
+
+use std::io::Cursor;
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::ScraperError;
+use crate::types::{ScreenshotFormat, ScreenshotResult};
+
+/// 缩放配置，只给出一边时按原比例缩放
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ResizeOptions {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// 截图输出配置
+#[derive(Debug, Clone)]
+pub struct ScreenshotOptions {
+    pub format: ScreenshotFormat,
+    /// JPEG 质量 1-100；WebP 始终为无损编码，忽略该值
+    pub quality: u8,
+    pub resize: Option<ResizeOptions>,
+}
+
+impl Default for ScreenshotOptions {
+    fn default() -> Self {
+        ScreenshotOptions {
+            format: ScreenshotFormat::Png,
+            quality: 90,
+            resize: None,
+        }
+    }
+}
+
+impl ScreenshotOptions {
+    /// 从节点配置解析（format / quality / resize）
+    pub fn from_config(config: &Value) -> Result<Self, ScraperError> {
+        let format = match config.get("format") {
+            Some(v) => serde_json::from_value(v.clone())
+                .map_err(|_| ScraperError::ScreenshotFailed(format!("不支持的图片格式: {}", v)))?,
+            None => ScreenshotFormat::Png,
+        };
+        let quality = config.get("quality")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(90)
+            .clamp(1, 100) as u8;
+        let resize = match config.get("resize") {
+            Some(Value::Null) | None => None,
+            Some(v) => Some(
+                serde_json::from_value::<ResizeOptions>(v.clone())
+                    .map_err(|e| ScraperError::ScreenshotFailed(format!("无效的缩放配置: {}", e)))?,
+            ),
+        };
+
+        Ok(ScreenshotOptions { format, quality, resize })
+    }
+}
+
+/// 对原始截图进行缩放和格式转换，返回 base64 编码结果
+pub fn process_screenshot(
+    raw: &[u8],
+    options: &ScreenshotOptions,
+) -> Result<ScreenshotResult, ScraperError> {
+    let image = image::load_from_memory(raw)
+        .map_err(|e| ScraperError::ScreenshotFailed(format!("无法解码截图: {}", e)))?;
+    let image = resize(image, options.resize.as_ref())?;
+
+    let mut buffer = Cursor::new(Vec::new());
+    let encoded = match options.format {
+        ScreenshotFormat::Png => image.write_with_encoder(PngEncoder::new(&mut buffer)),
+        ScreenshotFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut buffer, options.quality)),
+        ScreenshotFormat::Webp => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut buffer)),
+    };
+    encoded.map_err(|e| ScraperError::ScreenshotFailed(format!("图片编码失败: {}", e)))?;
+
+    Ok(ScreenshotResult {
+        data: base64::engine::general_purpose::STANDARD.encode(buffer.into_inner()),
+        width: image.width(),
+        height: image.height(),
+        format: options.format.clone(),
+    })
+}
+
+fn resize(image: DynamicImage, options: Option<&ResizeOptions>) -> Result<DynamicImage, ScraperError> {
+    let Some(options) = options else {
+        return Ok(image);
+    };
+
+    let (width, height) = match (options.width, options.height) {
+        (None, None) => return Ok(image),
+        (Some(w), Some(h)) => (w, h),
+        (Some(w), None) => (w, scale(image.height(), w, image.width())),
+        (None, Some(h)) => (scale(image.width(), h, image.height()), h),
+    };
+    if width == 0 || height == 0 {
+        return Err(ScraperError::ScreenshotFailed("缩放尺寸必须大于 0".to_string()));
+    }
+
+    Ok(image.resize_exact(width, height, FilterType::Lanczos3))
+}
+
+/// 按比例计算另一边长度
+fn scale(value: u32, target: u32, reference: u32) -> u32 {
+    ((value as u64 * target as u64) / reference.max(1) as u64).max(1) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgba};
+
+    fn raw_png(width: u32, height: u32) -> Vec<u8> {
+        let image = ImageBuffer::from_pixel(width, height, Rgba([200u8, 100, 50, 255]));
+        let mut buffer = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(image)
+            .write_with_encoder(PngEncoder::new(&mut buffer))
+            .unwrap();
+        buffer.into_inner()
+    }
+
+    #[test]
+    fn test_resize_keeps_aspect_ratio() {
+        let options = ScreenshotOptions::from_config(&serde_json::json!({
+            "format": "jpeg",
+            "quality": 60,
+            "resize": { "width": 320 },
+        }))
+        .unwrap();
+        let result = process_screenshot(&raw_png(1280, 720), &options).unwrap();
+        assert_eq!((result.width, result.height), (320, 180));
+        assert_eq!(result.format, ScreenshotFormat::Jpeg);
+
+        let bytes = base64::engine::general_purpose::STANDARD.decode(&result.data).unwrap();
+        assert_eq!(&bytes[..2], &[0xFF, 0xD8]);
+    }
+
+    #[test]
+    fn test_webp_conversion() {
+        let options = ScreenshotOptions::from_config(&serde_json::json!({ "format": "webp" })).unwrap();
+        let result = process_screenshot(&raw_png(64, 32), &options).unwrap();
+        let bytes = base64::engine::general_purpose::STANDARD.decode(&result.data).unwrap();
+        assert_eq!(&bytes[..4], b"RIFF");
+        assert_eq!(&bytes[8..12], b"WEBP");
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        assert!(ScreenshotOptions::from_config(&serde_json::json!({ "format": "gif" })).is_err());
+        let options = ScreenshotOptions::from_config(&serde_json::json!({ "resize": { "width": 0 } })).unwrap();
+        assert!(process_screenshot(&raw_png(10, 10), &options).is_err());
+    }
+}
@@ -3,19 +3,14 @@
 use serde::{Deserialize, Serialize};
 
 /// 选择器类型
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub enum SelectorType {
+    #[default]
     CssSelector,
     Xpath,
 }
 
-impl Default for SelectorType {
-    fn default() -> Self {
-        SelectorType::CssSelector
-    }
-}
-
 /// 滚动模式
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
@@ -27,48 +22,35 @@ pub enum ScrollMode {
 }
 
 /// 等待条件
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub enum WaitCondition {
+    #[default]
     Visible,
     Hidden,
     Attached,
     Detached,
 }
 
-impl Default for WaitCondition {
-    fn default() -> Self {
-        WaitCondition::Visible
-    }
-}
-
 /// 截图模式
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub enum ScreenshotMode {
     FullPage,
+    #[default]
     Viewport,
     Element { selector: String },
-}
-
-impl Default for ScreenshotMode {
-    fn default() -> Self {
-        ScreenshotMode::Viewport
-    }
+    Clip { x: u32, y: u32, width: u32, height: u32 },
 }
 
 /// 截图格式
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum ScreenshotFormat {
+    #[default]
     Png,
     Jpeg,
-}
-
-impl Default for ScreenshotFormat {
-    fn default() -> Self {
-        ScreenshotFormat::Png
-    }
+    Webp,
 }
 
 /// 视口配置
@@ -87,6 +69,115 @@ impl Default for Viewport {
     }
 }
 
+/// 页面获取模式
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum FetchMode {
+    /// 始终使用浏览器
+    #[default]
+    Browser,
+    /// 仅使用 HTTP 请求 + HTML 解析
+    Http,
+    /// 优先 HTTP，页面需要 JavaScript 渲染时回退到浏览器
+    Auto,
+}
+
+/// 滑动方向
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SwipeDirection {
+    #[default]
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// 设备模拟配置
+///
+/// 对应 Playwright 的设备描述符，用于抓取仅移动端可见的页面和响应式布局
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceProfile {
+    pub name: String,
+    pub viewport: Viewport,
+    pub device_scale_factor: f64,
+    pub is_mobile: bool,
+    pub has_touch: bool,
+    pub user_agent: String,
+}
+
+impl DeviceProfile {
+    /// 内置设备名称列表
+    pub const BUILTIN: &'static [&'static str] = &[
+        "iPhone SE",
+        "iPhone 14",
+        "iPhone 14 Pro Max",
+        "iPad Mini",
+        "Pixel 5",
+        "Pixel 7",
+        "Galaxy S9+",
+        "Desktop Chrome",
+    ];
+
+    /// 按名称查找内置设备（忽略大小写和空白）
+    pub fn by_name(name: &str) -> Option<Self> {
+        let normalized: String = name
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_lowercase();
+
+        let (name, width, height, dpr, is_mobile, user_agent) = match normalized.as_str() {
+            "iphonese" => (
+                "iPhone SE", 375, 667, 2.0, true,
+                IOS_SAFARI_UA,
+            ),
+            "iphone14" => (
+                "iPhone 14", 390, 664, 3.0, true,
+                IOS_SAFARI_UA,
+            ),
+            "iphone14promax" => (
+                "iPhone 14 Pro Max", 430, 740, 3.0, true,
+                IOS_SAFARI_UA,
+            ),
+            "ipadmini" => (
+                "iPad Mini", 768, 1024, 2.0, true,
+                IPAD_SAFARI_UA,
+            ),
+            "pixel5" => (
+                "Pixel 5", 393, 727, 2.75, true,
+                "Mozilla/5.0 (Linux; Android 11; Pixel 5) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+            ),
+            "pixel7" => (
+                "Pixel 7", 412, 839, 2.625, true,
+                "Mozilla/5.0 (Linux; Android 14; Pixel 7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+            ),
+            "galaxys9+" => (
+                "Galaxy S9+", 320, 658, 4.5, true,
+                "Mozilla/5.0 (Linux; Android 10; SM-G965U) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+            ),
+            "desktopchrome" => (
+                "Desktop Chrome", 1280, 720, 1.0, false,
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+            ),
+            _ => return None,
+        };
+
+        Some(DeviceProfile {
+            name: name.to_string(),
+            viewport: Viewport { width, height },
+            device_scale_factor: dpr,
+            is_mobile,
+            has_touch: is_mobile,
+            user_agent: user_agent.to_string(),
+        })
+    }
+}
+
+const IOS_SAFARI_UA: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 16_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.0 Mobile/15E148 Safari/604.1";
+const IPAD_SAFARI_UA: &str = "Mozilla/5.0 (iPad; CPU OS 16_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.0 Mobile/15E148 Safari/604.1";
+
 /// 提取的文本结果
 #[derive(Debug, Clone, Serialize)]
 pub struct TextResult {
@@ -123,3 +214,77 @@ pub struct LoopIterationResult {
     pub total: usize,
     pub element_html: String,
 }
+
+/// 循环元素中的单个元素
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ElementHtml {
+    pub index: usize,
+    pub html: String,
+}
+
+/// 爬虫动作返回的结构化数据
+///
+/// 以 `dataType` 字段区分种类，其余字段与之前的 JSON 输出保持一致，
+/// 旧的表达式（如 `data.text`、`data.count`）无需修改
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "dataType", rename_all = "camelCase")]
+pub enum ScraperData {
+    /// 打开页面
+    Navigation {
+        title: String,
+        url: String,
+        mode: FetchMode,
+        /// 模拟的设备名
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        device: Option<String>,
+        /// HTTP 模式下的响应状态码
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<u16>,
+    },
+    /// 单个元素的文本
+    Text {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        html: Option<String>,
+    },
+    /// 所有匹配元素的文本（multiple）
+    Texts { texts: Vec<String>, count: usize },
+    /// 单个元素的属性值
+    Attribute { value: Option<String> },
+    /// 所有匹配元素的属性值（multiple）
+    Attributes { values: Vec<Option<String>>, count: usize },
+    /// 循环元素
+    Elements { elements: Vec<ElementHtml>, total: usize },
+    /// 截图，data 为 Base64 编码的图片
+    Screenshot {
+        data: String,
+        width: u32,
+        height: u32,
+        format: ScreenshotFormat,
+        mode: ScreenshotMode,
+    },
+    /// 脚本返回值与控制台输出
+    Script { result: serde_json::Value, logs: Vec<String> },
+    /// 按字段批量提取，键为字段名
+    Fields(serde_json::Map<String, serde_json::Value>),
+    /// 导出的 HAR
+    Har(crate::har::HarArtifact),
+    /// 关闭页面，开启 HAR 记录时附带 HAR
+    Closed {
+        closed: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        har: Option<crate::har::HarArtifact>,
+    },
+    /// 点击、输入、滚动、等待等交互动作的回执
+    Action(serde_json::Map<String, serde_json::Value>),
+}
+
+impl ScraperData {
+    /// 由 JSON 对象构造交互动作回执；非对象的值放在 `result` 字段下
+    pub fn action(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Object(map) => ScraperData::Action(map),
+            other => ScraperData::Action(serde_json::Map::from_iter([("result".to_string(), other)])),
+        }
+    }
+}
@@ -0,0 +1,422 @@
+//! 组合等待策略
+//!
+//! 支持 URL 匹配、网络空闲、元素文本等于/变化、JS 谓词等条件，
+//! 以 any/all 语义组合，每个策略可单独设置超时
+
+use std::future::Future;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::error::ScraperError;
+use crate::types::{SelectorType, WaitCondition};
+
+/// 元素当前状态
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ElementState {
+    pub visible: bool,
+    pub text: String,
+}
+
+/// 页面状态探针，由浏览器驱动实现
+pub trait PageProbe {
+    /// 当前页面 URL
+    fn current_url(&self) -> impl Future<Output = Result<String, ScraperError>> + Send;
+
+    /// 查询元素，未挂载到 DOM 时返回 None
+    fn element(
+        &self,
+        selector: &str,
+        find_by: &SelectorType,
+    ) -> impl Future<Output = Result<Option<ElementState>, ScraperError>> + Send;
+
+    /// 距离最近一次网络活动已空闲的时长，有进行中的请求时为 0
+    fn network_idle_for(&self) -> impl Future<Output = Result<Duration, ScraperError>> + Send;
+
+    /// 在页面中执行谓词脚本并返回其真值
+    fn evaluate_predicate(&self, script: &str) -> impl Future<Output = Result<bool, ScraperError>> + Send;
+}
+
+/// 单个等待策略
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum WaitStrategy {
+    #[serde(rename_all = "camelCase")]
+    Selector {
+        selector: String,
+        #[serde(default)]
+        condition: WaitCondition,
+        #[serde(default)]
+        find_by: SelectorType,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    /// URL 匹配模式，`*` 匹配任意字符，不含 `*` 时按子串匹配
+    #[serde(rename_all = "camelCase")]
+    UrlMatches {
+        pattern: String,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    #[serde(rename_all = "camelCase")]
+    NetworkIdle {
+        #[serde(default = "default_idle_ms")]
+        idle_ms: u64,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    #[serde(rename_all = "camelCase")]
+    TextEquals {
+        selector: String,
+        text: String,
+        #[serde(default)]
+        find_by: SelectorType,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    /// 元素文本与开始等待时不同
+    #[serde(rename_all = "camelCase")]
+    TextChanges {
+        selector: String,
+        #[serde(default)]
+        find_by: SelectorType,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    #[serde(rename_all = "camelCase")]
+    JsPredicate {
+        script: String,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+}
+
+fn default_idle_ms() -> u64 {
+    500
+}
+
+impl WaitStrategy {
+    fn timeout_ms(&self) -> Option<u64> {
+        match self {
+            WaitStrategy::Selector { timeout_ms, .. }
+            | WaitStrategy::UrlMatches { timeout_ms, .. }
+            | WaitStrategy::NetworkIdle { timeout_ms, .. }
+            | WaitStrategy::TextEquals { timeout_ms, .. }
+            | WaitStrategy::TextChanges { timeout_ms, .. }
+            | WaitStrategy::JsPredicate { timeout_ms, .. } => *timeout_ms,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            WaitStrategy::Selector { selector, condition, .. } => format!("{} {:?}", selector, condition),
+            WaitStrategy::UrlMatches { pattern, .. } => format!("url ~ {}", pattern),
+            WaitStrategy::NetworkIdle { idle_ms, .. } => format!("network idle {}ms", idle_ms),
+            WaitStrategy::TextEquals { selector, text, .. } => format!("{} text == {:?}", selector, text),
+            WaitStrategy::TextChanges { selector, .. } => format!("{} text changes", selector),
+            WaitStrategy::JsPredicate { .. } => "js predicate".to_string(),
+        }
+    }
+}
+
+/// 多个策略的组合方式
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum WaitMode {
+    #[default]
+    All,
+    Any,
+}
+
+/// 等待计划
+#[derive(Debug, Clone)]
+pub struct WaitPlan {
+    pub strategies: Vec<WaitStrategy>,
+    pub mode: WaitMode,
+    /// 策略未单独设置超时时使用
+    pub timeout_ms: u64,
+    pub poll_interval_ms: u64,
+}
+
+impl WaitPlan {
+    pub fn new(strategies: Vec<WaitStrategy>, mode: WaitMode, timeout_ms: u64) -> Self {
+        WaitPlan {
+            strategies,
+            mode,
+            timeout_ms,
+            poll_interval_ms: 100,
+        }
+    }
+}
+
+/// 单个策略的等待结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyOutcome {
+    pub strategy: String,
+    pub satisfied: bool,
+    pub elapsed_ms: u64,
+}
+
+/// 按计划轮询探针，直到组合条件满足或超时
+pub async fn wait_for<P: PageProbe>(probe: &P, plan: &WaitPlan) -> Result<Vec<StrategyOutcome>, ScraperError> {
+    if plan.strategies.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // 文本变化以开始等待时的文本为基准
+    let mut baselines = Vec::with_capacity(plan.strategies.len());
+    for strategy in &plan.strategies {
+        baselines.push(match strategy {
+            WaitStrategy::TextChanges { selector, find_by, .. } => {
+                probe.element(selector, find_by).await?.map(|e| e.text)
+            }
+            _ => None,
+        });
+    }
+
+    let started = Instant::now();
+    // None 表示仍在等待，Some(true) 满足，Some(false) 超时
+    let mut states: Vec<Option<bool>> = vec![None; plan.strategies.len()];
+    let mut elapsed = vec![0u64; plan.strategies.len()];
+
+    loop {
+        let now_ms = started.elapsed().as_millis() as u64;
+        for (i, strategy) in plan.strategies.iter().enumerate() {
+            if states[i].is_some() {
+                continue;
+            }
+            if check(probe, strategy, baselines[i].as_deref()).await? {
+                states[i] = Some(true);
+                elapsed[i] = now_ms;
+            } else if now_ms >= strategy.timeout_ms().unwrap_or(plan.timeout_ms) {
+                states[i] = Some(false);
+                elapsed[i] = now_ms;
+            }
+        }
+
+        let done = match plan.mode {
+            WaitMode::All => states.iter().all(|s| *s == Some(true)) || states.contains(&Some(false)),
+            WaitMode::Any => states.contains(&Some(true)) || states.iter().all(|s| *s == Some(false)),
+        };
+        if done {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(plan.poll_interval_ms)).await;
+    }
+
+    let outcomes: Vec<StrategyOutcome> = plan.strategies.iter()
+        .zip(states.iter().zip(elapsed))
+        .map(|(strategy, (state, elapsed_ms))| StrategyOutcome {
+            strategy: strategy.describe(),
+            satisfied: *state == Some(true),
+            elapsed_ms,
+        })
+        .collect();
+
+    let satisfied = match plan.mode {
+        WaitMode::All => outcomes.iter().all(|o| o.satisfied),
+        WaitMode::Any => outcomes.iter().any(|o| o.satisfied),
+    };
+    if !satisfied {
+        let pending: Vec<&str> = outcomes.iter()
+            .filter(|o| !o.satisfied)
+            .map(|o| o.strategy.as_str())
+            .collect();
+        return Err(ScraperError::SelectorTimeout(pending.join(", ")));
+    }
+    Ok(outcomes)
+}
+
+async fn check<P: PageProbe>(probe: &P, strategy: &WaitStrategy, baseline: Option<&str>) -> Result<bool, ScraperError> {
+    Ok(match strategy {
+        WaitStrategy::Selector { selector, condition, find_by, .. } => {
+            let element = probe.element(selector, find_by).await?;
+            match condition {
+                WaitCondition::Visible => element.is_some_and(|e| e.visible),
+                WaitCondition::Hidden => element.is_none_or(|e| !e.visible),
+                WaitCondition::Attached => element.is_some(),
+                WaitCondition::Detached => element.is_none(),
+            }
+        }
+        WaitStrategy::UrlMatches { pattern, .. } => url_matches(pattern, &probe.current_url().await?),
+        WaitStrategy::NetworkIdle { idle_ms, .. } => {
+            probe.network_idle_for().await? >= Duration::from_millis(*idle_ms)
+        }
+        WaitStrategy::TextEquals { selector, text, find_by, .. } => probe.element(selector, find_by).await?
+            .is_some_and(|e| e.text.trim() == text.trim()),
+        WaitStrategy::TextChanges { selector, find_by, .. } => {
+            let current = probe.element(selector, find_by).await?.map(|e| e.text);
+            current.as_deref() != baseline
+        }
+        WaitStrategy::JsPredicate { script, .. } => probe.evaluate_predicate(script).await?,
+    })
+}
+
+/// `*` 通配匹配；不含通配符时按子串匹配
+pub fn url_matches(pattern: &str, url: &str) -> bool {
+    if !pattern.contains('*') {
+        return url.contains(pattern);
+    }
+
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let mut rest = url;
+    for (i, part) in parts.iter().enumerate() {
+        if part.is_empty() {
+            continue;
+        }
+        if i == 0 {
+            let Some(stripped) = rest.strip_prefix(part) else {
+                return false;
+            };
+            rest = stripped;
+        } else if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        } else {
+            match rest.find(part) {
+                Some(pos) => rest = &rest[pos + part.len()..],
+                None => return false,
+            }
+        }
+    }
+    true
+}
+
+/// 上下文级自动等待：交互动作前等待目标元素满足条件
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoWait {
+    #[serde(default = "default_auto_wait_timeout")]
+    pub timeout_ms: u64,
+    #[serde(default)]
+    pub condition: WaitCondition,
+}
+
+fn default_auto_wait_timeout() -> u64 {
+    5000
+}
+
+impl Default for AutoWait {
+    fn default() -> Self {
+        AutoWait {
+            timeout_ms: default_auto_wait_timeout(),
+            condition: WaitCondition::Visible,
+        }
+    }
+}
+
+impl AutoWait {
+    /// 解析配置：`true` 使用默认值，对象为自定义配置
+    pub fn from_config(value: Option<&serde_json::Value>) -> Option<Self> {
+        match value? {
+            serde_json::Value::Bool(true) => Some(AutoWait::default()),
+            serde_json::Value::Bool(false) | serde_json::Value::Null => None,
+            other => serde_json::from_value(other.clone()).ok(),
+        }
+    }
+
+    /// 针对某个元素的等待计划
+    pub fn plan(&self, selector: &str, find_by: &SelectorType) -> WaitPlan {
+        WaitPlan::new(
+            vec![WaitStrategy::Selector {
+                selector: selector.to_string(),
+                condition: self.condition.clone(),
+                find_by: find_by.clone(),
+                timeout_ms: None,
+            }],
+            WaitMode::All,
+            self.timeout_ms,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// 第 N 次查询后文本变为 "done"，URL 随之跳转
+    struct FakeProbe {
+        polls: AtomicU32,
+        ready_after: u32,
+    }
+
+    impl FakeProbe {
+        fn ready(&self) -> bool {
+            self.polls.load(Ordering::SeqCst) >= self.ready_after
+        }
+    }
+
+    impl PageProbe for FakeProbe {
+        async fn current_url(&self) -> Result<String, ScraperError> {
+            Ok(if self.ready() { "https://shop.com/orders/42/done" } else { "https://shop.com/checkout" }.to_string())
+        }
+
+        async fn element(&self, selector: &str, _find_by: &SelectorType) -> Result<Option<ElementState>, ScraperError> {
+            let polls = self.polls.fetch_add(1, Ordering::SeqCst);
+            Ok(match selector {
+                "#status" => Some(ElementState {
+                    visible: true,
+                    text: if polls >= self.ready_after { "done" } else { "pending" }.to_string(),
+                }),
+                _ => None,
+            })
+        }
+
+        async fn network_idle_for(&self) -> Result<Duration, ScraperError> {
+            Ok(Duration::from_millis(100))
+        }
+
+        async fn evaluate_predicate(&self, script: &str) -> Result<bool, ScraperError> {
+            Ok(script == "true")
+        }
+    }
+
+    fn strategies(value: serde_json::Value) -> Vec<WaitStrategy> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_all_and_any_semantics() {
+        let probe = FakeProbe { polls: AtomicU32::new(0), ready_after: 3 };
+        let mut plan = WaitPlan::new(strategies(serde_json::json!([
+            { "type": "textChanges", "selector": "#status" },
+            { "type": "urlMatches", "pattern": "https://shop.com/orders/*/done" },
+            { "type": "jsPredicate", "script": "true" },
+        ])), WaitMode::All, 2000);
+        plan.poll_interval_ms = 5;
+        let outcomes = wait_for(&probe, &plan).await.unwrap();
+        assert!(outcomes.iter().all(|o| o.satisfied));
+
+        let mut plan = WaitPlan::new(strategies(serde_json::json!([
+            { "type": "selector", "selector": "#missing", "timeoutMs": 20 },
+            { "type": "networkIdle", "idleMs": 50 },
+        ])), WaitMode::Any, 2000);
+        plan.poll_interval_ms = 5;
+        let outcomes = wait_for(&probe, &plan).await.unwrap();
+        assert!(outcomes[1].satisfied);
+    }
+
+    #[tokio::test]
+    async fn test_per_strategy_timeout() {
+        let probe = FakeProbe { polls: AtomicU32::new(0), ready_after: u32::MAX };
+        let mut plan = WaitPlan::new(strategies(serde_json::json!([
+            { "type": "textEquals", "selector": "#status", "text": "done", "timeoutMs": 30 },
+            { "type": "networkIdle", "idleMs": 50 },
+        ])), WaitMode::All, 5000);
+        plan.poll_interval_ms = 5;
+        let started = std::time::Instant::now();
+        let err = wait_for(&probe, &plan).await.unwrap_err();
+        assert!(matches!(err, ScraperError::SelectorTimeout(ref s) if s.contains("#status")));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_url_matches() {
+        assert!(url_matches("*/orders/*/done", "https://shop.com/orders/42/done"));
+        assert!(url_matches("checkout", "https://shop.com/checkout?step=2"));
+        assert!(!url_matches("https://shop.com/orders/*", "https://other.com/orders/1"));
+        assert_eq!(AutoWait::from_config(Some(&serde_json::json!(true))), Some(AutoWait::default()));
+        assert_eq!(AutoWait::from_config(Some(&serde_json::json!({ "timeoutMs": 100 }))).unwrap().timeout_ms, 100);
+    }
+}
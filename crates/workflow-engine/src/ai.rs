@@ -0,0 +1,238 @@
+use ai_service::{AIClient, AIRequest, AIResponse, ChatMessage, ModelType, ResponseFormat, StreamEvent};
+use async_trait::async_trait;
+use futures::StreamExt;
+use common::types::JsonValue;
+use tokio::sync::mpsc;
+
+/// Longest text preview carried by a progress event, in characters
+pub const PREVIEW_CHARS: usize = 500;
+
+/// Generates the output of AI nodes on behalf of the executor
+#[async_trait]
+pub trait AiGenerator: Send + Sync {
+    /// Generate the output of an AI node from its rendered parameters.
+    ///
+    /// With `deltas`, the completion is streamed and each piece of text is
+    /// sent on the channel as it arrives; the returned output still holds the
+    /// whole completion.
+    async fn generate(
+        &self,
+        parameters: &JsonValue,
+        deltas: Option<mpsc::UnboundedSender<String>>,
+    ) -> Result<JsonValue, String>;
+}
+
+#[async_trait]
+impl AiGenerator for AIClient {
+    async fn generate(
+        &self,
+        parameters: &JsonValue,
+        deltas: Option<mpsc::UnboundedSender<String>>,
+    ) -> Result<JsonValue, String> {
+        let request = build_request(parameters)?;
+        let format = request.response_format.clone();
+        let Some(deltas) = deltas else {
+            return AIClient::generate(self, request).await
+                .map(|response| response_output(&response))
+                .map_err(|e| e.to_string());
+        };
+
+        let mut stream = self.generate_stream(request).await.map_err(|e| e.to_string())?;
+        while let Some(event) = stream.next().await {
+            match event.map_err(|e| e.to_string())? {
+                // The executor stops listening only once generation is over
+                StreamEvent::Delta(delta) => {
+                    let _ = deltas.send(delta);
+                }
+                StreamEvent::Done(mut response) => {
+                    // Streamed answers cannot be repaired, only checked
+                    if let Some(format) = &format {
+                        response.structured = Some(format.parse(&response.content)?);
+                    }
+                    return Ok(response_output(&response));
+                }
+            }
+        }
+        Err("completion stream ended early".to_string())
+    }
+}
+
+/// Build the AI request of an AI node from its rendered parameters.
+///
+/// Parameters: `prompt`, `messages` (earlier turns of a conversation, e.g.
+/// `[{"role": "user", "content": "..."}]`) and `system`, at least one of
+/// `prompt` and `messages`; `model` (default `gpt-4`), `temperature`,
+/// `max_tokens` and `top_p`. The prompt follows the messages as the user's
+/// next turn.
+///
+/// `response_format` holds the answer to a JSON Schema: either the schema
+/// itself or `{"schema": ..., "name": ..., "max_repairs": ...}`. The node
+/// output then carries the checked answer as `structured`.
+pub fn build_request(parameters: &JsonValue) -> Result<AIRequest, String> {
+    let prompt = parameters.get("prompt")
+        .and_then(JsonValue::as_str)
+        .filter(|prompt| !prompt.is_empty());
+    let history: Vec<ChatMessage> = match parameters.get("messages") {
+        None | Some(JsonValue::Null) => Vec::new(),
+        Some(messages) => serde_json::from_value(messages.clone()).map_err(|e| format!("invalid messages: {}", e))?,
+    };
+    if prompt.is_none() && history.is_empty() {
+        return Err("missing prompt parameter".to_string());
+    }
+    let model = match parameters.get("model") {
+        None | Some(JsonValue::Null) => ModelType::GPT4,
+        Some(model) => serde_json::from_value(model.clone()).map_err(|_| format!("unsupported model: {}", model))?,
+    };
+
+    let system = parameters.get("system").and_then(JsonValue::as_str).filter(|system| !system.is_empty());
+    let mut request = if history.is_empty() && system.is_none() {
+        AIRequest::new(model, prompt.unwrap_or_default().to_string())
+    } else {
+        let mut messages: Vec<ChatMessage> = system.map(ChatMessage::system).into_iter().collect();
+        messages.extend(history);
+        messages.extend(prompt.map(ChatMessage::user));
+        AIRequest::with_messages(model, messages)
+    };
+    request.temperature = parameters.get("temperature").and_then(JsonValue::as_f64).map(|t| t as f32);
+    request.max_tokens = parameters.get("max_tokens").and_then(JsonValue::as_u64).map(|t| t as u32);
+    request.top_p = parameters.get("top_p").and_then(JsonValue::as_f64).map(|p| p as f32);
+    request.response_format = match parameters.get("response_format") {
+        None | Some(JsonValue::Null) => None,
+        Some(format) if format.get("schema").is_some() => Some(
+            serde_json::from_value(format.clone()).map_err(|e| format!("invalid response_format: {}", e))?,
+        ),
+        Some(schema) if schema.is_object() => Some(ResponseFormat::new(schema.clone())),
+        Some(_) => return Err("response_format must be a JSON Schema object".to_string()),
+    };
+    Ok(request)
+}
+
+/// Node output of an AI response; `usage` is counted against the organization
+pub fn response_output(response: &AIResponse) -> JsonValue {
+    // A cached completion was paid for by an earlier run
+    let usage = if response.cached {
+        serde_json::json!({ "prompt_tokens": 0, "completion_tokens": 0, "provider_calls": 0 })
+    } else {
+        serde_json::json!({
+            "prompt_tokens": response.usage.prompt_tokens,
+            "completion_tokens": response.usage.completion_tokens,
+            "provider_calls": 1
+        })
+    };
+    let mut output = serde_json::json!({
+        "ai_response": response.content,
+        "model": response.model,
+        "finish_reason": response.finish_reason,
+        "tool_calls": response.tool_calls,
+        "cached": response.cached,
+        "usage": usage,
+    });
+    if let Some(structured) = &response.structured {
+        output["structured"] = structured.clone();
+    }
+    if let Some(fallback_from) = &response.fallback_from {
+        output["fallback_from"] = JsonValue::from(fallback_from.as_str());
+    }
+    output
+}
+
+/// Text streamed so far by an AI node
+#[derive(Debug, Default)]
+pub struct StreamProgress {
+    /// Deltas received; providers stream about one token per delta
+    pub tokens: u64,
+    text: String,
+    /// Byte offset in `text` up to which deltas were taken
+    taken: usize,
+}
+
+impl StreamProgress {
+    pub fn push(&mut self, delta: &str) {
+        self.tokens += 1;
+        self.text.push_str(delta);
+    }
+
+    /// Text received since the previous call, for clients appending the
+    /// output as it streams
+    pub fn take_delta(&mut self) -> String {
+        let delta = self.text[self.taken..].to_string();
+        self.taken = self.text.len();
+        delta
+    }
+
+    /// The end of the text so far, at most [`PREVIEW_CHARS`] characters
+    pub fn preview(&self) -> String {
+        let chars = self.text.chars().count();
+        self.text.chars().skip(chars.saturating_sub(PREVIEW_CHARS)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai_service::MessageRole;
+
+    #[test]
+    fn test_build_request() {
+        let request = build_request(&serde_json::json!({
+            "prompt": "Summarize",
+            "model": "claude-3-opus",
+            "max_tokens": 100
+        })).unwrap();
+        assert_eq!(request.model, ModelType::Claude3Opus);
+        assert_eq!(request.max_tokens, Some(100));
+        assert_eq!(build_request(&serde_json::json!({ "prompt": "Hi" })).unwrap().model, ModelType::GPT4);
+        assert!(build_request(&serde_json::json!({ "model": "gpt-4" })).is_err());
+        assert!(build_request(&serde_json::json!({ "prompt": "Hi", "model": "gpt-9" })).is_err());
+
+        let request = build_request(&serde_json::json!({
+            "system": "You are terse.",
+            "messages": [
+                { "role": "user", "content": "Hi" },
+                { "role": "assistant", "content": "Hello." }
+            ],
+            "prompt": "Bye"
+        })).unwrap();
+        let roles: Vec<MessageRole> = request.messages.iter().map(|m| m.role).collect();
+        assert_eq!(roles, vec![MessageRole::System, MessageRole::User, MessageRole::Assistant, MessageRole::User]);
+        assert_eq!(request.messages[3].content, "Bye");
+        assert!(build_request(&serde_json::json!({ "messages": [{ "role": "robot" }] })).is_err());
+    }
+
+    #[test]
+    fn test_build_request_response_format() {
+        let schema = serde_json::json!({ "type": "object", "properties": { "sentiment": { "type": "string" } } });
+        let request = build_request(&serde_json::json!({ "prompt": "Rate", "response_format": schema })).unwrap();
+        assert_eq!(request.response_format, Some(ResponseFormat::new(schema.clone())));
+
+        let request = build_request(&serde_json::json!({
+            "prompt": "Rate",
+            "response_format": { "name": "rating", "schema": schema, "max_repairs": 0 }
+        })).unwrap();
+        let format = request.response_format.unwrap();
+        assert_eq!((format.name.as_str(), format.max_repairs), ("rating", 0));
+        assert!(build_request(&serde_json::json!({ "prompt": "Rate", "response_format": "json" })).is_err());
+    }
+
+    #[test]
+    fn test_preview_keeps_the_latest_text() {
+        let mut progress = StreamProgress::default();
+        progress.push("héllo ");
+        assert_eq!(progress.preview(), "héllo ");
+        for _ in 0..200 {
+            progress.push("wörld ");
+        }
+        assert_eq!(progress.tokens, 201);
+        let preview = progress.preview();
+        assert_eq!(preview.chars().count(), PREVIEW_CHARS);
+        assert!(preview.ends_with("wörld "));
+
+        let mut progress = StreamProgress::default();
+        progress.push("Once ");
+        progress.push("upon");
+        assert_eq!(progress.take_delta(), "Once upon");
+        progress.push(" a time");
+        assert_eq!(progress.take_delta(), " a time");
+        assert_eq!(progress.take_delta(), "");
+    }
+}
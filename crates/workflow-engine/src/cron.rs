@@ -0,0 +1,285 @@
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+
+/// How far ahead `next_after` looks before giving up on an expression that
+/// never matches, e.g. `0 0 30 2 *`
+const SEARCH_DAYS: i64 = 5 * 366;
+
+/// A parsed cron expression.
+///
+/// Five fields (`minute hour day-of-month month day-of-week`) or six with a
+/// leading seconds field. Each field accepts `*`, values, ranges `a-b`,
+/// steps `*/n`, `a/n` and `a-b/n`, and comma-separated lists of those;
+/// day-of-month and day-of-week also accept `?`. Months and weekdays accept
+/// names (`JAN`-`DEC`, `SUN`-`SAT`), and weekday 7 is Sunday. The macros
+/// `@yearly`, `@annually`, `@monthly`, `@weekly`, `@daily`, `@midnight` and
+/// `@hourly` are supported.
+///
+/// As in Vixie cron, when both day-of-month and day-of-week are restricted
+/// a day matching either field matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpression {
+    seconds: u64,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+const MONTH_NAMES: [&str; 12] = ["JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"];
+const WEEKDAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+struct FieldSpec {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    /// Names for `min`, `min + 1`, ...
+    names: &'static [&'static str],
+}
+
+const SECONDS: FieldSpec = FieldSpec { name: "second", min: 0, max: 59, names: &[] };
+const MINUTES: FieldSpec = FieldSpec { name: "minute", min: 0, max: 59, names: &[] };
+const HOURS: FieldSpec = FieldSpec { name: "hour", min: 0, max: 23, names: &[] };
+const DAYS_OF_MONTH: FieldSpec = FieldSpec { name: "day of month", min: 1, max: 31, names: &[] };
+const MONTHS: FieldSpec = FieldSpec { name: "month", min: 1, max: 12, names: &MONTH_NAMES };
+const DAYS_OF_WEEK: FieldSpec = FieldSpec { name: "day of week", min: 0, max: 7, names: &WEEKDAY_NAMES };
+
+impl CronExpression {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expanded = match expression.trim().to_ascii_lowercase().as_str() {
+            "@yearly" | "@annually" => "0 0 0 1 1 *".to_string(),
+            "@monthly" => "0 0 0 1 * *".to_string(),
+            "@weekly" => "0 0 0 * * 0".to_string(),
+            "@daily" | "@midnight" => "0 0 0 * * *".to_string(),
+            "@hourly" => "0 0 * * * *".to_string(),
+            other if other.starts_with('@') => return Err(format!("unknown cron macro: {}", expression.trim())),
+            _ => expression.trim().to_string(),
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let (seconds, rest) = match fields.len() {
+            5 => ("0", &fields[..]),
+            6 => (fields[0], &fields[1..]),
+            n => return Err(format!("expected 5 or 6 cron fields, found {}", n)),
+        };
+
+        let mut days_of_week = parse_field(rest[4], &DAYS_OF_WEEK)?;
+        // 7 is an alias for Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(Self {
+            seconds: parse_field(seconds, &SECONDS)?,
+            minutes: parse_field(rest[0], &MINUTES)?,
+            hours: parse_field(rest[1], &HOURS)?,
+            days_of_month: parse_field(rest[2], &DAYS_OF_MONTH)?,
+            months: parse_field(rest[3], &MONTHS)?,
+            days_of_week,
+            any_day_of_month: is_wildcard(rest[2]),
+            any_day_of_week: is_wildcard(rest[4]),
+        })
+    }
+
+    /// Whether the expression matches a local wall-clock time
+    pub fn matches(&self, time: &NaiveDateTime) -> bool {
+        has(self.seconds, time.second())
+            && has(self.minutes, time.minute())
+            && has(self.hours, time.hour())
+            && has(self.months, time.month())
+            && self.matches_day(time.date())
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day_of_month = has(self.days_of_month, date.day());
+        let day_of_week = has(self.days_of_week, date.weekday().num_days_from_sunday());
+        if self.any_day_of_month || self.any_day_of_week {
+            day_of_month && day_of_week
+        } else {
+            day_of_month || day_of_week
+        }
+    }
+
+    /// The first time strictly after `after` matching the expression in
+    /// `after`'s time zone.
+    ///
+    /// Wall-clock times skipped by a daylight saving jump fire when the jump
+    /// ends; times repeated when clocks go back fire only on their first
+    /// occurrence.
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = after.timezone();
+        let mut time = after.naive_local().with_nanosecond(0)? + Duration::seconds(1);
+        let limit = time + Duration::days(SEARCH_DAYS);
+
+        while time <= limit {
+            if !has(self.months, time.month()) {
+                let (year, month) = if time.month() == 12 { (time.year() + 1, 1) } else { (time.year(), time.month() + 1) };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.matches_day(time.date()) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !has(self.hours, time.hour()) {
+                time = time.with_minute(0)?.with_second(0)? + Duration::hours(1);
+                continue;
+            }
+            if !has(self.minutes, time.minute()) {
+                time = time.with_second(0)? + Duration::minutes(1);
+                continue;
+            }
+            if !has(self.seconds, time.second()) {
+                time += Duration::seconds(1);
+                continue;
+            }
+
+            let candidate = match tz.from_local_datetime(&time) {
+                LocalResult::Single(instant) => Some(instant),
+                LocalResult::Ambiguous(earliest, _) => Some(earliest),
+                LocalResult::None => gap_end(&tz, time),
+            };
+            match candidate {
+                Some(instant) if instant > *after => return Some(instant),
+                _ => time += Duration::seconds(1),
+            }
+        }
+        None
+    }
+}
+
+/// First instant after a wall-clock time skipped by a daylight saving jump
+fn gap_end<Tz: TimeZone>(tz: &Tz, time: NaiveDateTime) -> Option<DateTime<Tz>> {
+    let mut probe = time.with_second(0)?;
+    // Gaps start and end on minute boundaries and last at most a day
+    for _ in 0..24 * 60 {
+        probe += Duration::minutes(1);
+        if let Some(instant) = tz.from_local_datetime(&probe).earliest() {
+            return Some(instant);
+        }
+    }
+    None
+}
+
+fn has(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+fn is_wildcard(field: &str) -> bool {
+    field.starts_with('*') || field == "?"
+}
+
+fn parse_field(field: &str, spec: &FieldSpec) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid {} step: {}", spec.name, part))?;
+                if step == 0 {
+                    return Err(format!("{} step must be positive: {}", spec.name, part));
+                }
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+
+        let (start, end) = match range {
+            "*" | "?" => (spec.min, spec.max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (parse_value(start, spec)?, parse_value(end, spec)?),
+                // `a/n` runs from `a` to the end of the field
+                None if step.is_some() => (parse_value(range, spec)?, spec.max),
+                None => {
+                    let value = parse_value(range, spec)?;
+                    (value, value)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("invalid {} range: {}", spec.name, part));
+        }
+
+        let step = step.unwrap_or(1) as usize;
+        for value in (start..=end).step_by(step) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(value: &str, spec: &FieldSpec) -> Result<u32, String> {
+    let upper = value.to_ascii_uppercase();
+    let parsed = match spec.names.iter().position(|name| *name == upper) {
+        Some(index) => spec.min + index as u32,
+        None => value.parse().map_err(|_| format!("invalid {}: {}", spec.name, value))?,
+    };
+    if parsed < spec.min || parsed > spec.max {
+        return Err(format!("{} out of range {}-{}: {}", spec.name, spec.min, spec.max, value));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use chrono_tz::America::New_York;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let weekdays = CronExpression::parse("30 9 * * MON-FRI").unwrap();
+        let monday = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap().and_hms_opt(9, 30, 0).unwrap();
+        assert!(weekdays.matches(&monday));
+        assert!(!weekdays.matches(&(monday + Duration::days(5))));
+        assert!(!weekdays.matches(&(monday + Duration::seconds(1))));
+
+        let every_ten_seconds = CronExpression::parse("*/10 * * * * *").unwrap();
+        assert!(every_ten_seconds.matches(&(monday + Duration::seconds(20))));
+        assert!(!every_ten_seconds.matches(&(monday + Duration::seconds(25))));
+
+        assert_eq!(CronExpression::parse("0 0 * * 7").unwrap(), CronExpression::parse("0 0 * * sun").unwrap());
+        assert_eq!(CronExpression::parse("@daily").unwrap(), CronExpression::parse("0 0 * * *").unwrap());
+        assert_eq!(CronExpression::parse("5/20 * * * *").unwrap(), CronExpression::parse("5,25,45 * * * *").unwrap());
+
+        for invalid in ["* * * *", "60 * * * *", "*/0 * * * *", "5-1 * * * *", "* * * FOO *", "@sometimes"] {
+            assert!(CronExpression::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_next_after() {
+        let every_quarter = CronExpression::parse("*/15 * * * *").unwrap();
+        assert_eq!(every_quarter.next_after(&utc("2024-01-01T10:14:59Z")), Some(utc("2024-01-01T10:15:00Z")));
+        // Strictly after: a run is never returned twice
+        assert_eq!(every_quarter.next_after(&utc("2024-01-01T10:15:00Z")), Some(utc("2024-01-01T10:30:00Z")));
+
+        // Either day field matches when both are restricted
+        let first_or_friday = CronExpression::parse("0 0 1 * FRI").unwrap();
+        assert_eq!(first_or_friday.next_after(&utc("2024-02-01T00:00:00Z")), Some(utc("2024-02-02T00:00:00Z")));
+
+        let leap_day = CronExpression::parse("0 12 29 2 *").unwrap();
+        assert_eq!(leap_day.next_after(&utc("2024-03-01T00:00:00Z")), Some(utc("2028-02-29T12:00:00Z")));
+        assert_eq!(CronExpression::parse("0 0 30 2 *").unwrap().next_after(&utc("2024-01-01T00:00:00Z")), None);
+    }
+
+    #[test]
+    fn test_next_after_across_daylight_saving() {
+        let nightly = CronExpression::parse("30 2 * * *").unwrap();
+        // 2:30 does not exist on 2024-03-10 in New York; the run happens at 3:00 EDT
+        let before = New_York.with_ymd_and_hms(2024, 3, 10, 0, 0, 0).unwrap();
+        assert_eq!(nightly.next_after(&before).unwrap().with_timezone(&Utc), utc("2024-03-10T07:00:00Z"));
+
+        // 1:30 happens twice on 2024-11-03; it fires once
+        let twice = CronExpression::parse("30 1 * * *").unwrap();
+        let before = New_York.with_ymd_and_hms(2024, 11, 3, 0, 0, 0).unwrap();
+        let first = twice.next_after(&before).unwrap();
+        assert_eq!(first.with_timezone(&Utc), utc("2024-11-03T05:30:00Z"));
+        assert_eq!(twice.next_after(&first).unwrap().with_timezone(&Utc), utc("2024-11-04T06:30:00Z"));
+    }
+}
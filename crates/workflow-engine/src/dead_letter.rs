@@ -0,0 +1,523 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common::error::WorkflowError;
+use common::types::{ExecutionContext, ExecutionResult, ExecutionState, JsonValue, Workflow};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration};
+use uuid::Uuid;
+
+use crate::executor::WorkflowExecutor;
+
+/// Variable carrying the dead letter a requeued run belongs to
+pub const DEAD_LETTER_VARIABLE: &str = "dead_letter_id";
+
+/// Variable carrying how many times the dead letter's runs failed before this one
+pub const REQUEUE_ATTEMPT_VARIABLE: &str = "requeue_attempt";
+
+/// How often the auto-requeue loop looks for due dead letters
+const AUTO_REQUEUE_TICK_SECS: u64 = 5;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadLetterStatus {
+    /// Waiting to be requeued
+    Pending,
+    /// A rerun is in progress
+    Requeued,
+    /// A rerun completed
+    Resolved,
+}
+
+/// A failed execution kept for inspection and requeueing, together with
+/// the failed reruns of it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// ID of the first failed execution
+    pub id: Uuid,
+    pub workflow_id: Uuid,
+    pub organization_id: Option<Uuid>,
+    /// Definition the failed run executed
+    pub workflow: Workflow,
+    pub status: DeadLetterStatus,
+    /// Failed runs, the original one included
+    pub attempts: u32,
+    /// Executions of the original run and its reruns, oldest first
+    pub execution_ids: Vec<Uuid>,
+    pub failed_node: Option<Uuid>,
+    pub error: String,
+    /// Variables the original run started with
+    pub input: HashMap<String, JsonValue>,
+    /// Variables at the latest failure, node outputs included
+    pub variables: HashMap<String, JsonValue>,
+    pub first_failed_at: DateTime<Utc>,
+    pub last_failed_at: DateTime<Utc>,
+    /// Rerun that completed
+    pub resolved_by: Option<Uuid>,
+}
+
+/// A failed run as reported by the executor
+#[derive(Debug, Clone)]
+pub struct FailedExecution {
+    pub execution_id: Uuid,
+    pub workflow: Workflow,
+    pub organization_id: Option<Uuid>,
+    pub failed_node: Option<Uuid>,
+    pub error: String,
+    pub input: HashMap<String, JsonValue>,
+    pub variables: HashMap<String, JsonValue>,
+}
+
+/// Durable storage of dead letters
+#[async_trait]
+pub trait DeadLetterStore: Send + Sync {
+    async fn save(&self, letter: &DeadLetter) -> Result<(), WorkflowError>;
+    async fn get(&self, id: Uuid) -> Result<Option<DeadLetter>, WorkflowError>;
+    /// Dead letters, most recently failed first
+    async fn list(
+        &self,
+        workflow_id: Option<Uuid>,
+        status: Option<DeadLetterStatus>,
+    ) -> Result<Vec<DeadLetter>, WorkflowError>;
+    async fn delete(&self, id: Uuid) -> Result<bool, WorkflowError>;
+}
+
+/// In-memory dead letter store (for development, replace with database in production)
+#[derive(Default)]
+pub struct InMemoryDeadLetterStore {
+    letters: RwLock<HashMap<Uuid, DeadLetter>>,
+}
+
+impl InMemoryDeadLetterStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DeadLetterStore for InMemoryDeadLetterStore {
+    async fn save(&self, letter: &DeadLetter) -> Result<(), WorkflowError> {
+        self.letters.write().await.insert(letter.id, letter.clone());
+        Ok(())
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Option<DeadLetter>, WorkflowError> {
+        Ok(self.letters.read().await.get(&id).cloned())
+    }
+
+    async fn list(
+        &self,
+        workflow_id: Option<Uuid>,
+        status: Option<DeadLetterStatus>,
+    ) -> Result<Vec<DeadLetter>, WorkflowError> {
+        let letters = self.letters.read().await;
+        let mut matching: Vec<DeadLetter> = letters.values()
+            .filter(|l| workflow_id.is_none_or(|id| l.workflow_id == id))
+            .filter(|l| status.is_none_or(|status| l.status == status))
+            .cloned()
+            .collect();
+        matching.sort_by_key(|l| std::cmp::Reverse(l.last_failed_at));
+        Ok(matching)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, WorkflowError> {
+        Ok(self.letters.write().await.remove(&id).is_some())
+    }
+}
+
+/// Record a failed run. Reruns of a dead letter, recognized by the
+/// `dead_letter_id` variable, count as another attempt of it; any other
+/// failure starts a new dead letter.
+pub async fn record_failure(store: &dyn DeadLetterStore, failure: FailedExecution) -> Result<DeadLetter, WorkflowError> {
+    let now = Utc::now();
+    let requeued_from = failure.input.get(DEAD_LETTER_VARIABLE)
+        .and_then(|id| id.as_str())
+        .and_then(|id| Uuid::parse_str(id).ok());
+    let existing = match requeued_from {
+        Some(id) => store.get(id).await?,
+        None => None,
+    };
+
+    let letter = match existing {
+        Some(mut letter) => {
+            letter.status = DeadLetterStatus::Pending;
+            letter.attempts += 1;
+            if !letter.execution_ids.contains(&failure.execution_id) {
+                letter.execution_ids.push(failure.execution_id);
+            }
+            letter.failed_node = failure.failed_node;
+            letter.error = failure.error;
+            letter.variables = failure.variables;
+            letter.last_failed_at = now;
+            letter
+        }
+        None => DeadLetter {
+            id: failure.execution_id,
+            workflow_id: failure.workflow.id,
+            organization_id: failure.organization_id,
+            workflow: failure.workflow,
+            status: DeadLetterStatus::Pending,
+            attempts: 1,
+            execution_ids: vec![failure.execution_id],
+            failed_node: failure.failed_node,
+            error: failure.error,
+            input: failure.input,
+            variables: failure.variables,
+            first_failed_at: now,
+            last_failed_at: now,
+            resolved_by: None,
+        },
+    };
+    store.save(&letter).await?;
+    Ok(letter)
+}
+
+/// When failed runs are requeued without being asked to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequeuePolicy {
+    /// Failed runs, the original one included, after which a dead letter is
+    /// left for manual handling
+    pub max_attempts: u32,
+    /// Wait after a failure before requeueing
+    pub delay_secs: u64,
+    /// Continue from the failed node instead of rerunning the whole workflow
+    #[serde(default)]
+    pub resume_from_failure: bool,
+}
+
+/// Lists, inspects and requeues dead letters
+#[derive(Clone)]
+pub struct DeadLetterQueue {
+    store: Arc<dyn DeadLetterStore>,
+    executor: Arc<WorkflowExecutor>,
+    policy: Option<RequeuePolicy>,
+}
+
+impl DeadLetterQueue {
+    /// `executor` reruns requeued executions; give it the same store through
+    /// `WorkflowExecutor::with_dead_letters` so every failure is recorded
+    pub fn new(store: Arc<dyn DeadLetterStore>, executor: Arc<WorkflowExecutor>) -> Self {
+        Self { store, executor, policy: None }
+    }
+
+    /// Requeue failed runs automatically once `start` is called
+    pub fn with_policy(mut self, policy: RequeuePolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    pub fn policy(&self) -> Option<&RequeuePolicy> {
+        self.policy.as_ref()
+    }
+
+    pub async fn list(
+        &self,
+        workflow_id: Option<Uuid>,
+        status: Option<DeadLetterStatus>,
+    ) -> Result<Vec<DeadLetter>, WorkflowError> {
+        self.store.list(workflow_id, status).await
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Option<DeadLetter>, WorkflowError> {
+        self.store.get(id).await
+    }
+
+    /// Drop a dead letter without rerunning it
+    pub async fn discard(&self, id: Uuid) -> Result<bool, WorkflowError> {
+        self.store.delete(id).await
+    }
+
+    /// Rerun a dead letter in the background, returning the rerun's
+    /// execution ID, or `None` for an unknown dead letter.
+    ///
+    /// With `resume_from_failure` the last failed execution continues from
+    /// its failed node with the variables it had; otherwise the workflow runs
+    /// again from the start with the original input, plus `dead_letter_id`
+    /// and `requeue_attempt`.
+    pub async fn requeue(&self, id: Uuid, resume_from_failure: bool) -> Result<Option<Uuid>, WorkflowError> {
+        if self.executor.tasks().is_shutting_down() {
+            return Err(WorkflowError::QueueFull(format!("shutting down, not requeueing dead letter {}", id)));
+        }
+        let Some(mut letter) = self.store.get(id).await? else {
+            return Ok(None);
+        };
+        if letter.status != DeadLetterStatus::Pending {
+            return Err(WorkflowError::InvalidState(format!(
+                "dead letter {} is {:?}, only pending ones can be requeued",
+                id, letter.status
+            )));
+        }
+        letter.status = DeadLetterStatus::Requeued;
+        self.store.save(&letter).await?;
+
+        let execution_id = match (resume_from_failure, letter.failed_node) {
+            (true, Some(failed_node)) => self.resume(letter, failed_node).await,
+            _ => self.restart(letter),
+        };
+        Ok(Some(execution_id))
+    }
+
+    fn restart(&self, letter: DeadLetter) -> Uuid {
+        let execution_id = Uuid::new_v4();
+        let mut variables = letter.input.clone();
+        variables.insert(DEAD_LETTER_VARIABLE.to_string(), serde_json::json!(letter.id));
+        variables.insert(REQUEUE_ATTEMPT_VARIABLE.to_string(), serde_json::json!(letter.attempts));
+        let ctx = ExecutionContext {
+            execution_id,
+            workflow_id: letter.workflow_id,
+            variables: variables.clone(),
+            state: ExecutionState::Pending,
+            started_at: Utc::now(),
+            current_node: None,
+        };
+
+        let queue = self.clone();
+        self.executor.tasks().spawn(async move {
+            let result = queue.executor.execute(&letter.workflow, ctx).await;
+            queue.finish(letter.id, execution_id, result, variables).await;
+        });
+        execution_id
+    }
+
+    async fn resume(&self, letter: DeadLetter, failed_node: Uuid) -> Uuid {
+        let execution_id = letter.execution_ids.last().copied().unwrap_or(letter.id);
+        // After a restart only the dead letter still knows the variables
+        if self.executor.get_context(execution_id).await.is_none() {
+            self.executor.load_context(ExecutionContext {
+                execution_id,
+                workflow_id: letter.workflow_id,
+                variables: letter.variables.clone(),
+                state: ExecutionState::Failed,
+                started_at: Utc::now(),
+                current_node: Some(failed_node),
+            }).await;
+        }
+
+        let queue = self.clone();
+        self.executor.tasks().spawn(async move {
+            let result = queue.executor.resume_from_failure(&letter.workflow, execution_id, failed_node).await;
+            let mut input = letter.input.clone();
+            input.insert(DEAD_LETTER_VARIABLE.to_string(), serde_json::json!(letter.id));
+            queue.finish(letter.id, execution_id, result, input).await;
+        });
+        execution_id
+    }
+
+    /// Settle a dead letter after a rerun. Failures are normally recorded by
+    /// the executor already; they are recorded here when it was not.
+    async fn finish(
+        &self,
+        id: Uuid,
+        execution_id: Uuid,
+        result: Result<ExecutionResult, WorkflowError>,
+        input: HashMap<String, JsonValue>,
+    ) {
+        let outcome = async {
+            let Some(mut letter) = self.store.get(id).await? else {
+                return Ok(());
+            };
+            match &result {
+                Ok(result) if result.state == ExecutionState::Completed => {
+                    letter.status = DeadLetterStatus::Resolved;
+                    letter.resolved_by = Some(execution_id);
+                    if !letter.execution_ids.contains(&execution_id) {
+                        letter.execution_ids.push(execution_id);
+                    }
+                    self.store.save(&letter).await
+                }
+                _ if letter.status == DeadLetterStatus::Requeued => {
+                    let error = match &result {
+                        Ok(result) => result.error.clone().unwrap_or_else(|| format!("{:?}", result.state)),
+                        Err(e) => e.to_string(),
+                    };
+                    let failed_node = result.as_ref().ok()
+                        .and_then(|r| r.output.as_ref())
+                        .and_then(|output| output.get("failed_node"))
+                        .and_then(|node| serde_json::from_value(node.clone()).ok())
+                        .or(letter.failed_node);
+                    let variables = match self.executor.get_context(execution_id).await {
+                        Some(ctx) => ctx.variables.read().await.clone(),
+                        None => letter.variables.clone(),
+                    };
+                    record_failure(self.store.as_ref(), FailedExecution {
+                        execution_id,
+                        workflow: letter.workflow,
+                        organization_id: letter.organization_id,
+                        failed_node,
+                        error,
+                        input,
+                        variables,
+                    }).await.map(|_| ())
+                }
+                _ => Ok(()),
+            }
+        };
+        if let Err(e) = outcome.await {
+            tracing::error!("Failed to update dead letter {}: {}", id, e);
+        }
+    }
+
+    /// Requeue pending dead letters the policy allows another attempt whose
+    /// delay has passed; returns the rerun execution IDs
+    pub async fn requeue_due(&self, now: DateTime<Utc>) -> Vec<Uuid> {
+        let Some(policy) = &self.policy else {
+            return Vec::new();
+        };
+        let pending = match self.store.list(None, Some(DeadLetterStatus::Pending)).await {
+            Ok(pending) => pending,
+            Err(e) => {
+                tracing::error!("Failed to list dead letters: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let delay = chrono::Duration::seconds(policy.delay_secs as i64);
+        let mut execution_ids = Vec::new();
+        for letter in pending {
+            if letter.attempts >= policy.max_attempts || letter.last_failed_at + delay > now {
+                continue;
+            }
+            match self.requeue(letter.id, policy.resume_from_failure).await {
+                Ok(Some(execution_id)) => {
+                    tracing::info!(
+                        "Requeued dead letter {} (attempt {} of {})",
+                        letter.id, letter.attempts + 1, policy.max_attempts
+                    );
+                    execution_ids.push(execution_id);
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to requeue dead letter {}: {}", letter.id, e),
+            }
+        }
+        execution_ids
+    }
+
+    /// Requeue due dead letters in the background until the handle is
+    /// aborted or the executor shuts down
+    pub fn start(&self) -> JoinHandle<()> {
+        let queue = self.clone();
+        self.executor.tasks().spawn(async move {
+            let mut tick = interval(Duration::from_secs(AUTO_REQUEUE_TICK_SECS));
+            loop {
+                tokio::select! {
+                    _ = tick.tick() => {}
+                    _ = queue.executor.tasks().cancelled() => break,
+                }
+                queue.requeue_due(Utc::now()).await;
+            }
+        })
+    }
+
+    /// Stop automatic requeues and wait up to `timeout` for running reruns;
+    /// returns whether they all finished in time
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.executor.tasks().shutdown(timeout).await
+    }
+}
+
+impl Default for DeadLetterQueue {
+    fn default() -> Self {
+        Self::new(Arc::new(InMemoryDeadLetterStore::new()), Arc::new(WorkflowExecutor::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::types::{ActionType, Node, NodeConfig, NodeType, Position, WORKFLOW_SCHEMA_VERSION};
+
+    /// One HTTP action whose body only renders when `payload` holds JSON
+    fn workflow() -> Workflow {
+        Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Import".to_string(),
+            description: None,
+            nodes: vec![Node {
+                id: Uuid::new_v4(),
+                node_type: NodeType::Action { action_type: ActionType::Http },
+                config: NodeConfig {
+                    parameters: HashMap::from([("body".to_string(), serde_json::json!("{{ payload | json_decode }}"))]),
+                    ..Default::default()
+                },
+                position: Position { x: 0.0, y: 0.0 },
+                inputs: vec![],
+                outputs: vec![],
+            }],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        }
+    }
+
+    async fn settled(queue: &DeadLetterQueue, id: Uuid) -> DeadLetter {
+        for _ in 0..200 {
+            let letter = queue.get(id).await.unwrap().unwrap();
+            if letter.status != DeadLetterStatus::Requeued {
+                return letter;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("dead letter {} was not settled", id);
+    }
+
+    #[tokio::test]
+    async fn test_failed_runs_are_recorded_and_requeued() {
+        let store: Arc<dyn DeadLetterStore> = Arc::new(InMemoryDeadLetterStore::new());
+        let executor = Arc::new(WorkflowExecutor::new().with_dead_letters(store.clone()));
+        let queue = DeadLetterQueue::new(store, executor.clone()).with_policy(RequeuePolicy {
+            max_attempts: 2,
+            delay_secs: 0,
+            resume_from_failure: false,
+        });
+
+        let workflow = workflow();
+        let execution_id = Uuid::new_v4();
+        let ctx = ExecutionContext {
+            execution_id,
+            workflow_id: workflow.id,
+            variables: HashMap::from([("payload".to_string(), serde_json::json!("not json"))]),
+            state: ExecutionState::Pending,
+            started_at: Utc::now(),
+            current_node: None,
+        };
+        let result = executor.execute(&workflow, ctx).await.unwrap();
+        assert_eq!(result.state, ExecutionState::Failed);
+
+        let letter = queue.get(execution_id).await.unwrap().unwrap();
+        assert_eq!(letter.status, DeadLetterStatus::Pending);
+        assert_eq!(letter.attempts, 1);
+        assert_eq!(letter.failed_node, Some(workflow.nodes[0].id));
+        assert_eq!(queue.list(Some(workflow.id), None).await.unwrap().len(), 1);
+        assert!(queue.list(Some(Uuid::new_v4()), None).await.unwrap().is_empty());
+
+        // The policy reruns the workflow from the start; with the same input it fails again
+        let rerun = queue.requeue_due(Utc::now()).await;
+        assert_eq!(rerun.len(), 1);
+        let letter = settled(&queue, execution_id).await;
+        assert_eq!(letter.status, DeadLetterStatus::Pending);
+        assert_eq!(letter.attempts, 2);
+        assert_eq!(letter.execution_ids, vec![execution_id, rerun[0]]);
+        assert_eq!(letter.input["payload"], "not json");
+
+        // Out of attempts: only a manual requeue runs it again
+        assert!(queue.requeue_due(Utc::now()).await.is_empty());
+        let ctx = executor.get_context(rerun[0]).await.unwrap();
+        ctx.variables.write().await.insert("payload".to_string(), serde_json::json!("{\"id\": 1}"));
+        assert_eq!(queue.requeue(execution_id, true).await.unwrap(), Some(rerun[0]));
+        // Requeued or already resolved, either way not pending
+        assert!(queue.requeue(execution_id, true).await.is_err());
+
+        let letter = settled(&queue, execution_id).await;
+        assert_eq!(letter.status, DeadLetterStatus::Resolved);
+        assert_eq!(letter.resolved_by, Some(rerun[0]));
+
+        assert!(queue.discard(execution_id).await.unwrap());
+        assert_eq!(queue.requeue(execution_id, false).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,392 @@
+use common::types::{DataType, Edge, JsonValue, Node, Workflow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+use crate::expression::{collect_templates, template_expressions, Expression, PathSegment};
+
+/// A changed value, addressed by a dotted path
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldChange {
+    pub path: String,
+    pub before: JsonValue,
+    pub after: JsonValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeChange {
+    pub node_id: Uuid,
+    pub changes: Vec<FieldChange>,
+}
+
+/// A change that makes nodes of the new version read outputs that are gone
+/// or no longer fit; runs of the new version fail at those nodes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BreakingChange {
+    /// A removed node whose outputs are still read through edges or
+    /// `node_<id>` variables
+    RemovedNode { node_id: Uuid, referenced_by: Vec<Uuid> },
+    /// A removed output port still connected, or read as `input.<port>` by a
+    /// node it used to feed
+    RemovedOutput { node_id: Uuid, port: String, referenced_by: Vec<Uuid> },
+    /// An output port whose new type connected input ports cannot accept
+    ChangedOutputType {
+        node_id: Uuid,
+        port: String,
+        before: DataType,
+        after: DataType,
+        referenced_by: Vec<Uuid>,
+    },
+}
+
+/// Differences between two workflow definitions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkflowDiff {
+    /// Workflow-level fields: name, description, variables
+    pub fields: Vec<FieldChange>,
+    pub added_nodes: Vec<Node>,
+    pub removed_nodes: Vec<Node>,
+    pub changed_nodes: Vec<NodeChange>,
+    pub added_edges: Vec<Edge>,
+    pub removed_edges: Vec<Edge>,
+    #[serde(default)]
+    pub breaking_changes: Vec<BreakingChange>,
+}
+
+impl WorkflowDiff {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+            && self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.changed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        !self.breaking_changes.is_empty()
+    }
+}
+
+/// Compute the changes from `before` to `after`
+pub fn diff_workflows(before: &Workflow, after: &Workflow) -> WorkflowDiff {
+    let mut diff = WorkflowDiff::default();
+
+    let fields = |w: &Workflow| serde_json::json!({
+        "name": w.name,
+        "description": w.description,
+        "variables": w.variables,
+    });
+    json_changes("", &fields(before), &fields(after), &mut diff.fields);
+
+    for node in &after.nodes {
+        match before.nodes.iter().find(|n| n.id == node.id) {
+            None => diff.added_nodes.push(node.clone()),
+            Some(old) => {
+                let mut changes = Vec::new();
+                json_changes("", &to_json(old), &to_json(node), &mut changes);
+                if !changes.is_empty() {
+                    diff.changed_nodes.push(NodeChange { node_id: node.id, changes });
+                }
+            }
+        }
+    }
+    diff.removed_nodes = before.nodes.iter()
+        .filter(|n| !after.nodes.iter().any(|m| m.id == n.id))
+        .cloned()
+        .collect();
+
+    // Edges are compared by their endpoints; IDs may be regenerated by editors
+    let same = |a: &Edge, b: &Edge| {
+        a.source == b.source && a.source_handle == b.source_handle
+            && a.target == b.target && a.target_handle == b.target_handle
+    };
+    diff.added_edges = after.edges.iter()
+        .filter(|e| !before.edges.iter().any(|o| same(o, e)))
+        .cloned()
+        .collect();
+    diff.removed_edges = before.edges.iter()
+        .filter(|e| !after.edges.iter().any(|o| same(o, e)))
+        .cloned()
+        .collect();
+
+    diff.breaking_changes = breaking_changes(before, after);
+    diff
+}
+
+/// Outputs of `before` that `after` removed or retyped while still reading them
+fn breaking_changes(before: &Workflow, after: &Workflow) -> Vec<BreakingChange> {
+    let mut breaking = Vec::new();
+    for old in &before.nodes {
+        let Some(new) = after.nodes.iter().find(|n| n.id == old.id) else {
+            let handles: BTreeSet<&str> = before.edges.iter()
+                .filter(|e| e.source == old.id)
+                .map(|e| e.source_handle.as_str())
+                .collect();
+            let mut referenced_by: BTreeSet<Uuid> = handles.into_iter()
+                .flat_map(|handle| output_readers(before, after, old.id, handle))
+                .collect();
+            let variable = format!("node_{}", old.id);
+            referenced_by.extend(after.nodes.iter()
+                .filter(|n| node_references(n).iter().any(|path| reads_variable(path, &variable)))
+                .map(|n| n.id));
+            if !referenced_by.is_empty() {
+                breaking.push(BreakingChange::RemovedNode {
+                    node_id: old.id,
+                    referenced_by: referenced_by.into_iter().collect(),
+                });
+            }
+            continue;
+        };
+
+        for port in &old.outputs {
+            match new.outputs.iter().find(|p| p.name == port.name) {
+                None => {
+                    let referenced_by = output_readers(before, after, old.id, &port.name);
+                    if !referenced_by.is_empty() {
+                        breaking.push(BreakingChange::RemovedOutput {
+                            node_id: old.id,
+                            port: port.name.clone(),
+                            referenced_by: referenced_by.into_iter().collect(),
+                        });
+                    }
+                }
+                Some(retyped) if retyped.data_type != port.data_type => {
+                    let referenced_by: Vec<Uuid> = after.edges.iter()
+                        .filter(|e| e.source == old.id && e.source_handle == port.name)
+                        .filter(|e| match target_port_type(after, e) {
+                            Some(accepted) => port.data_type.can_coerce_to(accepted) && !retyped.data_type.can_coerce_to(accepted),
+                            None => false,
+                        })
+                        .map(|e| e.target)
+                        .collect::<BTreeSet<_>>()
+                        .into_iter()
+                        .collect();
+                    if !referenced_by.is_empty() {
+                        breaking.push(BreakingChange::ChangedOutputType {
+                            node_id: old.id,
+                            port: port.name.clone(),
+                            before: port.data_type.clone(),
+                            after: retyped.data_type.clone(),
+                            referenced_by,
+                        });
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    breaking
+}
+
+/// Nodes of `after` still reading output `handle` of `node_id`: targets of
+/// edges left on it, and nodes it fed in `before` that read `input.<handle>`
+/// without another edge providing it
+fn output_readers(before: &Workflow, after: &Workflow, node_id: Uuid, handle: &str) -> BTreeSet<Uuid> {
+    let mut readers: BTreeSet<Uuid> = after.edges.iter()
+        .filter(|e| e.source == node_id && e.source_handle == handle)
+        .map(|e| e.target)
+        .collect();
+    for edge in before.edges.iter().filter(|e| e.source == node_id && e.source_handle == handle) {
+        let Some(target) = after.nodes.iter().find(|n| n.id == edge.target) else {
+            continue;
+        };
+        let provided = after.edges.iter()
+            .any(|e| e.target == target.id && e.source_handle == handle && e.source != node_id);
+        if !provided && node_references(target).iter().any(|path| reads_input(path, handle)) {
+            readers.insert(target.id);
+        }
+    }
+    readers
+}
+
+/// Type of the input port an edge delivers to: the one named by the target
+/// handle, or the first input when the handle is empty
+fn target_port_type<'a>(workflow: &'a Workflow, edge: &Edge) -> Option<&'a DataType> {
+    let target = workflow.nodes.iter().find(|n| n.id == edge.target)?;
+    let port = if edge.target_handle.is_empty() {
+        target.inputs.first()
+    } else {
+        target.inputs.iter().find(|p| p.name == edge.target_handle || p.id == edge.target_handle)
+    };
+    port.map(|p| &p.data_type)
+}
+
+/// Paths read by the expressions in a node's parameters; invalid
+/// expressions are left to the validator
+fn node_references(node: &Node) -> Vec<Vec<PathSegment>> {
+    let mut templates = Vec::new();
+    for (name, value) in &node.config.parameters {
+        collect_templates(value, name.clone(), &mut templates);
+    }
+    templates.into_iter()
+        .filter_map(|(_, template)| template_expressions(template).ok())
+        .flatten()
+        .filter_map(|source| Expression::parse(source).ok())
+        .flat_map(|expression| expression.paths().into_iter().map(<[PathSegment]>::to_vec).collect::<Vec<_>>())
+        .collect()
+}
+
+fn reads_input(path: &[PathSegment], handle: &str) -> bool {
+    matches!(path, [PathSegment::Key(scope), PathSegment::Key(key), ..] if scope == "input" && key == handle)
+}
+
+/// Whether a path reads the variable, at the top level or under `vars`
+fn reads_variable(path: &[PathSegment], variable: &str) -> bool {
+    match path {
+        [PathSegment::Key(scope), PathSegment::Key(key), ..] if scope == "vars" => key == variable,
+        [PathSegment::Key(key), ..] => key == variable,
+        _ => false,
+    }
+}
+
+fn to_json(node: &Node) -> JsonValue {
+    serde_json::to_value(node).unwrap_or(JsonValue::Null)
+}
+
+/// Collect leaf-level changes between two JSON values
+fn json_changes(path: &str, before: &JsonValue, after: &JsonValue, out: &mut Vec<FieldChange>) {
+    match (before, after) {
+        (JsonValue::Object(old), JsonValue::Object(new)) => {
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                json_changes(
+                    &child,
+                    old.get(key).unwrap_or(&JsonValue::Null),
+                    new.get(key).unwrap_or(&JsonValue::Null),
+                    out,
+                );
+            }
+        }
+        _ if before != after => out.push(FieldChange {
+            path: path.to_string(),
+            before: before.clone(),
+            after: after.clone(),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use common::types::{ActionType, NodeConfig, NodeType, Port, Position, TriggerType, WORKFLOW_SCHEMA_VERSION};
+    use std::collections::HashMap;
+
+    fn port(name: &str, data_type: DataType) -> Port {
+        Port { id: name.to_string(), name: name.to_string(), data_type }
+    }
+
+    fn edge(source: &Node, source_handle: &str, target: &Node, target_handle: &str) -> Edge {
+        Edge {
+            id: Uuid::new_v4(),
+            source: source.id,
+            source_handle: source_handle.to_string(),
+            target: target.id,
+            target_handle: target_handle.to_string(),
+        }
+    }
+
+    fn workflow() -> Workflow {
+        let node = |node_type| Node {
+            id: Uuid::new_v4(),
+            node_type,
+            config: NodeConfig::default(),
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![],
+        };
+        let trigger = node(NodeType::Trigger { trigger_type: TriggerType::Webhook });
+        let action = node(NodeType::Action { action_type: ActionType::Http });
+        Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Orders".to_string(),
+            description: None,
+            edges: vec![edge(&trigger, "output", &action, "input")],
+            nodes: vec![trigger, action],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_diff_workflows() {
+        let before = workflow();
+        let mut after = before.clone();
+        after.name = "Orders v2".to_string();
+        let removed = after.nodes.remove(1);
+        after.edges.clear();
+
+        let diff = diff_workflows(&before, &after);
+        assert_eq!(diff.fields, [FieldChange {
+            path: "name".to_string(),
+            before: serde_json::json!("Orders"),
+            after: serde_json::json!("Orders v2"),
+        }]);
+        assert_eq!(diff.removed_nodes[0].id, removed.id);
+        assert_eq!(diff.removed_edges.len(), 1);
+        assert!(diff.added_nodes.is_empty() && diff.changed_nodes.is_empty());
+        // Nothing reads the removed node's outputs
+        assert!(!diff.is_breaking());
+        assert!(diff_workflows(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn test_breaking_changes() {
+        let mut before = workflow();
+        before.nodes[0].outputs = vec![port("output", DataType::Object), port("headers", DataType::Object)];
+        before.nodes[1].inputs = vec![port("input", DataType::Object)];
+        let fetch = Node {
+            id: Uuid::new_v4(),
+            node_type: NodeType::Action { action_type: ActionType::Http },
+            config: NodeConfig::default(),
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![port("response", DataType::Object)],
+        };
+        let mut notify = before.nodes[1].clone();
+        notify.id = Uuid::new_v4();
+        notify.config.parameters.insert(
+            "body".to_string(),
+            serde_json::json!({ "status": format!("{{{{ vars['node_{}'].status }}}}", fetch.id), "auth": "{{ input.headers.token }}" }),
+        );
+        before.edges.push(edge(&before.nodes[0], "headers", &notify, "input"));
+        before.edges.push(edge(&before.nodes[1], "output", &fetch, "input"));
+        before.edges.push(edge(&fetch, "response", &notify, "input"));
+        before.nodes.extend([fetch.clone(), notify.clone()]);
+
+        // The trigger's headers port goes away with its edge, while `notify`
+        // still reads it; `fetch` is deleted while `notify` reads its variable
+        let mut after = before.clone();
+        after.nodes[0].outputs.truncate(1);
+        after.nodes.retain(|n| n.id != fetch.id);
+        after.edges.retain(|e| e.source_handle != "headers" && e.source != fetch.id && e.target != fetch.id);
+        let diff = diff_workflows(&before, &after);
+        assert_eq!(diff.breaking_changes, [
+            BreakingChange::RemovedOutput { node_id: before.nodes[0].id, port: "headers".to_string(), referenced_by: vec![notify.id] },
+            BreakingChange::RemovedNode { node_id: fetch.id, referenced_by: vec![notify.id] },
+        ]);
+
+        // A retyped output is breaking only where a connected input rejects it
+        let mut after = before.clone();
+        after.nodes[0].outputs[0].data_type = DataType::String;
+        let diff = diff_workflows(&before, &after);
+        assert_eq!(diff.breaking_changes, [BreakingChange::ChangedOutputType {
+            node_id: before.nodes[0].id,
+            port: "output".to_string(),
+            before: DataType::Object,
+            after: DataType::String,
+            referenced_by: vec![before.nodes[1].id],
+        }]);
+        after.nodes[1].inputs[0].data_type = DataType::Any;
+        assert!(!diff_workflows(&before, &after).is_breaking());
+    }
+}
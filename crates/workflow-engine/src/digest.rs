@@ -0,0 +1,836 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use chrono_tz::Tz;
+use common::error::{ErrorCode, ErrorInfo};
+use common::types::{ApiRequest, ExecutionState, HttpMethod, Priority, ResourceUsage, RetryConfig};
+use integration_service::CredentialStore;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+use uuid::Uuid;
+
+use crate::cron::CronExpression;
+use crate::history::{ExecutionHistory, ExecutionRecord};
+use crate::http::HttpDispatcher;
+use crate::revisions::RevisionStore;
+use crate::tasks::TaskSupervisor;
+
+/// Daily at 08:00 in the subscription's time zone
+pub const DEFAULT_DIGEST_SCHEDULE: &str = "0 8 * * *";
+
+/// How often the background loop checks for due digests
+const DIGEST_TICK_SECS: u64 = 60;
+
+/// Failed executions listed with links; the counts cover all of them
+const MAX_FAILED_RUNS: usize = 20;
+
+/// Most frequent error messages listed
+const MAX_NOTABLE_ERRORS: usize = 5;
+
+/// Error messages are grouped on their first characters, so long messages
+/// differing only in a trailing detail count as one
+const ERROR_MESSAGE_CHARS: usize = 200;
+
+/// Period of the first digest of a subscription
+const FIRST_PERIOD_HOURS: i64 = 24;
+
+/// Timeout of a Slack webhook call
+const SLACK_TIMEOUT_SECS: u64 = 10;
+
+/// Where a digest is delivered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DigestChannel {
+    Email { to: String },
+    /// A Slack incoming webhook
+    Slack { webhook_url: String },
+}
+
+/// Whose workflows a digest covers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DigestScope {
+    /// Workflows the subscriber owns
+    User,
+    /// Every execution run for the organization
+    Organization { organization_id: Uuid },
+}
+
+/// What a subscription reports, when and where
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestSettings {
+    pub scope: DigestScope,
+    /// Cron expression of the delivery times, see [`CronExpression`]
+    #[serde(default = "default_schedule")]
+    pub schedule: String,
+    #[serde(default = "default_timezone")]
+    pub timezone: Tz,
+    pub channels: Vec<DigestChannel>,
+    /// Also deliver digests with no runs and no expiring credentials
+    #[serde(default)]
+    pub send_when_empty: bool,
+    /// Credentials expiring within this many days are listed
+    #[serde(default = "default_credential_lookahead_days")]
+    pub credential_lookahead_days: u32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_schedule() -> String {
+    DEFAULT_DIGEST_SCHEDULE.to_string()
+}
+
+fn default_timezone() -> Tz {
+    Tz::UTC
+}
+
+fn default_credential_lookahead_days() -> u32 {
+    7
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl DigestSettings {
+    pub fn validate(&self) -> Result<(), DigestError> {
+        CronExpression::parse(&self.schedule).map_err(DigestError::InvalidSchedule)?;
+        if self.channels.is_empty() {
+            return Err(DigestError::InvalidChannel("at least one channel is required".to_string()));
+        }
+        for channel in &self.channels {
+            match channel {
+                DigestChannel::Email { to } if !to.contains('@') => {
+                    return Err(DigestError::InvalidChannel(format!("invalid email address: {}", to)));
+                }
+                DigestChannel::Slack { webhook_url } if !webhook_url.starts_with("https://") => {
+                    return Err(DigestError::InvalidChannel(format!("Slack webhook must be an https URL: {}", webhook_url)));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A user's digest subscription
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestSubscription {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    #[serde(flatten)]
+    pub settings: DigestSettings,
+    pub created_at: DateTime<Utc>,
+    /// End of the period the last delivered digest covered
+    #[serde(default)]
+    pub last_sent_at: Option<DateTime<Utc>>,
+}
+
+impl DigestSubscription {
+    /// The first delivery time after the last digest (or the subscription)
+    pub fn next_due(&self) -> Option<DateTime<Utc>> {
+        let cron = CronExpression::parse(&self.settings.schedule).ok()?;
+        let after = self.last_sent_at.unwrap_or(self.created_at).with_timezone(&self.settings.timezone);
+        cron.next_after(&after).map(|at| at.with_timezone(&Utc))
+    }
+}
+
+/// Runs of one workflow in the digest period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowHealth {
+    pub workflow_id: Uuid,
+    pub name: String,
+    pub runs: usize,
+    pub failures: usize,
+    pub usage: ResourceUsage,
+}
+
+/// An error message shared by failed executions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotableError {
+    pub message: String,
+    pub occurrences: usize,
+    pub latest_execution_id: Uuid,
+    pub link: String,
+}
+
+/// A failed execution, linked for debugging
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedRun {
+    pub execution_id: Uuid,
+    pub workflow_id: Uuid,
+    pub workflow_name: String,
+    pub started_at: DateTime<Utc>,
+    pub error: Option<String>,
+    pub link: String,
+}
+
+/// A subscriber's credential about to stop working
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiringCredential {
+    pub credential_id: Uuid,
+    pub name: String,
+    pub integration: String,
+    pub expires_at: DateTime<Utc>,
+    pub expired: bool,
+}
+
+/// Summary of workflow health over a period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Digest {
+    pub subscription_id: Uuid,
+    pub user_id: Uuid,
+    pub scope: DigestScope,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub runs: usize,
+    pub failures: usize,
+    /// Resources consumed by the period's executions
+    pub usage: ResourceUsage,
+    /// Most failures first, then most runs
+    pub workflows: Vec<WorkflowHealth>,
+    pub notable_errors: Vec<NotableError>,
+    /// The latest failures, at most [`MAX_FAILED_RUNS`]
+    pub failed_runs: Vec<FailedRun>,
+    pub expiring_credentials: Vec<ExpiringCredential>,
+}
+
+impl Digest {
+    pub fn is_empty(&self) -> bool {
+        self.runs == 0 && self.expiring_credentials.is_empty()
+    }
+
+    pub fn subject(&self) -> String {
+        match self.failures {
+            0 => format!("Workflow digest: {} runs, no failures", self.runs),
+            1 => format!("Workflow digest: 1 failure in {} runs", self.runs),
+            n => format!("Workflow digest: {} failures in {} runs", n, self.runs),
+        }
+    }
+
+    /// Plain-text body, used for email and Slack
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "{} to {} (UTC)",
+                self.period_start.format("%Y-%m-%d %H:%M"),
+                self.period_end.format("%Y-%m-%d %H:%M"),
+            ),
+            format!("Runs: {}, failures: {}", self.runs, self.failures),
+            format!(
+                "Usage: {} tokens, {} provider calls, {} scraper pages, {} bytes stored",
+                self.usage.total_tokens(),
+                self.usage.provider_calls,
+                self.usage.scraper_pages,
+                self.usage.bytes_stored,
+            ),
+        ];
+
+        if !self.workflows.is_empty() {
+            lines.push(String::new());
+            lines.push("Workflows:".to_string());
+            for workflow in &self.workflows {
+                lines.push(format!("- {}: {} runs, {} failed", workflow.name, workflow.runs, workflow.failures));
+            }
+        }
+        if !self.notable_errors.is_empty() {
+            lines.push(String::new());
+            lines.push("Notable errors:".to_string());
+            for error in &self.notable_errors {
+                lines.push(format!("- {}x {} (latest: {})", error.occurrences, error.message, error.link));
+            }
+        }
+        if !self.failed_runs.is_empty() {
+            lines.push(String::new());
+            lines.push("Failed executions:".to_string());
+            for run in &self.failed_runs {
+                lines.push(format!(
+                    "- {} at {}: {} {}",
+                    run.workflow_name,
+                    run.started_at.format("%Y-%m-%d %H:%M"),
+                    run.error.as_deref().unwrap_or("failed"),
+                    run.link,
+                ));
+            }
+        }
+        if !self.expiring_credentials.is_empty() {
+            lines.push(String::new());
+            lines.push("Credentials to renew:".to_string());
+            for credential in &self.expiring_credentials {
+                let when = if credential.expired { "expired" } else { "expires" };
+                lines.push(format!(
+                    "- {} ({}) {} {}",
+                    credential.name,
+                    credential.integration,
+                    when,
+                    credential.expires_at.format("%Y-%m-%d %H:%M"),
+                ));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DigestError {
+    #[error("Invalid schedule: {0}")]
+    InvalidSchedule(String),
+
+    #[error("Invalid channel: {0}")]
+    InvalidChannel(String),
+
+    #[error("Digest subscription not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Digest delivery failed: {0}")]
+    Delivery(String),
+}
+
+impl ErrorInfo for DigestError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            DigestError::InvalidSchedule(_) | DigestError::InvalidChannel(_) => ErrorCode::InvalidInput,
+            DigestError::NotFound(_) => ErrorCode::NotFound,
+            DigestError::Delivery(_) => ErrorCode::UpstreamFailed,
+        }
+    }
+}
+
+/// Delivers digests to their channels
+#[async_trait]
+pub trait DigestSender: Send + Sync {
+    async fn send(&self, channel: &DigestChannel, digest: &Digest) -> Result<(), String>;
+}
+
+/// Sends email digests; the same mailer delivers the integration service's
+/// notifications
+pub use integration_service::notify::Mailer;
+
+/// Sends Slack digests through the gateway's HTTP dispatcher and email
+/// digests through a [`Mailer`]; channels without one fail
+#[derive(Default)]
+pub struct ChannelSender {
+    http: Option<Arc<dyn HttpDispatcher>>,
+    mailer: Option<Arc<dyn Mailer>>,
+}
+
+impl ChannelSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_http(mut self, http: Arc<dyn HttpDispatcher>) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    pub fn with_mailer(mut self, mailer: Arc<dyn Mailer>) -> Self {
+        self.mailer = Some(mailer);
+        self
+    }
+}
+
+#[async_trait]
+impl DigestSender for ChannelSender {
+    async fn send(&self, channel: &DigestChannel, digest: &Digest) -> Result<(), String> {
+        match channel {
+            DigestChannel::Email { to } => {
+                let mailer = self.mailer.as_ref().ok_or("email delivery is not configured")?;
+                mailer.send(to, &digest.subject(), &digest.to_text()).await
+            }
+            DigestChannel::Slack { webhook_url } => {
+                let http = self.http.as_ref().ok_or("Slack delivery is not configured")?;
+                let organization_id = match digest.scope {
+                    DigestScope::Organization { organization_id } => Some(organization_id),
+                    DigestScope::User => None,
+                };
+                let request = ApiRequest {
+                    id: Uuid::new_v4(),
+                    provider: "slack".to_string(),
+                    endpoint: webhook_url.clone(),
+                    method: HttpMethod::POST,
+                    headers: HashMap::from([("Content-Type".to_string(), "application/json".to_string())]),
+                    body: Some(serde_json::json!({
+                        "text": format!("*{}*\n{}", digest.subject(), digest.to_text()),
+                    })),
+                    priority: Priority::Low,
+                    workflow_id: Uuid::nil(),
+                    node_id: Uuid::nil(),
+                    organization_id,
+                    timeout: Duration::from_secs(SLACK_TIMEOUT_SECS),
+                    retry_config: RetryConfig { max_retries: 2, ..Default::default() },
+                };
+                let response = http.dispatch(request).await.map_err(|e| e.to_string())?;
+                if !(200..300).contains(&response.status_code) {
+                    return Err(format!("Slack webhook returned {}", response.status_code));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Digest subscriptions and the job delivering them on schedule
+#[derive(Clone)]
+pub struct DigestService {
+    subscriptions: Arc<RwLock<HashMap<Uuid, DigestSubscription>>>,
+    history: Arc<ExecutionHistory>,
+    /// Workflow owners and names
+    revisions: Arc<RevisionStore>,
+    credentials: Arc<CredentialStore>,
+    sender: Arc<dyn DigestSender>,
+    /// Prefix of the execution links, e.g. `https://flowvex.example.com`
+    base_url: String,
+    tasks: TaskSupervisor,
+}
+
+impl DigestService {
+    pub fn new(
+        history: Arc<ExecutionHistory>,
+        revisions: Arc<RevisionStore>,
+        credentials: Arc<CredentialStore>,
+        sender: Arc<dyn DigestSender>,
+    ) -> Self {
+        Self {
+            subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            history,
+            revisions,
+            credentials,
+            sender,
+            base_url: String::new(),
+            tasks: TaskSupervisor::new(),
+        }
+    }
+
+    /// Make execution links absolute; they are relative paths otherwise
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    fn execution_link(&self, execution_id: Uuid) -> String {
+        format!("{}/executions/{}", self.base_url, execution_id)
+    }
+
+    pub async fn subscribe(&self, user_id: Uuid, settings: DigestSettings) -> Result<DigestSubscription, DigestError> {
+        settings.validate()?;
+        let subscription = DigestSubscription {
+            id: Uuid::new_v4(),
+            user_id,
+            settings,
+            created_at: Utc::now(),
+            last_sent_at: None,
+        };
+        self.subscriptions.write().await.insert(subscription.id, subscription.clone());
+        Ok(subscription)
+    }
+
+    /// Replace a subscription's settings; the next period still starts at the last delivery
+    pub async fn update(&self, id: Uuid, settings: DigestSettings) -> Result<DigestSubscription, DigestError> {
+        settings.validate()?;
+        let mut subscriptions = self.subscriptions.write().await;
+        let subscription = subscriptions.get_mut(&id).ok_or(DigestError::NotFound(id))?;
+        subscription.settings = settings;
+        Ok(subscription.clone())
+    }
+
+    pub async fn unsubscribe(&self, id: Uuid) -> Option<DigestSubscription> {
+        self.subscriptions.write().await.remove(&id)
+    }
+
+    pub async fn get(&self, id: Uuid) -> Option<DigestSubscription> {
+        self.subscriptions.read().await.get(&id).cloned()
+    }
+
+    /// A user's subscriptions, oldest first
+    pub async fn list_for_user(&self, user_id: Uuid) -> Vec<DigestSubscription> {
+        let mut found: Vec<DigestSubscription> = self.subscriptions.read().await
+            .values()
+            .filter(|s| s.user_id == user_id)
+            .cloned()
+            .collect();
+        found.sort_by_key(|s| s.created_at);
+        found
+    }
+
+    /// Drop a deleted user's subscriptions; returns how many were removed
+    pub async fn remove_user(&self, user_id: Uuid) -> usize {
+        let mut subscriptions = self.subscriptions.write().await;
+        let before = subscriptions.len();
+        subscriptions.retain(|_, s| s.user_id != user_id);
+        before - subscriptions.len()
+    }
+
+    /// Compile the subscription's digest for the period since its last
+    /// delivery (the preceding day for the first one) up to `until`
+    pub async fn build(&self, subscription: &DigestSubscription, until: DateTime<Utc>) -> Digest {
+        let since = subscription.last_sent_at
+            .unwrap_or(until - ChronoDuration::hours(FIRST_PERIOD_HOURS));
+        // Counters cover executions the history sampled out or evicted;
+        // failure details come from the retained records
+        let counters = self.history.counters_between(since, until).await;
+        let records = self.history.list_between(since, until).await;
+        let in_scope: HashSet<Uuid> = match &subscription.settings.scope {
+            DigestScope::User => self.revisions.owned_by(subscription.user_id).await.into_iter().collect(),
+            DigestScope::Organization { organization_id } => counters.iter()
+                .filter(|(_, c)| c.organization_id == Some(*organization_id))
+                .map(|(id, _)| *id)
+                .chain(records.iter().filter(|r| r.organization_id == Some(*organization_id)).map(|r| r.workflow_id))
+                .collect(),
+        };
+        let records: Vec<ExecutionRecord> = records.into_iter()
+            .filter(|r| in_scope.contains(&r.workflow_id))
+            .collect();
+
+        let workflow_ids: Vec<Uuid> = counters.keys()
+            .copied()
+            .filter(|id| in_scope.contains(id))
+            .collect();
+        let names: HashMap<Uuid, String> = self.revisions.latest_many(&workflow_ids).await
+            .into_iter()
+            .map(|(id, workflow)| (id, workflow.name))
+            .collect();
+        let name_of = |id: Uuid| names.get(&id).cloned().unwrap_or_else(|| id.to_string());
+
+        let (mut runs, mut failures) = (0, 0);
+        let mut usage = ResourceUsage::default();
+        let mut workflows: HashMap<Uuid, WorkflowHealth> = HashMap::new();
+        for workflow_id in &workflow_ids {
+            let counted = &counters[workflow_id].counters;
+            runs += counted.runs as usize;
+            failures += counted.failures as usize;
+            usage.add(&counted.usage);
+            workflows.insert(*workflow_id, WorkflowHealth {
+                workflow_id: *workflow_id,
+                name: name_of(*workflow_id),
+                runs: counted.runs as usize,
+                failures: counted.failures as usize,
+                usage: counted.usage.clone(),
+            });
+        }
+
+        // Message -> (occurrences, latest execution); records are newest first
+        let mut errors: HashMap<String, (usize, Uuid)> = HashMap::new();
+        let mut failed_runs = Vec::new();
+        for record in records.iter().filter(|r| r.result.state == ExecutionState::Failed) {
+            let execution_id = record.result.execution_id;
+            if let Some(error) = &record.result.error {
+                let message: String = error.trim().chars().take(ERROR_MESSAGE_CHARS).collect();
+                errors.entry(message).or_insert((0, execution_id)).0 += 1;
+            }
+            if failed_runs.len() < MAX_FAILED_RUNS {
+                failed_runs.push(FailedRun {
+                    execution_id,
+                    workflow_id: record.workflow_id,
+                    workflow_name: name_of(record.workflow_id),
+                    started_at: record.started_at,
+                    error: record.result.error.clone(),
+                    link: self.execution_link(execution_id),
+                });
+            }
+        }
+
+        let mut workflows: Vec<WorkflowHealth> = workflows.into_values().collect();
+        workflows.sort_by(|a, b| b.failures.cmp(&a.failures).then(b.runs.cmp(&a.runs)).then(a.name.cmp(&b.name)));
+        let mut notable_errors: Vec<NotableError> = errors.into_iter()
+            .map(|(message, (occurrences, latest_execution_id))| NotableError {
+                message,
+                occurrences,
+                latest_execution_id,
+                link: self.execution_link(latest_execution_id),
+            })
+            .collect();
+        notable_errors.sort_by(|a, b| b.occurrences.cmp(&a.occurrences).then(a.message.cmp(&b.message)));
+        notable_errors.truncate(MAX_NOTABLE_ERRORS);
+
+        let lookahead = ChronoDuration::days(subscription.settings.credential_lookahead_days as i64);
+        let expiring_credentials = self.credentials.expiring_for_owner(subscription.user_id, until + lookahead).await
+            .into_iter()
+            .filter_map(|credential| {
+                let expires_at = credential.expires_at?;
+                Some(ExpiringCredential {
+                    credential_id: credential.id,
+                    name: credential.name,
+                    integration: credential.integration,
+                    expires_at,
+                    expired: expires_at <= until,
+                })
+            })
+            .collect();
+
+        Digest {
+            subscription_id: subscription.id,
+            user_id: subscription.user_id,
+            scope: subscription.settings.scope.clone(),
+            period_start: since,
+            period_end: until,
+            runs,
+            failures,
+            usage,
+            workflows,
+            notable_errors,
+            failed_runs,
+            expiring_credentials,
+        }
+    }
+
+    /// Deliver a digest to every channel of its subscription, attempting all
+    /// of them; fails if any failed
+    async fn deliver(&self, subscription: &DigestSubscription, digest: &Digest) -> Result<(), DigestError> {
+        let mut failed = Vec::new();
+        for channel in &subscription.settings.channels {
+            if let Err(e) = self.sender.send(channel, digest).await {
+                failed.push(e);
+            }
+        }
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(DigestError::Delivery(failed.join("; ")))
+        }
+    }
+
+    /// Build and deliver the digests due at `now`; returns the subscriptions
+    /// a digest was delivered for. A period is done once due, even if some
+    /// channel failed or the digest was empty and skipped.
+    pub async fn send_due(&self, now: DateTime<Utc>) -> Vec<Uuid> {
+        let due: Vec<DigestSubscription> = self.subscriptions.read().await
+            .values()
+            .filter(|s| s.settings.enabled && s.next_due().is_some_and(|at| at <= now))
+            .cloned()
+            .collect();
+
+        let mut sent = Vec::new();
+        for subscription in due {
+            let digest = self.build(&subscription, now).await;
+            if !digest.is_empty() || subscription.settings.send_when_empty {
+                match self.deliver(&subscription, &digest).await {
+                    Ok(()) => sent.push(subscription.id),
+                    Err(e) => tracing::warn!("Failed to deliver digest {}: {}", subscription.id, e),
+                }
+            }
+            if let Some(stored) = self.subscriptions.write().await.get_mut(&subscription.id) {
+                stored.last_sent_at = Some(now);
+            }
+        }
+        sent
+    }
+
+    /// Deliver due digests in the background until the handle is aborted or
+    /// the service shuts down
+    pub fn start(&self) -> JoinHandle<()> {
+        let service = self.clone();
+        self.tasks.spawn(async move {
+            let mut tick = interval(Duration::from_secs(DIGEST_TICK_SECS));
+            loop {
+                tokio::select! {
+                    _ = tick.tick() => {}
+                    _ = service.tasks.cancelled() => break,
+                }
+                service.send_due(Utc::now()).await;
+            }
+        })
+    }
+
+    /// Stop the delivery loop, waiting up to `timeout` for a round in progress
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.tasks.shutdown(timeout).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use common::types::{ExecutionResult, ExecutionUsage, Workflow, WORKFLOW_SCHEMA_VERSION};
+    use integration_service::credentials::CredentialStatus;
+    use integration_service::integrations::AuthType;
+    use integration_service::StoredCredential;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingSender {
+        sent: Mutex<Vec<(DigestChannel, Digest)>>,
+    }
+
+    #[async_trait]
+    impl DigestSender for RecordingSender {
+        async fn send(&self, channel: &DigestChannel, digest: &Digest) -> Result<(), String> {
+            self.sent.lock().unwrap().push((channel.clone(), digest.clone()));
+            match channel {
+                DigestChannel::Slack { .. } => Err("webhook gone".to_string()),
+                DigestChannel::Email { .. } => Ok(()),
+            }
+        }
+    }
+
+    fn workflow(name: &str) -> Workflow {
+        Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: None,
+            nodes: vec![],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        }
+    }
+
+    fn record(workflow_id: Uuid, started_at: DateTime<Utc>, error: Option<&str>, tokens: u64) -> ExecutionRecord {
+        let usage = ExecutionUsage {
+            totals: ResourceUsage { prompt_tokens: tokens, provider_calls: 1, ..Default::default() },
+            ..Default::default()
+        };
+        ExecutionRecord {
+            workflow_id,
+            organization_id: None,
+            started_at,
+            result: ExecutionResult {
+                execution_id: Uuid::new_v4(),
+                state: if error.is_some() { ExecutionState::Failed } else { ExecutionState::Completed },
+                completed_at: Some(started_at),
+                error: error.map(str::to_string),
+                output: None,
+                usage: Some(usage),
+            },
+            nodes: vec![],
+        }
+    }
+
+    fn settings(channels: Vec<DigestChannel>) -> DigestSettings {
+        serde_json::from_value(serde_json::json!({
+            "scope": { "type": "user" },
+            "timezone": "Europe/Berlin",
+            "channels": channels,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_build_digest() {
+        let (history, revisions, credentials) = (
+            Arc::new(ExecutionHistory::default()),
+            Arc::new(RevisionStore::new()),
+            Arc::new(CredentialStore::new()),
+        );
+        let user = Uuid::new_v4();
+        let (orders, invoices, foreign) = (workflow("Orders"), workflow("Invoices"), workflow("Someone else's"));
+        revisions.submit(orders.clone(), user).await;
+        revisions.submit(invoices.clone(), user).await;
+        revisions.submit(foreign.clone(), Uuid::new_v4()).await;
+
+        let now = Utc::now();
+        let hour = ChronoDuration::hours(1);
+        let timeout = record(orders.id, now - hour * 3, Some("upstream timed out"), 10);
+        for run in [
+            record(orders.id, now - hour * 5, Some("upstream timed out"), 10),
+            timeout.clone(),
+            record(orders.id, now - hour * 2, None, 10),
+            record(invoices.id, now - hour, Some("invalid credentials"), 0),
+            record(invoices.id, now - hour * 30, Some("before the period"), 0),
+            record(foreign.id, now - hour, Some("not ours"), 0),
+        ] {
+            history.record(run).await;
+        }
+        let credential = |name: &str, expires_at| StoredCredential {
+            id: Uuid::new_v4(),
+            owner: user,
+            name: name.to_string(),
+            integration: "crm".to_string(),
+            auth_type: AuthType::ApiKey,
+            schema_version: 1,
+            data: String::new(),
+            status: CredentialStatus::Active,
+            created_at: now,
+            updated_at: now,
+            rotated_at: None,
+            expires_at,
+            token_expires_at: None,
+        };
+        credentials.save(credential("CRM key", Some(now + ChronoDuration::days(3)))).await.unwrap();
+        credentials.save(credential("Old key", Some(now - hour))).await.unwrap();
+        credentials.save(credential("Later key", Some(now + ChronoDuration::days(30)))).await.unwrap();
+        credentials.save(credential("Forever key", None)).await.unwrap();
+
+        let service = DigestService::new(history, revisions, credentials, Arc::new(RecordingSender::default()))
+            .with_base_url("https://flowvex.example.com/");
+        let subscription = service.subscribe(user, settings(vec![DigestChannel::Email { to: "ops@example.com".to_string() }]))
+            .await
+            .unwrap();
+        let digest = service.build(&subscription, now).await;
+
+        assert_eq!((digest.runs, digest.failures), (4, 3));
+        assert_eq!(digest.usage.prompt_tokens, 30);
+        assert_eq!(digest.usage.provider_calls, 4);
+        assert_eq!(digest.workflows[0].name, "Orders");
+        assert_eq!((digest.workflows[0].runs, digest.workflows[0].failures), (3, 2));
+        assert_eq!(digest.notable_errors[0].message, "upstream timed out");
+        assert_eq!(digest.notable_errors[0].occurrences, 2);
+        assert_eq!(digest.notable_errors[0].latest_execution_id, timeout.result.execution_id);
+        assert_eq!(
+            digest.failed_runs[1].link,
+            format!("https://flowvex.example.com/executions/{}", timeout.result.execution_id),
+        );
+        let expiring: Vec<(&str, bool)> = digest.expiring_credentials.iter().map(|c| (c.name.as_str(), c.expired)).collect();
+        assert_eq!(expiring, vec![("Old key", true), ("CRM key", false)]);
+        assert!(digest.to_text().contains("- 2x upstream timed out (latest: https://flowvex.example.com/executions/"));
+        assert_eq!(digest.subject(), "Workflow digest: 3 failures in 4 runs");
+    }
+
+    #[tokio::test]
+    async fn test_send_due() {
+        let sender = Arc::new(RecordingSender::default());
+        let history = Arc::new(ExecutionHistory::default());
+        let revisions = Arc::new(RevisionStore::new());
+        let service = DigestService::new(history.clone(), revisions.clone(), Arc::new(CredentialStore::new()), sender.clone());
+        let user = Uuid::new_v4();
+        let orders = workflow("Orders");
+        revisions.submit(orders.clone(), user).await;
+
+        assert!(matches!(
+            service.subscribe(user, settings(vec![])).await,
+            Err(DigestError::InvalidChannel(_)),
+        ));
+        let mut invalid = settings(vec![DigestChannel::Email { to: "ops@example.com".to_string() }]);
+        invalid.schedule = "every morning".to_string();
+        assert!(matches!(service.subscribe(user, invalid).await, Err(DigestError::InvalidSchedule(_))));
+
+        let subscription = service.subscribe(user, settings(vec![
+            DigestChannel::Email { to: "ops@example.com".to_string() },
+            DigestChannel::Slack { webhook_url: "https://hooks.slack.com/services/T/B/X".to_string() },
+        ])).await.unwrap();
+        // 08:00 in Berlin is 06:00 UTC in summer
+        let due = Utc.with_ymd_and_hms(2026, 7, 1, 6, 0, 0).unwrap();
+        service.subscriptions.write().await.get_mut(&subscription.id).unwrap().created_at = due - ChronoDuration::hours(12);
+        assert_eq!(service.get(subscription.id).await.unwrap().next_due(), Some(due));
+
+        // Nothing ran: the empty digest is skipped but the period is done
+        assert!(service.send_due(due).await.is_empty());
+        assert!(sender.sent.lock().unwrap().is_empty());
+        assert_eq!(service.get(subscription.id).await.unwrap().last_sent_at, Some(due));
+
+        history.record(record(orders.id, due + ChronoDuration::hours(2), Some("boom"), 0)).await;
+        let next = due + ChronoDuration::days(1);
+        assert!(service.send_due(next - ChronoDuration::minutes(1)).await.is_empty());
+        // The Slack channel fails, so the delivery is not counted as sent
+        assert!(service.send_due(next).await.is_empty());
+        {
+            let sent = sender.sent.lock().unwrap();
+            assert_eq!(sent.len(), 2);
+            assert_eq!((sent[0].1.period_start, sent[0].1.period_end), (due, next));
+            assert_eq!(sent[0].1.failures, 1);
+        }
+        assert!(service.send_due(next).await.is_empty());
+        assert_eq!(sender.sent.lock().unwrap().len(), 2);
+
+        assert_eq!(service.remove_user(user).await, 1);
+        assert!(service.list_for_user(user).await.is_empty());
+    }
+}
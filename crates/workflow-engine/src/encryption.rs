@@ -0,0 +1,535 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine};
+use chrono::{DateTime, Utc};
+use common::error::WorkflowError;
+use common::types::JsonValue;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::dead_letter::{DeadLetter, DeadLetterStatus, DeadLetterStore};
+use crate::history::ExecutionRecord;
+use crate::settings::OrgSettingsStore;
+
+/// Key of the object an encrypted field is replaced with:
+/// `{"$encrypted": "<base64 nonce + ciphertext>"}`
+pub const ENCRYPTED_FIELD: &str = "$encrypted";
+
+const NONCE_LEN: usize = 12;
+
+/// An organization's data key, encrypted with a master key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WrappedDataKey {
+    pub organization_id: Uuid,
+    /// Master key the data key is encrypted with
+    pub master_key_id: u32,
+    /// Base64-encoded nonce + AES-256-GCM ciphertext of the data key
+    pub wrapped: String,
+    pub created_at: DateTime<Utc>,
+    /// Last time the data key was re-wrapped with a new master key
+    pub rotated_at: Option<DateTime<Utc>>,
+}
+
+/// Durable storage of wrapped data keys, one per organization
+#[async_trait]
+pub trait DataKeyStore: Send + Sync {
+    async fn get(&self, organization_id: Uuid) -> Result<Option<WrappedDataKey>, WorkflowError>;
+    /// Save or replace the data key of the key's organization
+    async fn save(&self, key: &WrappedDataKey) -> Result<(), WorkflowError>;
+    async fn list(&self) -> Result<Vec<WrappedDataKey>, WorkflowError>;
+}
+
+/// In-memory data key store (for development, replace with database in production)
+#[derive(Default)]
+pub struct InMemoryDataKeyStore {
+    keys: RwLock<HashMap<Uuid, WrappedDataKey>>,
+}
+
+impl InMemoryDataKeyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DataKeyStore for InMemoryDataKeyStore {
+    async fn get(&self, organization_id: Uuid) -> Result<Option<WrappedDataKey>, WorkflowError> {
+        Ok(self.keys.read().await.get(&organization_id).cloned())
+    }
+
+    async fn save(&self, key: &WrappedDataKey) -> Result<(), WorkflowError> {
+        self.keys.write().await.insert(key.organization_id, key.clone());
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<WrappedDataKey>, WorkflowError> {
+        Ok(self.keys.read().await.values().cloned().collect())
+    }
+}
+
+/// Master keys by ID; the current one wraps new and rotated data keys, older
+/// ones stay known until every data key is re-wrapped
+struct MasterKeys {
+    current: u32,
+    keys: HashMap<u32, Aes256Gcm>,
+}
+
+impl MasterKeys {
+    fn wrap(&self, data_key: &[u8]) -> Result<String, WorkflowError> {
+        seal(&self.keys[&self.current], data_key)
+    }
+
+    fn unwrap(&self, key: &WrappedDataKey) -> Result<Vec<u8>, WorkflowError> {
+        let master = self.keys.get(&key.master_key_id).ok_or_else(|| encryption_error(format!(
+            "data key of organization {} is wrapped with unknown master key {}",
+            key.organization_id, key.master_key_id
+        )))?;
+        open(master, &key.wrapped)
+    }
+}
+
+/// Field-level envelope encryption of stored execution payloads.
+///
+/// Each organization gets a random data key, created on first use and kept
+/// in a [`DataKeyStore`] wrapped with the master key. Payloads of
+/// organizations with `encrypt_execution_data` set are replaced field by
+/// field with `{"$encrypted": ...}` objects; decryption leaves unencrypted
+/// fields as they are, so data stored before encryption was enabled still
+/// reads. Rotating the master key re-wraps the data keys and leaves the
+/// payloads untouched.
+pub struct PayloadEncryption {
+    masters: RwLock<MasterKeys>,
+    keys: Arc<dyn DataKeyStore>,
+    settings: Arc<OrgSettingsStore>,
+    /// Unwrapped data keys
+    data_keys: RwLock<HashMap<Uuid, Aes256Gcm>>,
+}
+
+impl PayloadEncryption {
+    /// `settings` tells which organizations opted in to encryption
+    pub fn new(
+        master_key_id: u32,
+        master_key: &[u8; 32],
+        keys: Arc<dyn DataKeyStore>,
+        settings: Arc<OrgSettingsStore>,
+    ) -> Self {
+        Self {
+            masters: RwLock::new(MasterKeys {
+                current: master_key_id,
+                keys: HashMap::from([(master_key_id, Aes256Gcm::new(master_key.into()))]),
+            }),
+            keys,
+            settings,
+            data_keys: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Also unwrap data keys still wrapped with an earlier master key, e.g.
+    /// after a rotation that did not finish
+    pub fn with_previous_master_key(mut self, master_key_id: u32, master_key: &[u8; 32]) -> Self {
+        self.masters.get_mut().keys.entry(master_key_id).or_insert_with(|| Aes256Gcm::new(master_key.into()));
+        self
+    }
+
+    /// ID of the master key new data keys are wrapped with
+    pub async fn master_key_id(&self) -> u32 {
+        self.masters.read().await.current
+    }
+
+    pub async fn is_enabled(&self, organization_id: Uuid) -> bool {
+        self.settings.get(organization_id).await.encrypt_execution_data
+    }
+
+    /// Encrypt a value with the organization's data key
+    pub async fn encrypt_value(&self, organization_id: Uuid, value: &JsonValue) -> Result<JsonValue, WorkflowError> {
+        let data_key = self.data_key(organization_id).await?;
+        seal_value(&data_key, value)
+    }
+
+    /// Decrypt a value encrypted with the organization's data key; other
+    /// values are returned as they are
+    pub async fn decrypt_value(&self, organization_id: Uuid, value: &JsonValue) -> Result<JsonValue, WorkflowError> {
+        if !is_encrypted(value) {
+            return Ok(value.clone());
+        }
+        let data_key = self.data_key(organization_id).await?;
+        open_value(&data_key, value)
+    }
+
+    /// Encrypt the execution output and node inputs/outputs of a record, if
+    /// its organization opted in
+    pub async fn encrypt_record(&self, record: &mut ExecutionRecord) -> Result<(), WorkflowError> {
+        match record.organization_id {
+            Some(organization_id) if self.is_enabled(organization_id).await => {
+                self.seal_fields(organization_id, record_fields(record)).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn decrypt_record(&self, record: &mut ExecutionRecord) -> Result<(), WorkflowError> {
+        match record.organization_id {
+            Some(organization_id) => self.open_fields(organization_id, record_fields(record)).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Encrypt the input and variables of a dead letter, if its organization
+    /// opted in
+    pub async fn encrypt_dead_letter(&self, letter: &mut DeadLetter) -> Result<(), WorkflowError> {
+        match letter.organization_id {
+            Some(organization_id) if self.is_enabled(organization_id).await => {
+                self.seal_fields(organization_id, dead_letter_fields(letter)).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn decrypt_dead_letter(&self, letter: &mut DeadLetter) -> Result<(), WorkflowError> {
+        match letter.organization_id {
+            Some(organization_id) => self.open_fields(organization_id, dead_letter_fields(letter)).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Make `master_key` the current master key and re-wrap every data key
+    /// with it; returns the number of data keys re-wrapped.
+    ///
+    /// The previous master key is kept, so a rotation interrupted by a
+    /// storage error can be finished by calling this again with the same key.
+    pub async fn rotate_master_key(&self, master_key_id: u32, master_key: &[u8; 32]) -> Result<usize, WorkflowError> {
+        let mut masters = self.masters.write().await;
+        if masters.keys.contains_key(&master_key_id) && masters.current != master_key_id {
+            return Err(WorkflowError::ValidationFailed(format!(
+                "master key {} was used before, rotate to a new key ID",
+                master_key_id
+            )));
+        }
+        masters.keys.insert(master_key_id, Aes256Gcm::new(master_key.into()));
+        masters.current = master_key_id;
+
+        let mut rewrapped = 0;
+        for key in self.keys.list().await? {
+            if key.master_key_id == master_key_id {
+                continue;
+            }
+            let data_key = masters.unwrap(&key)?;
+            self.keys.save(&WrappedDataKey {
+                master_key_id,
+                wrapped: masters.wrap(&data_key)?,
+                rotated_at: Some(Utc::now()),
+                ..key
+            }).await?;
+            rewrapped += 1;
+        }
+        tracing::info!("Rotated to master key {}, re-wrapped {} data keys", master_key_id, rewrapped);
+        Ok(rewrapped)
+    }
+
+    /// The organization's data key, created and stored on first use
+    async fn data_key(&self, organization_id: Uuid) -> Result<Aes256Gcm, WorkflowError> {
+        if let Some(data_key) = self.data_keys.read().await.get(&organization_id) {
+            return Ok(data_key.clone());
+        }
+        let mut data_keys = self.data_keys.write().await;
+        if let Some(data_key) = data_keys.get(&organization_id) {
+            return Ok(data_key.clone());
+        }
+
+        let masters = self.masters.read().await;
+        let bytes = match self.keys.get(organization_id).await? {
+            Some(key) => masters.unwrap(&key)?,
+            None => {
+                let mut bytes = vec![0u8; 32];
+                rand::thread_rng().fill_bytes(&mut bytes);
+                self.keys.save(&WrappedDataKey {
+                    organization_id,
+                    master_key_id: masters.current,
+                    wrapped: masters.wrap(&bytes)?,
+                    created_at: Utc::now(),
+                    rotated_at: None,
+                }).await?;
+                bytes
+            }
+        };
+        let data_key = Aes256Gcm::new_from_slice(&bytes)
+            .map_err(|_| encryption_error(format!("invalid data key of organization {}", organization_id)))?;
+        data_keys.insert(organization_id, data_key.clone());
+        Ok(data_key)
+    }
+
+    async fn seal_fields(&self, organization_id: Uuid, fields: Vec<&mut JsonValue>) -> Result<(), WorkflowError> {
+        let fields: Vec<&mut JsonValue> = fields.into_iter().filter(|f| !is_encrypted(f)).collect();
+        if fields.is_empty() {
+            return Ok(());
+        }
+        let data_key = self.data_key(organization_id).await?;
+        for field in fields {
+            *field = seal_value(&data_key, field)?;
+        }
+        Ok(())
+    }
+
+    async fn open_fields(&self, organization_id: Uuid, fields: Vec<&mut JsonValue>) -> Result<(), WorkflowError> {
+        let fields: Vec<&mut JsonValue> = fields.into_iter().filter(|f| is_encrypted(f)).collect();
+        if fields.is_empty() {
+            return Ok(());
+        }
+        let data_key = self.data_key(organization_id).await?;
+        for field in fields {
+            *field = open_value(&data_key, field)?;
+        }
+        Ok(())
+    }
+}
+
+/// Dead letter store keeping the input and variables of dead letters
+/// encrypted at rest; the queue and API read them decrypted
+pub struct EncryptedDeadLetterStore {
+    inner: Arc<dyn DeadLetterStore>,
+    encryption: Arc<PayloadEncryption>,
+}
+
+impl EncryptedDeadLetterStore {
+    pub fn new(inner: Arc<dyn DeadLetterStore>, encryption: Arc<PayloadEncryption>) -> Self {
+        Self { inner, encryption }
+    }
+}
+
+#[async_trait]
+impl DeadLetterStore for EncryptedDeadLetterStore {
+    async fn save(&self, letter: &DeadLetter) -> Result<(), WorkflowError> {
+        let mut letter = letter.clone();
+        self.encryption.encrypt_dead_letter(&mut letter).await?;
+        self.inner.save(&letter).await
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Option<DeadLetter>, WorkflowError> {
+        let Some(mut letter) = self.inner.get(id).await? else {
+            return Ok(None);
+        };
+        self.encryption.decrypt_dead_letter(&mut letter).await?;
+        Ok(Some(letter))
+    }
+
+    async fn list(
+        &self,
+        workflow_id: Option<Uuid>,
+        status: Option<DeadLetterStatus>,
+    ) -> Result<Vec<DeadLetter>, WorkflowError> {
+        let mut letters = self.inner.list(workflow_id, status).await?;
+        for letter in &mut letters {
+            self.encryption.decrypt_dead_letter(letter).await?;
+        }
+        Ok(letters)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, WorkflowError> {
+        self.inner.delete(id).await
+    }
+}
+
+/// Parse a base64-encoded 256-bit master key
+pub fn decode_master_key(encoded: &str) -> Result<[u8; 32], WorkflowError> {
+    general_purpose::STANDARD.decode(encoded.trim()).ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| WorkflowError::ValidationFailed("master key must be 32 base64-encoded bytes".to_string()))
+}
+
+/// Whether a value is an encrypted field
+pub fn is_encrypted(value: &JsonValue) -> bool {
+    sealed_payload(value).is_some()
+}
+
+fn sealed_payload(value: &JsonValue) -> Option<&str> {
+    let object = value.as_object().filter(|o| o.len() == 1)?;
+    object.get(ENCRYPTED_FIELD)?.as_str()
+}
+
+fn record_fields(record: &mut ExecutionRecord) -> Vec<&mut JsonValue> {
+    let mut fields: Vec<&mut JsonValue> = record.result.output.iter_mut().collect();
+    for node in &mut record.nodes {
+        fields.extend(node.state.input.iter_mut());
+        fields.extend(node.state.output.iter_mut());
+    }
+    fields
+}
+
+fn dead_letter_fields(letter: &mut DeadLetter) -> Vec<&mut JsonValue> {
+    letter.input.values_mut().chain(letter.variables.values_mut()).collect()
+}
+
+fn seal_value(data_key: &Aes256Gcm, value: &JsonValue) -> Result<JsonValue, WorkflowError> {
+    let plaintext = serde_json::to_vec(value).map_err(|e| encryption_error(e.to_string()))?;
+    Ok(serde_json::json!({ ENCRYPTED_FIELD: seal(data_key, &plaintext)? }))
+}
+
+fn open_value(data_key: &Aes256Gcm, value: &JsonValue) -> Result<JsonValue, WorkflowError> {
+    let sealed = sealed_payload(value).ok_or_else(|| encryption_error("not an encrypted field".to_string()))?;
+    serde_json::from_slice(&open(data_key, sealed)?).map_err(|e| encryption_error(e.to_string()))
+}
+
+/// Encrypt with AES-256-GCM and a random nonce, as base64 of nonce + ciphertext
+fn seal(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<String, WorkflowError> {
+    let nonce_bytes: [u8; NONCE_LEN] = rand::thread_rng().gen();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), plaintext)
+        .map_err(|_| encryption_error("encryption failed".to_string()))?;
+    let mut sealed = nonce_bytes.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(general_purpose::STANDARD.encode(sealed))
+}
+
+fn open(cipher: &Aes256Gcm, sealed: &str) -> Result<Vec<u8>, WorkflowError> {
+    let data = general_purpose::STANDARD.decode(sealed)
+        .map_err(|_| encryption_error("invalid ciphertext".to_string()))?;
+    if data.len() < NONCE_LEN {
+        return Err(encryption_error("invalid ciphertext".to_string()));
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| encryption_error("decryption failed".to_string()))
+}
+
+fn encryption_error(message: String) -> WorkflowError {
+    WorkflowError::Storage(format!("encryption: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dead_letter::{record_failure, FailedExecution, InMemoryDeadLetterStore};
+    use crate::history::NodeRecord;
+    use crate::settings::OrgSettings;
+    use common::types::{ExecutionResult, ExecutionState, NodeExecutionState, Workflow, WORKFLOW_SCHEMA_VERSION};
+    use serde_json::json;
+
+    async fn opted_in(organization_id: Uuid) -> Arc<OrgSettingsStore> {
+        let settings = Arc::new(OrgSettingsStore::new());
+        settings.set(organization_id, OrgSettings {
+            encrypt_execution_data: true,
+            ..Default::default()
+        }).await;
+        settings
+    }
+
+    fn record(organization_id: Uuid) -> ExecutionRecord {
+        ExecutionRecord {
+            workflow_id: Uuid::new_v4(),
+            organization_id: Some(organization_id),
+            started_at: Utc::now(),
+            result: ExecutionResult {
+                execution_id: Uuid::new_v4(),
+                state: ExecutionState::Completed,
+                completed_at: Some(Utc::now()),
+                error: None,
+                output: Some(json!({ "ssn": "123-45-6789" })),
+                usage: None,
+            },
+            nodes: vec![NodeRecord {
+                state: NodeExecutionState {
+                    node_id: Uuid::new_v4(),
+                    state: ExecutionState::Completed,
+                    started_at: None,
+                    completed_at: None,
+                    input: Some(json!({ "card": "4111 1111 1111 1111" })),
+                    output: None,
+                    error: None,
+                },
+                duration_ms: 3,
+                provider: None,
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_envelope_encryption_and_master_key_rotation() {
+        let organization_id = Uuid::new_v4();
+        let settings = opted_in(organization_id).await;
+        let keys: Arc<dyn DataKeyStore> = Arc::new(InMemoryDataKeyStore::new());
+        let encryption = PayloadEncryption::new(1, &[7u8; 32], keys.clone(), settings.clone());
+
+        let plain = record(organization_id);
+        let mut stored = plain.clone();
+        encryption.encrypt_record(&mut stored).await.unwrap();
+        let stored_json = serde_json::to_string(&stored).unwrap();
+        assert!(!stored_json.contains("123-45-6789") && !stored_json.contains("4111"));
+        assert!(is_encrypted(stored.nodes[0].state.input.as_ref().unwrap()));
+        assert!(stored.nodes[0].state.output.is_none());
+        assert_eq!(keys.get(organization_id).await.unwrap().unwrap().master_key_id, 1);
+
+        // Organizations that did not opt in are stored as they are
+        let mut other = record(Uuid::new_v4());
+        encryption.encrypt_record(&mut other).await.unwrap();
+        assert_eq!(other.result.output, Some(json!({ "ssn": "123-45-6789" })));
+
+        let mut read = stored.clone();
+        encryption.decrypt_record(&mut read).await.unwrap();
+        assert_eq!(read.result.output, plain.result.output);
+        assert_eq!(read.nodes[0].state.input, plain.nodes[0].state.input);
+
+        assert_eq!(encryption.rotate_master_key(2, &[9u8; 32]).await.unwrap(), 1);
+        assert_eq!(encryption.rotate_master_key(2, &[9u8; 32]).await.unwrap(), 0);
+        assert!(encryption.rotate_master_key(1, &[7u8; 32]).await.is_err());
+        let rewrapped = keys.get(organization_id).await.unwrap().unwrap();
+        assert!(rewrapped.master_key_id == 2 && rewrapped.rotated_at.is_some());
+
+        // Payloads stored before the rotation read with the new master key alone
+        let restarted = PayloadEncryption::new(2, &[9u8; 32], keys.clone(), settings.clone());
+        let mut read = stored.clone();
+        restarted.decrypt_record(&mut read).await.unwrap();
+        assert_eq!(read.result.output, plain.result.output);
+        let retired = PayloadEncryption::new(1, &[7u8; 32], keys, settings);
+        assert!(retired.decrypt_record(&mut stored.clone()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_dead_letter_store() {
+        let organization_id = Uuid::new_v4();
+        let encryption = Arc::new(PayloadEncryption::new(
+            1,
+            &[3u8; 32],
+            Arc::new(InMemoryDataKeyStore::new()),
+            opted_in(organization_id).await,
+        ));
+        let inner = Arc::new(InMemoryDeadLetterStore::new());
+        let store = EncryptedDeadLetterStore::new(inner.clone(), encryption);
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Billing".to_string(),
+            description: None,
+            nodes: vec![],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        };
+
+        let letter = record_failure(&store, FailedExecution {
+            execution_id: Uuid::new_v4(),
+            workflow,
+            organization_id: Some(organization_id),
+            failed_node: None,
+            error: "Card declined".to_string(),
+            input: HashMap::from([("card".to_string(), json!("4111 1111 1111 1111"))]),
+            variables: HashMap::from([("amount".to_string(), json!(42))]),
+        }).await.unwrap();
+
+        let at_rest = inner.get(letter.id).await.unwrap().unwrap();
+        assert!(is_encrypted(&at_rest.input["card"]) && is_encrypted(&at_rest.variables["amount"]));
+        assert_eq!(at_rest.error, "Card declined");
+        let read = store.get(letter.id).await.unwrap().unwrap();
+        assert_eq!(read.input["card"], "4111 1111 1111 1111");
+        assert_eq!(store.list(None, None).await.unwrap()[0].variables["amount"], 42);
+    }
+}
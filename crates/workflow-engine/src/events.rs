@@ -0,0 +1,244 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common::error::WorkflowError;
+use common::types::ExecutionState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc, RwLock};
+use uuid::Uuid;
+
+/// Progress event emitted by the executor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionEvent {
+    pub execution_id: Uuid,
+    pub workflow_id: Uuid,
+    #[serde(flatten)]
+    pub kind: ExecutionEventKind,
+    /// Share of the workflow's top-level nodes finished or skipped, 0.0 to 1.0
+    pub progress: f32,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ExecutionEventKind {
+    NodeStarted { node_id: Uuid },
+    NodeCompleted { node_id: Uuid, duration_ms: u64 },
+    /// The node failed; the execution goes on when the node's `on_error` allows it
+    NodeFailed { node_id: Uuid, error: String },
+    /// A streaming AI node's generation so far: tokens received, the text
+    /// received since the node's previous progress event, and the end of the
+    /// text, capped at `ai::PREVIEW_CHARS` characters
+    NodeProgress {
+        node_id: Uuid,
+        tokens: u64,
+        #[serde(default)]
+        delta: String,
+        preview: String,
+    },
+    /// The execution was paused or resumed
+    StateChanged { state: ExecutionState },
+    ExecutionFinished { state: ExecutionState, error: Option<String> },
+}
+
+/// Receives executor progress events.
+///
+/// `publish` is called inline on the execution path and must not block.
+pub trait ExecutionEventBus: Send + Sync {
+    fn publish(&self, event: ExecutionEvent);
+}
+
+/// Event bus fanning events out to any number of subscribers
+pub struct BroadcastEventBus {
+    tx: broadcast::Sender<ExecutionEvent>,
+}
+
+impl BroadcastEventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity);
+        Self { tx }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ExecutionEvent> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for BroadcastEventBus {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl ExecutionEventBus for BroadcastEventBus {
+    fn publish(&self, event: ExecutionEvent) {
+        // No subscribers is fine, events are only for live progress
+        let _ = self.tx.send(event);
+    }
+}
+
+/// An event with its position in its execution's timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredEvent {
+    /// 1 for the first event of an execution, then increasing without gaps
+    pub sequence: u64,
+    #[serde(flatten)]
+    pub event: ExecutionEvent,
+}
+
+/// Append-only storage of execution events, the durable source for
+/// timelines and event replay
+#[async_trait]
+pub trait ExecutionEventStore: Send + Sync {
+    /// Append events, given in sequence order
+    async fn append(&self, events: Vec<StoredEvent>) -> Result<(), WorkflowError>;
+
+    /// Events of an execution with a sequence number above `after`, in order
+    async fn list(&self, execution_id: Uuid, after: u64) -> Result<Vec<StoredEvent>, WorkflowError>;
+}
+
+/// In-memory event store (for development, replace with database in production)
+#[derive(Default)]
+pub struct InMemoryEventStore {
+    events: RwLock<HashMap<Uuid, Vec<StoredEvent>>>,
+}
+
+impl InMemoryEventStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ExecutionEventStore for InMemoryEventStore {
+    async fn append(&self, events: Vec<StoredEvent>) -> Result<(), WorkflowError> {
+        let mut stored = self.events.write().await;
+        for event in events {
+            stored.entry(event.event.execution_id).or_default().push(event);
+        }
+        Ok(())
+    }
+
+    async fn list(&self, execution_id: Uuid, after: u64) -> Result<Vec<StoredEvent>, WorkflowError> {
+        let stored = self.events.read().await;
+        Ok(stored.get(&execution_id)
+            .map(|events| events.iter().filter(|e| e.sequence > after).cloned().collect())
+            .unwrap_or_default())
+    }
+}
+
+/// Event bus numbering events per execution and persisting them in order,
+/// optionally forwarding them to another bus for live delivery.
+///
+/// Writes happen on a background task so `publish` never waits on storage;
+/// create it inside a Tokio runtime.
+pub struct PersistentEventBus {
+    /// Next sequence number of each running execution
+    sequences: Mutex<HashMap<Uuid, u64>>,
+    tx: mpsc::UnboundedSender<StoredEvent>,
+    forward: Option<Arc<dyn ExecutionEventBus>>,
+}
+
+impl PersistentEventBus {
+    pub fn new(store: Arc<dyn ExecutionEventStore>) -> Self {
+        let (tx, mut rx) = mpsc::unbounded_channel::<StoredEvent>();
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                let mut batch = vec![event];
+                while let Ok(event) = rx.try_recv() {
+                    batch.push(event);
+                }
+                if let Err(e) = store.append(batch).await {
+                    tracing::error!("Failed to persist execution events: {}", e);
+                }
+            }
+        });
+        Self {
+            sequences: Mutex::new(HashMap::new()),
+            tx,
+            forward: None,
+        }
+    }
+
+    /// Also publish every event on `bus`, e.g. a [`BroadcastEventBus`] feeding WebSocket clients
+    pub fn with_forward(mut self, bus: Arc<dyn ExecutionEventBus>) -> Self {
+        self.forward = Some(bus);
+        self
+    }
+}
+
+impl ExecutionEventBus for PersistentEventBus {
+    fn publish(&self, event: ExecutionEvent) {
+        // Generation progress is only of interest while it happens
+        if matches!(event.kind, ExecutionEventKind::NodeProgress { .. }) {
+            if let Some(forward) = &self.forward {
+                forward.publish(event);
+            }
+            return;
+        }
+        let sequence = {
+            let mut sequences = self.sequences.lock().unwrap_or_else(|e| e.into_inner());
+            let next = sequences.entry(event.execution_id).or_insert(1);
+            let sequence = *next;
+            *next += 1;
+            if matches!(event.kind, ExecutionEventKind::ExecutionFinished { .. }) {
+                sequences.remove(&event.execution_id);
+            }
+            sequence
+        };
+        if let Some(forward) = &self.forward {
+            forward.publish(event.clone());
+        }
+        if self.tx.send(StoredEvent { sequence, event }).is_err() {
+            tracing::error!("Execution event writer stopped, dropping event");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_persistent_bus_numbers_events_per_execution() {
+        let store = Arc::new(InMemoryEventStore::new());
+        let live = Arc::new(BroadcastEventBus::default());
+        let mut subscriber = live.subscribe();
+        let bus = PersistentEventBus::new(store.clone()).with_forward(live.clone());
+
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let event = |execution_id: Uuid, kind: ExecutionEventKind| ExecutionEvent {
+            execution_id,
+            workflow_id: Uuid::new_v4(),
+            kind,
+            progress: 0.0,
+            timestamp: Utc::now(),
+        };
+        let node_id = Uuid::new_v4();
+        bus.publish(event(first, ExecutionEventKind::NodeStarted { node_id }));
+        bus.publish(event(second, ExecutionEventKind::NodeStarted { node_id }));
+        // Forwarded live but neither numbered nor stored
+        bus.publish(event(first, ExecutionEventKind::NodeProgress { node_id, tokens: 5, delta: "Hello".to_string(), preview: "Hello".to_string() }));
+        bus.publish(event(first, ExecutionEventKind::NodeCompleted { node_id, duration_ms: 3 }));
+        bus.publish(event(first, ExecutionEventKind::ExecutionFinished {
+            state: ExecutionState::Completed,
+            error: None,
+        }));
+        assert_eq!(subscriber.recv().await.unwrap().execution_id, first);
+
+        let mut timeline = Vec::new();
+        for _ in 0..50 {
+            timeline = store.list(first, 0).await.unwrap();
+            if timeline.len() == 3 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let sequences: Vec<u64> = timeline.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![1, 2, 3]);
+        assert!(matches!(timeline[2].event.kind, ExecutionEventKind::ExecutionFinished { .. }));
+        assert_eq!(store.list(first, 2).await.unwrap().len(), 1);
+        assert_eq!(store.list(second, 0).await.unwrap()[0].sequence, 1);
+    }
+}
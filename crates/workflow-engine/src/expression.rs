@@ -0,0 +1,667 @@
+use common::types::JsonValue;
+use std::fmt;
+
+use crate::filters::{to_text, FilterFn, FilterRegistry};
+
+/// Error raised while parsing or evaluating a condition expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpressionError(pub String);
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid expression: {}", self.0)
+    }
+}
+
+impl std::error::Error for ExpressionError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Dot,
+    Comma,
+    Pipe,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(JsonValue),
+    Path(Vec<PathSegment>),
+    Not(Box<Expr>),
+    Binary(Box<Expr>, &'static str, Box<Expr>),
+    Filter(Box<Expr>, FilterFn, Vec<Expr>),
+}
+
+/// Step of a path read from the expression scope
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Write a path the way it would appear in an expression, e.g.
+/// `input.items[0]` or `vars["node_…"]`
+pub fn path_to_string(segments: &[PathSegment]) -> String {
+    let mut text = String::new();
+    for segment in segments {
+        match segment {
+            PathSegment::Key(key) if is_identifier(key) => {
+                if !text.is_empty() {
+                    text.push('.');
+                }
+                text.push_str(key);
+            }
+            PathSegment::Key(key) => text.push_str(&format!("[{:?}]", key)),
+            PathSegment::Index(index) => text.push_str(&format!("[{}]", index)),
+        }
+    }
+    text
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Condition expression evaluator
+///
+/// Supports literals (numbers, quoted strings, `true`, `false`, `null`),
+/// dotted paths with `[n]` indexing resolved against a JSON scope,
+/// comparisons (`==`, `!=`, `>`, `>=`, `<`, `<=`, `contains`),
+/// boolean operators (`&&`/`and`, `||`/`or`, `!`/`not`), parentheses and
+/// filter pipelines (`value | date("%Y-%m-%d") | upper`) resolved from the
+/// central [`FilterRegistry`].
+pub struct Expression {
+    root: Expr,
+}
+
+impl Expression {
+    /// Parse an expression string
+    pub fn parse(source: &str) -> Result<Self, ExpressionError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let root = parser.parse_pipeline()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(ExpressionError(format!(
+                "unexpected token {:?}",
+                parser.tokens[parser.pos]
+            )));
+        }
+        Ok(Self { root })
+    }
+
+    /// Evaluate against a scope object and return the resulting value;
+    /// filter failures evaluate to `null`
+    pub fn evaluate(&self, scope: &JsonValue) -> JsonValue {
+        self.try_evaluate(scope).unwrap_or(JsonValue::Null)
+    }
+
+    /// Evaluate against a scope object, surfacing filter failures
+    pub fn try_evaluate(&self, scope: &JsonValue) -> Result<JsonValue, ExpressionError> {
+        eval(&self.root, scope)
+    }
+
+    /// Evaluate and coerce the result to a boolean
+    pub fn evaluate_bool(&self, scope: &JsonValue) -> bool {
+        is_truthy(&self.evaluate(scope))
+    }
+
+    /// Paths the expression reads from its scope, including filter arguments
+    pub fn paths(&self) -> Vec<&[PathSegment]> {
+        let mut paths = Vec::new();
+        collect_paths(&self.root, &mut paths);
+        paths
+    }
+}
+
+fn collect_paths<'a>(expr: &'a Expr, paths: &mut Vec<&'a [PathSegment]>) {
+    match expr {
+        Expr::Literal(_) => {}
+        Expr::Path(segments) => paths.push(segments),
+        Expr::Not(inner) => collect_paths(inner, paths),
+        Expr::Binary(left, _, right) => {
+            collect_paths(left, paths);
+            collect_paths(right, paths);
+        }
+        Expr::Filter(input, _, args) => {
+            collect_paths(input, paths);
+            for arg in args {
+                collect_paths(arg, paths);
+            }
+        }
+    }
+}
+
+/// The sources of the `{{ expression }}` placeholders in a template
+pub fn template_expressions(template: &str) -> Result<Vec<&str>, ExpressionError> {
+    let mut expressions = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| ExpressionError("unterminated '{{' in template".to_string()))?;
+        expressions.push(&after[..end]);
+        rest = &after[end + 2..];
+    }
+    Ok(expressions)
+}
+
+/// Collect the strings holding placeholders inside a parameter value, with
+/// their path, e.g. `headers.Authorization` or `items[2]`
+pub fn collect_templates<'a>(value: &'a JsonValue, path: String, templates: &mut Vec<(String, &'a str)>) {
+    match value {
+        JsonValue::String(s) if s.contains("{{") => templates.push((path, s)),
+        JsonValue::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                collect_templates(item, format!("{}[{}]", path, index), templates);
+            }
+        }
+        JsonValue::Object(map) => {
+            for (key, item) in map {
+                collect_templates(item, format!("{}.{}", path, key), templates);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Render `{{ expression }}` placeholders in a template
+///
+/// A template consisting of a single placeholder yields the raw value, so
+/// `"{{ input.items }}"` stays an array; otherwise values are interpolated
+/// as text.
+pub fn render_template(template: &str, scope: &JsonValue) -> Result<JsonValue, ExpressionError> {
+    render_template_mapped(template, scope, &mut |value| value)
+}
+
+/// Render a template like [`render_template`], passing each interpolated
+/// value through `map` first
+pub fn render_template_mapped(
+    template: &str,
+    scope: &JsonValue,
+    map: &mut dyn FnMut(JsonValue) -> JsonValue,
+) -> Result<JsonValue, ExpressionError> {
+    let trimmed = template.trim();
+    if let Some(inner) = trimmed.strip_prefix("{{").and_then(|t| t.strip_suffix("}}")) {
+        if !inner.contains("{{") && !inner.contains("}}") {
+            return Ok(map(Expression::parse(inner)?.try_evaluate(scope)?));
+        }
+    }
+
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| ExpressionError("unterminated '{{' in template".to_string()))?;
+        let value = map(Expression::parse(&after[..end])?.try_evaluate(scope)?);
+        rendered.push_str(&to_text(&value));
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(JsonValue::String(rendered))
+}
+
+/// Render every string inside a JSON value as a template
+pub fn render_value(value: &JsonValue, scope: &JsonValue) -> Result<JsonValue, ExpressionError> {
+    render_value_mapped(value, scope, &mut |value| value)
+}
+
+/// Render every string inside a JSON value as a template, passing each
+/// interpolated value through `map` first, e.g. to screen it
+pub fn render_value_mapped(
+    value: &JsonValue,
+    scope: &JsonValue,
+    map: &mut dyn FnMut(JsonValue) -> JsonValue,
+) -> Result<JsonValue, ExpressionError> {
+    Ok(match value {
+        JsonValue::String(s) if s.contains("{{") => render_template_mapped(s, scope, map)?,
+        JsonValue::Array(items) => JsonValue::Array(
+            items.iter().map(|v| render_value_mapped(v, scope, map)).collect::<Result<_, _>>()?,
+        ),
+        JsonValue::Object(object) => JsonValue::Object(
+            object.iter()
+                .map(|(k, v)| Ok((k.clone(), render_value_mapped(v, scope, map)?)))
+                .collect::<Result<_, ExpressionError>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+/// JavaScript-like truthiness used for condition results
+pub fn is_truthy(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Null => false,
+        JsonValue::Bool(b) => *b,
+        JsonValue::Number(n) => n.as_f64().map(|f| f != 0.0).unwrap_or(false),
+        JsonValue::String(s) => !s.is_empty(),
+        JsonValue::Array(a) => !a.is_empty(),
+        JsonValue::Object(_) => true,
+    }
+}
+
+/// Compare two values, treating numbers of different representations as equal
+pub fn values_equal(a: &JsonValue, b: &JsonValue) -> bool {
+    match (a, b) {
+        (JsonValue::Number(x), JsonValue::Number(y)) => x.as_f64() == y.as_f64(),
+        _ => a == b,
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ExpressionError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => { tokens.push(Token::LParen); i += 1; }
+            ')' => { tokens.push(Token::RParen); i += 1; }
+            '[' => { tokens.push(Token::LBracket); i += 1; }
+            ']' => { tokens.push(Token::RBracket); i += 1; }
+            '.' => { tokens.push(Token::Dot); i += 1; }
+            ',' => { tokens.push(Token::Comma); i += 1; }
+            '\'' | '"' => {
+                let quote = c;
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(ExpressionError("unterminated string".to_string())),
+                        Some('\\') => {
+                            if let Some(&next) = chars.get(i + 1) {
+                                value.push(next);
+                            }
+                            i += 2;
+                        }
+                        Some(&ch) if ch == quote => {
+                            i += 1;
+                            break;
+                        }
+                        Some(&ch) => {
+                            value.push(ch);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            c if c.is_ascii_digit() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let number = text
+                    .parse::<f64>()
+                    .map_err(|_| ExpressionError(format!("invalid number '{}'", text)))?;
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                tokens.push(match word.as_str() {
+                    "and" => Token::Op("&&"),
+                    "or" => Token::Op("||"),
+                    "not" => Token::Op("!"),
+                    "contains" => Token::Op("contains"),
+                    _ => Token::Ident(word),
+                });
+            }
+            _ => {
+                let two: String = chars[i..chars.len().min(i + 2)].iter().collect();
+                let op = match two.as_str() {
+                    "==" => Some("=="),
+                    "!=" => Some("!="),
+                    ">=" => Some(">="),
+                    "<=" => Some("<="),
+                    "&&" => Some("&&"),
+                    "||" => Some("||"),
+                    _ => None,
+                };
+                if let Some(op) = op {
+                    tokens.push(Token::Op(op));
+                    i += 2;
+                    continue;
+                }
+                if c == '|' {
+                    tokens.push(Token::Pipe);
+                    i += 1;
+                    continue;
+                }
+                let op = match c {
+                    '>' => ">",
+                    '<' => "<",
+                    '!' => "!",
+                    _ => return Err(ExpressionError(format!("unexpected character '{}'", c))),
+                };
+                tokens.push(Token::Op(op));
+                i += 1;
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_op(&self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => Some(*op),
+            _ => None,
+        }
+    }
+
+    /// Filters bind loosest: `a == b | upper` filters the comparison result
+    fn parse_pipeline(&mut self) -> Result<Expr, ExpressionError> {
+        let mut expr = self.parse_or()?;
+        while self.peek() == Some(&Token::Pipe) {
+            self.pos += 1;
+            let name = match self.next() {
+                Some(Token::Ident(name)) => name,
+                _ => return Err(ExpressionError("expected filter name after '|'".to_string())),
+            };
+            let filter = FilterRegistry::global()
+                .get(&name)
+                .ok_or_else(|| ExpressionError(format!("unknown filter '{}'", name)))?;
+
+            let mut args = Vec::new();
+            if self.peek() == Some(&Token::LParen) {
+                self.pos += 1;
+                if self.peek() == Some(&Token::RParen) {
+                    self.pos += 1;
+                } else {
+                    loop {
+                        args.push(self.parse_or()?);
+                        match self.next() {
+                            Some(Token::Comma) => continue,
+                            Some(Token::RParen) => break,
+                            _ => return Err(ExpressionError(format!("expected ',' or ')' in arguments to '{}'", name))),
+                        }
+                    }
+                }
+            }
+            expr = Expr::Filter(Box::new(expr), filter, args);
+        }
+        Ok(expr)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ExpressionError> {
+        let mut left = self.parse_and()?;
+        while let Some(op) = self.peek_op(&["||"]) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Expr::Binary(Box::new(left), op, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ExpressionError> {
+        let mut left = self.parse_comparison()?;
+        while let Some(op) = self.peek_op(&["&&"]) {
+            self.pos += 1;
+            let right = self.parse_comparison()?;
+            left = Expr::Binary(Box::new(left), op, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, ExpressionError> {
+        let left = self.parse_unary()?;
+        if let Some(op) = self.peek_op(&["==", "!=", ">", ">=", "<", "<=", "contains"]) {
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            return Ok(Expr::Binary(Box::new(left), op, Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ExpressionError> {
+        if self.peek_op(&["!"]).is_some() {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ExpressionError> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Literal(serde_json::json!(n))),
+            Some(Token::Str(s)) => Ok(Expr::Literal(JsonValue::String(s))),
+            Some(Token::LParen) => {
+                let expr = self.parse_pipeline()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err(ExpressionError("expected ')'".to_string())),
+                }
+            }
+            Some(Token::Ident(word)) => match word.as_str() {
+                "true" => Ok(Expr::Literal(JsonValue::Bool(true))),
+                "false" => Ok(Expr::Literal(JsonValue::Bool(false))),
+                "null" => Ok(Expr::Literal(JsonValue::Null)),
+                _ => self.parse_path(word),
+            },
+            Some(token) => Err(ExpressionError(format!("unexpected token {:?}", token))),
+            None => Err(ExpressionError("unexpected end of expression".to_string())),
+        }
+    }
+
+    fn parse_path(&mut self, first: String) -> Result<Expr, ExpressionError> {
+        let mut segments = vec![PathSegment::Key(first)];
+        loop {
+            match self.peek() {
+                Some(Token::Dot) => {
+                    self.pos += 1;
+                    match self.next() {
+                        Some(Token::Ident(key)) => segments.push(PathSegment::Key(key)),
+                        _ => return Err(ExpressionError("expected property name after '.'".to_string())),
+                    }
+                }
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+                    let segment = match self.next() {
+                        Some(Token::Number(n)) if n >= 0.0 && n.fract() == 0.0 => {
+                            PathSegment::Index(n as usize)
+                        }
+                        Some(Token::Str(key)) => PathSegment::Key(key),
+                        _ => return Err(ExpressionError("expected index or quoted key".to_string())),
+                    };
+                    if self.next() != Some(Token::RBracket) {
+                        return Err(ExpressionError("expected ']'".to_string()));
+                    }
+                    segments.push(segment);
+                }
+                _ => break,
+            }
+        }
+        Ok(Expr::Path(segments))
+    }
+}
+
+fn eval(expr: &Expr, scope: &JsonValue) -> Result<JsonValue, ExpressionError> {
+    Ok(match expr {
+        Expr::Literal(value) => value.clone(),
+        Expr::Path(segments) => resolve_path(segments, scope),
+        Expr::Not(inner) => JsonValue::Bool(!is_truthy(&eval(inner, scope)?)),
+        Expr::Filter(input, filter, args) => {
+            let value = eval(input, scope)?;
+            let args = args.iter().map(|a| eval(a, scope)).collect::<Result<Vec<_>, _>>()?;
+            filter(&value, &args)?
+        }
+        Expr::Binary(left, op, right) => {
+            let l = eval(left, scope)?;
+            // Short-circuit boolean operators
+            match *op {
+                "&&" if !is_truthy(&l) => return Ok(JsonValue::Bool(false)),
+                "||" if is_truthy(&l) => return Ok(JsonValue::Bool(true)),
+                _ => {}
+            }
+            let r = eval(right, scope)?;
+            JsonValue::Bool(match *op {
+                "&&" | "||" => is_truthy(&r),
+                "==" => values_equal(&l, &r),
+                "!=" => !values_equal(&l, &r),
+                "contains" => match (&l, &r) {
+                    (JsonValue::String(haystack), JsonValue::String(needle)) => haystack.contains(needle.as_str()),
+                    (JsonValue::Array(items), needle) => items.iter().any(|item| values_equal(item, needle)),
+                    (JsonValue::Object(map), JsonValue::String(key)) => map.contains_key(key),
+                    _ => false,
+                },
+                ordering_op => compare(&l, &r, ordering_op),
+            })
+        }
+    })
+}
+
+fn compare(l: &JsonValue, r: &JsonValue, op: &str) -> bool {
+    let ordering = match (l, r) {
+        (JsonValue::Number(a), JsonValue::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+        (JsonValue::String(a), JsonValue::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    let Some(ordering) = ordering else {
+        return false;
+    };
+    match op {
+        ">" => ordering.is_gt(),
+        ">=" => ordering.is_ge(),
+        "<" => ordering.is_lt(),
+        "<=" => ordering.is_le(),
+        _ => false,
+    }
+}
+
+fn resolve_path(segments: &[PathSegment], scope: &JsonValue) -> JsonValue {
+    let mut current = scope;
+    for segment in segments {
+        let next = match segment {
+            PathSegment::Key(key) => current.get(key.as_str()),
+            PathSegment::Index(index) => current.get(*index),
+        };
+        match next {
+            Some(value) => current = value,
+            None => return JsonValue::Null,
+        }
+    }
+    current.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_comparisons_and_paths() {
+        let scope = json!({
+            "input": { "status": 200, "items": [{ "name": "a" }, { "name": "b" }] },
+            "region": "eu"
+        });
+
+        let expr = Expression::parse("input.status >= 200 && input.status < 300").unwrap();
+        assert!(expr.evaluate_bool(&scope));
+
+        let expr = Expression::parse("input.items[1].name == 'b' and region != \"us\"").unwrap();
+        assert!(expr.evaluate_bool(&scope));
+
+        let expr = Expression::parse("!(input.missing) || false").unwrap();
+        assert!(expr.evaluate_bool(&scope));
+
+        let expr = Expression::parse("input.items contains 'a'").unwrap();
+        assert!(!expr.evaluate_bool(&scope));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Expression::parse("a ==").is_err());
+        assert!(Expression::parse("(a == 1").is_err());
+        assert!(Expression::parse("'open").is_err());
+        assert!(Expression::parse("a # b").is_err());
+        assert!(Expression::parse("a | no_such_filter").is_err());
+        assert!(Expression::parse("a | truncate(3").is_err());
+    }
+
+    #[test]
+    fn test_filter_pipelines_and_templates() {
+        let scope = json!({
+            "order": { "id": 42, "placed_at": "2024-03-05T14:30:00Z", "total": 1234.5, "tags": ["new", "vip"] },
+            "name": "  ada  "
+        });
+
+        let expr = Expression::parse("order.placed_at | date('%Y-%m-%d') | upper").unwrap();
+        assert_eq!(expr.evaluate(&scope), "2024-03-05");
+
+        let expr = Expression::parse("(name | trim | capitalize) == 'Ada'").unwrap();
+        assert!(expr.evaluate_bool(&scope));
+
+        let expr = Expression::parse("order.missing | default('n/a')").unwrap();
+        assert_eq!(expr.evaluate(&scope), "n/a");
+
+        let rendered = render_template(
+            "Order #{{ order.id }} on {{ order.placed_at | date(\"%d %B\", \"de\") }}: {{ order.total | number(2, 'de') }}",
+            &scope,
+        ).unwrap();
+        assert_eq!(rendered, "Order #42 on 05 März: 1.234,50");
+
+        // A lone placeholder keeps the value's type
+        assert_eq!(render_template("{{ order.tags }}", &scope).unwrap(), json!(["new", "vip"]));
+        assert_eq!(
+            render_value(&json!({ "q": "{{ order.tags | join(' ') | url_encode }}" }), &scope).unwrap(),
+            json!({ "q": "new%20vip" })
+        );
+
+        let failing = Expression::parse("name | json_decode").unwrap();
+        assert!(failing.try_evaluate(&scope).is_err());
+        assert!(failing.evaluate(&scope).is_null());
+    }
+
+    #[test]
+    fn test_referenced_paths() {
+        let expressions = template_expressions("{{ input.items[0].name }} by {{ vars['node-1'] | default(author) }}").unwrap();
+        assert_eq!(expressions.len(), 2);
+
+        let first = Expression::parse(expressions[0]).unwrap();
+        let paths: Vec<String> = first.paths().into_iter().map(path_to_string).collect();
+        assert_eq!(paths, vec!["input.items[0].name"]);
+
+        let second = Expression::parse(expressions[1]).unwrap();
+        let paths: Vec<String> = second.paths().into_iter().map(path_to_string).collect();
+        assert_eq!(paths, vec!["vars[\"node-1\"]", "author"]);
+
+        assert!(template_expressions("{{ input").is_err());
+        assert!(template_expressions("no placeholders").unwrap().is_empty());
+    }
+}
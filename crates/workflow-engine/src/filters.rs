@@ -0,0 +1,397 @@
+use base64::Engine;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use common::types::JsonValue;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{OnceLock, RwLock};
+
+use crate::expression::ExpressionError;
+
+/// A template filter: receives the piped value and the evaluated arguments
+pub type FilterFn = fn(&JsonValue, &[JsonValue]) -> Result<JsonValue, ExpressionError>;
+
+/// Central filter registry shared by every expression and template
+///
+/// Built-in filters are registered on first use; custom filters can be added
+/// with [`FilterRegistry::register`] and become available to all nodes.
+pub struct FilterRegistry {
+    filters: RwLock<HashMap<String, FilterFn>>,
+}
+
+impl FilterRegistry {
+    /// The process-wide registry
+    pub fn global() -> &'static FilterRegistry {
+        static REGISTRY: OnceLock<FilterRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(FilterRegistry::with_builtins)
+    }
+
+    fn with_builtins() -> Self {
+        let builtins: [(&str, FilterFn); 20] = [
+            ("upper", |v, _| Ok(JsonValue::String(to_text(v).to_uppercase()))),
+            ("lower", |v, _| Ok(JsonValue::String(to_text(v).to_lowercase()))),
+            ("trim", |v, _| Ok(JsonValue::String(to_text(v).trim().to_string()))),
+            ("capitalize", capitalize),
+            ("truncate", truncate),
+            ("replace", replace),
+            ("split", split),
+            ("join", join),
+            ("length", length),
+            ("default", |v, args| Ok(if v.is_null() || v == "" { arg(args, 0).clone() } else { v.clone() })),
+            ("date", date),
+            ("number", number),
+            ("json_encode", |v, _| Ok(JsonValue::String(v.to_string()))),
+            ("json_decode", json_decode),
+            ("base64_encode", |v, _| {
+                Ok(JsonValue::String(base64::engine::general_purpose::STANDARD.encode(to_text(v))))
+            }),
+            ("base64_decode", base64_decode),
+            ("url_encode", |v, _| Ok(JsonValue::String(urlencoding::encode(&to_text(v)).into_owned()))),
+            ("url_decode", url_decode),
+            ("first", |v, _| Ok(v.as_array().and_then(|a| a.first()).cloned().unwrap_or(JsonValue::Null))),
+            ("last", |v, _| Ok(v.as_array().and_then(|a| a.last()).cloned().unwrap_or(JsonValue::Null))),
+        ];
+
+        Self {
+            filters: RwLock::new(builtins.into_iter().map(|(name, f)| (name.to_string(), f)).collect()),
+        }
+    }
+
+    /// Register or replace a filter
+    pub fn register(&self, name: &str, filter: FilterFn) {
+        self.filters.write().unwrap().insert(name.to_string(), filter);
+    }
+
+    /// Look up a filter by name
+    pub fn get(&self, name: &str) -> Option<FilterFn> {
+        self.filters.read().unwrap().get(name).copied()
+    }
+
+    /// Names of all registered filters, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.filters.read().unwrap().keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+/// Render a value as plain text: strings verbatim, null as empty, others as JSON
+pub fn to_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        JsonValue::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn arg(args: &[JsonValue], index: usize) -> &JsonValue {
+    args.get(index).unwrap_or(&JsonValue::Null)
+}
+
+fn string_arg<'a>(args: &'a [JsonValue], index: usize, filter: &str) -> Result<&'a str, ExpressionError> {
+    arg(args, index)
+        .as_str()
+        .ok_or_else(|| ExpressionError(format!("{} expects a string argument at position {}", filter, index + 1)))
+}
+
+fn capitalize(value: &JsonValue, _: &[JsonValue]) -> Result<JsonValue, ExpressionError> {
+    let text = to_text(value);
+    let mut chars = text.chars();
+    Ok(JsonValue::String(match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new(),
+    }))
+}
+
+fn truncate(value: &JsonValue, args: &[JsonValue]) -> Result<JsonValue, ExpressionError> {
+    let text = to_text(value);
+    let max = arg(args, 0)
+        .as_u64()
+        .ok_or_else(|| ExpressionError("truncate expects a length".to_string()))? as usize;
+    if text.chars().count() <= max {
+        return Ok(JsonValue::String(text));
+    }
+    let suffix = arg(args, 1).as_str().unwrap_or("...");
+    let mut truncated: String = text.chars().take(max).collect();
+    truncated.push_str(suffix);
+    Ok(JsonValue::String(truncated))
+}
+
+fn replace(value: &JsonValue, args: &[JsonValue]) -> Result<JsonValue, ExpressionError> {
+    let from = string_arg(args, 0, "replace")?;
+    let to = string_arg(args, 1, "replace")?;
+    Ok(JsonValue::String(to_text(value).replace(from, to)))
+}
+
+fn split(value: &JsonValue, args: &[JsonValue]) -> Result<JsonValue, ExpressionError> {
+    let separator = string_arg(args, 0, "split")?;
+    Ok(JsonValue::Array(
+        to_text(value).split(separator).map(|s| JsonValue::String(s.to_string())).collect(),
+    ))
+}
+
+fn join(value: &JsonValue, args: &[JsonValue]) -> Result<JsonValue, ExpressionError> {
+    let separator = arg(args, 0).as_str().unwrap_or(",");
+    let items = value
+        .as_array()
+        .ok_or_else(|| ExpressionError("join expects an array".to_string()))?;
+    Ok(JsonValue::String(items.iter().map(to_text).collect::<Vec<_>>().join(separator)))
+}
+
+fn length(value: &JsonValue, _: &[JsonValue]) -> Result<JsonValue, ExpressionError> {
+    Ok(serde_json::json!(match value {
+        JsonValue::String(s) => s.chars().count(),
+        JsonValue::Array(a) => a.len(),
+        JsonValue::Object(o) => o.len(),
+        JsonValue::Null => 0,
+        _ => to_text(value).chars().count(),
+    }))
+}
+
+fn json_decode(value: &JsonValue, _: &[JsonValue]) -> Result<JsonValue, ExpressionError> {
+    match value {
+        JsonValue::String(s) => serde_json::from_str(s)
+            .map_err(|e| ExpressionError(format!("json_decode: {}", e))),
+        other => Ok(other.clone()),
+    }
+}
+
+fn base64_decode(value: &JsonValue, _: &[JsonValue]) -> Result<JsonValue, ExpressionError> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(to_text(value).trim())
+        .map_err(|e| ExpressionError(format!("base64_decode: {}", e)))?;
+    String::from_utf8(bytes)
+        .map(JsonValue::String)
+        .map_err(|_| ExpressionError("base64_decode: result is not valid UTF-8".to_string()))
+}
+
+fn url_decode(value: &JsonValue, _: &[JsonValue]) -> Result<JsonValue, ExpressionError> {
+    urlencoding::decode(&to_text(value))
+        .map(|s| JsonValue::String(s.into_owned()))
+        .map_err(|e| ExpressionError(format!("url_decode: {}", e)))
+}
+
+/// Parse a date from an RFC 3339 / ISO string or a Unix timestamp in seconds
+fn parse_date(value: &JsonValue) -> Result<DateTime<Utc>, ExpressionError> {
+    if let Some(seconds) = value.as_i64() {
+        return Utc
+            .timestamp_opt(seconds, 0)
+            .single()
+            .ok_or_else(|| ExpressionError(format!("date: invalid timestamp {}", seconds)));
+    }
+
+    let text = value
+        .as_str()
+        .ok_or_else(|| ExpressionError("date expects a string or timestamp".to_string()))?;
+    if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+        return Ok(date.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok(date.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map(|d| d.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+        .map_err(|_| ExpressionError(format!("date: cannot parse '{}'", text)))
+}
+
+/// `date(format = "%Y-%m-%d", locale = "en")`
+///
+/// Uses strftime syntax; `%B`, `%b`, `%A` and `%a` are rendered with the
+/// locale's month and weekday names.
+fn date(value: &JsonValue, args: &[JsonValue]) -> Result<JsonValue, ExpressionError> {
+    let date = parse_date(value)?;
+    let format = arg(args, 0).as_str().unwrap_or("%Y-%m-%d");
+    let locale = Locale::resolve(arg(args, 1).as_str())?;
+
+    // Substitute locale names first so chrono only sees numeric specifiers
+    let mut localized = String::with_capacity(format.len());
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            localized.push(c);
+            continue;
+        }
+        let month = date.month0() as usize;
+        let weekday = date.weekday().num_days_from_monday() as usize;
+        let name = match chars.peek() {
+            Some('B') => Some(locale.months[month]),
+            Some('b') => Some(locale.months_short[month]),
+            Some('A') => Some(locale.weekdays[weekday]),
+            Some('a') => Some(locale.weekdays_short[weekday]),
+            _ => None,
+        };
+        match name {
+            Some(name) => {
+                chars.next();
+                localized.push_str(name);
+            }
+            None => {
+                localized.push('%');
+                if let Some(next) = chars.next() {
+                    localized.push(next);
+                }
+            }
+        }
+    }
+
+    let mut rendered = String::new();
+    write!(rendered, "{}", date.format(&localized))
+        .map_err(|_| ExpressionError(format!("date: invalid format '{}'", format)))?;
+    Ok(JsonValue::String(rendered))
+}
+
+/// `number(decimals, locale = "en")` with locale grouping and decimal separators
+///
+/// Integers default to no decimals, other numbers to two.
+fn number(value: &JsonValue, args: &[JsonValue]) -> Result<JsonValue, ExpressionError> {
+    let n = match value {
+        JsonValue::Number(n) => n.as_f64().unwrap_or_default(),
+        JsonValue::String(s) => s
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| ExpressionError(format!("number: '{}' is not numeric", s)))?,
+        _ => return Err(ExpressionError("number expects a numeric value".to_string())),
+    };
+    let decimals = arg(args, 0)
+        .as_u64()
+        .map(|d| d as usize)
+        .unwrap_or(if value.is_i64() || value.is_u64() { 0 } else { 2 });
+    let locale = Locale::resolve(arg(args, 1).as_str())?;
+
+    let fixed = format!("{:.*}", decimals, n.abs());
+    let (integer, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+
+    let mut grouped = String::new();
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i).is_multiple_of(3) {
+            grouped.push_str(locale.thousands);
+        }
+        grouped.push(digit);
+    }
+    if !fraction.is_empty() {
+        grouped.push(locale.decimal);
+        grouped.push_str(fraction);
+    }
+    if n < 0.0 && fixed.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        grouped.insert(0, '-');
+    }
+    Ok(JsonValue::String(grouped))
+}
+
+/// Locale data used by the `date` and `number` filters
+struct Locale {
+    decimal: char,
+    thousands: &'static str,
+    months: [&'static str; 12],
+    months_short: [&'static str; 12],
+    /// Monday first
+    weekdays: [&'static str; 7],
+    weekdays_short: [&'static str; 7],
+}
+
+impl Locale {
+    /// Resolve a tag such as `de`, `de-DE` or `pt_BR` by its language part
+    fn resolve(tag: Option<&str>) -> Result<&'static Locale, ExpressionError> {
+        let tag = tag.unwrap_or("en");
+        let language = tag.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+        match language.as_str() {
+            "en" => Ok(&EN),
+            "de" => Ok(&DE),
+            "fr" => Ok(&FR),
+            "es" => Ok(&ES),
+            "zh" => Ok(&ZH),
+            "ja" => Ok(&JA),
+            _ => Err(ExpressionError(format!("unsupported locale '{}'", tag))),
+        }
+    }
+}
+
+static EN: Locale = Locale {
+    decimal: '.',
+    thousands: ",",
+    months: ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"],
+    months_short: ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"],
+    weekdays: ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"],
+    weekdays_short: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+};
+
+static DE: Locale = Locale {
+    decimal: ',',
+    thousands: ".",
+    months: ["Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September", "Oktober", "November", "Dezember"],
+    months_short: ["Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez"],
+    weekdays: ["Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag", "Sonntag"],
+    weekdays_short: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+};
+
+static FR: Locale = Locale {
+    decimal: ',',
+    thousands: "\u{202f}",
+    months: ["janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre", "novembre", "décembre"],
+    months_short: ["janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.", "nov.", "déc."],
+    weekdays: ["lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche"],
+    weekdays_short: ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+};
+
+static ES: Locale = Locale {
+    decimal: ',',
+    thousands: ".",
+    months: ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre"],
+    months_short: ["ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov", "dic"],
+    weekdays: ["lunes", "martes", "miércoles", "jueves", "viernes", "sábado", "domingo"],
+    weekdays_short: ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+};
+
+static ZH: Locale = Locale {
+    decimal: '.',
+    thousands: ",",
+    months: ["一月", "二月", "三月", "四月", "五月", "六月", "七月", "八月", "九月", "十月", "十一月", "十二月"],
+    months_short: ["1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月", "9月", "10月", "11月", "12月"],
+    weekdays: ["星期一", "星期二", "星期三", "星期四", "星期五", "星期六", "星期日"],
+    weekdays_short: ["周一", "周二", "周三", "周四", "周五", "周六", "周日"],
+};
+
+static JA: Locale = Locale {
+    decimal: '.',
+    thousands: ",",
+    months: ["1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月", "9月", "10月", "11月", "12月"],
+    months_short: ["1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月", "9月", "10月", "11月", "12月"],
+    weekdays: ["月曜日", "火曜日", "水曜日", "木曜日", "金曜日", "土曜日", "日曜日"],
+    weekdays_short: ["月", "火", "水", "木", "金", "土", "日"],
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn apply(name: &str, value: JsonValue, args: &[JsonValue]) -> Result<JsonValue, ExpressionError> {
+        FilterRegistry::global().get(name).expect("filter registered")(&value, args)
+    }
+
+    #[test]
+    fn test_locale_formatting() {
+        let value = json!("2024-03-05T14:30:00Z");
+        assert_eq!(apply("date", value.clone(), &[]).unwrap(), "2024-03-05");
+        assert_eq!(apply("date", value.clone(), &[json!("%A %d %B %Y"), json!("de-DE")]).unwrap(), "Dienstag 05 März 2024");
+        assert_eq!(apply("date", value, &[json!("%e %b, %H:%M"), json!("fr")]).unwrap(), " 5 mars, 14:30");
+        assert!(apply("date", json!("yesterday"), &[]).is_err());
+
+        assert_eq!(apply("number", json!(1234567), &[]).unwrap(), "1,234,567");
+        assert_eq!(apply("number", json!(-1234.5), &[json!(2), json!("de")]).unwrap(), "-1.234,50");
+        assert_eq!(apply("number", json!("0.004"), &[json!(1)]).unwrap(), "0.0");
+        assert!(apply("number", json!(1), &[json!(0), json!("xx")]).is_err());
+    }
+
+    #[test]
+    fn test_encoding_filters() {
+        let encoded = apply("base64_encode", json!("hello"), &[]).unwrap();
+        assert_eq!(encoded, "aGVsbG8=");
+        assert_eq!(apply("base64_decode", encoded, &[]).unwrap(), "hello");
+        assert_eq!(apply("url_encode", json!("a b&c"), &[]).unwrap(), "a%20b%26c");
+        assert_eq!(apply("json_decode", json!("{\"a\":[1]}"), &[]).unwrap(), json!({ "a": [1] }));
+        assert_eq!(apply("json_encode", json!({ "a": 1 }), &[]).unwrap(), "{\"a\":1}");
+        assert_eq!(apply("truncate", json!("abcdef"), &[json!(3)]).unwrap(), "abc...");
+    }
+}
@@ -0,0 +1,198 @@
+use common::types::{Node, NodeType, Workflow};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use uuid::Uuid;
+
+/// Nodes a workflow carries but never usefully runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GraphAnalysis {
+    /// Nodes no trigger leads to
+    pub unreachable: Vec<Uuid>,
+    /// Reachable condition and loop nodes whose result never reaches an
+    /// action, AI or custom node
+    pub dead_ends: Vec<Uuid>,
+}
+
+impl GraphAnalysis {
+    pub fn is_empty(&self) -> bool {
+        self.unreachable.is_empty() && self.dead_ends.is_empty()
+    }
+}
+
+/// What a cleanup or restore moved
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrashMove {
+    pub nodes: Vec<Uuid>,
+    pub edges: Vec<Uuid>,
+}
+
+/// Find unreachable and dead-end nodes.
+///
+/// A workflow without triggers reports nothing unreachable: the validator
+/// already rejects it, and cleanup must not empty it.
+pub fn analyze(workflow: &Workflow) -> GraphAnalysis {
+    let triggers: Vec<Uuid> = workflow.nodes.iter()
+        .filter(|n| matches!(n.node_type, NodeType::Trigger { .. }))
+        .map(|n| n.id)
+        .collect();
+    if triggers.is_empty() {
+        return GraphAnalysis::default();
+    }
+
+    let mut forward: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    let mut backward: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for edge in &workflow.edges {
+        forward.entry(edge.source).or_default().push(edge.target);
+        backward.entry(edge.target).or_default().push(edge.source);
+    }
+
+    let reachable = walk(triggers, &forward);
+    let effects = workflow.nodes.iter().filter(|n| does_work(n)).map(|n| n.id).collect();
+    let useful = walk(effects, &backward);
+
+    let mut analysis = GraphAnalysis::default();
+    for node in &workflow.nodes {
+        if !reachable.contains(&node.id) {
+            analysis.unreachable.push(node.id);
+        } else if matches!(node.node_type, NodeType::Condition { .. } | NodeType::Loop { .. })
+            && !useful.contains(&node.id)
+        {
+            analysis.dead_ends.push(node.id);
+        }
+    }
+    analysis
+}
+
+/// Move the given nodes, and every edge touching them, into the workflow's trash
+pub fn move_to_trash(workflow: &mut Workflow, node_ids: &[Uuid]) -> TrashMove {
+    let ids: HashSet<Uuid> = node_ids.iter().copied().collect();
+    let mut moved = TrashMove::default();
+
+    let (trashed, kept): (Vec<_>, Vec<_>) = workflow.nodes.drain(..).partition(|n| ids.contains(&n.id));
+    workflow.nodes = kept;
+    moved.nodes.extend(trashed.iter().map(|n| n.id));
+    workflow.trash.nodes.extend(trashed);
+
+    let (trashed, kept): (Vec<_>, Vec<_>) = workflow.edges.drain(..)
+        .partition(|e| ids.contains(&e.source) || ids.contains(&e.target));
+    workflow.edges = kept;
+    moved.edges.extend(trashed.iter().map(|e| e.id));
+    workflow.trash.edges.extend(trashed);
+
+    moved
+}
+
+/// Move every unreachable and dead-end node into the trash
+pub fn prune(workflow: &mut Workflow) -> TrashMove {
+    let analysis = analyze(workflow);
+    let ids: Vec<Uuid> = analysis.unreachable.into_iter().chain(analysis.dead_ends).collect();
+    move_to_trash(workflow, &ids)
+}
+
+/// Put trashed nodes back, along with trashed edges whose ends are both in
+/// the workflow again
+pub fn restore_from_trash(workflow: &mut Workflow, node_ids: &[Uuid]) -> TrashMove {
+    let ids: HashSet<Uuid> = node_ids.iter().copied().collect();
+    let mut moved = TrashMove::default();
+
+    let (restored, kept): (Vec<_>, Vec<_>) = workflow.trash.nodes.drain(..).partition(|n| ids.contains(&n.id));
+    workflow.trash.nodes = kept;
+    moved.nodes.extend(restored.iter().map(|n| n.id));
+    workflow.nodes.extend(restored);
+
+    let present: HashSet<Uuid> = workflow.nodes.iter().map(|n| n.id).collect();
+    let (restored, kept): (Vec<_>, Vec<_>) = workflow.trash.edges.drain(..)
+        .partition(|e| present.contains(&e.source) && present.contains(&e.target));
+    workflow.trash.edges = kept;
+    moved.edges.extend(restored.iter().map(|e| e.id));
+    workflow.edges.extend(restored);
+
+    moved
+}
+
+fn does_work(node: &Node) -> bool {
+    matches!(node.node_type, NodeType::Action { .. } | NodeType::AI { .. } | NodeType::Custom { .. })
+}
+
+fn walk(start: Vec<Uuid>, adjacency: &HashMap<Uuid, Vec<Uuid>>) -> HashSet<Uuid> {
+    let mut seen: HashSet<Uuid> = start.iter().copied().collect();
+    let mut queue: VecDeque<Uuid> = start.into();
+    while let Some(id) = queue.pop_front() {
+        for &next in adjacency.get(&id).into_iter().flatten() {
+            if seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::types::{ActionType, ConditionType, Edge, NodeConfig, Position, TriggerType, WORKFLOW_SCHEMA_VERSION};
+
+    fn node(node_type: NodeType) -> Node {
+        Node {
+            id: Uuid::new_v4(),
+            node_type,
+            config: NodeConfig::default(),
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![],
+        }
+    }
+
+    fn edge(source: Uuid, target: Uuid) -> Edge {
+        Edge {
+            id: Uuid::new_v4(),
+            source,
+            source_handle: "output".to_string(),
+            target,
+            target_handle: "input".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_prune_and_restore() {
+        let trigger = node(NodeType::Trigger { trigger_type: TriggerType::Manual });
+        let action = node(NodeType::Action { action_type: ActionType::Http });
+        let dangling_if = node(NodeType::Condition { condition_type: ConditionType::If });
+        let orphan = node(NodeType::Action { action_type: ActionType::Email });
+        let orphan_child = node(NodeType::Action { action_type: ActionType::Http });
+        let mut workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "graph".to_string(),
+            description: None,
+            edges: vec![
+                edge(trigger.id, action.id),
+                edge(action.id, dangling_if.id),
+                edge(orphan.id, orphan_child.id),
+            ],
+            nodes: vec![trigger.clone(), action.clone(), dangling_if.clone(), orphan.clone(), orphan_child.clone()],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+
+        let analysis = analyze(&workflow);
+        assert_eq!(analysis.unreachable, vec![orphan.id, orphan_child.id]);
+        assert_eq!(analysis.dead_ends, vec![dangling_if.id]);
+
+        let moved = prune(&mut workflow);
+        assert_eq!(moved.nodes.len(), 3);
+        assert_eq!(moved.edges.len(), 2);
+        assert_eq!(workflow.nodes.len(), 2);
+        assert_eq!(workflow.edges.len(), 1);
+        assert!(analyze(&workflow).is_empty());
+
+        // An edge comes back only once both of its ends are back
+        let moved = restore_from_trash(&mut workflow, &[orphan.id]);
+        assert_eq!((moved.nodes.len(), moved.edges.len()), (1, 0));
+        let moved = restore_from_trash(&mut workflow, &[orphan_child.id]);
+        assert_eq!((moved.nodes.len(), moved.edges.len()), (1, 1));
+        assert_eq!(workflow.trash.nodes.len(), 1);
+    }
+}
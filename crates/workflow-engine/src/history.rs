@@ -0,0 +1,475 @@
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Timelike, Utc};
+use chrono_tz::Tz;
+use common::types::{ActionType, ExecutionResult, ExecutionState, JsonValue, Node, NodeExecutionState, NodeType};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::encryption::PayloadEncryption;
+use crate::sampling::{hour_of, ExecutionCounters, HourWindow, SamplingPolicy, WorkflowCounters};
+
+/// Hours of execution counters kept per workflow
+const COUNTER_RETENTION_HOURS: i64 = 24 * 90;
+
+/// Organization of a workflow's latest execution, and the workflow's
+/// execution counters by the hour they started in
+type HourlyCounters = (Option<Uuid>, BTreeMap<DateTime<Utc>, ExecutionCounters>);
+
+/// A node run kept in the execution history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeRecord {
+    #[serde(flatten)]
+    pub state: NodeExecutionState,
+    pub duration_ms: u64,
+    /// External service the node called, see [`node_provider`]
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+/// The external service a node calls: the `provider` parameter or URL host of
+/// HTTP actions, the integration name, the scraper, or an AI node's `provider`
+pub fn node_provider(node: &Node) -> Option<String> {
+    let parameter = |name: &str| node.config.parameters.get(name).and_then(JsonValue::as_str).map(str::to_string);
+    match &node.node_type {
+        NodeType::Action { action_type: ActionType::Http } => parameter("provider")
+            .or_else(|| parameter("url").map(|url| crate::http::host(&url).to_string())),
+        NodeType::Action { action_type: ActionType::Integration } => parameter("integration"),
+        NodeType::Action { action_type: ActionType::Scraper } => Some("scraper".to_string()),
+        NodeType::AI { .. } => parameter("provider"),
+        _ => None,
+    }
+}
+
+/// Runs and failures bucketed by day of week (Monday first) and hour of day
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Heatmap {
+    pub runs: [[u32; 24]; 7],
+    pub failures: [[u32; 24]; 7],
+}
+
+impl Heatmap {
+    fn add(&mut self, at: DateTime<Tz>, runs: u32, failures: u32) {
+        let (day, hour) = (at.weekday().num_days_from_monday() as usize, at.hour() as usize);
+        self.runs[day][hour] += runs;
+        self.failures[day][hour] += failures;
+    }
+
+    pub fn total_failures(&self) -> u32 {
+        self.failures.iter().flatten().sum()
+    }
+}
+
+/// Failure patterns per workflow (executions, counted to the hour including
+/// sampled-out ones) and per provider (node runs of the retained history
+/// calling it)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FailureHeatmap {
+    /// Time zone the hours and days are in
+    pub timezone: String,
+    pub workflows: HashMap<Uuid, Heatmap>,
+    pub providers: HashMap<String, Heatmap>,
+}
+
+/// A finished execution with the node runs that led to its result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionRecord {
+    pub workflow_id: Uuid,
+    pub organization_id: Option<Uuid>,
+    pub started_at: DateTime<Utc>,
+    pub result: ExecutionResult,
+    /// Top-level node runs in execution order; loop bodies are part of their
+    /// loop node's output
+    pub nodes: Vec<NodeRecord>,
+}
+
+/// Recent executions of each workflow, for debugging runs, with hourly
+/// counters of all of them for analytics
+pub struct ExecutionHistory {
+    records: Arc<RwLock<HashMap<Uuid, ExecutionRecord>>>,
+    // Execution IDs per workflow, oldest first
+    by_workflow: Arc<RwLock<HashMap<Uuid, VecDeque<Uuid>>>>,
+    max_per_workflow: usize,
+    /// Sampling policies of high-frequency workflows
+    policies: Arc<RwLock<HashMap<Uuid, SamplingPolicy>>>,
+    /// Hour of the latest executions per sampled workflow
+    windows: Arc<RwLock<HashMap<Uuid, HourWindow>>>,
+    /// Counters per workflow and hour, including sampled-out executions
+    counters: Arc<RwLock<HashMap<Uuid, HourlyCounters>>>,
+    /// Encrypts the payloads of organizations that opted in before recording
+    encryption: Option<Arc<PayloadEncryption>>,
+}
+
+impl ExecutionHistory {
+    /// Keep the last `max_per_workflow` executions of each workflow
+    pub fn new(max_per_workflow: usize) -> Self {
+        Self {
+            records: Arc::new(RwLock::new(HashMap::new())),
+            by_workflow: Arc::new(RwLock::new(HashMap::new())),
+            max_per_workflow: max_per_workflow.max(1),
+            policies: Arc::new(RwLock::new(HashMap::new())),
+            windows: Arc::new(RwLock::new(HashMap::new())),
+            counters: Arc::new(RwLock::new(HashMap::new())),
+            encryption: None,
+        }
+    }
+
+    /// Keep execution payloads encrypted; records are returned as stored, use
+    /// [`PayloadEncryption::decrypt_record`] to read them
+    pub fn with_encryption(mut self, encryption: Arc<PayloadEncryption>) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    pub fn encryption(&self) -> Option<&Arc<PayloadEncryption>> {
+        self.encryption.as_ref()
+    }
+
+    /// Sample the executions of a workflow instead of keeping all of them
+    pub async fn set_sampling(&self, workflow_id: Uuid, policy: SamplingPolicy) -> Result<(), String> {
+        policy.validate()?;
+        self.policies.write().await.insert(workflow_id, policy);
+        Ok(())
+    }
+
+    /// Keep every execution of the workflow again
+    pub async fn clear_sampling(&self, workflow_id: Uuid) {
+        self.policies.write().await.remove(&workflow_id);
+        self.windows.write().await.remove(&workflow_id);
+    }
+
+    pub async fn sampling(&self, workflow_id: Uuid) -> Option<SamplingPolicy> {
+        self.policies.read().await.get(&workflow_id).cloned()
+    }
+
+    /// Count the execution, then keep it unless the workflow's sampling
+    /// policy drops it
+    pub async fn record(&self, mut record: ExecutionRecord) {
+        let execution_id = record.result.execution_id;
+        let (workflow_id, hour) = (record.workflow_id, hour_of(record.started_at));
+        let replaces = self.records.read().await.contains_key(&execution_id);
+        if !replaces {
+            let mut counters = self.counters.write().await;
+            let (organization_id, hours) = counters.entry(workflow_id).or_default();
+            *organization_id = record.organization_id;
+            hours.entry(hour).or_default().count(&record);
+            let oldest = hour - ChronoDuration::hours(COUNTER_RETENTION_HOURS);
+            while let Some(entry) = hours.first_entry().filter(|entry| *entry.key() < oldest) {
+                entry.remove();
+            }
+        }
+
+        let policy = self.policies.read().await.get(&workflow_id).cloned();
+        // (kept, provisionally kept as the last of its hour, dropped as no longer the last)
+        let (keep, provisional, superseded) = match &policy {
+            Some(policy) if !replaces => {
+                let selected = policy.selects(&record);
+                if policy.keep_hour_bounds {
+                    let mut windows = self.windows.write().await;
+                    match windows.get_mut(&workflow_id) {
+                        Some(window) if window.hour == hour => {
+                            let superseded = window.provisional.take();
+                            if !selected {
+                                window.provisional = Some(execution_id);
+                            }
+                            (true, !selected, superseded)
+                        }
+                        // Late executions of an earlier hour are neither its first nor its last
+                        Some(window) if window.hour > hour => (selected, false, None),
+                        _ => {
+                            windows.insert(workflow_id, HourWindow { hour, provisional: None });
+                            (true, false, None)
+                        }
+                    }
+                } else {
+                    (selected, false, None)
+                }
+            }
+            _ => (true, false, None),
+        };
+        let sampled_out = u64::from(!keep) + u64::from(superseded.is_some());
+        if sampled_out > 0 {
+            let mut counters = self.counters.write().await;
+            if let Some(counters) = counters.get_mut(&workflow_id).and_then(|(_, hours)| hours.get_mut(&hour)) {
+                counters.sampled_out += sampled_out;
+            }
+        }
+        if let Some(superseded) = superseded {
+            self.forget(workflow_id, superseded).await;
+        }
+        if !keep {
+            return;
+        }
+
+        if let Some(encryption) = &self.encryption {
+            // Payloads that should be encrypted are never kept in the clear
+            if let Err(e) = encryption.encrypt_record(&mut record).await {
+                tracing::error!("Failed to encrypt execution {}, not recording it: {}", execution_id, e);
+                if provisional {
+                    let mut windows = self.windows.write().await;
+                    if let Some(window) = windows.get_mut(&workflow_id).filter(|w| w.provisional == Some(execution_id)) {
+                        window.provisional = None;
+                    }
+                }
+                return;
+            }
+        }
+        let mut by_workflow = self.by_workflow.write().await;
+        let mut records = self.records.write().await;
+
+        let ids = by_workflow.entry(workflow_id).or_default();
+        if records.insert(execution_id, record).is_none() {
+            ids.push_back(execution_id);
+        }
+        while ids.len() > self.max_per_workflow {
+            if let Some(evicted) = ids.pop_front() {
+                records.remove(&evicted);
+            }
+        }
+    }
+
+    async fn forget(&self, workflow_id: Uuid, execution_id: Uuid) {
+        let mut by_workflow = self.by_workflow.write().await;
+        let mut records = self.records.write().await;
+        if let Some(ids) = by_workflow.get_mut(&workflow_id) {
+            ids.retain(|id| *id != execution_id);
+        }
+        records.remove(&execution_id);
+    }
+
+    /// Forget the executions of a deleted workflow; returns how many were dropped
+    pub async fn remove_workflow(&self, workflow_id: Uuid) -> usize {
+        let mut by_workflow = self.by_workflow.write().await;
+        let mut records = self.records.write().await;
+        let ids = by_workflow.remove(&workflow_id).unwrap_or_default();
+        self.policies.write().await.remove(&workflow_id);
+        self.windows.write().await.remove(&workflow_id);
+        self.counters.write().await.remove(&workflow_id);
+        ids.iter().filter(|id| records.remove(id).is_some()).count()
+    }
+
+    pub async fn get(&self, execution_id: Uuid) -> Option<ExecutionRecord> {
+        self.records.read().await.get(&execution_id).cloned()
+    }
+
+    /// Executions of a workflow, newest first
+    pub async fn list_for_workflow(&self, workflow_id: Uuid, limit: usize) -> Vec<ExecutionRecord> {
+        self.list_for_workflows(&[workflow_id], limit).await
+            .remove(&workflow_id)
+            .unwrap_or_default()
+    }
+
+    /// Executions of several workflows under one lock, newest first; every
+    /// requested workflow gets an entry
+    pub async fn list_for_workflows(&self, workflow_ids: &[Uuid], limit: usize) -> HashMap<Uuid, Vec<ExecutionRecord>> {
+        let by_workflow = self.by_workflow.read().await;
+        let records = self.records.read().await;
+        workflow_ids.iter()
+            .map(|workflow_id| {
+                let executions = by_workflow.get(workflow_id)
+                    .map(|ids| ids.iter().rev().filter_map(|id| records.get(id).cloned()).take(limit).collect())
+                    .unwrap_or_default();
+                (*workflow_id, executions)
+            })
+            .collect()
+    }
+
+    /// Executions started in `[since, until)` across all workflows, newest first
+    pub async fn list_between(&self, since: DateTime<Utc>, until: DateTime<Utc>) -> Vec<ExecutionRecord> {
+        let records = self.records.read().await;
+        let mut found: Vec<ExecutionRecord> = records.values()
+            .filter(|r| r.started_at >= since && r.started_at < until)
+            .cloned()
+            .collect();
+        found.sort_by_key(|r| std::cmp::Reverse(r.started_at));
+        found
+    }
+
+    /// Counters of the executions started in `[since, until)`, to the hour,
+    /// per workflow; unlike the retained records they include every execution
+    pub async fn counters_between(&self, since: DateTime<Utc>, until: DateTime<Utc>) -> HashMap<Uuid, WorkflowCounters> {
+        let counters = self.counters.read().await;
+        counters.iter()
+            .filter_map(|(workflow_id, (organization_id, hours))| {
+                let mut total = WorkflowCounters { organization_id: *organization_id, ..Default::default() };
+                let mut counted = false;
+                for (_, hour) in hours.range(hour_of(since)..until) {
+                    total.counters.add(hour);
+                    counted = true;
+                }
+                counted.then_some((*workflow_id, total))
+            })
+            .collect()
+    }
+
+    /// Bucket executions and provider calls started at or after `since` by
+    /// local day of week and hour in `timezone`; `workflow_id` limits the
+    /// buckets to one workflow
+    pub async fn failure_heatmap(
+        &self,
+        workflow_id: Option<Uuid>,
+        since: Option<DateTime<Utc>>,
+        timezone: Tz,
+    ) -> FailureHeatmap {
+        let records = self.records.read().await;
+        let mut heatmap = FailureHeatmap {
+            timezone: timezone.name().to_string(),
+            ..Default::default()
+        };
+
+        let counters = self.counters.read().await;
+        let since_hour = since.map(hour_of);
+        for (id, (_, hours)) in counters.iter().filter(|(id, _)| workflow_id.is_none_or(|w| **id == w)) {
+            let buckets = hours.iter().filter(|(hour, _)| since_hour.is_none_or(|since| **hour >= since));
+            for (hour, counted) in buckets {
+                heatmap.workflows.entry(*id).or_default()
+                    .add(hour.with_timezone(&timezone), counted.runs as u32, counted.failures as u32);
+            }
+        }
+
+        let selected = records.values()
+            .filter(|r| workflow_id.is_none_or(|id| r.workflow_id == id))
+            .filter(|r| since.is_none_or(|since| r.started_at >= since));
+        for record in selected {
+            for node in &record.nodes {
+                let (Some(provider), Some(started_at)) = (&node.provider, node.state.started_at) else {
+                    continue;
+                };
+                let failed = node.state.state == ExecutionState::Failed;
+                heatmap.providers.entry(provider.clone()).or_default()
+                    .add(started_at.with_timezone(&timezone), 1, u32::from(failed));
+            }
+        }
+        heatmap
+    }
+}
+
+impl Default for ExecutionHistory {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(workflow_id: Uuid) -> ExecutionRecord {
+        ExecutionRecord {
+            workflow_id,
+            organization_id: None,
+            started_at: Utc::now(),
+            result: ExecutionResult {
+                execution_id: Uuid::new_v4(),
+                state: ExecutionState::Completed,
+                completed_at: Some(Utc::now()),
+                error: None,
+                output: None,
+                usage: None,
+            },
+            nodes: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_history_keeps_newest_per_workflow() {
+        let history = ExecutionHistory::new(2);
+        let workflow_id = Uuid::new_v4();
+        let runs: Vec<ExecutionRecord> = (0..3).map(|_| record(workflow_id)).collect();
+        for run in &runs {
+            history.record(run.clone()).await;
+        }
+        history.record(record(Uuid::new_v4())).await;
+
+        let listed = history.list_for_workflow(workflow_id, 10).await;
+        let ids: Vec<Uuid> = listed.iter().map(|r| r.result.execution_id).collect();
+        assert_eq!(ids, vec![runs[2].result.execution_id, runs[1].result.execution_id]);
+        assert!(history.get(runs[0].result.execution_id).await.is_none());
+        assert_eq!(history.list_for_workflow(workflow_id, 1).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failure_heatmap_buckets_by_local_time() {
+        let history = ExecutionHistory::default();
+        let workflow_id = Uuid::new_v4();
+        // Mondays at 07:00 UTC, 02:00 in New York
+        for (day, state) in [(1, ExecutionState::Failed), (8, ExecutionState::Failed), (15, ExecutionState::Completed)] {
+            let started_at: DateTime<Utc> = format!("2024-01-{:02}T07:00:00Z", day).parse().unwrap();
+            let mut run = record(workflow_id);
+            run.started_at = started_at;
+            run.result.state = state.clone();
+            run.nodes.push(NodeRecord {
+                state: NodeExecutionState {
+                    node_id: Uuid::new_v4(),
+                    state,
+                    started_at: Some(started_at),
+                    completed_at: None,
+                    input: None,
+                    output: None,
+                    error: None,
+                },
+                duration_ms: 5,
+                provider: Some("api.example.com".to_string()),
+            });
+            history.record(run).await;
+        }
+
+        let heatmap = history.failure_heatmap(None, None, chrono_tz::America::New_York).await;
+        assert_eq!(heatmap.timezone, "America/New_York");
+        let provider = &heatmap.providers["api.example.com"];
+        assert_eq!((provider.runs[0][2], provider.failures[0][2]), (3, 2));
+        assert_eq!(heatmap.workflows[&workflow_id].total_failures(), 2);
+
+        let since = "2024-01-08T00:00:00Z".parse().unwrap();
+        let heatmap = history.failure_heatmap(Some(workflow_id), Some(since), Tz::UTC).await;
+        assert_eq!(heatmap.workflows[&workflow_id].failures[0][7], 1);
+        assert!(history.failure_heatmap(Some(Uuid::new_v4()), None, Tz::UTC).await.workflows.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sampling_keeps_failures_and_hour_bounds() {
+        let history = ExecutionHistory::default();
+        let workflow_id = Uuid::new_v4();
+        let policy = SamplingPolicy { success_percent: 0.0, keep_failures: true, keep_hour_bounds: true };
+        assert!(history.set_sampling(workflow_id, SamplingPolicy { success_percent: 150.0, ..policy.clone() }).await.is_err());
+        history.set_sampling(workflow_id, policy).await.unwrap();
+
+        let hour: DateTime<Utc> = "2024-01-01T07:00:00Z".parse().unwrap();
+        let run = |minute: i64, state: ExecutionState| {
+            let mut run = record(workflow_id);
+            run.started_at = hour + ChronoDuration::minutes(minute);
+            run.result.state = state;
+            run
+        };
+        let runs = [
+            run(0, ExecutionState::Completed),
+            run(10, ExecutionState::Completed),
+            run(20, ExecutionState::Completed),
+            run(30, ExecutionState::Failed),
+            run(40, ExecutionState::Completed),
+            run(70, ExecutionState::Completed),
+        ];
+        for run in &runs {
+            history.record(run.clone()).await;
+        }
+
+        // The first and last of each hour and the failure are kept
+        let kept: Vec<Uuid> = history.list_for_workflow(workflow_id, 10).await.iter().map(|r| r.result.execution_id).collect();
+        let expected: Vec<Uuid> = [5, 4, 3, 0].iter().map(|i| runs[*i].result.execution_id).collect();
+        assert_eq!(kept, expected);
+
+        let counters = history.counters_between(hour, hour + ChronoDuration::hours(1)).await;
+        let counted = &counters[&workflow_id].counters;
+        assert_eq!((counted.runs, counted.failures, counted.sampled_out), (5, 1, 2));
+        let heatmap = history.failure_heatmap(Some(workflow_id), None, Tz::UTC).await;
+        assert_eq!(heatmap.workflows[&workflow_id].runs[0][7], 5);
+
+        // Without hour bounds only the failures are left
+        history.set_sampling(workflow_id, SamplingPolicy { success_percent: 0.0, keep_failures: true, keep_hour_bounds: false }).await.unwrap();
+        history.record(run(80, ExecutionState::Completed)).await;
+        assert_eq!(history.list_for_workflow(workflow_id, 10).await.len(), 4);
+        let counters = history.counters_between(hour, hour + ChronoDuration::hours(2)).await;
+        assert_eq!((counters[&workflow_id].counters.runs, counters[&workflow_id].counters.sampled_out), (7, 3));
+    }
+}
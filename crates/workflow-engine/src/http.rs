@@ -0,0 +1,151 @@
+use async_trait::async_trait;
+use common::error::GatewayError;
+use common::types::{ApiRequest, ApiResponse, HttpMethod, JsonValue, Priority, RetryConfig};
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::filters::to_text;
+
+/// Default timeout of an HTTP action
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+/// Sends HTTP action requests on behalf of the executor.
+///
+/// Implemented by the API gateway, which queues requests in its request pool,
+/// applies provider rate limits and honors the request's `retry_config`.
+#[async_trait]
+pub trait HttpDispatcher: Send + Sync {
+    async fn dispatch(&self, request: ApiRequest) -> Result<ApiResponse, GatewayError>;
+}
+
+/// Build the gateway request of an HTTP action node from its rendered parameters.
+///
+/// Parameters: `url` (required), `method` (default GET), `headers`, `body`,
+/// `provider` (default: the URL host, used for rate limiting), `priority`,
+/// `timeout_ms` and `retry` (fields of [`RetryConfig`]; no retries when absent).
+pub fn build_request(
+    workflow_id: Uuid,
+    node_id: Uuid,
+    parameters: &JsonValue,
+) -> Result<ApiRequest, String> {
+    let url = parameters.get("url")
+        .and_then(JsonValue::as_str)
+        .filter(|url| !url.is_empty())
+        .ok_or("missing url parameter")?;
+
+    let method = match parameters.get("method").and_then(JsonValue::as_str) {
+        None => HttpMethod::GET,
+        Some(method) => match method.to_ascii_uppercase().as_str() {
+            "GET" => HttpMethod::GET,
+            "POST" => HttpMethod::POST,
+            "PUT" => HttpMethod::PUT,
+            "PATCH" => HttpMethod::PATCH,
+            "DELETE" => HttpMethod::DELETE,
+            other => return Err(format!("unsupported method: {}", other)),
+        },
+    };
+
+    let headers = match parameters.get("headers") {
+        None | Some(JsonValue::Null) => HashMap::new(),
+        Some(JsonValue::Object(map)) => map.iter().map(|(k, v)| (k.clone(), to_text(v))).collect(),
+        Some(_) => return Err("headers must be an object".to_string()),
+    };
+
+    let priority = match parameters.get("priority").and_then(JsonValue::as_str) {
+        None => Priority::Normal,
+        Some(priority) => match priority.to_ascii_lowercase().as_str() {
+            "critical" => Priority::Critical,
+            "high" => Priority::High,
+            "normal" => Priority::Normal,
+            "low" => Priority::Low,
+            other => return Err(format!("unsupported priority: {}", other)),
+        },
+    };
+
+    let retry_config = match parameters.get("retry") {
+        None | Some(JsonValue::Null) => RetryConfig { max_retries: 0, ..Default::default() },
+        Some(JsonValue::Object(fields)) => {
+            let mut config = serde_json::to_value(RetryConfig::default()).map_err(|e| e.to_string())?;
+            for (key, value) in fields {
+                config[key] = value.clone();
+            }
+            serde_json::from_value(config).map_err(|e| format!("invalid retry: {}", e))?
+        }
+        Some(_) => return Err("retry must be an object".to_string()),
+    };
+
+    let provider = parameters.get("provider")
+        .and_then(JsonValue::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| host(url).to_string());
+
+    Ok(ApiRequest {
+        id: Uuid::new_v4(),
+        provider,
+        endpoint: url.to_string(),
+        method,
+        headers,
+        body: parameters.get("body").filter(|b| !b.is_null()).cloned(),
+        priority,
+        workflow_id,
+        node_id,
+        organization_id: None,
+        timeout: Duration::from_millis(
+            parameters.get("timeout_ms").and_then(JsonValue::as_u64).unwrap_or(DEFAULT_TIMEOUT_MS),
+        ),
+        retry_config,
+    })
+}
+
+/// Node output for a gateway response
+pub fn response_output(response: &ApiResponse) -> JsonValue {
+    serde_json::json!({
+        "status": response.status_code,
+        "ok": (200..300).contains(&response.status_code),
+        "headers": response.headers,
+        "body": response.body,
+        "latency_ms": response.latency_ms,
+        "request_id": response.request_id
+    })
+}
+
+pub(crate) fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    let authority = authority.rsplit_once('@').map(|(_, host)| host).unwrap_or(authority);
+    authority.split(':').next().unwrap_or(authority)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_request() {
+        let parameters = serde_json::json!({
+            "url": "https://user@api.example.com:8443/v1/items?x=1",
+            "method": "post",
+            "headers": { "X-Count": 3 },
+            "body": { "name": "item" },
+            "priority": "high",
+            "retry": { "max_retries": 2 }
+        });
+        let request = build_request(Uuid::new_v4(), Uuid::new_v4(), &parameters).unwrap();
+        assert_eq!(request.provider, "api.example.com");
+        assert_eq!(request.method, HttpMethod::POST);
+        assert_eq!(request.headers["X-Count"], "3");
+        assert_eq!(request.priority, Priority::High);
+        assert_eq!(request.retry_config.max_retries, 2);
+        assert_eq!(request.retry_config.initial_delay_ms, 100);
+        assert_eq!(request.timeout, Duration::from_millis(DEFAULT_TIMEOUT_MS));
+
+        let request = build_request(Uuid::new_v4(), Uuid::new_v4(), &serde_json::json!({ "url": "http://localhost/x" })).unwrap();
+        assert_eq!(request.method, HttpMethod::GET);
+        assert_eq!(request.retry_config.max_retries, 0);
+        assert!(request.body.is_none());
+
+        assert!(build_request(Uuid::new_v4(), Uuid::new_v4(), &serde_json::json!({})).is_err());
+        assert!(build_request(Uuid::new_v4(), Uuid::new_v4(), &serde_json::json!({ "url": "http://x", "method": "TRACE" })).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use common::types::{ActionType, JsonValue};
+use integration_service::sharing::CREDENTIAL_REF_KEY;
+use uuid::Uuid;
+
+/// Parameters of an integration node that select the action rather than
+/// being passed to it
+const REQUEST_PARAMETERS: [&str; 4] = ["integration", "action", "credential", "params"];
+
+/// An integration action to run for a node
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntegrationRequest {
+    pub integration: String,
+    pub action: String,
+    /// Saved credential the action authenticates with
+    pub credential: Option<Uuid>,
+    pub params: JsonValue,
+}
+
+/// Build the integration action of an email, database or integration node
+/// from its rendered parameters.
+///
+/// `integration` names the integration, and defaults to `email` and
+/// `database` for those node types; `action` defaults to `send_email` and
+/// `query`. `credential` references a saved credential, either bound
+/// (`{"$credential": "<id>"}`) or as its ID. The action's parameters are
+/// `params` when given, otherwise the other node parameters.
+pub fn build_request(action_type: &ActionType, parameters: &JsonValue) -> Result<IntegrationRequest, String> {
+    let (default_integration, default_action) = match action_type {
+        ActionType::Email => (Some("email"), Some("send_email")),
+        ActionType::Database => (Some("database"), Some("query")),
+        _ => (None, None),
+    };
+    let integration = string_parameter(parameters, "integration")?
+        .or(default_integration)
+        .ok_or("missing integration parameter")?;
+    let action = string_parameter(parameters, "action")?
+        .or(default_action)
+        .ok_or("missing action parameter")?;
+
+    let credential = match parameters.get("credential") {
+        None | Some(JsonValue::Null) => None,
+        Some(reference) => {
+            let id = reference
+                .as_str()
+                .or_else(|| reference.get(CREDENTIAL_REF_KEY).and_then(JsonValue::as_str))
+                .ok_or_else(|| format!("credential must be a credential ID or a {} reference", CREDENTIAL_REF_KEY))?;
+            Some(id.parse().map_err(|_| format!("invalid credential ID {:?}", id))?)
+        }
+    };
+
+    let params = match parameters.get("params") {
+        Some(params @ JsonValue::Object(_)) => params.clone(),
+        Some(_) => return Err("params must be an object".to_string()),
+        None => JsonValue::Object(
+            parameters
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter(|(key, _)| !REQUEST_PARAMETERS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        ),
+    };
+
+    Ok(IntegrationRequest {
+        integration: integration.to_string(),
+        action: action.to_string(),
+        credential,
+        params,
+    })
+}
+
+fn string_parameter<'a>(parameters: &'a JsonValue, name: &str) -> Result<Option<&'a str>, String> {
+    match parameters.get(name) {
+        None | Some(JsonValue::Null) => Ok(None),
+        Some(JsonValue::String(value)) if !value.is_empty() => Ok(Some(value)),
+        Some(_) => Err(format!("{} must be a non-empty string", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_request() {
+        let credential = Uuid::new_v4();
+        let parameters = serde_json::json!({
+            "to": ["ops@example.com"],
+            "subject": "Report",
+            "credential": { CREDENTIAL_REF_KEY: credential.to_string() }
+        });
+        let request = build_request(&ActionType::Email, &parameters).unwrap();
+        assert_eq!((request.integration.as_str(), request.action.as_str()), ("email", "send_email"));
+        assert_eq!(request.credential, Some(credential));
+        assert_eq!(request.params, serde_json::json!({ "to": ["ops@example.com"], "subject": "Report" }));
+
+        let parameters = serde_json::json!({
+            "integration": "github",
+            "action": "create_issue",
+            "credential": credential.to_string(),
+            "params": { "title": "Flaky" }
+        });
+        let request = build_request(&ActionType::Integration, &parameters).unwrap();
+        assert_eq!((request.integration.as_str(), request.action.as_str()), ("github", "create_issue"));
+        assert_eq!(request.credential, Some(credential));
+        assert_eq!(request.params, serde_json::json!({ "title": "Flaky" }));
+
+        let request = build_request(&ActionType::Database, &serde_json::json!({ "query": "SELECT 1" })).unwrap();
+        assert_eq!((request.integration.as_str(), request.action.as_str(), request.credential), ("database", "query", None));
+
+        assert!(build_request(&ActionType::Integration, &serde_json::json!({ "action": "create_issue" })).is_err());
+        assert!(build_request(&ActionType::Integration, &serde_json::json!({ "integration": "github" })).is_err());
+        assert!(build_request(&ActionType::Email, &serde_json::json!({ "credential": "vault-1" })).is_err());
+        assert!(build_request(&ActionType::Email, &serde_json::json!({ "credential": 7 })).is_err());
+        assert!(build_request(&ActionType::Email, &serde_json::json!({ "params": [] })).is_err());
+    }
+}
@@ -1,9 +1,63 @@
+pub mod ai;
+pub mod cron;
+pub mod dead_letter;
+pub mod diff;
+pub mod digest;
+pub mod encryption;
+pub mod events;
 pub mod executor;
+pub mod expression;
+pub mod filters;
+pub mod graph;
+pub mod history;
+pub mod http;
+pub mod integration;
+pub mod ownership;
 pub mod parser;
+pub mod portable;
+pub mod quota;
+pub mod revisions;
+pub mod run_queue;
+pub mod sampling;
 pub mod scheduler;
+pub mod schema_drift;
+pub mod scraper;
+pub mod settings;
+pub mod tasks;
+pub mod templates;
+pub mod test_suite;
 pub mod validator;
+pub mod workflows;
+mod yaml;
 
+pub use ai::AiGenerator;
+pub use cron::CronExpression;
+pub use dead_letter::{DeadLetter, DeadLetterQueue, DeadLetterStatus, DeadLetterStore, InMemoryDeadLetterStore, RequeuePolicy};
+pub use diff::{BreakingChange, WorkflowDiff};
+pub use digest::{ChannelSender, Digest, DigestChannel, DigestScope, DigestSender, DigestService, DigestSettings, DigestSubscription, Mailer};
+pub use encryption::{DataKeyStore, EncryptedDeadLetterStore, InMemoryDataKeyStore, PayloadEncryption, WrappedDataKey};
+pub use events::{
+    BroadcastEventBus, ExecutionEvent, ExecutionEventBus, ExecutionEventKind, ExecutionEventStore,
+    InMemoryEventStore, PersistentEventBus, StoredEvent,
+};
 pub use executor::WorkflowExecutor;
+pub use expression::{render_template, Expression};
+pub use filters::FilterRegistry;
+pub use graph::GraphAnalysis;
+pub use history::{ExecutionHistory, ExecutionRecord};
+pub use http::HttpDispatcher;
+pub use ownership::{FailureAlert, Owner, OwnershipStore, ResolvedOwner, WorkflowOwnership};
 pub use parser::WorkflowParser;
-pub use scheduler::WorkflowScheduler;
+pub use portable::{PortableFormat, PortableWorkflow};
+pub use quota::{QuotaManager, OrgQuota, AdmissionPreview};
+pub use revisions::{RevisionStore, ReviewPolicy};
+pub use run_queue::{ConcurrencyLimit, OverflowPolicy, RunQueueMetrics, WorkflowRunQueue};
+pub use sampling::{ExecutionCounters, SamplingPolicy};
+pub use scheduler::{CatchUpPolicy, CronSchedule, LeaderLock, SchedulePersistence, WorkflowScheduler};
+pub use schema_drift::{ActionKey, DriftPolicy, DriftWarning, SchemaDriftDetector};
+pub use settings::{InjectionPolicy, OrgSettings, OrgSettingsStore};
+pub use tasks::TaskSupervisor;
+pub use test_suite::{run_suite, SuiteReport, TestSuiteStore, WorkflowTestCase};
+pub use templates::{TemplateStore, WorkflowTemplate};
 pub use validator::WorkflowValidator;
+pub use workflows::{InMemoryWorkflowStore, StoredWorkflow, WorkflowStore};
@@ -0,0 +1,330 @@
+use chrono::{DateTime, Utc};
+use common::types::ExecutionState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+use uuid::Uuid;
+
+use crate::history::ExecutionRecord;
+use crate::revisions::RevisionStore;
+
+/// Consecutive failures of a node before its owner is alerted
+pub const DEFAULT_ALERT_THRESHOLD: u32 = 3;
+
+/// Alerts kept in the inbox; the oldest are dropped first
+const MAX_ALERTS: usize = 1000;
+
+/// A workflow and its failing node, `None` for failures outside any node
+type StreakKey = (Uuid, Option<Uuid>);
+
+/// Who is responsible for a workflow or node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Owner {
+    User { user_id: Uuid },
+    /// A team; alerts go to its on-call members
+    Team { team: String },
+}
+
+/// Owners annotated on a workflow and its nodes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkflowOwnership {
+    /// Owner of the nodes without one of their own
+    #[serde(default)]
+    pub workflow: Option<Owner>,
+    #[serde(default)]
+    pub nodes: HashMap<Uuid, Owner>,
+}
+
+/// Where a resolved owner was annotated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnerSource {
+    Node,
+    Workflow,
+    /// No annotation; the workflow's creator (owning user)
+    Creator,
+}
+
+/// The owner responsible for a node and the users to notify for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedOwner {
+    pub owner: Owner,
+    pub source: OwnerSource,
+    /// The owning user, or the team's on-call members; empty for a team
+    /// without any
+    pub recipients: Vec<Uuid>,
+}
+
+/// A node failed in several executions in a row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailureAlert {
+    pub id: Uuid,
+    pub workflow_id: Uuid,
+    /// `None` when the execution failed outside of any node
+    pub node_id: Option<Uuid>,
+    /// The latest failed execution
+    pub execution_id: Uuid,
+    pub error: Option<String>,
+    pub consecutive_failures: u32,
+    pub owner: ResolvedOwner,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Node and workflow owners, team on-call rotations, and the alerts routed
+/// to them when a node keeps failing
+pub struct OwnershipStore {
+    annotations: Arc<RwLock<HashMap<Uuid, WorkflowOwnership>>>,
+    /// On-call members per team
+    on_call: Arc<RwLock<HashMap<String, Vec<Uuid>>>>,
+    /// Consecutive failed executions per workflow and failing node
+    streaks: Arc<RwLock<HashMap<StreakKey, u32>>>,
+    alerts: Arc<RwLock<Vec<FailureAlert>>>,
+    alert_tx: broadcast::Sender<FailureAlert>,
+    /// Creators of unannotated workflows
+    revisions: Option<Arc<RevisionStore>>,
+    alert_threshold: u32,
+}
+
+impl OwnershipStore {
+    pub fn new() -> Self {
+        let (alert_tx, _) = broadcast::channel(100);
+        Self {
+            annotations: Arc::new(RwLock::new(HashMap::new())),
+            on_call: Arc::new(RwLock::new(HashMap::new())),
+            streaks: Arc::new(RwLock::new(HashMap::new())),
+            alerts: Arc::new(RwLock::new(Vec::new())),
+            alert_tx,
+            revisions: None,
+            alert_threshold: DEFAULT_ALERT_THRESHOLD,
+        }
+    }
+
+    /// Route failures of unannotated workflows to their owning user
+    pub fn with_revisions(mut self, revisions: Arc<RevisionStore>) -> Self {
+        self.revisions = Some(revisions);
+        self
+    }
+
+    /// Alert after this many consecutive failures of a node (at least 1)
+    pub fn with_alert_threshold(mut self, threshold: u32) -> Self {
+        self.alert_threshold = threshold.max(1);
+        self
+    }
+
+    pub async fn set(&self, workflow_id: Uuid, ownership: WorkflowOwnership) {
+        self.annotations.write().await.insert(workflow_id, ownership);
+    }
+
+    pub async fn get(&self, workflow_id: Uuid) -> WorkflowOwnership {
+        self.annotations.read().await.get(&workflow_id).cloned().unwrap_or_default()
+    }
+
+    /// Replace a team's on-call members
+    pub async fn set_on_call(&self, team: &str, members: Vec<Uuid>) {
+        self.on_call.write().await.insert(team.to_string(), members);
+    }
+
+    pub async fn on_call(&self, team: &str) -> Vec<Uuid> {
+        self.on_call.read().await.get(team).cloned().unwrap_or_default()
+    }
+
+    /// Forget the annotations and failure streaks of a deleted workflow
+    pub async fn remove_workflow(&self, workflow_id: Uuid) {
+        self.annotations.write().await.remove(&workflow_id);
+        self.streaks.write().await.retain(|(workflow, _), _| *workflow != workflow_id);
+    }
+
+    /// The owner of a node (or of the workflow when `node_id` is `None`):
+    /// the node's annotation, else the workflow's, else the workflow's creator
+    pub async fn resolve(&self, workflow_id: Uuid, node_id: Option<Uuid>) -> Option<ResolvedOwner> {
+        let annotated = {
+            let annotations = self.annotations.read().await;
+            annotations.get(&workflow_id).and_then(|ownership| {
+                node_id.and_then(|id| ownership.nodes.get(&id))
+                    .map(|owner| (owner.clone(), OwnerSource::Node))
+                    .or_else(|| ownership.workflow.clone().map(|owner| (owner, OwnerSource::Workflow)))
+            })
+        };
+        let (owner, source) = match annotated {
+            Some(annotated) => annotated,
+            None => {
+                let creator = self.revisions.as_ref()?.owner(workflow_id).await?;
+                (Owner::User { user_id: creator }, OwnerSource::Creator)
+            }
+        };
+        let recipients = match &owner {
+            Owner::User { user_id } => vec![*user_id],
+            Owner::Team { team } => self.on_call(team).await,
+        };
+        Some(ResolvedOwner { owner, source, recipients })
+    }
+
+    /// Track a finished execution. A failure extends the streak of the node
+    /// that failed; once it reaches the threshold, the node's owner is alerted
+    /// (once per streak). Nodes that completed start over.
+    pub async fn observe(&self, record: &ExecutionRecord) -> Option<FailureAlert> {
+        let workflow_id = record.workflow_id;
+        let failed_node = record.nodes.iter().rev()
+            .find(|n| n.state.state == ExecutionState::Failed)
+            .map(|n| n.state.node_id);
+
+        let streak = {
+            let mut streaks = self.streaks.write().await;
+            for node in &record.nodes {
+                if node.state.state == ExecutionState::Completed {
+                    streaks.remove(&(workflow_id, Some(node.state.node_id)));
+                }
+            }
+            match record.result.state {
+                ExecutionState::Failed => {
+                    let streak = streaks.entry((workflow_id, failed_node)).or_insert(0);
+                    *streak += 1;
+                    *streak
+                }
+                ExecutionState::Completed => {
+                    streaks.remove(&(workflow_id, None));
+                    return None;
+                }
+                _ => return None,
+            }
+        };
+        if streak != self.alert_threshold {
+            return None;
+        }
+
+        let owner = self.resolve(workflow_id, failed_node).await?;
+        let alert = FailureAlert {
+            id: Uuid::new_v4(),
+            workflow_id,
+            node_id: failed_node,
+            execution_id: record.result.execution_id,
+            error: record.result.error.clone(),
+            consecutive_failures: streak,
+            owner,
+            created_at: Utc::now(),
+        };
+        // Nobody listening is fine; the inbox keeps the alert
+        let _ = self.alert_tx.send(alert.clone());
+        let mut alerts = self.alerts.write().await;
+        alerts.push(alert.clone());
+        if alerts.len() > MAX_ALERTS {
+            let excess = alerts.len() - MAX_ALERTS;
+            alerts.drain(..excess);
+        }
+        Some(alert)
+    }
+
+    /// Alerts routed to the user, newest first
+    pub async fn alerts_for(&self, user_id: Uuid) -> Vec<FailureAlert> {
+        let alerts = self.alerts.read().await;
+        alerts.iter().rev()
+            .filter(|alert| alert.owner.recipients.contains(&user_id))
+            .cloned()
+            .collect()
+    }
+
+    /// Receive failure alerts as they are raised (e.g. to page the on-call member)
+    pub fn subscribe(&self) -> broadcast::Receiver<FailureAlert> {
+        self.alert_tx.subscribe()
+    }
+}
+
+impl Default for OwnershipStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::NodeRecord;
+    use common::types::{ExecutionResult, NodeExecutionState};
+
+    fn record(workflow_id: Uuid, nodes: &[(Uuid, ExecutionState)]) -> ExecutionRecord {
+        let failed = nodes.iter().any(|(_, state)| *state == ExecutionState::Failed);
+        ExecutionRecord {
+            workflow_id,
+            organization_id: None,
+            started_at: Utc::now(),
+            result: ExecutionResult {
+                execution_id: Uuid::new_v4(),
+                state: if failed { ExecutionState::Failed } else { ExecutionState::Completed },
+                completed_at: Some(Utc::now()),
+                error: failed.then(|| "upstream returned 500".to_string()),
+                output: None,
+                usage: None,
+            },
+            nodes: nodes.iter()
+                .map(|(node_id, state)| NodeRecord {
+                    state: NodeExecutionState {
+                        node_id: *node_id,
+                        state: state.clone(),
+                        started_at: None,
+                        completed_at: None,
+                        input: None,
+                        output: None,
+                        error: None,
+                    },
+                    duration_ms: 0,
+                    provider: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_owner() {
+        let revisions = Arc::new(RevisionStore::new());
+        let store = OwnershipStore::new().with_revisions(revisions.clone());
+        let (workflow_id, fetch, notify) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        assert!(store.resolve(workflow_id, Some(fetch)).await.is_none());
+
+        let (lead, on_call) = (Uuid::new_v4(), Uuid::new_v4());
+        store.set_on_call("payments", vec![on_call]).await;
+        store.set(workflow_id, WorkflowOwnership {
+            workflow: Some(Owner::User { user_id: lead }),
+            nodes: HashMap::from([(fetch, Owner::Team { team: "payments".to_string() })]),
+        }).await;
+
+        let owner = store.resolve(workflow_id, Some(fetch)).await.unwrap();
+        assert_eq!((owner.source, owner.recipients), (OwnerSource::Node, vec![on_call]));
+        let owner = store.resolve(workflow_id, Some(notify)).await.unwrap();
+        assert_eq!((owner.source, owner.recipients), (OwnerSource::Workflow, vec![lead]));
+        let json = serde_json::to_value(&owner.owner).unwrap();
+        assert_eq!(json, serde_json::json!({ "type": "user", "user_id": lead }));
+    }
+
+    #[tokio::test]
+    async fn test_repeated_failures_alert_the_owner() {
+        let store = OwnershipStore::new().with_alert_threshold(2);
+        let mut alerts = store.subscribe();
+        let (workflow_id, trigger, fetch) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let on_call = Uuid::new_v4();
+        store.set_on_call("payments", vec![on_call]).await;
+        store.set(workflow_id, WorkflowOwnership {
+            workflow: None,
+            nodes: HashMap::from([(fetch, Owner::Team { team: "payments".to_string() })]),
+        }).await;
+
+        let failing = [(trigger, ExecutionState::Completed), (fetch, ExecutionState::Failed)];
+        assert!(store.observe(&record(workflow_id, &failing)).await.is_none());
+        // A success resets the streak
+        store.observe(&record(workflow_id, &[(trigger, ExecutionState::Completed), (fetch, ExecutionState::Completed)])).await;
+        assert!(store.observe(&record(workflow_id, &failing)).await.is_none());
+
+        let failed = record(workflow_id, &failing);
+        let alert = store.observe(&failed).await.unwrap();
+        assert_eq!((alert.node_id, alert.execution_id), (Some(fetch), failed.result.execution_id));
+        assert_eq!(alert.consecutive_failures, 2);
+        assert_eq!(alert.owner.owner, Owner::Team { team: "payments".to_string() });
+        assert_eq!(alerts.try_recv().unwrap().id, alert.id);
+        // Alerted once per streak
+        assert!(store.observe(&record(workflow_id, &failing)).await.is_none());
+
+        assert_eq!(store.alerts_for(on_call).await.len(), 1);
+        assert!(store.alerts_for(Uuid::new_v4()).await.is_empty());
+    }
+}
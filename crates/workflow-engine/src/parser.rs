@@ -1,8 +1,18 @@
-use common::types::Workflow;
+use chrono::Utc;
+use common::types::{JsonValue, Workflow, WORKFLOW_SCHEMA_VERSION};
 use common::ParseError;
+use integration_service::sharing::{PublicBundle, BUNDLE_VERSION};
 use std::collections::{HashMap, HashSet, VecDeque};
 use uuid::Uuid;
 
+use crate::portable::{self, PortableFormat};
+
+/// Upgrades a raw definition by one schema version
+type Migration = fn(JsonValue) -> Result<JsonValue, ParseError>;
+
+/// Migration steps; `MIGRATIONS[n - 1]` upgrades a version `n` definition to `n + 1`
+const MIGRATIONS: [Migration; WORKFLOW_SCHEMA_VERSION as usize - 1] = [migrate_v1_to_v2];
+
 /// Workflow parser implementation
 /// Responsible for parsing workflow definitions, analyzing node dependencies, and detecting cycles
 pub struct WorkflowParser;
@@ -12,10 +22,43 @@ impl WorkflowParser {
         Self
     }
 
-    /// Parse a workflow definition from JSON string
+    /// Parse a workflow definition from JSON string, migrating older schema versions
     pub fn parse(&self, definition: &str) -> Result<Workflow, ParseError> {
-        // Parse JSON into Workflow struct
-        let workflow: Workflow = serde_json::from_str(definition)
+        self.parse_versioned(definition).map(|(workflow, _)| workflow)
+    }
+
+    /// Parse a workflow definition of any supported schema version.
+    ///
+    /// Definitions without a `version` field are treated as version 1. They are
+    /// migrated step by step to [`WORKFLOW_SCHEMA_VERSION`] before validation;
+    /// the version the definition was written in is returned alongside it.
+    pub fn parse_versioned(&self, definition: &str) -> Result<(Workflow, u32), ParseError> {
+        let mut raw: JsonValue = serde_json::from_str(definition)
+            .map_err(|e| ParseError::InvalidJson(e.to_string()))?;
+        if !raw.is_object() {
+            return Err(ParseError::InvalidJson("workflow definition must be an object".to_string()));
+        }
+
+        let source_version = match raw.get("version") {
+            None | Some(JsonValue::Null) => 1,
+            Some(version) => version
+                .as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| ParseError::InvalidVersion(version.to_string()))?,
+        };
+        if source_version == 0 || source_version > WORKFLOW_SCHEMA_VERSION {
+            return Err(ParseError::UnsupportedVersion {
+                found: source_version,
+                supported: WORKFLOW_SCHEMA_VERSION,
+            });
+        }
+
+        for migration in &MIGRATIONS[source_version as usize - 1..] {
+            raw = migration(raw)?;
+        }
+        raw["version"] = WORKFLOW_SCHEMA_VERSION.into();
+
+        let workflow: Workflow = serde_json::from_value(raw)
             .map_err(|e| ParseError::InvalidJson(e.to_string()))?;
 
         // Validate basic structure
@@ -27,7 +70,30 @@ impl WorkflowParser {
         // Detect cycles
         self.detect_cycles(&workflow)?;
 
-        Ok(workflow)
+        Ok((workflow, source_version))
+    }
+
+    /// Serialize a workflow as a [`PortableWorkflow`](crate::portable::PortableWorkflow)
+    /// bundle for sharing with other flowvex instances
+    pub fn serialize_portable(&self, workflow: &Workflow, format: PortableFormat) -> Result<String, ParseError> {
+        portable::serialize(workflow, format)
+    }
+
+    /// Import a JSON or YAML portable bundle.
+    ///
+    /// The workflow gets new workflow, node and edge IDs (references to the
+    /// old node IDs are rewritten), is migrated and validated like any
+    /// definition, and comes back as a public bundle whose credential
+    /// placeholders still have to be bound.
+    pub fn import_portable(&self, bundle: &str) -> Result<PublicBundle, ParseError> {
+        let (raw, placeholders) = portable::unpack(bundle)?;
+        let (workflow, _) = self.parse_versioned(&raw.to_string())?;
+        Ok(PublicBundle {
+            version: BUNDLE_VERSION,
+            exported_at: Utc::now(),
+            workflow,
+            placeholders,
+        })
     }
 
     /// Validate basic workflow structure
@@ -66,7 +132,7 @@ impl WorkflowParser {
         for edge in &workflow.edges {
             dependency_graph
                 .entry(edge.target)
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(edge.source);
         }
 
@@ -100,7 +166,7 @@ impl WorkflowParser {
         for edge in &workflow.edges {
             adjacency_list
                 .entry(edge.source)
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(edge.target);
         }
 
@@ -109,15 +175,15 @@ impl WorkflowParser {
 
         // Run DFS from each node
         for node in &workflow.nodes {
-            if !visited.contains(&node.id) {
-                if self.has_cycle_dfs(
+            if !visited.contains(&node.id)
+                && self.has_cycle_dfs(
                     node.id,
                     &adjacency_list,
                     &mut visited,
                     &mut rec_stack,
-                )? {
-                    return Err(ParseError::CycleDetected(node.id));
-                }
+                )?
+            {
+                return Err(ParseError::CycleDetected(node.id));
             }
         }
 
@@ -167,7 +233,7 @@ impl WorkflowParser {
         for edge in &workflow.edges {
             adjacency_list
                 .entry(edge.source)
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(edge.target);
             *in_degree.get_mut(&edge.target).unwrap() += 1;
         }
@@ -225,6 +291,44 @@ impl WorkflowParser {
     }
 }
 
+/// Version 1 definitions could omit fields that version 2 requires:
+/// node positions, ports and config, edge IDs and handles, variables and timestamps.
+fn migrate_v1_to_v2(mut raw: JsonValue) -> Result<JsonValue, ParseError> {
+    let failed = |reason: &str| ParseError::MigrationFailed { from: 1, reason: reason.to_string() };
+    let now = JsonValue::String(chrono::Utc::now().to_rfc3339());
+    let object = raw.as_object_mut().ok_or_else(|| failed("definition is not an object"))?;
+
+    for key in ["created_at", "updated_at"] {
+        object.entry(key).or_insert_with(|| now.clone());
+    }
+    object.entry("variables").or_insert_with(|| serde_json::json!({}));
+    object.entry("edges").or_insert_with(|| serde_json::json!([]));
+
+    if let Some(nodes) = object.get_mut("nodes") {
+        let nodes = nodes.as_array_mut().ok_or_else(|| failed("nodes is not an array"))?;
+        for node in nodes {
+            let node = node.as_object_mut().ok_or_else(|| failed("node is not an object"))?;
+            node.entry("position").or_insert_with(|| serde_json::json!({ "x": 0.0, "y": 0.0 }));
+            node.entry("inputs").or_insert_with(|| serde_json::json!([]));
+            node.entry("outputs").or_insert_with(|| serde_json::json!([]));
+            let config = node.entry("config").or_insert_with(|| serde_json::json!({}));
+            if let Some(config) = config.as_object_mut() {
+                config.entry("parameters").or_insert_with(|| serde_json::json!({}));
+            }
+        }
+    }
+
+    let edges = object["edges"].as_array_mut().ok_or_else(|| failed("edges is not an array"))?;
+    for edge in edges {
+        let edge = edge.as_object_mut().ok_or_else(|| failed("edge is not an object"))?;
+        edge.entry("id").or_insert_with(|| Uuid::new_v4().to_string().into());
+        edge.entry("source_handle").or_insert_with(|| "output".into());
+        edge.entry("target_handle").or_insert_with(|| "input".into());
+    }
+
+    Ok(raw)
+}
+
 impl Default for WorkflowParser {
     fn default() -> Self {
         Self::new()
@@ -234,24 +338,27 @@ impl Default for WorkflowParser {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use common::types::{NodeType, TriggerType, Position, Port, NodeConfig};
+    use common::types::{Node, Edge, NodeType, TriggerType, Position, NodeConfig};
 
     fn create_test_workflow(nodes: Vec<Node>, edges: Vec<Edge>) -> Workflow {
         Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
             id: Uuid::new_v4(),
             name: "Test Workflow".to_string(),
+            description: None,
             nodes,
             edges,
             variables: HashMap::new(),
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
+            trash: Default::default(),
         }
     }
 
     fn create_test_node(id: Uuid) -> Node {
         Node {
             id,
-            node_type: NodeType::Trigger(TriggerType::Manual),
+            node_type: NodeType::Trigger { trigger_type: TriggerType::Manual },
             config: NodeConfig::default(),
             position: Position { x: 0.0, y: 0.0 },
             inputs: vec![],
@@ -269,8 +376,8 @@ mod tests {
             id: Uuid::new_v4(),
             source: node1.id,
             target: node2.id,
-            source_handle: None,
-            target_handle: None,
+            source_handle: String::new(),
+            target_handle: String::new(),
         };
 
         let workflow = create_test_workflow(vec![node1, node2], vec![edge]);
@@ -291,15 +398,15 @@ mod tests {
             id: Uuid::new_v4(),
             source: node1.id,
             target: node2.id,
-            source_handle: None,
-            target_handle: None,
+            source_handle: String::new(),
+            target_handle: String::new(),
         };
         let edge2 = Edge {
             id: Uuid::new_v4(),
             source: node2.id,
             target: node1.id,
-            source_handle: None,
-            target_handle: None,
+            source_handle: String::new(),
+            target_handle: String::new(),
         };
 
         let workflow = create_test_workflow(vec![node1, node2], vec![edge1, edge2]);
@@ -320,15 +427,15 @@ mod tests {
             id: Uuid::new_v4(),
             source: node1.id,
             target: node2.id,
-            source_handle: None,
-            target_handle: None,
+            source_handle: String::new(),
+            target_handle: String::new(),
         };
         let edge2 = Edge {
             id: Uuid::new_v4(),
             source: node2.id,
             target: node3.id,
-            source_handle: None,
-            target_handle: None,
+            source_handle: String::new(),
+            target_handle: String::new(),
         };
 
         let workflow = create_test_workflow(
@@ -345,4 +452,100 @@ mod tests {
         assert_eq!(sorted[1], node2.id);
         assert_eq!(sorted[2], node3.id);
     }
+
+    #[test]
+    fn test_parse_versioned_migrates_v1() {
+        let parser = WorkflowParser::new();
+        let (trigger, action) = (Uuid::new_v4(), Uuid::new_v4());
+        let v1 = serde_json::json!({
+            "id": Uuid::new_v4(),
+            "name": "Legacy",
+            "description": null,
+            "nodes": [
+                { "id": trigger, "node_type": { "type": "Trigger", "trigger_type": "Manual" } },
+                { "id": action, "node_type": { "type": "Trigger", "trigger_type": "Manual" }, "config": {} }
+            ],
+            "edges": [{ "source": trigger, "target": action }]
+        });
+
+        let (workflow, source_version) = parser.parse_versioned(&v1.to_string()).unwrap();
+        assert_eq!(source_version, 1);
+        assert_eq!(workflow.version, WORKFLOW_SCHEMA_VERSION);
+        assert_eq!(workflow.edges[0].source_handle, "output");
+        assert!(workflow.nodes[1].config.parameters.is_empty());
+
+        // Current definitions round-trip unchanged
+        let current = serde_json::to_string(&workflow).unwrap();
+        let (reparsed, source_version) = parser.parse_versioned(&current).unwrap();
+        assert_eq!(source_version, WORKFLOW_SCHEMA_VERSION);
+        assert_eq!(reparsed.edges[0].id, workflow.edges[0].id);
+    }
+
+    #[test]
+    fn test_parse_versioned_rejects_unsupported_versions() {
+        let parser = WorkflowParser::new();
+        let mut definition = serde_json::to_value(create_test_workflow(vec![create_test_node(Uuid::new_v4())], vec![])).unwrap();
+
+        for version in [0, WORKFLOW_SCHEMA_VERSION + 1] {
+            definition["version"] = version.into();
+            assert!(matches!(
+                parser.parse_versioned(&definition.to_string()),
+                Err(ParseError::UnsupportedVersion { found, .. }) if found == version
+            ));
+        }
+
+        definition["version"] = "2".into();
+        assert!(matches!(parser.parse_versioned(&definition.to_string()), Err(ParseError::InvalidVersion(_))));
+    }
+
+    #[test]
+    fn test_portable_round_trip() {
+        use common::types::ActionType;
+        use integration_service::sharing::{bind_credentials, PLACEHOLDER_KEY};
+
+        let parser = WorkflowParser::new();
+        let trigger = create_test_node(Uuid::new_v4());
+        let mut action = create_test_node(Uuid::new_v4());
+        action.node_type = NodeType::Action { action_type: ActionType::Http };
+        action.config.parameters.insert("api_key".to_string(), serde_json::json!("sk-live-123"));
+        action.config.parameters.insert(
+            "body".to_string(),
+            serde_json::json!(format!("{{{{ vars['node_{}'].id }}}}", trigger.id)),
+        );
+        let edge = Edge {
+            id: Uuid::new_v4(),
+            source: trigger.id,
+            target: action.id,
+            source_handle: "output".to_string(),
+            target_handle: "input".to_string(),
+        };
+        let workflow = create_test_workflow(vec![trigger.clone(), action.clone()], vec![edge.clone()]);
+
+        for format in [PortableFormat::Json, PortableFormat::Yaml] {
+            let exported = parser.serialize_portable(&workflow, format).unwrap();
+            assert!(!exported.contains("sk-live-123"));
+            assert!(!exported.contains(&workflow.id.to_string()) && !exported.contains(&edge.id.to_string()));
+
+            let bundle = parser.import_portable(&exported).unwrap();
+            let imported = &bundle.workflow;
+            assert_eq!(imported.name, "Test Workflow");
+            assert_ne!(imported.id, workflow.id);
+            let (new_trigger, new_action) = (&imported.nodes[0], &imported.nodes[1]);
+            assert_ne!(new_trigger.id, trigger.id);
+            assert_eq!((imported.edges[0].source, imported.edges[0].target), (new_trigger.id, new_action.id));
+            assert_eq!(
+                new_action.config.parameters["body"],
+                format!("{{{{ vars['node_{}'].id }}}}", new_trigger.id)
+            );
+
+            assert_eq!(bundle.placeholders.len(), 1);
+            assert_eq!(bundle.placeholders[0].node_id, new_action.id);
+            assert!(new_action.config.parameters["api_key"].get(PLACEHOLDER_KEY).is_some());
+            let bindings = HashMap::from([(bundle.placeholders[0].id.clone(), "vault-1".to_string())]);
+            assert!(bind_credentials(&bundle, &bindings).is_ok());
+        }
+
+        assert!(matches!(parser.import_portable(r#"{"format": "other"}"#), Err(ParseError::InvalidBundle(_))));
+        assert!(matches!(parser.import_portable("format: [oops"), Err(ParseError::InvalidYaml(_))));
+    }
 }
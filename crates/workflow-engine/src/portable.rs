@@ -0,0 +1,181 @@
+use chrono::{DateTime, Utc};
+use common::types::{JsonValue, Node, Workflow, WORKFLOW_SCHEMA_VERSION};
+use common::ParseError;
+use integration_service::sharing::{export_public, CredentialPlaceholder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::yaml;
+
+/// Value of the `format` field identifying a portable bundle
+pub const PORTABLE_FORMAT: &str = "flowvex-workflow";
+
+/// Current portable bundle version
+pub const PORTABLE_VERSION: u32 = 1;
+
+/// Text format of a portable bundle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortableFormat {
+    #[default]
+    Json,
+    Yaml,
+}
+
+/// Self-contained workflow definition that any flowvex instance can import.
+///
+/// The workflow ID, edge IDs, timestamps and trash are left out and
+/// credentials are replaced by named placeholders. Node IDs remain only to
+/// connect edges and expression references inside the bundle; importing
+/// replaces them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortableWorkflow {
+    pub format: String,
+    pub version: u32,
+    /// Workflow schema version of the nodes
+    pub schema_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub variables: HashMap<String, JsonValue>,
+    pub nodes: Vec<Node>,
+    #[serde(default)]
+    pub edges: Vec<PortableEdge>,
+    /// Credentials removed on export, to be bound on import
+    #[serde(default)]
+    pub credentials: Vec<CredentialPlaceholder>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortableEdge {
+    pub source: Uuid,
+    pub source_handle: String,
+    pub target: Uuid,
+    pub target_handle: String,
+}
+
+impl PortableWorkflow {
+    /// Strip a workflow down to its portable form
+    pub fn from_workflow(workflow: &Workflow) -> Self {
+        let sanitized = export_public(workflow);
+        Self {
+            format: PORTABLE_FORMAT.to_string(),
+            version: PORTABLE_VERSION,
+            schema_version: WORKFLOW_SCHEMA_VERSION,
+            exported_at: sanitized.exported_at,
+            name: sanitized.workflow.name,
+            description: sanitized.workflow.description,
+            variables: sanitized.workflow.variables,
+            nodes: sanitized.workflow.nodes,
+            edges: sanitized.workflow.edges.into_iter()
+                .map(|edge| PortableEdge {
+                    source: edge.source,
+                    source_handle: edge.source_handle,
+                    target: edge.target,
+                    target_handle: edge.target_handle,
+                })
+                .collect(),
+            credentials: sanitized.placeholders,
+        }
+    }
+}
+
+pub(crate) fn serialize(workflow: &Workflow, format: PortableFormat) -> Result<String, ParseError> {
+    let bundle = PortableWorkflow::from_workflow(workflow);
+    match format {
+        PortableFormat::Json => serde_json::to_string_pretty(&bundle).map_err(|e| ParseError::InvalidJson(e.to_string())),
+        PortableFormat::Yaml => serde_json::to_value(&bundle)
+            .map(|value| yaml::to_yaml(&value))
+            .map_err(|e| ParseError::InvalidJson(e.to_string())),
+    }
+}
+
+/// Read a JSON or YAML bundle into a raw workflow definition with fresh IDs,
+/// ready for [`WorkflowParser::parse_versioned`](crate::WorkflowParser::parse_versioned),
+/// and the credential placeholders pointing at the new node IDs
+pub(crate) fn unpack(text: &str) -> Result<(JsonValue, Vec<CredentialPlaceholder>), ParseError> {
+    let bundle = if text.trim_start().starts_with('{') {
+        serde_json::from_str(text).map_err(|e| ParseError::InvalidJson(e.to_string()))?
+    } else {
+        yaml::from_yaml(text).map_err(ParseError::InvalidYaml)?
+    };
+    if bundle.get("format").and_then(JsonValue::as_str) != Some(PORTABLE_FORMAT) {
+        return Err(ParseError::InvalidBundle(format!("format must be '{}'", PORTABLE_FORMAT)));
+    }
+    match bundle.get("version").and_then(JsonValue::as_u64) {
+        Some(version) if version <= PORTABLE_VERSION as u64 => {}
+        _ => return Err(ParseError::InvalidBundle(format!("unsupported bundle version {}", bundle["version"]))),
+    }
+    let field = |name: &str| bundle.get(name).cloned().unwrap_or(JsonValue::Null);
+
+    let nodes = field("nodes");
+    let Some(node_list) = nodes.as_array() else {
+        return Err(ParseError::InvalidBundle("nodes must be a list".to_string()));
+    };
+    let mut new_ids = HashMap::new();
+    for node in node_list {
+        let id = node.get("id").and_then(JsonValue::as_str)
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .ok_or_else(|| ParseError::InvalidBundle("every node needs a UUID id".to_string()))?;
+        new_ids.insert(id, Uuid::new_v4());
+    }
+
+    let mut edges = field("edges");
+    if let Some(edges) = edges.as_array_mut() {
+        for edge in edges.iter_mut().filter_map(JsonValue::as_object_mut) {
+            edge.insert("id".to_string(), Uuid::new_v4().to_string().into());
+        }
+    }
+    let now = JsonValue::String(Utc::now().to_rfc3339());
+    let mut raw = serde_json::json!({
+        "version": field("schema_version"),
+        "id": Uuid::new_v4(),
+        "name": field("name"),
+        "description": field("description"),
+        "variables": bundle.get("variables").cloned().unwrap_or_else(|| serde_json::json!({})),
+        "nodes": nodes,
+        "edges": edges,
+        "created_at": now,
+        "updated_at": now,
+    });
+    if raw["version"].is_null() {
+        raw["version"] = WORKFLOW_SCHEMA_VERSION.into();
+    }
+    let replacements: Vec<(String, String)> = new_ids.iter()
+        .map(|(old, new)| (old.to_string(), new.to_string()))
+        .collect();
+    remap_ids(&mut raw, &replacements);
+
+    let credentials = bundle.get("credentials").cloned().unwrap_or_else(|| serde_json::json!([]));
+    let mut credentials: Vec<CredentialPlaceholder> = serde_json::from_value(credentials)
+        .map_err(|e| ParseError::InvalidBundle(format!("credentials: {}", e)))?;
+    for credential in &mut credentials {
+        credential.node_id = *new_ids.get(&credential.node_id).ok_or_else(|| {
+            ParseError::InvalidBundle(format!("credential {} refers to an unknown node", credential.id))
+        })?;
+    }
+    Ok((raw, credentials))
+}
+
+/// Replace old node IDs wherever they appear: node and edge fields, and
+/// inside strings and keys such as `vars['node_<id>']` references
+fn remap_ids(value: &mut JsonValue, replacements: &[(String, String)]) {
+    let replace = |text: &str| {
+        replacements.iter().fold(text.to_string(), |text, (old, new)| text.replace(old.as_str(), new))
+    };
+    match value {
+        JsonValue::String(text) => *text = replace(text),
+        JsonValue::Array(items) => items.iter_mut().for_each(|item| remap_ids(item, replacements)),
+        JsonValue::Object(map) => {
+            let entries = std::mem::take(map);
+            for (key, mut child) in entries {
+                remap_ids(&mut child, replacements);
+                map.insert(replace(&key), child);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,372 @@
+use chrono::{DateTime, Datelike, Utc};
+use common::error::WorkflowError;
+use common::types::{ActionType, NodeType, ResourceUsage, Workflow};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Token estimate for AI nodes that don't configure `max_tokens`
+const DEFAULT_AI_TOKENS: u64 = 1000;
+
+/// What to do with an execution that would exceed a quota
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum OverQuotaAction {
+    #[default]
+    Reject,
+    Queue,
+}
+
+/// Organization quotas; `None` means unlimited
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrgQuota {
+    pub monthly_ai_tokens: Option<u64>,
+    pub monthly_scraper_pages: Option<u64>,
+    pub storage_bytes: Option<u64>,
+    #[serde(default)]
+    pub on_exceed: OverQuotaAction,
+}
+
+/// Usage counted against an organization's quotas
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrgUsage {
+    /// First day of the month the monthly counters belong to
+    pub period_start: Option<DateTime<Utc>>,
+    pub ai_tokens: u64,
+    pub scraper_pages: u64,
+    /// Storage is cumulative, not reset monthly
+    pub storage_bytes: u64,
+}
+
+/// A quota that the execution would push over its limit
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuotaViolation {
+    pub quota: String,
+    pub limit: u64,
+    pub used: u64,
+    pub requested: u64,
+}
+
+impl std::fmt::Display for QuotaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} used + {} requested exceeds limit of {}",
+            self.quota, self.used, self.requested, self.limit
+        )
+    }
+}
+
+/// Result of an admission check
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum AdmissionDecision {
+    Admitted,
+    /// Admitted despite violations because an admin overrode the quota
+    Overridden,
+    Queued,
+    Rejected,
+}
+
+/// Admission preview for an execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdmissionPreview {
+    pub organization_id: Uuid,
+    pub estimate: ResourceUsage,
+    pub usage: OrgUsage,
+    pub quota: OrgQuota,
+    pub violations: Vec<QuotaViolation>,
+    pub decision: AdmissionDecision,
+}
+
+/// Execution waiting for quota to become available
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedExecution {
+    pub workflow_id: Uuid,
+    pub estimate: ResourceUsage,
+    pub queued_at: DateTime<Utc>,
+}
+
+/// Quota-aware admission control for executions
+pub struct QuotaManager {
+    quotas: Arc<RwLock<HashMap<Uuid, OrgQuota>>>,
+    usage: Arc<RwLock<HashMap<Uuid, OrgUsage>>>,
+    queued: Arc<RwLock<HashMap<Uuid, VecDeque<QueuedExecution>>>>,
+}
+
+impl QuotaManager {
+    pub fn new() -> Self {
+        Self {
+            quotas: Arc::new(RwLock::new(HashMap::new())),
+            usage: Arc::new(RwLock::new(HashMap::new())),
+            queued: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Set the quotas of an organization
+    pub async fn set_quota(&self, organization_id: Uuid, quota: OrgQuota) {
+        let mut quotas = self.quotas.write().await;
+        quotas.insert(organization_id, quota);
+    }
+
+    /// Quotas of an organization (unlimited when never set)
+    pub async fn get_quota(&self, organization_id: Uuid) -> OrgQuota {
+        let quotas = self.quotas.read().await;
+        quotas.get(&organization_id).cloned().unwrap_or_default()
+    }
+
+    /// Current usage of an organization, with monthly counters rolled over if needed
+    pub async fn get_usage(&self, organization_id: Uuid) -> OrgUsage {
+        let mut usage = self.usage.write().await;
+        let org_usage = usage.entry(organization_id).or_default();
+        roll_period(org_usage, Utc::now());
+        org_usage.clone()
+    }
+
+    /// Count resources consumed by a finished execution
+    pub async fn record_usage(&self, organization_id: Uuid, consumed: &ResourceUsage) {
+        let mut usage = self.usage.write().await;
+        let org_usage = usage.entry(organization_id).or_default();
+        roll_period(org_usage, Utc::now());
+        org_usage.ai_tokens += consumed.total_tokens();
+        org_usage.scraper_pages += consumed.scraper_pages;
+        org_usage.storage_bytes += consumed.bytes_stored;
+    }
+
+    /// Rough resource estimate for a workflow run.
+    ///
+    /// Nodes may declare an `estimated_usage` parameter; AI nodes otherwise
+    /// count their `max_tokens` parameter.
+    pub fn estimate(&self, workflow: &Workflow) -> ResourceUsage {
+        let mut estimate = ResourceUsage::default();
+        for node in &workflow.nodes {
+            let params = &node.config.parameters;
+            if let Some(declared) = params.get("estimated_usage")
+                .and_then(|v| serde_json::from_value::<ResourceUsage>(v.clone()).ok())
+            {
+                estimate.add(&declared);
+            } else if matches!(node.node_type, NodeType::AI { .. }) {
+                estimate.completion_tokens += params.get("max_tokens")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(DEFAULT_AI_TOKENS);
+                estimate.provider_calls += 1;
+            } else if matches!(node.node_type, NodeType::Action { action_type: ActionType::Scraper }) {
+                let action = params.get("action");
+                let action_type = action.and_then(|a| a.get("type")).or(action).and_then(|v| v.as_str());
+                if action_type == Some("openPage") {
+                    estimate.scraper_pages += 1;
+                }
+            }
+        }
+        estimate
+    }
+
+    /// Preview the admission decision without reserving anything
+    pub async fn preview(&self, organization_id: Uuid, workflow: &Workflow, admin_override: bool) -> AdmissionPreview {
+        let estimate = self.estimate(workflow);
+        let usage = self.get_usage(organization_id).await;
+        let quota = self.get_quota(organization_id).await;
+        let violations = check_quota(&quota, &usage, &estimate);
+
+        let decision = if violations.is_empty() {
+            AdmissionDecision::Admitted
+        } else if admin_override {
+            AdmissionDecision::Overridden
+        } else {
+            match quota.on_exceed {
+                OverQuotaAction::Reject => AdmissionDecision::Rejected,
+                OverQuotaAction::Queue => AdmissionDecision::Queued,
+            }
+        };
+
+        AdmissionPreview {
+            organization_id,
+            estimate,
+            usage,
+            quota,
+            violations,
+            decision,
+        }
+    }
+
+    /// Check quotas before starting an execution.
+    ///
+    /// Returns the preview when the execution may start. Over-quota executions
+    /// are queued or rejected according to the organization's policy unless an
+    /// admin overrides the check.
+    pub async fn admit(
+        &self,
+        organization_id: Uuid,
+        workflow: &Workflow,
+        admin_override: bool,
+    ) -> Result<AdmissionPreview, WorkflowError> {
+        let preview = self.preview(organization_id, workflow, admin_override).await;
+        let details = preview.violations.iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        match preview.decision {
+            AdmissionDecision::Admitted => Ok(preview),
+            AdmissionDecision::Overridden => {
+                tracing::warn!("Quota overridden for organization {}: {}", organization_id, details);
+                Ok(preview)
+            }
+            AdmissionDecision::Queued => {
+                let mut queued = self.queued.write().await;
+                queued.entry(organization_id).or_default().push_back(QueuedExecution {
+                    workflow_id: workflow.id,
+                    estimate: preview.estimate.clone(),
+                    queued_at: Utc::now(),
+                });
+                Err(WorkflowError::QuotaExceeded(format!("execution queued until quota is available ({})", details)))
+            }
+            AdmissionDecision::Rejected => Err(WorkflowError::QuotaExceeded(details)),
+        }
+    }
+
+    /// Executions waiting on an organization's quota
+    pub async fn queued(&self, organization_id: Uuid) -> Vec<QueuedExecution> {
+        let queued = self.queued.read().await;
+        queued.get(&organization_id).map(|q| q.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    /// Release queued executions that now fit in the quota, in FIFO order
+    pub async fn release_queued(&self, organization_id: Uuid) -> Vec<QueuedExecution> {
+        let usage = self.get_usage(organization_id).await;
+        let quota = self.get_quota(organization_id).await;
+        let mut queued = self.queued.write().await;
+        let Some(queue) = queued.get_mut(&organization_id) else {
+            return Vec::new();
+        };
+
+        let mut projected = usage;
+        let mut released = Vec::new();
+        while let Some(next) = queue.front() {
+            if !check_quota(&quota, &projected, &next.estimate).is_empty() {
+                break;
+            }
+            projected.ai_tokens += next.estimate.total_tokens();
+            projected.scraper_pages += next.estimate.scraper_pages;
+            projected.storage_bytes += next.estimate.bytes_stored;
+            released.extend(queue.pop_front());
+        }
+        released
+    }
+}
+
+impl Default for QuotaManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reset monthly counters when a new month starts
+fn roll_period(usage: &mut OrgUsage, now: DateTime<Utc>) {
+    let period_start = now
+        .with_day(1)
+        .and_then(|d| d.date_naive().and_hms_opt(0, 0, 0))
+        .map(|d| d.and_utc())
+        .unwrap_or(now);
+    if usage.period_start != Some(period_start) {
+        usage.period_start = Some(period_start);
+        usage.ai_tokens = 0;
+        usage.scraper_pages = 0;
+    }
+}
+
+fn check_quota(quota: &OrgQuota, usage: &OrgUsage, estimate: &ResourceUsage) -> Vec<QuotaViolation> {
+    let checks = [
+        ("monthly_ai_tokens", quota.monthly_ai_tokens, usage.ai_tokens, estimate.total_tokens()),
+        ("monthly_scraper_pages", quota.monthly_scraper_pages, usage.scraper_pages, estimate.scraper_pages),
+        ("storage_bytes", quota.storage_bytes, usage.storage_bytes, estimate.bytes_stored),
+    ];
+
+    checks.into_iter()
+        .filter_map(|(name, limit, used, requested)| {
+            let limit = limit?;
+            (used + requested > limit).then(|| QuotaViolation {
+                quota: name.to_string(),
+                limit,
+                used,
+                requested,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::types::{AINodeType, Node, NodeConfig, Position, WORKFLOW_SCHEMA_VERSION};
+
+    fn ai_workflow(max_tokens: u64) -> Workflow {
+        let node = Node {
+            id: Uuid::new_v4(),
+            node_type: NodeType::AI { ai_type: AINodeType::TextGeneration },
+            config: NodeConfig {
+                parameters: HashMap::from([("max_tokens".to_string(), serde_json::json!(max_tokens))]),
+                ..Default::default()
+            },
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![],
+        };
+        Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "AI".to_string(),
+            description: None,
+            nodes: vec![node],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reject_and_admin_override() {
+        let manager = QuotaManager::new();
+        let org = Uuid::new_v4();
+        manager.set_quota(org, OrgQuota {
+            monthly_ai_tokens: Some(5000),
+            ..Default::default()
+        }).await;
+        manager.record_usage(org, &ResourceUsage { completion_tokens: 4500, ..Default::default() }).await;
+
+        assert!(manager.admit(org, &ai_workflow(400), false).await.is_ok());
+
+        let err = manager.admit(org, &ai_workflow(800), false).await.unwrap_err();
+        assert!(matches!(err, WorkflowError::QuotaExceeded(_)));
+        assert!(err.to_string().contains("monthly_ai_tokens"));
+
+        let preview = manager.admit(org, &ai_workflow(800), true).await.unwrap();
+        assert_eq!(preview.decision, AdmissionDecision::Overridden);
+    }
+
+    #[tokio::test]
+    async fn test_queue_and_release() {
+        let manager = QuotaManager::new();
+        let org = Uuid::new_v4();
+        manager.set_quota(org, OrgQuota {
+            monthly_ai_tokens: Some(1000),
+            on_exceed: OverQuotaAction::Queue,
+            ..Default::default()
+        }).await;
+        manager.record_usage(org, &ResourceUsage { prompt_tokens: 900, ..Default::default() }).await;
+
+        assert!(manager.admit(org, &ai_workflow(500), false).await.is_err());
+        assert_eq!(manager.queued(org).await.len(), 1);
+        assert!(manager.release_queued(org).await.is_empty());
+
+        manager.set_quota(org, OrgQuota {
+            monthly_ai_tokens: Some(2000),
+            on_exceed: OverQuotaAction::Queue,
+            ..Default::default()
+        }).await;
+        assert_eq!(manager.release_queued(org).await.len(), 1);
+        assert!(manager.queued(org).await.is_empty());
+    }
+}
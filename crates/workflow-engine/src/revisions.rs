@@ -0,0 +1,494 @@
+use chrono::{DateTime, Utc};
+use common::error::{ErrorCode, ErrorInfo};
+use common::types::Workflow;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+use uuid::Uuid;
+
+use crate::diff::{diff_workflows, WorkflowDiff};
+
+/// Review settings of a workflow
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReviewPolicy {
+    /// Edits to the active (production) revision need approval before they go live
+    pub require_review: bool,
+    /// Users notified of pending revisions; empty means any user allowed to approve
+    #[serde(default)]
+    pub reviewers: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionStatus {
+    /// Used by the scheduler and webhooks
+    Active,
+    /// Waiting for approval
+    Pending,
+    Rejected,
+    /// Replaced by a later active or pending revision
+    Superseded,
+}
+
+/// A saved version of a workflow definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRevision {
+    pub id: Uuid,
+    pub workflow_id: Uuid,
+    /// 1-based, increasing per workflow
+    pub number: u32,
+    pub workflow: Workflow,
+    pub status: RevisionStatus,
+    pub author: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub review_comment: Option<String>,
+}
+
+/// Request for approval of a pending revision
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    pub revision_id: Uuid,
+    pub workflow_id: Uuid,
+    pub number: u32,
+    pub author: Uuid,
+    pub submitted_at: DateTime<Utc>,
+    /// Changes relative to the active revision
+    pub diff: WorkflowDiff,
+}
+
+/// Result of submitting an edited workflow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum SubmitOutcome {
+    /// The revision went live immediately
+    Activated { revision: WorkflowRevision },
+    /// The revision waits for approval; the active revision stays in use
+    PendingReview { request: ApprovalRequest },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewEvent {
+    Requested,
+    Approved,
+    Rejected,
+}
+
+/// Notification about a review, for reviewers (requests) or authors (decisions)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewNotification {
+    pub id: Uuid,
+    /// `None` addresses every user allowed to approve
+    pub recipient: Option<Uuid>,
+    pub event: ReviewEvent,
+    pub workflow_id: Uuid,
+    pub revision_id: Uuid,
+    pub message: String,
+    /// Present on review requests
+    pub request: Option<ApprovalRequest>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReviewError {
+    #[error("Revision not found: {0}")]
+    RevisionNotFound(Uuid),
+
+    #[error("Revision {0} is not pending review")]
+    NotPending(Uuid),
+
+    #[error("Authors cannot review their own revisions")]
+    SelfReview,
+}
+
+impl ErrorInfo for ReviewError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            ReviewError::RevisionNotFound(_) => ErrorCode::NotFound,
+            ReviewError::NotPending(_) => ErrorCode::Conflict,
+            ReviewError::SelfReview => ErrorCode::PermissionDenied,
+        }
+    }
+}
+
+fn latest_of(revisions: &[WorkflowRevision]) -> Option<&Workflow> {
+    revisions.iter()
+        .find(|r| r.status == RevisionStatus::Pending)
+        .or_else(|| revisions.iter().find(|r| r.status == RevisionStatus::Active))
+        .map(|r| &r.workflow)
+}
+
+/// Tracks workflow revisions and the review gate in front of production workflows
+pub struct RevisionStore {
+    revisions: Arc<RwLock<HashMap<Uuid, Vec<WorkflowRevision>>>>,
+    policies: Arc<RwLock<HashMap<Uuid, ReviewPolicy>>>,
+    /// Owner of each workflow, initially the author of its first revision
+    owners: Arc<RwLock<HashMap<Uuid, Uuid>>>,
+    notifications: Arc<RwLock<Vec<ReviewNotification>>>,
+    notify_tx: broadcast::Sender<ReviewNotification>,
+}
+
+impl RevisionStore {
+    pub fn new() -> Self {
+        let (notify_tx, _) = broadcast::channel(100);
+        Self {
+            revisions: Arc::new(RwLock::new(HashMap::new())),
+            policies: Arc::new(RwLock::new(HashMap::new())),
+            owners: Arc::new(RwLock::new(HashMap::new())),
+            notifications: Arc::new(RwLock::new(Vec::new())),
+            notify_tx,
+        }
+    }
+
+    pub async fn set_policy(&self, workflow_id: Uuid, policy: ReviewPolicy) {
+        self.policies.write().await.insert(workflow_id, policy);
+    }
+
+    pub async fn get_policy(&self, workflow_id: Uuid) -> ReviewPolicy {
+        self.policies.read().await.get(&workflow_id).cloned().unwrap_or_default()
+    }
+
+    /// Save an edited workflow.
+    ///
+    /// The first revision, and any revision of a workflow without
+    /// `require_review`, becomes active at once. Otherwise the revision is
+    /// pending (replacing an earlier pending one) and reviewers are notified.
+    pub async fn submit(&self, workflow: Workflow, author: Uuid) -> SubmitOutcome {
+        let policy = self.get_policy(workflow.id).await;
+        let mut all = self.revisions.write().await;
+        let revisions = all.entry(workflow.id).or_default();
+
+        let active = revisions.iter().find(|r| r.status == RevisionStatus::Active).map(|r| r.workflow.clone());
+        let gated = policy.require_review && active.is_some();
+
+        for revision in revisions.iter_mut() {
+            let replaced = if gated { RevisionStatus::Pending } else { RevisionStatus::Active };
+            if revision.status == replaced {
+                revision.status = RevisionStatus::Superseded;
+            }
+        }
+
+        let revision = WorkflowRevision {
+            id: Uuid::new_v4(),
+            workflow_id: workflow.id,
+            number: revisions.len() as u32 + 1,
+            status: if gated { RevisionStatus::Pending } else { RevisionStatus::Active },
+            workflow,
+            author,
+            created_at: Utc::now(),
+            reviewed_by: None,
+            reviewed_at: None,
+            review_comment: None,
+        };
+        revisions.push(revision.clone());
+        drop(all);
+        self.owners.write().await.entry(revision.workflow_id).or_insert(author);
+
+        let Some(active) = active.filter(|_| gated) else {
+            return SubmitOutcome::Activated { revision };
+        };
+
+        let request = ApprovalRequest {
+            revision_id: revision.id,
+            workflow_id: revision.workflow_id,
+            number: revision.number,
+            author,
+            submitted_at: revision.created_at,
+            diff: diff_workflows(&active, &revision.workflow),
+        };
+        let message = format!("Revision {} of \"{}\" is waiting for review", revision.number, revision.workflow.name);
+        let recipients: Vec<Option<Uuid>> = if policy.reviewers.is_empty() {
+            vec![None]
+        } else {
+            policy.reviewers.iter().copied().map(Some).collect()
+        };
+        for recipient in recipients {
+            self.notify(recipient, ReviewEvent::Requested, &revision, message.clone(), Some(request.clone())).await;
+        }
+
+        SubmitOutcome::PendingReview { request }
+    }
+
+    /// Approve a pending revision; it replaces the active revision
+    pub async fn approve(
+        &self,
+        revision_id: Uuid,
+        reviewer: Uuid,
+        comment: Option<String>,
+    ) -> Result<WorkflowRevision, ReviewError> {
+        let revision = self.review(revision_id, reviewer, comment, RevisionStatus::Active).await?;
+        let message = format!("Revision {} of \"{}\" was approved and is now active", revision.number, revision.workflow.name);
+        self.notify(Some(revision.author), ReviewEvent::Approved, &revision, message, None).await;
+        Ok(revision)
+    }
+
+    /// Reject a pending revision; the active revision stays in use
+    pub async fn reject(
+        &self,
+        revision_id: Uuid,
+        reviewer: Uuid,
+        comment: Option<String>,
+    ) -> Result<WorkflowRevision, ReviewError> {
+        let revision = self.review(revision_id, reviewer, comment, RevisionStatus::Rejected).await?;
+        let message = format!("Revision {} of \"{}\" was rejected", revision.number, revision.workflow.name);
+        self.notify(Some(revision.author), ReviewEvent::Rejected, &revision, message, None).await;
+        Ok(revision)
+    }
+
+    async fn review(
+        &self,
+        revision_id: Uuid,
+        reviewer: Uuid,
+        comment: Option<String>,
+        decision: RevisionStatus,
+    ) -> Result<WorkflowRevision, ReviewError> {
+        let mut all = self.revisions.write().await;
+        let revisions = all.values_mut()
+            .find(|revisions| revisions.iter().any(|r| r.id == revision_id))
+            .ok_or(ReviewError::RevisionNotFound(revision_id))?;
+
+        let revision = revisions.iter().find(|r| r.id == revision_id).expect("revision present");
+        if revision.status != RevisionStatus::Pending {
+            return Err(ReviewError::NotPending(revision_id));
+        }
+        if revision.author == reviewer {
+            return Err(ReviewError::SelfReview);
+        }
+
+        if decision == RevisionStatus::Active {
+            for other in revisions.iter_mut().filter(|r| r.status == RevisionStatus::Active) {
+                other.status = RevisionStatus::Superseded;
+            }
+        }
+        let revision = revisions.iter_mut().find(|r| r.id == revision_id).expect("revision present");
+        revision.status = decision;
+        revision.reviewed_by = Some(reviewer);
+        revision.reviewed_at = Some(Utc::now());
+        revision.review_comment = comment;
+        Ok(revision.clone())
+    }
+
+    /// The workflow definition the scheduler and webhooks should run
+    pub async fn active(&self, workflow_id: Uuid) -> Option<Workflow> {
+        let all = self.revisions.read().await;
+        all.get(&workflow_id)?
+            .iter()
+            .find(|r| r.status == RevisionStatus::Active)
+            .map(|r| r.workflow.clone())
+    }
+
+    /// The newest definition of a workflow: the pending revision if there is
+    /// one, otherwise the active one
+    pub async fn latest(&self, workflow_id: Uuid) -> Option<Workflow> {
+        let all = self.revisions.read().await;
+        latest_of(all.get(&workflow_id)?).cloned()
+    }
+
+    /// [`latest`](Self::latest) for several workflows under one lock; unknown
+    /// workflows are left out
+    pub async fn latest_many(&self, workflow_ids: &[Uuid]) -> HashMap<Uuid, Workflow> {
+        let all = self.revisions.read().await;
+        workflow_ids.iter()
+            .filter_map(|id| Some((*id, latest_of(all.get(id)?)?.clone())))
+            .collect()
+    }
+
+    /// All revisions of a workflow, oldest first
+    pub async fn revisions(&self, workflow_id: Uuid) -> Vec<WorkflowRevision> {
+        let all = self.revisions.read().await;
+        all.get(&workflow_id).cloned().unwrap_or_default()
+    }
+
+    pub async fn get_revision(&self, revision_id: Uuid) -> Option<WorkflowRevision> {
+        let all = self.revisions.read().await;
+        all.values().flatten().find(|r| r.id == revision_id).cloned()
+    }
+
+    /// The user owning a workflow
+    pub async fn owner(&self, workflow_id: Uuid) -> Option<Uuid> {
+        self.owners.read().await.get(&workflow_id).copied()
+    }
+
+    /// Workflows owned by the user
+    pub async fn owned_by(&self, user_id: Uuid) -> Vec<Uuid> {
+        let owners = self.owners.read().await;
+        owners.iter().filter(|(_, owner)| **owner == user_id).map(|(workflow_id, _)| *workflow_id).collect()
+    }
+
+    /// Make another user the owner of a workflow; revision authors are kept
+    pub async fn transfer_ownership(&self, workflow_id: Uuid, new_owner: Uuid) -> bool {
+        match self.owners.write().await.get_mut(&workflow_id) {
+            Some(owner) => {
+                *owner = new_owner;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Delete a workflow with all its revisions, its review policy and the
+    /// notifications about it
+    pub async fn delete_workflow(&self, workflow_id: Uuid) -> bool {
+        let removed = self.revisions.write().await.remove(&workflow_id).is_some();
+        self.policies.write().await.remove(&workflow_id);
+        self.owners.write().await.remove(&workflow_id);
+        self.notifications.write().await.retain(|n| n.workflow_id != workflow_id);
+        removed
+    }
+
+    /// Drop a deleted user's notifications and take them off reviewer lists
+    pub async fn forget_user(&self, user_id: Uuid) {
+        self.notifications.write().await.retain(|n| n.recipient != Some(user_id));
+        for policy in self.policies.write().await.values_mut() {
+            policy.reviewers.retain(|reviewer| *reviewer != user_id);
+        }
+    }
+
+    /// Notifications addressed to the user, plus those addressed to every approver when `approver`
+    pub async fn notifications_for(&self, user_id: Uuid, approver: bool) -> Vec<ReviewNotification> {
+        let notifications = self.notifications.read().await;
+        notifications.iter()
+            .filter(|n| n.recipient == Some(user_id) || (approver && n.recipient.is_none()))
+            .cloned()
+            .collect()
+    }
+
+    /// Receive review notifications as they are sent (e.g. to forward them by email)
+    pub fn subscribe(&self) -> broadcast::Receiver<ReviewNotification> {
+        self.notify_tx.subscribe()
+    }
+
+    async fn notify(
+        &self,
+        recipient: Option<Uuid>,
+        event: ReviewEvent,
+        revision: &WorkflowRevision,
+        message: String,
+        request: Option<ApprovalRequest>,
+    ) {
+        let notification = ReviewNotification {
+            id: Uuid::new_v4(),
+            recipient,
+            event,
+            workflow_id: revision.workflow_id,
+            revision_id: revision.id,
+            message,
+            request,
+            created_at: Utc::now(),
+        };
+        // Nobody listening is fine; the inbox keeps the notification
+        let _ = self.notify_tx.send(notification.clone());
+        self.notifications.write().await.push(notification);
+    }
+}
+
+impl Default for RevisionStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::types::{ActionType, Edge, Node, NodeConfig, NodeType, Position, TriggerType, WORKFLOW_SCHEMA_VERSION};
+
+    fn workflow() -> Workflow {
+        let node = |node_type| Node {
+            id: Uuid::new_v4(),
+            node_type,
+            config: NodeConfig::default(),
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![],
+        };
+        let trigger = node(NodeType::Trigger { trigger_type: TriggerType::Webhook });
+        let action = node(NodeType::Action { action_type: ActionType::Http });
+        Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Orders".to_string(),
+            description: None,
+            edges: vec![Edge {
+                id: Uuid::new_v4(),
+                source: trigger.id,
+                source_handle: "output".to_string(),
+                target: action.id,
+                target_handle: "input".to_string(),
+            }],
+            nodes: vec![trigger, action],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_review_gate() {
+        let store = RevisionStore::new();
+        let (author, reviewer) = (Uuid::new_v4(), Uuid::new_v4());
+        let original = workflow();
+        let workflow_id = original.id;
+
+        store.set_policy(workflow_id, ReviewPolicy { require_review: true, reviewers: vec![reviewer] }).await;
+        assert!(matches!(store.submit(original.clone(), author).await, SubmitOutcome::Activated { .. }));
+
+        let mut edited = original.clone();
+        edited.nodes[1].config.parameters.insert("url".to_string(), serde_json::json!("https://example.com"));
+        let SubmitOutcome::PendingReview { request } = store.submit(edited, author).await else {
+            panic!("edit of a production workflow must wait for review");
+        };
+        assert_eq!(request.diff.changed_nodes[0].changes[0].path, "config.parameters.url");
+        assert!(store.active(workflow_id).await.unwrap().nodes[1].config.parameters.is_empty());
+
+        let inbox = store.notifications_for(reviewer, true).await;
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].event, ReviewEvent::Requested);
+
+        assert!(matches!(store.approve(request.revision_id, author, None).await, Err(ReviewError::SelfReview)));
+        store.approve(request.revision_id, reviewer, Some("lgtm".to_string())).await.unwrap();
+        assert_eq!(store.active(workflow_id).await.unwrap().nodes[1].config.parameters["url"], "https://example.com");
+        assert!(matches!(store.reject(request.revision_id, reviewer, None).await, Err(ReviewError::NotPending(_))));
+
+        let statuses: Vec<_> = store.revisions(workflow_id).await.iter().map(|r| r.status).collect();
+        assert_eq!(statuses, [RevisionStatus::Superseded, RevisionStatus::Active]);
+        assert_eq!(store.notifications_for(author, false).await[0].event, ReviewEvent::Approved);
+    }
+
+    #[tokio::test]
+    async fn test_ownership_transfer_and_deletion() {
+        let store = RevisionStore::new();
+        let (owner, editor, heir) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (kept, deleted) = (workflow(), workflow());
+        store.set_policy(deleted.id, ReviewPolicy { require_review: true, reviewers: vec![editor] }).await;
+        store.submit(kept.clone(), owner).await;
+        store.submit(deleted.clone(), owner).await;
+        // Editing someone else's workflow does not make the editor its owner
+        store.submit(deleted.clone(), editor).await;
+
+        let mut owned = store.owned_by(owner).await;
+        owned.sort();
+        let mut expected = vec![kept.id, deleted.id];
+        expected.sort();
+        assert_eq!(owned, expected);
+        assert!(store.owned_by(editor).await.is_empty());
+
+        assert!(store.transfer_ownership(kept.id, heir).await);
+        assert_eq!(store.owner(kept.id).await, Some(heir));
+        assert_eq!(store.revisions(kept.id).await[0].author, owner);
+
+        assert!(store.delete_workflow(deleted.id).await);
+        assert!(store.revisions(deleted.id).await.is_empty());
+        assert_eq!(store.owner(deleted.id).await, None);
+        assert!(store.notifications_for(editor, false).await.is_empty());
+        assert!(!store.transfer_ownership(deleted.id, heir).await);
+
+        store.set_policy(kept.id, ReviewPolicy { require_review: true, reviewers: vec![editor, heir] }).await;
+        store.forget_user(editor).await;
+        assert_eq!(store.get_policy(kept.id).await.reviewers, [heir]);
+    }
+}
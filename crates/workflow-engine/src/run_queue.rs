@@ -0,0 +1,456 @@
+use chrono::Utc;
+use common::error::WorkflowError;
+use common::types::{ExecutionContext, ExecutionResult, ExecutionState, Workflow};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::executor::WorkflowExecutor;
+
+/// Runs allowed to wait for a slot before new ones are rejected
+const DEFAULT_MAX_QUEUED: usize = 1000;
+
+/// What to do with a run of a workflow that is at its concurrency limit
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Wait until a run of the workflow finishes
+    #[default]
+    Queue,
+    /// Drop the new run
+    Skip,
+    /// Make room by cancelling the workflow's oldest run: a running one
+    /// unless all are already being cancelled, else the oldest queued one
+    CancelOldest,
+}
+
+/// Concurrency limit of one workflow
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConcurrencyLimit {
+    pub max_concurrent: usize,
+    #[serde(default)]
+    pub overflow: OverflowPolicy,
+}
+
+impl ConcurrencyLimit {
+    pub fn new(max_concurrent: usize, overflow: OverflowPolicy) -> Self {
+        Self { max_concurrent, overflow }
+    }
+}
+
+/// How a submitted run was admitted
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum RunAdmission {
+    Started,
+    Queued,
+    Skipped,
+}
+
+/// Queue depth and outcome counters
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunQueueMetrics {
+    pub running: usize,
+    pub queued: usize,
+    /// Highest queue depth seen
+    pub peak_queued: usize,
+    pub running_by_workflow: HashMap<Uuid, usize>,
+    pub queued_by_workflow: HashMap<Uuid, usize>,
+    /// Runs dropped by the skip policy
+    pub skipped: u64,
+    /// Runs cancelled, running or queued, by the cancel-oldest policy
+    pub cancelled: u64,
+    /// Runs rejected because the queue was full
+    pub rejected: u64,
+}
+
+/// A submitted run
+pub struct RunTicket {
+    pub execution_id: Uuid,
+    pub admission: RunAdmission,
+    handle: Option<JoinHandle<Result<ExecutionResult, WorkflowError>>>,
+}
+
+impl RunTicket {
+    /// Wait for the run to finish; skipped and cancelled runs finish as `Cancelled`
+    pub async fn finished(self) -> Result<ExecutionResult, WorkflowError> {
+        match self.handle {
+            Some(handle) => handle.await.map_err(|e| {
+                WorkflowError::NodeExecutionFailed(self.execution_id.to_string(), e.to_string())
+            })?,
+            None => Ok(cancelled_result(self.execution_id, "Skipped: workflow is at its concurrency limit")),
+        }
+    }
+}
+
+struct RunningRun {
+    execution_id: Uuid,
+    workflow_id: Uuid,
+    /// Taken once the run is being cancelled
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+struct PendingRun {
+    execution_id: Uuid,
+    workflow_id: Uuid,
+    /// `true` starts the run, `false` drops it
+    grant: oneshot::Sender<bool>,
+    cancel: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    /// In start order
+    running: Vec<RunningRun>,
+    pending: VecDeque<PendingRun>,
+    limits: HashMap<Uuid, ConcurrencyLimit>,
+    peak_queued: usize,
+    skipped: u64,
+    cancelled: u64,
+    rejected: u64,
+}
+
+impl QueueState {
+    fn running_of(&self, workflow_id: Uuid) -> usize {
+        self.running.iter().filter(|run| run.workflow_id == workflow_id).count()
+    }
+
+    /// Cancel the oldest run of a workflow; false when it has none left to cancel
+    fn cancel_oldest(&mut self, workflow_id: Uuid) -> bool {
+        if let Some(run) = self.running.iter_mut()
+            .find(|run| run.workflow_id == workflow_id && run.cancel.is_some())
+        {
+            if let Some(cancel) = run.cancel.take() {
+                let _ = cancel.send(());
+            }
+            tracing::info!("Cancelling execution {} of workflow {} for a newer run", run.execution_id, workflow_id);
+        } else if let Some(index) = self.pending.iter().position(|run| run.workflow_id == workflow_id) {
+            if let Some(run) = self.pending.remove(index) {
+                let _ = run.grant.send(false);
+                tracing::info!("Dropping queued execution {} of workflow {} for a newer run", run.execution_id, workflow_id);
+            }
+        } else {
+            return false;
+        }
+        self.cancelled += 1;
+        true
+    }
+}
+
+/// Bounds concurrent executions globally and per workflow.
+///
+/// Runs over the global limit wait in FIFO order; runs of a workflow at its
+/// own limit follow the workflow's overflow policy. Once `max_queued` runs
+/// are waiting, further runs are rejected with `WorkflowError::QueueFull`
+/// so triggers can push back on their callers.
+#[derive(Clone)]
+pub struct WorkflowRunQueue {
+    executor: Arc<WorkflowExecutor>,
+    max_concurrent: usize,
+    max_queued: usize,
+    /// Limit of workflows without their own; `None` leaves them unlimited
+    default_limit: Option<ConcurrencyLimit>,
+    state: Arc<Mutex<QueueState>>,
+}
+
+impl WorkflowRunQueue {
+    pub fn new(executor: Arc<WorkflowExecutor>, max_concurrent: usize) -> Self {
+        Self {
+            executor,
+            max_concurrent: max_concurrent.max(1),
+            max_queued: DEFAULT_MAX_QUEUED,
+            default_limit: None,
+            state: Arc::new(Mutex::new(QueueState::default())),
+        }
+    }
+
+    pub fn with_max_queued(mut self, max_queued: usize) -> Self {
+        self.max_queued = max_queued;
+        self
+    }
+
+    pub fn with_default_limit(mut self, limit: ConcurrencyLimit) -> Self {
+        self.default_limit = Some(limit);
+        self
+    }
+
+    /// Set the concurrency limit of one workflow
+    pub async fn set_limit(&self, workflow_id: Uuid, limit: ConcurrencyLimit) {
+        let mut state = self.state.lock().await;
+        state.limits.insert(workflow_id, limit);
+        // A raised limit may let queued runs start
+        self.dispatch(&mut state);
+    }
+
+    /// Return a workflow to the default limit
+    pub async fn remove_limit(&self, workflow_id: Uuid) {
+        let mut state = self.state.lock().await;
+        state.limits.remove(&workflow_id);
+        self.dispatch(&mut state);
+    }
+
+    pub async fn get_limit(&self, workflow_id: Uuid) -> Option<ConcurrencyLimit> {
+        let state = self.state.lock().await;
+        self.limit_of(&state, workflow_id).cloned()
+    }
+
+    fn limit_of<'a>(&'a self, state: &'a QueueState, workflow_id: Uuid) -> Option<&'a ConcurrencyLimit> {
+        state.limits.get(&workflow_id).or(self.default_limit.as_ref())
+    }
+
+    fn has_room(&self, state: &QueueState, workflow_id: Uuid) -> bool {
+        self.limit_of(state, workflow_id)
+            .is_none_or(|limit| state.running_of(workflow_id) < limit.max_concurrent)
+    }
+
+    /// Submit a run. It starts right away when both limits allow, else it
+    /// is queued, skipped or makes room per the workflow's overflow policy.
+    /// Once the executor is shutting down, runs are rejected.
+    pub async fn submit(&self, workflow: Workflow, ctx: ExecutionContext) -> Result<RunTicket, WorkflowError> {
+        let execution_id = ctx.execution_id;
+        let workflow_id = workflow.id;
+        if self.executor.tasks().is_shutting_down() {
+            return Err(WorkflowError::QueueFull(format!(
+                "shutting down, rejecting execution of workflow {}", workflow_id
+            )));
+        }
+        let mut state = self.state.lock().await;
+
+        let workflow_full = !self.has_room(&state, workflow_id);
+        if !workflow_full && state.running.len() < self.max_concurrent {
+            let (cancel, cancelled) = oneshot::channel();
+            state.running.push(RunningRun { execution_id, workflow_id, cancel: Some(cancel) });
+            let handle = self.executor.tasks().spawn(self.clone().run(workflow, ctx, None, cancelled));
+            return Ok(RunTicket { execution_id, admission: RunAdmission::Started, handle: Some(handle) });
+        }
+
+        if workflow_full {
+            match self.limit_of(&state, workflow_id).map(|limit| limit.overflow) {
+                Some(OverflowPolicy::Skip) => {
+                    state.skipped += 1;
+                    tracing::info!("Skipping execution {} of workflow {}: at its concurrency limit", execution_id, workflow_id);
+                    return Ok(RunTicket { execution_id, admission: RunAdmission::Skipped, handle: None });
+                }
+                Some(OverflowPolicy::CancelOldest) => {
+                    state.cancel_oldest(workflow_id);
+                }
+                Some(OverflowPolicy::Queue) | None => {}
+            }
+        }
+
+        if state.pending.len() >= self.max_queued {
+            state.rejected += 1;
+            return Err(WorkflowError::QueueFull(format!(
+                "{} runs waiting, rejecting execution of workflow {}",
+                state.pending.len(), workflow_id
+            )));
+        }
+        let (grant, granted) = oneshot::channel();
+        let (cancel, cancelled) = oneshot::channel();
+        state.pending.push_back(PendingRun { execution_id, workflow_id, grant, cancel });
+        state.peak_queued = state.peak_queued.max(state.pending.len());
+        let handle = self.executor.tasks().spawn(self.clone().run(workflow, ctx, Some(granted), cancelled));
+        Ok(RunTicket { execution_id, admission: RunAdmission::Queued, handle: Some(handle) })
+    }
+
+    async fn run(
+        self,
+        workflow: Workflow,
+        ctx: ExecutionContext,
+        granted: Option<oneshot::Receiver<bool>>,
+        cancelled: oneshot::Receiver<()>,
+    ) -> Result<ExecutionResult, WorkflowError> {
+        let execution_id = ctx.execution_id;
+        if let Some(granted) = granted {
+            if !granted.await.unwrap_or(false) {
+                return Ok(cancelled_result(execution_id, "Dropped from the run queue for a newer run"));
+            }
+        }
+
+        let executor = self.executor.clone();
+        let mut execution = self.executor.tasks().spawn(async move { executor.execute(&workflow, ctx).await });
+        let result = tokio::select! {
+            joined = &mut execution => joined.unwrap_or_else(|e| {
+                Err(WorkflowError::NodeExecutionFailed(execution_id.to_string(), e.to_string()))
+            }),
+            Ok(()) = cancelled => {
+                execution.abort();
+                let _ = self.executor.cancel(execution_id).await;
+                Ok(cancelled_result(execution_id, "Cancelled for a newer run of the workflow"))
+            }
+        };
+
+        let mut state = self.state.lock().await;
+        state.running.retain(|run| run.execution_id != execution_id);
+        self.dispatch(&mut state);
+        result
+    }
+
+    /// Start queued runs in FIFO order while there is room, passing over
+    /// runs of workflows that are at their own limit
+    fn dispatch(&self, state: &mut QueueState) {
+        let mut index = 0;
+        while index < state.pending.len() && state.running.len() < self.max_concurrent {
+            let workflow_id = state.pending[index].workflow_id;
+            if !self.has_room(state, workflow_id) {
+                index += 1;
+                continue;
+            }
+            let Some(run) = state.pending.remove(index) else {
+                break;
+            };
+            // A closed grant channel means the waiting task is gone
+            if run.grant.send(true).is_ok() {
+                state.running.push(RunningRun {
+                    execution_id: run.execution_id,
+                    workflow_id,
+                    cancel: Some(run.cancel),
+                });
+            }
+        }
+    }
+
+    pub async fn metrics(&self) -> RunQueueMetrics {
+        let state = self.state.lock().await;
+        let mut running_by_workflow = HashMap::new();
+        for run in &state.running {
+            *running_by_workflow.entry(run.workflow_id).or_insert(0) += 1;
+        }
+        let mut queued_by_workflow = HashMap::new();
+        for run in &state.pending {
+            *queued_by_workflow.entry(run.workflow_id).or_insert(0) += 1;
+        }
+        RunQueueMetrics {
+            running: state.running.len(),
+            queued: state.pending.len(),
+            peak_queued: state.peak_queued,
+            running_by_workflow,
+            queued_by_workflow,
+            skipped: state.skipped,
+            cancelled: state.cancelled,
+            rejected: state.rejected,
+        }
+    }
+}
+
+fn cancelled_result(execution_id: Uuid, reason: &str) -> ExecutionResult {
+    ExecutionResult {
+        execution_id,
+        state: ExecutionState::Cancelled,
+        completed_at: Some(Utc::now()),
+        error: Some(reason.to_string()),
+        output: None,
+        usage: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HttpDispatcher;
+    use common::error::GatewayError;
+    use common::types::{
+        ActionType, ApiRequest, ApiResponse, Node, NodeConfig, NodeType, Position, WORKFLOW_SCHEMA_VERSION,
+    };
+    use tokio::sync::Semaphore;
+
+    /// Holds every request until the test hands out permits
+    struct GatedDispatcher(Arc<Semaphore>);
+
+    #[async_trait::async_trait]
+    impl HttpDispatcher for GatedDispatcher {
+        async fn dispatch(&self, request: ApiRequest) -> Result<ApiResponse, GatewayError> {
+            self.0.acquire().await.unwrap().forget();
+            Ok(ApiResponse {
+                request_id: request.id,
+                status_code: 200,
+                headers: HashMap::new(),
+                body: None,
+                latency_ms: 1,
+            })
+        }
+    }
+
+    fn workflow() -> Workflow {
+        Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Sync".to_string(),
+            description: None,
+            nodes: vec![Node {
+                id: Uuid::new_v4(),
+                node_type: NodeType::Action { action_type: ActionType::Http },
+                config: NodeConfig {
+                    parameters: HashMap::from([("url".to_string(), serde_json::json!("https://api.example.com/sync"))]),
+                    ..Default::default()
+                },
+                position: Position { x: 0.0, y: 0.0 },
+                inputs: vec![],
+                outputs: vec![],
+            }],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        }
+    }
+
+    fn ctx(workflow: &Workflow) -> ExecutionContext {
+        ExecutionContext {
+            execution_id: Uuid::new_v4(),
+            workflow_id: workflow.id,
+            variables: HashMap::new(),
+            state: ExecutionState::Pending,
+            started_at: Utc::now(),
+            current_node: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limits_and_overflow_policies() {
+        let gate = Arc::new(Semaphore::new(0));
+        let executor = Arc::new(WorkflowExecutor::new().with_http_dispatcher(Arc::new(GatedDispatcher(gate.clone()))));
+        let queue = WorkflowRunQueue::new(executor, 3).with_max_queued(2);
+        let (queued, skipping, replacing, unlimited) = (workflow(), workflow(), workflow(), workflow());
+        queue.set_limit(queued.id, ConcurrencyLimit::new(1, OverflowPolicy::Queue)).await;
+        queue.set_limit(skipping.id, ConcurrencyLimit::new(1, OverflowPolicy::Skip)).await;
+        queue.set_limit(replacing.id, ConcurrencyLimit::new(1, OverflowPolicy::CancelOldest)).await;
+        let submit = |workflow: &Workflow| queue.submit(workflow.clone(), ctx(workflow));
+
+        let first = submit(&queued).await.unwrap();
+        let second = submit(&queued).await.unwrap();
+        assert_eq!(first.admission, RunAdmission::Started);
+        assert_eq!(second.admission, RunAdmission::Queued);
+
+        assert_eq!(submit(&skipping).await.unwrap().admission, RunAdmission::Started);
+        let skipped = submit(&skipping).await.unwrap();
+        assert_eq!(skipped.admission, RunAdmission::Skipped);
+        assert_eq!(skipped.finished().await.unwrap().state, ExecutionState::Cancelled);
+
+        // The global limit is reached, the replacement waits for the slot of the run it cancels
+        let oldest = submit(&replacing).await.unwrap();
+        assert_eq!(oldest.admission, RunAdmission::Started);
+        assert_eq!(submit(&replacing).await.unwrap().admission, RunAdmission::Queued);
+        assert_eq!(oldest.finished().await.unwrap().state, ExecutionState::Cancelled);
+
+        let metrics = queue.metrics().await;
+        assert_eq!((metrics.running, metrics.queued), (3, 1));
+        assert_eq!(metrics.running_by_workflow[&replacing.id], 1);
+        assert_eq!(metrics.queued_by_workflow[&queued.id], 1);
+
+        assert_eq!(submit(&unlimited).await.unwrap().admission, RunAdmission::Queued);
+        assert!(matches!(submit(&unlimited).await, Err(WorkflowError::QueueFull(_))));
+
+        gate.add_permits(10);
+        assert_eq!(first.finished().await.unwrap().state, ExecutionState::Completed);
+        assert_eq!(second.finished().await.unwrap().state, ExecutionState::Completed);
+
+        let metrics = queue.metrics().await;
+        assert_eq!(metrics.queued, 0);
+        assert_eq!(metrics.peak_queued, 2);
+        assert_eq!((metrics.skipped, metrics.cancelled, metrics.rejected), (1, 1, 1));
+    }
+}
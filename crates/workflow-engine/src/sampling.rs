@@ -0,0 +1,95 @@
+use chrono::{DateTime, Duration as ChronoDuration, DurationRound, Utc};
+use common::types::{ExecutionState, ResourceUsage};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::history::ExecutionRecord;
+
+/// Which executions of a high-frequency workflow the history keeps. Dropped
+/// executions still count towards the workflow's [`ExecutionCounters`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SamplingPolicy {
+    /// Share of successful executions kept, from 0 to 100
+    pub success_percent: f64,
+    /// Keep every failed execution
+    #[serde(default = "default_true")]
+    pub keep_failures: bool,
+    /// Keep the first and last execution started in each hour
+    #[serde(default = "default_true")]
+    pub keep_hour_bounds: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl SamplingPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=100.0).contains(&self.success_percent) {
+            return Err(format!("success_percent must be between 0 and 100, got {}", self.success_percent));
+        }
+        Ok(())
+    }
+
+    /// Whether the execution is kept for its own sake, regardless of its
+    /// place in the hour. Successes are picked by execution ID, so the
+    /// decision is the same wherever it is made.
+    pub fn selects(&self, record: &ExecutionRecord) -> bool {
+        if record.result.state == ExecutionState::Failed {
+            return self.keep_failures;
+        }
+        let bucket = (record.result.execution_id.as_u128() % 10_000) as f64;
+        bucket < self.success_percent * 100.0
+    }
+}
+
+/// Executions of one workflow started in one hour, counted before sampling
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionCounters {
+    pub runs: u64,
+    pub failures: u64,
+    /// Executions dropped from the history by its sampling policy
+    pub sampled_out: u64,
+    pub usage: ResourceUsage,
+}
+
+impl ExecutionCounters {
+    pub(crate) fn count(&mut self, record: &ExecutionRecord) {
+        self.runs += 1;
+        if record.result.state == ExecutionState::Failed {
+            self.failures += 1;
+        }
+        if let Some(usage) = &record.result.usage {
+            self.usage.add(&usage.totals);
+        }
+    }
+
+    pub fn add(&mut self, other: &ExecutionCounters) {
+        self.runs += other.runs;
+        self.failures += other.failures;
+        self.sampled_out += other.sampled_out;
+        self.usage.add(&other.usage);
+    }
+}
+
+/// The counters of a workflow over a period
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkflowCounters {
+    /// Organization of the workflow's latest execution
+    pub organization_id: Option<Uuid>,
+    pub counters: ExecutionCounters,
+}
+
+/// The hour a workflow's latest executions started in, tracking the one kept
+/// only because it is the last of the hour so far
+#[derive(Debug, Clone)]
+pub(crate) struct HourWindow {
+    pub hour: DateTime<Utc>,
+    /// Dropped once a later execution of the hour arrives
+    pub provisional: Option<Uuid>,
+}
+
+/// The start of the hour `at` falls in
+pub fn hour_of(at: DateTime<Utc>) -> DateTime<Utc> {
+    at.duration_trunc(ChronoDuration::hours(1)).unwrap_or(at)
+}
@@ -1,34 +1,311 @@
 use common::types::{Workflow, ExecutionContext, ExecutionState};
 use common::error::WorkflowError;
+use crate::cron::CronExpression;
 use crate::executor::WorkflowExecutor;
-use std::collections::HashMap;
+use crate::revisions::RevisionStore;
+use crate::run_queue::WorkflowRunQueue;
+use async_trait::async_trait;
+use chrono_tz::Tz;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use tokio::time::{interval, Duration};
 use uuid::Uuid;
-use chrono::{Utc, Datelike, Timelike};
+use chrono::{DateTime, Utc};
+
+/// Cron and interval runs fired later than this after their time count as
+/// missed and follow the schedule's catch-up policy
+const MISSED_RUN_GRACE_SECS: i64 = 60;
+
+/// Upper bound on the missed runs a `FireAll` schedule catches up on; older
+/// ones are dropped
+const MAX_CATCH_UP_RUNS: usize = 100;
+
+/// How often the leader replica reloads schedules changed through other replicas
+const RESYNC_INTERVAL_SECS: u64 = 30;
 
 /// Schedule configuration for a workflow
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScheduleConfig {
     pub workflow_id: Uuid,
     pub schedule_type: ScheduleType,
     pub enabled: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ScheduleType {
-    Cron(String),
+    Cron(CronSchedule),
+    /// Run every given duration, starting one interval after the schedule is
+    /// added; the next run time survives restarts when a store is configured
     Interval(Duration),
-    Webhook { url: String, secret: Option<String> },
+    Webhook {
+        url: String,
+        secret: Option<String>,
+        /// Also accept legacy `sha256=` signatures of the body alone, which
+        /// carry no timestamp and can be replayed; off unless a caller still
+        /// sends them
+        #[serde(default)]
+        accept_legacy_signatures: bool,
+    },
+    /// Poll an RSS/Atom/JSON feed; new entries are delivered via `trigger_feed_entries`
+    Feed { url: String, poll_interval: Duration },
+    /// Run once at the given time (on the first tick at or after it), then
+    /// removed; created with `schedule_once`
+    Once { run_at: DateTime<Utc> },
+}
+
+/// What to do with cron runs that fell due while the scheduler was down
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUpPolicy {
+    /// Drop missed runs
+    #[default]
+    Skip,
+    /// Run once for all missed runs, as of the latest one
+    FireOnce,
+    /// Run every missed run, oldest first
+    FireAll,
+}
+
+/// A cron expression evaluated in its own time zone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CronSchedule {
+    pub expression: String,
+    pub timezone: Tz,
+    pub catch_up: CatchUpPolicy,
+}
+
+impl CronSchedule {
+    /// A schedule in UTC that skips missed runs
+    pub fn new(expression: impl Into<String>) -> Self {
+        Self {
+            expression: expression.into(),
+            timezone: Tz::UTC,
+            catch_up: CatchUpPolicy::default(),
+        }
+    }
+
+    pub fn with_timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    pub fn with_catch_up(mut self, catch_up: CatchUpPolicy) -> Self {
+        self.catch_up = catch_up;
+        self
+    }
+}
+
+/// Runs of a cron or interval schedule due up to `checked_until` have been handled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleCheckpoint {
+    pub workflow_id: Uuid,
+    pub checked_until: DateTime<Utc>,
+}
+
+struct CronState {
+    expression: CronExpression,
+    checked_until: DateTime<Utc>,
+}
+
+struct IntervalState {
+    every: chrono::Duration,
+    next_fire: DateTime<Utc>,
+}
+
+/// A one-off schedule together with the workflow it runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OneOffSchedule {
+    pub id: Uuid,
+    pub workflow: Workflow,
+    pub run_at: DateTime<Utc>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Durable storage behind the scheduler, so schedules and their progress
+/// survive restarts and are shared between replicas
+#[async_trait]
+pub trait SchedulePersistence: Send + Sync {
+    async fn save_schedule(&self, config: &ScheduleConfig) -> Result<(), WorkflowError>;
+    /// Removing a schedule that is already gone is not an error
+    async fn delete_schedule(&self, workflow_id: Uuid) -> Result<(), WorkflowError>;
+    async fn load_schedules(&self) -> Result<Vec<ScheduleConfig>, WorkflowError>;
+
+    async fn save_one_off(&self, schedule: &OneOffSchedule) -> Result<(), WorkflowError>;
+    /// Removing a schedule that is already gone is not an error
+    async fn delete_one_off(&self, id: Uuid) -> Result<(), WorkflowError>;
+    /// Pending one-off schedules, soonest first
+    async fn load_one_offs(&self) -> Result<Vec<OneOffSchedule>, WorkflowError>;
+
+    /// Record how far a cron or interval schedule has been handled, so runs
+    /// missed while the process is down are found on the next start
+    async fn save_checkpoint(&self, checkpoint: &ScheduleCheckpoint) -> Result<(), WorkflowError>;
+    async fn load_checkpoint(&self, workflow_id: Uuid) -> Result<Option<ScheduleCheckpoint>, WorkflowError>;
+    async fn delete_checkpoint(&self, workflow_id: Uuid) -> Result<(), WorkflowError>;
+}
+
+/// Decides which replica fires time-based schedules, so replicas sharing a
+/// [`SchedulePersistence`] do not run the same cron workflow twice
+#[async_trait]
+pub trait LeaderLock: Send + Sync {
+    /// Take or keep leadership; `false` while another replica holds it
+    async fn try_acquire(&self) -> Result<bool, WorkflowError>;
+    async fn release(&self) -> Result<(), WorkflowError>;
+}
+
+/// File-based schedule store: one JSON file per one-off schedule, plus
+/// `schedules/` and `checkpoints/` subdirectories keyed by workflow ID
+pub struct ScheduleStore {
+    dir: PathBuf,
+}
+
+impl ScheduleStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path_for(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn schedule_path(&self, workflow_id: Uuid) -> PathBuf {
+        self.dir.join("schedules").join(format!("{}.json", workflow_id))
+    }
+
+    fn checkpoint_path(&self, workflow_id: Uuid) -> PathBuf {
+        self.dir.join("checkpoints").join(format!("{}.json", workflow_id))
+    }
+}
+
+#[async_trait]
+impl SchedulePersistence for ScheduleStore {
+    async fn save_schedule(&self, config: &ScheduleConfig) -> Result<(), WorkflowError> {
+        write_json(&self.schedule_path(config.workflow_id), config).await
+    }
+
+    async fn delete_schedule(&self, workflow_id: Uuid) -> Result<(), WorkflowError> {
+        remove_file(&self.schedule_path(workflow_id)).await
+    }
+
+    async fn load_schedules(&self) -> Result<Vec<ScheduleConfig>, WorkflowError> {
+        read_dir_json(&self.dir.join("schedules")).await
+    }
+
+    async fn save_one_off(&self, schedule: &OneOffSchedule) -> Result<(), WorkflowError> {
+        write_json(&self.path_for(schedule.id), schedule).await
+    }
+
+    async fn delete_one_off(&self, id: Uuid) -> Result<(), WorkflowError> {
+        remove_file(&self.path_for(id)).await
+    }
+
+    async fn load_one_offs(&self) -> Result<Vec<OneOffSchedule>, WorkflowError> {
+        let mut schedules: Vec<OneOffSchedule> = read_dir_json(&self.dir).await?;
+        schedules.sort_by_key(|s| s.run_at);
+        Ok(schedules)
+    }
+
+    async fn save_checkpoint(&self, checkpoint: &ScheduleCheckpoint) -> Result<(), WorkflowError> {
+        write_json(&self.checkpoint_path(checkpoint.workflow_id), checkpoint).await
+    }
+
+    async fn load_checkpoint(&self, workflow_id: Uuid) -> Result<Option<ScheduleCheckpoint>, WorkflowError> {
+        let path = self.checkpoint_path(workflow_id);
+        match tokio::fs::read(&path).await {
+            Ok(data) => read_json(&path, &data).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    async fn delete_checkpoint(&self, workflow_id: Uuid) -> Result<(), WorkflowError> {
+        remove_file(&self.checkpoint_path(workflow_id)).await
+    }
+}
+
+/// Write to a temporary file and rename it, so a crash never leaves a torn file
+async fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), WorkflowError> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(storage_error)?;
+    }
+    let data = serde_json::to_vec_pretty(value).map_err(|e| WorkflowError::Storage(e.to_string()))?;
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, data).await.map_err(storage_error)?;
+    tokio::fs::rename(&tmp, path).await.map_err(storage_error)
+}
+
+async fn remove_file(path: &Path) -> Result<(), WorkflowError> {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(storage_error(e)),
+    }
+}
+
+/// Every `*.json` file directly in `dir`; unreadable files are skipped
+async fn read_dir_json<T: DeserializeOwned>(dir: &Path) -> Result<Vec<T>, WorkflowError> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(storage_error(e)),
+    };
+
+    let mut values = Vec::new();
+    while let Some(entry) = entries.next_entry().await.map_err(storage_error)? {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let data = match tokio::fs::read(&path).await {
+            Ok(data) => data,
+            // Deleted since the listing, e.g. a run that just finished
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(storage_error(e)),
+        };
+        match read_json(&path, &data) {
+            Ok(value) => values.push(value),
+            Err(e) => tracing::warn!("Skipping unreadable schedule file: {}", e),
+        }
+    }
+    Ok(values)
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path, data: &[u8]) -> Result<T, WorkflowError> {
+    serde_json::from_slice(data)
+        .map_err(|e| WorkflowError::Storage(format!("{}: {}", path.display(), e)))
+}
+
+fn storage_error(e: std::io::Error) -> WorkflowError {
+    WorkflowError::Storage(e.to_string())
 }
 
 /// Workflow scheduler implementation
 /// Responsible for scheduling and triggering workflow executions
+#[derive(Clone)]
 pub struct WorkflowScheduler {
     executor: Arc<WorkflowExecutor>,
     schedules: Arc<RwLock<HashMap<Uuid, ScheduleConfig>>>,
     running: Arc<RwLock<bool>>,
+    revisions: Option<Arc<RevisionStore>>,
+    /// One-off schedules by workflow ID
+    one_off: Arc<RwLock<HashMap<Uuid, OneOffSchedule>>>,
+    /// Parsed expression and progress of each cron schedule
+    cron: Arc<RwLock<HashMap<Uuid, CronState>>>,
+    /// Next run time of each interval schedule
+    intervals: Arc<RwLock<HashMap<Uuid, IntervalState>>>,
+    /// Workflows run by cron and interval schedules when no active revision exists
+    workflows: Arc<RwLock<HashMap<Uuid, Workflow>>>,
+    /// One-off schedules whose run was started but not yet cleaned up
+    firing: Arc<RwLock<HashSet<Uuid>>>,
+    store: Option<Arc<dyn SchedulePersistence>>,
+    leader_lock: Option<Arc<dyn LeaderLock>>,
+    /// Bounds concurrent executions of all triggers
+    run_queue: Option<WorkflowRunQueue>,
 }
 
 impl WorkflowScheduler {
@@ -37,23 +314,536 @@ impl WorkflowScheduler {
             executor,
             schedules: Arc::new(RwLock::new(HashMap::new())),
             running: Arc::new(RwLock::new(false)),
+            revisions: None,
+            one_off: Arc::new(RwLock::new(HashMap::new())),
+            cron: Arc::new(RwLock::new(HashMap::new())),
+            intervals: Arc::new(RwLock::new(HashMap::new())),
+            workflows: Arc::new(RwLock::new(HashMap::new())),
+            firing: Arc::new(RwLock::new(HashSet::new())),
+            store: None,
+            leader_lock: None,
+            run_queue: None,
+        }
+    }
+
+    /// Persist schedules and cron and interval progress; call `restore` at
+    /// startup to reload them
+    pub fn with_store(mut self, store: Arc<dyn SchedulePersistence>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Only fire time-based schedules while holding `lock`. The leader
+    /// reloads schedules from the store when it takes over and periodically
+    /// after, to pick up changes made through other replicas.
+    pub fn with_leader_lock(mut self, lock: Arc<dyn LeaderLock>) -> Self {
+        self.leader_lock = Some(lock);
+        self
+    }
+
+    /// Start executions through `queue` instead of right away, so triggers
+    /// respect its global and per-workflow concurrency limits
+    pub fn with_run_queue(mut self, queue: WorkflowRunQueue) -> Self {
+        self.run_queue = Some(queue);
+        self
+    }
+
+    /// The executor running the scheduler's executions
+    pub fn executor(&self) -> &Arc<WorkflowExecutor> {
+        &self.executor
+    }
+
+    /// Whether this replica should fire schedules; always true without a leader lock
+    pub async fn is_leader(&self) -> bool {
+        let Some(lock) = &self.leader_lock else {
+            return true;
+        };
+        match lock.try_acquire().await {
+            Ok(leading) => leading,
+            Err(e) => {
+                tracing::error!("Failed to check scheduler leadership: {}", e);
+                false
+            }
         }
     }
 
-    /// Add a schedule for a workflow
+    /// Run the active revision of reviewed workflows, so pending edits
+    /// only take effect once approved
+    pub fn with_revisions(mut self, revisions: Arc<RevisionStore>) -> Self {
+        self.revisions = Some(revisions);
+        self
+    }
+
+    /// The definition to execute: the active revision when one exists
+    async fn resolve(&self, workflow: &Workflow) -> Workflow {
+        match &self.revisions {
+            Some(revisions) => revisions.active(workflow.id).await.unwrap_or_else(|| workflow.clone()),
+            None => workflow.clone(),
+        }
+    }
+
+    /// Definition run by cron and interval schedules of a workflow without an active revision
+    pub async fn register_workflow(&self, workflow: Workflow) {
+        self.workflows.write().await.insert(workflow.id, workflow);
+    }
+
+    /// Add a schedule for a workflow.
+    ///
+    /// Cron and interval schedules resume from their persisted checkpoint:
+    /// cron runs missed while the process was down are handled by the
+    /// catch-up policy, an overdue interval run fires on the next tick.
     pub async fn add_schedule(&self, config: ScheduleConfig) -> Result<(), WorkflowError> {
-        let mut schedules = self.schedules.write().await;
-        schedules.insert(config.workflow_id, config);
+        if matches!(config.schedule_type, ScheduleType::Once { .. }) {
+            return Err(WorkflowError::ValidationFailed(
+                "Once schedules need the workflow definition, use schedule_once".to_string(),
+            ));
+        }
+        self.discard_one_off(config.workflow_id).await?;
+        if let Some(store) = &self.store {
+            store.save_schedule(&config).await?;
+        }
+        self.install(config).await
+    }
+
+    /// Put a schedule in place without persisting it. Progress already made
+    /// in this process is kept when it is ahead of the stored checkpoint.
+    async fn install(&self, config: ScheduleConfig) -> Result<(), WorkflowError> {
+        let workflow_id = config.workflow_id;
+        let (mut cron_state, mut interval_state) = (None, None);
+        match &config.schedule_type {
+            ScheduleType::Cron(cron) => {
+                let expression = CronExpression::parse(&cron.expression).map_err(|e| {
+                    WorkflowError::ValidationFailed(format!("invalid cron expression '{}': {}", cron.expression, e))
+                })?;
+                let checkpoint = self.load_checkpoint(workflow_id).await?.map(|c| c.checked_until);
+                let current = self.cron.read().await.get(&workflow_id).map(|s| s.checked_until);
+                cron_state = Some(CronState {
+                    expression,
+                    checked_until: checkpoint.max(current).unwrap_or_else(Utc::now),
+                });
+            }
+            ScheduleType::Interval(every) => {
+                let every = chrono::Duration::from_std(*every)
+                    .ok()
+                    .filter(|every| *every >= chrono::Duration::seconds(1))
+                    .ok_or_else(|| {
+                        WorkflowError::ValidationFailed(format!("invalid interval {:?}, must be at least 1s", every))
+                    })?;
+                let checkpoint = self.load_checkpoint(workflow_id).await?.map(|c| c.checked_until + every);
+                let current = self.intervals.read().await.get(&workflow_id)
+                    .filter(|s| s.every == every)
+                    .map(|s| s.next_fire);
+                interval_state = Some(IntervalState {
+                    every,
+                    next_fire: checkpoint.max(current).unwrap_or_else(|| Utc::now() + every),
+                });
+            }
+            _ => {}
+        }
+
+        let mut cron = self.cron.write().await;
+        match cron_state {
+            Some(state) => cron.insert(workflow_id, state),
+            None => cron.remove(&workflow_id),
+        };
+        let mut intervals = self.intervals.write().await;
+        match interval_state {
+            Some(state) => intervals.insert(workflow_id, state),
+            None => intervals.remove(&workflow_id),
+        };
+        self.schedules.write().await.insert(workflow_id, config);
         Ok(())
     }
 
-    /// Remove a schedule
+    async fn load_checkpoint(&self, workflow_id: Uuid) -> Result<Option<ScheduleCheckpoint>, WorkflowError> {
+        match &self.store {
+            Some(store) => store.load_checkpoint(workflow_id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Remove a schedule; a one-off schedule is cancelled if it has not fired yet
     pub async fn remove_schedule(&self, workflow_id: Uuid) -> Result<(), WorkflowError> {
-        let mut schedules = self.schedules.write().await;
-        schedules.remove(&workflow_id);
+        self.discard_one_off(workflow_id).await?;
+        if let Some(store) = &self.store {
+            store.delete_schedule(workflow_id).await?;
+            store.delete_checkpoint(workflow_id).await?;
+        }
+        self.forget(workflow_id).await;
         Ok(())
     }
 
+    /// Drop a schedule from memory only
+    async fn forget(&self, workflow_id: Uuid) {
+        self.one_off.write().await.remove(&workflow_id);
+        self.cron.write().await.remove(&workflow_id);
+        self.intervals.write().await.remove(&workflow_id);
+        self.schedules.write().await.remove(&workflow_id);
+    }
+
+    /// Run a workflow once at `run_at`, replacing any existing schedule of the workflow
+    pub async fn schedule_once(&self, workflow: Workflow, run_at: DateTime<Utc>) -> Result<Uuid, WorkflowError> {
+        if run_at <= Utc::now() {
+            return Err(WorkflowError::ValidationFailed(format!("run_at {} is in the past", run_at)));
+        }
+        self.discard_one_off(workflow.id).await?;
+
+        let schedule = OneOffSchedule {
+            id: Uuid::new_v4(),
+            workflow,
+            run_at,
+            enabled: true,
+            created_at: Utc::now(),
+        };
+        if let Some(store) = &self.store {
+            store.save_one_off(&schedule).await?;
+        }
+        let id = schedule.id;
+        self.insert_one_off(schedule).await;
+        Ok(id)
+    }
+
+    /// Reload persisted schedules and return how many there are. Runs missed
+    /// while the process was down fire on the next tick.
+    ///
+    /// Safe to repeat: schedules removed from the store are dropped, and
+    /// one-off runs already started are not picked up again.
+    pub async fn restore(&self) -> Result<usize, WorkflowError> {
+        let Some(store) = &self.store else {
+            return Ok(0);
+        };
+        let configs = store.load_schedules().await?;
+        let one_offs = store.load_one_offs().await?;
+        let count = configs.len() + one_offs.len();
+
+        let persisted: HashSet<Uuid> = configs.iter().map(|c| c.workflow_id)
+            .chain(one_offs.iter().map(|s| s.workflow.id))
+            .collect();
+        let removed: Vec<Uuid> = self.schedules.read().await.keys()
+            .filter(|id| !persisted.contains(id))
+            .copied()
+            .collect();
+        for workflow_id in removed {
+            self.forget(workflow_id).await;
+        }
+
+        for config in configs {
+            let workflow_id = config.workflow_id;
+            if let Err(e) = self.install(config).await {
+                tracing::warn!("Skipping unusable schedule of workflow {}: {}", workflow_id, e);
+            }
+        }
+        let firing = self.firing.read().await.clone();
+        for schedule in one_offs.into_iter().filter(|s| !firing.contains(&s.id)) {
+            self.insert_one_off(schedule).await;
+        }
+        Ok(count)
+    }
+
+    /// Get the pending one-off schedule of a workflow
+    pub async fn get_one_off(&self, workflow_id: Uuid) -> Option<OneOffSchedule> {
+        self.one_off.read().await.get(&workflow_id).cloned()
+    }
+
+    async fn insert_one_off(&self, schedule: OneOffSchedule) {
+        let workflow_id = schedule.workflow.id;
+        self.schedules.write().await.insert(workflow_id, ScheduleConfig {
+            workflow_id,
+            schedule_type: ScheduleType::Once { run_at: schedule.run_at },
+            enabled: schedule.enabled,
+        });
+        self.one_off.write().await.insert(workflow_id, schedule);
+    }
+
+    async fn discard_one_off(&self, workflow_id: Uuid) -> Result<(), WorkflowError> {
+        let removed = self.one_off.write().await.remove(&workflow_id);
+        match (removed, &self.store) {
+            (Some(schedule), Some(store)) => store.delete_one_off(schedule.id).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn set_one_off_enabled(&self, workflow_id: Uuid, enabled: bool) -> Result<(), WorkflowError> {
+        let mut one_off = self.one_off.write().await;
+        let Some(schedule) = one_off.get_mut(&workflow_id) else {
+            return Ok(());
+        };
+        schedule.enabled = enabled;
+        match &self.store {
+            Some(store) => store.save_one_off(schedule).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Fire enabled one-off schedules due at `now` and remove them from the
+    /// schedule. The persisted copy is deleted once the run finishes, so a run
+    /// interrupted by a crash is retried after `restore`.
+    pub async fn run_due(&self, now: DateTime<Utc>) -> Vec<Uuid> {
+        let due: Vec<OneOffSchedule> = {
+            let mut schedules = self.schedules.write().await;
+            let mut one_off = self.one_off.write().await;
+            let mut firing = self.firing.write().await;
+            let ids: Vec<Uuid> = one_off.values()
+                .filter(|s| s.enabled && s.run_at <= now)
+                .map(|s| s.workflow.id)
+                .collect();
+            ids.iter()
+                .filter_map(|id| {
+                    schedules.remove(id);
+                    one_off.remove(id)
+                })
+                .inspect(|schedule| {
+                    firing.insert(schedule.id);
+                })
+                .collect()
+        };
+
+        let mut execution_ids = Vec::with_capacity(due.len());
+        for schedule in due {
+            let workflow = self.resolve(&schedule.workflow).await;
+
+            let mut variables = HashMap::new();
+            variables.insert("scheduled_at".to_string(), serde_json::json!(schedule.run_at));
+
+            let (execution_id, finished) = match self.launch(workflow, variables, "One-off").await {
+                Ok(launched) => launched,
+                Err(e) => {
+                    // Try again on the next tick
+                    tracing::warn!("Deferring one-off schedule {}: {}", schedule.id, e);
+                    self.firing.write().await.remove(&schedule.id);
+                    self.insert_one_off(schedule).await;
+                    continue;
+                }
+            };
+            let store = self.store.clone();
+            let firing = self.firing.clone();
+
+            self.executor.tasks().spawn(async move {
+                let _ = finished.await;
+                if let Some(store) = store {
+                    if let Err(e) = store.delete_one_off(schedule.id).await {
+                        tracing::error!("Failed to clean up one-off schedule {}: {}", schedule.id, e);
+                    }
+                }
+                firing.write().await.remove(&schedule.id);
+            });
+
+            execution_ids.push(execution_id);
+        }
+
+        execution_ids
+    }
+
+    /// Fire cron runs due up to `now`.
+    ///
+    /// Runs later than the grace period are missed runs, e.g. from while the
+    /// process was down, and are skipped, collapsed into one run or all fired
+    /// depending on the schedule's catch-up policy. Executions get the run
+    /// time as `scheduled_at` and whether it was missed as `missed_run`.
+    pub async fn run_due_cron(&self, now: DateTime<Utc>) -> Vec<Uuid> {
+        let cron_schedules: Vec<(Uuid, CronSchedule)> = {
+            let schedules = self.schedules.read().await;
+            schedules.values()
+                .filter(|config| config.enabled)
+                .filter_map(|config| match &config.schedule_type {
+                    ScheduleType::Cron(cron) => Some((config.workflow_id, cron.clone())),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let mut execution_ids = Vec::new();
+        for (workflow_id, schedule) in cron_schedules {
+            let (due, dropped) = {
+                let mut states = self.cron.write().await;
+                let Some(state) = states.get_mut(&workflow_id) else {
+                    continue;
+                };
+                let due = due_runs(&state.expression, schedule.timezone, state.checked_until, now);
+                if due.0.is_empty() {
+                    continue;
+                }
+                state.checked_until = now;
+                due
+            };
+
+            if let Some(store) = &self.store {
+                let checkpoint = ScheduleCheckpoint { workflow_id, checked_until: now };
+                if let Err(e) = store.save_checkpoint(&checkpoint).await {
+                    tracing::error!("Failed to save cron checkpoint of workflow {}: {}", workflow_id, e);
+                }
+            }
+
+            let grace = chrono::Duration::seconds(MISSED_RUN_GRACE_SECS);
+            let (missed, on_time): (Vec<_>, Vec<_>) = due.into_iter().partition(|run| now - *run > grace);
+            let missed_count = missed.len() + dropped;
+            let caught_up: Vec<DateTime<Utc>> = match schedule.catch_up {
+                CatchUpPolicy::Skip => Vec::new(),
+                CatchUpPolicy::FireOnce => missed.last().copied().into_iter().collect(),
+                CatchUpPolicy::FireAll => missed,
+            };
+            if missed_count > caught_up.len() {
+                tracing::warn!(
+                    "Skipping {} missed cron run(s) of workflow {}",
+                    missed_count - caught_up.len(), workflow_id
+                );
+            }
+
+            let Some(workflow) = self.scheduled_workflow(workflow_id).await else {
+                tracing::warn!("Cron schedule of workflow {} has no workflow definition to run", workflow_id);
+                continue;
+            };
+            let runs = caught_up.into_iter().map(|run| (run, true))
+                .chain(on_time.into_iter().map(|run| (run, false)));
+            for (scheduled_at, missed_run) in runs {
+                let mut variables = HashMap::new();
+                variables.insert("scheduled_at".to_string(), serde_json::json!(scheduled_at));
+                variables.insert("missed_run".to_string(), serde_json::json!(missed_run));
+                execution_ids.extend(self.spawn_execution(workflow.clone(), variables).await);
+            }
+        }
+        execution_ids
+    }
+
+    /// Fire interval schedules whose next run time has come.
+    ///
+    /// An interval fires at most once per call: runs overdue by more than the
+    /// grace period, e.g. from while the process was down, collapse into one
+    /// run as of the latest one, with `missed_run` set. The next run stays on the interval's
+    /// original cadence.
+    pub async fn run_due_interval(&self, now: DateTime<Utc>) -> Vec<Uuid> {
+        let enabled: Vec<Uuid> = {
+            let schedules = self.schedules.read().await;
+            schedules.values()
+                .filter(|config| config.enabled && matches!(config.schedule_type, ScheduleType::Interval(_)))
+                .map(|config| config.workflow_id)
+                .collect()
+        };
+
+        let mut execution_ids = Vec::new();
+        for workflow_id in enabled {
+            let (scheduled_at, last_due, skipped) = {
+                let mut intervals = self.intervals.write().await;
+                let Some(state) = intervals.get_mut(&workflow_id) else {
+                    continue;
+                };
+                if state.next_fire > now {
+                    continue;
+                }
+                let scheduled_at = state.next_fire;
+                let behind = ((now - scheduled_at).num_milliseconds() / state.every.num_milliseconds()) as i32;
+                let last_due = scheduled_at + state.every * behind;
+                state.next_fire = last_due + state.every;
+                (scheduled_at, last_due, behind)
+            };
+
+            if let Some(store) = &self.store {
+                let checkpoint = ScheduleCheckpoint { workflow_id, checked_until: last_due };
+                if let Err(e) = store.save_checkpoint(&checkpoint).await {
+                    tracing::error!("Failed to save interval checkpoint of workflow {}: {}", workflow_id, e);
+                }
+            }
+            if skipped > 0 {
+                tracing::warn!("Collapsing {} missed interval run(s) of workflow {}", skipped + 1, workflow_id);
+            }
+
+            let Some(workflow) = self.scheduled_workflow(workflow_id).await else {
+                tracing::warn!("Interval schedule of workflow {} has no workflow definition to run", workflow_id);
+                continue;
+            };
+            let missed_run = now - scheduled_at > chrono::Duration::seconds(MISSED_RUN_GRACE_SECS);
+            let mut variables = HashMap::new();
+            variables.insert("scheduled_at".to_string(), serde_json::json!(last_due));
+            variables.insert("missed_run".to_string(), serde_json::json!(missed_run));
+            execution_ids.extend(self.spawn_execution(workflow, variables).await);
+        }
+        execution_ids
+    }
+
+    /// When an enabled schedule next fires; `None` for disabled schedules and
+    /// for externally triggered ones
+    pub async fn next_fire_time(&self, workflow_id: Uuid) -> Option<DateTime<Utc>> {
+        let config = self.schedules.read().await.get(&workflow_id).cloned()?;
+        if !config.enabled {
+            return None;
+        }
+        match config.schedule_type {
+            ScheduleType::Cron(cron) => {
+                let states = self.cron.read().await;
+                let state = states.get(&workflow_id)?;
+                let after = state.checked_until.with_timezone(&cron.timezone);
+                state.expression.next_after(&after).map(|next| next.with_timezone(&Utc))
+            }
+            ScheduleType::Interval(_) => self.intervals.read().await.get(&workflow_id).map(|s| s.next_fire),
+            ScheduleType::Once { run_at } => Some(run_at),
+            ScheduleType::Webhook { .. } | ScheduleType::Feed { .. } => None,
+        }
+    }
+
+    /// The definition a cron, interval or webhook schedule runs: the active
+    /// revision, else the registered workflow
+    pub async fn scheduled_workflow(&self, workflow_id: Uuid) -> Option<Workflow> {
+        if let Some(revisions) = &self.revisions {
+            if let Some(active) = revisions.active(workflow_id).await {
+                return Some(active);
+            }
+        }
+        self.workflows.read().await.get(&workflow_id).cloned()
+    }
+
+    /// Start a cron or interval run; a run refused by the run queue is dropped
+    async fn spawn_execution(&self, workflow: Workflow, variables: HashMap<String, serde_json::Value>) -> Option<Uuid> {
+        let workflow_id = workflow.id;
+        match self.launch(workflow, variables, "Scheduled").await {
+            Ok((execution_id, _)) => Some(execution_id),
+            Err(e) => {
+                tracing::warn!("Dropping scheduled run of workflow {}: {}", workflow_id, e);
+                None
+            }
+        }
+    }
+
+    /// Start an execution, through the run queue when one is configured. The
+    /// returned handle completes once the execution finished or was skipped.
+    async fn launch(
+        &self,
+        workflow: Workflow,
+        variables: HashMap<String, serde_json::Value>,
+        trigger: &'static str,
+    ) -> Result<(Uuid, JoinHandle<()>), WorkflowError> {
+        let tasks = self.executor.tasks();
+        if tasks.is_shutting_down() {
+            return Err(WorkflowError::QueueFull(format!(
+                "shutting down, not starting {} execution of workflow {}", trigger, workflow.id
+            )));
+        }
+        let execution_id = Uuid::new_v4();
+        let ctx = ExecutionContext {
+            execution_id,
+            workflow_id: workflow.id,
+            variables,
+            state: ExecutionState::Pending,
+            started_at: Utc::now(),
+            current_node: None,
+        };
+
+        let report = move |result: Result<common::types::ExecutionResult, WorkflowError>| match result {
+            Ok(result) => tracing::info!("{} execution completed: {:?}", trigger, result),
+            Err(e) => tracing::error!("{} execution failed: {}", trigger, e),
+        };
+        let handle = match &self.run_queue {
+            Some(queue) => {
+                let ticket = queue.submit(workflow, ctx).await?;
+                tasks.spawn(async move { report(ticket.finished().await) })
+            }
+            None => {
+                let executor = self.executor.clone();
+                tasks.spawn(async move { report(executor.execute(&workflow, ctx).await) })
+            }
+        };
+        Ok((execution_id, handle))
+    }
+
     /// Start the scheduler
     pub async fn start(&self) -> Result<(), WorkflowError> {
         let mut running = self.running.write().await;
@@ -65,20 +855,50 @@ impl WorkflowScheduler {
 
         // Start scheduler loop
         let schedules = self.schedules.clone();
-        let _executor = self.executor.clone();
+        let scheduler = self.clone();
         let running_flag = self.running.clone();
+        let tasks = self.executor.tasks().clone();
 
-        tokio::spawn(async move {
-            let mut tick_interval = interval(Duration::from_secs(60)); // Check every minute
+        self.executor.tasks().spawn(async move {
+            // Cron expressions may have a seconds field
+            let mut tick_interval = interval(Duration::from_secs(1));
+            let mut leading = false;
+            let mut last_resync = tokio::time::Instant::now();
 
             loop {
-                tick_interval.tick().await;
+                tokio::select! {
+                    _ = tick_interval.tick() => {}
+                    _ = tasks.cancelled() => break,
+                }
 
                 let is_running = *running_flag.read().await;
                 if !is_running {
                     break;
                 }
 
+                if !scheduler.is_leader().await {
+                    if leading {
+                        tracing::info!("Scheduler lost leadership, no longer firing schedules");
+                    }
+                    leading = false;
+                    continue;
+                }
+                // A new leader takes over schedules changed through other replicas
+                if scheduler.leader_lock.is_some()
+                    && (!leading || last_resync.elapsed() >= Duration::from_secs(RESYNC_INTERVAL_SECS))
+                {
+                    if let Err(e) = scheduler.restore().await {
+                        tracing::error!("Failed to reload schedules: {}", e);
+                    }
+                    last_resync = tokio::time::Instant::now();
+                }
+                leading = true;
+
+                let now = Utc::now();
+                scheduler.run_due(now).await;
+                scheduler.run_due_cron(now).await;
+                scheduler.run_due_interval(now).await;
+
                 // Check all schedules
                 let schedules_map = schedules.read().await;
                 for (workflow_id, config) in schedules_map.iter() {
@@ -87,20 +907,22 @@ impl WorkflowScheduler {
                     }
 
                     match &config.schedule_type {
-                        ScheduleType::Cron(cron_expr) => {
-                            if Self::should_trigger_cron(cron_expr) {
-                                // Trigger workflow execution
-                                // Note: In real implementation, we'd need the actual workflow
-                                tracing::info!("Triggering workflow {} via cron", workflow_id);
-                            }
+                        ScheduleType::Cron(_) => {
+                            // Fired by run_due_cron above
                         }
-                        ScheduleType::Interval(_duration) => {
-                            // Interval-based scheduling would need separate tracking
-                            tracing::debug!("Interval schedule for workflow {}", workflow_id);
+                        ScheduleType::Interval(_) => {
+                            // Fired by run_due_interval above
                         }
                         ScheduleType::Webhook { .. } => {
                             // Webhooks are triggered externally, not by scheduler
                         }
+                        ScheduleType::Feed { url, .. } => {
+                            // Feed polling and GUID dedupe are done by the integration service
+                            tracing::debug!("Feed schedule for workflow {}: {}", workflow_id, url);
+                        }
+                        ScheduleType::Once { .. } => {
+                            // Fired by run_due above
+                        }
                     }
                 }
             }
@@ -113,135 +935,77 @@ impl WorkflowScheduler {
     pub async fn stop(&self) -> Result<(), WorkflowError> {
         let mut running = self.running.write().await;
         *running = false;
-        Ok(())
-    }
-
-    /// Check if a cron expression should trigger now
-    fn should_trigger_cron(cron_expr: &str) -> bool {
-        // Simplified cron parsing - in production, use a cron library
-        // This is a placeholder implementation
-        
-        // Parse cron expression: minute hour day month weekday
-        let parts: Vec<&str> = cron_expr.split_whitespace().collect();
-        if parts.len() != 5 {
-            return false;
-        }
-
-        let now = Utc::now();
-        let current_minute = now.minute();
-        let current_hour = now.hour();
-        let current_day = now.day();
-        let current_month = now.month();
-        let current_weekday = now.weekday().num_days_from_monday();
-
-        // Check minute
-        if !Self::matches_cron_field(parts[0], current_minute) {
-            return false;
-        }
-
-        // Check hour
-        if !Self::matches_cron_field(parts[1], current_hour) {
-            return false;
-        }
-
-        // Check day
-        if !Self::matches_cron_field(parts[2], current_day) {
-            return false;
-        }
-
-        // Check month
-        if !Self::matches_cron_field(parts[3], current_month) {
-            return false;
-        }
-
-        // Check weekday
-        if !Self::matches_cron_field(parts[4], current_weekday) {
-            return false;
+        drop(running);
+        // Let another replica take over right away
+        match &self.leader_lock {
+            Some(lock) => lock.release().await,
+            None => Ok(()),
         }
-
-        true
     }
 
-    /// Check if a cron field matches the current value
-    fn matches_cron_field(field: &str, value: u32) -> bool {
-        if field == "*" {
-            return true;
-        }
-
-        // Handle specific value
-        if let Ok(field_value) = field.parse::<u32>() {
-            return field_value == value;
-        }
-
-        // Handle range (e.g., "1-5")
-        if field.contains('-') {
-            let range_parts: Vec<&str> = field.split('-').collect();
-            if range_parts.len() == 2 {
-                if let (Ok(start), Ok(end)) = (range_parts[0].parse::<u32>(), range_parts[1].parse::<u32>()) {
-                    return value >= start && value <= end;
-                }
-            }
-        }
-
-        // Handle list (e.g., "1,3,5")
-        if field.contains(',') {
-            let values: Vec<u32> = field.split(',')
-                .filter_map(|v| v.parse::<u32>().ok())
-                .collect();
-            return values.contains(&value);
-        }
-
-        // Handle step (e.g., "*/5")
-        if field.contains('/') {
-            let step_parts: Vec<&str> = field.split('/').collect();
-            if step_parts.len() == 2 && step_parts[0] == "*" {
-                if let Ok(step) = step_parts[1].parse::<u32>() {
-                    return value % step == 0;
-                }
-            }
-        }
-
-        false
+    /// Stop the scheduler and wait up to `timeout` for the executions it
+    /// started and other background tasks of the executor. Triggers are
+    /// refused from then on. Returns whether all tasks finished in time.
+    pub async fn shutdown(&self, timeout: Duration) -> Result<bool, WorkflowError> {
+        self.stop().await?;
+        Ok(self.executor.tasks().shutdown(timeout).await)
     }
 
-    /// Trigger a workflow via webhook
+    /// Trigger a workflow via webhook; the payload and request headers are
+    /// available as `webhook_payload` and `webhook_headers`. Fails with
+    /// `WorkflowError::QueueFull` when the run queue pushes back or the
+    /// scheduler is shutting down.
     pub async fn trigger_webhook(
         &self,
         workflow: &Workflow,
         payload: serde_json::Value,
+        headers: HashMap<String, String>,
     ) -> Result<Uuid, WorkflowError> {
-        let execution_id = Uuid::new_v4();
-        
         let mut variables = HashMap::new();
         variables.insert("webhook_payload".to_string(), payload);
-
-        let ctx = ExecutionContext {
-            execution_id,
-            workflow_id: workflow.id,
-            variables,
-            state: ExecutionState::Pending,
-            started_at: Utc::now(),
-            current_node: None,
-        };
+        variables.insert("webhook_headers".to_string(), serde_json::json!(headers));
 
         // Execute workflow asynchronously
-        let executor = self.executor.clone();
-        let workflow_clone = workflow.clone();
-        
-        tokio::spawn(async move {
-            match executor.execute(&workflow_clone, ctx).await {
-                Ok(result) => {
-                    tracing::info!("Webhook execution completed: {:?}", result);
-                }
-                Err(e) => {
-                    tracing::error!("Webhook execution failed: {}", e);
-                }
-            }
-        });
+        let workflow = self.resolve(workflow).await;
+        let (execution_id, _) = self.launch(workflow, variables, "Webhook").await?;
+        Ok(execution_id)
+    }
 
+    /// Start a manual execution of a workflow with the given input variables
+    pub async fn trigger_manual(
+        &self,
+        workflow: &Workflow,
+        input: HashMap<String, serde_json::Value>,
+    ) -> Result<Uuid, WorkflowError> {
+        let (execution_id, _) = self.launch(workflow.clone(), input, "Manual").await?;
         Ok(execution_id)
     }
 
+    /// Trigger one workflow execution per new feed entry
+    pub async fn trigger_feed_entries(
+        &self,
+        workflow: &Workflow,
+        entries: Vec<serde_json::Value>,
+    ) -> Result<Vec<Uuid>, WorkflowError> {
+        let mut execution_ids = Vec::with_capacity(entries.len());
+        let workflow = &self.resolve(workflow).await;
+
+        for entry in entries {
+            let mut variables = HashMap::new();
+            variables.insert("feed_entry".to_string(), entry);
+
+            let (execution_id, _) = self.launch(workflow.clone(), variables, "Feed").await?;
+            execution_ids.push(execution_id);
+        }
+
+        Ok(execution_ids)
+    }
+
+    /// Get the schedule of a workflow
+    pub async fn get_schedule(&self, workflow_id: Uuid) -> Option<ScheduleConfig> {
+        self.schedules.read().await.get(&workflow_id).cloned()
+    }
+
     /// Get all active schedules
     pub async fn get_schedules(&self) -> HashMap<Uuid, ScheduleConfig> {
         let schedules = self.schedules.read().await;
@@ -250,27 +1014,76 @@ impl WorkflowScheduler {
 
     /// Enable a schedule
     pub async fn enable_schedule(&self, workflow_id: Uuid) -> Result<(), WorkflowError> {
-        let mut schedules = self.schedules.write().await;
-        if let Some(config) = schedules.get_mut(&workflow_id) {
-            config.enabled = true;
-            Ok(())
-        } else {
-            Err(WorkflowError::NodeNotFound(format!("Schedule not found for workflow {}", workflow_id)))
+        self.set_enabled(workflow_id, true).await?;
+        // Runs that fell due while disabled are not missed runs
+        let now = Utc::now();
+        let mut restarted = false;
+        if let Some(state) = self.cron.write().await.get_mut(&workflow_id) {
+            state.checked_until = now;
+            restarted = true;
+        }
+        if let Some(state) = self.intervals.write().await.get_mut(&workflow_id) {
+            state.next_fire = now + state.every;
+            restarted = true;
+        }
+        match &self.store {
+            Some(store) if restarted => {
+                store.save_checkpoint(&ScheduleCheckpoint { workflow_id, checked_until: now }).await
+            }
+            _ => Ok(()),
         }
     }
 
     /// Disable a schedule
     pub async fn disable_schedule(&self, workflow_id: Uuid) -> Result<(), WorkflowError> {
+        self.set_enabled(workflow_id, false).await
+    }
+
+    async fn set_enabled(&self, workflow_id: Uuid, enabled: bool) -> Result<(), WorkflowError> {
         let mut schedules = self.schedules.write().await;
-        if let Some(config) = schedules.get_mut(&workflow_id) {
-            config.enabled = false;
-            Ok(())
-        } else {
-            Err(WorkflowError::NodeNotFound(format!("Schedule not found for workflow {}", workflow_id)))
+        let Some(config) = schedules.get_mut(&workflow_id) else {
+            return Err(WorkflowError::NodeNotFound(format!("Schedule not found for workflow {}", workflow_id)));
+        };
+        config.enabled = enabled;
+        let config = config.clone();
+        drop(schedules);
+
+        if matches!(config.schedule_type, ScheduleType::Once { .. }) {
+            return self.set_one_off_enabled(workflow_id, enabled).await;
+        }
+        match &self.store {
+            Some(store) => store.save_schedule(&config).await,
+            None => Ok(()),
         }
     }
 }
 
+/// Cron runs after `after` up to and including `now`, oldest first; at most
+/// `MAX_CATCH_UP_RUNS` of them plus the number of older runs dropped
+fn due_runs(
+    expression: &CronExpression,
+    timezone: Tz,
+    after: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> (Vec<DateTime<Utc>>, usize) {
+    let mut runs = VecDeque::new();
+    let mut dropped = 0;
+    let mut cursor = after.with_timezone(&timezone);
+    while let Some(next) = expression.next_after(&cursor) {
+        let run = next.with_timezone(&Utc);
+        if run > now {
+            break;
+        }
+        runs.push_back(run);
+        if runs.len() > MAX_CATCH_UP_RUNS {
+            runs.pop_front();
+            dropped += 1;
+        }
+        cursor = next;
+    }
+    (runs.into(), dropped)
+}
+
 impl Default for WorkflowScheduler {
     fn default() -> Self {
         Self::new(Arc::new(WorkflowExecutor::new()))
@@ -281,19 +1094,6 @@ impl Default for WorkflowScheduler {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_cron_field_matching() {
-        assert!(WorkflowScheduler::matches_cron_field("*", 5));
-        assert!(WorkflowScheduler::matches_cron_field("5", 5));
-        assert!(!WorkflowScheduler::matches_cron_field("5", 6));
-        assert!(WorkflowScheduler::matches_cron_field("1-5", 3));
-        assert!(!WorkflowScheduler::matches_cron_field("1-5", 6));
-        assert!(WorkflowScheduler::matches_cron_field("1,3,5", 3));
-        assert!(!WorkflowScheduler::matches_cron_field("1,3,5", 2));
-        assert!(WorkflowScheduler::matches_cron_field("*/5", 10));
-        assert!(!WorkflowScheduler::matches_cron_field("*/5", 11));
-    }
-
     #[tokio::test]
     async fn test_add_remove_schedule() {
         let executor = Arc::new(WorkflowExecutor::new());
@@ -302,7 +1102,7 @@ mod tests {
         let workflow_id = Uuid::new_v4();
         let config = ScheduleConfig {
             workflow_id,
-            schedule_type: ScheduleType::Cron("0 0 * * *".to_string()),
+            schedule_type: ScheduleType::Cron(CronSchedule::new("0 0 * * *")),
             enabled: true,
         };
 
@@ -327,7 +1127,7 @@ mod tests {
         let workflow_id = Uuid::new_v4();
         let config = ScheduleConfig {
             workflow_id,
-            schedule_type: ScheduleType::Cron("0 0 * * *".to_string()),
+            schedule_type: ScheduleType::Cron(CronSchedule::new("0 0 * * *")),
             enabled: true,
         };
 
@@ -345,4 +1145,270 @@ mod tests {
         let schedules = scheduler.get_schedules().await;
         assert!(schedules.get(&workflow_id).unwrap().enabled);
     }
+
+    #[tokio::test]
+    async fn test_run_at_persists_fires_once_and_cleans_up() {
+        use common::types::{Node, NodeConfig, NodeType, Position, TriggerType, WORKFLOW_SCHEMA_VERSION};
+
+        let dir = std::env::temp_dir().join(format!("flowvex-schedules-{}", Uuid::new_v4()));
+        let store = Arc::new(ScheduleStore::new(&dir));
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Weekly report".to_string(),
+            description: None,
+            nodes: vec![Node {
+                id: Uuid::new_v4(),
+                node_type: NodeType::Trigger { trigger_type: TriggerType::Schedule },
+                config: NodeConfig::default(),
+                position: Position { x: 0.0, y: 0.0 },
+                inputs: vec![],
+                outputs: vec![],
+            }],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        };
+        let run_at = Utc::now() + chrono::Duration::hours(1);
+
+        let scheduler = WorkflowScheduler::default().with_store(store.clone());
+        assert!(scheduler.schedule_once(workflow.clone(), Utc::now()).await.is_err());
+        scheduler.schedule_once(workflow.clone(), run_at).await.unwrap();
+
+        // A restarted scheduler picks the schedule up again
+        let restarted = WorkflowScheduler::default().with_store(store.clone());
+        assert_eq!(restarted.restore().await.unwrap(), 1);
+        assert!(restarted.run_due(Utc::now()).await.is_empty());
+        assert_eq!(restarted.run_due(run_at).await.len(), 1);
+        assert!(restarted.run_due(run_at).await.is_empty());
+        assert!(restarted.get_schedules().await.is_empty());
+
+        for _ in 0..50 {
+            if store.load_one_offs().await.unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(store.load_one_offs().await.unwrap().is_empty());
+
+        // Cancelling before it fires removes the persisted copy
+        scheduler.remove_schedule(workflow.id).await.unwrap();
+        scheduler.schedule_once(workflow.clone(), run_at).await.unwrap();
+        scheduler.remove_schedule(workflow.id).await.unwrap();
+        assert!(store.load_one_offs().await.unwrap().is_empty());
+        assert!(scheduler.get_one_off(workflow.id).await.is_none());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_triggered_executions() {
+        use crate::history::ExecutionHistory;
+        use common::types::{Node, NodeConfig, NodeType, Position, TriggerType, WORKFLOW_SCHEMA_VERSION};
+
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Webhook".to_string(),
+            description: None,
+            nodes: vec![Node {
+                id: Uuid::new_v4(),
+                node_type: NodeType::Trigger { trigger_type: TriggerType::Webhook },
+                config: NodeConfig::default(),
+                position: Position { x: 0.0, y: 0.0 },
+                inputs: vec![],
+                outputs: vec![],
+            }],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        };
+        let history = Arc::new(ExecutionHistory::default());
+        let executor = Arc::new(WorkflowExecutor::new().with_history(history.clone()));
+        let scheduler = WorkflowScheduler::new(executor.clone());
+        scheduler.start().await.unwrap();
+
+        let execution_id = scheduler.trigger_webhook(&workflow, serde_json::json!({}), HashMap::new()).await.unwrap();
+        assert!(scheduler.shutdown(Duration::from_secs(5)).await.unwrap());
+        assert!(history.get(execution_id).await.is_some());
+        // The tick loop is gone and no new runs start
+        assert!(executor.tasks().is_empty());
+        assert!(matches!(
+            scheduler.trigger_webhook(&workflow, serde_json::json!({}), HashMap::new()).await,
+            Err(WorkflowError::QueueFull(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cron_catch_up_policies() {
+        let dir = std::env::temp_dir().join(format!("flowvex-schedules-{}", Uuid::new_v4()));
+        let store = Arc::new(ScheduleStore::new(&dir));
+        let now: DateTime<Utc> = "2024-01-01T12:00:30Z".parse().unwrap();
+        // Down since 8:30: the 9, 10 and 11 o'clock runs were missed, 12 o'clock is on time
+        let down_since: DateTime<Utc> = "2024-01-01T08:30:00Z".parse().unwrap();
+
+        let fired = |policy| {
+            let store = store.clone();
+            async move {
+                let scheduler = WorkflowScheduler::default().with_store(store.clone());
+                let workflow_id = Uuid::new_v4();
+                scheduler.register_workflow(Workflow {
+                    version: common::types::WORKFLOW_SCHEMA_VERSION,
+                    id: workflow_id,
+                    name: "Hourly".to_string(),
+                    description: None,
+                    nodes: vec![],
+                    edges: vec![],
+                    variables: HashMap::new(),
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                    trash: Default::default(),
+                }).await;
+                store.save_checkpoint(&ScheduleCheckpoint { workflow_id, checked_until: down_since }).await.unwrap();
+                scheduler.add_schedule(ScheduleConfig {
+                    workflow_id,
+                    schedule_type: ScheduleType::Cron(CronSchedule::new("0 * * * *").with_catch_up(policy)),
+                    enabled: true,
+                }).await.unwrap();
+
+                let fired = scheduler.run_due_cron(now).await.len();
+                // Handled runs are not fired again, also after a restart
+                assert!(scheduler.run_due_cron(now).await.is_empty());
+                assert_eq!(store.load_checkpoint(workflow_id).await.unwrap().unwrap().checked_until, now);
+                fired
+            }
+        };
+
+        assert_eq!(fired(CatchUpPolicy::Skip).await, 1);
+        assert_eq!(fired(CatchUpPolicy::FireOnce).await, 2);
+        assert_eq!(fired(CatchUpPolicy::FireAll).await, 4);
+
+        let scheduler = WorkflowScheduler::default();
+        let invalid = scheduler.add_schedule(ScheduleConfig {
+            workflow_id: Uuid::new_v4(),
+            schedule_type: ScheduleType::Cron(CronSchedule::new("61 * * * *")),
+            enabled: true,
+        }).await;
+        assert!(invalid.is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_interval_schedule_keeps_cadence_across_restarts() {
+        let dir = std::env::temp_dir().join(format!("flowvex-schedules-{}", Uuid::new_v4()));
+        let store = Arc::new(ScheduleStore::new(&dir));
+        let workflow_id = Uuid::new_v4();
+        let config = ScheduleConfig {
+            workflow_id,
+            schedule_type: ScheduleType::Interval(Duration::from_secs(3600)),
+            enabled: true,
+        };
+        let workflow = Workflow {
+            version: common::types::WORKFLOW_SCHEMA_VERSION,
+            id: workflow_id,
+            name: "Hourly sync".to_string(),
+            description: None,
+            nodes: vec![],
+            edges: vec![],
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        };
+        let scheduler = WorkflowScheduler::default().with_store(store.clone());
+        scheduler.register_workflow(workflow.clone()).await;
+        scheduler.add_schedule(config.clone()).await.unwrap();
+
+        let first = scheduler.next_fire_time(workflow_id).await.unwrap();
+        assert!(scheduler.run_due_interval(first - chrono::Duration::seconds(1)).await.is_empty());
+        assert_eq!(scheduler.run_due_interval(first).await.len(), 1);
+        assert!(scheduler.run_due_interval(first).await.is_empty());
+        let second = first + chrono::Duration::hours(1);
+        assert_eq!(scheduler.next_fire_time(workflow_id).await, Some(second));
+
+        // A restarted scheduler resumes the cadence, and runs overdue after a
+        // long outage collapse into one
+        let restarted = WorkflowScheduler::default().with_store(store.clone());
+        restarted.register_workflow(workflow).await;
+        restarted.add_schedule(config).await.unwrap();
+        assert_eq!(restarted.next_fire_time(workflow_id).await, Some(second));
+        let later = second + chrono::Duration::minutes(5 * 60 + 30);
+        assert_eq!(restarted.run_due_interval(later).await.len(), 1);
+        assert_eq!(restarted.next_fire_time(workflow_id).await, Some(second + chrono::Duration::hours(6)));
+
+        restarted.disable_schedule(workflow_id).await.unwrap();
+        assert!(restarted.next_fire_time(workflow_id).await.is_none());
+        restarted.remove_schedule(workflow_id).await.unwrap();
+        assert!(store.load_checkpoint(workflow_id).await.unwrap().is_none());
+
+        let invalid = restarted.add_schedule(ScheduleConfig {
+            workflow_id,
+            schedule_type: ScheduleType::Interval(Duration::ZERO),
+            enabled: true,
+        }).await;
+        assert!(invalid.is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_replicas_share_schedules_through_the_store() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        struct FlagLock(AtomicBool);
+
+        #[async_trait]
+        impl LeaderLock for FlagLock {
+            async fn try_acquire(&self) -> Result<bool, WorkflowError> {
+                Ok(self.0.load(Ordering::SeqCst))
+            }
+
+            async fn release(&self) -> Result<(), WorkflowError> {
+                self.0.store(false, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("flowvex-schedules-{}", Uuid::new_v4()));
+        let store = Arc::new(ScheduleStore::new(&dir));
+        let lock = Arc::new(FlagLock(AtomicBool::new(false)));
+        let replica = WorkflowScheduler::default().with_store(store.clone());
+        let leader = WorkflowScheduler::default().with_store(store.clone()).with_leader_lock(lock.clone());
+        assert!(!leader.is_leader().await);
+        lock.0.store(true, Ordering::SeqCst);
+        assert!(leader.is_leader().await);
+
+        let workflow_id = Uuid::new_v4();
+        replica.add_schedule(ScheduleConfig {
+            workflow_id,
+            schedule_type: ScheduleType::Cron(CronSchedule::new("0 * * * *").with_timezone(chrono_tz::Europe::Paris)),
+            enabled: true,
+        }).await.unwrap();
+        let first = replica.next_fire_time(workflow_id).await.unwrap();
+        replica.run_due_cron(first).await;
+
+        // The leader picks up the schedule with the progress made elsewhere
+        assert_eq!(leader.restore().await.unwrap(), 1);
+        let next = leader.next_fire_time(workflow_id).await.unwrap();
+        assert_eq!(next, first + chrono::Duration::hours(1));
+        assert_eq!(replica.next_fire_time(workflow_id).await, Some(next));
+
+        replica.disable_schedule(workflow_id).await.unwrap();
+        leader.restore().await.unwrap();
+        assert!(!leader.get_schedule(workflow_id).await.unwrap().enabled);
+
+        replica.remove_schedule(workflow_id).await.unwrap();
+        assert_eq!(leader.restore().await.unwrap(), 0);
+        assert!(leader.get_schedule(workflow_id).await.is_none());
+
+        leader.stop().await.unwrap();
+        assert!(!leader.is_leader().await);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
 }
@@ -0,0 +1,446 @@
+use chrono::{DateTime, Utc};
+use common::types::{ApiRequest, JsonValue};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+use uuid::Uuid;
+
+/// Deepest nesting level fingerprinted; deeper fields are ignored
+const MAX_DEPTH: usize = 8;
+
+/// Most field paths recorded per response
+const MAX_FIELDS: usize = 500;
+
+/// An integration action whose responses are fingerprinted
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ActionKey {
+    pub provider: String,
+    /// e.g. `GET /v1/customers/{id}`
+    pub action: String,
+}
+
+impl ActionKey {
+    /// Key of an HTTP action: the request's provider, and its method and URL
+    /// path with ID-like segments generalized, so calls for different
+    /// records share a fingerprint
+    pub fn for_http(request: &ApiRequest) -> Self {
+        let rest = request.endpoint.split_once("://").map(|(_, rest)| rest).unwrap_or(&request.endpoint);
+        let path = rest.split(['?', '#']).next().unwrap_or(rest);
+        let path = path.find('/').map(|at| &path[at..]).unwrap_or("/");
+        let segments: Vec<&str> = path.split('/')
+            .map(|segment| if is_id_segment(segment) { "{id}" } else { segment })
+            .collect();
+        Self {
+            provider: request.provider.clone(),
+            action: format!("{:?} {}", request.method, segments.join("/")),
+        }
+    }
+}
+
+fn is_id_segment(segment: &str) -> bool {
+    !segment.is_empty()
+        && (segment.chars().all(|c| c.is_ascii_digit())
+            || Uuid::parse_str(segment).is_ok()
+            || (segment.len() >= 16 && segment.chars().any(|c| c.is_ascii_digit())))
+}
+
+/// Field paths of a response with the JSON types seen at each. Object
+/// fields are joined with `.`, array items share the path `[]`, and `$` is
+/// the response itself.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResponseShape {
+    pub fields: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl ResponseShape {
+    pub fn of(value: &JsonValue) -> Self {
+        let mut shape = Self::default();
+        shape.add("$", value, 0);
+        shape
+    }
+
+    fn add(&mut self, path: &str, value: &JsonValue, depth: usize) {
+        if self.fields.len() >= MAX_FIELDS && !self.fields.contains_key(path) {
+            return;
+        }
+        self.fields.entry(path.to_string()).or_default().insert(type_name(value).to_string());
+        if depth >= MAX_DEPTH {
+            return;
+        }
+        match value {
+            JsonValue::Object(map) => {
+                for (key, child) in map {
+                    let child_path = if path == "$" { key.clone() } else { format!("{}.{}", path, key) };
+                    self.add(&child_path, child, depth + 1);
+                }
+            }
+            JsonValue::Array(items) => {
+                let item_path = if path == "$" { "[]".to_string() } else { format!("{}[]", path) };
+                for item in items {
+                    self.add(&item_path, item, depth + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Stable hash of the field paths and types (FNV-1a), identifying the
+    /// shape across restarts
+    pub fn fingerprint(&self) -> String {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for (path, types) in &self.fields {
+            for byte in path.bytes().chain([b'='])
+                .chain(types.iter().flat_map(|t| t.bytes().chain([b','])))
+                .chain([b';'])
+            {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        format!("{:016x}", hash)
+    }
+}
+
+fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+/// How much history a fingerprint is built from and what counts as drift
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriftPolicy {
+    /// Responses kept per action; older ones roll out of the fingerprint
+    pub window: usize,
+    /// Responses needed before drift is reported
+    pub min_samples: usize,
+    /// Share of the window a field must appear in to be expected; rarer
+    /// fields are treated as optional and never reported missing
+    pub stable_ratio: f64,
+    /// Also warn when responses only gain fields
+    pub report_additions: bool,
+}
+
+impl Default for DriftPolicy {
+    fn default() -> Self {
+        Self {
+            window: 20,
+            min_samples: 5,
+            stable_ratio: 0.9,
+            report_additions: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FieldDrift {
+    /// A field that was in (nearly) every response is gone
+    Removed { path: String },
+    /// A field no earlier response in the window had
+    Added { path: String },
+    /// A field holds a type it never had before; null is always allowed
+    TypeChanged { path: String, before: Vec<String>, after: Vec<String> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftSeverity {
+    /// Fields were removed or changed type; workflows reading them will fail
+    Breaking,
+    /// Only new fields appeared
+    Additive,
+}
+
+/// A workflow node calling a drifting action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AffectedNode {
+    pub workflow_id: Uuid,
+    pub node_id: Uuid,
+}
+
+/// Drift detected in the responses of an action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriftWarning {
+    pub id: Uuid,
+    pub key: ActionKey,
+    pub severity: DriftSeverity,
+    pub changes: Vec<FieldDrift>,
+    /// Every workflow node seen calling the action
+    pub affected: Vec<AffectedNode>,
+    /// Fingerprint of the latest response matching the old shape
+    pub fingerprint_before: String,
+    /// Fingerprint of the drifted response
+    pub fingerprint_after: String,
+    pub detected_at: DateTime<Utc>,
+    /// Responses with this drift seen since the warning was raised
+    pub occurrences: u32,
+    pub acknowledged: bool,
+}
+
+#[derive(Default)]
+struct ActionHistory {
+    samples: VecDeque<ResponseShape>,
+    callers: HashSet<AffectedNode>,
+}
+
+/// Records a rolling fingerprint of the responses of each integration
+/// action and warns the workflows calling it when responses drift from it
+pub struct SchemaDriftDetector {
+    policy: DriftPolicy,
+    actions: Arc<RwLock<HashMap<ActionKey, ActionHistory>>>,
+    warnings: Arc<RwLock<Vec<DriftWarning>>>,
+    warning_tx: broadcast::Sender<DriftWarning>,
+}
+
+impl SchemaDriftDetector {
+    pub fn new(policy: DriftPolicy) -> Self {
+        let (warning_tx, _) = broadcast::channel(100);
+        Self {
+            policy,
+            actions: Arc::new(RwLock::new(HashMap::new())),
+            warnings: Arc::new(RwLock::new(Vec::new())),
+            warning_tx,
+        }
+    }
+
+    /// Add a response of an action called by a workflow node to the action's
+    /// fingerprint. Returns the warning raised if the response drifted; a
+    /// drift already warned about and not yet acknowledged only counts as
+    /// another occurrence.
+    pub async fn observe(&self, key: ActionKey, caller: AffectedNode, response: &JsonValue) -> Option<DriftWarning> {
+        let shape = ResponseShape::of(response);
+        let (changes, fingerprint_before, affected) = {
+            let mut actions = self.actions.write().await;
+            let history = actions.entry(key.clone()).or_default();
+            history.callers.insert(caller);
+
+            let changes = if history.samples.len() >= self.policy.min_samples.max(1) {
+                self.compare(&history.samples, &shape)
+            } else {
+                Vec::new()
+            };
+            let fingerprint_before = history.samples.back().map(ResponseShape::fingerprint).unwrap_or_default();
+            let mut affected: Vec<AffectedNode> = history.callers.iter().copied().collect();
+            affected.sort_by_key(|node| (node.workflow_id, node.node_id));
+
+            history.samples.push_back(shape.clone());
+            while history.samples.len() > self.policy.window.max(1) {
+                history.samples.pop_front();
+            }
+            (changes, fingerprint_before, affected)
+        };
+
+        // The drifted shape soon becomes part of the fingerprint itself, so
+        // repeats are matched to their open warning by fingerprint
+        let fingerprint_after = shape.fingerprint();
+        let mut warnings = self.warnings.write().await;
+        if let Some(existing) = warnings.iter_mut()
+            .find(|w| !w.acknowledged && w.key == key && w.fingerprint_after == fingerprint_after)
+        {
+            existing.occurrences += 1;
+            existing.affected = affected;
+            return None;
+        }
+
+        let breaking = changes.iter().any(|change| !matches!(change, FieldDrift::Added { .. }));
+        if changes.is_empty() || !(breaking || self.policy.report_additions) {
+            return None;
+        }
+
+        let warning = DriftWarning {
+            id: Uuid::new_v4(),
+            key,
+            severity: if breaking { DriftSeverity::Breaking } else { DriftSeverity::Additive },
+            changes,
+            affected,
+            fingerprint_before,
+            fingerprint_after,
+            detected_at: Utc::now(),
+            occurrences: 1,
+            acknowledged: false,
+        };
+        tracing::warn!(
+            "Response schema of {} {} drifted ({} changes)",
+            warning.key.provider, warning.key.action, warning.changes.len()
+        );
+        // Nobody listening is fine; the warning is kept
+        let _ = self.warning_tx.send(warning.clone());
+        warnings.push(warning.clone());
+        Some(warning)
+    }
+
+    /// Differences between a response and the fields expected from the window
+    fn compare(&self, samples: &VecDeque<ResponseShape>, shape: &ResponseShape) -> Vec<FieldDrift> {
+        let mut seen: BTreeMap<&str, (usize, BTreeSet<&str>)> = BTreeMap::new();
+        for sample in samples {
+            for (path, types) in &sample.fields {
+                let (count, known) = seen.entry(path.as_str()).or_default();
+                *count += 1;
+                known.extend(types.iter().map(String::as_str));
+            }
+        }
+        let expected = (samples.len() as f64 * self.policy.stable_ratio).ceil() as usize;
+
+        let mut changes = Vec::new();
+        for (path, (count, _)) in &seen {
+            if *count >= expected && !shape.fields.contains_key(*path) {
+                changes.push(FieldDrift::Removed { path: path.to_string() });
+            }
+        }
+        for (path, types) in &shape.fields {
+            match seen.get(path.as_str()) {
+                None => changes.push(FieldDrift::Added { path: path.clone() }),
+                Some((_, known)) => {
+                    let unknown = types.iter().any(|t| t != "null" && !known.contains(t.as_str()));
+                    if unknown {
+                        changes.push(FieldDrift::TypeChanged {
+                            path: path.clone(),
+                            before: known.iter().map(|t| t.to_string()).collect(),
+                            after: types.iter().cloned().collect(),
+                        });
+                    }
+                }
+            }
+        }
+        collapse_children(changes)
+    }
+
+    /// Open warnings affecting a workflow, newest first; acknowledged ones too
+    /// when `include_acknowledged`
+    pub async fn warnings_for_workflow(&self, workflow_id: Uuid, include_acknowledged: bool) -> Vec<DriftWarning> {
+        let warnings = self.warnings.read().await;
+        warnings.iter()
+            .rev()
+            .filter(|w| include_acknowledged || !w.acknowledged)
+            .filter(|w| w.affected.iter().any(|node| node.workflow_id == workflow_id))
+            .cloned()
+            .collect()
+    }
+
+    pub async fn get_warning(&self, warning_id: Uuid) -> Option<DriftWarning> {
+        self.warnings.read().await.iter().find(|w| w.id == warning_id).cloned()
+    }
+
+    /// Mark a warning as handled; the same drift is not reported again while
+    /// the fingerprint catches up
+    pub async fn acknowledge(&self, warning_id: Uuid) -> Option<DriftWarning> {
+        let mut warnings = self.warnings.write().await;
+        let warning = warnings.iter_mut().find(|w| w.id == warning_id)?;
+        warning.acknowledged = true;
+        Some(warning.clone())
+    }
+
+    /// Receive warnings as they are raised (e.g. to notify workflow owners)
+    pub fn subscribe(&self) -> broadcast::Receiver<DriftWarning> {
+        self.warning_tx.subscribe()
+    }
+}
+
+impl Default for SchemaDriftDetector {
+    fn default() -> Self {
+        Self::new(DriftPolicy::default())
+    }
+}
+
+/// Report a removed or added object once rather than each of its fields
+fn collapse_children(changes: Vec<FieldDrift>) -> Vec<FieldDrift> {
+    let parent_of = |change: &FieldDrift| match change {
+        FieldDrift::Removed { path } => Some((0, path.clone())),
+        FieldDrift::Added { path } => Some((1, path.clone())),
+        FieldDrift::TypeChanged { .. } => None,
+    };
+    let parents: HashSet<(u8, String)> = changes.iter().filter_map(parent_of).collect();
+    changes.into_iter()
+        .filter(|change| match parent_of(change) {
+            Some((kind, path)) => !ancestors(&path).any(|ancestor| parents.contains(&(kind, ancestor.to_string()))),
+            None => true,
+        })
+        .collect()
+}
+
+/// `a.b[].c` -> `a.b[]`, `a.b`, `a`
+fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    path.char_indices()
+        .rev()
+        .filter_map(move |(at, c)| match c {
+            '.' | '[' => Some(&path[..at]),
+            _ => None,
+        })
+        .filter(|ancestor| !ancestor.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> ActionKey {
+        ActionKey { provider: "billing".to_string(), action: "GET /v1/invoices".to_string() }
+    }
+
+    #[test]
+    fn test_action_key_and_shape() {
+        let request = ApiRequest {
+            id: Uuid::new_v4(),
+            provider: "stripe".to_string(),
+            endpoint: format!("https://api.stripe.com/v1/customers/cus_9s6XKzkNRiz8i3/charges/42?expand={}", Uuid::new_v4()),
+            method: common::types::HttpMethod::GET,
+            headers: HashMap::new(),
+            body: None,
+            priority: common::types::Priority::Normal,
+            workflow_id: Uuid::new_v4(),
+            node_id: Uuid::new_v4(),
+            organization_id: None,
+            timeout: std::time::Duration::from_secs(1),
+            retry_config: Default::default(),
+        };
+        assert_eq!(ActionKey::for_http(&request).action, "GET /v1/customers/{id}/charges/{id}");
+
+        let shape = ResponseShape::of(&serde_json::json!({ "data": [{ "id": 1 }, { "id": "a", "note": null }] }));
+        let paths: Vec<&str> = shape.fields.keys().map(String::as_str).collect();
+        assert_eq!(paths, ["$", "data", "data[]", "data[].id", "data[].note"]);
+        assert_eq!(shape.fields["data[].id"].len(), 2);
+        assert_eq!(shape.fingerprint(), ResponseShape::of(&serde_json::json!({ "data": [{ "id": "b", "note": null }, { "id": 2 }] })).fingerprint());
+    }
+
+    #[tokio::test]
+    async fn test_drift_warning() {
+        let detector = SchemaDriftDetector::new(DriftPolicy { window: 10, min_samples: 3, ..Default::default() });
+        let (first, second) = (
+            AffectedNode { workflow_id: Uuid::new_v4(), node_id: Uuid::new_v4() },
+            AffectedNode { workflow_id: Uuid::new_v4(), node_id: Uuid::new_v4() },
+        );
+        let before = serde_json::json!({ "total": 10, "customer": { "id": "c1", "email": "a@example.com" }, "memo": "x" });
+        for index in 0..4 {
+            let caller = if index % 2 == 0 { first } else { second };
+            assert!(detector.observe(key(), caller, &before).await.is_none());
+        }
+        // A field only some responses carry is reported as added, never as removed
+        let mut optional = before.clone();
+        optional["coupon"] = serde_json::json!("SAVE");
+        detector.observe(key(), first, &optional).await.unwrap();
+
+        let after = serde_json::json!({ "total": "10.00", "memo": "x" });
+        let warning = detector.observe(key(), first, &after).await.unwrap();
+        assert_eq!(warning.severity, DriftSeverity::Breaking);
+        assert_eq!(warning.changes, [
+            FieldDrift::Removed { path: "customer".to_string() },
+            FieldDrift::TypeChanged { path: "total".to_string(), before: vec!["number".to_string()], after: vec!["string".to_string()] },
+        ]);
+        assert_eq!(warning.affected.len(), 2);
+        assert!(detector.observe(key(), first, &after).await.is_none());
+        assert_eq!(detector.warnings_for_workflow(second.workflow_id, false).await[0].occurrences, 2);
+
+        detector.acknowledge(warning.id).await.unwrap();
+        let open = detector.warnings_for_workflow(second.workflow_id, false).await;
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].changes, [FieldDrift::Added { path: "coupon".to_string() }]);
+        assert_eq!(detector.warnings_for_workflow(second.workflow_id, true).await.len(), 2);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::ai::AiGenerator;
+use ai_service::ModelType;
+use async_trait::async_trait;
+use common::types::JsonValue;
+use scraper_service::{
+    ProposedSelector, RepairRequest, ScraperAction, ScraperError, ScraperRequest, SelectorRepairer,
+};
+use std::sync::Arc;
+
+/// Parameters of a scraper node that are not fields of the action
+const REQUEST_PARAMETERS: [&str; 3] = ["action", "config", "context_id"];
+
+/// Build the scraper request of a scraper node from its rendered parameters.
+///
+/// `action` is either the action type (`openPage`, `getText`, ...) with the
+/// action fields as sibling parameters, or a full action object with a `type`
+/// field. `config` is passed through to the scraper. `context_id` selects a
+/// browser context explicitly; otherwise the execution's current context is used.
+pub fn build_request(
+    parameters: &JsonValue,
+    current_context: Option<String>,
+) -> Result<ScraperRequest, String> {
+    let action = match parameters.get("action") {
+        Some(JsonValue::String(action_type)) => {
+            let mut action = serde_json::Map::new();
+            action.insert("type".to_string(), JsonValue::String(action_type.clone()));
+            if let Some(fields) = parameters.as_object() {
+                for (key, value) in fields {
+                    if !REQUEST_PARAMETERS.contains(&key.as_str()) {
+                        action.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            JsonValue::Object(action)
+        }
+        Some(action @ JsonValue::Object(_)) => action.clone(),
+        Some(_) => return Err("action must be a string or an object".to_string()),
+        None => return Err("missing action parameter".to_string()),
+    };
+    let action: ScraperAction = serde_json::from_value(action).map_err(|e| format!("invalid action: {}", e))?;
+
+    let context_id = parameters.get("context_id")
+        .and_then(JsonValue::as_str)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .or(current_context);
+
+    Ok(ScraperRequest {
+        action,
+        context_id,
+        config: parameters.get("config").cloned().unwrap_or_else(|| serde_json::json!({})),
+    })
+}
+
+/// Proposes replacement selectors for scraper actions by asking an AI model
+/// to pick the intended element from the page outline
+pub struct AiSelectorRepairer {
+    ai: Arc<dyn AiGenerator>,
+    model: Option<ModelType>,
+}
+
+impl AiSelectorRepairer {
+    pub fn new(ai: Arc<dyn AiGenerator>) -> Self {
+        Self { ai, model: None }
+    }
+
+    /// Model used for proposals; the AI node default otherwise
+    pub fn with_model(mut self, model: ModelType) -> Self {
+        self.model = Some(model);
+        self
+    }
+}
+
+#[async_trait]
+impl SelectorRepairer for AiSelectorRepairer {
+    async fn propose(&self, request: &RepairRequest) -> Result<ProposedSelector, ScraperError> {
+        let mut parameters = serde_json::json!({
+            "prompt": repair_prompt(request),
+            "temperature": 0.0,
+        });
+        if let Some(model) = &self.model {
+            parameters["model"] = serde_json::to_value(model).map_err(|e| ScraperError::Internal(e.to_string()))?;
+        }
+        let output = self.ai.generate(&parameters, None).await.map_err(ScraperError::SelectorRepairFailed)?;
+        let reply = output.get("ai_response").and_then(JsonValue::as_str).unwrap_or_default();
+        parse_proposal(reply)
+            .ok_or_else(|| ScraperError::SelectorRepairFailed(format!("no selector in model reply: {}", reply)))
+    }
+}
+
+fn repair_prompt(request: &RepairRequest) -> String {
+    let mut prompt = format!(
+        "The {:?} selector `{}` no longer matches any element on {}.\n",
+        request.find_by, request.selector, request.url,
+    );
+    if !request.tried.is_empty() {
+        prompt.push_str(&format!("These alternatives did not match either: {}\n", request.tried.join(", ")));
+    }
+    prompt.push_str(
+        "Below is an outline of the page, one element per line, indented by depth, \
+         with ids, classes, key attributes and the element's own text.\n\
+         Propose one replacement selector of the same kind for the element the original selector most likely targeted. \
+         Prefer ids, data-testid and other stable attributes over positions. \
+         Reply with only a JSON object: {\"selector\": \"...\", \"reason\": \"...\"}\n\n",
+    );
+    prompt.push_str(&request.outline);
+    prompt
+}
+
+/// Read the proposal from the model reply: a JSON object, possibly inside a
+/// code fence, or else a bare selector on the first non-empty line
+fn parse_proposal(reply: &str) -> Option<ProposedSelector> {
+    if let (Some(start), Some(end)) = (reply.find('{'), reply.rfind('}')) {
+        if let Ok(proposal) = serde_json::from_str::<ProposedSelector>(&reply[start..=end]) {
+            return Some(proposal).filter(|p| !p.selector.trim().is_empty());
+        }
+    }
+    reply.lines()
+        .map(|line| line.trim().trim_matches('`').trim())
+        .find(|line| !line.is_empty())
+        .map(|selector| ProposedSelector { selector: selector.to_string(), reason: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_request() {
+        let parameters = serde_json::json!({
+            "action": "getText",
+            "selector": "h1",
+            "config": { "multiple": true }
+        });
+        let request = build_request(&parameters, Some("ctx-1".to_string())).unwrap();
+        assert!(matches!(request.action, ScraperAction::GetText { ref selector, .. } if selector == "h1"));
+        assert_eq!(request.context_id.as_deref(), Some("ctx-1"));
+        assert_eq!(request.config["multiple"], true);
+
+        let parameters = serde_json::json!({
+            "action": { "type": "openPage", "url": "https://example.com" },
+            "context_id": "ctx-2"
+        });
+        let request = build_request(&parameters, Some("ctx-1".to_string())).unwrap();
+        assert!(matches!(request.action, ScraperAction::OpenPage { .. }));
+        assert_eq!(request.context_id.as_deref(), Some("ctx-2"));
+
+        assert!(build_request(&serde_json::json!({}), None).is_err());
+        assert!(build_request(&serde_json::json!({ "action": "fly" }), None).is_err());
+    }
+
+    struct ReplyingAi(&'static str);
+
+    #[async_trait]
+    impl AiGenerator for ReplyingAi {
+        async fn generate(
+            &self,
+            parameters: &JsonValue,
+            _deltas: Option<tokio::sync::mpsc::UnboundedSender<String>>,
+        ) -> Result<JsonValue, String> {
+            let prompt = parameters["prompt"].as_str().unwrap();
+            assert!(prompt.contains("`.price`") && prompt.contains(".cost-value") && prompt.contains("span.amount"));
+            assert_eq!(parameters["model"], "claude-3-sonnet");
+            Ok(serde_json::json!({ "ai_response": self.0 }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ai_selector_repairer() {
+        let request = RepairRequest {
+            url: "https://shop.example.com/item/1".to_string(),
+            selector: ".price".to_string(),
+            find_by: Default::default(),
+            tried: vec![".cost-value".to_string()],
+            outline: "body\n  span.amount \"$20\"".to_string(),
+        };
+        let repair = |reply| AiSelectorRepairer::new(Arc::new(ReplyingAi(reply))).with_model(ModelType::Claude3Sonnet);
+
+        let proposal = repair("```json\n{\"selector\": \"span.amount\", \"reason\": \"holds the price\"}\n```")
+            .propose(&request).await.unwrap();
+        assert_eq!(proposal.selector, "span.amount");
+        assert_eq!(proposal.reason.as_deref(), Some("holds the price"));
+
+        let proposal = repair("\n`div.cost > span`\n").propose(&request).await.unwrap();
+        assert_eq!(proposal.selector, "div.cost > span");
+
+        let error = repair("  ").propose(&request).await.unwrap_err();
+        assert_eq!(error.code(), "SCRAPER_021");
+    }
+}
@@ -0,0 +1,189 @@
+use ai_service::ScreeningPolicy;
+use common::types::JsonValue;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Headers and environment variables injected into nodes of every workflow
+/// in an organization: headers into HTTP actions, environment variables into
+/// custom nodes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InjectionPolicy {
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Replace values the node sets itself; by default the node's value wins
+    #[serde(default)]
+    pub override_node_values: bool,
+    /// Workflows that opted out of injection
+    #[serde(default)]
+    pub opt_out: HashSet<Uuid>,
+}
+
+/// What an injection did to a node, reported in the node output
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Injection {
+    pub headers: Vec<String>,
+    pub env: Vec<String>,
+    /// Names the node already set, left untouched
+    pub skipped: Vec<String>,
+}
+
+impl InjectionPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.env.is_empty()
+    }
+
+    pub fn applies_to(&self, workflow_id: Uuid) -> bool {
+        !self.is_empty() && !self.opt_out.contains(&workflow_id)
+    }
+
+    /// Add the policy headers to the `headers` parameter of an HTTP action.
+    /// Header names are matched case-insensitively.
+    pub fn apply_headers(&self, parameters: &mut JsonValue) -> Injection {
+        let mut injection = Injection::default();
+        let Some(parameters) = parameters.as_object_mut() else {
+            return injection;
+        };
+        let headers = parameters.entry("headers").or_insert_with(|| JsonValue::Object(Default::default()));
+        if headers.is_null() {
+            *headers = JsonValue::Object(Default::default());
+        }
+        // Malformed headers are reported by the request builder
+        let Some(headers) = headers.as_object_mut() else {
+            return injection;
+        };
+
+        for (name, value) in sorted(&self.headers) {
+            let existing = headers.keys().find(|k| k.eq_ignore_ascii_case(name)).cloned();
+            match existing {
+                Some(_) if !self.override_node_values => {
+                    injection.skipped.push(name.clone());
+                    continue;
+                }
+                Some(key) => {
+                    headers.remove(&key);
+                }
+                None => {}
+            }
+            headers.insert(name.clone(), JsonValue::String(value.clone()));
+            injection.headers.push(name.clone());
+        }
+        injection
+    }
+
+    /// Add the policy environment variables to a custom node's environment
+    pub fn apply_env(&self, env: &mut HashMap<String, String>) -> Injection {
+        let mut injection = Injection::default();
+        for (name, value) in sorted(&self.env) {
+            if env.contains_key(name) && !self.override_node_values {
+                injection.skipped.push(name.clone());
+                continue;
+            }
+            env.insert(name.clone(), value.clone());
+            injection.env.push(name.clone());
+        }
+        injection
+    }
+}
+
+fn sorted(map: &HashMap<String, String>) -> Vec<(&String, &String)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort();
+    entries
+}
+
+/// Organization-wide execution settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrgSettings {
+    #[serde(default)]
+    pub injection: InjectionPolicy,
+    /// Encrypt node inputs/outputs and other payloads of executions before
+    /// they are stored, see [`crate::encryption::PayloadEncryption`]
+    #[serde(default)]
+    pub encrypt_execution_data: bool,
+    /// Screen content interpolated into AI node prompts for injection
+    /// attempts; without a policy prompts are not screened
+    #[serde(default)]
+    pub prompt_screening: Option<ScreeningPolicy>,
+}
+
+/// Settings of each organization, read by the executor at node execution time
+pub struct OrgSettingsStore {
+    settings: Arc<RwLock<HashMap<Uuid, OrgSettings>>>,
+}
+
+impl OrgSettingsStore {
+    pub fn new() -> Self {
+        Self {
+            settings: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub async fn get(&self, organization_id: Uuid) -> OrgSettings {
+        let settings = self.settings.read().await;
+        settings.get(&organization_id).cloned().unwrap_or_default()
+    }
+
+    pub async fn set(&self, organization_id: Uuid, settings: OrgSettings) {
+        self.settings.write().await.insert(organization_id, settings);
+    }
+
+    /// Opt a workflow out of (or back into) the organization's injection policy
+    pub async fn set_opt_out(&self, organization_id: Uuid, workflow_id: Uuid, opt_out: bool) {
+        let mut settings = self.settings.write().await;
+        let injection = &mut settings.entry(organization_id).or_default().injection;
+        if opt_out {
+            injection.opt_out.insert(workflow_id);
+        } else {
+            injection.opt_out.remove(&workflow_id);
+        }
+    }
+}
+
+impl Default for OrgSettingsStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_headers_and_env() {
+        let mut policy = InjectionPolicy {
+            headers: HashMap::from([
+                ("X-Request-Source".to_string(), "flowvex".to_string()),
+                ("X-Compliance".to_string(), "gdpr".to_string()),
+            ]),
+            env: HashMap::from([("TRACKING_ID".to_string(), "org-7".to_string())]),
+            ..Default::default()
+        };
+
+        let mut parameters = serde_json::json!({ "url": "https://example.com", "headers": { "x-compliance": "none" } });
+        let injection = policy.apply_headers(&mut parameters);
+        assert_eq!(injection.headers, ["X-Request-Source"]);
+        assert_eq!(injection.skipped, ["X-Compliance"]);
+        assert_eq!(parameters["headers"]["x-compliance"], "none");
+
+        policy.override_node_values = true;
+        let injection = policy.apply_headers(&mut parameters);
+        assert_eq!(injection.headers, ["X-Compliance", "X-Request-Source"]);
+        assert_eq!(parameters["headers"]["X-Compliance"], "gdpr");
+        assert!(parameters["headers"].get("x-compliance").is_none());
+
+        let mut env = HashMap::new();
+        assert_eq!(policy.apply_env(&mut env).env, ["TRACKING_ID"]);
+        assert_eq!(env["TRACKING_ID"], "org-7");
+
+        let workflow_id = Uuid::new_v4();
+        assert!(policy.applies_to(workflow_id));
+        policy.opt_out.insert(workflow_id);
+        assert!(!policy.applies_to(workflow_id));
+    }
+}
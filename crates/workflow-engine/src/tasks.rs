@@ -0,0 +1,137 @@
+use common::trace::TraceContext;
+use futures::FutureExt;
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::Instrument;
+
+/// Keeps track of the background tasks of the engine: executions started by
+/// triggers and requeues, their follow-up work and the tick loops.
+///
+/// Shutdown stops the tick loops and waits for the other tasks to finish, so
+/// an execution started before shutdown is not cut off halfway.
+#[derive(Clone, Default)]
+pub struct TaskSupervisor {
+    tracker: TaskTracker,
+    shutdown: CancellationToken,
+}
+
+impl TaskSupervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn a tracked task. A panic is logged before it reaches the join
+    /// handle, so it is not lost when nobody awaits the handle. The task
+    /// stays in the trace and span of the code spawning it.
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let trace = TraceContext::current();
+        let task = async move {
+            match trace {
+                Some(trace) => trace.scope(task).await,
+                None => task.await,
+            }
+        }
+        .instrument(tracing::Span::current());
+        self.tracker.spawn(async move {
+            match AssertUnwindSafe(task).catch_unwind().await {
+                Ok(output) => output,
+                Err(panic) => {
+                    tracing::error!("Background task panicked: {}", panic_message(panic.as_ref()));
+                    std::panic::resume_unwind(panic)
+                }
+            }
+        })
+    }
+
+    /// Completes once shutdown has begun; loops select on it to stop
+    pub async fn cancelled(&self) {
+        self.shutdown.cancelled().await
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
+
+    /// Number of tracked tasks still running
+    pub fn len(&self) -> usize {
+        self.tracker.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracker.is_empty()
+    }
+
+    /// Begin shutdown and wait up to `timeout` for the tracked tasks,
+    /// including ones spawned while waiting. Returns whether all finished.
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.shutdown.cancel();
+        self.tracker.close();
+        let finished = tokio::time::timeout(timeout, self.tracker.wait()).await.is_ok();
+        if !finished {
+            tracing::warn!("{} background tasks still running after {:?}", self.tracker.len(), timeout);
+        }
+        finished
+    }
+}
+
+/// Text of a panic payload, as passed to `panic!`
+pub fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_tracked_tasks() {
+        let tasks = TaskSupervisor::new();
+        let (done, finished) = tokio::sync::oneshot::channel();
+        tasks.spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let _ = done.send(());
+        });
+        let looping = tasks.clone();
+        let tick_loop = tasks.spawn(async move { looping.cancelled().await });
+
+        assert_eq!(tasks.len(), 2);
+        assert!(tasks.shutdown(Duration::from_secs(5)).await);
+        assert!(finished.await.is_ok());
+        assert!(tick_loop.is_finished());
+        assert!(tasks.is_shutting_down() && tasks.is_empty());
+
+        tasks.spawn(std::future::pending::<()>());
+        assert!(!tasks.shutdown(Duration::from_millis(20)).await);
+    }
+
+    #[tokio::test]
+    async fn test_panics_reach_the_join_handle() {
+        let tasks = TaskSupervisor::new();
+        let error = tasks.spawn(async { panic!("boom") }).await.unwrap_err();
+        assert!(error.is_panic());
+        assert_eq!(panic_message(error.into_panic().as_ref()), "boom");
+    }
+
+    #[tokio::test]
+    async fn test_spawned_tasks_keep_the_trace() {
+        let tasks = TaskSupervisor::new();
+        let trace = TraceContext::new_root();
+        let spawned = trace
+            .clone()
+            .scope(async { tasks.spawn(async { TraceContext::current() }).await })
+            .await;
+        assert_eq!(spawned.unwrap(), Some(trace));
+        assert_eq!(tasks.spawn(async { TraceContext::current() }).await.unwrap(), None);
+    }
+}
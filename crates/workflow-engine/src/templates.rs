@@ -0,0 +1,440 @@
+use chrono::{DateTime, Utc};
+use common::data_type::DataType;
+use common::error::{ErrorCode, ErrorInfo};
+use common::types::{JsonValue, Workflow};
+use common::ParseError;
+use integration_service::sharing::PublicBundle;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::filters::to_text;
+use crate::parser::WorkflowParser;
+use crate::portable::PortableWorkflow;
+
+/// A value supplied when a template is instantiated, referenced in the
+/// template's workflow as `[[ name ]]`.
+///
+/// A string that is only a placeholder is replaced by the value itself, so
+/// numbers, lists and objects keep their type; inside longer strings the
+/// value is inserted as text. The `[[ ]]` brackets keep placeholders apart
+/// from the `{{ }}` expressions evaluated at run time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateParameter {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Values are converted to this type following the port coercion rules
+    #[serde(default = "default_data_type")]
+    pub data_type: DataType,
+    /// Used when no value is supplied; a parameter without one is required
+    #[serde(default)]
+    pub default: Option<JsonValue>,
+}
+
+fn default_data_type() -> DataType {
+    DataType::String
+}
+
+/// Reusable workflow with parameter placeholders
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowTemplate {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub category: String,
+    pub tags: Vec<String>,
+    pub parameters: Vec<TemplateParameter>,
+    /// Credentials are replaced by placeholders bound on instantiation
+    pub workflow: PortableWorkflow,
+    pub author: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Contents of a template as created or edited
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateDraft {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "default_category")]
+    pub category: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub parameters: Vec<TemplateParameter>,
+    pub workflow: Workflow,
+}
+
+fn default_category() -> String {
+    "general".to_string()
+}
+
+/// Category with the number of templates in it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateCategory {
+    pub name: String,
+    pub templates: usize,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("Template not found: {0}")]
+    NotFound(Uuid),
+
+    #[error("Invalid parameter name: {0}")]
+    InvalidName(String),
+
+    #[error("Parameter declared twice: {0}")]
+    DuplicateParameter(String),
+
+    #[error("Placeholder [[ {0} ]] has no parameter")]
+    UndeclaredPlaceholder(String),
+
+    #[error("Missing value for parameter {0}")]
+    MissingValue(String),
+
+    #[error("Unknown parameter {0}")]
+    UnknownParameter(String),
+
+    #[error("Invalid value for parameter {name}: {reason}")]
+    InvalidValue { name: String, reason: String },
+
+    #[error("Instantiated workflow is invalid: {0}")]
+    InvalidWorkflow(#[from] ParseError),
+}
+
+impl ErrorInfo for TemplateError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            TemplateError::NotFound(_) => ErrorCode::NotFound,
+            _ => ErrorCode::InvalidInput,
+        }
+    }
+}
+
+/// Library of workflow templates, instantiated into new workflows
+pub struct TemplateStore {
+    templates: Arc<RwLock<HashMap<Uuid, WorkflowTemplate>>>,
+    parser: WorkflowParser,
+}
+
+impl TemplateStore {
+    pub fn new() -> Self {
+        Self {
+            templates: Arc::new(RwLock::new(HashMap::new())),
+            parser: WorkflowParser::new(),
+        }
+    }
+
+    /// Add a template. Its parameters must cover every placeholder in the
+    /// workflow; credentials are stripped like in a portable export.
+    pub async fn create(&self, draft: TemplateDraft, author: Uuid) -> Result<WorkflowTemplate, TemplateError> {
+        let now = Utc::now();
+        let template = WorkflowTemplate {
+            id: Uuid::new_v4(),
+            name: draft.name,
+            description: draft.description,
+            category: draft.category,
+            tags: draft.tags,
+            workflow: checked_workflow(&draft.workflow, &draft.parameters)?,
+            parameters: draft.parameters,
+            author,
+            created_at: now,
+            updated_at: now,
+        };
+        self.templates.write().await.insert(template.id, template.clone());
+        Ok(template)
+    }
+
+    /// Replace the contents of a template, keeping its ID and author
+    pub async fn update(&self, template_id: Uuid, draft: TemplateDraft) -> Result<WorkflowTemplate, TemplateError> {
+        let workflow = checked_workflow(&draft.workflow, &draft.parameters)?;
+        let mut templates = self.templates.write().await;
+        let template = templates.get_mut(&template_id).ok_or(TemplateError::NotFound(template_id))?;
+        template.name = draft.name;
+        template.description = draft.description;
+        template.category = draft.category;
+        template.tags = draft.tags;
+        template.parameters = draft.parameters;
+        template.workflow = workflow;
+        template.updated_at = Utc::now();
+        Ok(template.clone())
+    }
+
+    pub async fn delete(&self, template_id: Uuid) -> bool {
+        self.templates.write().await.remove(&template_id).is_some()
+    }
+
+    pub async fn get(&self, template_id: Uuid) -> Option<WorkflowTemplate> {
+        self.templates.read().await.get(&template_id).cloned()
+    }
+
+    /// Templates sorted by name, optionally of one category and matching a
+    /// search term in the name, description or tags
+    pub async fn list(&self, category: Option<&str>, search: Option<&str>) -> Vec<WorkflowTemplate> {
+        let search = search.map(str::to_lowercase);
+        let templates = self.templates.read().await;
+        let mut matching: Vec<WorkflowTemplate> = templates.values()
+            .filter(|t| category.is_none_or(|category| t.category.eq_ignore_ascii_case(category)))
+            .filter(|t| search.as_deref().is_none_or(|term| {
+                t.name.to_lowercase().contains(term)
+                    || t.description.as_deref().is_some_and(|d| d.to_lowercase().contains(term))
+                    || t.tags.iter().any(|tag| tag.to_lowercase().contains(term))
+            }))
+            .cloned()
+            .collect();
+        matching.sort_by(|a, b| a.name.cmp(&b.name).then(a.created_at.cmp(&b.created_at)));
+        matching
+    }
+
+    /// Categories in use, sorted by name
+    pub async fn categories(&self) -> Vec<TemplateCategory> {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for template in self.templates.read().await.values() {
+            *counts.entry(template.category.clone()).or_default() += 1;
+        }
+        counts.into_iter().map(|(name, templates)| TemplateCategory { name, templates }).collect()
+    }
+
+    /// Create a workflow from a template with the given parameter values.
+    ///
+    /// The workflow gets new IDs and is validated like an imported one; it
+    /// comes back as a public bundle whose credential placeholders still have
+    /// to be bound.
+    pub async fn instantiate(
+        &self,
+        template_id: Uuid,
+        values: &HashMap<String, JsonValue>,
+        name: Option<String>,
+    ) -> Result<PublicBundle, TemplateError> {
+        let template = self.get(template_id).await.ok_or(TemplateError::NotFound(template_id))?;
+        if let Some(unknown) = values.keys().find(|key| !template.parameters.iter().any(|p| &p.name == *key)) {
+            return Err(TemplateError::UnknownParameter(unknown.clone()));
+        }
+
+        let mut resolved = HashMap::new();
+        for parameter in &template.parameters {
+            let value = values.get(&parameter.name)
+                .or(parameter.default.as_ref())
+                .ok_or_else(|| TemplateError::MissingValue(parameter.name.clone()))?;
+            let value = parameter.data_type.coerce(value).map_err(|reason| TemplateError::InvalidValue {
+                name: parameter.name.clone(),
+                reason,
+            })?;
+            resolved.insert(parameter.name.as_str(), value);
+        }
+
+        let mut workflow = serde_json::to_value(&template.workflow).map_err(|e| ParseError::InvalidJson(e.to_string()))?;
+        substitute(&mut workflow, &resolved);
+        if let Some(name) = name {
+            workflow["name"] = name.into();
+        }
+        Ok(self.parser.import_portable(&workflow.to_string())?)
+    }
+}
+
+impl Default for TemplateStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Portable form of a template workflow after checking its parameters
+fn checked_workflow(workflow: &Workflow, parameters: &[TemplateParameter]) -> Result<PortableWorkflow, TemplateError> {
+    let mut names = BTreeSet::new();
+    for parameter in parameters {
+        if !is_parameter_name(&parameter.name) {
+            return Err(TemplateError::InvalidName(parameter.name.clone()));
+        }
+        if !names.insert(parameter.name.as_str()) {
+            return Err(TemplateError::DuplicateParameter(parameter.name.clone()));
+        }
+    }
+
+    let portable = PortableWorkflow::from_workflow(workflow);
+    let value = serde_json::to_value(&portable).map_err(|e| ParseError::InvalidJson(e.to_string()))?;
+    let mut used = BTreeSet::new();
+    collect_placeholders(&value, &mut used);
+    if let Some(undeclared) = used.into_iter().find(|name| !names.contains(name.as_str())) {
+        return Err(TemplateError::UndeclaredPlaceholder(undeclared));
+    }
+    Ok(portable)
+}
+
+fn is_parameter_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// `(start, end, name)` of each `[[ name ]]` in a string
+fn placeholders(text: &str) -> Vec<(usize, usize, &str)> {
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Some(start) = text[from..].find("[[").map(|at| from + at) {
+        let Some(end) = text[start..].find("]]").map(|at| start + at + 2) else {
+            break;
+        };
+        let name = text[start + 2..end - 2].trim();
+        if is_parameter_name(name) {
+            found.push((start, end, name));
+            from = end;
+        } else {
+            from = start + 2;
+        }
+    }
+    found
+}
+
+fn collect_placeholders(value: &JsonValue, names: &mut BTreeSet<String>) {
+    match value {
+        JsonValue::String(text) => names.extend(placeholders(text).into_iter().map(|(_, _, name)| name.to_string())),
+        JsonValue::Array(items) => items.iter().for_each(|item| collect_placeholders(item, names)),
+        JsonValue::Object(map) => map.values().for_each(|child| collect_placeholders(child, names)),
+        _ => {}
+    }
+}
+
+fn substitute(value: &mut JsonValue, values: &HashMap<&str, JsonValue>) {
+    match value {
+        JsonValue::String(text) => {
+            let found = placeholders(text);
+            if let [(0, end, name)] = found.as_slice() {
+                if *end == text.len() {
+                    *value = values[name].clone();
+                    return;
+                }
+            }
+            let mut rendered = String::with_capacity(text.len());
+            let mut last = 0;
+            for (start, end, name) in found {
+                rendered.push_str(&text[last..start]);
+                rendered.push_str(&to_text(&values[name]));
+                last = end;
+            }
+            rendered.push_str(&text[last..]);
+            *text = rendered;
+        }
+        JsonValue::Array(items) => items.iter_mut().for_each(|item| substitute(item, values)),
+        JsonValue::Object(map) => map.values_mut().for_each(|child| substitute(child, values)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::types::{ActionType, Edge, Node, NodeConfig, NodeType, Position, TriggerType, WORKFLOW_SCHEMA_VERSION};
+
+    fn draft() -> TemplateDraft {
+        let node = |node_type, parameters| Node {
+            id: Uuid::new_v4(),
+            node_type,
+            config: NodeConfig { parameters, ..Default::default() },
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![],
+        };
+        let trigger = node(NodeType::Trigger { trigger_type: TriggerType::Manual }, HashMap::new());
+        let action = node(NodeType::Action { action_type: ActionType::Http }, HashMap::from([
+            ("url".to_string(), serde_json::json!("https://hooks.example.com/[[ channel ]]?from={{ user }}")),
+            ("retries".to_string(), serde_json::json!("[[retries]]")),
+            ("body".to_string(), serde_json::json!(format!("{{{{ vars['node_{}'] }}}}", trigger.id))),
+        ]));
+        TemplateDraft {
+            name: "Notify channel".to_string(),
+            description: Some("Posts trigger data to a chat channel".to_string()),
+            category: "notifications".to_string(),
+            tags: vec!["chat".to_string()],
+            parameters: vec![
+                TemplateParameter { name: "channel".to_string(), description: None, data_type: DataType::String, default: None },
+                TemplateParameter { name: "retries".to_string(), description: None, data_type: DataType::Number, default: Some(serde_json::json!(3)) },
+            ],
+            workflow: Workflow {
+                version: WORKFLOW_SCHEMA_VERSION,
+                id: Uuid::new_v4(),
+                name: "Notify".to_string(),
+                description: None,
+                edges: vec![Edge {
+                    id: Uuid::new_v4(),
+                    source: trigger.id,
+                    source_handle: "output".to_string(),
+                    target: action.id,
+                    target_handle: "input".to_string(),
+                }],
+                nodes: vec![trigger, action],
+                variables: HashMap::new(),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                trash: Default::default(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_and_list() {
+        let store = TemplateStore::new();
+        let author = Uuid::new_v4();
+        let template = store.create(draft(), author).await.unwrap();
+        let mut other = draft();
+        other.name = "Archive".to_string();
+        other.category = "storage".to_string();
+        store.create(other, author).await.unwrap();
+
+        assert_eq!(store.list(None, None).await.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), ["Archive", "Notify channel"]);
+        assert_eq!(store.list(Some("Notifications"), None).await.len(), 1);
+        assert_eq!(store.list(None, Some("CHAT")).await.len(), 2);
+        assert_eq!(store.categories().await, [
+            TemplateCategory { name: "notifications".to_string(), templates: 1 },
+            TemplateCategory { name: "storage".to_string(), templates: 1 },
+        ]);
+
+        let mut undeclared = draft();
+        undeclared.parameters.remove(0);
+        assert!(matches!(store.update(template.id, undeclared).await, Err(TemplateError::UndeclaredPlaceholder(name)) if name == "channel"));
+        let mut renamed = draft();
+        renamed.name = "Notify team".to_string();
+        assert_eq!(store.update(template.id, renamed).await.unwrap().author, author);
+        assert!(store.delete(template.id).await);
+        assert!(store.get(template.id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_instantiate() {
+        let store = TemplateStore::new();
+        let source = draft();
+        let template = store.create(source.clone(), Uuid::new_v4()).await.unwrap();
+
+        let values = HashMap::from([
+            ("channel".to_string(), serde_json::json!("ops")),
+            ("retries".to_string(), serde_json::json!("5")),
+        ]);
+        let bundle = store.instantiate(template.id, &values, Some("Ops alerts".to_string())).await.unwrap();
+        let workflow = bundle.workflow;
+        assert_eq!(workflow.name, "Ops alerts");
+        assert_ne!(workflow.id, source.workflow.id);
+        let (trigger, action) = (&workflow.nodes[0], &workflow.nodes[1]);
+        assert_ne!(trigger.id, source.workflow.nodes[0].id);
+        let parameters = &action.config.parameters;
+        assert_eq!(parameters["url"], "https://hooks.example.com/ops?from={{ user }}");
+        assert_eq!(parameters["retries"], 5.0);
+        assert_eq!(parameters["body"], format!("{{{{ vars['node_{}'] }}}}", trigger.id));
+
+        // Defaults fill in, required values and types are checked
+        let channel_only = HashMap::from([("channel".to_string(), serde_json::json!("dev"))]);
+        let defaulted = store.instantiate(template.id, &channel_only, None).await.unwrap();
+        assert_eq!(defaulted.workflow.nodes[1].config.parameters["retries"], 3);
+        assert!(matches!(store.instantiate(template.id, &HashMap::new(), None).await, Err(TemplateError::MissingValue(_))));
+        let bad = HashMap::from([
+            ("channel".to_string(), serde_json::json!("ops")),
+            ("retries".to_string(), serde_json::json!("many")),
+        ]);
+        assert!(matches!(store.instantiate(template.id, &bad, None).await, Err(TemplateError::InvalidValue { .. })));
+        let unknown = HashMap::from([("region".to_string(), serde_json::json!("eu"))]);
+        assert!(matches!(store.instantiate(template.id, &unknown, None).await, Err(TemplateError::UnknownParameter(_))));
+    }
+}
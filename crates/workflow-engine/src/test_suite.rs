@@ -0,0 +1,428 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common::error::GatewayError;
+use common::types::{ApiRequest, ApiResponse, ExecutionContext, ExecutionState, JsonValue, Workflow};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::executor::WorkflowExecutor;
+use crate::expression::values_equal;
+use crate::filters::to_text;
+use crate::http::HttpDispatcher;
+
+/// A regression test of a workflow: the input its trigger provides, the
+/// provider responses its HTTP nodes receive, and what its nodes must output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowTestCase {
+    #[serde(default = "Uuid::new_v4")]
+    pub id: Uuid,
+    pub name: String,
+    /// Execution input the trigger would provide, e.g. `webhook_payload`
+    #[serde(default)]
+    pub trigger: HashMap<String, JsonValue>,
+    #[serde(default)]
+    pub mocks: Vec<MockResponse>,
+    pub assertions: Vec<Assertion>,
+}
+
+/// Response an HTTP node receives instead of calling its provider. A case
+/// fails on HTTP calls without a mock, so tests never reach real providers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockResponse {
+    pub node_id: Uuid,
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: Option<JsonValue>,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+/// Check of a value in a node's output, or in the execution result
+/// (`state`, `error`, `output`) when no node is given
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Assertion {
+    #[serde(default)]
+    pub node_id: Option<Uuid>,
+    /// JSONPath into the output: `$`, `.field`, `['field']`, `[0]` and `[*]`
+    #[serde(default = "default_path")]
+    pub path: String,
+    #[serde(flatten)]
+    pub check: Check,
+}
+
+fn default_path() -> String {
+    "$".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", content = "value", rename_all = "snake_case")]
+pub enum Check {
+    Equals(JsonValue),
+    /// Substring of a string, element of an array or key of an object
+    Contains(JsonValue),
+    /// Regular expression the value, as text, must match
+    Matches(String),
+    Exists,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssertionResult {
+    pub assertion: Assertion,
+    pub passed: bool,
+    /// Value found at the path; absent when nothing matched
+    pub actual: Option<JsonValue>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseReport {
+    pub case_id: Uuid,
+    pub name: String,
+    pub passed: bool,
+    pub state: ExecutionState,
+    pub error: Option<String>,
+    pub assertions: Vec<AssertionResult>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuiteReport {
+    pub workflow_id: Uuid,
+    pub passed: usize,
+    pub failed: usize,
+    pub cases: Vec<CaseReport>,
+    pub finished_at: DateTime<Utc>,
+}
+
+/// Test cases of each workflow
+pub struct TestSuiteStore {
+    suites: Arc<RwLock<HashMap<Uuid, Vec<WorkflowTestCase>>>>,
+}
+
+impl TestSuiteStore {
+    pub fn new() -> Self {
+        Self {
+            suites: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub async fn cases(&self, workflow_id: Uuid) -> Vec<WorkflowTestCase> {
+        self.suites.read().await.get(&workflow_id).cloned().unwrap_or_default()
+    }
+
+    /// Replace the suite of a workflow
+    pub async fn set_cases(&self, workflow_id: Uuid, cases: Vec<WorkflowTestCase>) {
+        let mut suites = self.suites.write().await;
+        if cases.is_empty() {
+            suites.remove(&workflow_id);
+        } else {
+            suites.insert(workflow_id, cases);
+        }
+    }
+
+    /// Drop the suite of a deleted workflow
+    pub async fn remove_workflow(&self, workflow_id: Uuid) -> bool {
+        self.suites.write().await.remove(&workflow_id).is_some()
+    }
+}
+
+impl Default for TestSuiteStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Run every case of a suite against a workflow.
+///
+/// Each case runs on its own executor whose HTTP nodes get the mocked
+/// responses. Nothing else is configured: AI nodes return placeholder
+/// output and scraper nodes echo their parameters, so no case has side
+/// effects or depends on external services.
+pub async fn run_suite(workflow: &Workflow, cases: &[WorkflowTestCase]) -> SuiteReport {
+    let mut reports = Vec::with_capacity(cases.len());
+    for case in cases {
+        reports.push(run_case(workflow, case).await);
+    }
+    let passed = reports.iter().filter(|r| r.passed).count();
+    SuiteReport {
+        workflow_id: workflow.id,
+        passed,
+        failed: reports.len() - passed,
+        cases: reports,
+        finished_at: Utc::now(),
+    }
+}
+
+async fn run_case(workflow: &Workflow, case: &WorkflowTestCase) -> CaseReport {
+    let started = std::time::Instant::now();
+    let mocks = case.mocks.iter().map(|mock| (mock.node_id, mock.clone())).collect();
+    let executor = WorkflowExecutor::new().with_http_dispatcher(Arc::new(MockDispatcher { mocks }));
+    let execution_id = Uuid::new_v4();
+    let ctx = ExecutionContext {
+        execution_id,
+        workflow_id: workflow.id,
+        variables: case.trigger.clone(),
+        state: ExecutionState::Pending,
+        started_at: Utc::now(),
+        current_node: None,
+    };
+
+    let (state, error, output) = match executor.execute(workflow, ctx).await {
+        Ok(result) => (result.state, result.error, result.output),
+        Err(e) => (ExecutionState::Failed, Some(e.to_string()), None),
+    };
+    let variables = match executor.get_context(execution_id).await {
+        Some(ctx) => ctx.variables.read().await.clone(),
+        None => HashMap::new(),
+    };
+    let execution = serde_json::json!({ "state": state, "error": error, "output": output });
+
+    let assertions: Vec<AssertionResult> = case.assertions.iter()
+        .map(|assertion| {
+            let subject = match assertion.node_id {
+                Some(node_id) => variables.get(&format!("node_{}", node_id)),
+                None => Some(&execution),
+            };
+            evaluate(assertion, subject)
+        })
+        .collect();
+    CaseReport {
+        case_id: case.id,
+        name: case.name.clone(),
+        passed: assertions.iter().all(|a| a.passed),
+        state,
+        error,
+        assertions,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+fn evaluate(assertion: &Assertion, subject: Option<&JsonValue>) -> AssertionResult {
+    let result = |passed: bool, actual: Option<JsonValue>, message: Option<String>| AssertionResult {
+        assertion: assertion.clone(),
+        passed,
+        actual,
+        message,
+    };
+    let Some(subject) = subject else {
+        return result(false, None, Some("node produced no output".to_string()));
+    };
+    let actual = match select(subject, &assertion.path) {
+        Ok(actual) => actual,
+        Err(e) => return result(false, None, Some(e)),
+    };
+    let Some(actual) = actual else {
+        return result(false, None, Some(format!("nothing at {}", assertion.path)));
+    };
+
+    let outcome = match &assertion.check {
+        Check::Exists => Ok(true),
+        Check::Equals(expected) => Ok(values_equal(&actual, expected)),
+        Check::Contains(expected) => Ok(match (&actual, expected) {
+            (JsonValue::String(text), JsonValue::String(part)) => text.contains(part.as_str()),
+            (JsonValue::Array(items), _) => items.iter().any(|item| values_equal(item, expected)),
+            (JsonValue::Object(map), JsonValue::String(key)) => map.contains_key(key),
+            _ => false,
+        }),
+        Check::Matches(pattern) => regex::Regex::new(pattern)
+            .map(|re| re.is_match(&to_text(&actual)))
+            .map_err(|e| format!("invalid pattern: {}", e)),
+    };
+    match outcome {
+        Ok(passed) => result(passed, Some(actual), None),
+        Err(e) => result(false, Some(actual), Some(e)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+fn parse_path(path: &str) -> Result<Vec<Step>, String> {
+    let invalid = || format!("invalid path {}", path);
+    let rest = path.trim().strip_prefix('$').ok_or_else(invalid)?;
+    let mut chars = rest.chars().peekable();
+    let mut steps = Vec::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                let mut key = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == '.' || c == '[' {
+                        break;
+                    }
+                    key.push(c);
+                    chars.next();
+                }
+                match key.as_str() {
+                    "" => return Err(invalid()),
+                    "*" => steps.push(Step::Wildcard),
+                    _ => steps.push(Step::Key(key)),
+                }
+            }
+            '[' => {
+                let mut inner = String::new();
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    inner.push(c);
+                }
+                let inner = inner.trim();
+                let quoted = inner.strip_prefix('\'').and_then(|s| s.strip_suffix('\''))
+                    .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+                steps.push(match (inner, quoted) {
+                    ("*", _) => Step::Wildcard,
+                    (_, Some(key)) => Step::Key(key.to_string()),
+                    _ => Step::Index(inner.parse().map_err(|_| invalid())?),
+                });
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(steps)
+}
+
+/// Value at a JSONPath; with a wildcard, the array of all matches
+fn select(value: &JsonValue, path: &str) -> Result<Option<JsonValue>, String> {
+    let steps = parse_path(path)?;
+    let mut matches = vec![value];
+    for step in &steps {
+        matches = matches.into_iter()
+            .flat_map(|value| -> Vec<&JsonValue> {
+                match (step, value) {
+                    (Step::Key(key), _) => value.get(key).into_iter().collect(),
+                    (Step::Index(index), _) => value.get(index).into_iter().collect(),
+                    (Step::Wildcard, JsonValue::Array(items)) => items.iter().collect(),
+                    (Step::Wildcard, JsonValue::Object(map)) => map.values().collect(),
+                    (Step::Wildcard, _) => Vec::new(),
+                }
+            })
+            .collect();
+    }
+    Ok(if steps.contains(&Step::Wildcard) {
+        Some(JsonValue::Array(matches.into_iter().cloned().collect()))
+    } else {
+        matches.first().map(|value| (*value).clone())
+    })
+}
+
+struct MockDispatcher {
+    mocks: HashMap<Uuid, MockResponse>,
+}
+
+#[async_trait]
+impl HttpDispatcher for MockDispatcher {
+    async fn dispatch(&self, request: ApiRequest) -> Result<ApiResponse, GatewayError> {
+        let mock = self.mocks.get(&request.node_id).ok_or_else(|| {
+            GatewayError::ProviderUnavailable(format!("no mocked response for node {} ({})", request.node_id, request.endpoint))
+        })?;
+        Ok(ApiResponse {
+            request_id: request.id,
+            status_code: mock.status,
+            headers: mock.headers.clone(),
+            body: mock.body.clone(),
+            latency_ms: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::types::{ActionType, ConditionType, Edge, Node, NodeConfig, NodeType, Position, TriggerType, WORKFLOW_SCHEMA_VERSION};
+
+    fn node(node_type: NodeType, parameters: HashMap<String, JsonValue>) -> Node {
+        Node {
+            id: Uuid::new_v4(),
+            node_type,
+            config: NodeConfig { parameters, ..Default::default() },
+            position: Position { x: 0.0, y: 0.0 },
+            inputs: vec![],
+            outputs: vec![],
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let value = serde_json::json!({ "body": { "items": [{ "id": 1 }, { "id": 2 }], "next page": null } });
+        assert_eq!(select(&value, "$.body.items[1].id").unwrap(), Some(serde_json::json!(2)));
+        assert_eq!(select(&value, "$.body.items[*].id").unwrap(), Some(serde_json::json!([1, 2])));
+        assert_eq!(select(&value, "$['body']['next page']").unwrap(), Some(JsonValue::Null));
+        assert_eq!(select(&value, "$.body.missing").unwrap(), None);
+        assert!(select(&value, "body.items").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_suite() {
+        let trigger = node(NodeType::Trigger { trigger_type: TriggerType::Webhook }, HashMap::new());
+        let lookup = node(
+            NodeType::Action { action_type: ActionType::Http },
+            HashMap::from([("url".to_string(), serde_json::json!("https://crm.example.com/customers/{{ webhook_payload.customer }}"))]),
+        );
+        let check = node(
+            NodeType::Condition { condition_type: ConditionType::If },
+            HashMap::from([("expression".to_string(), serde_json::json!(format!("vars['node_{}'].body.tier == 'gold'", lookup.id)))]),
+        );
+        let edges = vec![
+            Edge { id: Uuid::new_v4(), source: trigger.id, source_handle: "output".to_string(), target: lookup.id, target_handle: "input".to_string() },
+            Edge { id: Uuid::new_v4(), source: lookup.id, source_handle: "output".to_string(), target: check.id, target_handle: "input".to_string() },
+        ];
+        let (lookup_id, check_id) = (lookup.id, check.id);
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Tiering".to_string(),
+            description: None,
+            nodes: vec![trigger, lookup, check],
+            edges,
+            variables: HashMap::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            trash: Default::default(),
+        };
+
+        let assertion = |node_id, path: &str, check| Assertion { node_id, path: path.to_string(), check };
+        let gold = WorkflowTestCase {
+            id: Uuid::new_v4(),
+            name: "gold customer".to_string(),
+            trigger: HashMap::from([("webhook_payload".to_string(), serde_json::json!({ "customer": "c-42" }))]),
+            mocks: vec![MockResponse { node_id: lookup_id, status: 200, headers: HashMap::new(), body: Some(serde_json::json!({ "tier": "gold" })) }],
+            assertions: vec![
+                assertion(None, "$.state", Check::Equals(serde_json::json!("Completed"))),
+                assertion(Some(check_id), "$.branch", Check::Equals(serde_json::json!("true"))),
+                assertion(Some(lookup_id), "$.body", Check::Contains(serde_json::json!("tier"))),
+                assertion(Some(lookup_id), "$.body.tier", Check::Matches("^g(old|reen)$".to_string())),
+                assertion(Some(lookup_id), "$.body.discount", Check::Exists),
+            ],
+        };
+        let unmocked = WorkflowTestCase {
+            id: Uuid::new_v4(),
+            name: "provider not mocked".to_string(),
+            trigger: HashMap::new(),
+            mocks: vec![],
+            assertions: vec![assertion(None, "$.state", Check::Equals(serde_json::json!("Failed")))],
+        };
+
+        let report = run_suite(&workflow, &[gold, unmocked]).await;
+        assert_eq!((report.passed, report.failed), (1, 1));
+        let gold = &report.cases[0];
+        assert!(!gold.passed);
+        assert!(gold.assertions[..4].iter().all(|a| a.passed), "{:?}", gold.assertions);
+        assert_eq!(gold.assertions[4].message.as_deref(), Some("nothing at $.body.discount"));
+        let unmocked = &report.cases[1];
+        assert!(unmocked.passed);
+        assert!(unmocked.error.as_deref().unwrap().contains("no mocked response"));
+    }
+}
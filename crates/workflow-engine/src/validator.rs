@@ -1,4 +1,8 @@
-use common::types::{Workflow, Node, NodeType, DataType};
+use common::types::{ActionType, Workflow, Node, NodeType, DataType, OnError};
+use crate::executor::ERROR_HANDLE;
+use crate::expression::{collect_templates, path_to_string, template_expressions, Expression, PathSegment};
+use crate::graph;
+use crate::scraper;
 use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
@@ -24,6 +28,27 @@ pub enum ValidationError {
     MissingRequiredField(Uuid, String),
     NoTriggerNode,
     UnreachableNodes(Vec<Uuid>),
+    /// A `{{ ... }}` placeholder in a node parameter does not parse
+    InvalidExpression {
+        node_id: Uuid,
+        parameter: String,
+        message: String,
+    },
+    /// An expression reads an input handle or node output that is not
+    /// upstream of the node
+    UnknownReference {
+        node_id: Uuid,
+        parameter: String,
+        reference: String,
+    },
+    /// An expression reads into an input in a way its source port's type
+    /// does not allow, e.g. a field of a string
+    ReferenceTypeMismatch {
+        node_id: Uuid,
+        parameter: String,
+        reference: String,
+        data_type: DataType,
+    },
 }
 
 impl std::fmt::Display for ValidationError {
@@ -44,6 +69,17 @@ impl std::fmt::Display for ValidationError {
             ValidationError::UnreachableNodes(nodes) => {
                 write!(f, "Unreachable nodes: {:?}", nodes)
             }
+            ValidationError::InvalidExpression { node_id, parameter, message } => {
+                write!(f, "Invalid expression in parameter '{}' of node {}: {}", parameter, node_id, message)
+            }
+            ValidationError::UnknownReference { node_id, parameter, reference } => {
+                write!(f, "Parameter '{}' of node {} reads '{}', which is not an upstream output",
+                    parameter, node_id, reference)
+            }
+            ValidationError::ReferenceTypeMismatch { node_id, parameter, reference, data_type } => {
+                write!(f, "Parameter '{}' of node {} reads '{}', which a {:?} output does not have",
+                    parameter, node_id, reference, data_type)
+            }
         }
     }
 }
@@ -95,6 +131,35 @@ impl WorkflowValidator {
             }
         }
 
+        // Flag nodes graph cleanup would move to the trash; isolated nodes are reported above
+        let analysis = graph::analyze(workflow);
+        for id in analysis.unreachable.iter().filter(|id| self.has_connections(**id, workflow)) {
+            warnings.push(format!("Node {} is unreachable from any trigger", id));
+        }
+        for id in &analysis.dead_ends {
+            warnings.push(format!("Node {} is a dead end (its result never reaches an action)", id));
+        }
+
+        // Check the data flow of parameter expressions
+        for e in self.validate_expressions(workflow) {
+            errors.push(e.to_string());
+        }
+
+        // Check node error-handling policies
+        for node in &workflow.nodes {
+            if node.config.timeout_ms == Some(0) {
+                errors.push(format!("Node {} has a zero timeout", node.id));
+            }
+            let has_error_edge = workflow.edges.iter()
+                .any(|e| e.source == node.id && e.source_handle == ERROR_HANDLE);
+            if node.config.on_error == OnError::FallbackBranch && !has_error_edge {
+                warnings.push(format!(
+                    "Node {} falls back to the '{}' branch on failure but has no '{}' edge",
+                    node.id, ERROR_HANDLE, ERROR_HANDLE
+                ));
+            }
+        }
+
         Ok(ValidationResult {
             valid: errors.is_empty(),
             errors,
@@ -111,7 +176,7 @@ impl WorkflowValidator {
         match &node.node_type {
             NodeType::Trigger { trigger_type: _ } => {
                 // Validate trigger configuration
-                if node.inputs.len() > 0 {
+                if !node.inputs.is_empty() {
                     warnings.push(format!(
                         "Trigger node {} has input ports, which is unusual",
                         node.id
@@ -121,7 +186,7 @@ impl WorkflowValidator {
                     errors.push(format!("Trigger node {} must have at least one output", node.id));
                 }
             }
-            NodeType::Action { action_type: _ } => {
+            NodeType::Action { action_type } => {
                 // Validate action configuration
                 if node.inputs.is_empty() {
                     warnings.push(format!(
@@ -129,6 +194,9 @@ impl WorkflowValidator {
                         node.id
                     ));
                 }
+                if matches!(action_type, ActionType::Scraper) {
+                    self.validate_scraper_node(node, &mut errors, &mut warnings);
+                }
             }
             NodeType::Condition { condition_type: _ } => {
                 // Validate condition configuration
@@ -184,6 +252,27 @@ impl WorkflowValidator {
         })
     }
 
+    /// Check a scraper node's action and that its output ports expect the
+    /// response object carrying the typed scraper data
+    fn validate_scraper_node(&self, node: &Node, errors: &mut Vec<String>, warnings: &mut Vec<String>) {
+        let parameters = serde_json::to_value(&node.config.parameters).unwrap_or_default();
+        // A templated action type is only known once rendered
+        let templated = parameters.get("action")
+            .and_then(|action| action.as_str())
+            .is_some_and(|action| action.contains("{{"));
+        if !templated {
+            if let Err(e) = scraper::build_request(&parameters, None) {
+                errors.push(format!("Scraper node {} has an invalid action: {}", node.id, e));
+            }
+        }
+        for port in node.outputs.iter().filter(|p| !matches!(p.data_type, DataType::Object | DataType::Any)) {
+            warnings.push(format!(
+                "Output port '{}' of scraper node {} expects {:?}, but scraper nodes output an object",
+                port.name, node.id, port.data_type
+            ));
+        }
+    }
+
     /// Validate connection between two nodes
     fn validate_connection(
         &self,
@@ -192,11 +281,11 @@ impl WorkflowValidator {
     ) -> Result<(), ValidationError> {
         let source_node = node_map
             .get(&edge.source)
-            .ok_or_else(|| ValidationError::NodeNotFound(edge.source))?;
+            .ok_or(ValidationError::NodeNotFound(edge.source))?;
 
         let target_node = node_map
             .get(&edge.target)
-            .ok_or_else(|| ValidationError::NodeNotFound(edge.target))?;
+            .ok_or(ValidationError::NodeNotFound(edge.target))?;
 
         // Find the specific ports being connected
         let source_port = if !edge.source_handle.is_empty() {
@@ -210,7 +299,7 @@ impl WorkflowValidator {
             source_node
                 .outputs
                 .first()
-                .ok_or_else(|| ValidationError::NoOutputPorts(edge.source))?
+                .ok_or(ValidationError::NoOutputPorts(edge.source))?
         };
 
         let target_port = if !edge.target_handle.is_empty() {
@@ -224,7 +313,7 @@ impl WorkflowValidator {
             target_node
                 .inputs
                 .first()
-                .ok_or_else(|| ValidationError::NoInputPorts(edge.target))?
+                .ok_or(ValidationError::NoInputPorts(edge.target))?
         };
 
         // Validate type compatibility
@@ -240,84 +329,151 @@ impl WorkflowValidator {
         Ok(())
     }
 
-    /// Check if two data types are compatible for connection
+    /// Check if two data types are compatible for connection, following the
+    /// coercion rules of [`DataType::can_coerce_to`]
     fn are_types_compatible(&self, source_type: &DataType, target_type: &DataType) -> bool {
-        match (source_type, target_type) {
-            // Exact match
-            (a, b) if a == b => true,
-            // Any type can connect to Any
-            (DataType::Any, _) | (_, DataType::Any) => true,
-            // String can connect to String
-            (DataType::String, DataType::String) => true,
-            // Number types are compatible
-            (DataType::Number, DataType::Number) => true,
-            // Boolean types are compatible
-            (DataType::Boolean, DataType::Boolean) => true,
-            // Array types are compatible
-            (DataType::Array, DataType::Array) => true,
-            // Object types are compatible
-            (DataType::Object, DataType::Object) => true,
-            // Otherwise incompatible
-            _ => false,
-        }
+        source_type.can_coerce_to(target_type)
     }
 
     /// Validate required fields in node configuration
     fn validate_required_fields(&self, node: &Node) -> Result<(), ValidationError> {
+        use common::types::{LoopType, TriggerType};
+
+        let params = &node.config.parameters;
+        let missing = |field: &str| Err(ValidationError::MissingRequiredField(node.id, field.to_string()));
+
         // Check node-type specific required fields
         match &node.node_type {
-            NodeType::Trigger { trigger_type } => {
-                // Trigger-specific validation
-                match trigger_type {
-                    common::types::TriggerType::Webhook => {
-                        // Webhook should have URL configured
-                        if !node.config.parameters.contains_key("webhook_url") {
-                            return Err(ValidationError::MissingRequiredField(
-                                node.id,
-                                "webhook_url".to_string(),
-                            ));
-                        }
+            // Webhook should have URL configured
+            NodeType::Trigger { trigger_type: TriggerType::Webhook }
+                if !params.contains_key("webhook_url") => missing("webhook_url"),
+            // Schedule should have cron expression
+            NodeType::Trigger { trigger_type: TriggerType::Schedule }
+                if !params.contains_key("cron_expression") => missing("cron_expression"),
+            // Feed trigger needs the URL to poll
+            NodeType::Trigger { trigger_type: TriggerType::Rss }
+                if !params.contains_key("feed_url") => missing("feed_url"),
+            // Condition nodes route on an expression
+            NodeType::Condition { .. } if !params.contains_key("expression") => missing("expression"),
+            // While loops need a termination condition
+            NodeType::Loop { loop_type: LoopType::While } if !params.contains_key("condition") => {
+                missing("condition")
+            }
+            // AI nodes should have model and prompt configured
+            NodeType::AI { .. } if !params.contains_key("model") => missing("model"),
+            NodeType::AI { .. } if !params.contains_key("prompt") => missing("prompt"),
+            // Custom nodes must have language and code
+            NodeType::Custom { config: custom_config } if custom_config.language.is_empty() => {
+                missing("language")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the `{{ ... }}` expressions in node parameters.
+    ///
+    /// Each placeholder must parse. `input.<handle>` must name the source
+    /// handle of an incoming edge, and paths into it must fit the data type
+    /// of the source port. `vars["node_<id>"]` must name a node upstream of
+    /// the one being checked. Other variables are only known at run time.
+    pub fn validate_expressions(&self, workflow: &Workflow) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for node in &workflow.nodes {
+            let upstream = upstream_nodes(workflow, node.id);
+            let mut parameters: Vec<_> = node.config.parameters.iter().collect();
+            parameters.sort_by_key(|(name, _)| name.as_str());
+
+            let mut templates = Vec::new();
+            for (name, value) in parameters {
+                collect_templates(value, name.clone(), &mut templates);
+            }
+            for (parameter, template) in templates {
+                let sources = match template_expressions(template) {
+                    Ok(sources) => sources,
+                    Err(e) => {
+                        errors.push(ValidationError::InvalidExpression { node_id: node.id, parameter, message: e.0 });
+                        continue;
                     }
-                    common::types::TriggerType::Schedule => {
-                        // Schedule should have cron expression
-                        if !node.config.parameters.contains_key("cron_expression") {
-                            return Err(ValidationError::MissingRequiredField(
-                                node.id,
-                                "cron_expression".to_string(),
-                            ));
+                };
+                for source in sources {
+                    let expression = match Expression::parse(source) {
+                        Ok(expression) => expression,
+                        Err(e) => {
+                            errors.push(ValidationError::InvalidExpression {
+                                node_id: node.id,
+                                parameter: parameter.clone(),
+                                message: e.0,
+                            });
+                            continue;
+                        }
+                    };
+                    for path in expression.paths() {
+                        if let Err(e) = self.check_reference(workflow, node, &upstream, &parameter, path) {
+                            errors.push(e);
                         }
                     }
-                    _ => {}
                 }
             }
-            NodeType::AI { ai_type: _ } => {
-                // AI nodes should have model and prompt configured
-                if !node.config.parameters.contains_key("model") {
-                    return Err(ValidationError::MissingRequiredField(
-                        node.id,
-                        "model".to_string(),
-                    ));
-                }
-                if !node.config.parameters.contains_key("prompt") {
-                    return Err(ValidationError::MissingRequiredField(
-                        node.id,
-                        "prompt".to_string(),
-                    ));
+        }
+        errors
+    }
+
+    /// Check one path read by an expression of `node`
+    fn check_reference(
+        &self,
+        workflow: &Workflow,
+        node: &Node,
+        upstream: &HashSet<Uuid>,
+        parameter: &str,
+        path: &[PathSegment],
+    ) -> Result<(), ValidationError> {
+        let unknown = || ValidationError::UnknownReference {
+            node_id: node.id,
+            parameter: parameter.to_string(),
+            reference: path_to_string(path),
+        };
+        let key = |index: usize| match path.get(index) {
+            Some(PathSegment::Key(key)) => Some(key.as_str()),
+            _ => None,
+        };
+
+        let check_node = |key: Option<&str>| match key.and_then(|key| key.strip_prefix("node_")) {
+            Some(id) if !is_upstream(id, upstream) => Err(unknown()),
+            _ => Ok(()),
+        };
+
+        match key(0) {
+            // Outputs of upstream nodes keyed by the source handle of the edge
+            Some("input") if path.len() > 1 => {
+                let handle = key(1).ok_or_else(unknown)?;
+                let incoming: Vec<_> = workflow.edges.iter()
+                    .filter(|e| e.target == node.id && e.source_handle == handle)
+                    .collect();
+                if incoming.is_empty() {
+                    return Err(unknown());
                 }
-            }
-            NodeType::Custom { config: custom_config } => {
-                // Custom nodes must have language and code
-                if custom_config.language.is_empty() {
-                    return Err(ValidationError::MissingRequiredField(
-                        node.id,
-                        "language".to_string(),
-                    ));
+                // Ports missing on the source node are reported by the connection check
+                let data_types: Vec<&DataType> = incoming.iter()
+                    .filter_map(|e| workflow.nodes.iter().find(|n| n.id == e.source))
+                    .filter_map(|source| source.outputs.iter().find(|p| p.name == handle))
+                    .map(|port| &port.data_type)
+                    .collect();
+                match data_types.first() {
+                    Some(data_type) if !data_types.iter().any(|d| path_fits(d, &path[2..])) => {
+                        Err(ValidationError::ReferenceTypeMismatch {
+                            node_id: node.id,
+                            parameter: parameter.to_string(),
+                            reference: path_to_string(path),
+                            data_type: (*data_type).clone(),
+                        })
+                    }
+                    _ => Ok(()),
                 }
             }
-            _ => {}
+            // Node outputs are the `node_<id>` variables
+            Some("vars") => check_node(key(1)),
+            first => check_node(first),
         }
-
-        Ok(())
     }
 
     /// Check if a node is a trigger node
@@ -365,7 +521,7 @@ impl WorkflowValidator {
         for edge in &workflow.edges {
             adjacency
                 .entry(edge.source)
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(edge.target);
         }
 
@@ -404,6 +560,39 @@ impl WorkflowValidator {
     }
 }
 
+/// Nodes with a path of edges into `node_id`
+fn upstream_nodes(workflow: &Workflow, node_id: Uuid) -> HashSet<Uuid> {
+    let mut upstream = HashSet::new();
+    let mut queue = vec![node_id];
+    while let Some(id) = queue.pop() {
+        for edge in workflow.edges.iter().filter(|e| e.target == id) {
+            if upstream.insert(edge.source) {
+                queue.push(edge.source);
+            }
+        }
+    }
+    upstream
+}
+
+fn is_upstream(id: &str, upstream: &HashSet<Uuid>) -> bool {
+    Uuid::parse_str(id).is_ok_and(|id| upstream.contains(&id))
+}
+
+/// Whether a value of `data_type` can be read along `path`. Items of typed
+/// arrays are checked against their type; reads past an object key, an
+/// untyped array index or an `Any` value are not checked further.
+fn path_fits(data_type: &DataType, path: &[PathSegment]) -> bool {
+    match (data_type, path.first()) {
+        (DataType::Nullable(inner), _) => path_fits(inner, path),
+        (DataType::ArrayOf(item_type), Some(PathSegment::Index(_))) => path_fits(item_type, &path[1..]),
+        (_, None)
+        | (DataType::Any, _)
+        | (DataType::Object | DataType::ObjectSchema(_), Some(PathSegment::Key(_)))
+        | (DataType::Array, Some(PathSegment::Index(_))) => true,
+        _ => false,
+    }
+}
+
 impl Default for WorkflowValidator {
     fn default() -> Self {
         Self::new()
@@ -413,7 +602,7 @@ impl Default for WorkflowValidator {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use common::types::{TriggerType, Position, NodeConfig};
+    use common::types::{TriggerType, Position, NodeConfig, Port, WORKFLOW_SCHEMA_VERSION};
 
     fn create_test_node(id: Uuid, node_type: NodeType) -> Node {
         Node {
@@ -437,6 +626,7 @@ mod tests {
         let node2 = create_test_node(Uuid::new_v4(), NodeType::Action { action_type: common::types::ActionType::Http });
 
         let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
             id: Uuid::new_v4(),
             name: "Test".to_string(),
             description: None,
@@ -451,19 +641,144 @@ mod tests {
             variables: HashMap::new(),
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
+            trash: Default::default(),
         };
 
         let result = validator.validate(&workflow);
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_scraper_node_actions() {
+        let validator = WorkflowValidator::new();
+        let scraper_node = |parameters: serde_json::Value, data_type: DataType| {
+            let mut node = create_test_node(Uuid::new_v4(), NodeType::Action { action_type: ActionType::Scraper });
+            node.config.parameters = serde_json::from_value(parameters).unwrap();
+            node.outputs[0].data_type = data_type;
+            node
+        };
+
+        let valid = validator.validate_node(&scraper_node(
+            serde_json::json!({ "action": "getText", "selector": "h1" }),
+            DataType::Object,
+        )).unwrap();
+        assert!(valid.errors.is_empty());
+        assert!(!valid.warnings.iter().any(|w| w.contains("scraper")));
+
+        let templated = validator.validate_node(&scraper_node(
+            serde_json::json!({ "action": "{{ step }}" }),
+            DataType::Any,
+        )).unwrap();
+        assert!(templated.errors.is_empty());
+
+        let invalid = validator.validate_node(&scraper_node(
+            serde_json::json!({ "action": "getText" }),
+            DataType::String,
+        )).unwrap();
+        assert!(invalid.errors[0].contains("invalid action"));
+        assert!(invalid.warnings.iter().any(|w| w.contains("scraper nodes output an object")));
+    }
+
+    #[test]
+    fn test_expression_data_flow() {
+        let validator = WorkflowValidator::new();
+        let mut trigger = create_test_node(Uuid::new_v4(), NodeType::Trigger { trigger_type: TriggerType::Manual });
+        trigger.outputs[0].data_type = DataType::Object;
+        let mut action = create_test_node(Uuid::new_v4(), NodeType::Action { action_type: ActionType::Http });
+        action.config.parameters = serde_json::from_value(serde_json::json!({
+            "url": "https://example.com/orders/{{ input.output.id | url_encode }}",
+            "previous": format!("{{{{ vars['node_{}'].triggered }}}}", trigger.id),
+            "body": { "name": "{{ input.missing }}" },
+            "items": ["{{ input.output[0] }}"],
+            "own": format!("{{{{ vars['node_{}'] }}}}", action.id),
+            "broken": "{{ input. }}"
+        })).unwrap();
+        let workflow = Workflow {
+            version: WORKFLOW_SCHEMA_VERSION,
+            id: Uuid::new_v4(),
+            name: "Test".to_string(),
+            description: None,
+            nodes: vec![trigger.clone(), action.clone()],
+            edges: vec![common::types::Edge {
+                id: Uuid::new_v4(),
+                source: trigger.id,
+                source_handle: "output".to_string(),
+                target: action.id,
+                target_handle: "input".to_string(),
+            }],
+            variables: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            trash: Default::default(),
+        };
+
+        let errors = validator.validate_expressions(&workflow);
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert!(matches!(&errors[0], ValidationError::UnknownReference { parameter, reference, .. }
+            if parameter == "body.name" && reference == "input.missing"));
+        assert!(matches!(&errors[1], ValidationError::InvalidExpression { node_id, parameter, .. }
+            if *node_id == action.id && parameter == "broken"));
+        assert!(matches!(&errors[2], ValidationError::ReferenceTypeMismatch { parameter, data_type: DataType::Object, .. }
+            if parameter == "items[0]"));
+        assert!(matches!(&errors[3], ValidationError::UnknownReference { parameter, .. } if parameter == "own"));
+
+        let result = validator.validate(&workflow).unwrap();
+        assert!(!result.valid);
+        assert!(result.errors.iter().any(|e| e.contains("reads 'input.missing'")));
+    }
+
     #[test]
     fn test_type_compatibility() {
         let validator = WorkflowValidator::new();
         
         assert!(validator.are_types_compatible(&DataType::String, &DataType::String));
         assert!(validator.are_types_compatible(&DataType::Any, &DataType::String));
-        assert!(validator.are_types_compatible(&DataType::String, &DataType::Text));
-        assert!(!validator.are_types_compatible(&DataType::Number, &DataType::String));
+        assert!(validator.are_types_compatible(&DataType::String, &DataType::Any));
+        assert!(validator.are_types_compatible(&DataType::Number, &DataType::String));
+        assert!(!validator.are_types_compatible(&DataType::Object, &DataType::String));
+
+        let numbers = DataType::ArrayOf(Box::new(DataType::Number));
+        assert!(validator.are_types_compatible(&numbers, &DataType::ArrayOf(Box::new(DataType::String))));
+        assert!(validator.are_types_compatible(&numbers, &DataType::Array));
+        assert!(!validator.are_types_compatible(&DataType::ArrayOf(Box::new(DataType::Object)), &numbers));
+        let nullable = DataType::Nullable(Box::new(DataType::String));
+        assert!(validator.are_types_compatible(&DataType::String, &nullable));
+        assert!(!validator.are_types_compatible(&nullable, &DataType::String));
+    }
+
+    #[test]
+    fn test_data_type_serialization_and_coercion() {
+        use serde_json::json;
+
+        // Plain types keep their stored form
+        assert_eq!(serde_json::from_value::<DataType>(json!("Object")).unwrap(), DataType::Object);
+        let numbers: DataType = serde_json::from_value(json!({ "type": "Array", "itemType": "Number" })).unwrap();
+        assert_eq!(numbers, DataType::ArrayOf(Box::new(DataType::Number)));
+        let optional: DataType = serde_json::from_value(json!({ "type": "Optional", "innerType": "String" })).unwrap();
+        assert_eq!(serde_json::to_value(&optional).unwrap(), json!({ "type": "Nullable", "innerType": "String" }));
+        assert!(serde_json::from_value::<DataType>(json!("Tensor")).is_err());
+
+        assert_eq!(DataType::String.coerce(&json!(42)).unwrap(), json!("42"));
+        assert_eq!(DataType::Boolean.coerce(&json!("TRUE")).unwrap(), json!(true));
+        assert!(DataType::Number.coerce(&json!("abc")).is_err());
+        assert!(DataType::String.coerce(&json!(null)).is_err());
+        assert_eq!(optional.coerce(&json!(null)).unwrap(), json!(null));
+        assert_eq!(numbers.coerce(&json!(["1", 2.5])).unwrap(), json!([1, 2.5]));
+        assert!(numbers.coerce(&json!([1, "x"])).unwrap_err().contains("item 1"));
+
+        let order = DataType::ObjectSchema(json!({
+            "type": "object",
+            "required": ["id"],
+            "properties": {
+                "id": { "type": "integer" },
+                "lines": { "type": "array", "items": { "type": "number" } }
+            }
+        }));
+        assert_eq!(
+            order.coerce(&json!({ "id": "7", "lines": ["1.5"], "note": null })).unwrap(),
+            json!({ "id": 7, "lines": [1.5], "note": null })
+        );
+        assert!(order.coerce(&json!({ "lines": [] })).unwrap_err().contains("missing required property 'id'"));
+        assert!(order.coerce(&json!({ "id": 1.5 })).unwrap_err().contains("not an integer"));
     }
 }
@@ -0,0 +1,117 @@
+use async_trait::async_trait;
+use common::error::WorkflowError;
+use common::types::Workflow;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// A saved workflow definition and its owner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredWorkflow {
+    #[serde(flatten)]
+    pub workflow: Workflow,
+    pub owner_id: Uuid,
+    /// Inactive workflows are kept but not triggered
+    pub active: bool,
+}
+
+/// Durable storage of workflow definitions
+#[async_trait]
+pub trait WorkflowStore: Send + Sync {
+    /// Insert or replace a workflow
+    async fn save(&self, workflow: &StoredWorkflow) -> Result<(), WorkflowError>;
+    async fn get(&self, id: Uuid) -> Result<Option<StoredWorkflow>, WorkflowError>;
+    /// Workflows of `owner_id`, or of everyone; most recently updated first
+    async fn list(&self, owner_id: Option<Uuid>) -> Result<Vec<StoredWorkflow>, WorkflowError>;
+    async fn delete(&self, id: Uuid) -> Result<bool, WorkflowError>;
+}
+
+/// In-memory workflow store (for development, replace with database in production)
+#[derive(Default)]
+pub struct InMemoryWorkflowStore {
+    workflows: RwLock<HashMap<Uuid, StoredWorkflow>>,
+}
+
+impl InMemoryWorkflowStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl WorkflowStore for InMemoryWorkflowStore {
+    async fn save(&self, workflow: &StoredWorkflow) -> Result<(), WorkflowError> {
+        self.workflows.write().await.insert(workflow.workflow.id, workflow.clone());
+        Ok(())
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Option<StoredWorkflow>, WorkflowError> {
+        Ok(self.workflows.read().await.get(&id).cloned())
+    }
+
+    async fn list(&self, owner_id: Option<Uuid>) -> Result<Vec<StoredWorkflow>, WorkflowError> {
+        let workflows = self.workflows.read().await;
+        let mut matching: Vec<StoredWorkflow> = workflows.values()
+            .filter(|w| owner_id.is_none_or(|owner| w.owner_id == owner))
+            .cloned()
+            .collect();
+        matching.sort_by_key(|w| std::cmp::Reverse(w.workflow.updated_at));
+        Ok(matching)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool, WorkflowError> {
+        Ok(self.workflows.write().await.remove(&id).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use common::types::WORKFLOW_SCHEMA_VERSION;
+
+    fn stored(owner_id: Uuid, minutes_ago: i64) -> StoredWorkflow {
+        let updated_at = Utc::now() - Duration::minutes(minutes_ago);
+        StoredWorkflow {
+            workflow: Workflow {
+                version: WORKFLOW_SCHEMA_VERSION,
+                id: Uuid::new_v4(),
+                name: "Sync".to_string(),
+                description: None,
+                nodes: vec![],
+                edges: vec![],
+                variables: HashMap::new(),
+                created_at: updated_at,
+                updated_at,
+                trash: Default::default(),
+            },
+            owner_id,
+            active: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryWorkflowStore::new();
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let older = stored(alice, 10);
+        let newer = stored(alice, 1);
+        let other = stored(bob, 5);
+        for workflow in [&older, &newer, &other] {
+            store.save(workflow).await.unwrap();
+        }
+
+        let ids = |workflows: Vec<StoredWorkflow>| workflows.into_iter().map(|w| w.workflow.id).collect::<Vec<_>>();
+        assert_eq!(ids(store.list(Some(alice)).await.unwrap()), vec![newer.workflow.id, older.workflow.id]);
+        assert_eq!(store.list(None).await.unwrap().len(), 3);
+
+        let json = serde_json::to_value(&newer).unwrap();
+        assert_eq!(json["name"], "Sync");
+        assert_eq!(json["owner_id"], alice.to_string());
+
+        assert!(store.delete(older.workflow.id).await.unwrap());
+        assert!(!store.delete(older.workflow.id).await.unwrap());
+        assert!(store.get(older.workflow.id).await.unwrap().is_none());
+    }
+}
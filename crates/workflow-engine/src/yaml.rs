@@ -0,0 +1,385 @@
+//! Minimal YAML for portable workflow bundles.
+//!
+//! Covers what bundles need: block mappings and sequences, plain, single- and
+//! double-quoted scalars, comments, and JSON-style flow collections (`[]`,
+//! `{"a": 1}`). Anchors, tags, multi-document streams and block scalars
+//! (`|`, `>`) are not supported.
+
+use common::types::JsonValue;
+
+const INDENT: usize = 2;
+
+/// Render a JSON value as block-style YAML
+pub fn to_yaml(value: &JsonValue) -> String {
+    let mut out = String::new();
+    match value {
+        JsonValue::Object(map) if !map.is_empty() => write_block(value, 0, &mut out),
+        JsonValue::Array(items) if !items.is_empty() => write_block(value, 0, &mut out),
+        scalar => {
+            out.push_str(&scalar_text(scalar));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Parse the YAML subset described in the module docs
+pub fn from_yaml(text: &str) -> Result<JsonValue, String> {
+    let mut lines = Vec::new();
+    for (index, raw) in text.lines().enumerate() {
+        let number = index + 1;
+        let content = strip_comment(raw);
+        let trimmed = content.trim_start();
+        if trimmed.is_empty() || (trimmed == "---" && lines.is_empty()) {
+            continue;
+        }
+        if trimmed == "---" || trimmed == "..." {
+            return Err(format!("line {}: only a single document is supported", number));
+        }
+        let indent = content.len() - trimmed.len();
+        if content[..indent].contains('\t') {
+            return Err(format!("line {}: tabs are not allowed in indentation", number));
+        }
+        lines.push(Line { number, indent, text: trimmed.trim_end().to_string() });
+    }
+
+    let Some(indent) = lines.first().map(|line| line.indent) else {
+        return Ok(JsonValue::Null);
+    };
+    let mut parser = Parser { lines, pos: 0 };
+    let value = parser.block(indent)?;
+    match parser.lines.get(parser.pos) {
+        Some(line) => Err(format!("line {}: unexpected indentation", line.number)),
+        None => Ok(value),
+    }
+}
+
+fn write_block(value: &JsonValue, indent: usize, out: &mut String) {
+    let pad = " ".repeat(indent);
+    match value {
+        JsonValue::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            for key in keys {
+                out.push_str(&pad);
+                out.push_str(&string_text(key));
+                out.push(':');
+                write_value(&map[key], indent, out);
+            }
+        }
+        JsonValue::Array(items) => {
+            for item in items {
+                out.push_str(&pad);
+                out.push('-');
+                match item {
+                    JsonValue::Object(map) if !map.is_empty() => write_nested_item(item, indent, out),
+                    JsonValue::Array(items) if !items.is_empty() => write_nested_item(item, indent, out),
+                    scalar => {
+                        out.push(' ');
+                        out.push_str(&scalar_text(scalar));
+                        out.push('\n');
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The value after `key:`, inline for scalars and empty collections
+fn write_value(value: &JsonValue, indent: usize, out: &mut String) {
+    match value {
+        JsonValue::Object(map) if !map.is_empty() => {
+            out.push('\n');
+            write_block(value, indent + INDENT, out);
+        }
+        JsonValue::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            write_block(value, indent + INDENT, out);
+        }
+        scalar => {
+            out.push(' ');
+            out.push_str(&scalar_text(scalar));
+            out.push('\n');
+        }
+    }
+}
+
+/// A collection inside a sequence starts on the `- ` line
+fn write_nested_item(item: &JsonValue, indent: usize, out: &mut String) {
+    let mut nested = String::new();
+    write_block(item, indent + INDENT, &mut nested);
+    out.push(' ');
+    out.push_str(&nested[indent + INDENT..]);
+}
+
+fn scalar_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => string_text(s),
+        JsonValue::Object(_) => "{}".to_string(),
+        JsonValue::Array(_) => "[]".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Plain when the string cannot be mistaken for anything else, otherwise
+/// double-quoted (JSON escapes are valid YAML escapes)
+fn string_text(s: &str) -> String {
+    let plain = s.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | ' '))
+        && !s.ends_with(' ')
+        && plain_scalar(s).is_string();
+    if plain {
+        s.to_string()
+    } else {
+        JsonValue::String(s.to_string()).to_string()
+    }
+}
+
+struct Line {
+    number: usize,
+    indent: usize,
+    text: String,
+}
+
+struct Parser {
+    lines: Vec<Line>,
+    pos: usize,
+}
+
+impl Parser {
+    /// Parse the mapping or sequence whose entries start at `indent`
+    fn block(&mut self, indent: usize) -> Result<JsonValue, String> {
+        let line = &self.lines[self.pos];
+        if line.indent != indent {
+            return Err(format!("line {}: unexpected indentation", line.number));
+        }
+        if is_sequence_item(&line.text) {
+            self.sequence(indent)
+        } else if split_key(&line.text)?.is_some() {
+            self.mapping(indent)
+        } else {
+            let value = scalar(&line.text).map_err(|e| format!("line {}: {}", line.number, e))?;
+            self.pos += 1;
+            Ok(value)
+        }
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<JsonValue, String> {
+        let mut items = Vec::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.indent != indent || !is_sequence_item(&line.text) {
+                break;
+            }
+            let rest = line.text[1..].trim_start().to_string();
+            if rest.is_empty() {
+                self.pos += 1;
+                items.push(self.nested(indent)?);
+                continue;
+            }
+            // Parse the rest of the line as if it started its own line, so
+            // `- key: value` continues with the keys aligned under it
+            let offset = line.text.len() - rest.len();
+            let line = &mut self.lines[self.pos];
+            line.indent += offset;
+            line.text = rest;
+            let item_indent = line.indent;
+            items.push(self.block(item_indent)?);
+        }
+        Ok(JsonValue::Array(items))
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<JsonValue, String> {
+        let mut map = serde_json::Map::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.indent != indent || is_sequence_item(&line.text) {
+                break;
+            }
+            let number = line.number;
+            let Some((key, rest)) = split_key(&line.text)? else {
+                return Err(format!("line {}: expected 'key: value'", number));
+            };
+            self.pos += 1;
+            let value = if rest.is_empty() {
+                // A sequence may sit at the same indentation as its key
+                match self.lines.get(self.pos) {
+                    Some(next) if next.indent == indent && is_sequence_item(&next.text) => self.sequence(indent)?,
+                    _ => self.nested(indent)?,
+                }
+            } else {
+                scalar(&rest).map_err(|e| format!("line {}: {}", number, e))?
+            };
+            map.insert(key, value);
+        }
+        Ok(JsonValue::Object(map))
+    }
+
+    /// The block indented under the previous line, or null if there is none
+    fn nested(&mut self, parent_indent: usize) -> Result<JsonValue, String> {
+        match self.lines.get(self.pos) {
+            Some(next) if next.indent > parent_indent => {
+                let indent = next.indent;
+                self.block(indent)
+            }
+            _ => Ok(JsonValue::Null),
+        }
+    }
+}
+
+fn is_sequence_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Split `key: value` into the key and the (possibly empty) value text
+fn split_key(text: &str) -> Result<Option<(String, String)>, String> {
+    if text.starts_with('[') || text.starts_with('{') {
+        return Ok(None);
+    }
+    let (key, rest) = if text.starts_with('"') || text.starts_with('\'') {
+        let end = quoted_end(text).ok_or_else(|| "unterminated quoted string".to_string())?;
+        let key = match scalar(&text[..end])? {
+            JsonValue::String(key) => key,
+            _ => return Ok(None),
+        };
+        match text[end..].strip_prefix(':') {
+            Some(rest) if rest.is_empty() || rest.starts_with(' ') => (key, rest),
+            _ => return Ok(None),
+        }
+    } else {
+        let separator = text.find(": ").or_else(|| text.ends_with(':').then(|| text.len() - 1));
+        match separator {
+            Some(at) => (text[..at].trim_end().to_string(), &text[at + 1..]),
+            None => return Ok(None),
+        }
+    };
+    Ok(Some((key, rest.trim().to_string())))
+}
+
+/// Byte index just past the closing quote of a quoted scalar at the start of `text`
+fn quoted_end(text: &str) -> Option<usize> {
+    let quote = text.chars().next()?;
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((at, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            // '' is an escaped quote inside single quotes
+            '\'' if quote == '\'' && chars.peek().is_some_and(|(_, next)| *next == '\'') => {
+                chars.next();
+            }
+            c if c == quote => return Some(at + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn scalar(text: &str) -> Result<JsonValue, String> {
+    let text = text.trim();
+    if text.starts_with('"') || text.starts_with('\'') {
+        let end = quoted_end(text).ok_or_else(|| "unterminated quoted string".to_string())?;
+        if end != text.len() {
+            return Err(format!("unexpected text after quoted string: {}", &text[end..]));
+        }
+        return Ok(JsonValue::String(if text.starts_with('"') {
+            serde_json::from_str(text).map_err(|e| format!("invalid double-quoted string: {}", e))?
+        } else {
+            text[1..text.len() - 1].replace("''", "'")
+        }));
+    }
+    if text.starts_with('[') || text.starts_with('{') {
+        return serde_json::from_str(text).map_err(|e| format!("unsupported flow collection: {}", e));
+    }
+    if text.starts_with('|') || text.starts_with('>') {
+        return Err("block scalars are not supported".to_string());
+    }
+    Ok(plain_scalar(text))
+}
+
+fn plain_scalar(text: &str) -> JsonValue {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => JsonValue::Null,
+        "true" | "True" | "TRUE" => JsonValue::Bool(true),
+        "false" | "False" | "FALSE" => JsonValue::Bool(false),
+        _ => match serde_json::from_str::<serde_json::Number>(text) {
+            Ok(number) => JsonValue::Number(number),
+            Err(_) => JsonValue::String(text.to_string()),
+        },
+    }
+}
+
+/// Drop a `#` comment, ignoring `#` inside quoted scalars and within words
+fn strip_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let mut previous = ' ';
+    let mut chars = line.char_indices().peekable();
+    while let Some((at, c)) = chars.next() {
+        match (quote, c) {
+            (Some('"'), '\\') => {
+                chars.next();
+            }
+            (Some('\''), '\'') if chars.peek().is_some_and(|(_, next)| *next == '\'') => {
+                chars.next();
+            }
+            (Some(open), c) if c == open => quote = None,
+            // Quotes only open a scalar at its start, not inside words like it's
+            (None, '"' | '\'') if matches!(previous, ' ' | ':' | '-' | '[' | '{' | ',') => quote = Some(c),
+            (None, '#') if previous == ' ' => return &line[..at],
+            _ => {}
+        }
+        previous = c;
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let value = serde_json::json!({
+            "name": "Order sync",
+            "version": 1,
+            "ratio": 0.5,
+            "enabled": true,
+            "missing": null,
+            "tricky": ["true", "42", "", " padded", "a: b", "# not a comment", "it's", "line\nbreak", "ünïcode"],
+            "nested": [[1, 2], [], {}, { "a": { "b": [{ "c": "d" }] } }],
+            "key with: colon": "value",
+        });
+        let yaml = to_yaml(&value);
+        assert!(yaml.contains("name: Order sync\n"));
+        assert!(yaml.contains("- - 1\n"));
+        assert_eq!(from_yaml(&yaml).unwrap(), value);
+    }
+
+    #[test]
+    fn test_hand_written_yaml() {
+        let yaml = r#"
+---
+# Exported by hand
+name: 'Nightly ''sync'''   # trailing comment
+tags: [a, b]
+nodes:
+- id: first
+  config: {"url": "https://example.com/#top"}
+- id: second
+count: 3
+"#;
+        let value = from_yaml(yaml).unwrap_err();
+        assert!(value.contains("flow collection"), "{}", value);
+
+        let yaml = yaml.replace("[a, b]", r#"["a", "b"]"#);
+        let value = from_yaml(&yaml).unwrap();
+        assert_eq!(value["name"], "Nightly 'sync'");
+        assert_eq!(value["tags"], serde_json::json!(["a", "b"]));
+        assert_eq!(value["nodes"][0]["config"]["url"], "https://example.com/#top");
+        assert_eq!(value["nodes"][1]["id"], "second");
+        assert_eq!(value["count"], 3);
+
+        assert!(from_yaml("a: 1\n\tb: 2").is_err());
+        assert!(from_yaml("text: |\n  block").is_err());
+    }
+}
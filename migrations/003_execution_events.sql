@@ -0,0 +1,20 @@
+-- 003_execution_events.sql
+-- Execution events, persisted in order for post-hoc timelines and event replay
+
+-- Execution events table (append-only)
+CREATE TABLE IF NOT EXISTS execution_events (
+    execution_id UUID NOT NULL,
+    sequence BIGINT NOT NULL,
+    workflow_id UUID NOT NULL,
+    event_type VARCHAR(50) NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+    PRIMARY KEY (execution_id, sequence)
+);
+
+CREATE INDEX IF NOT EXISTS idx_execution_events_workflow_id ON execution_events(workflow_id);
+CREATE INDEX IF NOT EXISTS idx_execution_events_created_at ON execution_events(created_at);
+
+COMMENT ON TABLE execution_events IS 'Executor progress events, numbered per execution starting at 1';
+COMMENT ON COLUMN execution_events.payload IS 'The full event as sent to WebSocket clients';
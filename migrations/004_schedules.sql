@@ -0,0 +1,27 @@
+-- 004_schedules.sql
+-- Workflow schedules shared by scheduler replicas
+
+-- Cron, interval, webhook and feed schedules, one per workflow
+CREATE TABLE IF NOT EXISTS workflow_schedules (
+    workflow_id UUID PRIMARY KEY,
+    config JSONB NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- One-off schedules, deleted once their run finishes
+CREATE TABLE IF NOT EXISTS one_off_schedules (
+    id UUID PRIMARY KEY,
+    workflow_id UUID NOT NULL,
+    run_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    schedule JSONB NOT NULL
+);
+
+-- How far each cron and interval schedule has been handled
+CREATE TABLE IF NOT EXISTS schedule_checkpoints (
+    workflow_id UUID PRIMARY KEY,
+    checked_until TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_one_off_schedules_run_at ON one_off_schedules(run_at);
+
+COMMENT ON COLUMN one_off_schedules.schedule IS 'The schedule including the workflow definition it runs';
@@ -0,0 +1,16 @@
+-- 005_dead_letters.sql
+-- Failed executions kept for inspection and requeueing
+
+CREATE TABLE IF NOT EXISTS dead_letters (
+    id UUID PRIMARY KEY,
+    workflow_id UUID NOT NULL,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('pending', 'requeued', 'resolved')),
+    letter JSONB NOT NULL,
+    last_failed_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_dead_letters_workflow ON dead_letters(workflow_id, last_failed_at DESC);
+CREATE INDEX IF NOT EXISTS idx_dead_letters_status ON dead_letters(status);
+
+COMMENT ON COLUMN dead_letters.id IS 'ID of the first failed execution';
+COMMENT ON COLUMN dead_letters.letter IS 'The dead letter including the workflow definition, failure and variables';
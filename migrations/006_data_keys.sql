@@ -0,0 +1,15 @@
+-- 006_data_keys.sql
+-- Per-organization data keys for encrypting stored execution payloads
+
+CREATE TABLE IF NOT EXISTS organization_data_keys (
+    organization_id UUID PRIMARY KEY,
+    master_key_id INTEGER NOT NULL,
+    wrapped_key TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    rotated_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_organization_data_keys_master ON organization_data_keys(master_key_id);
+
+COMMENT ON COLUMN organization_data_keys.master_key_id IS 'Master key the data key is wrapped with; rotation re-wraps keys with the new master key';
+COMMENT ON COLUMN organization_data_keys.wrapped_key IS 'Base64 of nonce + AES-256-GCM ciphertext of the data key';
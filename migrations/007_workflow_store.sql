@@ -0,0 +1,11 @@
+-- 007_workflow_store.sql
+-- Workflow definitions saved through the gateway's workflow endpoints
+
+-- Owners are users of the gateway's user service, which are not kept in the users table
+ALTER TABLE workflows DROP CONSTRAINT IF EXISTS workflows_user_id_fkey;
+
+CREATE INDEX IF NOT EXISTS idx_workflows_user_updated ON workflows(user_id, updated_at DESC);
+
+COMMENT ON COLUMN workflows.user_id IS 'Owner of the workflow';
+COMMENT ON COLUMN workflows.definition IS 'The workflow definition, validated before it is saved';
+COMMENT ON COLUMN workflows.is_active IS 'Inactive workflows are kept but not triggered';
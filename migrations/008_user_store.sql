@@ -0,0 +1,17 @@
+-- 008_user_store.sql
+-- Accounts of the gateway's user service
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS avatar TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+
+-- Email addresses are unique among the users not deleted, ignoring case,
+-- so deleting an account frees its address
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_email_key;
+DROP INDEX IF EXISTS idx_users_email;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_email_active ON users(lower(email)) WHERE deleted_at IS NULL;
+
+-- Listing pages newest first
+CREATE INDEX IF NOT EXISTS idx_users_created_at ON users(created_at DESC, id) WHERE deleted_at IS NULL;
+
+COMMENT ON COLUMN users.email IS 'Login email address, stored lowercase';
+COMMENT ON COLUMN users.deleted_at IS 'When the account was deleted; deleted accounts are kept but cannot sign in';
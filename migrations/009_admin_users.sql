@@ -0,0 +1,9 @@
+-- 009_admin_users.sql
+-- Password resets forced by admins
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS must_change_password BOOLEAN NOT NULL DEFAULT false;
+
+-- Admin user listings filter by role and active flag
+CREATE INDEX IF NOT EXISTS idx_users_role_active ON users(role, is_active) WHERE deleted_at IS NULL;
+
+COMMENT ON COLUMN users.must_change_password IS 'Set when an admin reset the password; cleared when the user changes it';
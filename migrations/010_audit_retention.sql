@@ -0,0 +1,16 @@
+-- 010_audit_retention.sql
+-- Audit log columns written by the audit service, and indexes of retention purges
+
+ALTER TABLE audit_logs ADD COLUMN IF NOT EXISTS result VARCHAR(20) NOT NULL DEFAULT 'Success';
+UPDATE audit_logs SET is_sensitive = false WHERE is_sensitive IS NULL;
+ALTER TABLE audit_logs RENAME COLUMN is_sensitive TO is_security_sensitive;
+ALTER TABLE audit_logs ALTER COLUMN is_security_sensitive SET NOT NULL;
+-- Anonymized entries and non-HTTP events carry no valid address
+ALTER TABLE audit_logs ALTER COLUMN ip_address TYPE TEXT;
+ALTER INDEX idx_audit_logs_is_sensitive RENAME TO idx_audit_logs_is_security_sensitive;
+
+-- Purges select expired entries per category, oldest first
+CREATE INDEX IF NOT EXISTS idx_audit_logs_retention
+    ON audit_logs(is_security_sensitive, resource_type, created_at);
+
+COMMENT ON COLUMN audit_logs.result IS 'Success, Failure or Denied';
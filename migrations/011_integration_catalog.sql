@@ -0,0 +1,22 @@
+-- 011_integration_catalog.sql
+-- Catalog of the registered integrations and organizations' enablement of them
+
+CREATE TABLE IF NOT EXISTS integration_catalog (
+    name VARCHAR(100) PRIMARY KEY,
+    entry JSONB NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS organization_integrations (
+    organization_id UUID NOT NULL,
+    integration VARCHAR(100) NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    disabled_actions TEXT[] NOT NULL DEFAULT '{}',
+    updated_by UUID NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (organization_id, integration)
+);
+
+COMMENT ON COLUMN integration_catalog.entry IS 'Integration info, tags and action names, republished at every gateway start';
+COMMENT ON TABLE organization_integrations IS 'Integrations and actions disabled per organization; integrations without a row are enabled';
+COMMENT ON COLUMN organization_integrations.disabled_actions IS 'Actions disabled while the integration itself is enabled';
@@ -0,0 +1,19 @@
+-- 012_oauth_states.sql
+-- OAuth2 authorizations started through the gateway, awaiting the provider's redirect
+
+CREATE TABLE IF NOT EXISTS oauth_states (
+    state VARCHAR(64) PRIMARY KEY,
+    integration VARCHAR(100) NOT NULL,
+    owner UUID NOT NULL,
+    credential_name VARCHAR(255) NOT NULL,
+    credential_id UUID,
+    code_verifier VARCHAR(128) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_oauth_states_expires_at ON oauth_states(expires_at);
+
+COMMENT ON TABLE oauth_states IS 'Single-use OAuth2 states; a row is deleted when the callback completes it or after it expires';
+COMMENT ON COLUMN oauth_states.code_verifier IS 'PKCE code verifier sent with the authorization code exchange';
+COMMENT ON COLUMN oauth_states.credential_id IS 'Credential whose token is replaced, when reconnecting';
@@ -0,0 +1,25 @@
+-- 013_credentials.sql
+-- Users' saved integration credentials, encrypted with the credentials master key
+
+CREATE TABLE IF NOT EXISTS credentials (
+    id UUID PRIMARY KEY,
+    owner UUID NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    integration VARCHAR(100) NOT NULL,
+    auth_type VARCHAR(20) NOT NULL,
+    schema_version INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    status JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    rotated_at TIMESTAMP WITH TIME ZONE,
+    expires_at TIMESTAMP WITH TIME ZONE,
+    token_expires_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_credentials_owner ON credentials(owner);
+CREATE INDEX IF NOT EXISTS idx_credentials_integration ON credentials(integration);
+
+COMMENT ON COLUMN credentials.data IS 'Envelope-encrypted credential fields: env1:<master key ID>:<wrapped data key>:<ciphertext>';
+COMMENT ON COLUMN credentials.status IS 'Active, or needs_attention with the reason the owner must reconnect';
+COMMENT ON COLUMN credentials.token_expires_at IS 'Expiry of the OAuth2 access token in data, refreshed before then';
@@ -0,0 +1,17 @@
+-- 014_sessions.sql
+-- Signed-in devices of users; tokens of revoked or expired sessions are rejected
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    device_name VARCHAR(255),
+    ip_address TEXT NOT NULL,
+    user_agent TEXT NOT NULL,
+    issued_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_user_id ON sessions(user_id);
+CREATE INDEX IF NOT EXISTS idx_sessions_expires_at ON sessions(expires_at);
+
+COMMENT ON COLUMN sessions.expires_at IS 'Expiry of the session''s latest token, moved when the token is refreshed';